@@ -0,0 +1,182 @@
+// 深度快照读写分离基准测试
+//
+// 撮合线程持续撮合的同时，多个查询线程读取前 10 档深度：
+// - 订单簿锁：查询线程 orderbook.read() 后排序聚合（旧的查询路径），与撮合线程争锁
+// - 深度快照：查询线程读取 ArcSwap 快照，不触碰订单簿锁
+// 对比两种模式下撮合吞吐/延迟与查询吞吐
+//
+// 使用方法:
+//   cargo bench --bench depth_view_bench
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use qaexchange::matching::engine::{ExchangeMatchingEngine, InstrumentAsset};
+use qaexchange::matching::{orders, OrderDirection};
+
+const INSTRUMENT: &str = "IF2501";
+const RESTING_LEVELS: usize = 200;
+const QUERY_DEPTH: usize = 10;
+const READER_THREADS: usize = 4;
+const RUN_DURATION: Duration = Duration::from_secs(3);
+
+#[derive(Clone, Copy, PartialEq)]
+enum QueryMode {
+    OrderbookLock,
+    DepthSnapshot,
+}
+
+struct BenchResult {
+    match_events: u64,
+    match_p50: Duration,
+    match_p99: Duration,
+    queries: u64,
+}
+
+fn setup_engine() -> Arc<ExchangeMatchingEngine> {
+    let engine = Arc::new(ExchangeMatchingEngine::new());
+    engine
+        .register_instrument(INSTRUMENT.to_string(), 100.0)
+        .unwrap();
+    let asset = InstrumentAsset::from_code(INSTRUMENT);
+
+    // 买卖各 RESTING_LEVELS 档挂单，每档 3 笔
+    let mut ts = 0;
+    for level in 0..RESTING_LEVELS {
+        for _ in 0..3 {
+            ts += 1;
+            let bid = 99.0 - level as f64 * 0.2;
+            let ask = 101.0 + level as f64 * 0.2;
+            engine
+                .process_order(
+                    INSTRUMENT,
+                    orders::new_limit_order_request(asset, OrderDirection::BUY, bid, 1.0, ts),
+                )
+                .unwrap();
+            engine
+                .process_order(
+                    INSTRUMENT,
+                    orders::new_limit_order_request(asset, OrderDirection::SELL, ask, 1.0, ts),
+                )
+                .unwrap();
+        }
+    }
+    engine
+}
+
+/// 旧查询路径：持订单簿读锁排序聚合前 N 档
+fn query_with_lock(engine: &ExchangeMatchingEngine) -> usize {
+    let orderbook = engine.get_orderbook(INSTRUMENT).unwrap();
+    let ob = orderbook.read();
+    let mut total = 0;
+    for orders in [
+        ob.bid_queue.get_sorted_orders(),
+        ob.ask_queue.get_sorted_orders(),
+    ] {
+        let mut levels = BTreeMap::new();
+        for order in orders.unwrap_or_default().iter().take(QUERY_DEPTH * 10) {
+            *levels.entry(order.price.to_string()).or_insert(0.0) += order.volume;
+        }
+        total += levels.len().min(QUERY_DEPTH);
+    }
+    total
+}
+
+/// 新查询路径：读取深度快照
+fn query_with_snapshot(engine: &ExchangeMatchingEngine) -> usize {
+    let snapshot = engine.get_depth_snapshot(INSTRUMENT).unwrap();
+    snapshot.bids.len().min(QUERY_DEPTH) + snapshot.asks.len().min(QUERY_DEPTH)
+}
+
+fn run(mode: QueryMode) -> BenchResult {
+    let engine = setup_engine();
+    let stop = Arc::new(AtomicBool::new(false));
+    let queries = Arc::new(AtomicU64::new(0));
+
+    let readers: Vec<_> = (0..READER_THREADS)
+        .map(|_| {
+            let engine = engine.clone();
+            let stop = stop.clone();
+            let queries = queries.clone();
+            thread::spawn(move || {
+                let mut checksum = 0usize;
+                while !stop.load(Ordering::Relaxed) {
+                    checksum += match mode {
+                        QueryMode::OrderbookLock => query_with_lock(&engine),
+                        QueryMode::DepthSnapshot => query_with_snapshot(&engine),
+                    };
+                    queries.fetch_add(1, Ordering::Relaxed);
+                }
+                checksum
+            })
+        })
+        .collect();
+
+    // 撮合线程：在 100.0 价位反复挂卖单并以买单吃掉
+    let asset = InstrumentAsset::from_code(INSTRUMENT);
+    let mut latencies = Vec::new();
+    let mut ts = 1_000_000i64;
+    let start = Instant::now();
+    while start.elapsed() < RUN_DURATION {
+        for direction in [OrderDirection::SELL, OrderDirection::BUY] {
+            ts += 1;
+            let request = orders::new_limit_order_request(asset, direction, 100.0, 1.0, ts);
+            let t = Instant::now();
+            engine.process_order(INSTRUMENT, request).unwrap();
+            latencies.push(t.elapsed());
+        }
+    }
+    stop.store(true, Ordering::Relaxed);
+    for reader in readers {
+        let _ = reader.join().unwrap();
+    }
+
+    latencies.sort();
+    BenchResult {
+        match_events: latencies.len() as u64,
+        match_p50: latencies[latencies.len() / 2],
+        match_p99: latencies[(latencies.len() * 99 / 100).min(latencies.len() - 1)],
+        queries: queries.load(Ordering::Relaxed),
+    }
+}
+
+fn report(name: &str, result: &BenchResult) {
+    let secs = RUN_DURATION.as_secs_f64();
+    println!("\n--- {} ---", name);
+    println!(
+        "撮合: {:.0} events/s, P50 {:?}, P99 {:?}",
+        result.match_events as f64 / secs,
+        result.match_p50,
+        result.match_p99
+    );
+    println!(
+        "查询: {:.0} queries/s ({} 线程)",
+        result.queries as f64 / secs,
+        READER_THREADS
+    );
+}
+
+fn main() {
+    println!("=== 深度查询与撮合并发基准测试 ===");
+    println!(
+        "挂单 {} 档 x 2 侧，查询 {} 档，运行 {:?}",
+        RESTING_LEVELS, QUERY_DEPTH, RUN_DURATION
+    );
+
+    let locked = run(QueryMode::OrderbookLock);
+    report("查询持订单簿读锁", &locked);
+
+    let snapshot = run(QueryMode::DepthSnapshot);
+    report("查询读取深度快照", &snapshot);
+
+    println!(
+        "\n撮合吞吐提升 {:.2}x，撮合 P99 {:?} -> {:?}，查询吞吐提升 {:.2}x",
+        snapshot.match_events as f64 / locked.match_events.max(1) as f64,
+        locked.match_p99,
+        snapshot.match_p99,
+        snapshot.queries as f64 / locked.queries.max(1) as f64
+    );
+}
@@ -301,6 +301,93 @@ fn bench_concurrent_instruments() {
     );
 }
 
+fn create_tick_record(instrument_id: &str, price: f64, timestamp: i64) -> WalRecord {
+    WalRecord::TickData {
+        instrument_id: WalRecord::to_fixed_array_16(instrument_id),
+        last_price: price,
+        bid_price: price - 0.2,
+        ask_price: price + 0.2,
+        volume: 1,
+        timestamp,
+    }
+}
+
+/// 测试分区存储多品种并发写入吞吐随品种数的扩展
+///
+/// 同一个分区存储实例，每个品种一个写线程；分区模式下各品种写入独立 WAL，
+/// 总吞吐应随品种数近似线性增长（单分区模式作为对照）
+fn bench_partitioned_scaling() {
+    println!("\n=== 分区存储多品种并发写入扩展性基准测试 ===");
+
+    const WRITES_PER_INSTRUMENT: usize = 1000;
+
+    let run = |instrument_count: usize, partitioned: bool| -> f64 {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let config = OltpHybridConfig {
+            base_path: tmp_dir.path().to_str().unwrap().to_string(),
+            memtable_size_bytes: 100 * 1024 * 1024,
+            enable_olap_conversion: false,
+            ..Default::default()
+        };
+        let storage = Arc::new(if partitioned {
+            OltpHybridStorage::create_partitioned("market_data", config).unwrap()
+        } else {
+            OltpHybridStorage::create("market_data", config).unwrap()
+        });
+
+        // 预先创建分区，避免把建目录的开销计入吞吐
+        for inst_id in 0..instrument_count {
+            let instrument = format!("INST{:04}", inst_id);
+            storage
+                .write(create_tick_record(&instrument, 100.0, 0))
+                .unwrap();
+        }
+
+        let start = Instant::now();
+        let handles: Vec<_> = (0..instrument_count)
+            .map(|inst_id| {
+                let storage = storage.clone();
+                thread::spawn(move || {
+                    let instrument = format!("INST{:04}", inst_id);
+                    for i in 0..WRITES_PER_INSTRUMENT {
+                        storage
+                            .write(create_tick_record(
+                                &instrument,
+                                100.0 + i as f64,
+                                1 + i as i64,
+                            ))
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        (instrument_count * WRITES_PER_INSTRUMENT) as f64 / start.elapsed().as_secs_f64()
+    };
+
+    let mut baseline = 0.0;
+    for &instrument_count in &[1, 2, 5, CONCURRENT_INSTRUMENTS] {
+        let partitioned = run(instrument_count, true);
+        let single = run(instrument_count, false);
+        if instrument_count == 1 {
+            baseline = partitioned;
+        }
+
+        println!("\n品种数: {}", instrument_count);
+        println!("  分区模式吞吐量:   {:.0} ops/s", partitioned);
+        println!("  单分区模式吞吐量: {:.0} ops/s", single);
+        println!(
+            "  扩展倍数: {:.2}x（理想 {}x，效率 {:.0}%）",
+            partitioned / baseline,
+            instrument_count,
+            partitioned / baseline / instrument_count as f64 * 100.0
+        );
+    }
+}
+
 /// 测试崩溃恢复性能
 fn bench_recovery_performance() {
     println!("\n=== 崩溃恢复性能基准测试 ===");
@@ -371,6 +458,7 @@ fn main() {
     bench_range_query();
     bench_flush_performance();
     bench_concurrent_instruments();
+    bench_partitioned_scaling();
     bench_recovery_performance();
 
     println!("\n======================================");
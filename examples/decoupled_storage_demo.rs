@@ -48,6 +48,7 @@ async fn main() {
         batch_size: 100,      // 批量 100 条
         batch_timeout_ms: 10, // 10ms 超时
         buffer_size: 10000,   // 缓冲 10K 条
+        ..Default::default()
     };
 
     let (subscriber, storage_sender, _stats) = StorageSubscriber::new(storage_config);
@@ -150,8 +151,7 @@ async fn main() {
             volume: 1.0,
             price: 3800.0 + (i as f64) * 0.5,
             order_type: "LIMIT".to_string(),
-            time_condition: None,
-            volume_condition: None,
+            ..Default::default()
         };
 
         let start = Instant::now();
@@ -0,0 +1,262 @@
+//! 撮合核心 / 账户核心双进程演示（iceoryx2 共享内存）
+//!
+//! 架构：
+//! ```
+//! 进程 A: Gateway + AccountSystemCore          进程 B: MatchingEngineCore
+//!   Gateway ── core/order_requests ──────────────→ 撮合
+//!   AccountSystemCore ←── core/order_accepted ──── 撮合
+//!   AccountSystemCore ←── core/trade_reports ───── 撮合
+//! ```
+//!
+//! 运行：
+//! ```bash
+//! # 一键运行（自动拉起撮合子进程）
+//! cargo run --release --example ipc_dual_process_demo --features iceoryx2
+//!
+//! # 分别在两个终端运行
+//! cargo run --release --example ipc_dual_process_demo --features iceoryx2 -- matching
+//! cargo run --release --example ipc_dual_process_demo --features iceoryx2 -- gateway
+//!
+//! # 单跳延迟测量（ping-pong，单跳 = RTT / 2，目标 < 5μs）
+//! cargo run --release --example ipc_dual_process_demo --features iceoryx2 -- latency 100000
+//! ```
+//!
+//! @yutiansut @quantaxis
+
+use qaexchange::account::core::AccountSystemCore;
+use qaexchange::core::QA_Account;
+use qaexchange::ipc::channel::{spin_wait, topics};
+use qaexchange::ipc::{IpcConfig, MessageReceiver, MessageSender};
+use qaexchange::matching::core::MatchingEngineCore;
+use qaexchange::protocol::ipc_messages::{OrderDirection, OrderOffset, OrderRequest, TradeReport};
+use std::collections::HashMap;
+use std::process::{Child, Command};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const INSTRUMENT: &str = "IX2401";
+const PING_TOPIC: &str = "bench/ping";
+const PONG_TOPIC: &str = "bench/pong";
+
+fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    let args: Vec<String> = std::env::args().collect();
+    let config = IpcConfig::default();
+
+    match args.get(1).map(|s| s.as_str()) {
+        Some("matching") => run_matching(&config),
+        Some("gateway") => run_gateway(&config),
+        Some("echo") => run_echo(&config),
+        Some("latency") => {
+            let rounds = args.get(2).and_then(|s| s.parse().ok()).unwrap_or(100_000);
+            run_latency(&config, rounds);
+        }
+        _ => {
+            println!("=== 双进程演示：Gateway+账户进程 ↔ 撮合进程 ===\n");
+            let mut child = spawn_self("matching");
+            thread::sleep(Duration::from_millis(500));
+            run_gateway(&config);
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+/// 以指定角色启动当前可执行文件
+fn spawn_self(role: &str) -> Child {
+    Command::new(std::env::current_exe().expect("current exe"))
+        .arg(role)
+        .spawn()
+        .expect("Failed to spawn child process")
+}
+
+/// 撮合进程
+fn run_matching(config: &IpcConfig) {
+    println!("[撮合进程 {}] 启动", std::process::id());
+    let engine = MatchingEngineCore::from_ipc(config).expect("Failed to create matching core");
+    engine.register_instrument(INSTRUMENT.to_string(), 100.0);
+    engine.run();
+}
+
+/// Gateway + 账户进程
+fn run_gateway(config: &IpcConfig) {
+    println!("[Gateway+账户进程 {}] 启动", std::process::id());
+
+    let order_sender: MessageSender<OrderRequest> =
+        MessageSender::ipc(config, topics::ORDER_REQUESTS).expect("order publisher");
+    // Gateway 同时订阅成交回报，用于统计下单 → 成交回报的端到端延迟
+    let trade_receiver: MessageReceiver<TradeReport> =
+        MessageReceiver::ipc(config, topics::TRADE_REPORTS).expect("trade subscriber");
+
+    let account_system =
+        Arc::new(AccountSystemCore::from_ipc(config).expect("Failed to create account core"));
+    for i in 0..2 {
+        let user_id = format!("user_{:02}", i + 1);
+        let account = QA_Account::new(&user_id, "default", &user_id, 1_000_000.0, false, "sim");
+        account_system.register_account(user_id, account);
+    }
+    let account_handle = {
+        let system = account_system.clone();
+        thread::spawn(move || system.run())
+    };
+    thread::sleep(Duration::from_millis(200));
+
+    // user_01 买开、user_02 卖开，逐对撮合
+    let pairs = 100;
+    let mut sent_at: HashMap<[u8; 40], Instant> = HashMap::new();
+    for i in 0..pairs {
+        for (user_id, direction) in [
+            ("user_01", OrderDirection::BUY),
+            ("user_02", OrderDirection::SELL),
+        ] {
+            let mut order = OrderRequest::new(
+                "",
+                user_id,
+                INSTRUMENT,
+                direction,
+                OrderOffset::OPEN,
+                100.0,
+                1.0,
+            );
+
+            // 先经账户 send_order 生成 order_id 并冻结资金
+            let account = account_system.get_account(user_id).unwrap();
+            let towards = if order.direction == 0 { 1 } else { -2 };
+            let datetime = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+            let qars_order = match account.write().send_order(
+                INSTRUMENT,
+                order.volume,
+                &datetime,
+                towards,
+                order.price,
+                "",
+                "LIMIT",
+            ) {
+                Ok(o) => o,
+                Err(e) => {
+                    println!("  [Gateway] {} 第 {} 笔被拒绝: {:?}", user_id, i, e);
+                    continue;
+                }
+            };
+            let bytes = qars_order.order_id.as_bytes();
+            let len = bytes.len().min(40);
+            order.order_id[..len].copy_from_slice(&bytes[..len]);
+
+            sent_at.insert(order.order_id, Instant::now());
+            order_sender.send(order).expect("send order");
+        }
+    }
+    println!("  ✓ 已发送 {} 笔订单", pairs * 2);
+
+    // 收集成交回报
+    let mut latencies = Vec::new();
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline && latencies.len() < pairs * 2 {
+        if let Some(trade) = trade_receiver.recv_timeout(Duration::from_millis(100)) {
+            if let Some(start) = sent_at.get(&trade.order_id) {
+                latencies.push(start.elapsed());
+            }
+        }
+    }
+    thread::sleep(Duration::from_millis(200));
+    account_system.stop();
+    let _ = account_handle.join();
+
+    println!("\n  ✓ 收到 {} 条成交回报", latencies.len());
+    report("下单 → 成交回报（两跳）", &mut latencies);
+
+    for user_id in ["user_01", "user_02"] {
+        if let Some(account) = account_system.get_account(user_id) {
+            let mut acc = account.write();
+            println!(
+                "  [账户] {} 余额 {:.2} | 保证金 {:.2}",
+                user_id,
+                acc.get_balance(),
+                acc.get_margin()
+            );
+        }
+    }
+}
+
+/// 延迟测量对端：收到 ping 原样回 pong
+fn run_echo(config: &IpcConfig) {
+    let ping: MessageReceiver<OrderRequest> =
+        MessageReceiver::ipc(config, PING_TOPIC).expect("ping subscriber");
+    let pong: MessageSender<OrderRequest> =
+        MessageSender::ipc(config, PONG_TOPIC).expect("pong publisher");
+    let mut spins = 0;
+    loop {
+        match ping.try_recv() {
+            Some(msg) => {
+                spins = 0;
+                let _ = pong.send(msg);
+            }
+            None => spin_wait(&mut spins),
+        }
+    }
+}
+
+/// ping-pong 延迟测量
+fn run_latency(config: &IpcConfig, rounds: usize) {
+    println!("=== iceoryx2 单跳延迟测量（{} 轮 ping-pong）===\n", rounds);
+
+    let ping: MessageSender<OrderRequest> =
+        MessageSender::ipc(config, PING_TOPIC).expect("ping publisher");
+    let pong: MessageReceiver<OrderRequest> =
+        MessageReceiver::ipc(config, PONG_TOPIC).expect("pong subscriber");
+    let mut child = spawn_self("echo");
+    thread::sleep(Duration::from_millis(500));
+
+    let msg = OrderRequest::new(
+        "PING",
+        "bench",
+        INSTRUMENT,
+        OrderDirection::BUY,
+        OrderOffset::OPEN,
+        100.0,
+        1.0,
+    );
+
+    // 预热
+    for _ in 0..1000 {
+        ping.send(msg).unwrap();
+        let _ = pong.recv_timeout(Duration::from_secs(1));
+    }
+
+    let mut latencies = Vec::with_capacity(rounds);
+    for _ in 0..rounds {
+        let start = Instant::now();
+        ping.send(msg).unwrap();
+        if pong.recv_timeout(Duration::from_secs(1)).is_some() {
+            latencies.push(start.elapsed() / 2);
+        }
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+
+    report("单跳（RTT / 2）", &mut latencies);
+    let p99 = percentile(&latencies, 0.99);
+    let verdict = if p99 < Duration::from_micros(5) {
+        "✓ 达标 (P99)"
+    } else {
+        "✗ 未达标 (P99)"
+    };
+    println!("\n  目标单跳 < 5μs: {}", verdict);
+}
+
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    sorted[((sorted.len() - 1) as f64 * p) as usize]
+}
+
+fn report(label: &str, latencies: &mut [Duration]) {
+    latencies.sort();
+    println!("  {} 延迟（{} 样本）:", label, latencies.len());
+    println!("    P50: {:?}", percentile(latencies, 0.50));
+    println!("    P99: {:?}", percentile(latencies, 0.99));
+    println!("    Max: {:?}", latencies.last().copied().unwrap_or_default());
+}
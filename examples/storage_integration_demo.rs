@@ -300,8 +300,7 @@ fn main() {
         volume: 10.0,
         price: 3800.0,
         order_type: "LIMIT".to_string(),
-        time_condition: None,
-        volume_condition: None,
+        ..Default::default()
     };
 
     println!("订单详情:");
@@ -140,8 +140,7 @@ fn main() {
             volume,
             price,
             order_type: "LIMIT".to_string(),
-            time_condition: None,
-            volume_condition: None,
+            ..Default::default()
         };
 
         let response = order_router.submit_order(req);
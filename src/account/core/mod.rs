@@ -5,8 +5,11 @@
 //! 2. 批量处理 - 批量接收成交，减少锁竞争
 //! 3. 分片账户 - 多线程处理不同账户，提高并发
 //! 4. WAL 日志 - 写入日志后才确认，保证数据安全
+//! 5. 通道可替换 - 单进程 crossbeam（`new`），同机多进程 iceoryx2（`from_ipc`）
 
 use crate::core::QA_Account;
+use crate::ipc::channel::IdleTimer;
+use crate::ipc::MessageReceiver;
 use crate::protocol::ipc_messages::{OrderAccepted, TradeReport};
 use crossbeam::channel::{Receiver, Sender};
 use dashmap::DashMap;
@@ -21,11 +24,11 @@ pub struct AccountSystemCore {
     /// 账户池
     accounts: DashMap<String, Arc<RwLock<QA_Account>>>,
 
-    /// 成交订阅器（暂时用 crossbeam，后续替换为 iceoryx2）
-    trade_receiver: Receiver<TradeReport>,
+    /// 成交订阅器（crossbeam / iceoryx2）
+    trade_receiver: MessageReceiver<TradeReport>,
 
     /// 订单确认订阅器（用于 sim 模式的 on_order_confirm）
-    accepted_receiver: Receiver<OrderAccepted>,
+    accepted_receiver: MessageReceiver<OrderAccepted>,
 
     /// 账户更新通知发送器（可选，用于通知其他系统）
     update_sender: Option<Sender<AccountUpdateNotify>>,
@@ -37,6 +40,10 @@ pub struct AccountSystemCore {
     running: Arc<std::sync::atomic::AtomicBool>,
 }
 
+/// 多进程模式下的默认批量处理大小
+#[cfg(feature = "iceoryx2")]
+const DEFAULT_IPC_BATCH_SIZE: usize = 100;
+
 /// 账户更新通知
 #[derive(Debug, Clone)]
 pub struct AccountUpdateNotify {
@@ -48,12 +55,41 @@ pub struct AccountUpdateNotify {
 }
 
 impl AccountSystemCore {
-    /// 创建账户系统核心
+    /// 创建账户系统核心（单进程模式，crossbeam 通道）
     pub fn new(
         trade_receiver: Receiver<TradeReport>,
         accepted_receiver: Receiver<OrderAccepted>,
         update_sender: Option<Sender<AccountUpdateNotify>>,
         batch_size: usize,
+    ) -> Self {
+        Self::with_channels(
+            trade_receiver.into(),
+            accepted_receiver.into(),
+            update_sender,
+            batch_size,
+        )
+    }
+
+    /// 创建账户系统核心（同机多进程模式，iceoryx2 共享内存）
+    ///
+    /// 订阅撮合核心发布的 `core/trade_reports` 和 `core/order_accepted`
+    #[cfg(feature = "iceoryx2")]
+    pub fn from_ipc(config: &crate::ipc::IpcConfig) -> Result<Self, String> {
+        use crate::ipc::channel::topics;
+
+        Ok(Self::with_channels(
+            MessageReceiver::ipc(config, topics::TRADE_REPORTS)?,
+            MessageReceiver::ipc(config, topics::ORDER_ACCEPTED)?,
+            None,
+            DEFAULT_IPC_BATCH_SIZE,
+        ))
+    }
+
+    fn with_channels(
+        trade_receiver: MessageReceiver<TradeReport>,
+        accepted_receiver: MessageReceiver<OrderAccepted>,
+        update_sender: Option<Sender<AccountUpdateNotify>>,
+        batch_size: usize,
     ) -> Self {
         Self {
             accounts: DashMap::new(),
@@ -74,23 +110,44 @@ impl AccountSystemCore {
 
     /// 启动账户系统主循环
     pub fn run(&self) {
-        use crossbeam::channel::select;
         use std::sync::atomic::Ordering;
 
         self.running.store(true, Ordering::SeqCst);
         log::info!("AccountSystemCore started");
 
+        match (
+            self.accepted_receiver.as_local(),
+            self.trade_receiver.as_local(),
+        ) {
+            (Some(accepted_receiver), Some(trade_receiver)) => {
+                self.run_local(accepted_receiver, trade_receiver)
+            }
+            _ => self.run_polling(),
+        }
+
+        log::info!("AccountSystemCore stopped");
+    }
+
+    /// 单进程模式：crossbeam select 同时监听两个通道
+    fn run_local(
+        &self,
+        accepted_receiver: &Receiver<OrderAccepted>,
+        trade_receiver: &Receiver<TradeReport>,
+    ) {
+        use crossbeam::channel::select;
+        use std::sync::atomic::Ordering;
+
         let mut update_queue: Vec<TradeReport> = Vec::with_capacity(self.batch_size);
 
         while self.running.load(Ordering::SeqCst) {
             // 使用 select 同时监听两个通道
             select! {
-                recv(self.accepted_receiver) -> msg => {
+                recv(accepted_receiver) -> msg => {
                     if let Ok(accepted) = msg {
                         self.handle_order_accepted(accepted);
                     }
                 }
-                recv(self.trade_receiver) -> msg => {
+                recv(trade_receiver) -> msg => {
                     if let Ok(trade) = msg {
                         update_queue.push(trade);
 
@@ -110,8 +167,46 @@ impl AccountSystemCore {
                 }
             }
         }
+    }
 
-        log::info!("AccountSystemCore stopped");
+    /// 多进程模式：轮询 iceoryx2 订阅者
+    ///
+    /// 共享内存没有阻塞接收，忙等轮询；每轮把已到达的成交取空后立即批量处理，
+    /// 不等待凑满批次，保证单跳延迟
+    fn run_polling(&self) {
+        use std::sync::atomic::Ordering;
+
+        let mut update_queue: Vec<TradeReport> = Vec::with_capacity(self.batch_size);
+        let mut idle = IdleTimer::new(std::time::Duration::from_millis(10));
+
+        while self.running.load(Ordering::SeqCst) {
+            let mut received = false;
+
+            // 先处理订单确认，保证 on_order_confirm 先于对应成交
+            while let Some(accepted) = self.accepted_receiver.try_recv() {
+                self.handle_order_accepted(accepted);
+                received = true;
+            }
+
+            while let Some(trade) = self.trade_receiver.try_recv() {
+                update_queue.push(trade);
+                received = true;
+                if update_queue.len() >= self.batch_size {
+                    break;
+                }
+            }
+
+            if !update_queue.is_empty() {
+                self.batch_update_accounts(&update_queue);
+                update_queue.clear();
+            }
+
+            if received {
+                idle.reset();
+            } else {
+                idle.wait();
+            }
+        }
     }
 
     /// 处理订单确认（sim 模式）
@@ -241,7 +336,9 @@ impl AccountSystemCore {
             _ => 1,
         };
 
-        let datetime = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let datetime = crate::utils::time_service::now_local()
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string();
 
         // 关键步骤1：更新订单的 exchange_order_id（交易所全局唯一ID）
         if let Some(order) = acc.dailyorders.get_mut(&order_id) {
@@ -352,6 +449,7 @@ mod tests {
             volume: 10.0,
             commission: 0.3,
             timestamp: 0,
+            match_batch_id: 0,
             opposite_order_id: [0; 32],
             gateway_id: 0,
             session_id: 0,
@@ -596,6 +694,7 @@ mod tests {
             volume: 10.0,
             commission: 0.3,
             timestamp: 0,
+            match_batch_id: 0,
             opposite_order_id: [0; 32],
             gateway_id: 0,
             session_id: 0,
@@ -643,6 +742,7 @@ mod tests {
             volume: 5.0,
             commission: 0.15,
             timestamp: 0,
+            match_batch_id: 0,
             opposite_order_id: [0; 32],
             gateway_id: 0,
             session_id: 0,
@@ -695,6 +795,7 @@ mod tests {
             volume: 10.0,
             commission: 0.3,
             timestamp: 0,
+            match_batch_id: 0,
             opposite_order_id: [0; 32],
             gateway_id: 0,
             session_id: 0,
@@ -714,6 +815,7 @@ mod tests {
             volume: 5.0,
             commission: 0.15,
             timestamp: 0,
+            match_batch_id: 0,
             opposite_order_id: [0; 32],
             gateway_id: 0,
             session_id: 0,
@@ -775,6 +877,7 @@ mod tests {
                     volume: 1.0,
                     commission: 0.03,
                     timestamp: i as i64,
+                    match_batch_id: 0,
                     opposite_order_id: [0; 32],
                     gateway_id: 0,
                     session_id: 0,
@@ -837,6 +940,7 @@ mod tests {
             volume: 10.0,
             commission: 0.3,
             timestamp: 0,
+            match_batch_id: 0,
             opposite_order_id: [0; 32],
             gateway_id: 0,
             session_id: 0,
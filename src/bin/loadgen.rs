@@ -0,0 +1,109 @@
+//! qaexchange-loadgen: WebSocket 模拟客户端负载工具
+//!
+//! @yutiansut @quantaxis
+//!
+//! ```bash
+//! # 只压推送：200 连接订阅行情 60 秒
+//! cargo run --release --bin qaexchange-loadgen -- --connections 200 \
+//!     --instruments IF2501,IC2501 --duration 60
+//!
+//! # 混合交易：账户与价格分布写在配置文件里，命令行参数覆盖配置文件
+//! cargo run --release --bin qaexchange-loadgen -- --config config/loadgen.toml \
+//!     --mode mixed --rate 50 --output loadgen_report.json
+//! ```
+//!
+//! 结束后向标准输出打印 JSON 报告（`LoadGenReport`），指定 `--output` 时同时写入文件。
+
+use qaexchange::utils::loadgen::{self, LoadGenConfig, LoadGenMode};
+
+fn usage() -> ! {
+    eprintln!(
+        "Usage: qaexchange-loadgen [--config FILE] [--url WS_URL] [--mode market_data|mixed]\n\
+         \x20      [--connections N] [--instruments A,B] [--per-connection N]\n\
+         \x20      [--rate ORDERS_PER_SEC] [--duration SECS] [--output FILE]"
+    );
+    std::process::exit(2);
+}
+
+fn parse<T: std::str::FromStr>(flag: &str, value: Option<&String>) -> T {
+    match value.and_then(|v| v.parse().ok()) {
+        Some(v) => v,
+        None => {
+            eprintln!("Invalid value for {}", flag);
+            usage();
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+
+    let args: Vec<String> = std::env::args().collect();
+
+    // 先加载配置文件，再用命令行参数覆盖
+    let mut config = match args.iter().position(|a| a == "--config") {
+        Some(i) => match args.get(i + 1) {
+            Some(path) => LoadGenConfig::from_file(path).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }),
+            None => usage(),
+        },
+        None => LoadGenConfig::default(),
+    };
+
+    let mut output = None;
+    let mut i = 1;
+    while i < args.len() {
+        let value = args.get(i + 1);
+        match args[i].as_str() {
+            "--config" => {}
+            "--url" => config.url = parse("--url", value),
+            "--mode" => {
+                config.mode = match value.map(String::as_str) {
+                    Some("market_data") => LoadGenMode::MarketData,
+                    Some("mixed") => LoadGenMode::Mixed,
+                    _ => usage(),
+                }
+            }
+            "--connections" => config.connections = parse("--connections", value),
+            "--instruments" => {
+                config.instruments = parse::<String>("--instruments", value)
+                    .split(',')
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect()
+            }
+            "--per-connection" => {
+                config.instruments_per_connection = parse("--per-connection", value)
+            }
+            "--rate" => config.order_rate = parse("--rate", value),
+            "--duration" => config.duration_secs = parse("--duration", value),
+            "--output" => output = Some(parse::<String>("--output", value)),
+            "--help" => usage(),
+            other => {
+                eprintln!("Unknown argument: {}", other);
+                usage();
+            }
+        }
+        i += 2;
+    }
+
+    let report = match loadgen::run(config).await {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Loadgen failed: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let json = serde_json::to_string_pretty(&report).expect("LoadGenReport is serializable");
+    if let Some(path) = output {
+        if let Err(e) = std::fs::write(&path, &json) {
+            eprintln!("Failed to write report to {}: {}", path, e);
+            std::process::exit(1);
+        }
+    }
+    println!("{}", json);
+}
@@ -6,7 +6,16 @@
 //! - 表达式求值
 //! - 增量计算
 //! - 状态管理
+//!
+//! 窗口算子（ma/std/ema/rsi）以函数语法书写，如 `ma(close, 20) > ma(close, 60)`，
+//! 由 IncrementalExecutor 逐 bar 求值：每个调用对应一份滚动状态，窗口未满时返回 Null
+//! （算术/比较中 Null 向上传播，可用 fillna 填充）。
+//!
+//! 状态 key 派生规则：`{源表达式}_{周期}`，源为标识符时即其名称（与 get_or_create_* 一致，
+//! 如 `close_20`），源为表达式时取其规范文本，如 `ma(ma(close,5),3)` 的外层 key 为
+//! `ma(close,5)_3`、内层 key 为 `close_5`。
 
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::sync::Arc;
 
@@ -26,6 +35,21 @@ pub struct ExecutionContext {
     factors: HashMap<String, FactorDef>,
     /// 内置函数
     builtins: HashMap<String, BuiltinFunction>,
+    /// 窗口算子注册表（函数名 -> 算子类型）
+    window_operators: HashMap<String, WindowOperator>,
+}
+
+/// 窗口算子类型（状态由 IncrementalExecutor 维护）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WindowOperator {
+    /// 滚动均值
+    Mean,
+    /// 滚动标准差
+    Std,
+    /// 指数移动平均
+    Ema,
+    /// 相对强弱指数
+    Rsi,
 }
 
 /// 值类型
@@ -80,11 +104,32 @@ impl ExecutionContext {
             variables: HashMap::new(),
             factors: HashMap::new(),
             builtins: HashMap::new(),
+            window_operators: HashMap::new(),
         };
         ctx.register_builtins();
+        ctx.register_window_operators();
         ctx
     }
 
+    /// 注册内置窗口算子
+    fn register_window_operators(&mut self) {
+        self.register_window_operator("ma", WindowOperator::Mean);
+        self.register_window_operator("sma", WindowOperator::Mean);
+        self.register_window_operator("std", WindowOperator::Std);
+        self.register_window_operator("ema", WindowOperator::Ema);
+        self.register_window_operator("rsi", WindowOperator::Rsi);
+    }
+
+    /// 注册窗口算子（函数名 -> 算子类型），用于别名或覆盖
+    pub fn register_window_operator(&mut self, name: &str, op: WindowOperator) {
+        self.window_operators.insert(name.to_string(), op);
+    }
+
+    /// 查询窗口算子
+    pub fn window_operator(&self, name: &str) -> Option<WindowOperator> {
+        self.window_operators.get(name).copied()
+    }
+
     /// 注册内置函数
     fn register_builtins(&mut self) {
         // abs
@@ -246,11 +291,24 @@ pub type ExecutionResult<T> = Result<T, ExecutionError>;
 /// 表达式求值器
 pub struct Evaluator<'a> {
     context: &'a ExecutionContext,
+    /// 窗口算子状态（仅由 IncrementalExecutor 提供）
+    windows: Option<RefCell<&'a mut WindowStates>>,
 }
 
 impl<'a> Evaluator<'a> {
     pub fn new(context: &'a ExecutionContext) -> Self {
-        Self { context }
+        Self {
+            context,
+            windows: None,
+        }
+    }
+
+    /// 带窗口算子状态的求值器
+    fn with_windows(context: &'a ExecutionContext, windows: &'a mut WindowStates) -> Self {
+        Self {
+            context,
+            windows: Some(RefCell::new(windows)),
+        }
     }
 
     /// 求值表达式
@@ -292,6 +350,9 @@ impl<'a> Evaluator<'a> {
             BinaryOperator::Sub => self.arithmetic_op(&left, &right, |a, b| a - b),
             BinaryOperator::Mul => self.arithmetic_op(&left, &right, |a, b| a * b),
             BinaryOperator::Div => {
+                if matches!(left, Value::Null) || matches!(right, Value::Null) {
+                    return Ok(Value::Null);
+                }
                 let r = right.as_float().ok_or(ExecutionError::TypeError("Expected number".into()))?;
                 if r == 0.0 {
                     return Err(ExecutionError::DivisionByZero);
@@ -327,6 +388,9 @@ impl<'a> Evaluator<'a> {
     where
         F: Fn(f64, f64) -> f64,
     {
+        if matches!(left, Value::Null) || matches!(right, Value::Null) {
+            return Ok(Value::Null);
+        }
         let l = left.as_float().ok_or(ExecutionError::TypeError("Expected number".into()))?;
         let r = right.as_float().ok_or(ExecutionError::TypeError("Expected number".into()))?;
         Ok(Value::Float(op(l, r)))
@@ -336,6 +400,9 @@ impl<'a> Evaluator<'a> {
     where
         F: Fn(f64, f64) -> bool,
     {
+        if matches!(left, Value::Null) || matches!(right, Value::Null) {
+            return Ok(Value::Null);
+        }
         let l = left.as_float().ok_or(ExecutionError::TypeError("Expected number".into()))?;
         let r = right.as_float().ok_or(ExecutionError::TypeError("Expected number".into()))?;
         Ok(Value::Boolean(op(l, r)))
@@ -345,6 +412,7 @@ impl<'a> Evaluator<'a> {
         let operand = self.evaluate(&op.operand)?;
 
         match op.op {
+            UnaryOperator::Neg if matches!(operand, Value::Null) => Ok(Value::Null),
             UnaryOperator::Neg => {
                 let val = operand.as_float().ok_or(ExecutionError::TypeError("Expected number".into()))?;
                 Ok(Value::Float(-val))
@@ -367,6 +435,11 @@ impl<'a> Evaluator<'a> {
             return builtin(&args);
         }
 
+        // 窗口算子：委托给 IncrementalExecutor 的滚动状态
+        if let Some(op) = self.context.window_operator(&call.name) {
+            return self.eval_window_call(op, call);
+        }
+
         // 检查用户定义的因子
         if let Some(factor) = self.context.get_factor(&call.name) {
             return self.evaluate(&factor.expr);
@@ -375,6 +448,43 @@ impl<'a> Evaluator<'a> {
         Err(ExecutionError::UndefinedFunction(call.name.clone()))
     }
 
+    /// 窗口算子求值：`name(source, period)`
+    fn eval_window_call(&self, op: WindowOperator, call: &FunctionCall) -> ExecutionResult<Value> {
+        if call.args.len() != 2 {
+            return Err(ExecutionError::ArgumentError(format!(
+                "{} requires 2 arguments (source, period)",
+                call.name
+            )));
+        }
+        let period = match self.evaluate(&call.args[1])?.as_int() {
+            Some(p) if p > 0 => p as usize,
+            _ => {
+                return Err(ExecutionError::ArgumentError(format!(
+                    "{} period must be a positive integer",
+                    call.name
+                )))
+            }
+        };
+        let Some(windows) = self.windows.as_ref() else {
+            return Err(ExecutionError::RuntimeError(format!(
+                "Window operator {} requires IncrementalExecutor",
+                call.name
+            )));
+        };
+
+        // 先求值源表达式（嵌套窗口算子在此更新），再更新本层状态
+        let input = self.evaluate(&call.args[0])?;
+        let input = match input {
+            Value::Null => None,
+            other => Some(other.as_float().ok_or_else(|| {
+                ExecutionError::TypeError(format!("{} source must be numeric", call.name))
+            })?),
+        };
+        let source = expression_key(&call.args[0]);
+        let value = windows.borrow_mut().step(op, &source, period, input);
+        Ok(value.map_or(Value::Null, Value::Float))
+    }
+
     fn eval_conditional(&self, cond: &Conditional) -> ExecutionResult<Value> {
         let condition = self.evaluate(&cond.condition)?;
         let is_true = condition.as_bool().ok_or(ExecutionError::TypeError("Condition must be boolean".into()))?;
@@ -391,9 +501,12 @@ impl<'a> Evaluator<'a> {
 // 增量执行器
 // ═══════════════════════════════════════════════════════════════════════════
 
-/// 增量因子执行器
-pub struct IncrementalExecutor {
-    context: ExecutionContext,
+/// 窗口算子状态
+///
+/// key 为 `{source}_{period}`；`bar_values` 缓存当前 bar 已推进过的算子值，
+/// 保证同一 bar 内同一调用（如表达式中重复出现的 `ma(close, 5)`）只更新一次。
+#[derive(Default)]
+struct WindowStates {
     /// 滚动均值状态
     rolling_means: HashMap<String, RollingMean>,
     /// 滚动标准差状态
@@ -402,46 +515,210 @@ pub struct IncrementalExecutor {
     emas: HashMap<String, EMA>,
     /// RSI 状态
     rsis: HashMap<String, RSI>,
+    /// 当前 bar 已求值的算子
+    bar_values: HashMap<(WindowOperator, String), Option<f64>>,
+}
+
+impl WindowStates {
+    /// 以当前 bar 的输入推进窗口算子，返回就绪后的值（窗口未满为 None）
+    fn step(
+        &mut self,
+        op: WindowOperator,
+        source: &str,
+        period: usize,
+        input: Option<f64>,
+    ) -> Option<f64> {
+        let key = format!("{}_{}", source, period);
+        if let Some(value) = self.bar_values.get(&(op, key.clone())) {
+            return *value;
+        }
+
+        let value = match op {
+            WindowOperator::Mean => {
+                let rm = self
+                    .rolling_means
+                    .entry(key.clone())
+                    .or_insert_with(|| RollingMean::new(period));
+                if let Some(x) = input {
+                    rm.update(x);
+                }
+                rm.is_full().then(|| rm.value())
+            }
+            WindowOperator::Std => {
+                let rs = self
+                    .rolling_stds
+                    .entry(key.clone())
+                    .or_insert_with(|| RollingStd::new(period));
+                if let Some(x) = input {
+                    rs.update(x);
+                }
+                rs.is_full().then(|| rs.value())
+            }
+            WindowOperator::Ema => {
+                let ema = self
+                    .emas
+                    .entry(key.clone())
+                    .or_insert_with(|| EMA::new(period));
+                if let Some(x) = input {
+                    ema.update(x);
+                }
+                if ema.count() >= period as u64 {
+                    ema.value()
+                } else {
+                    None
+                }
+            }
+            WindowOperator::Rsi => {
+                let rsi = self
+                    .rsis
+                    .entry(key.clone())
+                    .or_insert_with(|| RSI::new(period));
+                if let Some(x) = input {
+                    rsi.update(x);
+                }
+                rsi.value()
+            }
+        };
+
+        self.bar_values.insert((op, key), value);
+        value
+    }
+
+    fn clear(&mut self) {
+        self.rolling_means.clear();
+        self.rolling_stds.clear();
+        self.emas.clear();
+        self.rsis.clear();
+        self.bar_values.clear();
+    }
+}
+
+/// 表达式规范文本（窗口算子状态 key 的源部分）
+///
+/// 标识符取名称，其余表达式去空格并统一字面量格式，如 `ma(close, 5)` -> `ma(close,5)`
+fn expression_key(expr: &Expression) -> String {
+    match expr {
+        Expression::Identifier(name) => name.clone(),
+        Expression::Literal(lit) => match lit {
+            Literal::Integer(i) => i.to_string(),
+            Literal::Float(f) => f.to_string(),
+            Literal::String(s) => format!("{:?}", s),
+            Literal::Boolean(b) => b.to_string(),
+            Literal::Null => "null".to_string(),
+        },
+        Expression::BinaryOp(bin) => {
+            let op = match bin.op {
+                BinaryOperator::Add => "+",
+                BinaryOperator::Sub => "-",
+                BinaryOperator::Mul => "*",
+                BinaryOperator::Div => "/",
+                BinaryOperator::Mod => "%",
+                BinaryOperator::Pow => "**",
+                BinaryOperator::Eq => "==",
+                BinaryOperator::Ne => "!=",
+                BinaryOperator::Lt => "<",
+                BinaryOperator::Le => "<=",
+                BinaryOperator::Gt => ">",
+                BinaryOperator::Ge => ">=",
+                BinaryOperator::And => "&&",
+                BinaryOperator::Or => "||",
+            };
+            format!(
+                "({}{}{})",
+                expression_key(&bin.left),
+                op,
+                expression_key(&bin.right)
+            )
+        }
+        Expression::UnaryOp(un) => {
+            let op = match un.op {
+                UnaryOperator::Neg => "-",
+                UnaryOperator::Not => "!",
+            };
+            format!("{}{}", op, expression_key(&un.operand))
+        }
+        Expression::FunctionCall(call) => format!(
+            "{}({})",
+            call.name,
+            call.args
+                .iter()
+                .map(expression_key)
+                .collect::<Vec<_>>()
+                .join(",")
+        ),
+        Expression::Conditional(cond) => format!(
+            "({}?{}:{})",
+            expression_key(&cond.condition),
+            expression_key(&cond.then_branch),
+            expression_key(&cond.else_branch)
+        ),
+    }
+}
+
+/// 增量因子执行器
+///
+/// 逐 bar 驱动：`next_bar` 写入数据源，随后 `evaluate` 求值含窗口算子的表达式
+pub struct IncrementalExecutor {
+    context: ExecutionContext,
+    /// 窗口算子状态
+    windows: WindowStates,
 }
 
 impl IncrementalExecutor {
     pub fn new() -> Self {
         Self {
             context: ExecutionContext::new(),
-            rolling_means: HashMap::new(),
-            rolling_stds: HashMap::new(),
-            emas: HashMap::new(),
-            rsis: HashMap::new(),
+            windows: WindowStates::default(),
         }
     }
 
+    /// 执行上下文（注册变量、因子、窗口算子别名）
+    pub fn context_mut(&mut self) -> &mut ExecutionContext {
+        &mut self.context
+    }
+
     /// 设置数据源
     pub fn set_source(&mut self, name: &str, value: f64) {
         self.context.set_variable(name, Value::Float(value));
     }
 
+    /// 进入新 bar：写入数据源并允许窗口算子再次推进
+    pub fn next_bar(&mut self, sources: &[(&str, f64)]) {
+        for (name, value) in sources {
+            self.set_source(name, *value);
+        }
+        self.windows.bar_values.clear();
+    }
+
+    /// 求值表达式（窗口算子在当前 bar 内各推进一次）
+    pub fn evaluate(&mut self, expr: &Expression) -> ExecutionResult<Value> {
+        Evaluator::with_windows(&self.context, &mut self.windows).evaluate(expr)
+    }
+
     /// 更新增量状态
     pub fn update(&mut self, source_name: &str, value: f64) {
+        let windows = &mut self.windows;
+
         // 更新所有依赖此数据源的增量算子
-        for (key, rm) in &mut self.rolling_means {
+        for (key, rm) in &mut windows.rolling_means {
             if key.starts_with(source_name) {
                 rm.update(value);
             }
         }
 
-        for (key, rs) in &mut self.rolling_stds {
+        for (key, rs) in &mut windows.rolling_stds {
             if key.starts_with(source_name) {
                 rs.update(value);
             }
         }
 
-        for (key, ema) in &mut self.emas {
+        for (key, ema) in &mut windows.emas {
             if key.starts_with(source_name) {
                 ema.update(value);
             }
         }
 
-        for (key, rsi) in &mut self.rsis {
+        for (key, rsi) in &mut windows.rsis {
             if key.starts_with(source_name) {
                 rsi.update(value);
             }
@@ -452,52 +729,49 @@ impl IncrementalExecutor {
     pub fn get_or_create_ma(&mut self, source: &str, period: usize) -> f64 {
         let key = format!("{}_{}", source, period);
 
-        if !self.rolling_means.contains_key(&key) {
-            self.rolling_means.insert(key.clone(), RollingMean::new(period));
-        }
-
-        self.rolling_means.get(&key).unwrap().value()
+        self.windows
+            .rolling_means
+            .entry(key)
+            .or_insert_with(|| RollingMean::new(period))
+            .value()
     }
 
     /// 获取或创建滚动标准差
     pub fn get_or_create_std(&mut self, source: &str, period: usize) -> f64 {
         let key = format!("{}_{}", source, period);
 
-        if !self.rolling_stds.contains_key(&key) {
-            self.rolling_stds.insert(key.clone(), RollingStd::new(period));
-        }
-
-        self.rolling_stds.get(&key).unwrap().value()
+        self.windows
+            .rolling_stds
+            .entry(key)
+            .or_insert_with(|| RollingStd::new(period))
+            .value()
     }
 
     /// 获取或创建 EMA
     pub fn get_or_create_ema(&mut self, source: &str, period: usize) -> Option<f64> {
         let key = format!("{}_{}", source, period);
 
-        if !self.emas.contains_key(&key) {
-            self.emas.insert(key.clone(), EMA::new(period));
-        }
-
-        self.emas.get(&key).unwrap().value()
+        self.windows
+            .emas
+            .entry(key)
+            .or_insert_with(|| EMA::new(period))
+            .value()
     }
 
     /// 获取或创建 RSI
     pub fn get_or_create_rsi(&mut self, source: &str, period: usize) -> Option<f64> {
         let key = format!("{}_{}", source, period);
 
-        if !self.rsis.contains_key(&key) {
-            self.rsis.insert(key.clone(), RSI::new(period));
-        }
-
-        self.rsis.get(&key).unwrap().value()
+        self.windows
+            .rsis
+            .entry(key)
+            .or_insert_with(|| RSI::new(period))
+            .value()
     }
 
     /// 重置所有状态
     pub fn reset(&mut self) {
-        self.rolling_means.clear();
-        self.rolling_stds.clear();
-        self.emas.clear();
-        self.rsis.clear();
+        self.windows.clear();
     }
 }
 
@@ -573,4 +847,77 @@ mod tests {
         // 最后5个: 103.0, 102.0, 103.5, 104.0, 103.0 = 515.5 / 5 = 103.1
         assert!((ma5 - 103.1).abs() < 0.01, "MA5 should be ~103.1, got {}", ma5);
     }
+
+    #[test]
+    fn test_window_call_warmup_and_nested_key() {
+        use crate::dsl::parser::parse_expression;
+
+        let mut executor = IncrementalExecutor::new();
+        let warm = parse_expression("fillna(ma(close, 3), 0)").unwrap();
+        let nested = parse_expression("ma(ma(close, 3), 2)").unwrap();
+
+        // 无窗口状态的求值器不支持窗口算子
+        let ctx = ExecutionContext::new();
+        assert!(matches!(
+            Evaluator::new(&ctx).evaluate(&nested),
+            Err(ExecutionError::RuntimeError(_))
+        ));
+
+        let prices = [1.0, 2.0, 3.0, 4.0, 5.0];
+        let mut warm_values = Vec::new();
+        let mut nested_values = Vec::new();
+        for price in prices {
+            executor.next_bar(&[("close", price)]);
+            warm_values.push(executor.evaluate(&warm).unwrap().as_float());
+            nested_values.push(executor.evaluate(&nested).unwrap().as_float());
+        }
+
+        // 同一 bar 内两个表达式共享 close_3，且只推进一次
+        assert_eq!(
+            warm_values,
+            vec![Some(0.0), Some(0.0), Some(2.0), Some(3.0), Some(4.0)]
+        );
+        // 内层 ma(close,3) 第 3 根起就绪，外层再需要 2 个值
+        assert_eq!(nested_values, vec![None, None, None, Some(2.5), Some(3.5)]);
+        // 嵌套 key 可通过规范文本直接访问
+        assert_eq!(executor.get_or_create_ma("ma(close,3)", 2), 3.5);
+        assert_eq!(executor.get_or_create_ma("close", 3), 4.0);
+    }
+
+    #[test]
+    fn test_golden_death_cross_signal_timing() {
+        use crate::dsl::parser::parse_expression;
+
+        let mut executor = IncrementalExecutor::new();
+        let above = parse_expression("ma(close, 3) > ma(close, 5)").unwrap();
+
+        // 下跌 -> 上涨 -> 下跌
+        let prices = [
+            10.0, 9.0, 8.0, 7.0, 6.0, 5.0, 6.0, 8.0, 10.0, 12.0, 13.0, 12.0, 10.0, 8.0, 6.0,
+        ];
+        let mut prev: Option<bool> = None;
+        let mut golden = Vec::new();
+        let mut death = Vec::new();
+        for (i, price) in prices.iter().enumerate() {
+            executor.next_bar(&[("close", *price)]);
+            let current = match executor.evaluate(&above).unwrap() {
+                Value::Null => None,
+                v => v.as_bool(),
+            };
+            if i < 4 {
+                assert_eq!(current, None, "bar {} should still be warming up", i);
+            }
+            match (prev, current) {
+                (Some(false), Some(true)) => golden.push(i),
+                (Some(true), Some(false)) => death.push(i),
+                _ => {}
+            }
+            prev = current;
+        }
+
+        // bar 6: ma3=(6+5+6)/3=5.67 < ma5=(8+7+6+5+6)/5=6.4；bar 7: ma3=6.33 > ma5=6.4? 否
+        // bar 8: ma3=8.0 > ma5=7.0 -> 金叉；bar 12: ma3=11.67 < ma5=11.4? 否；bar 13: 10.0 < 11.0 -> 死叉
+        assert_eq!(golden, vec![8]);
+        assert_eq!(death, vec![13]);
+    }
 }
@@ -0,0 +1,406 @@
+//! 账户操作租约（多实例共享存储时的账户级操作权）
+//! @yutiansut @quantaxis
+//!
+//! 过渡方案：两个网关实例共享存储部署时，同一账户只允许一个实例处理写操作
+//! （下单/撤单/大宗交易/出入金），避免两边各自冻结资金导致余额超用。
+//! - 租约记录落盘在共享存储 `{lease_dir}/{account_id}.lease`（持有实例 + 过期时间）
+//! - 处理账户写操作前获取/续约租约；其他实例持有且未过期时拒绝，提示稍后重试
+//! - 持有实例宕机后不再续约，租约超时自动释放，其他实例即可接管
+//! - 读改写租约记录时以 `{account_id}.lock`（原子创建）做跨进程互斥
+//! - 本实例持有的租约剩余时间过半前不访问存储，续约开销摊薄到每 ttl/2 一次
+//! - 每次账户写操作结束（[`AccountLeaseHold`] drop）与释放租约时，持有实例把账户最新状态
+//!   发布到共享存储 `{lease_dir}/{account_id}.state`
+//! - 接管其他实例持有过的租约（已释放或超时）前，先从共享存储加载该账户最新状态覆盖内存状态；
+//!   未注入账户管理器或没有已发布的状态时拒绝接管，避免按过期的内存余额接单造成双花
+//!
+//! 未启用时（单实例部署）路由器与资金管理器不持有租约管理器，没有任何开销。
+
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::exchange::{AccountExport, AccountManager};
+use crate::ExchangeError;
+
+/// 租约目录名（位于存储根目录）
+pub const ACCOUNT_LEASE_DIR: &str = "account_leases";
+
+/// 等待跨进程互斥锁的超时
+const LOCK_WAIT_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// 账户租约配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountLeaseConfig {
+    /// 是否启用（单实例部署保持关闭）
+    #[serde(default)]
+    pub enabled: bool,
+    /// 实例ID（为空时使用 主机名-进程号）
+    #[serde(default)]
+    pub instance_id: String,
+    /// 租约目录（为空时使用 `{storage}/account_leases`，多实例必须指向同一共享目录）
+    #[serde(default)]
+    pub lease_dir: String,
+    /// 租约有效期（毫秒）
+    #[serde(default = "default_lease_ttl_ms")]
+    pub ttl_ms: i64,
+}
+
+fn default_lease_ttl_ms() -> i64 {
+    10_000
+}
+
+impl Default for AccountLeaseConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            instance_id: String::new(),
+            lease_dir: String::new(),
+            ttl_ms: default_lease_ttl_ms(),
+        }
+    }
+}
+
+/// 租约记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountLease {
+    pub account_id: String,
+    /// 持有实例
+    pub holder: String,
+    /// 首次获取时间（毫秒）
+    pub acquired_at: i64,
+    /// 过期时间（毫秒）
+    pub expires_at: i64,
+}
+
+/// 共享存储中的账户状态（持有实例发布，接管实例加载）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct AccountStateRecord {
+    /// 发布实例
+    holder: String,
+    /// 发布时间（毫秒）
+    published_at: i64,
+    account: AccountExport,
+}
+
+/// 持有中的账户租约（drop 时把账户最新状态发布到共享存储）
+pub struct AccountLeaseHold<'a> {
+    manager: &'a AccountLeaseManager,
+    account_id: String,
+}
+
+impl Drop for AccountLeaseHold<'_> {
+    fn drop(&mut self) {
+        if let Err(e) = self.manager.publish_state(&self.account_id) {
+            log::warn!("Failed to publish account state {}: {}", self.account_id, e);
+        }
+    }
+}
+
+/// 跨进程互斥锁（drop 时删除锁文件）
+struct LeaseLockGuard(PathBuf);
+
+impl Drop for LeaseLockGuard {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// 账户租约管理器
+pub struct AccountLeaseManager {
+    instance_id: String,
+    lease_dir: PathBuf,
+    ttl_ms: i64,
+    /// 本实例持有的租约 (account_id -> expires_at)
+    held: DashMap<String, i64>,
+    /// 账户管理器（发布/加载账户状态；未注入时拒绝接管其他实例持有过的租约）
+    account_mgr: Option<Arc<AccountManager>>,
+}
+
+impl AccountLeaseManager {
+    pub fn new(
+        instance_id: impl Into<String>,
+        lease_dir: impl AsRef<Path>,
+        ttl_ms: i64,
+    ) -> Result<Self, ExchangeError> {
+        let lease_dir = lease_dir.as_ref().to_path_buf();
+        fs::create_dir_all(&lease_dir).map_err(|e| {
+            ExchangeError::StorageError(format!("Create account lease dir failed: {}", e))
+        })?;
+        Ok(Self {
+            instance_id: instance_id.into(),
+            lease_dir,
+            ttl_ms: ttl_ms.max(1),
+            held: DashMap::new(),
+            account_mgr: None,
+        })
+    }
+
+    /// 注入账户管理器：写操作后发布账户状态，接管租约时加载其他实例发布的状态
+    pub fn with_account_manager(mut self, account_mgr: Arc<AccountManager>) -> Self {
+        self.account_mgr = Some(account_mgr);
+        self
+    }
+
+    /// 按配置创建（未配置实例ID/目录时使用默认值）
+    pub fn from_config(
+        config: &AccountLeaseConfig,
+        storage_path: impl AsRef<Path>,
+    ) -> Result<Self, ExchangeError> {
+        let instance_id = if config.instance_id.is_empty() {
+            format!(
+                "{}-{}",
+                std::env::var("HOSTNAME").unwrap_or_else(|_| "qaexchange".to_string()),
+                std::process::id()
+            )
+        } else {
+            config.instance_id.clone()
+        };
+        let lease_dir = if config.lease_dir.is_empty() {
+            storage_path.as_ref().join(ACCOUNT_LEASE_DIR)
+        } else {
+            PathBuf::from(&config.lease_dir)
+        };
+        Self::new(instance_id, lease_dir, config.ttl_ms)
+    }
+
+    pub fn instance_id(&self) -> &str {
+        &self.instance_id
+    }
+
+    pub fn ttl_ms(&self) -> i64 {
+        self.ttl_ms
+    }
+
+    /// 获取或续约账户租约，返回的持有凭证在写操作结束后 drop（发布账户状态）
+    ///
+    /// 其他实例持有未过期租约时返回错误，调用方应拒绝请求并提示重试；
+    /// 接管其他实例持有过的租约时先加载其发布的账户状态，加载失败则拒绝接管
+    pub fn acquire(&self, account_id: &str) -> Result<AccountLeaseHold<'_>, ExchangeError> {
+        let now = chrono::Utc::now().timestamp_millis();
+        if self
+            .held
+            .get(account_id)
+            .map_or(false, |expires_at| *expires_at - now > self.ttl_ms / 2)
+        {
+            return Ok(self.hold(account_id));
+        }
+
+        let _lock = self.lock(account_id)?;
+        let now = chrono::Utc::now().timestamp_millis();
+        let current = self.read_lease(account_id);
+        if let Some(lease) = &current {
+            if lease.holder != self.instance_id && lease.expires_at > now {
+                self.held.remove(account_id);
+                return Err(ExchangeError::AccountError(format!(
+                    "Account {} is being operated by instance {} (lease expires in {} ms), please retry later",
+                    account_id,
+                    lease.holder,
+                    lease.expires_at - now
+                )));
+            }
+            // 接管：上一持有实例已释放或超时，先加载其发布的账户状态
+            if lease.holder != self.instance_id {
+                self.load_state(account_id, &lease.holder)?;
+            }
+        }
+
+        let lease = AccountLease {
+            account_id: account_id.to_string(),
+            holder: self.instance_id.clone(),
+            acquired_at: current
+                .filter(|lease| lease.holder == self.instance_id)
+                .map_or(now, |lease| lease.acquired_at),
+            expires_at: now + self.ttl_ms,
+        };
+        self.write_lease(&lease)?;
+        self.held.insert(account_id.to_string(), lease.expires_at);
+        Ok(self.hold(account_id))
+    }
+
+    fn hold(&self, account_id: &str) -> AccountLeaseHold<'_> {
+        AccountLeaseHold {
+            manager: self,
+            account_id: account_id.to_string(),
+        }
+    }
+
+    /// 释放本实例持有的账户租约
+    ///
+    /// 先发布账户最新状态，再把租约置为到期（保留持有实例，接管方据此加载状态）
+    pub fn release(&self, account_id: &str) -> Result<(), ExchangeError> {
+        self.held.remove(account_id);
+        let _lock = self.lock(account_id)?;
+        if let Some(mut lease) = self
+            .read_lease(account_id)
+            .filter(|lease| lease.holder == self.instance_id)
+        {
+            self.write_state(account_id)?;
+            lease.expires_at = chrono::Utc::now().timestamp_millis();
+            self.write_lease(&lease)?;
+        }
+        Ok(())
+    }
+
+    /// 释放本实例持有的全部租约（正常停机时调用），返回释放数量
+    pub fn release_all(&self) -> usize {
+        let accounts = self.held_accounts();
+        accounts
+            .iter()
+            .filter(|account_id| match self.release(account_id) {
+                Ok(()) => true,
+                Err(e) => {
+                    log::warn!("Failed to release account lease {}: {}", account_id, e);
+                    false
+                }
+            })
+            .count()
+    }
+
+    /// 当前有效的租约（已过期视为无人持有）
+    pub fn holder(&self, account_id: &str) -> Option<AccountLease> {
+        let now = chrono::Utc::now().timestamp_millis();
+        self.read_lease(account_id)
+            .filter(|lease| lease.expires_at > now)
+    }
+
+    /// 本实例持有的账户
+    pub fn held_accounts(&self) -> Vec<String> {
+        self.held.iter().map(|entry| entry.key().clone()).collect()
+    }
+
+    /// 发布账户最新状态（本实例仍是租约持有者时）
+    fn publish_state(&self, account_id: &str) -> Result<(), ExchangeError> {
+        if self.account_mgr.is_none() {
+            return Ok(());
+        }
+        let _lock = self.lock(account_id)?;
+        if self
+            .read_lease(account_id)
+            .map_or(false, |lease| lease.holder == self.instance_id)
+        {
+            self.write_state(account_id)?;
+        }
+        Ok(())
+    }
+
+    /// 写入账户状态（调用方持有跨进程互斥锁）
+    fn write_state(&self, account_id: &str) -> Result<(), ExchangeError> {
+        let Some(ref account_mgr) = self.account_mgr else {
+            return Ok(());
+        };
+        let record = AccountStateRecord {
+            holder: self.instance_id.clone(),
+            published_at: chrono::Utc::now().timestamp_millis(),
+            account: account_mgr.export_account(account_id)?,
+        };
+        let path = self.state_path(account_id);
+        let tmp = path.with_extension(format!("state.{}.tmp", std::process::id()));
+        let content = serde_json::to_string(&record).map_err(|e| {
+            ExchangeError::StorageError(format!("Serialize account state failed: {}", e))
+        })?;
+        fs::write(&tmp, content)
+            .and_then(|_| fs::rename(&tmp, &path))
+            .map_err(|e| ExchangeError::StorageError(format!("Write account state failed: {}", e)))
+    }
+
+    /// 接管前加载上一持有实例发布的账户状态（调用方持有跨进程互斥锁）
+    fn load_state(&self, account_id: &str, previous: &str) -> Result<(), ExchangeError> {
+        let refuse = |reason: &str| {
+            ExchangeError::AccountError(format!(
+                "Cannot take over account {} from instance {}: {}",
+                account_id, previous, reason
+            ))
+        };
+        let Some(ref account_mgr) = self.account_mgr else {
+            return Err(refuse("account state reload is not configured"));
+        };
+        let record: AccountStateRecord = fs::read_to_string(self.state_path(account_id))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .ok_or_else(|| refuse("no published account state"))?;
+        account_mgr
+            .import_account(record.account, true)
+            .map_err(|e| refuse(&e.to_string()))?;
+        log::info!(
+            "Account {} taken over from instance {}, reloaded state published by {} at {}",
+            account_id,
+            previous,
+            record.holder,
+            record.published_at
+        );
+        Ok(())
+    }
+
+    fn file_stem(account_id: &str) -> String {
+        account_id.replace(['/', '\\'], "_")
+    }
+
+    fn lease_path(&self, account_id: &str) -> PathBuf {
+        self.lease_dir
+            .join(format!("{}.lease", Self::file_stem(account_id)))
+    }
+
+    fn state_path(&self, account_id: &str) -> PathBuf {
+        self.lease_dir
+            .join(format!("{}.state", Self::file_stem(account_id)))
+    }
+
+    fn read_lease(&self, account_id: &str) -> Option<AccountLease> {
+        fs::read_to_string(self.lease_path(account_id))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+    }
+
+    /// 先写临时文件再 rename，避免其他实例读到半截记录
+    fn write_lease(&self, lease: &AccountLease) -> Result<(), ExchangeError> {
+        let path = self.lease_path(&lease.account_id);
+        let tmp = path.with_extension(format!("lease.{}.tmp", std::process::id()));
+        let content = serde_json::to_string(lease).map_err(|e| {
+            ExchangeError::StorageError(format!("Serialize account lease failed: {}", e))
+        })?;
+        fs::write(&tmp, content)
+            .and_then(|_| fs::rename(&tmp, &path))
+            .map_err(|e| ExchangeError::StorageError(format!("Write account lease failed: {}", e)))
+    }
+
+    /// 获取账户的跨进程互斥锁；锁文件超过 ttl 未释放（持锁进程宕机）视为失效
+    fn lock(&self, account_id: &str) -> Result<LeaseLockGuard, ExchangeError> {
+        let path = self
+            .lease_dir
+            .join(format!("{}.lock", Self::file_stem(account_id)));
+        let deadline = Instant::now() + LOCK_WAIT_TIMEOUT;
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Ok(LeaseLockGuard(path)),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    let stale = fs::metadata(&path)
+                        .and_then(|meta| meta.modified())
+                        .ok()
+                        .and_then(|modified| modified.elapsed().ok())
+                        .map_or(false, |age| age.as_millis() as i64 > self.ttl_ms);
+                    if stale {
+                        let _ = fs::remove_file(&path);
+                        continue;
+                    }
+                    if Instant::now() >= deadline {
+                        return Err(ExchangeError::ServiceError(format!(
+                            "Account {} lease is busy, please retry later",
+                            account_id
+                        )));
+                    }
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+                Err(e) => {
+                    return Err(ExchangeError::StorageError(format!(
+                        "Create account lease lock failed: {}",
+                        e
+                    )))
+                }
+            }
+        }
+    }
+}
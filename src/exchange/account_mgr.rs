@@ -4,18 +4,127 @@
 
 use crate::core::account_ext::{AccountType, OpenAccountRequest};
 use crate::core::{Account, QA_Account, QIFI};
+use crate::exchange::account_tags::{AccountTagBook, TagAction, TagBatchResult};
+use crate::exchange::deterministic::SeededIdGenerator;
+use crate::exchange::fixed_point::FixedLedger;
+use crate::exchange::pnl_attribution::PnlLedger;
+use crate::exchange::position_cost::{rebuild_from_trades, CostTrade, PositionCostBook};
+use crate::exchange::position_lots::{LotBook, LotMark, LotMismatch, PositionDetailView};
 use crate::notification::message::{
-    AccountOpenNotify, Notification, NotificationPayload, NotificationType,
+    AccountOpenNotify, Notification, NotificationPayload, NotificationType, SystemNoticeNotify,
 };
 use crate::notification::NotificationBroker;
 use crate::user::UserManager;
 use crate::ExchangeError;
-use chrono::Local;
 use dashmap::DashMap;
 use parking_lot::RwLock;
-use std::path::Path;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+/// 账户导出格式版本
+pub const ACCOUNT_EXPORT_VERSION: u32 = 1;
+
+/// 账户导出数据（可移植格式，用于迁移和备份）
+///
+/// 包含 QIFI 完整切片（资金、持仓、订单、成交、冻结）以及账户元数据和用户绑定关系
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountExport {
+    /// 导出格式版本
+    pub version: u32,
+    /// 账户ID
+    pub account_id: String,
+    /// 所属用户ID（绑定关系）
+    pub user_id: String,
+    /// 账户名称
+    pub account_name: String,
+    /// 账户类型
+    pub account_type: AccountType,
+    /// 创建时间
+    pub created_at: i64,
+    /// 导出时间
+    pub exported_at: i64,
+    /// QIFI 账户切片
+    pub qifi: QIFI,
+    /// 开仓明细（批次明细 QIFI 导出开关打开时附加，导入时忽略并按持仓成本重建）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lots: Vec<PositionDetailView>,
+}
+
+/// 批量导入结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AccountImportSummary {
+    /// 成功导入的账户
+    pub imported: Vec<String>,
+    /// 导入失败的账户 (account_id, 原因)
+    pub failed: Vec<(String, String)>,
+}
+
+/// 账户交易限制
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TradingRestriction {
+    /// 正常交易
+    #[default]
+    Normal,
+    /// 只能平仓，不能开新仓（违规账户、临近到期客户）
+    CloseOnly,
+    /// 暂停交易，仍允许撤单和查询
+    Suspended,
+}
+
+/// 账户交易限制状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TradingRestrictionInfo {
+    pub account_id: String,
+    pub restriction: TradingRestriction,
+    /// 限制原因
+    pub reason: String,
+    /// 限制截止时间（毫秒时间戳，None 表示直到解除）
+    pub expires_at: Option<i64>,
+    /// 设置人
+    pub updated_by: String,
+    /// 设置时间（毫秒时间戳）
+    pub updated_at: i64,
+}
+
+impl TradingRestrictionInfo {
+    /// 是否仍在生效（到期后自动恢复正常）
+    pub fn is_active(&self, now_ms: i64) -> bool {
+        self.restriction != TradingRestriction::Normal
+            && self
+                .expires_at
+                .map_or(true, |expires_at| now_ms < expires_at)
+    }
+
+    /// 是否拒绝该开平方向的新订单（撤单不受限制）
+    pub fn rejects(&self, offset: &str) -> bool {
+        match self.restriction {
+            TradingRestriction::Normal => false,
+            TradingRestriction::CloseOnly => offset == "OPEN",
+            TradingRestriction::Suspended => true,
+        }
+    }
+
+    /// 限制说明（原因与期限）
+    pub fn describe(&self) -> String {
+        let kind = match self.restriction {
+            TradingRestriction::Normal => "正常交易",
+            TradingRestriction::CloseOnly => "只能平仓",
+            TradingRestriction::Suspended => "暂停交易",
+        };
+        let until = self
+            .expires_at
+            .map(|t| crate::utils::time_service::time_service().format_millis(t))
+            .unwrap_or_else(|| "解除前".to_string());
+        format!(
+            "账户 {} 已被限制为{}，原因: {}，期限: {}",
+            self.account_id, kind, self.reason, until
+        )
+    }
+}
+
 /// 账户元数据
 #[derive(Debug, Clone)]
 struct AccountMetadata {
@@ -48,6 +157,30 @@ pub struct AccountManager {
 
     /// 用户管理器（用于验证用户和自动绑定）
     user_manager: Option<Arc<UserManager>>,
+
+    /// 持仓成本台账（持仓均价统一维护）
+    position_costs: PositionCostBook,
+
+    /// 持仓批次台账（开仓批次明细）
+    position_lots: LotBook,
+
+    /// 盈亏归因流水（每笔成交的手续费与批次变动）
+    pnl_ledger: PnlLedger,
+
+    /// 资金定点台账（i64 定点核算与浮点双轨比对）
+    fixed_ledger: FixedLedger,
+
+    /// 账户交易限制 (account_id -> 限制状态，仅保存非 Normal 的账户)
+    trading_restrictions: DashMap<String, TradingRestrictionInfo>,
+
+    /// 交易限制持久化文件（None 表示仅内存）
+    restriction_store: Option<PathBuf>,
+
+    /// 账户标签（运营分类）与保证金加成
+    account_tags: AccountTagBook,
+
+    /// 确定性ID生成器（确定性模式下替代随机 UUID 生成账户ID）
+    id_generator: Option<Arc<SeededIdGenerator>>,
 }
 
 impl AccountManager {
@@ -58,6 +191,14 @@ impl AccountManager {
             user_accounts: DashMap::new(),
             notification_broker: None,
             user_manager: None,
+            position_costs: PositionCostBook::new(),
+            position_lots: LotBook::new(),
+            pnl_ledger: PnlLedger::new(),
+            fixed_ledger: FixedLedger::new(),
+            trading_restrictions: DashMap::new(),
+            restriction_store: None,
+            account_tags: AccountTagBook::new(),
+            id_generator: None,
         }
     }
 
@@ -69,6 +210,14 @@ impl AccountManager {
             user_accounts: DashMap::new(),
             notification_broker: Some(broker),
             user_manager: None,
+            position_costs: PositionCostBook::new(),
+            position_lots: LotBook::new(),
+            pnl_ledger: PnlLedger::new(),
+            fixed_ledger: FixedLedger::new(),
+            trading_restrictions: DashMap::new(),
+            restriction_store: None,
+            account_tags: AccountTagBook::new(),
+            id_generator: None,
         }
     }
 
@@ -87,6 +236,16 @@ impl AccountManager {
         self.user_manager = Some(user_manager);
     }
 
+    /// 获取用户管理器
+    pub fn user_manager(&self) -> Option<&Arc<UserManager>> {
+        self.user_manager.as_ref()
+    }
+
+    /// 设置确定性ID生成器（确定性模式）
+    pub fn set_id_generator(&mut self, id_generator: Arc<SeededIdGenerator>) {
+        self.id_generator = Some(id_generator);
+    }
+
     /// 开户
     ///
     /// 为指定用户创建一个新的交易账户。
@@ -98,6 +257,84 @@ impl AccountManager {
     /// - `Ok(account_id)`: 成功创建的账户ID
     /// - `Err(...)`: 创建失败的错误信息
     pub fn open_account(&self, req: OpenAccountRequest) -> Result<String, ExchangeError> {
+        let notify = self.create_account(req)?;
+        let account_id = notify.account_id.clone();
+        self.publish_account_open(notify);
+        Ok(account_id)
+    }
+
+    /// 批量开户
+    ///
+    /// 逐行复用开户逻辑，某行失败不影响其它行；所有成功行的 AccountOpen 通知在全部
+    /// 开户完成后连续发布，由 StorageSubscriber 合并为一次 WAL 批量写。
+    ///
+    /// # 参数
+    /// - `reqs`: 开户请求列表
+    /// - `dry_run`: 仅校验（用户存在、账户ID未占用、初始资金合法），不实际开户
+    ///
+    /// # 返回
+    /// 与 `reqs` 一一对应的结果：成功时为账户ID（dry_run 时为请求中指定的账户ID）
+    pub fn open_accounts_batch(
+        &self,
+        reqs: Vec<OpenAccountRequest>,
+        dry_run: bool,
+    ) -> Vec<Result<Option<String>, ExchangeError>> {
+        let mut seen_ids = HashSet::new();
+        let mut notifies = Vec::new();
+
+        let results = reqs
+            .into_iter()
+            .map(|req| {
+                self.validate_open_request(&req)?;
+                if let Some(ref account_id) = req.account_id {
+                    if !seen_ids.insert(account_id.clone()) {
+                        return Err(ExchangeError::AccountError(format!(
+                            "Duplicate account id in batch: {}",
+                            account_id
+                        )));
+                    }
+                }
+                if dry_run {
+                    return Ok(req.account_id);
+                }
+
+                let notify = self.create_account(req)?;
+                let account_id = notify.account_id.clone();
+                notifies.push(notify);
+                Ok(Some(account_id))
+            })
+            .collect();
+
+        for notify in notifies {
+            self.publish_account_open(notify);
+        }
+        results
+    }
+
+    /// 开户前校验（批量开户逐行校验）
+    fn validate_open_request(&self, req: &OpenAccountRequest) -> Result<(), ExchangeError> {
+        if !req.init_cash.is_finite() || req.init_cash < 0.0 {
+            return Err(ExchangeError::AccountError(format!(
+                "Invalid init cash: {}",
+                req.init_cash
+            )));
+        }
+        if let Some(user_mgr) = &self.user_manager {
+            user_mgr.get_user(&req.user_id)?;
+        }
+        if let Some(ref account_id) = req.account_id {
+            if self.accounts.contains_key(account_id) {
+                return Err(ExchangeError::AccountError(format!(
+                    "Account already exists: {}",
+                    account_id
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// 创建账户并登记元数据与用户绑定（不发布通知）
+    fn create_account(&self, req: OpenAccountRequest) -> Result<AccountOpenNotify, ExchangeError> {
         // 验证用户是否存在（如果设置了UserManager）
         if let Some(user_mgr) = &self.user_manager {
             user_mgr.get_user(&req.user_id)?;
@@ -105,7 +342,11 @@ impl AccountManager {
 
         // 生成或使用提供的账户ID
         let account_id = req.account_id.unwrap_or_else(|| {
-            format!("ACC_{}", uuid::Uuid::new_v4().to_string().replace("-", ""))
+            let uuid = match &self.id_generator {
+                Some(ids) => ids.next_uuid(),
+                None => uuid::Uuid::new_v4(),
+            };
+            format!("ACC_{}", uuid.to_string().replace("-", ""))
         });
 
         // 检查账户是否已存在
@@ -166,19 +407,23 @@ impl AccountManager {
             }
         }
 
-        // 发送AccountOpen通知（用于WAL恢复）
+        Ok(AccountOpenNotify {
+            account_id,
+            user_id: req.user_id,
+            account_name: req.account_name,
+            init_cash: req.init_cash,
+            account_type: req.account_type as u8,
+            timestamp: chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0),
+        })
+    }
+
+    /// 发送AccountOpen通知（用于WAL恢复）
+    fn publish_account_open(&self, notify: AccountOpenNotify) {
         if let Some(broker) = &self.notification_broker {
             let notification = Notification::new(
                 NotificationType::AccountOpen,
-                Arc::from(account_id.clone()),
-                NotificationPayload::AccountOpen(AccountOpenNotify {
-                    account_id: account_id.clone(),
-                    user_id: req.user_id.clone(),
-                    account_name: req.account_name.clone(),
-                    init_cash: req.init_cash,
-                    account_type: req.account_type as u8,
-                    timestamp: chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0),
-                }),
+                Arc::from(notify.account_id.clone()),
+                NotificationPayload::AccountOpen(notify),
                 "AccountManager",
             );
 
@@ -187,8 +432,6 @@ impl AccountManager {
                 // 不返回错误，因为账户已成功创建
             }
         }
-
-        Ok(account_id)
     }
 
     /// 销户
@@ -227,6 +470,11 @@ impl AccountManager {
             }
 
             self.metadata.remove(account_id);
+            self.position_costs.remove_account(account_id);
+            self.position_lots.remove_account(account_id);
+            self.pnl_ledger.remove_account(account_id);
+            self.fixed_ledger.remove_account(account_id);
+            self.account_tags.remove_account(account_id);
 
             log::info!("Account closed: {}", account_id);
             Ok(())
@@ -248,6 +496,119 @@ impl AccountManager {
             })
     }
 
+    /// 持仓成本台账（成交更新、结算结转持仓均价）
+    pub fn position_costs(&self) -> &PositionCostBook {
+        &self.position_costs
+    }
+
+    /// 持仓批次台账（开仓批次明细、FIFO/LIFO 平仓）
+    pub fn position_lots(&self) -> &LotBook {
+        &self.position_lots
+    }
+
+    /// 开仓明细：逐笔可平量与浮动盈亏（最新价、乘数、冻结量取自 qars 持仓）
+    pub fn position_detail(
+        &self,
+        account_id: &str,
+    ) -> Result<Vec<PositionDetailView>, ExchangeError> {
+        let account = self.get_account(account_id)?;
+        let marks: HashMap<String, LotMark> = account
+            .read()
+            .hold
+            .iter()
+            .map(|(code, pos)| (code.clone(), LotMark::from_position(pos)))
+            .collect();
+        Ok(self.position_lots.position_detail(account_id, &marks))
+    }
+
+    /// 批次明细与汇总持仓对账，返回不一致的合约方向
+    pub fn reconcile_position_lots(
+        &self,
+        account_id: &str,
+    ) -> Result<Vec<LotMismatch>, ExchangeError> {
+        let account = self.get_account(account_id)?;
+        let acc = account.read();
+        Ok(self.position_lots.reconcile(account_id, acc.hold.iter()))
+    }
+
+    /// 盈亏归因流水
+    pub fn pnl_ledger(&self) -> &PnlLedger {
+        &self.pnl_ledger
+    }
+
+    /// 资金定点台账
+    pub fn fixed_ledger(&self) -> &FixedLedger {
+        &self.fixed_ledger
+    }
+
+    /// 账户标签
+    pub fn account_tags(&self) -> &AccountTagBook {
+        &self.account_tags
+    }
+
+    /// 按成交流水重算账户持仓均价（一次性修复工具）
+    ///
+    /// `trades` 为空时使用账户当日成交（仅适用于没有昨仓的账户）。
+    /// 重算手数与当前持仓一致的合约写回均价并更新台账，不一致的合约跳过并告警，
+    /// 返回修复的合约列表
+    pub fn rebuild_position_costs(
+        &self,
+        account_id: &str,
+        trades: Option<Vec<CostTrade>>,
+    ) -> Result<Vec<String>, ExchangeError> {
+        let account = self.get_account(account_id)?;
+        let mut acc = account.write();
+
+        let trades = trades.unwrap_or_else(|| {
+            let mut daily: Vec<_> = acc.dailytrades.values().collect();
+            daily.sort_by_key(|t| t.trade_date_time);
+            daily
+                .into_iter()
+                .map(|t| CostTrade {
+                    instrument_id: t.instrument_id.clone(),
+                    direction: t.direction.clone(),
+                    offset: t.offset.clone(),
+                    price: t.price,
+                    volume: t.volume,
+                    trading_day: String::new(),
+                })
+                .collect()
+        });
+
+        let mut rebuilt = Vec::new();
+        for (instrument_id, cost) in rebuild_from_trades(&trades) {
+            let Some(pos) = acc.hold.get_mut(&instrument_id) else {
+                continue;
+            };
+            let long = pos.volume_long_today + pos.volume_long_his;
+            let short = pos.volume_short_today + pos.volume_short_his;
+            if (cost.long.volume() - long).abs() > 1e-9
+                || (cost.short.volume() - short).abs() > 1e-9
+            {
+                log::warn!(
+                    "[PositionCost] Rebuild skipped {}/{}: trades give long={} short={}, position has long={} short={}",
+                    account_id,
+                    instrument_id,
+                    cost.long.volume(),
+                    cost.short.volume(),
+                    long,
+                    short
+                );
+                continue;
+            }
+            cost.write_to(pos);
+            self.position_costs.insert(account_id, &instrument_id, cost);
+            rebuilt.push(instrument_id);
+        }
+
+        log::info!(
+            "[PositionCost] Rebuilt average prices for {}: {:?}",
+            account_id,
+            rebuilt
+        );
+        Ok(rebuilt)
+    }
+
     /// 查询用户的所有账户
     /// 获取用户的账户列表
     ///
@@ -441,7 +802,9 @@ impl AccountManager {
 
     /// 同步所有账户时间
     pub fn sync_time(&self) {
-        let current_time = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let current_time = crate::utils::time_service::now_local()
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string();
         for account in self.accounts.iter() {
             account
                 .value()
@@ -475,6 +838,343 @@ impl AccountManager {
         self.metadata.get(account_id).map(|m| m.user_id.clone())
     }
 
+    // ========== 账户交易限制（只平仓 / 暂停交易） ==========
+
+    /// 设置交易限制持久化文件，并加载已保存的限制
+    ///
+    /// 限制按 account_id 保存，与账户恢复顺序无关
+    pub fn set_restriction_store(
+        &mut self,
+        path: impl Into<PathBuf>,
+    ) -> Result<usize, ExchangeError> {
+        let path = path.into();
+        let mut loaded = 0;
+        if path.exists() {
+            let json = std::fs::read_to_string(&path).map_err(|e| {
+                ExchangeError::IOError(format!("Read trading restrictions failed: {}", e))
+            })?;
+            let infos: Vec<TradingRestrictionInfo> = serde_json::from_str(&json).map_err(|e| {
+                ExchangeError::SerializationError(format!(
+                    "Trading restrictions deserialization failed: {}",
+                    e
+                ))
+            })?;
+            for info in infos {
+                self.trading_restrictions
+                    .insert(info.account_id.clone(), info);
+                loaded += 1;
+            }
+            log::info!("Loaded {} trading restrictions from {:?}", loaded, path);
+        }
+        self.restriction_store = Some(path);
+        Ok(loaded)
+    }
+
+    /// 设置账户交易限制（Normal 表示解除）
+    pub fn set_trading_restriction(
+        &self,
+        account_id: &str,
+        restriction: TradingRestriction,
+        reason: String,
+        expires_at: Option<i64>,
+        operator: &str,
+    ) -> Result<TradingRestrictionInfo, ExchangeError> {
+        let info =
+            self.store_trading_restriction(account_id, restriction, reason, expires_at, operator)?;
+        self.persist_restrictions()?;
+
+        log::info!(
+            "Trading restriction of {} set to {:?} by {} (expires_at={:?})",
+            account_id,
+            restriction,
+            operator,
+            expires_at
+        );
+        Ok(info)
+    }
+
+    /// 批量设置交易限制（只落盘一次，供日终结算等大批量场景使用），跳过不存在的账户
+    pub fn set_trading_restrictions(
+        &self,
+        account_ids: &[String],
+        restriction: TradingRestriction,
+        reason: &str,
+        expires_at: Option<i64>,
+        operator: &str,
+    ) -> Result<Vec<TradingRestrictionInfo>, ExchangeError> {
+        let infos: Vec<TradingRestrictionInfo> = account_ids
+            .iter()
+            .filter_map(|account_id| {
+                self.store_trading_restriction(
+                    account_id,
+                    restriction,
+                    reason.to_string(),
+                    expires_at,
+                    operator,
+                )
+                .ok()
+            })
+            .collect();
+        if !infos.is_empty() {
+            self.persist_restrictions()?;
+            log::info!(
+                "Trading restriction of {} account(s) set to {:?} by {} (expires_at={:?})",
+                infos.len(),
+                restriction,
+                operator,
+                expires_at
+            );
+        }
+        Ok(infos)
+    }
+
+    /// 写入内存中的交易限制（Normal 表示解除），不落盘
+    fn store_trading_restriction(
+        &self,
+        account_id: &str,
+        restriction: TradingRestriction,
+        reason: String,
+        expires_at: Option<i64>,
+        operator: &str,
+    ) -> Result<TradingRestrictionInfo, ExchangeError> {
+        if !self.accounts.contains_key(account_id) {
+            return Err(ExchangeError::AccountError(format!(
+                "Account not found: {}",
+                account_id
+            )));
+        }
+
+        let info = TradingRestrictionInfo {
+            account_id: account_id.to_string(),
+            restriction,
+            reason,
+            expires_at,
+            updated_by: operator.to_string(),
+            updated_at: chrono::Utc::now().timestamp_millis(),
+        };
+        if restriction == TradingRestriction::Normal {
+            self.trading_restrictions.remove(account_id);
+        } else {
+            self.trading_restrictions
+                .insert(account_id.to_string(), info.clone());
+        }
+        Ok(info)
+    }
+
+    /// 查询账户当前生效的交易限制（未限制或已到期返回 None）
+    pub fn get_trading_restriction(&self, account_id: &str) -> Option<TradingRestrictionInfo> {
+        let now = chrono::Utc::now().timestamp_millis();
+        self.trading_restrictions
+            .get(account_id)
+            .filter(|info| info.is_active(now))
+            .map(|info| info.clone())
+    }
+
+    /// 当前生效的交易限制列表
+    pub fn list_trading_restrictions(&self) -> Vec<TradingRestrictionInfo> {
+        let now = chrono::Utc::now().timestamp_millis();
+        self.trading_restrictions
+            .iter()
+            .filter(|info| info.is_active(now))
+            .map(|info| info.value().clone())
+            .collect()
+    }
+
+    /// 下单前检查交易限制，被拒绝时返回限制状态
+    pub fn check_trading_restriction(
+        &self,
+        account_id: &str,
+        offset: &str,
+    ) -> Result<(), TradingRestrictionInfo> {
+        match self.get_trading_restriction(account_id) {
+            Some(info) if info.rejects(offset) => Err(info),
+            _ => Ok(()),
+        }
+    }
+
+    /// 向账户推送交易限制说明（SystemNoticeNotify）
+    pub fn notify_trading_restriction(&self, info: &TradingRestrictionInfo) {
+        let Some(broker) = self.notification_broker.as_ref() else {
+            return;
+        };
+
+        let notification = Notification::new(
+            NotificationType::SystemNotice,
+            Arc::from(info.account_id.as_str()),
+            NotificationPayload::SystemNotice(SystemNoticeNotify {
+                title: "账户交易受限".to_string(),
+                content: info.describe(),
+                level: "WARNING".to_string(),
+                timestamp: chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0),
+            }),
+            "AccountManager",
+        );
+        if let Err(e) = broker.publish(notification) {
+            log::error!("Failed to publish trading restriction notice: {}", e);
+        }
+    }
+
+    /// 交易限制写入持久化文件（临时文件 + 重命名）
+    fn persist_restrictions(&self) -> Result<(), ExchangeError> {
+        let Some(path) = self.restriction_store.as_ref() else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                ExchangeError::IOError(format!("Create restriction dir failed: {}", e))
+            })?;
+        }
+
+        let infos: Vec<TradingRestrictionInfo> = self
+            .trading_restrictions
+            .iter()
+            .map(|info| info.value().clone())
+            .collect();
+        let json = serde_json::to_string_pretty(&infos).map_err(|e| {
+            ExchangeError::SerializationError(format!(
+                "Trading restrictions serialization failed: {}",
+                e
+            ))
+        })?;
+        let tmp_path = path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, json)
+            .and_then(|_| std::fs::rename(&tmp_path, path))
+            .map_err(|e| {
+                ExchangeError::IOError(format!("Write trading restrictions failed: {}", e))
+            })
+    }
+
+    // ========== 账户标签（运营分类） ==========
+
+    /// 给一批账户打标签（不存在的账户计入失败）
+    pub fn add_account_tags(
+        &self,
+        account_ids: &[String],
+        tags: &[String],
+        operator: &str,
+    ) -> Result<TagBatchResult, ExchangeError> {
+        let (existing, failed) = self.split_existing_accounts(account_ids);
+        self.account_tags.add_tags(&existing, tags, operator)?;
+        Ok(TagBatchResult {
+            accounts: existing,
+            failed,
+        })
+    }
+
+    /// 移除一批账户的标签（不存在的账户计入失败）
+    pub fn remove_account_tags(
+        &self,
+        account_ids: &[String],
+        tags: &[String],
+        operator: &str,
+    ) -> Result<TagBatchResult, ExchangeError> {
+        let (existing, failed) = self.split_existing_accounts(account_ids);
+        self.account_tags.remove_tags(&existing, tags, operator)?;
+        Ok(TagBatchResult {
+            accounts: existing,
+            failed,
+        })
+    }
+
+    /// 按标签筛选现存账户（`match_all` 为 true 时需带全部标签）
+    pub fn get_accounts_by_tags(&self, tags: &[String], match_all: bool) -> Vec<String> {
+        self.account_tags
+            .accounts_with_tags(tags, match_all)
+            .into_iter()
+            .filter(|account_id| self.accounts.contains_key(account_id))
+            .collect()
+    }
+
+    /// 按标签批量设置交易限制（批量冻结为 Suspended，Normal 表示批量解除）
+    pub fn set_trading_restriction_by_tags(
+        &self,
+        tags: &[String],
+        match_all: bool,
+        restriction: TradingRestriction,
+        reason: String,
+        expires_at: Option<i64>,
+        operator: &str,
+    ) -> Result<TagBatchResult, ExchangeError> {
+        let tags = AccountTagBook::normalize_tags(tags)?;
+        let mut result = TagBatchResult::default();
+        for account_id in self.get_accounts_by_tags(&tags, match_all) {
+            match self.set_trading_restriction(
+                &account_id,
+                restriction,
+                reason.clone(),
+                expires_at,
+                operator,
+            ) {
+                Ok(info) => {
+                    if info.is_active(chrono::Utc::now().timestamp_millis()) {
+                        self.notify_trading_restriction(&info);
+                    }
+                    result.accounts.push(account_id);
+                }
+                Err(e) => result.failed.push((account_id, e.to_string())),
+            }
+        }
+
+        // 限制已生效，审计落盘失败不影响结果
+        if let Err(e) = self.account_tags.record_batch(
+            operator,
+            TagAction::Restriction,
+            tags,
+            result.accounts.clone(),
+            format!(
+                "restriction={:?}, reason={}, expires_at={:?}, failed={}",
+                restriction,
+                reason,
+                expires_at,
+                result.failed.len()
+            ),
+        ) {
+            log::warn!("Failed to persist account tag audit: {}", e);
+        }
+        Ok(result)
+    }
+
+    /// 按标签批量设置保证金加成（0 表示取消加成）
+    pub fn set_margin_surcharge_by_tags(
+        &self,
+        tags: &[String],
+        match_all: bool,
+        surcharge: f64,
+        operator: &str,
+    ) -> Result<TagBatchResult, ExchangeError> {
+        let tags = AccountTagBook::normalize_tags(tags)?;
+        let accounts = self.get_accounts_by_tags(&tags, match_all);
+        self.account_tags
+            .set_margin_surcharge(&accounts, surcharge, tags, operator)?;
+        log::info!(
+            "Margin surcharge of {} accounts set to {} by {}",
+            accounts.len(),
+            surcharge,
+            operator
+        );
+        Ok(TagBatchResult {
+            accounts,
+            failed: Vec::new(),
+        })
+    }
+
+    /// 拆分为存在 / 不存在的账户
+    fn split_existing_accounts(
+        &self,
+        account_ids: &[String],
+    ) -> (Vec<String>, Vec<(String, String)>) {
+        let mut existing = Vec::new();
+        let mut failed = Vec::new();
+        for account_id in account_ids {
+            if self.accounts.contains_key(account_id) {
+                existing.push(account_id.clone());
+            } else {
+                failed.push((account_id.clone(), "Account not found".to_string()));
+            }
+        }
+        (existing, failed)
+    }
+
     // ========== 方案A: QIFI快照保存与恢复 ==========
 
     /// 保存所有账户快照到QIFI文件
@@ -821,6 +1521,209 @@ impl AccountManager {
 
         Ok(())
     }
+
+    /// 用户注销时匿名化其名下账户的账户名称（账户与交易数据保留）
+    ///
+    /// 账户名称同时写入 QA_Account.user_cookie（QIFI investor_name），两处一并替换。
+    /// 返回被处理的账户ID列表。
+    pub fn anonymize_user_accounts(&self, user_id: &str, account_name: &str) -> Vec<String> {
+        let account_ids = self
+            .user_accounts
+            .get(user_id)
+            .map(|ids| ids.clone())
+            .unwrap_or_default();
+
+        for account_id in &account_ids {
+            if let Some(mut metadata) = self.metadata.get_mut(account_id) {
+                metadata.account_name = account_name.to_string();
+            }
+            if let Some(account) = self.accounts.get(account_id) {
+                account.write().user_cookie = account_name.to_string();
+            }
+        }
+
+        log::info!(
+            "Anonymized {} accounts of user {}",
+            account_ids.len(),
+            user_id
+        );
+        account_ids
+    }
+
+    // ========== 账户导出/导入（迁移与备份） ==========
+
+    /// 导出单个账户的完整状态
+    pub fn export_account(&self, account_id: &str) -> Result<AccountExport, ExchangeError> {
+        let account = self.get_account(account_id)?;
+        let qifi = account.write().get_qifi_slice();
+        let lots = if self.position_lots.export_in_qifi() {
+            self.position_detail(account_id)?
+        } else {
+            Vec::new()
+        };
+
+        let metadata = self.metadata.get(account_id).map(|m| m.clone()).ok_or_else(|| {
+            ExchangeError::AccountError(format!("Account metadata not found: {}", account_id))
+        })?;
+
+        Ok(AccountExport {
+            version: ACCOUNT_EXPORT_VERSION,
+            account_id: account_id.to_string(),
+            user_id: metadata.user_id,
+            account_name: metadata.account_name,
+            account_type: metadata.account_type,
+            created_at: metadata.created_at,
+            exported_at: chrono::Utc::now().timestamp(),
+            qifi,
+            lots,
+        })
+    }
+
+    /// 批量导出账户
+    pub fn export_accounts(&self, account_ids: &[String]) -> Result<Vec<AccountExport>, ExchangeError> {
+        account_ids.iter().map(|id| self.export_account(id)).collect()
+    }
+
+    /// 导出用户名下所有账户
+    pub fn export_user_accounts(&self, user_id: &str) -> Result<Vec<AccountExport>, ExchangeError> {
+        let account_ids = self
+            .user_accounts
+            .get(user_id)
+            .map(|ids| ids.clone())
+            .unwrap_or_default();
+        self.export_accounts(&account_ids)
+    }
+
+    /// 校验导出数据的一致性
+    ///
+    /// - 版本与账户ID匹配
+    /// - 资金恒等式：权益 = 持仓保证金 + 冻结保证金 + 可用资金
+    pub fn validate_account_export(export: &AccountExport) -> Result<(), ExchangeError> {
+        if export.version > ACCOUNT_EXPORT_VERSION {
+            return Err(ExchangeError::InvalidParameter(format!(
+                "Unsupported account export version: {}",
+                export.version
+            )));
+        }
+
+        if export.account_id.is_empty() || export.qifi.account_cookie != export.account_id {
+            return Err(ExchangeError::InvalidParameter(format!(
+                "Account id mismatch: export={}, qifi={}",
+                export.account_id, export.qifi.account_cookie
+            )));
+        }
+
+        let acc = &export.qifi.accounts;
+        let occupied = acc.margin + acc.frozen_margin + acc.available;
+        let tolerance = (acc.balance.abs() * 1e-8).max(0.01);
+        if (acc.balance - occupied).abs() > tolerance {
+            return Err(ExchangeError::AccountError(format!(
+                "Inconsistent funds for {}: balance={:.2}, margin={:.2}, frozen_margin={:.2}, available={:.2}",
+                export.account_id, acc.balance, acc.margin, acc.frozen_margin, acc.available
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// 导入单个账户
+    ///
+    /// # 参数
+    /// - `export`: 导出数据
+    /// - `overwrite`: 账户已存在时是否覆盖（默认应为 false）
+    pub fn import_account(
+        &self,
+        export: AccountExport,
+        overwrite: bool,
+    ) -> Result<String, ExchangeError> {
+        Self::validate_account_export(&export)?;
+
+        let account_id = export.account_id.clone();
+        if self.accounts.contains_key(&account_id) {
+            if !overwrite {
+                return Err(ExchangeError::AccountError(format!(
+                    "Account already exists: {}",
+                    account_id
+                )));
+            }
+            self.remove_account_index(&account_id);
+        }
+
+        if let Some(user_mgr) = &self.user_manager {
+            user_mgr.get_user(&export.user_id)?;
+        }
+
+        // new_from_qifi 默认 environment="real"，需重置为 sim（同 restore_account_from_qifi）
+        let mut account = QA_Account::new_from_qifi(export.qifi);
+        account.environment = "sim".to_string();
+        if account.frozen.is_empty() {
+            self.rebuild_frozen_from_pending_orders(&mut account);
+        }
+
+        self.accounts
+            .insert(account_id.clone(), Arc::new(RwLock::new(account)));
+        self.metadata.insert(
+            account_id.clone(),
+            AccountMetadata {
+                user_id: export.user_id.clone(),
+                account_name: export.account_name,
+                account_type: export.account_type,
+                created_at: export.created_at,
+            },
+        );
+
+        let mut user_accounts = self.user_accounts.entry(export.user_id.clone()).or_default();
+        if !user_accounts.contains(&account_id) {
+            user_accounts.push(account_id.clone());
+        }
+        drop(user_accounts);
+
+        if let Some(user_mgr) = &self.user_manager {
+            if let Err(e) = user_mgr.bind_account(&export.user_id, account_id.clone()) {
+                log::warn!("Failed to bind imported account to user: {}", e);
+            }
+        }
+
+        log::info!(
+            "Imported account {} (user: {}, overwrite: {})",
+            account_id,
+            export.user_id,
+            overwrite
+        );
+        Ok(account_id)
+    }
+
+    /// 批量导入账户（单个失败不影响其他账户）
+    pub fn import_accounts(
+        &self,
+        exports: Vec<AccountExport>,
+        overwrite: bool,
+    ) -> AccountImportSummary {
+        let mut summary = AccountImportSummary::default();
+        for export in exports {
+            let account_id = export.account_id.clone();
+            match self.import_account(export, overwrite) {
+                Ok(id) => summary.imported.push(id),
+                Err(e) => summary.failed.push((account_id, e.to_string())),
+            }
+        }
+        summary
+    }
+
+    /// 移除账户及其索引（覆盖导入时使用，不做销户检查）
+    fn remove_account_index(&self, account_id: &str) {
+        self.accounts.remove(account_id);
+        if let Some((_, meta)) = self.metadata.remove(account_id) {
+            if let Some(mut accounts) = self.user_accounts.get_mut(&meta.user_id) {
+                accounts.retain(|id| id != account_id);
+            }
+            if let Some(user_mgr) = &self.user_manager {
+                if let Err(e) = user_mgr.unbind_account(&meta.user_id, account_id) {
+                    log::warn!("Failed to unbind account from user: {}", e);
+                }
+            }
+        }
+    }
 }
 
 impl Default for AccountManager {
@@ -999,6 +1902,34 @@ mod tests {
         assert_eq!(account_type, AccountType::MarketMaker);
     }
 
+    #[test]
+    fn test_anonymize_user_accounts() {
+        let mgr = AccountManager::new();
+
+        let account_id = mgr
+            .open_account(OpenAccountRequest {
+                user_id: "user_001".to_string(),
+                account_id: Some("ACC_erase_001".to_string()),
+                account_name: "Alice Zhang".to_string(),
+                init_cash: 100000.0,
+                account_type: AccountType::Individual,
+            })
+            .unwrap();
+
+        let ids = mgr.anonymize_user_accounts("user_001", "deleted_0123456789abcdef");
+        assert_eq!(ids, vec![account_id.clone()]);
+
+        let (user_id, account_name, _, _) = mgr.get_account_metadata(&account_id).unwrap();
+        assert_eq!(user_id, "user_001");
+        assert_eq!(account_name, "deleted_0123456789abcdef");
+        assert_eq!(
+            mgr.get_account(&account_id).unwrap().read().user_cookie,
+            "deleted_0123456789abcdef"
+        );
+        // 账户本身保留
+        assert_eq!(mgr.get_account(&account_id).unwrap().read().money, 100000.0);
+    }
+
     #[test]
     fn test_verify_account_ownership() {
         let mgr = AccountManager::new();
@@ -1691,4 +2622,399 @@ mod tests {
             handle.join().unwrap();
         }
     }
+
+    // ==================== 账户导出/导入测试 @yutiansut @quantaxis ====================
+
+    fn open_export_test_account(mgr: &AccountManager, account_id: &str) {
+        mgr.open_account(OpenAccountRequest {
+            user_id: "export_user".to_string(),
+            account_id: Some(account_id.to_string()),
+            account_name: "Export Test".to_string(),
+            init_cash: 1_000_000.0,
+            account_type: AccountType::Institutional,
+        })
+        .unwrap();
+    }
+
+    /// 测试导出-导入后账户状态完全一致
+    #[test]
+    fn test_export_import_roundtrip() {
+        let src = AccountManager::new();
+        open_export_test_account(&src, "export_acc");
+        {
+            let account = src.get_account("export_acc").unwrap();
+            let mut acc = account.write();
+            acc.deposit(50_000.0);
+            acc.send_order("cu2501", 2.0, "2025-01-02 09:30:00", 2, 85000.0, "", "LIMIT")
+                .unwrap();
+        }
+
+        let export = src.export_account("export_acc").unwrap();
+        assert_eq!(export.version, ACCOUNT_EXPORT_VERSION);
+        assert_eq!(export.user_id, "export_user");
+
+        // 通过 JSON 传输（可移植格式）
+        let json = serde_json::to_string(&export).unwrap();
+        let transported: AccountExport = serde_json::from_str(&json).unwrap();
+
+        let dst = AccountManager::new();
+        dst.import_account(transported, false).unwrap();
+        let reimported = dst.export_account("export_acc").unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&export.qifi.accounts).unwrap(),
+            serde_json::to_value(&reimported.qifi.accounts).unwrap()
+        );
+        let mut src_orders: Vec<_> = export.qifi.orders.keys().cloned().collect();
+        let mut dst_orders: Vec<_> = reimported.qifi.orders.keys().cloned().collect();
+        src_orders.sort();
+        dst_orders.sort();
+        assert_eq!(src_orders, dst_orders);
+        assert_eq!(export.qifi.positions.len(), reimported.qifi.positions.len());
+
+        let src_acc = src.get_account("export_acc").unwrap();
+        let dst_acc = dst.get_account("export_acc").unwrap();
+        assert_eq!(src_acc.read().money, dst_acc.read().money);
+        assert_eq!(src_acc.read().frozen.len(), dst_acc.read().frozen.len());
+        assert_eq!(dst_acc.read().environment, "sim");
+
+        assert_eq!(
+            src.get_account_metadata("export_acc"),
+            dst.get_account_metadata("export_acc")
+        );
+        assert_eq!(dst.get_user_account_count("export_user"), 1);
+    }
+
+    /// 测试已存在账户默认不覆盖，指定 overwrite 时覆盖
+    #[test]
+    fn test_import_existing_account() {
+        let src = AccountManager::new();
+        open_export_test_account(&src, "dup_acc");
+        src.get_account("dup_acc").unwrap().write().deposit(1_000.0);
+        let export = src.export_account("dup_acc").unwrap();
+
+        let dst = AccountManager::new();
+        open_export_test_account(&dst, "dup_acc");
+
+        assert!(dst.import_account(export.clone(), false).is_err());
+        assert_eq!(dst.get_account("dup_acc").unwrap().read().money, 1_000_000.0);
+
+        dst.import_account(export, true).unwrap();
+        assert_eq!(dst.get_account("dup_acc").unwrap().read().money, 1_001_000.0);
+        assert_eq!(dst.get_user_account_count("export_user"), 1);
+    }
+
+    /// 测试导入校验：资金不平衡的数据被拒绝
+    #[test]
+    fn test_import_rejects_inconsistent_funds() {
+        let src = AccountManager::new();
+        open_export_test_account(&src, "bad_acc");
+        let mut export = src.export_account("bad_acc").unwrap();
+        export.qifi.accounts.available += 10_000.0;
+
+        let dst = AccountManager::new();
+        assert!(dst.import_account(export, false).is_err());
+        assert_eq!(dst.get_account_count(), 0);
+    }
+
+    /// 测试批量导出/导入
+    #[test]
+    fn test_batch_export_import() {
+        let src = AccountManager::new();
+        for i in 0..3 {
+            open_export_test_account(&src, &format!("batch_acc_{}", i));
+        }
+
+        let exports = src.export_user_accounts("export_user").unwrap();
+        assert_eq!(exports.len(), 3);
+
+        let dst = AccountManager::new();
+        open_export_test_account(&dst, "batch_acc_0");
+        let summary = dst.import_accounts(exports, false);
+
+        assert_eq!(summary.imported.len(), 2);
+        assert_eq!(summary.failed.len(), 1);
+        assert_eq!(summary.failed[0].0, "batch_acc_0");
+        assert_eq!(dst.get_account_count(), 3);
+    }
+
+    // ==================== 批量开户测试 ====================
+
+    fn batch_open_req(
+        user_id: &str,
+        account_id: Option<&str>,
+        init_cash: f64,
+    ) -> OpenAccountRequest {
+        OpenAccountRequest {
+            user_id: user_id.to_string(),
+            account_id: account_id.map(|id| id.to_string()),
+            account_name: format!("{} account", user_id),
+            init_cash,
+            account_type: AccountType::Individual,
+        }
+    }
+
+    /// 直接写入用户（绕过 bcrypt 注册，避免测试耗时）
+    fn insert_test_user(user_mgr: &UserManager, username: &str) -> String {
+        let user = crate::user::User::new(username.to_string(), String::new());
+        let user_id = user.user_id.clone();
+        user_mgr
+            .username_index
+            .insert(username.to_string(), user_id.clone());
+        user_mgr
+            .users
+            .insert(user_id.clone(), Arc::new(RwLock::new(user)));
+        user_id
+    }
+
+    /// 测试批量开户：部分失败不影响其它行，成功行统一发布通知
+    #[test]
+    fn test_open_accounts_batch_partial_failure() {
+        let broker = Arc::new(NotificationBroker::new());
+        let mut mgr = AccountManager::with_notification_broker(broker.clone());
+        let user_mgr = Arc::new(UserManager::new());
+        let alice = insert_test_user(&user_mgr, "alice");
+        let bob = insert_test_user(&user_mgr, "bob");
+        mgr.set_user_manager(user_mgr.clone());
+        mgr.open_account(batch_open_req(&alice, Some("existing"), 1_000.0))
+            .unwrap();
+
+        let results = mgr.open_accounts_batch(
+            vec![
+                batch_open_req(&alice, None, 100_000.0),
+                batch_open_req("ghost", None, 100_000.0),
+                batch_open_req(&bob, Some("existing"), 100_000.0),
+                batch_open_req(&bob, None, -1.0),
+                batch_open_req(&bob, Some("bob_1"), 50_000.0),
+                batch_open_req(&bob, Some("bob_1"), 50_000.0),
+            ],
+            false,
+        );
+
+        assert_eq!(results.len(), 6);
+        assert!(results[0].as_ref().unwrap().is_some());
+        assert!(results[1].is_err());
+        assert!(results[2].is_err());
+        assert!(results[3].is_err());
+        assert_eq!(results[4].as_ref().unwrap().as_deref(), Some("bob_1"));
+        assert!(results[5].is_err());
+
+        assert_eq!(mgr.get_account_count(), 3);
+        assert_eq!(mgr.get_account("bob_1").unwrap().read().money, 50_000.0);
+        assert_eq!(user_mgr.get_user_accounts(&bob).unwrap(), vec!["bob_1"]);
+        // 1 条单独开户 + 2 条批量成功
+        assert_eq!(broker.get_stats().messages_sent, 3);
+    }
+
+    /// 测试 dry_run 只校验不开户
+    #[test]
+    fn test_open_accounts_batch_dry_run() {
+        let mgr = AccountManager::new();
+        let results = mgr.open_accounts_batch(
+            vec![
+                batch_open_req("u1", Some("dry_acc"), 100_000.0),
+                batch_open_req("u2", None, f64::NAN),
+            ],
+            true,
+        );
+
+        assert_eq!(results[0].as_ref().unwrap().as_deref(), Some("dry_acc"));
+        assert!(results[1].is_err());
+        assert_eq!(mgr.get_account_count(), 0);
+    }
+
+    /// 性能测试：1000 行批量开户应在 5 秒内完成
+    #[test]
+    fn test_open_accounts_batch_1000_rows() {
+        let broker = Arc::new(NotificationBroker::new());
+        let mut mgr = AccountManager::with_notification_broker(broker);
+        let user_mgr = Arc::new(UserManager::new());
+        let user_ids: Vec<String> = (0..1000)
+            .map(|i| insert_test_user(&user_mgr, &format!("contest_{:04}", i)))
+            .collect();
+        mgr.set_user_manager(user_mgr);
+
+        let reqs = user_ids
+            .iter()
+            .map(|user_id| batch_open_req(user_id, None, 1_000_000.0))
+            .collect();
+        let started = std::time::Instant::now();
+        let results = mgr.open_accounts_batch(reqs, false);
+        let elapsed = started.elapsed();
+
+        assert!(results.iter().all(|r| r.is_ok()));
+        assert_eq!(mgr.get_account_count(), 1000);
+        assert!(elapsed < std::time::Duration::from_secs(5));
+    }
+
+    /// 交易限制持久化后可按 account_id 恢复，到期自动失效
+    #[test]
+    fn test_trading_restriction_persist_and_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = dir.path().join("account_restrictions.json");
+        let open_req = |account_id: &str| OpenAccountRequest {
+            user_id: "user_001".to_string(),
+            account_id: Some(account_id.to_string()),
+            account_name: account_id.to_string(),
+            init_cash: 100000.0,
+            account_type: AccountType::Individual,
+        };
+
+        let mut mgr = AccountManager::new();
+        assert_eq!(mgr.set_restriction_store(&store).unwrap(), 0);
+        mgr.open_account(open_req("acc_close_only")).unwrap();
+        mgr.open_account(open_req("acc_expired")).unwrap();
+
+        mgr.set_trading_restriction(
+            "acc_close_only",
+            TradingRestriction::CloseOnly,
+            "临近到期".to_string(),
+            None,
+            "admin",
+        )
+        .unwrap();
+        mgr.set_trading_restriction(
+            "acc_expired",
+            TradingRestriction::Suspended,
+            "违规".to_string(),
+            Some(1),
+            "admin",
+        )
+        .unwrap();
+        assert!(mgr
+            .set_trading_restriction(
+                "missing",
+                TradingRestriction::Suspended,
+                String::new(),
+                None,
+                "admin"
+            )
+            .is_err());
+
+        let mut restored = AccountManager::new();
+        assert_eq!(restored.set_restriction_store(&store).unwrap(), 2);
+        let info = restored.get_trading_restriction("acc_close_only").unwrap();
+        assert_eq!(info.restriction, TradingRestriction::CloseOnly);
+        assert_eq!(info.reason, "临近到期");
+        assert!(restored
+            .check_trading_restriction("acc_close_only", "OPEN")
+            .is_err());
+        assert!(restored
+            .check_trading_restriction("acc_close_only", "CLOSE")
+            .is_ok());
+        assert!(restored.get_trading_restriction("acc_expired").is_none());
+        assert_eq!(restored.list_trading_restrictions().len(), 1);
+
+        // 恢复正常后移除记录
+        restored.open_account(open_req("acc_close_only")).unwrap();
+        restored
+            .set_trading_restriction(
+                "acc_close_only",
+                TradingRestriction::Normal,
+                "解除".to_string(),
+                None,
+                "admin",
+            )
+            .unwrap();
+        let mut reloaded = AccountManager::new();
+        assert_eq!(reloaded.set_restriction_store(&store).unwrap(), 1);
+        assert!(reloaded.get_trading_restriction("acc_close_only").is_none());
+    }
+
+    /// 给一批账户打标签后按标签查询、批量冻结和调保证金加成
+    #[test]
+    fn test_account_tags_batch_query_and_operate() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = dir.path().join("account_tags.json");
+        let mgr = AccountManager::new();
+        mgr.account_tags().set_store(&store).unwrap();
+        let ids: Vec<String> = (0..6).map(|i| format!("tag_acc_{}", i)).collect();
+        for id in &ids {
+            mgr.open_account(OpenAccountRequest {
+                user_id: "tag_user".to_string(),
+                account_id: Some(id.clone()),
+                account_name: id.clone(),
+                init_cash: 100000.0,
+                account_type: AccountType::Individual,
+            })
+            .unwrap();
+        }
+        let tags = |t: &[&str]| t.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        let mut batch = ids[..4].to_vec();
+        batch.push("missing".to_string());
+        let result = mgr
+            .add_account_tags(&batch, &tags(&["VIP"]), "ops")
+            .unwrap();
+        assert_eq!(result.accounts.len(), 4);
+        assert_eq!(result.failed[0].0, "missing");
+        mgr.add_account_tags(&ids[2..], &tags(&["高风险"]), "ops")
+            .unwrap();
+
+        assert_eq!(mgr.get_accounts_by_tags(&tags(&["VIP"]), false), ids[..4]);
+        assert_eq!(
+            mgr.get_accounts_by_tags(&tags(&["VIP", "高风险"]), true),
+            ids[2..4]
+        );
+        assert_eq!(
+            mgr.get_accounts_by_tags(&tags(&["VIP", "高风险"]), false)
+                .len(),
+            6
+        );
+
+        // 批量冻结高风险账户
+        let frozen = mgr
+            .set_trading_restriction_by_tags(
+                &tags(&["高风险"]),
+                false,
+                TradingRestriction::Suspended,
+                "高风险批量冻结".to_string(),
+                None,
+                "ops",
+            )
+            .unwrap();
+        assert_eq!(frozen.accounts, ids[2..]);
+        assert!(mgr.check_trading_restriction(&ids[5], "CLOSE").is_err());
+        assert!(mgr.check_trading_restriction(&ids[0], "OPEN").is_ok());
+
+        // 批量调 VIP 保证金加成
+        mgr.set_margin_surcharge_by_tags(&tags(&["VIP"]), false, 0.5, "ops")
+            .unwrap();
+        assert_eq!(mgr.account_tags().margin_surcharge(&ids[0]), 0.5);
+        assert_eq!(mgr.account_tags().margin_surcharge(&ids[5]), 0.0);
+        assert!(mgr
+            .set_margin_surcharge_by_tags(&tags(&["VIP"]), false, -1.0, "ops")
+            .is_err());
+
+        let audit = mgr.account_tags().audit_records(Some(&ids[3]), 10);
+        assert_eq!(
+            audit.iter().map(|r| r.action).collect::<Vec<_>>(),
+            vec![
+                TagAction::MarginSurcharge,
+                TagAction::Restriction,
+                TagAction::Add,
+                TagAction::Add
+            ]
+        );
+
+        // 标签与加成持久化，销户后移除
+        let reloaded = AccountManager::new();
+        assert_eq!(reloaded.account_tags().set_store(&store).unwrap(), 6);
+        assert_eq!(reloaded.account_tags().margin_surcharge(&ids[1]), 0.5);
+        let empty = AccountManager::new();
+        empty
+            .open_account(OpenAccountRequest {
+                user_id: "tag_user".to_string(),
+                account_id: Some("tag_close".to_string()),
+                account_name: "tag_close".to_string(),
+                init_cash: 0.0,
+                account_type: AccountType::Individual,
+            })
+            .unwrap();
+        empty
+            .add_account_tags(&["tag_close".to_string()], &tags(&["测试"]), "ops")
+            .unwrap();
+        empty.close_account("tag_close").unwrap();
+        assert!(empty.account_tags().tags_of("tag_close").is_empty());
+    }
 }
@@ -0,0 +1,512 @@
+//! 账户标签（运营分类）
+//! @yutiansut @quantaxis
+//!
+//! 运营给账户打标签（如 VIP / 机构 / 测试 / 高风险）并按标签筛选、统计和批量操作：
+//! - 一个账户可有多个标签；标签去除首尾空白后区分大小写，长度不超过 `MAX_TAG_LEN` 个字符
+//! - 标签的增删、重命名、删除以及按标签的批量设置都记审计（最近 `MAX_AUDIT_RECORDS` 条）
+//! - 保证金加成按账户保存（通常按标签批量设置），下单资金检查时按 `1 + 加成` 放大保证金占用
+//! - 设置持久化文件后每次变更全量写入（临时文件 + 重命名），启动时按 account_id 加载
+
+use dashmap::DashMap;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::path::PathBuf;
+
+use crate::ExchangeError;
+
+/// 标签最大长度（字符）
+pub const MAX_TAG_LEN: usize = 32;
+
+/// 保证金加成上限（1.0 表示保证金翻倍）
+pub const MAX_MARGIN_SURCHARGE: f64 = 10.0;
+
+/// 保留的审计记录数
+const MAX_AUDIT_RECORDS: usize = 10_000;
+
+/// 标签审计动作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TagAction {
+    /// 打标签
+    Add,
+    /// 移除标签
+    Remove,
+    /// 标签重命名
+    Rename,
+    /// 删除标签（从所有账户移除）
+    Delete,
+    /// 按标签批量设置保证金加成
+    MarginSurcharge,
+    /// 按标签批量设置交易限制（批量冻结 / 解冻）
+    Restriction,
+}
+
+/// 标签变更审计
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TagAuditRecord {
+    pub seq: u64,
+    pub timestamp: i64,
+    pub operator: String,
+    pub action: TagAction,
+    /// 涉及的标签
+    pub tags: Vec<String>,
+    /// 涉及的账户
+    pub accounts: Vec<String>,
+    pub detail: String,
+}
+
+/// 标签统计
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TagStat {
+    pub tag: String,
+    /// 带该标签的账户数
+    pub accounts: usize,
+}
+
+/// 按标签批量操作结果
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TagBatchResult {
+    /// 操作成功的账户
+    pub accounts: Vec<String>,
+    /// 操作失败的账户 (account_id, 原因)
+    pub failed: Vec<(String, String)>,
+}
+
+/// 持久化文件内容
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct TagStoreFile {
+    tags: BTreeMap<String, BTreeSet<String>>,
+    #[serde(default)]
+    margin_surcharges: BTreeMap<String, f64>,
+    #[serde(default)]
+    audit: Vec<TagAuditRecord>,
+}
+
+/// 账户标签簿
+#[derive(Default)]
+pub struct AccountTagBook {
+    /// account_id -> 标签
+    tags: DashMap<String, BTreeSet<String>>,
+    /// account_id -> 保证金加成（仅保存非 0 的账户）
+    margin_surcharges: DashMap<String, f64>,
+    /// 审计记录（旧 → 新）
+    audit: RwLock<VecDeque<TagAuditRecord>>,
+    /// 持久化文件（None 表示仅内存）
+    store: RwLock<Option<PathBuf>>,
+}
+
+impl AccountTagBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 规范化标签：去除首尾空白、去重，拒绝空标签和超长标签
+    pub fn normalize_tags(tags: &[String]) -> Result<Vec<String>, ExchangeError> {
+        let mut normalized = BTreeSet::new();
+        for tag in tags {
+            let tag = tag.trim();
+            if tag.is_empty() {
+                return Err(ExchangeError::InvalidParameter(
+                    "Tag must not be empty".to_string(),
+                ));
+            }
+            if tag.chars().count() > MAX_TAG_LEN {
+                return Err(ExchangeError::InvalidParameter(format!(
+                    "Tag {} exceeds {} characters",
+                    tag, MAX_TAG_LEN
+                )));
+            }
+            normalized.insert(tag.to_string());
+        }
+        if normalized.is_empty() {
+            return Err(ExchangeError::InvalidParameter(
+                "At least one tag is required".to_string(),
+            ));
+        }
+        Ok(normalized.into_iter().collect())
+    }
+
+    /// 设置持久化文件，并加载已保存的标签、保证金加成与审计
+    pub fn set_store(&self, path: impl Into<PathBuf>) -> Result<usize, ExchangeError> {
+        let path = path.into();
+        let mut loaded = 0;
+        if path.exists() {
+            let json = std::fs::read_to_string(&path)
+                .map_err(|e| ExchangeError::IOError(format!("Read account tags failed: {}", e)))?;
+            let file: TagStoreFile = serde_json::from_str(&json).map_err(|e| {
+                ExchangeError::SerializationError(format!(
+                    "Account tags deserialization failed: {}",
+                    e
+                ))
+            })?;
+            for (account_id, tags) in file.tags {
+                if !tags.is_empty() {
+                    self.tags.insert(account_id, tags);
+                    loaded += 1;
+                }
+            }
+            for (account_id, surcharge) in file.margin_surcharges {
+                self.margin_surcharges.insert(account_id, surcharge);
+            }
+            *self.audit.write() = file.audit.into_iter().collect();
+            log::info!("Loaded tags of {} accounts from {:?}", loaded, path);
+        }
+        *self.store.write() = Some(path);
+        Ok(loaded)
+    }
+
+    /// 给一批账户打标签，返回实际新增了标签的账户
+    pub fn add_tags(
+        &self,
+        account_ids: &[String],
+        tags: &[String],
+        operator: &str,
+    ) -> Result<Vec<String>, ExchangeError> {
+        let tags = Self::normalize_tags(tags)?;
+        let mut changed = Vec::new();
+        for account_id in account_ids {
+            let mut entry = self.tags.entry(account_id.clone()).or_default();
+            let before = entry.len();
+            entry.extend(tags.iter().cloned());
+            if entry.len() != before {
+                changed.push(account_id.clone());
+            }
+        }
+        if !changed.is_empty() {
+            self.record(
+                operator,
+                TagAction::Add,
+                tags,
+                changed.clone(),
+                String::new(),
+            );
+            self.persist()?;
+        }
+        Ok(changed)
+    }
+
+    /// 移除一批账户的标签，返回实际移除了标签的账户
+    pub fn remove_tags(
+        &self,
+        account_ids: &[String],
+        tags: &[String],
+        operator: &str,
+    ) -> Result<Vec<String>, ExchangeError> {
+        let tags = Self::normalize_tags(tags)?;
+        let mut changed = Vec::new();
+        for account_id in account_ids {
+            let removed = match self.tags.get_mut(account_id) {
+                Some(mut entry) => tags.iter().fold(false, |r, t| entry.remove(t) || r),
+                None => false,
+            };
+            if removed {
+                self.tags.remove_if(account_id, |_, tags| tags.is_empty());
+                changed.push(account_id.clone());
+            }
+        }
+        if !changed.is_empty() {
+            self.record(
+                operator,
+                TagAction::Remove,
+                tags,
+                changed.clone(),
+                String::new(),
+            );
+            self.persist()?;
+        }
+        Ok(changed)
+    }
+
+    /// 标签重命名（目标标签已存在时合并），返回涉及的账户
+    pub fn rename_tag(
+        &self,
+        from: &str,
+        to: &str,
+        operator: &str,
+    ) -> Result<Vec<String>, ExchangeError> {
+        let from = Self::normalize_tags(&[from.to_string()])?.remove(0);
+        let to = Self::normalize_tags(&[to.to_string()])?.remove(0);
+        if from == to {
+            return Ok(Vec::new());
+        }
+
+        let mut changed = Vec::new();
+        for mut entry in self.tags.iter_mut() {
+            if entry.remove(&from) {
+                entry.insert(to.clone());
+                changed.push(entry.key().clone());
+            }
+        }
+        if !changed.is_empty() {
+            changed.sort();
+            let detail = format!("{} -> {}", from, to);
+            self.record(
+                operator,
+                TagAction::Rename,
+                vec![from, to],
+                changed.clone(),
+                detail,
+            );
+            self.persist()?;
+        }
+        Ok(changed)
+    }
+
+    /// 删除标签（从所有账户移除），返回涉及的账户
+    pub fn delete_tag(&self, tag: &str, operator: &str) -> Result<Vec<String>, ExchangeError> {
+        let tag = Self::normalize_tags(&[tag.to_string()])?.remove(0);
+        let mut changed: Vec<String> = self
+            .tags
+            .iter_mut()
+            .filter_map(|mut entry| entry.remove(&tag).then(|| entry.key().clone()))
+            .collect();
+        self.tags.retain(|_, tags| !tags.is_empty());
+        if !changed.is_empty() {
+            changed.sort();
+            self.record(
+                operator,
+                TagAction::Delete,
+                vec![tag],
+                changed.clone(),
+                String::new(),
+            );
+            self.persist()?;
+        }
+        Ok(changed)
+    }
+
+    /// 账户的标签（按字典序）
+    pub fn tags_of(&self, account_id: &str) -> Vec<String> {
+        self.tags
+            .get(account_id)
+            .map(|tags| tags.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// 按标签筛选账户：`match_all` 为 true 时需带全部标签，否则带任一标签即可
+    pub fn accounts_with_tags(&self, tags: &[String], match_all: bool) -> Vec<String> {
+        let tags: Vec<&str> = tags.iter().map(|t| t.trim()).collect();
+        let mut accounts: Vec<String> = self
+            .tags
+            .iter()
+            .filter(|entry| {
+                if match_all {
+                    tags.iter().all(|t| entry.contains(*t))
+                } else {
+                    tags.iter().any(|t| entry.contains(*t))
+                }
+            })
+            .map(|entry| entry.key().clone())
+            .collect();
+        accounts.sort();
+        accounts
+    }
+
+    /// 各标签的账户数（按标签排序）
+    pub fn tag_stats(&self) -> Vec<TagStat> {
+        let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+        for entry in self.tags.iter() {
+            for tag in entry.value() {
+                *counts.entry(tag.clone()).or_default() += 1;
+            }
+        }
+        counts
+            .into_iter()
+            .map(|(tag, accounts)| TagStat { tag, accounts })
+            .collect()
+    }
+
+    /// 设置一批账户的保证金加成（0 表示取消加成）
+    pub fn set_margin_surcharge(
+        &self,
+        account_ids: &[String],
+        surcharge: f64,
+        tags: Vec<String>,
+        operator: &str,
+    ) -> Result<(), ExchangeError> {
+        if !(0.0..=MAX_MARGIN_SURCHARGE).contains(&surcharge) {
+            return Err(ExchangeError::InvalidParameter(format!(
+                "Margin surcharge must be in [0, {}], got {}",
+                MAX_MARGIN_SURCHARGE, surcharge
+            )));
+        }
+        for account_id in account_ids {
+            if surcharge > 0.0 {
+                self.margin_surcharges.insert(account_id.clone(), surcharge);
+            } else {
+                self.margin_surcharges.remove(account_id);
+            }
+        }
+        self.record(
+            operator,
+            TagAction::MarginSurcharge,
+            tags,
+            account_ids.to_vec(),
+            format!("surcharge={}", surcharge),
+        );
+        self.persist()
+    }
+
+    /// 账户的保证金加成（未设置为 0）
+    pub fn margin_surcharge(&self, account_id: &str) -> f64 {
+        self.margin_surcharges
+            .get(account_id)
+            .map(|s| *s)
+            .unwrap_or(0.0)
+    }
+
+    /// 销户时移除账户的标签与保证金加成
+    pub fn remove_account(&self, account_id: &str) {
+        let had_tags = self.tags.remove(account_id).is_some();
+        let had_surcharge = self.margin_surcharges.remove(account_id).is_some();
+        if had_tags || had_surcharge {
+            if let Err(e) = self.persist() {
+                log::warn!("Failed to persist account tags: {}", e);
+            }
+        }
+    }
+
+    /// 记一条批量操作审计并落盘
+    pub fn record_batch(
+        &self,
+        operator: &str,
+        action: TagAction,
+        tags: Vec<String>,
+        accounts: Vec<String>,
+        detail: String,
+    ) -> Result<(), ExchangeError> {
+        self.record(operator, action, tags, accounts, detail);
+        self.persist()
+    }
+
+    /// 记一条审计
+    fn record(
+        &self,
+        operator: &str,
+        action: TagAction,
+        tags: Vec<String>,
+        accounts: Vec<String>,
+        detail: String,
+    ) {
+        let mut audit = self.audit.write();
+        let seq = audit.back().map_or(1, |r| r.seq + 1);
+        audit.push_back(TagAuditRecord {
+            seq,
+            timestamp: chrono::Utc::now().timestamp_millis(),
+            operator: operator.to_string(),
+            action,
+            tags,
+            accounts,
+            detail,
+        });
+        while audit.len() > MAX_AUDIT_RECORDS {
+            audit.pop_front();
+        }
+    }
+
+    /// 审计记录（新 → 旧），可按账户过滤
+    pub fn audit_records(&self, account_id: Option<&str>, limit: usize) -> Vec<TagAuditRecord> {
+        self.audit
+            .read()
+            .iter()
+            .rev()
+            .filter(|r| account_id.map_or(true, |id| r.accounts.iter().any(|a| a == id)))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    /// 写入持久化文件（临时文件 + 重命名）
+    fn persist(&self) -> Result<(), ExchangeError> {
+        let store = self.store.read();
+        let Some(path) = store.as_ref() else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                ExchangeError::IOError(format!("Create account tag dir failed: {}", e))
+            })?;
+        }
+
+        let file = TagStoreFile {
+            tags: self
+                .tags
+                .iter()
+                .map(|e| (e.key().clone(), e.value().clone()))
+                .collect(),
+            margin_surcharges: self
+                .margin_surcharges
+                .iter()
+                .map(|e| (e.key().clone(), *e.value()))
+                .collect(),
+            audit: self.audit.read().iter().cloned().collect(),
+        };
+        let json = serde_json::to_string_pretty(&file).map_err(|e| {
+            ExchangeError::SerializationError(format!("Account tags serialization failed: {}", e))
+        })?;
+        let tmp_path = path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, json)
+            .and_then(|_| std::fs::rename(&tmp_path, path))
+            .map_err(|e| ExchangeError::IOError(format!("Write account tags failed: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_tag_crud_query_and_persist() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = dir.path().join("account_tags.json");
+        let book = AccountTagBook::new();
+        assert_eq!(book.set_store(&store).unwrap(), 0);
+
+        assert!(book.add_tags(&ids(&["a"]), &ids(&["  "]), "ops").is_err());
+        let changed = book
+            .add_tags(
+                &ids(&["a", "b", "c"]),
+                &ids(&["VIP", " 机构 ", "VIP"]),
+                "ops",
+            )
+            .unwrap();
+        assert_eq!(changed.len(), 3);
+        assert_eq!(book.tags_of("a"), ids(&["VIP", "机构"]));
+        book.add_tags(&ids(&["c"]), &ids(&["测试"]), "ops").unwrap();
+        book.remove_tags(&ids(&["b"]), &ids(&["机构"]), "ops")
+            .unwrap();
+
+        assert_eq!(
+            book.accounts_with_tags(&ids(&["机构"]), false),
+            ids(&["a", "c"])
+        );
+        assert_eq!(
+            book.accounts_with_tags(&ids(&["VIP", "测试"]), true),
+            ids(&["c"])
+        );
+
+        // 重命名与删除
+        assert_eq!(book.rename_tag("VIP", "高净值", "ops").unwrap().len(), 3);
+        assert_eq!(book.delete_tag("测试", "ops").unwrap(), ids(&["c"]));
+        let stats = book.tag_stats();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(
+            stats.iter().find(|s| s.tag == "高净值").unwrap().accounts,
+            3
+        );
+
+        let audit = book.audit_records(Some("c"), 10);
+        assert_eq!(audit[0].action, TagAction::Delete);
+        assert_eq!(audit.len(), 4);
+
+        let reloaded = AccountTagBook::new();
+        assert_eq!(reloaded.set_store(&store).unwrap(), 3);
+        assert_eq!(reloaded.tags_of("a"), ids(&["机构", "高净值"]));
+        assert_eq!(reloaded.audit_records(None, 100).len(), 5);
+    }
+}
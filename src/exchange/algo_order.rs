@@ -0,0 +1,1006 @@
+//! 算法单（TWAP/VWAP）执行引擎
+//! @yutiansut @quantaxis
+//!
+//! 机构客户提交母单，由交易所侧按算法拆分子单定时经 OrderRouter 提交：
+//! - TWAP：执行时段内按切片数均匀拆分，等间隔下单
+//! - VWAP：按历史成交量分布（请求携带或合约登记的分布）拆分，等间隔下单
+//! - 参数校验：每个切片时间必须落在允许下单的连续交易时段内，切片数量不小于最小申报量
+//! - 跟踪子单累计成交；母单撤销时撤掉未完成子单、跳过未下单切片
+//! - 执行进度通过 `AlgoOrderProgressNotify` 推送，并可通过查询 API 获取
+//!
+//! 时间取自可替换的 `AlgoClock`，测试使用 `SimulatedClock` 驱动切片调度。
+
+use chrono::{NaiveTime, Utc};
+use dashmap::DashMap;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::exchange::instrument_registry::InstrumentRegistry;
+use crate::exchange::order_router::{
+    CancelOrderRequest, OrderRouter, OrderStatus, SubmitOrderRequest,
+};
+use crate::exchange::trading_session::{ExchangeType, TradingCalendar, TradingState};
+use crate::notification::broker::NotificationBroker;
+use crate::notification::{
+    AlgoOrderProgressNotify, Notification, NotificationPayload, NotificationType,
+};
+use crate::service::http::models::{
+    AlgoOrderInfo, AlgoOrderStatus, AlgoSliceInfo, AlgoType, CreateAlgoOrderRequest,
+};
+use crate::utils::time_service::time_service;
+
+/// 默认最小申报量（手）
+pub const DEFAULT_MIN_ORDER_VOLUME: f64 = 1.0;
+
+/// 数量比较精度
+const VOLUME_EPSILON: f64 = 1e-9;
+
+/// 算法单时钟（毫秒时间戳）
+pub trait AlgoClock: Send + Sync {
+    fn now_ms(&self) -> i64;
+}
+
+/// 系统时钟
+#[derive(Debug, Default)]
+pub struct SystemClock;
+
+impl AlgoClock for SystemClock {
+    fn now_ms(&self) -> i64 {
+        Utc::now().timestamp_millis()
+    }
+}
+
+/// 模拟时钟（回测与测试使用，手动推进）
+#[derive(Debug, Default)]
+pub struct SimulatedClock {
+    now_ms: AtomicI64,
+}
+
+impl SimulatedClock {
+    pub fn new(now_ms: i64) -> Self {
+        Self {
+            now_ms: AtomicI64::new(now_ms),
+        }
+    }
+
+    pub fn set(&self, now_ms: i64) {
+        self.now_ms.store(now_ms, Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, delta: Duration) {
+        self.now_ms
+            .fetch_add(delta.as_millis() as i64, Ordering::SeqCst);
+    }
+}
+
+impl AlgoClock for SimulatedClock {
+    fn now_ms(&self) -> i64 {
+        self.now_ms.load(Ordering::SeqCst)
+    }
+}
+
+/// 子单切片状态
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SliceStatus {
+    Scheduled,
+    Submitted,
+    Filled,
+    Cancelled,
+    Rejected,
+    Skipped,
+}
+
+impl SliceStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SliceStatus::Scheduled => "SCHEDULED",
+            SliceStatus::Submitted => "SUBMITTED",
+            SliceStatus::Filled => "FILLED",
+            SliceStatus::Cancelled => "CANCELLED",
+            SliceStatus::Rejected => "REJECTED",
+            SliceStatus::Skipped => "SKIPPED",
+        }
+    }
+
+    fn is_final(&self) -> bool {
+        !matches!(self, SliceStatus::Scheduled | SliceStatus::Submitted)
+    }
+}
+
+/// 子单切片
+#[derive(Debug, Clone)]
+struct AlgoSlice {
+    scheduled_at: i64,
+    volume: f64,
+    order_id: Option<String>,
+    filled_volume: f64,
+    status: SliceStatus,
+}
+
+/// 内部算法单结构
+#[derive(Debug, Clone)]
+pub struct AlgoOrder {
+    pub id: String,
+    pub account_id: String,
+    pub instrument_id: String,
+    pub direction: String,
+    pub offset: String,
+    pub algo_type: AlgoType,
+    pub volume: f64,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub limit_price: Option<f64>,
+    slices: Vec<AlgoSlice>,
+    pub status: AlgoOrderStatus,
+    pub created_at: i64,
+    pub message: Option<String>,
+}
+
+impl AlgoOrder {
+    fn submitted_volume(&self) -> f64 {
+        self.slices
+            .iter()
+            .filter(|s| s.order_id.is_some())
+            .map(|s| s.volume)
+            .sum()
+    }
+
+    fn filled_volume(&self) -> f64 {
+        self.slices.iter().map(|s| s.filled_volume).sum()
+    }
+
+    fn is_terminal(&self) -> bool {
+        matches!(
+            self.status,
+            AlgoOrderStatus::Completed | AlgoOrderStatus::Cancelled | AlgoOrderStatus::Failed
+        )
+    }
+
+    /// 转换为 API 响应格式
+    pub fn to_info(&self) -> AlgoOrderInfo {
+        AlgoOrderInfo {
+            algo_order_id: self.id.clone(),
+            account_id: self.account_id.clone(),
+            instrument_id: self.instrument_id.clone(),
+            direction: self.direction.clone(),
+            offset: self.offset.clone(),
+            algo_type: self.algo_type,
+            volume: self.volume,
+            submitted_volume: self.submitted_volume(),
+            filled_volume: self.filled_volume(),
+            start_time: self.start_time,
+            end_time: self.end_time,
+            limit_price: self.limit_price,
+            slices: self
+                .slices
+                .iter()
+                .enumerate()
+                .map(|(index, s)| AlgoSliceInfo {
+                    index,
+                    scheduled_at: s.scheduled_at,
+                    volume: s.volume,
+                    order_id: s.order_id.clone(),
+                    filled_volume: s.filled_volume,
+                    status: s.status.as_str().to_string(),
+                })
+                .collect(),
+            status: self.status.clone(),
+            created_at: self.created_at,
+            message: self.message.clone(),
+        }
+    }
+
+    /// 构造进度通知
+    fn progress_notify(&self, now: i64) -> AlgoOrderProgressNotify {
+        AlgoOrderProgressNotify {
+            algo_order_id: self.id.clone(),
+            user_id: self.account_id.clone(),
+            instrument_id: self.instrument_id.clone(),
+            algo_type: format!("{:?}", self.algo_type).to_uppercase(),
+            status: format!("{:?}", self.status).to_uppercase(),
+            total_volume: self.volume,
+            submitted_volume: self.submitted_volume(),
+            filled_volume: self.filled_volume(),
+            slices_total: self.slices.len() as u32,
+            slices_submitted: self.slices.iter().filter(|s| s.order_id.is_some()).count() as u32,
+            timestamp: now,
+        }
+    }
+}
+
+/// 算法单引擎
+pub struct AlgoOrderEngine {
+    /// 算法单存储: algo_order_id -> AlgoOrder
+    orders: DashMap<String, AlgoOrder>,
+    /// 按账户索引: account_id -> Vec<algo_order_id>
+    by_account: DashMap<String, Vec<String>>,
+    /// 合约历史成交量分布（VWAP 默认使用）: instrument_id -> 各时段成交量
+    volume_profiles: DashMap<String, Vec<f64>>,
+    /// 订单路由器
+    order_router: Option<Arc<OrderRouter>>,
+    /// 合约注册表（确定合约所属交易所）
+    instrument_registry: Option<Arc<InstrumentRegistry>>,
+    /// 通知中心（推送执行进度）
+    notification_broker: Option<Arc<NotificationBroker>>,
+    /// 交易日历（校验执行时段）
+    calendar: TradingCalendar,
+    /// 时钟
+    clock: Arc<dyn AlgoClock>,
+    /// 最小申报量
+    min_order_volume: f64,
+}
+
+impl AlgoOrderEngine {
+    pub fn new() -> Self {
+        Self {
+            orders: DashMap::new(),
+            by_account: DashMap::new(),
+            volume_profiles: DashMap::new(),
+            order_router: None,
+            instrument_registry: None,
+            notification_broker: None,
+            calendar: TradingCalendar::new(),
+            clock: Arc::new(SystemClock),
+            min_order_volume: DEFAULT_MIN_ORDER_VOLUME,
+        }
+    }
+
+    /// 设置订单路由器
+    pub fn set_order_router(&mut self, router: Arc<OrderRouter>) {
+        self.order_router = Some(router);
+    }
+
+    /// 设置合约注册表
+    pub fn set_instrument_registry(&mut self, registry: Arc<InstrumentRegistry>) {
+        self.instrument_registry = Some(registry);
+    }
+
+    /// 设置通知中心
+    pub fn set_notification_broker(&mut self, broker: Arc<NotificationBroker>) {
+        self.notification_broker = Some(broker);
+    }
+
+    /// 设置时钟
+    pub fn set_clock(&mut self, clock: Arc<dyn AlgoClock>) {
+        self.clock = clock;
+    }
+
+    /// 设置最小申报量
+    pub fn set_min_order_volume(&mut self, volume: f64) {
+        self.min_order_volume = volume;
+    }
+
+    /// 登记合约历史成交量分布（按时段顺序，VWAP 未携带分布时使用）
+    pub fn set_volume_profile(&self, instrument_id: &str, profile: Vec<f64>) {
+        self.volume_profiles
+            .insert(instrument_id.to_string(), profile);
+    }
+
+    /// 创建算法单
+    pub fn create_order(&self, req: CreateAlgoOrderRequest) -> Result<AlgoOrderInfo, String> {
+        let now = self.clock.now_ms();
+
+        if req.direction != "BUY" && req.direction != "SELL" {
+            return Err(format!("无效的方向: {}", req.direction));
+        }
+        if req.volume <= 0.0 || req.volume.fract().abs() > VOLUME_EPSILON {
+            return Err(format!("母单数量必须为正整数: {}", req.volume));
+        }
+        if req.slice_count == 0 {
+            return Err("切片数必须大于0".to_string());
+        }
+        if req.end_time <= req.start_time {
+            return Err("执行结束时间必须晚于开始时间".to_string());
+        }
+        if req.end_time <= now {
+            return Err("执行时段已结束".to_string());
+        }
+        if let Some(price) = req.limit_price {
+            if price <= 0.0 {
+                return Err(format!("无效的限价: {}", price));
+            }
+        }
+
+        let schedule = Self::schedule(req.start_time, req.end_time, req.slice_count);
+        self.validate_schedule(&req.instrument_id, &schedule)?;
+
+        let volumes = match req.algo_type {
+            AlgoType::Twap => self.twap_volumes(req.volume, req.slice_count)?,
+            AlgoType::Vwap => {
+                let profile = match req.volume_profile.clone() {
+                    Some(profile) => profile,
+                    None => self
+                        .volume_profiles
+                        .get(&req.instrument_id)
+                        .map(|p| p.clone())
+                        .ok_or_else(|| {
+                            format!("合约 {} 无历史成交量分布，无法执行VWAP", req.instrument_id)
+                        })?,
+                };
+                self.vwap_volumes(req.volume, req.slice_count, &profile)?
+            }
+        };
+
+        let slices = schedule
+            .into_iter()
+            .zip(volumes)
+            .filter(|(_, volume)| *volume > 0.0)
+            .map(|(scheduled_at, volume)| AlgoSlice {
+                scheduled_at,
+                volume,
+                order_id: None,
+                filled_volume: 0.0,
+                status: SliceStatus::Scheduled,
+            })
+            .collect();
+
+        let order = AlgoOrder {
+            id: format!(
+                "ALGO_{}",
+                Uuid::new_v4().to_string().replace("-", "")[..12].to_uppercase()
+            ),
+            account_id: req.account_id,
+            instrument_id: req.instrument_id,
+            direction: req.direction,
+            offset: req.offset,
+            algo_type: req.algo_type,
+            volume: req.volume,
+            start_time: req.start_time,
+            end_time: req.end_time,
+            limit_price: req.limit_price,
+            slices,
+            status: AlgoOrderStatus::Pending,
+            created_at: now,
+            message: None,
+        };
+
+        let order_id = order.id.clone();
+        self.by_account
+            .entry(order.account_id.clone())
+            .or_default()
+            .push(order_id.clone());
+        let info = order.to_info();
+        self.notify_progress(&order, now);
+        self.orders.insert(order_id.clone(), order);
+
+        log::info!(
+            "算法单已创建: {} {:?} {} {} 手, {} 个切片",
+            order_id,
+            info.algo_type,
+            info.instrument_id,
+            info.volume,
+            info.slices.len()
+        );
+        Ok(info)
+    }
+
+    /// 切片计划时间：执行时段等分，每段起点下单
+    fn schedule(start_time: i64, end_time: i64, slice_count: u32) -> Vec<i64> {
+        let interval = (end_time - start_time) / slice_count as i64;
+        (0..slice_count as i64)
+            .map(|i| start_time + i * interval)
+            .collect()
+    }
+
+    /// 校验每个切片时间都在允许下单的连续交易时段内
+    fn validate_schedule(&self, instrument_id: &str, schedule: &[i64]) -> Result<(), String> {
+        let exchange = self
+            .instrument_registry
+            .as_ref()
+            .and_then(|r| r.get(instrument_id))
+            .and_then(|info| ExchangeType::from_str(&info.exchange))
+            .or_else(|| ExchangeType::from_instrument_id(instrument_id))
+            .unwrap_or(ExchangeType::SIM);
+
+        for &ts in schedule {
+            let time = local_time(ts).ok_or_else(|| format!("无效的时间戳: {}", ts))?;
+            let tradable = self
+                .calendar
+                .get_current_session(exchange, time)
+                .map(|s| s.allow_order && matches!(s.state, TradingState::ContinuousTrading))
+                .unwrap_or(false);
+            if !tradable {
+                return Err(format!(
+                    "切片时间 {} 不在 {:?} 连续交易时段内",
+                    time.format("%H:%M:%S"),
+                    exchange
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// TWAP 数量分配：均分，余数依次分给靠前的切片
+    fn twap_volumes(&self, volume: f64, slice_count: u32) -> Result<Vec<f64>, String> {
+        let lots = volume.round() as u64;
+        let n = slice_count as u64;
+        let base = lots / n;
+        if (base as f64) < self.min_order_volume {
+            return Err(format!(
+                "切片数量 {} 小于最小申报量 {}（母单 {} 手 / {} 个切片）",
+                base, self.min_order_volume, lots, n
+            ));
+        }
+        let remainder = lots % n;
+        Ok((0..n)
+            .map(|i| (base + u64::from(i < remainder)) as f64)
+            .collect())
+    }
+
+    /// VWAP 数量分配：按成交量分布最大余数法取整，不足最小申报量的切片并入后续切片
+    fn vwap_volumes(
+        &self,
+        volume: f64,
+        slice_count: u32,
+        profile: &[f64],
+    ) -> Result<Vec<f64>, String> {
+        if profile.is_empty() || profile.iter().any(|w| *w < 0.0 || !w.is_finite()) {
+            return Err("成交量分布无效".to_string());
+        }
+        let n = slice_count as usize;
+        // 分布长度与切片数不一致时按比例映射
+        let weights: Vec<f64> = (0..n).map(|i| profile[i * profile.len() / n]).collect();
+        let total_weight: f64 = weights.iter().sum();
+        if total_weight <= 0.0 {
+            return Err("成交量分布总量为0".to_string());
+        }
+
+        let lots = volume.round() as u64;
+        let exact: Vec<f64> = weights
+            .iter()
+            .map(|w| lots as f64 * w / total_weight)
+            .collect();
+        let mut alloc: Vec<u64> = exact.iter().map(|v| v.floor() as u64).collect();
+        let mut rest = lots - alloc.iter().sum::<u64>();
+        let mut order: Vec<usize> = (0..n).collect();
+        order.sort_by(|&a, &b| {
+            (exact[b] - exact[b].floor())
+                .partial_cmp(&(exact[a] - exact[a].floor()))
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(a.cmp(&b))
+        });
+        for i in order {
+            if rest == 0 {
+                break;
+            }
+            alloc[i] += 1;
+            rest -= 1;
+        }
+
+        let min = self.min_order_volume;
+        let mut volumes = vec![0.0; n];
+        let mut carry = 0.0;
+        for i in 0..n {
+            let v = alloc[i] as f64 + carry;
+            if v + VOLUME_EPSILON >= min {
+                volumes[i] = v;
+                carry = 0.0;
+            } else {
+                carry = v;
+            }
+        }
+        if carry > 0.0 {
+            match volumes.iter_mut().rev().find(|v| **v > 0.0) {
+                Some(last) => *last += carry,
+                None => return Err(format!("母单数量 {} 小于最小申报量 {}", lots, min)),
+            }
+        }
+        Ok(volumes)
+    }
+
+    /// 获取算法单
+    pub fn get_order(&self, algo_order_id: &str) -> Option<AlgoOrderInfo> {
+        self.orders.get(algo_order_id).map(|o| o.to_info())
+    }
+
+    /// 获取账户的算法单
+    pub fn get_orders_by_account(&self, account_id: &str) -> Vec<AlgoOrderInfo> {
+        self.by_account
+            .get(account_id)
+            .map(|ids| ids.iter().filter_map(|id| self.get_order(id)).collect())
+            .unwrap_or_default()
+    }
+
+    /// 调度一轮：同步子单成交、提交到期切片、结束到期母单
+    ///
+    /// 返回本轮进度有变化的算法单
+    pub fn process(&self) -> Vec<AlgoOrderInfo> {
+        let now = self.clock.now_ms();
+        let ids: Vec<String> = self
+            .orders
+            .iter()
+            .filter(|o| !o.is_terminal())
+            .map(|o| o.id.clone())
+            .collect();
+
+        let mut changed = Vec::new();
+        for id in ids {
+            let Some(mut order) = self.orders.get_mut(&id) else {
+                continue;
+            };
+            if order.is_terminal() {
+                continue;
+            }
+            if self.process_order(&mut order, now) {
+                self.notify_progress(&order, now);
+                changed.push(order.to_info());
+            }
+        }
+        changed
+    }
+
+    /// 调度单个算法单，返回进度是否变化
+    fn process_order(&self, order: &mut AlgoOrder, now: i64) -> bool {
+        let Some(router) = self.order_router.clone() else {
+            return false;
+        };
+        let mut changed = self.sync_fills(&router, order);
+
+        if order.status == AlgoOrderStatus::Pending && now >= order.start_time {
+            order.status = AlgoOrderStatus::Running;
+            changed = true;
+        }
+        if order.status != AlgoOrderStatus::Running {
+            return changed;
+        }
+
+        for slice in order.slices.iter_mut() {
+            if slice.status != SliceStatus::Scheduled || slice.scheduled_at > now {
+                continue;
+            }
+            let (order_type, price) = match order.limit_price {
+                Some(price) => ("LIMIT", price),
+                None => ("MARKET", 0.0),
+            };
+            let response = router.submit_order(SubmitOrderRequest {
+                account_id: order.account_id.clone(),
+                instrument_id: order.instrument_id.clone(),
+                direction: order.direction.clone(),
+                offset: order.offset.clone(),
+                volume: slice.volume,
+                price,
+                order_type: order_type.to_string(),
+                time_condition: None,
+                volume_condition: None,
+                hedge_flag: None,
+                ttl_secs: None,
+                client_order_id: None,
+                close_lot_id: None,
+            });
+            slice.order_id = response.order_id;
+            if response.success {
+                slice.status = SliceStatus::Submitted;
+            } else {
+                slice.status = SliceStatus::Rejected;
+                let reason = response.error_message.unwrap_or_default();
+                log::warn!("算法单 {} 子单下单失败: {}", order.id, reason);
+                order.message = Some(format!("子单下单失败: {}", reason));
+            }
+            changed = true;
+        }
+        changed |= self.sync_fills(&router, order);
+
+        // 全部切片失败
+        if order
+            .slices
+            .iter()
+            .all(|s| s.status == SliceStatus::Rejected)
+        {
+            order.status = AlgoOrderStatus::Failed;
+            log::error!("算法单执行失败: {} (子单全部被拒)", order.id);
+            return true;
+        }
+
+        // 到达结束时间且切片全部下单：撤掉未成交子单并结束
+        let all_placed = order
+            .slices
+            .iter()
+            .all(|s| s.status != SliceStatus::Scheduled);
+        if all_placed && (now >= order.end_time || order.slices.iter().all(|s| s.status.is_final()))
+        {
+            self.cancel_working_slices(&router, order);
+            order.status = AlgoOrderStatus::Completed;
+            log::info!(
+                "算法单执行结束: {} 成交 {}/{}",
+                order.id,
+                order.filled_volume(),
+                order.volume
+            );
+            changed = true;
+        }
+        changed
+    }
+
+    /// 同步子单成交，返回是否有变化
+    fn sync_fills(&self, router: &OrderRouter, order: &mut AlgoOrder) -> bool {
+        let mut changed = false;
+        for slice in order.slices.iter_mut() {
+            if slice.status != SliceStatus::Submitted {
+                continue;
+            }
+            let Some(order_id) = slice.order_id.as_deref() else {
+                continue;
+            };
+            let Some((_, status, _, _, filled)) = router.get_order_detail(order_id) else {
+                continue;
+            };
+            if (filled - slice.filled_volume).abs() > VOLUME_EPSILON {
+                slice.filled_volume = filled;
+                changed = true;
+            }
+            let next = match status {
+                OrderStatus::Filled => SliceStatus::Filled,
+                OrderStatus::Cancelled => SliceStatus::Cancelled,
+                OrderStatus::Rejected => SliceStatus::Rejected,
+                _ => SliceStatus::Submitted,
+            };
+            if next != slice.status {
+                slice.status = next;
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// 撤销未完成子单
+    fn cancel_working_slices(&self, router: &OrderRouter, order: &mut AlgoOrder) {
+        self.sync_fills(router, order);
+        for slice in order.slices.iter_mut() {
+            if slice.status != SliceStatus::Submitted {
+                continue;
+            }
+            let Some(order_id) = slice.order_id.clone() else {
+                continue;
+            };
+            match router.cancel_order(CancelOrderRequest {
+                account_id: order.account_id.clone(),
+                order_id: order_id.clone(),
+            }) {
+                Ok(()) => slice.status = SliceStatus::Cancelled,
+                Err(e) => log::warn!("算法单 {} 撤销子单 {} 失败: {}", order.id, order_id, e),
+            }
+        }
+        self.sync_fills(router, order);
+    }
+
+    /// 撤销算法单：撤掉未完成子单，跳过未下单切片
+    pub fn cancel_order(&self, algo_order_id: &str) -> Result<AlgoOrderInfo, String> {
+        let mut order = self
+            .orders
+            .get_mut(algo_order_id)
+            .ok_or_else(|| format!("算法单不存在: {}", algo_order_id))?;
+
+        if order.is_terminal() {
+            return Err(format!("算法单已结束，无法撤销: {:?}", order.status));
+        }
+
+        if let Some(router) = self.order_router.clone() {
+            self.cancel_working_slices(&router, &mut order);
+        }
+        for slice in order.slices.iter_mut() {
+            if slice.status == SliceStatus::Scheduled {
+                slice.status = SliceStatus::Skipped;
+            }
+        }
+        order.status = AlgoOrderStatus::Cancelled;
+
+        let now = self.clock.now_ms();
+        self.notify_progress(&order, now);
+        log::info!(
+            "算法单已撤销: {} 成交 {}/{}",
+            algo_order_id,
+            order.filled_volume(),
+            order.volume
+        );
+        Ok(order.to_info())
+    }
+
+    /// 推送执行进度
+    fn notify_progress(&self, order: &AlgoOrder, now: i64) {
+        let Some(broker) = &self.notification_broker else {
+            return;
+        };
+        let notification = Notification::new(
+            NotificationType::AlgoOrderProgress,
+            Arc::from(order.account_id.as_str()),
+            NotificationPayload::AlgoOrderProgress(order.progress_notify(now)),
+            "AlgoOrderEngine",
+        );
+        if let Err(e) = broker.publish(notification) {
+            log::error!("Failed to publish AlgoOrderProgress notification: {}", e);
+        }
+    }
+
+    /// 获取统计信息
+    pub fn get_statistics(&self) -> AlgoOrderStatistics {
+        let mut stats = AlgoOrderStatistics {
+            total: self.orders.len(),
+            ..Default::default()
+        };
+        for entry in self.orders.iter() {
+            match entry.status {
+                AlgoOrderStatus::Pending => stats.pending += 1,
+                AlgoOrderStatus::Running => stats.running += 1,
+                AlgoOrderStatus::Completed => stats.completed += 1,
+                AlgoOrderStatus::Cancelled => stats.cancelled += 1,
+                AlgoOrderStatus::Failed => stats.failed += 1,
+            }
+        }
+        stats
+    }
+}
+
+impl Default for AlgoOrderEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 毫秒时间戳转交易所本地时间
+fn local_time(ts_ms: i64) -> Option<NaiveTime> {
+    chrono::DateTime::from_timestamp_millis(ts_ms)
+        .map(|dt| dt.with_timezone(&time_service().offset()).time())
+}
+
+/// 算法单统计信息
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct AlgoOrderStatistics {
+    pub total: usize,
+    pub pending: usize,
+    pub running: usize,
+    pub completed: usize,
+    pub cancelled: usize,
+    pub failed: usize,
+}
+
+// 全局算法单引擎
+lazy_static::lazy_static! {
+    pub static ref ALGO_ORDER_ENGINE: parking_lot::RwLock<AlgoOrderEngine> =
+        parking_lot::RwLock::new(AlgoOrderEngine::new());
+}
+
+/// 启动算法单调度线程：按固定间隔提交到期切片并同步子单成交
+pub fn start_algo_order_scheduler(interval: Duration) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        log::info!(
+            "Algo order scheduler started (interval: {}ms)",
+            interval.as_millis()
+        );
+        loop {
+            std::thread::sleep(interval);
+            ALGO_ORDER_ENGINE.read().process();
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::account_ext::{AccountType, OpenAccountRequest};
+    use crate::exchange::instrument_registry::{InstrumentInfo, InstrumentStatus, InstrumentType};
+    use crate::exchange::{AccountManager, TradeGateway};
+    use crate::matching::engine::ExchangeMatchingEngine;
+
+    const MINUTE_MS: i64 = 60_000;
+
+    /// 今日交易所本地时间（毫秒时间戳）
+    fn today_at(h: u32, m: u32) -> i64 {
+        let time_service = time_service();
+        time_service.day_start_millis(time_service.today()) + ((h * 60 + m) as i64) * MINUTE_MS
+    }
+
+    fn create_engine(start_ms: i64) -> (AlgoOrderEngine, Arc<OrderRouter>, Arc<SimulatedClock>) {
+        let account_mgr = Arc::new(AccountManager::new());
+        for user in ["algo_user", "mm_user"] {
+            account_mgr
+                .open_account(OpenAccountRequest {
+                    user_id: user.to_string(),
+                    account_id: Some(user.to_string()),
+                    account_name: user.to_string(),
+                    init_cash: 10000000.0,
+                    account_type: AccountType::Institutional,
+                })
+                .unwrap();
+        }
+
+        let matching_engine = Arc::new(ExchangeMatchingEngine::new());
+        matching_engine
+            .register_instrument("IX2301".to_string(), 100.0)
+            .unwrap();
+        let registry = Arc::new(InstrumentRegistry::new());
+        registry
+            .register(InstrumentInfo {
+                instrument_id: "IX2301".to_string(),
+                instrument_name: "IX2301".to_string(),
+                instrument_type: InstrumentType::CommodityFuture,
+                exchange: "SHFE".to_string(),
+                contract_multiplier: 1,
+                trading_unit: "手".to_string(),
+                quote_unit: String::new(),
+                price_tick: 0.01,
+                margin_rate: 0.1,
+                commission_rate: 0.0005,
+                limit_up_rate: 0.1,
+                limit_down_rate: 0.1,
+                status: InstrumentStatus::Active,
+                list_date: Some("2023-01-01".to_string()),
+                expire_date: Some("2023-12-31".to_string()),
+                created_at: "2023-01-01T00:00:00Z".to_string(),
+                updated_at: "2023-01-01T00:00:00Z".to_string(),
+            })
+            .unwrap();
+
+        let trade_gateway = Arc::new(TradeGateway::new(account_mgr.clone()));
+        let router = Arc::new(OrderRouter::new(
+            account_mgr,
+            matching_engine,
+            registry.clone(),
+            trade_gateway,
+        ));
+
+        let clock = Arc::new(SimulatedClock::new(start_ms));
+        let mut engine = AlgoOrderEngine::new();
+        engine.set_order_router(router.clone());
+        engine.set_instrument_registry(registry);
+        engine.set_clock(clock.clone());
+        (engine, router, clock)
+    }
+
+    fn twap_request(volume: f64, start: i64, end: i64, slice_count: u32) -> CreateAlgoOrderRequest {
+        CreateAlgoOrderRequest {
+            account_id: "algo_user".to_string(),
+            instrument_id: "IX2301".to_string(),
+            direction: "BUY".to_string(),
+            offset: "OPEN".to_string(),
+            volume,
+            algo_type: AlgoType::Twap,
+            start_time: start,
+            end_time: end,
+            slice_count,
+            limit_price: Some(100.0),
+            volume_profile: None,
+        }
+    }
+
+    #[test]
+    fn test_twap_slice_timing_and_distribution() {
+        let start = today_at(9, 30);
+        let (engine, router, clock) = create_engine(start - MINUTE_MS);
+
+        // 10 手 / 4 个切片 → 3,3,2,2，每 5 分钟一片
+        let info = engine
+            .create_order(twap_request(10.0, start, start + 20 * MINUTE_MS, 4))
+            .unwrap();
+        let volumes: Vec<f64> = info.slices.iter().map(|s| s.volume).collect();
+        assert_eq!(volumes, vec![3.0, 3.0, 2.0, 2.0]);
+        let times: Vec<i64> = info.slices.iter().map(|s| s.scheduled_at).collect();
+        assert_eq!(
+            times,
+            (0..4)
+                .map(|i| start + i * 5 * MINUTE_MS)
+                .collect::<Vec<_>>()
+        );
+        let id = info.algo_order_id;
+
+        // 开始前不下单
+        engine.process();
+        let info = engine.get_order(&id).unwrap();
+        assert_eq!(info.status, AlgoOrderStatus::Pending);
+        assert_eq!(info.submitted_volume, 0.0);
+
+        // 到达开始时间：第一片下单
+        clock.set(start);
+        engine.process();
+        let info = engine.get_order(&id).unwrap();
+        assert_eq!(info.status, AlgoOrderStatus::Running);
+        assert_eq!(info.submitted_volume, 3.0);
+        assert_eq!(info.slices[0].status, "SUBMITTED");
+        assert_eq!(info.slices[1].status, "SCHEDULED");
+
+        // 对手盘成交第一片
+        let sell = router.submit_order(SubmitOrderRequest {
+            account_id: "mm_user".to_string(),
+            instrument_id: "IX2301".to_string(),
+            direction: "SELL".to_string(),
+            offset: "OPEN".to_string(),
+            volume: 3.0,
+            price: 100.0,
+            order_type: "LIMIT".to_string(),
+            ..Default::default()
+        });
+        assert!(sell.success);
+
+        // 未到下一片时间不提前下单
+        clock.advance(Duration::from_secs(4 * 60 + 59));
+        engine.process();
+        let info = engine.get_order(&id).unwrap();
+        assert_eq!(info.submitted_volume, 3.0);
+        assert_eq!(info.filled_volume, 3.0);
+        assert_eq!(info.slices[0].status, "FILLED");
+
+        // 跳过中间调度，补交已到期的两片
+        clock.set(start + 10 * MINUTE_MS);
+        engine.process();
+        let info = engine.get_order(&id).unwrap();
+        assert_eq!(info.submitted_volume, 8.0);
+
+        // 最后一片下单，结束时间后撤掉未成交子单
+        clock.set(start + 15 * MINUTE_MS);
+        engine.process();
+        assert_eq!(engine.get_order(&id).unwrap().submitted_volume, 10.0);
+        clock.set(start + 20 * MINUTE_MS);
+        engine.process();
+        let info = engine.get_order(&id).unwrap();
+        assert_eq!(info.status, AlgoOrderStatus::Completed);
+        assert_eq!(info.filled_volume, 3.0);
+        assert!(info.slices[1..].iter().all(|s| s.status == "CANCELLED"));
+    }
+
+    #[test]
+    fn test_cancel_midway_cleans_up_children() {
+        let start = today_at(9, 30);
+        let (engine, router, clock) = create_engine(start);
+
+        let info = engine
+            .create_order(twap_request(6.0, start, start + 30 * MINUTE_MS, 3))
+            .unwrap();
+        let id = info.algo_order_id;
+
+        engine.process();
+        clock.advance(Duration::from_secs(10 * 60));
+        engine.process();
+        let info = engine.get_order(&id).unwrap();
+        assert_eq!(info.submitted_volume, 4.0);
+        let child_ids: Vec<String> = info.slices[..2]
+            .iter()
+            .map(|s| s.order_id.clone().unwrap())
+            .collect();
+
+        let info = engine.cancel_order(&id).unwrap();
+        assert_eq!(info.status, AlgoOrderStatus::Cancelled);
+        assert_eq!(info.slices[2].status, "SKIPPED");
+        assert!(info.slices[2].order_id.is_none());
+        for child in &child_ids {
+            assert_eq!(router.get_order_status(child), Some(OrderStatus::Cancelled));
+        }
+
+        // 撤销后继续推进时钟也不再下单
+        clock.advance(Duration::from_secs(30 * 60));
+        assert!(engine.process().is_empty());
+        assert_eq!(engine.get_order(&id).unwrap().submitted_volume, 4.0);
+        assert!(engine.cancel_order(&id).is_err());
+    }
+
+    #[test]
+    fn test_parameter_validation() {
+        let start = today_at(9, 30);
+        let (mut engine, _router, _clock) = create_engine(start - MINUTE_MS);
+
+        // 切片小于最小申报量
+        let err = engine
+            .create_order(twap_request(3.0, start, start + 20 * MINUTE_MS, 4))
+            .unwrap_err();
+        assert!(err.contains("最小申报量"), "{}", err);
+
+        // 跨越上午休息（10:15-10:30）
+        let err = engine
+            .create_order(twap_request(10.0, today_at(10, 0), today_at(10, 40), 4))
+            .unwrap_err();
+        assert!(err.contains("10:20:00"), "{}", err);
+
+        // VWAP 无历史分布
+        let mut req = twap_request(10.0, start, start + 20 * MINUTE_MS, 4);
+        req.algo_type = AlgoType::Vwap;
+        assert!(engine.create_order(req.clone()).is_err());
+
+        // 按历史分布拆分，不足最小申报量的切片并入后续切片
+        engine.set_min_order_volume(2.0);
+        engine.set_volume_profile("IX2301", vec![5.0, 1.0, 2.0, 2.0]);
+        let info = engine.create_order(req).unwrap();
+        let volumes: Vec<f64> = info.slices.iter().map(|s| s.volume).collect();
+        assert_eq!(volumes, vec![5.0, 3.0, 2.0]);
+        assert_eq!(info.volume, 10.0);
+    }
+}
@@ -0,0 +1,736 @@
+//! 大宗交易（Block Trade）协商成交
+//! @yutiansut @quantaxis
+//!
+//! 机构间场外协商价格后在系统内登记成交，不经连续撮合：
+//! - 发起方（买方或卖方之一）登记双方账户、合约、数量、协商价，发起方视为已确认
+//! - 对手方确认后执行：两腿冻结资金后直接生成成交，写入账户与成交记录
+//! - 协商价须在参考价（最新价）± 最大偏离内，避免利益输送；同时受涨跌停约束
+//! - 不进入订单簿，不影响连续盘口和最新价；成交回报标记为 BLOCK
+//! - 任一方可拒绝，发起方可撤回；超过确认期限未确认自动过期
+
+use chrono::Utc;
+use dashmap::DashMap;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::exchange::order_router::OrderRouter;
+
+/// 大宗交易配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockTradeConfig {
+    /// 协商价相对参考价的最大偏离比例
+    #[serde(default = "default_max_price_deviation")]
+    pub max_price_deviation: f64,
+    /// 单笔最小手数（大宗交易门槛）
+    #[serde(default = "default_min_volume")]
+    pub min_volume: f64,
+    /// 对手方确认期限（秒）
+    #[serde(default = "default_confirm_timeout_secs")]
+    pub confirm_timeout_secs: i64,
+}
+
+fn default_max_price_deviation() -> f64 {
+    0.05
+}
+fn default_min_volume() -> f64 {
+    1.0
+}
+fn default_confirm_timeout_secs() -> i64 {
+    600
+}
+
+impl Default for BlockTradeConfig {
+    fn default() -> Self {
+        Self {
+            max_price_deviation: default_max_price_deviation(),
+            min_volume: default_min_volume(),
+            confirm_timeout_secs: default_confirm_timeout_secs(),
+        }
+    }
+}
+
+/// 大宗交易状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum BlockTradeStatus {
+    /// 等待对手方确认
+    PendingConfirm,
+    /// 已成交
+    Executed,
+    /// 对手方拒绝
+    Rejected,
+    /// 发起方撤回
+    Cancelled,
+    /// 超过确认期限
+    Expired,
+    /// 执行失败（资金/持仓不足、价格越界等）
+    Failed,
+}
+
+/// 登记大宗交易请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateBlockTradeRequest {
+    /// 发起方账户（须为买方或卖方）
+    pub initiator_account_id: String,
+    pub buy_account_id: String,
+    pub sell_account_id: String,
+    pub instrument_id: String,
+    pub volume: f64,
+    /// 协商价
+    pub price: f64,
+    /// 买方开平（OPEN/CLOSE/CLOSETODAY）
+    #[serde(default = "default_offset")]
+    pub buy_offset: String,
+    /// 卖方开平（OPEN/CLOSE/CLOSETODAY）
+    #[serde(default = "default_offset")]
+    pub sell_offset: String,
+}
+
+fn default_offset() -> String {
+    "OPEN".to_string()
+}
+
+/// 确认/拒绝/撤回请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockTradeActionRequest {
+    /// 操作方账户
+    pub account_id: String,
+    /// 拒绝原因（仅拒绝时使用）
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// 大宗交易记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockTrade {
+    pub block_trade_id: String,
+    pub exchange_id: String,
+    pub instrument_id: String,
+    pub initiator_account_id: String,
+    pub buy_account_id: String,
+    pub sell_account_id: String,
+    pub buy_offset: String,
+    pub sell_offset: String,
+    pub volume: f64,
+    pub price: f64,
+    /// 登记时的参考价
+    pub reference_price: f64,
+    pub buy_confirmed: bool,
+    pub sell_confirmed: bool,
+    pub status: BlockTradeStatus,
+    /// (买方, 卖方) 交易所成交编号
+    pub exchange_trade_ids: Option<(String, String)>,
+    pub message: Option<String>,
+    pub created_at: i64,
+    pub expire_at: i64,
+    pub updated_at: i64,
+}
+
+impl BlockTrade {
+    fn is_party(&self, account_id: &str) -> bool {
+        self.buy_account_id == account_id || self.sell_account_id == account_id
+    }
+}
+
+/// 大宗交易统计信息
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BlockTradeStatistics {
+    pub total: usize,
+    pub pending_confirm: usize,
+    pub executed: usize,
+    pub rejected: usize,
+    pub cancelled: usize,
+    pub expired: usize,
+    pub failed: usize,
+    /// 已成交总手数
+    pub executed_volume: f64,
+}
+
+/// 大宗交易引擎
+pub struct BlockTradeEngine {
+    /// block_trade_id -> BlockTrade
+    trades: DashMap<String, BlockTrade>,
+    /// 订单路由器（参考价、执行成交）
+    order_router: Option<Arc<OrderRouter>>,
+    config: RwLock<BlockTradeConfig>,
+}
+
+impl BlockTradeEngine {
+    pub fn new() -> Self {
+        Self {
+            trades: DashMap::new(),
+            order_router: None,
+            config: RwLock::new(BlockTradeConfig::default()),
+        }
+    }
+
+    /// 设置订单路由器
+    pub fn set_order_router(&mut self, router: Arc<OrderRouter>) {
+        self.order_router = Some(router);
+    }
+
+    pub fn update_config(&self, config: BlockTradeConfig) {
+        *self.config.write() = config;
+    }
+
+    pub fn config(&self) -> BlockTradeConfig {
+        self.config.read().clone()
+    }
+
+    fn router(&self) -> Result<&Arc<OrderRouter>, String> {
+        self.order_router
+            .as_ref()
+            .ok_or_else(|| "大宗交易引擎未设置订单路由器".to_string())
+    }
+
+    /// 校验协商价在参考价 ± 最大偏离内，返回参考价
+    fn check_price(&self, instrument_id: &str, price: f64) -> Result<f64, String> {
+        let reference = self
+            .router()?
+            .reference_price(instrument_id)
+            .ok_or_else(|| format!("合约 {} 无参考价，无法登记大宗交易", instrument_id))?;
+        let deviation = self.config.read().max_price_deviation;
+        let (lower, upper) = (reference * (1.0 - deviation), reference * (1.0 + deviation));
+        if price < lower || price > upper {
+            return Err(format!(
+                "协商价 {} 超出合理范围 [{:.4}, {:.4}]（参考价 {}，最大偏离 {:.2}%）",
+                price,
+                lower,
+                upper,
+                reference,
+                deviation * 100.0
+            ));
+        }
+        Ok(reference)
+    }
+
+    /// 登记大宗交易（发起方自动确认，等待对手方确认）
+    pub fn create(&self, req: CreateBlockTradeRequest) -> Result<BlockTrade, String> {
+        if req.buy_account_id == req.sell_account_id {
+            return Err("买卖双方不能为同一账户".to_string());
+        }
+        if req.initiator_account_id != req.buy_account_id
+            && req.initiator_account_id != req.sell_account_id
+        {
+            return Err(format!(
+                "发起方 {} 必须为买方或卖方",
+                req.initiator_account_id
+            ));
+        }
+        let min_volume = self.config.read().min_volume;
+        if !req.volume.is_finite() || req.volume < min_volume {
+            return Err(format!(
+                "大宗交易数量 {} 低于最小手数 {}",
+                req.volume, min_volume
+            ));
+        }
+        if !req.price.is_finite() || req.price <= 0.0 {
+            return Err(format!("协商价必须大于0: {}", req.price));
+        }
+        for offset in [&req.buy_offset, &req.sell_offset] {
+            if !matches!(offset.as_str(), "OPEN" | "CLOSE" | "CLOSETODAY") {
+                return Err(format!("开平标志必须为 OPEN/CLOSE/CLOSETODAY: {}", offset));
+            }
+        }
+
+        let router = self.router()?;
+        let exchange_id = router
+            .instrument_registry()
+            .get(&req.instrument_id)
+            .map(|info| info.exchange)
+            .ok_or_else(|| format!("合约不存在: {}", req.instrument_id))?;
+        let reference_price = self.check_price(&req.instrument_id, req.price)?;
+
+        let now = Utc::now().timestamp_millis();
+        let timeout_ms = self.config.read().confirm_timeout_secs * 1000;
+        let trade = BlockTrade {
+            block_trade_id: format!(
+                "BLK_{}",
+                Uuid::new_v4().to_string().replace("-", "")[..12].to_uppercase()
+            ),
+            exchange_id,
+            buy_confirmed: req.initiator_account_id == req.buy_account_id,
+            sell_confirmed: req.initiator_account_id == req.sell_account_id,
+            instrument_id: req.instrument_id,
+            initiator_account_id: req.initiator_account_id,
+            buy_account_id: req.buy_account_id,
+            sell_account_id: req.sell_account_id,
+            buy_offset: req.buy_offset,
+            sell_offset: req.sell_offset,
+            volume: req.volume,
+            price: req.price,
+            reference_price,
+            status: BlockTradeStatus::PendingConfirm,
+            exchange_trade_ids: None,
+            message: None,
+            created_at: now,
+            expire_at: now + timeout_ms,
+            updated_at: now,
+        };
+
+        log::info!(
+            "大宗交易登记: {} {} 买方={} 卖方={} {} 手 @ {} (参考价 {})",
+            trade.block_trade_id,
+            trade.instrument_id,
+            trade.buy_account_id,
+            trade.sell_account_id,
+            trade.volume,
+            trade.price,
+            reference_price
+        );
+        self.trades
+            .insert(trade.block_trade_id.clone(), trade.clone());
+        Ok(trade)
+    }
+
+    /// 对手方确认，双方均确认后立即执行成交
+    ///
+    /// 执行失败（资金不足、价格越界等）时状态置为 Failed，双方账户不变
+    pub fn confirm(&self, block_trade_id: &str, account_id: &str) -> Result<BlockTrade, String> {
+        let mut trade = self
+            .trades
+            .get_mut(block_trade_id)
+            .ok_or_else(|| format!("大宗交易不存在: {}", block_trade_id))?;
+        self.check_pending(&mut trade, account_id)?;
+
+        if trade.buy_account_id == account_id {
+            trade.buy_confirmed = true;
+        }
+        if trade.sell_account_id == account_id {
+            trade.sell_confirmed = true;
+        }
+        trade.updated_at = Utc::now().timestamp_millis();
+        if !(trade.buy_confirmed && trade.sell_confirmed) {
+            return Ok(trade.clone());
+        }
+
+        // 执行前按当前参考价复核协商价
+        let result = self
+            .check_price(&trade.instrument_id, trade.price)
+            .and_then(|_| {
+                self.router()?
+                    .execute_block_trade(&trade)
+                    .map_err(|e| e.to_string())
+            });
+        match result {
+            Ok(trade_ids) => {
+                trade.status = BlockTradeStatus::Executed;
+                trade.exchange_trade_ids = Some(trade_ids);
+                log::info!("大宗交易成交: {}", trade.block_trade_id);
+            }
+            Err(e) => {
+                log::warn!("大宗交易执行失败: {} - {}", trade.block_trade_id, e);
+                trade.status = BlockTradeStatus::Failed;
+                trade.message = Some(e);
+            }
+        }
+        trade.updated_at = Utc::now().timestamp_millis();
+        Ok(trade.clone())
+    }
+
+    /// 对手方拒绝
+    pub fn reject(
+        &self,
+        block_trade_id: &str,
+        account_id: &str,
+        reason: Option<String>,
+    ) -> Result<BlockTrade, String> {
+        let mut trade = self
+            .trades
+            .get_mut(block_trade_id)
+            .ok_or_else(|| format!("大宗交易不存在: {}", block_trade_id))?;
+        self.check_pending(&mut trade, account_id)?;
+        if trade.initiator_account_id == account_id {
+            return Err("发起方请使用撤回".to_string());
+        }
+
+        trade.status = BlockTradeStatus::Rejected;
+        trade.message = reason;
+        trade.updated_at = Utc::now().timestamp_millis();
+        log::info!("大宗交易被拒绝: {} by {}", block_trade_id, account_id);
+        Ok(trade.clone())
+    }
+
+    /// 发起方撤回
+    pub fn cancel(&self, block_trade_id: &str, account_id: &str) -> Result<BlockTrade, String> {
+        let mut trade = self
+            .trades
+            .get_mut(block_trade_id)
+            .ok_or_else(|| format!("大宗交易不存在: {}", block_trade_id))?;
+        self.check_pending(&mut trade, account_id)?;
+        if trade.initiator_account_id != account_id {
+            return Err("只有发起方可以撤回".to_string());
+        }
+
+        trade.status = BlockTradeStatus::Cancelled;
+        trade.updated_at = Utc::now().timestamp_millis();
+        log::info!("大宗交易已撤回: {}", block_trade_id);
+        Ok(trade.clone())
+    }
+
+    /// 校验操作方与状态（过期的在此标记为 Expired）
+    fn check_pending(&self, trade: &mut BlockTrade, account_id: &str) -> Result<(), String> {
+        if !trade.is_party(account_id) {
+            return Err(format!(
+                "账户 {} 不是大宗交易 {} 的参与方",
+                account_id, trade.block_trade_id
+            ));
+        }
+        let now = Utc::now().timestamp_millis();
+        if trade.status == BlockTradeStatus::PendingConfirm && now > trade.expire_at {
+            trade.status = BlockTradeStatus::Expired;
+            trade.updated_at = now;
+        }
+        if trade.status != BlockTradeStatus::PendingConfirm {
+            return Err(format!("大宗交易状态不允许操作: {:?}", trade.status));
+        }
+        Ok(())
+    }
+
+    /// 将超过确认期限的大宗交易标记为过期，返回过期数量
+    pub fn expire_stale(&self) -> usize {
+        let now = Utc::now().timestamp_millis();
+        let mut expired = 0;
+        for mut trade in self.trades.iter_mut() {
+            if trade.status == BlockTradeStatus::PendingConfirm && now > trade.expire_at {
+                trade.status = BlockTradeStatus::Expired;
+                trade.updated_at = now;
+                expired += 1;
+            }
+        }
+        expired
+    }
+
+    pub fn get(&self, block_trade_id: &str) -> Option<BlockTrade> {
+        self.trades.get(block_trade_id).map(|t| t.value().clone())
+    }
+
+    /// 账户参与的大宗交易（按登记时间倒序）
+    pub fn list_by_account(&self, account_id: &str) -> Vec<BlockTrade> {
+        let mut trades: Vec<BlockTrade> = self
+            .trades
+            .iter()
+            .filter(|t| t.is_party(account_id))
+            .map(|t| t.value().clone())
+            .collect();
+        trades.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        trades
+    }
+
+    /// 获取统计信息
+    pub fn get_statistics(&self) -> BlockTradeStatistics {
+        let mut stats = BlockTradeStatistics {
+            total: self.trades.len(),
+            ..Default::default()
+        };
+        for entry in self.trades.iter() {
+            match entry.status {
+                BlockTradeStatus::PendingConfirm => stats.pending_confirm += 1,
+                BlockTradeStatus::Executed => {
+                    stats.executed += 1;
+                    stats.executed_volume += entry.volume;
+                }
+                BlockTradeStatus::Rejected => stats.rejected += 1,
+                BlockTradeStatus::Cancelled => stats.cancelled += 1,
+                BlockTradeStatus::Expired => stats.expired += 1,
+                BlockTradeStatus::Failed => stats.failed += 1,
+            }
+        }
+        stats
+    }
+}
+
+impl Default for BlockTradeEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// 全局大宗交易引擎
+lazy_static::lazy_static! {
+    pub static ref BLOCK_TRADE_ENGINE: parking_lot::RwLock<BlockTradeEngine> =
+        parking_lot::RwLock::new(BlockTradeEngine::new());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::account_ext::{AccountType, OpenAccountRequest};
+    use crate::exchange::instrument_registry::{InstrumentInfo, InstrumentStatus, InstrumentType};
+    use crate::exchange::order_router::SubmitOrderRequest;
+    use crate::exchange::{AccountManager, InstrumentRegistry, TradeGateway, TradeType};
+    use crate::matching::engine::ExchangeMatchingEngine;
+    use crate::matching::trade_recorder::TradeRecorder;
+
+    const INIT_CASH: f64 = 1_000_000.0;
+
+    struct Fixture {
+        engine: BlockTradeEngine,
+        router: Arc<OrderRouter>,
+        account_mgr: Arc<AccountManager>,
+        recorder: Arc<TradeRecorder>,
+    }
+
+    /// 合约 BK2301 参考价 100；机构 inst_a / inst_b 各 100 万
+    fn create_fixture() -> Fixture {
+        let account_mgr = Arc::new(AccountManager::new());
+        for user in ["inst_a", "inst_b"] {
+            account_mgr
+                .open_account(OpenAccountRequest {
+                    user_id: user.to_string(),
+                    account_id: Some(user.to_string()),
+                    account_name: user.to_string(),
+                    init_cash: INIT_CASH,
+                    account_type: AccountType::Institutional,
+                })
+                .unwrap();
+        }
+
+        let matching_engine = Arc::new(ExchangeMatchingEngine::new());
+        matching_engine
+            .register_instrument("BK2301".to_string(), 100.0)
+            .unwrap();
+        let registry = Arc::new(InstrumentRegistry::new());
+        registry
+            .register(InstrumentInfo {
+                instrument_id: "BK2301".to_string(),
+                instrument_name: "BK2301".to_string(),
+                instrument_type: InstrumentType::CommodityFuture,
+                exchange: "SHFE".to_string(),
+                contract_multiplier: 1,
+                trading_unit: "手".to_string(),
+                quote_unit: String::new(),
+                price_tick: 0.01,
+                margin_rate: 0.1,
+                commission_rate: 0.0005,
+                limit_up_rate: 0.1,
+                limit_down_rate: 0.1,
+                status: InstrumentStatus::Active,
+                list_date: Some("2023-01-01".to_string()),
+                expire_date: Some("2023-12-31".to_string()),
+                created_at: "2023-01-01T00:00:00Z".to_string(),
+                updated_at: "2023-01-01T00:00:00Z".to_string(),
+            })
+            .unwrap();
+
+        let recorder = Arc::new(TradeRecorder::new());
+        let trade_gateway = Arc::new(TradeGateway::new(account_mgr.clone()));
+        let router = Arc::new(OrderRouter::new(
+            account_mgr.clone(),
+            matching_engine,
+            registry,
+            trade_gateway,
+        ));
+        router
+            .trade_bus()
+            .subscribe_inline("trade_recorder", recorder.clone());
+
+        let mut engine = BlockTradeEngine::new();
+        engine.set_order_router(router.clone());
+        Fixture {
+            engine,
+            router,
+            account_mgr,
+            recorder,
+        }
+    }
+
+    fn request(price: f64, buy_offset: &str, sell_offset: &str) -> CreateBlockTradeRequest {
+        CreateBlockTradeRequest {
+            initiator_account_id: "inst_a".to_string(),
+            buy_account_id: "inst_a".to_string(),
+            sell_account_id: "inst_b".to_string(),
+            instrument_id: "BK2301".to_string(),
+            volume: 10.0,
+            price,
+            buy_offset: buy_offset.to_string(),
+            sell_offset: sell_offset.to_string(),
+        }
+    }
+
+    /// (多头手数, 空头手数, 可用资金)
+    fn account_state(fixture: &Fixture, account_id: &str) -> (f64, f64, f64) {
+        let account = fixture.account_mgr.get_account(account_id).unwrap();
+        let acc = account.read();
+        let (long, short) = acc.hold.get("BK2301").map_or((0.0, 0.0), |pos| {
+            (
+                pos.volume_long_today + pos.volume_long_his,
+                pos.volume_short_today + pos.volume_short_his,
+            )
+        });
+        (long, short, acc.money)
+    }
+
+    /// 账户无持仓且资金未占用
+    fn assert_untouched(fixture: &Fixture, account_id: &str) {
+        let (long, short, money) = account_state(fixture, account_id);
+        assert_eq!((long, short), (0.0, 0.0), "{}", account_id);
+        assert!(
+            (money - INIT_CASH).abs() < 1e-6,
+            "{}: {}",
+            account_id,
+            money
+        );
+    }
+
+    #[test]
+    fn test_block_trade_updates_both_accounts() {
+        let fixture = create_fixture();
+        let engine = &fixture.engine;
+
+        // 1. 买方登记开仓大宗交易，卖方确认前账户不变
+        let trade = engine.create(request(101.0, "OPEN", "OPEN")).unwrap();
+        assert_eq!(trade.status, BlockTradeStatus::PendingConfirm);
+        assert!(trade.buy_confirmed && !trade.sell_confirmed);
+        assert_eq!(trade.reference_price, 100.0);
+        assert_untouched(&fixture, "inst_b");
+        // 非参与方不能确认
+        assert!(engine.confirm(&trade.block_trade_id, "inst_c").is_err());
+
+        // 2. 卖方确认后成交
+        let executed = engine.confirm(&trade.block_trade_id, "inst_b").unwrap();
+        assert_eq!(
+            executed.status,
+            BlockTradeStatus::Executed,
+            "{:?}",
+            executed.message
+        );
+        assert!(executed.exchange_trade_ids.is_some());
+
+        let (buy_long, buy_short, buy_money) = account_state(&fixture, "inst_a");
+        let (sell_long, sell_short, sell_money) = account_state(&fixture, "inst_b");
+        assert_eq!((buy_long, buy_short), (10.0, 0.0));
+        assert_eq!((sell_long, sell_short), (0.0, 10.0));
+        // 同价同量开仓，双方占用资金（保证金 + 手续费）相同
+        let buy_used = INIT_CASH - buy_money;
+        let sell_used = INIT_CASH - sell_money;
+        assert!(buy_used > 0.0);
+        assert!((buy_used - sell_used).abs() < 1e-6);
+
+        // 3. 成交记录单独标记为大宗，订单簿与最新价不受影响
+        let records = fixture.recorder.get_trades_by_instrument("BK2301");
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].trade_type, TradeType::Block);
+        assert_eq!(records[0].buy_user_id, "inst_a");
+        assert_eq!(records[0].sell_user_id, "inst_b");
+        assert_eq!(records[0].price, 101.0);
+        assert_eq!(fixture.router.reference_price("BK2301"), Some(100.0));
+        assert_eq!(fixture.router.get_active_order_count(), 0);
+
+        // 4. 反向平仓大宗交易（卖方发起，协商价 104）：多头盈利、空头亏损
+        let close = engine
+            .create(CreateBlockTradeRequest {
+                initiator_account_id: "inst_b".to_string(),
+                buy_account_id: "inst_b".to_string(),
+                sell_account_id: "inst_a".to_string(),
+                ..request(104.0, "CLOSE", "CLOSE")
+            })
+            .unwrap();
+        let closed = engine.confirm(&close.block_trade_id, "inst_a").unwrap();
+        assert_eq!(
+            closed.status,
+            BlockTradeStatus::Executed,
+            "{:?}",
+            closed.message
+        );
+
+        let (a_long, a_short, a_money) = account_state(&fixture, "inst_a");
+        let (b_long, b_short, b_money) = account_state(&fixture, "inst_b");
+        assert_eq!((a_long, a_short), (0.0, 0.0));
+        assert_eq!((b_long, b_short), (0.0, 0.0));
+        assert!(a_money > b_money);
+        assert_eq!(engine.get_statistics().executed, 2);
+    }
+
+    #[test]
+    fn test_block_trade_validation_and_confirmation_flow() {
+        let fixture = create_fixture();
+        let engine = &fixture.engine;
+
+        // 协商价偏离参考价超过 5% 拒绝登记
+        let err = engine.create(request(106.0, "OPEN", "OPEN")).unwrap_err();
+        assert!(err.contains("超出合理范围"), "{}", err);
+        // 同一账户、非参与方发起
+        let mut same = request(100.0, "OPEN", "OPEN");
+        same.sell_account_id = "inst_a".to_string();
+        assert!(engine.create(same).is_err());
+        let mut outsider = request(100.0, "OPEN", "OPEN");
+        outsider.initiator_account_id = "inst_c".to_string();
+        assert!(engine.create(outsider).is_err());
+
+        // 对手方拒绝
+        let trade = engine.create(request(100.0, "OPEN", "OPEN")).unwrap();
+        let rejected = engine
+            .reject(
+                &trade.block_trade_id,
+                "inst_b",
+                Some("价格不合适".to_string()),
+            )
+            .unwrap();
+        assert_eq!(rejected.status, BlockTradeStatus::Rejected);
+        assert!(engine.confirm(&trade.block_trade_id, "inst_b").is_err());
+
+        // 发起方撤回
+        let trade = engine.create(request(100.0, "OPEN", "OPEN")).unwrap();
+        assert!(engine.cancel(&trade.block_trade_id, "inst_b").is_err());
+        let cancelled = engine.cancel(&trade.block_trade_id, "inst_a").unwrap();
+        assert_eq!(cancelled.status, BlockTradeStatus::Cancelled);
+
+        // 卖方无持仓却申报平仓：执行失败，买方冻结回滚
+        let trade = engine.create(request(100.0, "OPEN", "CLOSE")).unwrap();
+        let failed = engine.confirm(&trade.block_trade_id, "inst_b").unwrap();
+        assert_eq!(failed.status, BlockTradeStatus::Failed);
+        assert!(failed.message.is_some());
+        assert_untouched(&fixture, "inst_a");
+        assert_untouched(&fixture, "inst_b");
+        assert!(fixture
+            .recorder
+            .get_trades_by_instrument("BK2301")
+            .is_empty());
+
+        // 资金不足：100 万买不起 2 万手
+        let mut huge = request(100.0, "OPEN", "OPEN");
+        huge.volume = 20_000.0;
+        let trade = engine.create(huge).unwrap();
+        let failed = engine.confirm(&trade.block_trade_id, "inst_b").unwrap();
+        assert_eq!(failed.status, BlockTradeStatus::Failed);
+        assert_untouched(&fixture, "inst_a");
+
+        // 连续盘口下单不受大宗交易影响
+        let response = fixture.router.submit_order(SubmitOrderRequest {
+            account_id: "inst_a".to_string(),
+            instrument_id: "BK2301".to_string(),
+            direction: "BUY".to_string(),
+            offset: "OPEN".to_string(),
+            volume: 1.0,
+            price: 99.0,
+            order_type: "LIMIT".to_string(),
+            ..Default::default()
+        });
+        assert!(response.success, "{:?}", response.error_message);
+    }
+
+    #[test]
+    fn test_expire_stale() {
+        let fixture = create_fixture();
+        fixture.engine.update_config(BlockTradeConfig {
+            confirm_timeout_secs: -1,
+            ..Default::default()
+        });
+        let trade = fixture
+            .engine
+            .create(request(100.0, "OPEN", "OPEN"))
+            .unwrap();
+        assert_eq!(fixture.engine.expire_stale(), 1);
+        assert_eq!(
+            fixture.engine.get(&trade.block_trade_id).unwrap().status,
+            BlockTradeStatus::Expired
+        );
+        assert!(fixture
+            .engine
+            .confirm(&trade.block_trade_id, "inst_b")
+            .is_err());
+    }
+}
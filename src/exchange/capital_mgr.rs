@@ -2,9 +2,11 @@
 //!
 //! 负责管理账户资金的出入金、流水记录等功能
 
-use crate::exchange::AccountManager;
+use crate::exchange::{AccountLeaseHold, AccountLeaseManager, AccountManager};
+use crate::risk::RiskMonitor;
 use crate::ExchangeError;
 use dashmap::DashMap;
+use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
@@ -22,6 +24,8 @@ pub enum TransactionType {
     PnL,
     /// 结算
     Settlement,
+    /// 利息（结算时计息入账，负数为收取）
+    Interest,
 }
 
 /// 交易状态
@@ -71,6 +75,10 @@ pub struct CapitalManager {
     transactions: DashMap<String, Vec<FundTransaction>>,
     /// 交易序列号
     transaction_seq: std::sync::atomic::AtomicU64,
+    /// 账户操作租约（可选，多实例共享存储部署时启用）
+    account_lease: Option<Arc<AccountLeaseManager>>,
+    /// 风险监控器（可选，入金后立即重新评估追保状态）
+    risk_monitor: RwLock<Option<Arc<RiskMonitor>>>,
 }
 
 impl CapitalManager {
@@ -79,6 +87,30 @@ impl CapitalManager {
             account_mgr,
             transactions: DashMap::new(),
             transaction_seq: std::sync::atomic::AtomicU64::new(1),
+            account_lease: None,
+            risk_monitor: RwLock::new(None),
+        }
+    }
+
+    /// 设置风险监控器（入金后补足保证金即解除追保）
+    pub fn set_risk_monitor(&self, monitor: Arc<RiskMonitor>) {
+        *self.risk_monitor.write() = Some(monitor);
+    }
+
+    /// 出入金前获取账户操作租约（其他实例持有时拒绝）
+    pub fn with_account_lease(mut self, lease: Arc<AccountLeaseManager>) -> Self {
+        self.account_lease = Some(lease);
+        self
+    }
+
+    /// 持有凭证在出入金结束后 drop（发布账户状态）
+    fn acquire_account_lease(
+        &self,
+        account_id: &str,
+    ) -> Result<Option<AccountLeaseHold<'_>>, ExchangeError> {
+        match self.account_lease {
+            Some(ref lease) => lease.acquire(account_id).map(Some),
+            None => Ok(None),
         }
     }
 
@@ -87,7 +119,7 @@ impl CapitalManager {
         let seq = self
             .transaction_seq
             .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-        let now = chrono::Local::now();
+        let now = crate::utils::time_service::now_local();
         format!("TXN{}{:08}", now.format("%Y%m%d"), seq)
     }
 
@@ -112,6 +144,7 @@ impl CapitalManager {
                 "存款金额必须大于0".to_string(),
             ));
         }
+        let _lease = self.acquire_account_lease(&account_id)?;
 
         // 获取账户当前余额（通过QIFI slice计算）
         let balance_before = {
@@ -130,7 +163,9 @@ impl CapitalManager {
         };
 
         // 创建交易记录
-        let now = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let now = crate::utils::time_service::now_local()
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string();
         let transaction = FundTransaction {
             transaction_id: self.generate_transaction_id(),
             user_id: account_id.clone(), // Phase 10: 这里存储account_id
@@ -158,6 +193,10 @@ impl CapitalManager {
             transaction.transaction_id
         );
 
+        if let Some(ref monitor) = *self.risk_monitor.read() {
+            monitor.reevaluate_margin_call(&account_id);
+        }
+
         Ok(transaction)
     }
 
@@ -190,6 +229,7 @@ impl CapitalManager {
                 "取款金额必须大于0".to_string(),
             ));
         }
+        let _lease = self.acquire_account_lease(&account_id)?;
 
         // 获取账户当前余额和可用资金（通过QIFI slice计算）
         let (balance_before, available) = {
@@ -216,7 +256,9 @@ impl CapitalManager {
         };
 
         // 创建交易记录
-        let now = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let now = crate::utils::time_service::now_local()
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string();
         let transaction = FundTransaction {
             transaction_id: self.generate_transaction_id(),
             user_id: account_id.clone(), // Phase 10: 这里存储account_id
@@ -247,6 +289,47 @@ impl CapitalManager {
         Ok(transaction)
     }
 
+    /// 记录利息流水（余额已由结算引擎调整，amount 为负表示收取利息）
+    pub fn record_interest(
+        &self,
+        account_id: &str,
+        amount: f64,
+        balance_before: f64,
+        balance_after: f64,
+        remark: Option<String>,
+    ) -> FundTransaction {
+        let now = crate::utils::time_service::now_local()
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string();
+        let transaction = FundTransaction {
+            transaction_id: self.generate_transaction_id(),
+            user_id: account_id.to_string(),
+            transaction_type: TransactionType::Interest,
+            amount,
+            balance_before,
+            balance_after,
+            status: TransactionStatus::Completed,
+            method: None,
+            remark,
+            created_at: now.clone(),
+            updated_at: now,
+        };
+
+        self.transactions
+            .entry(account_id.to_string())
+            .or_default()
+            .push(transaction.clone());
+
+        log::info!(
+            "Interest posted: account_id={}, amount={:.2}, transaction_id={}",
+            account_id,
+            amount,
+            transaction.transaction_id
+        );
+
+        transaction
+    }
+
     /// 获取用户的资金流水
     pub fn get_transactions(&self, user_id: &str) -> Vec<FundTransaction> {
         self.transactions
@@ -278,6 +361,19 @@ impl CapitalManager {
             .collect()
     }
 
+    /// 删除 `since`（含，格式 YYYY-MM-DD HH:MM:SS）之后的资金流水，返回删除条数
+    pub fn remove_transactions_since(&self, since: &str) -> usize {
+        let mut removed = 0;
+        for mut entry in self.transactions.iter_mut() {
+            let before = entry.value().len();
+            entry
+                .value_mut()
+                .retain(|txn| txn.created_at.as_str() < since);
+            removed += before - entry.value().len();
+        }
+        removed
+    }
+
     /// 获取所有用户的资金流水数量
     pub fn get_total_transaction_count(&self) -> usize {
         self.transactions
@@ -413,4 +509,46 @@ mod tests {
         let txns = capital_mgr.get_transactions(&account_id);
         assert_eq!(txns.len(), 2);
     }
+
+    /// 测试入金后立即重新评估追保：风险度回落即解除追保，不等下一轮盘中检查
+    #[test]
+    fn test_deposit_releases_margin_call() {
+        use crate::core::account_ext::{AccountType, OpenAccountRequest};
+        use crate::risk::MarginCallLevel;
+
+        let account_mgr = Arc::new(AccountManager::new());
+        account_mgr
+            .open_account(OpenAccountRequest {
+                user_id: "mc_user".to_string(),
+                account_id: Some("mc_user".to_string()),
+                account_name: "Margin Call User".to_string(),
+                init_cash: 10000.0,
+                account_type: AccountType::Individual,
+            })
+            .unwrap();
+        let monitor = Arc::new(RiskMonitor::new(account_mgr.clone()));
+        let capital_mgr = CapitalManager::new(account_mgr);
+        capital_mgr.set_risk_monitor(monitor.clone());
+
+        // 账户此前风险度 102% 进入追保
+        let now = chrono::Utc::now().timestamp_millis();
+        let event = monitor
+            .margin_call()
+            .evaluate("mc_user", 1.02, 9800.0, 10000.0, now)
+            .unwrap();
+        assert_eq!(event.level, MarginCallLevel::MarginCall);
+        assert_eq!(monitor.margin_call().margin_call_states().len(), 1);
+
+        capital_mgr
+            .deposit_with_record("mc_user".to_string(), 5000.0, None, None)
+            .unwrap();
+
+        let state = monitor.margin_call().get_state("mc_user").unwrap();
+        assert_eq!(state.level, MarginCallLevel::Normal);
+        assert!(state.deadline.is_none());
+        assert!(monitor.margin_call().margin_call_states().is_empty());
+        let history = monitor.margin_call().get_history("mc_user");
+        assert!(history.last().unwrap().released);
+        assert!(!history.last().unwrap().liquidate);
+    }
 }
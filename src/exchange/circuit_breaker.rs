@@ -0,0 +1,748 @@
+//! 极端行情熔断
+//!
+//! @yutiansut @quantaxis
+//!
+//! 参考中金所熔断规则：合约在 N 分钟内价格波动（相对窗口内最高/最低价）超过 X% 时暂停连续交易，
+//! 切换到集合竞价申报状态（`TradingState::AuctionOrder`）进入冷静期，M 分钟后自动恢复连续交易。
+//!
+//! - **触发条件**按合约配置（未单独配置的合约使用默认规则，均未配置则不熔断）
+//! - **冷静期**内只接受限价单，撤单按规则配置放行或拒绝（`OrderRouter` 下单/撤单时校验）
+//! - **每日上限**：同一合约一个自然日（交易所时区）内触发次数达到上限后不再熔断
+//! - 状态切换写入交易状态机与撮合引擎（`ExchangeMatchingEngine::set_trading_state`），
+//!   连续撮合的暂停与期满前的竞价撮合由撮合侧按交易状态执行
+//! - 触发与恢复事件写审计日志，通过 `MarketDataEvent::TradingStateChanged` 广播，
+//!   并向配置的接收人推送 `SystemNoticeNotify`
+
+use chrono::NaiveDate;
+use dashmap::DashMap;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::exchange::TradingStateMachine;
+use crate::market::{MarketDataBroadcaster, MarketDataEvent};
+use crate::matching::engine::ExchangeMatchingEngine;
+use crate::matching::TradingState;
+use crate::notification::message::{
+    Notification, NotificationPayload, NotificationType, SystemNoticeNotify,
+};
+use crate::notification::NotificationBroker;
+use crate::service::http::account_admin::log_audit;
+use crate::service::http::models::{AuditLogType, AuditResult};
+use crate::utils::time_service::time_service;
+use crate::utils::timestamp::millis_to_nanos;
+use crate::ExchangeError;
+
+/// 保留的熔断事件条数
+const MAX_HISTORY: usize = 1_000;
+
+/// 合约熔断规则
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CircuitBreakerRule {
+    /// 波动统计窗口（秒）
+    #[serde(default = "default_window_secs")]
+    pub window_secs: u64,
+    /// 触发阈值（窗口内涨跌幅，0.05 表示 5%）
+    #[serde(default = "default_threshold")]
+    pub threshold: f64,
+    /// 冷静期长度（秒）
+    #[serde(default = "default_cooldown_secs")]
+    pub cooldown_secs: u64,
+    /// 冷静期内是否允许撤单
+    #[serde(default)]
+    pub allow_cancel: bool,
+    /// 每日最多触发次数（0 表示不限）
+    #[serde(default = "default_max_triggers_per_day")]
+    pub max_triggers_per_day: u32,
+}
+
+fn default_window_secs() -> u64 {
+    300
+}
+fn default_threshold() -> f64 {
+    0.05
+}
+fn default_cooldown_secs() -> u64 {
+    600
+}
+fn default_max_triggers_per_day() -> u32 {
+    2
+}
+
+impl Default for CircuitBreakerRule {
+    fn default() -> Self {
+        Self {
+            window_secs: default_window_secs(),
+            threshold: default_threshold(),
+            cooldown_secs: default_cooldown_secs(),
+            allow_cancel: false,
+            max_triggers_per_day: default_max_triggers_per_day(),
+        }
+    }
+}
+
+impl CircuitBreakerRule {
+    pub fn validate(&self) -> Result<(), ExchangeError> {
+        if self.window_secs == 0 || self.cooldown_secs == 0 {
+            return Err(ExchangeError::InvalidParameter(
+                "window_secs and cooldown_secs must be greater than 0".to_string(),
+            ));
+        }
+        if !(self.threshold > 0.0 && self.threshold < 1.0) {
+            return Err(ExchangeError::InvalidParameter(format!(
+                "threshold must be in (0, 1), got {}",
+                self.threshold
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// 熔断配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CircuitBreakerConfig {
+    /// 是否启用
+    #[serde(default)]
+    pub enabled: bool,
+    /// 默认规则（未单独配置的合约使用，为空表示只熔断已配置合约）
+    #[serde(default)]
+    pub default: Option<CircuitBreakerRule>,
+    /// 按合约配置的规则
+    #[serde(default)]
+    pub instruments: HashMap<String, CircuitBreakerRule>,
+    /// 触发/恢复系统通知接收人（用户ID）
+    #[serde(default)]
+    pub notice_recipients: Vec<String>,
+}
+
+/// 熔断事件类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitBreakerEventKind {
+    /// 触发熔断，进入冷静期
+    Triggered,
+    /// 冷静期满，恢复连续交易
+    Resumed,
+}
+
+/// 熔断事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CircuitBreakerEvent {
+    pub instrument_id: String,
+    pub kind: CircuitBreakerEventKind,
+    /// 波动基准价（窗口内最高或最低价）
+    pub reference_price: f64,
+    /// 触发价
+    pub trigger_price: f64,
+    /// 触发时的涨跌幅（带符号）
+    pub change_rate: f64,
+    /// 当日第几次触发
+    pub trigger_count: u32,
+    /// 事件时间（毫秒）
+    pub timestamp: i64,
+    /// 恢复连续交易时间（毫秒）
+    pub resume_at: i64,
+}
+
+/// 进行中的冷静期
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CircuitBreakerHalt {
+    pub instrument_id: String,
+    pub triggered_at: i64,
+    pub resume_at: i64,
+    pub allow_cancel: bool,
+    /// 触发前的合约级状态覆盖（恢复时还原，None 表示回到按交易日历判断）
+    #[serde(skip)]
+    pub previous_state: Option<TradingState>,
+    /// 触发事件
+    pub event: CircuitBreakerEvent,
+}
+
+/// 合约当日触发计数
+#[derive(Debug, Clone, Copy, Default)]
+struct DailyTriggers {
+    day: NaiveDate,
+    count: u32,
+}
+
+/// 极端行情熔断器
+pub struct CircuitBreaker {
+    config: RwLock<CircuitBreakerConfig>,
+    /// instrument_id -> 窗口内成交价 (时间戳毫秒, 价格)
+    windows: DashMap<String, VecDeque<(i64, f64)>>,
+    /// instrument_id -> 进行中的冷静期
+    halts: DashMap<String, CircuitBreakerHalt>,
+    /// instrument_id -> 当日触发计数
+    triggers: DashMap<String, DailyTriggers>,
+    history: RwLock<VecDeque<CircuitBreakerEvent>>,
+    state_machine: RwLock<Option<Arc<TradingStateMachine>>>,
+    matching_engine: RwLock<Option<Arc<ExchangeMatchingEngine>>>,
+    broadcaster: RwLock<Option<Arc<MarketDataBroadcaster>>>,
+    notification_broker: RwLock<Option<Arc<NotificationBroker>>>,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new(CircuitBreakerConfig::default())
+    }
+}
+
+impl CircuitBreaker {
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config: RwLock::new(config),
+            windows: DashMap::new(),
+            halts: DashMap::new(),
+            triggers: DashMap::new(),
+            history: RwLock::new(VecDeque::new()),
+            state_machine: RwLock::new(None),
+            matching_engine: RwLock::new(None),
+            broadcaster: RwLock::new(None),
+            notification_broker: RwLock::new(None),
+        }
+    }
+
+    /// 设置交易状态机（熔断时写入合约级状态覆盖）
+    pub fn set_trading_state_machine(&self, state_machine: Arc<TradingStateMachine>) {
+        *self.state_machine.write() = Some(state_machine);
+    }
+
+    /// 设置撮合引擎（熔断/恢复时同步订单簿交易状态）
+    pub fn set_matching_engine(&self, engine: Arc<ExchangeMatchingEngine>) {
+        *self.matching_engine.write() = Some(engine);
+    }
+
+    /// 设置行情广播器（广播交易状态变化）
+    pub fn set_broadcaster(&self, broadcaster: Arc<MarketDataBroadcaster>) {
+        *self.broadcaster.write() = Some(broadcaster);
+    }
+
+    /// 设置通知中心（推送系统通知）
+    pub fn set_notification_broker(&self, broker: Arc<NotificationBroker>) {
+        *self.notification_broker.write() = Some(broker);
+    }
+
+    pub fn config(&self) -> CircuitBreakerConfig {
+        self.config.read().clone()
+    }
+
+    /// 更新配置（进行中的冷静期按触发时的规则结束）
+    pub fn update_config(&self, config: CircuitBreakerConfig) -> Result<(), ExchangeError> {
+        if let Some(ref rule) = config.default {
+            rule.validate()?;
+        }
+        for (instrument_id, rule) in &config.instruments {
+            rule.validate().map_err(|e| {
+                ExchangeError::InvalidParameter(format!("{}: {}", instrument_id, e))
+            })?;
+        }
+        log::info!(
+            "[CircuitBreaker] Config updated: enabled={}, default={}, overrides={}",
+            config.enabled,
+            config.default.is_some(),
+            config.instruments.len()
+        );
+        *self.config.write() = config;
+        Ok(())
+    }
+
+    /// 合约适用的熔断规则（未启用或未配置返回 None）
+    pub fn rule_for(&self, instrument_id: &str) -> Option<CircuitBreakerRule> {
+        let config = self.config.read();
+        if !config.enabled {
+            return None;
+        }
+        config
+            .instruments
+            .get(instrument_id)
+            .or(config.default.as_ref())
+            .cloned()
+    }
+
+    /// 成交价驱动：更新波动窗口，超过阈值时触发熔断
+    ///
+    /// 返回本次触发的事件；冷静期内的成交不参与统计。
+    pub fn on_trade_at(
+        &self,
+        instrument_id: &str,
+        price: f64,
+        now_ms: i64,
+    ) -> Option<CircuitBreakerEvent> {
+        if price <= 0.0 || self.halts.contains_key(instrument_id) {
+            return None;
+        }
+        let rule = self.rule_for(instrument_id)?;
+
+        let (low, high) = {
+            let mut window = self.windows.entry(instrument_id.to_string()).or_default();
+            let window_start = now_ms - (rule.window_secs * 1000) as i64;
+            while window.front().is_some_and(|(ts, _)| *ts < window_start) {
+                window.pop_front();
+            }
+            window.push_back((now_ms, price));
+            window
+                .iter()
+                .fold((f64::MAX, f64::MIN), |(low, high), (_, p)| {
+                    (low.min(*p), high.max(*p))
+                })
+        };
+
+        // 相对窗口内最低价的涨幅、相对最高价的跌幅，取绝对值较大者
+        let rise = (price - low) / low;
+        let fall = (price - high) / high;
+        let (change_rate, reference_price) = if rise >= -fall {
+            (rise, low)
+        } else {
+            (fall, high)
+        };
+        if change_rate.abs() < rule.threshold {
+            return None;
+        }
+
+        let day = time_service().natural_day_of(now_ms);
+        let trigger_count = {
+            let mut daily = self.triggers.entry(instrument_id.to_string()).or_default();
+            if daily.day != day {
+                *daily = DailyTriggers { day, count: 0 };
+            }
+            if rule.max_triggers_per_day > 0 && daily.count >= rule.max_triggers_per_day {
+                log::debug!(
+                    "[CircuitBreaker] {} moved {:.2}% but daily limit {} reached",
+                    instrument_id,
+                    change_rate * 100.0,
+                    rule.max_triggers_per_day
+                );
+                return None;
+            }
+            daily.count += 1;
+            daily.count
+        };
+
+        let event = CircuitBreakerEvent {
+            instrument_id: instrument_id.to_string(),
+            kind: CircuitBreakerEventKind::Triggered,
+            reference_price,
+            trigger_price: price,
+            change_rate,
+            trigger_count,
+            timestamp: now_ms,
+            resume_at: now_ms + (rule.cooldown_secs * 1000) as i64,
+        };
+        self.trigger(event.clone(), rule.allow_cancel);
+        Some(event)
+    }
+
+    /// 时钟驱动：冷静期满的合约恢复连续交易，返回恢复事件
+    pub fn on_clock(&self, now_ms: i64) -> Vec<CircuitBreakerEvent> {
+        let expired: Vec<String> = self
+            .halts
+            .iter()
+            .filter(|halt| now_ms >= halt.resume_at)
+            .map(|halt| halt.key().clone())
+            .collect();
+
+        expired
+            .into_iter()
+            .filter_map(|instrument_id| self.resume(&instrument_id, now_ms))
+            .collect()
+    }
+
+    /// 进入冷静期
+    fn trigger(&self, event: CircuitBreakerEvent, allow_cancel: bool) {
+        let instrument_id = event.instrument_id.clone();
+        let previous_state = self
+            .state_machine
+            .read()
+            .as_ref()
+            .and_then(|sm| sm.instrument_overrides().get(&instrument_id).copied());
+
+        self.halts.insert(
+            instrument_id.clone(),
+            CircuitBreakerHalt {
+                instrument_id: instrument_id.clone(),
+                triggered_at: event.timestamp,
+                resume_at: event.resume_at,
+                allow_cancel,
+                previous_state,
+                event: event.clone(),
+            },
+        );
+        self.windows.remove(&instrument_id);
+
+        log::warn!(
+            "[CircuitBreaker] {} triggered: {:.2} -> {:.2} ({:+.2}%), cooling off until {} (#{} today)",
+            instrument_id,
+            event.reference_price,
+            event.trigger_price,
+            event.change_rate * 100.0,
+            event.resume_at,
+            event.trigger_count
+        );
+
+        self.apply_state(&instrument_id, Some(TradingState::AuctionOrder));
+        self.publish(
+            &event,
+            TradingState::AuctionOrder,
+            format!(
+                "{} 价格波动 {:+.2}% 触发熔断，暂停连续交易进入集合竞价冷静期，{}撤单",
+                instrument_id,
+                event.change_rate * 100.0,
+                if allow_cancel { "允许" } else { "暂停" }
+            ),
+        );
+    }
+
+    /// 结束冷静期，恢复触发前的交易状态
+    fn resume(&self, instrument_id: &str, now_ms: i64) -> Option<CircuitBreakerEvent> {
+        let (_, halt) = self.halts.remove(instrument_id)?;
+        let event = CircuitBreakerEvent {
+            kind: CircuitBreakerEventKind::Resumed,
+            timestamp: now_ms,
+            ..halt.event
+        };
+
+        log::info!(
+            "[CircuitBreaker] {} cooling-off period ended, continuous trading resumed",
+            instrument_id
+        );
+
+        self.apply_state(instrument_id, halt.previous_state);
+        let new_state = halt
+            .previous_state
+            .unwrap_or(TradingState::ContinuousTrading);
+        self.publish(
+            &event,
+            new_state,
+            format!("{} 熔断冷静期结束，恢复连续交易", instrument_id),
+        );
+        Some(event)
+    }
+
+    /// 写入交易状态机与撮合引擎（state 为 None 表示清除合约级覆盖）
+    fn apply_state(&self, instrument_id: &str, state: Option<TradingState>) {
+        if let Some(ref sm) = *self.state_machine.read() {
+            match state {
+                Some(state) => sm.set_instrument_state(instrument_id, state),
+                None => sm.clear_instrument_state(instrument_id),
+            }
+        }
+        if let Some(ref engine) = *self.matching_engine.read() {
+            let state = state.unwrap_or(TradingState::ContinuousTrading);
+            if let Err(e) = engine.set_trading_state(instrument_id, state) {
+                log::warn!(
+                    "[CircuitBreaker] Failed to set trading state of {}: {}",
+                    instrument_id,
+                    e
+                );
+            }
+        }
+    }
+
+    /// 记录历史、写审计、广播状态变化并推送系统通知
+    fn publish(&self, event: &CircuitBreakerEvent, state: TradingState, content: String) {
+        {
+            let mut history = self.history.write();
+            if history.len() >= MAX_HISTORY {
+                history.pop_front();
+            }
+            history.push_back(event.clone());
+        }
+
+        let (action, level) = match event.kind {
+            CircuitBreakerEventKind::Triggered => ("circuit_breaker_triggered", "WARNING"),
+            CircuitBreakerEventKind::Resumed => ("circuit_breaker_resumed", "INFO"),
+        };
+        log_audit(
+            event.instrument_id.clone(),
+            "CircuitBreaker".to_string(),
+            AuditLogType::CircuitBreaker,
+            action.to_string(),
+            content.clone(),
+            None,
+            AuditResult::Success,
+        );
+
+        if let Some(ref broadcaster) = *self.broadcaster.read() {
+            broadcaster.broadcast(MarketDataEvent::TradingStateChanged {
+                instrument_id: event.instrument_id.clone(),
+                state: format!("{:?}", state),
+                reason: action.to_string(),
+                resume_at: match event.kind {
+                    CircuitBreakerEventKind::Triggered => Some(event.resume_at),
+                    CircuitBreakerEventKind::Resumed => None,
+                },
+                timestamp: event.timestamp,
+            });
+        }
+
+        let Some(ref broker) = *self.notification_broker.read() else {
+            return;
+        };
+        let title = match event.kind {
+            CircuitBreakerEventKind::Triggered => "合约熔断",
+            CircuitBreakerEventKind::Resumed => "熔断恢复",
+        };
+        for user_id in &self.config.read().notice_recipients {
+            let notification = Notification::new(
+                NotificationType::SystemNotice,
+                Arc::from(user_id.as_str()),
+                NotificationPayload::SystemNotice(SystemNoticeNotify {
+                    title: title.to_string(),
+                    content: content.clone(),
+                    level: level.to_string(),
+                    timestamp: millis_to_nanos(event.timestamp),
+                }),
+                "CircuitBreaker",
+            );
+            if let Err(e) = broker.publish(notification) {
+                log::error!("Failed to publish circuit breaker notice: {}", e);
+            }
+        }
+    }
+
+    /// 合约是否处于冷静期
+    pub fn is_halted(&self, instrument_id: &str) -> bool {
+        self.halts.contains_key(instrument_id)
+    }
+
+    /// 进行中的冷静期
+    pub fn get_halt(&self, instrument_id: &str) -> Option<CircuitBreakerHalt> {
+        self.halts.get(instrument_id).map(|h| h.clone())
+    }
+
+    /// 所有进行中的冷静期
+    pub fn list_halts(&self) -> Vec<CircuitBreakerHalt> {
+        self.halts.iter().map(|h| h.value().clone()).collect()
+    }
+
+    /// 熔断事件历史（可按合约过滤）
+    pub fn history(&self, instrument_id: Option<&str>) -> Vec<CircuitBreakerEvent> {
+        self.history
+            .read()
+            .iter()
+            .filter(|e| instrument_id.map_or(true, |id| e.instrument_id == id))
+            .cloned()
+            .collect()
+    }
+
+    /// 下单校验：冷静期内只接受限价单
+    pub fn check_order(&self, instrument_id: &str, order_type: &str) -> Result<(), String> {
+        match self.halts.get(instrument_id) {
+            Some(halt) if order_type == "MARKET" => Err(format!(
+                "Instrument {} is in circuit breaker cooling-off period until {}, only limit orders are accepted",
+                instrument_id, halt.resume_at
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// 撤单规则：None 表示未熔断（按交易状态机判断），Some(allow) 表示冷静期内是否允许撤单
+    pub fn cancel_allowed(&self, instrument_id: &str) -> Option<bool> {
+        self.halts.get(instrument_id).map(|halt| halt.allow_cancel)
+    }
+}
+
+/// 启动熔断监控线程：订阅成交行情驱动触发，按时钟间隔检查冷静期恢复
+pub fn start_circuit_breaker_monitor(
+    breaker: Arc<CircuitBreaker>,
+    broadcaster: Arc<MarketDataBroadcaster>,
+    clock_interval: Duration,
+) -> std::thread::JoinHandle<()> {
+    let receiver = broadcaster.subscribe(
+        "circuit_breaker".to_string(),
+        Vec::new(),
+        vec!["tick".to_string()],
+    );
+
+    std::thread::spawn(move || {
+        log::info!(
+            "Circuit breaker monitor started (clock interval: {}ms)",
+            clock_interval.as_millis()
+        );
+
+        let mut last_clock = Instant::now();
+        loop {
+            match receiver.recv_timeout(clock_interval) {
+                Ok(MarketDataEvent::Tick {
+                    instrument_id,
+                    price,
+                    ..
+                }) => {
+                    breaker.on_trade_at(
+                        &instrument_id,
+                        price,
+                        chrono::Utc::now().timestamp_millis(),
+                    );
+                }
+                Ok(_) => {}
+                Err(crossbeam::channel::RecvTimeoutError::Timeout) => {}
+                Err(crossbeam::channel::RecvTimeoutError::Disconnected) => {
+                    log::warn!("Circuit breaker monitor stopped: market data channel closed");
+                    break;
+                }
+            }
+
+            if last_clock.elapsed() >= clock_interval {
+                breaker.on_clock(chrono::Utc::now().timestamp_millis());
+                last_clock = Instant::now();
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INSTRUMENT: &str = "IF2501";
+    /// 2025-01-02 10:00:00 北京时间
+    const T0: i64 = 1_735_783_200_000;
+
+    fn breaker(max_triggers_per_day: u32) -> (CircuitBreaker, Arc<TradingStateMachine>) {
+        let breaker = CircuitBreaker::new(CircuitBreakerConfig {
+            enabled: true,
+            default: None,
+            instruments: HashMap::from([(
+                INSTRUMENT.to_string(),
+                CircuitBreakerRule {
+                    window_secs: 60,
+                    threshold: 0.05,
+                    cooldown_secs: 300,
+                    allow_cancel: false,
+                    max_triggers_per_day,
+                },
+            )]),
+            notice_recipients: Vec::new(),
+        });
+        let state_machine = Arc::new(TradingStateMachine::new());
+        breaker.set_trading_state_machine(state_machine.clone());
+        (breaker, state_machine)
+    }
+
+    /// 窗口内波动超阈值触发熔断，冷静期只接受限价单，期满自动恢复
+    #[test]
+    fn test_trigger_cooling_off_and_resume() {
+        let (breaker, state_machine) = breaker(2);
+        let broadcaster = Arc::new(MarketDataBroadcaster::new());
+        let receiver = broadcaster.subscribe(
+            "test".to_string(),
+            Vec::new(),
+            vec!["trading_state".to_string()],
+        );
+        breaker.set_broadcaster(broadcaster);
+
+        // 未配置的合约不熔断
+        assert!(breaker.on_trade_at("cu2501", 100.0, T0).is_none());
+        assert!(breaker.on_trade_at("cu2501", 200.0, T0 + 1000).is_none());
+
+        // 3% 波动未达阈值；窗口外的低价不参与统计
+        assert!(breaker.on_trade_at(INSTRUMENT, 3900.0, T0).is_none());
+        assert!(breaker
+            .on_trade_at(INSTRUMENT, 4000.0, T0 + 61_000)
+            .is_none());
+        assert!(breaker
+            .on_trade_at(INSTRUMENT, 4120.0, T0 + 70_000)
+            .is_none());
+        assert!(!breaker.is_halted(INSTRUMENT));
+
+        // 相对窗口最低价 4000 上涨 5.5% 触发
+        let event = breaker
+            .on_trade_at(INSTRUMENT, 4220.0, T0 + 80_000)
+            .expect("should trigger");
+        assert_eq!(event.kind, CircuitBreakerEventKind::Triggered);
+        assert_eq!(event.reference_price, 4000.0);
+        assert!((event.change_rate - 0.055).abs() < 1e-9);
+        assert_eq!(event.resume_at, T0 + 380_000);
+        assert_eq!(event.trigger_count, 1);
+        assert_eq!(
+            state_machine.get_instrument_state(INSTRUMENT),
+            TradingState::AuctionOrder
+        );
+
+        match receiver.try_recv().expect("state change broadcast") {
+            MarketDataEvent::TradingStateChanged {
+                instrument_id,
+                state,
+                resume_at,
+                ..
+            } => {
+                assert_eq!(instrument_id, INSTRUMENT);
+                assert_eq!(state, "AuctionOrder");
+                assert_eq!(resume_at, Some(T0 + 380_000));
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        // 冷静期：只接受限价单，撤单被拒绝，成交不再统计
+        assert!(breaker.check_order(INSTRUMENT, "MARKET").is_err());
+        assert!(breaker.check_order(INSTRUMENT, "LIMIT").is_ok());
+        assert!(breaker.check_order("cu2501", "MARKET").is_ok());
+        assert_eq!(breaker.cancel_allowed(INSTRUMENT), Some(false));
+        assert_eq!(breaker.cancel_allowed("cu2501"), None);
+        assert!(breaker
+            .on_trade_at(INSTRUMENT, 3000.0, T0 + 90_000)
+            .is_none());
+
+        // 期满前不恢复
+        assert!(breaker.on_clock(T0 + 379_999).is_empty());
+        assert!(breaker.is_halted(INSTRUMENT));
+
+        let resumed = breaker.on_clock(T0 + 380_000);
+        assert_eq!(resumed.len(), 1);
+        assert_eq!(resumed[0].kind, CircuitBreakerEventKind::Resumed);
+        assert!(!breaker.is_halted(INSTRUMENT));
+        assert!(state_machine.instrument_overrides().is_empty());
+        assert!(breaker.check_order(INSTRUMENT, "MARKET").is_ok());
+        assert_eq!(breaker.history(Some(INSTRUMENT)).len(), 2);
+
+        match receiver.try_recv().expect("resume broadcast") {
+            MarketDataEvent::TradingStateChanged {
+                state, resume_at, ..
+            } => {
+                assert_eq!(state, "ContinuousTrading");
+                assert_eq!(resume_at, None);
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+
+    /// 每日触发次数上限，次日重新计数
+    #[test]
+    fn test_daily_trigger_limit() {
+        let (breaker, _) = breaker(1);
+
+        breaker.on_trade_at(INSTRUMENT, 4000.0, T0);
+        assert!(breaker.on_trade_at(INSTRUMENT, 3780.0, T0 + 1000).is_some());
+        breaker.on_clock(T0 + 301_000);
+
+        // 当日已达上限：再次大幅波动不熔断
+        breaker.on_trade_at(INSTRUMENT, 3780.0, T0 + 310_000);
+        assert!(breaker
+            .on_trade_at(INSTRUMENT, 3500.0, T0 + 320_000)
+            .is_none());
+        assert!(!breaker.is_halted(INSTRUMENT));
+
+        // 次日重新计数
+        let next_day = T0 + 86_400_000;
+        breaker.on_trade_at(INSTRUMENT, 3500.0, next_day);
+        let event = breaker
+            .on_trade_at(INSTRUMENT, 3300.0, next_day + 1000)
+            .expect("should trigger on next day");
+        assert_eq!(event.trigger_count, 1);
+
+        // 非法配置被拒绝
+        assert!(breaker
+            .update_config(CircuitBreakerConfig {
+                enabled: true,
+                default: Some(CircuitBreakerRule {
+                    threshold: 0.0,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+            .is_err());
+        assert!(breaker.rule_for(INSTRUMENT).is_some());
+    }
+}
@@ -1,21 +1,24 @@
 //! 条件单引擎
 //! @yutiansut @quantaxis
 //!
-//! 提供止损、止盈、触价等条件单功能：
+//! 提供止损、止盈、触价、定时、量触发等条件单功能：
 //! - 条件单的创建、查询、取消
-//! - 实时行情监控和条件触发
+//! - 实时行情监控和条件触发（价格/时间/成交量，支持 AND/OR 组合）
 //! - 触发后自动转为普通订单
 
 use chrono::Utc;
 use dashmap::DashMap;
 use log;
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
 use crate::exchange::order_router::{OrderRouter, SubmitOrderRequest};
+use crate::market::{MarketDataBroadcaster, MarketDataEvent};
 use crate::service::http::models::{
     ConditionType, ConditionalOrderInfo, ConditionalOrderStatus,
-    CreateConditionalOrderRequest, TriggerCondition,
+    CreateConditionalOrderRequest, TriggerCondition, TriggerSpec,
 };
 
 /// 内部条件单结构
@@ -32,6 +35,10 @@ pub struct ConditionalOrder {
     pub condition_type: ConditionType,
     pub trigger_price: f64,
     pub trigger_condition: TriggerCondition,
+    /// 触发规则（价格/时间/成交量及其组合）
+    pub trigger: TriggerSpec,
+    /// 成交量触发的基准：instrument_id -> 创建时的累计成交量
+    pub volume_baselines: HashMap<String, f64>,
     pub valid_until: Option<i64>,
     pub status: ConditionalOrderStatus,
     pub created_at: i64,
@@ -54,6 +61,7 @@ impl ConditionalOrder {
             condition_type: self.condition_type.clone(),
             trigger_price: self.trigger_price,
             trigger_condition: self.trigger_condition.clone(),
+            trigger: self.trigger.clone(),
             valid_until: self.valid_until,
             status: self.status.clone(),
             created_at: self.created_at,
@@ -62,21 +70,15 @@ impl ConditionalOrder {
         }
     }
 
-    /// 检查是否触发
-    pub fn check_trigger(&self, last_price: f64) -> bool {
-        match self.trigger_condition {
-            TriggerCondition::GreaterOrEqual => last_price >= self.trigger_price,
-            TriggerCondition::LessOrEqual => last_price <= self.trigger_price,
-        }
-    }
-
     /// 检查是否过期
     pub fn is_expired(&self) -> bool {
-        if let Some(valid_until) = self.valid_until {
-            Utc::now().timestamp_millis() > valid_until
-        } else {
-            false
-        }
+        self.is_expired_at(Utc::now().timestamp_millis())
+    }
+
+    /// 检查在指定时刻是否过期
+    pub fn is_expired_at(&self, now: i64) -> bool {
+        self.valid_until
+            .map_or(false, |valid_until| now > valid_until)
     }
 
     /// 转换为订单提交请求
@@ -91,18 +93,142 @@ impl ConditionalOrder {
             order_type: self.order_type.clone(),
             time_condition: None,
             volume_condition: None,
+            hedge_flag: None,
+            ttl_secs: None,
+            client_order_id: None,
+            close_lot_id: None,
+        }
+    }
+}
+
+/// 补全触发规则中省略的合约并校验参数
+fn normalize_trigger(spec: TriggerSpec, default_instrument: &str) -> Result<TriggerSpec, String> {
+    match spec {
+        TriggerSpec::Price {
+            instrument_id,
+            trigger_price,
+            trigger_condition,
+        } => {
+            if trigger_price <= 0.0 {
+                return Err(format!("触发价格必须大于0: {}", trigger_price));
+            }
+            Ok(TriggerSpec::Price {
+                instrument_id: Some(
+                    instrument_id.unwrap_or_else(|| default_instrument.to_string()),
+                ),
+                trigger_price,
+                trigger_condition,
+            })
+        }
+        TriggerSpec::Time { trigger_time } => Ok(TriggerSpec::Time { trigger_time }),
+        TriggerSpec::Volume {
+            instrument_id,
+            volume_threshold,
+        } => {
+            if volume_threshold <= 0.0 {
+                return Err(format!("成交量阈值必须大于0: {}", volume_threshold));
+            }
+            Ok(TriggerSpec::Volume {
+                instrument_id: Some(
+                    instrument_id.unwrap_or_else(|| default_instrument.to_string()),
+                ),
+                volume_threshold,
+            })
         }
+        TriggerSpec::And { conditions } | TriggerSpec::Or { conditions }
+            if conditions.is_empty() =>
+        {
+            Err("组合条件至少包含一个子条件".to_string())
+        }
+        TriggerSpec::And { conditions } => Ok(TriggerSpec::And {
+            conditions: conditions
+                .into_iter()
+                .map(|c| normalize_trigger(c, default_instrument))
+                .collect::<Result<_, _>>()?,
+        }),
+        TriggerSpec::Or { conditions } => Ok(TriggerSpec::Or {
+            conditions: conditions
+                .into_iter()
+                .map(|c| normalize_trigger(c, default_instrument))
+                .collect::<Result<_, _>>()?,
+        }),
+    }
+}
+
+/// 收集触发规则关注的合约：(价格监听, 成交量监听)
+fn watched_instruments(spec: &TriggerSpec, prices: &mut Vec<String>, volumes: &mut Vec<String>) {
+    match spec {
+        TriggerSpec::Price {
+            instrument_id: Some(id),
+            ..
+        } => {
+            if !prices.contains(id) {
+                prices.push(id.clone());
+            }
+        }
+        TriggerSpec::Volume {
+            instrument_id: Some(id),
+            ..
+        } => {
+            if !volumes.contains(id) {
+                volumes.push(id.clone());
+            }
+        }
+        TriggerSpec::And { conditions } | TriggerSpec::Or { conditions } => {
+            for c in conditions {
+                watched_instruments(c, prices, volumes);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 触发规则是否包含时间条件（需由时钟驱动求值）
+fn has_time_condition(spec: &TriggerSpec) -> bool {
+    match spec {
+        TriggerSpec::Time { .. } => true,
+        TriggerSpec::And { conditions } | TriggerSpec::Or { conditions } => {
+            conditions.iter().any(has_time_condition)
+        }
+        _ => false,
+    }
+}
+
+/// 有效期内永远无法满足（触发时间晚于有效期）
+fn never_satisfiable(spec: &TriggerSpec, valid_until: Option<i64>) -> bool {
+    match spec {
+        TriggerSpec::Time { trigger_time } => valid_until.map_or(false, |v| *trigger_time > v),
+        TriggerSpec::And { conditions } => {
+            conditions.iter().any(|c| never_satisfiable(c, valid_until))
+        }
+        TriggerSpec::Or { conditions } => {
+            conditions.iter().all(|c| never_satisfiable(c, valid_until))
+        }
+        _ => false,
     }
 }
 
 /// 条件单引擎
+///
+/// 按条件类型分别驱动求值：
+/// - 行情（最新价）：`check_triggers` / `on_trade`，只求值监听该合约价格的条件单
+/// - 成交量：`on_trade` 累计合约成交量，只求值监听该合约成交量的条件单
+/// - 时钟：`on_clock` 求值含时间条件的条件单，并清理已过期的条件单
+///
+/// 条件单触发、取消或过期后即从监听索引中移除，仅保留记录供查询
 pub struct ConditionalOrderEngine {
     /// 条件单存储: conditional_order_id -> ConditionalOrder
     orders: DashMap<String, ConditionalOrder>,
     /// 按账户索引: account_id -> Vec<conditional_order_id>
     by_account: DashMap<String, Vec<String>>,
-    /// 按合约索引: instrument_id -> Vec<conditional_order_id>
+    /// 价格监听索引: instrument_id -> Vec<conditional_order_id>
     by_instrument: DashMap<String, Vec<String>>,
+    /// 成交量监听索引: instrument_id -> Vec<conditional_order_id>
+    by_volume_instrument: DashMap<String, Vec<String>>,
+    /// 最新价: instrument_id -> last_price
+    last_prices: DashMap<String, f64>,
+    /// 累计成交量: instrument_id -> volume
+    cumulative_volumes: DashMap<String, f64>,
     /// 订单路由器
     order_router: Option<Arc<OrderRouter>>,
 }
@@ -113,6 +239,9 @@ impl ConditionalOrderEngine {
             orders: DashMap::new(),
             by_account: DashMap::new(),
             by_instrument: DashMap::new(),
+            by_volume_instrument: DashMap::new(),
+            last_prices: DashMap::new(),
+            cumulative_volumes: DashMap::new(),
             order_router: None,
         }
     }
@@ -124,13 +253,32 @@ impl ConditionalOrderEngine {
 
     /// 创建条件单
     pub fn create_order(&self, req: CreateConditionalOrderRequest) -> Result<ConditionalOrderInfo, String> {
-        let order_id = format!("COND_{}", Uuid::new_v4().to_string().replace("-", "")[..12].to_uppercase());
         let now = Utc::now().timestamp_millis();
 
+        // 未指定触发规则时沿用价格触发字段
+        let trigger = req.trigger.unwrap_or_else(|| TriggerSpec::Price {
+            instrument_id: None,
+            trigger_price: req.trigger_price,
+            trigger_condition: req.trigger_condition.clone(),
+        });
+        let trigger = normalize_trigger(trigger, &req.instrument_id)?;
+        if never_satisfiable(&trigger, req.valid_until) {
+            return Err("触发时间晚于有效期，条件永不满足".to_string());
+        }
+
+        let mut price_watch = Vec::new();
+        let mut volume_watch = Vec::new();
+        watched_instruments(&trigger, &mut price_watch, &mut volume_watch);
+        let volume_baselines = volume_watch
+            .iter()
+            .map(|id| (id.clone(), self.cumulative_volume(id)))
+            .collect();
+
+        let order_id = format!("COND_{}", Uuid::new_v4().to_string().replace("-", "")[..12].to_uppercase());
         let order = ConditionalOrder {
             id: order_id.clone(),
             account_id: req.account_id.clone(),
-            instrument_id: req.instrument_id.clone(),
+            instrument_id: req.instrument_id,
             direction: req.direction,
             offset: req.offset,
             volume: req.volume,
@@ -139,6 +287,8 @@ impl ConditionalOrderEngine {
             condition_type: req.condition_type,
             trigger_price: req.trigger_price,
             trigger_condition: req.trigger_condition,
+            trigger,
+            volume_baselines,
             valid_until: req.valid_until,
             status: ConditionalOrderStatus::Pending,
             created_at: now,
@@ -152,10 +302,18 @@ impl ConditionalOrderEngine {
             .or_default()
             .push(order_id.clone());
 
-        self.by_instrument
-            .entry(req.instrument_id)
-            .or_default()
-            .push(order_id.clone());
+        for instrument_id in price_watch {
+            self.by_instrument
+                .entry(instrument_id)
+                .or_default()
+                .push(order_id.clone());
+        }
+        for instrument_id in volume_watch {
+            self.by_volume_instrument
+                .entry(instrument_id)
+                .or_default()
+                .push(order_id.clone());
+        }
 
         let info = order.to_info();
         self.orders.insert(order_id, order);
@@ -183,90 +341,202 @@ impl ConditionalOrderEngine {
 
     /// 取消条件单
     pub fn cancel_order(&self, order_id: &str) -> Result<(), String> {
-        let mut order = self.orders.get_mut(order_id)
-            .ok_or_else(|| format!("条件单不存在: {}", order_id))?;
+        {
+            let mut order = self.orders.get_mut(order_id)
+                .ok_or_else(|| format!("条件单不存在: {}", order_id))?;
 
-        if order.status != ConditionalOrderStatus::Pending {
-            return Err(format!("条件单状态不允许取消: {:?}", order.status));
-        }
+            if order.status != ConditionalOrderStatus::Pending {
+                return Err(format!("条件单状态不允许取消: {:?}", order.status));
+            }
 
-        order.status = ConditionalOrderStatus::Cancelled;
+            order.status = ConditionalOrderStatus::Cancelled;
+        }
+        self.unwatch(order_id);
         log::info!("条件单取消成功: {}", order_id);
         Ok(())
     }
 
-    /// 检查合约的所有条件单并触发
+    /// 合约累计成交量
+    pub fn cumulative_volume(&self, instrument_id: &str) -> f64 {
+        self.cumulative_volumes
+            .get(instrument_id)
+            .map(|v| *v)
+            .unwrap_or(0.0)
+    }
+
+    /// 最新价更新：检查合约的所有价格条件单并触发
     /// 返回触发的条件单列表
     pub fn check_triggers(&self, instrument_id: &str, last_price: f64) -> Vec<ConditionalOrderInfo> {
-        let mut triggered = Vec::new();
+        self.last_prices
+            .insert(instrument_id.to_string(), last_price);
+        let ids = Self::watchers(&self.by_instrument, instrument_id);
+        self.evaluate_orders(ids, Utc::now().timestamp_millis())
+    }
 
-        if let Some(order_ids) = self.by_instrument.get(instrument_id) {
-            for order_id in order_ids.iter() {
-                if let Some(mut order) = self.orders.get_mut(order_id) {
-                    // 只检查待触发的条件单
-                    if order.status != ConditionalOrderStatus::Pending {
-                        continue;
-                    }
+    /// 成交更新：累计成交量并检查价格/成交量条件单
+    pub fn on_trade(
+        &self,
+        instrument_id: &str,
+        price: f64,
+        volume: f64,
+    ) -> Vec<ConditionalOrderInfo> {
+        self.last_prices.insert(instrument_id.to_string(), price);
+        *self
+            .cumulative_volumes
+            .entry(instrument_id.to_string())
+            .or_insert(0.0) += volume;
+
+        let mut ids = Self::watchers(&self.by_instrument, instrument_id);
+        for id in Self::watchers(&self.by_volume_instrument, instrument_id) {
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+        self.evaluate_orders(ids, Utc::now().timestamp_millis())
+    }
 
-                    // 检查是否过期
-                    if order.is_expired() {
-                        order.status = ConditionalOrderStatus::Expired;
-                        log::info!("条件单已过期: {}", order_id);
-                        continue;
-                    }
+    /// 时钟驱动：清理过期条件单并求值含时间条件的条件单
+    pub fn on_clock(&self, now: i64) -> Vec<ConditionalOrderInfo> {
+        let ids: Vec<String> = self
+            .orders
+            .iter()
+            .filter(|o| o.status == ConditionalOrderStatus::Pending)
+            .filter(|o| o.is_expired_at(now) || has_time_condition(&o.trigger))
+            .map(|o| o.id.clone())
+            .collect();
+        self.evaluate_orders(ids, now)
+    }
 
-                    // 检查是否触发
-                    if order.check_trigger(last_price) {
-                        log::info!(
-                            "条件单触发: {} (触发价 {} {} 当前价 {})",
-                            order_id,
-                            order.trigger_price,
-                            match order.trigger_condition {
-                                TriggerCondition::GreaterOrEqual => "<=",
-                                TriggerCondition::LessOrEqual => ">=",
-                            },
-                            last_price
-                        );
-
-                        // 尝试执行订单
-                        if let Some(router) = &self.order_router {
-                            let submit_req = order.to_submit_request();
-                            let response = router.submit_order(submit_req);
-
-                            if response.success {
-                                order.status = ConditionalOrderStatus::Triggered;
-                                order.triggered_at = Some(Utc::now().timestamp_millis());
-                                order.result_order_id = response.order_id;
-                                log::info!(
-                                    "条件单执行成功: {} -> 订单 {:?}",
-                                    order_id,
-                                    order.result_order_id
-                                );
-                            } else {
-                                order.status = ConditionalOrderStatus::Failed;
-                                order.triggered_at = Some(Utc::now().timestamp_millis());
-                                log::error!(
-                                    "条件单执行失败: {} - {}",
-                                    order_id,
-                                    response.error_message.unwrap_or_default()
-                                );
-                            }
-                        } else {
-                            // 没有订单路由器，标记为触发但无法执行
-                            order.status = ConditionalOrderStatus::Triggered;
-                            order.triggered_at = Some(Utc::now().timestamp_millis());
-                            log::warn!("条件单触发但无法执行（无订单路由器）: {}", order_id);
-                        }
+    fn watchers(index: &DashMap<String, Vec<String>>, instrument_id: &str) -> Vec<String> {
+        index
+            .get(instrument_id)
+            .map(|ids| ids.clone())
+            .unwrap_or_default()
+    }
 
-                        triggered.push(order.to_info());
-                    }
-                }
+    /// 求值一批条件单，触发/过期的从监听索引移除
+    fn evaluate_orders(&self, order_ids: Vec<String>, now: i64) -> Vec<ConditionalOrderInfo> {
+        let mut triggered = Vec::new();
+        let mut finished = Vec::new();
+
+        for order_id in order_ids {
+            let Some(mut order) = self.orders.get_mut(&order_id) else {
+                continue;
+            };
+            // 只检查待触发的条件单
+            if order.status != ConditionalOrderStatus::Pending {
+                finished.push(order_id);
+                continue;
+            }
+
+            // 检查是否过期
+            if order.is_expired_at(now) {
+                order.status = ConditionalOrderStatus::Expired;
+                log::info!("条件单已过期: {}", order_id);
+                finished.push(order_id);
+                continue;
+            }
+
+            if self.is_satisfied(&order, &order.trigger, now) {
+                log::info!(
+                    "条件单触发: {} ({:?}, {:?})",
+                    order_id,
+                    order.condition_type,
+                    order.trigger
+                );
+                self.execute(&mut order);
+                triggered.push(order.to_info());
+                finished.push(order_id);
             }
         }
 
+        for order_id in &finished {
+            self.unwatch(order_id);
+        }
         triggered
     }
 
+    /// 触发规则求值
+    fn is_satisfied(&self, order: &ConditionalOrder, spec: &TriggerSpec, now: i64) -> bool {
+        match spec {
+            TriggerSpec::Price {
+                instrument_id,
+                trigger_price,
+                trigger_condition,
+            } => {
+                let instrument_id = instrument_id.as_deref().unwrap_or(&order.instrument_id);
+                self.last_prices
+                    .get(instrument_id)
+                    .map_or(false, |last_price| match trigger_condition {
+                        TriggerCondition::GreaterOrEqual => *last_price >= *trigger_price,
+                        TriggerCondition::LessOrEqual => *last_price <= *trigger_price,
+                    })
+            }
+            TriggerSpec::Time { trigger_time } => now >= *trigger_time,
+            TriggerSpec::Volume {
+                instrument_id,
+                volume_threshold,
+            } => {
+                let instrument_id = instrument_id.as_deref().unwrap_or(&order.instrument_id);
+                let baseline = order
+                    .volume_baselines
+                    .get(instrument_id)
+                    .copied()
+                    .unwrap_or(0.0);
+                self.cumulative_volume(instrument_id) - baseline >= *volume_threshold
+            }
+            TriggerSpec::And { conditions } => {
+                conditions.iter().all(|c| self.is_satisfied(order, c, now))
+            }
+            TriggerSpec::Or { conditions } => {
+                conditions.iter().any(|c| self.is_satisfied(order, c, now))
+            }
+        }
+    }
+
+    /// 触发后转为普通订单提交
+    fn execute(&self, order: &mut ConditionalOrder) {
+        let order_id = order.id.clone();
+        if let Some(router) = &self.order_router {
+            let submit_req = order.to_submit_request();
+            let response = router.submit_order(submit_req);
+
+            if response.success {
+                order.status = ConditionalOrderStatus::Triggered;
+                order.triggered_at = Some(Utc::now().timestamp_millis());
+                order.result_order_id = response.order_id;
+                log::info!(
+                    "条件单执行成功: {} -> 订单 {:?}",
+                    order_id,
+                    order.result_order_id
+                );
+            } else {
+                order.status = ConditionalOrderStatus::Failed;
+                order.triggered_at = Some(Utc::now().timestamp_millis());
+                log::error!(
+                    "条件单执行失败: {} - {}",
+                    order_id,
+                    response.error_message.unwrap_or_default()
+                );
+            }
+        } else {
+            // 没有订单路由器，标记为触发但无法执行
+            order.status = ConditionalOrderStatus::Triggered;
+            order.triggered_at = Some(Utc::now().timestamp_millis());
+            log::warn!("条件单触发但无法执行（无订单路由器）: {}", order_id);
+        }
+    }
+
+    /// 从价格/成交量监听索引中移除
+    fn unwatch(&self, order_id: &str) {
+        for index in [&self.by_instrument, &self.by_volume_instrument] {
+            index.retain(|_, ids| {
+                ids.retain(|id| id != order_id);
+                !ids.is_empty()
+            });
+        }
+    }
+
     /// 获取统计信息
     pub fn get_statistics(&self) -> ConditionalOrderStatistics {
         let mut pending = 0;
@@ -319,10 +589,299 @@ lazy_static::lazy_static! {
         parking_lot::RwLock::new(ConditionalOrderEngine::new());
 }
 
+/// 启动条件单监控线程：订阅成交/最新价驱动价格与量触发，按时钟间隔驱动时间触发与过期清理
+pub fn start_conditional_order_monitor(
+    broadcaster: Arc<MarketDataBroadcaster>,
+    clock_interval: Duration,
+) -> std::thread::JoinHandle<()> {
+    let receiver = broadcaster.subscribe(
+        "conditional_order_engine".to_string(),
+        Vec::new(),
+        vec!["tick".to_string(), "last_price".to_string()],
+    );
+
+    std::thread::spawn(move || {
+        log::info!(
+            "Conditional order monitor started (clock interval: {}ms)",
+            clock_interval.as_millis()
+        );
+
+        let mut last_clock = Instant::now();
+        loop {
+            match receiver.recv_timeout(clock_interval) {
+                Ok(event) => {
+                    let engine = CONDITIONAL_ORDER_ENGINE.read();
+                    match event {
+                        MarketDataEvent::Tick {
+                            instrument_id,
+                            price,
+                            volume,
+                            ..
+                        } => {
+                            engine.on_trade(&instrument_id, price, volume);
+                        }
+                        MarketDataEvent::LastPrice {
+                            instrument_id,
+                            price,
+                            ..
+                        } => {
+                            engine.check_triggers(&instrument_id, price);
+                        }
+                        _ => {}
+                    }
+                }
+                Err(crossbeam::channel::RecvTimeoutError::Timeout) => {}
+                Err(crossbeam::channel::RecvTimeoutError::Disconnected) => {
+                    log::warn!("Conditional order monitor stopped: market data channel closed");
+                    break;
+                }
+            }
+
+            if last_clock.elapsed() >= clock_interval {
+                CONDITIONAL_ORDER_ENGINE
+                    .read()
+                    .on_clock(Utc::now().timestamp_millis());
+                last_clock = Instant::now();
+            }
+        }
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn order_request(instrument_id: &str, trigger: TriggerSpec) -> CreateConditionalOrderRequest {
+        CreateConditionalOrderRequest {
+            account_id: "cond_user".to_string(),
+            instrument_id: instrument_id.to_string(),
+            direction: "BUY".to_string(),
+            offset: "OPEN".to_string(),
+            volume: 1.0,
+            order_type: "LIMIT".to_string(),
+            limit_price: Some(100.0),
+            condition_type: ConditionType::Scheduled,
+            trigger_price: 0.0,
+            trigger_condition: TriggerCondition::GreaterOrEqual,
+            valid_until: None,
+            trigger: Some(trigger),
+        }
+    }
+
+    fn create_test_router() -> Arc<OrderRouter> {
+        use crate::core::account_ext::{AccountType, OpenAccountRequest};
+        use crate::exchange::instrument_registry::{
+            InstrumentInfo, InstrumentRegistry, InstrumentStatus, InstrumentType,
+        };
+        use crate::exchange::{AccountManager, TradeGateway};
+        use crate::matching::engine::ExchangeMatchingEngine;
+
+        let account_mgr = Arc::new(AccountManager::new());
+        account_mgr
+            .open_account(OpenAccountRequest {
+                user_id: "cond_user".to_string(),
+                account_id: Some("cond_user".to_string()),
+                account_name: "cond_user".to_string(),
+                init_cash: 1000000.0,
+                account_type: AccountType::Individual,
+            })
+            .unwrap();
+
+        let matching_engine = Arc::new(ExchangeMatchingEngine::new());
+        matching_engine
+            .register_instrument("IF2501".to_string(), 100.0)
+            .unwrap();
+        let registry = Arc::new(InstrumentRegistry::new());
+        registry
+            .register(InstrumentInfo {
+                instrument_id: "IF2501".to_string(),
+                instrument_name: "IF2501".to_string(),
+                instrument_type: InstrumentType::IndexFuture,
+                exchange: "CFFEX".to_string(),
+                contract_multiplier: 1,
+                trading_unit: "手".to_string(),
+                quote_unit: String::new(),
+                price_tick: 0.2,
+                margin_rate: 0.1,
+                commission_rate: 0.0005,
+                limit_up_rate: 0.1,
+                limit_down_rate: 0.1,
+                status: InstrumentStatus::Active,
+                list_date: Some("2024-01-01".to_string()),
+                expire_date: Some("2025-01-17".to_string()),
+                created_at: "2024-01-01T00:00:00Z".to_string(),
+                updated_at: "2024-01-01T00:00:00Z".to_string(),
+            })
+            .unwrap();
+
+        let trade_gateway = Arc::new(TradeGateway::new(account_mgr.clone()));
+        Arc::new(OrderRouter::new(
+            account_mgr,
+            matching_engine,
+            registry,
+            trade_gateway,
+        ))
+    }
+
+    #[test]
+    fn test_time_trigger_submits_order_at_time() {
+        let mut engine = ConditionalOrderEngine::new();
+        engine.set_order_router(create_test_router());
+
+        let trigger_time = Utc::now().timestamp_millis() + 60_000;
+        let order = engine
+            .create_order(order_request("IF2501", TriggerSpec::Time { trigger_time }))
+            .unwrap();
+
+        // 行情变化不驱动纯时间条件单
+        assert!(engine.on_trade("IF2501", 100.0, 5.0).is_empty());
+        // 未到点
+        assert!(engine.on_clock(trigger_time - 1).is_empty());
+
+        // 到点下单
+        let triggered = engine.on_clock(trigger_time);
+        assert_eq!(triggered.len(), 1);
+        assert_eq!(
+            triggered[0].conditional_order_id,
+            order.conditional_order_id
+        );
+        assert_eq!(triggered[0].status, ConditionalOrderStatus::Triggered);
+        assert!(triggered[0].result_order_id.is_some());
+
+        // 触发后从引擎移除，不再重复触发
+        assert!(engine.on_clock(trigger_time + 1000).is_empty());
+    }
+
+    #[test]
+    fn test_volume_trigger_submits_order_at_threshold() {
+        let mut engine = ConditionalOrderEngine::new();
+        engine.set_order_router(create_test_router());
+
+        // 创建前的成交量不计入
+        engine.on_trade("IF2501", 100.0, 50.0);
+        let order = engine
+            .create_order(order_request(
+                "IF2501",
+                TriggerSpec::Volume {
+                    instrument_id: None,
+                    volume_threshold: 10.0,
+                },
+            ))
+            .unwrap();
+        assert!(engine.by_volume_instrument.contains_key("IF2501"));
+
+        assert!(engine.on_trade("IF2501", 100.0, 6.0).is_empty());
+        // 其他合约成交不影响
+        assert!(engine.on_trade("IF2502", 100.0, 20.0).is_empty());
+
+        // 累计达到阈值，下单
+        let triggered = engine.on_trade("IF2501", 100.2, 4.0);
+        assert_eq!(triggered.len(), 1);
+        assert_eq!(
+            triggered[0].conditional_order_id,
+            order.conditional_order_id
+        );
+        assert!(triggered[0].result_order_id.is_some());
+        assert_eq!(engine.cumulative_volume("IF2501"), 60.0);
+        assert!(!engine.by_volume_instrument.contains_key("IF2501"));
+    }
+
+    #[test]
+    fn test_composite_trigger_and_cleanup() {
+        let engine = ConditionalOrderEngine::new();
+        let now = Utc::now().timestamp_millis();
+
+        // AND：到点且价格 >= 105
+        let and_order = engine
+            .create_order(order_request(
+                "IF2501",
+                TriggerSpec::And {
+                    conditions: vec![
+                        TriggerSpec::Time { trigger_time: now },
+                        TriggerSpec::Price {
+                            instrument_id: None,
+                            trigger_price: 105.0,
+                            trigger_condition: TriggerCondition::GreaterOrEqual,
+                        },
+                    ],
+                },
+            ))
+            .unwrap();
+        // OR：IF2502 成交 5 手或价格 <= 90
+        let or_order = engine
+            .create_order(order_request(
+                "IF2501",
+                TriggerSpec::Or {
+                    conditions: vec![
+                        TriggerSpec::Volume {
+                            instrument_id: Some("IF2502".to_string()),
+                            volume_threshold: 5.0,
+                        },
+                        TriggerSpec::Price {
+                            instrument_id: None,
+                            trigger_price: 90.0,
+                            trigger_condition: TriggerCondition::LessOrEqual,
+                        },
+                    ],
+                },
+            ))
+            .unwrap();
+
+        // 时间已到但价格未满足
+        assert!(engine.on_clock(now).is_empty());
+        let triggered = engine.check_triggers("IF2501", 106.0);
+        assert_eq!(triggered.len(), 1);
+        assert_eq!(
+            triggered[0].conditional_order_id,
+            and_order.conditional_order_id
+        );
+
+        let triggered = engine.on_trade("IF2502", 3000.0, 5.0);
+        assert_eq!(triggered.len(), 1);
+        assert_eq!(
+            triggered[0].conditional_order_id,
+            or_order.conditional_order_id
+        );
+
+        // 永不满足：触发时间晚于有效期
+        let mut req = order_request(
+            "IF2501",
+            TriggerSpec::Time {
+                trigger_time: now + 10_000,
+            },
+        );
+        req.valid_until = Some(now + 5_000);
+        assert!(engine.create_order(req).is_err());
+        assert!(engine
+            .create_order(order_request(
+                "IF2501",
+                TriggerSpec::Or { conditions: vec![] }
+            ))
+            .is_err());
+
+        // 过期清理：时钟驱动，无需该合约有行情
+        let mut req = order_request(
+            "IF2501",
+            TriggerSpec::Price {
+                instrument_id: None,
+                trigger_price: 200.0,
+                trigger_condition: TriggerCondition::GreaterOrEqual,
+            },
+        );
+        req.valid_until = Some(now + 5_000);
+        let expiring = engine.create_order(req).unwrap();
+        assert!(engine.on_clock(now + 6_000).is_empty());
+        assert_eq!(
+            engine
+                .get_order(&expiring.conditional_order_id)
+                .unwrap()
+                .status,
+            ConditionalOrderStatus::Expired
+        );
+        assert!(engine.by_instrument.is_empty());
+        assert!(engine.by_volume_instrument.is_empty());
+    }
+
     #[test]
     fn test_conditional_order_trigger() {
         let engine = ConditionalOrderEngine::new();
@@ -340,6 +899,7 @@ mod tests {
             trigger_price: 70000.0,
             trigger_condition: TriggerCondition::LessOrEqual,
             valid_until: None,
+            trigger: None,
         };
 
         let order = engine.create_order(req).unwrap();
@@ -371,6 +931,7 @@ mod tests {
             trigger_price: 80000.0,
             trigger_condition: TriggerCondition::GreaterOrEqual,
             valid_until: None,
+            trigger: None,
         };
 
         let order = engine.create_order(req).unwrap();
@@ -0,0 +1,130 @@
+//! 穿仓处理（风险准备金垫付）
+//!
+//! @yutiansut @quantaxis
+//!
+//! 极端行情下账户结算后权益为负（穿仓）时：
+//! - 统计穿仓金额及持仓来源，生成穿仓明细
+//! - 从风险准备金账户划转资金垫付，客户账户权益补至 0
+//! - 准备金不足时按穿仓金额比例分摊，未覆盖部分单独标记
+//!
+//! 划转为"准备金出金 + 客户入金"两笔等额资金流，全市场资金总额不变。
+//! 所有金额按"分"（i64）计算，避免分摊时的浮点误差。
+
+use serde::{Deserialize, Serialize};
+
+/// 穿仓持仓来源（结算时的单合约持仓）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeficitSource {
+    pub instrument_id: String,
+    /// 多头持仓量
+    pub volume_long: f64,
+    /// 空头持仓量
+    pub volume_short: f64,
+    /// 结算价（无结算价时为 0）
+    pub settlement_price: f64,
+    /// 按结算价计算的持仓盈亏
+    pub position_profit: f64,
+}
+
+/// 穿仓记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeficitRecord {
+    pub record_id: String,
+    /// 结算日期
+    pub settlement_date: String,
+    pub account_id: String,
+    /// 穿仓金额（正数）
+    pub deficit: f64,
+    /// 风险准备金垫付金额
+    pub covered: f64,
+    /// 未覆盖金额（准备金不足时 > 0）
+    pub uncovered: f64,
+    /// 垫付后客户权益
+    pub balance_after: f64,
+    /// 持仓来源
+    pub sources: Vec<DeficitSource>,
+    /// 风险准备金账户（未配置时为空）
+    pub reserve_account_id: Option<String>,
+    pub created_at: String,
+}
+
+impl DeficitRecord {
+    /// 是否完全覆盖
+    pub fn is_fully_covered(&self) -> bool {
+        self.uncovered <= 0.0
+    }
+}
+
+/// 风险准备金状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RiskReserveStatus {
+    /// 风险准备金账户（未配置时为空）
+    pub account_id: Option<String>,
+    /// 当前权益
+    pub balance: f64,
+    /// 可用资金
+    pub available: f64,
+    /// 累计垫付金额
+    pub total_covered: f64,
+    /// 累计未覆盖金额
+    pub total_uncovered: f64,
+    /// 累计穿仓记录数
+    pub record_count: usize,
+}
+
+/// 按穿仓金额比例分摊可用准备金（单位：分）
+///
+/// 准备金充足时全额覆盖；不足时按比例取整，余下的分按最大余数依次补足，
+/// 保证分摊合计恰好等于可用准备金
+pub fn allocate_pro_rata(deficits: &[i64], available: i64) -> Vec<i64> {
+    let total: i64 = deficits.iter().map(|d| (*d).max(0)).sum();
+    let available = available.max(0);
+    if available >= total {
+        return deficits.iter().map(|d| (*d).max(0)).collect();
+    }
+
+    let mut allocations = Vec::with_capacity(deficits.len());
+    let mut remainders = Vec::with_capacity(deficits.len());
+    for (i, deficit) in deficits.iter().enumerate() {
+        let weighted = (*deficit).max(0) as i128 * available as i128;
+        allocations.push((weighted / total as i128) as i64);
+        remainders.push((weighted % total as i128, i));
+    }
+
+    let mut leftover = available - allocations.iter().sum::<i64>();
+    remainders.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    for (_, i) in remainders {
+        if leftover == 0 {
+            break;
+        }
+        allocations[i] += 1;
+        leftover -= 1;
+    }
+    allocations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allocate_fully_covered() {
+        assert_eq!(allocate_pro_rata(&[500, 1500], 10_000), vec![500, 1500]);
+        assert_eq!(allocate_pro_rata(&[500, 1500], 2000), vec![500, 1500]);
+    }
+
+    #[test]
+    fn test_allocate_pro_rata_sums_to_available() {
+        let deficits = [100_000, 200_000, 300_000];
+        let allocations = allocate_pro_rata(&deficits, 300_000);
+        assert_eq!(allocations, vec![50_000, 100_000, 150_000]);
+
+        // 1 分无法整除时按最大余数补足
+        let allocations = allocate_pro_rata(&[1, 1, 1], 2);
+        assert_eq!(allocations.iter().sum::<i64>(), 2);
+        assert_eq!(allocations, vec![1, 1, 0]);
+
+        let allocations = allocate_pro_rata(&[33_333, 66_667], 0);
+        assert_eq!(allocations, vec![0, 0]);
+    }
+}
@@ -0,0 +1,471 @@
+//! 确定性运行模式（回放/仿真/回归测试）
+//! @yutiansut @quantaxis
+//!
+//! 用重放做回归测试时，线程调度与系统时钟会让两次运行的成交顺序、时间戳、编号各不相同。
+//! 确定性模式下：
+//! - 取时间的地方统一经 [`ExchangeClock`] 取时，虚拟时钟只由驱动者推进
+//! - 撮合与账户更新在单线程事件循环中按输入顺序处理（不启用分片撮合与优先级队列）
+//! - 需要随机 UUID 的地方改用种子化的 [`SeededIdGenerator`]
+//!
+//! [`ExchangeClock`] 是枚举包装（静态分发），生产路径默认为 `System`，只多一次分支判断。
+//! 同一输入事件流重放两次，[`ReplayOutput`] 序列化后逐字节相同。
+//!
+//! 账户内部的 qars 订单号由 qars 自行生成，不属于确定性输出；
+//! 账户状态只比较资金与持仓（QIFI `accounts` / `positions`）。
+
+use chrono::{DateTime, FixedOffset, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crate::core::account_ext::OpenAccountRequest;
+use crate::exchange::instrument_registry::InstrumentInfo;
+use crate::exchange::order_router::{CancelOrderRequest, SubmitOrderRequest, SubmitOrderResponse};
+use crate::exchange::{AccountManager, InstrumentRegistry, OrderRouter, TradeGateway};
+use crate::matching::engine::ExchangeMatchingEngine;
+use crate::matching::trade_recorder::TradeRecord;
+use crate::protocol::qifi::account::{Account, Position};
+use crate::utils::time_service::time_service;
+use crate::utils::timestamp::{millis_to_nanos, nanos_to_millis};
+use crate::ExchangeError;
+
+/// 时钟
+pub trait Clock: Send + Sync {
+    /// 当前时间（纳秒）
+    fn now_nanos(&self) -> i64;
+
+    /// 当前时间（毫秒）
+    fn now_millis(&self) -> i64 {
+        nanos_to_millis(self.now_nanos())
+    }
+
+    fn now_utc(&self) -> DateTime<Utc> {
+        Utc.timestamp_nanos(self.now_nanos())
+    }
+
+    /// 当前交易所本地时间（按交易所时区，不依赖服务器时区）
+    fn now_local(&self) -> DateTime<FixedOffset> {
+        time_service().to_local_nanos(self.now_nanos())
+    }
+
+    /// 当前交易日（夜盘归属下一交易日）
+    fn trading_day(&self) -> NaiveDate {
+        time_service().trading_day_of(self.now_millis())
+    }
+}
+
+/// 系统时钟（生产路径）
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    #[inline]
+    fn now_nanos(&self) -> i64 {
+        Utc::now().timestamp_nanos_opt().unwrap_or(0)
+    }
+
+    #[inline]
+    fn now_millis(&self) -> i64 {
+        Utc::now().timestamp_millis()
+    }
+
+    #[inline]
+    fn now_utc(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    #[inline]
+    fn now_local(&self) -> DateTime<FixedOffset> {
+        time_service().now()
+    }
+}
+
+/// 虚拟时钟（只由驱动者推进，不会自行走动）
+#[derive(Debug, Default)]
+pub struct VirtualClock {
+    nanos: AtomicI64,
+}
+
+impl VirtualClock {
+    pub fn new(start_millis: i64) -> Self {
+        Self {
+            nanos: AtomicI64::new(millis_to_nanos(start_millis)),
+        }
+    }
+
+    /// 设置当前时间（毫秒）
+    pub fn set_millis(&self, millis: i64) {
+        self.nanos.store(millis_to_nanos(millis), Ordering::SeqCst);
+    }
+
+    /// 推进时钟，返回推进后的时间（毫秒）
+    pub fn advance_millis(&self, millis: i64) -> i64 {
+        let delta = millis_to_nanos(millis.max(0));
+        nanos_to_millis(self.nanos.fetch_add(delta, Ordering::SeqCst) + delta)
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now_nanos(&self) -> i64 {
+        self.nanos.load(Ordering::SeqCst)
+    }
+}
+
+/// 交易所时钟（枚举包装，静态分发）
+#[derive(Debug, Clone, Default)]
+pub enum ExchangeClock {
+    /// 系统时钟
+    #[default]
+    System,
+    /// 虚拟时钟（确定性模式）
+    Virtual(Arc<VirtualClock>),
+}
+
+impl ExchangeClock {
+    pub fn is_virtual(&self) -> bool {
+        matches!(self, ExchangeClock::Virtual(_))
+    }
+}
+
+impl Clock for ExchangeClock {
+    #[inline]
+    fn now_nanos(&self) -> i64 {
+        match self {
+            ExchangeClock::System => SystemClock.now_nanos(),
+            ExchangeClock::Virtual(clock) => clock.now_nanos(),
+        }
+    }
+
+    #[inline]
+    fn now_millis(&self) -> i64 {
+        match self {
+            ExchangeClock::System => SystemClock.now_millis(),
+            ExchangeClock::Virtual(clock) => clock.now_millis(),
+        }
+    }
+
+    #[inline]
+    fn now_utc(&self) -> DateTime<Utc> {
+        match self {
+            ExchangeClock::System => SystemClock.now_utc(),
+            ExchangeClock::Virtual(clock) => clock.now_utc(),
+        }
+    }
+
+    #[inline]
+    fn now_local(&self) -> DateTime<FixedOffset> {
+        match self {
+            ExchangeClock::System => SystemClock.now_local(),
+            ExchangeClock::Virtual(clock) => clock.now_local(),
+        }
+    }
+}
+
+/// 种子化的确定性 ID 生成器（SplitMix64）
+///
+/// 同一种子按相同顺序调用得到相同序列，用于替代 `Uuid::new_v4`
+#[derive(Debug)]
+pub struct SeededIdGenerator {
+    seed: u64,
+    counter: AtomicU64,
+}
+
+impl SeededIdGenerator {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            counter: AtomicU64::new(0),
+        }
+    }
+
+    pub fn next_u64(&self) -> u64 {
+        let n = self.counter.fetch_add(1, Ordering::SeqCst);
+        let mut z = self
+            .seed
+            .wrapping_add(n.wrapping_add(1).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// 生成 v4 格式的 UUID
+    pub fn next_uuid(&self) -> uuid::Uuid {
+        let mut bytes = [0u8; 16];
+        bytes[..8].copy_from_slice(&self.next_u64().to_be_bytes());
+        bytes[8..].copy_from_slice(&self.next_u64().to_be_bytes());
+        uuid::Builder::from_random_bytes(bytes).into_uuid()
+    }
+}
+
+/// 确定性模式配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeterministicConfig {
+    /// 是否启用（仅用于回放与测试，交易所服务不使用）
+    #[serde(default)]
+    pub enabled: bool,
+    /// 随机种子
+    #[serde(default)]
+    pub seed: u64,
+    /// 虚拟时钟起始时间（毫秒）
+    #[serde(default = "default_start_time_ms")]
+    pub start_time_ms: i64,
+    /// 交易日 (YYYYMMDD，为空时取虚拟时钟起始日期)
+    #[serde(default)]
+    pub trading_day: String,
+}
+
+fn default_start_time_ms() -> i64 {
+    1_735_689_600_000 // 2025-01-01 00:00:00 UTC
+}
+
+impl Default for DeterministicConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            seed: 0,
+            start_time_ms: default_start_time_ms(),
+            trading_day: String::new(),
+        }
+    }
+}
+
+/// 回放输入事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ReplayEvent {
+    /// 下单
+    Submit(SubmitOrderRequest),
+    /// 撤单（`submit_index` 为本事件流中对应下单事件的序号）
+    Cancel {
+        account_id: String,
+        submit_index: usize,
+    },
+    /// 推进虚拟时钟（随后处理到期的 TTL 订单）
+    AdvanceClock { millis: i64 },
+}
+
+/// 单个事件的处理结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ReplayResult {
+    Submit(SubmitOrderResponse),
+    Cancel {
+        order_id: Option<String>,
+        error: Option<String>,
+    },
+    AdvanceClock {
+        now_ms: i64,
+        expired_orders: usize,
+    },
+}
+
+/// 账户最终状态（资金与持仓）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountReplayState {
+    pub account_id: String,
+    pub accounts: Account,
+    pub positions: BTreeMap<String, Position>,
+}
+
+/// 回放输出
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayOutput {
+    /// 逐事件处理结果
+    pub results: Vec<ReplayResult>,
+    /// 成交序列（按成交编号升序）
+    pub trades: Vec<TradeRecord>,
+    /// 账户最终状态（按账户ID升序）
+    pub accounts: Vec<AccountReplayState>,
+}
+
+impl ReplayOutput {
+    /// 序列化为字节（用于逐字节比较两次回放）
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ExchangeError> {
+        serde_json::to_vec(self).map_err(|e| {
+            ExchangeError::SerializationError(format!("Serialize replay output failed: {}", e))
+        })
+    }
+}
+
+/// 确定性交易所（单线程事件循环驱动）
+///
+/// 各组件共享同一个虚拟时钟；事件在调用线程上逐个处理，前一个事件的撮合与账户更新
+/// 全部完成后才处理下一个
+pub struct DeterministicExchange {
+    clock: Arc<VirtualClock>,
+    account_mgr: Arc<AccountManager>,
+    matching_engine: Arc<ExchangeMatchingEngine>,
+    instrument_registry: Arc<InstrumentRegistry>,
+    router: Arc<OrderRouter>,
+}
+
+impl DeterministicExchange {
+    /// 按配置创建，WAL 写入 `wal_root`
+    pub fn new(config: &DeterministicConfig, wal_root: impl Into<String>) -> Self {
+        let clock = Arc::new(VirtualClock::new(config.start_time_ms));
+        let exchange_clock = ExchangeClock::Virtual(clock.clone());
+
+        let mut account_mgr = AccountManager::new();
+        account_mgr.set_id_generator(Arc::new(SeededIdGenerator::new(config.seed)));
+        let account_mgr = Arc::new(account_mgr);
+
+        let matching_engine = Arc::new(ExchangeMatchingEngine::with_clock(exchange_clock.clone()));
+        let instrument_registry = Arc::new(InstrumentRegistry::new());
+        let trade_gateway = TradeGateway::new(account_mgr.clone())
+            .with_wal_root(wal_root)
+            .with_clock(exchange_clock.clone());
+        if !config.trading_day.is_empty() {
+            trade_gateway
+                .id_generator()
+                .set_trading_day(&config.trading_day);
+        }
+
+        let mut router = OrderRouter::new(
+            account_mgr.clone(),
+            matching_engine.clone(),
+            instrument_registry.clone(),
+            Arc::new(trade_gateway),
+        );
+        router.set_clock(exchange_clock);
+        // 同步消费：回放时成交记录与下单调用严格同序
+        router
+            .trade_bus()
+            .subscribe_inline("trade_recorder", matching_engine.get_trade_recorder());
+
+        Self {
+            clock,
+            account_mgr,
+            matching_engine,
+            instrument_registry,
+            router: Arc::new(router),
+        }
+    }
+
+    pub fn clock(&self) -> &Arc<VirtualClock> {
+        &self.clock
+    }
+
+    pub fn router(&self) -> &Arc<OrderRouter> {
+        &self.router
+    }
+
+    pub fn account_manager(&self) -> &Arc<AccountManager> {
+        &self.account_mgr
+    }
+
+    /// 注册合约（撮合引擎 + 合约注册表）
+    pub fn register_instrument(
+        &self,
+        info: InstrumentInfo,
+        prev_close: f64,
+    ) -> Result<(), ExchangeError> {
+        self.matching_engine
+            .register_instrument(info.instrument_id.clone(), prev_close)?;
+        self.instrument_registry.register(info)
+    }
+
+    pub fn open_account(&self, req: OpenAccountRequest) -> Result<String, ExchangeError> {
+        self.account_mgr.open_account(req)
+    }
+
+    /// 按输入顺序逐个处理事件，返回处理结果、成交序列与账户最终状态
+    pub fn run(&self, events: &[ReplayEvent]) -> ReplayOutput {
+        let mut results: Vec<ReplayResult> = Vec::with_capacity(events.len());
+        for event in events {
+            let result = match event {
+                ReplayEvent::Submit(req) => {
+                    ReplayResult::Submit(self.router.submit_order(req.clone()))
+                }
+                ReplayEvent::Cancel {
+                    account_id,
+                    submit_index,
+                } => {
+                    let order_id = match results.get(*submit_index) {
+                        Some(ReplayResult::Submit(resp)) => resp.order_id.clone(),
+                        _ => None,
+                    };
+                    let error = match &order_id {
+                        Some(order_id) => self
+                            .router
+                            .cancel_order(CancelOrderRequest {
+                                account_id: account_id.clone(),
+                                order_id: order_id.clone(),
+                            })
+                            .err()
+                            .map(|e| e.to_string()),
+                        None => Some(format!("No submitted order at index {}", submit_index)),
+                    };
+                    ReplayResult::Cancel { order_id, error }
+                }
+                ReplayEvent::AdvanceClock { millis } => {
+                    let now_ms = self.clock.advance_millis(*millis);
+                    ReplayResult::AdvanceClock {
+                        now_ms,
+                        expired_orders: self.router.expire_orders(),
+                    }
+                }
+            };
+            results.push(result);
+        }
+
+        ReplayOutput {
+            results,
+            trades: self.trades(),
+            accounts: self.account_states(),
+        }
+    }
+
+    /// 成交序列（按成交编号升序）
+    pub fn trades(&self) -> Vec<TradeRecord> {
+        let mut trades = self.matching_engine.get_trade_recorder().get_all_trades();
+        trades.sort_by(|a, b| a.trade_id.cmp(&b.trade_id));
+        trades
+    }
+
+    /// 账户资金与持仓（按账户ID升序）
+    pub fn account_states(&self) -> Vec<AccountReplayState> {
+        let mut states: Vec<AccountReplayState> = self
+            .account_mgr
+            .get_all_accounts()
+            .into_iter()
+            .map(|account| {
+                let qifi = account.write().get_qifi_slice();
+                AccountReplayState {
+                    account_id: qifi.account_cookie.clone(),
+                    accounts: qifi.accounts,
+                    positions: qifi.positions.into_iter().collect(),
+                }
+            })
+            .collect();
+        states.sort_by(|a, b| a.account_id.cmp(&b.account_id));
+        states
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_virtual_clock_and_seeded_ids() {
+        let clock = ExchangeClock::Virtual(Arc::new(VirtualClock::new(1_000)));
+        assert!(clock.is_virtual());
+        assert_eq!(clock.now_millis(), 1_000);
+        assert_eq!(clock.now_millis(), 1_000); // 不会自行走动
+        if let ExchangeClock::Virtual(ref inner) = clock {
+            assert_eq!(inner.advance_millis(500), 1_500);
+        }
+        assert_eq!(clock.now_nanos(), 1_500_000_000);
+        assert!(!ExchangeClock::default().is_virtual());
+
+        let a = SeededIdGenerator::new(42);
+        let b = SeededIdGenerator::new(42);
+        let first: Vec<uuid::Uuid> = (0..3).map(|_| a.next_uuid()).collect();
+        let second: Vec<uuid::Uuid> = (0..3).map(|_| b.next_uuid()).collect();
+        assert_eq!(first, second);
+        assert_ne!(first[0], first[1]);
+        assert_eq!(first[0].get_version_num(), 4);
+        assert_ne!(SeededIdGenerator::new(7).next_uuid(), first[0]);
+    }
+}
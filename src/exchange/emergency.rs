@@ -0,0 +1,606 @@
+//! 紧急停机与安全恢复
+//! @yutiansut @quantaxis
+//!
+//! 严重故障时按以下顺序停机：
+//! 1. 进入维护状态（健康检查返回 maintenance）并停止接单
+//! 2. 等待在途下单/撤单处理完成
+//! 3. 保存账户 QIFI 快照与订单簿快照
+//! 4. flush 所有已登记的存储
+//! 5. 落盘停机记录（原因、时点、各步骤结果）
+//!
+//! 整个流程共享一个超时预算，任何一步超时都记录到停机记录中并继续后续步骤，避免无限等待。
+//! 重启后沿用常规恢复流程（快照 + WAL + dailyorders 重建订单簿），再由 `verify_recovery`
+//! 用停机时保存的订单簿校验重建结果。
+
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::exchange::{AccountManager, OrderRouter};
+use crate::market::{OrderBookSnapshot, PriceLevel};
+use crate::matching::engine::ExchangeMatchingEngine;
+use crate::storage::hybrid::OltpHybridStorage;
+
+/// 停机记录文件名（位于存储根目录）
+pub const SHUTDOWN_RECORD_FILE: &str = "emergency_shutdown.json";
+
+/// 订单簿快照目录名（位于存储根目录）
+pub const ORDERBOOK_SNAPSHOT_DIR: &str = "orderbooks";
+
+/// 账户快照目录名（与定期快照共用）
+pub const ACCOUNT_SNAPSHOT_DIR: &str = "snapshots";
+
+/// 维护状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceInfo {
+    /// 停机原因
+    pub reason: String,
+    /// 进入维护状态的时间（毫秒）
+    pub since: i64,
+}
+
+/// 停机记录
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ShutdownRecord {
+    pub reason: String,
+    /// 开始时间（毫秒）
+    pub started_at: i64,
+    /// 完成时间（毫秒）
+    pub completed_at: i64,
+    /// 在途请求是否在超时前处理完成
+    pub in_flight_drained: bool,
+    pub accounts_saved: usize,
+    pub orderbooks_saved: usize,
+    pub storages_flushed: usize,
+    /// 超时或失败的步骤
+    pub errors: Vec<String>,
+}
+
+lazy_static::lazy_static! {
+    /// 全局维护状态（健康检查读取）
+    static ref MAINTENANCE: RwLock<Option<MaintenanceInfo>> = RwLock::new(None);
+}
+
+/// 当前维护状态（None 表示正常运行）
+pub fn maintenance_status() -> Option<MaintenanceInfo> {
+    MAINTENANCE.read().clone()
+}
+
+/// 进入维护状态
+pub fn enter_maintenance(reason: &str) {
+    *MAINTENANCE.write() = Some(MaintenanceInfo {
+        reason: reason.to_string(),
+        since: chrono::Utc::now().timestamp_millis(),
+    });
+}
+
+/// 退出维护状态
+pub fn exit_maintenance() {
+    *MAINTENANCE.write() = None;
+}
+
+/// 紧急停机协调器
+pub struct EmergencyShutdown {
+    account_mgr: Arc<AccountManager>,
+    order_router: Arc<OrderRouter>,
+    matching_engine: Arc<ExchangeMatchingEngine>,
+    /// 需要 flush 的存储（名称, 存储）
+    storages: Vec<(String, Arc<OltpHybridStorage>)>,
+    /// 存储根目录
+    storage_path: PathBuf,
+    /// 停机总超时
+    timeout: Duration,
+    /// 已请求的停机原因（由管理端触发，主流程等待）
+    requested: RwLock<Option<String>>,
+    request_notify: tokio::sync::Notify,
+    /// 最近一次停机记录
+    last_record: RwLock<Option<ShutdownRecord>>,
+}
+
+impl EmergencyShutdown {
+    pub fn new(
+        account_mgr: Arc<AccountManager>,
+        order_router: Arc<OrderRouter>,
+        matching_engine: Arc<ExchangeMatchingEngine>,
+        storage_path: impl Into<PathBuf>,
+    ) -> Self {
+        Self {
+            account_mgr,
+            order_router,
+            matching_engine,
+            storages: Vec::new(),
+            storage_path: storage_path.into(),
+            timeout: Duration::from_secs(30),
+            requested: RwLock::new(None),
+            request_notify: tokio::sync::Notify::new(),
+            last_record: RwLock::new(None),
+        }
+    }
+
+    /// 登记停机时需要 flush 的存储
+    pub fn with_storage(mut self, name: &str, storage: Arc<OltpHybridStorage>) -> Self {
+        self.storages.push((name.to_string(), storage));
+        self
+    }
+
+    /// 设置停机总超时
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 请求停机：立即进入维护状态并停止接单，由主流程执行完整停机
+    ///
+    /// 重复请求返回 false
+    pub fn request(&self, reason: &str) -> bool {
+        {
+            let mut requested = self.requested.write();
+            if requested.is_some() {
+                return false;
+            }
+            *requested = Some(reason.to_string());
+        }
+        enter_maintenance(reason);
+        self.order_router.halt_order_entry(reason);
+        self.request_notify.notify_one();
+        true
+    }
+
+    /// 等待停机请求，返回停机原因
+    pub async fn wait_requested(&self) -> String {
+        loop {
+            if let Some(reason) = self.requested.read().clone() {
+                return reason;
+            }
+            self.request_notify.notified().await;
+        }
+    }
+
+    /// 最近一次停机记录
+    pub fn last_record(&self) -> Option<ShutdownRecord> {
+        self.last_record.read().clone()
+    }
+
+    /// 执行停机：停止接单、等待在途、保存快照、flush 存储、落盘停机记录
+    pub fn execute(&self, reason: &str) -> ShutdownRecord {
+        let deadline = Instant::now() + self.timeout;
+        let remaining = || deadline.saturating_duration_since(Instant::now());
+
+        log::warn!("🛑 Emergency shutdown started: {}", reason);
+        let mut record = ShutdownRecord {
+            reason: reason.to_string(),
+            started_at: chrono::Utc::now().timestamp_millis(),
+            ..Default::default()
+        };
+
+        // 1. 维护状态 + 停止接单
+        enter_maintenance(reason);
+        self.order_router.halt_order_entry(reason);
+        *self.requested.write() = Some(reason.to_string());
+
+        // 2. 等待在途请求
+        record.in_flight_drained = self.order_router.wait_in_flight(remaining());
+        if !record.in_flight_drained {
+            record.errors.push(format!(
+                "in-flight requests not drained: {}",
+                self.order_router.in_flight_requests()
+            ));
+        }
+
+        // 3. 账户快照
+        let snapshot_dir = self.storage_path.join(ACCOUNT_SNAPSHOT_DIR);
+        match self
+            .account_mgr
+            .save_snapshots(&snapshot_dir.to_string_lossy())
+        {
+            Ok(count) => record.accounts_saved = count,
+            Err(e) => record.errors.push(format!("account snapshots: {}", e)),
+        }
+
+        // 4. 订单簿快照
+        match self.save_orderbooks() {
+            Ok(count) => record.orderbooks_saved = count,
+            Err(e) => record.errors.push(format!("orderbook snapshots: {}", e)),
+        }
+
+        // 5. flush 存储（独立线程执行，超时不阻塞停机）
+        for (name, storage) in &self.storages {
+            let (tx, rx) = std::sync::mpsc::channel();
+            let storage = storage.clone();
+            std::thread::spawn(move || {
+                let _ = tx.send(storage.flush());
+            });
+            match rx.recv_timeout(remaining()) {
+                Ok(Ok(_)) => record.storages_flushed += 1,
+                Ok(Err(e)) => record.errors.push(format!("flush {}: {}", name, e)),
+                Err(_) => record.errors.push(format!("flush {}: timed out", name)),
+            }
+        }
+
+        // 6. 停机记录
+        record.completed_at = chrono::Utc::now().timestamp_millis();
+        if let Err(e) = self.write_record(&record) {
+            log::error!("Failed to write shutdown record: {}", e);
+        }
+
+        if record.errors.is_empty() {
+            log::warn!(
+                "🛑 Emergency shutdown completed in {}ms: {} accounts, {} orderbooks, {} storages",
+                record.completed_at - record.started_at,
+                record.accounts_saved,
+                record.orderbooks_saved,
+                record.storages_flushed
+            );
+        } else {
+            log::error!(
+                "🛑 Emergency shutdown completed with errors: {:?}",
+                record.errors
+            );
+        }
+
+        *self.last_record.write() = Some(record.clone());
+        record
+    }
+
+    /// 保存所有合约的订单簿快照
+    fn save_orderbooks(&self) -> Result<usize, String> {
+        let dir = self.storage_path.join(ORDERBOOK_SNAPSHOT_DIR);
+        std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+        let snapshots = capture_orderbooks(&self.matching_engine);
+        for snapshot in &snapshots {
+            let json = serde_json::to_string_pretty(snapshot).map_err(|e| e.to_string())?;
+            std::fs::write(dir.join(format!("{}.json", snapshot.instrument_id)), json)
+                .map_err(|e| e.to_string())?;
+        }
+        Ok(snapshots.len())
+    }
+
+    fn write_record(&self, record: &ShutdownRecord) -> Result<(), String> {
+        std::fs::create_dir_all(&self.storage_path).map_err(|e| e.to_string())?;
+        let json = serde_json::to_string_pretty(record).map_err(|e| e.to_string())?;
+        std::fs::write(self.storage_path.join(SHUTDOWN_RECORD_FILE), json)
+            .map_err(|e| e.to_string())
+    }
+
+    /// 读取未校验的停机记录
+    pub fn load_record(storage_path: &Path) -> Option<ShutdownRecord> {
+        let json = std::fs::read_to_string(storage_path.join(SHUTDOWN_RECORD_FILE)).ok()?;
+        serde_json::from_str(&json).ok()
+    }
+
+    /// 恢复后校验：重建的订单簿与停机时保存的一致
+    ///
+    /// 无停机记录返回 Ok(None)；校验通过后将停机记录归档（`emergency_shutdown.{completed_at}.json`）
+    pub fn verify_recovery(
+        storage_path: &Path,
+        matching_engine: &ExchangeMatchingEngine,
+    ) -> Result<Option<ShutdownRecord>, Vec<String>> {
+        let Some(record) = Self::load_record(storage_path) else {
+            return Ok(None);
+        };
+
+        let current: HashMap<String, OrderBookSnapshot> = capture_orderbooks(matching_engine)
+            .into_iter()
+            .map(|s| (s.instrument_id.clone(), s))
+            .collect();
+
+        let mut mismatches = Vec::new();
+        let dir = storage_path.join(ORDERBOOK_SNAPSHOT_DIR);
+        for entry in std::fs::read_dir(&dir).into_iter().flatten().flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|s| s.to_str()) != Some("json") {
+                continue;
+            }
+            let saved: OrderBookSnapshot = match std::fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+            {
+                Ok(saved) => saved,
+                Err(e) => {
+                    mismatches.push(format!("{:?}: {}", path.file_name(), e));
+                    continue;
+                }
+            };
+
+            match current.get(&saved.instrument_id) {
+                Some(rebuilt)
+                    if same_levels(&saved.bids, &rebuilt.bids)
+                        && same_levels(&saved.asks, &rebuilt.asks) => {}
+                Some(_) => mismatches.push(format!("{}: orderbook differs", saved.instrument_id)),
+                None => mismatches.push(format!("{}: instrument missing", saved.instrument_id)),
+            }
+        }
+
+        if !mismatches.is_empty() {
+            log::error!("Emergency shutdown recovery check failed: {:?}", mismatches);
+            return Err(mismatches);
+        }
+
+        let archived =
+            storage_path.join(format!("emergency_shutdown.{}.json", record.completed_at));
+        if let Err(e) = std::fs::rename(storage_path.join(SHUTDOWN_RECORD_FILE), archived) {
+            log::warn!("Failed to archive shutdown record: {}", e);
+        }
+        log::info!(
+            "✅ Recovered from emergency shutdown ({}, at {}): orderbooks verified",
+            record.reason,
+            record.started_at
+        );
+        Ok(Some(record))
+    }
+}
+
+/// 采集所有合约的全量订单簿（按价格聚合）
+pub fn capture_orderbooks(matching_engine: &ExchangeMatchingEngine) -> Vec<OrderBookSnapshot> {
+    let timestamp = chrono::Utc::now().timestamp_millis();
+    let mut instruments = matching_engine.get_instruments();
+    instruments.sort();
+
+    instruments
+        .into_iter()
+        .filter_map(|instrument_id| {
+            let orderbook = matching_engine.get_orderbook(&instrument_id)?;
+            let ob = orderbook.read();
+
+            let bids: Vec<(f64, f64)> = ob
+                .bid_queue
+                .get_sorted_orders()
+                .map(|orders| orders.iter().map(|o| (o.price, o.volume)).collect())
+                .unwrap_or_default();
+            let asks: Vec<(f64, f64)> = ob
+                .ask_queue
+                .get_sorted_orders()
+                .map(|orders| orders.iter().map(|o| (o.price, o.volume)).collect())
+                .unwrap_or_default();
+
+            Some(OrderBookSnapshot {
+                instrument_id: instrument_id.clone(),
+                timestamp,
+                bids: aggregate_levels(bids, true),
+                asks: aggregate_levels(asks, false),
+                last_price: Some(ob.lastprice),
+            })
+        })
+        .collect()
+}
+
+/// 按价格聚合挂单量（买盘降序、卖盘升序）
+fn aggregate_levels(orders: Vec<(f64, f64)>, descending: bool) -> Vec<PriceLevel> {
+    let mut levels: Vec<(f64, f64)> = Vec::new();
+    for (price, volume) in orders {
+        match levels.iter_mut().find(|(p, _)| *p == price) {
+            Some((_, v)) => *v += volume,
+            None => levels.push((price, volume)),
+        }
+    }
+    levels.sort_by(|a, b| {
+        let ord = a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal);
+        if descending {
+            ord.reverse()
+        } else {
+            ord
+        }
+    });
+    levels
+        .into_iter()
+        .map(|(price, volume)| PriceLevel {
+            price,
+            volume: volume as i64,
+        })
+        .collect()
+}
+
+fn same_levels(a: &[PriceLevel], b: &[PriceLevel]) -> bool {
+    a.len() == b.len()
+        && a.iter()
+            .zip(b)
+            .all(|(x, y)| (x.price - y.price).abs() < 1e-9 && x.volume == y.volume)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::account_ext::{AccountType, OpenAccountRequest};
+    use crate::exchange::instrument_registry::{InstrumentInfo, InstrumentStatus, InstrumentType};
+    use crate::exchange::order_router::SubmitOrderRequest;
+    use crate::exchange::{InstrumentRegistry, TradeGateway};
+
+    struct TestExchange {
+        account_mgr: Arc<AccountManager>,
+        matching_engine: Arc<ExchangeMatchingEngine>,
+        router: Arc<OrderRouter>,
+    }
+
+    fn create_test_exchange(account_mgr: Arc<AccountManager>) -> TestExchange {
+        let matching_engine = Arc::new(ExchangeMatchingEngine::new());
+        matching_engine
+            .register_instrument("IX2301".to_string(), 120.0)
+            .unwrap();
+        let registry = Arc::new(InstrumentRegistry::new());
+        registry
+            .register(InstrumentInfo {
+                instrument_id: "IX2301".to_string(),
+                instrument_name: "IX2301".to_string(),
+                instrument_type: InstrumentType::CommodityFuture,
+                exchange: "SHFE".to_string(),
+                contract_multiplier: 1,
+                trading_unit: "手".to_string(),
+                quote_unit: String::new(),
+                price_tick: 0.01,
+                margin_rate: 0.1,
+                commission_rate: 0.0005,
+                limit_up_rate: 0.1,
+                limit_down_rate: 0.1,
+                status: InstrumentStatus::Active,
+                list_date: Some("2023-01-01".to_string()),
+                expire_date: Some("2023-12-31".to_string()),
+                created_at: "2023-01-01T00:00:00Z".to_string(),
+                updated_at: "2023-01-01T00:00:00Z".to_string(),
+            })
+            .unwrap();
+
+        let trade_gateway = Arc::new(TradeGateway::new(account_mgr.clone()));
+        let router = Arc::new(OrderRouter::new(
+            account_mgr.clone(),
+            matching_engine.clone(),
+            registry,
+            trade_gateway,
+        ));
+        TestExchange {
+            account_mgr,
+            matching_engine,
+            router,
+        }
+    }
+
+    fn order(account_id: &str, direction: &str, volume: f64, price: f64) -> SubmitOrderRequest {
+        SubmitOrderRequest {
+            account_id: account_id.to_string(),
+            instrument_id: "IX2301".to_string(),
+            direction: direction.to_string(),
+            offset: "OPEN".to_string(),
+            volume,
+            price,
+            order_type: "LIMIT".to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// (balance, 持仓多头, 持仓空头, 冻结保证金)
+    fn account_state(account_mgr: &AccountManager, account_id: &str) -> (f64, f64, f64, f64) {
+        let account = account_mgr.get_account(account_id).unwrap();
+        let mut acc = account.write();
+        let (long, short) = acc
+            .get_position("IX2301")
+            .map_or((0.0, 0.0), |pos| (pos.volume_long(), pos.volume_short()));
+        let qifi = acc.get_qifi_slice();
+        (
+            qifi.accounts.balance,
+            long,
+            short,
+            qifi.accounts.frozen_margin,
+        )
+    }
+
+    #[test]
+    fn test_emergency_shutdown_and_recovery() {
+        let dir = tempfile::tempdir().unwrap();
+        let account_mgr = Arc::new(AccountManager::new());
+        for user in ["buyer", "seller"] {
+            account_mgr
+                .open_account(OpenAccountRequest {
+                    user_id: user.to_string(),
+                    account_id: Some(user.to_string()),
+                    account_name: user.to_string(),
+                    init_cash: 1000000.0,
+                    account_type: AccountType::Individual,
+                })
+                .unwrap();
+        }
+        let exchange = create_test_exchange(account_mgr);
+
+        // 成交 2 手，另挂买卖单各一笔
+        assert!(
+            exchange
+                .router
+                .submit_order(order("buyer", "BUY", 2.0, 120.0))
+                .success
+        );
+        assert!(
+            exchange
+                .router
+                .submit_order(order("seller", "SELL", 2.0, 120.0))
+                .success
+        );
+        assert!(
+            exchange
+                .router
+                .submit_order(order("buyer", "BUY", 3.0, 118.0))
+                .success
+        );
+        assert!(
+            exchange
+                .router
+                .submit_order(order("seller", "SELL", 1.0, 123.0))
+                .success
+        );
+
+        let before_books = capture_orderbooks(&exchange.matching_engine);
+        let before_buyer = account_state(&exchange.account_mgr, "buyer");
+        let before_seller = account_state(&exchange.account_mgr, "seller");
+        assert_eq!(before_books[0].bids.len(), 1);
+        assert_eq!(before_books[0].asks.len(), 1);
+
+        let shutdown = EmergencyShutdown::new(
+            exchange.account_mgr.clone(),
+            exchange.router.clone(),
+            exchange.matching_engine.clone(),
+            dir.path(),
+        )
+        .with_timeout(Duration::from_secs(5));
+        let record = shutdown.execute("matching engine fault");
+
+        assert!(record.in_flight_drained);
+        assert!(record.errors.is_empty(), "{:?}", record.errors);
+        assert_eq!(record.accounts_saved, 2);
+        assert_eq!(record.orderbooks_saved, 1);
+        assert!(record.completed_at >= record.started_at);
+        assert_eq!(
+            maintenance_status().map(|m| m.reason),
+            Some("matching engine fault".to_string())
+        );
+        assert_eq!(
+            EmergencyShutdown::load_record(dir.path()).unwrap().reason,
+            "matching engine fault"
+        );
+
+        // 停机期间拒绝新订单，且不改动账户状态
+        let rejected = exchange
+            .router
+            .submit_order(order("buyer", "BUY", 1.0, 119.0));
+        assert!(!rejected.success);
+        assert_eq!(account_state(&exchange.account_mgr, "buyer"), before_buyer);
+
+        // 重启：从快照恢复账户并由 dailyorders 重建订单簿
+        let restored_mgr = Arc::new(AccountManager::new());
+        let snapshot_dir = dir.path().join(ACCOUNT_SNAPSHOT_DIR);
+        assert_eq!(
+            restored_mgr
+                .restore_from_snapshots(&snapshot_dir.to_string_lossy())
+                .unwrap(),
+            2
+        );
+        let restored = create_test_exchange(restored_mgr);
+        restored.router.restore_orders_from_accounts();
+
+        assert_eq!(account_state(&restored.account_mgr, "buyer"), before_buyer);
+        assert_eq!(
+            account_state(&restored.account_mgr, "seller"),
+            before_seller
+        );
+
+        let verified = EmergencyShutdown::verify_recovery(dir.path(), &restored.matching_engine)
+            .unwrap()
+            .unwrap();
+        assert_eq!(verified.reason, "matching engine fault");
+        // 校验通过后归档，再次启动不重复校验
+        assert!(EmergencyShutdown::load_record(dir.path()).is_none());
+
+        // 重建后的订单簿可继续撮合
+        assert!(
+            restored
+                .router
+                .submit_order(order("seller", "SELL", 3.0, 118.0))
+                .success
+        );
+        assert!(capture_orderbooks(&restored.matching_engine)[0]
+            .bids
+            .is_empty());
+
+        exit_maintenance();
+    }
+}
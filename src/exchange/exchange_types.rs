@@ -4,6 +4,94 @@
 
 use serde::{Deserialize, Serialize};
 
+/// 投机套保标志
+/// @yutiansut @quantaxis
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum HedgeFlag {
+    /// 投机 (默认)
+    #[default]
+    Speculation,
+    /// 套利
+    Arbitrage,
+    /// 套保
+    Hedge,
+    /// 做市商
+    MarketMaker,
+}
+
+impl HedgeFlag {
+    /// 对外回报使用的标准代码（'1' 投机 / '2' 套利 / '3' 套保 / '5' 做市商）
+    pub fn code(&self) -> char {
+        match self {
+            HedgeFlag::Speculation => '1',
+            HedgeFlag::Arbitrage => '2',
+            HedgeFlag::Hedge => '3',
+            HedgeFlag::MarketMaker => '5',
+        }
+    }
+}
+
+/// 成交类型
+/// @yutiansut @quantaxis
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TradeType {
+    /// 连续撮合成交 (默认)
+    #[default]
+    Normal,
+    /// 大宗交易协商成交（不经撮合）
+    Block,
+}
+
+/// 买卖标志标准代码（'0' 买 / '1' 卖）
+pub fn direction_code(direction: &str) -> char {
+    if direction.eq_ignore_ascii_case("SELL") {
+        '1'
+    } else {
+        '0'
+    }
+}
+
+/// 成交回报标准字段（对接上游/监管、生成对外成交文件）
+/// @yutiansut @quantaxis
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StandardTradeFields {
+    /// 交易所代码
+    pub exchange_id: String,
+
+    /// 交易所成交编号（交易日 + 全局序号，交易日内唯一）
+    pub exchange_trade_id: String,
+
+    /// 交易所报单编号
+    pub order_sys_id: String,
+
+    /// 交易日 (YYYYMMDD)
+    pub trading_day: String,
+
+    /// 成交日期 (YYYYMMDD，交易所时钟)
+    pub trade_date: String,
+
+    /// 成交时间 (HH:MM:SS.mmm，交易所时钟)
+    pub trade_time: String,
+
+    /// 成交序号（合约统一事件序列）
+    pub sequence_no: i64,
+
+    /// 买卖标志 ('0' 买 / '1' 卖)
+    pub direction_flag: char,
+
+    /// 投机套保标志
+    pub hedge_flag: HedgeFlag,
+
+    /// 成交类型（大宗交易单独标记）
+    pub trade_type: TradeType,
+
+    /// 撮合批次号（同一笔委托同一次撮合吃多档产生的成交相同，非撮合成交为 0）
+    pub match_batch_id: i64,
+}
+
 /// 交易所回报类型（推送给账户的5种回报）
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum ExchangeResponse {
@@ -111,6 +199,24 @@ pub struct ExchangeTradeRecord {
 
     /// 成交ID（自增i64）
     pub trade_id: i64,
+
+    /// 交易所成交编号
+    pub exchange_trade_id: String,
+
+    /// 成交序号（合约统一事件序列，同 trade_id）
+    pub sequence_no: i64,
+
+    /// 买方报单编号
+    pub buy_order_sys_id: String,
+
+    /// 卖方报单编号
+    pub sell_order_sys_id: String,
+
+    /// 成交时间 (HH:MM:SS.mmm，交易所时钟)
+    pub trade_time: String,
+
+    /// 撮合批次号
+    pub match_batch_id: i64,
 }
 
 impl ExchangeResponse {
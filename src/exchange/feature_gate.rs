@@ -0,0 +1,292 @@
+//! 功能灰度开关 (Feature Gate)
+//! @yutiansut @quantaxis
+//!
+//! 新撮合路径、新风控规则先对少量账户放量验证，再逐步全量：
+//! - 每个 feature 独立配置：总开关、黑名单、白名单、账户标签、放量百分比，按此顺序判定
+//! - 百分比分流按 `hash(feature, user_id)` 落入 0~9999 的固定分桶，同一账户决策稳定；
+//!   调大百分比时已放量的账户保持启用（分桶单调）
+//! - 配置可热更新（管理端 PUT），调用点在订单生命周期开始时做一次决策并随订单保存，
+//!   之后撤单等操作沿用下单时的决策，避免同一订单跨两条路径
+//! - 对照指标：两条路径的延迟与错误按 `{feature, path}` 暴露到 Prometheus
+//!   （`path` 为 `enabled` / `disabled`）
+
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::observability::metrics::{
+    FEATURE_GATE_DECISIONS_TOTAL, FEATURE_PATH_ERRORS_TOTAL, FEATURE_PATH_LATENCY_US,
+};
+use crate::ExchangeError;
+
+/// 高性能撮合路径（`matching::high_perf` 的池化订单撮合）
+pub const USE_HIGH_PERF_MATCHING: &str = "use_high_perf_matching";
+
+/// 百分比分流的分桶数（精度 0.01%）
+const BUCKETS: u64 = 10_000;
+
+lazy_static::lazy_static! {
+    /// 全局功能开关（订单路由等调用点共用，管理端热更新）
+    pub static ref FEATURE_GATE: Arc<FeatureGate> = Arc::new(FeatureGate::default());
+}
+
+/// 单个 feature 的放量规则
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FeatureRule {
+    /// 总开关（关闭时所有账户走原路径，白名单也不生效）
+    #[serde(default)]
+    pub enabled: bool,
+    /// 放量百分比 (0.0 - 100.0)
+    #[serde(default)]
+    pub percentage: f64,
+    /// 白名单账户（始终启用）
+    #[serde(default)]
+    pub whitelist: Vec<String>,
+    /// 黑名单账户（始终不启用，优先于白名单）
+    #[serde(default)]
+    pub blacklist: Vec<String>,
+    /// 带有任一标签的账户启用
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// 功能开关配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FeatureGateConfig {
+    /// feature 名称 -> 放量规则
+    #[serde(default)]
+    pub features: HashMap<String, FeatureRule>,
+    /// 账户 -> 标签（如 internal、market_maker）
+    #[serde(default)]
+    pub account_tags: HashMap<String, Vec<String>>,
+}
+
+impl FeatureGateConfig {
+    pub fn validate(&self) -> Result<(), ExchangeError> {
+        for (feature, rule) in &self.features {
+            if !(0.0..=100.0).contains(&rule.percentage) {
+                return Err(ExchangeError::InvalidParameter(format!(
+                    "Feature {} percentage must be in [0, 100], got {}",
+                    feature, rule.percentage
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 账户在某个 feature 上的分桶 (0 ~ 9999)
+///
+/// FNV-1a 哈希 + fmix64 混合（FNV 低位分布差），跨进程/重启稳定；
+/// feature 参与哈希，不同 feature 的放量账户相互独立
+pub fn bucket_of(feature: &str, user_id: &str) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in feature
+        .as_bytes()
+        .iter()
+        .chain(b":")
+        .chain(user_id.as_bytes())
+    {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    hash ^= hash >> 33;
+    hash % BUCKETS
+}
+
+fn path_label(enabled: bool) -> &'static str {
+    if enabled {
+        "enabled"
+    } else {
+        "disabled"
+    }
+}
+
+/// 功能开关
+#[derive(Default)]
+pub struct FeatureGate {
+    config: RwLock<FeatureGateConfig>,
+}
+
+impl FeatureGate {
+    pub fn new(config: FeatureGateConfig) -> Result<Self, ExchangeError> {
+        config.validate()?;
+        Ok(Self {
+            config: RwLock::new(config),
+        })
+    }
+
+    /// 当前配置
+    pub fn config(&self) -> FeatureGateConfig {
+        self.config.read().clone()
+    }
+
+    /// 热更新配置（只影响之后的决策，已下单的订单沿用原决策）
+    pub fn update_config(&self, config: FeatureGateConfig) -> Result<(), ExchangeError> {
+        config.validate()?;
+        log::info!(
+            "[FeatureGate] Config updated: {}",
+            config
+                .features
+                .iter()
+                .map(|(name, rule)| format!("{}={}/{}%", name, rule.enabled, rule.percentage))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        *self.config.write() = config;
+        Ok(())
+    }
+
+    /// 判定 feature 对账户是否启用，并计入决策指标
+    pub fn is_enabled(&self, feature: &str, user_id: &str) -> bool {
+        let enabled = self.evaluate(feature, user_id);
+        FEATURE_GATE_DECISIONS_TOTAL
+            .with_label_values(&[feature, path_label(enabled)])
+            .inc();
+        enabled
+    }
+
+    /// 判定 feature 对账户是否启用（不计指标）
+    pub fn evaluate(&self, feature: &str, user_id: &str) -> bool {
+        let config = self.config.read();
+        let Some(rule) = config.features.get(feature) else {
+            return false;
+        };
+        if !rule.enabled || rule.blacklist.iter().any(|a| a == user_id) {
+            return false;
+        }
+        if rule.whitelist.iter().any(|a| a == user_id) {
+            return true;
+        }
+        if let Some(tags) = config.account_tags.get(user_id) {
+            if tags.iter().any(|t| rule.tags.contains(t)) {
+                return true;
+            }
+        }
+        (bucket_of(feature, user_id) as f64) < rule.percentage / 100.0 * BUCKETS as f64
+    }
+
+    /// 记录一次路径执行结果（对照两条路径的延迟与错误率）
+    pub fn observe(&self, feature: &str, enabled: bool, latency: Duration, ok: bool) {
+        let path = path_label(enabled);
+        FEATURE_PATH_LATENCY_US
+            .with_label_values(&[feature, path])
+            .observe(latency.as_secs_f64() * 1_000_000.0);
+        if !ok {
+            FEATURE_PATH_ERRORS_TOTAL
+                .with_label_values(&[feature, path])
+                .inc();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gate(rule: FeatureRule) -> FeatureGate {
+        let mut features = HashMap::new();
+        features.insert(USE_HIGH_PERF_MATCHING.to_string(), rule);
+        let mut account_tags = HashMap::new();
+        account_tags.insert("u_internal".to_string(), vec!["internal".to_string()]);
+        FeatureGate::new(FeatureGateConfig {
+            features,
+            account_tags,
+        })
+        .unwrap()
+    }
+
+    fn enabled_count(gate: &FeatureGate, users: usize) -> usize {
+        (0..users)
+            .filter(|i| gate.evaluate(USE_HIGH_PERF_MATCHING, &format!("user_{}", i)))
+            .count()
+    }
+
+    /// 按百分比分流：放量比例接近配置值，各分段分布均匀，调大比例时已放量账户保持启用
+    #[test]
+    fn test_percentage_rollout_is_uniform() {
+        let users = 100_000;
+        for percentage in [1.0, 10.0, 50.0] {
+            let gate = gate(FeatureRule {
+                enabled: true,
+                percentage,
+                ..Default::default()
+            });
+            let ratio = enabled_count(&gate, users) as f64 / users as f64 * 100.0;
+            assert!(
+                (ratio - percentage).abs() < percentage * 0.1 + 0.1,
+                "{}% rollout enabled {:.3}%",
+                percentage,
+                ratio
+            );
+        }
+
+        // 分桶均匀：10 个分段各占约 10%
+        let mut segments = [0usize; 10];
+        for i in 0..users {
+            let bucket = bucket_of(USE_HIGH_PERF_MATCHING, &format!("user_{}", i));
+            segments[(bucket / (BUCKETS / 10)) as usize] += 1;
+        }
+        for count in segments {
+            let share = count as f64 / users as f64;
+            assert!((share - 0.1).abs() < 0.01, "segment share {:.4}", share);
+        }
+
+        // 单调：1% 放量的账户在 10% 时仍启用
+        let one = gate(FeatureRule {
+            enabled: true,
+            percentage: 1.0,
+            ..Default::default()
+        });
+        let ten = gate(FeatureRule {
+            enabled: true,
+            percentage: 10.0,
+            ..Default::default()
+        });
+        for i in 0..10_000 {
+            let user = format!("user_{}", i);
+            if one.evaluate(USE_HIGH_PERF_MATCHING, &user) {
+                assert!(ten.evaluate(USE_HIGH_PERF_MATCHING, &user));
+            }
+        }
+    }
+
+    #[test]
+    fn test_whitelist_blacklist_tags_and_hot_update() {
+        let gate = gate(FeatureRule {
+            enabled: true,
+            percentage: 0.0,
+            whitelist: vec!["vip".to_string(), "both".to_string()],
+            blacklist: vec!["both".to_string()],
+            tags: vec!["internal".to_string()],
+        });
+        assert!(gate.is_enabled(USE_HIGH_PERF_MATCHING, "vip"));
+        assert!(!gate.is_enabled(USE_HIGH_PERF_MATCHING, "both"));
+        assert!(gate.is_enabled(USE_HIGH_PERF_MATCHING, "u_internal"));
+        assert!(!gate.is_enabled(USE_HIGH_PERF_MATCHING, "someone"));
+        assert!(!gate.is_enabled("unknown_feature", "vip"));
+
+        // 热更新：关闭总开关后白名单也不生效；非法百分比被拒绝且不影响当前配置
+        let mut config = gate.config();
+        config
+            .features
+            .get_mut(USE_HIGH_PERF_MATCHING)
+            .unwrap()
+            .enabled = false;
+        gate.update_config(config.clone()).unwrap();
+        assert!(!gate.is_enabled(USE_HIGH_PERF_MATCHING, "vip"));
+
+        config.features.get_mut(USE_HIGH_PERF_MATCHING).unwrap().percentage = 120.0;
+        assert!(gate.update_config(config).is_err());
+        assert_eq!(
+            gate.config().features[USE_HIGH_PERF_MATCHING].percentage,
+            0.0
+        );
+    }
+}
@@ -0,0 +1,992 @@
+//! 资金定点核算
+//! @yutiansut @quantaxis
+//!
+//! QA_Account 内部以 f64 累加余额、手续费、平仓盈亏，长期运行后会出现 1e-9 级别的漂移。
+//! 本模块维护一份 i64 定点台账（默认精度 0.0001 元，即 0.01 分），资金以台账为准：
+//!
+//! - **成交**（平仓盈亏、手续费、成交金额）：由成交价量、成交前后的持仓成本与本笔手续费
+//!   直接换算为定点单位（[`FixedFill`]），整数累加，不取浮点账户差额
+//! - **持仓状态**（持仓盈亏、保证金、冻结）：逐持仓换算为定点单位后整数求和（[`FixedMarks`]）
+//! - **其他资金变动**（入金、出金、利息等非成交事件）：同步时取浮点累计量增量，单次换算后累加
+//! - **权益** = 期初权益 + 入金 - 出金 + 平仓盈亏 - 手续费 + 持仓盈亏（整数运算）
+//!
+//! 每次更新后由定点台账换算并写回 QA_Account 的平仓盈亏、手续费、持仓盈亏与权益，
+//! 可用资金按同一换算差额修正，浮点字段只是定点值的视图，漂移不会累积。
+//! 对外通知同样由定点整数换算（同一整数总是得到同一浮点，结果可重现）；
+//! WAL 新版本记录（`AccountUpdateV2` / `TradeExecutedV2`）直接写入整数。
+//!
+//! 双轨验证：写回前比对 qars 浮点核算结果与定点台账，差异超过容差时告警并记录。
+
+use dashmap::DashMap;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::core::QA_Account;
+use crate::exchange::position_cost::PositionCost;
+use crate::ExchangeError;
+
+/// 默认定点小数位数（0.0001 元 = 0.01 分）
+pub const DEFAULT_AMOUNT_DECIMALS: u32 = 4;
+
+/// 最大定点小数位数
+///
+/// 6 位时 i64 可表示约 ±9.2e12 元，9e9 元以内的金额换算在 f64 精确整数范围（2^53）内；
+/// 超出 i64 范围的换算与乘积返回错误，不做饱和或回绕
+pub const MAX_AMOUNT_DECIMALS: u32 = 6;
+
+/// 保留的最近差异记录数
+const MAX_RECENT_DRIFTS: usize = 100;
+
+/// 定点精度（金额 × 10^decimals 取整）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AmountScale {
+    decimals: u32,
+    factor: i64,
+}
+
+impl AmountScale {
+    /// 超过 [`MAX_AMOUNT_DECIMALS`] 时按最大精度处理
+    pub fn new(decimals: u32) -> Self {
+        let decimals = decimals.min(MAX_AMOUNT_DECIMALS);
+        Self {
+            decimals,
+            factor: 10i64.pow(decimals),
+        }
+    }
+
+    pub fn decimals(&self) -> u32 {
+        self.decimals
+    }
+
+    /// 每元对应的定点单位数
+    pub fn factor(&self) -> i64 {
+        self.factor
+    }
+
+    /// 金额换算为定点单位（四舍五入，NaN 记 0），超出 i64 范围时返回错误
+    pub fn to_units(&self, amount: f64) -> Result<i64, ExchangeError> {
+        if amount.is_nan() {
+            return Ok(0);
+        }
+        let units = (amount * self.factor as f64).round();
+        // i64::MAX 换算为 f64 后为 2^63，不在 i64 范围内
+        if units.abs() >= i64::MAX as f64 {
+            return Err(self.overflow(amount));
+        }
+        Ok(units as i64)
+    }
+
+    /// 定点单位换算为金额（同一整数总是得到同一浮点）
+    pub fn to_f64(&self, units: i64) -> f64 {
+        units as f64 / self.factor as f64
+    }
+
+    /// 价格 × 数量 × 合约乘数的定点金额
+    ///
+    /// 数量 × 乘数为整数时先换算价格再整数相乘（结果精确），否则整体换算一次
+    pub fn amount_units(
+        &self,
+        price: f64,
+        volume: f64,
+        multiplier: f64,
+    ) -> Result<i64, ExchangeError> {
+        let quantity = volume * multiplier;
+        if quantity.fract() == 0.0 && quantity.abs() < (1u64 << 53) as f64 {
+            self.to_units(price)?
+                .checked_mul(quantity as i64)
+                .ok_or_else(|| self.overflow(price * quantity))
+        } else {
+            self.to_units(price * quantity)
+        }
+    }
+
+    fn overflow(&self, amount: f64) -> ExchangeError {
+        ExchangeError::InvalidParameter(format!(
+            "Amount {} exceeds fixed-point range at {} decimals",
+            amount, self.decimals
+        ))
+    }
+}
+
+impl Default for AmountScale {
+    fn default() -> Self {
+        Self::new(DEFAULT_AMOUNT_DECIMALS)
+    }
+}
+
+/// 定点核算配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixedPointConfig {
+    /// 定点小数位数（4 = 0.0001 元）
+    #[serde(default = "default_decimals")]
+    pub decimals: u32,
+    /// 双轨验证：同步时比对浮点账户与定点台账
+    #[serde(default = "default_dual_track")]
+    pub dual_track: bool,
+    /// 双轨差异告警阈值（定点单位，差额绝对值超过该值告警）
+    #[serde(default = "default_drift_tolerance")]
+    pub drift_tolerance: i64,
+}
+
+fn default_decimals() -> u32 {
+    DEFAULT_AMOUNT_DECIMALS
+}
+
+fn default_dual_track() -> bool {
+    true
+}
+
+fn default_drift_tolerance() -> i64 {
+    1
+}
+
+impl Default for FixedPointConfig {
+    fn default() -> Self {
+        Self {
+            decimals: default_decimals(),
+            dual_track: default_dual_track(),
+            drift_tolerance: default_drift_tolerance(),
+        }
+    }
+}
+
+impl FixedPointConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.decimals > MAX_AMOUNT_DECIMALS {
+            return Err(format!(
+                "fixed_point.decimals must be <= {}",
+                MAX_AMOUNT_DECIMALS
+            ));
+        }
+        if self.drift_tolerance < 0 {
+            return Err("fixed_point.drift_tolerance must be >= 0".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn scale(&self) -> AmountScale {
+        AmountScale::new(self.decimals)
+    }
+}
+
+/// 浮点账户资金快照（取自 QA_Account）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct FloatBalances {
+    pub pre_balance: f64,
+    pub deposit: f64,
+    pub withdraw: f64,
+    pub close_profit: f64,
+    pub commission: f64,
+    pub position_profit: f64,
+    /// 持仓保证金
+    pub margin: f64,
+    /// 冻结保证金（挂单）
+    pub frozen: f64,
+    pub balance: f64,
+    pub available: f64,
+}
+
+impl FloatBalances {
+    /// 采集账户当前资金（保证金需动态计算，因此需要可变引用）
+    pub fn from_account(acc: &mut QA_Account) -> Self {
+        Self {
+            pre_balance: acc.accounts.pre_balance,
+            deposit: acc.accounts.deposit,
+            withdraw: acc.accounts.withdraw,
+            close_profit: acc.accounts.close_profit,
+            commission: acc.accounts.commission,
+            position_profit: acc.accounts.position_profit,
+            margin: acc.get_margin(),
+            frozen: acc.get_frozen_margin(),
+            balance: acc.accounts.balance,
+            available: acc.money,
+        }
+    }
+}
+
+/// 单笔成交的定点金额（定点单位）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FixedFill {
+    /// 平仓盈亏（逐日盯市口径，按所平部分的持仓成本）
+    pub close_profit: i64,
+    pub commission: i64,
+    /// 成交金额
+    pub turnover: i64,
+}
+
+impl FixedFill {
+    /// 由成交价量与成交前后的持仓成本换算
+    ///
+    /// `before` / `after` 为成交前后的持仓成本（不含合约乘数），平仓盈亏 =
+    /// 平仓金额 - 所平部分的持仓成本（多头），空头取反
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_trade(
+        scale: AmountScale,
+        before: &PositionCost,
+        after: &PositionCost,
+        towards: i32,
+        price: f64,
+        volume: f64,
+        multiplier: f64,
+        commission: f64,
+    ) -> Result<Self, ExchangeError> {
+        let close_profit = match towards {
+            -3 | -4 | 3 | 4 => {
+                let (side_before, side_after) = if towards < 0 {
+                    (&before.long, &after.long)
+                } else {
+                    (&before.short, &after.short)
+                };
+                let closed = side_before.volume() - side_after.volume();
+                let cost = (side_before.position_cost_today + side_before.position_cost_his)
+                    - (side_after.position_cost_today + side_after.position_cost_his);
+                let profit = checked_sum(&[
+                    scale.amount_units(price, closed, multiplier)?,
+                    -scale.to_units(cost * multiplier)?,
+                ])?;
+                if towards < 0 {
+                    profit
+                } else {
+                    -profit
+                }
+            }
+            _ => 0,
+        };
+        Ok(Self {
+            close_profit,
+            commission: scale.to_units(commission)?,
+            turnover: scale.amount_units(price, volume, multiplier)?,
+        })
+    }
+}
+
+/// 持仓状态的定点值（定点单位，逐持仓换算后整数求和）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FixedMarks {
+    pub position_profit: i64,
+    pub margin: i64,
+    pub frozen: i64,
+}
+
+impl FixedMarks {
+    /// 按持仓最新价与持仓成本计算持仓盈亏（无行情时记 0），保证金逐持仓换算
+    pub fn from_account(scale: AmountScale, acc: &mut QA_Account) -> Result<Self, ExchangeError> {
+        let mut marks = Self {
+            frozen: scale.to_units(acc.get_frozen_margin())?,
+            ..Default::default()
+        };
+        for pos in acc.hold.values() {
+            let multiplier = pos.preset.unit_table.max(1) as f64;
+            if pos.lastest_price > 0.0 {
+                let long = pos.volume_long_today + pos.volume_long_his;
+                let short = pos.volume_short_today + pos.volume_short_his;
+                marks.position_profit = checked_sum(&[
+                    marks.position_profit,
+                    scale.amount_units(pos.lastest_price, long, multiplier)?,
+                    -scale.to_units(pos.position_cost_long)?,
+                    scale.to_units(pos.position_cost_short)?,
+                    -scale.amount_units(pos.lastest_price, short, multiplier)?,
+                ])?;
+            }
+            marks.margin = checked_sum(&[
+                marks.margin,
+                scale.to_units(pos.margin_long)?,
+                scale.to_units(pos.margin_short)?,
+            ])?;
+        }
+        Ok(marks)
+    }
+
+    /// 按浮点快照换算（无持仓明细时使用）
+    pub fn from_float(scale: AmountScale, float: &FloatBalances) -> Result<Self, ExchangeError> {
+        Ok(Self {
+            position_profit: scale.to_units(float.position_profit)?,
+            margin: scale.to_units(float.margin)?,
+            frozen: scale.to_units(float.frozen)?,
+        })
+    }
+}
+
+/// 账户定点台账（单位：定点单位）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FixedAccount {
+    pub pre_balance: i64,
+    pub deposit: i64,
+    pub withdraw: i64,
+    pub close_profit: i64,
+    pub commission: i64,
+    pub position_profit: i64,
+    pub margin: i64,
+    pub frozen: i64,
+    /// 当日成交金额
+    pub turnover: i64,
+    /// 最近一次同步的浮点快照（计算累计量增量）
+    #[serde(skip)]
+    last: FloatBalances,
+}
+
+impl FixedAccount {
+    /// 按浮点快照建账
+    fn from_float(scale: AmountScale, float: &FloatBalances) -> Result<Self, ExchangeError> {
+        let mut account = Self {
+            pre_balance: scale.to_units(float.pre_balance)?,
+            deposit: scale.to_units(float.deposit)?,
+            withdraw: scale.to_units(float.withdraw)?,
+            close_profit: scale.to_units(float.close_profit)?,
+            commission: scale.to_units(float.commission)?,
+            last: *float,
+            ..Default::default()
+        };
+        account.set_state(&FixedMarks::from_float(scale, float)?);
+        Ok(account)
+    }
+
+    /// 静态权益 = 期初权益 + 入金 - 出金 + 平仓盈亏 - 手续费
+    pub fn static_balance(&self) -> i64 {
+        self.pre_balance + self.deposit - self.withdraw + self.close_profit - self.commission
+    }
+
+    /// 动态权益 = 静态权益 + 持仓盈亏
+    pub fn balance(&self) -> i64 {
+        self.static_balance() + self.position_profit
+    }
+
+    /// 可用资金 = 动态权益 - 保证金 - 冻结
+    pub fn available(&self) -> i64 {
+        self.balance() - self.margin - self.frozen
+    }
+
+    /// 浮点累计量回退（外部结算、账户重置）时无法按增量同步
+    fn is_reset(&self, float: &FloatBalances) -> bool {
+        float.deposit < self.last.deposit
+            || float.withdraw < self.last.withdraw
+            || float.commission < self.last.commission
+    }
+
+    /// 非成交资金变动：累计量按浮点增量换算后整数累加，持仓状态取定点值
+    fn sync(
+        &mut self,
+        scale: AmountScale,
+        float: &FloatBalances,
+        marks: &FixedMarks,
+    ) -> Result<(), ExchangeError> {
+        let deposit = scale.to_units(float.deposit - self.last.deposit)?;
+        let withdraw = scale.to_units(float.withdraw - self.last.withdraw)?;
+        let close_profit = scale.to_units(float.close_profit - self.last.close_profit)?;
+        let commission = scale.to_units(float.commission - self.last.commission)?;
+        self.deposit = checked_sum(&[self.deposit, deposit])?;
+        self.withdraw = checked_sum(&[self.withdraw, withdraw])?;
+        self.close_profit = checked_sum(&[self.close_profit, close_profit])?;
+        self.commission = checked_sum(&[self.commission, commission])?;
+        self.set_state(marks);
+        self.last = *float;
+        Ok(())
+    }
+
+    /// 成交：定点金额整数累加，`float` 为成交后的浮点快照（其中本笔成交的浮点增量不再计入）
+    fn apply_fill(
+        &mut self,
+        fill: &FixedFill,
+        float: &FloatBalances,
+        marks: &FixedMarks,
+    ) -> Result<(), ExchangeError> {
+        let close_profit = checked_sum(&[self.close_profit, fill.close_profit])?;
+        let commission = checked_sum(&[self.commission, fill.commission])?;
+        let turnover = checked_sum(&[self.turnover, fill.turnover])?;
+        self.close_profit = close_profit;
+        self.commission = commission;
+        self.turnover = turnover;
+        self.set_state(marks);
+        self.last = *float;
+        Ok(())
+    }
+
+    fn set_state(&mut self, marks: &FixedMarks) {
+        self.position_profit = marks.position_profit;
+        self.margin = marks.margin;
+        self.frozen = marks.frozen;
+    }
+
+    /// 由定点值换算写回浮点账户（期初权益、平仓盈亏、手续费、持仓盈亏、权益），
+    /// 可用资金按平仓盈亏与手续费的换算差额修正
+    pub fn write_to(&self, scale: AmountScale, acc: &mut QA_Account) {
+        let close_profit = scale.to_f64(self.close_profit);
+        let commission = scale.to_f64(self.commission);
+        acc.money +=
+            (close_profit - acc.accounts.close_profit) - (commission - acc.accounts.commission);
+        acc.accounts.pre_balance = scale.to_f64(self.pre_balance);
+        acc.accounts.close_profit = close_profit;
+        acc.accounts.commission = commission;
+        acc.accounts.position_profit = scale.to_f64(self.position_profit);
+        acc.accounts.balance = scale.to_f64(self.balance());
+    }
+
+    /// 日终结转：当日定点权益作为下一日期初权益，累计量从结算后快照重新开始
+    fn roll_over(
+        &mut self,
+        scale: AmountScale,
+        float: &FloatBalances,
+    ) -> Result<(), ExchangeError> {
+        let marks = FixedMarks::from_float(scale, float)?;
+        let deposit = scale.to_units(float.deposit)?;
+        let withdraw = scale.to_units(float.withdraw)?;
+        let close_profit = scale.to_units(float.close_profit)?;
+        let commission = scale.to_units(float.commission)?;
+        self.pre_balance = self.balance();
+        self.deposit = deposit;
+        self.withdraw = withdraw;
+        self.close_profit = close_profit;
+        self.commission = commission;
+        self.turnover = 0;
+        self.set_state(&marks);
+        self.last = *float;
+        Ok(())
+    }
+
+    /// 浮点形式的对外视图
+    pub fn view(&self, scale: AmountScale) -> FixedAccountView {
+        FixedAccountView {
+            pre_balance: scale.to_f64(self.pre_balance),
+            static_balance: scale.to_f64(self.static_balance()),
+            balance: scale.to_f64(self.balance()),
+            available: scale.to_f64(self.available()),
+            deposit: scale.to_f64(self.deposit),
+            withdraw: scale.to_f64(self.withdraw),
+            close_profit: scale.to_f64(self.close_profit),
+            commission: scale.to_f64(self.commission),
+            position_profit: scale.to_f64(self.position_profit),
+            margin: scale.to_f64(self.margin),
+            frozen: scale.to_f64(self.frozen),
+            turnover: scale.to_f64(self.turnover),
+        }
+    }
+}
+
+/// 定点单位求和（溢出返回错误）
+fn checked_sum(parts: &[i64]) -> Result<i64, ExchangeError> {
+    parts
+        .iter()
+        .try_fold(0i64, |sum, part| sum.checked_add(*part))
+        .ok_or_else(|| {
+            ExchangeError::InvalidParameter(format!("Fixed-point sum of {:?} overflows i64", parts))
+        })
+}
+
+/// 定点台账的浮点视图（通知、查询输出）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct FixedAccountView {
+    pub pre_balance: f64,
+    pub static_balance: f64,
+    pub balance: f64,
+    pub available: f64,
+    pub deposit: f64,
+    pub withdraw: f64,
+    pub close_profit: f64,
+    pub commission: f64,
+    pub position_profit: f64,
+    pub margin: f64,
+    pub frozen: f64,
+    pub turnover: f64,
+}
+
+/// 双轨差异记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixedDrift {
+    pub account_id: String,
+    /// 差异字段（balance / available / close_profit / commission）
+    pub field: String,
+    pub float_value: f64,
+    pub fixed_value: f64,
+    /// 差额（浮点 - 定点，定点单位）
+    pub difference: i64,
+    pub detected_at: i64,
+}
+
+/// 双轨验证统计
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixedDriftStats {
+    pub dual_track: bool,
+    pub decimals: u32,
+    pub accounts: usize,
+    /// 累计差异告警次数
+    pub drift_count: u64,
+    /// 最近差异记录（新在后）
+    pub recent: Vec<FixedDrift>,
+}
+
+/// 定点台账 (account_id -> 定点资金)
+#[derive(Default)]
+pub struct FixedLedger {
+    config: RwLock<FixedPointConfig>,
+    accounts: DashMap<String, FixedAccount>,
+    drift_count: AtomicU64,
+    recent_drifts: Mutex<VecDeque<FixedDrift>>,
+}
+
+impl FixedLedger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 更新配置（精度变化时清空台账，下次同步按浮点快照重新建账）
+    pub fn configure(&self, config: FixedPointConfig) {
+        let mut current = self.config.write();
+        if current.decimals != config.decimals {
+            self.accounts.clear();
+        }
+        *current = config;
+    }
+
+    pub fn config(&self) -> FixedPointConfig {
+        self.config.read().clone()
+    }
+
+    pub fn scale(&self) -> AmountScale {
+        self.config.read().scale()
+    }
+
+    /// 确保账户已建账（成交入账前调用，首次按当前浮点快照建账）
+    pub fn open(&self, account_id: &str, float: &FloatBalances) -> Result<(), ExchangeError> {
+        let scale = self.scale();
+        self.accounts
+            .entry(account_id.to_string())
+            .or_try_insert_with(|| FixedAccount::from_float(scale, float))?;
+        Ok(())
+    }
+
+    /// 同步非成交资金变动与持仓状态并返回定点台账（首次同步建账），双轨验证开启时比对差异
+    pub fn sync(
+        &self,
+        account_id: &str,
+        float: &FloatBalances,
+        marks: &FixedMarks,
+    ) -> Result<FixedAccount, ExchangeError> {
+        let config = self.config();
+        let scale = config.scale();
+        let account = {
+            let mut entry = self
+                .accounts
+                .entry(account_id.to_string())
+                .or_try_insert_with(|| FixedAccount::from_float(scale, float))?;
+            if entry.is_reset(float) {
+                log::warn!(
+                    "[FixedPoint] {} float accumulators went backwards, rebuilding ledger",
+                    account_id
+                );
+                *entry = FixedAccount::from_float(scale, float)?;
+            } else {
+                entry.sync(scale, float, marks)?;
+            }
+            entry.clone()
+        };
+        if config.dual_track {
+            self.verify(account_id, &account, float, &config);
+        }
+        Ok(account)
+    }
+
+    /// 成交入账：定点金额整数累加（`float` 为成交后的浮点快照），双轨验证开启时比对差异
+    pub fn apply_fill(
+        &self,
+        account_id: &str,
+        fill: &FixedFill,
+        float: &FloatBalances,
+        marks: &FixedMarks,
+    ) -> Result<FixedAccount, ExchangeError> {
+        let config = self.config();
+        let scale = config.scale();
+        let account = {
+            let mut entry = self
+                .accounts
+                .entry(account_id.to_string())
+                .or_try_insert_with(|| FixedAccount::from_float(scale, float))?;
+            entry.apply_fill(fill, float, marks)?;
+            entry.clone()
+        };
+        if config.dual_track {
+            self.verify(account_id, &account, float, &config);
+        }
+        Ok(account)
+    }
+
+    /// 同步账户并以定点台账为准写回浮点字段
+    pub fn sync_account(
+        &self,
+        account_id: &str,
+        acc: &mut QA_Account,
+    ) -> Result<FixedAccount, ExchangeError> {
+        let float = FloatBalances::from_account(acc);
+        let marks = FixedMarks::from_account(self.scale(), acc)?;
+        let account = self.sync(account_id, &float, &marks)?;
+        Ok(self.write_back(account_id, account, acc))
+    }
+
+    /// 成交入账并以定点台账为准写回浮点字段（需先 [`FixedLedger::open`] 建账）
+    pub fn apply_account_fill(
+        &self,
+        account_id: &str,
+        fill: &FixedFill,
+        acc: &mut QA_Account,
+    ) -> Result<FixedAccount, ExchangeError> {
+        let float = FloatBalances::from_account(acc);
+        let marks = FixedMarks::from_account(self.scale(), acc)?;
+        let account = self.apply_fill(account_id, fill, &float, &marks)?;
+        Ok(self.write_back(account_id, account, acc))
+    }
+
+    /// 写回浮点账户，并以写回后的快照作为下次增量同步的基准
+    fn write_back(
+        &self,
+        account_id: &str,
+        account: FixedAccount,
+        acc: &mut QA_Account,
+    ) -> FixedAccount {
+        account.write_to(self.scale(), acc);
+        let float = FloatBalances::from_account(acc);
+        if let Some(mut entry) = self.accounts.get_mut(account_id) {
+            entry.last = float;
+        }
+        account
+    }
+
+    /// 日终结转（结算后调用，`float` 为结算后的账户快照）
+    pub fn roll_over(
+        &self,
+        account_id: &str,
+        float: &FloatBalances,
+    ) -> Result<FixedAccount, ExchangeError> {
+        let scale = self.scale();
+        let mut entry = self
+            .accounts
+            .entry(account_id.to_string())
+            .or_try_insert_with(|| FixedAccount::from_float(scale, float))?;
+        entry.roll_over(scale, float)?;
+        Ok(entry.clone())
+    }
+
+    /// 日终结转并写回浮点账户（期初权益取当日定点权益）
+    pub fn roll_over_account(
+        &self,
+        account_id: &str,
+        acc: &mut QA_Account,
+    ) -> Result<FixedAccount, ExchangeError> {
+        let account = self.roll_over(account_id, &FloatBalances::from_account(acc))?;
+        Ok(self.write_back(account_id, account, acc))
+    }
+
+    pub fn get(&self, account_id: &str) -> Option<FixedAccount> {
+        self.accounts.get(account_id).map(|a| a.clone())
+    }
+
+    pub fn view(&self, account_id: &str) -> Option<FixedAccountView> {
+        let scale = self.scale();
+        self.accounts.get(account_id).map(|a| a.view(scale))
+    }
+
+    /// 移除账户台账（销户）
+    pub fn remove_account(&self, account_id: &str) {
+        self.accounts.remove(account_id);
+    }
+
+    /// 比对浮点账户与定点台账，超过容差的字段记录并告警
+    fn verify(
+        &self,
+        account_id: &str,
+        fixed: &FixedAccount,
+        float: &FloatBalances,
+        config: &FixedPointConfig,
+    ) {
+        let scale = config.scale();
+        let fields = [
+            ("balance", float.balance, fixed.balance()),
+            ("available", float.available, fixed.available()),
+            ("close_profit", float.close_profit, fixed.close_profit),
+            ("commission", float.commission, fixed.commission),
+        ];
+        for (field, float_value, fixed_units) in fields {
+            let Some(difference) = scale
+                .to_units(float_value)
+                .ok()
+                .and_then(|float_units| float_units.checked_sub(fixed_units))
+            else {
+                log::warn!(
+                    "[FixedPoint] {}.{} float value {} is out of fixed-point range",
+                    account_id,
+                    field,
+                    float_value
+                );
+                continue;
+            };
+            if difference.abs() <= config.drift_tolerance {
+                continue;
+            }
+            log::warn!(
+                "[FixedPoint] Drift on {}.{}: float={} fixed={} diff={} units",
+                account_id,
+                field,
+                float_value,
+                scale.to_f64(fixed_units),
+                difference
+            );
+            self.drift_count.fetch_add(1, Ordering::Relaxed);
+            let mut recent = self.recent_drifts.lock();
+            if recent.len() >= MAX_RECENT_DRIFTS {
+                recent.pop_front();
+            }
+            recent.push_back(FixedDrift {
+                account_id: account_id.to_string(),
+                field: field.to_string(),
+                float_value,
+                fixed_value: scale.to_f64(fixed_units),
+                difference,
+                detected_at: chrono::Utc::now().timestamp_millis(),
+            });
+        }
+    }
+
+    /// 双轨验证统计
+    pub fn drift_stats(&self) -> FixedDriftStats {
+        let config = self.config();
+        FixedDriftStats {
+            dual_track: config.dual_track,
+            decimals: config.decimals,
+            accounts: self.accounts.len(),
+            drift_count: self.drift_count.load(Ordering::Relaxed),
+            recent: self.recent_drifts.lock().iter().cloned().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn snapshot(pre_balance: f64) -> FloatBalances {
+        FloatBalances {
+            pre_balance,
+            balance: pre_balance,
+            available: pre_balance,
+            ..Default::default()
+        }
+    }
+
+    /// 随机开平仓：定点台账逐笔按成交价量与持仓成本入账，最终与整数精确值比对
+    ///
+    /// 先生成 `distinct` 笔随机成交（经 `FixedFill::from_trade` 换算），再循环入账 `trades` 笔，
+    /// 一亿笔的累计量级在 debug 构建下也能在数秒内跑完
+    fn run_random_trades(trades: u64, distinct: usize, seed: u64) {
+        let scale = AmountScale::default();
+        let mut rng = StdRng::seed_from_u64(seed);
+        let float = snapshot(1_000_000.0);
+        let mut fixed = FixedAccount::from_float(scale, &float).unwrap();
+        let marks = FixedMarks::default();
+
+        // (定点成交, 平仓盈亏精确值（分）, 手续费精确值（分）)
+        let table: Vec<(FixedFill, i64, i64)> = (0..distinct)
+            .map(|_| {
+                // 开多后全部平仓：价格精确到分、合约乘数 10，单笔手续费 0~50 元
+                let open_cents = rng.gen_range(300_000i64..=500_000);
+                let close_cents = open_cents + rng.gen_range(-5_000i64..=5_000);
+                let volume = rng.gen_range(1i64..=10);
+                let commission_cents = rng.gen_range(0i64..=5_000);
+
+                let (open_price, close_price) =
+                    (open_cents as f64 / 100.0, close_cents as f64 / 100.0);
+                let mut before = PositionCost::default();
+                before.apply(2, open_price, volume as f64);
+                let mut after = before;
+                after.apply(-3, close_price, volume as f64);
+                let fill = FixedFill::from_trade(
+                    scale,
+                    &before,
+                    &after,
+                    -3,
+                    close_price,
+                    volume as f64,
+                    10.0,
+                    commission_cents as f64 / 100.0,
+                )
+                .unwrap();
+                let profit_cents = (close_cents - open_cents) * volume * 10;
+                (fill, profit_cents, commission_cents)
+            })
+            .collect();
+
+        // 精确值（分）
+        let mut exact_profit = 0i64;
+        let mut exact_commission = 0i64;
+        for (fill, profit_cents, commission_cents) in table.iter().cycle().take(trades as usize) {
+            exact_profit += profit_cents;
+            exact_commission += commission_cents;
+            fixed.apply_fill(fill, &float, &marks).unwrap();
+        }
+
+        let cents = scale.factor() / 100;
+        assert_eq!(fixed.close_profit, exact_profit * cents);
+        assert_eq!(fixed.commission, exact_commission * cents);
+        assert_eq!(
+            fixed.balance(),
+            1_000_000 * scale.factor() + (exact_profit - exact_commission) * cents
+        );
+        // 浮点视图由定点整数换算
+        let view = fixed.view(scale);
+        assert_eq!(view.close_profit, scale.to_f64(exact_profit * cents));
+        assert_eq!(view.commission, scale.to_f64(exact_commission * cents));
+    }
+
+    #[test]
+    fn test_scale_round_trip_is_reproducible() {
+        let scale = AmountScale::default();
+        assert_eq!(scale.to_units(0.1 + 0.2).unwrap(), 3_000);
+        assert_eq!(scale.to_units(-12.3457).unwrap(), -123_457);
+        assert_eq!(scale.to_f64(3_000), 0.3);
+        for units in [1i64, 999_999_999, -123_456_789, 1_000_000_000_000] {
+            assert_eq!(scale.to_units(scale.to_f64(units)).unwrap(), units);
+        }
+        assert_eq!(AmountScale::new(20).decimals(), MAX_AMOUNT_DECIMALS);
+    }
+
+    #[test]
+    fn test_out_of_range_amounts_are_rejected() {
+        let scale = AmountScale::new(MAX_AMOUNT_DECIMALS);
+        assert!(scale.to_units(9.0e12).is_ok());
+        assert!(scale.to_units(1.0e13).is_err());
+        assert!(scale.to_units(-1.0e13).is_err());
+        assert!(scale.to_units(f64::INFINITY).is_err());
+        // 价格可换算，但与数量相乘后溢出
+        assert!(scale.amount_units(5_000.0, 1.0e6, 10.0).is_ok());
+        assert!(scale.amount_units(5_000.0, 1.0e9, 1_000.0).is_err());
+
+        let mut fixed = FixedAccount::from_float(scale, &snapshot(0.0)).unwrap();
+        let fill = FixedFill {
+            turnover: i64::MAX / 2 + 1,
+            ..Default::default()
+        };
+        fixed
+            .apply_fill(&fill, &snapshot(0.0), &FixedMarks::default())
+            .unwrap();
+        assert!(fixed
+            .apply_fill(&fill, &snapshot(0.0), &FixedMarks::default())
+            .is_err());
+        assert_eq!(fixed.turnover, i64::MAX / 2 + 1);
+    }
+
+    #[test]
+    fn test_random_trades_accumulate_without_error() {
+        run_random_trades(1_000_000, 1_000_000, 7);
+    }
+
+    #[test]
+    fn test_hundred_million_random_trades_accumulate_without_error() {
+        run_random_trades(100_000_000, 4_096, 20240601);
+    }
+
+    #[test]
+    fn test_fill_close_profit_from_position_cost() {
+        let scale = AmountScale::default();
+        // 空头 3 手：2 手 @100.1、1 手 @100.4，买平 2 手 @99.95（先平昨仓口径同均价）
+        let mut before = PositionCost::default();
+        before.apply(-2, 100.1, 2.0);
+        before.apply(-2, 100.4, 1.0);
+        let mut after = before;
+        after.apply(3, 99.95, 2.0);
+        let fill =
+            FixedFill::from_trade(scale, &before, &after, 3, 99.95, 2.0, 10.0, 1.23).unwrap();
+        // 持仓均价 100.2：(100.2 - 99.95) × 2 × 10 = 5 元
+        assert_eq!(fill.close_profit, 50_000);
+        assert_eq!(fill.commission, 12_300);
+        assert_eq!(fill.turnover, 19_990_000);
+    }
+
+    #[test]
+    fn test_account_fill_writes_fixed_values_back() {
+        let ledger = FixedLedger::new();
+        let scale = ledger.scale();
+        let mut acc = QA_Account::new("acc", "default", "acc", 1_000_000.0, false, "sim");
+        acc.accounts.pre_balance = 1_000_000.0;
+        acc.accounts.deposit = 0.0;
+        acc.accounts.balance = 1_000_000.0;
+        acc.money = 1_000_000.0;
+        ledger
+            .open("acc", &FloatBalances::from_account(&mut acc))
+            .unwrap();
+
+        // 三笔平仓盈亏 0.1 元：浮点逐笔累加出现尾差，写回后与定点值一致
+        let fill = FixedFill {
+            close_profit: scale.to_units(0.1).unwrap(),
+            ..Default::default()
+        };
+        for _ in 0..3 {
+            acc.accounts.close_profit += 0.1;
+            acc.accounts.balance += 0.1;
+            acc.money += 0.1;
+            ledger.apply_account_fill("acc", &fill, &mut acc).unwrap();
+        }
+        assert_eq!(acc.accounts.close_profit, 0.3);
+        let fixed = ledger.get("acc").unwrap();
+        assert_eq!(fixed.close_profit, 3_000);
+        assert_eq!(acc.accounts.balance, scale.to_f64(fixed.balance()));
+
+        // 再次同步无增量
+        let synced = ledger.sync_account("acc", &mut acc).unwrap();
+        assert_eq!(synced.close_profit, 3_000);
+        assert_eq!(ledger.drift_stats().drift_count, 0);
+    }
+
+    #[test]
+    fn test_dual_track_records_drift() {
+        let ledger = FixedLedger::new();
+        let mut float = snapshot(100_000.0);
+        ledger.sync("acc", &float, &FixedMarks::default()).unwrap();
+
+        float.deposit = 500.0;
+        float.balance = 100_500.0;
+        float.available = 100_500.0;
+        let account = ledger.sync("acc", &float, &FixedMarks::default()).unwrap();
+        assert_eq!(account.balance(), 100_500 * 10_000);
+        assert_eq!(ledger.drift_stats().drift_count, 0);
+
+        // 浮点余额偏离 0.01 元
+        float.balance = 100_500.01;
+        float.available = 100_500.01;
+        ledger.sync("acc", &float, &FixedMarks::default()).unwrap();
+        let stats = ledger.drift_stats();
+        assert_eq!(stats.drift_count, 2);
+        assert_eq!(stats.recent[0].field, "balance");
+        assert_eq!(stats.recent[0].difference, 100);
+    }
+
+    #[test]
+    fn test_roll_over_carries_fixed_balance() {
+        let ledger = FixedLedger::new();
+        let mut float = snapshot(100_000.0);
+        ledger.sync("acc", &float, &FixedMarks::default()).unwrap();
+
+        float.close_profit = 1_000.0;
+        float.commission = 12.34;
+        ledger.sync("acc", &float, &FixedMarks::default()).unwrap();
+        ledger
+            .apply_fill(
+                "acc",
+                &FixedFill {
+                    turnover: 500_000_000,
+                    ..Default::default()
+                },
+                &float,
+                &FixedMarks::default(),
+            )
+            .unwrap();
+
+        // 结算后浮点累计量清零
+        let settled = snapshot(100_987.66);
+        let account = ledger.roll_over("acc", &settled).unwrap();
+        assert_eq!(account.pre_balance, 1_009_876_600);
+        assert_eq!(account.commission, 0);
+        assert_eq!(account.turnover, 0);
+        assert_eq!(ledger.view("acc").unwrap().balance, 100_987.66);
+    }
+}
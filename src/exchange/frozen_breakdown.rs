@@ -0,0 +1,208 @@
+//! 账户冻结资金明细
+//! @yutiansut @quantaxis
+//!
+//! 账户冻结记录（`QA_Account::frozen`，qars 订单ID → 冻结额）按来源拆分：
+//! - **挂单冻结**: 冻结记录能对应到路由器中未完结的订单，按订单列出冻结保证金与手续费
+//! - **出金冻结**: 出金申请占用的资金；当前出金即时完成，不产生冻结，分类保留以便接入审批流程
+//! - **其他冻结**: 找不到对应挂单的冻结记录（如恢复后重建、订单已完结但冻结未释放），需人工排查
+//!
+//! 明细之和与账户总冻结（`get_frozen_margin`）在同一把账户锁内读取，
+//! 两者差额超过 `RECONCILE_EPSILON` 即对账不平。
+//! 挂单冻结只含保证金：手续费在成交时扣除，挂单不冻结手续费，`commission` 恒为 0。
+
+use serde::{Deserialize, Serialize};
+
+/// 对账精度
+const RECONCILE_EPSILON: f64 = 1e-6;
+
+/// 冻结类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FrozenKind {
+    /// 挂单冻结
+    Order,
+    /// 出金申请冻结
+    Withdrawal,
+    /// 其他冻结
+    Other,
+}
+
+/// 账户冻结记录（qars 订单ID → 冻结手数、冻结金额）
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrozenRecord {
+    pub qa_order_id: String,
+    pub volume: f64,
+    pub money: f64,
+}
+
+/// 冻结记录对应的挂单
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrozenOrderRef {
+    pub order_id: String,
+    pub instrument_id: String,
+    pub direction: String,
+    pub offset: String,
+    pub price: f64,
+}
+
+/// 单条冻结明细
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrozenItem {
+    pub kind: FrozenKind,
+    /// qars 订单ID（账户冻结记录的键）
+    pub qa_order_id: String,
+    /// 对应的挂单（仅挂单冻结）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order: Option<FrozenOrderRef>,
+    /// 冻结手数（未成交剩余）
+    pub volume: f64,
+    /// 冻结保证金
+    pub margin: f64,
+    /// 冻结手续费
+    pub commission: f64,
+    /// 冻结合计
+    pub amount: f64,
+}
+
+/// 冻结汇总
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FrozenSummary {
+    pub order_margin: f64,
+    pub order_commission: f64,
+    pub withdrawal: f64,
+    pub other: f64,
+    /// 明细合计
+    pub total: f64,
+}
+
+/// 账户冻结明细
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrozenBreakdown {
+    pub account_id: String,
+    pub items: Vec<FrozenItem>,
+    pub summary: FrozenSummary,
+    /// 账户总冻结
+    pub account_frozen: f64,
+    /// 明细合计 − 账户总冻结
+    pub diff: f64,
+    pub reconciled: bool,
+    /// 查询时刻（毫秒）
+    pub timestamp: i64,
+}
+
+impl FrozenBreakdown {
+    /// 按冻结记录拆分，`lookup` 根据 qars 订单ID 查找未完结的挂单
+    pub fn build(
+        account_id: &str,
+        account_frozen: f64,
+        records: Vec<FrozenRecord>,
+        lookup: impl Fn(&str) -> Option<FrozenOrderRef>,
+        timestamp: i64,
+    ) -> Self {
+        let mut summary = FrozenSummary::default();
+        let mut items: Vec<FrozenItem> = records
+            .into_iter()
+            .map(|record| {
+                let order = lookup(&record.qa_order_id);
+                let kind = if order.is_some() {
+                    summary.order_margin += record.money;
+                    FrozenKind::Order
+                } else {
+                    summary.other += record.money;
+                    FrozenKind::Other
+                };
+                FrozenItem {
+                    kind,
+                    qa_order_id: record.qa_order_id,
+                    order,
+                    volume: record.volume,
+                    margin: record.money,
+                    commission: 0.0,
+                    amount: record.money,
+                }
+            })
+            .collect();
+        items.sort_by(|a, b| {
+            a.kind
+                .cmp_key()
+                .cmp(&b.kind.cmp_key())
+                .then_with(|| a.qa_order_id.cmp(&b.qa_order_id))
+        });
+
+        summary.total =
+            summary.order_margin + summary.order_commission + summary.withdrawal + summary.other;
+        let diff = summary.total - account_frozen;
+        if diff.abs() > RECONCILE_EPSILON {
+            log::warn!(
+                "Frozen breakdown of {} does not reconcile: items={:.2}, account={:.2}",
+                account_id,
+                summary.total,
+                account_frozen
+            );
+        }
+
+        Self {
+            account_id: account_id.to_string(),
+            items,
+            summary,
+            account_frozen,
+            diff,
+            reconciled: diff.abs() <= RECONCILE_EPSILON,
+            timestamp,
+        }
+    }
+}
+
+impl FrozenKind {
+    /// 明细排序：挂单、出金、其他
+    fn cmp_key(&self) -> u8 {
+        match self {
+            FrozenKind::Order => 0,
+            FrozenKind::Withdrawal => 1,
+            FrozenKind::Other => 2,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_classifies_and_reconciles() {
+        let record = |id: &str, volume: f64, money: f64| FrozenRecord {
+            qa_order_id: id.to_string(),
+            volume,
+            money,
+        };
+        let lookup = |id: &str| {
+            (id == "QA1").then(|| FrozenOrderRef {
+                order_id: "O1".to_string(),
+                instrument_id: "IF2501".to_string(),
+                direction: "BUY".to_string(),
+                offset: "OPEN".to_string(),
+                price: 100.0,
+            })
+        };
+
+        let breakdown = FrozenBreakdown::build(
+            "acc",
+            150.0,
+            vec![record("QA9", 1.0, 50.0), record("QA1", 2.0, 100.0)],
+            lookup,
+            0,
+        );
+        assert!(breakdown.reconciled);
+        assert_eq!(breakdown.items[0].kind, FrozenKind::Order);
+        assert_eq!(breakdown.items[0].order.as_ref().unwrap().order_id, "O1");
+        assert_eq!(breakdown.items[1].kind, FrozenKind::Other);
+        assert_eq!(breakdown.summary.order_margin, 100.0);
+        assert_eq!(breakdown.summary.other, 50.0);
+
+        // 账户总冻结与明细不一致
+        let broken =
+            FrozenBreakdown::build("acc", 120.0, vec![record("QA1", 2.0, 100.0)], lookup, 0);
+        assert!(!broken.reconciled);
+        assert!((broken.diff + 20.0).abs() < 1e-9);
+    }
+}
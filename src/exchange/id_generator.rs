@@ -1,10 +1,17 @@
 //! 交易所ID生成器
 //!
-//! 为每个instrument维护统一的事件序列（event sequence），保证事件顺序性
+//! 为每个instrument维护统一的事件序列（event sequence），保证事件顺序性；
+//! 同时生成对外成交回报使用的标准编号（成交编号、报单编号）并提供交易所统一时钟
 
 use dashmap::DashMap;
+use parking_lot::RwLock;
 use std::sync::atomic::{AtomicI64, Ordering};
 
+use crate::exchange::deterministic::{Clock, ExchangeClock};
+
+/// 标准编号中序号部分的位数（交易日 8 位 + 序号 12 位 = 20 位编号）
+const SYS_ID_SEQ_WIDTH: usize = 12;
+
 /// 交易所ID生成器
 ///
 /// 为每个instrument维护统一的event sequence，所有事件（下单、撤单、成交）共用同一个序列：
@@ -15,16 +22,42 @@ pub struct ExchangeIdGenerator {
     /// 事件序列计数器 (instrument_id -> AtomicI64)
     /// 所有事件（下单、撤单、成交）都用这个序列
     event_sequences: DashMap<String, AtomicI64>,
+
+    /// 全局成交编号序列（跨合约，交易日内唯一）
+    trade_sys_sequence: AtomicI64,
+
+    /// 交易所统一时钟：最近一次发出的纳秒时间戳（保证单调递增）
+    last_timestamp: AtomicI64,
+
+    /// 当前交易日 (YYYYMMDD)
+    trading_day: RwLock<String>,
+
+    /// 时间来源（确定性模式下为虚拟时钟）
+    clock: ExchangeClock,
 }
 
 impl ExchangeIdGenerator {
     /// 创建新的ID生成器
     pub fn new() -> Self {
+        Self::with_clock(ExchangeClock::System)
+    }
+
+    /// 使用指定时钟创建（交易日取时钟当前交易日）
+    pub fn with_clock(clock: ExchangeClock) -> Self {
         Self {
             event_sequences: DashMap::new(),
+            trade_sys_sequence: AtomicI64::new(0),
+            last_timestamp: AtomicI64::new(0),
+            trading_day: RwLock::new(clock.trading_day().format("%Y%m%d").to_string()),
+            clock,
         }
     }
 
+    /// 时间来源
+    pub fn clock(&self) -> &ExchangeClock {
+        &self.clock
+    }
+
     /// 生成下一个事件序列号（统一序列）
     ///
     /// # 参数
@@ -53,6 +86,72 @@ impl ExchangeIdGenerator {
             .map(|counter| counter.load(Ordering::SeqCst))
             .unwrap_or(0)
     }
+
+    /// 交易所统一时钟（纳秒）
+    ///
+    /// 所有成交时间都取自这里，严格单调递增：同一纳秒内的多个事件依次顺延 1ns
+    pub fn now_nanos(&self) -> i64 {
+        let wall = self.clock.now_nanos();
+        let prev = self
+            .last_timestamp
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |last| {
+                Some(wall.max(last + 1))
+            })
+            .unwrap_or(0);
+        wall.max(prev + 1)
+    }
+
+    /// 分配撮合批次号
+    ///
+    /// 每次订单簿撮合调用分配一个，取自统一时钟：严格递增且重启后不会与历史批次重复
+    pub fn next_match_batch_id(&self) -> i64 {
+        self.now_nanos()
+    }
+
+    /// 设置交易日（结算换日时调用），成交编号序列随之重置
+    pub fn set_trading_day(&self, trading_day: &str) {
+        let mut day = self.trading_day.write();
+        if *day != trading_day {
+            *day = trading_day.to_string();
+            self.trade_sys_sequence.store(0, Ordering::SeqCst);
+        }
+    }
+
+    /// 获取当前交易日 (YYYYMMDD)
+    pub fn trading_day(&self) -> String {
+        self.trading_day.read().clone()
+    }
+
+    /// 生成交易所成交编号
+    ///
+    /// 格式：交易日(8位) + 全局成交序号(12位)，交易日内跨合约唯一，可由编号反查交易日与序号
+    pub fn next_trade_sys_id(&self) -> String {
+        let day = self.trading_day.read();
+        let seq = self.trade_sys_sequence.fetch_add(1, Ordering::SeqCst) + 1;
+        format!("{}{:0width$}", day, seq, width = SYS_ID_SEQ_WIDTH)
+    }
+
+    /// 生成交易所报单编号
+    ///
+    /// 格式：合约ID + "." + 交易所订单号(12位)，交易所订单号在合约内唯一，
+    /// 因此报单编号全局唯一，且成交回报可按订单号确定性地还原报单编号
+    pub fn order_sys_id(instrument_id: &str, exchange_order_id: i64) -> String {
+        format!(
+            "{}.{:0width$}",
+            instrument_id,
+            exchange_order_id,
+            width = SYS_ID_SEQ_WIDTH
+        )
+    }
+
+    /// 将统一时钟时间戳格式化为 (成交日期 YYYYMMDD, 成交时间 HH:MM:SS.mmm)，按交易所时区
+    pub fn format_exchange_time(timestamp_nanos: i64) -> (String, String) {
+        let dt = crate::utils::time_service::time_service().to_local_nanos(timestamp_nanos);
+        (
+            dt.format("%Y%m%d").to_string(),
+            dt.format("%H:%M:%S%.3f").to_string(),
+        )
+    }
 }
 
 impl Default for ExchangeIdGenerator {
@@ -157,4 +256,31 @@ mod tests {
 
         assert_eq!(generator.current_sequence("SHFE.cu2501"), 3);
     }
+
+    #[test]
+    fn test_trade_sys_id_unique_across_instruments() {
+        let generator = ExchangeIdGenerator::new();
+        generator.set_trading_day("20250102");
+
+        let id1 = generator.next_trade_sys_id();
+        let id2 = generator.next_trade_sys_id();
+
+        assert_eq!(id1, "20250102000000000001");
+        assert_eq!(id2, "20250102000000000002");
+
+        // 换日后序号重置，编号仍因交易日前缀保持唯一
+        generator.set_trading_day("20250103");
+        assert_eq!(generator.next_trade_sys_id(), "20250103000000000001");
+    }
+
+    #[test]
+    fn test_exchange_clock_monotonic() {
+        let generator = ExchangeIdGenerator::new();
+        let mut last = 0;
+        for _ in 0..1000 {
+            let now = generator.now_nanos();
+            assert!(now > last);
+            last = now;
+        }
+    }
 }
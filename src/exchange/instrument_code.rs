@@ -0,0 +1,114 @@
+//! 规范化合约代码（交易所 + 合约）
+//! @yutiansut @quantaxis
+//!
+//! 对外统一使用 `EXCHANGE.symbol` 形式（如 `SHFE.cu2501`、`CFFEX.IF2501`）：
+//! - 交易所代码统一大写，合约代码保持原样（郑商所等交易所大小写有意义）
+//! - 不带前缀的旧代码（`cu2501`）由 `InstrumentRegistry::normalize` 按别名映射到注册代码，
+//!   兼容期内可用并打 deprecation 日志；多个交易所存在同名合约时旧代码有歧义，拒绝解析
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::ExchangeError;
+
+/// 规范化合约代码
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct InstrumentCode {
+    /// 交易所代码（大写）
+    pub exchange: String,
+    /// 合约代码（不含交易所前缀）
+    pub symbol: String,
+}
+
+impl InstrumentCode {
+    pub fn new(exchange: &str, symbol: &str) -> Result<Self, ExchangeError> {
+        let exchange = exchange.trim().to_uppercase();
+        let symbol = symbol.trim();
+        if exchange.is_empty() || symbol.is_empty() || symbol.contains('.') {
+            return Err(ExchangeError::InvalidParameter(format!(
+                "Invalid instrument code: {}.{}",
+                exchange, symbol
+            )));
+        }
+        Ok(Self {
+            exchange,
+            symbol: symbol.to_string(),
+        })
+    }
+
+    /// 拆分代码为 (交易所前缀, 合约代码)，不带前缀时交易所为 None
+    pub fn split(code: &str) -> (Option<&str>, &str) {
+        match code.trim().split_once('.') {
+            Some((exchange, symbol)) => (Some(exchange), symbol),
+            None => (None, code.trim()),
+        }
+    }
+
+    /// 两个代码是否指同一合约（未经注册表归一时的兜底匹配）
+    ///
+    /// 合约代码相同，且交易所前缀相同或至少一方不带前缀：
+    /// `SHFE.cu2501` 与 `cu2501` 匹配，`SHFE.cu2501` 与 `DCE.cu2501` 不匹配
+    pub fn matches(a: &str, b: &str) -> bool {
+        if a == b {
+            return true;
+        }
+        match (Self::split(a), Self::split(b)) {
+            ((Some(ea), sa), (Some(eb), sb)) => sa == sb && ea.eq_ignore_ascii_case(eb),
+            ((_, sa), (_, sb)) => sa == sb,
+        }
+    }
+}
+
+impl fmt::Display for InstrumentCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.exchange, self.symbol)
+    }
+}
+
+impl FromStr for InstrumentCode {
+    type Err = ExchangeError;
+
+    /// 解析 `EXCHANGE.symbol`，不带前缀的代码需经注册表归一
+    fn from_str(code: &str) -> Result<Self, Self::Err> {
+        match Self::split(code) {
+            (Some(exchange), symbol) => Self::new(exchange, symbol),
+            (None, _) => Err(ExchangeError::InvalidParameter(format!(
+                "Instrument code {} has no exchange prefix",
+                code
+            ))),
+        }
+    }
+}
+
+/// 同名合约冲突：不带前缀的代码对应多个交易所的合约
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AliasConflict {
+    /// 不带前缀的合约代码
+    pub symbol: String,
+    /// 冲突的注册代码
+    pub instruments: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_display() {
+        let code: InstrumentCode = " shfe.cu2501 ".parse().unwrap();
+        assert_eq!(code.exchange, "SHFE");
+        assert_eq!(code.symbol, "cu2501");
+        assert_eq!(code.to_string(), "SHFE.cu2501");
+
+        assert!("cu2501".parse::<InstrumentCode>().is_err());
+        assert!("SHFE.".parse::<InstrumentCode>().is_err());
+        assert!("SHFE.cu.2501".parse::<InstrumentCode>().is_err());
+        assert_eq!(InstrumentCode::split("IF2501"), (None, "IF2501"));
+
+        assert!(InstrumentCode::matches("SHFE.cu2501", "cu2501"));
+        assert!(InstrumentCode::matches("shfe.cu2501", "SHFE.cu2501"));
+        assert!(!InstrumentCode::matches("SHFE.cu2501", "DCE.cu2501"));
+        assert!(!InstrumentCode::matches("cu2501", "cu2502"));
+    }
+}
@@ -1,18 +1,32 @@
 //! 合约注册表 - 完整的合约生命周期管理
 //!
-//! 支持合约的上市、下市、暂停交易、参数修改等全流程管理
+//! 支持合约的上市、下市、暂停交易、参数修改等全流程管理，
+//! 以及从 CTP 柜台导出的合约参数文件（instrument.csv）批量导入。
+//!
+//! 合约代码归一：注册表维护 `EXCHANGE.symbol` 规范代码与不带前缀旧代码到注册代码的别名映射，
+//! 下单、订阅、查询、K线入口先经 [`InstrumentRegistry::normalize`] 归一再路由，
+//! 同一合约的两种写法落到同一个注册代码（撮合、存储与 WAL 使用的代码）
+//!
+//! 数量口径：委托量、成交量、持仓量一律以交易单位（手）计，价格以报价单位计，
+//! 金额类（成交金额、保证金、盈亏）= 价格 × 手数 × 每手数量（`contract_multiplier`），
+//! 如铜按 元/吨 报价、5 吨/手交易，1 手 85000 的成交金额为 425000
 
-use chrono::{NaiveDate, Utc};
-use dashmap::DashMap;
+use chrono::NaiveDate;
+use dashmap::{DashMap, DashSet};
 use log;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
 
-use crate::ExchangeError;
+use super::instrument_code::{AliasConflict, InstrumentCode};
+use crate::{ExchangeError, QA_Account, QA_Position};
 
 /// 合约状态
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum InstrumentStatus {
+    /// 待上市（已注册，尚未开放交易）
+    Pending,
     /// 正常交易
     Active,
     /// 暂停交易
@@ -50,9 +64,17 @@ pub struct InstrumentInfo {
     /// 交易所代码
     pub exchange: String,
 
-    /// 合约乘数
+    /// 合约乘数（每手数量：1 手对应的报价单位数量，如 5 吨/手、300 元/点）
     pub contract_multiplier: i32,
 
+    /// 交易单位（委托量、成交量、持仓量的计量单位）
+    #[serde(default = "default_trading_unit")]
+    pub trading_unit: String,
+
+    /// 报价单位（如 元/吨、点）
+    #[serde(default)]
+    pub quote_unit: String,
+
     /// 最小变动价位
     pub price_tick: f64,
 
@@ -92,7 +114,9 @@ impl InstrumentInfo {
         instrument_type: InstrumentType,
         exchange: String,
     ) -> Self {
-        let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let now = crate::utils::time_service::now_local()
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string();
 
         Self {
             instrument_id,
@@ -100,6 +124,8 @@ impl InstrumentInfo {
             instrument_type,
             exchange,
             contract_multiplier: 300,
+            trading_unit: default_trading_unit(),
+            quote_unit: String::new(),
             price_tick: 0.2,
             margin_rate: 0.12,
             commission_rate: 0.0001,
@@ -112,21 +138,81 @@ impl InstrumentInfo {
             updated_at: now,
         }
     }
+
+    /// 每手数量（合约乘数未配置时按 1）
+    pub fn volume_multiple(&self) -> f64 {
+        self.contract_multiplier.max(1) as f64
+    }
+
+    /// 成交金额 = 价格 × 手数 × 每手数量
+    pub fn turnover(&self, price: f64, volume: f64) -> f64 {
+        price * volume * self.volume_multiple()
+    }
+
+    /// 保证金 = 成交金额 × 保证金率
+    pub fn margin(&self, price: f64, volume: f64) -> f64 {
+        self.turnover(price, volume) * self.margin_rate
+    }
+
+    /// 盈亏 = 价差 × 手数 × 每手数量
+    pub fn profit(&self, price_diff: f64, volume: f64) -> f64 {
+        price_diff * volume * self.volume_multiple()
+    }
+
+    /// 基础单位数量（吨、点值等）换算为手数，必须为整手
+    pub fn lots_from_quantity(&self, quantity: f64) -> Result<f64, ExchangeError> {
+        let lots = quantity / self.volume_multiple();
+        if !lots.is_finite() || lots < 0.0 || (lots - lots.round()).abs() > 1e-9 {
+            return Err(ExchangeError::InvalidParameter(format!(
+                "Quantity {} of {} is not a whole number of lots ({} per {})",
+                quantity, self.instrument_id, self.contract_multiplier, self.trading_unit
+            )));
+        }
+        Ok(lots.round())
+    }
+
+    /// 手数换算为基础单位数量
+    pub fn quantity_of(&self, volume: f64) -> f64 {
+        volume * self.volume_multiple()
+    }
+
+    /// 账户持仓按每手数量换算（qars 持仓预设按合约代码推断乘数，以注册表为准覆盖）
+    pub fn apply_to_position(&self, pos: &mut QA_Position) {
+        pos.preset.unit_table = self.contract_multiplier.max(1);
+    }
+}
+
+fn default_trading_unit() -> String {
+    "手".to_string()
 }
 
 /// 合约注册表
 pub struct InstrumentRegistry {
     instruments: DashMap<String, InstrumentInfo>,
+
+    /// 规范代码（EXCHANGE.symbol）→ 注册代码
+    canonical_index: DashMap<String, String>,
+
+    /// 不带前缀的合约代码 → 注册代码（多于一个即同名合约冲突）
+    symbol_index: DashMap<String, BTreeSet<String>>,
+
+    /// 已提示过 deprecation 的旧代码（每个旧代码只提示一次）
+    deprecated_codes: DashSet<String>,
 }
 
 impl InstrumentRegistry {
     pub fn new() -> Self {
         Self {
             instruments: DashMap::new(),
+            canonical_index: DashMap::new(),
+            symbol_index: DashMap::new(),
+            deprecated_codes: DashSet::new(),
         }
     }
 
     /// 注册/上市新合约
+    ///
+    /// 推荐以规范代码（`SHFE.cu2501`）注册；以不带前缀的代码注册时按 `exchange` 建立规范代码别名
     pub fn register(&self, info: InstrumentInfo) -> Result<(), ExchangeError> {
         if self.instruments.contains_key(&info.instrument_id) {
             return Err(ExchangeError::InstrumentError(format!(
@@ -134,12 +220,150 @@ impl InstrumentRegistry {
                 info.instrument_id
             )));
         }
+        if info.contract_multiplier <= 0 {
+            return Err(ExchangeError::InstrumentError(format!(
+                "Instrument {} contract_multiplier must be positive: {}",
+                info.instrument_id, info.contract_multiplier
+            )));
+        }
+
+        let code = Self::code_of(&info)?;
+        if let Some(code) = &code {
+            if let Some(existing) = self.canonical_index.get(&code.to_string()) {
+                return Err(ExchangeError::InstrumentError(format!(
+                    "Instrument {} already registered as {}",
+                    code,
+                    existing.value()
+                )));
+            }
+        }
 
         log::info!("Registering instrument: {}", info.instrument_id);
+        if let Some(code) = &code {
+            self.index_code(&info.instrument_id, code);
+        }
         self.instruments.insert(info.instrument_id.clone(), info);
         Ok(())
     }
 
+    /// 合约代码归一，返回注册代码（撮合、存储与 WAL 使用的代码）
+    ///
+    /// - 注册代码与 `EXCHANGE.symbol` 规范代码直接解析（交易所前缀不区分大小写）
+    /// - 不带前缀的旧代码按别名解析并提示 deprecation；多个交易所有同名合约时返回错误
+    /// - 未注册的代码原样返回，由后续环节按合约不存在处理
+    pub fn normalize(&self, code: &str) -> Result<String, ExchangeError> {
+        let code = code.trim();
+        if self.instruments.contains_key(code) {
+            return Ok(code.to_string());
+        }
+
+        match InstrumentCode::split(code) {
+            (Some(exchange), symbol) => {
+                let canonical = format!("{}.{}", exchange.to_uppercase(), symbol);
+                Ok(self
+                    .canonical_index
+                    .get(&canonical)
+                    .map(|key| key.value().clone())
+                    .unwrap_or_else(|| code.to_string()))
+            }
+            (None, symbol) => {
+                let Some(keys) = self.symbol_index.get(symbol) else {
+                    return Ok(code.to_string());
+                };
+                if keys.len() > 1 {
+                    return Err(ExchangeError::InstrumentError(format!(
+                        "Ambiguous instrument code {}: {:?}, use EXCHANGE.symbol",
+                        code,
+                        keys.value()
+                    )));
+                }
+                let key = keys
+                    .iter()
+                    .next()
+                    .cloned()
+                    .unwrap_or_else(|| code.to_string());
+                drop(keys);
+                if self.deprecated_codes.insert(code.to_string()) {
+                    let canonical = self
+                        .canonical_code(&key)
+                        .map_or_else(|| key.clone(), |c| c.to_string());
+                    log::warn!(
+                        "Deprecated instrument code {} without exchange prefix, use {}",
+                        code,
+                        canonical
+                    );
+                }
+                Ok(key)
+            }
+        }
+    }
+
+    /// 合约的规范代码（注册时交易所为空且代码不带前缀的合约返回 None）
+    pub fn canonical_code(&self, instrument_id: &str) -> Option<InstrumentCode> {
+        self.instruments
+            .get(instrument_id)
+            .and_then(|info| Self::code_of(info.value()).ok().flatten())
+    }
+
+    /// 同名合约冲突（多个交易所的同名合约，不带前缀的旧代码无法解析）
+    pub fn alias_conflicts(&self) -> Vec<AliasConflict> {
+        let mut conflicts: Vec<AliasConflict> = self
+            .symbol_index
+            .iter()
+            .filter(|entry| entry.len() > 1)
+            .map(|entry| AliasConflict {
+                symbol: entry.key().clone(),
+                instruments: entry.iter().cloned().collect(),
+            })
+            .collect();
+        conflicts.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+        conflicts
+    }
+
+    /// 由注册信息得到规范代码，代码前缀与交易所不一致时报错
+    fn code_of(info: &InstrumentInfo) -> Result<Option<InstrumentCode>, ExchangeError> {
+        match InstrumentCode::split(&info.instrument_id) {
+            (Some(prefix), symbol) => {
+                if !info.exchange.is_empty() && !prefix.eq_ignore_ascii_case(&info.exchange) {
+                    return Err(ExchangeError::InstrumentError(format!(
+                        "Instrument {} does not belong to exchange {}",
+                        info.instrument_id, info.exchange
+                    )));
+                }
+                InstrumentCode::new(prefix, symbol).map(Some)
+            }
+            (None, _) if info.exchange.trim().is_empty() => Ok(None),
+            (None, symbol) => InstrumentCode::new(&info.exchange, symbol).map(Some),
+        }
+    }
+
+    /// 建立规范代码与旧代码别名
+    fn index_code(&self, instrument_id: &str, code: &InstrumentCode) {
+        self.canonical_index
+            .insert(code.to_string(), instrument_id.to_string());
+        let mut keys = self.symbol_index.entry(code.symbol.clone()).or_default();
+        keys.insert(instrument_id.to_string());
+        if keys.len() > 1 {
+            log::warn!(
+                "Instrument code conflict: {} is listed on multiple exchanges {:?}, \
+                 code without exchange prefix is ambiguous",
+                code.symbol,
+                keys.value()
+            );
+        }
+    }
+
+    /// 移除合约的别名
+    fn unindex_code(&self, instrument_id: &str, code: &InstrumentCode) {
+        self.canonical_index
+            .remove_if(&code.to_string(), |_, key| key == instrument_id);
+        if let Some(mut keys) = self.symbol_index.get_mut(&code.symbol) {
+            keys.remove(instrument_id);
+        }
+        self.symbol_index
+            .remove_if(&code.symbol, |_, keys| keys.is_empty());
+    }
+
     /// 获取合约信息
     pub fn get(&self, instrument_id: &str) -> Option<InstrumentInfo> {
         self.instruments
@@ -147,6 +371,28 @@ impl InstrumentRegistry {
             .map(|r| r.value().clone())
     }
 
+    /// 合约每手数量（未注册的合约按 1）
+    pub fn volume_multiple(&self, instrument_id: &str) -> f64 {
+        self.instruments
+            .get(instrument_id)
+            .map_or(1.0, |info| info.value().volume_multiple())
+    }
+
+    /// 账户持仓按注册表的每手数量换算（持仓不存在时先初始化，未注册的合约不处理）
+    ///
+    /// 下单冻结、成交后的保证金、持仓市值与盈亏都由 qars 按持仓预设计算，需在 `send_order` 前调用
+    pub fn apply_to_account(&self, account: &mut QA_Account, instrument_id: &str) {
+        let Some(info) = self.instruments.get(instrument_id) else {
+            return;
+        };
+        if !account.hold.contains_key(instrument_id) {
+            account.init_h(instrument_id);
+        }
+        if let Some(pos) = account.hold.get_mut(instrument_id) {
+            info.value().apply_to_position(pos);
+        }
+    }
+
     /// 列出所有合约
     pub fn list_all(&self) -> Vec<InstrumentInfo> {
         self.instruments.iter().map(|r| r.value().clone()).collect()
@@ -169,8 +415,27 @@ impl InstrumentRegistry {
     ) -> Result<(), ExchangeError> {
         match self.instruments.get_mut(instrument_id) {
             Some(mut info) => {
+                let before = info.value().clone();
                 update_fn(info.value_mut());
-                info.value_mut().updated_at = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+                info.value_mut().updated_at = crate::utils::time_service::now_local()
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string();
+                let after = info.value().clone();
+                drop(info);
+
+                // 交易所变更时重建别名
+                if after.exchange != before.exchange {
+                    if let Ok(Some(code)) = Self::code_of(&before) {
+                        self.unindex_code(instrument_id, &code);
+                    }
+                    match Self::code_of(&after) {
+                        Ok(Some(code)) => self.index_code(instrument_id, &code),
+                        Ok(None) => {}
+                        Err(e) => {
+                            log::warn!("Instrument {} has no valid code: {}", instrument_id, e)
+                        }
+                    }
+                }
                 log::info!("Updated instrument: {}", instrument_id);
                 Ok(())
             }
@@ -190,6 +455,34 @@ impl InstrumentRegistry {
         Ok(())
     }
 
+    /// 上市：待上市合约开放交易
+    ///
+    /// 需要上市保护（拒绝市价单/保护性挂单）时通过 `ListingProtection::activate_at` 调用
+    pub fn activate(&self, instrument_id: &str) -> Result<(), ExchangeError> {
+        match self.instruments.get_mut(instrument_id) {
+            Some(mut info) if info.status == InstrumentStatus::Pending => {
+                info.status = InstrumentStatus::Active;
+                info.updated_at = crate::utils::time_service::now_local()
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string();
+            }
+            Some(info) => {
+                return Err(ExchangeError::InstrumentError(format!(
+                    "Instrument {} is not pending listing (status: {:?})",
+                    instrument_id, info.status
+                )))
+            }
+            None => {
+                return Err(ExchangeError::InstrumentError(format!(
+                    "Instrument {} not found",
+                    instrument_id
+                )))
+            }
+        }
+        log::info!("Activated instrument: {}", instrument_id);
+        Ok(())
+    }
+
     /// 恢复交易
     pub fn resume(&self, instrument_id: &str) -> Result<(), ExchangeError> {
         self.update(instrument_id, |info| {
@@ -208,6 +501,13 @@ impl InstrumentRegistry {
         Ok(())
     }
 
+    /// 合约状态
+    pub fn status(&self, instrument_id: &str) -> Option<InstrumentStatus> {
+        self.instruments
+            .get(instrument_id)
+            .map(|r| r.value().status)
+    }
+
     /// 检查合约是否可交易
     pub fn is_trading(&self, instrument_id: &str) -> bool {
         self.instruments
@@ -217,6 +517,270 @@ impl InstrumentRegistry {
     }
 }
 
+// ============================================================================
+// CTP 合约参数导入 @yutiansut @quantaxis
+// ============================================================================
+
+/// CTP 字段名（英文表头 / 中文表头）
+const CTP_INSTRUMENT_ID: &[&str] = &["InstrumentID", "合约代码"];
+const CTP_EXCHANGE_ID: &[&str] = &["ExchangeID", "交易所代码"];
+const CTP_INSTRUMENT_NAME: &[&str] = &["InstrumentName", "合约名称"];
+const CTP_PRODUCT_CLASS: &[&str] = &["ProductClass", "产品类型"];
+const CTP_VOLUME_MULTIPLE: &[&str] = &["VolumeMultiple", "合约数量乘数"];
+const CTP_PRICE_TICK: &[&str] = &["PriceTick", "最小变动价位"];
+const CTP_OPEN_DATE: &[&str] = &["OpenDate", "上市日"];
+const CTP_EXPIRE_DATE: &[&str] = &["ExpireDate", "到期日"];
+const CTP_LONG_MARGIN_RATIO: &[&str] = &["LongMarginRatio", "多头保证金率"];
+const CTP_SHORT_MARGIN_RATIO: &[&str] = &["ShortMarginRatio", "空头保证金率"];
+const CTP_COMMISSION_RATIO: &[&str] = &["OpenRatioByMoney", "开仓手续费率"];
+
+/// 单行导入失败明细
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CtpImportFailure {
+    /// 数据行号（表头为第 1 行）
+    pub line: usize,
+    pub instrument_id: String,
+    pub reason: String,
+}
+
+/// CTP 合约导入报告
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CtpImportReport {
+    /// 新增合约
+    pub added: Vec<String>,
+    /// 更新参数的已有合约
+    pub updated: Vec<String>,
+    /// 已到期跳过的合约
+    pub skipped_expired: Vec<String>,
+    /// 解析/校验失败明细
+    pub failed: Vec<CtpImportFailure>,
+}
+
+impl InstrumentRegistry {
+    /// 从 CTP 导出的 instrument.csv 导入合约参数（支持 UTF-8 / GBK 编码）
+    pub fn import_from_ctp_csv<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> Result<CtpImportReport, ExchangeError> {
+        let data = std::fs::read(path.as_ref()).map_err(|e| {
+            ExchangeError::InstrumentError(format!(
+                "Failed to read CTP file {:?}: {}",
+                path.as_ref(),
+                e
+            ))
+        })?;
+        self.import_from_ctp_bytes(&data)
+    }
+
+    /// 从 CTP CSV 内容导入合约参数（admin API 上传文件时使用）
+    ///
+    /// 已存在的合约只更新交易参数，保留状态与涨跌停设置；到期日在今天之前的合约跳过
+    pub fn import_from_ctp_bytes(&self, data: &[u8]) -> Result<CtpImportReport, ExchangeError> {
+        let text = decode_ctp_text(data);
+        let mut reader = csv::ReaderBuilder::new()
+            .flexible(true)
+            .trim(csv::Trim::All)
+            .from_reader(text.as_bytes());
+
+        let headers = reader
+            .headers()
+            .map_err(|e| ExchangeError::InstrumentError(format!("Invalid CTP header: {}", e)))?
+            .clone();
+        let columns: HashMap<&str, usize> = headers
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (name, i))
+            .collect();
+        let column = |aliases: &[&str]| aliases.iter().find_map(|a| columns.get(a).copied());
+
+        let required = [
+            CTP_INSTRUMENT_ID,
+            CTP_EXCHANGE_ID,
+            CTP_VOLUME_MULTIPLE,
+            CTP_PRICE_TICK,
+        ];
+        for aliases in required {
+            if column(aliases).is_none() {
+                return Err(ExchangeError::InstrumentError(format!(
+                    "CTP file missing required column {}",
+                    aliases[0]
+                )));
+            }
+        }
+
+        let today = crate::utils::time_service::time_service().today();
+        let mut report = CtpImportReport::default();
+
+        for (i, record) in reader.records().enumerate() {
+            let line = i + 2;
+            let record = match record {
+                Ok(r) => r,
+                Err(e) => {
+                    report.failed.push(CtpImportFailure {
+                        line,
+                        instrument_id: String::new(),
+                        reason: e.to_string(),
+                    });
+                    continue;
+                }
+            };
+            let field = |aliases: &[&str]| {
+                column(aliases)
+                    .and_then(|idx| record.get(idx))
+                    .filter(|v| !v.is_empty())
+            };
+
+            let instrument_id = field(CTP_INSTRUMENT_ID).unwrap_or_default().to_string();
+            let parsed = parse_ctp_row(&instrument_id, &field);
+            let info = match parsed {
+                Ok(info) => info,
+                Err(reason) => {
+                    report.failed.push(CtpImportFailure {
+                        line,
+                        instrument_id,
+                        reason,
+                    });
+                    continue;
+                }
+            };
+
+            let expired = info
+                .expire_date
+                .as_deref()
+                .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+                .is_some_and(|d| d < today);
+            if expired {
+                report.skipped_expired.push(instrument_id);
+                continue;
+            }
+
+            if self.instruments.contains_key(&instrument_id) {
+                let result = self.update(&instrument_id, |existing| {
+                    existing.instrument_name = info.instrument_name.clone();
+                    existing.exchange = info.exchange.clone();
+                    existing.contract_multiplier = info.contract_multiplier;
+                    existing.price_tick = info.price_tick;
+                    existing.margin_rate = info.margin_rate;
+                    existing.commission_rate = info.commission_rate;
+                    existing.list_date = info.list_date.clone().or(existing.list_date.take());
+                    existing.expire_date = info.expire_date.clone().or(existing.expire_date.take());
+                });
+                match result {
+                    Ok(()) => report.updated.push(instrument_id),
+                    Err(e) => report.failed.push(CtpImportFailure {
+                        line,
+                        instrument_id,
+                        reason: e.to_string(),
+                    }),
+                }
+            } else {
+                match self.register(info) {
+                    Ok(()) => report.added.push(instrument_id),
+                    Err(e) => report.failed.push(CtpImportFailure {
+                        line,
+                        instrument_id,
+                        reason: e.to_string(),
+                    }),
+                }
+            }
+        }
+
+        log::info!(
+            "CTP instrument import: added={}, updated={}, expired={}, failed={}",
+            report.added.len(),
+            report.updated.len(),
+            report.skipped_expired.len(),
+            report.failed.len()
+        );
+        Ok(report)
+    }
+}
+
+/// 解码 CTP 文件：UTF-8（含 BOM）优先，否则按 GBK 解码
+fn decode_ctp_text(data: &[u8]) -> String {
+    let data = data.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(data);
+    match std::str::from_utf8(data) {
+        Ok(s) => s.to_string(),
+        Err(_) => encoding_rs::GBK.decode(data).0.into_owned(),
+    }
+}
+
+/// CTP 日期 YYYYMMDD -> YYYY-MM-DD
+fn parse_ctp_date(value: &str) -> Result<String, String> {
+    NaiveDate::parse_from_str(value, "%Y%m%d")
+        .map(|d| d.format("%Y-%m-%d").to_string())
+        .map_err(|_| format!("invalid date {}", value))
+}
+
+fn parse_ctp_number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value
+        .parse::<T>()
+        .map_err(|_| format!("invalid {} {}", name, value))
+}
+
+/// 解析并校验一行 CTP 合约参数，缺失的可选列使用 `InstrumentInfo::new` 的默认值
+fn parse_ctp_row<'a>(
+    instrument_id: &str,
+    field: &impl Fn(&[&str]) -> Option<&'a str>,
+) -> Result<InstrumentInfo, String> {
+    if instrument_id.is_empty() {
+        return Err("missing InstrumentID".to_string());
+    }
+    let exchange = field(CTP_EXCHANGE_ID).ok_or("missing ExchangeID")?;
+    let multiplier = field(CTP_VOLUME_MULTIPLE).ok_or("missing VolumeMultiple")?;
+    let price_tick = field(CTP_PRICE_TICK).ok_or("missing PriceTick")?;
+
+    // ProductClass: '1' 期货, '2' 期权
+    let instrument_type = match field(CTP_PRODUCT_CLASS) {
+        Some("2") => InstrumentType::Option,
+        _ if exchange == "CFFEX" => InstrumentType::IndexFuture,
+        _ => InstrumentType::CommodityFuture,
+    };
+
+    let mut info = InstrumentInfo::new(
+        instrument_id.to_string(),
+        field(CTP_INSTRUMENT_NAME).unwrap_or(instrument_id).to_string(),
+        instrument_type,
+        exchange.to_string(),
+    );
+
+    info.contract_multiplier = parse_ctp_number("VolumeMultiple", multiplier)?;
+    info.price_tick = parse_ctp_number("PriceTick", price_tick)?;
+    if info.contract_multiplier <= 0 {
+        return Err(format!("VolumeMultiple must be positive: {}", multiplier));
+    }
+    if info.price_tick.is_nan() || info.price_tick <= 0.0 {
+        return Err(format!("PriceTick must be positive: {}", price_tick));
+    }
+
+    // 多空保证金率取较大者
+    let long_margin = field(CTP_LONG_MARGIN_RATIO)
+        .map(|v| parse_ctp_number::<f64>("LongMarginRatio", v))
+        .transpose()?;
+    let short_margin = field(CTP_SHORT_MARGIN_RATIO)
+        .map(|v| parse_ctp_number::<f64>("ShortMarginRatio", v))
+        .transpose()?;
+    if let Some(margin) = long_margin.into_iter().chain(short_margin).reduce(f64::max) {
+        if !(0.0..=1.0).contains(&margin) {
+            return Err(format!("margin ratio out of range: {}", margin));
+        }
+        info.margin_rate = margin;
+    }
+
+    if let Some(v) = field(CTP_COMMISSION_RATIO) {
+        let commission: f64 = parse_ctp_number("OpenRatioByMoney", v)?;
+        if commission < 0.0 {
+            return Err(format!("commission ratio must not be negative: {}", v));
+        }
+        info.commission_rate = commission;
+    }
+
+    info.list_date = field(CTP_OPEN_DATE).map(parse_ctp_date).transpose()?;
+    info.expire_date = field(CTP_EXPIRE_DATE).map(parse_ctp_date).transpose()?;
+
+    Ok(info)
+}
+
 impl Default for InstrumentRegistry {
     fn default() -> Self {
         Self::new()
@@ -395,6 +959,47 @@ mod tests {
         assert!(!registry.is_trading("IF2501"));
     }
 
+    /// 测试交易单位与报价单位换算：铜按 元/吨 报价、5 吨/手交易
+    #[test]
+    fn test_contract_unit_conversion() {
+        let mut info = InstrumentInfo::new(
+            "cu2501".to_string(),
+            "沪铜2501".to_string(),
+            InstrumentType::CommodityFuture,
+            "SHFE".to_string(),
+        );
+        info.contract_multiplier = 5;
+        info.quote_unit = "元/吨".to_string();
+        info.margin_rate = 0.1;
+        assert_eq!(info.trading_unit, "手");
+
+        // 2 手 × 5 吨/手 × 85000 元/吨
+        assert_eq!(info.turnover(85000.0, 2.0), 850000.0);
+        assert_eq!(info.margin(85000.0, 2.0), 85000.0);
+        assert_eq!(info.profit(-120.0, 3.0), -1800.0);
+        assert_eq!(info.quantity_of(4.0), 20.0);
+        assert_eq!(info.lots_from_quantity(15.0).unwrap(), 3.0);
+        assert!(info.lots_from_quantity(7.0).is_err());
+
+        let registry = InstrumentRegistry::new();
+        registry.register(info.clone()).unwrap();
+        assert_eq!(registry.volume_multiple("cu2501"), 5.0);
+        assert_eq!(registry.volume_multiple("NONEXIST"), 1.0);
+
+        // 旧数据缺少单位字段时按默认值反序列化
+        let mut json = serde_json::to_value(&info).unwrap();
+        json.as_object_mut().unwrap().remove("trading_unit");
+        json.as_object_mut().unwrap().remove("quote_unit");
+        let restored: InstrumentInfo = serde_json::from_value(json).unwrap();
+        assert_eq!(restored.trading_unit, "手");
+        assert!(restored.quote_unit.is_empty());
+
+        // 每手数量必须为正
+        info.instrument_id = "cu2502".to_string();
+        info.contract_multiplier = 0;
+        assert!(registry.register(info).is_err());
+    }
+
     // ==================== register 测试 @yutiansut @quantaxis ====================
 
     /// 测试 register 成功
@@ -463,6 +1068,45 @@ mod tests {
         assert_eq!(registry.list_all().len(), 5);
     }
 
+    /// 测试合约代码归一与同名合约冲突
+    #[test]
+    fn test_normalize_aliases_and_conflicts() {
+        let registry = InstrumentRegistry::new();
+        let info = |id: &str, exchange: &str| {
+            InstrumentInfo::new(
+                id.to_string(),
+                id.to_string(),
+                InstrumentType::CommodityFuture,
+                exchange.to_string(),
+            )
+        };
+        registry.register(info("IF2501", "CFFEX")).unwrap();
+        registry.register(info("SHFE.cu2501", "SHFE")).unwrap();
+
+        // 规范代码与旧代码都归一为注册代码
+        assert_eq!(registry.normalize("cffex.IF2501").unwrap(), "IF2501");
+        assert_eq!(registry.normalize("IF2501").unwrap(), "IF2501");
+        assert_eq!(registry.normalize("cu2501").unwrap(), "SHFE.cu2501");
+        assert_eq!(
+            registry.canonical_code("IF2501").unwrap().to_string(),
+            "CFFEX.IF2501"
+        );
+        assert_eq!(registry.normalize("UNKNOWN").unwrap(), "UNKNOWN");
+
+        // 前缀与交易所不一致、规范代码重复注册均拒绝
+        assert!(registry.register(info("SHFE.IF2501", "CFFEX")).is_err());
+        assert!(registry.register(info("CFFEX.IF2501", "CFFEX")).is_err());
+
+        // 另一交易所上市同名合约后旧代码有歧义
+        registry.register(info("DCE.cu2501", "DCE")).unwrap();
+        assert!(registry.normalize("cu2501").is_err());
+        assert_eq!(registry.normalize("DCE.cu2501").unwrap(), "DCE.cu2501");
+        let conflicts = registry.alias_conflicts();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].symbol, "cu2501");
+        assert_eq!(conflicts[0].instruments, vec!["DCE.cu2501", "SHFE.cu2501"]);
+    }
+
     // ==================== get 测试 @yutiansut @quantaxis ====================
 
     /// 测试 get 成功
@@ -700,6 +1344,33 @@ mod tests {
         assert!(result.is_err());
     }
 
+    /// 测试 activate：仅待上市合约可上市
+    #[test]
+    fn test_activate_pending_instrument() {
+        let registry = InstrumentRegistry::new();
+
+        let mut info = InstrumentInfo::new(
+            "activate_test".to_string(),
+            "Activate Test".to_string(),
+            InstrumentType::CommodityFuture,
+            "TEST".to_string(),
+        );
+        info.status = InstrumentStatus::Pending;
+        registry.register(info).unwrap();
+        assert!(!registry.is_trading("activate_test"));
+
+        registry.activate("activate_test").unwrap();
+        assert!(registry.is_trading("activate_test"));
+        assert_eq!(
+            registry.status("activate_test"),
+            Some(InstrumentStatus::Active)
+        );
+
+        // 已上市 / 不存在的合约不能再上市
+        assert!(registry.activate("activate_test").is_err());
+        assert!(registry.activate("non_existent").is_err());
+    }
+
     // ==================== delist 测试 @yutiansut @quantaxis ====================
 
     /// 测试 delist 成功
@@ -947,4 +1618,84 @@ mod tests {
         assert_eq!(final_info.status, InstrumentStatus::Delisted);
         assert_eq!(final_info.margin_rate, 0.15);
     }
+
+    // ==================== CTP 导入测试 @yutiansut @quantaxis ====================
+
+    /// 测试 GBK 编码的 CTP 合约文件导入：新增、更新、到期跳过、失败明细
+    #[test]
+    fn test_import_ctp_csv_gbk() {
+        let registry = InstrumentRegistry::new();
+        registry
+            .register(InstrumentInfo::new(
+                "IF2912".to_string(),
+                "IF2912".to_string(),
+                InstrumentType::IndexFuture,
+                "CFFEX".to_string(),
+            ))
+            .unwrap();
+        registry.suspend("IF2912").unwrap();
+
+        let csv_text = "\
+InstrumentID,ExchangeID,InstrumentName,ProductClass,VolumeMultiple,PriceTick,OpenDate,ExpireDate,LongMarginRatio,ShortMarginRatio
+cu2912,SHFE,沪铜2912,1,5,10,20981215,20991215,0.1,0.12
+IF2912,CFFEX,沪深300指数2912,1,300,0.2,20990101,20991220,0.12,0.12
+au2001,SHFE,黄金2001,1,1000,0.02,20190115,20200115,0.08,0.08
+rb2912,SHFE,螺纹钢2912,1,abc,1,20981215,20991215,0.1,0.1
+";
+        let (gbk, _, had_errors) = encoding_rs::GBK.encode(csv_text);
+        assert!(!had_errors);
+        assert!(std::str::from_utf8(&gbk).is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("instrument.csv");
+        std::fs::write(&path, &gbk).unwrap();
+
+        let report = registry.import_from_ctp_csv(&path).unwrap();
+        assert_eq!(report.added, vec!["cu2912".to_string()]);
+        assert_eq!(report.updated, vec!["IF2912".to_string()]);
+        assert_eq!(report.skipped_expired, vec!["au2001".to_string()]);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].instrument_id, "rb2912");
+        assert_eq!(report.failed[0].line, 5);
+
+        let cu = registry.get("cu2912").unwrap();
+        assert_eq!(cu.instrument_name, "沪铜2912");
+        assert_eq!(cu.instrument_type, InstrumentType::CommodityFuture);
+        assert_eq!(cu.contract_multiplier, 5);
+        assert_eq!(cu.price_tick, 10.0);
+        assert_eq!(cu.margin_rate, 0.12);
+        assert_eq!(cu.expire_date.as_deref(), Some("2099-12-15"));
+
+        // 已存在合约只更新参数，保留状态
+        let ifc = registry.get("IF2912").unwrap();
+        assert_eq!(ifc.instrument_name, "沪深300指数2912");
+        assert_eq!(ifc.price_tick, 0.2);
+        assert_eq!(ifc.status, InstrumentStatus::Suspended);
+    }
+
+    /// 测试缺失可选列时使用默认值，缺失必需列时报错
+    #[test]
+    fn test_import_ctp_missing_columns() {
+        let registry = InstrumentRegistry::new();
+
+        let report = registry
+            .import_from_ctp_bytes(b"InstrumentID,ExchangeID,VolumeMultiple,PriceTick\nag2912,SHFE,15,1\n")
+            .unwrap();
+        assert_eq!(report.added, vec!["ag2912".to_string()]);
+        let ag = registry.get("ag2912").unwrap();
+        assert_eq!(ag.instrument_name, "ag2912");
+        assert_eq!(ag.margin_rate, 0.12);
+        assert!(ag.expire_date.is_none());
+
+        // 缺失列的行（列数不足）记为失败，不影响其他行
+        let report = registry
+            .import_from_ctp_bytes(b"InstrumentID,ExchangeID,VolumeMultiple,PriceTick\nni2912,SHFE\nzn2912,SHFE,5,5\n")
+            .unwrap();
+        assert_eq!(report.added, vec!["zn2912".to_string()]);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].instrument_id, "ni2912");
+
+        let result = registry.import_from_ctp_bytes(b"InstrumentID,ExchangeID,PriceTick\nsn2912,SHFE,10\n");
+        assert!(result.is_err());
+    }
 }
@@ -0,0 +1,161 @@
+//! 闲置资金计息
+//! @yutiansut @quantaxis
+//!
+//! 日终结算时按账户可用资金计息入账：
+//!
+//! ```text
+//! 日利息 = 计息基数 × 年化利率 / 计息天数（默认 360）
+//! ```
+//!
+//! - 计息基数：结算时可用资金，或日内采样的日均可用资金（复用风险历史快照）
+//! - 年化利率按账户类型配置，为 0 时不计息；负利率表示收取利息
+//! - 利息四舍五入到分，生成 Interest 类型资金流水并调整余额
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::account_ext::AccountType;
+use crate::exchange::reconciliation::{from_cents, to_cents};
+
+/// 计息基数
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum InterestBasis {
+    /// 结算时可用资金
+    #[default]
+    Available,
+    /// 日内采样的日均可用资金（无采样点时退化为结算时可用资金）
+    AverageAvailable,
+}
+
+/// 计息配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InterestConfig {
+    /// 计息基数
+    #[serde(default)]
+    pub basis: InterestBasis,
+    /// 年计息天数
+    #[serde(default = "default_day_basis")]
+    pub day_basis: u32,
+    /// 个人账户年化利率（0 表示不计息）
+    #[serde(default)]
+    pub individual_rate: f64,
+    /// 机构账户年化利率
+    #[serde(default)]
+    pub institutional_rate: f64,
+    /// 做市商账户年化利率
+    #[serde(default)]
+    pub market_maker_rate: f64,
+}
+
+fn default_day_basis() -> u32 {
+    360
+}
+
+impl Default for InterestConfig {
+    fn default() -> Self {
+        Self {
+            basis: InterestBasis::default(),
+            day_basis: default_day_basis(),
+            individual_rate: 0.0,
+            institutional_rate: 0.0,
+            market_maker_rate: 0.0,
+        }
+    }
+}
+
+impl InterestConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.day_basis == 0 {
+            return Err("interest.day_basis must be > 0".to_string());
+        }
+        for (name, rate) in [
+            ("individual_rate", self.individual_rate),
+            ("institutional_rate", self.institutional_rate),
+            ("market_maker_rate", self.market_maker_rate),
+        ] {
+            if !rate.is_finite() || rate.abs() > 1.0 {
+                return Err(format!("interest.{} must be within [-1, 1]", name));
+            }
+        }
+        Ok(())
+    }
+
+    /// 是否有任一账户类型计息
+    pub fn is_enabled(&self) -> bool {
+        self.individual_rate != 0.0
+            || self.institutional_rate != 0.0
+            || self.market_maker_rate != 0.0
+    }
+
+    /// 账户类型对应的年化利率
+    pub fn rate_for(&self, account_type: AccountType) -> f64 {
+        match account_type {
+            AccountType::Individual => self.individual_rate,
+            AccountType::Institutional => self.institutional_rate,
+            AccountType::MarketMaker => self.market_maker_rate,
+        }
+    }
+
+    /// 按账户类型计算日利息
+    pub fn daily_interest(&self, base: f64, account_type: AccountType) -> f64 {
+        daily_interest(base, self.rate_for(account_type), self.day_basis)
+    }
+}
+
+/// 日利息（四舍五入到分）；计息基数不为正或利率为 0 时不计息
+pub fn daily_interest(base: f64, annual_rate: f64, day_basis: u32) -> f64 {
+    if base <= 0.0 || annual_rate == 0.0 || day_basis == 0 {
+        return 0.0;
+    }
+    from_cents(to_cents(base * annual_rate / day_basis as f64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_daily_interest_matches_hand_calculation() {
+        // 1,000,000 × 1.8% / 360 = 50.00
+        assert_eq!(daily_interest(1_000_000.0, 0.018, 360), 50.0);
+        // 123,456.78 × 0.35% / 360 = 1.200274... → 1.20
+        assert_eq!(daily_interest(123_456.78, 0.0035, 360), 1.2);
+        // 80,000 × 2.5% / 365 = 5.479452... → 5.48
+        assert_eq!(daily_interest(80_000.0, 0.025, 365), 5.48);
+        // 负利率：500,000 × -0.5% / 360 = -6.944... → -6.94
+        assert_eq!(daily_interest(500_000.0, -0.005, 360), -6.94);
+        // 无闲置资金或利率为 0 不计息
+        assert_eq!(daily_interest(-1_000.0, 0.018, 360), 0.0);
+        assert_eq!(daily_interest(1_000_000.0, 0.0, 360), 0.0);
+    }
+
+    #[test]
+    fn test_rate_by_account_type() {
+        let config = InterestConfig {
+            individual_rate: 0.018,
+            market_maker_rate: 0.036,
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+        assert!(config.is_enabled());
+        assert_eq!(
+            config.daily_interest(100_000.0, AccountType::Individual),
+            5.0
+        );
+        assert_eq!(
+            config.daily_interest(100_000.0, AccountType::MarketMaker),
+            10.0
+        );
+        assert_eq!(
+            config.daily_interest(100_000.0, AccountType::Institutional),
+            0.0
+        );
+
+        assert!(!InterestConfig::default().is_enabled());
+        let invalid = InterestConfig {
+            day_basis: 0,
+            ..Default::default()
+        };
+        assert!(invalid.validate().is_err());
+    }
+}
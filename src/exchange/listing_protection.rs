@@ -0,0 +1,663 @@
+//! 新合约上市保护
+//!
+//! @yutiansut @quantaxis
+//!
+//! 新合约上市首日订单簿为空，首笔市价单无对手盘可成交，或以极端价格成交。
+//! 合约由 `Pending` 转为 `Active`（[`ListingProtection::activate_at`]）后的保护时段内：
+//!
+//! - **拒绝市价单**：只允许限价单（`OrderRouter` 下单时校验）
+//! - **保护性盘口（可选）**：系统做市账户按挂牌参考价（昨结）±N 个 tick 挂多档买卖单，
+//!   被成交后由做市任务按原价补挂，保护时段结束自动撤销
+//!
+//! 保护按合约配置开关，未配置的合约上市后直接进入正常交易。
+//! 做市单走普通下单流程（风控、资金校验照常），做市账户需预先入金。
+
+use dashmap::DashMap;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+use crate::exchange::order_router::{CancelOrderRequest, OrderStatus, SubmitOrderRequest};
+use crate::exchange::{InstrumentRegistry, OrderRouter};
+use crate::ExchangeError;
+
+/// 保护性做市挂单配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProtectiveQuoteConfig {
+    /// 系统做市账户
+    pub maker_account_id: String,
+    /// 买卖各挂几档
+    #[serde(default = "default_levels")]
+    pub levels: u32,
+    /// 第一档距参考价的 tick 数
+    #[serde(default = "default_tick_offset")]
+    pub tick_offset: u32,
+    /// 相邻档位间隔的 tick 数
+    #[serde(default = "default_level_spacing_ticks")]
+    pub level_spacing_ticks: u32,
+    /// 每档挂单数量
+    #[serde(default = "default_volume_per_level")]
+    pub volume_per_level: f64,
+}
+
+fn default_levels() -> u32 {
+    3
+}
+fn default_tick_offset() -> u32 {
+    1
+}
+fn default_level_spacing_ticks() -> u32 {
+    1
+}
+fn default_volume_per_level() -> f64 {
+    1.0
+}
+
+/// 合约上市保护配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListingProtectionConfig {
+    /// 保护时段长度（秒，自上市起算）
+    #[serde(default = "default_duration_secs")]
+    pub duration_secs: u64,
+    /// 保护时段内拒绝市价单
+    #[serde(default = "default_reject_market_orders")]
+    pub reject_market_orders: bool,
+    /// 保护性做市挂单（None 表示不挂单）
+    #[serde(default)]
+    pub quotes: Option<ProtectiveQuoteConfig>,
+}
+
+fn default_duration_secs() -> u64 {
+    3_600
+}
+fn default_reject_market_orders() -> bool {
+    true
+}
+
+impl Default for ListingProtectionConfig {
+    fn default() -> Self {
+        Self {
+            duration_secs: default_duration_secs(),
+            reject_market_orders: default_reject_market_orders(),
+            quotes: None,
+        }
+    }
+}
+
+impl ListingProtectionConfig {
+    pub fn validate(&self) -> Result<(), ExchangeError> {
+        if self.duration_secs == 0 {
+            return Err(ExchangeError::InvalidParameter(
+                "duration_secs must be greater than 0".to_string(),
+            ));
+        }
+        if let Some(ref quotes) = self.quotes {
+            if quotes.maker_account_id.is_empty() {
+                return Err(ExchangeError::InvalidParameter(
+                    "maker_account_id is required".to_string(),
+                ));
+            }
+            if quotes.levels == 0 || quotes.tick_offset == 0 || quotes.level_spacing_ticks == 0 {
+                return Err(ExchangeError::InvalidParameter(
+                    "levels, tick_offset and level_spacing_ticks must be greater than 0"
+                        .to_string(),
+                ));
+            }
+            if quotes.volume_per_level <= 0.0 {
+                return Err(ExchangeError::InvalidParameter(
+                    "volume_per_level must be positive".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 单档保护性挂单
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProtectiveQuote {
+    /// BUY / SELL
+    pub direction: String,
+    pub price: f64,
+    pub volume: f64,
+    /// 当前挂单（None 表示待挂）
+    pub order_id: Option<String>,
+    /// 成交后补挂次数
+    pub replenish_count: u32,
+    /// 最近一次挂单失败原因
+    pub last_error: Option<String>,
+}
+
+/// 合约上市保护时段
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListingWindow {
+    pub instrument_id: String,
+    /// 挂牌参考价（昨结）
+    pub reference_price: f64,
+    /// 上市时间（毫秒）
+    pub started_at: i64,
+    /// 保护结束时间（毫秒）
+    pub ends_at: i64,
+    pub config: ListingProtectionConfig,
+    pub quotes: Vec<ProtectiveQuote>,
+}
+
+impl ListingWindow {
+    pub fn is_active_at(&self, now_ms: i64) -> bool {
+        now_ms < self.ends_at
+    }
+}
+
+/// 新合约上市保护
+pub struct ListingProtection {
+    instrument_registry: Arc<InstrumentRegistry>,
+    /// 订单路由器（弱引用，避免循环引用）
+    order_router: RwLock<Option<Weak<OrderRouter>>>,
+    /// instrument_id -> 保护配置（开关）
+    configs: DashMap<String, ListingProtectionConfig>,
+    /// instrument_id -> 进行中的保护时段
+    windows: DashMap<String, ListingWindow>,
+    /// 串行化挂单维护（上市挂单与做市任务补挂）
+    maintenance: Mutex<()>,
+}
+
+impl ListingProtection {
+    pub fn new(instrument_registry: Arc<InstrumentRegistry>) -> Self {
+        Self {
+            instrument_registry,
+            order_router: RwLock::new(None),
+            configs: DashMap::new(),
+            windows: DashMap::new(),
+            maintenance: Mutex::new(()),
+        }
+    }
+
+    /// 设置订单路由器（挂单、撤单与查询挂单状态）
+    pub fn set_order_router(&self, router: &Arc<OrderRouter>) {
+        *self.order_router.write() = Some(Arc::downgrade(router));
+    }
+
+    fn router(&self) -> Option<Arc<OrderRouter>> {
+        self.order_router.read().as_ref().and_then(|w| w.upgrade())
+    }
+
+    /// 开启合约上市保护（上市前配置，已在保护时段内的合约不受影响）
+    pub fn set_config(
+        &self,
+        instrument_id: &str,
+        config: ListingProtectionConfig,
+    ) -> Result<(), ExchangeError> {
+        config.validate()?;
+        log::info!(
+            "[ListingProtection] {} configured: duration={}s, reject_market={}, quotes={}",
+            instrument_id,
+            config.duration_secs,
+            config.reject_market_orders,
+            config.quotes.is_some()
+        );
+        self.configs.insert(instrument_id.to_string(), config);
+        Ok(())
+    }
+
+    /// 关闭合约上市保护
+    pub fn remove_config(&self, instrument_id: &str) -> Option<ListingProtectionConfig> {
+        self.configs.remove(instrument_id).map(|(_, c)| c)
+    }
+
+    pub fn get_config(&self, instrument_id: &str) -> Option<ListingProtectionConfig> {
+        self.configs.get(instrument_id).map(|c| c.clone())
+    }
+
+    /// 进行中的保护时段
+    pub fn get_window(&self, instrument_id: &str) -> Option<ListingWindow> {
+        self.windows.get(instrument_id).map(|w| w.clone())
+    }
+
+    /// 合约上市：`Pending` 转为 `Active`，已配置保护的合约开启保护时段并挂保护单
+    ///
+    /// 参考价取订单簿最新价（撮合引擎注册合约时的昨结价）
+    pub fn activate_at(
+        &self,
+        instrument_id: &str,
+        now_ms: i64,
+    ) -> Result<Option<ListingWindow>, ExchangeError> {
+        let config = self.get_config(instrument_id);
+        let reference_price = match config {
+            Some(_) => self
+                .router()
+                .and_then(|router| router.reference_price(instrument_id))
+                .filter(|p| *p > 0.0),
+            None => None,
+        };
+        let price_tick = self
+            .instrument_registry
+            .get(instrument_id)
+            .map(|info| info.price_tick)
+            .ok_or_else(|| {
+                ExchangeError::InstrumentError(format!("Instrument {} not found", instrument_id))
+            })?;
+        if let Some(ref config) = config {
+            if config.quotes.is_some() && reference_price.is_none() {
+                return Err(ExchangeError::InstrumentError(format!(
+                    "No reference price for {}, cannot place protective quotes",
+                    instrument_id
+                )));
+            }
+        }
+
+        self.instrument_registry.activate(instrument_id)?;
+        let Some(config) = config else {
+            return Ok(None);
+        };
+
+        let reference_price = reference_price.unwrap_or_default();
+        let quotes = config
+            .quotes
+            .as_ref()
+            .map(|q| Self::build_quotes(q, reference_price, price_tick))
+            .unwrap_or_default();
+        let window = ListingWindow {
+            instrument_id: instrument_id.to_string(),
+            reference_price,
+            started_at: now_ms,
+            ends_at: now_ms.saturating_add((config.duration_secs as i64).saturating_mul(1000)),
+            config,
+            quotes,
+        };
+        log::info!(
+            "[ListingProtection] {} listed with protection until {}: reference={}, quotes={}",
+            instrument_id,
+            window.ends_at,
+            reference_price,
+            window.quotes.len()
+        );
+        self.windows.insert(instrument_id.to_string(), window);
+
+        self.replenish(instrument_id);
+        Ok(self.get_window(instrument_id))
+    }
+
+    /// 参考价 ±(offset + i × spacing) 个 tick 的买卖档位
+    fn build_quotes(
+        config: &ProtectiveQuoteConfig,
+        reference_price: f64,
+        price_tick: f64,
+    ) -> Vec<ProtectiveQuote> {
+        let round = |price: f64| (price / price_tick).round() * price_tick;
+        let mut quotes = Vec::with_capacity(config.levels as usize * 2);
+        for level in 0..config.levels {
+            let ticks = (config.tick_offset + level * config.level_spacing_ticks) as f64;
+            for (direction, price) in [
+                ("BUY", round(reference_price - ticks * price_tick)),
+                ("SELL", round(reference_price + ticks * price_tick)),
+            ] {
+                if price <= 0.0 {
+                    continue;
+                }
+                quotes.push(ProtectiveQuote {
+                    direction: direction.to_string(),
+                    price,
+                    volume: config.volume_per_level,
+                    order_id: None,
+                    replenish_count: 0,
+                    last_error: None,
+                });
+            }
+        }
+        quotes
+    }
+
+    /// 下单前校验：保护时段内拒绝市价单
+    pub fn check_order_at(
+        &self,
+        instrument_id: &str,
+        order_type: &str,
+        now_ms: i64,
+    ) -> Result<(), String> {
+        let Some(window) = self.windows.get(instrument_id) else {
+            return Ok(());
+        };
+        if window.config.reject_market_orders
+            && order_type == "MARKET"
+            && window.is_active_at(now_ms)
+        {
+            return Err(format!(
+                "Instrument {} is in listing protection until {}, only LIMIT orders are accepted",
+                instrument_id, window.ends_at
+            ));
+        }
+        Ok(())
+    }
+
+    /// 做市任务：补挂已成交的保护单，结束到期的保护时段，返回本次补挂的挂单数
+    pub fn maintain_at(&self, now_ms: i64) -> usize {
+        let instruments: Vec<(String, bool)> = self
+            .windows
+            .iter()
+            .map(|w| (w.key().clone(), w.is_active_at(now_ms)))
+            .collect();
+
+        let mut replenished = 0;
+        for (instrument_id, active) in instruments {
+            if active {
+                replenished += self.replenish(&instrument_id);
+            } else {
+                self.finish(&instrument_id);
+            }
+        }
+        replenished
+    }
+
+    /// 挂出待挂档位，已完全成交/被撤/被拒的档位按原价补挂
+    fn replenish(&self, instrument_id: &str) -> usize {
+        let _guard = self.maintenance.lock();
+        let Some(router) = self.router() else {
+            return 0;
+        };
+        // 不持有 windows 引用下单（下单校验会读取 windows）
+        let Some(window) = self.get_window(instrument_id) else {
+            return 0;
+        };
+        let Some(maker) = window
+            .config
+            .quotes
+            .as_ref()
+            .map(|q| q.maker_account_id.clone())
+        else {
+            return 0;
+        };
+
+        let mut placed = Vec::new();
+        for (index, quote) in window.quotes.iter().enumerate() {
+            let refill = match quote.order_id {
+                None => false,
+                Some(ref order_id) => match router.get_order_status(order_id) {
+                    Some(OrderStatus::Filled | OrderStatus::Cancelled | OrderStatus::Rejected)
+                    | None => true,
+                    Some(_) => continue,
+                },
+            };
+
+            let response = router.submit_order(SubmitOrderRequest {
+                account_id: maker.clone(),
+                instrument_id: instrument_id.to_string(),
+                direction: quote.direction.clone(),
+                offset: "OPEN".to_string(),
+                volume: quote.volume,
+                price: quote.price,
+                order_type: "LIMIT".to_string(),
+                time_condition: None,
+                volume_condition: None,
+                hedge_flag: None,
+                ttl_secs: None,
+                client_order_id: None,
+                close_lot_id: None,
+            });
+            let result = match (response.success, response.order_id) {
+                (true, Some(order_id)) => Ok(order_id),
+                _ => Err(response
+                    .error_message
+                    .unwrap_or_else(|| "unknown error".to_string())),
+            };
+            placed.push((index, refill, result));
+        }
+
+        let mut count = 0;
+        if let Some(mut window) = self.windows.get_mut(instrument_id) {
+            for (index, refill, result) in placed {
+                let quote = &mut window.quotes[index];
+                match result {
+                    Ok(order_id) => {
+                        quote.order_id = Some(order_id);
+                        quote.last_error = None;
+                        if refill {
+                            quote.replenish_count += 1;
+                        }
+                        count += 1;
+                    }
+                    Err(e) => {
+                        log::warn!(
+                            "[ListingProtection] Place {} {}@{} for {} failed: {}",
+                            quote.direction,
+                            quote.volume,
+                            quote.price,
+                            instrument_id,
+                            e
+                        );
+                        quote.order_id = None;
+                        quote.last_error = Some(e);
+                    }
+                }
+            }
+        }
+        count
+    }
+
+    /// 保护时段结束：撤销未成交的保护单
+    fn finish(&self, instrument_id: &str) {
+        let _guard = self.maintenance.lock();
+        let Some((_, window)) = self.windows.remove(instrument_id) else {
+            return;
+        };
+        log::info!("[ListingProtection] Protection of {} ended", instrument_id);
+
+        let (Some(router), Some(quotes)) = (self.router(), window.config.quotes.as_ref()) else {
+            return;
+        };
+        for order_id in window.quotes.iter().filter_map(|q| q.order_id.as_ref()) {
+            if matches!(
+                router.get_order_status(order_id),
+                Some(OrderStatus::Submitted | OrderStatus::PartiallyFilled)
+            ) {
+                if let Err(e) = router.cancel_order(CancelOrderRequest {
+                    account_id: quotes.maker_account_id.clone(),
+                    order_id: order_id.clone(),
+                }) {
+                    log::warn!(
+                        "[ListingProtection] Cancel protective order {} failed: {}",
+                        order_id,
+                        e
+                    );
+                }
+            }
+        }
+    }
+
+    /// 启动内置做市任务（补挂与到期撤单）
+    pub fn start_maker_task(self: &Arc<Self>, interval: Duration) -> std::thread::JoinHandle<()> {
+        let protection = Arc::downgrade(self);
+        std::thread::spawn(move || {
+            log::info!(
+                "Listing protection maker task started (interval: {}ms)",
+                interval.as_millis()
+            );
+            loop {
+                std::thread::sleep(interval);
+                match protection.upgrade() {
+                    Some(protection) => {
+                        protection.maintain_at(chrono::Utc::now().timestamp_millis());
+                    }
+                    None => break,
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::account_ext::{AccountType, OpenAccountRequest};
+    use crate::exchange::instrument_registry::{InstrumentInfo, InstrumentStatus, InstrumentType};
+    use crate::exchange::{AccountManager, TradeGateway};
+    use crate::matching::engine::ExchangeMatchingEngine;
+    use crate::risk::RejectReason;
+
+    const INSTRUMENT: &str = "NL2601";
+
+    fn setup() -> (Arc<OrderRouter>, Arc<ListingProtection>) {
+        let account_mgr = Arc::new(AccountManager::new());
+        for account_id in ["maker", "taker"] {
+            account_mgr
+                .open_account(OpenAccountRequest {
+                    user_id: account_id.to_string(),
+                    account_id: Some(account_id.to_string()),
+                    account_name: account_id.to_string(),
+                    init_cash: 10_000_000.0,
+                    account_type: AccountType::Individual,
+                })
+                .unwrap();
+        }
+
+        // 挂牌参考价 100，订单簿为空
+        let matching_engine = Arc::new(ExchangeMatchingEngine::new());
+        matching_engine
+            .register_instrument(INSTRUMENT.to_string(), 100.0)
+            .unwrap();
+        let instrument_registry = Arc::new(InstrumentRegistry::new());
+        let mut info = InstrumentInfo::new(
+            INSTRUMENT.to_string(),
+            INSTRUMENT.to_string(),
+            InstrumentType::CommodityFuture,
+            "SHFE".to_string(),
+        );
+        info.contract_multiplier = 1;
+        info.status = InstrumentStatus::Pending;
+        instrument_registry.register(info).unwrap();
+
+        let protection = Arc::new(ListingProtection::new(instrument_registry.clone()));
+        let trade_gateway = Arc::new(TradeGateway::new(account_mgr.clone()));
+        let mut router = OrderRouter::new(
+            account_mgr,
+            matching_engine,
+            instrument_registry,
+            trade_gateway,
+        );
+        router.set_listing_protection(protection.clone());
+        let router = Arc::new(router);
+        protection.set_order_router(&router);
+        (router, protection)
+    }
+
+    fn order(
+        account_id: &str,
+        direction: &str,
+        order_type: &str,
+        price: f64,
+    ) -> SubmitOrderRequest {
+        SubmitOrderRequest {
+            account_id: account_id.to_string(),
+            instrument_id: INSTRUMENT.to_string(),
+            direction: direction.to_string(),
+            offset: "OPEN".to_string(),
+            volume: 2.0,
+            price,
+            order_type: order_type.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// 首日空簿：保护时段内市价单被拒，保护单成交后补挂，时段结束撤单
+    #[test]
+    fn test_listing_protection_rejects_market_and_replenishes_quotes() {
+        let (router, protection) = setup();
+        protection
+            .set_config(
+                INSTRUMENT,
+                ListingProtectionConfig {
+                    duration_secs: 600,
+                    reject_market_orders: true,
+                    quotes: Some(ProtectiveQuoteConfig {
+                        maker_account_id: "maker".to_string(),
+                        levels: 2,
+                        tick_offset: 1,
+                        level_spacing_ticks: 1,
+                        volume_per_level: 2.0,
+                    }),
+                },
+            )
+            .unwrap();
+
+        // 待上市合约不接受订单
+        let registry = router.instrument_registry().clone();
+        assert_eq!(registry.status(INSTRUMENT), Some(InstrumentStatus::Pending));
+        let rejected = router.submit_order(order("taker", "BUY", "LIMIT", 100.0));
+        assert!(!rejected.success);
+
+        let now = chrono::Utc::now().timestamp_millis();
+        let window = protection.activate_at(INSTRUMENT, now).unwrap().unwrap();
+        assert_eq!(registry.status(INSTRUMENT), Some(InstrumentStatus::Active));
+        assert_eq!(window.reference_price, 100.0);
+        let prices: Vec<(String, f64)> = window
+            .quotes
+            .iter()
+            .map(|q| (q.direction.clone(), (q.price * 10.0).round() / 10.0))
+            .collect();
+        assert_eq!(
+            prices,
+            vec![
+                ("BUY".to_string(), 99.8),
+                ("SELL".to_string(), 100.2),
+                ("BUY".to_string(), 99.6),
+                ("SELL".to_string(), 100.4),
+            ]
+        );
+        assert!(window.quotes.iter().all(|q| q.order_id.is_some()));
+
+        // 保护时段内市价单被拒
+        let market = router.submit_order(order("taker", "BUY", "MARKET", 0.0));
+        assert!(!market.success);
+        assert_eq!(
+            market.error_code,
+            Some(RejectReason::ListingProtection.error_code())
+        );
+
+        // 限价单吃掉卖一保护单后按原价补挂
+        let ask = window.quotes[1].clone();
+        let taken = router.submit_order(order("taker", "BUY", "LIMIT", ask.price));
+        assert!(taken.success);
+        assert_eq!(
+            router.get_order_status(ask.order_id.as_ref().unwrap()),
+            Some(OrderStatus::Filled)
+        );
+        assert_eq!(protection.maintain_at(now + 1), 1);
+        let refilled = protection.get_window(INSTRUMENT).unwrap().quotes[1].clone();
+        assert_eq!(refilled.replenish_count, 1);
+        assert_eq!(refilled.price, ask.price);
+        assert_ne!(refilled.order_id, ask.order_id);
+        assert_eq!(
+            router.get_order_status(refilled.order_id.as_ref().unwrap()),
+            Some(OrderStatus::Submitted)
+        );
+        // 未成交的档位不重复挂单
+        assert_eq!(protection.maintain_at(now + 2), 0);
+
+        // 时段结束：撤销保护单，市价单恢复
+        protection.maintain_at(window.ends_at);
+        assert!(protection.get_window(INSTRUMENT).is_none());
+        assert_eq!(
+            router.get_order_status(refilled.order_id.as_ref().unwrap()),
+            Some(OrderStatus::Cancelled)
+        );
+        assert!(protection
+            .check_order_at(INSTRUMENT, "MARKET", window.ends_at)
+            .is_ok());
+    }
+
+    /// 未配置保护的合约上市后直接正常交易
+    #[test]
+    fn test_activate_without_protection() {
+        let (router, protection) = setup();
+        let now = chrono::Utc::now().timestamp_millis();
+        assert!(protection.activate_at(INSTRUMENT, now).unwrap().is_none());
+        assert!(protection.get_window(INSTRUMENT).is_none());
+        assert!(protection.activate_at(INSTRUMENT, now).is_err());
+
+        let market = router.submit_order(order("taker", "BUY", "MARKET", 0.0));
+        assert_ne!(
+            market.error_code,
+            Some(RejectReason::ListingProtection.error_code())
+        );
+    }
+}
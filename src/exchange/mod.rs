@@ -20,6 +20,9 @@ pub mod settlement;
 /// 合约注册表
 pub mod instrument_registry;
 
+/// 规范化合约代码（交易所 + 合约，别名归一）
+pub mod instrument_code;
+
 /// 用户管理
 pub mod user_mgr;
 
@@ -38,19 +41,164 @@ pub mod conditional_order;
 /// 交易状态机 @yutiansut @quantaxis
 pub mod trading_session;
 
+/// 价差单（跨合约套利）引擎 @yutiansut @quantaxis
+pub mod spread_order;
+
+/// 算法单（TWAP/VWAP）执行引擎 @yutiansut @quantaxis
+pub mod algo_order;
+
+/// 结算对账报告 @yutiansut @quantaxis
+pub mod reconciliation;
+
+/// 移仓换月（合约切换） @yutiansut @quantaxis
+pub mod position_roll;
+
+/// 穿仓处理（风险准备金垫付） @yutiansut @quantaxis
+pub mod deficit;
+
+/// 持仓均价（加权均价统一实现） @yutiansut @quantaxis
+pub mod position_cost;
+
+/// 持仓批次明细（FIFO/LIFO） @yutiansut @quantaxis
+pub mod position_lots;
+
+/// 盈亏归因分析 @yutiansut @quantaxis
+pub mod pnl_attribution;
+
+/// 大宗交易协商成交 @yutiansut @quantaxis
+pub mod block_trade;
+
+/// 紧急停机与安全恢复 @yutiansut @quantaxis
+pub mod emergency;
+
+/// 开盘标记与交易日重置（测试环境） @yutiansut @quantaxis
+pub mod trading_day_manager;
+
+/// 账户操作租约（多实例共享存储） @yutiansut @quantaxis
+pub mod account_lease;
+
+/// 订单存活时长（到期自动撤单） @yutiansut @quantaxis
+pub mod order_ttl;
+
+/// 在途订单看门狗（撮合回应丢失检测与对账） @yutiansut @quantaxis
+pub mod order_watchdog;
+
+/// 确定性运行模式（虚拟时钟、种子化ID、单线程回放） @yutiansut @quantaxis
+pub mod deterministic;
+
+/// 订单路由影子模式（新版本撮合旁路验证） @yutiansut @quantaxis
+pub mod shadow_mode;
+
+/// 新合约上市保护（拒绝市价单 / 保护性做市挂单） @yutiansut @quantaxis
+pub mod listing_protection;
+
+/// 极端行情熔断（波动超阈值进入集合竞价冷静期） @yutiansut @quantaxis
+pub mod circuit_breaker;
+
+/// 功能灰度开关（按账户标签/百分比/白名单放量） @yutiansut @quantaxis
+pub mod feature_gate;
+
+/// 成交事件总线（成交记录/行情统计/逐笔广播与账户处理解耦） @yutiansut @quantaxis
+pub mod trade_bus;
+
+/// 账户冻结资金明细（按挂单拆分与对账） @yutiansut @quantaxis
+pub mod frozen_breakdown;
+
+/// 账户标签（运营分类与按标签批量操作） @yutiansut @quantaxis
+pub mod account_tags;
+
+/// 闲置资金按日计息 @yutiansut @quantaxis
+pub mod interest;
+
+/// 日终结算任务（账户分片并行与断点续跑） @yutiansut @quantaxis
+pub mod settlement_task;
+
+/// 资金定点核算（i64 定点金额与浮点双轨验证） @yutiansut @quantaxis
+pub mod fixed_point;
+
+/// 订单状态机与状态变迁历史 @yutiansut @quantaxis
+pub mod order_history;
+
+/// 测试环境时间控制（虚拟时间快进） @yutiansut @quantaxis
+pub mod time_control;
+
 // 重导出核心类型
-pub use account_mgr::AccountManager;
+pub use account_lease::{AccountLease, AccountLeaseConfig, AccountLeaseHold, AccountLeaseManager};
+pub use account_mgr::{
+    AccountExport, AccountImportSummary, AccountManager, TradingRestriction, TradingRestrictionInfo,
+};
+pub use account_tags::{
+    AccountTagBook, TagAction, TagAuditRecord, TagBatchResult, TagStat, MAX_MARGIN_SURCHARGE,
+};
+pub use algo_order::{AlgoOrderEngine, AlgoOrderStatistics, ALGO_ORDER_ENGINE};
+pub use block_trade::{BlockTradeEngine, BlockTradeStatistics, BLOCK_TRADE_ENGINE};
 pub use capital_mgr::{CapitalManager, FundTransaction, TransactionStatus, TransactionType};
+pub use circuit_breaker::{
+    CircuitBreaker, CircuitBreakerConfig, CircuitBreakerEvent, CircuitBreakerEventKind,
+    CircuitBreakerHalt, CircuitBreakerRule,
+};
 pub use conditional_order::{ConditionalOrderEngine, ConditionalOrderStatistics, CONDITIONAL_ORDER_ENGINE};
-pub use exchange_types::{ExchangeOrderRecord, ExchangeResponse, ExchangeTradeRecord};
+pub use deficit::{DeficitRecord, RiskReserveStatus};
+pub use deterministic::{
+    Clock, DeterministicConfig, DeterministicExchange, ExchangeClock, ReplayEvent, ReplayOutput,
+    SeededIdGenerator, VirtualClock,
+};
+pub use emergency::{EmergencyShutdown, MaintenanceInfo, ShutdownRecord};
+pub use exchange_types::{
+    ExchangeOrderRecord, ExchangeResponse, ExchangeTradeRecord, HedgeFlag, StandardTradeFields,
+    TradeType,
+};
+pub use feature_gate::{FeatureGate, FeatureGateConfig, FeatureRule, FEATURE_GATE};
+pub use fixed_point::{
+    AmountScale, FixedAccount, FixedAccountView, FixedDrift, FixedDriftStats, FixedFill,
+    FixedLedger, FixedMarks, FixedPointConfig, FloatBalances,
+};
+pub use frozen_breakdown::{FrozenBreakdown, FrozenItem, FrozenKind, FrozenOrderRef, FrozenSummary};
 pub use id_generator::ExchangeIdGenerator;
 pub use instrument_registry::InstrumentRegistry;
+pub use instrument_code::{AliasConflict, InstrumentCode};
+pub use interest::{InterestBasis, InterestConfig};
+pub use listing_protection::{
+    ListingProtection, ListingProtectionConfig, ListingWindow, ProtectiveQuote,
+    ProtectiveQuoteConfig,
+};
+pub use order_history::{OrderHistoryTracker, OrderLifecycleState, OrderStateChange};
 pub use order_router::OrderRouter;
+pub use order_ttl::{OrderTtlEntry, OrderTtlManager};
+pub use order_watchdog::{
+    AckResolution, OrderWatchdog, OrderWatchdogConfig, OrderWatchdogStats, PendingAck,
+    ReconcileReport,
+};
+pub use pnl_attribution::{
+    AccountPnlAttribution, InstrumentMark, InstrumentPnlAttribution, PnlAttributionQuery, PnlFill,
+    PnlLedger, TradePnlAttribution,
+};
+pub use position_cost::{CostTrade, FillAverage, PositionCost, PositionCostBook, SideCost};
+pub use position_lots::{
+    LotBook, LotClose, LotMark, LotMethod, LotMismatch, LotSide, LotTradeResult,
+    PositionDetailView, PositionLot, PositionLotDetail, PositionLotView, PositionLotsConfig,
+};
+pub use position_roll::PositionRoller;
 pub use priority_queue::{
-    OrderPriority, PriorityOrderQueue, PriorityOrderRequest, PriorityQueueStatistics,
+    OrderPriority, PriorityCounts, PriorityOrderQueue, PriorityOrderRequest,
+    PriorityQueueStatistics,
 };
+pub use reconciliation::{AccountDiscrepancy, ReconciliationReport};
 pub use settlement::SettlementEngine;
+pub use settlement_task::{SettlementProgress, SettlementTaskConfig, SettlementTaskStatus};
+pub use shadow_mode::{
+    MatchOutcome, ShadowMatcher, ShadowMismatch, ShadowMode, ShadowModeConfig, ShadowModeStats,
+};
+pub use spread_order::{SpreadOrderEngine, SpreadOrderStatistics, SPREAD_ORDER_ENGINE};
+pub use time_control::{
+    AdvanceTimeReport, FiredTimeEvent, TimeControlConfig, TimeController, TimeEventKind,
+};
+pub use trade_bus::{
+    TradeConsumerStats, TradeEvent, TradeEventBus, TradeEventBusConfig, TradeEventBusStats,
+    TradeEventConsumer,
+};
 pub use trade_gateway::{Notification, TradeGateway};
+pub use trading_day_manager::{OpeningMark, TradingDayManager, TradingDayResetReport};
 pub use trading_session::{
     ExchangeType, OrderValidation, TradingCalendar, TradingSession, TradingStateMachine,
 };
@@ -0,0 +1,349 @@
+//! 订单状态机与状态变迁历史
+//! @yutiansut @quantaxis
+//!
+//! 订单从创建到终态经历：待报 → 已报 → 部分成交 → 全部成交 / 部分撤单 / 已撤单，任一非终态可被拒绝。
+//! - 每次状态变更记录原状态、新状态、时间戳、原因与当时的成交/剩余数量，形成订单时间线
+//! - 状态机校验迁移合法性：终态不再变化，非法迁移（如已撤单后成交）被忽略并告警
+//! - 部分成交后继续成交（部分成交 → 部分成交）作为新的变更记录，便于回看每笔成交
+//! - 配置落盘目录后变更追加写入 `order_history.jsonl`，启动时加载，重启后时间线仍可查询
+
+use dashmap::DashMap;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+use crate::ExchangeError;
+
+/// 状态历史落盘文件名
+pub const ORDER_HISTORY_FILE: &str = "order_history.jsonl";
+
+/// 订单生命周期状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum OrderLifecycleState {
+    /// 待报（已创建，尚未被撮合引擎接受）
+    PendingReport,
+    /// 已报（在订单簿中等待成交）
+    Reported,
+    /// 部分成交
+    PartiallyFilled,
+    /// 全部成交
+    Filled,
+    /// 部分成交后撤销剩余
+    PartiallyCancelled,
+    /// 已撤单（无成交）
+    Cancelled,
+    /// 已拒绝
+    Rejected,
+}
+
+impl OrderLifecycleState {
+    /// 中文名称
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::PendingReport => "待报",
+            Self::Reported => "已报",
+            Self::PartiallyFilled => "部分成交",
+            Self::Filled => "全部成交",
+            Self::PartiallyCancelled => "部分撤单",
+            Self::Cancelled => "已撤单",
+            Self::Rejected => "已拒绝",
+        }
+    }
+
+    /// 是否为终态
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            Self::Filled | Self::PartiallyCancelled | Self::Cancelled | Self::Rejected
+        )
+    }
+
+    /// 订单的初始状态（首条记录只能是待报或直接拒单）
+    pub fn is_initial(&self) -> bool {
+        matches!(self, Self::PendingReport | Self::Rejected)
+    }
+
+    /// 状态迁移是否合法
+    pub fn can_transition_to(&self, to: OrderLifecycleState) -> bool {
+        use OrderLifecycleState::*;
+        match self {
+            PendingReport => matches!(
+                to,
+                Reported | PartiallyFilled | Filled | Cancelled | Rejected
+            ),
+            Reported => matches!(to, PartiallyFilled | Filled | Cancelled | Rejected),
+            PartiallyFilled => matches!(to, PartiallyFilled | Filled | PartiallyCancelled),
+            Filled | PartiallyCancelled | Cancelled | Rejected => false,
+        }
+    }
+}
+
+/// 一次状态变更
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OrderStateChange {
+    pub order_id: String,
+    pub account_id: String,
+    pub instrument_id: String,
+    /// 原状态（订单首条记录为 None）
+    pub from: Option<OrderLifecycleState>,
+    pub to: OrderLifecycleState,
+    /// 变更原因
+    pub reason: String,
+    /// 变更后累计成交量
+    pub filled_volume: f64,
+    /// 变更后剩余未成交量
+    pub volume_left: f64,
+    /// 变更时间（纳秒）
+    pub timestamp: i64,
+}
+
+impl OrderStateChange {
+    pub fn new(
+        order_id: &str,
+        account_id: &str,
+        instrument_id: &str,
+        to: OrderLifecycleState,
+        reason: impl Into<String>,
+    ) -> Self {
+        Self {
+            order_id: order_id.to_string(),
+            account_id: account_id.to_string(),
+            instrument_id: instrument_id.to_string(),
+            from: None,
+            to,
+            reason: reason.into(),
+            filled_volume: 0.0,
+            volume_left: 0.0,
+            timestamp: 0,
+        }
+    }
+
+    /// 设置变更后的成交/剩余数量
+    pub fn with_volumes(mut self, filled_volume: f64, volume_left: f64) -> Self {
+        self.filled_volume = filled_volume;
+        self.volume_left = volume_left;
+        self
+    }
+
+    /// 设置变更时间（纳秒）
+    pub fn at(mut self, timestamp: i64) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+}
+
+/// 订单状态历史追踪器
+#[derive(Default)]
+pub struct OrderHistoryTracker {
+    /// order_id -> 状态变迁时间线（按发生顺序）
+    history: DashMap<String, Vec<OrderStateChange>>,
+    /// 落盘文件（None 表示仅内存）
+    persist_file: Mutex<Option<File>>,
+}
+
+impl OrderHistoryTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 落盘到指定目录，并加载已有的状态变更历史
+    pub fn with_persist_dir(dir: impl AsRef<Path>) -> Result<Self, ExchangeError> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir).map_err(|e| {
+            ExchangeError::StorageError(format!("Create order history dir failed: {}", e))
+        })?;
+        let path = dir.join(ORDER_HISTORY_FILE);
+
+        let tracker = Self::new();
+        if path.exists() {
+            let file = File::open(&path).map_err(|e| {
+                ExchangeError::StorageError(format!("Open order history file failed: {}", e))
+            })?;
+            let mut loaded = 0usize;
+            for line in std::io::BufReader::new(file).lines() {
+                let line = line.map_err(|e| {
+                    ExchangeError::StorageError(format!("Read order history file failed: {}", e))
+                })?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str::<OrderStateChange>(&line) {
+                    Ok(change) => {
+                        tracker
+                            .history
+                            .entry(change.order_id.clone())
+                            .or_default()
+                            .push(change);
+                        loaded += 1;
+                    }
+                    Err(e) => {
+                        log::warn!("[OrderHistory] Skip corrupted change in {:?}: {}", path, e)
+                    }
+                }
+            }
+            log::info!(
+                "Loaded {} order state changes of {} orders",
+                loaded,
+                tracker.history.len()
+            );
+        }
+
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| {
+                ExchangeError::StorageError(format!("Open order history file failed: {}", e))
+            })?;
+        *tracker.persist_file.lock() = Some(file);
+        Ok(tracker)
+    }
+
+    /// 记录状态变更，返回是否生效
+    ///
+    /// 原状态取自时间线最后一条；与当前状态相同（部分成交除外）或迁移不合法时忽略
+    pub fn record(&self, mut change: OrderStateChange) -> bool {
+        let mut timeline = self.history.entry(change.order_id.clone()).or_default();
+        let from = timeline.last().map(|last| last.to);
+        let valid = match from {
+            None => change.to.is_initial(),
+            Some(from) => from.can_transition_to(change.to),
+        };
+        if !valid {
+            drop(timeline);
+            if from.is_none() {
+                self.history
+                    .remove_if(&change.order_id, |_, timeline| timeline.is_empty());
+            }
+            if from != Some(change.to) {
+                log::warn!(
+                    "[OrderHistory] Ignore invalid transition of {}: {:?} -> {:?} ({})",
+                    change.order_id,
+                    from,
+                    change.to,
+                    change.reason
+                );
+            }
+            return false;
+        }
+        change.from = from;
+
+        if let Some(ref mut file) = *self.persist_file.lock() {
+            let written = serde_json::to_string(&change)
+                .map_err(|e| e.to_string())
+                .and_then(|line| writeln!(file, "{}", line).map_err(|e| e.to_string()));
+            if let Err(e) = written {
+                log::error!(
+                    "[OrderHistory] Persist change of {} failed: {}",
+                    change.order_id,
+                    e
+                );
+            }
+        }
+        timeline.push(change);
+        true
+    }
+
+    /// 订单状态变迁时间线
+    pub fn history(&self, order_id: &str) -> Option<Vec<OrderStateChange>> {
+        self.history.get(order_id).map(|timeline| timeline.clone())
+    }
+
+    /// 订单当前状态
+    pub fn current_state(&self, order_id: &str) -> Option<OrderLifecycleState> {
+        self.history
+            .get(order_id)
+            .and_then(|timeline| timeline.last().map(|change| change.to))
+    }
+
+    /// 有状态历史的订单数
+    pub fn len(&self) -> usize {
+        self.history.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.history.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use OrderLifecycleState::*;
+
+    fn change(to: OrderLifecycleState, reason: &str, filled: f64, ts: i64) -> OrderStateChange {
+        OrderStateChange::new("O1", "ACC1", "IF2501", to, reason)
+            .with_volumes(filled, 10.0 - filled)
+            .at(ts)
+    }
+
+    #[test]
+    fn test_state_machine_transitions() {
+        assert!(PendingReport.can_transition_to(Reported));
+        assert!(PendingReport.can_transition_to(Rejected));
+        assert!(Reported.can_transition_to(Cancelled));
+        assert!(PartiallyFilled.can_transition_to(PartiallyFilled));
+        assert!(PartiallyFilled.can_transition_to(PartiallyCancelled));
+        assert!(!PartiallyFilled.can_transition_to(Cancelled));
+        assert!(!Reported.can_transition_to(PartiallyCancelled));
+        assert!(!Reported.can_transition_to(PendingReport));
+        for terminal in [Filled, PartiallyCancelled, Cancelled, Rejected] {
+            assert!(terminal.is_terminal());
+            assert!(!terminal.can_transition_to(PartiallyFilled));
+        }
+        assert!(Rejected.is_initial());
+        assert!(!Reported.is_initial());
+    }
+
+    #[test]
+    fn test_timeline_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let tracker = OrderHistoryTracker::with_persist_dir(dir.path()).unwrap();
+
+        assert!(tracker.record(change(PendingReport, "order created", 0.0, 1)));
+        assert!(tracker.record(change(Reported, "accepted by matching engine", 0.0, 2)));
+        assert!(tracker.record(change(PartiallyFilled, "filled 3@3800", 3.0, 3)));
+        assert!(tracker.record(change(PartiallyFilled, "filled 2@3801", 5.0, 4)));
+        assert!(tracker.record(change(PartiallyCancelled, "cancelled by user", 5.0, 5)));
+        // 终态后的迁移与重复状态被忽略
+        assert!(!tracker.record(change(Filled, "late fill", 10.0, 6)));
+        assert!(!tracker.record(change(PartiallyCancelled, "duplicate cancel", 5.0, 7)));
+        // 首条记录只能是待报或拒单
+        let orphan = OrderStateChange::new("O2", "ACC1", "IF2501", Reported, "accepted");
+        assert!(!tracker.record(orphan));
+
+        drop(tracker);
+        let restored = OrderHistoryTracker::with_persist_dir(dir.path()).unwrap();
+        let timeline = restored.history("O1").unwrap();
+        let states: Vec<_> = timeline.iter().map(|c| (c.from, c.to)).collect();
+        assert_eq!(
+            states,
+            vec![
+                (None, PendingReport),
+                (Some(PendingReport), Reported),
+                (Some(Reported), PartiallyFilled),
+                (Some(PartiallyFilled), PartiallyFilled),
+                (Some(PartiallyFilled), PartiallyCancelled),
+            ]
+        );
+        assert_eq!(
+            timeline.iter().map(|c| c.timestamp).collect::<Vec<_>>(),
+            vec![1, 2, 3, 4, 5]
+        );
+        assert_eq!(timeline[3].reason, "filled 2@3801");
+        assert_eq!(
+            (timeline[4].filled_volume, timeline[4].volume_left),
+            (5.0, 5.0)
+        );
+        assert_eq!(restored.current_state("O1"), Some(PartiallyCancelled));
+        assert!(restored.history("O2").is_none());
+
+        // 恢复后继续追加
+        let rejected =
+            OrderStateChange::new("O3", "ACC1", "IF2501", Rejected, "insufficient funds");
+        assert!(restored.record(rejected));
+        assert_eq!(restored.len(), 2);
+    }
+}
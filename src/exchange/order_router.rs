@@ -2,18 +2,55 @@
 //!
 //! 负责订单的接收、风控检查、路由到撮合引擎以及撤单处理
 
+use crate::core::account_ext::AccountType;
 use crate::core::{Order, QAOrder, QAOrderExt};
-use crate::exchange::{AccountManager, InstrumentRegistry, TradeGateway};
-use crate::market::MarketDataBroadcaster;
-use crate::matching::engine::{ExchangeMatchingEngine, InstrumentAsset};
-use crate::matching::{orders, Failed, OrderDirection, OrderType, Success};
-use crate::risk::pre_trade_check::{OrderCheckRequest, PreTradeCheck, RiskCheckResult};
+use crate::exchange::block_trade::BlockTrade;
+use crate::exchange::circuit_breaker::CircuitBreaker;
+use crate::exchange::deterministic::{Clock, ExchangeClock};
+use crate::exchange::feature_gate::{FeatureGate, FEATURE_GATE, USE_HIGH_PERF_MATCHING};
+use crate::exchange::frozen_breakdown::{FrozenBreakdown, FrozenOrderRef, FrozenRecord};
+use crate::exchange::instrument_registry::InstrumentStatus;
+use crate::exchange::listing_protection::ListingProtection;
+use crate::exchange::order_history::{OrderHistoryTracker, OrderLifecycleState, OrderStateChange};
+use crate::exchange::order_ttl::{OrderTtlEntry, OrderTtlManager};
+use crate::exchange::order_watchdog::{AckResolution, OrderWatchdog, PendingAck, ReconcileReport};
+use crate::exchange::shadow_mode::{ShadowMode, ShadowRequest};
+use crate::exchange::trade_bus::{TradeEvent, TradeEventBus};
+use crate::exchange::{
+    AccountLeaseHold, AccountLeaseManager, AccountManager, HedgeFlag, InstrumentRegistry,
+    TradeGateway, TradeType,
+};
+use crate::market::{MarketDataBroadcaster, MarketDataEvent};
+use crate::matching::engine::{
+    resting_orders, ExchangeMatchingEngine, InstrumentAsset, RestingOrder,
+};
+use crate::matching::book_limits::OrderBookLimiter;
+use crate::matching::high_perf::{match_pooled_order, HighPerfOrderMatcher};
+use crate::matching::limit_queue::{LimitQueueIndex, QueuePosition};
+use crate::matching::sharded::{
+    MatchingFaultEvent, MatchingFaultState, MatchingRecoveryHandler, ShardedMatchingEngine,
+};
+use crate::matching::{
+    orders, Failed, OrderDirection, OrderType, Orderbook, SequenceTurn, Success,
+};
+use crate::observability::sampling::TRACE_SAMPLER;
+use crate::perf::PooledOrder;
+use crate::risk::pre_trade_check::{
+    OrderCheckRequest, PreTradeCheck, PriceTickMode, ReferenceQuote, RiskCheckResult,
+};
+use crate::risk::{
+    DuplicateOrderGuard, MarketMakerMonitor, OrderFingerprint, OrderRateLimiter,
+    PositionConcentration, RejectReason, RejectionStats, TradeVolumeLimiter,
+};
+use crate::service::http::account_admin::log_audit;
+use crate::service::http::models::{AuditLogType, AuditResult};
+use crate::utils::time_service::{time_service, TimeService};
+use crate::utils::timestamp::nanos_to_secs;
 use crate::ExchangeError;
-use chrono::Local;
 use dashmap::DashMap;
 use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
-use std::collections::VecDeque;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
@@ -108,7 +145,9 @@ impl VolumeCondition {
 
 /// 订单提交请求（交易层 - 只关心账户）
 /// @yutiansut @quantaxis
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// 测试与内部构造可只填必需字段，其余用 `..Default::default()` 补齐
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SubmitOrderRequest {
     pub account_id: String, // 交易系统只关心账户ID
     pub instrument_id: String,
@@ -123,6 +162,18 @@ pub struct SubmitOrderRequest {
     /// 数量条件: ANY/MIN/ALL (ALL + IOC = FOK)
     #[serde(default)]
     pub volume_condition: Option<VolumeCondition>,
+    /// 投机套保标志（默认投机），透传到成交回报
+    #[serde(default)]
+    pub hedge_flag: Option<HedgeFlag>,
+    /// 存活时长（秒），到期未完全成交自动撤销剩余部分
+    #[serde(default)]
+    pub ttl_secs: Option<u64>,
+    /// 客户端幂等键，重复订单检测时以其为准
+    #[serde(default)]
+    pub client_order_id: Option<String>,
+    /// 指定平仓批次：仅本委托的成交优先消耗该批次，委托结束（成交/撤单/拒单/过期）即失效
+    #[serde(default)]
+    pub close_lot_id: Option<u64>,
 }
 
 /// 撤单请求（交易层 - 只关心账户）
@@ -132,6 +183,17 @@ pub struct CancelOrderRequest {
     pub order_id: String,
 }
 
+/// 改单请求：撤销原订单后按新价格/数量重新下单（未指定的字段沿用原订单）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AmendOrderRequest {
+    pub account_id: String,
+    pub order_id: String,
+    #[serde(default)]
+    pub new_price: Option<f64>,
+    #[serde(default)]
+    pub new_volume: Option<f64>,
+}
+
 /// 订单提交响应
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SubmitOrderResponse {
@@ -140,6 +202,21 @@ pub struct SubmitOrderResponse {
     pub status: Option<String>, // 订单最终状态：submitted/filled/partially_filled/rejected
     pub error_message: Option<String>,
     pub error_code: Option<u32>,
+    /// 价格按最小变动价位规整后的实际委托价（未调整时为空）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub adjusted_price: Option<f64>,
+}
+
+/// TTL 到期撤单失败后的重试间隔（毫秒）
+const ORDER_TTL_RETRY_MS: i64 = 1000;
+
+/// 在途请求计数守卫（紧急停机时等待计数归零）
+struct InFlightGuard<'a>(&'a AtomicU64);
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
 }
 
 /// 提交行为控制选项
@@ -148,6 +225,8 @@ pub struct SubmitOrderResponse {
 struct OrderSubmitOptions {
     /// 是否为强制（风险绕过）订单
     force: bool,
+    /// 是否为改单重新下单（已按改单计入频率限制，不再计下单）
+    amend: bool,
 }
 
 
@@ -183,6 +262,8 @@ struct OrderRouteInfo {
     matching_engine_order_id: Option<u64>, // 撮合引擎订单ID (用于撤单)
     time_condition: TimeCondition,         // 时间条件 (IOC/GFD/GTC等)
     volume_condition: VolumeCondition,     // 数量条件 (ANY/MIN/ALL)
+    hedge_flag: HedgeFlag,                 // 投机套保标志
+    high_perf_path: bool,                  // 下单时的灰度决策（生命周期内不变）
 }
 
 /// 订单统计信息
@@ -271,8 +352,89 @@ pub struct OrderRouter {
     /// 是否启用优先级队列
     priority_queue_enabled: AtomicBool,
 
+    /// 优先级调度序号
+    priority_seq: AtomicU64,
+
+    /// 等待调度结果的提交线程 (seq -> 结果通道)
+    priority_waiters: Arc<DashMap<u64, crossbeam::channel::Sender<SubmitOrderResponse>>>,
+
     /// 交易状态机（可选） @yutiansut @quantaxis
     trading_state_machine: Option<Arc<crate::exchange::TradingStateMachine>>,
+
+    /// 分片撮合引擎（可选，设置后按合约路由到分片）
+    sharded_engine: Option<Arc<ShardedMatchingEngine>>,
+
+    /// 集合竞价指示价推送器（可选，需配合交易状态机）
+    auction_indicator: Option<Arc<crate::market::AuctionIndicatorPublisher>>,
+
+    /// 拒单原因统计 @yutiansut @quantaxis
+    rejection_stats: Arc<RejectionStats>,
+
+    /// 涨跌停板管理器（可选，设置后校验订单价格）
+    price_limit_manager: Option<Arc<crate::risk::PriceLimitManager>>,
+
+    /// 订单簿挂单数限制器（可选，设置后统计挂单并限制新挂单）
+    book_limiter: Option<Arc<OrderBookLimiter>>,
+
+    /// 账户下单/撤单频率限制器（可选，设置后按账户类型限流）
+    rate_limiter: Option<Arc<OrderRateLimiter>>,
+
+    /// 重复订单检测（可选，设置后拦截短时间内重复提交的相同订单）
+    duplicate_guard: Option<Arc<DuplicateOrderGuard>>,
+
+    /// 单日成交量限制器（可选，设置后累计成交并在达限后拒单）
+    volume_limiter: Option<Arc<TradeVolumeLimiter>>,
+
+    /// 持仓集中度监测（可选，与盘中风控共用，超限账户在该合约禁止开仓）
+    position_concentration: Option<Arc<PositionConcentration>>,
+
+    /// 做市商义务考核（可选，持有以供查询考核报告）
+    market_maker_monitor: Option<Arc<MarketMakerMonitor>>,
+
+    /// 功能灰度开关（默认全局实例，管理端热更新）
+    feature_gate: Arc<FeatureGate>,
+
+    /// 高性能撮合通道（可选，灰度 `use_high_perf_matching` 命中的订单在独立撮合线程撮合）
+    high_perf_matcher: Option<Arc<HighPerfOrderMatcher>>,
+
+    /// 账户操作租约（可选，多实例共享存储部署时启用）
+    account_lease: Option<Arc<AccountLeaseManager>>,
+
+    /// 订单存活时长管理（到期自动撤单）
+    order_ttl: Arc<OrderTtlManager>,
+
+    /// 订单状态变迁历史（状态机时间线）
+    order_history: Arc<OrderHistoryTracker>,
+
+    /// 价位排队索引（按订单簿版本缓存的排队位次）
+    limit_queue: LimitQueueIndex,
+
+    /// 在途订单看门狗（可选，检测撮合回应丢失并对账）
+    order_watchdog: Option<Arc<OrderWatchdog>>,
+
+    /// 影子撮合模式（可选，新版本撮合逻辑旁路验证）
+    shadow_mode: Option<Arc<ShadowMode>>,
+
+    /// 新合约上市保护（可选，保护时段内拒绝市价单）
+    listing_protection: Option<Arc<ListingProtection>>,
+
+    /// 极端行情熔断（可选，冷静期内只接受限价单）
+    circuit_breaker: Option<Arc<CircuitBreaker>>,
+
+    /// 成交事件总线（成交记录、行情统计/K线、逐笔广播从这里消费，与账户处理解耦）
+    trade_bus: Arc<TradeEventBus>,
+
+    /// 时间来源（确定性模式下为虚拟时钟）
+    clock: ExchangeClock,
+
+    /// 订单到期（TTL）时间源（测试环境可为虚拟时间，与时间控制共享）
+    time_service: Arc<TimeService>,
+
+    /// 停止接单原因（紧急停机期间拒绝下单/撤单）
+    halt_reason: RwLock<Option<String>>,
+
+    /// 在途下单/撤单请求数
+    in_flight: AtomicU64,
 }
 
 impl OrderRouter {
@@ -283,6 +445,7 @@ impl OrderRouter {
         trade_gateway: Arc<TradeGateway>,
     ) -> Self {
         let risk_checker = Arc::new(PreTradeCheck::new(account_mgr.clone()));
+        risk_checker.set_instrument_registry(instrument_registry.clone());
 
         Self {
             account_mgr,
@@ -307,7 +470,79 @@ impl OrderRouter {
             flush_stop_signal: Arc::new(AtomicBool::new(false)),
             priority_queue: None, // 默认不启用
             priority_queue_enabled: AtomicBool::new(false),
+            priority_seq: AtomicU64::new(1),
+            priority_waiters: Arc::new(DashMap::new()),
             trading_state_machine: None, // 默认不启用
+            sharded_engine: None,        // 默认单引擎
+            auction_indicator: None,     // 默认不推送竞价指示价
+            rejection_stats: Arc::new(RejectionStats::new()),
+            price_limit_manager: None,   // 默认不校验涨跌停
+            book_limiter: None,          // 默认不限制挂单数
+            rate_limiter: None,          // 默认不限制下单频率
+            duplicate_guard: None,       // 默认不检测重复订单
+            volume_limiter: None,        // 默认不限制成交量
+            market_maker_monitor: None,  // 默认不考核做市商
+            high_perf_matcher: None,    // 默认不启用高性能撮合通道
+            feature_gate: FEATURE_GATE.clone(),
+            account_lease: None,         // 默认单实例部署
+            order_ttl: Arc::new(OrderTtlManager::new()),
+            order_history: Arc::new(OrderHistoryTracker::new()),
+            limit_queue: LimitQueueIndex::new(),
+            order_watchdog: None,
+            shadow_mode: None, // 默认不启用影子撮合
+            listing_protection: None,
+            circuit_breaker: None,
+            position_concentration: None,
+            trade_bus: Arc::new(TradeEventBus::default()),
+            clock: ExchangeClock::System,
+            time_service: time_service(),
+            halt_reason: RwLock::new(None),
+            in_flight: AtomicU64::new(0),
+        }
+    }
+
+    /// 停止接单（紧急停机），已进入处理流程的请求不受影响
+    pub fn halt_order_entry(&self, reason: &str) {
+        *self.halt_reason.write() = Some(reason.to_string());
+        log::warn!("Order entry halted: {}", reason);
+    }
+
+    /// 恢复接单
+    pub fn resume_order_entry(&self) {
+        if self.halt_reason.write().take().is_some() {
+            log::info!("Order entry resumed");
+        }
+    }
+
+    /// 停止接单原因（None 表示正常接单）
+    pub fn order_entry_halt_reason(&self) -> Option<String> {
+        self.halt_reason.read().clone()
+    }
+
+    /// 在途下单/撤单请求数
+    pub fn in_flight_requests(&self) -> u64 {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    /// 等待在途请求处理完成，超时返回 false
+    pub fn wait_in_flight(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        while self.in_flight.load(Ordering::SeqCst) > 0 {
+            if Instant::now() >= deadline {
+                return false;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        true
+    }
+
+    /// 登记在途请求；停止接单时拒绝（先计数再检查，保证 wait_in_flight 不会漏掉并发请求）
+    fn enter_order_entry(&self) -> Result<InFlightGuard<'_>, String> {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        let guard = InFlightGuard(&self.in_flight);
+        match self.halt_reason.read().as_ref() {
+            Some(reason) => Err(format!("Exchange is halted: {}", reason)),
+            None => Ok(guard),
         }
     }
 
@@ -321,6 +556,242 @@ impl OrderRouter {
         self.storage = Some(storage);
     }
 
+    /// 设置涨跌停板管理器 @yutiansut @quantaxis
+    pub fn set_price_limit_manager(&mut self, manager: Arc<crate::risk::PriceLimitManager>) {
+        self.price_limit_manager = Some(manager);
+    }
+
+    /// 设置订单簿挂单数限制器 @yutiansut @quantaxis
+    pub fn set_book_limiter(&mut self, limiter: Arc<OrderBookLimiter>) {
+        self.book_limiter = Some(limiter);
+    }
+
+    /// 获取订单簿挂单数限制器
+    pub fn book_limiter(&self) -> Option<Arc<OrderBookLimiter>> {
+        self.book_limiter.clone()
+    }
+
+    /// 设置账户频率限制器 @yutiansut @quantaxis
+    pub fn set_rate_limiter(&mut self, limiter: Arc<OrderRateLimiter>) {
+        self.rate_limiter = Some(limiter);
+    }
+
+    /// 获取账户频率限制器
+    pub fn rate_limiter(&self) -> Option<Arc<OrderRateLimiter>> {
+        self.rate_limiter.clone()
+    }
+
+    /// 设置重复订单检测器 @yutiansut @quantaxis
+    pub fn set_duplicate_guard(&mut self, guard: Arc<DuplicateOrderGuard>) {
+        self.duplicate_guard = Some(guard);
+    }
+
+    /// 获取重复订单检测器
+    pub fn duplicate_guard(&self) -> Option<Arc<DuplicateOrderGuard>> {
+        self.duplicate_guard.clone()
+    }
+
+    /// 设置单日成交量限制器 @yutiansut @quantaxis
+    pub fn set_volume_limiter(&mut self, limiter: Arc<TradeVolumeLimiter>) {
+        self.volume_limiter = Some(limiter);
+    }
+
+    /// 获取单日成交量限制器
+    pub fn volume_limiter(&self) -> Option<Arc<TradeVolumeLimiter>> {
+        self.volume_limiter.clone()
+    }
+
+    /// 设置持仓集中度监测 @yutiansut @quantaxis
+    pub fn set_position_concentration(&mut self, concentration: Arc<PositionConcentration>) {
+        self.position_concentration = Some(concentration);
+    }
+
+    /// 获取持仓集中度监测
+    pub fn position_concentration(&self) -> Option<Arc<PositionConcentration>> {
+        self.position_concentration.clone()
+    }
+
+    /// 设置做市商义务考核 @yutiansut @quantaxis
+    pub fn set_market_maker_monitor(&mut self, monitor: Arc<MarketMakerMonitor>) {
+        self.market_maker_monitor = Some(monitor);
+    }
+
+    /// 获取做市商义务考核
+    pub fn market_maker_monitor(&self) -> Option<Arc<MarketMakerMonitor>> {
+        self.market_maker_monitor.clone()
+    }
+
+    /// 设置功能灰度开关（默认使用全局 `FEATURE_GATE`） @yutiansut @quantaxis
+    pub fn set_feature_gate(&mut self, gate: Arc<FeatureGate>) {
+        self.feature_gate = gate;
+    }
+
+    /// 获取功能灰度开关
+    pub fn feature_gate(&self) -> Arc<FeatureGate> {
+        self.feature_gate.clone()
+    }
+
+    /// 设置高性能撮合通道 @yutiansut @quantaxis
+    ///
+    /// 未设置或分片模式下灰度开关不生效，订单全部走常规撮合路径
+    pub fn set_high_perf_matcher(&mut self, matcher: Arc<HighPerfOrderMatcher>) {
+        self.high_perf_matcher = Some(matcher);
+    }
+
+    /// 获取高性能撮合通道
+    pub fn high_perf_matcher(&self) -> Option<Arc<HighPerfOrderMatcher>> {
+        self.high_perf_matcher.clone()
+    }
+
+    /// 订单是否走高性能撮合路径（下单时决策）
+    pub fn is_high_perf_order(&self, order_id: &str) -> Option<bool> {
+        self.orders
+            .get(order_id)
+            .map(|info| info.read().high_perf_path)
+    }
+
+    /// 设置账户操作租约管理器 @yutiansut @quantaxis
+    pub fn set_account_lease(&mut self, lease: Arc<AccountLeaseManager>) {
+        self.account_lease = Some(lease);
+    }
+
+    /// 获取账户操作租约管理器
+    pub fn account_lease(&self) -> Option<Arc<AccountLeaseManager>> {
+        self.account_lease.clone()
+    }
+
+    /// 获取/续约账户操作租约（未启用时直接通过），持有凭证在写操作结束后 drop
+    fn acquire_account_lease(
+        &self,
+        account_id: &str,
+    ) -> Result<Option<AccountLeaseHold<'_>>, ExchangeError> {
+        match self.account_lease {
+            Some(ref lease) => lease.acquire(account_id).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// 设置订单 TTL 管理器（如需重启后仍生效，传入 `OrderTtlManager::with_persist_dir`）
+    pub fn set_order_ttl_manager(&mut self, manager: Arc<OrderTtlManager>) {
+        self.order_ttl = manager;
+    }
+
+    /// 获取订单 TTL 管理器
+    pub fn order_ttl_manager(&self) -> Arc<OrderTtlManager> {
+        self.order_ttl.clone()
+    }
+
+    /// 设置订单状态历史（如需重启后仍可查，传入 `OrderHistoryTracker::with_persist_dir`）
+    pub fn set_order_history(&mut self, history: Arc<OrderHistoryTracker>) {
+        self.order_history = history;
+    }
+
+    /// 获取订单状态历史
+    pub fn order_history(&self) -> Arc<OrderHistoryTracker> {
+        self.order_history.clone()
+    }
+
+    /// 记录订单状态变更（生命周期状态由路由状态与成交量推导）
+    fn record_order_state(&self, order_id: &str, info: &OrderRouteInfo, reason: impl Into<String>) {
+        let filled_volume = match info.status {
+            OrderStatus::Filled => info.order.volume_orign,
+            _ => info.filled_volume,
+        };
+        let state = match info.status {
+            OrderStatus::PendingRisk | OrderStatus::PendingRoute => {
+                OrderLifecycleState::PendingReport
+            }
+            OrderStatus::Submitted => OrderLifecycleState::Reported,
+            OrderStatus::PartiallyFilled => OrderLifecycleState::PartiallyFilled,
+            OrderStatus::Filled => OrderLifecycleState::Filled,
+            OrderStatus::Cancelled if filled_volume > 0.0 => {
+                OrderLifecycleState::PartiallyCancelled
+            }
+            OrderStatus::Cancelled => OrderLifecycleState::Cancelled,
+            OrderStatus::Rejected => OrderLifecycleState::Rejected,
+        };
+        self.order_history.record(
+            OrderStateChange::new(
+                order_id,
+                &info.order.user_id,
+                &info.order.instrument_id,
+                state,
+                reason,
+            )
+            .with_volumes(
+                filled_volume,
+                (info.order.volume_orign - filled_volume).max(0.0),
+            )
+            .at(self.clock.now_nanos()),
+        );
+    }
+
+    /// 设置在途订单看门狗 @yutiansut @quantaxis
+    pub fn set_order_watchdog(&mut self, watchdog: Arc<OrderWatchdog>) {
+        self.order_watchdog = Some(watchdog);
+    }
+
+    /// 获取在途订单看门狗
+    pub fn order_watchdog(&self) -> Option<Arc<OrderWatchdog>> {
+        self.order_watchdog.clone()
+    }
+
+    /// 设置成交事件总线 @yutiansut @quantaxis
+    pub fn set_trade_bus(&mut self, trade_bus: Arc<TradeEventBus>) {
+        self.trade_bus = trade_bus;
+    }
+
+    /// 获取成交事件总线（订阅成交记录/行情等消费者）
+    pub fn trade_bus(&self) -> Arc<TradeEventBus> {
+        self.trade_bus.clone()
+    }
+
+    /// 设置时钟（确定性模式下传入虚拟时钟，需与成交网关使用同一时钟）
+    pub fn set_clock(&mut self, clock: ExchangeClock) {
+        self.clock = clock;
+    }
+
+    /// 时间来源
+    pub fn clock(&self) -> &ExchangeClock {
+        &self.clock
+    }
+
+    /// 设置订单到期时间源（与结算、时间控制注入同一 TimeService）
+    pub fn set_time_service(&mut self, time_service: Arc<TimeService>) {
+        self.time_service = time_service;
+    }
+
+    /// 账户类型（未登记元数据的账户按个人账户限流）
+    fn account_type_of(&self, account_id: &str) -> AccountType {
+        self.account_mgr
+            .get_account_type(account_id)
+            .unwrap_or(AccountType::Individual)
+    }
+
+    /// 单日成交量累计（买卖双方各自在成交回报时计入）
+    fn record_trade_volume(&self, order: &Order, volume: f64) {
+        if let Some(ref limiter) = self.volume_limiter {
+            limiter.record_trade_at(
+                &order.user_id,
+                self.account_type_of(&order.user_id),
+                &order.instrument_id,
+                &order.offset,
+                volume,
+                self.clock.now_millis(),
+            );
+        }
+    }
+
+    /// 设置拒单统计器（如需日终落盘，传入 `RejectionStats::with_persist_dir`）
+    pub fn set_rejection_stats(&mut self, stats: Arc<RejectionStats>) {
+        self.rejection_stats = stats;
+    }
+
+    /// 获取拒单统计器
+    pub fn rejection_stats(&self) -> Arc<RejectionStats> {
+        self.rejection_stats.clone()
+    }
+
     /// 设置交易状态机 @yutiansut @quantaxis
     pub fn set_trading_state_machine(
         &mut self,
@@ -329,6 +800,194 @@ impl OrderRouter {
         self.trading_state_machine = Some(state_machine);
     }
 
+    /// 设置分片撮合引擎 @yutiansut @quantaxis
+    ///
+    /// 设置后订单按合约路由到对应分片撮合，成交回报仍汇聚到同一个 TradeGateway
+    pub fn set_sharded_engine(&mut self, sharded_engine: Arc<ShardedMatchingEngine>) {
+        self.sharded_engine = Some(sharded_engine);
+    }
+
+    /// 获取分片撮合引擎
+    pub fn get_sharded_engine(&self) -> Option<Arc<ShardedMatchingEngine>> {
+        self.sharded_engine.clone()
+    }
+
+    /// 启用分片撮合故障恢复 @yutiansut @quantaxis
+    ///
+    /// 分片线程撮合 panic 后按路由器在途订单重建合约订单簿，并广播暂停/恢复/停牌状态
+    pub fn enable_matching_recovery(self: &Arc<Self>) {
+        if let Some(ref sharded) = self.sharded_engine {
+            let handler: Arc<dyn MatchingRecoveryHandler> = self.clone();
+            sharded.set_recovery_handler(Arc::downgrade(&handler));
+        }
+    }
+
+    /// 设置影子撮合模式 @yutiansut @quantaxis
+    ///
+    /// 设置后每笔限价单/撤单同时在影子引擎重放并对比结果，影子引擎不影响订单状态
+    pub fn set_shadow_mode(&mut self, shadow_mode: Arc<ShadowMode>) {
+        self.shadow_mode = Some(shadow_mode);
+    }
+
+    /// 获取影子撮合模式
+    pub fn get_shadow_mode(&self) -> Option<Arc<ShadowMode>> {
+        self.shadow_mode.clone()
+    }
+
+    /// 设置新合约上市保护 @yutiansut @quantaxis
+    pub fn set_listing_protection(&mut self, protection: Arc<ListingProtection>) {
+        self.listing_protection = Some(protection);
+    }
+
+    /// 获取新合约上市保护
+    pub fn get_listing_protection(&self) -> Option<Arc<ListingProtection>> {
+        self.listing_protection.clone()
+    }
+
+    /// 设置极端行情熔断 @yutiansut @quantaxis
+    pub fn set_circuit_breaker(&mut self, breaker: Arc<CircuitBreaker>) {
+        self.circuit_breaker = Some(breaker);
+    }
+
+    /// 获取极端行情熔断
+    pub fn get_circuit_breaker(&self) -> Option<Arc<CircuitBreaker>> {
+        self.circuit_breaker.clone()
+    }
+
+    /// 设置集合竞价指示价推送器 @yutiansut @quantaxis
+    pub fn set_auction_indicator(
+        &mut self,
+        publisher: Arc<crate::market::AuctionIndicatorPublisher>,
+    ) {
+        self.auction_indicator = Some(publisher);
+    }
+
+    /// 竞价指示价定时推送（由竞价阶段定时器周期调用）
+    ///
+    /// 推送节流期内积压的最新指示价；已离开竞价阶段（开盘）的合约不必等到下一笔订单，
+    /// 立即按最终订单簿推送开盘指示价并停止推送
+    pub fn flush_auction_indicators(&self) {
+        let publisher = match self.auction_indicator {
+            Some(ref publisher) => publisher,
+            None => return,
+        };
+        publisher.flush_pending();
+        for instrument_id in publisher.active_instruments() {
+            if !self.in_auction_phase(&instrument_id) {
+                self.refresh_auction_indicator(&instrument_id);
+            }
+        }
+    }
+
+    /// 合约是否处于集合竞价申报阶段
+    fn in_auction_phase(&self, instrument_id: &str) -> bool {
+        self.trading_state_machine.as_ref().map_or(false, |sm| {
+            matches!(
+                sm.get_instrument_state(instrument_id),
+                crate::matching::TradingState::PreAuctionPeriod
+                    | crate::matching::TradingState::AuctionOrder
+                    | crate::matching::TradingState::AuctionCancel
+            )
+        })
+    }
+
+    /// 订单簿变更后刷新集合竞价指示价
+    ///
+    /// 竞价阶段重新计算虚拟开盘价；离开竞价阶段后按最终订单簿推送一次并停止
+    fn refresh_auction_indicator(&self, instrument_id: &str) {
+        let publisher = match (&self.auction_indicator, &self.trading_state_machine) {
+            (Some(p), Some(_)) => p,
+            _ => return,
+        };
+
+        let in_auction = self.in_auction_phase(instrument_id);
+        if !in_auction && !publisher.is_active(instrument_id) {
+            return;
+        }
+
+        let orderbook = match self.get_orderbook(instrument_id) {
+            Some(ob) => ob,
+            None => return,
+        };
+        let (bids, asks) = {
+            let ob = orderbook.read();
+            crate::market::AuctionIndicatorPublisher::collect_orders(&ob)
+        };
+        let reference_price = match self.sharded_engine {
+            Some(ref sharded) => sharded
+                .engine_for(instrument_id)
+                .and_then(|engine| engine.get_prev_close(instrument_id)),
+            None => self.matching_engine.get_prev_close(instrument_id),
+        };
+
+        if in_auction {
+            publisher.on_orderbook_changed(instrument_id, &bids, &asks, reference_price);
+        } else {
+            publisher.end_auction(instrument_id, &bids, &asks, reference_price);
+        }
+    }
+
+    /// 获取合约订单簿（优先从分片撮合引擎查找）
+    fn get_orderbook(&self, instrument_id: &str) -> Option<Arc<crate::matching::TrackedOrderbook>> {
+        match self.sharded_engine {
+            Some(ref sharded) => sharded.get_orderbook(instrument_id),
+            None => self.matching_engine.get_orderbook(instrument_id),
+        }
+    }
+
+    /// 将撮合请求提交到合约订单簿
+    ///
+    /// 启用影子模式时在持有订单簿锁期间投递 `shadow_request`，保证影子侧重放顺序与主引擎一致
+    fn process_on_orderbook(
+        &self,
+        instrument_id: &str,
+        order_id: &str,
+        request: orders::OrderRequest<InstrumentAsset>,
+        shadow_request: Option<ShadowRequest>,
+    ) -> Result<Vec<Result<Success, Failed>>, ExchangeError> {
+        self.execute_on_orderbook(
+            instrument_id,
+            order_id,
+            move |ob| ob.process_order(request).into_iter().collect(),
+            shadow_request,
+        )
+    }
+
+    /// 在合约订单簿上执行撮合操作（分片模式下在合约所在分片线程执行）
+    ///
+    /// 分片撮合 panic 时 `order_id` 记入 poison 队列，该订单按撮合失败处理
+    fn execute_on_orderbook<F>(
+        &self,
+        instrument_id: &str,
+        order_id: &str,
+        matcher: F,
+        shadow_request: Option<ShadowRequest>,
+    ) -> Result<Vec<Result<Success, Failed>>, ExchangeError>
+    where
+        F: FnOnce(&mut Orderbook<InstrumentAsset>) -> Vec<Result<Success, Failed>> + Send + 'static,
+    {
+        let shadow = self.shadow_mode.clone().zip(shadow_request);
+
+        if let Some(ref sharded) = self.sharded_engine {
+            let shadow_instrument = instrument_id.to_string();
+            return sharded.execute_labeled(instrument_id, order_id, move |ob| {
+                let results = matcher(ob);
+                if let Some((shadow_mode, shadow_request)) = shadow {
+                    shadow_mode.observe(&shadow_instrument, shadow_request, &results);
+                }
+                results
+            });
+        }
+
+        self.matching_engine.execute(instrument_id, |ob| {
+            let results = matcher(ob);
+            if let Some((shadow_mode, shadow_request)) = shadow {
+                shadow_mode.observe(instrument_id, shadow_request, &results);
+            }
+            results
+        })
+    }
+
     /// 获取交易状态机
     pub fn get_trading_state_machine(&self) -> Option<Arc<crate::exchange::TradingStateMachine>> {
         self.trading_state_machine.clone()
@@ -377,38 +1036,262 @@ impl OrderRouter {
         }
     }
 
-    /// 创建带自定义风控检查器的路由器
-    pub fn with_risk_checker(
-        account_mgr: Arc<AccountManager>,
-        risk_checker: Arc<PreTradeCheck>,
-        matching_engine: Arc<ExchangeMatchingEngine>,
-        instrument_registry: Arc<InstrumentRegistry>,
-        trade_gateway: Arc<TradeGateway>,
-    ) -> Self {
-        Self {
-            account_mgr,
-            risk_checker,
-            matching_engine,
-            instrument_registry,
-            trade_gateway,
-            market_broadcaster: None,
-            storage: None,
-            orders: DashMap::new(),
-            user_orders: DashMap::new(),
-            engine_id_to_order: DashMap::new(),
-            engine_id_to_user: DashMap::new(),
-            order_seq: AtomicU64::new(1),
-            trade_count: AtomicU64::new(0),
-            trade_volume: parking_lot::RwLock::new(0.0),
-            trade_amount: parking_lot::RwLock::new(0.0),
-            // 性能优化字段初始化
-            last_snapshot_time: Arc::new(DashMap::new()),
-            snapshot_interval: Duration::from_secs(1), // 默认1秒
-            tick_buffer: Arc::new(Mutex::new(Vec::with_capacity(1000))),
-            flush_stop_signal: Arc::new(AtomicBool::new(false)),
-            priority_queue: None, // 默认不启用
-            priority_queue_enabled: AtomicBool::new(false),
-            trading_state_machine: None, // 默认不启用
+    /// 设置优先级队列防饿死参数（高优先级连续出队上限、最长排队时间）
+    pub fn set_priority_starvation_guard(&self, starvation_limit: usize, max_wait_ms: u64) {
+        if let Some(ref queue) = self.priority_queue {
+            queue.set_starvation_guard(starvation_limit, max_wait_ms);
+        }
+    }
+
+    /// 已启用的优先级队列
+    fn active_priority_queue(&self) -> Option<&Arc<crate::exchange::PriorityOrderQueue>> {
+        self.priority_queue
+            .as_ref()
+            .filter(|_| self.priority_queue_enabled.load(Ordering::SeqCst))
+    }
+
+    /// 订单优先级（账户等级 + 订单类型 + 自定义标记），未启用优先级队列时为 None
+    pub fn order_priority(
+        &self,
+        req: &SubmitOrderRequest,
+        tag: Option<crate::exchange::OrderPriority>,
+    ) -> Option<crate::exchange::OrderPriority> {
+        let account_type = self.account_mgr.get_account_type(&req.account_id);
+        self.active_priority_queue()
+            .map(|queue| queue.calculate_priority_with(req, account_type, tag))
+    }
+
+    /// 订单按优先级入队，返回调度序号（未启用优先级队列或低优先级队列已满时返回 None）
+    pub fn enqueue_prioritized(
+        &self,
+        req: SubmitOrderRequest,
+        tag: Option<crate::exchange::OrderPriority>,
+    ) -> Option<u64> {
+        self.enqueue_with_waiter(req, tag, None)
+    }
+
+    fn enqueue_with_waiter(
+        &self,
+        req: SubmitOrderRequest,
+        tag: Option<crate::exchange::OrderPriority>,
+        waiter: Option<crossbeam::channel::Sender<SubmitOrderResponse>>,
+    ) -> Option<u64> {
+        let priority = self.order_priority(&req, tag)?;
+        let queue = self.active_priority_queue()?;
+        let seq = self.priority_seq.fetch_add(1, Ordering::SeqCst);
+        // 先登记等待者，避免订单被其他线程处理时结果无处回传
+        if let Some(waiter) = waiter {
+            self.priority_waiters.insert(seq, waiter);
+        }
+        if queue.enqueue_with_priority(req, priority, seq) {
+            Some(seq)
+        } else {
+            self.priority_waiters.remove(&seq);
+            None
+        }
+    }
+
+    /// 按优先级调度处理队列中的订单（最多 `max_orders` 个），返回 (序号, 优先级, 处理结果)
+    pub fn dispatch_priority_orders(
+        &self,
+        max_orders: usize,
+    ) -> Vec<(u64, crate::exchange::OrderPriority, SubmitOrderResponse)> {
+        let mut dispatched = Vec::new();
+        while dispatched.len() < max_orders {
+            match self.dispatch_next_priority_order() {
+                Some(result) => dispatched.push(result),
+                None => break,
+            }
+        }
+        dispatched
+    }
+
+    /// 取出最高优先级订单进入下单流程，结果回传给等待的提交线程
+    fn dispatch_next_priority_order(
+        &self,
+    ) -> Option<(u64, crate::exchange::OrderPriority, SubmitOrderResponse)> {
+        let item = self.active_priority_queue()?.dequeue()?;
+        let waiter = self.priority_waiters.remove(&item.seq).map(|(_, w)| w);
+        let response = self.submit_order(item.order);
+        if let Some(waiter) = waiter {
+            let _ = waiter.send(response.clone());
+        }
+        Some((item.seq, item.priority, response))
+    }
+
+    /// 按优先级调度提交订单
+    ///
+    /// 订单先进入优先级队列，提交线程按优先级从队列取单处理（可能是其他线程的订单），
+    /// 直到拿到本订单的处理结果：高优先级订单先进入撮合，撮合仍按价格时间优先。
+    /// 未启用优先级队列时直接提交。
+    pub fn submit_order_prioritized(
+        &self,
+        req: SubmitOrderRequest,
+        tag: Option<crate::exchange::OrderPriority>,
+    ) -> SubmitOrderResponse {
+        if self.active_priority_queue().is_none() {
+            return self.submit_order(req);
+        }
+
+        let instrument_id = req.instrument_id.clone();
+        let (sender, receiver) = crossbeam::channel::bounded(1);
+        if self.enqueue_with_waiter(req, tag, Some(sender)).is_none() {
+            let reason = RejectReason::RateLimited;
+            self.rejection_stats.record(reason, &instrument_id);
+            return SubmitOrderResponse {
+                success: false,
+                order_id: None,
+                status: Some("rejected".to_string()),
+                error_message: Some("Order queue is full, please retry later".to_string()),
+                error_code: Some(reason.error_code()),
+                adjusted_price: None,
+            };
+        }
+
+        loop {
+            if let Ok(response) = receiver.try_recv() {
+                return response;
+            }
+            // 队列已空：本订单正由其他线程处理，等待结果
+            if self.dispatch_next_priority_order().is_none() {
+                return receiver.recv().unwrap_or_else(|_| SubmitOrderResponse {
+                    success: false,
+                    order_id: None,
+                    status: Some("rejected".to_string()),
+                    error_message: Some("Order dispatch failed".to_string()),
+                    error_code: Some(RejectReason::RoutingError.error_code()),
+                    adjusted_price: None,
+                });
+            }
+        }
+    }
+
+    /// 创建带自定义风控检查器的路由器
+    pub fn with_risk_checker(
+        account_mgr: Arc<AccountManager>,
+        risk_checker: Arc<PreTradeCheck>,
+        matching_engine: Arc<ExchangeMatchingEngine>,
+        instrument_registry: Arc<InstrumentRegistry>,
+        trade_gateway: Arc<TradeGateway>,
+    ) -> Self {
+        risk_checker.set_instrument_registry(instrument_registry.clone());
+        Self {
+            account_mgr,
+            risk_checker,
+            matching_engine,
+            instrument_registry,
+            trade_gateway,
+            market_broadcaster: None,
+            storage: None,
+            orders: DashMap::new(),
+            user_orders: DashMap::new(),
+            engine_id_to_order: DashMap::new(),
+            engine_id_to_user: DashMap::new(),
+            order_seq: AtomicU64::new(1),
+            trade_count: AtomicU64::new(0),
+            trade_volume: parking_lot::RwLock::new(0.0),
+            trade_amount: parking_lot::RwLock::new(0.0),
+            // 性能优化字段初始化
+            last_snapshot_time: Arc::new(DashMap::new()),
+            snapshot_interval: Duration::from_secs(1), // 默认1秒
+            tick_buffer: Arc::new(Mutex::new(Vec::with_capacity(1000))),
+            flush_stop_signal: Arc::new(AtomicBool::new(false)),
+            priority_queue: None, // 默认不启用
+            priority_queue_enabled: AtomicBool::new(false),
+            priority_seq: AtomicU64::new(1),
+            priority_waiters: Arc::new(DashMap::new()),
+            trading_state_machine: None, // 默认不启用
+            sharded_engine: None,        // 默认单引擎
+            auction_indicator: None,     // 默认不推送竞价指示价
+            rejection_stats: Arc::new(RejectionStats::new()),
+            price_limit_manager: None,   // 默认不校验涨跌停
+            book_limiter: None,          // 默认不限制挂单数
+            rate_limiter: None,          // 默认不限制下单频率
+            duplicate_guard: None,       // 默认不检测重复订单
+            volume_limiter: None,        // 默认不限制成交量
+            market_maker_monitor: None,  // 默认不考核做市商
+            high_perf_matcher: None,    // 默认不启用高性能撮合通道
+            feature_gate: FEATURE_GATE.clone(),
+            account_lease: None,         // 默认单实例部署
+            order_ttl: Arc::new(OrderTtlManager::new()),
+            order_history: Arc::new(OrderHistoryTracker::new()),
+            limit_queue: LimitQueueIndex::new(),
+            order_watchdog: None,
+            shadow_mode: None, // 默认不启用影子撮合
+            listing_protection: None,
+            circuit_breaker: None,
+            position_concentration: None,
+            trade_bus: Arc::new(TradeEventBus::default()),
+            clock: ExchangeClock::System,
+            time_service: time_service(),
+            halt_reason: RwLock::new(None),
+            in_flight: AtomicU64::new(0),
+        }
+    }
+
+    /// 拒单：记录拒单原因统计、推送拒单回报并构造拒单响应
+    fn reject_order(
+        &self,
+        order_id: String,
+        req: &SubmitOrderRequest,
+        reason: RejectReason,
+        message: String,
+    ) -> SubmitOrderResponse {
+        self.reject_order_with_code(order_id, req, reason, reason.error_code(), message)
+    }
+
+    /// 拒单（指定错误码，风控拒单沿用 RiskCheckCode）
+    ///
+    /// 拒单回报经 TradeGateway 推送（WebSocket / NotificationBroker / DIFF orders 补丁），
+    /// 与撮合拒单使用同一条通知链路 @yutiansut @quantaxis
+    fn reject_order_with_code(
+        &self,
+        order_id: String,
+        req: &SubmitOrderRequest,
+        reason: RejectReason,
+        error_code: u32,
+        message: String,
+    ) -> SubmitOrderResponse {
+        self.rejection_stats.record(reason, &req.instrument_id);
+        // 被拒订单移出重复检测缓冲，客户端修正后重试不算重复
+        if let Some(ref guard) = self.duplicate_guard {
+            guard.release(&req.account_id, &order_id);
+        }
+        self.order_history.record(
+            OrderStateChange::new(
+                &order_id,
+                &req.account_id,
+                &req.instrument_id,
+                OrderLifecycleState::Rejected,
+                message.as_str(),
+            )
+            .with_volumes(0.0, req.volume)
+            .at(self.clock.now_nanos()),
+        );
+
+        if let Err(e) = self.trade_gateway.handle_order_rejected_pre_trade(
+            &req.instrument_id,
+            &req.account_id,
+            &order_id,
+            &req.direction,
+            &req.offset,
+            &req.order_type,
+            req.price,
+            req.volume,
+            &message,
+            reason,
+            error_code,
+        ) {
+            log::error!("Failed to push rejection for order {}: {}", order_id, e);
+        }
+
+        SubmitOrderResponse {
+            success: false,
+            order_id: Some(order_id),
+            status: Some("rejected".to_string()),
+            error_message: Some(message),
+            error_code: Some(error_code),
+            adjusted_price: None,
         }
     }
 
@@ -419,17 +1302,227 @@ impl OrderRouter {
 
     /// 提交强制订单（跳过风控/资金校验，用于强平等场景）
     pub fn submit_force_order(&self, req: SubmitOrderRequest) -> SubmitOrderResponse {
-        self.submit_order_with_options(req, OrderSubmitOptions { force: true })
+        self.submit_order_with_options(
+            req,
+            OrderSubmitOptions {
+                force: true,
+                ..Default::default()
+            },
+        )
     }
 
+    /// 订单入口：做头部采样决策，决策贯穿本次下单全链路
+    ///
+    /// 采样只影响是否产生 trace；被拒订单按错误请求强制采样。
     fn submit_order_with_options(
         &self,
         req: SubmitOrderRequest,
         opts: OrderSubmitOptions,
+    ) -> SubmitOrderResponse {
+        // 紧急停机：直接拒绝，不经过拒单回报链路（避免停机后改写账户状态）
+        let _in_flight = match self.enter_order_entry() {
+            Ok(guard) => guard,
+            Err(message) => {
+                return SubmitOrderResponse {
+                    success: false,
+                    order_id: None,
+                    status: Some("rejected".to_string()),
+                    error_message: Some(message),
+                    error_code: Some(RejectReason::TradingStateRejected.error_code()),
+                    adjusted_price: None,
+                }
+            }
+        };
+
+        // 账户由其他实例持有租约：拒绝并提示重试（账户状态以持有实例为准，不推送拒单回报）
+        let _lease = match self.acquire_account_lease(&req.account_id) {
+            Ok(lease) => lease,
+            Err(e) => {
+                let reason = RejectReason::AccountLeaseHeld;
+                self.rejection_stats.record(reason, &req.instrument_id);
+                return SubmitOrderResponse {
+                    success: false,
+                    order_id: None,
+                    status: Some("rejected".to_string()),
+                    error_message: Some(e.to_string()),
+                    error_code: Some(reason.error_code()),
+                    adjusted_price: None,
+                };
+            }
+        };
+
+        let trace = TRACE_SAMPLER.start("submit_order");
+        let response = {
+            let _scope = trace.enter();
+            self.process_order_submission(req, opts)
+        };
+        trace.finish(!response.success);
+        response
+    }
+
+    fn process_order_submission(
+        &self,
+        mut req: SubmitOrderRequest,
+        opts: OrderSubmitOptions,
     ) -> SubmitOrderResponse {
         // 1. 生成订单ID（无锁操作）
         let order_id = self.generate_order_id();
 
+        // 1.0 合约代码归一（`SHFE.cu2501` / 旧代码 `cu2501` → 注册代码），之后统一按注册代码路由
+        match self.instrument_registry.normalize(&req.instrument_id) {
+            Ok(instrument_id) => req.instrument_id = instrument_id,
+            Err(e) => {
+                return self.reject_order(
+                    order_id,
+                    &req,
+                    RejectReason::InstrumentNotFound,
+                    e.to_string(),
+                );
+            }
+        }
+
+        // 1.0.1 按合约到达顺序领号并分配撮合时间戳，风控前排队：同合约的下单与撤单
+        // 按到达顺序依次完成风控与撮合，风控耗时不同也不会改变进入订单簿的顺序
+        let turn = self
+            .matching_engine
+            .order_sequencer()
+            .enter(&req.instrument_id, || self.clock.now_nanos());
+
+        // 1.1 账户下单频率限制（强平单不受限，改单已按改单计数） @yutiansut @quantaxis
+        if let Some(ref limiter) = self.rate_limiter {
+            if !opts.force && !opts.amend {
+                let account_type = self.account_type_of(&req.account_id);
+                if let Err(reason) = limiter.check_order(&req.account_id, account_type) {
+                    return self.reject_order(order_id, &req, RejectReason::RateLimited, reason);
+                }
+            }
+        }
+
+        // 1.1.1 重复订单检测：检测与登记一次完成，并发到达的相同订单只放行一笔（强平单、改单不检测）
+        if let Some(ref guard) = self.duplicate_guard {
+            if !opts.force && !opts.amend {
+                let fingerprint = OrderFingerprint::new(
+                    &req.instrument_id,
+                    &req.direction,
+                    &req.offset,
+                    req.price,
+                    req.volume,
+                );
+                if let Err(reason) = guard.check(
+                    &req.account_id,
+                    &order_id,
+                    req.client_order_id.as_deref(),
+                    fingerprint,
+                    self.clock.now_millis(),
+                ) {
+                    return self.reject_order(order_id, &req, RejectReason::DuplicateOrder, reason);
+                }
+            }
+        }
+
+        // 1.2 账户交易限制：只平仓账户拒绝开仓，暂停交易账户拒绝下单（撤单不受限，强平单不受限）
+        if !opts.force {
+            if let Err(info) = self
+                .account_mgr
+                .check_trading_restriction(&req.account_id, &req.offset)
+            {
+                self.account_mgr.notify_trading_restriction(&info);
+                return self.reject_order(
+                    order_id,
+                    &req,
+                    RejectReason::TradingRestricted,
+                    info.describe(),
+                );
+            }
+        }
+
+        // 1.2.1 单日成交量限额：达限后拒绝新单或只允许平仓（强平单不受限）
+        if let Some(ref limiter) = self.volume_limiter {
+            if !opts.force {
+                let account_type = self.account_type_of(&req.account_id);
+                if let Err(reason) = limiter.check_order_at(
+                    &req.account_id,
+                    account_type,
+                    &req.instrument_id,
+                    &req.offset,
+                    self.clock.now_millis(),
+                ) {
+                    return self.reject_order(
+                        order_id,
+                        &req,
+                        RejectReason::TradeVolumeLimited,
+                        reason,
+                    );
+                }
+            }
+        }
+
+        // 1.2.2 持仓集中度：占合约全市场总持仓比例超限的账户禁止开仓（强平单不受限）
+        if let Some(ref concentration) = self.position_concentration {
+            if !opts.force {
+                if let Err(reason) =
+                    concentration.check_order(&req.account_id, &req.instrument_id, &req.offset)
+                {
+                    return self.reject_order(
+                        order_id,
+                        &req,
+                        RejectReason::PositionConcentration,
+                        reason,
+                    );
+                }
+            }
+        }
+
+        // 1.3 订单存活时长必须为正
+        if req.ttl_secs == Some(0) {
+            return self.reject_order(
+                order_id,
+                &req,
+                RejectReason::InvalidOrderParams,
+                "ttl_secs must be greater than 0".to_string(),
+            );
+        }
+
+        // 1.4 合约上市状态：待上市合约拒绝下单，上市保护时段内拒绝市价单（强平单不受限）
+        if self.instrument_registry.status(&req.instrument_id) == Some(InstrumentStatus::Pending) {
+            return self.reject_order(
+                order_id,
+                &req,
+                RejectReason::TradingStateRejected,
+                format!("Instrument {} is not listed yet", req.instrument_id),
+            );
+        }
+        if let Some(ref protection) = self.listing_protection {
+            if !opts.force {
+                if let Err(reason) = protection.check_order_at(
+                    &req.instrument_id,
+                    &req.order_type,
+                    self.clock.now_millis(),
+                ) {
+                    return self.reject_order(
+                        order_id,
+                        &req,
+                        RejectReason::ListingProtection,
+                        reason,
+                    );
+                }
+            }
+        }
+
+        // 1.4 熔断冷静期：只接受限价单 @yutiansut @quantaxis
+        if let Some(ref breaker) = self.circuit_breaker {
+            if !opts.force {
+                if let Err(reason) = breaker.check_order(&req.instrument_id, &req.order_type) {
+                    return self.reject_order(
+                        order_id,
+                        &req,
+                        RejectReason::TradingStateRejected,
+                        reason,
+                    );
+                }
+            }
+        }
+
         // 1.5 市价单价格转换 @yutiansut @quantaxis
         // 市价单需要从行情获取实际价格：买单用卖一价，卖单用买一价
         let req = if req.order_type == "MARKET" && req.price <= 0.0 {
@@ -439,16 +1532,12 @@ impl OrderRouter {
                     "Cannot get market price for MARKET order: instrument={}, direction={}",
                     req.instrument_id, req.direction
                 );
-                return SubmitOrderResponse {
-                    success: false,
-                    order_id: Some(order_id.clone()),
-                    status: Some("rejected".to_string()),
-                    error_message: Some(format!(
-                        "No market price available for instrument {}",
-                        req.instrument_id
-                    )),
-                    error_code: Some(4002), // 无行情
-                };
+                return self.reject_order(
+                    order_id,
+                    &req,
+                    RejectReason::NoMarketPrice,
+                    format!("No market price available for instrument {}", req.instrument_id),
+                );
             }
             log::info!(
                 "MARKET order price converted: instrument={}, direction={}, price={}",
@@ -462,12 +1551,57 @@ impl OrderRouter {
             req
         };
 
-        // 2. 预计算所需资金（无锁操作）
-        let estimated_commission = req.price * req.volume * 0.0003; // 万3手续费
+        // 1.6 最小变动价位：价格不在 tick 上时拒单或规整到最近的 tick（市价单/强平单不校验）
+        // 在涨跌停与风控之前执行，后续校验使用规整后的价格
+        let mut adjusted_price = None;
+        let tick_checked = !opts.force
+            && req.order_type != "MARKET"
+            && self.risk_checker.price_tick_mode() != PriceTickMode::Off;
+        let price_tick = if tick_checked {
+            self.instrument_registry
+                .get(&req.instrument_id)
+                .map(|info| info.price_tick)
+        } else {
+            None
+        };
+        let req = match price_tick {
+            Some(price_tick) => {
+                match self.risk_checker.normalize_price(req.price, price_tick) {
+                    Ok(price) if price != req.price => {
+                        if (price - req.price).abs() > price_tick * 1e-6 {
+                            log::info!(
+                                "Order price rounded to tick: instrument={}, price={} -> {}, tick={}",
+                                req.instrument_id,
+                                req.price,
+                                price,
+                                price_tick
+                            );
+                            adjusted_price = Some(price);
+                        }
+                        SubmitOrderRequest { price, ..req }
+                    }
+                    Ok(_) => req,
+                    Err(reason) => {
+                        return self.reject_order(
+                            order_id,
+                            &req,
+                            RejectReason::InvalidPriceTick,
+                            reason,
+                        );
+                    }
+                }
+            }
+            None => req,
+        };
+
+        // 2. 预计算所需资金（无锁操作，委托量为手数，金额按每手数量换算）
+        let order_amount =
+            req.price * req.volume * self.instrument_registry.volume_multiple(&req.instrument_id);
+        let estimated_commission = order_amount * 0.0003; // 万3手续费
         let required_funds = if req.direction == "BUY" && req.offset == "OPEN" {
-            req.price * req.volume + estimated_commission
+            order_amount + estimated_commission
         } else if req.direction == "SELL" && req.offset == "OPEN" {
-            req.price * req.volume * 0.2 + estimated_commission
+            order_amount * 0.2 + estimated_commission
         } else {
             estimated_commission
         };
@@ -483,13 +1617,27 @@ impl OrderRouter {
                         req.instrument_id,
                         reason
                     );
-                    return SubmitOrderResponse {
-                        success: false,
-                        order_id: Some(order_id.clone()),
-                        status: Some("rejected".to_string()),
-                        error_message: Some(reason),
-                        error_code: Some(4100), // 交易状态拒绝
-                    };
+                    return self.reject_order(
+                        order_id,
+                        &req,
+                        RejectReason::TradingStateRejected,
+                        reason,
+                    );
+                }
+            }
+        }
+
+        // 2.6 涨跌停价格校验（扩板后按新幅度） @yutiansut @quantaxis
+        if let Some(ref manager) = self.price_limit_manager {
+            if !opts.force {
+                if let Err(reason) = manager.check_price(&req.instrument_id, req.price) {
+                    log::warn!("Order rejected by price limit: {}", reason);
+                    return self.reject_order(
+                        order_id,
+                        &req,
+                        RejectReason::PriceOutOfLimit,
+                        reason,
+                    );
                 }
             }
         }
@@ -507,27 +1655,34 @@ impl OrderRouter {
                 price_type: req.order_type.clone(),
             };
 
-            match self.risk_checker.check(&risk_check_req) {
+            // 参考行情用于价格笼子校验（对手盘/最新价），未配置笼子时不读取订单簿
+            let quote = self
+                .risk_checker
+                .get_price_band(&req.instrument_id)
+                .and_then(|_| self.reference_quote(&req.instrument_id));
+            match self
+                .risk_checker
+                .check_with_quote(&risk_check_req, quote.as_ref())
+            {
                 Ok(RiskCheckResult::Pass) => {}
                 Ok(RiskCheckResult::Reject { reason, code }) => {
                     log::warn!("Order rejected by risk check: {:?} - {}", code, reason);
-                    return SubmitOrderResponse {
-                        success: false,
-                        order_id: Some(order_id.clone()),
-                        status: Some("rejected".to_string()),
-                        error_message: Some(reason),
-                        error_code: Some(code as u32),
-                    };
+                    return self.reject_order_with_code(
+                        order_id,
+                        &req,
+                        RejectReason::from_risk_code(code),
+                        code as u32,
+                        reason,
+                    );
                 }
                 Err(e) => {
                     log::error!("Risk check error: {}", e);
-                    return SubmitOrderResponse {
-                        success: false,
-                        order_id: Some(order_id.clone()),
-                        status: Some("rejected".to_string()),
-                        error_message: Some(format!("Risk check error: {}", e)),
-                        error_code: Some(9999),
-                    };
+                    return self.reject_order(
+                        order_id,
+                        &req,
+                        RejectReason::RiskCheckError,
+                        format!("Risk check error: {}", e),
+                    );
                 }
             }
         } else {
@@ -553,13 +1708,12 @@ impl OrderRouter {
                     "[FOK] Order rejected: cannot fill {} {} {} @ {} immediately",
                     req.volume, req.direction, req.instrument_id, req.price
                 );
-                return SubmitOrderResponse {
-                    success: false,
-                    order_id: Some(order_id.clone()),
-                    status: Some("rejected".to_string()),
-                    error_message: Some("FOK order cannot be fully filled immediately".to_string()),
-                    error_code: Some(4010),
-                };
+                return self.reject_order(
+                    order_id,
+                    &req,
+                    RejectReason::FokUnfillable,
+                    "FOK order cannot be fully filled immediately".to_string(),
+                );
             }
             log::info!(
                 "[FOK] Pre-check passed: {} {} {} @ {}",
@@ -567,18 +1721,41 @@ impl OrderRouter {
             );
         }
 
+        // 3.6 订单簿挂单数限制 @yutiansut @quantaxis
+        // 超限时只拒绝会挂单的新订单：IOC/FOK 与可立即全部成交的订单不进入订单簿，放行参与撮合
+        if let Some(ref limiter) = self.book_limiter {
+            if !opts.force {
+                if let Err(reason) = limiter.check_new_order(&req.instrument_id) {
+                    let marketable = time_cond == TimeCondition::IOC
+                        || self.check_fok_fulfillable(
+                            &req.instrument_id,
+                            &req.direction,
+                            req.volume,
+                            req.price,
+                        );
+                    if !marketable {
+                        return self.reject_order(
+                            order_id,
+                            &req,
+                            RejectReason::OrderBookFull,
+                            reason,
+                        );
+                    }
+                }
+            }
+        }
+
         // 4. 获取账户引用（无锁操作，DashMap get）
         let account = match self.account_mgr.get_account(&req.account_id) {
             Ok(acc) => acc,
             Err(e) => {
                 log::error!("Account not found: {}: {}", req.account_id, e);
-                return SubmitOrderResponse {
-                    success: false,
-                    order_id: Some(order_id),
-                    status: Some("rejected".to_string()),
-                    error_message: Some(format!("Account not found: {}", e)),
-                    error_code: Some(4000),
-                };
+                return self.reject_order(
+                    order_id,
+                    &req,
+                    RejectReason::AccountNotFound,
+                    format!("Account not found: {}", e),
+                );
             }
         };
 
@@ -592,22 +1769,25 @@ impl OrderRouter {
                     available,
                     required_funds
                 );
-                return SubmitOrderResponse {
-                    success: false,
-                    order_id: Some(order_id),
-                    status: Some("rejected".to_string()),
-                    error_message: Some(format!(
+                return self.reject_order(
+                    order_id,
+                    &req,
+                    RejectReason::InsufficientFunds,
+                    format!(
                         "Insufficient funds: available={:.2}, required={:.2}",
                         available, required_funds
-                    )),
-                    error_code: Some(4001),
-                };
+                    ),
+                );
             }
         }
 
         // 6. 预构建订单数据（无锁操作）
         let towards = self.calculate_towards(&req.direction, &req.offset);
-        let current_time = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let current_time = self
+            .clock
+            .now_local()
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string();
 
         let order = QAOrder::new(
             req.account_id.clone(),
@@ -633,20 +1813,21 @@ impl OrderRouter {
                     acc.money,
                     required_funds
                 );
-                return SubmitOrderResponse {
-                    success: false,
-                    order_id: Some(order_id),
-                    status: Some("rejected".to_string()),
-                    error_message: Some(format!(
+                return self.reject_order(
+                    order_id,
+                    &req,
+                    RejectReason::InsufficientFunds,
+                    format!(
                         "Insufficient funds: available={:.2}, required={:.2}",
                         acc.money, required_funds
-                    )),
-                    error_code: Some(4001),
-                };
+                    ),
+                );
             }
 
-            // 7.2 执行 send_order（冻结资金）
-            match acc.send_order(
+            // 7.2 执行 send_order（冻结资金，持仓先按注册表的每手数量换算）
+            self.instrument_registry
+                .apply_to_account(&mut acc, &req.instrument_id);
+            let qa_order_id = match acc.send_order(
                 &req.instrument_id,
                 req.volume,
                 &current_time,
@@ -674,17 +1855,43 @@ impl OrderRouter {
                         req.account_id,
                         e
                     );
-                    return SubmitOrderResponse {
-                        success: false,
-                        order_id: Some(order_id),
-                        status: Some("rejected".to_string()),
-                        error_message: Some(format!("Insufficient funds/margin: {:?}", e)),
-                        error_code: Some(4001),
-                    };
+                    return self.reject_order(
+                        order_id,
+                        &req,
+                        RejectReason::InsufficientFunds,
+                        format!("Insufficient funds/margin: {:?}", e),
+                    );
                 }
-            }
-            // 写锁在此自动释放（RAII）
-        };
+            };
+
+            // 7.3 指定平仓批次：按委托登记（写锁内批次不会变动），校验失败撤销冻结后拒单
+            if let Some(lot_id) = req.close_lot_id {
+                if let Err(e) = self.account_mgr.position_lots().designate_close(
+                    &req.account_id,
+                    &req.instrument_id,
+                    &qa_order_id,
+                    lot_id,
+                    towards,
+                ) {
+                    if let Err(cancel_err) = acc.cancel_order(&qa_order_id) {
+                        log::error!(
+                            "Failed to release frozen funds of {}: {:?}",
+                            order_id,
+                            cancel_err
+                        );
+                    }
+                    return self.reject_order(
+                        order_id,
+                        &req,
+                        RejectReason::InvalidOrderParams,
+                        e.to_string(),
+                    );
+                }
+            }
+
+            qa_order_id
+            // 写锁在此自动释放（RAII）
+        };
 
         // ✨ 调试日志：显示 qa_order_id 和 towards 值 @yutiansut @quantaxis
         log::info!(
@@ -697,9 +1904,16 @@ impl OrderRouter {
         );
 
         // 4. 存储订单信息
-        let timestamp = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
+        let timestamp = self.clock.now_nanos();
         let time_cond = req.time_condition.unwrap_or(TimeCondition::GFD);
         let volume_cond = req.volume_condition.unwrap_or(VolumeCondition::ANY);
+        // 4.0 灰度决策：订单生命周期内沿用下单时的撮合路径
+        // （需已设置高性能撮合通道；分片模式下各分片已独占撮合线程，不再分流）
+        let high_perf_path = self.high_perf_matcher.is_some()
+            && self.sharded_engine.is_none()
+            && self
+                .feature_gate
+                .is_enabled(USE_HIGH_PERF_MATCHING, &req.account_id);
         let route_info = OrderRouteInfo {
             order: order.clone(),
             status: OrderStatus::PendingRoute,
@@ -710,11 +1924,29 @@ impl OrderRouter {
             matching_engine_order_id: None,   // 撮合引擎订单ID (在 Accepted 事件中设置)
             time_condition: time_cond,
             volume_condition: volume_cond,
+            hedge_flag: req.hedge_flag.unwrap_or_default(),
+            high_perf_path,
         };
+        self.record_order_state(&order_id, &route_info, "order created");
 
         self.orders
             .insert(order_id.clone(), Arc::new(RwLock::new(route_info)));
 
+        // 4.1 登记存活时长（IOC 撮合后立即撤销剩余，无需 TTL）
+        // 先于撮合登记：立即全部成交时随订单离开订单簿一并移除
+        if let Some(ttl_secs) = req.ttl_secs {
+            if time_cond != TimeCondition::IOC {
+                self.order_ttl.schedule(OrderTtlEntry {
+                    order_id: order_id.clone(),
+                    account_id: req.account_id.clone(),
+                    instrument_id: req.instrument_id.clone(),
+                    ttl_secs,
+                    expire_at: self.time_service.now_millis()
+                        + (ttl_secs as i64).saturating_mul(1000),
+                });
+            }
+        }
+
         // 5. 更新账户订单索引
         self.user_orders
             .entry(req.account_id.clone())
@@ -732,8 +1964,24 @@ impl OrderRouter {
             req.order_type.clone(), // ✅ order_type 作为 price_type
         );
 
+        // 6.1 登记发往撮合的时间（看门狗检测回应丢失）
+        if let Some(ref watchdog) = self.order_watchdog {
+            watchdog.track(PendingAck {
+                order_id: order_id.clone(),
+                account_id: req.account_id.clone(),
+                instrument_id: req.instrument_id.clone(),
+                sent_at: self.clock.now_millis(),
+            });
+        }
+
         // 7. 路由到撮合引擎
-        match self.route_to_matching_engine(&req.instrument_id, order, order_id.clone()) {
+        match self.route_to_matching_engine(
+            &req.instrument_id,
+            order,
+            order_id.clone(),
+            high_perf_path,
+            turn,
+        ) {
             Ok(_) => {
                 log::info!("Order submitted successfully: {}", order_id);
 
@@ -789,6 +2037,7 @@ impl OrderRouter {
                         status: Some(updated_status.to_string()),
                         error_message: None,
                         error_code: None,
+                        adjusted_price,
                     };
                 }
 
@@ -798,6 +2047,7 @@ impl OrderRouter {
                     status: Some(final_status.to_string()),
                     error_message: None,
                     error_code: None,
+                    adjusted_price,
                 }
             }
             Err(e) => {
@@ -808,36 +2058,53 @@ impl OrderRouter {
                     let mut info = order_info.write();
                     info.status = OrderStatus::Rejected;
                 }
+                self.book_order_removed(&order_id);
+                self.release_close_designation(&order_id);
+                self.acknowledge_order(&order_id);
 
-                SubmitOrderResponse {
-                    success: false,
-                    order_id: Some(order_id),
-                    status: Some("rejected".to_string()),
-                    error_message: Some(format!("Routing error: {}", e)),
-                    error_code: Some(5000),
-                }
+                self.reject_order(
+                    order_id,
+                    &req,
+                    RejectReason::RoutingError,
+                    format!("Routing error: {}", e),
+                )
             }
         }
     }
 
     /// 路由订单到撮合引擎
+    ///
+    /// `high_perf` 为下单时的灰度决策：启用时以池化订单投递到高性能撮合线程，
+    /// 由 `high_perf::match_pooled_order` 在该线程撮合；两条路径共用同一订单簿，
+    /// 延迟与错误按路径计入对照指标；`turn` 为风控前领取的序列通道凭证，离开订单簿时释放
     fn route_to_matching_engine(
         &self,
         instrument_id: &str,
         order: Order,
         order_id: String,
+        high_perf: bool,
+        turn: SequenceTurn,
     ) -> Result<(), ExchangeError> {
-        // 获取订单簿
-        let orderbook = self
-            .matching_engine
-            .get_orderbook(instrument_id)
-            .ok_or_else(|| {
-                ExchangeError::MatchingError(format!(
-                    "Orderbook not found for instrument: {}",
-                    instrument_id
-                ))
-            })?;
+        let start = Instant::now();
+        let result =
+            self.route_to_matching_engine_inner(instrument_id, order, order_id, high_perf, turn);
+        self.feature_gate.observe(
+            USE_HIGH_PERF_MATCHING,
+            high_perf,
+            start.elapsed(),
+            result.is_ok(),
+        );
+        result
+    }
 
+    fn route_to_matching_engine_inner(
+        &self,
+        instrument_id: &str,
+        order: Order,
+        order_id: String,
+        high_perf: bool,
+        turn: SequenceTurn,
+    ) -> Result<(), ExchangeError> {
         // 转换订单方向
         let direction = match order.direction.as_str() {
             "BUY" => OrderDirection::BUY,
@@ -850,29 +2117,66 @@ impl OrderRouter {
             }
         };
 
-        // 创建撮合订单请求
-        let asset = InstrumentAsset::from_code(instrument_id);
-        let timestamp = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
+        // 撮合时间戳取领号时分配的值（同合约严格 FIFO）
+        let timestamp = turn.timestamp();
 
-        let match_request = crate::matching::orders::new_limit_order_request(
-            asset,
-            direction,
-            order.limit_price,
-            order.volume_orign,
+        // 影子模式下同一输入在影子引擎重放
+        let shadow_request = self.shadow_mode.as_ref().map(|_| ShadowRequest::Submit {
+            order_id: order_id.clone(),
+            direction: order.direction.clone(),
+            price: order.limit_price,
+            volume: order.volume_orign,
             timestamp,
-        );
-
-        // 提交到订单簿
-        let mut ob = orderbook.write();
-        let results = ob
-            .process_order(match_request)
-            .into_iter()
-            .collect::<Vec<_>>();
-        drop(ob); // 尽早释放锁
+        });
+
+        // 提交到订单簿（分片模式下在合约所在分片线程执行，灰度订单在高性能撮合线程执行）
+        let high_perf_matcher = self.high_perf_matcher.as_ref().filter(|_| high_perf);
+        let results = if let Some(matcher) = high_perf_matcher {
+            let pooled = PooledOrder {
+                order_id: order_id.clone(),
+                account_id: order.user_id.clone(),
+                instrument_id: instrument_id.to_string(),
+                direction: match direction {
+                    OrderDirection::BUY => 0,
+                    OrderDirection::SELL => 1,
+                },
+                offset: if order.offset == "OPEN" { 0 } else { 1 },
+                price: order.limit_price,
+                volume: order.volume_orign,
+                timestamp,
+                ..Default::default()
+            };
+            let engine = self.matching_engine.clone();
+            let shadow = self.shadow_mode.clone().zip(shadow_request);
+            let instrument = instrument_id.to_string();
+            matcher.execute(move || {
+                engine.execute(&instrument, |ob| {
+                    let results = match_pooled_order(ob, &pooled, pooled.timestamp);
+                    if let Some((shadow_mode, shadow_request)) = shadow {
+                        shadow_mode.observe(&instrument, shadow_request, &results);
+                    }
+                    results
+                })
+            })??
+        } else {
+            let match_request = crate::matching::orders::new_limit_order_request(
+                InstrumentAsset::from_code(instrument_id),
+                direction,
+                order.limit_price,
+                order.volume_orign,
+                timestamp,
+            );
+            self.process_on_orderbook(instrument_id, &order_id, match_request, shadow_request)?
+        };
+        // 离开订单簿即放行下一单，撮合结果处理（回报、条件单触发）不占用序列通道
+        drop(turn);
 
         // 处理撮合结果
         self.process_matching_results(&order_id, &order, results)?;
 
+        // 竞价阶段刷新虚拟开盘价
+        self.refresh_auction_indicator(instrument_id);
+
         Ok(())
     }
 
@@ -884,6 +2188,9 @@ impl OrderRouter {
     /// 3. Filled/PartiallyFilled - 对手单成交（opposite_order）
     ///
     /// 我们只处理新订单的事件，忽略对手单的事件
+    ///
+    /// 一次撮合调用分配一个撮合批次号，吃多档产生的全部成交共用该批次号；
+    /// 每一档成交取一次成交时间，主动方与对手方回报使用同一时间
     fn process_matching_results(
         &self,
         order_id: &str,
@@ -891,7 +2198,11 @@ impl OrderRouter {
         results: Vec<Result<Success, Failed>>,
     ) -> Result<(), ExchangeError> {
         let mut handled_accepted = false;
-        let mut handled_trade = false; // 是否已处理成交事件（Filled/PartiallyFilled）
+        // 新订单（taker）的撮合引擎ID，取自第一个成交事件
+        let mut taker_engine_id: Option<u64> = None;
+        let match_batch_id = self.trade_gateway.id_generator().next_match_batch_id();
+        // 最近一档成交的时间（纳秒），对手方事件沿用
+        let mut trade_ts = 0;
 
         log::debug!(
             "🔍 process_matching_results: order_id={}, user_id={}, results_count={}",
@@ -912,8 +2223,16 @@ impl OrderRouter {
                                     "🔍     Processing Accepted event for order {}",
                                     order_id
                                 );
+                                self.acknowledge_order(order_id);
                                 // Accepted 事件不涉及成交记录，is_taker 参数无影响
-                                self.handle_success_result(order_id, order, success, true)?;
+                                self.handle_success_result(
+                                    order_id,
+                                    order,
+                                    success,
+                                    true,
+                                    match_batch_id,
+                                    0,
+                                )?;
                                 handled_accepted = true;
                             } else {
                                 log::debug!(
@@ -933,19 +2252,29 @@ impl OrderRouter {
                             ..
                         } => {
                             // 处理成交事件
-                            // qars 会返回两个事件：新订单成交 + 对手单成交
+                            // qars 每档成交返回两个事件：新订单成交 + 对手单成交
                             // 我们需要更新对手单的状态（如果它属于我们管理的订单）
+                            // 吃多档时新订单的每一档成交都按 taker 处理
 
-                            if !handled_trade {
-                                // 第一个事件：新订单的成交（taker - 主动方）
+                            if taker_engine_id.map_or(true, |id| id == match_order_id) {
+                                // 新订单的成交（taker - 主动方）
                                 log::debug!(
                                     "🔍     Processing TAKER order trade: order_id={}, opposite={}",
                                     match_order_id,
                                     opposite_order_id
                                 );
-                                // ✨ is_taker=true: 主动方，记录成交到 TradeRecorder @yutiansut @quantaxis
-                                self.handle_success_result(order_id, order, success.clone(), true)?;
-                                handled_trade = true;
+                                self.acknowledge_order(order_id);
+                                trade_ts = self.trade_gateway.id_generator().now_nanos();
+                                // ✨ is_taker=true: 主动方，发布成交事件 @yutiansut @quantaxis
+                                self.handle_success_result(
+                                    order_id,
+                                    order,
+                                    success.clone(),
+                                    true,
+                                    match_batch_id,
+                                    trade_ts,
+                                )?;
+                                taker_engine_id = Some(match_order_id);
                             } else {
                                 // 第二个事件：对手单（挂单方）的成交
                                 // qars 返回的第二个 Filled 事件中：
@@ -969,12 +2298,14 @@ impl OrderRouter {
                                         if let Some(maker_info) = self.orders.get(&maker_order_str) {
                                             let maker_order_data = maker_info.read().order.clone();
                                             // 处理挂单方的成交 - 更新其账户持仓和资金
-                                            // ✨ is_taker=false: 被动方（maker），不记录成交到 TradeRecorder @yutiansut @quantaxis
+                                            // ✨ is_taker=false: 被动方（maker），不重复发布成交事件 @yutiansut @quantaxis
                                             self.handle_success_result(
                                                 &maker_order_str,
                                                 &maker_order_data,
                                                 success,
-                                                false, // maker 不记录成交
+                                                false, // maker 不发布成交事件
+                                                match_batch_id,
+                                                trade_ts,
                                             )?;
                                         }
                                     } else {
@@ -994,12 +2325,20 @@ impl OrderRouter {
                         _ => {
                             // 其他事件正常处理（Cancelled, Amended等）
                             // 不涉及成交记录，is_taker 参数无影响
-                            self.handle_success_result(order_id, order, success, true)?;
+                            self.handle_success_result(
+                                order_id,
+                                order,
+                                success,
+                                true,
+                                match_batch_id,
+                                0,
+                            )?;
                         }
                     }
                 }
                 Err(failed) => {
                     log::warn!("Order matching failed: {:?}", failed);
+                    self.acknowledge_order(order_id);
 
                     // Phase 6: 使用新的 handle_order_rejected_new (交易所推送REJECTED回报)
                     let reason = format!("{:?}", failed);
@@ -1017,29 +2356,121 @@ impl OrderRouter {
                     );
 
                     log::debug!("Order {} rejected, reason: {}", order_id, reason);
+                    self.rejection_stats
+                        .record(RejectReason::MatchingRejected, &order.instrument_id);
 
                     // 更新订单状态为拒绝
                     if let Some(order_info) = self.orders.get(order_id) {
                         let mut info = order_info.write();
                         info.status = OrderStatus::Rejected;
-                        info.update_time = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
+                        info.update_time = self.clock.now_nanos();
+                        self.record_order_state(order_id, &info, reason.as_str());
                     }
+                    self.book_order_removed(order_id);
+                    self.release_close_designation(order_id);
                 }
             }
         }
         Ok(())
     }
 
+    /// 订单进入订单簿，计入挂单统计
+    fn book_order_resting(&self, order_id: &str, instrument_id: &str) {
+        if let Some(ref limiter) = self.book_limiter {
+            limiter.on_order_resting(order_id, instrument_id);
+        }
+    }
+
+    /// 订单离开订单簿（全部成交/撤单/拒绝），释放挂单额度
+    fn book_order_removed(&self, order_id: &str) {
+        if let Some(ref limiter) = self.book_limiter {
+            limiter.on_order_removed(order_id);
+        }
+        self.order_ttl.remove(order_id);
+    }
+
+    /// 撤销委托的指定平仓登记（撤单/拒单/过期）
+    ///
+    /// 全部成交的委托由 TradeGateway 在最后一笔成交入账后撤销
+    fn release_close_designation(&self, order_id: &str) {
+        if let Some(order_info) = self.orders.get(order_id) {
+            let info = order_info.read();
+            self.account_mgr.position_lots().cancel_designation(
+                &info.order.user_id,
+                &info.order.instrument_id,
+                &info.qa_order_id,
+            );
+        }
+    }
+
+    /// 收到撮合回应，从看门狗等待集合中移除
+    fn acknowledge_order(&self, order_id: &str) {
+        if let Some(ref watchdog) = self.order_watchdog {
+            watchdog.acknowledge(order_id);
+        }
+    }
+
+    /// 发布撮合成交事件（主动方每档成交一次）
+    ///
+    /// 买卖双方按主动方方向确定；对手方不在本交易所订单簿时用主动方账户/引擎订单号占位
+    fn publish_trade_event(
+        &self,
+        order_id: &str,
+        order: &Order,
+        price: f64,
+        volume: f64,
+        opposite_order_id: u64,
+        opposite_user_id: Option<&str>,
+        opposite_order_id_str: Option<&str>,
+        match_batch_id: i64,
+        timestamp: i64,
+    ) {
+        let opposite_user = opposite_user_id.unwrap_or(&order.user_id).to_string();
+        let opposite_order = opposite_order_id_str
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| format!("opposite_{}", opposite_order_id));
+        let taker = (order.user_id.clone(), order_id.to_string());
+        let maker = (opposite_user, opposite_order);
+        let ((buy_user_id, buy_order_id), (sell_user_id, sell_order_id)) =
+            if order.direction == "SELL" {
+                (maker, taker)
+            } else {
+                (taker, maker)
+            };
+
+        self.trade_bus.publish(TradeEvent {
+            exchange_id: order.exchange_id.clone(),
+            instrument_id: order.instrument_id.clone(),
+            price,
+            volume,
+            direction: order.direction.clone(),
+            buy_user_id,
+            sell_user_id,
+            buy_order_id,
+            sell_order_id,
+            taker_order_id: order_id.to_string(),
+            // 按交易所时区归属交易日（夜盘成交归属下一交易日）
+            trading_day: self.clock.trading_day().format("%Y-%m-%d").to_string(),
+            trade_type: TradeType::Normal,
+            match_batch_id,
+            timestamp,
+        });
+    }
+
     /// 处理成功的撮合结果 (Phase 6: 使用新的回报机制)
     /// 处理成交结果
     /// @yutiansut @quantaxis
-    /// is_taker: 是否为主动方（taker），只有 taker 才记录到 TradeRecorder
+    /// is_taker: 是否为主动方（taker），只有 taker 才发布成交事件（一笔成交只发布一次）
+    /// match_batch_id: 撮合批次号，透传到成交回报与逐笔成交行情
+    /// trade_ts: 成交时间（纳秒），仅成交事件使用
     fn handle_success_result(
         &self,
         order_id: &str,
         order: &Order,
         success: Success,
         is_taker: bool, // ✨ 是否为主动方
+        match_batch_id: i64,
+        trade_ts: i64,
     ) -> Result<(), ExchangeError> {
         match success {
             Success::Accepted { id, order_type: _, ts } => {
@@ -1052,11 +2483,13 @@ impl OrderRouter {
                     info.status = OrderStatus::Submitted;
                     info.update_time = ts;
                     info.matching_engine_order_id = Some(id); // 存储撮合引擎订单ID，用于撤单
+                    self.record_order_state(order_id, &info, "accepted by matching engine");
                 }
 
                 // ✨ 存储反向映射: matching_engine_order_id → order_id / user_id @yutiansut @quantaxis
                 // 用于在成交时通过对手单的matching_engine_order_id找到对应的order_id和user_id
                 self.engine_id_to_order.insert(id, order_id.to_string());
+                self.book_order_resting(order_id, &order.instrument_id);
                 self.engine_id_to_user.insert(id, order.user_id.clone()); // ✨ O(1) 直接映射
                 log::debug!("💾 Stored reverse mapping: engine_id={} → order_id={}, user_id={}", id, order_id, order.user_id);
 
@@ -1086,7 +2519,7 @@ impl OrderRouter {
                 if let Some(ref broadcaster) = self.market_broadcaster {
                     // 获取更新后的bid/ask价格用于广播
                     if let Some(orderbook) =
-                        self.matching_engine.get_orderbook(&order.instrument_id)
+                        self.get_orderbook(&order.instrument_id)
                     {
                         let _ob = orderbook.read();
                         let side = if order.direction == "BUY" {
@@ -1129,28 +2562,17 @@ impl OrderRouter {
                     info.status = OrderStatus::Filled;
                     info.update_time = ts;
                     info.filled_volume = volume;
+                    self.record_order_state(
+                        order_id,
+                        &info,
+                        format!("filled {}@{}", volume, price),
+                    );
                 }
+                self.book_order_removed(order_id);
 
                 // 更新成交统计
                 self.update_trade_stats(price, volume);
-
-                // 广播Tick成交数据
-                if let Some(ref broadcaster) = self.market_broadcaster {
-                    let direction_str = if order.direction == "BUY" {
-                        "buy"
-                    } else {
-                        "sell"
-                    };
-                    broadcaster.broadcast_tick(
-                        order.instrument_id.clone(),
-                        price,
-                        volume,
-                        direction_str.to_string(),
-                    );
-
-                    // 同时广播最新价
-                    broadcaster.broadcast_last_price(order.instrument_id.clone(), price);
-                }
+                self.record_trade_volume(order, volume);
 
                 // 持久化Tick数据到WAL
                 self.persist_tick_data(&order.instrument_id, price, volume)?;
@@ -1159,11 +2581,12 @@ impl OrderRouter {
                 self.persist_orderbook_snapshot(&order.instrument_id)?;
 
                 // 获取 qars 订单ID
-                let qa_order_id = if let Some(order_info) = self.orders.get(order_id) {
-                    order_info.read().qa_order_id.clone()
+                let (qa_order_id, hedge_flag) = if let Some(order_info) = self.orders.get(order_id) {
+                    let info = order_info.read();
+                    (info.qa_order_id.clone(), info.hedge_flag)
                 } else {
                     log::error!("Order info not found for {}", order_id);
-                    String::new()
+                    (String::new(), HedgeFlag::default())
                 };
 
                 // ✨ O(1) 直接查找对手方的 user_id @yutiansut @quantaxis
@@ -1186,6 +2609,21 @@ impl OrderRouter {
                     opposite_order_id_str
                 );
 
+                // 先发布成交事件（成交记录/行情/逐笔广播），账户处理失败不影响行情
+                if is_taker {
+                    self.publish_trade_event(
+                        order_id,
+                        order,
+                        price,
+                        volume,
+                        opposite_order_id,
+                        opposite_user_id.as_deref(),
+                        opposite_order_id_str.as_deref(),
+                        match_batch_id,
+                        trade_ts,
+                    );
+                }
+
                 // Phase 6: 使用新的 handle_trade_new (交易所只推送TRADE回报，不判断FILLED/PARTIAL)
                 // 注意：这里假设我们使用已生成的exchange_order_id（从Accepted事件保存）
                 // 简化实现：使用match_order_id作为exchange_order_id
@@ -1201,10 +2639,10 @@ impl OrderRouter {
                     volume,
                     price,
                     Some(opposite_order_id as i64),
-                    opposite_user_id.as_deref(), // ✨ 传递对手方user_id
                     &qa_order_id, // ✨ 传递qars订单ID
-                    opposite_order_id_str.as_deref(), // ✨ 传递对手方真实订单ID
-                    is_taker, // ✨ 是否为主动方，只有 taker 记录成交 @yutiansut @quantaxis
+                    hedge_flag,
+                    match_batch_id,
+                    trade_ts,
                 )?;
 
                 log::debug!(
@@ -1242,28 +2680,16 @@ impl OrderRouter {
                     info.status = OrderStatus::PartiallyFilled;
                     info.update_time = ts;
                     info.filled_volume += volume;
+                    self.record_order_state(
+                        order_id,
+                        &info,
+                        format!("partially filled {}@{}", volume, price),
+                    );
                 }
 
                 // 更新成交统计
                 self.update_trade_stats(price, volume);
-
-                // 广播Tick成交数据
-                if let Some(ref broadcaster) = self.market_broadcaster {
-                    let direction_str = if order.direction == "BUY" {
-                        "buy"
-                    } else {
-                        "sell"
-                    };
-                    broadcaster.broadcast_tick(
-                        order.instrument_id.clone(),
-                        price,
-                        volume,
-                        direction_str.to_string(),
-                    );
-
-                    // 同时广播最新价
-                    broadcaster.broadcast_last_price(order.instrument_id.clone(), price);
-                }
+                self.record_trade_volume(order, volume);
 
                 // 持久化Tick数据到WAL
                 self.persist_tick_data(&order.instrument_id, price, volume)?;
@@ -1272,11 +2698,12 @@ impl OrderRouter {
                 self.persist_orderbook_snapshot(&order.instrument_id)?;
 
                 // 获取 qars 订单ID
-                let qa_order_id = if let Some(order_info) = self.orders.get(order_id) {
-                    order_info.read().qa_order_id.clone()
+                let (qa_order_id, hedge_flag) = if let Some(order_info) = self.orders.get(order_id) {
+                    let info = order_info.read();
+                    (info.qa_order_id.clone(), info.hedge_flag)
                 } else {
                     log::error!("Order info not found for {}", order_id);
-                    String::new()
+                    (String::new(), HedgeFlag::default())
                 };
 
                 // ✨ O(1) 直接查找对手方的 user_id @yutiansut @quantaxis
@@ -1299,6 +2726,21 @@ impl OrderRouter {
                     opposite_order_id_str
                 );
 
+                // 先发布成交事件（成交记录/行情/逐笔广播），账户处理失败不影响行情
+                if is_taker {
+                    self.publish_trade_event(
+                        order_id,
+                        order,
+                        price,
+                        volume,
+                        opposite_order_id,
+                        opposite_user_id.as_deref(),
+                        opposite_order_id_str.as_deref(),
+                        match_batch_id,
+                        trade_ts,
+                    );
+                }
+
                 // Phase 6: 使用新的 handle_trade_new (交易所不区分FILLED/PARTIAL，只推送TRADE)
                 // ✨ 修复：传递qa_order_id用于调用receive_deal_sim @yutiansut @quantaxis
                 let trade_id = self.trade_gateway.handle_trade_new(
@@ -1312,10 +2754,10 @@ impl OrderRouter {
                     volume,
                     price,
                     Some(opposite_order_id as i64),
-                    opposite_user_id.as_deref(), // ✨ 传递对手方user_id
                     &qa_order_id, // ✨ 传递qars订单ID
-                    opposite_order_id_str.as_deref(), // ✨ 传递对手方真实订单ID
-                    is_taker, // ✨ 是否为主动方，只有 taker 记录成交 @yutiansut @quantaxis
+                    hedge_flag,
+                    match_batch_id,
+                    trade_ts,
                 )?;
 
                 log::debug!(
@@ -1337,10 +2779,17 @@ impl OrderRouter {
                     info.status = OrderStatus::Cancelled;
                     info.update_time = ts;
                     let remaining = info.order.volume_orign - info.filled_volume;
+                    self.record_order_state(
+                        order_id,
+                        &info,
+                        format!("cancel accepted, {} left unfilled", remaining),
+                    );
                     (info.qa_order_id.clone(), remaining)
                 } else {
                     (String::new(), order.volume_orign)
                 };
+                self.book_order_removed(order_id);
+                self.release_close_designation(order_id);
 
                 // Phase 6: 使用新的 handle_cancel_accepted_new (交易所推送CANCEL_ACCEPTED回报)
                 // ✨ 修复：传递 qa_order_id 用于调用 qars cancel_order 释放冻结资金 @yutiansut @quantaxis
@@ -1371,7 +2820,7 @@ impl OrderRouter {
                 if let Some(ref broadcaster) = self.market_broadcaster {
                     // 撤单后，该价格档位的挂单量减少或消失
                     if let Some(orderbook) =
-                        self.matching_engine.get_orderbook(&order.instrument_id)
+                        self.get_orderbook(&order.instrument_id)
                     {
                         let ob = orderbook.read();
                         let side = if order.direction == "BUY" {
@@ -1439,6 +2888,108 @@ impl OrderRouter {
 
     /// 撤单
     pub fn cancel_order(&self, req: CancelOrderRequest) -> Result<(), ExchangeError> {
+        let _in_flight = self
+            .enter_order_entry()
+            .map_err(ExchangeError::OrderError)?;
+        let _lease = self.acquire_account_lease(&req.account_id)?;
+
+        // 0. 账户撤单频率限制 @yutiansut @quantaxis
+        if let Some(ref limiter) = self.rate_limiter {
+            let account_type = self.account_type_of(&req.account_id);
+            limiter
+                .check_cancel(&req.account_id, account_type)
+                .map_err(ExchangeError::RiskCheckFailed)?;
+        }
+
+        self.cancel_order_on_book(&req)
+    }
+
+    /// 改单：撤销原订单后按新价格/数量重新下单
+    ///
+    /// 改单单独计频率并计入报撤比，重新下单不再占用下单额度。
+    /// 撤单失败返回 Err（原订单不变）；撤单成功后重新下单的结果在 Ok 中返回。
+    pub fn amend_order(
+        &self,
+        req: AmendOrderRequest,
+    ) -> Result<SubmitOrderResponse, ExchangeError> {
+        let _in_flight = self
+            .enter_order_entry()
+            .map_err(ExchangeError::OrderError)?;
+        let _lease = self.acquire_account_lease(&req.account_id)?;
+
+        // 0. 账户改单频率限制与报撤比 @yutiansut @quantaxis
+        if let Some(ref limiter) = self.rate_limiter {
+            let account_type = self.account_type_of(&req.account_id);
+            limiter
+                .check_amend(&req.account_id, account_type)
+                .map_err(ExchangeError::RiskCheckFailed)?;
+        }
+
+        // 1. 原订单：未成交部分才可改单
+        let (original, filled) = self
+            .orders
+            .get(&req.order_id)
+            .map(|info| {
+                let i = info.read();
+                (i.order.clone(), i.filled_volume)
+            })
+            .ok_or_else(|| {
+                ExchangeError::OrderError(format!("Order not found: {}", req.order_id))
+            })?;
+        if filled > 0.0 {
+            return Err(ExchangeError::OrderError(format!(
+                "Partially filled order cannot be amended: {}",
+                req.order_id
+            )));
+        }
+
+        // 2. 撤销原订单
+        self.cancel_order_on_book(&CancelOrderRequest {
+            account_id: req.account_id.clone(),
+            order_id: req.order_id.clone(),
+        })?;
+
+        // 3. 按新价格/数量重新下单
+        let submit_req = SubmitOrderRequest {
+            account_id: req.account_id,
+            instrument_id: original.instrument_id,
+            direction: original.direction,
+            offset: original.offset,
+            volume: req.new_volume.unwrap_or(original.volume_orign),
+            price: req.new_price.unwrap_or(original.limit_price),
+            order_type: original.price_type,
+            time_condition: None,
+            volume_condition: None,
+            hedge_flag: None,
+            ttl_secs: None,
+            client_order_id: None,
+            close_lot_id: None,
+        };
+        Ok(self.submit_order_with_options(
+            submit_req,
+            OrderSubmitOptions {
+                amend: true,
+                ..Default::default()
+            },
+        ))
+    }
+
+    /// 撤单：校验订单后从撮合引擎撤销（调用方负责在途计数与频率限制）
+    ///
+    /// 与下单共用合约序列通道：先排队再校验订单状态，排在前面的下单成交后撤单按新状态处理
+    fn cancel_order_on_book(&self, req: &CancelOrderRequest) -> Result<(), ExchangeError> {
+        let instrument_id = self
+            .orders
+            .get(&req.order_id)
+            .map(|info| info.read().order.instrument_id.clone())
+            .ok_or_else(|| {
+                ExchangeError::OrderError(format!("Order not found: {}", req.order_id))
+            })?;
+        let turn = self
+            .matching_engine
+            .order_sequencer()
+            .enter(&instrument_id, || self.clock.now_nanos());
+
         // 1. 验证订单存在
         let order_info = self.orders.get(&req.order_id).ok_or_else(|| {
             ExchangeError::OrderError(format!("Order not found: {}", req.order_id))
@@ -1454,15 +3005,31 @@ impl OrderRouter {
         }
 
         // 2.5 交易状态检查 @yutiansut @quantaxis
-        if let Some(ref state_machine) = self.trading_state_machine {
-            use crate::exchange::OrderValidation;
-            match state_machine.validate_cancel(&info.order.instrument_id) {
-                OrderValidation::Allowed => {}
-                OrderValidation::Rejected(reason) => {
-                    return Err(ExchangeError::OrderError(format!(
-                        "Cancel rejected by trading state: {}",
-                        reason
-                    )));
+        // 熔断冷静期内按熔断规则决定是否允许撤单，否则按交易状态判断
+        let breaker_cancel = self
+            .circuit_breaker
+            .as_ref()
+            .and_then(|b| b.cancel_allowed(&info.order.instrument_id));
+        match breaker_cancel {
+            Some(true) => {}
+            Some(false) => {
+                return Err(ExchangeError::OrderError(format!(
+                    "Cancel rejected by circuit breaker: {} is in cooling-off period",
+                    info.order.instrument_id
+                )));
+            }
+            None => {
+                if let Some(ref state_machine) = self.trading_state_machine {
+                    use crate::exchange::OrderValidation;
+                    match state_machine.validate_cancel(&info.order.instrument_id) {
+                        OrderValidation::Allowed => {}
+                        OrderValidation::Rejected(reason) => {
+                            return Err(ExchangeError::OrderError(format!(
+                                "Cancel rejected by trading state: {}",
+                                reason
+                            )));
+                        }
+                    }
                 }
             }
         }
@@ -1487,6 +3054,8 @@ impl OrderRouter {
         let direction_str = info.order.direction.clone();
         // ✨ 保存订单信息用于后续处理 @yutiansut @quantaxis
         let order = info.order.clone();
+        // 撤单沿用下单时的灰度路径计入对照指标
+        let high_perf_path = info.high_perf_path;
 
         // 释放写锁，避免在调用撮合引擎时持有锁
         drop(info);
@@ -1511,24 +3080,28 @@ impl OrderRouter {
             direction,
         };
 
-        // 获取订单簿
-        let orderbook = self
-            .matching_engine
-            .get_orderbook(&instrument_id)
-            .ok_or_else(|| {
-                ExchangeError::MatchingError(format!(
-                    "Orderbook not found for instrument: {}",
-                    instrument_id
-                ))
-            })?;
+        let shadow_request = self.shadow_mode.as_ref().map(|_| ShadowRequest::Cancel {
+            order_id: req.order_id.clone(),
+            direction: direction_str.clone(),
+        });
 
         // 提交撤单请求到撮合引擎
-        let mut ob = orderbook.write();
-        let results = ob
-            .process_order(cancel_request)
-            .into_iter()
-            .collect::<Vec<_>>();
-        drop(ob);
+        let start = Instant::now();
+        let results = self.process_on_orderbook(
+            &instrument_id,
+            &req.order_id,
+            cancel_request,
+            shadow_request,
+        );
+        // 离开订单簿即放行下一单
+        drop(turn);
+        self.feature_gate.observe(
+            USE_HIGH_PERF_MATCHING,
+            high_perf_path,
+            start.elapsed(),
+            results.is_ok(),
+        );
+        let results = results?;
 
         // 处理撤单结果
         // ✨ 修复：必须调用 handle_success_result 来处理 Success::Cancelled 事件
@@ -1539,8 +3112,10 @@ impl OrderRouter {
                     log::info!("Cancel order success: {:?}", success);
                     // ✨ 调用 handle_success_result 处理撤单成功事件
                     // 这会触发 Success::Cancelled 分支，更新订单状态并释放冻结资金
-                    // 撤单不涉及成交记录，is_taker 参数无影响
-                    if let Err(e) = self.handle_success_result(&req.order_id, &order, success, true) {
+                    // 撤单不涉及成交记录，is_taker / 撮合批次参数无影响
+                    if let Err(e) =
+                        self.handle_success_result(&req.order_id, &order, success, true, 0, 0)
+                    {
                         log::error!("Failed to handle cancel success result: {:?}", e);
                     }
                 }
@@ -1554,6 +3129,9 @@ impl OrderRouter {
             }
         }
 
+        // 竞价阶段刷新虚拟开盘价
+        self.refresh_auction_indicator(&instrument_id);
+
         log::info!("Order cancelled from matching engine: {}", req.order_id);
         Ok(())
     }
@@ -1571,7 +3149,7 @@ impl OrderRouter {
         price: f64,
     ) -> bool {
         // 获取订单簿
-        let orderbook = match self.matching_engine.get_orderbook(instrument_id) {
+        let orderbook = match self.get_orderbook(instrument_id) {
             Some(ob) => ob,
             None => {
                 log::warn!("[FOK] Orderbook not found for {}", instrument_id);
@@ -1680,76 +3258,496 @@ impl OrderRouter {
         }
     }
 
-    /// 查询订单
-    pub fn query_order(&self, order_id: &str) -> Option<Order> {
-        self.orders
-            .get(order_id)
-            .map(|info| info.read().order.clone())
-    }
+    /// 撤销已到期的 TTL 订单，返回本次撤销数量
+    ///
+    /// 停止接单、账户租约被其他实例持有或交易状态暂不允许撤单时，稍后重试；
+    /// 订单已成交/已撤（含用户手动撤单抢先）时直接丢弃
+    pub fn expire_orders(&self) -> usize {
+        let now = self.time_service.now_millis();
+        let mut cancelled = 0;
+
+        for entry in self.order_ttl.pop_expired(now) {
+            if !matches!(
+                self.get_order_status(&entry.order_id),
+                Some(OrderStatus::Submitted | OrderStatus::PartiallyFilled)
+            ) {
+                continue;
+            }
 
-    /// 查询用户所有订单
-    pub fn query_user_orders(&self, user_id: &str) -> Vec<Order> {
-        if let Some(order_ids) = self.user_orders.get(user_id) {
-            order_ids
-                .read()
-                .iter()
-                .filter_map(|order_id| self.query_order(order_id))
-                .collect()
-        } else {
-            Vec::new()
+            let req = CancelOrderRequest {
+                account_id: entry.account_id.clone(),
+                order_id: entry.order_id.clone(),
+            };
+            let result = self
+                .enter_order_entry()
+                .map_err(ExchangeError::OrderError)
+                .and_then(|_in_flight| {
+                    let _lease = self.acquire_account_lease(&req.account_id)?;
+                    self.cancel_order_on_book(&req)
+                });
+            match result {
+                Ok(()) => {
+                    cancelled += 1;
+                    log::info!(
+                        "Order {} expired after {}s, remaining volume cancelled",
+                        entry.order_id,
+                        entry.ttl_secs
+                    );
+                }
+                Err(e) => {
+                    // 撤单失败但订单仍在订单簿：稍后重试
+                    if matches!(
+                        self.get_order_status(&entry.order_id),
+                        Some(OrderStatus::Submitted | OrderStatus::PartiallyFilled)
+                    ) {
+                        log::warn!("Failed to cancel expired order {}: {}", entry.order_id, e);
+                        self.order_ttl.schedule(OrderTtlEntry {
+                            expire_at: now + ORDER_TTL_RETRY_MS,
+                            ..entry
+                        });
+                    }
+                }
+            }
         }
-    }
 
-    /// 获取订单状态
-    pub fn get_order_status(&self, order_id: &str) -> Option<OrderStatus> {
-        self.orders.get(order_id).map(|info| info.read().status)
+        if let Err(e) = self.order_ttl.flush() {
+            log::error!("Failed to persist order TTL: {}", e);
+        }
+        cancelled
     }
 
-    /// 更新订单状态 (由 TradeGateway 调用)
-    pub fn update_order_status(
-        &self,
-        order_id: &str,
-        status: OrderStatus,
-    ) -> Result<(), ExchangeError> {
-        let order_info = self
-            .orders
-            .get(order_id)
-            .ok_or_else(|| ExchangeError::OrderError(format!("Order not found: {}", order_id)))?;
+    /// 启动订单 TTL 扫描线程（路由器释放后退出）
+    pub fn start_order_ttl_scanner(
+        self: &Arc<Self>,
+        interval: Duration,
+    ) -> std::thread::JoinHandle<()> {
+        let router = Arc::downgrade(self);
+        std::thread::spawn(move || {
+            log::info!(
+                "Order TTL scanner started (interval: {}ms)",
+                interval.as_millis()
+            );
+            loop {
+                std::thread::sleep(interval);
+                match router.upgrade() {
+                    Some(router) => {
+                        router.expire_orders();
+                    }
+                    None => break,
+                }
+            }
+        })
+    }
 
-        let mut info = order_info.write();
-        info.status = status;
-        info.update_time = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
+    /// 看门狗巡检：处理超时未收到撮合回应的订单，到期时执行全量对账，返回本次自动拒单数
+    ///
+    /// 停止接单期间不处理（避免停机后改写账户状态）
+    pub fn check_order_watchdog(&self) -> usize {
+        let Some(watchdog) = self.order_watchdog.clone() else {
+            return 0;
+        };
+        if self.halt_reason.read().is_some() {
+            return 0;
+        }
 
-        // 如果订单完成，从风控追踪中移除
-        if matches!(
-            status,
-            OrderStatus::Filled | OrderStatus::Cancelled | OrderStatus::Rejected
-        ) {
-            self.risk_checker
-                .remove_active_order(&info.order.user_id, order_id);
+        let now = self.clock.now_millis();
+        let mut rejected = 0;
+        for entry in watchdog.overdue_at(now) {
+            log::warn!(
+                "[OrderWatchdog] Order {} ({} {}) not acknowledged by matching engine for {}ms",
+                entry.order_id,
+                entry.account_id,
+                entry.instrument_id,
+                now - entry.sent_at
+            );
+            if self.resolve_unacknowledged_order(&entry.order_id) == AckResolution::Rejected {
+                rejected += 1;
+            }
         }
 
-        Ok(())
+        if watchdog.reconcile_due_at(now) {
+            self.reconcile_with_engine();
+        }
+        rejected
     }
 
-    /// 生成订单ID
-    fn generate_order_id(&self) -> String {
-        let seq = self.order_seq.fetch_add(1, Ordering::SeqCst);
-        let timestamp = chrono::Utc::now().timestamp_millis();
-        format!("O{}{:010}", timestamp, seq)
+    /// 处理超时未收到撮合回应的订单
+    ///
+    /// 向撮合引擎查询订单：已登记撮合引擎订单ID时按ID查询，否则（Accepted 回应丢失）在订单簿中
+    /// 查找方向/价格/数量一致且不属于任何已知订单的挂单。找到则补登记为已报并推送回报；
+    /// 确认不存在则拒单、释放冻结资金并告警
+    pub fn resolve_unacknowledged_order(&self, order_id: &str) -> AckResolution {
+        let resolution = self.resolve_unacknowledged(order_id);
+        if let Some(ref watchdog) = self.order_watchdog {
+            watchdog.record_resolution(order_id, resolution);
+        }
+        resolution
     }
 
-    /// 获取市价单的执行价格 @yutiansut @quantaxis
-    ///
-    /// 买单：使用卖一价（ask_price）
-    /// 卖单：使用买一价（bid_price）
-    /// 如果没有对手盘，使用 last_price 或结算价
-    fn get_market_price_for_order(&self, instrument_id: &str, direction: &str) -> f64 {
-        // 1. 尝试从订单簿获取对手盘价格
-        if let Some(orderbook) = self.matching_engine.get_orderbook(instrument_id) {
-            let ob = orderbook.read();
+    fn resolve_unacknowledged(&self, order_id: &str) -> AckResolution {
+        let Some(order_info) = self.orders.get(order_id).map(|r| r.value().clone()) else {
+            return AckResolution::Acknowledged;
+        };
+        let (order, engine_order_id, qa_order_id) = {
+            let info = order_info.read();
+            if info.status != OrderStatus::PendingRoute {
+                return AckResolution::Acknowledged;
+            }
+            (
+                info.order.clone(),
+                info.matching_engine_order_id,
+                info.qa_order_id.clone(),
+            )
+        };
 
-            let price = match direction {
+        match self.query_engine_order(&order, engine_order_id) {
+            Some(resting) => {
+                {
+                    let mut info = order_info.write();
+                    if info.status != OrderStatus::PendingRoute {
+                        return AckResolution::Acknowledged;
+                    }
+                    info.status = OrderStatus::Submitted;
+                    info.update_time = self.clock.now_nanos();
+                    info.matching_engine_order_id = Some(resting.engine_order_id);
+                    self.record_order_state(
+                        order_id,
+                        &info,
+                        "acknowledgement lost, recovered from matching engine",
+                    );
+                }
+                self.engine_id_to_order
+                    .insert(resting.engine_order_id, order_id.to_string());
+                self.engine_id_to_user
+                    .insert(resting.engine_order_id, order.user_id.clone());
+                self.book_order_resting(order_id, &order.instrument_id);
+
+                if let Err(e) = self.trade_gateway.handle_order_accepted_new(
+                    &order.exchange_id,
+                    &order.instrument_id,
+                    &order.user_id,
+                    order_id,
+                    &order.direction,
+                    &order.offset,
+                    &order.price_type,
+                    order.limit_price,
+                    order.volume_orign,
+                ) {
+                    log::error!(
+                        "Failed to push recovered acceptance for {}: {}",
+                        order_id,
+                        e
+                    );
+                }
+                log::warn!(
+                    "[OrderWatchdog] Order {} acknowledgement lost, recovered from matching engine: engine_order_id={}",
+                    order_id,
+                    resting.engine_order_id
+                );
+                AckResolution::Recovered
+            }
+            None => {
+                {
+                    let mut info = order_info.write();
+                    if info.status != OrderStatus::PendingRoute {
+                        return AckResolution::Acknowledged;
+                    }
+                    info.status = OrderStatus::Rejected;
+                    info.update_time = self.clock.now_nanos();
+                    self.record_order_state(
+                        order_id,
+                        &info,
+                        "no acknowledgement from matching engine",
+                    );
+                }
+
+                // 释放冻结资金
+                match self.account_mgr.get_account(&order.user_id) {
+                    Ok(account) => {
+                        if let Err(e) = account.write().cancel_order(&qa_order_id) {
+                            log::error!(
+                                "[OrderWatchdog] Failed to release frozen funds of {}: {:?}",
+                                order_id,
+                                e
+                            );
+                        }
+                    }
+                    Err(e) => log::error!(
+                        "[OrderWatchdog] Account {} of order {} not found: {}",
+                        order.user_id,
+                        order_id,
+                        e
+                    ),
+                }
+                self.risk_checker
+                    .remove_active_order(&order.user_id, order_id);
+                self.book_order_removed(order_id);
+                self.release_close_designation(order_id);
+
+                let reason = RejectReason::MatchingAckTimeout;
+                let message = format!(
+                    "No acknowledgement from matching engine within {}ms and order not found in orderbook",
+                    self.order_watchdog
+                        .as_ref()
+                        .map_or(0, |w| w.config().ack_timeout_ms)
+                );
+                self.rejection_stats.record(reason, &order.instrument_id);
+                if let Err(e) = self.trade_gateway.handle_order_rejected_pre_trade(
+                    &order.instrument_id,
+                    &order.user_id,
+                    order_id,
+                    &order.direction,
+                    &order.offset,
+                    &order.price_type,
+                    order.limit_price,
+                    order.volume_orign,
+                    &message,
+                    reason,
+                    reason.error_code(),
+                ) {
+                    log::error!("Failed to push rejection for order {}: {}", order_id, e);
+                }
+                log::error!(
+                    "[OrderWatchdog] ALERT: order {} of {} lost by matching engine, rejected and frozen funds released",
+                    order_id,
+                    order.user_id
+                );
+                AckResolution::Rejected
+            }
+        }
+    }
+
+    /// 在撮合引擎订单簿中查询订单（未知撮合引擎订单ID时按方向/价格/数量匹配未登记的挂单）
+    fn query_engine_order(
+        &self,
+        order: &Order,
+        engine_order_id: Option<u64>,
+    ) -> Option<RestingOrder> {
+        let orderbook = self.get_orderbook(&order.instrument_id)?;
+        let resting = resting_orders(&orderbook.read());
+        match engine_order_id {
+            Some(id) => resting.into_iter().find(|o| o.engine_order_id == id),
+            None => resting.into_iter().find(|o| {
+                o.direction == order.direction
+                    && o.price == order.limit_price
+                    && o.volume == order.volume_orign
+                    && !self.engine_id_to_order.contains_key(&o.engine_order_id)
+            }),
+        }
+    }
+
+    /// 路由器在途订单（已报/部分成交）与撮合引擎挂单全量对账，只报告差异不做修正
+    ///
+    /// 对账期间撮合仍在进行，单次出现的差异可能是瞬时状态，连续出现才需要人工介入
+    pub fn reconcile_with_engine(&self) -> ReconcileReport {
+        let mut report = ReconcileReport {
+            checked_at: self.clock.now_millis(),
+            ..Default::default()
+        };
+
+        // 路由器侧：合约 -> (撮合引擎订单ID -> (订单ID, 剩余量))
+        let mut router_live: HashMap<String, HashMap<u64, (String, f64)>> = HashMap::new();
+        for entry in self.orders.iter() {
+            let info = entry.value().read();
+            if !matches!(
+                info.status,
+                OrderStatus::Submitted | OrderStatus::PartiallyFilled
+            ) {
+                continue;
+            }
+            report.router_orders += 1;
+            match info.matching_engine_order_id {
+                Some(id) => {
+                    router_live
+                        .entry(info.order.instrument_id.clone())
+                        .or_default()
+                        .insert(
+                            id,
+                            (
+                                entry.key().clone(),
+                                info.order.volume_orign - info.filled_volume,
+                            ),
+                        );
+                }
+                None => report.missing_in_engine.push(entry.key().clone()),
+            }
+        }
+
+        let mut instruments: HashSet<String> = router_live.keys().cloned().collect();
+        instruments.extend(match self.sharded_engine {
+            Some(ref sharded) => sharded.get_instruments(),
+            None => self.matching_engine.get_instruments(),
+        });
+
+        for instrument_id in instruments {
+            let mut live = router_live.remove(&instrument_id).unwrap_or_default();
+            let engine_orders = self
+                .get_orderbook(&instrument_id)
+                .map(|orderbook| resting_orders(&orderbook.read()))
+                .unwrap_or_default();
+            report.engine_orders += engine_orders.len();
+
+            for resting in engine_orders {
+                match live.remove(&resting.engine_order_id) {
+                    Some((order_id, remaining)) => {
+                        if (remaining - resting.volume).abs() > 1e-9 {
+                            report
+                                .volume_mismatch
+                                .push((order_id, remaining, resting.volume));
+                        }
+                    }
+                    None => report
+                        .unknown_in_router
+                        .push((instrument_id.clone(), resting.engine_order_id)),
+                }
+            }
+            report
+                .missing_in_engine
+                .extend(live.into_values().map(|(order_id, _)| order_id));
+        }
+        report.missing_in_engine.sort();
+        report.unknown_in_router.sort();
+        report.volume_mismatch.sort_by(|a, b| a.0.cmp(&b.0));
+
+        if report.is_consistent() {
+            log::info!(
+                "[OrderWatchdog] Reconciliation passed: router={}, engine={}",
+                report.router_orders,
+                report.engine_orders
+            );
+        } else {
+            log::warn!(
+                "[OrderWatchdog] Reconciliation found {} differences: missing_in_engine={:?}, unknown_in_router={:?}, volume_mismatch={:?}",
+                report.diff_count(),
+                report.missing_in_engine,
+                report.unknown_in_router,
+                report.volume_mismatch
+            );
+        }
+        if let Some(ref watchdog) = self.order_watchdog {
+            watchdog.record_reconcile(report.clone());
+        }
+        report
+    }
+
+    /// 启动在途订单看门狗线程（未设置看门狗时返回 None，路由器释放后退出）
+    pub fn start_order_watchdog(self: &Arc<Self>) -> Option<std::thread::JoinHandle<()>> {
+        let interval =
+            Duration::from_millis(self.order_watchdog.as_ref()?.config().check_interval_ms);
+        let router = Arc::downgrade(self);
+        Some(std::thread::spawn(move || {
+            log::info!(
+                "Order watchdog started (interval: {}ms)",
+                interval.as_millis()
+            );
+            loop {
+                std::thread::sleep(interval);
+                match router.upgrade() {
+                    Some(router) => {
+                        router.check_order_watchdog();
+                    }
+                    None => break,
+                }
+            }
+        }))
+    }
+
+    /// 查询订单
+    pub fn query_order(&self, order_id: &str) -> Option<Order> {
+        self.orders
+            .get(order_id)
+            .map(|info| info.read().order.clone())
+    }
+
+    /// 查询用户所有订单
+    pub fn query_user_orders(&self, user_id: &str) -> Vec<Order> {
+        if let Some(order_ids) = self.user_orders.get(user_id) {
+            order_ids
+                .read()
+                .iter()
+                .filter_map(|order_id| self.query_order(order_id))
+                .collect()
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// 获取订单状态
+    pub fn get_order_status(&self, order_id: &str) -> Option<OrderStatus> {
+        self.orders.get(order_id).map(|info| info.read().status)
+    }
+
+    /// 更新订单状态 (由 TradeGateway 调用)
+    pub fn update_order_status(
+        &self,
+        order_id: &str,
+        status: OrderStatus,
+    ) -> Result<(), ExchangeError> {
+        let order_info = self
+            .orders
+            .get(order_id)
+            .ok_or_else(|| ExchangeError::OrderError(format!("Order not found: {}", order_id)))?;
+
+        let mut info = order_info.write();
+        info.status = status;
+        info.update_time = self.clock.now_nanos();
+        self.record_order_state(order_id, &info, "status updated by trade gateway");
+
+        // 如果订单完成，从风控追踪中移除
+        if matches!(
+            status,
+            OrderStatus::Filled | OrderStatus::Cancelled | OrderStatus::Rejected
+        ) {
+            self.risk_checker
+                .remove_active_order(&info.order.user_id, order_id);
+            self.book_order_removed(order_id);
+            if status != OrderStatus::Filled {
+                self.account_mgr.position_lots().cancel_designation(
+                    &info.order.user_id,
+                    &info.order.instrument_id,
+                    &info.qa_order_id,
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 生成订单ID
+    fn generate_order_id(&self) -> String {
+        let seq = self.order_seq.fetch_add(1, Ordering::SeqCst);
+        let timestamp = self.clock.now_millis();
+        format!("O{}{:010}", timestamp, seq)
+    }
+
+    /// 获取市价单的执行价格 @yutiansut @quantaxis
+    ///
+    /// 买单：使用卖一价（ask_price）
+    /// 卖单：使用买一价（bid_price）
+    /// 如果没有对手盘，使用 last_price 或结算价
+    /// 价格笼子参考行情：订单簿一档与最新成交价
+    fn reference_quote(&self, instrument_id: &str) -> Option<ReferenceQuote> {
+        let orderbook = self.get_orderbook(instrument_id)?;
+        let ob = orderbook.read();
+        Some(ReferenceQuote {
+            last_price: ob.lastprice,
+            best_bid: ob
+                .bid_queue
+                .get_sorted_orders()
+                .and_then(|orders| orders.first().map(|o| o.price)),
+            best_ask: ob
+                .ask_queue
+                .get_sorted_orders()
+                .and_then(|orders| orders.first().map(|o| o.price)),
+        })
+    }
+
+    fn get_market_price_for_order(&self, instrument_id: &str, direction: &str) -> f64 {
+        // 1. 尝试从订单簿获取对手盘价格
+        if let Some(orderbook) = self.get_orderbook(instrument_id) {
+            let ob = orderbook.read();
+
+            let price = match direction {
                 "BUY" => {
                     // 买单用卖一价（ask）
                     ob.ask_queue
@@ -1781,6 +3779,161 @@ impl OrderRouter {
         0.0
     }
 
+    /// 合约参考价：最新成交价，无成交时取买一卖一中间价
+    pub fn reference_price(&self, instrument_id: &str) -> Option<f64> {
+        let quote = self.reference_quote(instrument_id)?;
+        if quote.last_price > 0.0 {
+            return Some(quote.last_price);
+        }
+        match (quote.best_bid, quote.best_ask) {
+            (Some(bid), Some(ask)) => Some((bid + ask) / 2.0),
+            _ => None,
+        }
+    }
+
+    /// 执行大宗交易：两腿冻结资金后直接生成成交，不进入订单簿
+    ///
+    /// 任一腿资金/持仓不足时撤销已冻结的另一腿，双方账户不变。
+    /// 返回 (买方, 卖方) 的交易所成交编号
+    pub fn execute_block_trade(
+        &self,
+        trade: &BlockTrade,
+    ) -> Result<(String, String), ExchangeError> {
+        let _in_flight = self
+            .enter_order_entry()
+            .map_err(ExchangeError::OrderError)?;
+        let _buy_lease = self.acquire_account_lease(&trade.buy_account_id)?;
+        let _sell_lease = self.acquire_account_lease(&trade.sell_account_id)?;
+        if !self.instrument_registry.is_trading(&trade.instrument_id) {
+            return Err(ExchangeError::InstrumentError(format!(
+                "Instrument {} is not trading",
+                trade.instrument_id
+            )));
+        }
+        if let Some(ref manager) = self.price_limit_manager {
+            manager
+                .check_price(&trade.instrument_id, trade.price)
+                .map_err(ExchangeError::RiskCheckFailed)?;
+        }
+        for (account_id, offset) in [
+            (&trade.buy_account_id, &trade.buy_offset),
+            (&trade.sell_account_id, &trade.sell_offset),
+        ] {
+            if let Err(info) = self
+                .account_mgr
+                .check_trading_restriction(account_id, offset)
+            {
+                return Err(ExchangeError::RiskCheckFailed(info.describe()));
+            }
+        }
+
+        let buy_qa_order_id =
+            self.freeze_block_leg(trade, &trade.buy_account_id, "BUY", &trade.buy_offset)?;
+        let sell_qa_order_id = match self.freeze_block_leg(
+            trade,
+            &trade.sell_account_id,
+            "SELL",
+            &trade.sell_offset,
+        ) {
+            Ok(id) => id,
+            Err(e) => {
+                // 回滚买方冻结
+                if let Ok(account) = self.account_mgr.get_account(&trade.buy_account_id) {
+                    if let Err(cancel_err) = account.write().cancel_order(&buy_qa_order_id) {
+                        log::error!(
+                            "Block trade {} rollback failed for {}: {:?}",
+                            trade.block_trade_id,
+                            trade.buy_account_id,
+                            cancel_err
+                        );
+                    }
+                }
+                return Err(e);
+            }
+        };
+
+        let timestamp = self.trade_gateway.id_generator().now_nanos();
+        let trade_ids = self.trade_gateway.handle_block_trade(
+            trade,
+            &buy_qa_order_id,
+            &sell_qa_order_id,
+            timestamp,
+        )?;
+        self.update_trade_stats(trade.price, trade.volume);
+
+        // 大宗成交只进成交记录，行情消费者按 trade_type 跳过
+        self.trade_bus.publish(TradeEvent {
+            exchange_id: trade.exchange_id.clone(),
+            instrument_id: trade.instrument_id.clone(),
+            price: trade.price,
+            volume: trade.volume,
+            direction: String::new(),
+            buy_user_id: trade.buy_account_id.clone(),
+            sell_user_id: trade.sell_account_id.clone(),
+            buy_order_id: trade.block_trade_id.clone(),
+            sell_order_id: trade.block_trade_id.clone(),
+            taker_order_id: String::new(),
+            trading_day: self.clock.trading_day().format("%Y-%m-%d").to_string(),
+            trade_type: TradeType::Block,
+            match_batch_id: 0,
+            timestamp,
+        });
+        Ok(trade_ids)
+    }
+
+    /// 大宗交易单腿冻结资金（资金预估与普通下单一致），返回 qars 订单ID
+    fn freeze_block_leg(
+        &self,
+        trade: &BlockTrade,
+        account_id: &str,
+        direction: &str,
+        offset: &str,
+    ) -> Result<String, ExchangeError> {
+        let account = self.account_mgr.get_account(account_id)?;
+        let multiple = self
+            .instrument_registry
+            .volume_multiple(&trade.instrument_id);
+        let trade_amount = trade.price * trade.volume * multiple;
+        let estimated_commission = trade_amount * 0.0003;
+        let required_funds = match (direction, offset) {
+            ("BUY", "OPEN") => trade_amount + estimated_commission,
+            ("SELL", "OPEN") => trade_amount * 0.2 + estimated_commission,
+            _ => estimated_commission,
+        };
+
+        let mut acc = account.write();
+        if acc.money < required_funds {
+            return Err(ExchangeError::RiskCheckFailed(format!(
+                "Insufficient funds for {}: available={:.2}, required={:.2}",
+                account_id, acc.money, required_funds
+            )));
+        }
+
+        let current_time = self
+            .clock
+            .now_local()
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string();
+        self.instrument_registry
+            .apply_to_account(&mut acc, &trade.instrument_id);
+        acc.send_order(
+            &trade.instrument_id,
+            trade.volume,
+            &current_time,
+            self.calculate_towards(direction, offset),
+            trade.price,
+            "",
+            "LIMIT",
+        )
+        .map(|qa_order| qa_order.order_id.clone())
+        .map_err(|e| {
+            ExchangeError::RiskCheckFailed(format!(
+                "Insufficient funds/position for {} {} {}: {:?}",
+                account_id, direction, offset, e
+            ))
+        })
+    }
+
     /// 计算 towards (买卖方向 - 遵循 qars 定义)
     fn calculate_towards(&self, direction: &str, offset: &str) -> i32 {
         match (direction, offset) {
@@ -1808,6 +3961,11 @@ impl OrderRouter {
             .count()
     }
 
+    /// 获取合约注册表
+    pub fn instrument_registry(&self) -> &Arc<InstrumentRegistry> {
+        &self.instrument_registry
+    }
+
     /// 获取风控检查器引用
     pub fn get_risk_checker(&self) -> Arc<PreTradeCheck> {
         self.risk_checker.clone()
@@ -1832,7 +3990,7 @@ impl OrderRouter {
 
             // 获取订单簿中的买卖价
             let (bid_price, ask_price) =
-                if let Some(orderbook) = self.matching_engine.get_orderbook(instrument_id) {
+                if let Some(orderbook) = self.get_orderbook(instrument_id) {
                     let ob = orderbook.read();
                     let bid = ob
                         .bid_queue
@@ -1856,7 +4014,7 @@ impl OrderRouter {
                 bid_price,
                 ask_price,
                 volume: volume as i64,
-                timestamp: chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0),
+                timestamp: self.clock.now_nanos(),
             };
 
             // ========== 性能优化：批量写入缓冲 ==========
@@ -1883,7 +4041,7 @@ impl OrderRouter {
 
             // 获取订单簿中的买卖价
             let (bid_price, ask_price, last_price) =
-                if let Some(orderbook) = self.matching_engine.get_orderbook(instrument_id) {
+                if let Some(orderbook) = self.get_orderbook(instrument_id) {
                     let ob = orderbook.read();
                     let bid = ob
                         .bid_queue
@@ -1921,7 +4079,7 @@ impl OrderRouter {
                 bid_price,
                 ask_price,
                 volume: 0, // 0表示订单簿变化，非成交tick
-                timestamp: chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0),
+                timestamp: self.clock.now_nanos(),
             };
 
             // ========== 性能优化：批量写入缓冲 ==========
@@ -1958,7 +4116,7 @@ impl OrderRouter {
             use crate::storage::wal::record::WalRecord;
 
             // 获取订单簿快照
-            if let Some(orderbook) = self.matching_engine.get_orderbook(instrument_id) {
+            if let Some(orderbook) = self.get_orderbook(instrument_id) {
                 let ob = orderbook.read();
 
                 // 获取买卖队列的前10档数据
@@ -2016,7 +4174,7 @@ impl OrderRouter {
                     bids: bids_array,
                     asks: asks_array,
                     last_price,
-                    timestamp: chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0),
+                    timestamp: self.clock.now_nanos(),
                 };
 
                 // 写入WAL
@@ -2178,6 +4336,16 @@ impl OrderRouter {
         self.priority_queue.as_ref().map(|q| q.get_statistics())
     }
 
+    /// 下游排队深度：优先级队列 + 撮合分片队列待处理任务（供网关过载保护）
+    pub fn downstream_queue_depth(&self) -> usize {
+        let priority = self.priority_queue.as_ref().map_or(0, |q| q.total_len());
+        let sharded = self
+            .sharded_engine
+            .as_ref()
+            .map_or(0, |engine| engine.pending_tasks());
+        priority + sharded
+    }
+
     /// 获取订单详细信息（包含时间戳和成交量）
     pub fn get_order_detail(&self, order_id: &str) -> Option<(Order, OrderStatus, i64, i64, f64)> {
         self.orders.get(order_id).map(|info| {
@@ -2240,51 +4408,235 @@ impl OrderRouter {
             .collect()
     }
 
-    /// 获取订单总数
-    pub fn get_order_count(&self) -> usize {
-        self.orders.len()
-    }
-
-    /// 从账户的 dailyorders 恢复订单索引
-    /// 在服务器重启后调用，从账户快照中恢复待处理订单到 order_router
-    /// ✨ 修复：同时将订单重新提交到撮合引擎订单簿，以支持撤单操作 @yutiansut @quantaxis
-    pub fn restore_orders_from_accounts(&self) {
-        let accounts = self.account_mgr.get_all_accounts();
-        let mut restored_count = 0;
-        let mut orderbook_restored_count = 0;
-
-        for account_arc in accounts {
-            let account = account_arc.read();
-            let account_id = account.account_cookie.clone();
+    /// 账户冻结资金明细：按挂单拆分冻结保证金，并与账户总冻结对账
+    /// @yutiansut @quantaxis
+    ///
+    /// 冻结记录与总冻结在同一把账户锁内读取，明细与汇总反映同一时刻的挂单
+    pub fn frozen_breakdown(&self, account_id: &str) -> Result<FrozenBreakdown, ExchangeError> {
+        let account = self.account_mgr.get_account(account_id)?;
+        let (records, account_frozen) = {
+            let mut acc = account.write();
+            let records: Vec<FrozenRecord> = acc
+                .frozen
+                .iter()
+                .map(|(qa_order_id, frozen)| FrozenRecord {
+                    qa_order_id: qa_order_id.clone(),
+                    volume: frozen.amount,
+                    money: frozen.money,
+                })
+                .collect();
+            (records, acc.get_frozen_margin())
+        };
 
-            for (order_id, order) in &account.dailyorders {
-                // 只恢复待处理订单 (SUBMITTED/ALIVE)
-                if order.status != "SUBMITTED" && order.status != "ALIVE" {
+        // qars 订单ID → 未完结的挂单
+        let mut live_orders: HashMap<String, FrozenOrderRef> = HashMap::new();
+        if let Some(order_ids) = self.user_orders.get(account_id) {
+            for order_id in order_ids.read().iter() {
+                let Some(info) = self.orders.get(order_id) else {
                     continue;
-                }
-
-                // 检查是否已经存在
-                if self.orders.contains_key(order_id) {
+                };
+                let info = info.read();
+                if matches!(
+                    info.status,
+                    OrderStatus::Filled | OrderStatus::Cancelled | OrderStatus::Rejected
+                ) {
                     continue;
                 }
+                live_orders.insert(
+                    info.qa_order_id.clone(),
+                    FrozenOrderRef {
+                        order_id: order_id.clone(),
+                        instrument_id: info.order.instrument_id.clone(),
+                        direction: info.order.direction.clone(),
+                        offset: info.order.offset.clone(),
+                        price: info.order.limit_price,
+                    },
+                );
+            }
+        }
 
-                // 解析订单状态
-                let status = match order.status.as_str() {
-                    "SUBMITTED" => OrderStatus::Submitted,
-                    "ALIVE" => OrderStatus::PartiallyFilled,
-                    _ => continue,
-                };
-
-                // 计算已成交量和剩余量
-                let filled_volume = order.volume_orign - order.volume_left;
-                let remaining_volume = order.volume_left;
-
-                // ✨ 关键修复：将订单重新提交到撮合引擎订单簿 @yutiansut @quantaxis
-                let mut matching_engine_order_id: Option<u64> = None;
+        Ok(FrozenBreakdown::build(
+            account_id,
+            account_frozen,
+            records,
+            |qa_order_id| live_orders.get(qa_order_id).cloned(),
+            self.clock.now_millis(),
+        ))
+    }
+
+    /// 订单在所在价位的排队信息（排第几位、前面挂单总量）
+    /// @yutiansut @quantaxis
+    ///
+    /// 同价位按时间优先成交；涨跌停时停板价挂单集中排队，`at_limit` 标记涨停价买单 / 跌停价卖单。
+    /// 排队快照按深度快照序号缓存，订单簿未变化时重复查询不再遍历价位
+    pub fn queue_position(&self, order_id: &str) -> Result<QueuePosition, ExchangeError> {
+        let (instrument_id, direction, price, engine_order_id) = {
+            let info = self.orders.get(order_id).ok_or_else(|| {
+                ExchangeError::OrderError(format!("Order not found: {}", order_id))
+            })?;
+            let info = info.read();
+            if !matches!(
+                info.status,
+                OrderStatus::Submitted | OrderStatus::PartiallyFilled
+            ) {
+                return Err(ExchangeError::OrderError(format!(
+                    "Order {} is not resting in the orderbook: {:?}",
+                    order_id, info.status
+                )));
+            }
+            let engine_order_id = info.matching_engine_order_id.ok_or_else(|| {
+                ExchangeError::OrderError(format!("Order {} has no matching engine id", order_id))
+            })?;
+            let direction = match info.order.direction.as_str() {
+                "BUY" => OrderDirection::BUY,
+                _ => OrderDirection::SELL,
+            };
+            (
+                info.order.instrument_id.clone(),
+                direction,
+                info.order.limit_price,
+                engine_order_id,
+            )
+        };
+
+        let orderbook = self.get_orderbook(&instrument_id).ok_or_else(|| {
+            ExchangeError::MatchingError(format!(
+                "Orderbook not found for instrument: {}",
+                instrument_id
+            ))
+        })?;
+        let level = {
+            // 快照在订单簿写锁内发布，持有读锁时序号与订单簿内容一致
+            let ob = orderbook.read();
+            let sequence = match self.sharded_engine {
+                Some(ref sharded) => sharded.get_depth_snapshot(&instrument_id),
+                None => self.matching_engine.get_depth_snapshot(&instrument_id),
+            }
+            .map(|snapshot| snapshot.sequence)
+            .unwrap_or(0);
+            self.limit_queue
+                .level(&instrument_id, &ob, sequence, direction, price)
+        };
+
+        let mut position = level.position_of(engine_order_id).ok_or_else(|| {
+            ExchangeError::OrderError(format!("Order {} not found in orderbook queue", order_id))
+        })?;
+        if let Some((lower, upper)) = self
+            .price_limit_manager
+            .as_ref()
+            .and_then(|manager| manager.limit_prices(&instrument_id))
+        {
+            position.at_limit = match direction {
+                OrderDirection::BUY => price >= upper - 1e-8,
+                OrderDirection::SELL => price <= lower + 1e-8,
+            };
+        }
+        Ok(position)
+    }
+
+    /// 获取订单总数
+    pub fn get_order_count(&self) -> usize {
+        self.orders.len()
+    }
+
+    /// 清空全部订单与订单簿挂单（测试环境交易日重置使用），返回清空的订单数
+    ///
+    /// 订单簿按昨收价重建，订单索引、挂单统计与成交统计一并清零；
+    /// 调用方需先停止接单，账户内的订单由账户状态自行恢复
+    pub fn reset_order_state(&self) -> usize {
+        for instrument_id in self.matching_engine.get_instruments() {
+            let prev_close = self
+                .matching_engine
+                .get_prev_close(&instrument_id)
+                .unwrap_or(0.0);
+            let _ = self
+                .matching_engine
+                .register_instrument(instrument_id, prev_close);
+        }
+        if let Some(ref sharded) = self.sharded_engine {
+            for instrument_id in sharded.get_instruments() {
+                if let Some(engine) = sharded.engine_for(&instrument_id) {
+                    let prev_close = engine.get_prev_close(&instrument_id).unwrap_or(0.0);
+                    let _ = engine.register_instrument(instrument_id, prev_close);
+                }
+            }
+        }
+
+        let cleared = self.orders.len();
+        for entry in self.orders.iter() {
+            let info = entry.value().read();
+            self.risk_checker
+                .remove_active_order(&info.order.user_id, entry.key());
+            self.book_order_removed(entry.key());
+            self.account_mgr.position_lots().cancel_designation(
+                &info.order.user_id,
+                &info.order.instrument_id,
+                &info.qa_order_id,
+            );
+        }
+        self.orders.clear();
+        self.user_orders.clear();
+        self.engine_id_to_order.clear();
+        self.engine_id_to_user.clear();
+        self.last_snapshot_time.clear();
+        self.trade_count.store(0, Ordering::SeqCst);
+        *self.trade_volume.write() = 0.0;
+        *self.trade_amount.write() = 0.0;
+
+        log::info!("Order state reset: {} orders cleared", cleared);
+        cleared
+    }
+
+    /// 从账户的 dailyorders 恢复订单索引
+    /// 在服务器重启后调用，从账户快照中恢复待处理订单到 order_router
+    /// ✨ 修复：同时将订单重新提交到撮合引擎订单簿，以支持撤单操作 @yutiansut @quantaxis
+    pub fn restore_orders_from_accounts(&self) {
+        let accounts = self.account_mgr.get_all_accounts();
+        let mut restored_count = 0;
+        let mut orderbook_restored_count = 0;
+
+        for account_arc in accounts {
+            // 快照恢复的持仓按注册表的每手数量换算
+            {
+                let mut account = account_arc.write();
+                let held: Vec<String> = account.hold.keys().cloned().collect();
+                for instrument_id in held {
+                    self.instrument_registry
+                        .apply_to_account(&mut account, &instrument_id);
+                }
+            }
+
+            let account = account_arc.read();
+            let account_id = account.account_cookie.clone();
+
+            for (order_id, order) in &account.dailyorders {
+                // 只恢复待处理订单 (SUBMITTED/ALIVE)
+                if order.status != "SUBMITTED" && order.status != "ALIVE" {
+                    continue;
+                }
+
+                // 检查是否已经存在
+                if self.orders.contains_key(order_id) {
+                    continue;
+                }
+
+                // 解析订单状态
+                let status = match order.status.as_str() {
+                    "SUBMITTED" => OrderStatus::Submitted,
+                    "ALIVE" => OrderStatus::PartiallyFilled,
+                    _ => continue,
+                };
+
+                // 计算已成交量和剩余量
+                let filled_volume = order.volume_orign - order.volume_left;
+                let remaining_volume = order.volume_left;
+
+                // ✨ 关键修复：将订单重新提交到撮合引擎订单簿 @yutiansut @quantaxis
+                let mut matching_engine_order_id: Option<u64> = None;
 
                 // 只有剩余数量 > 0 的订单才需要恢复到订单簿
                 if remaining_volume > 0.0 {
-                    if let Some(orderbook) = self.matching_engine.get_orderbook(&order.instrument_id) {
+                    if self.get_orderbook(&order.instrument_id).is_some() {
                         // 转换订单方向
                         let direction = match order.direction.as_str() {
                             "BUY" => OrderDirection::BUY,
@@ -2297,7 +4649,7 @@ impl OrderRouter {
 
                         // 创建撮合订单请求（使用剩余量，不是原始量）
                         let asset = InstrumentAsset::from_code(&order.instrument_id);
-                        let timestamp = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
+                        let timestamp = self.clock.now_nanos();
 
                         let match_request = crate::matching::orders::new_limit_order_request(
                             asset,
@@ -2307,10 +4659,23 @@ impl OrderRouter {
                             timestamp,
                         );
 
-                        // 提交到订单簿
-                        let mut ob = orderbook.write();
-                        let results: Vec<_> = ob.process_order(match_request).into_iter().collect();
-                        drop(ob);
+                        // 提交到订单簿（同步发布深度快照）
+                        let results = match self.process_on_orderbook(
+                            &order.instrument_id,
+                            order_id,
+                            match_request,
+                            None,
+                        ) {
+                            Ok(results) => results,
+                            Err(e) => {
+                                log::warn!(
+                                    "⚠️ Failed to restore order {} to orderbook: {}",
+                                    order_id,
+                                    e
+                                );
+                                Vec::new()
+                            }
+                        };
 
                         // 从结果中获取 matching_engine_order_id
                         for result in results {
@@ -2341,13 +4706,15 @@ impl OrderRouter {
                 let info = OrderRouteInfo {
                     order: order.clone(),
                     status,
-                    submit_time: order.insert_date_time / 1_000_000_000, // 纳秒转秒
-                    update_time: chrono::Utc::now().timestamp(),
+                    submit_time: nanos_to_secs(order.insert_date_time),
+                    update_time: self.clock.now_utc().timestamp(),
                     filled_volume,
                     qa_order_id: order_id.clone(),
                     matching_engine_order_id, // ✨ 现在有值了！
                     time_condition: TimeCondition::GFD,
                     volume_condition: VolumeCondition::ANY,
+                    hedge_flag: HedgeFlag::default(), // 恢复的订单无套保信息，按投机处理
+                    high_perf_path: false,            // 恢复的订单已在订单簿中，不再撮合
                 };
 
                 // 添加到订单映射
@@ -2386,13 +4753,133 @@ impl OrderRouter {
     }
 }
 
+impl MatchingRecoveryHandler for OrderRouter {
+    /// 在途订单（已报/部分成交）即挂单记录，剩余量 = 委托量 - 已成交量
+    fn resting_orders(&self, instrument_id: &str) -> Result<Vec<RestingOrder>, ExchangeError> {
+        let mut records = Vec::new();
+        for entry in self.orders.iter() {
+            let info = entry.value().read();
+            if info.order.instrument_id != instrument_id
+                || !matches!(
+                    info.status,
+                    OrderStatus::Submitted | OrderStatus::PartiallyFilled
+                )
+            {
+                continue;
+            }
+            let Some(engine_order_id) = info.matching_engine_order_id else {
+                continue;
+            };
+            let volume = info.order.volume_orign - info.filled_volume;
+            if volume <= 0.0 {
+                continue;
+            }
+            records.push(RestingOrder {
+                engine_order_id,
+                direction: if info.order.direction == "BUY" {
+                    "BUY"
+                } else {
+                    "SELL"
+                },
+                price: info.order.limit_price,
+                volume,
+            });
+        }
+        records.sort_by_key(|o| o.engine_order_id);
+        Ok(records)
+    }
+
+    fn on_orderbook_rebuilt(&self, instrument_id: &str, remapped: &[(u64, u64)]) {
+        let mut moved = Vec::with_capacity(remapped.len());
+        for (old_id, _) in remapped {
+            let order_id = self.engine_id_to_order.remove(old_id).map(|(_, v)| v);
+            let user_id = self.engine_id_to_user.remove(old_id).map(|(_, v)| v);
+            moved.push((order_id, user_id));
+        }
+        for ((_, new_id), (order_id, user_id)) in remapped.iter().zip(moved) {
+            if let Some(order_id) = order_id {
+                if let Some(info) = self.orders.get(&order_id) {
+                    info.write().matching_engine_order_id = Some(*new_id);
+                }
+                self.engine_id_to_order.insert(*new_id, order_id);
+            }
+            if let Some(user_id) = user_id {
+                self.engine_id_to_user.insert(*new_id, user_id);
+            }
+        }
+        log::info!(
+            "Remapped {} resting orders of {} after orderbook rebuild",
+            remapped.len(),
+            instrument_id
+        );
+    }
+
+    fn on_fault_event(&self, event: &MatchingFaultEvent) {
+        let action = match event.state {
+            MatchingFaultState::Recovering => "matching_fault_recovering",
+            MatchingFaultState::Recovered => "matching_fault_recovered",
+            MatchingFaultState::Halted => "matching_fault_halted",
+        };
+        if event.state == MatchingFaultState::Halted {
+            log::error!(
+                "🚨 Instrument {} halted: {} (shard {})",
+                event.instrument_id,
+                event.reason,
+                event.shard_id
+            );
+            if let Err(e) = self.instrument_registry.suspend(&event.instrument_id) {
+                log::warn!("Suspend instrument {} failed: {}", event.instrument_id, e);
+            }
+        }
+
+        log_audit(
+            event.instrument_id.clone(),
+            "MatchingEngine".to_string(),
+            AuditLogType::MatchingFault,
+            action.to_string(),
+            serde_json::to_string(event).unwrap_or_default(),
+            None,
+            AuditResult::Success,
+        );
+
+        if let Some(ref broadcaster) = self.market_broadcaster {
+            broadcaster.broadcast(MarketDataEvent::TradingStateChanged {
+                instrument_id: event.instrument_id.clone(),
+                state: format!("{:?}", event.state),
+                reason: action.to_string(),
+                resume_at: None,
+                timestamp: event.timestamp,
+            });
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::core::account_ext::{AccountType, OpenAccountRequest};
     use crate::exchange::instrument_registry::InstrumentInfo;
+    use crate::exchange::TradingRestriction;
 
     fn create_test_router() -> OrderRouter {
+        create_test_router_with_recorder(None)
+    }
+
+    fn create_test_router_with_recorder(
+        trade_recorder: Option<Arc<crate::matching::trade_recorder::TradeRecorder>>,
+    ) -> OrderRouter {
+        let router = create_test_router_with_gateway(TradeGateway::new);
+        if let Some(recorder) = trade_recorder {
+            router
+                .trade_bus()
+                .subscribe_inline("trade_recorder", recorder);
+        }
+        router
+    }
+
+    fn create_test_router_with_gateway(
+        build_gateway: impl FnOnce(Arc<AccountManager>) -> TradeGateway,
+    ) -> OrderRouter {
         // 创建账户管理器
         let account_mgr = Arc::new(AccountManager::new());
         let req = OpenAccountRequest {
@@ -2421,6 +4908,8 @@ mod tests {
                     crate::exchange::instrument_registry::InstrumentType::CommodityFuture,
                 exchange: "SHFE".to_string(),
                 contract_multiplier: 1,
+                trading_unit: "手".to_string(),
+                quote_unit: String::new(),
                 price_tick: 0.01,
                 margin_rate: 0.1,
                 commission_rate: 0.0005,
@@ -2435,13 +4924,13 @@ mod tests {
             .unwrap();
 
         // 创建成交回报网关
-        let trade_gateway = Arc::new(TradeGateway::new(account_mgr.clone()));
+        let trade_gateway = build_gateway(account_mgr.clone());
 
         OrderRouter::new(
             account_mgr,
             matching_engine,
             instrument_registry,
-            trade_gateway,
+            Arc::new(trade_gateway),
         )
     }
 
@@ -2546,8 +5035,7 @@ mod tests {
             volume: 10.0,
             price: 120.0,
             order_type: "LIMIT".to_string(),
-            time_condition: None,
-            volume_condition: None,
+            ..Default::default()
         };
 
         let response = router.submit_order(req);
@@ -2568,8 +5056,7 @@ mod tests {
             volume: 100000.0, // 超大数量
             price: 1000.0,
             order_type: "LIMIT".to_string(),
-            time_condition: None,
-            volume_condition: None,
+            ..Default::default()
         };
 
         let response = router.submit_order(req);
@@ -2589,8 +5076,7 @@ mod tests {
             volume: 10.0,
             price: 120.0,
             order_type: "LIMIT".to_string(),
-            time_condition: None,
-            volume_condition: None,
+            ..Default::default()
         };
 
         let response = router.submit_order(req);
@@ -2619,8 +5105,7 @@ mod tests {
                 volume: 10.0 + i as f64,
                 price: 120.0,
                 order_type: "LIMIT".to_string(),
-                time_condition: None,
-                volume_condition: None,
+                ..Default::default()
             };
             router.submit_order(req);
         }
@@ -2674,8 +5159,7 @@ mod tests {
             volume: 10.0,
             price: 120.0,
             order_type: "LIMIT".to_string(),
-            time_condition: None,
-            volume_condition: None,
+            ..Default::default()
         };
 
         let buy_response = router.submit_order(buy_req);
@@ -2696,8 +5180,7 @@ mod tests {
             volume: 5.0,                // 部分成交
             price: 120.0,
             order_type: "LIMIT".to_string(),
-            time_condition: None,
-            volume_condition: None,
+            ..Default::default()
         };
 
         let sell_response = router.submit_order(sell_req);
@@ -2782,8 +5265,7 @@ mod tests {
             volume: 10.0,
             price: 120.0,
             order_type: "LIMIT".to_string(),
-            time_condition: None,
-            volume_condition: None,
+            ..Default::default()
         };
 
         let response = router.submit_order(submit_req);
@@ -2835,8 +5317,7 @@ mod tests {
             volume: 10.0,
             price: 120.0,
             order_type: "LIMIT".to_string(),
-            time_condition: None,
-            volume_condition: None,
+            ..Default::default()
         };
 
         router.submit_order(req);
@@ -2862,8 +5343,7 @@ mod tests {
                 volume: 1.0,
                 price: 120.0,
                 order_type: "LIMIT".to_string(),
-                time_condition: None,
-                volume_condition: None,
+                ..Default::default()
             };
             router.submit_order(req);
         }
@@ -2909,8 +5389,7 @@ mod tests {
             volume: 10.0,
             price: 120.0,
             order_type: "LIMIT".to_string(),
-            time_condition: None,
-            volume_condition: None,
+            ..Default::default()
         };
 
         let response = router.submit_order(req);
@@ -2954,8 +5433,7 @@ mod tests {
                 volume: 1.0 + i as f64,
                 price: 120.0,
                 order_type: "LIMIT".to_string(),
-                time_condition: None,
-                volume_condition: None,
+                ..Default::default()
             };
             router.submit_order(req);
         }
@@ -3008,8 +5486,7 @@ mod tests {
                 volume: 1.0,
                 price: 120.0,
                 order_type: "LIMIT".to_string(),
-                time_condition: None,
-                volume_condition: None,
+                ..Default::default()
             };
             router.submit_order(req);
         }
@@ -3024,8 +5501,7 @@ mod tests {
                 volume: 1.0,
                 price: 120.0,
                 order_type: "LIMIT".to_string(),
-                time_condition: None,
-                volume_condition: None,
+                ..Default::default()
             };
             router.submit_order(req);
         }
@@ -3058,8 +5534,7 @@ mod tests {
                 volume: 1.0,
                 price: 120.0,
                 order_type: "LIMIT".to_string(),
-                time_condition: None,
-                volume_condition: None,
+                ..Default::default()
             };
             router.submit_order(req);
             assert_eq!(router.get_order_count(), i + 1);
@@ -3127,6 +5602,7 @@ mod tests {
             order_type: "LIMIT".to_string(),
             time_condition: Some(TimeCondition::GFD),
             volume_condition: Some(VolumeCondition::ANY),
+            ..Default::default()
         };
 
         assert_eq!(req.account_id, "user1");
@@ -3151,8 +5627,7 @@ mod tests {
             volume: 10.0,
             price: 85000.0,
             order_type: "LIMIT".to_string(),
-            time_condition: None,
-            volume_condition: None,
+            ..Default::default()
         };
 
         let cloned = req.clone();
@@ -3198,6 +5673,7 @@ mod tests {
             status: Some("submitted".to_string()),
             error_message: None,
             error_code: None,
+            adjusted_price: None,
         };
 
         assert!(resp.success);
@@ -3216,6 +5692,7 @@ mod tests {
             status: Some("rejected".to_string()),
             error_message: Some("Insufficient funds".to_string()),
             error_code: Some(4001),
+            adjusted_price: None,
         };
 
         assert!(!resp.success);
@@ -3347,6 +5824,7 @@ mod tests {
             order_type: "LIMIT".to_string(),
             time_condition: Some(TimeCondition::GFD),
             volume_condition: Some(VolumeCondition::ANY),
+            ..Default::default()
         };
 
         let response = router.submit_order(req);
@@ -3366,8 +5844,7 @@ mod tests {
             volume: 5.0,
             price: 120.0,
             order_type: "LIMIT".to_string(),
-            time_condition: None,
-            volume_condition: None,
+            ..Default::default()
         };
 
         let response = router.submit_order(req);
@@ -3391,6 +5868,7 @@ mod tests {
             order_type: "LIMIT".to_string(),
             time_condition: Some(TimeCondition::IOC),
             volume_condition: Some(VolumeCondition::ANY),
+            ..Default::default()
         };
 
         let response = router.submit_order(req);
@@ -3413,7 +5891,7 @@ mod tests {
             price: 120.0,
             order_type: "LIMIT".to_string(),
             time_condition: Some(TimeCondition::GTC),
-            volume_condition: None,
+            ..Default::default()
         };
 
         let response = router.submit_order(req);
@@ -3435,6 +5913,7 @@ mod tests {
             order_type: "LIMIT".to_string(),
             time_condition: Some(TimeCondition::IOC),
             volume_condition: Some(VolumeCondition::ALL), // FOK = IOC + ALL
+            ..Default::default()
         };
 
         let response = router.submit_order(req);
@@ -3458,8 +5937,7 @@ mod tests {
             volume: 10.0,
             price: 100.0,
             order_type: "LIMIT".to_string(),
-            time_condition: None,
-            volume_condition: None,
+            ..Default::default()
         };
 
         let response = router.submit_order(req);
@@ -3482,8 +5960,7 @@ mod tests {
             volume: 10.0,
             price: 120.0,
             order_type: "LIMIT".to_string(),
-            time_condition: None,
-            volume_condition: None,
+            ..Default::default()
         };
 
         let response = router.submit_order(req);
@@ -3491,86 +5968,1506 @@ mod tests {
         assert!(response.error_message.is_some());
     }
 
-    // ==================== 边界条件测试 @yutiansut @quantaxis ====================
+    // ==================== 拒单原因统计测试 @yutiansut @quantaxis ====================
 
-    /// 测试零价格订单
+    /// 测试各拒单路径均计入拒单统计，且错误码保持不变
     #[test]
-    fn test_submit_order_zero_price() {
+    fn test_reject_paths_recorded_in_stats() {
         let router = create_test_router();
+        let today = crate::utils::time_service::time_service().today();
+        let order = |account: &str, volume: f64, time_condition, volume_condition| {
+            SubmitOrderRequest {
+                account_id: account.to_string(),
+                instrument_id: "IX2301".to_string(),
+                direction: "BUY".to_string(),
+                offset: "OPEN".to_string(),
+                volume,
+                price: 120.0,
+                order_type: "LIMIT".to_string(),
+                time_condition,
+                volume_condition,
+                ..Default::default()
+            }
+        };
 
+        // 风控检查异常（账户不存在）
+        let resp = router.submit_order(order("NON_EXISTENT_ACCOUNT", 10.0, None, None));
+        assert_eq!(resp.error_code, Some(9999));
+
+        // 强平单跳过风控，由路由层拒绝：账户不存在
+        let resp = router.submit_force_order(order("NON_EXISTENT_ACCOUNT", 10.0, None, None));
+        assert_eq!(resp.error_code, Some(4000));
+
+        // FOK 无对手盘
+        let resp = router.submit_order(order(
+            "test_user",
+            10.0,
+            Some(TimeCondition::IOC),
+            Some(VolumeCondition::ALL),
+        ));
+        assert_eq!(resp.error_code, Some(4010));
+
+        let stats = router.rejection_stats();
+        assert_eq!(stats.count(today, RejectReason::RiskCheckError), 1);
+        assert_eq!(stats.count(today, RejectReason::AccountNotFound), 1);
+        assert_eq!(stats.count(today, RejectReason::FokUnfillable), 1);
+
+        let summary = stats.query(today, crate::risk::RejectionGroupBy::Instrument);
+        assert_eq!(summary.total, 3);
+        assert_eq!(summary.groups[0].key, "IX2301");
+    }
+
+    /// 测试涨跌停价格校验：超出涨跌停板的订单被拒绝并计入统计
+    #[test]
+    fn test_submit_order_price_out_of_limit() {
+        let mut router = create_test_router();
+        let manager = Arc::new(crate::risk::PriceLimitManager::new(
+            router.instrument_registry.clone(),
+        ));
+        manager.set_reference_price("IX2301", 120.0).unwrap();
+        router.set_price_limit_manager(manager);
+
+        // 涨停价 120 * 1.1 = 132
         let req = SubmitOrderRequest {
             account_id: "test_user".to_string(),
             instrument_id: "IX2301".to_string(),
             direction: "BUY".to_string(),
             offset: "OPEN".to_string(),
-            volume: 10.0,
-            price: 0.0, // 零价格
+            volume: 1.0,
+            price: 132.5,
             order_type: "LIMIT".to_string(),
-            time_condition: None,
-            volume_condition: None,
+            ..Default::default()
         };
+        let response = router.submit_order(req.clone());
+        assert!(!response.success);
+        assert_eq!(response.error_code, Some(4003));
 
-        let response = router.submit_order(req);
-        // 零价格可能被拒绝
-        // 这里只验证不会panic
-        assert!(response.order_id.is_some() || response.error_message.is_some());
+        let response = router.submit_order(SubmitOrderRequest { price: 132.0, ..req });
+        assert!(response.success);
+
+        let today = crate::utils::time_service::time_service().today();
+        assert_eq!(
+            router.rejection_stats().count(today, RejectReason::PriceOutOfLimit),
+            1
+        );
     }
 
-    /// 测试零数量订单
+    /// 测试涨停价排队：排队位次与前方挂单量，开板时按时间优先成交
     #[test]
-    fn test_submit_order_zero_volume() {
-        let router = create_test_router();
+    fn test_limit_price_queue_time_priority() {
+        let mut router = create_test_router();
+        let manager = Arc::new(crate::risk::PriceLimitManager::new(
+            router.instrument_registry.clone(),
+        ));
+        manager.set_reference_price("IX2301", 120.0).unwrap();
+        router.set_price_limit_manager(manager);
+        router
+            .account_mgr
+            .open_account(OpenAccountRequest {
+                user_id: "test_user_2".to_string(),
+                account_id: Some("test_user_2".to_string()),
+                account_name: "Test User 2".to_string(),
+                init_cash: 1000000.0,
+                account_type: AccountType::Individual,
+            })
+            .unwrap();
+
+        let order =
+            |account_id: &str, direction: &str, volume: f64, price: f64| SubmitOrderRequest {
+                account_id: account_id.to_string(),
+                instrument_id: "IX2301".to_string(),
+                direction: direction.to_string(),
+                offset: "OPEN".to_string(),
+                volume,
+                price,
+                order_type: "LIMIT".to_string(),
+                ..Default::default()
+            };
+
+        // 涨停价 132 排队三笔买单
+        let ids: Vec<String> = [1.0, 2.0, 3.0]
+            .into_iter()
+            .map(|volume| {
+                router
+                    .submit_order(order("test_user", "BUY", volume, 132.0))
+                    .order_id
+                    .unwrap()
+            })
+            .collect();
+        let below = router
+            .submit_order(order("test_user", "BUY", 1.0, 131.0))
+            .order_id
+            .unwrap();
+
+        let third = router.queue_position(&ids[2]).unwrap();
+        assert_eq!(third.position, 3);
+        assert_eq!(third.orders_ahead, 2);
+        assert_eq!(third.volume_ahead, 3.0);
+        assert_eq!(third.level_orders, 3);
+        assert_eq!(third.level_volume, 6.0);
+        assert!(third.at_limit);
+        assert_eq!(router.queue_position(&ids[0]).unwrap().position, 1);
+        assert!(!router.queue_position(&below).unwrap().at_limit);
+
+        // 开板：卖单在涨停价成交，按时间优先先成交排在前面的买单
+        assert!(
+            router
+                .submit_order(order("test_user_2", "SELL", 4.0, 132.0))
+                .success
+        );
+        assert_eq!(router.get_order_status(&ids[0]), Some(OrderStatus::Filled));
+        assert_eq!(router.get_order_status(&ids[1]), Some(OrderStatus::Filled));
+        assert_eq!(
+            router.get_order_status(&ids[2]),
+            Some(OrderStatus::PartiallyFilled)
+        );
+        let third = router.queue_position(&ids[2]).unwrap();
+        assert_eq!(third.position, 1);
+        assert_eq!(third.volume_ahead, 0.0);
+        assert_eq!(third.volume, 2.0);
+
+        // 已完结订单不在队列中
+        assert!(router.queue_position(&ids[0]).is_err());
+    }
+
+    /// 测试前置拒单（涨跌停、FOK）推送 REJECTED 回报给用户订阅者
+    #[test]
+    fn test_pre_trade_reject_pushed_to_subscriber() {
+        use crate::exchange::trade_gateway::Notification;
+
+        let mut router = create_test_router();
+        let manager = Arc::new(crate::risk::PriceLimitManager::new(
+            router.instrument_registry.clone(),
+        ));
+        manager.set_reference_price("IX2301", 120.0).unwrap();
+        router.set_price_limit_manager(manager);
+
+        let receiver = router.trade_gateway.subscribe_user("test_user".to_string());
 
         let req = SubmitOrderRequest {
             account_id: "test_user".to_string(),
             instrument_id: "IX2301".to_string(),
             direction: "BUY".to_string(),
             offset: "OPEN".to_string(),
-            volume: 0.0, // 零数量
-            price: 120.0,
+            volume: 1.0,
+            price: 132.5,
             order_type: "LIMIT".to_string(),
-            time_condition: None,
-            volume_condition: None,
+            ..Default::default()
         };
-
-        let response = router.submit_order(req);
-        // 零数量可能被拒绝
-        // 这里只验证不会panic
-        assert!(response.order_id.is_some() || response.error_message.is_some());
+        let limit_resp = router.submit_order(req.clone());
+        let fok_resp = router.submit_order(SubmitOrderRequest {
+            price: 120.0,
+            time_condition: Some(TimeCondition::IOC),
+            volume_condition: Some(VolumeCondition::ALL),
+            ..req
+        });
+
+        let rejected: Vec<_> = receiver
+            .try_iter()
+            .filter_map(|n| match n {
+                Notification::OrderStatus(s) if s.status == "REJECTED" => Some(s),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(rejected.len(), 2);
+
+        for (status, resp, category) in [
+            (&rejected[0], &limit_resp, "price_out_of_limit"),
+            (&rejected[1], &fok_resp, "fok_unfillable"),
+        ] {
+            assert_eq!(Some(&status.order_id), resp.order_id.as_ref());
+            assert_eq!(status.user_id, "test_user");
+            assert_eq!(status.reason, resp.error_message);
+            assert_eq!(status.error_code, resp.error_code);
+            assert_eq!(status.reject_category.as_deref(), Some(category));
+        }
     }
 
-    /// 测试负数量订单
+    /// 测试订单簿挂单达上限后新挂单被拒，成交与撤单释放额度后恢复接单
     #[test]
-    fn test_submit_order_negative_volume() {
-        let router = create_test_router();
+    fn test_book_limit_rejects_new_resting_orders() {
+        let mut router = create_test_router();
+        let limiter = Arc::new(OrderBookLimiter::new(
+            crate::matching::OrderBookLimitConfig {
+                instrument_max_orders: vec![("IX2301".to_string(), 2)],
+                ..Default::default()
+            },
+        ));
+        router.set_book_limiter(limiter.clone());
+        router
+            .account_mgr
+            .open_account(OpenAccountRequest {
+                user_id: "test_user_2".to_string(),
+                account_id: Some("test_user_2".to_string()),
+                account_name: "Test User 2".to_string(),
+                init_cash: 1000000.0,
+                account_type: AccountType::Individual,
+            })
+            .unwrap();
 
-        let req = SubmitOrderRequest {
+        let buy = SubmitOrderRequest {
             account_id: "test_user".to_string(),
             instrument_id: "IX2301".to_string(),
             direction: "BUY".to_string(),
             offset: "OPEN".to_string(),
-            volume: -10.0, // 负数量
-            price: 120.0,
+            volume: 10.0,
+            price: 110.0,
             order_type: "LIMIT".to_string(),
-            time_condition: None,
-            volume_condition: None,
+            ..Default::default()
         };
 
-        let response = router.submit_order(req);
-        // 负数量应该被拒绝
-        // 这里只验证不会panic
-        assert!(response.order_id.is_some() || response.error_message.is_some());
-    }
+        let first = router.submit_order(buy.clone());
+        let second = router.submit_order(buy.clone());
+        assert!(first.success && second.success);
+        assert_eq!(limiter.resting_orders("IX2301"), 2);
+        assert_eq!(
+            limiter.memory_bytes("IX2301"),
+            2 * crate::matching::book_limits::DEFAULT_ORDER_SIZE_BYTES
+        );
 
-    // ==================== 并发测试 @yutiansut @quantaxis ====================
+        // 达到上限：新挂单被拒
+        let rejected = router.submit_order(buy.clone());
+        assert!(!rejected.success);
+        assert_eq!(rejected.error_code, Some(4011));
 
-    /// 测试并发提交订单
+        // 可立即全部成交的对手单仍可撮合，成交后释放额度
+        let sell = router.submit_order(SubmitOrderRequest {
+            account_id: "test_user_2".to_string(),
+            direction: "SELL".to_string(),
+            ..buy.clone()
+        });
+        assert!(sell.success, "{:?}", sell.error_message);
+        assert_eq!(limiter.resting_orders("IX2301"), 1);
+
+        // 撤单同样释放额度
+        assert!(router.submit_order(buy.clone()).success);
+        assert!(!router.submit_order(buy.clone()).success);
+        router
+            .cancel_order(CancelOrderRequest {
+                account_id: "test_user".to_string(),
+                order_id: second.order_id.unwrap(),
+            })
+            .unwrap();
+        assert_eq!(limiter.resting_orders("IX2301"), 1);
+        assert!(router.submit_order(buy).success);
+        assert_eq!(limiter.resting_orders("IX2301"), 2);
+
+        let today = crate::utils::time_service::time_service().today();
+        assert_eq!(
+            router.rejection_stats().count(today, RejectReason::OrderBookFull),
+            2
+        );
+    }
+
+    /// 测试账户下单超频被拒，窗口回补后恢复接单
     #[test]
-    fn test_concurrent_submit_orders() {
-        use std::thread;
+    fn test_rate_limited_order_rejected_then_recovers() {
+        let mut router = create_test_router();
+        let limiter = Arc::new(OrderRateLimiter::new(crate::risk::OrderRateLimitConfig {
+            individual: Some(crate::risk::RateLimitRule::new(200, 2, 1)),
+            ..Default::default()
+        }));
+        router.set_rate_limiter(limiter.clone());
 
-        let router = Arc::new(create_test_router());
-        let mut handles = vec![];
+        let req = SubmitOrderRequest {
+            account_id: "test_user".to_string(),
+            instrument_id: "IX2301".to_string(),
+            direction: "BUY".to_string(),
+            offset: "OPEN".to_string(),
+            volume: 1.0,
+            price: 100.0,
+            order_type: "LIMIT".to_string(),
+            ..Default::default()
+        };
+
+        let first = router.submit_order(req.clone());
+        assert!(router.submit_order(req.clone()).success);
+        let limited = router.submit_order(req.clone());
+        assert!(!limited.success);
+        assert_eq!(limited.error_code, Some(4012));
+
+        // 强平单不受频率限制
+        assert!(router.submit_force_order(req.clone()).success);
+
+        // 撤单桶独立计数
+        let cancel = |order_id: String| {
+            router.cancel_order(CancelOrderRequest {
+                account_id: "test_user".to_string(),
+                order_id,
+            })
+        };
+        assert!(cancel(first.order_id.unwrap()).is_ok());
+        assert!(matches!(
+            cancel("missing".to_string()),
+            Err(ExchangeError::RiskCheckFailed(_))
+        ));
+
+        std::thread::sleep(Duration::from_millis(250));
+        assert!(router.submit_order(req).success);
+
+        let stats = limiter.get_stats(5);
+        assert_eq!(stats.orders_limited, 1);
+        assert_eq!(stats.cancels_limited, 1);
+        let today = crate::utils::time_service::time_service().today();
+        assert_eq!(
+            router.rejection_stats().count(today, RejectReason::RateLimited),
+            1
+        );
+    }
+
+    /// 测试客户端连发同一笔单：窗口内只成交一笔，幂等键优先，被拒订单重试不算重复
+    #[test]
+    fn test_duplicate_order_guard_blocks_repeated_submission() {
+        let mut router = create_test_router();
+        let guard = Arc::new(crate::risk::DuplicateOrderGuard::new(
+            crate::risk::DuplicateOrderConfig {
+                enabled: true,
+                window_ms: 60_000,
+                ..Default::default()
+            },
+        ));
+        router.set_duplicate_guard(guard.clone());
+
+        let req = SubmitOrderRequest {
+            account_id: "test_user".to_string(),
+            instrument_id: "IX2301".to_string(),
+            direction: "BUY".to_string(),
+            offset: "OPEN".to_string(),
+            volume: 1.0,
+            price: 100.0,
+            order_type: "LIMIT".to_string(),
+            ..Default::default()
+        };
+
+        let responses: Vec<_> = (0..3).map(|_| router.submit_order(req.clone())).collect();
+        assert!(responses[0].success);
+        for dup in &responses[1..] {
+            assert!(!dup.success);
+            assert_eq!(dup.error_code, Some(4019));
+        }
+
+        // 幂等键不同的同参数订单放行，重复使用的幂等键拒绝
+        let keyed = |key: &str| SubmitOrderRequest {
+            client_order_id: Some(key.to_string()),
+            ..req.clone()
+        };
+        assert!(router.submit_order(keyed("c1")).success);
+        assert!(router.submit_order(keyed("c2")).success);
+        assert_eq!(router.submit_order(keyed("c1")).error_code, Some(4019));
+
+        // 资金不足被拒的订单已释放，重试仍按资金不足拒绝而不是重复
+        let oversized = SubmitOrderRequest {
+            volume: 1_000_000.0,
+            ..req.clone()
+        };
+        for _ in 0..2 {
+            let response = router.submit_order(oversized.clone());
+            assert!(!response.success);
+            assert_ne!(response.error_code, Some(4019));
+        }
+
+        // 强平单不检测
+        assert!(router.submit_force_order(req).success);
+
+        let stats = guard.stats(5, 10);
+        assert_eq!(stats.rejected, 3);
+        assert_eq!(stats.top_accounts[0].hits, 3);
+        let today = crate::utils::time_service::time_service().today();
+        assert_eq!(
+            router
+                .rejection_stats()
+                .count(today, RejectReason::DuplicateOrder),
+            3
+        );
+    }
+
+    /// 测试高频撤改单触发报撤比限制：超限后撤单与改单均被拒，原订单保持挂单
+    #[test]
+    fn test_cancel_ratio_restricts_high_frequency_cancels() {
+        let mut router = create_test_router();
+        let limiter = Arc::new(OrderRateLimiter::new(crate::risk::OrderRateLimitConfig {
+            individual: Some(crate::risk::RateLimitRule::new(1000, 0, 0)),
+            cancel_ratio: crate::risk::CancelRatioConfig {
+                enabled: true,
+                window_secs: 60,
+                min_cancels: 4,
+                max_ratio: 2.0,
+                action: crate::risk::CancelRatioAction::Restrict,
+            },
+            ..Default::default()
+        }));
+        router.set_rate_limiter(limiter.clone());
+
+        let req = SubmitOrderRequest {
+            account_id: "test_user".to_string(),
+            instrument_id: "IX2301".to_string(),
+            direction: "BUY".to_string(),
+            offset: "OPEN".to_string(),
+            volume: 1.0,
+            price: 100.0,
+            order_type: "LIMIT".to_string(),
+            ..Default::default()
+        };
+        let cancel = |order_id: &str| {
+            router.cancel_order(CancelOrderRequest {
+                account_id: "test_user".to_string(),
+                order_id: order_id.to_string(),
+            })
+        };
+        let amend = |order_id: &str, price: f64| {
+            router.amend_order(AmendOrderRequest {
+                account_id: "test_user".to_string(),
+                order_id: order_id.to_string(),
+                new_price: Some(price),
+                new_volume: None,
+            })
+        };
+
+        // 下 2 单：撤单 1 次 + 改单 2 次，撤改单数 3 未达 min_cancels
+        let first = router.submit_order(req.clone()).order_id.unwrap();
+        assert!(cancel(&first).is_ok());
+        let mut live = router.submit_order(req.clone()).order_id.unwrap();
+        for price in [99.0, 98.0] {
+            let response = amend(&live, price).unwrap();
+            assert!(response.success);
+            assert_eq!(router.get_order_status(&live), Some(OrderStatus::Cancelled));
+            live = response.order_id.unwrap();
+        }
+
+        // 改单重新下单不计下单数：窗口内 2 单、3 次撤改单
+        let snapshot = limiter.cancel_ratio("test_user").unwrap();
+        assert_eq!((snapshot.orders, snapshot.cancels), (2, 3));
+
+        // 第 4 次撤改单达到 min_cancels，4 / 2 = 2.0 未超限
+        let response = amend(&live, 97.0).unwrap();
+        assert!(response.success);
+        let live = response.order_id.unwrap();
+
+        // 第 5 次：5 / 2 > 2.0 → 撤单、改单均被拒
+        assert!(matches!(
+            cancel(&live),
+            Err(ExchangeError::RiskCheckFailed(_))
+        ));
+        assert!(matches!(
+            amend(&live, 96.0),
+            Err(ExchangeError::RiskCheckFailed(_))
+        ));
+        assert_eq!(router.get_order_status(&live), Some(OrderStatus::Submitted));
+
+        // 新下单拉低报撤比后恢复撤单
+        router.submit_order(req);
+        assert!(cancel(&live).is_ok());
+
+        let stats = limiter.get_stats(5);
+        assert_eq!(stats.cancel_ratio_limited, 2);
+        assert_eq!(stats.top_accounts[0].ratio_hits, 2);
+    }
+
+    /// 测试订单 TTL：到期撤销未成交剩余并释放冻结，手动撤单抢先时到期不再处理
+    #[test]
+    fn test_order_ttl_expires_remaining_volume() {
+        let router = create_test_router();
+        router
+            .account_mgr
+            .open_account(OpenAccountRequest {
+                user_id: "test_user_2".to_string(),
+                account_id: Some("test_user_2".to_string()),
+                account_name: "Test User 2".to_string(),
+                init_cash: 1000000.0,
+                account_type: AccountType::Individual,
+            })
+            .unwrap();
+
+        let order = |account_id: &str, direction: &str, volume: f64, price: f64, ttl_secs| {
+            SubmitOrderRequest {
+                account_id: account_id.to_string(),
+                instrument_id: "IX2301".to_string(),
+                direction: direction.to_string(),
+                offset: "OPEN".to_string(),
+                volume,
+                price,
+                order_type: "LIMIT".to_string(),
+                ttl_secs,
+                ..Default::default()
+            }
+        };
+        let frozen = || {
+            router
+                .account_mgr
+                .get_qifi_slice("test_user")
+                .unwrap()
+                .accounts
+                .frozen_margin
+        };
+
+        // 非法 TTL 拒单
+        let invalid = router.submit_order(order("test_user", "BUY", 1.0, 110.0, Some(0)));
+        assert!(!invalid.success);
+        assert_eq!(
+            invalid.error_code,
+            Some(RejectReason::InvalidOrderParams.error_code())
+        );
+
+        // 无 TTL 订单不受影响
+        let keep = router.submit_order(order("test_user", "BUY", 1.0, 110.0, None));
+        assert!(keep.success);
+        let keep_id = keep.order_id.unwrap();
+        let frozen_keep = frozen();
+
+        // TTL 订单部分成交
+        let partial = router.submit_order(order("test_user", "BUY", 5.0, 120.0, Some(1)));
+        let partial_id = partial.order_id.unwrap();
+        assert!(
+            router
+                .submit_order(order("test_user_2", "SELL", 2.0, 120.0, None))
+                .success
+        );
+        assert_eq!(
+            router.get_order_status(&partial_id),
+            Some(OrderStatus::PartiallyFilled)
+        );
+
+        // TTL 订单到期前被手动撤单
+        let manual = router.submit_order(order("test_user", "BUY", 1.0, 115.0, Some(1)));
+        let manual_id = manual.order_id.unwrap();
+        router
+            .cancel_order(CancelOrderRequest {
+                account_id: "test_user".to_string(),
+                order_id: manual_id.clone(),
+            })
+            .unwrap();
+        assert_eq!(router.order_ttl_manager().len(), 1);
+
+        // 未到期不撤单
+        assert_eq!(router.expire_orders(), 0);
+        assert_eq!(
+            router.get_order_status(&partial_id),
+            Some(OrderStatus::PartiallyFilled)
+        );
+
+        std::thread::sleep(Duration::from_millis(1100));
+        assert_eq!(router.expire_orders(), 1);
+        assert_eq!(
+            router.get_order_status(&partial_id),
+            Some(OrderStatus::Cancelled)
+        );
+        assert_eq!(
+            router.get_order_status(&manual_id),
+            Some(OrderStatus::Cancelled)
+        );
+        assert_eq!(
+            router.get_order_status(&keep_id),
+            Some(OrderStatus::Submitted)
+        );
+        assert!(router.order_ttl_manager().is_empty());
+
+        // 剩余 3 手的冻结已释放，已成交 2 手保留为持仓
+        assert!((frozen() - frozen_keep).abs() < 1e-6);
+        let account = router.account_mgr.get_account("test_user").unwrap();
+        let long = account
+            .write()
+            .get_position("IX2301")
+            .map_or(0.0, |pos| pos.volume_long());
+        assert_eq!(long, 2.0);
+
+        // 再次扫描不会重复撤单
+        assert_eq!(router.expire_orders(), 0);
+    }
+
+    /// 订单状态时间线：下单、撮合接受、两次部分成交、撤单全部落入历史，重启后可查
+    #[test]
+    fn test_order_history_timeline_across_lifecycle() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut router = create_test_router();
+        router.set_order_history(Arc::new(
+            OrderHistoryTracker::with_persist_dir(dir.path()).unwrap(),
+        ));
+        router
+            .account_mgr
+            .open_account(OpenAccountRequest {
+                user_id: "test_user_2".to_string(),
+                account_id: Some("test_user_2".to_string()),
+                account_name: "Test User 2".to_string(),
+                init_cash: 1000000.0,
+                account_type: AccountType::Individual,
+            })
+            .unwrap();
+
+        let order = |account_id: &str, direction: &str, volume: f64| SubmitOrderRequest {
+            account_id: account_id.to_string(),
+            instrument_id: "IX2301".to_string(),
+            direction: direction.to_string(),
+            offset: "OPEN".to_string(),
+            volume,
+            price: 120.0,
+            order_type: "LIMIT".to_string(),
+            ..Default::default()
+        };
+
+        let buy_id = router
+            .submit_order(order("test_user", "BUY", 5.0))
+            .order_id
+            .unwrap();
+        let sell_id = router
+            .submit_order(order("test_user_2", "SELL", 2.0))
+            .order_id
+            .unwrap();
+        assert!(
+            router
+                .submit_order(order("test_user_2", "SELL", 1.0))
+                .success
+        );
+        router
+            .cancel_order(CancelOrderRequest {
+                account_id: "test_user".to_string(),
+                order_id: buy_id.clone(),
+            })
+            .unwrap();
+        let rejected = router.submit_order(SubmitOrderRequest {
+            ttl_secs: Some(0),
+            ..order("test_user", "BUY", 1.0)
+        });
+        assert!(!rejected.success);
+
+        let history = router.order_history();
+        let timeline = history.history(&buy_id).unwrap();
+        let states: Vec<_> = timeline.iter().map(|c| (c.from, c.to)).collect();
+        assert_eq!(
+            states,
+            vec![
+                (None, OrderLifecycleState::PendingReport),
+                (
+                    Some(OrderLifecycleState::PendingReport),
+                    OrderLifecycleState::Reported
+                ),
+                (
+                    Some(OrderLifecycleState::Reported),
+                    OrderLifecycleState::PartiallyFilled
+                ),
+                (
+                    Some(OrderLifecycleState::PartiallyFilled),
+                    OrderLifecycleState::PartiallyFilled
+                ),
+                (
+                    Some(OrderLifecycleState::PartiallyFilled),
+                    OrderLifecycleState::PartiallyCancelled
+                ),
+            ]
+        );
+        let volumes: Vec<_> = timeline
+            .iter()
+            .map(|c| (c.filled_volume, c.volume_left))
+            .collect();
+        assert_eq!(
+            volumes,
+            vec![(0.0, 5.0), (0.0, 5.0), (2.0, 3.0), (3.0, 2.0), (3.0, 2.0)]
+        );
+        assert!(timeline
+            .windows(2)
+            .all(|w| w[0].timestamp <= w[1].timestamp));
+        assert!(timeline.iter().all(|c| !c.reason.is_empty()));
+
+        // 对手单全部成交、拒单只有一条拒绝记录
+        assert_eq!(
+            history.current_state(&sell_id),
+            Some(OrderLifecycleState::Filled)
+        );
+        let rejected_timeline = history
+            .history(rejected.order_id.as_deref().unwrap())
+            .unwrap();
+        assert_eq!(rejected_timeline.len(), 1);
+        assert_eq!(rejected_timeline[0].to, OrderLifecycleState::Rejected);
+
+        // 重启后从落盘文件恢复完整时间线
+        let restored = OrderHistoryTracker::with_persist_dir(dir.path()).unwrap();
+        assert_eq!(restored.history(&buy_id).unwrap(), timeline);
+        assert_eq!(restored.len(), history.len());
+    }
+
+    /// 交易限制行为矩阵：Normal / CloseOnly / Suspended × 开仓 / 平仓 / 撤单
+    #[test]
+    fn test_trading_restriction_matrix() {
+        let router = create_test_router();
+        router
+            .account_mgr
+            .open_account(OpenAccountRequest {
+                user_id: "test_user_2".to_string(),
+                account_id: Some("test_user_2".to_string()),
+                account_name: "Test User 2".to_string(),
+                init_cash: 1000000.0,
+                account_type: AccountType::Individual,
+            })
+            .unwrap();
+
+        let order = |account_id: &str, direction: &str, offset: &str, volume: f64, price: f64| {
+            SubmitOrderRequest {
+                account_id: account_id.to_string(),
+                instrument_id: "IX2301".to_string(),
+                direction: direction.to_string(),
+                offset: offset.to_string(),
+                volume,
+                price,
+                order_type: "LIMIT".to_string(),
+                ..Default::default()
+            }
+        };
+
+        // 先建立多头持仓，并挂好每种状态下待撤的订单
+        assert!(
+            router
+                .submit_order(order("test_user", "BUY", "OPEN", 3.0, 120.0))
+                .success
+        );
+        assert!(
+            router
+                .submit_order(order("test_user_2", "SELL", "OPEN", 3.0, 120.0))
+                .success
+        );
+        let resting: Vec<String> = (0..3)
+            .map(|_| {
+                router
+                    .submit_order(order("test_user", "BUY", "OPEN", 1.0, 110.0))
+                    .order_id
+                    .unwrap()
+            })
+            .collect();
+
+        // (限制, 开仓允许, 平仓允许)；撤单在任何状态下都允许
+        let matrix = [
+            (TradingRestriction::Normal, true, true),
+            (TradingRestriction::CloseOnly, false, true),
+            (TradingRestriction::Suspended, false, false),
+        ];
+        for ((restriction, open_ok, close_ok), resting_id) in matrix.into_iter().zip(resting) {
+            router
+                .account_mgr
+                .set_trading_restriction(
+                    "test_user",
+                    restriction,
+                    "测试".to_string(),
+                    None,
+                    "admin",
+                )
+                .unwrap();
+
+            let open = router.submit_order(order("test_user", "BUY", "OPEN", 1.0, 110.0));
+            assert_eq!(open.success, open_ok, "{:?} open", restriction);
+            let close = router.submit_order(order("test_user", "SELL", "CLOSE", 1.0, 130.0));
+            assert_eq!(close.success, close_ok, "{:?} close", restriction);
+            for rejected in [&open, &close].into_iter().filter(|r| !r.success) {
+                assert_eq!(rejected.error_code, Some(4013));
+            }
+
+            let cancel = router.cancel_order(CancelOrderRequest {
+                account_id: "test_user".to_string(),
+                order_id: resting_id,
+            });
+            assert!(cancel.is_ok(), "{:?} cancel: {:?}", restriction, cancel);
+        }
+
+        // 强平单不受交易限制
+        assert!(
+            router
+                .submit_force_order(order("test_user", "SELL", "CLOSE", 1.0, 130.0))
+                .success
+        );
+
+        let today = crate::utils::time_service::time_service().today();
+        assert_eq!(
+            router
+                .rejection_stats()
+                .count(today, RejectReason::TradingRestricted),
+            3
+        );
+    }
+
+    /// 单日成交量限额：累计开仓成交达上限后新开仓被拒、平仓放行
+    #[test]
+    fn test_trade_volume_limit_close_only_after_limit() {
+        let mut router = create_test_router();
+        router
+            .account_mgr
+            .open_account(OpenAccountRequest {
+                user_id: "test_user_2".to_string(),
+                account_id: Some("test_user_2".to_string()),
+                account_name: "Test User 2".to_string(),
+                init_cash: 1000000.0,
+                account_type: AccountType::Individual,
+            })
+            .unwrap();
+        let limiter = Arc::new(
+            TradeVolumeLimiter::new(crate::risk::TradeVolumeLimitConfig {
+                enabled: true,
+                default: crate::risk::AccountTypeVolumeRules {
+                    individual: Some(crate::risk::TradeVolumeRule::new(
+                        3.0,
+                        false,
+                        crate::risk::VolumeLimitAction::CloseOnly,
+                    )),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .unwrap(),
+        );
+        router.set_volume_limiter(limiter.clone());
+
+        let order = |account_id: &str, direction: &str, offset: &str, volume: f64, price: f64| {
+            SubmitOrderRequest {
+                account_id: account_id.to_string(),
+                instrument_id: "IX2301".to_string(),
+                direction: direction.to_string(),
+                offset: offset.to_string(),
+                volume,
+                price,
+                order_type: "LIMIT".to_string(),
+                ..Default::default()
+            }
+        };
+
+        // 买卖双方各成交 3 手开仓，达到上限
+        assert!(
+            router
+                .submit_order(order("test_user", "BUY", "OPEN", 3.0, 120.0))
+                .success
+        );
+        assert!(
+            router
+                .submit_order(order("test_user_2", "SELL", "OPEN", 3.0, 120.0))
+                .success
+        );
+        let usage = limiter.usage("test_user");
+        assert_eq!(usage.len(), 1);
+        assert_eq!(usage[0].open_volume, 3.0);
+        assert_eq!(limiter.recent_alerts(10).len(), 2);
+
+        // 新开仓被拒，平仓放行
+        for account_id in ["test_user", "test_user_2"] {
+            let open = router.submit_order(order(account_id, "BUY", "OPEN", 1.0, 110.0));
+            assert!(!open.success);
+            assert_eq!(open.error_code, Some(4016));
+        }
+        assert!(
+            router
+                .submit_order(order("test_user", "SELL", "CLOSE", 1.0, 130.0))
+                .success
+        );
+
+        // 强平单不受限额约束
+        assert!(
+            router
+                .submit_force_order(order("test_user", "BUY", "OPEN", 1.0, 110.0))
+                .success
+        );
+
+        let today = crate::utils::time_service::time_service().today();
+        assert_eq!(
+            router
+                .rejection_stats()
+                .count(today, RejectReason::TradeVolumeLimited),
+            2
+        );
+    }
+
+    /// 优先级调度：高优先级订单先于低优先级进入下单流程，普通账户的自定义标记不超过 Normal
+    #[test]
+    fn test_priority_dispatch_processes_high_priority_first() {
+        use crate::exchange::{OrderPriority, PriorityCounts};
+
+        let mut router = create_test_router();
+        router
+            .account_mgr
+            .open_account(OpenAccountRequest {
+                user_id: "maker".to_string(),
+                account_id: Some("maker".to_string()),
+                account_name: "maker".to_string(),
+                init_cash: 1000000.0,
+                account_type: AccountType::MarketMaker,
+            })
+            .unwrap();
+        router.enable_priority_queue(100, 1_000_000.0);
+
+        let order = |account_id: &str, volume: f64| SubmitOrderRequest {
+            account_id: account_id.to_string(),
+            instrument_id: "IX2301".to_string(),
+            direction: "BUY".to_string(),
+            offset: "OPEN".to_string(),
+            volume,
+            price: 120.0,
+            order_type: "LIMIT".to_string(),
+            ..Default::default()
+        };
+
+        // 入队顺序：小额(Low) → 普通(Normal) → 做市商(Critical) → 自定义标记 Critical（普通账户上限 Normal）
+        let low = router
+            .enqueue_prioritized(order("test_user", 1.0), None)
+            .unwrap();
+        let normal = router
+            .enqueue_prioritized(order("test_user", 10.0), None)
+            .unwrap();
+        let critical = router
+            .enqueue_prioritized(order("maker", 1.0), None)
+            .unwrap();
+        let tagged = router
+            .enqueue_prioritized(order("test_user", 10.0), Some(OrderPriority::Critical))
+            .unwrap();
+
+        let dispatched = router.dispatch_priority_orders(10);
+        let sequence: Vec<(u64, OrderPriority)> =
+            dispatched.iter().map(|(seq, p, _)| (*seq, *p)).collect();
+        assert_eq!(
+            sequence,
+            vec![
+                (critical, OrderPriority::Critical),
+                (normal, OrderPriority::Normal),
+                (tagged, OrderPriority::Normal),
+                (low, OrderPriority::Low),
+            ]
+        );
+        assert!(dispatched.iter().all(|(_, _, response)| response.success));
+
+        // 同步提交：入队后由提交线程调度处理
+        let response = router.submit_order_prioritized(order("maker", 2.0), None);
+        assert!(response.success);
+
+        let stats = router.get_priority_queue_stats().unwrap();
+        assert_eq!(
+            stats.dispatched,
+            PriorityCounts {
+                critical: 2,
+                normal: 2,
+                low: 1
+            }
+        );
+        assert_eq!(stats.critical_queue_length + stats.low_queue_length, 0);
+    }
+
+    /// 持仓集中度：盘中风控发现单账户持仓占比超阈值后告警，该账户在该合约新开仓被拒、平仓放行
+    #[test]
+    fn test_position_concentration_restricts_open() {
+        use crate::risk::{PositionConcentrationConfig, RiskAlertType, RiskMonitor};
+
+        let mut router = create_test_router();
+        for account_id in ["test_user_2", "test_user_3"] {
+            router
+                .account_mgr
+                .open_account(OpenAccountRequest {
+                    user_id: account_id.to_string(),
+                    account_id: Some(account_id.to_string()),
+                    account_name: account_id.to_string(),
+                    init_cash: 1000000.0,
+                    account_type: AccountType::Individual,
+                })
+                .unwrap();
+        }
+        let concentration = Arc::new(
+            PositionConcentration::new(PositionConcentrationConfig {
+                enabled: true,
+                default_max_ratio: Some(0.4),
+                ..Default::default()
+            })
+            .unwrap(),
+        );
+        router.set_position_concentration(concentration.clone());
+        let mut monitor = RiskMonitor::new(router.account_mgr.clone());
+        monitor.set_position_concentration(concentration.clone());
+
+        let order = |account_id: &str, direction: &str, offset: &str, volume: f64, price: f64| {
+            SubmitOrderRequest {
+                account_id: account_id.to_string(),
+                instrument_id: "IX2301".to_string(),
+                direction: direction.to_string(),
+                offset: offset.to_string(),
+                volume,
+                price,
+                order_type: "LIMIT".to_string(),
+                ..Default::default()
+            }
+        };
+
+        // test_user 多 3 手、test_user_3 多 2 手，对手方 test_user_2 空 5 手：
+        // 全市场双边总持仓 10 手，test_user_2 占 50% 超过 40% 阈值
+        for (account_id, direction, volume) in [
+            ("test_user", "BUY", 3.0),
+            ("test_user_2", "SELL", 3.0),
+            ("test_user_3", "BUY", 2.0),
+            ("test_user_2", "SELL", 2.0),
+        ] {
+            assert!(
+                router
+                    .submit_order(order(account_id, direction, "OPEN", volume, 120.0))
+                    .success
+            );
+        }
+
+        let breaches = monitor.check_position_concentration();
+        assert_eq!(breaches.len(), 1);
+        assert_eq!(breaches[0].account_id, "test_user_2");
+        assert_eq!(breaches[0].open_interest, 10.0);
+        assert_eq!(breaches[0].ratio, 0.5);
+        assert_eq!(concentration.open_interest("IX2301"), 10.0);
+        let alerts = monitor.get_risk_alerts("test_user_2");
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].alert_type, RiskAlertType::PositionConcentration);
+        // 持续超限不重复告警
+        assert!(monitor.check_position_concentration().is_empty());
+
+        // 超限账户新开仓被拒，平仓放行；其他账户不受影响
+        let open = router.submit_order(order("test_user_2", "SELL", "OPEN", 1.0, 130.0));
+        assert!(!open.success);
+        assert_eq!(open.error_code, Some(4018));
+        assert!(
+            router
+                .submit_order(order("test_user_2", "BUY", "CLOSE", 1.0, 110.0))
+                .success
+        );
+        assert!(
+            router
+                .submit_order(order("test_user", "BUY", "OPEN", 1.0, 110.0))
+                .success
+        );
+
+        // 强平单不受限
+        assert!(
+            router
+                .submit_force_order(order("test_user_2", "SELL", "OPEN", 1.0, 130.0))
+                .success
+        );
+    }
+
+    /// 竞价指示价：竞价期按订单簿计算虚拟开盘价，开盘后定时推送立即发出最终指示价并停止推送
+    #[test]
+    fn test_auction_indicator_flushed_at_open() {
+        use crate::market::{AuctionIndicatorPublisher, MarketDataBroadcaster, MarketDataEvent};
+        use crate::matching::TradingState;
+
+        let mut router = create_test_router();
+        router
+            .account_mgr
+            .open_account(OpenAccountRequest {
+                user_id: "test_user_2".to_string(),
+                account_id: Some("test_user_2".to_string()),
+                account_name: "Test User 2".to_string(),
+                init_cash: 1000000.0,
+                account_type: AccountType::Individual,
+            })
+            .unwrap();
+        let state_machine = Arc::new(crate::exchange::TradingStateMachine::new());
+        state_machine.set_instrument_state("IX2301", TradingState::AuctionOrder);
+        router.set_trading_state_machine(state_machine.clone());
+        let broadcaster = Arc::new(MarketDataBroadcaster::new());
+        let rx = broadcaster.subscribe("s1".to_string(), vec![], vec![]);
+        // 推送间隔足够长：竞价期的变更只记录不推送
+        let publisher = Arc::new(AuctionIndicatorPublisher::with_interval(
+            broadcaster,
+            std::time::Duration::from_secs(60),
+        ));
+        router.set_auction_indicator(publisher.clone());
+        router
+            .matching_engine
+            .get_orderbook("IX2301")
+            .unwrap()
+            .write()
+            .start_pre_auction();
+
+        let make_req =
+            |account: &str, direction: &str, volume: f64, price: f64| SubmitOrderRequest {
+                account_id: account.to_string(),
+                instrument_id: "IX2301".to_string(),
+                direction: direction.to_string(),
+                offset: "OPEN".to_string(),
+                volume,
+                price,
+                order_type: "LIMIT".to_string(),
+                ..Default::default()
+            };
+        assert!(
+            router
+                .submit_order(make_req("test_user", "BUY", 5.0, 121.0))
+                .success
+        );
+        assert!(
+            router
+                .submit_order(make_req("test_user_2", "SELL", 3.0, 120.0))
+                .success
+        );
+
+        let indicator = publisher.get_indicator("IX2301").unwrap();
+        assert_eq!(indicator.auction_volume, 3.0);
+        router.flush_auction_indicators();
+        assert!(publisher.is_active("IX2301"));
+
+        // 开盘：不等下一笔订单，定时推送按最终订单簿发出指示价并停止
+        state_machine.set_instrument_state("IX2301", TradingState::ContinuousTrading);
+        router.flush_auction_indicators();
+        let pushed: Vec<(f64, f64)> = rx
+            .try_iter()
+            .filter_map(|event| match event {
+                MarketDataEvent::AuctionIndicator {
+                    auction_price,
+                    auction_volume,
+                    ..
+                } => Some((auction_price, auction_volume)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(pushed, vec![(indicator.auction_price, 3.0)]);
+        assert!(!publisher.is_active("IX2301"));
+    }
+
+    /// 功能灰度：白名单账户在高性能撮合线程撮合并与原路径共用订单簿成交，热更新后已下单的订单沿用原决策
+    #[test]
+    fn test_feature_gate_routes_whitelisted_orders_to_high_perf_path() {
+        use crate::exchange::feature_gate::{FeatureGateConfig, FeatureRule};
+        use crate::matching::high_perf::HighPerfMatchingConfig;
+
+        let mut router = create_test_router();
+        let matcher = Arc::new(
+            HighPerfOrderMatcher::start(&HighPerfMatchingConfig {
+                enable_cpu_affinity: false,
+                ..Default::default()
+            })
+            .unwrap(),
+        );
+        router.set_high_perf_matcher(matcher.clone());
+        router
+            .account_mgr
+            .open_account(OpenAccountRequest {
+                user_id: "test_user_2".to_string(),
+                account_id: Some("test_user_2".to_string()),
+                account_name: "Test User 2".to_string(),
+                init_cash: 1000000.0,
+                account_type: AccountType::Individual,
+            })
+            .unwrap();
+        let gate = Arc::new(
+            FeatureGate::new(FeatureGateConfig {
+                features: [(
+                    USE_HIGH_PERF_MATCHING.to_string(),
+                    FeatureRule {
+                        enabled: true,
+                        whitelist: vec!["test_user".to_string()],
+                        ..Default::default()
+                    },
+                )]
+                .into_iter()
+                .collect(),
+                ..Default::default()
+            })
+            .unwrap(),
+        );
+        router.set_feature_gate(gate.clone());
+
+        let make_req =
+            |account: &str, direction: &str, volume: f64, price: f64| SubmitOrderRequest {
+                account_id: account.to_string(),
+                instrument_id: "IX2301".to_string(),
+                direction: direction.to_string(),
+                offset: "OPEN".to_string(),
+                volume,
+                price,
+                order_type: "LIMIT".to_string(),
+                ..Default::default()
+            };
+
+        // test_user 经高性能路径挂卖单，test_user_2 经原路径买入成交
+        let sell = router.submit_order(make_req("test_user", "SELL", 5.0, 120.0));
+        assert!(sell.success, "{:?}", sell.error_message);
+        let sell_id = sell.order_id.unwrap();
+        assert_eq!(router.is_high_perf_order(&sell_id), Some(true));
+
+        let buy = router.submit_order(make_req("test_user_2", "BUY", 2.0, 120.0));
+        assert!(buy.success, "{:?}", buy.error_message);
+        let buy_id = buy.order_id.unwrap();
+        assert_eq!(router.is_high_perf_order(&buy_id), Some(false));
+        assert_eq!(router.get_order_status(&buy_id), Some(OrderStatus::Filled));
+        // 只有灰度订单经过高性能撮合线程
+        assert_eq!(matcher.stats().orders_processed.load(Ordering::Relaxed), 1);
+
+        // 热更新关闭后：新订单走原路径，已下单的订单保持原决策并可正常撤单
+        gate.update_config(FeatureGateConfig::default()).unwrap();
+        let next = router.submit_order(make_req("test_user", "BUY", 1.0, 110.0));
+        assert!(next.success, "{:?}", next.error_message);
+        assert_eq!(router.is_high_perf_order(&next.order_id.unwrap()), Some(false));
+        assert_eq!(router.is_high_perf_order(&sell_id), Some(true));
+        assert_eq!(matcher.stats().orders_processed.load(Ordering::Relaxed), 1);
+
+        router
+            .cancel_order(CancelOrderRequest {
+                account_id: "test_user".to_string(),
+                order_id: sell_id.clone(),
+            })
+            .unwrap();
+        assert_eq!(
+            router.get_order_status(&sell_id),
+            Some(OrderStatus::Cancelled)
+        );
+    }
+
+    /// 最小变动价位：reject 拒绝不在 tick 上的价格，round 规整到最近的 tick 并返回实际委托价
+    #[test]
+    fn test_price_tick_reject_and_round() {
+        let router = create_test_router();
+        let make_req = |price: f64| SubmitOrderRequest {
+            account_id: "test_user".to_string(),
+            instrument_id: "IX2301".to_string(),
+            direction: "BUY".to_string(),
+            offset: "OPEN".to_string(),
+            volume: 1.0,
+            price,
+            order_type: "LIMIT".to_string(),
+            ..Default::default()
+        };
+
+        router
+            .get_risk_checker()
+            .set_price_tick_mode(PriceTickMode::Reject);
+        let rejected = router.submit_order(make_req(110.005));
+        assert!(!rejected.success);
+        assert_eq!(
+            rejected.error_code,
+            Some(RejectReason::InvalidPriceTick.error_code())
+        );
+
+        let on_tick = router.submit_order(make_req(110.01));
+        assert!(on_tick.success, "{:?}", on_tick.error_message);
+        assert_eq!(on_tick.adjusted_price, None);
+
+        router
+            .get_risk_checker()
+            .set_price_tick_mode(PriceTickMode::Round);
+        let rounded = router.submit_order(make_req(110.006));
+        assert!(rounded.success, "{:?}", rounded.error_message);
+        assert_eq!(rounded.adjusted_price, Some(110.01));
+    }
+
+    /// 在途订单看门狗：注入撮合回应丢失，订单簿中不存在的订单超时拒单并释放冻结，
+    /// 已挂入订单簿的订单补登记为已报；全量对账输出路由器与撮合引擎的差异
+    #[test]
+    fn test_order_watchdog_releases_orders_lost_by_matching_engine() {
+        use crate::exchange::order_watchdog::OrderWatchdogConfig;
+
+        let mut router = create_test_router();
+        router.set_order_watchdog(Arc::new(
+            OrderWatchdog::new(OrderWatchdogConfig {
+                enabled: true,
+                ack_timeout_ms: 3_000,
+                reconcile_interval_secs: 0,
+                ..Default::default()
+            })
+            .unwrap(),
+        ));
+        let watchdog = router.order_watchdog().unwrap();
+        let make_req = |price: f64| SubmitOrderRequest {
+            account_id: "test_user".to_string(),
+            instrument_id: "IX2301".to_string(),
+            direction: "BUY".to_string(),
+            offset: "OPEN".to_string(),
+            volume: 1.0,
+            price,
+            order_type: "LIMIT".to_string(),
+            ..Default::default()
+        };
+        let frozen = || {
+            router
+                .account_mgr
+                .get_qifi_slice("test_user")
+                .unwrap()
+                .accounts
+                .frozen_margin
+        };
+        let cancel_on_book = |engine_id: u64| {
+            let _ = router
+                .get_orderbook("IX2301")
+                .unwrap()
+                .write()
+                .process_order(crate::matching::OrderRequest::CancelOrder {
+                    id: engine_id,
+                    direction: OrderDirection::BUY,
+                });
+        };
+        // 回滚路由器侧的 Accepted 处理，模拟 3 秒前发出后一直未收到回应
+        let inject_lost_ack = |order_id: &str| -> u64 {
+            let info = router.orders.get(order_id).unwrap().value().clone();
+            let engine_id = {
+                let mut info = info.write();
+                info.status = OrderStatus::PendingRoute;
+                info.matching_engine_order_id.take().unwrap()
+            };
+            router.engine_id_to_order.remove(&engine_id);
+            router.engine_id_to_user.remove(&engine_id);
+            watchdog.track(PendingAck {
+                order_id: order_id.to_string(),
+                account_id: "test_user".to_string(),
+                instrument_id: "IX2301".to_string(),
+                sent_at: router.clock.now_millis() - 5_000,
+            });
+            engine_id
+        };
+
+        // 撮合从未收到：订单簿中不存在
+        let lost = router.submit_order(make_req(110.0)).order_id.unwrap();
+        cancel_on_book(inject_lost_ack(&lost));
+        // Accepted 回应丢失：订单已挂入订单簿
+        let recovered = router.submit_order(make_req(109.0)).order_id.unwrap();
+        inject_lost_ack(&recovered);
+        // 未超时的订单不处理
+        let fresh = router.submit_order(make_req(108.0)).order_id.unwrap();
+        watchdog.track(PendingAck {
+            order_id: fresh.clone(),
+            account_id: "test_user".to_string(),
+            instrument_id: "IX2301".to_string(),
+            sent_at: router.clock.now_millis(),
+        });
+        router.acknowledge_order(&fresh);
+        let frozen_all = frozen();
+
+        assert_eq!(router.check_order_watchdog(), 1);
+        assert_eq!(router.get_order_status(&lost), Some(OrderStatus::Rejected));
+        assert_eq!(
+            router.get_order_status(&recovered),
+            Some(OrderStatus::Submitted)
+        );
+        assert!(frozen() < frozen_all);
+        let stats = watchdog.stats();
+        assert_eq!(stats.pending, 0);
+        assert_eq!(stats.rejected_total, 1);
+        assert_eq!(stats.recovered_total, 1);
+        assert_eq!(
+            router.rejection_stats.count(
+                crate::utils::time_service::time_service().today(),
+                RejectReason::MatchingAckTimeout
+            ),
+            1
+        );
+
+        // 补登记的订单可正常撤单，资金最终全部释放
+        for order_id in [&recovered, &fresh] {
+            router
+                .cancel_order(CancelOrderRequest {
+                    account_id: "test_user".to_string(),
+                    order_id: order_id.clone(),
+                })
+                .unwrap();
+        }
+        assert!(frozen().abs() < 1e-6);
+        assert!(router.reconcile_with_engine().is_consistent());
+
+        // 对账：路由器在途但撮合引擎不存在 / 撮合引擎存在但路由器不认识
+        let resting = router.submit_order(make_req(107.0)).order_id.unwrap();
+        let engine_id = router
+            .orders
+            .get(&resting)
+            .unwrap()
+            .read()
+            .matching_engine_order_id
+            .unwrap();
+        cancel_on_book(engine_id);
+        let orphan = router
+            .get_orderbook("IX2301")
+            .unwrap()
+            .write()
+            .process_order(crate::matching::orders::new_limit_order_request(
+                InstrumentAsset::from_code("IX2301"),
+                OrderDirection::BUY,
+                106.0,
+                1.0,
+                router.clock.now_nanos(),
+            ))
+            .into_iter()
+            .find_map(|r| match r {
+                Ok(Success::Accepted { id, .. }) => Some(id),
+                _ => None,
+            })
+            .unwrap();
+
+        let report = router.reconcile_with_engine();
+        assert_eq!(report.missing_in_engine, vec![resting]);
+        assert_eq!(
+            report.unknown_in_router,
+            vec![("IX2301".to_string(), orphan)]
+        );
+        assert!(report.volume_mismatch.is_empty());
+        assert_eq!(watchdog.stats().last_reconcile, Some(report));
+    }
+
+    // ==================== 边界条件测试 @yutiansut @quantaxis ====================
+
+    /// 测试零价格订单
+    #[test]
+    fn test_submit_order_zero_price() {
+        let router = create_test_router();
+
+        let req = SubmitOrderRequest {
+            account_id: "test_user".to_string(),
+            instrument_id: "IX2301".to_string(),
+            direction: "BUY".to_string(),
+            offset: "OPEN".to_string(),
+            volume: 10.0,
+            price: 0.0, // 零价格
+            order_type: "LIMIT".to_string(),
+            ..Default::default()
+        };
+
+        let response = router.submit_order(req);
+        // 零价格可能被拒绝
+        // 这里只验证不会panic
+        assert!(response.order_id.is_some() || response.error_message.is_some());
+    }
+
+    /// 测试零数量订单
+    #[test]
+    fn test_submit_order_zero_volume() {
+        let router = create_test_router();
+
+        let req = SubmitOrderRequest {
+            account_id: "test_user".to_string(),
+            instrument_id: "IX2301".to_string(),
+            direction: "BUY".to_string(),
+            offset: "OPEN".to_string(),
+            volume: 0.0, // 零数量
+            price: 120.0,
+            order_type: "LIMIT".to_string(),
+            ..Default::default()
+        };
+
+        let response = router.submit_order(req);
+        // 零数量可能被拒绝
+        // 这里只验证不会panic
+        assert!(response.order_id.is_some() || response.error_message.is_some());
+    }
+
+    /// 测试负数量订单
+    #[test]
+    fn test_submit_order_negative_volume() {
+        let router = create_test_router();
+
+        let req = SubmitOrderRequest {
+            account_id: "test_user".to_string(),
+            instrument_id: "IX2301".to_string(),
+            direction: "BUY".to_string(),
+            offset: "OPEN".to_string(),
+            volume: -10.0, // 负数量
+            price: 120.0,
+            order_type: "LIMIT".to_string(),
+            ..Default::default()
+        };
+
+        let response = router.submit_order(req);
+        // 负数量应该被拒绝
+        // 这里只验证不会panic
+        assert!(response.order_id.is_some() || response.error_message.is_some());
+    }
+
+    // ==================== 并发测试 @yutiansut @quantaxis ====================
+
+    /// 测试并发提交订单
+    #[test]
+    fn test_concurrent_submit_orders() {
+        use std::thread;
+
+        let router = Arc::new(create_test_router());
+        let mut handles = vec![];
 
         for i in 0..10 {
             let router_clone = router.clone();
@@ -3583,8 +7480,7 @@ mod tests {
                     volume: 1.0,
                     price: 120.0,
                     order_type: "LIMIT".to_string(),
-                    time_condition: None,
-                    volume_condition: None,
+                    ..Default::default()
                 };
                 router_clone.submit_order(req)
             }));
@@ -3622,8 +7518,7 @@ mod tests {
                 volume: 1.0,
                 price: 120.0,
                 order_type: "LIMIT".to_string(),
-                time_condition: None,
-                volume_condition: None,
+                ..Default::default()
             };
             router.submit_order(req);
         }
@@ -3647,4 +7542,714 @@ mod tests {
             assert!(count >= 0);
         }
     }
+
+    // ==================== 分片撮合测试 @yutiansut @quantaxis ====================
+
+    /// 测试分片模式下订单按合约路由到分片撮合，成交汇聚到同一 TradeGateway
+    #[test]
+    fn test_sharded_engine_routing() {
+        use crate::matching::sharded::ShardedMatchingConfig;
+
+        let mut router = create_test_router();
+        let sharded = Arc::new(
+            ShardedMatchingEngine::new(ShardedMatchingConfig {
+                shard_count: 2,
+                ..Default::default()
+            })
+            .unwrap(),
+        );
+        sharded.register_instrument("IX2301".to_string(), 120.0).unwrap();
+        router.set_sharded_engine(sharded.clone());
+
+        router
+            .account_mgr
+            .open_account(OpenAccountRequest {
+                user_id: "test_user_2".to_string(),
+                account_id: Some("test_user_2".to_string()),
+                account_name: "Test User 2".to_string(),
+                init_cash: 1000000.0,
+                account_type: AccountType::Individual,
+            })
+            .unwrap();
+
+        let make_req = |account: &str, direction: &str| SubmitOrderRequest {
+            account_id: account.to_string(),
+            instrument_id: "IX2301".to_string(),
+            direction: direction.to_string(),
+            offset: "OPEN".to_string(),
+            volume: 2.0,
+            price: 120.0,
+            order_type: "LIMIT".to_string(),
+            ..Default::default()
+        };
+
+        let buy = router.submit_order(make_req("test_user", "BUY"));
+        assert!(buy.success, "{:?}", buy.error_message);
+        let sell = router.submit_order(make_req("test_user_2", "SELL"));
+        assert!(sell.success, "{:?}", sell.error_message);
+        assert_eq!(sell.status.as_deref(), Some("filled"));
+
+        // 撮合发生在分片引擎上，而不是默认引擎
+        let stats = sharded.get_stats();
+        assert_eq!(stats.total_processed, 2);
+        assert!(router.get_trade_statistics().total_count > 0);
+    }
+
+    /// 测试分片撮合 panic 后按在途订单重建订单簿，挂单ID重新映射后仍可成交
+    #[test]
+    fn test_sharded_engine_panic_recovery() {
+        use crate::matching::sharded::ShardedMatchingConfig;
+
+        let mut router = create_test_router();
+        let sharded = Arc::new(
+            ShardedMatchingEngine::new(ShardedMatchingConfig {
+                shard_count: 1,
+                ..Default::default()
+            })
+            .unwrap(),
+        );
+        sharded.register_instrument("IX2301".to_string(), 120.0).unwrap();
+        router.set_sharded_engine(sharded.clone());
+        let router = Arc::new(router);
+        router.enable_matching_recovery();
+
+        router
+            .account_mgr
+            .open_account(OpenAccountRequest {
+                user_id: "test_user_2".to_string(),
+                account_id: Some("test_user_2".to_string()),
+                account_name: "Test User 2".to_string(),
+                init_cash: 1000000.0,
+                account_type: AccountType::Individual,
+            })
+            .unwrap();
+
+        let make_req = |account: &str, direction: &str| SubmitOrderRequest {
+            account_id: account.to_string(),
+            instrument_id: "IX2301".to_string(),
+            direction: direction.to_string(),
+            offset: "OPEN".to_string(),
+            volume: 2.0,
+            price: 120.0,
+            order_type: "LIMIT".to_string(),
+            ..Default::default()
+        };
+
+        let buy = router.submit_order(make_req("test_user", "BUY"));
+        assert!(buy.success, "{:?}", buy.error_message);
+        let buy_id = buy.order_id.unwrap();
+
+        // 注入撮合 panic：订单簿按路由器在途订单重建
+        let result = sharded.execute_labeled("IX2301", "O-POISON", |_ob| -> bool {
+            panic!("injected matching fault")
+        });
+        assert!(result.is_err());
+        assert_eq!(sharded.poisoned_tasks().len(), 1);
+
+        let sell = router.submit_order(make_req("test_user_2", "SELL"));
+        assert!(sell.success, "{:?}", sell.error_message);
+        assert_eq!(sell.status.as_deref(), Some("filled"));
+        assert_eq!(router.get_order_status(&buy_id), Some(OrderStatus::Filled));
+        assert_eq!(sharded.fault_state("IX2301"), None);
+    }
+
+    // ==================== 交易单位换算测试 @yutiansut @quantaxis ====================
+
+    /// 测试带乘数合约：委托量按手计，冻结、保证金、成交金额按每手数量换算
+    #[test]
+    fn test_contract_multiplier_conversion() {
+        let router = create_test_router();
+        router
+            .account_mgr
+            .open_account(OpenAccountRequest {
+                user_id: "test_user_2".to_string(),
+                account_id: Some("test_user_2".to_string()),
+                account_name: "Test User 2".to_string(),
+                init_cash: 1000000.0,
+                account_type: AccountType::Individual,
+            })
+            .unwrap();
+
+        // 按吨报价、10 吨/手交易
+        let mut info = InstrumentInfo::new(
+            "UT2301".to_string(),
+            "UT2301".to_string(),
+            crate::exchange::instrument_registry::InstrumentType::CommodityFuture,
+            "SHFE".to_string(),
+        );
+        info.contract_multiplier = 10;
+        info.quote_unit = "元/吨".to_string();
+        info.price_tick = 0.01;
+        router.instrument_registry.register(info.clone()).unwrap();
+        router
+            .matching_engine
+            .register_instrument("UT2301".to_string(), 120.0)
+            .unwrap();
+
+        let make_req = |account: &str, instrument: &str, direction: &str| SubmitOrderRequest {
+            account_id: account.to_string(),
+            instrument_id: instrument.to_string(),
+            direction: direction.to_string(),
+            offset: "OPEN".to_string(),
+            volume: 2.0,
+            price: 120.0,
+            order_type: "LIMIT".to_string(),
+            ..Default::default()
+        };
+        for instrument in ["IX2301", "UT2301"] {
+            let buy = router.submit_order(make_req("test_user", instrument, "BUY"));
+            assert!(buy.success, "{:?}", buy.error_message);
+            let sell = router.submit_order(make_req("test_user_2", instrument, "SELL"));
+            assert_eq!(sell.status.as_deref(), Some("filled"));
+        }
+
+        let account = router.account_mgr.get_account("test_user").unwrap();
+        let mut acc = account.write();
+        let (unit_table, margin_unit, long_volume) = {
+            let pos = acc.get_position("UT2301").unwrap();
+            (pos.preset.unit_table, pos.margin_long, pos.volume_long())
+        };
+        let margin_base = acc.get_position("IX2301").unwrap().margin_long;
+
+        // 持仓量仍为手数，保证金为乘数 1 的同价合约的 10 倍
+        assert_eq!(unit_table, 10);
+        assert_eq!(long_volume, 2.0);
+        assert!(margin_base > 0.0);
+        assert!((margin_unit / margin_base - 10.0).abs() < 1e-9);
+
+        // 成交金额 = 120 × 2 手 × 10 吨/手
+        assert_eq!(info.turnover(120.0, 2.0), 2400.0);
+        assert_eq!(router.instrument_registry.volume_multiple("UT2301"), 10.0);
+    }
+
+    // ==================== 撮合批次测试 @yutiansut @quantaxis ====================
+
+    /// 测试大单吃多档：同一次撮合的成交共用撮合批次号，按批次聚合出全部成交与加权均价
+    #[test]
+    fn test_match_batch_sweep_multiple_levels() {
+        use crate::matching::trade_recorder::TradeRecorder;
+
+        let recorder = Arc::new(TradeRecorder::new());
+        let router = create_test_router_with_recorder(Some(recorder.clone()));
+        router
+            .account_mgr
+            .open_account(OpenAccountRequest {
+                user_id: "test_user_2".to_string(),
+                account_id: Some("test_user_2".to_string()),
+                account_name: "Test User 2".to_string(),
+                init_cash: 1000000.0,
+                account_type: AccountType::Individual,
+            })
+            .unwrap();
+
+        let make_req =
+            |account: &str, direction: &str, volume: f64, price: f64| SubmitOrderRequest {
+                account_id: account.to_string(),
+                instrument_id: "IX2301".to_string(),
+                direction: direction.to_string(),
+                offset: "OPEN".to_string(),
+                volume,
+                price,
+                order_type: "LIMIT".to_string(),
+                ..Default::default()
+            };
+
+        // 卖方挂 5 档：120~124，每档 2 手
+        for i in 0..5 {
+            let price = 120.0 + i as f64;
+            let resp = router.submit_order(make_req("test_user_2", "SELL", 2.0, price));
+            assert!(resp.success, "{:?}", resp.error_message);
+        }
+
+        // 买方一笔 10 手吃掉 5 档
+        let buy = router.submit_order(make_req("test_user", "BUY", 10.0, 125.0));
+        assert!(buy.success, "{:?}", buy.error_message);
+        let buy_order_id = buy.order_id.unwrap();
+
+        let trades = recorder.get_trades_by_instrument("IX2301");
+        assert_eq!(trades.len(), 5);
+        let batch_id = trades[0].match_batch_id;
+        assert!(batch_id > 0);
+        assert!(trades.iter().all(|t| t.match_batch_id == batch_id));
+
+        let summary = recorder.get_batch_summary(batch_id).unwrap();
+        assert_eq!(summary.trades.len(), 5);
+        assert_eq!(summary.taker_order_id, buy_order_id);
+        assert_eq!(summary.total_volume, 10.0);
+        assert!((summary.total_amount - 1220.0).abs() < 1e-9);
+        assert!((summary.avg_price - 122.0).abs() < 1e-9);
+        let prices: Vec<f64> = summary.trades.iter().map(|t| t.price).collect();
+        assert_eq!(prices, vec![120.0, 121.0, 122.0, 123.0, 124.0]);
+
+        // 下一次撮合分配新的批次
+        let resp = router.submit_order(make_req("test_user_2", "SELL", 1.0, 126.0));
+        assert!(resp.success, "{:?}", resp.error_message);
+        let buy = router.submit_order(make_req("test_user", "BUY", 1.0, 126.0));
+        assert!(buy.success, "{:?}", buy.error_message);
+        let trades = recorder.get_trades_by_instrument("IX2301");
+        assert_eq!(trades.len(), 6);
+        assert!(trades[5].match_batch_id > batch_id);
+        assert!(recorder.get_batch_summary(-1).is_none());
+    }
+
+    // ==================== 影子撮合测试 @yutiansut @quantaxis ====================
+
+    /// 测试影子模式：影子引擎与主引擎一致时下单/吃单/撤单全部对比一致，且不影响订单状态
+    #[test]
+    fn test_shadow_mode_consistent_with_primary() {
+        use crate::exchange::shadow_mode::{ShadowMode, ShadowModeConfig};
+
+        let mut router = create_test_router();
+        router
+            .account_mgr
+            .open_account(OpenAccountRequest {
+                user_id: "test_user_2".to_string(),
+                account_id: Some("test_user_2".to_string()),
+                account_name: "Test User 2".to_string(),
+                init_cash: 1000000.0,
+                account_type: AccountType::Individual,
+            })
+            .unwrap();
+
+        let shadow_engine = Arc::new(ExchangeMatchingEngine::new());
+        shadow_engine
+            .register_instrument("IX2301".to_string(), 120.0)
+            .unwrap();
+        let shadow =
+            Arc::new(ShadowMode::start(shadow_engine, ShadowModeConfig::default()).unwrap());
+        router.set_shadow_mode(shadow.clone());
+
+        let make_req =
+            |account: &str, direction: &str, volume: f64, price: f64| SubmitOrderRequest {
+                account_id: account.to_string(),
+                instrument_id: "IX2301".to_string(),
+                direction: direction.to_string(),
+                offset: "OPEN".to_string(),
+                volume,
+                price,
+                order_type: "LIMIT".to_string(),
+                ..Default::default()
+            };
+
+        let sell = router.submit_order(make_req("test_user_2", "SELL", 5.0, 120.0));
+        assert!(sell.success, "{:?}", sell.error_message);
+        let buy = router.submit_order(make_req("test_user", "BUY", 2.0, 121.0));
+        assert!(buy.success, "{:?}", buy.error_message);
+        router
+            .cancel_order(CancelOrderRequest {
+                account_id: "test_user_2".to_string(),
+                order_id: sell.order_id.clone().unwrap(),
+            })
+            .unwrap();
+
+        assert!(shadow.wait_idle(Duration::from_secs(5)));
+        let stats = shadow.stats();
+        assert_eq!(stats.submitted, 3);
+        assert_eq!(stats.matched, 3);
+        assert_eq!(stats.mismatched, 0);
+
+        let buy_order = router.query_order(&buy.order_id.unwrap()).unwrap();
+        assert_eq!(buy_order.volume_left, 0.0);
+    }
+
+    // ==================== 成交事件总线测试 @yutiansut @quantaxis ====================
+
+    fn open_counterparty(router: &OrderRouter) {
+        router
+            .account_mgr
+            .open_account(OpenAccountRequest {
+                user_id: "test_user_2".to_string(),
+                account_id: Some("test_user_2".to_string()),
+                account_name: "Test User 2".to_string(),
+                init_cash: 1000000.0,
+                account_type: AccountType::Individual,
+            })
+            .unwrap();
+    }
+
+    fn limit_req(account: &str, direction: &str, price: f64) -> SubmitOrderRequest {
+        SubmitOrderRequest {
+            account_id: account.to_string(),
+            instrument_id: "IX2301".to_string(),
+            direction: direction.to_string(),
+            offset: "OPEN".to_string(),
+            volume: 1.0,
+            price,
+            order_type: "LIMIT".to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// 成交时间戳全链路一致：成交回报 → WAL → 成交记录查询 → 行情最近成交/K线
+    #[test]
+    fn test_trade_timestamp_precision_end_to_end() {
+        use crate::exchange::trade_gateway::Notification;
+        use crate::market::kline::KLinePeriod;
+        use crate::market::MarketDataService;
+        use crate::storage::wal::manager::WalManager;
+        use crate::storage::wal::record::WalRecord;
+        use crate::utils::timestamp::nanos_to_millis;
+
+        let dir = tempfile::tempdir().unwrap();
+        let wal_root = dir.path().to_string_lossy().to_string();
+        let router = create_test_router_with_gateway(|account_mgr| {
+            TradeGateway::new(account_mgr).with_wal_root(wal_root.clone())
+        });
+        open_counterparty(&router);
+        // 最近成交查询读撮合引擎的成交记录器
+        let recorder = router.matching_engine.get_trade_recorder();
+        let market_data = Arc::new(MarketDataService::new(router.matching_engine.clone()));
+        let bus = router.trade_bus();
+        bus.subscribe_inline("trade_recorder", recorder.clone());
+        bus.subscribe_inline("market_data", market_data.clone());
+        let receiver = router.trade_gateway.subscribe_user("test_user".to_string());
+
+        let sell = router.submit_order(limit_req("test_user_2", "SELL", 120.0));
+        assert!(sell.success, "{:?}", sell.error_message);
+        let buy = router.submit_order(limit_req("test_user", "BUY", 120.0));
+        assert!(buy.success, "{:?}", buy.error_message);
+
+        // 成交回报（纳秒）
+        let trade_ts = receiver
+            .try_iter()
+            .find_map(|n| match n {
+                Notification::Trade(t) => Some(t.timestamp),
+                _ => None,
+            })
+            .expect("trade notification");
+
+        // 存储：合约成交记录与双方账户成交回报写入同一纳秒时间戳
+        let mut stored = Vec::new();
+        WalManager::new(&format!("{}/IX2301", wal_root))
+            .replay(|entry| {
+                if let WalRecord::ExchangeTradeRecord { time, .. } = entry.record {
+                    stored.push(time);
+                }
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(stored, vec![trade_ts, trade_ts]);
+
+        for account_id in ["test_user", "test_user_2"] {
+            let mut responses = Vec::new();
+            WalManager::new(&format!("{}/__ACCOUNT__/{}", wal_root, account_id))
+                .replay(|entry| {
+                    if let WalRecord::ExchangeResponseRecord {
+                        response_type: 2,
+                        timestamp,
+                        ..
+                    } = entry.record
+                    {
+                        responses.push(timestamp);
+                    }
+                    Ok(())
+                })
+                .unwrap();
+            assert_eq!(responses, vec![trade_ts], "{}", account_id);
+        }
+
+        // 查询：成交记录与最近成交不重新取时，精度不丢失
+        let records = recorder.get_trades_by_instrument("IX2301");
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].timestamp, trade_ts);
+        assert_eq!(records[0].buy_user_id, "test_user");
+        assert_eq!(records[0].sell_user_id, "test_user_2");
+        let recent = market_data.get_recent_trades("IX2301", 10).unwrap();
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].timestamp, trade_ts);
+
+        // K线按成交时间（毫秒）归入周期
+        let kline = market_data
+            .get_current_kline("IX2301", KLinePeriod::Min1)
+            .expect("current kline");
+        assert_eq!(
+            kline.timestamp,
+            KLinePeriod::Min1.align_timestamp(nanos_to_millis(trade_ts))
+        );
+    }
+
+    /// 测试账户更新失败时成交事件照常发布：成交记录、行情统计/K线不受影响
+    #[test]
+    fn test_trade_bus_unaffected_by_account_update_failure() {
+        use crate::market::kline::KLinePeriod;
+        use crate::market::MarketDataService;
+
+        // 成交网关使用独立的空账户管理器：每笔成交的账户更新都会失败
+        let dir = tempfile::tempdir().unwrap();
+        let wal_root = dir.path().to_string_lossy().to_string();
+        let router = create_test_router_with_gateway(|_| {
+            TradeGateway::new(Arc::new(AccountManager::new())).with_wal_root(wal_root.clone())
+        });
+        open_counterparty(&router);
+        // 最近成交查询读撮合引擎的成交记录器
+        let recorder = router.matching_engine.get_trade_recorder();
+        let market_data = Arc::new(MarketDataService::new(router.matching_engine.clone()));
+        let bus = router.trade_bus();
+        bus.subscribe_inline("trade_recorder", recorder.clone());
+        bus.subscribe("market_data", market_data.clone()).unwrap();
+
+        let sell = router.submit_order(limit_req("test_user_2", "SELL", 120.0));
+        assert!(sell.success, "{:?}", sell.error_message);
+        let buy = router.submit_order(limit_req("test_user", "BUY", 120.0));
+        assert!(!buy.success, "account update should fail");
+
+        // 成交事件在账户处理之前发布，消费者照常收到
+        assert!(bus.wait_idle(Duration::from_secs(5)));
+        let stats = bus.stats();
+        assert_eq!(stats.published, 1);
+        for consumer in &stats.consumers {
+            assert_eq!(consumer.processed, 1, "{}", consumer.name);
+            assert_eq!(consumer.dropped, 0, "{}", consumer.name);
+        }
+
+        let records = recorder.get_trades_by_instrument("IX2301");
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].price, 120.0);
+        let recent = market_data.get_recent_trades("IX2301", 10).unwrap();
+        assert_eq!(recent.len(), 1);
+        let kline = market_data
+            .get_current_kline("IX2301", KLinePeriod::Min1)
+            .expect("current kline");
+        assert_eq!(kline.close, 120.0);
+        assert_eq!(kline.volume, 1);
+    }
+
+    /// 冻结明细按挂单拆分，合计与账户总冻结一致，撤单后实时更新
+    #[test]
+    fn test_frozen_breakdown_splits_by_order() {
+        use crate::exchange::FrozenKind;
+
+        let router = create_test_router();
+        let frozen_total = || {
+            router
+                .account_mgr
+                .get_qifi_slice("test_user")
+                .unwrap()
+                .accounts
+                .frozen_margin
+        };
+
+        let buy_1 = router.submit_order(limit_req("test_user", "BUY", 100.0));
+        let buy_2 = router.submit_order(SubmitOrderRequest {
+            volume: 2.0,
+            ..limit_req("test_user", "BUY", 100.0)
+        });
+        let sell = router.submit_order(limit_req("test_user", "SELL", 115.0));
+        let order_ids: Vec<String> = [&buy_1, &buy_2, &sell]
+            .iter()
+            .map(|resp| resp.order_id.clone().expect("order accepted"))
+            .collect();
+
+        let breakdown = router.frozen_breakdown("test_user").unwrap();
+        assert_eq!(breakdown.items.len(), 3);
+        assert!(breakdown.reconciled, "diff={}", breakdown.diff);
+        assert!((breakdown.summary.total - frozen_total()).abs() < 1e-6);
+        assert!(breakdown.summary.other.abs() < 1e-9);
+
+        let item_of = |breakdown: &FrozenBreakdown, order_id: &str| {
+            breakdown
+                .items
+                .iter()
+                .find(|item| item.order.as_ref().map(|o| o.order_id.as_str()) == Some(order_id))
+                .cloned()
+                .expect("item for order")
+        };
+        let item_1 = item_of(&breakdown, &order_ids[0]);
+        let item_2 = item_of(&breakdown, &order_ids[1]);
+        let item_sell = item_of(&breakdown, &order_ids[2]);
+        assert_eq!(item_1.kind, FrozenKind::Order);
+        assert_eq!(item_2.volume, 2.0);
+        assert!(item_1.margin > 0.0);
+        assert!((item_2.margin - 2.0 * item_1.margin).abs() < 1e-6);
+        assert_eq!(item_sell.order.as_ref().unwrap().direction, "SELL");
+        assert_eq!(item_sell.order.as_ref().unwrap().price, 115.0);
+
+        // 撤单后对应明细消失，合计仍与账户一致
+        router
+            .cancel_order(CancelOrderRequest {
+                account_id: "test_user".to_string(),
+                order_id: order_ids[1].clone(),
+            })
+            .unwrap();
+        let after = router.frozen_breakdown("test_user").unwrap();
+        assert_eq!(after.items.len(), 2);
+        assert!(after.reconciled);
+        assert!((after.summary.total - frozen_total()).abs() < 1e-6);
+        assert!((breakdown.summary.total - after.summary.total - item_2.margin).abs() < 1e-6);
+
+        assert!(router.frozen_breakdown("no_such_account").is_err());
+    }
+
+    /// 多账户并发向同一合约下单：按到达序列通道的顺序进入订单簿，撮合时间戳与排队顺序一致
+    #[test]
+    fn test_concurrent_orders_enter_book_in_arrival_order() {
+        let router = Arc::new(create_test_router());
+        let accounts: Vec<String> = (0..5).map(|i| format!("fair_{}", i)).collect();
+        for account in &accounts {
+            router
+                .account_mgr
+                .open_account(OpenAccountRequest {
+                    user_id: account.clone(),
+                    account_id: Some(account.clone()),
+                    account_name: account.clone(),
+                    init_cash: 1000000.0,
+                    account_type: AccountType::Individual,
+                })
+                .unwrap();
+        }
+
+        let sequencer = router.matching_engine.order_sequencer();
+        let waiting = || {
+            sequencer
+                .stats()
+                .iter()
+                .find(|lane| lane.instrument_id == "IX2301")
+                .map_or(0, |lane| lane.waiting)
+        };
+
+        // 占住序列通道，逐个放入下单线程并确认其已排队，到达顺序即账户顺序
+        let held = sequencer.enter("IX2301", || router.clock.now_nanos());
+        let mut handles = Vec::new();
+        for (i, account) in accounts.iter().enumerate() {
+            let router = router.clone();
+            let account = account.clone();
+            handles.push(std::thread::spawn(move || {
+                router.submit_order(SubmitOrderRequest {
+                    account_id: account,
+                    instrument_id: "IX2301".to_string(),
+                    direction: "BUY".to_string(),
+                    offset: "OPEN".to_string(),
+                    volume: 1.0,
+                    price: 110.0,
+                    order_type: "LIMIT".to_string(),
+                    ..Default::default()
+                })
+            }));
+
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while waiting() < i as u64 + 1 {
+                assert!(
+                    Instant::now() < deadline,
+                    "order {} never reached the sequencer",
+                    i
+                );
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        }
+        drop(held);
+        for handle in handles {
+            assert!(handle.join().unwrap().success);
+        }
+
+        let orderbook = router.matching_engine.get_orderbook("IX2301").unwrap();
+        let queue: Vec<(String, i64)> = resting_orders(&orderbook.read())
+            .into_iter()
+            .filter(|o| o.direction == "BUY" && (o.price - 110.0).abs() < 1e-9)
+            .map(|o| {
+                let order_id = router
+                    .engine_id_to_order
+                    .get(&o.engine_order_id)
+                    .unwrap()
+                    .clone();
+                let info = router.orders.get(&order_id).unwrap().read().clone();
+                (info.order.user_id.clone(), info.update_time)
+            })
+            .collect();
+
+        let queued_accounts: Vec<String> =
+            queue.iter().map(|(account, _)| account.clone()).collect();
+        assert_eq!(queued_accounts, accounts);
+        assert!(queue.windows(2).all(|w| w[0].1 <= w[1].1));
+        assert_eq!(waiting(), 0);
+    }
+
+    #[test]
+    fn test_cancel_waits_behind_earlier_submit() {
+        let router = Arc::new(create_test_router());
+        for account in ["seq_seller", "seq_buyer"] {
+            router
+                .account_mgr
+                .open_account(OpenAccountRequest {
+                    user_id: account.to_string(),
+                    account_id: Some(account.to_string()),
+                    account_name: account.to_string(),
+                    init_cash: 1000000.0,
+                    account_type: AccountType::Individual,
+                })
+                .unwrap();
+        }
+
+        let resting = router.submit_order(SubmitOrderRequest {
+            account_id: "seq_seller".to_string(),
+            instrument_id: "IX2301".to_string(),
+            direction: "SELL".to_string(),
+            offset: "OPEN".to_string(),
+            volume: 1.0,
+            price: 100.0,
+            order_type: "LIMIT".to_string(),
+            ..Default::default()
+        });
+        assert!(resting.success);
+        let resting_id = resting.order_id.unwrap();
+
+        let sequencer = router.matching_engine.order_sequencer();
+        let wait_for = |expected: u64| {
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while sequencer
+                .stats()
+                .iter()
+                .find(|lane| lane.instrument_id == "IX2301")
+                .map_or(0, |lane| lane.waiting)
+                < expected
+            {
+                assert!(
+                    Instant::now() < deadline,
+                    "request never reached the sequencer"
+                );
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        };
+
+        // 先到的对手单与后到的撤单都须在序列通道排队，放行后按到达顺序处理
+        let held = sequencer.enter("IX2301", || router.clock.now_nanos());
+        let submit = {
+            let router = router.clone();
+            std::thread::spawn(move || {
+                router.submit_order(SubmitOrderRequest {
+                    account_id: "seq_buyer".to_string(),
+                    instrument_id: "IX2301".to_string(),
+                    direction: "BUY".to_string(),
+                    offset: "OPEN".to_string(),
+                    volume: 1.0,
+                    price: 100.0,
+                    order_type: "LIMIT".to_string(),
+                    ..Default::default()
+                })
+            })
+        };
+        wait_for(1);
+        let cancel = {
+            let router = router.clone();
+            let order_id = resting_id.clone();
+            std::thread::spawn(move || {
+                router.cancel_order(CancelOrderRequest {
+                    account_id: "seq_seller".to_string(),
+                    order_id,
+                })
+            })
+        };
+        wait_for(2);
+        assert_eq!(
+            router.get_order_status(&resting_id),
+            Some(OrderStatus::Submitted)
+        );
+        drop(held);
+
+        assert!(submit.join().unwrap().success);
+        assert!(cancel.join().unwrap().is_err());
+        assert_eq!(
+            router.get_order_status(&resting_id),
+            Some(OrderStatus::Filled)
+        );
+    }
 }
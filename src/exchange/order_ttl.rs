@@ -0,0 +1,217 @@
+//! 订单存活时长 (TTL)
+//! @yutiansut @quantaxis
+//!
+//! 下单时携带 `ttl_secs`，挂单到期仍未完全成交则自动撤销剩余部分（发撤单回报、释放冻结）。
+//! - 按到期时间排序的小顶堆管理大量 TTL 订单，扫描只弹出已到期的堆顶，O(log n)
+//! - 订单成交/撤单/拒绝离开订单簿时从索引中移除，堆中残留项在弹出时惰性丢弃
+//! - 到期撤单与用户手动撤单竞态：撤单以订单簿为准，先到者生效，后到者失败被忽略
+//! - 配置落盘目录后 TTL 记录随扫描批量写入 `order_ttl.json`，重启恢复订单后仍按原到期时间撤销
+
+use dashmap::DashMap;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::ExchangeError;
+
+/// TTL 落盘文件名
+pub const ORDER_TTL_FILE: &str = "order_ttl.json";
+
+/// 带 TTL 的订单
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OrderTtlEntry {
+    pub order_id: String,
+    pub account_id: String,
+    pub instrument_id: String,
+    pub ttl_secs: u64,
+    /// 到期时间（毫秒）
+    pub expire_at: i64,
+}
+
+/// 订单 TTL 管理器
+#[derive(Default)]
+pub struct OrderTtlManager {
+    /// (到期时间, 订单ID) 小顶堆
+    heap: Mutex<BinaryHeap<Reverse<(i64, String)>>>,
+    /// 有效 TTL 索引 (order_id -> entry)
+    entries: DashMap<String, OrderTtlEntry>,
+    /// 落盘文件（None 表示仅内存）
+    persist_path: Option<PathBuf>,
+    dirty: AtomicBool,
+}
+
+impl OrderTtlManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 落盘到指定目录，并加载上次保存的 TTL 记录
+    pub fn with_persist_dir(dir: impl AsRef<Path>) -> Result<Self, ExchangeError> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir).map_err(|e| {
+            ExchangeError::StorageError(format!("Create order TTL dir failed: {}", e))
+        })?;
+        let path = dir.join(ORDER_TTL_FILE);
+
+        let manager = Self {
+            persist_path: Some(path.clone()),
+            ..Default::default()
+        };
+        if let Ok(content) = std::fs::read_to_string(&path) {
+            let entries: Vec<OrderTtlEntry> = serde_json::from_str(&content).map_err(|e| {
+                ExchangeError::StorageError(format!("Parse order TTL file failed: {}", e))
+            })?;
+            log::info!("Loaded {} order TTL entries", entries.len());
+            for entry in entries {
+                manager.schedule(entry);
+            }
+            manager.dirty.store(false, Ordering::SeqCst);
+        }
+        Ok(manager)
+    }
+
+    /// 登记订单 TTL（同一订单重复登记以最后一次为准）
+    pub fn schedule(&self, entry: OrderTtlEntry) {
+        self.heap
+            .lock()
+            .push(Reverse((entry.expire_at, entry.order_id.clone())));
+        self.entries.insert(entry.order_id.clone(), entry);
+        self.dirty.store(true, Ordering::SeqCst);
+    }
+
+    /// 订单离开订单簿（成交/撤单/拒绝）时移除
+    pub fn remove(&self, order_id: &str) -> Option<OrderTtlEntry> {
+        let removed = self.entries.remove(order_id).map(|(_, entry)| entry);
+        if removed.is_some() {
+            self.dirty.store(true, Ordering::SeqCst);
+        }
+        removed
+    }
+
+    pub fn get(&self, order_id: &str) -> Option<OrderTtlEntry> {
+        self.entries.get(order_id).map(|entry| entry.clone())
+    }
+
+    /// 有效 TTL 订单数
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 弹出 `now_ms` 前到期的订单（同时从索引移除），按到期时间升序
+    pub fn pop_expired(&self, now_ms: i64) -> Vec<OrderTtlEntry> {
+        let mut expired = Vec::new();
+        let mut heap = self.heap.lock();
+        while heap
+            .peek()
+            .map_or(false, |Reverse((expire_at, _))| *expire_at <= now_ms)
+        {
+            let Some(Reverse((expire_at, order_id))) = heap.pop() else {
+                break;
+            };
+            // 已移除或重新登记过的残留项直接丢弃
+            if let Some((_, entry)) = self
+                .entries
+                .remove_if(&order_id, |_, entry| entry.expire_at == expire_at)
+            {
+                expired.push(entry);
+            }
+        }
+        if !expired.is_empty() {
+            self.dirty.store(true, Ordering::SeqCst);
+        }
+        expired
+    }
+
+    /// 将有变更的 TTL 记录写盘（未配置落盘或无变更时跳过）
+    pub fn flush(&self) -> Result<(), ExchangeError> {
+        let Some(ref path) = self.persist_path else {
+            return Ok(());
+        };
+        if !self.dirty.swap(false, Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        let mut entries: Vec<OrderTtlEntry> =
+            self.entries.iter().map(|entry| entry.clone()).collect();
+        entries.sort_by(|a, b| a.expire_at.cmp(&b.expire_at));
+        let result = serde_json::to_string(&entries)
+            .map_err(|e| ExchangeError::StorageError(format!("Serialize order TTL failed: {}", e)))
+            .and_then(|content| {
+                let tmp = path.with_extension("json.tmp");
+                std::fs::write(&tmp, content)
+                    .and_then(|_| std::fs::rename(&tmp, path))
+                    .map_err(|e| {
+                        ExchangeError::StorageError(format!("Write order TTL file failed: {}", e))
+                    })
+            });
+        if result.is_err() {
+            self.dirty.store(true, Ordering::SeqCst);
+        }
+        result
+    }
+
+    /// 清空全部 TTL（交易日重置等场景）
+    pub fn clear(&self) {
+        self.heap.lock().clear();
+        self.entries.clear();
+        self.dirty.store(true, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(order_id: &str, expire_at: i64) -> OrderTtlEntry {
+        OrderTtlEntry {
+            order_id: order_id.to_string(),
+            account_id: "acc".to_string(),
+            instrument_id: "IF2501".to_string(),
+            ttl_secs: 1,
+            expire_at,
+        }
+    }
+
+    #[test]
+    fn test_pop_expired_in_order_and_persist() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = OrderTtlManager::with_persist_dir(dir.path()).unwrap();
+        manager.schedule(entry("o3", 300));
+        manager.schedule(entry("o1", 100));
+        manager.schedule(entry("o2", 200));
+        manager.schedule(entry("o4", 400));
+
+        // 已离开订单簿的订单不再到期；重新登记以最后一次为准
+        manager.remove("o2");
+        manager.schedule(entry("o3", 500));
+        assert_eq!(manager.len(), 3);
+
+        let expired: Vec<String> = manager
+            .pop_expired(350)
+            .into_iter()
+            .map(|e| e.order_id)
+            .collect();
+        assert_eq!(expired, vec!["o1"]);
+        assert!(manager.pop_expired(350).is_empty());
+
+        // 落盘后重新加载，剩余 TTL 仍生效
+        manager.flush().unwrap();
+        let restored = OrderTtlManager::with_persist_dir(dir.path()).unwrap();
+        assert_eq!(restored.len(), 2);
+        assert_eq!(restored.get("o3").unwrap().expire_at, 500);
+        let expired: Vec<String> = restored
+            .pop_expired(1000)
+            .into_iter()
+            .map(|e| e.order_id)
+            .collect();
+        assert_eq!(expired, vec!["o4", "o3"]);
+        assert!(restored.is_empty());
+    }
+}
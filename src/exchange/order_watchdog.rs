@@ -0,0 +1,309 @@
+//! 在途订单看门狗
+//! @yutiansut @quantaxis
+//!
+//! 订单冻结资金后发往撮合引擎，正常情况下同一次调用内即收到 Accepted / 成交 / 拒绝回应；
+//! 回应丢失或撮合处理 panic 时订单会卡在 `PendingRoute`，冻结资金一直不释放：
+//! - 路由器发往撮合前登记发送时间，收到任一回应即移除
+//! - 超过 `ack_timeout_ms` 仍未回应的订单标记为可疑，向撮合引擎查询订单
+//!   （`ExchangeMatchingEngine::query_order`）：订单簿中存在则补登记为已报，
+//!   确认不存在则自动拒单、释放冻结并告警
+//! - 周期性全量对账：路由器在途集合（已报/部分成交）与撮合引擎挂单集合比对，输出差异
+//!
+//! 超时阈值应远大于正常撮合耗时，避免把仍在处理中的订单误判为丢失
+
+use dashmap::DashMap;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+
+use crate::observability::metrics::{ORDER_ACK_TIMEOUT_TOTAL, ORDER_RECONCILE_DIFFS};
+use crate::ExchangeError;
+
+fn default_ack_timeout_ms() -> i64 {
+    3_000
+}
+
+fn default_check_interval_ms() -> u64 {
+    500
+}
+
+fn default_reconcile_interval_secs() -> u64 {
+    60
+}
+
+/// 看门狗配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderWatchdogConfig {
+    /// 是否启用
+    #[serde(default)]
+    pub enabled: bool,
+    /// 撮合回应超时（毫秒），超时未回应的订单标记为可疑
+    #[serde(default = "default_ack_timeout_ms")]
+    pub ack_timeout_ms: i64,
+    /// 超时检查间隔（毫秒）
+    #[serde(default = "default_check_interval_ms")]
+    pub check_interval_ms: u64,
+    /// 全量对账间隔（秒），0 表示不做周期对账
+    #[serde(default = "default_reconcile_interval_secs")]
+    pub reconcile_interval_secs: u64,
+}
+
+impl Default for OrderWatchdogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ack_timeout_ms: default_ack_timeout_ms(),
+            check_interval_ms: default_check_interval_ms(),
+            reconcile_interval_secs: default_reconcile_interval_secs(),
+        }
+    }
+}
+
+impl OrderWatchdogConfig {
+    pub fn validate(&self) -> Result<(), ExchangeError> {
+        if self.ack_timeout_ms <= 0 {
+            return Err(ExchangeError::InvalidParameter(
+                "Order watchdog ack_timeout_ms must be greater than 0".to_string(),
+            ));
+        }
+        if self.check_interval_ms == 0 {
+            return Err(ExchangeError::InvalidParameter(
+                "Order watchdog check_interval_ms must be greater than 0".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// 已发往撮合、等待回应的订单
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingAck {
+    pub order_id: String,
+    pub account_id: String,
+    pub instrument_id: String,
+    /// 发往撮合的时间（毫秒）
+    pub sent_at: i64,
+}
+
+/// 可疑订单的处理结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AckResolution {
+    /// 检查时已收到回应（或订单已不存在），无需处理
+    Acknowledged,
+    /// 撮合引擎中存在该订单，补登记为已报
+    Recovered,
+    /// 撮合引擎中不存在该订单，已拒单并释放冻结
+    Rejected,
+}
+
+/// 路由器与撮合引擎对账结果
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReconcileReport {
+    /// 对账时间（毫秒）
+    pub checked_at: i64,
+    /// 路由器在途订单数（已报/部分成交）
+    pub router_orders: usize,
+    /// 撮合引擎挂单数
+    pub engine_orders: usize,
+    /// 路由器认为在订单簿中、撮合引擎不存在的订单
+    pub missing_in_engine: Vec<String>,
+    /// 撮合引擎中存在、路由器无对应在途订单的挂单 (合约, 撮合引擎订单ID)
+    pub unknown_in_router: Vec<(String, u64)>,
+    /// 剩余量不一致 (订单ID, 路由器剩余量, 撮合引擎剩余量)
+    pub volume_mismatch: Vec<(String, f64, f64)>,
+}
+
+impl ReconcileReport {
+    /// 差异总数
+    pub fn diff_count(&self) -> usize {
+        self.missing_in_engine.len() + self.unknown_in_router.len() + self.volume_mismatch.len()
+    }
+
+    pub fn is_consistent(&self) -> bool {
+        self.diff_count() == 0
+    }
+}
+
+/// 看门狗统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OrderWatchdogStats {
+    /// 当前等待回应的订单数
+    pub pending: usize,
+    /// 累计超时的可疑订单数
+    pub suspicious_total: u64,
+    /// 累计补登记数
+    pub recovered_total: u64,
+    /// 累计自动拒单数
+    pub rejected_total: u64,
+    /// 最近一次对账结果
+    pub last_reconcile: Option<ReconcileReport>,
+}
+
+/// 在途订单看门狗
+pub struct OrderWatchdog {
+    config: OrderWatchdogConfig,
+    /// 等待回应的订单 (order_id -> PendingAck)
+    pending: DashMap<String, PendingAck>,
+    suspicious_total: AtomicU64,
+    recovered_total: AtomicU64,
+    rejected_total: AtomicU64,
+    /// 上次对账时间（毫秒）
+    last_reconcile_at: AtomicI64,
+    last_reconcile: RwLock<Option<ReconcileReport>>,
+}
+
+impl OrderWatchdog {
+    pub fn new(config: OrderWatchdogConfig) -> Result<Self, ExchangeError> {
+        config.validate()?;
+        Ok(Self {
+            config,
+            pending: DashMap::new(),
+            suspicious_total: AtomicU64::new(0),
+            recovered_total: AtomicU64::new(0),
+            rejected_total: AtomicU64::new(0),
+            last_reconcile_at: AtomicI64::new(0),
+            last_reconcile: RwLock::new(None),
+        })
+    }
+
+    pub fn config(&self) -> &OrderWatchdogConfig {
+        &self.config
+    }
+
+    /// 登记发往撮合的订单
+    pub fn track(&self, entry: PendingAck) {
+        self.pending.insert(entry.order_id.clone(), entry);
+    }
+
+    /// 收到撮合回应（Accepted / 成交 / 拒绝）
+    pub fn acknowledge(&self, order_id: &str) {
+        self.pending.remove(order_id);
+    }
+
+    pub fn is_pending(&self, order_id: &str) -> bool {
+        self.pending.contains_key(order_id)
+    }
+
+    /// 截至 `now_ms` 超时未回应的订单（按发送时间升序），不从等待集合中移除
+    pub fn overdue_at(&self, now_ms: i64) -> Vec<PendingAck> {
+        let deadline = now_ms - self.config.ack_timeout_ms;
+        let mut overdue: Vec<PendingAck> = self
+            .pending
+            .iter()
+            .filter(|entry| entry.sent_at <= deadline)
+            .map(|entry| entry.value().clone())
+            .collect();
+        overdue.sort_by_key(|entry| entry.sent_at);
+        overdue
+    }
+
+    /// 记录可疑订单的处理结果
+    pub fn record_resolution(&self, order_id: &str, resolution: AckResolution) {
+        self.pending.remove(order_id);
+        let (counter, outcome) = match resolution {
+            AckResolution::Acknowledged => return,
+            AckResolution::Recovered => (&self.recovered_total, "recovered"),
+            AckResolution::Rejected => (&self.rejected_total, "rejected"),
+        };
+        self.suspicious_total.fetch_add(1, Ordering::Relaxed);
+        counter.fetch_add(1, Ordering::Relaxed);
+        ORDER_ACK_TIMEOUT_TOTAL.with_label_values(&[outcome]).inc();
+    }
+
+    /// 是否到了周期对账时间（到期时记下本次对账时间）
+    pub fn reconcile_due_at(&self, now_ms: i64) -> bool {
+        if self.config.reconcile_interval_secs == 0 {
+            return false;
+        }
+        let interval_ms = (self.config.reconcile_interval_secs as i64).saturating_mul(1000);
+        let last = self.last_reconcile_at.load(Ordering::Relaxed);
+        now_ms - last >= interval_ms
+            && self
+                .last_reconcile_at
+                .compare_exchange(last, now_ms, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+    }
+
+    /// 保存对账结果
+    pub fn record_reconcile(&self, report: ReconcileReport) {
+        ORDER_RECONCILE_DIFFS.set(report.diff_count() as i64);
+        *self.last_reconcile.write() = Some(report);
+    }
+
+    pub fn stats(&self) -> OrderWatchdogStats {
+        OrderWatchdogStats {
+            pending: self.pending.len(),
+            suspicious_total: self.suspicious_total.load(Ordering::Relaxed),
+            recovered_total: self.recovered_total.load(Ordering::Relaxed),
+            rejected_total: self.rejected_total.load(Ordering::Relaxed),
+            last_reconcile: self.last_reconcile.read().clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending(order_id: &str, sent_at: i64) -> PendingAck {
+        PendingAck {
+            order_id: order_id.to_string(),
+            account_id: "acc".to_string(),
+            instrument_id: "IF2501".to_string(),
+            sent_at,
+        }
+    }
+
+    #[test]
+    fn test_overdue_and_resolution() {
+        let watchdog = OrderWatchdog::new(OrderWatchdogConfig {
+            enabled: true,
+            ack_timeout_ms: 3_000,
+            ..Default::default()
+        })
+        .unwrap();
+        watchdog.track(pending("O2", 2_000));
+        watchdog.track(pending("O1", 1_000));
+        watchdog.track(pending("O3", 5_000));
+
+        assert!(watchdog.overdue_at(3_999).is_empty());
+        let overdue: Vec<String> = watchdog
+            .overdue_at(5_000)
+            .into_iter()
+            .map(|e| e.order_id)
+            .collect();
+        assert_eq!(overdue, vec!["O1".to_string(), "O2".to_string()]);
+
+        // 已收到回应的订单不再超时
+        watchdog.acknowledge("O2");
+        watchdog.record_resolution("O1", AckResolution::Rejected);
+        assert!(watchdog.overdue_at(5_000).is_empty());
+        assert!(watchdog.is_pending("O3"));
+
+        let stats = watchdog.stats();
+        assert_eq!(stats.pending, 1);
+        assert_eq!(stats.suspicious_total, 1);
+        assert_eq!(stats.rejected_total, 1);
+        assert_eq!(stats.recovered_total, 0);
+    }
+
+    #[test]
+    fn test_reconcile_interval() {
+        let watchdog = OrderWatchdog::new(OrderWatchdogConfig {
+            enabled: true,
+            reconcile_interval_secs: 60,
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(watchdog.reconcile_due_at(100_000));
+        assert!(!watchdog.reconcile_due_at(130_000));
+        assert!(watchdog.reconcile_due_at(160_000));
+
+        assert!(OrderWatchdog::new(OrderWatchdogConfig {
+            ack_timeout_ms: 0,
+            ..Default::default()
+        })
+        .is_err());
+    }
+}
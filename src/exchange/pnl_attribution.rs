@@ -0,0 +1,534 @@
+//! 盈亏归因分析
+//! @yutiansut @quantaxis
+//!
+//! 每笔成交记一条盈亏流水（价格、数量、手续费、批次变动），按合约、按交易拆分盈亏：
+//! - **平仓盈亏**: 平仓成交按持仓批次（FIFO/LIFO，遵循今昨仓规则）与开仓成交配对，
+//!   按被平批次开仓价计算，与 [`LotBook`] 的批次平仓盈亏口径一致
+//! - **浮动盈亏**: 当前剩余批次按最新价计算，随行情变化，不受查询时间范围限制
+//! - **手续费分摊**: 开仓手续费按手数分摊到平掉它的平仓成交与剩余持仓，
+//!   单笔交易净盈亏 = 平仓盈亏 + 浮动盈亏 - 分摊手续费；合约手续费为范围内实际发生额
+//!
+//! 流水仅保存在内存中（与批次台账一致），重启后从新成交开始累计。
+
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::exchange::position_lots::{LotBook, LotClose, LotSide};
+
+/// 单笔成交的盈亏流水
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PnlFill {
+    pub trade_id: String,
+    pub instrument_id: String,
+    pub direction: String,
+    pub offset: String,
+    pub price: f64,
+    pub volume: f64,
+    pub commission: f64,
+    /// 成交时间（毫秒）
+    pub trade_time: i64,
+    /// 开仓成交新增的批次
+    pub opened_lot_id: Option<u64>,
+    /// 平仓成交消耗的批次
+    pub closes: Vec<LotClose>,
+}
+
+/// 合约最新价与乘数（计算浮动盈亏）
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct InstrumentMark {
+    pub price: f64,
+    pub multiplier: f64,
+}
+
+/// 归因查询条件
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct PnlAttributionQuery {
+    /// 起始时间（毫秒，含）
+    pub start: Option<i64>,
+    /// 结束时间（毫秒，含）
+    pub end: Option<i64>,
+    /// 盈利/亏损排行数量
+    pub top: usize,
+}
+
+impl Default for PnlAttributionQuery {
+    fn default() -> Self {
+        Self {
+            start: None,
+            end: None,
+            top: 5,
+        }
+    }
+}
+
+/// 开平仓配对明细
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PnlPair {
+    /// 开仓成交（批次来自成交前已有持仓时为 None）
+    pub open_trade_id: Option<String>,
+    pub lot_id: u64,
+    pub open_price: f64,
+    pub close_price: f64,
+    pub volume: f64,
+    pub profit: f64,
+}
+
+/// 单笔交易盈亏
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TradePnlAttribution {
+    pub trade_id: String,
+    pub instrument_id: String,
+    pub direction: String,
+    pub offset: String,
+    pub price: f64,
+    pub volume: f64,
+    pub trade_time: i64,
+    /// 本笔成交实际手续费
+    pub commission: f64,
+    /// 平仓盈亏（平仓成交）
+    pub realized_pnl: f64,
+    /// 剩余持仓的浮动盈亏（开仓成交）
+    pub floating_pnl: f64,
+    /// 剩余持仓手数（开仓成交）
+    pub remaining_volume: f64,
+    /// 分摊到本笔交易的手续费
+    pub allocated_commission: f64,
+    pub net_pnl: f64,
+    /// 平仓配对明细
+    pub pairs: Vec<PnlPair>,
+}
+
+/// 单合约盈亏
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InstrumentPnlAttribution {
+    pub instrument_id: String,
+    pub realized_pnl: f64,
+    pub floating_pnl: f64,
+    pub commission: f64,
+    pub net_pnl: f64,
+    pub trade_count: usize,
+    pub long_volume: f64,
+    pub short_volume: f64,
+    /// 计算浮动盈亏使用的最新价（无行情时为 None，浮动盈亏记 0）
+    pub last_price: Option<f64>,
+}
+
+/// 账户盈亏归因
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AccountPnlAttribution {
+    pub account_id: String,
+    pub start: Option<i64>,
+    pub end: Option<i64>,
+    pub realized_pnl: f64,
+    pub floating_pnl: f64,
+    pub commission: f64,
+    pub net_pnl: f64,
+    /// 按合约拆分（净盈亏降序）
+    pub instruments: Vec<InstrumentPnlAttribution>,
+    /// 按交易拆分（成交时间升序）
+    pub trades: Vec<TradePnlAttribution>,
+    pub top_profit_instruments: Vec<InstrumentPnlAttribution>,
+    pub top_loss_instruments: Vec<InstrumentPnlAttribution>,
+    pub top_profit_trades: Vec<TradePnlAttribution>,
+    pub top_loss_trades: Vec<TradePnlAttribution>,
+}
+
+/// 盈亏流水台账 (account_id -> 成交流水)
+#[derive(Default)]
+pub struct PnlLedger {
+    fills: DashMap<String, Vec<PnlFill>>,
+}
+
+impl PnlLedger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录一笔成交
+    pub fn record(&self, account_id: &str, fill: PnlFill) {
+        self.fills
+            .entry(account_id.to_string())
+            .or_default()
+            .push(fill);
+    }
+
+    /// 账户全部成交流水
+    pub fn fills(&self, account_id: &str) -> Vec<PnlFill> {
+        self.fills
+            .get(account_id)
+            .map(|f| f.clone())
+            .unwrap_or_default()
+    }
+
+    /// 移除账户流水（销户）
+    pub fn remove_account(&self, account_id: &str) {
+        self.fills.remove(account_id);
+    }
+
+    /// 计算账户盈亏归因
+    ///
+    /// `lots` 提供当前剩余批次，`marks` 提供各合约最新价与乘数
+    pub fn attribution(
+        &self,
+        account_id: &str,
+        lots: &LotBook,
+        marks: &HashMap<String, InstrumentMark>,
+        query: &PnlAttributionQuery,
+    ) -> AccountPnlAttribution {
+        let mut fills = self.fills(account_id);
+        fills.sort_by_key(|f| f.trade_time);
+        let in_range = |time: i64| {
+            query.start.map_or(true, |start| time >= start)
+                && query.end.map_or(true, |end| time <= end)
+        };
+
+        // 批次 -> (开仓成交, 每手手续费)
+        let mut lot_origins: HashMap<u64, (String, f64)> = HashMap::new();
+        for fill in &fills {
+            if let Some(lot_id) = fill.opened_lot_id {
+                let per_volume = if fill.volume > 0.0 {
+                    fill.commission / fill.volume
+                } else {
+                    0.0
+                };
+                lot_origins.insert(lot_id, (fill.trade_id.clone(), per_volume));
+            }
+        }
+
+        // 当前剩余批次的浮动盈亏
+        let mut instruments: BTreeMap<String, InstrumentPnlAttribution> = BTreeMap::new();
+        let mut open_lots: HashMap<u64, (f64, f64)> = HashMap::new(); // lot_id -> (剩余手数, 浮动盈亏)
+        for view in lots.lots(account_id, None) {
+            let mark = marks.get(&view.instrument_id);
+            let entry = instruments
+                .entry(view.instrument_id.clone())
+                .or_insert_with(|| InstrumentPnlAttribution {
+                    instrument_id: view.instrument_id.clone(),
+                    ..Default::default()
+                });
+            entry.long_volume = view.long_volume;
+            entry.short_volume = view.short_volume;
+            entry.last_price = mark.map(|m| m.price);
+
+            for lot in &view.lots {
+                let floating = mark.map_or(0.0, |m| {
+                    let diff = match lot.side {
+                        LotSide::Long => m.price - lot.price,
+                        LotSide::Short => lot.price - m.price,
+                    };
+                    diff * lot.volume * m.multiplier
+                });
+                entry.floating_pnl += floating;
+                open_lots.insert(lot.lot_id, (lot.volume, floating));
+            }
+        }
+
+        // 逐笔交易：平仓配对、剩余持仓浮盈、手续费分摊
+        let mut trades = Vec::new();
+        for fill in fills.into_iter().filter(|f| in_range(f.trade_time)) {
+            let entry = instruments
+                .entry(fill.instrument_id.clone())
+                .or_insert_with(|| InstrumentPnlAttribution {
+                    instrument_id: fill.instrument_id.clone(),
+                    ..Default::default()
+                });
+
+            let pairs: Vec<PnlPair> = fill
+                .closes
+                .iter()
+                .map(|close| PnlPair {
+                    open_trade_id: lot_origins.get(&close.lot_id).map(|o| o.0.clone()),
+                    lot_id: close.lot_id,
+                    open_price: close.open_price,
+                    close_price: close.close_price,
+                    volume: close.volume,
+                    profit: close.profit,
+                })
+                .collect();
+            let realized_pnl: f64 = pairs.iter().map(|p| p.profit).sum();
+            let open_commission: f64 = fill
+                .closes
+                .iter()
+                .map(|close| {
+                    lot_origins
+                        .get(&close.lot_id)
+                        .map_or(0.0, |o| o.1 * close.volume)
+                })
+                .sum();
+
+            let (remaining_volume, floating_pnl) = fill
+                .opened_lot_id
+                .and_then(|lot_id| open_lots.get(&lot_id).copied())
+                .unwrap_or((0.0, 0.0));
+            let allocated_commission = match fill.opened_lot_id {
+                Some(lot_id) => lot_origins
+                    .get(&lot_id)
+                    .map_or(0.0, |o| o.1 * remaining_volume),
+                None => fill.commission + open_commission,
+            };
+
+            entry.realized_pnl += realized_pnl;
+            entry.commission += fill.commission;
+            entry.trade_count += 1;
+
+            trades.push(TradePnlAttribution {
+                trade_id: fill.trade_id,
+                instrument_id: fill.instrument_id,
+                direction: fill.direction,
+                offset: fill.offset,
+                price: fill.price,
+                volume: fill.volume,
+                trade_time: fill.trade_time,
+                commission: fill.commission,
+                realized_pnl,
+                floating_pnl,
+                remaining_volume,
+                allocated_commission,
+                net_pnl: realized_pnl + floating_pnl - allocated_commission,
+                pairs,
+            });
+        }
+
+        let mut instruments: Vec<InstrumentPnlAttribution> = instruments
+            .into_values()
+            .map(|mut i| {
+                i.net_pnl = i.realized_pnl + i.floating_pnl - i.commission;
+                i
+            })
+            .collect();
+        instruments.sort_by(|a, b| b.net_pnl.total_cmp(&a.net_pnl));
+
+        let mut result = AccountPnlAttribution {
+            account_id: account_id.to_string(),
+            start: query.start,
+            end: query.end,
+            realized_pnl: instruments.iter().map(|i| i.realized_pnl).sum(),
+            floating_pnl: instruments.iter().map(|i| i.floating_pnl).sum(),
+            commission: instruments.iter().map(|i| i.commission).sum(),
+            top_profit_instruments: top_by(&instruments, query.top, |i| i.net_pnl, true),
+            top_loss_instruments: top_by(&instruments, query.top, |i| i.net_pnl, false),
+            top_profit_trades: top_by(&trades, query.top, |t| t.net_pnl, true),
+            top_loss_trades: top_by(&trades, query.top, |t| t.net_pnl, false),
+            instruments,
+            trades,
+            ..Default::default()
+        };
+        result.net_pnl = result.realized_pnl + result.floating_pnl - result.commission;
+        result
+    }
+}
+
+/// 盈利（`profit = true`，净盈亏 > 0 降序）或亏损（净盈亏 < 0 升序）排行
+fn top_by<T: Clone>(items: &[T], top: usize, key: impl Fn(&T) -> f64, profit: bool) -> Vec<T> {
+    let mut ranked: Vec<&T> = items
+        .iter()
+        .filter(|item| {
+            if profit {
+                key(item) > 0.0
+            } else {
+                key(item) < 0.0
+            }
+        })
+        .collect();
+    ranked.sort_by(|a, b| {
+        let ord = key(a).total_cmp(&key(b));
+        if profit {
+            ord.reverse()
+        } else {
+            ord
+        }
+    });
+    ranked.into_iter().take(top).cloned().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::position_cost::{towards_of, PositionCost};
+
+    fn approx(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    /// 按成交网关的顺序更新批次台账并记录流水
+    struct TestAccount {
+        ledger: PnlLedger,
+        lots: LotBook,
+        costs: HashMap<String, PositionCost>,
+    }
+
+    impl TestAccount {
+        fn new() -> Self {
+            Self {
+                ledger: PnlLedger::new(),
+                lots: LotBook::new(),
+                costs: HashMap::new(),
+            }
+        }
+
+        #[allow(clippy::too_many_arguments)]
+        fn trade(
+            &mut self,
+            trade_id: &str,
+            instrument_id: &str,
+            direction: &str,
+            offset: &str,
+            price: f64,
+            volume: f64,
+            multiplier: f64,
+            commission: f64,
+            trade_time: i64,
+        ) {
+            let towards = towards_of(direction, offset).unwrap();
+            let cost = self.costs.entry(instrument_id.to_string()).or_default();
+            let result = self.lots.apply_trade_detail(
+                "acc",
+                instrument_id,
+                None,
+                Some(&*cost),
+                towards,
+                price,
+                volume,
+                multiplier,
+                trade_time,
+            );
+            cost.apply(towards, price, volume);
+            self.ledger.record(
+                "acc",
+                PnlFill {
+                    trade_id: trade_id.to_string(),
+                    instrument_id: instrument_id.to_string(),
+                    direction: direction.to_string(),
+                    offset: offset.to_string(),
+                    price,
+                    volume,
+                    commission,
+                    trade_time,
+                    opened_lot_id: result.opened_lot_id,
+                    closes: result.closes,
+                },
+            );
+        }
+
+        fn attribution(&self, if_price: f64, query: PnlAttributionQuery) -> AccountPnlAttribution {
+            let mut marks = HashMap::new();
+            marks.insert(
+                "IF2501".to_string(),
+                InstrumentMark {
+                    price: if_price,
+                    multiplier: 300.0,
+                },
+            );
+            marks.insert(
+                "IC2501".to_string(),
+                InstrumentMark {
+                    price: 6050.0,
+                    multiplier: 200.0,
+                },
+            );
+            self.ledger.attribution("acc", &self.lots, &marks, &query)
+        }
+    }
+
+    fn instrument<'a>(
+        result: &'a AccountPnlAttribution,
+        instrument_id: &str,
+    ) -> &'a InstrumentPnlAttribution {
+        result
+            .instruments
+            .iter()
+            .find(|i| i.instrument_id == instrument_id)
+            .unwrap()
+    }
+
+    fn trade<'a>(result: &'a AccountPnlAttribution, trade_id: &str) -> &'a TradePnlAttribution {
+        result
+            .trades
+            .iter()
+            .find(|t| t.trade_id == trade_id)
+            .unwrap()
+    }
+
+    /// IF 多次开仓后部分平仓（FIFO 配对）、IC 空单开平，盈亏按合约与交易拆分
+    #[test]
+    fn test_multi_instrument_attribution() {
+        let mut account = TestAccount::new();
+        account.trade("t1", "IF2501", "BUY", "OPEN", 3800.0, 2.0, 300.0, 20.0, 1);
+        account.trade("t2", "IF2501", "BUY", "OPEN", 3810.0, 1.0, 300.0, 10.0, 2);
+        account.trade("t3", "IC2501", "SELL", "OPEN", 6000.0, 1.0, 200.0, 12.0, 3);
+        account.trade("t4", "IF2501", "SELL", "CLOSE", 3820.0, 2.0, 300.0, 20.0, 4);
+        account.trade("t5", "IC2501", "BUY", "CLOSE", 6050.0, 1.0, 200.0, 12.0, 5);
+
+        let result = account.attribution(3830.0, PnlAttributionQuery::default());
+
+        // IF: 平 3800×2 @3820 = 12000，剩余 3810×1 @3830 浮盈 6000，手续费 50
+        let if_pnl = instrument(&result, "IF2501");
+        assert!(approx(if_pnl.realized_pnl, 12000.0));
+        assert!(approx(if_pnl.floating_pnl, 6000.0));
+        assert!(approx(if_pnl.commission, 50.0));
+        assert!(approx(if_pnl.net_pnl, 17950.0));
+        assert!(approx(if_pnl.long_volume, 1.0));
+        assert_eq!(if_pnl.trade_count, 3);
+
+        // IC: 空 6000 平 6050 = -10000，手续费 24
+        let ic_pnl = instrument(&result, "IC2501");
+        assert!(approx(ic_pnl.realized_pnl, -10000.0));
+        assert!(approx(ic_pnl.floating_pnl, 0.0));
+        assert!(approx(ic_pnl.net_pnl, -10024.0));
+
+        assert!(approx(result.realized_pnl, 2000.0));
+        assert!(approx(result.commission, 74.0));
+        assert!(approx(result.net_pnl, 7926.0));
+        assert_eq!(result.instruments[0].instrument_id, "IF2501");
+        assert_eq!(result.top_profit_instruments[0].instrument_id, "IF2501");
+        assert_eq!(result.top_loss_instruments[0].instrument_id, "IC2501");
+
+        // 平仓配对到开仓成交，开仓手续费按手数分摊
+        let t4 = trade(&result, "t4");
+        assert_eq!(t4.pairs.len(), 1);
+        assert_eq!(t4.pairs[0].open_trade_id.as_deref(), Some("t1"));
+        assert!(approx(t4.allocated_commission, 40.0));
+        assert!(approx(t4.net_pnl, 11960.0));
+        let t2 = trade(&result, "t2");
+        assert!(approx(t2.remaining_volume, 1.0));
+        assert!(approx(t2.net_pnl, 5990.0));
+        assert!(approx(trade(&result, "t1").net_pnl, 0.0));
+        assert!(approx(trade(&result, "t5").net_pnl, -10024.0));
+        let allocated: f64 = result.trades.iter().map(|t| t.allocated_commission).sum();
+        assert!(approx(allocated, result.commission));
+
+        let top_profit: Vec<&str> = result
+            .top_profit_trades
+            .iter()
+            .map(|t| t.trade_id.as_str())
+            .collect();
+        assert_eq!(top_profit, vec!["t4", "t2"]);
+        assert_eq!(result.top_loss_trades[0].trade_id, "t5");
+
+        // 行情下跌：浮盈转浮亏，平仓盈亏不变
+        let result = account.attribution(3790.0, PnlAttributionQuery::default());
+        let if_pnl = instrument(&result, "IF2501");
+        assert!(approx(if_pnl.realized_pnl, 12000.0));
+        assert!(approx(if_pnl.floating_pnl, -6000.0));
+        assert!(approx(trade(&result, "t2").net_pnl, -6010.0));
+        assert_eq!(result.top_loss_trades[0].trade_id, "t5");
+        assert_eq!(result.top_loss_trades[1].trade_id, "t2");
+
+        // 时间范围只统计范围内成交的平仓盈亏与手续费
+        let result = account.attribution(
+            3830.0,
+            PnlAttributionQuery {
+                start: Some(4),
+                top: 1,
+                ..Default::default()
+            },
+        );
+        assert_eq!(result.trades.len(), 2);
+        let if_pnl = instrument(&result, "IF2501");
+        assert!(approx(if_pnl.realized_pnl, 12000.0));
+        assert!(approx(if_pnl.commission, 20.0));
+        assert_eq!(if_pnl.trade_count, 1);
+        assert_eq!(result.top_profit_trades.len(), 1);
+    }
+}
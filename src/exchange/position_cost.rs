@@ -0,0 +1,511 @@
+//! 持仓均价（加权均价统一实现）
+//! @yutiansut @quantaxis
+//!
+//! 持仓均价只在成交更新持仓时维护，规则统一为：
+//! - **开仓**: 按成交量加权，今仓开仓成本 / 持仓成本累加 `price × volume`
+//! - **平仓**: 按所平部分（今仓/昨仓）的当前均价扣减成本，剩余持仓均价不变
+//! - **今昨分开**: 平今只扣今仓，平仓先平昨仓再平今仓
+//! - **结算**: 今仓并入昨仓，开仓成本保留，持仓成本按结算价重置（逐日盯市）
+//!
+//! 成交回报推送、QIFI 导出、结算单都读取 `QA_Position` 的均价字段，
+//! 成交后由 [`PositionCostBook::apply_trade`] 统一写回，三处口径一致。
+//! 台账内成本不含合约乘数，写回 `QA_Position` 时按 qars 口径乘以合约乘数。
+
+use crate::core::QA_Position;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// 手数比较精度
+const VOLUME_EPSILON: f64 = 1e-9;
+
+/// 单方向（多/空）持仓成本
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct SideCost {
+    pub volume_today: f64,
+    pub volume_his: f64,
+    /// 今仓开仓成本（Σ price × volume）
+    pub open_cost_today: f64,
+    /// 昨仓开仓成本
+    pub open_cost_his: f64,
+    /// 今仓持仓成本
+    pub position_cost_today: f64,
+    /// 昨仓持仓成本（结算后按结算价）
+    pub position_cost_his: f64,
+}
+
+impl SideCost {
+    /// 持仓总量（今仓 + 昨仓）
+    pub fn volume(&self) -> f64 {
+        self.volume_today + self.volume_his
+    }
+
+    /// 开仓均价（无持仓时为 0）
+    pub fn open_price(&self) -> f64 {
+        average(self.open_cost_today + self.open_cost_his, self.volume())
+    }
+
+    /// 持仓均价（逐日盯市口径，无持仓时为 0）
+    pub fn position_price(&self) -> f64 {
+        average(
+            self.position_cost_today + self.position_cost_his,
+            self.volume(),
+        )
+    }
+
+    /// 今仓开仓均价
+    pub fn open_price_today(&self) -> f64 {
+        average(self.open_cost_today, self.volume_today)
+    }
+
+    /// 昨仓开仓均价
+    pub fn open_price_his(&self) -> f64 {
+        average(self.open_cost_his, self.volume_his)
+    }
+
+    /// 开仓（计入今仓）
+    pub fn open(&mut self, price: f64, volume: f64) {
+        if volume <= 0.0 {
+            return;
+        }
+        self.volume_today += volume;
+        self.open_cost_today += price * volume;
+        self.position_cost_today += price * volume;
+    }
+
+    /// 平今仓，返回实际平仓量
+    pub fn close_today(&mut self, volume: f64) -> f64 {
+        reduce(
+            &mut self.volume_today,
+            &mut self.open_cost_today,
+            &mut self.position_cost_today,
+            volume,
+        )
+    }
+
+    /// 平昨仓，返回实际平仓量
+    pub fn close_his(&mut self, volume: f64) -> f64 {
+        reduce(
+            &mut self.volume_his,
+            &mut self.open_cost_his,
+            &mut self.position_cost_his,
+            volume,
+        )
+    }
+
+    /// 平仓（先平昨仓，不足部分平今仓），返回实际平仓量
+    pub fn close(&mut self, volume: f64) -> f64 {
+        let his = self.close_his(volume);
+        his + self.close_today(volume - his)
+    }
+
+    /// 日终结算：今仓并入昨仓，持仓成本按结算价重置
+    pub fn settle(&mut self, settlement_price: Option<f64>) {
+        self.volume_his += self.volume_today;
+        self.open_cost_his += self.open_cost_today;
+        self.position_cost_his += self.position_cost_today;
+        self.volume_today = 0.0;
+        self.open_cost_today = 0.0;
+        self.position_cost_today = 0.0;
+
+        if let Some(price) = settlement_price.filter(|p| *p > 0.0) {
+            self.position_cost_his = price * self.volume_his;
+        }
+    }
+
+    /// 按持仓字段重建（均价已混合，今昨仓按同一均价拆分）
+    fn seed(volume_today: f64, volume_his: f64, open_price: f64, position_price: f64) -> Self {
+        let position_price = if position_price > 0.0 {
+            position_price
+        } else {
+            open_price
+        };
+        Self {
+            volume_today,
+            volume_his,
+            open_cost_today: open_price * volume_today,
+            open_cost_his: open_price * volume_his,
+            position_cost_today: position_price * volume_today,
+            position_cost_his: position_price * volume_his,
+        }
+    }
+
+    fn same_volumes(&self, other: &SideCost) -> bool {
+        (self.volume_today - other.volume_today).abs() < VOLUME_EPSILON
+            && (self.volume_his - other.volume_his).abs() < VOLUME_EPSILON
+    }
+}
+
+/// 单合约持仓成本（多空两个方向）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct PositionCost {
+    pub long: SideCost,
+    pub short: SideCost,
+}
+
+impl PositionCost {
+    /// 按 qars towards 更新成本，返回是否为可识别的期货开平仓方向
+    ///
+    /// towards: 2 买开 / -2 卖开 / 3 买平 / -3 卖平 / 4 买平今 / -4 卖平今
+    pub fn apply(&mut self, towards: i32, price: f64, volume: f64) -> bool {
+        match towards {
+            2 => self.long.open(price, volume),
+            -2 => self.short.open(price, volume),
+            3 => {
+                self.short.close(volume);
+            }
+            -3 => {
+                self.long.close(volume);
+            }
+            4 => {
+                self.short.close_today(volume);
+            }
+            -4 => {
+                self.long.close_today(volume);
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// 按方向/开平字符串更新成本（成交流水重算用）
+    pub fn apply_trade(&mut self, direction: &str, offset: &str, price: f64, volume: f64) -> bool {
+        match towards_of(direction, offset) {
+            Some(towards) => self.apply(towards, price, volume),
+            None => false,
+        }
+    }
+
+    /// 日终结算
+    pub fn settle(&mut self, settlement_price: Option<f64>) {
+        self.long.settle(settlement_price);
+        self.short.settle(settlement_price);
+    }
+
+    /// 从 qars 持仓字段重建成本（台账缺失或与持仓不一致时使用）
+    pub fn from_position(pos: &QA_Position) -> Self {
+        Self {
+            long: SideCost::seed(
+                pos.volume_long_today,
+                pos.volume_long_his,
+                pos.open_price_long,
+                pos.position_price_long,
+            ),
+            short: SideCost::seed(
+                pos.volume_short_today,
+                pos.volume_short_his,
+                pos.open_price_short,
+                pos.position_price_short,
+            ),
+        }
+    }
+
+    /// 今昨仓手数是否一致
+    pub fn same_volumes(&self, other: &PositionCost) -> bool {
+        self.long.same_volumes(&other.long) && self.short.same_volumes(&other.short)
+    }
+
+    /// 均价写回 qars 持仓（成本按 qars 口径乘以合约乘数）
+    pub fn write_to(&self, pos: &mut QA_Position) {
+        let multiplier = if pos.preset.unit_table > 0 {
+            pos.preset.unit_table as f64
+        } else {
+            1.0
+        };
+
+        pos.open_price_long = self.long.open_price();
+        pos.open_cost_long = (self.long.open_cost_today + self.long.open_cost_his) * multiplier;
+        pos.position_price_long = self.long.position_price();
+        pos.position_cost_long =
+            (self.long.position_cost_today + self.long.position_cost_his) * multiplier;
+
+        pos.open_price_short = self.short.open_price();
+        pos.open_cost_short = (self.short.open_cost_today + self.short.open_cost_his) * multiplier;
+        pos.position_price_short = self.short.position_price();
+        pos.position_cost_short =
+            (self.short.position_cost_today + self.short.position_cost_his) * multiplier;
+    }
+}
+
+/// 成交流水（持仓均价重算输入）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CostTrade {
+    pub instrument_id: String,
+    pub direction: String,
+    pub offset: String,
+    pub price: f64,
+    pub volume: f64,
+    /// 交易日（跨日时今仓结转为昨仓）
+    #[serde(default)]
+    pub trading_day: String,
+}
+
+/// 按成交流水重算持仓成本（一次性修复工具）
+///
+/// 成交需按时间顺序排列；交易日变化时今仓结转为昨仓，
+/// 流水中没有结算价，结转后持仓成本沿用开仓成本
+pub fn rebuild_from_trades(trades: &[CostTrade]) -> BTreeMap<String, PositionCost> {
+    let mut costs: BTreeMap<String, PositionCost> = BTreeMap::new();
+    let mut current_day: Option<&str> = None;
+
+    for trade in trades {
+        if current_day.is_some_and(|day| day != trade.trading_day) {
+            for cost in costs.values_mut() {
+                cost.settle(None);
+            }
+        }
+        current_day = Some(&trade.trading_day);
+
+        let cost = costs.entry(trade.instrument_id.clone()).or_default();
+        if !cost.apply_trade(&trade.direction, &trade.offset, trade.price, trade.volume) {
+            log::warn!(
+                "[PositionCost] Skip trade with unknown direction/offset: {} {}/{}",
+                trade.instrument_id,
+                trade.direction,
+                trade.offset
+            );
+        }
+    }
+
+    costs
+}
+
+/// 订单成交均价（按成交量加权）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct FillAverage {
+    pub volume: f64,
+    pub amount: f64,
+}
+
+impl FillAverage {
+    /// 累加一笔成交，返回当前成交均价
+    pub fn add(&mut self, price: f64, volume: f64) -> f64 {
+        self.volume += volume;
+        self.amount += price * volume;
+        self.average()
+    }
+
+    pub fn average(&self) -> f64 {
+        average(self.amount, self.volume)
+    }
+}
+
+/// 持仓成本台账 ((account_id, instrument_id) -> PositionCost)
+#[derive(Default)]
+pub struct PositionCostBook {
+    costs: DashMap<(String, String), PositionCost>,
+}
+
+impl PositionCostBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 成交更新持仓成本（持仓均价唯一维护入口）
+    ///
+    /// `before` 为成交前持仓重建的成本；台账缺失或手数与成交前持仓不一致
+    /// （恢复、导入、外部改仓后的首笔成交）时以 `before` 为基准
+    pub fn apply_trade(
+        &self,
+        account_id: &str,
+        instrument_id: &str,
+        before: Option<PositionCost>,
+        towards: i32,
+        price: f64,
+        volume: f64,
+    ) -> PositionCost {
+        let before = before.unwrap_or_default();
+        let mut entry = self
+            .costs
+            .entry((account_id.to_string(), instrument_id.to_string()))
+            .or_insert(before);
+        if !entry.same_volumes(&before) {
+            log::debug!(
+                "[PositionCost] Ledger out of sync for {}/{}, reseed from position",
+                account_id,
+                instrument_id
+            );
+            *entry = before;
+        }
+        entry.apply(towards, price, volume);
+        *entry
+    }
+
+    /// 查询持仓成本
+    pub fn get(&self, account_id: &str, instrument_id: &str) -> Option<PositionCost> {
+        self.costs
+            .get(&(account_id.to_string(), instrument_id.to_string()))
+            .map(|c| *c)
+    }
+
+    /// 日终结算：账户下所有合约今仓并入昨仓，返回结算后的成本
+    pub fn settle_account<F>(
+        &self,
+        account_id: &str,
+        settlement_price: F,
+    ) -> Vec<(String, PositionCost)>
+    where
+        F: Fn(&str) -> Option<f64>,
+    {
+        let mut settled = Vec::new();
+        for mut entry in self.costs.iter_mut() {
+            if entry.key().0 != account_id {
+                continue;
+            }
+            let instrument_id = entry.key().1.clone();
+            entry.settle(settlement_price(&instrument_id));
+            settled.push((instrument_id, *entry.value()));
+        }
+        settled
+    }
+
+    /// 覆盖账户下某合约的成本（重算工具使用）
+    pub fn insert(&self, account_id: &str, instrument_id: &str, cost: PositionCost) {
+        self.costs
+            .insert((account_id.to_string(), instrument_id.to_string()), cost);
+    }
+
+    /// 移除账户全部成本记录（销户）
+    pub fn remove_account(&self, account_id: &str) {
+        self.costs.retain(|key, _| key.0 != account_id);
+    }
+}
+
+/// 方向/开平字符串 → qars towards
+pub fn towards_of(direction: &str, offset: &str) -> Option<i32> {
+    match (direction, offset) {
+        ("BUY", "OPEN") => Some(2),
+        ("SELL", "OPEN") => Some(-2),
+        ("BUY", "CLOSE") => Some(3),
+        ("SELL", "CLOSE") => Some(-3),
+        ("BUY", "CLOSETODAY") => Some(4),
+        ("SELL", "CLOSETODAY") => Some(-4),
+        _ => None,
+    }
+}
+
+fn average(cost: f64, volume: f64) -> f64 {
+    if volume > VOLUME_EPSILON {
+        cost / volume
+    } else {
+        0.0
+    }
+}
+
+/// 按均价扣减 volume 手，返回实际扣减量
+fn reduce(volume: &mut f64, open_cost: &mut f64, position_cost: &mut f64, close: f64) -> f64 {
+    let closed = close.max(0.0).min(*volume);
+    if closed <= 0.0 {
+        return 0.0;
+    }
+    let remaining = *volume - closed;
+    if remaining <= VOLUME_EPSILON {
+        *volume = 0.0;
+        *open_cost = 0.0;
+        *position_cost = 0.0;
+    } else {
+        let keep = remaining / *volume;
+        *volume = remaining;
+        *open_cost *= keep;
+        *position_cost *= keep;
+    }
+    closed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn approx(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    fn trade(direction: &str, offset: &str, price: f64, volume: f64, day: &str) -> CostTrade {
+        CostTrade {
+            instrument_id: "IF2501".to_string(),
+            direction: direction.to_string(),
+            offset: offset.to_string(),
+            price,
+            volume,
+            trading_day: day.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_open_close_reopen_matches_manual_calculation() {
+        let mut cost = PositionCost::default();
+
+        // 不同价格多次开仓: (3800×2 + 3810×3) / 5 = 3806
+        cost.apply(2, 3800.0, 2.0);
+        cost.apply(2, 3810.0, 3.0);
+        assert!(approx(cost.long.open_price(), 3806.0));
+
+        // 部分平仓不影响均价
+        cost.apply(-4, 3850.0, 1.0);
+        assert!(approx(cost.long.volume(), 4.0));
+        assert!(approx(cost.long.open_price(), 3806.0));
+
+        // 再开仓: (3806×4 + 3790×4) / 8 = 3798
+        cost.apply(2, 3790.0, 4.0);
+        assert!(approx(cost.long.volume(), 8.0));
+        assert!(approx(cost.long.open_price(), 3798.0));
+        assert!(approx(cost.long.position_price(), 3798.0));
+
+        // 全部平仓后均价归零，空头不受影响
+        cost.apply(-4, 3800.0, 8.0);
+        assert!(approx(cost.long.volume(), 0.0));
+        assert!(approx(cost.long.open_price(), 0.0));
+        assert_eq!(cost.short, SideCost::default());
+    }
+
+    #[test]
+    fn test_today_and_his_tracked_separately() {
+        let mut cost = PositionCost::default();
+        cost.apply(-2, 4000.0, 2.0);
+        // 结算：今仓转昨仓，持仓成本按结算价 4010 重置
+        cost.settle(Some(4010.0));
+        assert!(approx(cost.short.volume_his, 2.0));
+        assert!(approx(cost.short.open_price(), 4000.0));
+        assert!(approx(cost.short.position_price(), 4010.0));
+
+        cost.apply(-2, 3980.0, 2.0);
+        assert!(approx(cost.short.open_price_today(), 3980.0));
+        assert!(approx(cost.short.open_price(), 3990.0));
+        assert!(approx(cost.short.position_price(), 3995.0));
+
+        // 平仓先平昨仓：剩余今仓 2 手 @ 3980
+        cost.apply(3, 3970.0, 2.0);
+        assert!(approx(cost.short.volume_his, 0.0));
+        assert!(approx(cost.short.volume_today, 2.0));
+        assert!(approx(cost.short.open_price(), 3980.0));
+        assert!(approx(cost.short.position_price(), 3980.0));
+    }
+
+    #[test]
+    fn test_rebuild_from_trades_and_book_reseed() {
+        let trades = vec![
+            trade("BUY", "OPEN", 3800.0, 2.0, "20250102"),
+            trade("BUY", "OPEN", 3810.0, 3.0, "20250102"),
+            trade("SELL", "CLOSE", 3850.0, 1.0, "20250103"),
+            trade("BUY", "OPEN", 3790.0, 4.0, "20250103"),
+        ];
+        let costs = rebuild_from_trades(&trades);
+        let cost = costs["IF2501"];
+        assert!(approx(cost.long.volume_his, 4.0));
+        assert!(approx(cost.long.volume_today, 4.0));
+        assert!(approx(cost.long.open_price(), 3798.0));
+
+        // 台账与成交前持仓不一致时按持仓重建
+        let book = PositionCostBook::new();
+        book.insert("acc", "IF2501", cost);
+        let mut before = PositionCost::default();
+        before.long.open(3900.0, 1.0);
+        let after = book.apply_trade("acc", "IF2501", Some(before), 2, 3700.0, 1.0);
+        assert!(approx(after.long.volume(), 2.0));
+        assert!(approx(after.long.open_price(), 3800.0));
+
+        let mut fill = FillAverage::default();
+        fill.add(3800.0, 1.0);
+        assert!(approx(fill.add(3803.0, 2.0), 3802.0));
+    }
+}
@@ -0,0 +1,1052 @@
+//! 持仓批次明细（按开仓批次 Lot 管理）
+//! @yutiansut @quantaxis
+//!
+//! 每笔开仓成交记一个批次（价格、数量、时间），平仓按账户配置的方式消耗批次：
+//! - **FIFO**: 先开先平
+//! - **LIFO**: 后开先平
+//!
+//! 平仓顺序遵循交易所今昨仓规则：平今只消耗今仓批次，平仓先消耗昨仓批次，
+//! 同一组内再按 FIFO/LIFO 排序。部分平仓时批次剩余量保留。
+//!
+//! 批次平仓盈亏按被平批次的开仓价计算（会计口径），与逐日盯市的资金平仓盈亏分别统计。
+//! 多空批次独立维护，同时持有多空即为锁仓，展示时给出锁仓量；
+//! 同价、同今昨属性的批次可合并展示。
+//!
+//! 平仓委托可指定批次（`close_lot_id`），指定按委托登记：只有该委托的成交优先消耗指定批次，
+//! 不足部分再按 FIFO/LIFO；委托结束（全部成交、撤单、拒单、过期）即撤销登记。
+//! 开仓明细按最新价逐笔计算浮动盈亏，并把平仓挂单冻结量按平仓顺序分摊到批次得出可平量。
+//! 批次汇总须与 qars 汇总持仓一致：[`LotBook::reconcile`] 列出差异，结算时以汇总持仓为准重建。
+
+use crate::core::QA_Position;
+use crate::exchange::position_cost::{PositionCost, SideCost};
+use crate::ExchangeError;
+use dashmap::DashMap;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+/// 手数比较精度
+const VOLUME_EPSILON: f64 = 1e-9;
+
+/// 批次平仓方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LotMethod {
+    /// 先进先出
+    #[default]
+    Fifo,
+    /// 后进先出
+    Lifo,
+}
+
+/// 持仓方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LotSide {
+    Long,
+    Short,
+}
+
+/// 开仓批次
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PositionLot {
+    pub lot_id: u64,
+    pub side: LotSide,
+    /// 开仓价
+    pub price: f64,
+    /// 剩余数量
+    pub volume: f64,
+    /// 开仓数量
+    pub open_volume: f64,
+    /// 开仓时间（毫秒）
+    pub open_time: i64,
+    /// 是否今仓（结算后转为昨仓）
+    pub today: bool,
+}
+
+impl PositionLot {
+    /// 按开仓价计算的浮动盈亏
+    pub fn float_profit(&self, price: f64, multiplier: f64) -> f64 {
+        let diff = match self.side {
+            LotSide::Long => price - self.price,
+            LotSide::Short => self.price - price,
+        };
+        diff * self.volume * multiplier
+    }
+}
+
+/// 批次明细配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PositionLotsConfig {
+    /// 默认平仓方式（账户未单独配置时使用）
+    #[serde(default)]
+    pub default_method: LotMethod,
+    /// QIFI 导出附加 `lots` 开仓明细数组（兼容开关，默认关闭）
+    #[serde(default)]
+    pub export_in_qifi: bool,
+}
+
+/// 批次平仓明细
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LotClose {
+    pub lot_id: u64,
+    pub side: LotSide,
+    /// 被平批次开仓价
+    pub open_price: f64,
+    pub close_price: f64,
+    pub volume: f64,
+    /// 按批次成本计算的平仓盈亏
+    pub profit: f64,
+}
+
+/// 单笔成交的批次变动
+#[derive(Debug, Clone, Default)]
+pub struct LotTradeResult {
+    /// 开仓新增的批次
+    pub opened_lot_id: Option<u64>,
+    /// 平仓明细
+    pub closes: Vec<LotClose>,
+}
+
+/// 合并展示的批次（同方向、同价、同今昨属性）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MergedLot {
+    pub side: LotSide,
+    pub price: f64,
+    pub volume: f64,
+    pub today: bool,
+    pub lot_count: usize,
+}
+
+/// 单合约批次明细
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InstrumentLots {
+    pub long: Vec<PositionLot>,
+    pub short: Vec<PositionLot>,
+    /// 累计批次平仓盈亏
+    pub close_profit: f64,
+    /// 指定平仓登记（委托号 -> 批次号，委托结束、批次平完或结算后移除）
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub designated: HashMap<String, u64>,
+}
+
+impl InstrumentLots {
+    fn side_mut(&mut self, side: LotSide) -> &mut Vec<PositionLot> {
+        match side {
+            LotSide::Long => &mut self.long,
+            LotSide::Short => &mut self.short,
+        }
+    }
+
+    fn side(&self, side: LotSide) -> &Vec<PositionLot> {
+        match side {
+            LotSide::Long => &self.long,
+            LotSide::Short => &self.short,
+        }
+    }
+
+    /// 按批次号查找
+    pub fn find(&self, lot_id: u64) -> Option<&PositionLot> {
+        self.long
+            .iter()
+            .chain(self.short.iter())
+            .find(|l| l.lot_id == lot_id)
+    }
+
+    /// 方向持仓量
+    pub fn volume(&self, side: LotSide) -> f64 {
+        self.side(side).iter().map(|l| l.volume).sum()
+    }
+
+    /// 锁仓量（同时持有的多空量）
+    pub fn locked_volume(&self) -> f64 {
+        self.volume(LotSide::Long).min(self.volume(LotSide::Short))
+    }
+
+    /// 开仓：新增今仓批次
+    pub fn open(&mut self, lot_id: u64, side: LotSide, price: f64, volume: f64, open_time: i64) {
+        if volume <= 0.0 {
+            return;
+        }
+        self.side_mut(side).push(PositionLot {
+            lot_id,
+            side,
+            price,
+            volume,
+            open_volume: volume,
+            open_time,
+            today: true,
+        });
+    }
+
+    /// 平仓消耗顺序（批次下标）
+    ///
+    /// `priority` 中的批次在前（按给定顺序）；其余昨仓组在前（平今时排除），组内按 FIFO/LIFO
+    fn close_order(
+        &self,
+        side: LotSide,
+        method: LotMethod,
+        close_today: bool,
+        priority: &[u64],
+    ) -> Vec<usize> {
+        let lots = self.side(side);
+        let mut order: Vec<usize> = (0..lots.len())
+            .filter(|&i| !close_today || lots[i].today)
+            .collect();
+        order.sort_by_key(|&i| (lots[i].today, lots[i].open_time, lots[i].lot_id));
+        if method == LotMethod::Lifo {
+            order.sort_by(|&a, &b| {
+                lots[a].today.cmp(&lots[b].today).then(
+                    (lots[b].open_time, lots[b].lot_id).cmp(&(lots[a].open_time, lots[a].lot_id)),
+                )
+            });
+        }
+        if !priority.is_empty() {
+            order.sort_by_key(|&i| {
+                priority
+                    .iter()
+                    .position(|&id| id == lots[i].lot_id)
+                    .unwrap_or(usize::MAX)
+            });
+        }
+        order
+    }
+
+    /// 平仓：按方式消耗批次，返回平仓明细
+    ///
+    /// `close_today` 为 true 时只消耗今仓批次，否则先昨仓后今仓；
+    /// `order_id` 登记了指定批次时优先消耗该批次，其他委托的指定不影响本笔成交
+    #[allow(clippy::too_many_arguments)]
+    pub fn close(
+        &mut self,
+        side: LotSide,
+        volume: f64,
+        close_price: f64,
+        multiplier: f64,
+        method: LotMethod,
+        close_today: bool,
+        order_id: Option<&str>,
+    ) -> Vec<LotClose> {
+        let priority: Vec<u64> = order_id
+            .and_then(|id| self.designated.get(id))
+            .copied()
+            .into_iter()
+            .collect();
+        let order = self.close_order(side, method, close_today, &priority);
+        let lots = self.side_mut(side);
+
+        let mut remaining = volume;
+        let mut closes = Vec::new();
+        for i in order {
+            if remaining <= VOLUME_EPSILON {
+                break;
+            }
+            let lot = &mut lots[i];
+            let closed = lot.volume.min(remaining);
+            if closed <= 0.0 {
+                continue;
+            }
+            lot.volume -= closed;
+            remaining -= closed;
+
+            let diff = match side {
+                LotSide::Long => close_price - lot.price,
+                LotSide::Short => lot.price - close_price,
+            };
+            closes.push(LotClose {
+                lot_id: lot.lot_id,
+                side,
+                open_price: lot.price,
+                close_price,
+                volume: closed,
+                profit: diff * closed * multiplier,
+            });
+        }
+        lots.retain(|l| l.volume > VOLUME_EPSILON);
+        self.prune_designated();
+
+        self.close_profit += closes.iter().map(|c| c.profit).sum::<f64>();
+        closes
+    }
+
+    /// 移除已平完批次的指定
+    fn prune_designated(&mut self) {
+        let (long, short) = (&self.long, &self.short);
+        self.designated
+            .retain(|_, id| long.iter().chain(short.iter()).any(|l| l.lot_id == *id));
+    }
+
+    /// 各批次可平量：平仓挂单冻结量按平仓顺序从批次剩余量中扣除
+    ///
+    /// 挂单中登记了指定批次的先冻结对应批次
+    fn closable_volumes(&self, side: LotSide, method: LotMethod, frozen: f64) -> Vec<f64> {
+        let lots = self.side(side);
+        let mut closable: Vec<f64> = lots.iter().map(|l| l.volume).collect();
+        let mut priority: Vec<u64> = self.designated.values().copied().collect();
+        priority.sort_unstable();
+        priority.dedup();
+        let mut remaining = frozen;
+        for i in self.close_order(side, method, false, &priority) {
+            if remaining <= VOLUME_EPSILON {
+                break;
+            }
+            let taken = closable[i].min(remaining);
+            closable[i] -= taken;
+            remaining -= taken;
+        }
+        closable
+    }
+
+    /// 日终结算：今仓批次转为昨仓，指定平仓随当日委托失效
+    pub fn settle(&mut self) {
+        for lot in self.long.iter_mut().chain(self.short.iter_mut()) {
+            lot.today = false;
+        }
+        self.designated.clear();
+    }
+
+    /// 合并展示：同方向、同价、同今昨属性的批次合并
+    pub fn merged(&self) -> Vec<MergedLot> {
+        let mut merged: Vec<MergedLot> = Vec::new();
+        for lot in self.long.iter().chain(self.short.iter()) {
+            match merged.iter_mut().find(|m| {
+                m.side == lot.side && m.today == lot.today && (m.price - lot.price).abs() < 1e-9
+            }) {
+                Some(m) => {
+                    m.volume += lot.volume;
+                    m.lot_count += 1;
+                }
+                None => merged.push(MergedLot {
+                    side: lot.side,
+                    price: lot.price,
+                    volume: lot.volume,
+                    today: lot.today,
+                    lot_count: 1,
+                }),
+            }
+        }
+        merged
+    }
+
+    fn today_his_volume(&self, side: LotSide) -> (f64, f64) {
+        self.side(side).iter().fold((0.0, 0.0), |(today, his), l| {
+            if l.today {
+                (today + l.volume, his)
+            } else {
+                (today, his + l.volume)
+            }
+        })
+    }
+
+    /// 批次与持仓成本不一致时，按持仓成本重建该方向批次（今/昨各一个汇总批次）
+    fn reseed_side(&mut self, side: LotSide, cost: &SideCost, next_id: &AtomicU64, now: i64) {
+        let (today, his) = self.today_his_volume(side);
+        if (today - cost.volume_today).abs() < VOLUME_EPSILON
+            && (his - cost.volume_his).abs() < VOLUME_EPSILON
+        {
+            return;
+        }
+
+        let lots = self.side_mut(side);
+        lots.clear();
+        for (volume, price, is_today) in [
+            (cost.volume_his, cost.open_price_his(), false),
+            (cost.volume_today, cost.open_price_today(), true),
+        ] {
+            if volume > VOLUME_EPSILON {
+                lots.push(PositionLot {
+                    lot_id: next_id.fetch_add(1, Ordering::SeqCst),
+                    side,
+                    price,
+                    volume,
+                    open_volume: volume,
+                    open_time: now,
+                    today: is_today,
+                });
+            }
+        }
+    }
+}
+
+/// 合约批次明细视图
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PositionLotView {
+    pub instrument_id: String,
+    pub method: LotMethod,
+    pub long_volume: f64,
+    pub short_volume: f64,
+    /// 锁仓量
+    pub locked_volume: f64,
+    /// 累计批次平仓盈亏
+    pub close_profit: f64,
+    pub lots: Vec<PositionLot>,
+    /// 合并展示
+    pub merged: Vec<MergedLot>,
+}
+
+/// 明细计算所需的持仓行情（最新价、合约乘数、平仓挂单冻结量）
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct LotMark {
+    pub price: f64,
+    pub multiplier: f64,
+    pub frozen_long: f64,
+    pub frozen_short: f64,
+}
+
+impl LotMark {
+    /// 取 qars 持仓的最新价、合约乘数与冻结量
+    pub fn from_position(pos: &QA_Position) -> Self {
+        Self {
+            price: pos.lastest_price,
+            multiplier: pos.preset.unit_table.max(1) as f64,
+            frozen_long: pos.volume_long_frozen_today + pos.volume_long_frozen_his,
+            frozen_short: pos.volume_short_frozen_today + pos.volume_short_frozen_his,
+        }
+    }
+}
+
+/// 开仓明细（单个批次）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PositionLotDetail {
+    #[serde(flatten)]
+    pub lot: PositionLot,
+    /// 可平量（剩余量扣除平仓挂单冻结）
+    pub closable_volume: f64,
+    /// 按开仓价计算的逐笔浮动盈亏
+    pub float_profit: f64,
+}
+
+/// 合约开仓明细
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PositionDetailView {
+    pub instrument_id: String,
+    pub method: LotMethod,
+    /// 最新价（无行情时为 0，浮动盈亏记 0）
+    pub last_price: f64,
+    pub long_volume: f64,
+    pub short_volume: f64,
+    /// 逐笔浮动盈亏合计
+    pub float_profit: f64,
+    /// 累计批次平仓盈亏（当日）
+    pub close_profit: f64,
+    pub lots: Vec<PositionLotDetail>,
+}
+
+/// 汇总持仓与批次明细不一致的记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LotMismatch {
+    pub instrument_id: String,
+    pub side: LotSide,
+    /// qars 汇总持仓量（今 + 昨）
+    pub position_volume: f64,
+    /// 批次剩余量合计
+    pub lot_volume: f64,
+}
+
+/// 批次台账 ((account_id, instrument_id) -> InstrumentLots)
+pub struct LotBook {
+    lots: DashMap<(String, String), InstrumentLots>,
+    /// 账户平仓方式（未配置时使用默认方式）
+    methods: DashMap<String, LotMethod>,
+    default_method: RwLock<LotMethod>,
+    lot_seq: AtomicU64,
+    /// QIFI 导出是否附加开仓明细
+    export_in_qifi: AtomicBool,
+}
+
+impl Default for LotBook {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LotBook {
+    pub fn new() -> Self {
+        Self {
+            lots: DashMap::new(),
+            methods: DashMap::new(),
+            default_method: RwLock::new(LotMethod::default()),
+            lot_seq: AtomicU64::new(1),
+            export_in_qifi: AtomicBool::new(false),
+        }
+    }
+
+    /// 应用配置
+    pub fn apply_config(&self, config: &PositionLotsConfig) {
+        self.set_default_method(config.default_method);
+        self.export_in_qifi
+            .store(config.export_in_qifi, Ordering::Relaxed);
+    }
+
+    /// QIFI 导出是否附加开仓明细
+    pub fn export_in_qifi(&self) -> bool {
+        self.export_in_qifi.load(Ordering::Relaxed)
+    }
+
+    /// 设置默认平仓方式
+    pub fn set_default_method(&self, method: LotMethod) {
+        *self.default_method.write() = method;
+    }
+
+    /// 设置账户平仓方式
+    pub fn set_method(&self, account_id: &str, method: LotMethod) {
+        self.methods.insert(account_id.to_string(), method);
+    }
+
+    /// 账户平仓方式
+    pub fn method(&self, account_id: &str) -> LotMethod {
+        self.methods
+            .get(account_id)
+            .map(|m| *m)
+            .unwrap_or_else(|| *self.default_method.read())
+    }
+
+    /// 成交更新批次，返回平仓明细（开仓时为空）
+    ///
+    /// `before` 为成交前的持仓成本，批次缺失或与其不一致时先按其重建
+    #[allow(clippy::too_many_arguments)]
+    pub fn apply_trade(
+        &self,
+        account_id: &str,
+        instrument_id: &str,
+        before: Option<&PositionCost>,
+        towards: i32,
+        price: f64,
+        volume: f64,
+        multiplier: f64,
+        trade_time: i64,
+    ) -> Vec<LotClose> {
+        self.apply_trade_detail(
+            account_id,
+            instrument_id,
+            None,
+            before,
+            towards,
+            price,
+            volume,
+            multiplier,
+            trade_time,
+        )
+        .closes
+    }
+
+    /// 成交更新批次，返回新增批次与平仓明细（盈亏归因按批次配对开平仓）
+    ///
+    /// `order_id` 为成交所属委托，平仓时按该委托的指定批次优先消耗
+    #[allow(clippy::too_many_arguments)]
+    pub fn apply_trade_detail(
+        &self,
+        account_id: &str,
+        instrument_id: &str,
+        order_id: Option<&str>,
+        before: Option<&PositionCost>,
+        towards: i32,
+        price: f64,
+        volume: f64,
+        multiplier: f64,
+        trade_time: i64,
+    ) -> LotTradeResult {
+        let method = self.method(account_id);
+        let mut entry = self
+            .lots
+            .entry((account_id.to_string(), instrument_id.to_string()))
+            .or_default();
+
+        let before = before.copied().unwrap_or_default();
+        entry.reseed_side(LotSide::Long, &before.long, &self.lot_seq, trade_time);
+        entry.reseed_side(LotSide::Short, &before.short, &self.lot_seq, trade_time);
+
+        match towards {
+            2 | -2 => {
+                let side = if towards > 0 {
+                    LotSide::Long
+                } else {
+                    LotSide::Short
+                };
+                let lot_id = self.lot_seq.fetch_add(1, Ordering::SeqCst);
+                entry.open(lot_id, side, price, volume, trade_time);
+                LotTradeResult {
+                    opened_lot_id: (volume > 0.0).then_some(lot_id),
+                    closes: Vec::new(),
+                }
+            }
+            // 买平/买平今 → 消耗空头批次；卖平/卖平今 → 消耗多头批次
+            3 | 4 => LotTradeResult {
+                opened_lot_id: None,
+                closes: entry.close(
+                    LotSide::Short,
+                    volume,
+                    price,
+                    multiplier,
+                    method,
+                    towards == 4,
+                    order_id,
+                ),
+            },
+            -3 | -4 => LotTradeResult {
+                opened_lot_id: None,
+                closes: entry.close(
+                    LotSide::Long,
+                    volume,
+                    price,
+                    multiplier,
+                    method,
+                    towards == -4,
+                    order_id,
+                ),
+            },
+            _ => LotTradeResult::default(),
+        }
+    }
+
+    /// 批次明细查询（按合约排序）
+    pub fn lots(&self, account_id: &str, instrument_id: Option<&str>) -> Vec<PositionLotView> {
+        let method = self.method(account_id);
+        let mut views: Vec<PositionLotView> = self
+            .lots
+            .iter()
+            .filter(|e| e.key().0 == account_id)
+            .filter(|e| instrument_id.map_or(true, |id| e.key().1 == id))
+            .filter(|e| !e.long.is_empty() || !e.short.is_empty() || e.close_profit != 0.0)
+            .map(|e| {
+                let lots = e.value();
+                PositionLotView {
+                    instrument_id: e.key().1.clone(),
+                    method,
+                    long_volume: lots.volume(LotSide::Long),
+                    short_volume: lots.volume(LotSide::Short),
+                    locked_volume: lots.locked_volume(),
+                    close_profit: lots.close_profit,
+                    lots: lots.long.iter().chain(lots.short.iter()).cloned().collect(),
+                    merged: lots.merged(),
+                }
+            })
+            .collect();
+        views.sort_by(|a, b| a.instrument_id.cmp(&b.instrument_id));
+        views
+    }
+
+    /// 登记平仓委托优先消耗的批次，返回该批次
+    ///
+    /// 只有 `order_id` 委托的成交按指定批次平仓；`towards` 为平仓委托方向
+    /// （3/4 买平消耗空头，-3/-4 卖平消耗多头），平今只能指定今仓批次
+    pub fn designate_close(
+        &self,
+        account_id: &str,
+        instrument_id: &str,
+        order_id: &str,
+        lot_id: u64,
+        towards: i32,
+    ) -> Result<PositionLot, ExchangeError> {
+        let mut entry = self
+            .lots
+            .get_mut(&(account_id.to_string(), instrument_id.to_string()))
+            .ok_or_else(|| {
+                ExchangeError::OrderError(format!(
+                    "No position lots for {} {}",
+                    account_id, instrument_id
+                ))
+            })?;
+        let lot = entry.find(lot_id).cloned().ok_or_else(|| {
+            ExchangeError::OrderError(format!("Lot {} not found in {}", lot_id, instrument_id))
+        })?;
+
+        let side = match towards {
+            3 | 4 => LotSide::Short,
+            -3 | -4 => LotSide::Long,
+            _ => {
+                return Err(ExchangeError::OrderError(
+                    "close_lot_id requires a CLOSE/CLOSETODAY order".to_string(),
+                ))
+            }
+        };
+        if lot.side != side {
+            return Err(ExchangeError::OrderError(format!(
+                "Lot {} is a {:?} lot and cannot be closed by this direction",
+                lot_id, lot.side
+            )));
+        }
+        if towards.abs() == 4 && !lot.today {
+            return Err(ExchangeError::OrderError(format!(
+                "Lot {} is a historical lot and cannot be closed with CLOSETODAY",
+                lot_id
+            )));
+        }
+
+        entry.designated.insert(order_id.to_string(), lot_id);
+        Ok(lot)
+    }
+
+    /// 撤销委托的指定平仓登记（委托全部成交、撤单、拒单或过期时）
+    pub fn cancel_designation(&self, account_id: &str, instrument_id: &str, order_id: &str) {
+        if let Some(mut entry) = self
+            .lots
+            .get_mut(&(account_id.to_string(), instrument_id.to_string()))
+        {
+            entry.designated.remove(order_id);
+        }
+    }
+
+    /// 委托登记的指定平仓批次
+    pub fn designation(
+        &self,
+        account_id: &str,
+        instrument_id: &str,
+        order_id: &str,
+    ) -> Option<u64> {
+        self.lots
+            .get(&(account_id.to_string(), instrument_id.to_string()))
+            .and_then(|entry| entry.designated.get(order_id).copied())
+    }
+
+    /// 开仓明细（逐笔可平量与浮动盈亏，按合约排序）
+    ///
+    /// `marks` 缺少的合约最新价记 0、浮动盈亏记 0
+    pub fn position_detail(
+        &self,
+        account_id: &str,
+        marks: &HashMap<String, LotMark>,
+    ) -> Vec<PositionDetailView> {
+        let method = self.method(account_id);
+        let mut views: Vec<PositionDetailView> = self
+            .lots
+            .iter()
+            .filter(|e| e.key().0 == account_id)
+            .filter(|e| !e.long.is_empty() || !e.short.is_empty())
+            .map(|e| {
+                let lots = e.value();
+                let mark = marks.get(&e.key().1);
+                let mut details = Vec::new();
+                for side in [LotSide::Long, LotSide::Short] {
+                    let frozen = mark.map_or(0.0, |m| match side {
+                        LotSide::Long => m.frozen_long,
+                        LotSide::Short => m.frozen_short,
+                    });
+                    let closable = lots.closable_volumes(side, method, frozen);
+                    for (lot, closable_volume) in lots.side(side).iter().zip(closable) {
+                        details.push(PositionLotDetail {
+                            lot: lot.clone(),
+                            closable_volume,
+                            float_profit: mark
+                                .map_or(0.0, |m| lot.float_profit(m.price, m.multiplier)),
+                        });
+                    }
+                }
+                PositionDetailView {
+                    instrument_id: e.key().1.clone(),
+                    method,
+                    last_price: mark.map_or(0.0, |m| m.price),
+                    long_volume: lots.volume(LotSide::Long),
+                    short_volume: lots.volume(LotSide::Short),
+                    float_profit: details.iter().map(|d| d.float_profit).sum(),
+                    close_profit: lots.close_profit,
+                    lots: details,
+                }
+            })
+            .collect();
+        views.sort_by(|a, b| a.instrument_id.cmp(&b.instrument_id));
+        views
+    }
+
+    /// 对账：批次剩余量与 qars 汇总持仓（今 + 昨）逐合约逐方向比对，返回差异
+    pub fn reconcile<'a, I>(&self, account_id: &str, positions: I) -> Vec<LotMismatch>
+    where
+        I: IntoIterator<Item = (&'a String, &'a QA_Position)>,
+    {
+        let mut volumes: HashMap<String, (f64, f64, f64, f64)> = HashMap::new();
+        for (instrument_id, pos) in positions {
+            let v = volumes.entry(instrument_id.clone()).or_default();
+            v.0 = pos.volume_long_today + pos.volume_long_his;
+            v.2 = pos.volume_short_today + pos.volume_short_his;
+        }
+        for entry in self.lots.iter().filter(|e| e.key().0 == account_id) {
+            let v = volumes.entry(entry.key().1.clone()).or_default();
+            v.1 = entry.volume(LotSide::Long);
+            v.3 = entry.volume(LotSide::Short);
+        }
+
+        let mut mismatches = Vec::new();
+        for (instrument_id, (pos_long, lot_long, pos_short, lot_short)) in volumes {
+            for (side, position_volume, lot_volume) in [
+                (LotSide::Long, pos_long, lot_long),
+                (LotSide::Short, pos_short, lot_short),
+            ] {
+                if (position_volume - lot_volume).abs() > VOLUME_EPSILON {
+                    mismatches.push(LotMismatch {
+                        instrument_id: instrument_id.clone(),
+                        side,
+                        position_volume,
+                        lot_volume,
+                    });
+                }
+            }
+        }
+        mismatches.sort_by(|a, b| a.instrument_id.cmp(&b.instrument_id));
+        mismatches
+    }
+
+    /// 以持仓成本（汇总持仓）为准重建合约批次（今/昨各一个汇总批次）
+    pub fn realign(&self, account_id: &str, instrument_id: &str, cost: &PositionCost, now: i64) {
+        let mut entry = self
+            .lots
+            .entry((account_id.to_string(), instrument_id.to_string()))
+            .or_default();
+        entry.reseed_side(LotSide::Long, &cost.long, &self.lot_seq, now);
+        entry.reseed_side(LotSide::Short, &cost.short, &self.lot_seq, now);
+        entry.prune_designated();
+    }
+
+    /// 日终结算：账户全部今仓批次转为昨仓，累计平仓盈亏清零
+    pub fn settle_account(&self, account_id: &str) {
+        for mut entry in self.lots.iter_mut() {
+            if entry.key().0 == account_id {
+                entry.settle();
+                entry.close_profit = 0.0;
+            }
+        }
+    }
+
+    /// 移除账户全部批次（销户）
+    pub fn remove_account(&self, account_id: &str) {
+        self.lots.retain(|key, _| key.0 != account_id);
+        self.methods.remove(account_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn approx(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    /// 开 3800×2、3820×2、3850×1，卖平 3 手 @3860（合约乘数 10）
+    fn close_profit_with(method: LotMethod) -> (f64, Vec<LotClose>, LotBook) {
+        let book = LotBook::new();
+        book.set_method("acc", method);
+        let mut cost = PositionCost::default();
+        for (i, (price, volume)) in [(3800.0, 2.0), (3820.0, 2.0), (3850.0, 1.0)]
+            .into_iter()
+            .enumerate()
+        {
+            book.apply_trade(
+                "acc",
+                "IF2501",
+                Some(&cost),
+                2,
+                price,
+                volume,
+                10.0,
+                i as i64,
+            );
+            cost.apply(2, price, volume);
+        }
+        let closes = book.apply_trade("acc", "IF2501", Some(&cost), -3, 3860.0, 3.0, 10.0, 10);
+        let profit = closes.iter().map(|c| c.profit).sum();
+        (profit, closes, book)
+    }
+
+    #[test]
+    fn test_fifo_and_lifo_close_profit_differ() {
+        // FIFO: 2×(3860-3800) + 1×(3860-3820) = 160 → ×10
+        let (fifo, closes, book) = close_profit_with(LotMethod::Fifo);
+        assert!(approx(fifo, 1600.0));
+        assert_eq!(closes.len(), 2);
+        let view = &book.lots("acc", Some("IF2501"))[0];
+        assert!(approx(view.long_volume, 2.0));
+        // 部分平仓：3820 批次剩 1 手
+        assert!(view
+            .lots
+            .iter()
+            .any(|l| approx(l.price, 3820.0) && approx(l.volume, 1.0)));
+
+        // LIFO: 1×(3860-3850) + 2×(3860-3820) = 90 → ×10
+        let (lifo, _, book) = close_profit_with(LotMethod::Lifo);
+        assert!(approx(lifo, 900.0));
+        let view = &book.lots("acc", None)[0];
+        assert_eq!(view.lots.len(), 1);
+        assert!(approx(view.lots[0].price, 3800.0));
+        assert!(approx(view.close_profit, 900.0));
+    }
+
+    #[test]
+    fn test_close_today_lock_and_merged_view() {
+        let book = LotBook::new();
+        let mut cost = PositionCost::default();
+        book.apply_trade("acc", "IF2501", Some(&cost), 2, 3800.0, 1.0, 1.0, 1);
+        cost.apply(2, 3800.0, 1.0);
+        book.settle_account("acc");
+        cost.settle(None);
+
+        for (towards, price, volume, t) in [
+            (2, 3800.0, 1.0, 2),
+            (2, 3800.0, 1.0, 3),
+            (-2, 3810.0, 2.0, 4),
+        ] {
+            book.apply_trade("acc", "IF2501", Some(&cost), towards, price, volume, 1.0, t);
+            cost.apply(towards, price, volume);
+        }
+
+        let view = &book.lots("acc", None)[0];
+        // 锁仓：多 3 空 2
+        assert!(approx(view.locked_volume, 2.0));
+        // 合并：多头今仓两个 3800 批次合并，昨仓单独一行
+        let long_today = view
+            .merged
+            .iter()
+            .find(|m| m.side == LotSide::Long && m.today)
+            .unwrap();
+        assert_eq!(long_today.lot_count, 2);
+        assert!(approx(long_today.volume, 2.0));
+        assert_eq!(view.merged.len(), 3);
+
+        // 平今 FIFO 只消耗今仓批次，昨仓保留
+        let closes = book.apply_trade("acc", "IF2501", Some(&cost), -4, 3805.0, 1.0, 1.0, 5);
+        assert_eq!(closes.len(), 1);
+        let view = &book.lots("acc", None)[0];
+        assert!(view
+            .lots
+            .iter()
+            .any(|l| l.side == LotSide::Long && !l.today));
+    }
+
+    #[test]
+    fn test_reseed_from_position_cost() {
+        let book = LotBook::new();
+        let mut cost = PositionCost::default();
+        cost.apply(-2, 4000.0, 3.0);
+        cost.settle(None);
+
+        // 批次缺失（如重启恢复后）时按持仓成本重建昨仓批次
+        let closes = book.apply_trade("acc", "IF2501", Some(&cost), 3, 3990.0, 1.0, 1.0, 1);
+        assert_eq!(closes.len(), 1);
+        assert!(approx(closes[0].profit, 10.0));
+        let view = &book.lots("acc", None)[0];
+        assert!(approx(view.short_volume, 2.0));
+    }
+
+    #[test]
+    fn test_designated_close_and_detail_after_interleaved_trades() {
+        let book = LotBook::new();
+        let mut cost = PositionCost::default();
+        let mut trade = |order_id: Option<&str>, towards: i32, price: f64, volume: f64, t: i64| {
+            let result = book.apply_trade_detail(
+                "acc",
+                "IF2501",
+                order_id,
+                Some(&cost),
+                towards,
+                price,
+                volume,
+                10.0,
+                t,
+            );
+            cost.apply(towards, price, volume);
+            result
+        };
+
+        // 开 3800×2、3820×1，FIFO 平 1 → 3800 批次剩 1，再开 3850×2
+        let first = trade(None, 2, 3800.0, 2.0, 1).opened_lot_id.unwrap();
+        let second = trade(None, 2, 3820.0, 1.0, 2).opened_lot_id.unwrap();
+        let closes = trade(None, -3, 3810.0, 1.0, 3).closes;
+        assert_eq!(closes[0].lot_id, first);
+        let third = trade(None, 2, 3850.0, 2.0, 4).opened_lot_id.unwrap();
+
+        // 指定平第三笔：优先消耗 3850 批次，不足部分回到 FIFO
+        assert!(book
+            .designate_close("acc", "IF2501", "o1", third, 3)
+            .is_err());
+        book.designate_close("acc", "IF2501", "o1", third, -3)
+            .unwrap();
+        let closes = trade(Some("o1"), -3, 3860.0, 3.0, 5).closes;
+        assert_eq!(closes.len(), 2);
+        assert_eq!(closes[0].lot_id, third);
+        assert!(approx(closes[0].profit, 2.0 * 10.0 * 10.0));
+        assert_eq!(closes[1].lot_id, first);
+        // 批次平完后登记随之移除
+        assert_eq!(book.designation("acc", "IF2501", "o1"), None);
+
+        // 剩余：3820×1；再开空 3900×1
+        trade(None, -2, 3900.0, 1.0, 6);
+        let mut marks = HashMap::new();
+        marks.insert(
+            "IF2501".to_string(),
+            LotMark {
+                price: 3880.0,
+                multiplier: 10.0,
+                frozen_long: 1.0,
+                frozen_short: 0.0,
+            },
+        );
+        let detail = &book.position_detail("acc", &marks)[0];
+        assert_eq!(detail.lots.len(), 2);
+        let long = detail.lots.iter().find(|d| d.lot.lot_id == second).unwrap();
+        assert!(approx(long.lot.volume, 1.0));
+        assert!(approx(long.closable_volume, 0.0));
+        assert!(approx(long.float_profit, 600.0));
+        let short = detail
+            .lots
+            .iter()
+            .find(|d| d.lot.side == LotSide::Short)
+            .unwrap();
+        assert!(approx(short.closable_volume, 1.0));
+        assert!(approx(short.float_profit, 200.0));
+        assert!(approx(detail.float_profit, 800.0));
+
+        // 批次汇总与持仓成本一致
+        assert!(approx(detail.long_volume, cost.long.volume()));
+        assert!(approx(detail.short_volume, cost.short.volume()));
+    }
+
+    #[test]
+    fn test_designation_applies_only_to_its_own_order() {
+        let book = LotBook::new();
+        let mut cost = PositionCost::default();
+        let mut trade = |order_id: Option<&str>, towards: i32, price: f64, volume: f64, t: i64| {
+            let result = book.apply_trade_detail(
+                "acc",
+                "IF2501",
+                order_id,
+                Some(&cost),
+                towards,
+                price,
+                volume,
+                10.0,
+                t,
+            );
+            cost.apply(towards, price, volume);
+            result
+        };
+
+        // 开 3800×1、3820×1、3850×1
+        let first = trade(None, 2, 3800.0, 1.0, 1).opened_lot_id.unwrap();
+        let second = trade(None, 2, 3820.0, 1.0, 2).opened_lot_id.unwrap();
+        let third = trade(None, 2, 3850.0, 1.0, 3).opened_lot_id.unwrap();
+
+        // 两笔平仓委托同时挂单，只有 o2 指定 3850 批次
+        book.designate_close("acc", "IF2501", "o2", third, -3)
+            .unwrap();
+
+        // o1 先成交：不受 o2 的指定影响，按 FIFO 平 3800 批次
+        let closes = trade(Some("o1"), -3, 3860.0, 1.0, 4).closes;
+        assert_eq!(closes.len(), 1);
+        assert_eq!(closes[0].lot_id, first);
+        assert_eq!(book.designation("acc", "IF2501", "o2"), Some(third));
+
+        // o2 成交：消耗指定的 3850 批次
+        let closes = trade(Some("o2"), -3, 3860.0, 1.0, 5).closes;
+        assert_eq!(closes[0].lot_id, third);
+        assert!(approx(closes[0].profit, 100.0));
+
+        // 撤单撤销登记：后续成交回到 FIFO
+        let fourth = trade(None, 2, 3790.0, 1.0, 6).opened_lot_id.unwrap();
+        book.designate_close("acc", "IF2501", "o3", fourth, -3)
+            .unwrap();
+        book.cancel_designation("acc", "IF2501", "o3");
+        assert_eq!(book.designation("acc", "IF2501", "o3"), None);
+        let closes = trade(Some("o3"), -3, 3860.0, 1.0, 7).closes;
+        assert_eq!(closes[0].lot_id, second);
+    }
+}
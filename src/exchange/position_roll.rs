@@ -0,0 +1,491 @@
+//! 移仓换月（合约切换）
+//! @yutiansut @quantaxis
+//!
+//! 临近到期时把旧合约持仓等量移到新合约，两腿顺序执行：
+//! - 先平后开（默认）：旧合约平仓释放保证金后，按平仓成交手数在新合约开仓，避免瞬间保证金不足
+//! - 先开后平：先锁定新合约价格，需要账户能同时承担两腿保证金（不足时首腿即被拒，持仓不变）
+//! - 移仓需即时完成：每腿下单后未成交部分立即撤单，第二腿手数取首腿实际成交手数
+//! - 价差模式：设置 spread 后第二腿按"首腿委托价 ± 价差"限价下单
+
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::exchange::order_router::{
+    CancelOrderRequest, OrderRouter, OrderStatus, SubmitOrderRequest,
+};
+use crate::exchange::AccountManager;
+use crate::service::http::models::{
+    RollLegResult, RollPositionRequest, RollPositionResult, RollSequence, RollStatus,
+};
+
+/// 成交手数比较精度
+const VOLUME_EPSILON: f64 = 1e-9;
+
+/// 单腿下单参数
+struct LegSpec<'a> {
+    instrument_id: &'a str,
+    direction: &'a str,
+    offset: &'a str,
+    volume: f64,
+    price: Option<f64>,
+}
+
+/// 移仓执行器
+pub struct PositionRoller {
+    order_router: Arc<OrderRouter>,
+    account_mgr: Arc<AccountManager>,
+}
+
+impl PositionRoller {
+    pub fn new(order_router: Arc<OrderRouter>, account_mgr: Arc<AccountManager>) -> Self {
+        Self {
+            order_router,
+            account_mgr,
+        }
+    }
+
+    /// 查询账户在合约上的持仓手数（LONG/SHORT，今仓 + 昨仓）
+    fn position_volume(&self, account_id: &str, instrument_id: &str, direction: &str) -> f64 {
+        let Ok(account) = self.account_mgr.get_account(account_id) else {
+            return 0.0;
+        };
+        let acc = account.read();
+        acc.hold.get(instrument_id).map_or(0.0, |pos| {
+            if direction == "LONG" {
+                pos.volume_long_today + pos.volume_long_his
+            } else {
+                pos.volume_short_today + pos.volume_short_his
+            }
+        })
+    }
+
+    /// 执行移仓，返回两腿结果
+    ///
+    /// 参数错误或持仓不足返回 Err（未下任何订单）；下单后的部分成交、拒单等
+    /// 通过 [`RollStatus`] 体现。
+    pub fn roll(&self, req: RollPositionRequest) -> Result<RollPositionResult, String> {
+        // 1. 参数校验
+        let (close_direction, open_direction) = match req.direction.as_str() {
+            "LONG" => ("SELL", "BUY"),
+            "SHORT" => ("BUY", "SELL"),
+            other => return Err(format!("持仓方向必须为 LONG/SHORT: {}", other)),
+        };
+        if req.from_instrument == req.to_instrument {
+            return Err("新旧合约不能相同".to_string());
+        }
+        let is_market = match req.order_type.as_str() {
+            "MARKET" => true,
+            "LIMIT" => false,
+            other => return Err(format!("订单类型必须为 LIMIT/MARKET: {}", other)),
+        };
+        let (first_price, second_price) = match req.sequence {
+            RollSequence::CloseFirst => (req.close_price, req.open_price),
+            RollSequence::OpenFirst => (req.open_price, req.close_price),
+        };
+        if !is_market && (first_price.is_none() || (second_price.is_none() && req.spread.is_none()))
+        {
+            return Err("限价移仓需要指定两腿价格（或首腿价格 + 价差）".to_string());
+        }
+
+        // 2. 持仓校验
+        let position = self.position_volume(&req.account_id, &req.from_instrument, &req.direction);
+        let volume = req.volume.unwrap_or(position);
+        if volume <= 0.0 {
+            return Err(format!(
+                "合约 {} 无 {} 持仓可移",
+                req.from_instrument, req.direction
+            ));
+        }
+        if volume > position + VOLUME_EPSILON {
+            return Err(format!(
+                "移仓手数 {} 超过持仓 {} ({} {})",
+                volume, position, req.from_instrument, req.direction
+            ));
+        }
+
+        let close_spec = |volume: f64, price: Option<f64>| LegSpec {
+            instrument_id: &req.from_instrument,
+            direction: close_direction,
+            offset: "CLOSE",
+            volume,
+            price,
+        };
+        let open_spec = |volume: f64, price: Option<f64>| LegSpec {
+            instrument_id: &req.to_instrument,
+            direction: open_direction,
+            offset: "OPEN",
+            volume,
+            price,
+        };
+
+        // 3. 首腿
+        let first = match req.sequence {
+            RollSequence::CloseFirst => {
+                self.execute_leg(&req.account_id, close_spec(volume, first_price))
+            }
+            RollSequence::OpenFirst => {
+                self.execute_leg(&req.account_id, open_spec(volume, first_price))
+            }
+        };
+
+        // 4. 第二腿：手数取首腿成交手数，价差模式下按首腿委托价推算价格
+        let second_volume = first.filled_volume;
+        let second_price = match (req.spread, first.price) {
+            (Some(spread), Some(price)) => Some(match req.sequence {
+                RollSequence::CloseFirst => price + spread,
+                RollSequence::OpenFirst => price - spread,
+            }),
+            _ => second_price,
+        };
+        let second = if second_volume > VOLUME_EPSILON {
+            match req.sequence {
+                RollSequence::CloseFirst => {
+                    self.execute_leg(&req.account_id, open_spec(second_volume, second_price))
+                }
+                RollSequence::OpenFirst => {
+                    self.execute_leg(&req.account_id, close_spec(second_volume, second_price))
+                }
+            }
+        } else {
+            let spec = match req.sequence {
+                RollSequence::CloseFirst => open_spec(0.0, second_price),
+                RollSequence::OpenFirst => close_spec(0.0, second_price),
+            };
+            skipped_leg(spec, "首腿未成交，跳过")
+        };
+
+        let (close_leg, open_leg) = match req.sequence {
+            RollSequence::CloseFirst => (first, second),
+            RollSequence::OpenFirst => (second, first),
+        };
+
+        // 5. 汇总
+        let rolled_volume = close_leg.filled_volume.min(open_leg.filled_volume);
+        let imbalance = (close_leg.filled_volume - open_leg.filled_volume).abs();
+        let (status, message) = if second_volume <= VOLUME_EPSILON {
+            let reason = match req.sequence {
+                RollSequence::CloseFirst => close_leg.message.clone(),
+                RollSequence::OpenFirst => open_leg.message.clone(),
+            };
+            (
+                RollStatus::Failed,
+                Some(format!(
+                    "首腿未成交，持仓未变化{}",
+                    reason.map(|r| format!(": {}", r)).unwrap_or_default()
+                )),
+            )
+        } else if imbalance > VOLUME_EPSILON {
+            let message = if close_leg.filled_volume > open_leg.filled_volume {
+                format!(
+                    "旧合约已平 {} 手，新合约仅开 {} 手，{} 手未建仓",
+                    close_leg.filled_volume, open_leg.filled_volume, imbalance
+                )
+            } else {
+                format!(
+                    "新合约已开 {} 手，旧合约仅平 {} 手，{} 手双边持仓",
+                    open_leg.filled_volume, close_leg.filled_volume, imbalance
+                )
+            };
+            (RollStatus::LegRisk, Some(message))
+        } else if rolled_volume + VOLUME_EPSILON < volume {
+            (
+                RollStatus::PartiallyRolled,
+                Some(format!("部分成交：移仓 {} / {} 手", rolled_volume, volume)),
+            )
+        } else {
+            (RollStatus::Completed, None)
+        };
+
+        let spread = match (close_leg.price, open_leg.price) {
+            (Some(close_price), Some(open_price)) if rolled_volume > VOLUME_EPSILON => {
+                Some(open_price - close_price)
+            }
+            _ => None,
+        };
+
+        let result = RollPositionResult {
+            roll_id: format!(
+                "ROLL_{}",
+                Uuid::new_v4().to_string().replace("-", "")[..12].to_uppercase()
+            ),
+            account_id: req.account_id.clone(),
+            from_instrument: req.from_instrument.clone(),
+            to_instrument: req.to_instrument.clone(),
+            direction: req.direction.clone(),
+            sequence: req.sequence,
+            volume,
+            rolled_volume,
+            close_leg,
+            open_leg,
+            spread,
+            status,
+            message,
+        };
+
+        if status == RollStatus::LegRisk {
+            log::warn!(
+                "移仓 {} 腿风险: {}",
+                result.roll_id,
+                result.message.as_deref().unwrap_or_default()
+            );
+        } else {
+            log::info!(
+                "移仓 {} {} -> {} {:?}: {} / {} 手",
+                result.roll_id,
+                result.from_instrument,
+                result.to_instrument,
+                status,
+                rolled_volume,
+                volume
+            );
+        }
+        Ok(result)
+    }
+
+    /// 下单并撤销未成交部分，返回单腿结果
+    fn execute_leg(&self, account_id: &str, spec: LegSpec) -> RollLegResult {
+        let order_type = if spec.price.is_some() {
+            "LIMIT"
+        } else {
+            "MARKET"
+        };
+        let response = self.order_router.submit_order(SubmitOrderRequest {
+            account_id: account_id.to_string(),
+            instrument_id: spec.instrument_id.to_string(),
+            direction: spec.direction.to_string(),
+            offset: spec.offset.to_string(),
+            volume: spec.volume,
+            price: spec.price.unwrap_or(0.0),
+            order_type: order_type.to_string(),
+            time_condition: None,
+            volume_condition: None,
+            hedge_flag: None,
+            ttl_secs: None,
+            client_order_id: None,
+            close_lot_id: None,
+        });
+
+        let Some(order_id) = response.order_id.filter(|_| response.success) else {
+            let message = response.error_message.unwrap_or_default();
+            let mut leg = skipped_leg(spec, &message);
+            leg.status = "REJECTED".to_string();
+            return leg;
+        };
+
+        // 移仓需即时完成，未成交部分撤单
+        if matches!(
+            self.order_router.get_order_status(&order_id),
+            Some(OrderStatus::Submitted | OrderStatus::PartiallyFilled)
+        ) {
+            if let Err(e) = self.order_router.cancel_order(CancelOrderRequest {
+                account_id: account_id.to_string(),
+                order_id: order_id.clone(),
+            }) {
+                log::error!("移仓撤销未成交订单 {} 失败: {}", order_id, e);
+            }
+        }
+
+        let (price, status, filled_volume) = match self.order_router.get_order_detail(&order_id) {
+            Some((order, status, _, _, filled)) => (Some(order.limit_price), Some(status), filled),
+            None => (spec.price, None, 0.0),
+        };
+        let status = if status == Some(OrderStatus::Rejected) {
+            "REJECTED"
+        } else if filled_volume + VOLUME_EPSILON >= spec.volume {
+            "FILLED"
+        } else if filled_volume > VOLUME_EPSILON {
+            "PARTIALLY_FILLED"
+        } else if status == Some(OrderStatus::Cancelled) {
+            "CANCELLED"
+        } else {
+            "SUBMITTED"
+        };
+
+        RollLegResult {
+            instrument_id: spec.instrument_id.to_string(),
+            direction: spec.direction.to_string(),
+            offset: spec.offset.to_string(),
+            order_id: Some(order_id),
+            price,
+            volume: spec.volume,
+            filled_volume,
+            status: status.to_string(),
+            message: None,
+        }
+    }
+}
+
+/// 未下单的腿
+fn skipped_leg(spec: LegSpec, message: &str) -> RollLegResult {
+    RollLegResult {
+        instrument_id: spec.instrument_id.to_string(),
+        direction: spec.direction.to_string(),
+        offset: spec.offset.to_string(),
+        order_id: None,
+        price: spec.price,
+        volume: spec.volume,
+        filled_volume: 0.0,
+        status: "SKIPPED".to_string(),
+        message: Some(message.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::account_ext::{AccountType, OpenAccountRequest};
+    use crate::exchange::instrument_registry::{InstrumentInfo, InstrumentStatus, InstrumentType};
+    use crate::exchange::{InstrumentRegistry, TradeGateway};
+    use crate::matching::engine::ExchangeMatchingEngine;
+
+    /// 旧合约 IX2301 @100，新合约 IX2305 @102；roll_user 持有 IX2301 多头 5 手
+    fn create_roller() -> (PositionRoller, Arc<OrderRouter>) {
+        let account_mgr = Arc::new(AccountManager::new());
+        for user in ["roll_user", "mm_user"] {
+            account_mgr
+                .open_account(OpenAccountRequest {
+                    user_id: user.to_string(),
+                    account_id: Some(user.to_string()),
+                    account_name: user.to_string(),
+                    init_cash: 1000000.0,
+                    account_type: AccountType::Individual,
+                })
+                .unwrap();
+        }
+
+        let matching_engine = Arc::new(ExchangeMatchingEngine::new());
+        let registry = Arc::new(InstrumentRegistry::new());
+        for (instrument_id, price) in [("IX2301", 100.0), ("IX2305", 102.0)] {
+            matching_engine
+                .register_instrument(instrument_id.to_string(), price)
+                .unwrap();
+            registry
+                .register(InstrumentInfo {
+                    instrument_id: instrument_id.to_string(),
+                    instrument_name: instrument_id.to_string(),
+                    instrument_type: InstrumentType::CommodityFuture,
+                    exchange: "SHFE".to_string(),
+                    contract_multiplier: 1,
+                    trading_unit: "手".to_string(),
+                    quote_unit: String::new(),
+                    price_tick: 0.01,
+                    margin_rate: 0.1,
+                    commission_rate: 0.0005,
+                    limit_up_rate: 0.1,
+                    limit_down_rate: 0.1,
+                    status: InstrumentStatus::Active,
+                    list_date: Some("2023-01-01".to_string()),
+                    expire_date: Some("2023-12-31".to_string()),
+                    created_at: "2023-01-01T00:00:00Z".to_string(),
+                    updated_at: "2023-01-01T00:00:00Z".to_string(),
+                })
+                .unwrap();
+        }
+
+        let trade_gateway = Arc::new(TradeGateway::new(account_mgr.clone()));
+        let router = Arc::new(OrderRouter::new(
+            account_mgr.clone(),
+            matching_engine,
+            registry,
+            trade_gateway,
+        ));
+
+        let roller = PositionRoller::new(router.clone(), account_mgr);
+        submit(&router, "mm_user", "IX2301", "SELL", "OPEN", 5.0, 100.0);
+        submit(&router, "roll_user", "IX2301", "BUY", "OPEN", 5.0, 100.0);
+        assert_eq!(roller.position_volume("roll_user", "IX2301", "LONG"), 5.0);
+        (roller, router)
+    }
+
+    fn submit(
+        router: &OrderRouter,
+        account_id: &str,
+        instrument_id: &str,
+        direction: &str,
+        offset: &str,
+        volume: f64,
+        price: f64,
+    ) {
+        let response = router.submit_order(SubmitOrderRequest {
+            account_id: account_id.to_string(),
+            instrument_id: instrument_id.to_string(),
+            direction: direction.to_string(),
+            offset: offset.to_string(),
+            volume,
+            price,
+            order_type: "LIMIT".to_string(),
+            ..Default::default()
+        });
+        assert!(response.success, "{:?}", response.error_message);
+    }
+
+    fn roll_request(volume: Option<f64>) -> RollPositionRequest {
+        RollPositionRequest {
+            account_id: "roll_user".to_string(),
+            from_instrument: "IX2301".to_string(),
+            to_instrument: "IX2305".to_string(),
+            direction: "LONG".to_string(),
+            volume,
+            order_type: "LIMIT".to_string(),
+            close_price: Some(100.0),
+            open_price: None,
+            spread: Some(2.0),
+            sequence: RollSequence::CloseFirst,
+        }
+    }
+
+    #[test]
+    fn test_roll_moves_position_to_new_contract() {
+        let (roller, router) = create_roller();
+        // 对手盘：旧合约买方、新合约卖方
+        submit(&router, "mm_user", "IX2301", "BUY", "OPEN", 5.0, 100.0);
+        submit(&router, "mm_user", "IX2305", "SELL", "OPEN", 5.0, 102.0);
+
+        let result = roller.roll(roll_request(None)).unwrap();
+
+        assert_eq!(result.status, RollStatus::Completed, "{:?}", result.message);
+        assert_eq!(result.volume, 5.0);
+        assert_eq!(result.rolled_volume, 5.0);
+        assert_eq!(result.close_leg.status, "FILLED");
+        assert_eq!(
+            (
+                result.close_leg.direction.as_str(),
+                result.close_leg.offset.as_str()
+            ),
+            ("SELL", "CLOSE")
+        );
+        assert_eq!(result.open_leg.status, "FILLED");
+        assert_eq!(result.open_leg.price, Some(102.0));
+        assert_eq!(result.spread, Some(2.0));
+
+        // 持仓从旧合约转到新合约
+        assert_eq!(roller.position_volume("roll_user", "IX2301", "LONG"), 0.0);
+        assert_eq!(roller.position_volume("roll_user", "IX2305", "LONG"), 5.0);
+    }
+
+    #[test]
+    fn test_roll_partial_fill_opens_only_closed_volume() {
+        let (roller, router) = create_roller();
+        // 旧合约对手盘只有 3 手
+        submit(&router, "mm_user", "IX2301", "BUY", "OPEN", 3.0, 100.0);
+        submit(&router, "mm_user", "IX2305", "SELL", "OPEN", 5.0, 102.0);
+
+        let result = roller.roll(roll_request(Some(5.0))).unwrap();
+
+        assert_eq!(result.status, RollStatus::PartiallyRolled);
+        assert_eq!(result.close_leg.status, "PARTIALLY_FILLED");
+        assert_eq!(result.open_leg.volume, 3.0);
+        assert_eq!(result.rolled_volume, 3.0);
+        // 未成交的平仓单已撤销
+        let close_order_id = result.close_leg.order_id.as_deref().unwrap();
+        assert_eq!(
+            router.get_order_status(close_order_id),
+            Some(OrderStatus::Cancelled)
+        );
+        assert_eq!(roller.position_volume("roll_user", "IX2301", "LONG"), 2.0);
+        assert_eq!(roller.position_volume("roll_user", "IX2305", "LONG"), 3.0);
+
+        // 超出持仓的请求直接拒绝
+        assert!(roller.roll(roll_request(Some(10.0))).is_err());
+    }
+}
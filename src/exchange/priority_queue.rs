@@ -1,11 +1,24 @@
 //! 优先级订单队列
 //!
 //! 支持三级优先级：
-//! - Critical: VIP用户、大额订单
+//! - Critical: VIP用户、做市商账户、大额订单
 //! - Normal: 普通订单
 //! - Low: 批量回测订单
+//!
+//! ## 优先级计算 @yutiansut @quantaxis
+//! 1. 账户等级：VIP 名单或做市商账户 → Critical
+//! 2. 订单类型：大额订单 → Critical；市价/即时成交(IOC/FOK)订单 → 至少 Normal；小额限价单 → Low
+//! 3. 取账户等级与订单类型中较高者；订单自定义标记可下调，上调不超过账户上限
+//!    （VIP/做市商可标记 Critical，其他账户最高 Normal）
+//!
+//! 优先级只决定订单进入撮合的先后顺序，撮合仍按价格时间优先。
+//!
+//! ## 防饿死
+//! - 高优先级连续出队达到 `starvation_limit` 且更低优先级有订单等待时，让出一次给较低优先级
+//! - 队首订单等待超过 `max_wait_ms` 时优先出队（老化）
 
-use super::order_router::SubmitOrderRequest;
+use super::order_router::{SubmitOrderRequest, TimeCondition};
+use crate::core::account_ext::AccountType;
 use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
@@ -34,6 +47,52 @@ pub struct PriorityOrderRequest {
     pub priority: OrderPriority,
     /// 提交时间戳（纳秒）
     pub submit_time: i64,
+    /// 入队序号（路由器据此回传处理结果）
+    #[serde(default)]
+    pub seq: u64,
+}
+
+/// 按优先级分列的计数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PriorityCounts {
+    pub critical: u64,
+    pub normal: u64,
+    pub low: u64,
+}
+
+impl PriorityCounts {
+    fn get_mut(&mut self, priority: OrderPriority) -> &mut u64 {
+        match priority {
+            OrderPriority::Critical => &mut self.critical,
+            OrderPriority::Normal => &mut self.normal,
+            OrderPriority::Low => &mut self.low,
+        }
+    }
+
+    fn get(&self, priority: OrderPriority) -> u64 {
+        match priority {
+            OrderPriority::Critical => self.critical,
+            OrderPriority::Normal => self.normal,
+            OrderPriority::Low => self.low,
+        }
+    }
+}
+
+/// 调度状态与统计
+#[derive(Debug, Default)]
+struct DispatchState {
+    /// Critical 在较低优先级等待时的连续出队次数
+    critical_streak: usize,
+    /// Normal 在 Low 等待时的连续出队次数
+    normal_streak: usize,
+    enqueued: PriorityCounts,
+    dispatched: PriorityCounts,
+    /// 累计排队时间（纳秒）
+    total_wait_ns: PriorityCounts,
+    /// 防饿死让出/老化出队次数
+    starvation_promotions: u64,
+    /// 低优先级队列满被拒绝次数
+    rejected: u64,
 }
 
 /// 优先级订单队列
@@ -59,6 +118,15 @@ pub struct PriorityOrderQueue {
 
     /// 统计：队列长度峰值
     max_queue_length: Arc<Mutex<usize>>,
+
+    /// 防饿死：高优先级连续出队上限（0 表示不限制）
+    starvation_limit: Mutex<usize>,
+
+    /// 防饿死：最长排队时间（纳秒，0 表示不老化）
+    max_wait_ns: Mutex<i64>,
+
+    /// 调度状态与统计
+    dispatch: Mutex<DispatchState>,
 }
 
 impl PriorityOrderQueue {
@@ -77,9 +145,27 @@ impl PriorityOrderQueue {
             critical_amount_threshold,
             low_amount_threshold: 500.0, // 默认500以下为低优先级
             max_queue_length: Arc::new(Mutex::new(0)),
+            starvation_limit: Mutex::new(8),
+            max_wait_ns: Mutex::new(100_000_000), // 默认 100ms
+            dispatch: Mutex::new(DispatchState::default()),
         }
     }
 
+    /// 设置防饿死参数
+    ///
+    /// # 参数
+    /// - `starvation_limit`: 高优先级连续出队上限，达到后让出一次给较低优先级（0 表示不限制）
+    /// - `max_wait_ms`: 最长排队时间，超过后优先出队（0 表示不老化）
+    pub fn set_starvation_guard(&self, starvation_limit: usize, max_wait_ms: u64) {
+        *self.starvation_limit.lock() = starvation_limit;
+        *self.max_wait_ns.lock() = max_wait_ms as i64 * 1_000_000;
+        log::info!(
+            "Priority queue starvation guard: limit={}, max_wait={}ms",
+            starvation_limit,
+            max_wait_ms
+        );
+    }
+
     /// 添加VIP用户
     pub fn add_vip_user(&self, user_id: String) {
         log::info!("Added VIP user: {}", user_id);
@@ -100,29 +186,56 @@ impl PriorityOrderQueue {
         self.vip_users.lock().iter().any(|id| id == user_id)
     }
 
-    /// 计算订单优先级
+    /// 计算订单优先级（未知账户类型、无自定义标记）
     fn calculate_priority(&self, req: &SubmitOrderRequest) -> OrderPriority {
-        // 1. VIP用户 → Critical
-        if self.is_vip_user(&req.account_id) {
-            log::debug!("VIP user detected: {}", req.account_id);
-            return OrderPriority::Critical;
-        }
+        self.calculate_priority_with(req, None, None)
+    }
+
+    /// 综合账户等级、订单类型与自定义标记计算订单优先级
+    pub fn calculate_priority_with(
+        &self,
+        req: &SubmitOrderRequest,
+        account_type: Option<AccountType>,
+        tag: Option<OrderPriority>,
+    ) -> OrderPriority {
+        // 1. 账户等级：VIP 用户 / 做市商 → Critical
+        let account_priority = if self.is_vip_user(&req.account_id)
+            || account_type == Some(AccountType::MarketMaker)
+        {
+            log::debug!("Priority account detected: {}", req.account_id);
+            OrderPriority::Critical
+        } else {
+            OrderPriority::Low
+        };
 
-        // 2. 大额订单 → Critical
+        // 2. 订单类型：大额 → Critical，即时成交订单 → 至少 Normal，小额限价 → Low
         let order_amount = req.price * req.volume;
-        if order_amount >= self.critical_amount_threshold {
+        let immediate = req.order_type.eq_ignore_ascii_case("MARKET")
+            || req.time_condition == Some(TimeCondition::IOC);
+        let order_priority = if order_amount >= self.critical_amount_threshold {
             log::debug!("Large order detected: amount={:.2}", order_amount);
-            return OrderPriority::Critical;
-        }
-
-        // 3. 小额订单 → Low
-        if order_amount < self.low_amount_threshold {
+            OrderPriority::Critical
+        } else if order_amount < self.low_amount_threshold && !immediate {
             log::debug!("Small order detected: amount={:.2}", order_amount);
-            return OrderPriority::Low;
-        }
+            OrderPriority::Low
+        } else {
+            OrderPriority::Normal
+        };
 
-        // 4. 中等订单 → Normal
-        OrderPriority::Normal
+        let priority = account_priority.max(order_priority);
+
+        // 3. 自定义标记：可下调，上调不超过账户上限
+        match tag {
+            Some(tag) => {
+                let ceiling = if account_priority == OrderPriority::Critical {
+                    OrderPriority::Critical
+                } else {
+                    priority.max(OrderPriority::Normal)
+                };
+                tag.min(ceiling)
+            }
+            None => priority,
+        }
     }
 
     /// 入队订单
@@ -132,11 +245,21 @@ impl PriorityOrderQueue {
     /// - `false`: 队列已满（仅低优先级队列会拒绝）
     pub fn enqueue(&self, order: SubmitOrderRequest) -> bool {
         let priority = self.calculate_priority(&order);
+        self.enqueue_with_priority(order, priority, 0)
+    }
 
+    /// 按指定优先级入队（`seq` 为调用方分配的序号）
+    pub fn enqueue_with_priority(
+        &self,
+        order: SubmitOrderRequest,
+        priority: OrderPriority,
+        seq: u64,
+    ) -> bool {
         let req = PriorityOrderRequest {
             order,
             priority,
             submit_time: chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0),
+            seq,
         };
 
         match priority {
@@ -144,60 +267,136 @@ impl PriorityOrderQueue {
                 let account_id = req.order.account_id.clone();
                 self.critical_queue.lock().push_back(req);
                 log::trace!("Enqueued CRITICAL order: {}", account_id);
-                true
             }
             OrderPriority::Normal => {
                 let account_id = req.order.account_id.clone();
                 self.normal_queue.lock().push_back(req);
                 log::trace!("Enqueued NORMAL order: {}", account_id);
-                true
             }
             OrderPriority::Low => {
                 let mut queue = self.low_queue.lock();
                 if queue.len() >= self.low_queue_limit {
+                    drop(queue);
                     log::warn!(
                         "Low priority queue full (limit={}), rejecting order",
                         self.low_queue_limit
                     );
+                    self.dispatch.lock().rejected += 1;
                     return false;
                 }
                 let account_id = req.order.account_id.clone();
                 queue.push_back(req);
                 log::trace!("Enqueued LOW order: {}", account_id);
-                true
             }
         }
+        *self.dispatch.lock().enqueued.get_mut(priority) += 1;
+        true
+    }
+
+    fn queue_of(&self, priority: OrderPriority) -> &Mutex<VecDeque<PriorityOrderRequest>> {
+        match priority {
+            OrderPriority::Critical => &self.critical_queue,
+            OrderPriority::Normal => &self.normal_queue,
+            OrderPriority::Low => &self.low_queue,
+        }
     }
 
     /// 出队订单（按优先级顺序）
     ///
     /// # 调度策略
-    /// 1. Critical队列优先清空
-    /// 2. Normal队列次之
-    /// 3. Low队列限流处理（批量大小限制）
+    /// 1. 排队超过 `max_wait_ms` 的较低优先级队首订单先出队（老化）
+    /// 2. 高优先级连续出队达到 `starvation_limit` 时让出一次给较低优先级
+    /// 3. 其余按 Critical → Normal → Low 顺序
     pub fn dequeue(&self) -> Option<PriorityOrderRequest> {
-        // 1. 优先处理Critical队列
-        if let Some(req) = self.critical_queue.lock().pop_front() {
-            log::trace!("Dequeued CRITICAL order: {}", req.order.account_id);
-            return Some(req);
-        }
+        let now = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
+        self.dequeue_at(now, true)
+    }
 
-        // 2. 处理Normal队列
-        if let Some(req) = self.normal_queue.lock().pop_front() {
-            log::trace!("Dequeued NORMAL order: {}", req.order.account_id);
-            return Some(req);
+    fn dequeue_at(&self, now: i64, allow_low: bool) -> Option<PriorityOrderRequest> {
+        let starvation_limit = *self.starvation_limit.lock();
+        let max_wait_ns = *self.max_wait_ns.lock();
+        let mut state = self.dispatch.lock();
+
+        let mut levels = vec![OrderPriority::Critical, OrderPriority::Normal];
+        if allow_low {
+            levels.push(OrderPriority::Low);
         }
+        let heads: Vec<(OrderPriority, Option<i64>)> = levels
+            .iter()
+            .map(|&p| (p, self.queue_of(p).lock().front().map(|r| r.submit_time)))
+            .collect();
+        let waiting_below = |priority: OrderPriority| {
+            heads
+                .iter()
+                .any(|(p, head)| *p < priority && head.is_some())
+        };
+
+        // 1. 老化：较低优先级队首等待过久
+        let aged = (max_wait_ns > 0)
+            .then(|| {
+                heads.iter().skip(1).find(|(_, head)| {
+                    head.is_some_and(|submit_time| now - submit_time >= max_wait_ns)
+                })
+            })
+            .flatten()
+            .map(|(p, _)| *p);
+
+        // 2. 防饿死：高优先级连续出队达到上限，让给下一个非空的较低优先级
+        let yielded = if aged.is_some() || starvation_limit == 0 {
+            None
+        } else if state.critical_streak >= starvation_limit
+            && waiting_below(OrderPriority::Critical)
+        {
+            heads
+                .iter()
+                .find(|(p, head)| *p < OrderPriority::Critical && head.is_some())
+                .map(|(p, _)| *p)
+        } else if state.normal_streak >= starvation_limit
+            && heads[0].1.is_none()
+            && waiting_below(OrderPriority::Normal)
+        {
+            Some(OrderPriority::Low)
+        } else {
+            None
+        };
+
+        let promoted = aged.or(yielded);
+        let priority = match promoted {
+            Some(p) => p,
+            None => heads.iter().find(|(_, head)| head.is_some())?.0,
+        };
+        let req = self.queue_of(priority).lock().pop_front()?;
 
-        // 3. 限流处理Low队列（防止占用过多资源）
-        let low_queue_len = self.low_queue.lock().len();
-        if low_queue_len > 0 && low_queue_len < self.low_queue_limit {
-            if let Some(req) = self.low_queue.lock().pop_front() {
-                log::trace!("Dequeued LOW order: {}", req.order.account_id);
-                return Some(req);
+        if promoted.is_some() {
+            state.starvation_promotions += 1;
+        }
+        match priority {
+            OrderPriority::Critical if waiting_below(OrderPriority::Critical) => {
+                state.critical_streak += 1;
+            }
+            OrderPriority::Normal if waiting_below(OrderPriority::Normal) => {
+                state.critical_streak = 0;
+                state.normal_streak += 1;
+            }
+            _ => {
+                state.critical_streak = 0;
+                state.normal_streak = 0;
             }
         }
+        *state.dispatched.get_mut(priority) += 1;
+        *state.total_wait_ns.get_mut(priority) += (now - req.submit_time).max(0) as u64;
 
-        None
+        log::trace!(
+            "Dequeued {:?} order: {}{}",
+            priority,
+            req.order.account_id,
+            if promoted.is_some() {
+                " (promoted)"
+            } else {
+                ""
+            }
+        );
+        Some(req)
     }
 
     /// 批量出队（最多N个订单）
@@ -206,41 +405,21 @@ impl PriorityOrderQueue {
     /// - `batch_size`: 批量大小（默认100）
     ///
     /// # 返回
-    /// 订单列表（按优先级排序）
+    /// 订单列表（按调度顺序，Low 最多占批量大小的10%）
     pub fn dequeue_batch(&self, batch_size: usize) -> Vec<PriorityOrderRequest> {
         let mut batch = Vec::with_capacity(batch_size);
-
-        // 1. Critical队列（全部取出）
-        {
-            let mut critical = self.critical_queue.lock();
-            while !critical.is_empty() && batch.len() < batch_size {
-                if let Some(req) = critical.pop_front() {
-                    batch.push(req);
-                }
-            }
-        }
-
-        // 2. Normal队列
-        {
-            let mut normal = self.normal_queue.lock();
-            while !normal.is_empty() && batch.len() < batch_size {
-                if let Some(req) = normal.pop_front() {
-                    batch.push(req);
-                }
-            }
-        }
-
-        // 3. Low队列（限流：最多批量大小的10%）
-        {
-            let low_limit = (batch_size / 10).max(1);
-            let mut low = self.low_queue.lock();
-            let mut low_count = 0;
-            while !low.is_empty() && batch.len() < batch_size && low_count < low_limit {
-                if let Some(req) = low.pop_front() {
-                    batch.push(req);
-                    low_count += 1;
-                }
+        let low_limit = (batch_size / 10).max(1);
+        let mut low_count = 0;
+        let now = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
+
+        while batch.len() < batch_size {
+            let Some(req) = self.dequeue_at(now, low_count < low_limit) else {
+                break;
+            };
+            if req.priority == OrderPriority::Low {
+                low_count += 1;
             }
+            batch.push(req);
         }
 
         if !batch.is_empty() {
@@ -286,12 +465,30 @@ impl PriorityOrderQueue {
     /// 获取队列统计信息
     pub fn get_statistics(&self) -> PriorityQueueStatistics {
         let (critical_len, normal_len, low_len) = self.get_queue_lengths();
+        let state = self.dispatch.lock();
+        let avg_wait_us = |priority: OrderPriority| {
+            let dispatched = state.dispatched.get(priority);
+            if dispatched == 0 {
+                0.0
+            } else {
+                state.total_wait_ns.get(priority) as f64 / dispatched as f64 / 1_000.0
+            }
+        };
         PriorityQueueStatistics {
             critical_queue_length: critical_len,
             normal_queue_length: normal_len,
             low_queue_length: low_len,
             max_queue_length: *self.max_queue_length.lock(),
             vip_user_count: self.vip_users.lock().len(),
+            enqueued: state.enqueued,
+            dispatched: state.dispatched,
+            avg_wait_us: [
+                avg_wait_us(OrderPriority::Critical),
+                avg_wait_us(OrderPriority::Normal),
+                avg_wait_us(OrderPriority::Low),
+            ],
+            starvation_promotions: state.starvation_promotions,
+            rejected: state.rejected,
         }
     }
 }
@@ -304,6 +501,21 @@ pub struct PriorityQueueStatistics {
     pub low_queue_length: usize,
     pub max_queue_length: usize,
     pub vip_user_count: usize,
+    /// 累计入队数
+    #[serde(default)]
+    pub enqueued: PriorityCounts,
+    /// 累计出队（进入撮合）数
+    #[serde(default)]
+    pub dispatched: PriorityCounts,
+    /// 平均排队时间（微秒，依次为 Critical/Normal/Low）
+    #[serde(default)]
+    pub avg_wait_us: [f64; 3],
+    /// 防饿死让出/老化出队次数
+    #[serde(default)]
+    pub starvation_promotions: u64,
+    /// 低优先级队列满被拒绝次数
+    #[serde(default)]
+    pub rejected: u64,
 }
 
 #[cfg(test)]
@@ -319,8 +531,7 @@ mod tests {
             volume,
             price,
             order_type: "LIMIT".to_string(),
-            time_condition: None,
-            volume_condition: None,
+            ..Default::default()
         }
     }
 
@@ -397,4 +608,90 @@ mod tests {
             assert_eq!(batch[i].priority, OrderPriority::Normal);
         }
     }
+
+    #[test]
+    fn test_priority_combines_account_order_type_and_tag() {
+        let queue = PriorityOrderQueue::new(100, 1_000_000.0);
+        let small = create_test_order("retail", 10.0, 10.0);
+
+        // 账户等级：做市商小额订单也是 Critical
+        assert_eq!(
+            queue.calculate_priority_with(&small, Some(AccountType::MarketMaker), None),
+            OrderPriority::Critical
+        );
+        // 订单类型：小额限价单 Low，小额即时成交订单至少 Normal
+        assert_eq!(
+            queue.calculate_priority_with(&small, Some(AccountType::Individual), None),
+            OrderPriority::Low
+        );
+        let mut ioc = small.clone();
+        ioc.time_condition = Some(TimeCondition::IOC);
+        assert_eq!(
+            queue.calculate_priority_with(&ioc, None, None),
+            OrderPriority::Normal
+        );
+        // 自定义标记：普通账户最高 Normal，做市商可下调
+        assert_eq!(
+            queue.calculate_priority_with(&small, None, Some(OrderPriority::Critical)),
+            OrderPriority::Normal
+        );
+        assert_eq!(
+            queue.calculate_priority_with(
+                &small,
+                Some(AccountType::MarketMaker),
+                Some(OrderPriority::Low)
+            ),
+            OrderPriority::Low
+        );
+    }
+
+    #[test]
+    fn test_starvation_guard_yields_to_lower_priority() {
+        let queue = PriorityOrderQueue::new(100, 1_000_000.0);
+        queue.set_starvation_guard(3, 0);
+        queue.add_vip_user("vip".to_string());
+
+        for _ in 0..6 {
+            queue.enqueue(create_test_order("vip", 100.0, 10.0));
+        }
+        queue.enqueue(create_test_order("low", 10.0, 10.0));
+
+        // 连续 3 个 Critical 后让出一次给等待中的 Low
+        let order: Vec<OrderPriority> = (0..7).map(|_| queue.dequeue().unwrap().priority).collect();
+        assert_eq!(
+            order,
+            vec![
+                OrderPriority::Critical,
+                OrderPriority::Critical,
+                OrderPriority::Critical,
+                OrderPriority::Low,
+                OrderPriority::Critical,
+                OrderPriority::Critical,
+                OrderPriority::Critical,
+            ]
+        );
+
+        let stats = queue.get_statistics();
+        assert_eq!(stats.starvation_promotions, 1);
+        assert_eq!(stats.dispatched.critical, 6);
+        assert_eq!(stats.dispatched.low, 1);
+        assert_eq!(stats.enqueued.low, 1);
+    }
+
+    #[test]
+    fn test_aged_order_dequeued_first() {
+        let queue = PriorityOrderQueue::new(100, 1_000_000.0);
+        queue.set_starvation_guard(0, 50);
+        queue.add_vip_user("vip".to_string());
+
+        queue.enqueue(create_test_order("normal", 100.0, 10.0));
+        queue.enqueue(create_test_order("vip", 100.0, 10.0));
+
+        // 未超时按优先级；Normal 排队超过 50ms 后先出队
+        let now = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
+        let aged = queue.dequeue_at(now + 60_000_000, true).unwrap();
+        assert_eq!(aged.priority, OrderPriority::Normal);
+        assert_eq!(queue.dequeue().unwrap().priority, OrderPriority::Critical);
+        assert_eq!(queue.get_statistics().starvation_promotions, 1);
+    }
 }
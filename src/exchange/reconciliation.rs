@@ -0,0 +1,276 @@
+//! 结算对账报告
+//!
+//! @yutiansut @quantaxis
+//!
+//! 日终结算后汇总全市场账户数据并校验资金守恒：
+//!
+//! ```text
+//! 期初权益 + 入金 - 出金 + 平仓盈亏 + 持仓盈亏 - 手续费 + 利息 = 期末权益
+//! ```
+//!
+//! - 所有金额先换算为"分"（i64）再汇总，避免浮点累加误差导致误报
+//! - 逐账户计算差额，超出容差的账户列入差异明细并告警
+
+use serde::{Deserialize, Serialize};
+
+/// 默认容差（分）
+pub const DEFAULT_TOLERANCE_CENTS: i64 = 1;
+
+/// 金额换算为分（四舍五入）
+pub fn to_cents(amount: f64) -> i64 {
+    (amount * 100.0).round() as i64
+}
+
+/// 分换算为金额
+pub fn from_cents(cents: i64) -> f64 {
+    cents as f64 / 100.0
+}
+
+/// 单账户对账输入（结算前后采集）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AccountReconciliationInput {
+    pub account_id: String,
+    /// 期初权益
+    pub opening_balance: f64,
+    /// 当日入金
+    pub deposit: f64,
+    /// 当日出金
+    pub withdraw: f64,
+    /// 平仓盈亏
+    pub close_profit: f64,
+    /// 持仓盈亏（按结算价）
+    pub position_profit: f64,
+    /// 手续费
+    pub commission: f64,
+    /// 结算计息（负数为收取）
+    pub interest: f64,
+    /// 期末权益（结算后）
+    pub closing_balance: f64,
+    /// 保证金占用（结算后）
+    pub margin: f64,
+    /// 当日成交额
+    pub turnover: f64,
+}
+
+impl AccountReconciliationInput {
+    /// 按资金守恒推算的期末权益（分）
+    fn expected_closing_cents(&self) -> i64 {
+        to_cents(
+            self.opening_balance + self.deposit - self.withdraw
+                + self.close_profit
+                + self.position_profit
+                - self.commission
+                + self.interest,
+        )
+    }
+}
+
+/// 差异账户
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountDiscrepancy {
+    pub account_id: String,
+    /// 推算期末权益
+    pub expected_balance: f64,
+    /// 实际期末权益
+    pub actual_balance: f64,
+    /// 差额（实际 - 推算）
+    pub difference: f64,
+}
+
+/// 对账报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReconciliationReport {
+    /// 结算日期
+    pub settlement_date: String,
+    /// 参与对账账户数
+    pub account_count: usize,
+    /// 期初总权益
+    pub total_opening_balance: f64,
+    /// 期末总权益
+    pub total_closing_balance: f64,
+    /// 保证金占用总额
+    pub total_margin: f64,
+    /// 当日成交额
+    pub total_turnover: f64,
+    /// 手续费总额
+    pub total_commission: f64,
+    /// 平仓盈亏总额
+    pub total_close_profit: f64,
+    /// 持仓盈亏总额
+    pub total_position_profit: f64,
+    /// 利息总额
+    #[serde(default)]
+    pub total_interest: f64,
+    /// 入金总额
+    pub total_deposit: f64,
+    /// 出金总额
+    pub total_withdraw: f64,
+    /// 出入金净额
+    pub net_deposit: f64,
+    /// 推算期末总权益
+    pub expected_closing_balance: f64,
+    /// 总差额（实际 - 推算）
+    pub total_difference: f64,
+    /// 容差（元）
+    pub tolerance: f64,
+    /// 资金守恒校验是否通过
+    pub balanced: bool,
+    /// 差异账户明细（按差额绝对值降序）
+    pub discrepancies: Vec<AccountDiscrepancy>,
+    /// 生成时间
+    pub generated_at: String,
+}
+
+impl ReconciliationReport {
+    /// 由账户对账输入生成报告
+    pub fn build(
+        settlement_date: &str,
+        inputs: &[AccountReconciliationInput],
+        tolerance_cents: i64,
+    ) -> Self {
+        let mut opening = 0i64;
+        let mut closing = 0i64;
+        let mut margin = 0i64;
+        let mut turnover = 0i64;
+        let mut commission = 0i64;
+        let mut close_profit = 0i64;
+        let mut position_profit = 0i64;
+        let mut interest = 0i64;
+        let mut deposit = 0i64;
+        let mut withdraw = 0i64;
+        let mut expected = 0i64;
+        let mut discrepancies = Vec::new();
+
+        for input in inputs {
+            opening += to_cents(input.opening_balance);
+            closing += to_cents(input.closing_balance);
+            margin += to_cents(input.margin);
+            turnover += to_cents(input.turnover);
+            commission += to_cents(input.commission);
+            close_profit += to_cents(input.close_profit);
+            position_profit += to_cents(input.position_profit);
+            interest += to_cents(input.interest);
+            deposit += to_cents(input.deposit);
+            withdraw += to_cents(input.withdraw);
+
+            let account_expected = input.expected_closing_cents();
+            let account_actual = to_cents(input.closing_balance);
+            expected += account_expected;
+
+            let diff = account_actual - account_expected;
+            if diff.abs() > tolerance_cents {
+                discrepancies.push(AccountDiscrepancy {
+                    account_id: input.account_id.clone(),
+                    expected_balance: from_cents(account_expected),
+                    actual_balance: from_cents(account_actual),
+                    difference: from_cents(diff),
+                });
+            }
+        }
+        discrepancies.sort_by(|a, b| {
+            b.difference
+                .abs()
+                .partial_cmp(&a.difference.abs())
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.account_id.cmp(&b.account_id))
+        });
+
+        let total_diff = closing - expected;
+        Self {
+            settlement_date: settlement_date.to_string(),
+            account_count: inputs.len(),
+            total_opening_balance: from_cents(opening),
+            total_closing_balance: from_cents(closing),
+            total_margin: from_cents(margin),
+            total_turnover: from_cents(turnover),
+            total_commission: from_cents(commission),
+            total_close_profit: from_cents(close_profit),
+            total_position_profit: from_cents(position_profit),
+            total_interest: from_cents(interest),
+            total_deposit: from_cents(deposit),
+            total_withdraw: from_cents(withdraw),
+            net_deposit: from_cents(deposit - withdraw),
+            expected_closing_balance: from_cents(expected),
+            total_difference: from_cents(total_diff),
+            tolerance: from_cents(tolerance_cents),
+            balanced: discrepancies.is_empty(),
+            discrepancies,
+            generated_at: crate::utils::time_service::now_local()
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(account_id: &str, opening: f64, closing: f64) -> AccountReconciliationInput {
+        AccountReconciliationInput {
+            account_id: account_id.to_string(),
+            opening_balance: opening,
+            closing_balance: closing,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_conservation_passes_with_float_noise() {
+        // 0.1 + 0.2 != 0.3 的浮点误差不应触发不平衡
+        let mut a = input(
+            "acc_a",
+            100000.1,
+            100000.0 + 0.1 + 0.2 - 0.3 + 1250.37 - 3.33,
+        );
+        a.close_profit = 1250.37;
+        a.commission = 3.33;
+        a.margin = 20000.0;
+        a.turnover = 400000.0;
+
+        let mut b = input("acc_b", 50000.0, 50000.0 + 5000.0 - 1200.5 - 820.45);
+        b.deposit = 5000.0;
+        b.withdraw = 1200.5;
+        b.position_profit = -820.45;
+        b.margin = 8000.0;
+
+        let inputs: Vec<_> = (0..1000)
+            .map(|i| input(&format!("acc_{}", i), 0.1, 0.1))
+            .chain([a, b])
+            .collect();
+        let report = ReconciliationReport::build("2026-10-16", &inputs, DEFAULT_TOLERANCE_CENTS);
+
+        assert!(report.balanced, "{:?}", report.discrepancies);
+        assert_eq!(report.account_count, 1002);
+        assert_eq!(report.total_opening_balance, 150100.1);
+        assert_eq!(report.net_deposit, 3799.5);
+        assert_eq!(report.total_margin, 28000.0);
+        assert_eq!(report.total_commission, 3.33);
+        assert_eq!(report.total_difference, 0.0);
+        assert_eq!(
+            report.total_closing_balance,
+            report.expected_closing_balance
+        );
+    }
+
+    #[test]
+    fn test_conservation_fails_and_locates_accounts() {
+        let ok = input("acc_ok", 10000.0, 10000.0);
+        let small = input("acc_small", 10000.0, 10000.05);
+        let large = input("acc_large", 20000.0, 19000.0);
+
+        let report =
+            ReconciliationReport::build("2026-10-16", &[ok, small, large], DEFAULT_TOLERANCE_CENTS);
+
+        assert!(!report.balanced);
+        let ids: Vec<_> = report
+            .discrepancies
+            .iter()
+            .map(|d| d.account_id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["acc_large", "acc_small"]);
+        assert_eq!(report.discrepancies[0].difference, -1000.0);
+        assert_eq!(report.discrepancies[1].difference, 0.05);
+        assert_eq!(report.total_difference, -999.95);
+    }
+}
@@ -10,21 +10,39 @@
 //! - **批量聚合**: 减少锁竞争和内存分配
 
 use std::collections::HashMap;
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
 use std::sync::{Arc, Weak};
 use std::time::Instant;
 
 use chrono::Utc;
 use dashmap::DashMap;
 use log;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use super::{AccountManager, OrderRouter};
-use crate::exchange::order_router::SubmitOrderRequest;
+use super::deficit::{allocate_pro_rata, DeficitRecord, DeficitSource, RiskReserveStatus};
+use super::interest::{InterestBasis, InterestConfig};
+use super::reconciliation::{
+    from_cents, to_cents, AccountReconciliationInput, ReconciliationReport, DEFAULT_TOLERANCE_CENTS,
+};
+use super::settlement_task::{
+    FailedAccount, SettlementProgress, SettlementTask, SettlementTaskConfig, SettlementTaskStore,
+    ShardOutput, ShardStatus, SETTLEMENT_OPERATOR,
+};
+use super::{AccountManager, CapitalManager, OrderRouter, PositionCost, TradingRestriction};
+use crate::core::account_ext::AccountType;
+use crate::exchange::order_router::{OrderStatus, SubmitOrderRequest};
 use crate::market::MarketDataService;
-use crate::risk::RiskMonitor;
+use crate::notification::message::{
+    MarginCallNotify, Notification, NotificationPayload, NotificationType, RiskAlertNotify,
+};
+use crate::notification::{SystemEvent, SystemEventCategory, SystemEventLevel};
+use crate::risk::{PriceLimitManager, RiskMonitor};
+use crate::service::http::account_admin::log_audit;
+use crate::service::http::models::{AuditLogType, AuditResult};
+use crate::utils::time_service::{time_service, TimeService};
 use crate::ExchangeError;
 
 /// 结算结果
@@ -51,6 +69,10 @@ pub struct SettlementResult {
     /// 总盈亏
     pub total_profit: f64,
 
+    /// 总利息（负数为收取）
+    #[serde(default)]
+    pub total_interest: f64,
+
     /// 结算耗时（毫秒）
     #[serde(default)]
     pub elapsed_ms: u64,
@@ -71,6 +93,8 @@ struct PreCalculatedSettlement {
     close_profit: f64,
     /// 手续费
     commission: f64,
+    /// 利息
+    interest: f64,
     /// 结算前权益
     pre_balance: f64,
     /// 结算后权益
@@ -81,6 +105,14 @@ struct PreCalculatedSettlement {
     risk_ratio: f64,
     /// 是否需要强平
     need_force_close: bool,
+    /// 期初权益（对账用）
+    opening_balance: f64,
+    /// 当日入金
+    deposit: f64,
+    /// 当日出金
+    withdraw: f64,
+    /// 当日成交额
+    turnover: f64,
 }
 
 /// 账户结算信息
@@ -91,6 +123,8 @@ pub struct AccountSettlement {
     pub close_profit: f64,    // 平仓盈亏
     pub position_profit: f64, // 持仓盈亏
     pub commission: f64,      // 手续费
+    #[serde(default)]
+    pub interest: f64,        // 利息（负数为收取）
     pub pre_balance: f64,     // 结算前权益
     pub balance: f64,         // 结算后权益
     pub risk_ratio: f64,      // 风险度
@@ -291,6 +325,9 @@ pub struct SettlementEngine {
     /// 风险监控器（记录强平）
     risk_monitor: Arc<RwLock<Option<Arc<RiskMonitor>>>>,
 
+    /// 涨跌停板管理器（日终按结算价判断连续停板与扩板）
+    price_limit_manager: Arc<RwLock<Option<Arc<PriceLimitManager>>>>,
+
     // ========== 性能统计 ==========
     /// 总结算账户数（原子计数）
     stats_settled_count: AtomicU64,
@@ -319,6 +356,39 @@ pub struct SettlementEngine {
 
     /// 最大重试次数
     max_retry_count: u32,
+
+    /// 对账报告 (settlement_date -> ReconciliationReport)
+    reconciliation_history: Arc<DashMap<String, ReconciliationReport>>,
+
+    // ========== 穿仓处理 ==========
+    /// 风险准备金账户（垫付穿仓）
+    risk_reserve_account: Arc<RwLock<Option<String>>>,
+
+    /// 穿仓告警接收人（管理员用户ID）
+    deficit_alert_recipients: Arc<RwLock<Vec<String>>>,
+
+    /// 穿仓记录 (settlement_date -> Vec<DeficitRecord>)
+    deficit_history: Arc<DashMap<String, Vec<DeficitRecord>>>,
+
+    // ========== 闲置资金计息 ==========
+    /// 计息配置（按账户类型的年化利率）
+    interest_config: Arc<RwLock<InterestConfig>>,
+
+    /// 资金管理器（记录利息流水）
+    capital_mgr: Arc<RwLock<Option<Arc<CapitalManager>>>>,
+
+    /// 上次日终结算时间（毫秒，日均余额计息区间起点）
+    last_settlement_ms: AtomicI64,
+
+    // ========== 分片结算与断点续跑 ==========
+    /// 结算任务配置
+    task_config: Arc<RwLock<SettlementTaskConfig>>,
+
+    /// 结算任务 (settlement_date -> SettlementTask)
+    settlement_tasks: Arc<DashMap<String, Arc<Mutex<SettlementTask>>>>,
+
+    /// 时间源（结算日期、任务时间、计息区间；测试环境可为虚拟时间）
+    time_service: Arc<RwLock<Arc<TimeService>>>,
 }
 
 /// 强平任务
@@ -355,6 +425,7 @@ impl SettlementEngine {
             order_router: Arc::new(RwLock::new(None)),
             market_data_service: Arc::new(RwLock::new(None)),
             risk_monitor: Arc::new(RwLock::new(None)),
+            price_limit_manager: Arc::new(RwLock::new(None)),
             stats_settled_count: AtomicU64::new(0),
             stats_total_time_us: AtomicU64::new(0),
             force_close_queue: Arc::new(sender),
@@ -365,13 +436,27 @@ impl SettlementEngine {
             account_liquidations: Arc::new(DashMap::new()),
             liquidation_seq: AtomicU64::new(1),
             max_retry_count: 3,
+            reconciliation_history: Arc::new(DashMap::new()),
+            risk_reserve_account: Arc::new(RwLock::new(None)),
+            deficit_alert_recipients: Arc::new(RwLock::new(vec!["admin".to_string()])),
+            deficit_history: Arc::new(DashMap::new()),
+            interest_config: Arc::new(RwLock::new(InterestConfig::default())),
+            capital_mgr: Arc::new(RwLock::new(None)),
+            last_settlement_ms: AtomicI64::new(i64::MIN),
+            task_config: Arc::new(RwLock::new(SettlementTaskConfig::default())),
+            settlement_tasks: Arc::new(DashMap::new()),
+            time_service: Arc::new(RwLock::new(time_service())),
         }
     }
 
     /// 生成强平ID
     fn generate_liquidation_id(&self) -> String {
         let seq = self.liquidation_seq.fetch_add(1, Ordering::SeqCst);
-        format!("LIQ{}{:08}", Utc::now().format("%Y%m%d"), seq)
+        format!(
+            "LIQ{}{:08}",
+            self.time_service().now().format("%Y%m%d"),
+            seq
+        )
     }
 
     /// 获取强平记录
@@ -468,6 +553,10 @@ impl SettlementEngine {
                                 order_type: "LIMIT".to_string(),
                                 time_condition: None,
                                 volume_condition: None,
+                                hedge_flag: None,
+                                ttl_secs: None,
+                                client_order_id: None,
+                                close_lot_id: None,
                             };
 
                             let _ = router.submit_force_order(submit_req);
@@ -580,6 +669,44 @@ impl SettlementEngine {
         *self.risk_monitor.write() = Some(monitor);
     }
 
+    /// 注入涨跌停板管理器
+    pub fn set_price_limit_manager(&self, manager: Arc<PriceLimitManager>) {
+        *self.price_limit_manager.write() = Some(manager);
+    }
+
+    /// 注入资金管理器（记录利息流水）
+    pub fn set_capital_manager(&self, capital_mgr: Arc<CapitalManager>) {
+        *self.capital_mgr.write() = Some(capital_mgr);
+    }
+
+    /// 注入时间服务（与交易日管理、时间控制共享时间源）
+    pub fn set_time_service(&self, service: Arc<TimeService>) {
+        *self.time_service.write() = service;
+    }
+
+    /// 当前时间服务
+    pub fn time_service(&self) -> Arc<TimeService> {
+        self.time_service.read().clone()
+    }
+
+    /// 设置计息配置
+    pub fn set_interest_config(&self, config: InterestConfig) {
+        log::info!(
+            "[Settlement] Interest config: basis={:?}, day_basis={}, rates=({}, {}, {})",
+            config.basis,
+            config.day_basis,
+            config.individual_rate,
+            config.institutional_rate,
+            config.market_maker_rate
+        );
+        *self.interest_config.write() = config;
+    }
+
+    /// 获取计息配置
+    pub fn get_interest_config(&self) -> InterestConfig {
+        self.interest_config.read().clone()
+    }
+
     /// 设置结算价
     pub fn set_settlement_price(&self, instrument_id: String, price: f64) {
         log::info!("Settlement price set: {} = {}", instrument_id, price);
@@ -597,144 +724,262 @@ impl SettlementEngine {
         );
     }
 
-    /// 执行日终结算（高性能并行版本）
+    /// 执行日终结算（账户分片并行，支持断点续跑）
     ///
-    /// ## 三阶段处理流程
-    /// 1. **预计算阶段** (并行，只读锁): 计算所有账户的结算数据
-    /// 2. **应用阶段** (并行，短写锁): 应用结算结果到账户
-    /// 3. **强平阶段** (异步队列): 将强平任务入队，不阻塞主流程
+    /// ## 处理流程
+    /// 1. **任务准备**: 同日已有任务时续跑未完成分片，否则按账户 ID 排序切分分片
+    /// 2. **分片结算** (Rayon 并行): 分片内账户串行执行 预计算(只读锁) -> 应用(短写锁)，
+    ///    每完成一个分片落盘进度，可选提前解除该分片已结算账户的交易限制
+    /// 3. **汇总阶段**: 由全部分片产出重建汇总与对账，强平任务异步入队，不阻塞主流程
     ///
     /// ## 性能特性
-    /// - 使用 Rayon 并行处理，充分利用多核 CPU
-    /// - 10,000 账户结算时间: ~60 秒 (8 核) vs ~500 秒 (单线程)
+    /// - 分片间并行、分片内串行，单账户只在自己的分片内加锁
+    /// - 100,000 账户结算目标 2 分钟内完成（8 核，分片 1000）
+    /// - 汇总按账户 ID 顺序累加，并行结果与串行结算一致
     pub fn daily_settlement(&self) -> Result<SettlementResult, ExchangeError> {
         // 确保强平线程已启动，避免任务丢失
         self.start_force_close_worker();
 
         let start_time = Instant::now();
-        let settlement_date = Utc::now().format("%Y-%m-%d").to_string();
-        let parallelism = rayon::current_num_threads();
+        let settlement_date = self.time_service().now().format("%Y-%m-%d").to_string();
+        let config = self.task_config.read().clone();
+        let store = SettlementTaskStore::new(&config.state_dir);
+        let parallelism = if config.parallelism > 0 {
+            config.parallelism
+        } else {
+            rayon::current_num_threads()
+        };
+
+        self.publish_system_event(
+            SystemEvent::new(
+                SystemEventCategory::Settlement,
+                SystemEventLevel::Info,
+                "日终结算开始",
+            )
+            .with_details(serde_json::json!({
+                "settlement_date": settlement_date,
+                "parallelism": parallelism,
+            })),
+        );
+
+        let task = match self.load_settlement_task(&store, &settlement_date)? {
+            Some(task) => task,
+            None => {
+                // 日终落盘当日拒单统计
+                if let Some(router) = self
+                    .order_router
+                    .read()
+                    .as_ref()
+                    .and_then(|weak| weak.upgrade())
+                {
+                    if let Err(e) = router.rejection_stats().flush() {
+                        log::warn!("[Settlement] Failed to persist rejection stats: {}", e);
+                    }
+                }
+
+                // 涨跌停扩板判断：结算价作为收盘参考与次日基准价
+                self.update_price_limits(&settlement_date);
+
+                let account_ids: Vec<String> = self
+                    .account_mgr
+                    .get_all_accounts()
+                    .iter()
+                    .map(|account| account.read().account_cookie.clone())
+                    .collect();
+
+                if account_ids.is_empty() {
+                    self.reconcile(&settlement_date, &[]);
+                    let result = SettlementResult {
+                        settlement_date,
+                        total_accounts: 0,
+                        settled_accounts: 0,
+                        failed_accounts: 0,
+                        force_closed_accounts: vec![],
+                        total_commission: 0.0,
+                        total_profit: 0.0,
+                        total_interest: 0.0,
+                        elapsed_ms: 0,
+                        parallelism,
+                    };
+                    self.notify_settlement_finished(&result);
+                    return Ok(result);
+                }
+
+                let task = SettlementTask::new(
+                    &settlement_date,
+                    account_ids,
+                    config.shard_size,
+                    self.interest_window(),
+                    self.time_service().now_millis(),
+                );
+                if let Err(e) = store.save_accounts(&task) {
+                    log::error!("[Settlement] Failed to persist settlement accounts: {}", e);
+                }
+                self.register_settlement_task(task)
+            }
+        };
+
+        // 待处理分片：未处理分片结算全部账户，失败分片只重试失败账户
+        let (jobs, interest_window) = {
+            let task = task.lock();
+            let jobs: Vec<(usize, Vec<String>)> = task
+                .pending_shards()
+                .into_iter()
+                .map(|index| match task.outputs.get(&index) {
+                    Some(output) => (
+                        index,
+                        output.failed.iter().map(|f| f.account_id.clone()).collect(),
+                    ),
+                    None => (index, task.shard_accounts(index).to_vec()),
+                })
+                .collect();
+            (jobs, task.interest_window)
+        };
 
         log::info!(
-            "[Settlement] Starting parallel settlement for {} with {} threads",
+            "[Settlement] Starting settlement for {}: {} shard(s) to process with {} threads",
             settlement_date,
+            jobs.len(),
             parallelism
         );
 
-        // 获取所有账户
-        let accounts = self.account_mgr.get_all_accounts();
-        let total_accounts = accounts.len();
+        let mut new_settlements: Vec<AccountSettlement> = Vec::new();
+        if !jobs.is_empty() {
+            {
+                let mut task = task.lock();
+                task.begin_run(self.time_service().now_millis());
+                if let Err(e) = store.save_task(&task) {
+                    log::error!("[Settlement] Failed to persist settlement task: {}", e);
+                }
+            }
 
-        if total_accounts == 0 {
-            return Ok(SettlementResult {
-                settlement_date,
-                total_accounts: 0,
-                settled_accounts: 0,
-                failed_accounts: 0,
-                force_closed_accounts: vec![],
-                total_commission: 0.0,
-                total_profit: 0.0,
-                elapsed_ms: 0,
-                parallelism,
-            });
-        }
+            if config.restrict_trading {
+                self.restrict_unsettled_accounts(&jobs, &config);
+            }
 
-        // ========== Phase 1: 并行预计算 (只读锁) ==========
-        let phase1_start = Instant::now();
-        let pre_calcs: Vec<Option<PreCalculatedSettlement>> = accounts
-            .par_iter()
-            .map(|account| self.pre_calculate_account(account))
-            .collect();
-        let phase1_elapsed = phase1_start.elapsed();
+            // 分片并行、分片内串行，每个分片完成后落盘
+            let run_shard = |(index, account_ids): &(usize, Vec<String>)| {
+                let shard_start = Instant::now();
+                let output = self.settle_accounts(account_ids, interest_window, &settlement_date);
+                let settlements = output.settlements.clone();
 
-        log::debug!(
-            "[Settlement] Phase 1 (pre-calc) completed in {:?} for {} accounts",
-            phase1_elapsed,
-            total_accounts
-        );
+                if config.restrict_trading && config.early_release {
+                    self.release_settled_accounts(&settlements);
+                }
 
-        // ========== Phase 2: 并行应用结算 (短写锁) ==========
-        let phase2_start = Instant::now();
-        let results: Vec<Result<AccountSettlement, String>> = accounts
-            .par_iter()
-            .zip(pre_calcs.par_iter())
-            .map(|(account, pre_calc)| {
-                if let Some(calc) = pre_calc {
-                    self.apply_settlement(account, calc, &settlement_date)
-                } else {
-                    Err("Pre-calculation failed".to_string())
+                let mut task = task.lock();
+                let merged = task.record_shard(
+                    *index,
+                    output,
+                    account_ids.len(),
+                    shard_start.elapsed().as_millis() as u64,
+                    self.time_service().now_millis(),
+                );
+                if let Err(e) = store.save_shard(&settlement_date, *index, merged) {
+                    log::error!("[Settlement] Failed to persist shard {}: {}", index, e);
                 }
-            })
-            .collect();
-        let phase2_elapsed = phase2_start.elapsed();
+                if let Err(e) = store.save_task(&task) {
+                    log::error!("[Settlement] Failed to persist settlement task: {}", e);
+                }
+                settlements
+            };
+            let shard_results: Vec<Vec<AccountSettlement>> = if config.parallelism > 0 {
+                match rayon::ThreadPoolBuilder::new()
+                    .num_threads(config.parallelism)
+                    .build()
+                {
+                    Ok(pool) => pool.install(|| jobs.par_iter().map(run_shard).collect()),
+                    Err(e) => {
+                        log::warn!("[Settlement] Failed to build thread pool: {}", e);
+                        jobs.par_iter().map(run_shard).collect()
+                    }
+                }
+            } else {
+                jobs.par_iter().map(run_shard).collect()
+            };
+            new_settlements = shard_results.into_iter().flatten().collect();
 
-        log::debug!(
-            "[Settlement] Phase 2 (apply) completed in {:?}",
-            phase2_elapsed
-        );
+            if config.restrict_trading && !config.early_release {
+                self.release_settled_accounts(&new_settlements);
+            }
+
+            let mut task = task.lock();
+            task.finish_run(self.time_service().now_millis());
+            if let Err(e) = store.save_task(&task) {
+                log::error!("[Settlement] Failed to persist settlement task: {}", e);
+            }
+        }
 
-        // ========== Phase 3: 异步强平入队 ==========
+        // ========== 汇总阶段：按分片（账户 ID）顺序重建 ==========
+        let (total_accounts, settled_accounts, failed_accounts) = {
+            let task = task.lock();
+            let failed: usize = task.shards.iter().map(|s| s.failed).sum();
+            let settled: usize = task.shards.iter().map(|s| s.settled).sum();
+            (task.total_accounts, settled, failed)
+        };
         let mut force_closed_accounts: Vec<String> = Vec::new();
-        let mut settled_accounts = 0;
-        let mut failed_accounts = 0;
         let mut total_commission = 0.0;
         let mut total_profit = 0.0;
-
-        for (i, result) in results.into_iter().enumerate() {
-            match result {
-                Ok(settlement) => {
-                    settled_accounts += 1;
+        let mut total_interest = 0.0;
+        let mut reconciliation_inputs = Vec::with_capacity(total_accounts);
+        let mut deficit_accounts: Vec<(String, f64)> = Vec::new();
+        {
+            let task = task.lock();
+            for index in 0..task.shards.len() {
+                let Some(output) = task.outputs.get(&index) else {
+                    continue;
+                };
+                for settlement in &output.settlements {
                     total_commission += settlement.commission;
                     total_profit += settlement.close_profit + settlement.position_profit;
-
-                    // 获取账户 ID
-                    let account_id = pre_calcs[i]
-                        .as_ref()
-                        .map(|c| c.account_id.clone())
-                        .unwrap_or_else(|| settlement.user_id.clone());
-
+                    total_interest += settlement.interest;
+                    if settlement.balance < 0.0 {
+                        deficit_accounts.push((settlement.user_id.clone(), settlement.risk_ratio));
+                    }
                     if settlement.force_close {
                         force_closed_accounts.push(settlement.user_id.clone());
-
-                        // 异步入队强平任务
-                        if let Err(e) = self.force_close_queue.send(ForceCloseTask {
-                            account_id: account_id.clone(),
-                            risk_ratio: settlement.risk_ratio,
-                            remark: Some("Settlement risk threshold".to_string()),
-                        }) {
-                            log::error!(
-                                "[Settlement] Failed to enqueue force close task for {}: {}",
-                                account_id,
-                                e
-                            );
-                        }
                     }
-
-                    // 保存账户结算历史
-                    self.account_history
-                        .entry(account_id)
-                        .and_modify(|entries| {
-                            entries.push(settlement.clone());
-                            if entries.len() > 180 {
-                                let drop = entries.len().saturating_sub(180);
-                                entries.drain(0..drop);
-                            }
-                        })
-                        .or_insert_with(|| vec![settlement]);
                 }
-                Err(e) => {
-                    failed_accounts += 1;
-                    log::error!("[Settlement] Account {} failed: {}", i, e);
+                reconciliation_inputs.extend(output.reconciliation.iter().cloned());
+            }
+        }
+
+        // 本轮新结算账户：强平入队、账户结算历史
+        for settlement in new_settlements {
+            let account_id = settlement.user_id.clone();
+            if settlement.force_close {
+                // 异步入队强平任务
+                if let Err(e) = self.force_close_queue.send(ForceCloseTask {
+                    account_id: account_id.clone(),
+                    risk_ratio: settlement.risk_ratio,
+                    remark: Some("Settlement risk threshold".to_string()),
+                }) {
+                    log::error!(
+                        "[Settlement] Failed to enqueue force close task for {}: {}",
+                        account_id,
+                        e
+                    );
                 }
             }
+
+            // 保存账户结算历史
+            self.stats_settled_count.fetch_add(1, Ordering::Relaxed);
+            self.account_history
+                .entry(account_id)
+                .and_modify(|entries| {
+                    entries.push(settlement.clone());
+                    if entries.len() > 180 {
+                        let drop = entries.len().saturating_sub(180);
+                        entries.drain(0..drop);
+                    }
+                })
+                .or_insert_with(|| vec![settlement]);
         }
 
         let elapsed = start_time.elapsed();
         let elapsed_ms = elapsed.as_millis() as u64;
 
         // 更新统计
-        self.stats_settled_count
-            .fetch_add(settled_accounts as u64, Ordering::Relaxed);
         self.stats_total_time_us
             .fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
 
@@ -746,14 +991,26 @@ impl SettlementEngine {
             force_closed_accounts: force_closed_accounts.clone(),
             total_commission,
             total_profit,
+            total_interest,
             elapsed_ms,
             parallelism,
         };
 
+        self.last_settlement_ms
+            .store(interest_window.1, Ordering::Relaxed);
+
         // 保存结算结果
         self.settlement_history
             .insert(settlement_date.clone(), result.clone());
 
+        // 生成对账报告
+        self.reconcile(&settlement_date, &reconciliation_inputs);
+
+        // 穿仓处理：风险准备金垫付（按当前权益判断，重跑不会重复垫付）
+        if !deficit_accounts.is_empty() {
+            self.cover_deficits(&settlement_date, &deficit_accounts);
+        }
+
         log::info!(
             "[Settlement] Completed in {}ms: settled={}, failed={}, force_closed={}, threads={}",
             elapsed_ms,
@@ -763,13 +1020,605 @@ impl SettlementEngine {
             parallelism
         );
 
+        self.notify_settlement_finished(&result);
+
         Ok(result)
     }
 
+    /// 发布系统事件（运维监控频道）
+    fn publish_system_event(&self, event: SystemEvent) {
+        if let Some(broker) = self.account_mgr.notification_broker() {
+            broker.publish_system_event(event, "SettlementEngine");
+        }
+    }
+
+    /// 发布结算结束事件（有失败账户时为 WARNING）
+    fn notify_settlement_finished(&self, result: &SettlementResult) {
+        let level = if result.failed_accounts > 0 {
+            SystemEventLevel::Warning
+        } else {
+            SystemEventLevel::Info
+        };
+        self.publish_system_event(
+            SystemEvent::new(SystemEventCategory::Settlement, level, "日终结算完成").with_details(
+                serde_json::json!({
+                    "settlement_date": result.settlement_date,
+                    "total_accounts": result.total_accounts,
+                    "settled_accounts": result.settled_accounts,
+                    "failed_accounts": result.failed_accounts,
+                    "force_closed_accounts": result.force_closed_accounts.len(),
+                    "elapsed_ms": result.elapsed_ms,
+                }),
+            ),
+        );
+    }
+
+    /// 发布强平事件（存在被拒强平单时为 ERROR）
+    fn notify_liquidation_event(&self, result: &ForceLiquidationResult) {
+        let level = if result.failed_count() > 0 {
+            SystemEventLevel::Error
+        } else {
+            SystemEventLevel::Warning
+        };
+        self.publish_system_event(
+            SystemEvent::new(
+                SystemEventCategory::Liquidation,
+                level,
+                format!("账户 {} 强平", result.account_id),
+            )
+            .with_details(serde_json::json!({
+                "liquidation_id": result.liquidation_id,
+                "account_id": result.account_id,
+                "trigger_risk_ratio": result.trigger_risk_ratio,
+                "orders": result.orders.len(),
+                "failed_orders": result.failed_count(),
+                "status": result.overall_status,
+                "remark": result.remark,
+            })),
+        );
+    }
+
+    /// 串行结算一组账户（分片内），单账户失败或 panic 不影响其他账户
+    fn settle_accounts(
+        &self,
+        account_ids: &[String],
+        interest_window: (i64, i64),
+        date: &str,
+    ) -> ShardOutput {
+        let mut output = ShardOutput::default();
+        for account_id in account_ids {
+            let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+                self.settle_account(account_id, interest_window, date)
+            }))
+            .unwrap_or_else(|_| Err("Settlement panicked".to_string()));
+
+            match outcome {
+                Ok((settlement, input)) => {
+                    output.settlements.push(settlement);
+                    output.reconciliation.push(input);
+                }
+                Err(reason) => {
+                    log::error!("[Settlement] Account {} failed: {}", account_id, reason);
+                    output.failed.push(FailedAccount {
+                        account_id: account_id.clone(),
+                        reason,
+                    });
+                }
+            }
+        }
+        output
+    }
+
+    /// 结算单个账户，返回结算信息与对账输入
+    fn settle_account(
+        &self,
+        account_id: &str,
+        interest_window: (i64, i64),
+        date: &str,
+    ) -> Result<(AccountSettlement, AccountReconciliationInput), String> {
+        let account = self
+            .account_mgr
+            .get_account(account_id)
+            .map_err(|e| e.to_string())?;
+        let calc = self
+            .pre_calculate_account(&account, interest_window)
+            .ok_or_else(|| "Pre-calculation failed".to_string())?;
+        let settlement = self.apply_settlement(&account, &calc, date)?;
+
+        let input = AccountReconciliationInput {
+            account_id: calc.account_id.clone(),
+            opening_balance: calc.opening_balance,
+            deposit: calc.deposit,
+            withdraw: calc.withdraw,
+            close_profit: calc.close_profit,
+            position_profit: calc.position_profit,
+            commission: calc.commission,
+            interest: calc.interest,
+            closing_balance: settlement.balance,
+            margin: settlement.margin,
+            turnover: calc.turnover,
+        };
+        Ok((settlement, input))
+    }
+
+    /// 加载同日结算任务（内存优先，其次落盘目录），并补齐已处理分片的产出
+    fn load_settlement_task(
+        &self,
+        store: &SettlementTaskStore,
+        settlement_date: &str,
+    ) -> Result<Option<Arc<Mutex<SettlementTask>>>, ExchangeError> {
+        if let Some(task) = self.settlement_tasks.get(settlement_date) {
+            return Ok(Some(task.value().clone()));
+        }
+        let Some(mut task) = store.load(settlement_date)? else {
+            return Ok(None);
+        };
+
+        for shard in task
+            .shards
+            .iter()
+            .filter(|s| s.status != ShardStatus::Pending)
+        {
+            match store.load_shard(settlement_date, shard.index)? {
+                Some(output) => {
+                    task.outputs.insert(shard.index, output);
+                }
+                None => {
+                    return Err(ExchangeError::SettlementError(format!(
+                        "Settlement task {} is missing output of shard {}",
+                        settlement_date, shard.index
+                    )));
+                }
+            }
+        }
+
+        log::info!(
+            "[Settlement] Resuming task {} from {:?} (run {}, {} pending shard(s))",
+            settlement_date,
+            task.status,
+            task.runs + 1,
+            task.pending_shards().len()
+        );
+        Ok(Some(self.register_settlement_task(task)))
+    }
+
+    /// 登记结算任务（其他结算日只保留进度，释放分片产出）
+    fn register_settlement_task(&self, task: SettlementTask) -> Arc<Mutex<SettlementTask>> {
+        for entry in self.settlement_tasks.iter() {
+            entry.value().lock().outputs.clear();
+        }
+        let date = task.settlement_date.clone();
+        let task = Arc::new(Mutex::new(task));
+        self.settlement_tasks.insert(date, task.clone());
+        task
+    }
+
+    /// 结算期间暂停待结算账户交易（已有生效限制的账户保持原限制）
+    fn restrict_unsettled_accounts(
+        &self,
+        jobs: &[(usize, Vec<String>)],
+        config: &SettlementTaskConfig,
+    ) {
+        let account_ids: Vec<String> = jobs
+            .iter()
+            .flat_map(|(_, ids)| ids.iter())
+            .filter(|id| self.account_mgr.get_trading_restriction(id).is_none())
+            .cloned()
+            .collect();
+        let expires_at =
+            self.time_service().now_millis() + config.restriction_timeout_secs as i64 * 1000;
+        if let Err(e) = self.account_mgr.set_trading_restrictions(
+            &account_ids,
+            TradingRestriction::Suspended,
+            "日终结算中",
+            Some(expires_at),
+            SETTLEMENT_OPERATOR,
+        ) {
+            log::error!("[Settlement] Failed to restrict accounts: {}", e);
+        }
+    }
+
+    /// 解除已结算账户的结算限制（其他来源的限制不受影响）
+    fn release_settled_accounts(&self, settlements: &[AccountSettlement]) {
+        let account_ids: Vec<String> = settlements
+            .iter()
+            .filter(|s| {
+                self.account_mgr
+                    .get_trading_restriction(&s.user_id)
+                    .is_some_and(|info| info.updated_by == SETTLEMENT_OPERATOR)
+            })
+            .map(|s| s.user_id.clone())
+            .collect();
+        if let Err(e) = self.account_mgr.set_trading_restrictions(
+            &account_ids,
+            TradingRestriction::Normal,
+            "日终结算完成",
+            None,
+            SETTLEMENT_OPERATOR,
+        ) {
+            log::error!("[Settlement] Failed to release settled accounts: {}", e);
+        }
+    }
+
+    /// 设置结算任务配置（分片大小、并行度、落盘目录、结算期间交易限制）
+    pub fn set_task_config(&self, config: SettlementTaskConfig) {
+        log::info!(
+            "[Settlement] Task config: shard_size={}, parallelism={}, state_dir={:?}, restrict_trading={}",
+            config.shard_size,
+            config.parallelism,
+            config.state_dir,
+            config.restrict_trading
+        );
+        *self.task_config.write() = config;
+    }
+
+    /// 获取结算任务配置
+    pub fn get_task_config(&self) -> SettlementTaskConfig {
+        self.task_config.read().clone()
+    }
+
+    /// 查询结算进度（日期为空时取最近一次任务，进程重启后从落盘目录加载）
+    pub fn get_settlement_progress(&self, date: Option<&str>) -> Option<SettlementProgress> {
+        let now_ms = self.time_service().now_millis();
+        let Some(date) = date else {
+            return self
+                .settlement_tasks
+                .iter()
+                .map(|entry| entry.value().lock().progress(now_ms))
+                .max_by(|a, b| a.settlement_date.cmp(&b.settlement_date));
+        };
+        if let Some(task) = self.settlement_tasks.get(date) {
+            return Some(task.value().lock().progress(now_ms));
+        }
+        let store = SettlementTaskStore::new(&self.task_config.read().state_dir);
+        match store.load(date) {
+            Ok(task) => task.map(|task| task.progress(now_ms)),
+            Err(e) => {
+                log::warn!(
+                    "[Settlement] Failed to load settlement task {}: {}",
+                    date,
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    /// 生成对账报告并校验资金守恒，不平衡时告警并列出差异账户
+    fn reconcile(
+        &self,
+        settlement_date: &str,
+        inputs: &[AccountReconciliationInput],
+    ) -> ReconciliationReport {
+        let report = ReconciliationReport::build(settlement_date, inputs, DEFAULT_TOLERANCE_CENTS);
+
+        if report.balanced {
+            log::info!(
+                "[Reconciliation] {} balanced: accounts={}, closing={:.2}, net_deposit={:.2}, commission={:.2}",
+                settlement_date,
+                report.account_count,
+                report.total_closing_balance,
+                report.net_deposit,
+                report.total_commission
+            );
+        } else {
+            log::error!(
+                "[Reconciliation] {} IMBALANCED: total difference {:.2}, {} account(s) mismatched",
+                settlement_date,
+                report.total_difference,
+                report.discrepancies.len()
+            );
+            for d in report.discrepancies.iter().take(20) {
+                log::error!(
+                    "[Reconciliation]   {} expected={:.2} actual={:.2} diff={:.2}",
+                    d.account_id,
+                    d.expected_balance,
+                    d.actual_balance,
+                    d.difference
+                );
+            }
+        }
+
+        self.reconciliation_history
+            .insert(settlement_date.to_string(), report.clone());
+        report
+    }
+
+    /// 获取对账报告
+    pub fn get_reconciliation_report(&self, date: &str) -> Option<ReconciliationReport> {
+        self.reconciliation_history
+            .get(date)
+            .map(|r| r.value().clone())
+    }
+
+    /// 设置风险准备金账户（穿仓垫付来源）
+    pub fn set_risk_reserve_account(&self, account_id: Option<String>) {
+        log::info!("[Settlement] Risk reserve account set to {:?}", account_id);
+        *self.risk_reserve_account.write() = account_id;
+    }
+
+    /// 设置穿仓告警接收人
+    pub fn set_deficit_alert_recipients(&self, recipients: Vec<String>) {
+        *self.deficit_alert_recipients.write() = recipients;
+    }
+
+    /// 风险准备金状态（余额与累计垫付）
+    pub fn get_risk_reserve_status(&self) -> RiskReserveStatus {
+        let account_id = self.risk_reserve_account.read().clone();
+        let (balance, available) = account_id
+            .as_deref()
+            .and_then(|id| self.account_mgr.get_qifi_slice(id).ok())
+            .map(|qifi| (qifi.accounts.balance, qifi.accounts.available))
+            .unwrap_or((0.0, 0.0));
+
+        let mut total_covered = 0i64;
+        let mut total_uncovered = 0i64;
+        let mut record_count = 0;
+        for entry in self.deficit_history.iter() {
+            for record in entry.value() {
+                total_covered += to_cents(record.covered);
+                total_uncovered += to_cents(record.uncovered);
+                record_count += 1;
+            }
+        }
+
+        RiskReserveStatus {
+            account_id,
+            balance,
+            available,
+            total_covered: from_cents(total_covered),
+            total_uncovered: from_cents(total_uncovered),
+            record_count,
+        }
+    }
+
+    /// 查询穿仓记录（date 为空时返回全部，按结算日期排序）
+    pub fn get_deficit_records(&self, date: Option<&str>) -> Vec<DeficitRecord> {
+        if let Some(date) = date {
+            return self
+                .deficit_history
+                .get(date)
+                .map(|r| r.value().clone())
+                .unwrap_or_default();
+        }
+
+        let mut records: Vec<DeficitRecord> = self
+            .deficit_history
+            .iter()
+            .flat_map(|r| r.value().clone())
+            .collect();
+        records.sort_by(|a, b| {
+            a.settlement_date
+                .cmp(&b.settlement_date)
+                .then_with(|| a.created_at.cmp(&b.created_at))
+        });
+        records
+    }
+
+    /// 穿仓处理：统计穿仓金额与持仓来源，从风险准备金账户划转垫付
+    ///
+    /// 准备金不足时按穿仓金额比例分摊，未覆盖部分记入 `uncovered` 并告警
+    fn cover_deficits(
+        &self,
+        settlement_date: &str,
+        accounts: &[(String, f64)],
+    ) -> Vec<DeficitRecord> {
+        let reserve_id = self.risk_reserve_account.read().clone();
+
+        // 统计穿仓金额（分）与持仓来源
+        let mut deficits = Vec::with_capacity(accounts.len());
+        for (account_id, risk_ratio) in accounts {
+            if reserve_id.as_deref() == Some(account_id.as_str()) {
+                log::error!(
+                    "[Deficit] Risk reserve account {} itself is in deficit",
+                    account_id
+                );
+                continue;
+            }
+            let Ok(account) = self.account_mgr.get_account(account_id) else {
+                continue;
+            };
+            let (deficit, sources) = {
+                let mut acc = account.write();
+                let balance = acc.get_qifi_slice().accounts.balance;
+                (-to_cents(balance), self.collect_deficit_sources(&acc))
+            };
+            if deficit > 0 {
+                deficits.push((account_id.clone(), account, *risk_ratio, deficit, sources));
+            }
+        }
+        if deficits.is_empty() {
+            return Vec::new();
+        }
+
+        let reserve_account = match reserve_id.as_deref() {
+            Some(id) => match self.account_mgr.get_account(id) {
+                Ok(account) => Some(account),
+                Err(_) => {
+                    log::error!("[Deficit] Risk reserve account {} not found", id);
+                    None
+                }
+            },
+            None => {
+                log::error!("[Deficit] No risk reserve account configured");
+                None
+            }
+        };
+        let reserve_available = reserve_account
+            .as_ref()
+            .map(|account| to_cents(account.write().get_qifi_slice().accounts.available))
+            .unwrap_or(0);
+
+        let amounts: Vec<i64> = deficits.iter().map(|d| d.3).collect();
+        let allocations = allocate_pro_rata(&amounts, reserve_available);
+
+        let mut records = Vec::with_capacity(deficits.len());
+        for ((account_id, account, risk_ratio, deficit, sources), covered) in
+            deficits.into_iter().zip(allocations)
+        {
+            // 准备金出金、客户入金，两笔等额
+            if covered > 0 {
+                if let Some(reserve) = reserve_account.as_ref() {
+                    reserve.write().withdraw(from_cents(covered));
+                    account.write().deposit(from_cents(covered));
+                }
+            }
+            let balance_after = account.write().get_qifi_slice().accounts.balance;
+
+            let record = DeficitRecord {
+                record_id: uuid::Uuid::new_v4().to_string(),
+                settlement_date: settlement_date.to_string(),
+                account_id,
+                deficit: from_cents(deficit),
+                covered: from_cents(covered),
+                uncovered: from_cents(deficit - covered),
+                balance_after,
+                sources,
+                reserve_account_id: reserve_id.clone(),
+                created_at: self
+                    .time_service()
+                    .now()
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string(),
+            };
+            self.notify_deficit(&record, risk_ratio);
+            log_audit(
+                record.account_id.clone(),
+                "SettlementEngine".to_string(),
+                AuditLogType::RiskAlert,
+                "deficit_coverage".to_string(),
+                format!(
+                    "穿仓 {:.2}，风险准备金 {:?} 垫付 {:.2}，未覆盖 {:.2}",
+                    record.deficit, record.reserve_account_id, record.covered, record.uncovered
+                ),
+                None,
+                if record.is_fully_covered() {
+                    AuditResult::Success
+                } else {
+                    AuditResult::Failed
+                },
+            );
+            records.push(record);
+        }
+
+        let total_deficit: f64 = records.iter().map(|r| r.deficit).sum();
+        let total_uncovered: f64 = records.iter().map(|r| r.uncovered).sum();
+        if total_uncovered > 0.0 {
+            log::error!(
+                "[Deficit] {} {} account(s) in deficit {:.2}, reserve insufficient, uncovered {:.2}",
+                settlement_date,
+                records.len(),
+                total_deficit,
+                total_uncovered
+            );
+        } else {
+            log::warn!(
+                "[Deficit] {} {} account(s) in deficit {:.2}, fully covered by risk reserve",
+                settlement_date,
+                records.len(),
+                total_deficit
+            );
+        }
+
+        self.deficit_history
+            .entry(settlement_date.to_string())
+            .or_default()
+            .extend(records.iter().cloned());
+        records
+    }
+
+    /// 穿仓账户的持仓来源（按结算价计算持仓盈亏）
+    fn collect_deficit_sources(
+        &self,
+        acc: &qars::qaaccount::account::QA_Account,
+    ) -> Vec<DeficitSource> {
+        let mut sources: Vec<DeficitSource> = acc
+            .hold
+            .iter()
+            .filter_map(|(code, pos)| {
+                let volume_long = pos.volume_long_today + pos.volume_long_his;
+                let volume_short = pos.volume_short_today + pos.volume_short_his;
+                if volume_long <= 0.0 && volume_short <= 0.0 {
+                    return None;
+                }
+                let settlement_price = self.settlement_prices.get(code).map(|p| *p.value());
+                let position_profit = settlement_price.map_or(0.0, |price| {
+                    (price - pos.open_price_long) * volume_long.max(0.0)
+                        + (pos.open_price_short - price) * volume_short.max(0.0)
+                });
+                Some(DeficitSource {
+                    instrument_id: code.clone(),
+                    volume_long,
+                    volume_short,
+                    settlement_price: settlement_price.unwrap_or(0.0),
+                    position_profit,
+                })
+            })
+            .collect();
+        sources.sort_by(|a, b| {
+            a.position_profit
+                .partial_cmp(&b.position_profit)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        sources
+    }
+
+    /// 向管理员推送穿仓告警
+    fn notify_deficit(&self, record: &DeficitRecord, risk_ratio: f64) {
+        let Some(broker) = self.account_mgr.notification_broker() else {
+            return;
+        };
+
+        let (severity, suggestion) = if record.is_fully_covered() {
+            ("CRITICAL", "已由风险准备金垫付，请向客户追偿")
+        } else {
+            ("EMERGENCY", "风险准备金不足，请补充准备金并处理未覆盖穿仓")
+        };
+        for recipient in self.deficit_alert_recipients.read().iter() {
+            let notification = Notification::new(
+                NotificationType::RiskAlert,
+                Arc::from(recipient.as_str()),
+                NotificationPayload::RiskAlert(RiskAlertNotify {
+                    user_id: recipient.clone(),
+                    alert_type: "NEGATIVE_EQUITY".to_string(),
+                    severity: severity.to_string(),
+                    message: format!(
+                        "账户 {} 结算穿仓 {:.2}，准备金垫付 {:.2}，未覆盖 {:.2}",
+                        record.account_id, record.deficit, record.covered, record.uncovered
+                    ),
+                    risk_ratio,
+                    suggestion: suggestion.to_string(),
+                    timestamp: Utc::now().timestamp_nanos_opt().unwrap_or(0),
+                }),
+                "SettlementEngine",
+            );
+            if let Err(e) = broker.publish(notification) {
+                log::error!("Failed to publish RiskAlert notification: {}", e);
+            }
+        }
+    }
+
+    /// 按各合约结算价更新涨跌停扩板状态
+    fn update_price_limits(&self, settlement_date: &str) {
+        let Some(manager) = self.price_limit_manager.read().clone() else {
+            return;
+        };
+        for entry in self.settlement_prices.iter() {
+            if let Err(e) = manager.on_daily_close(entry.key(), settlement_date, *entry.value()) {
+                log::warn!(
+                    "[Settlement] Failed to update price limit for {}: {}",
+                    entry.key(),
+                    e
+                );
+            }
+        }
+    }
+
     /// 预计算单个账户的结算数据（只读，无锁竞争）
     fn pre_calculate_account(
         &self,
         account: &Arc<parking_lot::RwLock<qars::qaaccount::account::QA_Account>>,
+        interest_window: (i64, i64),
     ) -> Option<PreCalculatedSettlement> {
         let acc = account.read();
         let account_id = acc.account_cookie.clone();
@@ -777,27 +1626,46 @@ impl SettlementEngine {
         let close_profit = acc.accounts.close_profit;
         let commission = acc.accounts.commission;
         let current_margin = acc.accounts.margin;
+        // 成交金额按持仓预设的每手数量换算（委托量为手数）
+        let turnover: f64 = acc
+            .dailytrades
+            .values()
+            .map(|trade| {
+                let multiple = acc
+                    .hold
+                    .get(&trade.instrument_id)
+                    .map_or(1, |pos| pos.preset.unit_table.max(1));
+                trade.price * trade.volume * multiple as f64
+            })
+            .sum();
 
-        // 计算持仓盈亏
+        // 计算持仓盈亏（价差 × 手数 × 每手数量）
         let mut position_profit = 0.0;
         for (code, pos) in acc.hold.iter() {
             if let Some(settlement_price) = self.settlement_prices.get(code) {
+                let multiple = pos.preset.unit_table.max(1) as f64;
+
                 // 多头盈亏
                 let long_volume = pos.volume_long_today + pos.volume_long_his;
                 if long_volume > 0.0 {
-                    position_profit += (settlement_price.value() - pos.open_price_long) * long_volume;
+                    position_profit +=
+                        (settlement_price.value() - pos.open_price_long) * long_volume * multiple;
                 }
 
                 // 空头盈亏
                 let short_volume = pos.volume_short_today + pos.volume_short_his;
                 if short_volume > 0.0 {
-                    position_profit += (pos.open_price_short - settlement_price.value()) * short_volume;
+                    position_profit +=
+                        (pos.open_price_short - settlement_price.value()) * short_volume * multiple;
                 }
             }
         }
 
+        // 闲置资金计息
+        let interest = self.calculate_interest(&account_id, acc.money, interest_window);
+
         // 计算新权益
-        let new_balance = pre_balance + position_profit + close_profit - commission;
+        let new_balance = pre_balance + position_profit + close_profit - commission + interest;
 
         // 计算风险度
         let risk_ratio = if new_balance > 0.0 {
@@ -813,11 +1681,16 @@ impl SettlementEngine {
             position_profit,
             close_profit,
             commission,
+            interest,
             pre_balance,
             new_balance,
             new_margin: current_margin,
             risk_ratio,
             need_force_close,
+            opening_balance: acc.accounts.pre_balance,
+            deposit: acc.accounts.deposit,
+            withdraw: acc.accounts.withdraw,
+            turnover,
         })
     }
 
@@ -837,18 +1710,45 @@ impl SettlementEngine {
         date: &str,
     ) -> Result<AccountSettlement, String> {
         // 获取写锁并执行结算
-        {
+        let interest_posting = {
             let mut acc = account.write();
 
+            // 定点台账先同步结算前资金，结转以当日定点权益为准
+            let fixed_ledger = self.account_mgr.fixed_ledger();
+            fixed_ledger
+                .sync_account(&calc.account_id, &mut acc)
+                .map_err(|e| format!("Fixed-point sync failed: {}", e))?;
+
             // 【关键】调用 QA_Account::settle() 完成完整结算流程
             // 包括：清空日订单/成交、持仓结转、释放冻结资金、重置账户状态
             acc.settle();
+            self.settle_position_costs(&mut acc);
 
             // settle() 已经更新了大部分字段，这里补充预计算的盈亏值
             // 因为 settle() 使用账户内部状态计算，我们用预计算值确保一致性
             acc.accounts.position_profit = calc.position_profit;
             acc.accounts.risk_ratio = calc.risk_ratio;
-        } // 写锁在此释放
+
+            let posting = Self::post_interest(&mut acc, calc.interest);
+            if let Err(e) = fixed_ledger.roll_over_account(&calc.account_id, &mut acc) {
+                log::error!(
+                    "[FixedPoint] roll over failed for {}: {}",
+                    calc.account_id,
+                    e
+                );
+            }
+            posting
+        }; // 写锁在此释放
+
+        if let Some((balance_before, balance_after)) = interest_posting {
+            self.record_interest(
+                &calc.account_id,
+                calc.interest,
+                balance_before,
+                balance_after,
+                date,
+            );
+        }
 
         // 重新读取结算后的最终状态
         let final_state = {
@@ -862,6 +1762,7 @@ impl SettlementEngine {
             close_profit: calc.close_profit,
             position_profit: calc.position_profit,
             commission: calc.commission,
+            interest: calc.interest,
             pre_balance: calc.pre_balance,
             balance: final_state.0,
             risk_ratio: calc.risk_ratio,
@@ -871,6 +1772,83 @@ impl SettlementEngine {
         })
     }
 
+    /// 计息区间：上次日终结算至今（首次结算取最近 24 小时）
+    fn interest_window(&self) -> (i64, i64) {
+        let now_ms = self.time_service().now_millis();
+        let last = self.last_settlement_ms.load(Ordering::Relaxed);
+        let start_ms = if last == i64::MIN {
+            now_ms - 24 * 60 * 60 * 1000
+        } else {
+            last
+        };
+        (start_ms, now_ms)
+    }
+
+    /// 计算账户当日利息（按账户类型利率，未配置利率时为 0）
+    ///
+    /// 日均余额计息复用风险历史快照（权益 - 保证金占用），区间内无采样点时按结算时可用资金计息
+    fn calculate_interest(&self, account_id: &str, available: f64, window: (i64, i64)) -> f64 {
+        let config = self.interest_config.read();
+        if !config.is_enabled() {
+            return 0.0;
+        }
+        let account_type = self
+            .account_mgr
+            .get_account_type(account_id)
+            .unwrap_or(AccountType::Individual);
+        let base = match config.basis {
+            InterestBasis::Available => available,
+            InterestBasis::AverageAvailable => self
+                .risk_monitor
+                .read()
+                .as_ref()
+                .and_then(|m| {
+                    m.risk_history()
+                        .average_available(account_id, window.0, window.1)
+                })
+                .unwrap_or(available),
+        };
+        config.daily_interest(base, account_type)
+    }
+
+    /// 利息入账（结算后执行，与穿仓垫付一致计入次日出入金），返回入账前后权益
+    fn post_interest(
+        acc: &mut qars::qaaccount::account::QA_Account,
+        interest: f64,
+    ) -> Option<(f64, f64)> {
+        if interest == 0.0 {
+            return None;
+        }
+        let balance_before = acc.get_qifi_slice().accounts.balance;
+        if interest > 0.0 {
+            acc.deposit(interest);
+        } else {
+            acc.withdraw(-interest);
+        }
+        let balance_after = acc.get_qifi_slice().accounts.balance;
+        Some((balance_before, balance_after))
+    }
+
+    /// 记录利息资金流水
+    fn record_interest(
+        &self,
+        account_id: &str,
+        interest: f64,
+        balance_before: f64,
+        balance_after: f64,
+        date: &str,
+    ) {
+        if let Some(capital_mgr) = self.capital_mgr.read().as_ref() {
+            capital_mgr.record_interest(
+                account_id,
+                interest,
+                balance_before,
+                balance_after,
+                Some(format!("{} 结算计息", date)),
+            );
+        }
+    }
+
     /// 获取结算统计信息
     pub fn get_settlement_stats(&self) -> SettlementStats {
         SettlementStats {
@@ -880,6 +1858,47 @@ impl SettlementEngine {
         }
     }
 
+    /// 结转持仓成本与批次：今仓并入昨仓，持仓均价按结算价重置（开仓均价不变）
+    fn settle_position_costs(&self, acc: &mut qars::qaaccount::account::QA_Account) {
+        let account_id = acc.account_cookie.clone();
+        let settled = self
+            .account_mgr
+            .position_costs()
+            .settle_account(&account_id, |code| {
+                self.settlement_prices.get(code).map(|p| *p.value())
+            });
+        for (instrument_id, cost) in settled {
+            if let Some(pos) = acc.hold.get_mut(&instrument_id) {
+                cost.write_to(pos);
+            }
+        }
+
+        // 批次明细与汇总持仓对账，不一致时以汇总持仓为准重建该合约批次
+        let lots = self.account_mgr.position_lots();
+        for mismatch in lots.reconcile(&account_id, acc.hold.iter()) {
+            log::warn!(
+                "[Settlement] Position lots mismatch for {} {} {:?}: position={}, lots={}, realigning",
+                account_id,
+                mismatch.instrument_id,
+                mismatch.side,
+                mismatch.position_volume,
+                mismatch.lot_volume
+            );
+            let cost = acc
+                .hold
+                .get(&mismatch.instrument_id)
+                .map(PositionCost::from_position)
+                .unwrap_or_default();
+            lots.realign(
+                &account_id,
+                &mismatch.instrument_id,
+                &cost,
+                self.time_service().now_millis(),
+            );
+        }
+        lots.settle_account(&account_id);
+    }
+
     /// 结算单个账户
     ///
     /// **重要**: 调用 QA_Account::settle() 方法完成完整结算
@@ -892,7 +1911,7 @@ impl SettlementEngine {
         let account = self.account_mgr.get_account(user_id)?;
 
         // 记录结算前状态
-        let (pre_balance, close_profit, commission, position_profit, margin) = {
+        let (pre_balance, close_profit, commission, position_profit, margin, interest) = {
             let acc = account.read();
             let pre_balance = acc.accounts.balance;
             let close_profit = acc.accounts.close_profit;
@@ -912,14 +1931,27 @@ impl SettlementEngine {
                     }
                 }
             }
-            (pre_balance, close_profit, commission, position_profit, acc.accounts.margin)
+            let interest = self.calculate_interest(user_id, acc.money, self.interest_window());
+            (
+                pre_balance,
+                close_profit,
+                commission,
+                position_profit,
+                acc.accounts.margin,
+                interest,
+            )
         };
         let _ = margin; // 暂未使用但保留以备后用
 
         // 【关键】调用 QA_Account::settle() 完成完整结算
-        {
+        let interest_posting = {
             let mut acc = account.write();
             acc.settle();
+            self.settle_position_costs(&mut acc);
+            Self::post_interest(&mut acc, interest)
+        };
+        if let Some((balance_before, balance_after)) = interest_posting {
+            self.record_interest(user_id, interest, balance_before, balance_after, date);
         }
 
         // 读取结算后状态
@@ -959,6 +1991,7 @@ impl SettlementEngine {
             close_profit,
             position_profit,
             commission,
+            interest,
             pre_balance,
             balance: final_balance,
             risk_ratio,
@@ -1052,7 +2085,11 @@ impl SettlementEngine {
 
         // 生成强平ID
         let liquidation_id = self.generate_liquidation_id();
-        let start_time = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let start_time = self
+            .time_service()
+            .now()
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string();
 
         if plans.is_empty() {
             log::info!(
@@ -1091,7 +2128,11 @@ impl SettlementEngine {
         );
 
         let mut orders = Vec::with_capacity(plans.len());
-        let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let now = self
+            .time_service()
+            .now()
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string();
 
         for plan in plans.into_iter() {
             let price = self.calculate_force_price(
@@ -1109,6 +2150,10 @@ impl SettlementEngine {
                 order_type: "LIMIT".to_string(),
                 time_condition: None,
                 volume_condition: None,
+                hedge_flag: None,
+                ttl_secs: None,
+                client_order_id: None,
+                close_lot_id: None,
             };
 
             let response = order_router.submit_force_order(submit_req);
@@ -1170,7 +2215,12 @@ impl SettlementEngine {
 
         // 如果所有订单都已完成（成功或失败），标记完成时间
         if result.is_complete() {
-            result.complete_time = Some(Utc::now().format("%Y-%m-%d %H:%M:%S").to_string());
+            result.complete_time = Some(
+                self.time_service()
+                    .now()
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string(),
+            );
         }
 
         // 保存强平历史
@@ -1200,9 +2250,306 @@ impl SettlementEngine {
             liquidation_id, account_id, result.orders.len(), result.overall_status, balance_before, balance_after
         );
 
+        self.notify_liquidation_event(&result);
+
+        Ok(result)
+    }
+
+    /// 管理端手动强平（风控员操作）
+    ///
+    /// - `instrument_id` 为空时强平全部持仓，`volume` 仅在指定合约时生效（部分强平，先平多后平空）
+    /// - 以对手价下单，无行情时取涨跌停保护价，并限制在涨跌停范围内
+    /// - 走强制通道绕过账户下单限制，但仍进入撮合；对手盘不足时剩余部分挂单等待
+    /// - 记录 LiquidationRecord 并向账户推送 MarginCallNotify
+    pub fn manual_liquidate(
+        &self,
+        account_id: &str,
+        instrument_id: Option<&str>,
+        volume: Option<f64>,
+        operator: &str,
+        reason: Option<String>,
+    ) -> Result<ForceLiquidationResult, ExchangeError> {
+        if volume.is_some() && instrument_id.is_none() {
+            return Err(ExchangeError::InvalidParameter(
+                "volume requires instrument_id".to_string(),
+            ));
+        }
+        if let Some(v) = volume {
+            if v <= 0.0 {
+                return Err(ExchangeError::InvalidParameter(format!(
+                    "Invalid liquidation volume: {}",
+                    v
+                )));
+            }
+        }
+
+        let order_router = self
+            .order_router
+            .read()
+            .as_ref()
+            .and_then(|weak| weak.upgrade())
+            .ok_or_else(|| {
+                ExchangeError::InternalError(
+                    "OrderRouter not configured for SettlementEngine".to_string(),
+                )
+            })?;
+
+        let account = self.account_mgr.get_account(account_id)?;
+        let mut acc = account.write();
+        let balance_before = acc.get_balance();
+        let risk_ratio_before = acc.get_riskratio();
+        let margin_before = acc.accounts.margin;
+
+        let mut plans = Vec::new();
+        let mut remaining = volume.unwrap_or(f64::MAX);
+        for (hold_instrument, pos) in acc.hold.iter() {
+            if instrument_id.is_some_and(|id| id != hold_instrument) {
+                continue;
+            }
+            let sides = [
+                (
+                    "SELL",
+                    pos.volume_long_today + pos.volume_long_his,
+                    pos.open_price_long,
+                ),
+                (
+                    "BUY",
+                    pos.volume_short_today + pos.volume_short_his,
+                    pos.open_price_short,
+                ),
+            ];
+            for (direction, hold_volume, open_price) in sides {
+                let close_volume = hold_volume.min(remaining);
+                if close_volume <= 0.0 {
+                    continue;
+                }
+                remaining -= close_volume;
+                plans.push(ForcePlan {
+                    instrument_id: hold_instrument.clone(),
+                    direction: direction.to_string(),
+                    offset: "CLOSE".to_string(),
+                    volume: close_volume,
+                    reference_price: self
+                        .settlement_prices
+                        .get(hold_instrument)
+                        .map(|p| *p)
+                        .unwrap_or(open_price.max(0.01)),
+                });
+            }
+        }
+        drop(acc); // 释放账户锁，避免阻塞撮合
+
+        if plans.is_empty() {
+            return Err(ExchangeError::InvalidParameter(format!(
+                "Account {} has no position to liquidate{}",
+                account_id,
+                instrument_id
+                    .map(|id| format!(" in {}", id))
+                    .unwrap_or_default()
+            )));
+        }
+
+        let liquidation_id = self.generate_liquidation_id();
+        let start_time = self
+            .time_service()
+            .now()
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string();
+        let remark = Some(format!(
+            "manual by {}: {}",
+            operator,
+            reason.unwrap_or_else(|| "force liquidation".to_string())
+        ));
+
+        log::warn!(
+            "[Liquidation {}] Manual liquidation by {} for account {}: {} orders",
+            liquidation_id,
+            operator,
+            account_id,
+            plans.len()
+        );
+
+        let mut orders = Vec::with_capacity(plans.len());
+        for plan in plans {
+            let price = self.calculate_protected_price(
+                &plan.instrument_id,
+                &plan.direction,
+                plan.reference_price,
+            );
+            let response = order_router.submit_force_order(SubmitOrderRequest {
+                account_id: account_id.to_string(),
+                instrument_id: plan.instrument_id.clone(),
+                direction: plan.direction.clone(),
+                offset: plan.offset.clone(),
+                volume: plan.volume,
+                price,
+                order_type: "LIMIT".to_string(),
+                time_condition: None,
+                volume_condition: None,
+                hedge_flag: None,
+                ttl_secs: None,
+                client_order_id: None,
+                close_lot_id: None,
+            });
+
+            let mut order = ForceLiquidationOrder::new(
+                plan.instrument_id,
+                plan.direction,
+                plan.offset,
+                plan.volume,
+                price,
+            );
+            order.order_id = response.order_id;
+            if response.success {
+                order.status = ForceLiquidationStatus::Submitted;
+            } else {
+                order.status = ForceLiquidationStatus::Rejected;
+                order.error = response.error_message;
+            }
+            order.submit_time = Some(start_time.clone());
+            order.update_time = Some(start_time.clone());
+            orders.push(order);
+        }
+
+        let result = ForceLiquidationResult {
+            liquidation_id: liquidation_id.clone(),
+            account_id: account_id.to_string(),
+            orders,
+            trigger_risk_ratio: risk_ratio_before,
+            balance_before,
+            balance_after: balance_before,
+            start_time,
+            complete_time: None,
+            overall_status: ForceLiquidationStatus::Pending,
+            remark: remark.clone(),
+        };
+        self.liquidation_history
+            .insert(liquidation_id.clone(), result);
+        self.account_liquidations
+            .entry(account_id.to_string())
+            .or_default()
+            .push(liquidation_id.clone());
+
+        // 同步撮合结果（立即成交部分）
+        let result = self
+            .refresh_liquidation(&liquidation_id)
+            .ok_or_else(|| ExchangeError::InternalError("Liquidation record lost".to_string()))?;
+
+        if let Some(risk_monitor) = self.risk_monitor.read().clone() {
+            risk_monitor.record_liquidation(
+                account_id.to_string(),
+                risk_ratio_before,
+                balance_before,
+                result.balance_after,
+                result
+                    .orders
+                    .iter()
+                    .map(|o| o.instrument_id.clone())
+                    .collect(),
+                remark,
+            );
+        }
+
+        self.notify_margin_call(account_id, margin_before, balance_before, &result);
+        self.notify_liquidation_event(&result);
+
         Ok(result)
     }
 
+    /// 同步强平单执行状态（已报/部分成交/完成/失败）
+    pub fn refresh_liquidation(&self, liquidation_id: &str) -> Option<ForceLiquidationResult> {
+        let order_router = self
+            .order_router
+            .read()
+            .as_ref()
+            .and_then(|weak| weak.upgrade());
+        let mut result = self.liquidation_history.get_mut(liquidation_id)?;
+
+        if let Some(router) = order_router.filter(|_| !result.is_complete()) {
+            let now = self
+                .time_service()
+                .now()
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string();
+            for order in result.orders.iter_mut() {
+                if order.status.is_final() {
+                    continue;
+                }
+                let Some((_, status, _, _, filled)) = order
+                    .order_id
+                    .as_deref()
+                    .and_then(|id| router.get_order_detail(id))
+                else {
+                    continue;
+                };
+                let new_status = match status {
+                    OrderStatus::PendingRisk | OrderStatus::PendingRoute => {
+                        ForceLiquidationStatus::Pending
+                    }
+                    OrderStatus::Submitted if filled > 0.0 => {
+                        ForceLiquidationStatus::PartiallyFilled
+                    }
+                    OrderStatus::Submitted => ForceLiquidationStatus::Submitted,
+                    OrderStatus::PartiallyFilled => ForceLiquidationStatus::PartiallyFilled,
+                    OrderStatus::Filled => ForceLiquidationStatus::Filled,
+                    OrderStatus::Cancelled => ForceLiquidationStatus::Cancelled,
+                    OrderStatus::Rejected => ForceLiquidationStatus::Rejected,
+                };
+                if new_status != order.status || filled != order.filled_volume {
+                    order.status = new_status;
+                    order.filled_volume = filled;
+                    order.update_time = Some(now.clone());
+                }
+            }
+
+            result.update_overall_status();
+            if let Ok(account) = self.account_mgr.get_account(&result.account_id) {
+                result.balance_after = account.write().get_balance();
+            }
+            if result.is_complete() && result.complete_time.is_none() {
+                result.complete_time = Some(now);
+            }
+        }
+
+        Some(result.clone())
+    }
+
+    /// 向账户推送强平通知
+    fn notify_margin_call(
+        &self,
+        account_id: &str,
+        current_margin: f64,
+        balance: f64,
+        result: &ForceLiquidationResult,
+    ) {
+        let Some(broker) = self.account_mgr.notification_broker() else {
+            return;
+        };
+
+        let notification = Notification::new(
+            NotificationType::MarginCall,
+            Arc::from(account_id),
+            NotificationPayload::MarginCall(MarginCallNotify {
+                user_id: account_id.to_string(),
+                current_margin,
+                required_margin: (current_margin - balance).max(0.0),
+                level: "liquidation".to_string(),
+                deadline: Utc::now().timestamp_nanos_opt().unwrap_or(0),
+                message: format!(
+                    "账户已被强制平仓 (强平ID {}, {} 笔强平单, 状态 {:?})",
+                    result.liquidation_id,
+                    result.orders.len(),
+                    result.overall_status
+                ),
+                timestamp: Utc::now().timestamp_nanos_opt().unwrap_or(0),
+            }),
+            "SettlementEngine",
+        );
+        if let Err(e) = broker.publish(notification) {
+            log::error!("Failed to publish MarginCall notification: {}", e);
+        }
+    }
+
     /// 获取所有结算历史
     pub fn get_settlement_history(&self) -> Vec<SettlementResult> {
         self.settlement_history
@@ -1243,6 +2590,7 @@ impl Default for SettlementEngine {
             order_router: Arc::new(RwLock::new(None)),
             market_data_service: Arc::new(RwLock::new(None)),
             risk_monitor: Arc::new(RwLock::new(None)),
+            price_limit_manager: Arc::new(RwLock::new(None)),
             stats_settled_count: AtomicU64::new(0),
             stats_total_time_us: AtomicU64::new(0),
             force_close_queue: Arc::new(sender),
@@ -1253,6 +2601,16 @@ impl Default for SettlementEngine {
             account_liquidations: Arc::new(DashMap::new()),
             liquidation_seq: AtomicU64::new(1),
             max_retry_count: 3,
+            reconciliation_history: Arc::new(DashMap::new()),
+            risk_reserve_account: Arc::new(RwLock::new(None)),
+            deficit_alert_recipients: Arc::new(RwLock::new(vec!["admin".to_string()])),
+            deficit_history: Arc::new(DashMap::new()),
+            interest_config: Arc::new(RwLock::new(InterestConfig::default())),
+            capital_mgr: Arc::new(RwLock::new(None)),
+            last_settlement_ms: AtomicI64::new(i64::MIN),
+            task_config: Arc::new(RwLock::new(SettlementTaskConfig::default())),
+            settlement_tasks: Arc::new(DashMap::new()),
+            time_service: Arc::new(RwLock::new(time_service())),
         }
     }
 }
@@ -1294,6 +2652,41 @@ impl SettlementEngine {
             _ => market_price,
         }
     }
+
+    /// 计算手动强平价格：对手价，无行情时取涨跌停保护价，并限制在涨跌停范围内
+    fn calculate_protected_price(
+        &self,
+        instrument_id: &str,
+        direction: &str,
+        reference_price: f64,
+    ) -> f64 {
+        let limits = self
+            .price_limit_manager
+            .read()
+            .as_ref()
+            .and_then(|manager| manager.limit_prices(instrument_id));
+        let counterparty = self
+            .market_data_service
+            .read()
+            .as_ref()
+            .and_then(|svc| svc.get_tick_data(instrument_id).ok())
+            .and_then(|tick| match direction {
+                "SELL" => tick.bid_price.or(Some(tick.last_price)),
+                _ => tick.ask_price.or(Some(tick.last_price)),
+            })
+            .filter(|price| *price > 0.0);
+
+        let price = counterparty
+            .or_else(|| {
+                limits.map(|(lower, upper)| if direction == "SELL" { lower } else { upper })
+            })
+            .unwrap_or(reference_price.max(0.01));
+
+        match limits {
+            Some((lower, upper)) => price.clamp(lower, upper),
+            None => price,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -1732,6 +3125,7 @@ mod tests {
             force_closed_accounts: vec!["acc1".to_string(), "acc2".to_string()],
             total_commission: 1500.0,
             total_profit: 50000.0,
+            total_interest: 0.0,
             elapsed_ms: 1200,
             parallelism: 8,
         };
@@ -1757,6 +3151,7 @@ mod tests {
             close_profit: 1000.0,      // 平仓盈亏
             position_profit: -500.0,    // 持仓盈亏
             commission: 50.0,           // 手续费
+            interest: 0.0,              // 利息
             pre_balance: 100000.0,      // 结算前权益
             balance: 100450.0,          // 结算后权益 = pre_balance + close_profit + position_profit - commission
             risk_ratio: 0.15,           // 风险度 15%
@@ -1818,6 +3213,230 @@ mod tests {
         assert!(result.parallelism > 0, "应使用并行处理");
     }
 
+    /// 测试结算后自动生成对账报告
+    #[test]
+    fn test_daily_settlement_generates_reconciliation_report() {
+        let account_mgr = Arc::new(AccountManager::new());
+        for i in 0..3 {
+            account_mgr
+                .open_account(OpenAccountRequest {
+                    user_id: format!("recon_user_{}", i),
+                    account_id: None,
+                    account_name: format!("Recon User {}", i),
+                    init_cash: 100000.0 * (i + 1) as f64,
+                    account_type: AccountType::Individual,
+                })
+                .unwrap();
+        }
+
+        let engine = SettlementEngine::new(account_mgr);
+        let result = engine.daily_settlement().unwrap();
+
+        let report = engine
+            .get_reconciliation_report(&result.settlement_date)
+            .expect("结算后应生成对账报告");
+        assert_eq!(report.account_count, 3);
+        assert!(report.balanced, "{:?}", report.discrepancies);
+        assert_eq!(report.total_opening_balance, 600000.0);
+        assert_eq!(report.total_closing_balance, 600000.0);
+        assert_eq!(report.net_deposit, 0.0);
+        assert_eq!(report.total_turnover, 0.0);
+        assert!(engine.get_reconciliation_report("1999-01-01").is_none());
+    }
+
+    /// 测试穿仓账户由风险准备金垫付，准备金不足时按比例分摊，资金总额守恒
+    #[test]
+    fn test_daily_settlement_covers_negative_equity_from_risk_reserve() {
+        for (reserve_cash, expected_uncovered) in
+            [(100000.0, [0.0, 0.0]), (10000.0, [2500.0, 7500.0])]
+        {
+            let account_mgr = Arc::new(AccountManager::new());
+            let open = |user_id: &str, init_cash: f64| {
+                account_mgr
+                    .open_account(OpenAccountRequest {
+                        user_id: user_id.to_string(),
+                        account_id: None,
+                        account_name: user_id.to_string(),
+                        init_cash,
+                        account_type: AccountType::Individual,
+                    })
+                    .unwrap()
+            };
+            let reserve = open("risk_reserve", reserve_cash);
+            let client_a = open("deficit_a", 10000.0);
+            let client_b = open("deficit_b", 10000.0);
+
+            // 模拟极端行情亏损：权益分别穿仓 5000 / 15000
+            account_mgr
+                .get_account(&client_a)
+                .unwrap()
+                .write()
+                .withdraw(15000.0);
+            account_mgr
+                .get_account(&client_b)
+                .unwrap()
+                .write()
+                .withdraw(25000.0);
+
+            let balance = |id: &str| account_mgr.get_qifi_slice(id).unwrap().accounts.balance;
+            let total = || balance(&reserve) + balance(&client_a) + balance(&client_b);
+            let total_before = total();
+
+            let engine = SettlementEngine::new(account_mgr.clone());
+            engine.set_risk_reserve_account(Some(reserve.clone()));
+            let result = engine.daily_settlement().unwrap();
+
+            let mut records = engine.get_deficit_records(Some(&result.settlement_date));
+            records.sort_by(|a, b| a.deficit.partial_cmp(&b.deficit).unwrap());
+            assert_eq!(records.len(), 2);
+            assert_eq!(records[0].account_id, client_a);
+            assert_eq!(records[0].deficit, 5000.0);
+            assert_eq!(records[1].deficit, 15000.0);
+            assert_eq!(records[0].uncovered, expected_uncovered[0]);
+            assert_eq!(records[1].uncovered, expected_uncovered[1]);
+
+            // 客户权益补至 0（或仅剩未覆盖部分），准备金等额扣减
+            assert_eq!(balance(&client_a), -expected_uncovered[0]);
+            assert_eq!(balance(&client_b), -expected_uncovered[1]);
+            let covered = 20000.0 - expected_uncovered[0] - expected_uncovered[1];
+            assert_eq!(balance(&reserve), reserve_cash - covered);
+            assert!((total() - total_before).abs() < 1e-6);
+
+            let status = engine.get_risk_reserve_status();
+            assert_eq!(status.account_id, Some(reserve.clone()));
+            assert_eq!(status.total_covered, covered);
+            assert_eq!(status.record_count, 2);
+        }
+    }
+
+    /// 测试结算计息：按账户类型利率计息入账，生成利息流水，对账守恒（与手算对照）
+    #[test]
+    fn test_daily_settlement_accrues_interest_by_account_type() {
+        use crate::exchange::{CapitalManager, TransactionType};
+
+        let account_mgr = Arc::new(AccountManager::new());
+        let open = |user_id: &str, init_cash: f64, account_type: AccountType| {
+            account_mgr
+                .open_account(OpenAccountRequest {
+                    user_id: user_id.to_string(),
+                    account_id: None,
+                    account_name: user_id.to_string(),
+                    init_cash,
+                    account_type,
+                })
+                .unwrap()
+        };
+        let individual = open("interest_ind", 1_000_000.0, AccountType::Individual);
+        let maker = open("interest_mm", 360_000.0, AccountType::MarketMaker);
+        let institution = open("interest_inst", 500_000.0, AccountType::Institutional);
+
+        let capital_mgr = Arc::new(CapitalManager::new(account_mgr.clone()));
+        let engine = SettlementEngine::new(account_mgr.clone());
+        engine.set_capital_manager(capital_mgr.clone());
+        engine.set_interest_config(InterestConfig {
+            individual_rate: 0.018,
+            market_maker_rate: -0.036,
+            ..Default::default()
+        });
+
+        let result = engine.daily_settlement().unwrap();
+
+        // 1,000,000 × 1.8% / 360 = 50.00；360,000 × -3.6% / 360 = -36.00；机构利率为 0 不计息
+        let interest_of = |id: &str| engine.get_account_settlements(id)[0].interest;
+        assert_eq!(interest_of(&individual), 50.0);
+        assert_eq!(interest_of(&maker), -36.0);
+        assert_eq!(interest_of(&institution), 0.0);
+        assert!((result.total_interest - 14.0).abs() < 1e-9);
+
+        let balance = |id: &str| account_mgr.get_qifi_slice(id).unwrap().accounts.balance;
+        assert_eq!(balance(&individual), 1_000_050.0);
+        assert_eq!(balance(&maker), 359_964.0);
+        assert_eq!(balance(&institution), 500_000.0);
+
+        let txns = capital_mgr.get_transactions(&individual);
+        assert_eq!(txns.len(), 1);
+        assert_eq!(txns[0].transaction_type, TransactionType::Interest);
+        assert_eq!(txns[0].amount, 50.0);
+        assert_eq!(txns[0].balance_after - txns[0].balance_before, 50.0);
+        assert_eq!(capital_mgr.get_transactions(&maker)[0].amount, -36.0);
+        assert!(capital_mgr.get_transactions(&institution).is_empty());
+
+        let report = engine
+            .get_reconciliation_report(&result.settlement_date)
+            .unwrap();
+        assert!(report.balanced, "{:?}", report.discrepancies);
+        assert_eq!(report.total_interest, 14.0);
+    }
+
+    /// 测试日均可用资金计息：复用风险历史采样点，无采样点时按结算时可用资金
+    #[test]
+    fn test_interest_on_average_available_from_risk_samples() {
+        use crate::risk::RiskSnapshot;
+
+        let (engine, account_mgr) = create_test_settlement_engine();
+        let account_id = account_mgr.get_all_accounts()[0]
+            .read()
+            .account_cookie
+            .clone();
+        let monitor = Arc::new(RiskMonitor::new(account_mgr.clone()));
+        engine.set_risk_monitor(monitor.clone());
+        engine.set_interest_config(InterestConfig {
+            basis: InterestBasis::AverageAvailable,
+            individual_rate: 0.018,
+            ..Default::default()
+        });
+
+        // 无采样点：按可用资金 1,000,000 计息 50.00
+        let window = engine.interest_window();
+        assert_eq!(
+            engine.calculate_interest(&account_id, 1_000_000.0, window),
+            50.0
+        );
+
+        // 日内可用 900,000 / 1,000,000 / 1,070,000，日均 990,000 × 1.8% / 360 = 49.50
+        let now_ms = Utc::now().timestamp_millis();
+        for (i, (balance, margin)) in [
+            (1_000_000.0, 100_000.0),
+            (1_000_000.0, 0.0),
+            (1_100_000.0, 30_000.0),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let snapshot = RiskSnapshot {
+                timestamp: 0,
+                balance,
+                margin,
+                risk_ratio: margin / balance,
+                position_value: 0.0,
+            };
+            monitor.risk_history().record_sample(
+                now_ms - 3_600_000 + i as i64 * 60_000,
+                vec![(account_id.clone(), snapshot)],
+            );
+        }
+        let window = engine.interest_window();
+        assert_eq!(
+            engine.calculate_interest(&account_id, 1_000_000.0, window),
+            49.5
+        );
+
+        let result = engine.daily_settlement().unwrap();
+        assert_eq!(result.total_interest, 49.5);
+        assert_eq!(
+            account_mgr
+                .get_qifi_slice(&account_id)
+                .unwrap()
+                .accounts
+                .balance,
+            1_000_049.5
+        );
+
+        // 结算后计息区间从本次结算开始，旧采样点不再计入
+        let (start_ms, _) = engine.interest_window();
+        assert!(start_ms > now_ms - 3_600_000 + 120_000);
+    }
+
     /// 测试 Default trait 实现
     #[test]
     fn test_settlement_engine_default() {
@@ -1829,4 +3448,322 @@ mod tests {
         assert!(engine.account_history.is_empty());
         assert!(engine.liquidation_history.is_empty());
     }
+
+    /// 测试管理端手动强平：对手盘不足时部分成交，剩余挂单等待，后续成交后完成
+    #[test]
+    fn test_manual_liquidation_partial_fill_waits_for_liquidity() {
+        use crate::exchange::instrument_registry::{
+            InstrumentInfo, InstrumentRegistry, InstrumentStatus, InstrumentType,
+        };
+        use crate::exchange::TradeGateway;
+        use crate::matching::engine::ExchangeMatchingEngine;
+
+        let account_mgr = Arc::new(AccountManager::new());
+        for user in ["liq_user", "mm_user", "bid_user"] {
+            account_mgr
+                .open_account(OpenAccountRequest {
+                    user_id: user.to_string(),
+                    account_id: Some(user.to_string()),
+                    account_name: user.to_string(),
+                    init_cash: 1000000.0,
+                    account_type: AccountType::Individual,
+                })
+                .unwrap();
+        }
+
+        let matching_engine = Arc::new(ExchangeMatchingEngine::new());
+        matching_engine
+            .register_instrument("IX2301".to_string(), 100.0)
+            .unwrap();
+        let registry = Arc::new(InstrumentRegistry::new());
+        registry
+            .register(InstrumentInfo {
+                instrument_id: "IX2301".to_string(),
+                instrument_name: "IX2301".to_string(),
+                instrument_type: InstrumentType::CommodityFuture,
+                exchange: "SHFE".to_string(),
+                contract_multiplier: 1,
+                trading_unit: "手".to_string(),
+                quote_unit: String::new(),
+                price_tick: 0.01,
+                margin_rate: 0.1,
+                commission_rate: 0.0005,
+                limit_up_rate: 0.1,
+                limit_down_rate: 0.1,
+                status: InstrumentStatus::Active,
+                list_date: Some("2023-01-01".to_string()),
+                expire_date: Some("2023-12-31".to_string()),
+                created_at: "2023-01-01T00:00:00Z".to_string(),
+                updated_at: "2023-01-01T00:00:00Z".to_string(),
+            })
+            .unwrap();
+        let trade_gateway = Arc::new(TradeGateway::new(account_mgr.clone()));
+        let router = Arc::new(OrderRouter::new(
+            account_mgr.clone(),
+            matching_engine.clone(),
+            registry,
+            trade_gateway,
+        ));
+
+        let engine = SettlementEngine::new(account_mgr.clone());
+        engine.set_order_router(router.clone());
+        engine.set_market_data_service(Arc::new(MarketDataService::new(matching_engine)));
+        let risk_monitor = Arc::new(RiskMonitor::new(account_mgr.clone()));
+        engine.set_risk_monitor(risk_monitor.clone());
+
+        let order = |account: &str, direction: &str, offset: &str, volume: f64, price: f64| {
+            let resp = router.submit_order(SubmitOrderRequest {
+                account_id: account.to_string(),
+                instrument_id: "IX2301".to_string(),
+                direction: direction.to_string(),
+                offset: offset.to_string(),
+                volume,
+                price,
+                order_type: "LIMIT".to_string(),
+                ..Default::default()
+            });
+            assert!(resp.success, "{:?}", resp.error_message);
+        };
+
+        // liq_user 建立 10 手多头
+        order("mm_user", "SELL", "OPEN", 10.0, 100.0);
+        order("liq_user", "BUY", "OPEN", 10.0, 100.0);
+        // 对手盘只有 4 手买单
+        order("bid_user", "BUY", "OPEN", 4.0, 99.0);
+
+        let result = engine
+            .manual_liquidate("liq_user", Some("IX2301"), None, "risk_officer", None)
+            .unwrap();
+        assert_eq!(result.orders.len(), 1);
+        let liq_order = &result.orders[0];
+        assert_eq!(liq_order.direction, "SELL");
+        assert_eq!(liq_order.volume, 10.0);
+        assert_eq!(liq_order.price, 99.0, "以对手价（买一）下单");
+        assert_eq!(liq_order.filled_volume, 4.0);
+        assert_eq!(liq_order.status, ForceLiquidationStatus::PartiallyFilled);
+        assert!(!result.is_complete());
+        assert!(result.complete_time.is_none());
+        assert!(result.remark.as_deref().unwrap().contains("risk_officer"));
+        assert_eq!(risk_monitor.get_liquidation_records("liq_user").len(), 1);
+
+        // 剩余 6 手挂单等待，新对手盘到达后成交
+        order("bid_user", "BUY", "OPEN", 6.0, 99.0);
+        let refreshed = engine.refresh_liquidation(&result.liquidation_id).unwrap();
+        assert_eq!(refreshed.orders[0].filled_volume, 10.0);
+        assert_eq!(refreshed.overall_status, ForceLiquidationStatus::Filled);
+        assert!(refreshed.complete_time.is_some());
+
+        // 部分强平数量需要指定合约
+        assert!(engine
+            .manual_liquidate("liq_user", None, Some(1.0), "risk_officer", None)
+            .is_err());
+    }
+
+    // ==================== 分片并行与断点续跑 ====================
+
+    /// 创建一批账户（余额、出入金、账户类型各不相同）
+    fn create_settlement_accounts(count: usize) -> Arc<AccountManager> {
+        let account_mgr = Arc::new(AccountManager::new());
+        for i in 0..count {
+            let account_id = format!("ACC{:04}", i);
+            account_mgr
+                .open_account(OpenAccountRequest {
+                    user_id: format!("user_{}", i),
+                    account_id: Some(account_id.clone()),
+                    account_name: account_id.clone(),
+                    init_cash: 100_000.0 + i as f64 * 1_234.56,
+                    account_type: if i % 3 == 0 {
+                        AccountType::MarketMaker
+                    } else {
+                        AccountType::Individual
+                    },
+                })
+                .unwrap();
+            let account = account_mgr.get_account(&account_id).unwrap();
+            if i % 4 == 0 {
+                account.write().deposit(5_000.0 + i as f64);
+            }
+            if i % 5 == 0 {
+                account.write().withdraw(2_000.0);
+            }
+        }
+        account_mgr
+    }
+
+    fn create_sharded_engine(
+        account_mgr: Arc<AccountManager>,
+        config: SettlementTaskConfig,
+    ) -> SettlementEngine {
+        let engine = SettlementEngine::new(account_mgr);
+        engine.set_interest_config(InterestConfig {
+            individual_rate: 0.018,
+            market_maker_rate: 0.036,
+            ..Default::default()
+        });
+        engine.set_task_config(config);
+        engine
+    }
+
+    fn balance_of(account_mgr: &AccountManager, account_id: &str) -> f64 {
+        account_mgr
+            .get_qifi_slice(account_id)
+            .unwrap()
+            .accounts
+            .balance
+    }
+
+    /// 测试分片并行结算与串行结算结果一致
+    #[test]
+    fn test_parallel_settlement_matches_serial() {
+        let run = |config: SettlementTaskConfig| {
+            let account_mgr = create_settlement_accounts(50);
+            let engine = create_sharded_engine(account_mgr.clone(), config);
+            let result = engine.daily_settlement().unwrap();
+            let balances: Vec<f64> = (0..50)
+                .map(|i| balance_of(&account_mgr, &format!("ACC{:04}", i)))
+                .collect();
+            let report = engine
+                .get_reconciliation_report(&result.settlement_date)
+                .unwrap();
+            let progress = engine.get_settlement_progress(None).unwrap();
+            (result, balances, report, progress)
+        };
+
+        // 单线程单分片（串行）
+        let (serial, serial_balances, serial_report, _) = run(SettlementTaskConfig {
+            parallelism: 1,
+            ..Default::default()
+        });
+        // 4 线程、每分片 7 个账户
+        let (parallel, parallel_balances, parallel_report, progress) = run(SettlementTaskConfig {
+            shard_size: 7,
+            parallelism: 4,
+            ..Default::default()
+        });
+
+        assert_eq!(serial.settled_accounts, 50);
+        assert_eq!(parallel.settled_accounts, 50);
+        assert_eq!(parallel.failed_accounts, 0);
+        assert_eq!(parallel.parallelism, 4);
+        assert!(serial.total_interest > 0.0);
+        assert_eq!(parallel.total_interest, serial.total_interest);
+        assert_eq!(parallel.total_commission, serial.total_commission);
+        assert_eq!(parallel.total_profit, serial.total_profit);
+        assert_eq!(parallel_balances, serial_balances);
+        assert!(serial_report.balanced && parallel_report.balanced);
+        assert_eq!(
+            parallel_report.total_closing_balance,
+            serial_report.total_closing_balance
+        );
+
+        assert_eq!(progress.total_shards, 8);
+        assert_eq!(progress.completed_shards, 8);
+        assert_eq!(progress.percentage, 100.0);
+        assert_eq!(progress.eta_ms, Some(0));
+        assert!(progress.failed_accounts.is_empty());
+    }
+
+    /// 测试重跑时跳过已完成分片，汇总由落盘的分片产出重建
+    #[test]
+    fn test_settlement_resumes_from_persisted_shards() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = SettlementTaskConfig {
+            shard_size: 4,
+            state_dir: dir.path().to_str().unwrap().to_string(),
+            ..Default::default()
+        };
+
+        let first_mgr = create_settlement_accounts(10);
+        let first = create_sharded_engine(first_mgr.clone(), config.clone());
+        let first_result = first.daily_settlement().unwrap();
+        let date = first_result.settlement_date.clone();
+
+        // 模拟最后一个分片完成前进程中断
+        let store = SettlementTaskStore::new(&config.state_dir);
+        let mut task = store.load(&date).unwrap().unwrap();
+        task.status = crate::exchange::SettlementTaskStatus::Running;
+        task.shards[2].status = ShardStatus::Pending;
+        task.shards[2].settled = 0;
+        store.save_task(&task).unwrap();
+
+        // 重启后（账户状态为结算前）只结算未完成分片
+        let second_mgr = create_settlement_accounts(10);
+        let second = create_sharded_engine(second_mgr.clone(), config);
+        let interrupted = second.get_settlement_progress(Some(&date)).unwrap();
+        assert_eq!(interrupted.completed_shards, 2);
+        assert_eq!(interrupted.processed_accounts, 8);
+
+        // 已完成分片的结果取自落盘文件
+        let result = second.daily_settlement().unwrap();
+        assert_eq!(result.settled_accounts, 10);
+        assert!((result.total_interest - first_result.total_interest).abs() < 1e-6);
+
+        let untouched = create_settlement_accounts(10);
+        assert_eq!(
+            balance_of(&second_mgr, "ACC0000"),
+            balance_of(&untouched, "ACC0000")
+        );
+        assert_eq!(
+            balance_of(&second_mgr, "ACC0009"),
+            balance_of(&first_mgr, "ACC0009")
+        );
+        assert_eq!(second.get_account_settlements("ACC0000").len(), 0);
+        assert_eq!(second.get_account_settlements("ACC0009").len(), 1);
+
+        let progress = second.get_settlement_progress(Some(&date)).unwrap();
+        assert_eq!(progress.runs, 2);
+        assert_eq!(progress.completed_shards, 3);
+        assert_eq!(progress.percentage, 100.0);
+
+        // 已完成的任务再次执行不会重复结算
+        let rerun = second.daily_settlement().unwrap();
+        assert_eq!(rerun.total_interest, result.total_interest);
+        assert_eq!(
+            balance_of(&second_mgr, "ACC0009"),
+            balance_of(&first_mgr, "ACC0009")
+        );
+    }
+
+    /// 测试结算期间限制交易，结算完成后解除（人工限制保持不变）
+    #[test]
+    fn test_settlement_releases_settled_accounts() {
+        let account_mgr = create_settlement_accounts(6);
+        account_mgr
+            .set_trading_restriction(
+                "ACC0001",
+                TradingRestriction::CloseOnly,
+                "manual".to_string(),
+                None,
+                "admin",
+            )
+            .unwrap();
+
+        let engine = create_sharded_engine(
+            account_mgr.clone(),
+            SettlementTaskConfig {
+                shard_size: 2,
+                restrict_trading: true,
+                ..Default::default()
+            },
+        );
+        engine.restrict_unsettled_accounts(
+            &[(0, vec!["ACC0000".to_string(), "ACC0001".to_string()])],
+            &engine.get_task_config(),
+        );
+        let locked = account_mgr.get_trading_restriction("ACC0000").unwrap();
+        assert_eq!(locked.restriction, TradingRestriction::Suspended);
+        assert_eq!(locked.updated_by, SETTLEMENT_OPERATOR);
+        assert!(locked.expires_at.is_some());
+
+        let result = engine.daily_settlement().unwrap();
+        assert_eq!(result.settled_accounts, 6);
+        for i in [0, 2, 3, 4, 5] {
+            assert!(account_mgr
+                .get_trading_restriction(&format!("ACC{:04}", i))
+                .is_none());
+        }
+        let manual = account_mgr.get_trading_restriction("ACC0001").unwrap();
+        assert_eq!(manual.restriction, TradingRestriction::CloseOnly);
+        assert_eq!(manual.updated_by, "admin");
+    }
 }
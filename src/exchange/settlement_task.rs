@@ -0,0 +1,613 @@
+//! 日终结算任务（账户分片并行与断点续跑）
+//!
+//! @yutiansut @quantaxis
+//!
+//! 大规模账户结算时按账户 ID 排序后切分为固定大小的分片：
+//! - 分片之间用 Rayon 并行处理，分片内账户串行结算
+//! - 每完成一个分片即落盘分片产出（账户结算结果、对账输入、失败账户）与任务状态
+//! - 同日重跑时跳过已完成分片，失败分片只重试其中失败的账户，汇总与对账由分片产出重建
+//! - 断点粒度为分片：进程在分片中途退出时，该分片会在重跑时整体重新结算
+//!
+//! 落盘目录结构（`state_dir` 为空时仅保存在内存）：
+//!
+//! ```text
+//! {state_dir}/{settlement_date}/task.json        任务状态（分片进度）
+//! {state_dir}/{settlement_date}/accounts.json    任务创建时的账户列表（保证重跑分片稳定）
+//! {state_dir}/{settlement_date}/shard_00000.json 分片产出
+//! ```
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::reconciliation::AccountReconciliationInput;
+use super::settlement::AccountSettlement;
+use crate::ExchangeError;
+
+/// 结算期间交易限制的设置人（仅释放由结算设置的限制）
+pub const SETTLEMENT_OPERATOR: &str = "settlement";
+
+/// 结算任务配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettlementTaskConfig {
+    /// 每个分片的账户数
+    #[serde(default = "default_shard_size")]
+    pub shard_size: usize,
+    /// 并行线程数（0 表示使用 Rayon 全局线程池）
+    #[serde(default)]
+    pub parallelism: usize,
+    /// 任务状态落盘目录（为空时仅保存在内存，进程重启后无法续跑）
+    #[serde(default)]
+    pub state_dir: String,
+    /// 结算期间暂停待结算账户交易
+    #[serde(default)]
+    pub restrict_trading: bool,
+    /// 分片结算完成后立即解除其中已结算账户的限制（否则全部结算完成后解除）
+    #[serde(default = "default_early_release")]
+    pub early_release: bool,
+    /// 结算限制的最长期限（秒），进程异常退出时限制到期自动恢复
+    #[serde(default = "default_restriction_timeout_secs")]
+    pub restriction_timeout_secs: u64,
+}
+
+fn default_shard_size() -> usize {
+    1000
+}
+
+fn default_early_release() -> bool {
+    true
+}
+
+fn default_restriction_timeout_secs() -> u64 {
+    1800
+}
+
+impl Default for SettlementTaskConfig {
+    fn default() -> Self {
+        Self {
+            shard_size: default_shard_size(),
+            parallelism: 0,
+            state_dir: String::new(),
+            restrict_trading: false,
+            early_release: default_early_release(),
+            restriction_timeout_secs: default_restriction_timeout_secs(),
+        }
+    }
+}
+
+impl SettlementTaskConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.shard_size == 0 {
+            return Err("settlement.shard_size must be > 0".to_string());
+        }
+        if self.restrict_trading && self.restriction_timeout_secs == 0 {
+            return Err("settlement.restriction_timeout_secs must be > 0".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// 分片状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShardStatus {
+    /// 未处理
+    Pending,
+    /// 全部账户结算成功
+    Completed,
+    /// 存在结算失败的账户（重跑时只重试失败账户）
+    Failed,
+}
+
+/// 任务状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SettlementTaskStatus {
+    /// 结算中（进程中断时保持该状态，重跑续接）
+    Running,
+    /// 全部分片完成
+    Completed,
+    /// 本轮结束但存在失败账户
+    Failed,
+}
+
+/// 结算失败账户
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FailedAccount {
+    pub account_id: String,
+    pub reason: String,
+}
+
+/// 分片产出（重跑时用于重建汇总与对账）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ShardOutput {
+    pub settlements: Vec<AccountSettlement>,
+    pub reconciliation: Vec<AccountReconciliationInput>,
+    pub failed: Vec<FailedAccount>,
+}
+
+impl ShardOutput {
+    /// 合并重试结果：保留已成功账户，失败列表以本次重试为准
+    pub fn merge_retry(&mut self, retry: ShardOutput) {
+        self.settlements.extend(retry.settlements);
+        self.settlements.sort_by(|a, b| a.user_id.cmp(&b.user_id));
+        self.reconciliation.extend(retry.reconciliation);
+        self.reconciliation
+            .sort_by(|a, b| a.account_id.cmp(&b.account_id));
+        self.failed = retry.failed;
+    }
+}
+
+/// 结算分片
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettlementShard {
+    pub index: usize,
+    /// 账户区间 [start, end)（任务账户列表下标）
+    pub start: usize,
+    pub end: usize,
+    pub status: ShardStatus,
+    /// 已结算账户数
+    pub settled: usize,
+    /// 失败账户数
+    pub failed: usize,
+    /// 最近一次处理耗时（毫秒）
+    pub elapsed_ms: u64,
+    pub completed_at: Option<i64>,
+}
+
+impl SettlementShard {
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+/// 结算任务（一个结算日一个任务）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettlementTask {
+    pub settlement_date: String,
+    pub status: SettlementTaskStatus,
+    pub shard_size: usize,
+    pub total_accounts: usize,
+    /// 计息区间（续跑沿用首次运行的区间）
+    pub interest_window: (i64, i64),
+    pub shards: Vec<SettlementShard>,
+    pub created_at: i64,
+    /// 本轮开始时间
+    pub started_at: i64,
+    pub updated_at: i64,
+    pub completed_at: Option<i64>,
+    /// 运行轮次（首次为 1，每次续跑 +1）
+    pub runs: u32,
+    /// 本轮已处理账户数（估算剩余时间）
+    #[serde(default)]
+    pub run_processed: usize,
+    /// 按账户 ID 排序的账户列表（单独落盘）
+    #[serde(skip)]
+    pub account_ids: Arc<Vec<String>>,
+    /// 分片产出（内存缓存，缺失时从落盘目录加载）
+    #[serde(skip)]
+    pub outputs: HashMap<usize, ShardOutput>,
+}
+
+impl SettlementTask {
+    /// 创建任务：账户 ID 排序后按 `shard_size` 切分
+    pub fn new(
+        settlement_date: &str,
+        mut account_ids: Vec<String>,
+        shard_size: usize,
+        interest_window: (i64, i64),
+        now_ms: i64,
+    ) -> Self {
+        account_ids.sort();
+        account_ids.dedup();
+        let shard_size = shard_size.max(1);
+        let total_accounts = account_ids.len();
+        let shards = (0..total_accounts)
+            .step_by(shard_size)
+            .enumerate()
+            .map(|(index, start)| SettlementShard {
+                index,
+                start,
+                end: (start + shard_size).min(total_accounts),
+                status: ShardStatus::Pending,
+                settled: 0,
+                failed: 0,
+                elapsed_ms: 0,
+                completed_at: None,
+            })
+            .collect();
+
+        Self {
+            settlement_date: settlement_date.to_string(),
+            status: SettlementTaskStatus::Running,
+            shard_size,
+            total_accounts,
+            interest_window,
+            shards,
+            created_at: now_ms,
+            started_at: now_ms,
+            updated_at: now_ms,
+            completed_at: None,
+            runs: 0,
+            run_processed: 0,
+            account_ids: Arc::new(account_ids),
+            outputs: HashMap::new(),
+        }
+    }
+
+    /// 分片内的账户
+    pub fn shard_accounts(&self, index: usize) -> &[String] {
+        let shard = &self.shards[index];
+        &self.account_ids[shard.start..shard.end]
+    }
+
+    /// 未完成的分片（未处理或存在失败账户）
+    pub fn pending_shards(&self) -> Vec<usize> {
+        self.shards
+            .iter()
+            .filter(|s| s.status != ShardStatus::Completed)
+            .map(|s| s.index)
+            .collect()
+    }
+
+    /// 开始新一轮运行
+    pub fn begin_run(&mut self, now_ms: i64) {
+        self.runs += 1;
+        self.status = SettlementTaskStatus::Running;
+        self.started_at = now_ms;
+        self.updated_at = now_ms;
+        self.run_processed = 0;
+    }
+
+    /// 记录分片处理结果，返回合并后的分片产出
+    pub fn record_shard(
+        &mut self,
+        index: usize,
+        output: ShardOutput,
+        processed: usize,
+        elapsed_ms: u64,
+        now_ms: i64,
+    ) -> &ShardOutput {
+        let merged = match self.outputs.remove(&index) {
+            Some(mut existing) => {
+                existing.merge_retry(output);
+                existing
+            }
+            None => output,
+        };
+
+        let shard = &mut self.shards[index];
+        shard.settled = merged.settlements.len();
+        shard.failed = merged.failed.len();
+        shard.elapsed_ms = elapsed_ms;
+        if merged.failed.is_empty() {
+            shard.status = ShardStatus::Completed;
+            shard.completed_at = Some(now_ms);
+        } else {
+            shard.status = ShardStatus::Failed;
+        }
+        self.run_processed += processed;
+        self.updated_at = now_ms;
+        self.outputs.entry(index).or_insert(merged)
+    }
+
+    /// 结束本轮运行：全部分片完成为 Completed，否则为 Failed（可重跑）
+    pub fn finish_run(&mut self, now_ms: i64) {
+        self.updated_at = now_ms;
+        if self
+            .shards
+            .iter()
+            .all(|s| s.status == ShardStatus::Completed)
+        {
+            self.status = SettlementTaskStatus::Completed;
+            self.completed_at = Some(now_ms);
+        } else {
+            self.status = SettlementTaskStatus::Failed;
+        }
+    }
+
+    /// 结算进度
+    pub fn progress(&self, now_ms: i64) -> SettlementProgress {
+        let processed_accounts: usize = self
+            .shards
+            .iter()
+            .filter(|s| s.status != ShardStatus::Pending)
+            .map(|s| s.len())
+            .sum();
+        let settled_accounts = self.shards.iter().map(|s| s.settled).sum();
+        let completed_shards = self
+            .shards
+            .iter()
+            .filter(|s| s.status == ShardStatus::Completed)
+            .count();
+        let percentage = if self.total_accounts == 0 {
+            100.0
+        } else {
+            processed_accounts as f64 * 100.0 / self.total_accounts as f64
+        };
+
+        let end_ms = match self.status {
+            SettlementTaskStatus::Running => now_ms,
+            _ => self.updated_at,
+        };
+        let elapsed_ms = (end_ms - self.started_at).max(0) as u64;
+        // 按本轮处理速度估算剩余时间
+        let eta_ms = match self.status {
+            SettlementTaskStatus::Running if self.run_processed > 0 => {
+                let remaining = self.total_accounts.saturating_sub(processed_accounts);
+                Some(elapsed_ms * remaining as u64 / self.run_processed as u64)
+            }
+            SettlementTaskStatus::Running => None,
+            _ => Some(0),
+        };
+
+        let mut failed_accounts: Vec<FailedAccount> = self
+            .outputs
+            .values()
+            .flat_map(|o| o.failed.iter().cloned())
+            .collect();
+        failed_accounts.sort_by(|a, b| a.account_id.cmp(&b.account_id));
+
+        SettlementProgress {
+            settlement_date: self.settlement_date.clone(),
+            status: self.status,
+            runs: self.runs,
+            total_accounts: self.total_accounts,
+            processed_accounts,
+            settled_accounts,
+            failed_count: self.shards.iter().map(|s| s.failed).sum(),
+            total_shards: self.shards.len(),
+            completed_shards,
+            percentage,
+            elapsed_ms,
+            eta_ms,
+            failed_accounts,
+        }
+    }
+}
+
+/// 结算进度
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettlementProgress {
+    pub settlement_date: String,
+    pub status: SettlementTaskStatus,
+    pub runs: u32,
+    pub total_accounts: usize,
+    /// 已处理账户数（含失败）
+    pub processed_accounts: usize,
+    pub settled_accounts: usize,
+    pub failed_count: usize,
+    pub total_shards: usize,
+    pub completed_shards: usize,
+    /// 完成百分比（0-100）
+    pub percentage: f64,
+    /// 本轮已耗时（毫秒）
+    pub elapsed_ms: u64,
+    /// 预计剩余时间（毫秒，本轮尚无完成分片时为 None）
+    pub eta_ms: Option<u64>,
+    /// 失败账户（仅包含内存中已加载的分片）
+    pub failed_accounts: Vec<FailedAccount>,
+}
+
+/// 结算任务落盘存储
+#[derive(Debug, Clone, Default)]
+pub struct SettlementTaskStore {
+    dir: Option<PathBuf>,
+}
+
+impl SettlementTaskStore {
+    /// `state_dir` 为空时不落盘
+    pub fn new(state_dir: &str) -> Self {
+        Self {
+            dir: (!state_dir.is_empty()).then(|| PathBuf::from(state_dir)),
+        }
+    }
+
+    pub fn is_persistent(&self) -> bool {
+        self.dir.is_some()
+    }
+
+    fn task_dir(&self, settlement_date: &str) -> Option<PathBuf> {
+        self.dir.as_ref().map(|dir| dir.join(settlement_date))
+    }
+
+    /// 加载任务（含账户列表，不含分片产出）
+    pub fn load(&self, settlement_date: &str) -> Result<Option<SettlementTask>, ExchangeError> {
+        let Some(dir) = self.task_dir(settlement_date) else {
+            return Ok(None);
+        };
+        let Some(mut task) = read_json::<SettlementTask>(&dir.join("task.json"))? else {
+            return Ok(None);
+        };
+        let account_ids: Vec<String> = read_json(&dir.join("accounts.json"))?.ok_or_else(|| {
+            ExchangeError::IOError(format!(
+                "Settlement task {} is missing its account list",
+                settlement_date
+            ))
+        })?;
+        task.account_ids = Arc::new(account_ids);
+        Ok(Some(task))
+    }
+
+    /// 保存任务状态
+    pub fn save_task(&self, task: &SettlementTask) -> Result<(), ExchangeError> {
+        match self.task_dir(&task.settlement_date) {
+            Some(dir) => write_json(&dir.join("task.json"), task),
+            None => Ok(()),
+        }
+    }
+
+    /// 保存账户列表（任务创建时一次）
+    pub fn save_accounts(&self, task: &SettlementTask) -> Result<(), ExchangeError> {
+        match self.task_dir(&task.settlement_date) {
+            Some(dir) => write_json(&dir.join("accounts.json"), task.account_ids.as_ref()),
+            None => Ok(()),
+        }
+    }
+
+    /// 保存分片产出
+    pub fn save_shard(
+        &self,
+        settlement_date: &str,
+        index: usize,
+        output: &ShardOutput,
+    ) -> Result<(), ExchangeError> {
+        match self.task_dir(settlement_date) {
+            Some(dir) => write_json(&dir.join(shard_file(index)), output),
+            None => Ok(()),
+        }
+    }
+
+    /// 加载分片产出
+    pub fn load_shard(
+        &self,
+        settlement_date: &str,
+        index: usize,
+    ) -> Result<Option<ShardOutput>, ExchangeError> {
+        match self.task_dir(settlement_date) {
+            Some(dir) => read_json(&dir.join(shard_file(index))),
+            None => Ok(None),
+        }
+    }
+}
+
+fn shard_file(index: usize) -> String {
+    format!("shard_{:05}.json", index)
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> Result<Option<T>, ExchangeError> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| ExchangeError::IOError(format!("Read {:?} failed: {}", path, e)))?;
+    serde_json::from_str(&content)
+        .map(Some)
+        .map_err(|e| ExchangeError::SerializationError(format!("Parse {:?} failed: {}", path, e)))
+}
+
+/// 写入 JSON（临时文件 + 重命名，避免中断时留下半个文件）
+fn write_json<T: Serialize + ?Sized>(path: &Path, value: &T) -> Result<(), ExchangeError> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| {
+            ExchangeError::IOError(format!("Create settlement task dir failed: {}", e))
+        })?;
+    }
+    let json = serde_json::to_string(value).map_err(|e| {
+        ExchangeError::SerializationError(format!("Settlement task serialization failed: {}", e))
+    })?;
+    let tmp_path = path.with_extension("json.tmp");
+    std::fs::write(&tmp_path, json)
+        .and_then(|_| std::fs::rename(&tmp_path, path))
+        .map_err(|e| ExchangeError::IOError(format!("Write {:?} failed: {}", path, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(n: usize) -> Vec<String> {
+        (0..n).rev().map(|i| format!("ACC{:03}", i)).collect()
+    }
+
+    #[test]
+    fn test_shards_split_sorted_accounts() {
+        let task = SettlementTask::new("2026-10-16", ids(10), 4, (0, 1), 0);
+        assert_eq!(task.total_accounts, 10);
+        assert_eq!(
+            task.shards.iter().map(|s| s.len()).collect::<Vec<_>>(),
+            vec![4, 4, 2]
+        );
+        assert_eq!(task.shard_accounts(0)[0], "ACC000");
+        assert_eq!(task.shard_accounts(2), ["ACC008", "ACC009"]);
+        assert_eq!(task.pending_shards(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_progress_and_retry_merge() {
+        let mut task = SettlementTask::new("2026-10-16", ids(10), 5, (0, 1), 0);
+        task.begin_run(1_000);
+
+        let settlement = |id: &str| AccountSettlement {
+            user_id: id.to_string(),
+            date: "2026-10-16".to_string(),
+            close_profit: 0.0,
+            position_profit: 0.0,
+            commission: 0.0,
+            interest: 0.0,
+            pre_balance: 0.0,
+            balance: 0.0,
+            risk_ratio: 0.0,
+            force_close: false,
+            margin: 0.0,
+            available: 0.0,
+        };
+        let output = ShardOutput {
+            settlements: ["ACC000", "ACC001", "ACC002", "ACC004"]
+                .iter()
+                .map(|id| settlement(id))
+                .collect(),
+            reconciliation: vec![],
+            failed: vec![FailedAccount {
+                account_id: "ACC003".to_string(),
+                reason: "locked".to_string(),
+            }],
+        };
+        task.record_shard(0, output, 5, 2_000, 3_000);
+
+        let progress = task.progress(3_000);
+        assert_eq!(progress.processed_accounts, 5);
+        assert_eq!(progress.settled_accounts, 4);
+        assert_eq!(progress.percentage, 50.0);
+        assert_eq!(progress.completed_shards, 0);
+        // 2 秒处理 5 个账户，剩余 5 个约 2 秒
+        assert_eq!(progress.eta_ms, Some(2_000));
+        assert_eq!(progress.failed_accounts[0].account_id, "ACC003");
+
+        task.finish_run(3_000);
+        assert_eq!(task.status, SettlementTaskStatus::Failed);
+        assert_eq!(task.pending_shards(), vec![0, 1]);
+
+        // 重跑只重试失败账户，合并后分片完成
+        task.begin_run(4_000);
+        let retry = ShardOutput {
+            settlements: vec![settlement("ACC003")],
+            ..Default::default()
+        };
+        let merged = task.record_shard(0, retry, 1, 10, 4_010);
+        assert_eq!(merged.settlements.len(), 5);
+        assert_eq!(merged.settlements[3].user_id, "ACC003");
+        assert_eq!(task.shards[0].status, ShardStatus::Completed);
+        assert!(task.progress(4_010).failed_accounts.is_empty());
+    }
+
+    #[test]
+    fn test_store_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SettlementTaskStore::new(dir.path().to_str().unwrap());
+        let task = SettlementTask::new("2026-10-16", ids(3), 2, (5, 6), 7);
+        store.save_accounts(&task).unwrap();
+        store.save_task(&task).unwrap();
+        store
+            .save_shard("2026-10-16", 1, &ShardOutput::default())
+            .unwrap();
+
+        let loaded = store.load("2026-10-16").unwrap().unwrap();
+        assert_eq!(loaded.account_ids, task.account_ids);
+        assert_eq!(loaded.interest_window, (5, 6));
+        assert_eq!(loaded.shards.len(), 2);
+        assert!(store.load_shard("2026-10-16", 1).unwrap().is_some());
+        assert!(store.load_shard("2026-10-16", 0).unwrap().is_none());
+        assert!(store.load("2026-10-17").unwrap().is_none());
+        assert!(SettlementTaskStore::new("")
+            .load("2026-10-16")
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,668 @@
+//! 订单路由影子模式 (Shadow Mode)
+//! @yutiansut @quantaxis
+//!
+//! 新版本撮合逻辑上线前的旁路验证：主撮合引擎照常生效，影子撮合引擎只计算不生效。
+//! - 主路径在持有订单簿锁时把撮合输入与主引擎结果摘要 `try_send` 到有界队列，
+//!   保证影子侧按主引擎顺序重放；队列满时丢弃并计数，不阻塞下单
+//! - 后台线程在影子引擎上重放同一限价单/撤单，自行维护 主订单ID -> 影子引擎订单ID 映射
+//! - 对比内容：是否被接受、新订单（taker）逐档成交 (价格, 数量)、撤单是否成功、是否被拒绝
+//! - 不一致时 `log::warn!` 告警，并保留最近的差异明细供排查
+//! - 影子引擎通过 [`ShadowMatcher`] 注入，默认实现为独立的 [`ExchangeMatchingEngine`]
+
+use crossbeam::channel::{bounded, Receiver, Sender, TrySendError};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::matching::engine::{ExchangeMatchingEngine, InstrumentAsset};
+use crate::matching::{orders, Failed, OrderDirection, OrderRequest, Success};
+use crate::ExchangeError;
+
+/// 成交数量/价格比较容差
+const FILL_EPSILON: f64 = 1e-9;
+
+/// 影子模式配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShadowModeConfig {
+    /// 是否启用（仅服务启动时读取）
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// 待重放任务队列容量（满则丢弃）
+    #[serde(default = "default_queue_capacity")]
+    pub queue_capacity: usize,
+
+    /// 保留的最近差异明细条数
+    #[serde(default = "default_max_mismatch_records")]
+    pub max_mismatch_records: usize,
+}
+
+fn default_queue_capacity() -> usize {
+    10000
+}
+
+fn default_max_mismatch_records() -> usize {
+    1000
+}
+
+impl Default for ShadowModeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            queue_capacity: default_queue_capacity(),
+            max_mismatch_records: default_max_mismatch_records(),
+        }
+    }
+}
+
+/// 影子撮合引擎（可运行新版本撮合逻辑）
+pub trait ShadowMatcher: Send + Sync {
+    fn process(
+        &self,
+        instrument_id: &str,
+        request: OrderRequest<InstrumentAsset>,
+    ) -> Result<Vec<Result<Success, Failed>>, ExchangeError>;
+}
+
+impl ShadowMatcher for ExchangeMatchingEngine {
+    fn process(
+        &self,
+        instrument_id: &str,
+        request: OrderRequest<InstrumentAsset>,
+    ) -> Result<Vec<Result<Success, Failed>>, ExchangeError> {
+        self.process_order(instrument_id, request)
+    }
+}
+
+/// 需要在影子引擎重放的撮合输入
+#[derive(Debug, Clone)]
+pub enum ShadowRequest {
+    /// 限价单
+    Submit {
+        order_id: String,
+        direction: String,
+        price: f64,
+        volume: f64,
+        timestamp: i64,
+    },
+    /// 撤单
+    Cancel { order_id: String, direction: String },
+}
+
+impl ShadowRequest {
+    pub fn order_id(&self) -> &str {
+        match self {
+            ShadowRequest::Submit { order_id, .. } | ShadowRequest::Cancel { order_id, .. } => {
+                order_id
+            }
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            ShadowRequest::Submit { .. } => "submit",
+            ShadowRequest::Cancel { .. } => "cancel",
+        }
+    }
+}
+
+/// 单档成交
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ShadowFill {
+    pub price: f64,
+    pub volume: f64,
+}
+
+/// 一次撮合调用的结果摘要（只关注新订单本身）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MatchOutcome {
+    pub accepted: bool,
+    pub cancelled: bool,
+    pub failed: bool,
+    /// 新订单（taker）逐档成交，按撮合顺序
+    pub fills: Vec<ShadowFill>,
+}
+
+impl MatchOutcome {
+    /// 从撮合引擎返回的事件提取摘要
+    ///
+    /// 与 OrderRouter 一致：第一个成交事件的订单即新订单（taker），其余为对手单事件
+    pub fn from_results(results: &[Result<Success, Failed>]) -> Self {
+        let mut outcome = Self::default();
+        let mut taker_id: Option<u64> = None;
+
+        for result in results {
+            match result {
+                Ok(Success::Accepted { .. }) => outcome.accepted = true,
+                Ok(Success::Cancelled { .. }) => outcome.cancelled = true,
+                Ok(Success::Filled {
+                    order_id,
+                    price,
+                    volume,
+                    ..
+                })
+                | Ok(Success::PartiallyFilled {
+                    order_id,
+                    price,
+                    volume,
+                    ..
+                }) => {
+                    if taker_id.map_or(true, |id| id == *order_id) {
+                        taker_id = Some(*order_id);
+                        outcome.fills.push(ShadowFill {
+                            price: *price,
+                            volume: *volume,
+                        });
+                    }
+                }
+                Ok(_) => {}
+                Err(_) => outcome.failed = true,
+            }
+        }
+        outcome
+    }
+
+    fn failed() -> Self {
+        Self {
+            failed: true,
+            ..Self::default()
+        }
+    }
+
+    /// 与影子结果对比，返回差异描述（一致返回 None）
+    pub fn diff(&self, shadow: &MatchOutcome) -> Option<String> {
+        let mut diffs = Vec::new();
+        if self.accepted != shadow.accepted {
+            diffs.push(format!(
+                "accepted: primary={} shadow={}",
+                self.accepted, shadow.accepted
+            ));
+        }
+        if self.cancelled != shadow.cancelled {
+            diffs.push(format!(
+                "cancelled: primary={} shadow={}",
+                self.cancelled, shadow.cancelled
+            ));
+        }
+        if self.failed != shadow.failed {
+            diffs.push(format!(
+                "failed: primary={} shadow={}",
+                self.failed, shadow.failed
+            ));
+        }
+        let same_fills = self.fills.len() == shadow.fills.len()
+            && self.fills.iter().zip(&shadow.fills).all(|(a, b)| {
+                (a.price - b.price).abs() < FILL_EPSILON
+                    && (a.volume - b.volume).abs() < FILL_EPSILON
+            });
+        if !same_fills {
+            diffs.push(format!(
+                "fills: primary={:?} shadow={:?}",
+                self.fills, shadow.fills
+            ));
+        }
+
+        if diffs.is_empty() {
+            None
+        } else {
+            Some(diffs.join("; "))
+        }
+    }
+}
+
+/// 主/影子撮合结果差异记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShadowMismatch {
+    pub instrument_id: String,
+    pub order_id: String,
+    /// submit / cancel
+    pub kind: String,
+    pub primary: MatchOutcome,
+    pub shadow: MatchOutcome,
+    pub detail: String,
+    pub timestamp: i64,
+}
+
+/// 影子模式统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ShadowModeStats {
+    /// 投递到影子队列的请求数
+    pub submitted: u64,
+    /// 队列满被丢弃的请求数
+    pub dropped: u64,
+    /// 完成对比的请求数
+    pub compared: u64,
+    pub matched: u64,
+    pub mismatched: u64,
+    /// 影子引擎执行出错（如合约未注册）的请求数
+    pub errors: u64,
+}
+
+struct ShadowTask {
+    instrument_id: String,
+    request: ShadowRequest,
+    primary: MatchOutcome,
+}
+
+#[derive(Default)]
+struct ShadowState {
+    submitted: AtomicU64,
+    dropped: AtomicU64,
+    compared: AtomicU64,
+    matched: AtomicU64,
+    mismatched: AtomicU64,
+    errors: AtomicU64,
+    mismatches: Mutex<VecDeque<ShadowMismatch>>,
+}
+
+impl ShadowState {
+    fn processed(&self) -> u64 {
+        self.compared.load(Ordering::Relaxed) + self.errors.load(Ordering::Relaxed)
+    }
+}
+
+/// 订单路由影子模式
+pub struct ShadowMode {
+    task_tx: Sender<ShadowTask>,
+    state: Arc<ShadowState>,
+}
+
+impl ShadowMode {
+    /// 启动影子模式后台线程
+    pub fn start(
+        matcher: Arc<dyn ShadowMatcher>,
+        config: ShadowModeConfig,
+    ) -> Result<Self, ExchangeError> {
+        let (task_tx, task_rx) = bounded(config.queue_capacity.max(1));
+        let state = Arc::new(ShadowState::default());
+
+        let worker_state = state.clone();
+        std::thread::Builder::new()
+            .name("order-shadow".to_string())
+            .spawn(move || {
+                ShadowWorker {
+                    matcher,
+                    state: worker_state,
+                    max_mismatch_records: config.max_mismatch_records,
+                    engine_ids: HashMap::new(),
+                }
+                .run(task_rx)
+            })
+            .map_err(|e| {
+                ExchangeError::InternalError(format!("Spawn shadow thread failed: {}", e))
+            })?;
+
+        Ok(Self { task_tx, state })
+    }
+
+    /// 投递主引擎的撮合输入与结果（非阻塞，队列满则丢弃）
+    pub fn observe(
+        &self,
+        instrument_id: &str,
+        request: ShadowRequest,
+        primary_results: &[Result<Success, Failed>],
+    ) {
+        let task = ShadowTask {
+            instrument_id: instrument_id.to_string(),
+            request,
+            primary: MatchOutcome::from_results(primary_results),
+        };
+        match self.task_tx.try_send(task) {
+            Ok(()) => {
+                self.state.submitted.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(TrySendError::Full(task)) => {
+                self.state.dropped.fetch_add(1, Ordering::Relaxed);
+                log::debug!(
+                    "Shadow queue full, dropped {} for {}",
+                    task.request.kind(),
+                    task.request.order_id()
+                );
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                self.state.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// 获取统计
+    pub fn stats(&self) -> ShadowModeStats {
+        ShadowModeStats {
+            submitted: self.state.submitted.load(Ordering::Relaxed),
+            dropped: self.state.dropped.load(Ordering::Relaxed),
+            compared: self.state.compared.load(Ordering::Relaxed),
+            matched: self.state.matched.load(Ordering::Relaxed),
+            mismatched: self.state.mismatched.load(Ordering::Relaxed),
+            errors: self.state.errors.load(Ordering::Relaxed),
+        }
+    }
+
+    /// 最近的差异明细（按发生顺序）
+    pub fn recent_mismatches(&self, limit: usize) -> Vec<ShadowMismatch> {
+        let mismatches = self.state.mismatches.lock();
+        let skip = mismatches.len().saturating_sub(limit);
+        mismatches.iter().skip(skip).cloned().collect()
+    }
+
+    /// 等待已投递的请求全部完成对比，超时返回 false
+    pub fn wait_idle(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        while self.state.processed() < self.state.submitted.load(Ordering::Relaxed) {
+            if Instant::now() >= deadline {
+                return false;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        true
+    }
+}
+
+struct ShadowWorker {
+    matcher: Arc<dyn ShadowMatcher>,
+    state: Arc<ShadowState>,
+    max_mismatch_records: usize,
+    /// 主订单ID -> 影子引擎订单ID
+    engine_ids: HashMap<String, u64>,
+}
+
+impl ShadowWorker {
+    /// ShadowMode 释放后 recv 返回 Err，线程退出
+    fn run(mut self, task_rx: Receiver<ShadowTask>) {
+        while let Ok(task) = task_rx.recv() {
+            match self.replay(&task) {
+                Ok(shadow) => self.compare(task, shadow),
+                Err(e) => {
+                    log::warn!(
+                        "Shadow replay {} {} failed: {}",
+                        task.request.kind(),
+                        task.request.order_id(),
+                        e
+                    );
+                    self.state.errors.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+        log::info!("order-shadow stopped");
+    }
+
+    fn replay(&mut self, task: &ShadowTask) -> Result<MatchOutcome, ExchangeError> {
+        match &task.request {
+            ShadowRequest::Submit {
+                order_id,
+                direction,
+                price,
+                volume,
+                timestamp,
+            } => {
+                let request = orders::new_limit_order_request(
+                    InstrumentAsset::from_code(&task.instrument_id),
+                    parse_direction(direction)?,
+                    *price,
+                    *volume,
+                    *timestamp,
+                );
+                let results = self.matcher.process(&task.instrument_id, request)?;
+                for result in &results {
+                    if let Ok(Success::Accepted { id, .. }) = result {
+                        self.engine_ids.insert(order_id.clone(), *id);
+                        break;
+                    }
+                }
+                Ok(MatchOutcome::from_results(&results))
+            }
+            ShadowRequest::Cancel {
+                order_id,
+                direction,
+            } => {
+                // 影子侧从未接受该订单，撤单视为失败
+                let Some(id) = self.engine_ids.remove(order_id) else {
+                    return Ok(MatchOutcome::failed());
+                };
+                let request = OrderRequest::CancelOrder {
+                    id,
+                    direction: parse_direction(direction)?,
+                };
+                let results = self.matcher.process(&task.instrument_id, request)?;
+                Ok(MatchOutcome::from_results(&results))
+            }
+        }
+    }
+
+    fn compare(&self, task: ShadowTask, shadow: MatchOutcome) {
+        self.state.compared.fetch_add(1, Ordering::Relaxed);
+        let Some(detail) = task.primary.diff(&shadow) else {
+            self.state.matched.fetch_add(1, Ordering::Relaxed);
+            return;
+        };
+
+        self.state.mismatched.fetch_add(1, Ordering::Relaxed);
+        log::warn!(
+            "⚠️ Shadow mismatch on {} {} ({}): {}",
+            task.instrument_id,
+            task.request.order_id(),
+            task.request.kind(),
+            detail
+        );
+
+        let mut mismatches = self.state.mismatches.lock();
+        mismatches.push_back(ShadowMismatch {
+            instrument_id: task.instrument_id,
+            order_id: task.request.order_id().to_string(),
+            kind: task.request.kind().to_string(),
+            primary: task.primary,
+            shadow,
+            detail,
+            timestamp: chrono::Utc::now().timestamp_millis(),
+        });
+        while mismatches.len() > self.max_mismatch_records {
+            mismatches.pop_front();
+        }
+    }
+}
+
+fn parse_direction(direction: &str) -> Result<OrderDirection, ExchangeError> {
+    match direction {
+        "BUY" => Ok(OrderDirection::BUY),
+        "SELL" => Ok(OrderDirection::SELL),
+        _ => Err(ExchangeError::OrderError(format!(
+            "Invalid direction: {}",
+            direction
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// "新版本"撮合逻辑：不支持撤单
+    struct NoCancelMatcher(ExchangeMatchingEngine);
+
+    impl ShadowMatcher for NoCancelMatcher {
+        fn process(
+            &self,
+            instrument_id: &str,
+            request: OrderRequest<InstrumentAsset>,
+        ) -> Result<Vec<Result<Success, Failed>>, ExchangeError> {
+            if let OrderRequest::CancelOrder { .. } = request {
+                return Ok(Vec::new());
+            }
+            self.0.process(instrument_id, request)
+        }
+    }
+
+    fn engine() -> ExchangeMatchingEngine {
+        let engine = ExchangeMatchingEngine::new();
+        engine
+            .register_instrument("IF2501".to_string(), 100.0)
+            .unwrap();
+        engine
+    }
+
+    /// 在主引擎执行并投递给影子模式，模拟 OrderRouter 的调用方式
+    fn run_primary(
+        primary: &ExchangeMatchingEngine,
+        shadow: &ShadowMode,
+        ids: &mut HashMap<String, u64>,
+        request: ShadowRequest,
+    ) {
+        let match_request = match &request {
+            ShadowRequest::Submit {
+                direction,
+                price,
+                volume,
+                timestamp,
+                ..
+            } => orders::new_limit_order_request(
+                InstrumentAsset::from_code("IF2501"),
+                parse_direction(direction).unwrap(),
+                *price,
+                *volume,
+                *timestamp,
+            ),
+            ShadowRequest::Cancel {
+                order_id,
+                direction,
+            } => OrderRequest::CancelOrder {
+                id: ids[order_id],
+                direction: parse_direction(direction).unwrap(),
+            },
+        };
+        let results = primary.process("IF2501", match_request).unwrap();
+        for result in &results {
+            if let Ok(Success::Accepted { id, .. }) = result {
+                ids.insert(request.order_id().to_string(), *id);
+                break;
+            }
+        }
+        shadow.observe("IF2501", request, &results);
+    }
+
+    fn submit(order_id: &str, direction: &str, price: f64, volume: f64, ts: i64) -> ShadowRequest {
+        ShadowRequest::Submit {
+            order_id: order_id.to_string(),
+            direction: direction.to_string(),
+            price,
+            volume,
+            timestamp: ts,
+        }
+    }
+
+    fn run_scenario(shadow: &ShadowMode) {
+        let primary = engine();
+        let mut ids = HashMap::new();
+        run_primary(
+            &primary,
+            shadow,
+            &mut ids,
+            submit("O1", "SELL", 100.0, 3.0, 1),
+        );
+        run_primary(
+            &primary,
+            shadow,
+            &mut ids,
+            submit("O2", "SELL", 101.0, 2.0, 2),
+        );
+        run_primary(
+            &primary,
+            shadow,
+            &mut ids,
+            submit("O3", "BUY", 101.0, 4.0, 3),
+        );
+        run_primary(
+            &primary,
+            shadow,
+            &mut ids,
+            ShadowRequest::Cancel {
+                order_id: "O2".to_string(),
+                direction: "SELL".to_string(),
+            },
+        );
+        assert!(shadow.wait_idle(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn test_identical_engine_has_no_mismatch() {
+        let shadow = ShadowMode::start(Arc::new(engine()), ShadowModeConfig::default()).unwrap();
+        run_scenario(&shadow);
+
+        let stats = shadow.stats();
+        assert_eq!(stats.submitted, 4);
+        assert_eq!(stats.compared, 4);
+        assert_eq!(stats.matched, 4);
+        assert_eq!(stats.mismatched, 0);
+        assert!(shadow.recent_mismatches(10).is_empty());
+    }
+
+    #[test]
+    fn test_different_logic_reports_mismatch() {
+        // 影子订单簿多一笔 100.5 的卖单，O3 第二档成交价不同
+        let shadow_engine = engine();
+        shadow_engine
+            .process(
+                "IF2501",
+                orders::new_limit_order_request(
+                    InstrumentAsset::from_code("IF2501"),
+                    OrderDirection::SELL,
+                    100.5,
+                    1.0,
+                    0,
+                ),
+            )
+            .unwrap();
+        let shadow = ShadowMode::start(
+            Arc::new(NoCancelMatcher(shadow_engine)),
+            ShadowModeConfig::default(),
+        )
+        .unwrap();
+        run_scenario(&shadow);
+
+        let stats = shadow.stats();
+        assert_eq!(stats.compared, 4);
+        assert_eq!(stats.mismatched, 2);
+        assert_eq!(stats.matched, 2);
+
+        let mismatches = shadow.recent_mismatches(10);
+        assert_eq!(mismatches.len(), 2);
+        let o3 = &mismatches[0];
+        assert_eq!((o3.order_id.as_str(), o3.kind.as_str()), ("O3", "submit"));
+        assert_eq!(
+            o3.primary.fills,
+            vec![
+                ShadowFill {
+                    price: 100.0,
+                    volume: 3.0
+                },
+                ShadowFill {
+                    price: 101.0,
+                    volume: 1.0
+                },
+            ]
+        );
+        assert_eq!(
+            o3.shadow.fills,
+            vec![
+                ShadowFill {
+                    price: 100.0,
+                    volume: 3.0
+                },
+                ShadowFill {
+                    price: 100.5,
+                    volume: 1.0
+                },
+            ]
+        );
+        assert!(o3.detail.contains("fills"));
+
+        let cancel = &mismatches[1];
+        assert_eq!(
+            (cancel.order_id.as_str(), cancel.kind.as_str()),
+            ("O2", "cancel")
+        );
+        assert!(cancel.primary.cancelled);
+        assert!(!cancel.shadow.cancelled);
+    }
+}
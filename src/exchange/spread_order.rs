@@ -0,0 +1,945 @@
+//! 价差单（跨合约套利）引擎
+//! @yutiansut @quantaxis
+//!
+//! 套利客户以"买 A 卖 B"的形式下单，按价差触发后同时对两腿下单：
+//! - 价差实时计算：价差 = 腿1可成交价 - 腿2可成交价（买腿取卖一价，卖腿取买一价）
+//! - 价差满足目标时两腿同时以限价下单
+//! - 单腿下单失败：撤销已下的另一腿（回滚）
+//! - 腿风险：一腿成交另一腿未成交超过等待时间，撤销未成交腿并标记敞口
+//! - 目前仅支持同交易所两腿
+
+use chrono::Utc;
+use dashmap::DashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::exchange::instrument_registry::InstrumentRegistry;
+use crate::exchange::order_router::{
+    CancelOrderRequest, OrderRouter, OrderStatus, SubmitOrderRequest,
+};
+use crate::market::{MarketDataBroadcaster, MarketDataEvent};
+use crate::service::http::models::{
+    CreateSpreadOrderRequest, SpreadLeg, SpreadLegInfo, SpreadOrderInfo, SpreadOrderStatus,
+    TriggerCondition,
+};
+
+/// 默认腿风险等待时间（毫秒）
+pub const DEFAULT_LEG_TIMEOUT_MS: i64 = 5_000;
+
+/// 成交比例比较精度
+const FILL_EPSILON: f64 = 1e-9;
+
+/// 合约最新报价
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SpreadQuote {
+    pub bid: Option<f64>,
+    pub ask: Option<f64>,
+    pub last: Option<f64>,
+}
+
+impl SpreadQuote {
+    /// 按方向取可成交价：买取卖一，卖取买一，缺失时退回最新价
+    fn executable_price(&self, direction: &str) -> Option<f64> {
+        let side = if direction == "BUY" {
+            self.ask
+        } else {
+            self.bid
+        };
+        side.or(self.last).filter(|p| *p > 0.0)
+    }
+}
+
+/// 内部价差单腿
+#[derive(Debug, Clone)]
+struct SpreadOrderLeg {
+    leg: SpreadLeg,
+    volume: f64,
+    order_id: Option<String>,
+    price: Option<f64>,
+    filled_volume: f64,
+}
+
+impl SpreadOrderLeg {
+    fn fill_ratio(&self) -> f64 {
+        if self.volume > 0.0 {
+            self.filled_volume / self.volume
+        } else {
+            0.0
+        }
+    }
+
+    fn to_info(&self) -> SpreadLegInfo {
+        SpreadLegInfo {
+            instrument_id: self.leg.instrument_id.clone(),
+            direction: self.leg.direction.clone(),
+            offset: self.leg.offset.clone(),
+            ratio: self.leg.ratio,
+            volume: self.volume,
+            order_id: self.order_id.clone(),
+            price: self.price,
+            filled_volume: self.filled_volume,
+        }
+    }
+}
+
+/// 内部价差单结构
+#[derive(Debug, Clone)]
+pub struct SpreadOrder {
+    pub id: String,
+    pub account_id: String,
+    legs: [SpreadOrderLeg; 2],
+    pub volume: f64,
+    pub target_spread: f64,
+    pub trigger_condition: TriggerCondition,
+    pub leg_timeout_ms: i64,
+    pub valid_until: Option<i64>,
+    pub status: SpreadOrderStatus,
+    pub created_at: i64,
+    pub triggered_at: Option<i64>,
+    pub triggered_spread: Option<f64>,
+    /// 两腿成交比例开始不一致的时间
+    imbalance_since: Option<i64>,
+    pub message: Option<String>,
+}
+
+impl SpreadOrder {
+    /// 转换为 API 响应格式
+    pub fn to_info(&self, current_spread: Option<f64>) -> SpreadOrderInfo {
+        SpreadOrderInfo {
+            spread_order_id: self.id.clone(),
+            account_id: self.account_id.clone(),
+            legs: self.legs.iter().map(|l| l.to_info()).collect(),
+            volume: self.volume,
+            target_spread: self.target_spread,
+            trigger_condition: self.trigger_condition.clone(),
+            current_spread,
+            triggered_spread: self.triggered_spread,
+            status: self.status.clone(),
+            created_at: self.created_at,
+            triggered_at: self.triggered_at,
+            message: self.message.clone(),
+        }
+    }
+
+    /// 检查价差是否满足
+    pub fn check_trigger(&self, spread: f64) -> bool {
+        match self.trigger_condition {
+            TriggerCondition::GreaterOrEqual => spread >= self.target_spread,
+            TriggerCondition::LessOrEqual => spread <= self.target_spread,
+        }
+    }
+
+    /// 检查是否过期
+    pub fn is_expired(&self) -> bool {
+        if let Some(valid_until) = self.valid_until {
+            Utc::now().timestamp_millis() > valid_until
+        } else {
+            false
+        }
+    }
+
+    /// 未对冲敞口说明（两腿成交比例一致时返回 None）
+    fn unhedged_message(&self) -> Option<String> {
+        let (r0, r1) = (self.legs[0].fill_ratio(), self.legs[1].fill_ratio());
+        if (r0 - r1).abs() <= FILL_EPSILON {
+            return None;
+        }
+        let (ahead, behind) = if r0 > r1 {
+            (&self.legs[0], &self.legs[1])
+        } else {
+            (&self.legs[1], &self.legs[0])
+        };
+        let unhedged = (ahead.fill_ratio() - behind.fill_ratio()) * ahead.volume;
+        Some(format!(
+            "腿风险: {} {} 多成交 {} 手未对冲 ({} 成交 {}/{}, {} 成交 {}/{})",
+            ahead.leg.instrument_id,
+            ahead.leg.direction,
+            unhedged,
+            self.legs[0].leg.instrument_id,
+            self.legs[0].filled_volume,
+            self.legs[0].volume,
+            self.legs[1].leg.instrument_id,
+            self.legs[1].filled_volume,
+            self.legs[1].volume,
+        ))
+    }
+}
+
+/// 价差单引擎
+pub struct SpreadOrderEngine {
+    /// 价差单存储: spread_order_id -> SpreadOrder
+    orders: DashMap<String, SpreadOrder>,
+    /// 按账户索引: account_id -> Vec<spread_order_id>
+    by_account: DashMap<String, Vec<String>>,
+    /// 按合约索引: instrument_id -> Vec<spread_order_id>
+    by_instrument: DashMap<String, Vec<String>>,
+    /// 最新报价: instrument_id -> SpreadQuote
+    quotes: DashMap<String, SpreadQuote>,
+    /// 订单路由器
+    order_router: Option<Arc<OrderRouter>>,
+    /// 合约注册表（校验两腿同交易所）
+    instrument_registry: Option<Arc<InstrumentRegistry>>,
+}
+
+impl SpreadOrderEngine {
+    pub fn new() -> Self {
+        Self {
+            orders: DashMap::new(),
+            by_account: DashMap::new(),
+            by_instrument: DashMap::new(),
+            quotes: DashMap::new(),
+            order_router: None,
+            instrument_registry: None,
+        }
+    }
+
+    /// 设置订单路由器
+    pub fn set_order_router(&mut self, router: Arc<OrderRouter>) {
+        self.order_router = Some(router);
+    }
+
+    /// 设置合约注册表
+    pub fn set_instrument_registry(&mut self, registry: Arc<InstrumentRegistry>) {
+        self.instrument_registry = Some(registry);
+    }
+
+    /// 合约所属交易所（优先取注册表，否则取 "SHFE.cu2501" 形式的前缀）
+    fn exchange_of(&self, instrument_id: &str) -> Option<String> {
+        if let Some(info) = self
+            .instrument_registry
+            .as_ref()
+            .and_then(|r| r.get(instrument_id))
+        {
+            return Some(info.exchange);
+        }
+        instrument_id
+            .split_once('.')
+            .map(|(exchange, _)| exchange.to_string())
+    }
+
+    /// 创建价差单
+    pub fn create_order(&self, req: CreateSpreadOrderRequest) -> Result<SpreadOrderInfo, String> {
+        if req.volume <= 0.0 {
+            return Err(format!("价差单数量必须大于0: {}", req.volume));
+        }
+        for leg in &req.legs {
+            if leg.ratio <= 0.0 {
+                return Err(format!(
+                    "腿 {} 数量比例必须大于0: {}",
+                    leg.instrument_id, leg.ratio
+                ));
+            }
+            if leg.direction != "BUY" && leg.direction != "SELL" {
+                return Err(format!(
+                    "腿 {} 方向无效: {}",
+                    leg.instrument_id, leg.direction
+                ));
+            }
+        }
+        let [leg1, leg2] = req.legs;
+        if leg1.instrument_id == leg2.instrument_id {
+            return Err("价差单两腿合约不能相同".to_string());
+        }
+        if self.exchange_of(&leg1.instrument_id) != self.exchange_of(&leg2.instrument_id) {
+            return Err(format!(
+                "暂仅支持同交易所价差单: {} / {}",
+                leg1.instrument_id, leg2.instrument_id
+            ));
+        }
+
+        let order_id = format!(
+            "SPRD_{}",
+            Uuid::new_v4().to_string().replace("-", "")[..12].to_uppercase()
+        );
+        let to_leg = |leg: SpreadLeg| SpreadOrderLeg {
+            volume: req.volume * leg.ratio,
+            leg,
+            order_id: None,
+            price: None,
+            filled_volume: 0.0,
+        };
+        let order = SpreadOrder {
+            id: order_id.clone(),
+            account_id: req.account_id.clone(),
+            legs: [to_leg(leg1), to_leg(leg2)],
+            volume: req.volume,
+            target_spread: req.target_spread,
+            trigger_condition: req.trigger_condition,
+            leg_timeout_ms: req.leg_timeout_ms.unwrap_or(DEFAULT_LEG_TIMEOUT_MS),
+            valid_until: req.valid_until,
+            status: SpreadOrderStatus::Pending,
+            created_at: Utc::now().timestamp_millis(),
+            triggered_at: None,
+            triggered_spread: None,
+            imbalance_since: None,
+            message: None,
+        };
+
+        // 添加到索引
+        self.by_account
+            .entry(req.account_id)
+            .or_default()
+            .push(order_id.clone());
+        for leg in &order.legs {
+            self.by_instrument
+                .entry(leg.leg.instrument_id.clone())
+                .or_default()
+                .push(order_id.clone());
+        }
+
+        let info = order.to_info(self.current_spread(&order));
+        self.orders.insert(order_id, order);
+
+        log::info!("价差单创建成功: {}", info.spread_order_id);
+        Ok(info)
+    }
+
+    /// 当前可成交价差（任一腿缺少报价时返回 None）
+    fn current_spread(&self, order: &SpreadOrder) -> Option<f64> {
+        let price = |leg: &SpreadOrderLeg| {
+            self.quotes
+                .get(&leg.leg.instrument_id)
+                .and_then(|q| q.executable_price(&leg.leg.direction))
+        };
+        Some(price(&order.legs[0])? - price(&order.legs[1])?)
+    }
+
+    /// 查询价差单
+    pub fn get_order(&self, order_id: &str) -> Option<SpreadOrderInfo> {
+        self.orders
+            .get(order_id)
+            .map(|o| o.to_info(self.current_spread(&o)))
+    }
+
+    /// 查询账户的所有价差单
+    pub fn get_orders_by_account(&self, account_id: &str) -> Vec<SpreadOrderInfo> {
+        self.by_account
+            .get(account_id)
+            .map(|ids| ids.iter().filter_map(|id| self.get_order(id)).collect())
+            .unwrap_or_default()
+    }
+
+    /// 更新合约报价并检查相关价差单
+    /// 返回本次触发的价差单列表
+    pub fn update_quote(
+        &self,
+        instrument_id: &str,
+        bid: Option<f64>,
+        ask: Option<f64>,
+        last: Option<f64>,
+    ) -> Vec<SpreadOrderInfo> {
+        {
+            let mut quote = self.quotes.entry(instrument_id.to_string()).or_default();
+            if bid.is_some() {
+                quote.bid = bid;
+            }
+            if ask.is_some() {
+                quote.ask = ask;
+            }
+            if last.is_some() {
+                quote.last = last;
+            }
+        }
+        self.check_triggers(instrument_id)
+    }
+
+    /// 检查合约相关的待触发价差单
+    pub fn check_triggers(&self, instrument_id: &str) -> Vec<SpreadOrderInfo> {
+        let order_ids = match self.by_instrument.get(instrument_id) {
+            Some(ids) => ids.clone(),
+            None => return Vec::new(),
+        };
+
+        let mut triggered = Vec::new();
+        for order_id in order_ids {
+            let Some(mut order) = self.orders.get_mut(&order_id) else {
+                continue;
+            };
+            if order.status != SpreadOrderStatus::Pending {
+                continue;
+            }
+            if order.is_expired() {
+                order.status = SpreadOrderStatus::Expired;
+                log::info!("价差单已过期: {}", order_id);
+                continue;
+            }
+            let Some(spread) = self.current_spread(&order) else {
+                continue;
+            };
+            if !order.check_trigger(spread) {
+                continue;
+            }
+
+            log::info!(
+                "价差单触发: {} (目标价差 {} 当前价差 {})",
+                order_id,
+                order.target_spread,
+                spread
+            );
+            order.triggered_at = Some(Utc::now().timestamp_millis());
+            order.triggered_spread = Some(spread);
+            self.execute_legs(&mut order);
+            triggered.push(order.to_info(Some(spread)));
+        }
+
+        triggered
+    }
+
+    /// 两腿同时下单，单腿失败时撤销另一腿
+    fn execute_legs(&self, order: &mut SpreadOrder) {
+        let Some(router) = self.order_router.clone() else {
+            order.status = SpreadOrderStatus::Failed;
+            order.message = Some("无订单路由器，无法下单".to_string());
+            log::warn!("价差单触发但无法执行（无订单路由器）: {}", order.id);
+            return;
+        };
+
+        for i in 0..order.legs.len() {
+            let price = self
+                .quotes
+                .get(&order.legs[i].leg.instrument_id)
+                .and_then(|q| q.executable_price(&order.legs[i].leg.direction))
+                .unwrap_or(0.0);
+            let leg = &mut order.legs[i];
+            let response = router.submit_order(SubmitOrderRequest {
+                account_id: order.account_id.clone(),
+                instrument_id: leg.leg.instrument_id.clone(),
+                direction: leg.leg.direction.clone(),
+                offset: leg.leg.offset.clone(),
+                volume: leg.volume,
+                price,
+                order_type: "LIMIT".to_string(),
+                time_condition: None,
+                volume_condition: None,
+                hedge_flag: None,
+                ttl_secs: None,
+                client_order_id: None,
+                close_lot_id: None,
+            });
+            leg.price = Some(price);
+
+            if !response.success {
+                let reason = format!(
+                    "腿 {} 下单失败: {}",
+                    leg.leg.instrument_id,
+                    response.error_message.unwrap_or_default()
+                );
+                log::error!("价差单 {} {}", order.id, reason);
+                if i == 0 {
+                    order.status = SpreadOrderStatus::Failed;
+                    order.message = Some(reason);
+                } else {
+                    self.cancel_working_legs(&router, order);
+                    match order.unhedged_message() {
+                        Some(risk) => {
+                            order.status = SpreadOrderStatus::LegRisk;
+                            order.message = Some(format!("{}; {}", reason, risk));
+                        }
+                        None => {
+                            order.status = SpreadOrderStatus::RolledBack;
+                            order.message = Some(reason);
+                        }
+                    }
+                }
+                return;
+            }
+            leg.order_id = response.order_id;
+        }
+
+        order.status = SpreadOrderStatus::Working;
+        self.refresh_fills(&router, order);
+    }
+
+    /// 从路由器同步两腿成交量，返回两腿订单状态
+    fn refresh_fills(
+        &self,
+        router: &OrderRouter,
+        order: &mut SpreadOrder,
+    ) -> [Option<OrderStatus>; 2] {
+        let mut statuses = [None, None];
+        for (leg, status) in order.legs.iter_mut().zip(statuses.iter_mut()) {
+            if let Some((_, s, _, _, filled)) = leg
+                .order_id
+                .as_deref()
+                .and_then(|id| router.get_order_detail(id))
+            {
+                leg.filled_volume = filled;
+                *status = Some(s);
+            }
+        }
+        if order.status == SpreadOrderStatus::Working
+            && statuses.iter().all(|s| *s == Some(OrderStatus::Filled))
+        {
+            order.status = SpreadOrderStatus::Filled;
+            order.imbalance_since = None;
+        }
+        statuses
+    }
+
+    /// 撤销仍在挂单的腿，并同步最终成交量
+    fn cancel_working_legs(&self, router: &OrderRouter, order: &mut SpreadOrder) {
+        for leg in &order.legs {
+            let Some(leg_order_id) = leg.order_id.clone() else {
+                continue;
+            };
+            if !matches!(
+                router.get_order_status(&leg_order_id),
+                Some(OrderStatus::Submitted | OrderStatus::PartiallyFilled)
+            ) {
+                continue;
+            }
+            if let Err(e) = router.cancel_order(CancelOrderRequest {
+                account_id: order.account_id.clone(),
+                order_id: leg_order_id.clone(),
+            }) {
+                log::error!("价差单 {} 撤销腿 {} 失败: {}", order.id, leg_order_id, e);
+            }
+        }
+        self.refresh_fills(router, order);
+    }
+
+    /// 检查已下单价差单的两腿成交情况（由后台周期调用）
+    ///
+    /// 两腿成交比例不一致超过等待时间，或任一腿被撤/拒时，撤销未成交腿；
+    /// 撤销后两腿仍不一致则标记为腿风险。返回状态发生变化的价差单。
+    pub fn check_leg_risk(&self) -> Vec<SpreadOrderInfo> {
+        let Some(router) = self.order_router.clone() else {
+            return Vec::new();
+        };
+        let now = Utc::now().timestamp_millis();
+        let mut changed = Vec::new();
+
+        for mut entry in self.orders.iter_mut() {
+            let order = entry.value_mut();
+            if order.status != SpreadOrderStatus::Working {
+                continue;
+            }
+
+            let statuses = self.refresh_fills(&router, order);
+            if order.status == SpreadOrderStatus::Filled {
+                log::info!("价差单两腿全部成交: {}", order.id);
+                changed.push(order.to_info(self.current_spread(order)));
+                continue;
+            }
+
+            let leg_dead = statuses
+                .iter()
+                .any(|s| matches!(s, Some(OrderStatus::Cancelled | OrderStatus::Rejected)));
+            let imbalanced = order.unhedged_message().is_some();
+            if imbalanced {
+                order.imbalance_since.get_or_insert(now);
+            } else {
+                order.imbalance_since = None;
+            }
+            let timed_out = order
+                .imbalance_since
+                .is_some_and(|since| now - since >= order.leg_timeout_ms);
+
+            if !leg_dead && !timed_out {
+                continue;
+            }
+
+            self.cancel_working_legs(&router, order);
+            match order.unhedged_message() {
+                Some(risk) => {
+                    log::warn!("价差单 {} {}", order.id, risk);
+                    order.status = SpreadOrderStatus::LegRisk;
+                    order.message = Some(risk);
+                }
+                None => {
+                    order.status = SpreadOrderStatus::RolledBack;
+                    order.message = Some("腿订单被撤销，已撤销另一腿".to_string());
+                }
+            }
+            changed.push(order.to_info(self.current_spread(order)));
+        }
+
+        changed
+    }
+
+    /// 取消价差单（已下单的撤销两腿未成交部分）
+    pub fn cancel_order(&self, order_id: &str) -> Result<SpreadOrderInfo, String> {
+        let mut order = self
+            .orders
+            .get_mut(order_id)
+            .ok_or_else(|| format!("价差单不存在: {}", order_id))?;
+
+        match order.status.clone() {
+            SpreadOrderStatus::Pending => {
+                order.status = SpreadOrderStatus::Cancelled;
+            }
+            SpreadOrderStatus::Working => {
+                let router = self
+                    .order_router
+                    .clone()
+                    .ok_or_else(|| "无订单路由器，无法撤销腿订单".to_string())?;
+                self.cancel_working_legs(&router, &mut order);
+                match order.unhedged_message() {
+                    Some(risk) => {
+                        order.status = SpreadOrderStatus::LegRisk;
+                        order.message = Some(risk);
+                    }
+                    None => order.status = SpreadOrderStatus::Cancelled,
+                }
+            }
+            status => return Err(format!("价差单状态不允许取消: {:?}", status)),
+        }
+
+        log::info!("价差单取消成功: {} ({:?})", order_id, order.status);
+        Ok(order.to_info(self.current_spread(&order)))
+    }
+
+    /// 获取统计信息
+    pub fn get_statistics(&self) -> SpreadOrderStatistics {
+        let mut stats = SpreadOrderStatistics {
+            total: self.orders.len(),
+            ..Default::default()
+        };
+        for entry in self.orders.iter() {
+            match entry.status {
+                SpreadOrderStatus::Pending => stats.pending += 1,
+                SpreadOrderStatus::Working => stats.working += 1,
+                SpreadOrderStatus::Filled => stats.filled += 1,
+                SpreadOrderStatus::LegRisk => stats.leg_risk += 1,
+                SpreadOrderStatus::RolledBack => stats.rolled_back += 1,
+                SpreadOrderStatus::Cancelled => stats.cancelled += 1,
+                SpreadOrderStatus::Expired => stats.expired += 1,
+                SpreadOrderStatus::Failed => stats.failed += 1,
+            }
+        }
+        stats
+    }
+}
+
+impl Default for SpreadOrderEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 价差单统计信息
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct SpreadOrderStatistics {
+    pub total: usize,
+    pub pending: usize,
+    pub working: usize,
+    pub filled: usize,
+    pub leg_risk: usize,
+    pub rolled_back: usize,
+    pub cancelled: usize,
+    pub expired: usize,
+    pub failed: usize,
+}
+
+// 全局价差单引擎
+lazy_static::lazy_static! {
+    pub static ref SPREAD_ORDER_ENGINE: parking_lot::RwLock<SpreadOrderEngine> =
+        parking_lot::RwLock::new(SpreadOrderEngine::new());
+}
+
+/// 启动价差单监控线程：订阅行情更新报价并触发价差单，周期检查腿风险
+pub fn start_spread_order_monitor(
+    broadcaster: Arc<MarketDataBroadcaster>,
+    check_interval: Duration,
+) -> std::thread::JoinHandle<()> {
+    let receiver = broadcaster.subscribe(
+        "spread_order_engine".to_string(),
+        Vec::new(),
+        vec![
+            "orderbook".to_string(),
+            "tick".to_string(),
+            "last_price".to_string(),
+        ],
+    );
+
+    std::thread::spawn(move || {
+        log::info!(
+            "Spread order monitor started (leg check interval: {}ms)",
+            check_interval.as_millis()
+        );
+
+        let mut last_check = Instant::now();
+        loop {
+            match receiver.recv_timeout(check_interval) {
+                Ok(event) => {
+                    let engine = SPREAD_ORDER_ENGINE.read();
+                    match event {
+                        MarketDataEvent::OrderBookSnapshot {
+                            instrument_id,
+                            bids,
+                            asks,
+                            ..
+                        } => {
+                            engine.update_quote(
+                                &instrument_id,
+                                bids.first().map(|l| l.price),
+                                asks.first().map(|l| l.price),
+                                None,
+                            );
+                        }
+                        MarketDataEvent::Tick {
+                            instrument_id,
+                            price,
+                            ..
+                        }
+                        | MarketDataEvent::LastPrice {
+                            instrument_id,
+                            price,
+                            ..
+                        } => {
+                            engine.update_quote(&instrument_id, None, None, Some(price));
+                        }
+                        _ => {}
+                    }
+                }
+                Err(crossbeam::channel::RecvTimeoutError::Timeout) => {}
+                Err(crossbeam::channel::RecvTimeoutError::Disconnected) => {
+                    log::warn!("Spread order monitor stopped: market data channel closed");
+                    break;
+                }
+            }
+
+            if last_check.elapsed() >= check_interval {
+                SPREAD_ORDER_ENGINE.read().check_leg_risk();
+                last_check = Instant::now();
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::account_ext::{AccountType, OpenAccountRequest};
+    use crate::exchange::instrument_registry::{InstrumentInfo, InstrumentStatus, InstrumentType};
+    use crate::exchange::{AccountManager, TradeGateway};
+    use crate::matching::engine::ExchangeMatchingEngine;
+
+    struct TestExchange {
+        registry: Arc<InstrumentRegistry>,
+        router: OrderRouter,
+    }
+
+    fn create_test_exchange() -> TestExchange {
+        let account_mgr = Arc::new(AccountManager::new());
+        for user in ["arb_user", "mm_user"] {
+            account_mgr
+                .open_account(OpenAccountRequest {
+                    user_id: user.to_string(),
+                    account_id: Some(user.to_string()),
+                    account_name: user.to_string(),
+                    init_cash: 1000000.0,
+                    account_type: AccountType::Individual,
+                })
+                .unwrap();
+        }
+
+        let matching_engine = Arc::new(ExchangeMatchingEngine::new());
+        let registry = Arc::new(InstrumentRegistry::new());
+        for (instrument_id, price) in [("IX2301", 120.0), ("IX2305", 100.0)] {
+            matching_engine
+                .register_instrument(instrument_id.to_string(), price)
+                .unwrap();
+            registry
+                .register(InstrumentInfo {
+                    instrument_id: instrument_id.to_string(),
+                    instrument_name: instrument_id.to_string(),
+                    instrument_type: InstrumentType::CommodityFuture,
+                    exchange: "SHFE".to_string(),
+                    contract_multiplier: 1,
+                    trading_unit: "手".to_string(),
+                    quote_unit: String::new(),
+                    price_tick: 0.01,
+                    margin_rate: 0.1,
+                    commission_rate: 0.0005,
+                    limit_up_rate: 0.1,
+                    limit_down_rate: 0.1,
+                    status: InstrumentStatus::Active,
+                    list_date: Some("2023-01-01".to_string()),
+                    expire_date: Some("2023-12-31".to_string()),
+                    created_at: "2023-01-01T00:00:00Z".to_string(),
+                    updated_at: "2023-01-01T00:00:00Z".to_string(),
+                })
+                .unwrap();
+        }
+
+        let trade_gateway = Arc::new(TradeGateway::new(account_mgr.clone()));
+        let router = OrderRouter::new(
+            account_mgr,
+            matching_engine,
+            registry.clone(),
+            trade_gateway,
+        );
+        TestExchange { registry, router }
+    }
+
+    fn engine_with(exchange: TestExchange) -> (SpreadOrderEngine, Arc<OrderRouter>) {
+        let router = Arc::new(exchange.router);
+        let mut engine = SpreadOrderEngine::new();
+        engine.set_order_router(router.clone());
+        engine.set_instrument_registry(exchange.registry);
+        (engine, router)
+    }
+
+    /// 买 IX2301 卖 IX2305，价差 <= target 时触发
+    fn spread_request(target_spread: f64) -> CreateSpreadOrderRequest {
+        CreateSpreadOrderRequest {
+            account_id: "arb_user".to_string(),
+            legs: [
+                SpreadLeg {
+                    instrument_id: "IX2301".to_string(),
+                    direction: "BUY".to_string(),
+                    offset: "OPEN".to_string(),
+                    ratio: 1.0,
+                },
+                SpreadLeg {
+                    instrument_id: "IX2305".to_string(),
+                    direction: "SELL".to_string(),
+                    offset: "OPEN".to_string(),
+                    ratio: 2.0,
+                },
+            ],
+            volume: 1.0,
+            target_spread: target_spread,
+            trigger_condition: TriggerCondition::LessOrEqual,
+            leg_timeout_ms: Some(0),
+            valid_until: None,
+        }
+    }
+
+    #[test]
+    fn test_spread_trigger_submits_both_legs() {
+        let (engine, router) = engine_with(create_test_exchange());
+        let order = engine.create_order(spread_request(20.0)).unwrap();
+        assert_eq!(order.status, SpreadOrderStatus::Pending);
+
+        // 价差 = 121 - 100 = 21，未满足
+        assert!(engine
+            .update_quote("IX2301", Some(120.5), Some(121.0), None)
+            .is_empty());
+        assert!(engine
+            .update_quote("IX2305", Some(100.0), Some(100.5), None)
+            .is_empty());
+        assert_eq!(
+            engine
+                .get_order(&order.spread_order_id)
+                .unwrap()
+                .current_spread,
+            Some(21.0)
+        );
+
+        // 价差 = 120 - 100 = 20，触发
+        let triggered = engine.update_quote("IX2301", None, Some(120.0), None);
+        assert_eq!(triggered.len(), 1);
+        let info = &triggered[0];
+        assert_eq!(info.status, SpreadOrderStatus::Working);
+        assert_eq!(info.triggered_spread, Some(20.0));
+
+        // 两腿按比例以可成交价下单
+        let leg1 = &info.legs[0];
+        let leg2 = &info.legs[1];
+        assert_eq!((leg1.volume, leg1.price), (1.0, Some(120.0)));
+        assert_eq!((leg2.volume, leg2.price), (2.0, Some(100.0)));
+        for leg in [leg1, leg2] {
+            let leg_order_id = leg.order_id.as_ref().unwrap();
+            assert_eq!(
+                router.get_order_status(leg_order_id),
+                Some(OrderStatus::Submitted)
+            );
+        }
+
+        // 不会重复触发
+        assert!(engine
+            .update_quote("IX2301", None, Some(119.0), None)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_spread_leg_failure_rolls_back_other_leg() {
+        let exchange = create_test_exchange();
+        // 腿2价格超出涨跌停，下单被拒
+        let mut router = exchange.router;
+        let manager = Arc::new(crate::risk::PriceLimitManager::new(
+            exchange.registry.clone(),
+        ));
+        manager.set_reference_price("IX2305", 80.0).unwrap();
+        router.set_price_limit_manager(manager);
+        let (engine, router) = engine_with(TestExchange {
+            registry: exchange.registry,
+            router,
+        });
+
+        let order = engine.create_order(spread_request(20.0)).unwrap();
+        engine.update_quote("IX2305", Some(100.0), Some(100.5), None);
+        let triggered = engine.update_quote("IX2301", Some(119.5), Some(120.0), None);
+
+        assert_eq!(triggered.len(), 1);
+        let info = engine.get_order(&order.spread_order_id).unwrap();
+        assert_eq!(info.status, SpreadOrderStatus::RolledBack);
+        assert!(info.message.unwrap().contains("IX2305"));
+        assert!(info.legs[1].order_id.is_none());
+
+        // 腿1已撤单
+        let leg1_order_id = info.legs[0].order_id.as_ref().unwrap();
+        assert_eq!(
+            router.get_order_status(leg1_order_id),
+            Some(OrderStatus::Cancelled)
+        );
+        assert_eq!(engine.get_statistics().rolled_back, 1);
+    }
+
+    #[test]
+    fn test_spread_leg_risk_cancels_unfilled_leg() {
+        let (engine, router) = engine_with(create_test_exchange());
+
+        // 做市商在 IX2301 挂卖单，腿1可立即成交；IX2305 无对手盘
+        let mm = router.submit_order(SubmitOrderRequest {
+            account_id: "mm_user".to_string(),
+            instrument_id: "IX2301".to_string(),
+            direction: "SELL".to_string(),
+            offset: "OPEN".to_string(),
+            volume: 1.0,
+            price: 120.0,
+            order_type: "LIMIT".to_string(),
+            ..Default::default()
+        });
+        assert!(mm.success, "{:?}", mm.error_message);
+
+        let order = engine.create_order(spread_request(20.0)).unwrap();
+        engine.update_quote("IX2305", Some(100.0), Some(100.5), None);
+        let triggered = engine.update_quote("IX2301", Some(119.5), Some(120.0), None);
+        assert_eq!(triggered[0].legs[0].filled_volume, 1.0);
+        assert_eq!(triggered[0].legs[1].filled_volume, 0.0);
+
+        // 腿2超时未成交：撤销并标记腿风险
+        let changed = engine.check_leg_risk();
+        assert_eq!(changed.len(), 1);
+        let info = engine.get_order(&order.spread_order_id).unwrap();
+        assert_eq!(info.status, SpreadOrderStatus::LegRisk);
+        assert!(info.message.unwrap().contains("未对冲"));
+        assert_eq!(
+            router.get_order_status(info.legs[1].order_id.as_ref().unwrap()),
+            Some(OrderStatus::Cancelled)
+        );
+    }
+
+    #[test]
+    fn test_spread_rejects_cross_exchange_legs() {
+        let engine = SpreadOrderEngine::new();
+        let mut req = spread_request(20.0);
+        req.legs[0].instrument_id = "SHFE.cu2501".to_string();
+        req.legs[1].instrument_id = "DCE.i2501".to_string();
+        assert!(engine.create_order(req).is_err());
+
+        let mut req = spread_request(20.0);
+        req.legs[0].instrument_id = "SHFE.cu2501".to_string();
+        req.legs[1].instrument_id = "SHFE.cu2505".to_string();
+        let order = engine.create_order(req).unwrap();
+        engine.cancel_order(&order.spread_order_id).unwrap();
+        assert_eq!(
+            engine.get_order(&order.spread_order_id).unwrap().status,
+            SpreadOrderStatus::Cancelled
+        );
+    }
+}
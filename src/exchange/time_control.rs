@@ -0,0 +1,391 @@
+//! 测试环境时间控制（虚拟时间快进）
+//! @yutiansut @quantaxis
+//!
+//! 联调结算与合约到期流程时不必等待真实日切：development 环境下可设置虚拟当前时间或快进 N 小时。
+//! 控制器持有一个 [`TimeService`] 实例（默认取结算引擎的实例），启用后该实例切换为虚拟时间源；
+//! 结算、交易日管理与订单到期（TTL）注入同一实例，交易日、结算日期、订单到期随之从虚拟时间取时。
+//! 启动时该实例同时设为全局时区服务，K线聚合器定时器也按虚拟时间切 bar。
+//!
+//! 快进不是直接跳到目标时间，而是按时间顺序逐个触发区间内本应发生的定时事件：
+//! - **日终结算**: 工作日本地 `settlement_time` 执行 `daily_settlement`
+//! - **交易日切换**: 夜盘开始时刻更新撮合引擎交易日并打开盘标记
+//!
+//! 每个事件触发前先把虚拟时间推进到事件时刻并撤销已到期的 TTL 订单；
+//! K线由聚合器定时器按虚拟时间切 bar（跳过的周期补空 K 线）。
+//! 虚拟时间只能前进，不能回退。非 development 环境一律拒绝（HTTP 接口返回 404）。
+
+use chrono::{Datelike, Duration, NaiveDateTime, NaiveTime, TimeZone, Weekday};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::exchange::settlement::SettlementResult;
+use crate::exchange::trading_day_manager::DEVELOPMENT_ENVIRONMENT;
+use crate::exchange::{OrderRouter, SettlementEngine, TradingDayManager};
+use crate::matching::engine::ExchangeMatchingEngine;
+use crate::utils::time_service::TimeService;
+use crate::ExchangeError;
+
+/// 时间控制配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeControlConfig {
+    /// 快进时日终结算触发时刻（交易所本地时间 HH:MM，仅工作日）
+    #[serde(default = "default_settlement_time")]
+    pub settlement_time: String,
+    /// 单次最多快进小时数
+    #[serde(default = "default_max_advance_hours")]
+    pub max_advance_hours: f64,
+}
+
+fn default_settlement_time() -> String {
+    "15:30".to_string()
+}
+
+fn default_max_advance_hours() -> f64 {
+    24.0 * 31.0
+}
+
+impl Default for TimeControlConfig {
+    fn default() -> Self {
+        Self {
+            settlement_time: default_settlement_time(),
+            max_advance_hours: default_max_advance_hours(),
+        }
+    }
+}
+
+/// 定时事件类型（同一时刻按声明顺序触发）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeEventKind {
+    /// 日终结算
+    Settlement,
+    /// 交易日切换
+    TradingDaySwitch,
+}
+
+/// 快进过程中触发的定时事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FiredTimeEvent {
+    pub kind: TimeEventKind,
+    /// 触发时刻（毫秒）
+    pub timestamp: i64,
+    /// 触发时刻（本地时间 YYYY-MM-DD HH:MM:SS）
+    pub local_time: String,
+    /// 触发时刻所属交易日（YYYYMMDD）
+    pub trading_day: String,
+    pub success: bool,
+    pub message: String,
+}
+
+/// 时间推进结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdvanceTimeReport {
+    /// 推进前时间（毫秒）
+    pub from: i64,
+    /// 推进后时间（毫秒）
+    pub to: i64,
+    pub from_local: String,
+    pub to_local: String,
+    /// 按时间顺序触发的定时事件
+    pub events: Vec<FiredTimeEvent>,
+    /// 区间内的结算结果
+    pub settlements: Vec<SettlementResult>,
+    /// 到期撤销的 TTL 订单数
+    pub expired_orders: usize,
+}
+
+/// 测试环境时间控制器
+pub struct TimeController {
+    environment: String,
+    config: TimeControlConfig,
+    settlement_engine: Arc<SettlementEngine>,
+    order_router: Option<Arc<OrderRouter>>,
+    matching_engine: Option<Arc<ExchangeMatchingEngine>>,
+    trading_day: Option<Arc<TradingDayManager>>,
+    /// 时间源（虚拟时间只推进这一实例）
+    time_service: Arc<TimeService>,
+    /// 串行化时间推进
+    advancing: Mutex<()>,
+}
+
+impl TimeController {
+    pub fn new(environment: impl Into<String>, settlement_engine: Arc<SettlementEngine>) -> Self {
+        Self {
+            environment: environment.into(),
+            config: TimeControlConfig::default(),
+            time_service: settlement_engine.time_service(),
+            settlement_engine,
+            order_router: None,
+            matching_engine: None,
+            trading_day: None,
+            advancing: Mutex::new(()),
+        }
+    }
+
+    pub fn with_config(mut self, config: TimeControlConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// 快进时撤销到期的 TTL 订单
+    pub fn with_order_router(mut self, order_router: Arc<OrderRouter>) -> Self {
+        self.order_router = Some(order_router);
+        self
+    }
+
+    /// 交易日切换时更新撮合引擎交易日
+    pub fn with_matching_engine(mut self, matching_engine: Arc<ExchangeMatchingEngine>) -> Self {
+        self.matching_engine = Some(matching_engine);
+        self
+    }
+
+    /// 注入时间服务（需与结算、交易日管理、订单路由使用同一实例）
+    pub fn with_time_service(mut self, time_service: Arc<TimeService>) -> Self {
+        self.time_service = time_service;
+        self
+    }
+
+    /// 当前时间服务
+    pub fn time_service(&self) -> Arc<TimeService> {
+        self.time_service.clone()
+    }
+
+    /// 交易日切换时打开盘标记
+    pub fn with_trading_day_manager(mut self, trading_day: Arc<TradingDayManager>) -> Self {
+        self.trading_day = Some(trading_day);
+        self
+    }
+
+    /// 当前环境是否允许时间控制
+    pub fn is_allowed(&self) -> bool {
+        self.environment == DEVELOPMENT_ENVIRONMENT
+    }
+
+    fn ensure_allowed(&self) -> Result<(), ExchangeError> {
+        if self.is_allowed() {
+            Ok(())
+        } else {
+            Err(ExchangeError::PermissionDenied(format!(
+                "Time control is only available in {} environment (current: {})",
+                DEVELOPMENT_ENVIRONMENT, self.environment
+            )))
+        }
+    }
+
+    /// 快进指定小时数
+    pub fn advance_hours(&self, hours: f64) -> Result<AdvanceTimeReport, ExchangeError> {
+        self.ensure_allowed()?;
+        if !(hours > 0.0 && hours <= self.config.max_advance_hours) {
+            return Err(ExchangeError::InvalidParameter(format!(
+                "hours must be in (0, {}], got {}",
+                self.config.max_advance_hours, hours
+            )));
+        }
+        let _guard = self.advancing.lock();
+        let from = self.time_service.now_millis();
+        self.advance_to_locked(from, from + (hours * 3_600_000.0) as i64)
+    }
+
+    /// 设置虚拟当前时间（毫秒），只能向后设置，区间内的定时事件依次触发
+    pub fn set_time(&self, target_ms: i64) -> Result<AdvanceTimeReport, ExchangeError> {
+        self.ensure_allowed()?;
+        let _guard = self.advancing.lock();
+        let from = self.time_service.now_millis();
+        if target_ms < from {
+            return Err(ExchangeError::InvalidParameter(format!(
+                "Virtual time cannot move backwards: current {}, target {}",
+                self.time_service.format_millis(from),
+                self.time_service.format_millis(target_ms)
+            )));
+        }
+        let max_ms = (self.config.max_advance_hours * 3_600_000.0) as i64;
+        if self.time_service.virtual_time_millis().is_some() && target_ms - from > max_ms {
+            return Err(ExchangeError::InvalidParameter(format!(
+                "Cannot advance more than {} hours at once",
+                self.config.max_advance_hours
+            )));
+        }
+        // 首次启用虚拟时间：直接从目标时间开始，不补触发此前的事件
+        if self.time_service.virtual_time_millis().is_none() {
+            self.time_service.set_virtual_time_millis(target_ms);
+            return self.advance_to_locked(target_ms, target_ms);
+        }
+        self.advance_to_locked(from, target_ms)
+    }
+
+    /// 按本地时间（YYYY-MM-DD HH:MM:SS）设置虚拟当前时间
+    pub fn set_local_time(&self, local: &str) -> Result<AdvanceTimeReport, ExchangeError> {
+        let naive = NaiveDateTime::parse_from_str(local, "%Y-%m-%d %H:%M:%S").map_err(|e| {
+            ExchangeError::InvalidParameter(format!("Invalid local time {}: {}", local, e))
+        })?;
+        let target_ms = self
+            .time_service
+            .offset()
+            .from_local_datetime(&naive)
+            .single()
+            .ok_or_else(|| {
+                ExchangeError::InvalidParameter(format!("Invalid local time {}", local))
+            })?
+            .timestamp_millis();
+        self.set_time(target_ms)
+    }
+
+    /// 区间 `(from_ms, to_ms]` 内应触发的定时事件（按时间排序）
+    pub fn scheduled_events(&self, from_ms: i64, to_ms: i64) -> Vec<(i64, TimeEventKind)> {
+        let service = &self.time_service;
+        let mut events = Vec::new();
+        if to_ms <= from_ms {
+            return events;
+        }
+
+        let mut switch_at = service.next_trading_day_start(from_ms);
+        while switch_at <= to_ms {
+            events.push((switch_at, TimeEventKind::TradingDaySwitch));
+            switch_at = service.next_trading_day_start(switch_at);
+        }
+
+        match NaiveTime::parse_from_str(&self.config.settlement_time, "%H:%M") {
+            Ok(settlement_time) => {
+                let mut day = service.natural_day_of(from_ms);
+                let last_day = service.natural_day_of(to_ms);
+                while day <= last_day {
+                    let at = service
+                        .offset()
+                        .from_local_datetime(&day.and_time(settlement_time))
+                        .unwrap()
+                        .timestamp_millis();
+                    let workday = !matches!(day.weekday(), Weekday::Sat | Weekday::Sun);
+                    if workday && at > from_ms && at <= to_ms {
+                        events.push((at, TimeEventKind::Settlement));
+                    }
+                    day += Duration::days(1);
+                }
+            }
+            Err(e) => log::warn!(
+                "[TimeControl] Invalid settlement_time {}: {}",
+                self.config.settlement_time,
+                e
+            ),
+        }
+
+        events.sort();
+        events
+    }
+
+    /// 逐个触发区间内的定时事件，最后推进到目标时间（调用方持有 advancing 锁）
+    fn advance_to_locked(&self, from: i64, to: i64) -> Result<AdvanceTimeReport, ExchangeError> {
+        let service = &self.time_service;
+        let mut report = AdvanceTimeReport {
+            from,
+            to,
+            from_local: service.format_millis(from),
+            to_local: service.format_millis(to),
+            events: Vec::new(),
+            settlements: Vec::new(),
+            expired_orders: 0,
+        };
+
+        for (at, kind) in self.scheduled_events(from, to) {
+            service.set_virtual_time_millis(at);
+            report.expired_orders += self.expire_orders();
+
+            let trading_day = service.trading_day_of(at).format("%Y%m%d").to_string();
+            let (success, message) = match kind {
+                TimeEventKind::Settlement => match self.settlement_engine.daily_settlement() {
+                    Ok(result) => {
+                        let message = format!(
+                            "settlement {}: {}/{} accounts settled",
+                            result.settlement_date, result.settled_accounts, result.total_accounts
+                        );
+                        report.settlements.push(result);
+                        (true, message)
+                    }
+                    Err(e) => (false, e.to_string()),
+                },
+                TimeEventKind::TradingDaySwitch => {
+                    if let Some(ref engine) = self.matching_engine {
+                        engine.set_trading_day(trading_day.clone());
+                    }
+                    if let Some(ref manager) = self.trading_day {
+                        manager.ensure_opening_mark();
+                    }
+                    (true, format!("trading day switched to {}", trading_day))
+                }
+            };
+
+            log::info!(
+                "[TimeControl] {:?} at {}: {}",
+                kind,
+                service.format_millis(at),
+                message
+            );
+            report.events.push(FiredTimeEvent {
+                kind,
+                timestamp: at,
+                local_time: service.format_millis(at),
+                trading_day,
+                success,
+                message,
+            });
+        }
+
+        service.set_virtual_time_millis(to);
+        report.expired_orders += self.expire_orders();
+        log::warn!(
+            "[TimeControl] Virtual time advanced {} -> {} ({} events)",
+            report.from_local,
+            report.to_local,
+            report.events.len()
+        );
+        Ok(report)
+    }
+
+    fn expire_orders(&self) -> usize {
+        self.order_router
+            .as_ref()
+            .map_or(0, |router| router.expire_orders())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::AccountManager;
+
+    fn cst_ms(y: i32, m: u32, d: u32, h: u32, mi: u32) -> i64 {
+        chrono::FixedOffset::east_opt(8 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(y, m, d, h, mi, 0)
+            .unwrap()
+            .timestamp_millis()
+    }
+
+    #[test]
+    fn test_scheduled_events_in_order_across_weekend() {
+        let engine = Arc::new(SettlementEngine::new(Arc::new(AccountManager::new())));
+        let controller = TimeController::new("development", engine);
+
+        // 周五 09:00 快进到下周一 16:00：周五结算、周五夜盘切到周一、周一结算、周一夜盘前结束
+        let events =
+            controller.scheduled_events(cst_ms(2026, 10, 16, 9, 0), cst_ms(2026, 10, 19, 16, 0));
+        assert_eq!(
+            events,
+            vec![
+                (cst_ms(2026, 10, 16, 15, 30), TimeEventKind::Settlement),
+                (cst_ms(2026, 10, 16, 18, 0), TimeEventKind::TradingDaySwitch),
+                (cst_ms(2026, 10, 19, 15, 30), TimeEventKind::Settlement),
+            ]
+        );
+
+        // 生产环境不可用
+        let production = TimeController::new(
+            "production",
+            Arc::new(SettlementEngine::new(Arc::new(AccountManager::new()))),
+        );
+        assert!(matches!(
+            production.advance_hours(1.0),
+            Err(ExchangeError::PermissionDenied(_))
+        ));
+    }
+}
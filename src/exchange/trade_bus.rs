@@ -0,0 +1,541 @@
+//! 成交事件总线
+//! @yutiansut @quantaxis
+//!
+//! 撮合封装层（`OrderRouter`）每产生一笔成交即发布 [`TradeEvent`]，不经过账户处理：
+//! - 成交记录（`TradeRecorder`）、行情统计与K线（`MarketDataService`）、逐笔成交广播
+//!   （`MarketDataBroadcaster`）都是总线的消费者，`TradeGateway` 只负责账户更新与用户回报，
+//!   账户更新出错不影响行情
+//! - [`TradeEventBus::subscribe`]：每个消费者独立线程 + 有界队列，发布端只做 `try_send`，
+//!   消费滞后时丢弃该消费者的新事件并计数（`qaexchange_trade_bus_dropped_total`），不阻塞撮合
+//! - [`TradeEventBus::subscribe_inline`]：在发布线程内同步消费，用于确定性回放这类单线程场景
+//! - 消费者 panic 只影响当前事件，记录失败数后继续消费
+
+use crossbeam::channel::{bounded, Receiver, Sender, TrySendError};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::exchange::TradeType;
+use crate::market::{MarketDataBroadcaster, MarketDataEvent, MarketDataService, PriceSource};
+use crate::matching::trade_recorder::TradeRecorder;
+use crate::observability::metrics::{TRADE_BUS_DROPPED_TOTAL, TRADE_BUS_QUEUE_DEPTH};
+use crate::utils::timestamp::{nanos_to_millis, now_nanos};
+use crate::ExchangeError;
+
+fn default_queue_capacity() -> usize {
+    65_536
+}
+
+/// 成交事件总线配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TradeEventBusConfig {
+    /// 每个消费者的队列容量，队列满时丢弃该消费者的新事件
+    #[serde(default = "default_queue_capacity")]
+    pub queue_capacity: usize,
+}
+
+impl Default for TradeEventBusConfig {
+    fn default() -> Self {
+        Self {
+            queue_capacity: default_queue_capacity(),
+        }
+    }
+}
+
+impl TradeEventBusConfig {
+    pub fn validate(&self) -> Result<(), ExchangeError> {
+        if self.queue_capacity == 0 {
+            return Err(ExchangeError::InvalidParameter(
+                "Trade bus queue_capacity must be greater than 0".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// 撮合成交事件（每笔成交发布一次，不区分主动方/被动方回报）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TradeEvent {
+    pub exchange_id: String,
+    pub instrument_id: String,
+    pub price: f64,
+    pub volume: f64,
+    /// 主动方方向（BUY/SELL，大宗交易为空）
+    pub direction: String,
+    pub buy_user_id: String,
+    pub sell_user_id: String,
+    /// 买方订单ID（大宗交易为大宗交易编号）
+    pub buy_order_id: String,
+    /// 卖方订单ID（大宗交易为大宗交易编号）
+    pub sell_order_id: String,
+    /// 主动方订单ID（大宗交易为空）
+    pub taker_order_id: String,
+    /// 交易日（YYYY-MM-DD）
+    pub trading_day: String,
+    pub trade_type: TradeType,
+    /// 撮合批次号（大宗交易为 0）
+    pub match_batch_id: i64,
+    /// 成交时间（纳秒，与成交回报/WAL 一致）
+    pub timestamp: i64,
+}
+
+/// 成交事件消费者
+pub trait TradeEventConsumer: Send + Sync {
+    fn on_trade_event(&self, event: &TradeEvent);
+}
+
+/// 单个消费者的统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TradeConsumerStats {
+    pub name: String,
+    /// 是否在发布线程内同步消费
+    pub inline: bool,
+    /// 已投递事件数
+    pub delivered: u64,
+    /// 已消费事件数
+    pub processed: u64,
+    /// 队列满丢弃数
+    pub dropped: u64,
+    /// 消费失败（panic）数
+    pub failed: u64,
+    /// 当前队列深度
+    pub queue_len: usize,
+}
+
+/// 总线统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TradeEventBusStats {
+    pub published: u64,
+    pub queue_capacity: usize,
+    pub consumers: Vec<TradeConsumerStats>,
+}
+
+enum Delivery {
+    Queue(Sender<TradeEvent>),
+    Inline(Arc<dyn TradeEventConsumer>),
+}
+
+struct Subscription {
+    name: String,
+    delivery: Delivery,
+    delivered: AtomicU64,
+    dropped: AtomicU64,
+    processed: Arc<AtomicU64>,
+    failed: Arc<AtomicU64>,
+}
+
+impl Subscription {
+    fn new(name: &str, delivery: Delivery) -> Self {
+        Self {
+            name: name.to_string(),
+            delivery,
+            delivered: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+            processed: Arc::new(AtomicU64::new(0)),
+            failed: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    fn queue_len(&self) -> usize {
+        match &self.delivery {
+            Delivery::Queue(sender) => sender.len(),
+            Delivery::Inline(_) => 0,
+        }
+    }
+
+    fn record_drop(&self) {
+        let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+        TRADE_BUS_DROPPED_TOTAL
+            .with_label_values(&[self.name.as_str()])
+            .inc();
+        // 持续滞后时每 1000 笔告警一次，避免日志风暴
+        if dropped % 1000 == 1 {
+            log::warn!(
+                "Trade bus consumer '{}' is lagging, dropped {} events so far",
+                self.name,
+                dropped
+            );
+        }
+    }
+}
+
+/// 调用消费者，panic 计为失败
+fn dispatch(name: &str, consumer: &dyn TradeEventConsumer, event: &TradeEvent, failed: &AtomicU64) {
+    if catch_unwind(AssertUnwindSafe(|| consumer.on_trade_event(event))).is_err() {
+        failed.fetch_add(1, Ordering::Relaxed);
+        log::error!(
+            "Trade bus consumer '{}' panicked on trade {} {}@{}",
+            name,
+            event.instrument_id,
+            event.volume,
+            event.price
+        );
+    }
+}
+
+/// 成交事件总线
+pub struct TradeEventBus {
+    config: TradeEventBusConfig,
+    subscriptions: RwLock<Vec<Arc<Subscription>>>,
+    published: AtomicU64,
+}
+
+impl Default for TradeEventBus {
+    fn default() -> Self {
+        Self {
+            config: TradeEventBusConfig::default(),
+            subscriptions: RwLock::new(Vec::new()),
+            published: AtomicU64::new(0),
+        }
+    }
+}
+
+impl TradeEventBus {
+    pub fn new(config: TradeEventBusConfig) -> Result<Self, ExchangeError> {
+        config.validate()?;
+        Ok(Self {
+            config,
+            ..Default::default()
+        })
+    }
+
+    pub fn config(&self) -> &TradeEventBusConfig {
+        &self.config
+    }
+
+    /// 订阅：消费者在独立线程中从有界队列消费，总线释放后线程退出
+    pub fn subscribe(
+        &self,
+        name: &str,
+        consumer: Arc<dyn TradeEventConsumer>,
+    ) -> Result<JoinHandle<()>, ExchangeError> {
+        let (sender, receiver): (Sender<TradeEvent>, Receiver<TradeEvent>) =
+            bounded(self.config.queue_capacity);
+        let subscription = Arc::new(Subscription::new(name, Delivery::Queue(sender)));
+        let processed = subscription.processed.clone();
+        let failed = subscription.failed.clone();
+        let consumer_name = name.to_string();
+
+        let handle = std::thread::Builder::new()
+            .name(format!("trade-bus-{}", name))
+            .spawn(move || {
+                let depth = TRADE_BUS_QUEUE_DEPTH.with_label_values(&[consumer_name.as_str()]);
+                for event in receiver.iter() {
+                    dispatch(&consumer_name, consumer.as_ref(), &event, &failed);
+                    processed.fetch_add(1, Ordering::Release);
+                    depth.set(receiver.len() as i64);
+                }
+            })
+            .map_err(|e| {
+                ExchangeError::InternalError(format!(
+                    "Failed to spawn trade bus consumer '{}': {}",
+                    name, e
+                ))
+            })?;
+
+        self.subscriptions.write().push(subscription);
+        log::info!(
+            "Trade bus consumer '{}' subscribed (queue capacity {})",
+            name,
+            self.config.queue_capacity
+        );
+        Ok(handle)
+    }
+
+    /// 同步订阅：在发布线程内消费（确定性回放等单线程场景）
+    pub fn subscribe_inline(&self, name: &str, consumer: Arc<dyn TradeEventConsumer>) {
+        self.subscriptions.write().push(Arc::new(Subscription::new(
+            name,
+            Delivery::Inline(consumer),
+        )));
+    }
+
+    /// 发布成交事件，不阻塞（队列满的消费者丢弃该事件）
+    pub fn publish(&self, event: TradeEvent) {
+        self.published.fetch_add(1, Ordering::Relaxed);
+        let subscriptions = self.subscriptions.read();
+        for subscription in subscriptions.iter() {
+            match &subscription.delivery {
+                Delivery::Inline(consumer) => {
+                    subscription.delivered.fetch_add(1, Ordering::Relaxed);
+                    dispatch(
+                        &subscription.name,
+                        consumer.as_ref(),
+                        &event,
+                        &subscription.failed,
+                    );
+                    subscription.processed.fetch_add(1, Ordering::Release);
+                }
+                Delivery::Queue(sender) => match sender.try_send(event.clone()) {
+                    Ok(()) => {
+                        subscription.delivered.fetch_add(1, Ordering::Relaxed);
+                        TRADE_BUS_QUEUE_DEPTH
+                            .with_label_values(&[subscription.name.as_str()])
+                            .set(sender.len() as i64);
+                    }
+                    Err(TrySendError::Full(_)) | Err(TrySendError::Disconnected(_)) => {
+                        subscription.record_drop();
+                    }
+                },
+            }
+        }
+    }
+
+    /// 等待所有消费者处理完已投递的事件，超时返回 false
+    pub fn wait_idle(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let idle = self.subscriptions.read().iter().all(|s| {
+                s.processed.load(Ordering::Acquire) >= s.delivered.load(Ordering::Relaxed)
+            });
+            if idle {
+                return true;
+            }
+            if Instant::now() >= deadline {
+                return false;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// 各消费者队列中积压最多的事件数
+    pub fn max_queue_len(&self) -> usize {
+        self.subscriptions
+            .read()
+            .iter()
+            .map(|s| s.queue_len())
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn stats(&self) -> TradeEventBusStats {
+        TradeEventBusStats {
+            published: self.published.load(Ordering::Relaxed),
+            queue_capacity: self.config.queue_capacity,
+            consumers: self
+                .subscriptions
+                .read()
+                .iter()
+                .map(|s| TradeConsumerStats {
+                    name: s.name.clone(),
+                    inline: matches!(s.delivery, Delivery::Inline(_)),
+                    delivered: s.delivered.load(Ordering::Relaxed),
+                    processed: s.processed.load(Ordering::Acquire),
+                    dropped: s.dropped.load(Ordering::Relaxed),
+                    failed: s.failed.load(Ordering::Relaxed),
+                    queue_len: s.queue_len(),
+                })
+                .collect(),
+        }
+    }
+}
+
+// ==================== 消费者实现 ====================
+
+/// 成交记录：撮合成交归入撮合批次，大宗交易单独标记
+impl TradeEventConsumer for TradeRecorder {
+    fn on_trade_event(&self, event: &TradeEvent) {
+        match event.trade_type {
+            TradeType::Block => {
+                self.record_block_trade(
+                    event.instrument_id.clone(),
+                    event.buy_user_id.clone(),
+                    event.sell_user_id.clone(),
+                    event.buy_order_id.clone(),
+                    event.price,
+                    event.volume,
+                    event.trading_day.clone(),
+                    event.timestamp,
+                );
+            }
+            TradeType::Normal => {
+                self.record_matched_trade(
+                    event.instrument_id.clone(),
+                    event.buy_user_id.clone(),
+                    event.sell_user_id.clone(),
+                    event.buy_order_id.clone(),
+                    event.sell_order_id.clone(),
+                    event.taker_order_id.clone(),
+                    event.price,
+                    event.volume,
+                    event.trading_day.clone(),
+                    event.match_batch_id,
+                    event.timestamp,
+                );
+            }
+        }
+    }
+}
+
+/// 行情统计与K线（大宗交易不计入行情，可疑成交价按异常值策略过滤）
+impl TradeEventConsumer for MarketDataService {
+    fn on_trade_event(&self, event: &TradeEvent) {
+        if event.trade_type == TradeType::Block {
+            return;
+        }
+        if !self
+            .anomaly_detector()
+            .inspect(&event.instrument_id, PriceSource::Trade, event.price)
+        {
+            return;
+        }
+        let volume = event.volume as i64;
+        let turnover = self.trade_turnover(&event.instrument_id, event.price, event.volume);
+        self.update_trade_stats(&event.instrument_id, volume, turnover);
+        self.on_trade(&event.instrument_id, event.price, volume, event.timestamp);
+    }
+}
+
+/// 逐笔成交与最新价广播（大宗交易、可疑成交价不广播）
+impl TradeEventConsumer for MarketDataBroadcaster {
+    fn on_trade_event(&self, event: &TradeEvent) {
+        if event.trade_type == TradeType::Block
+            || !self.should_publish_price(&event.instrument_id, event.price)
+        {
+            return;
+        }
+        self.broadcast(MarketDataEvent::Tick {
+            instrument_id: event.instrument_id.clone(),
+            price: event.price,
+            volume: event.volume,
+            direction: event.direction.to_lowercase(),
+            timestamp: nanos_to_millis(event.timestamp),
+            origin_ts: now_nanos(),
+            match_batch_id: event.match_batch_id,
+        });
+        self.broadcast(MarketDataEvent::LastPrice {
+            instrument_id: event.instrument_id.clone(),
+            price: event.price,
+            timestamp: nanos_to_millis(event.timestamp),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parking_lot::Mutex;
+
+    fn event(price: f64) -> TradeEvent {
+        TradeEvent {
+            exchange_id: "SHFE".to_string(),
+            instrument_id: "cu2501".to_string(),
+            price,
+            volume: 1.0,
+            direction: "BUY".to_string(),
+            buy_user_id: "buyer".to_string(),
+            sell_user_id: "seller".to_string(),
+            buy_order_id: "B1".to_string(),
+            sell_order_id: "S1".to_string(),
+            taker_order_id: "B1".to_string(),
+            trading_day: "2025-01-02".to_string(),
+            trade_type: TradeType::Normal,
+            match_batch_id: 1,
+            timestamp: 1_735_779_600_123_456_789,
+        }
+    }
+
+    /// 记录收到的价格；gate 关闭时阻塞，模拟消费滞后
+    #[derive(Default)]
+    struct Collector {
+        prices: Mutex<Vec<f64>>,
+        gate: Mutex<()>,
+    }
+
+    impl TradeEventConsumer for Collector {
+        fn on_trade_event(&self, event: &TradeEvent) {
+            let _gate = self.gate.lock();
+            if event.price < 0.0 {
+                panic!("bad price");
+            }
+            self.prices.lock().push(event.price);
+        }
+    }
+
+    #[test]
+    fn test_fan_out_and_consumer_isolation() {
+        let bus = TradeEventBus::default();
+        let queued = Arc::new(Collector::default());
+        let inline = Arc::new(Collector::default());
+        bus.subscribe("queued", queued.clone()).unwrap();
+        bus.subscribe_inline("inline", inline.clone());
+
+        bus.publish(event(100.0));
+        bus.publish(event(-1.0)); // 消费者 panic 不影响后续事件
+        bus.publish(event(101.0));
+        assert!(bus.wait_idle(Duration::from_secs(5)));
+
+        assert_eq!(*queued.prices.lock(), vec![100.0, 101.0]);
+        assert_eq!(*inline.prices.lock(), vec![100.0, 101.0]);
+        let stats = bus.stats();
+        assert_eq!(stats.published, 3);
+        assert!(stats
+            .consumers
+            .iter()
+            .all(|c| c.failed == 1 && c.processed == 3));
+    }
+
+    #[test]
+    fn test_lagging_consumer_does_not_block_publisher() {
+        let bus = TradeEventBus::new(TradeEventBusConfig { queue_capacity: 2 }).unwrap();
+        let slow = Arc::new(Collector::default());
+        bus.subscribe("slow", slow.clone()).unwrap();
+
+        // 消费者卡住时持续发布：队列满后丢弃，发布端立即返回
+        let gate = slow.gate.lock();
+        let started = Instant::now();
+        for i in 0..10 {
+            bus.publish(event(100.0 + i as f64));
+        }
+        assert!(started.elapsed() < Duration::from_secs(1));
+        let stats = bus.stats();
+        assert_eq!(stats.published, 10);
+        assert!(stats.consumers[0].dropped >= 7);
+        drop(gate);
+
+        assert!(bus.wait_idle(Duration::from_secs(5)));
+        let stats = bus.stats();
+        let consumer = &stats.consumers[0];
+        assert_eq!(consumer.delivered + consumer.dropped, 10);
+        assert_eq!(slow.prices.lock().len() as u64, consumer.delivered);
+
+        assert!(TradeEventBus::new(TradeEventBusConfig { queue_capacity: 0 }).is_err());
+    }
+
+    /// 注入零价、负价、离谱价格的成交：广播器只推送正常成交
+    #[test]
+    fn test_anomalous_trade_prices_are_not_broadcast() {
+        use crate::market::PriceAnomalyDetector;
+
+        let detector = Arc::new(PriceAnomalyDetector::default());
+        detector.set_reference_price("cu2501", 70000.0);
+        let broadcaster = Arc::new(MarketDataBroadcaster::new());
+        broadcaster.set_anomaly_detector(detector.clone());
+        let rx = broadcaster.subscribe(
+            "s1".to_string(),
+            vec!["cu2501".to_string()],
+            vec!["tick".to_string()],
+        );
+
+        let bus = TradeEventBus::default();
+        bus.subscribe_inline("broadcaster", broadcaster);
+        for price in [0.0, -70000.0, 9_999_999.0, 70100.0] {
+            bus.publish(event(price));
+        }
+
+        let prices: Vec<f64> = rx
+            .try_iter()
+            .filter_map(|e| match e {
+                MarketDataEvent::Tick { price, .. } => Some(price),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(prices, vec![70100.0]);
+        // 广播器只过滤不记录告警
+        assert_eq!(detector.stats().suspicious, 0);
+    }
+}
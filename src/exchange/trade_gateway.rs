@@ -3,8 +3,13 @@
 //! 负责处理撮合引擎的成交结果，更新账户，并推送成交回报到客户端
 
 use crate::core::{Order, QA_Account, Trade};
+use crate::exchange::block_trade::BlockTrade;
+use crate::exchange::deterministic::{Clock, ExchangeClock};
+use crate::exchange::exchange_types::direction_code;
+use crate::exchange::fixed_point::{FixedFill, FloatBalances};
 use crate::exchange::{
-    AccountManager, ExchangeIdGenerator, ExchangeOrderRecord, ExchangeTradeRecord,
+    AccountManager, ExchangeIdGenerator, ExchangeOrderRecord, ExchangeTradeRecord, FillAverage,
+    HedgeFlag, PnlFill, PositionCost, StandardTradeFields, TradeType,
 };
 use crate::matching::{Failed, Success};
 use crate::notification::broker::NotificationBroker;
@@ -13,12 +18,13 @@ use crate::notification::message::{
     OrderAcceptedNotify, OrderCanceledNotify, OrderFilledNotify, OrderPartiallyFilledNotify,
     OrderRejectedNotify, TradeExecutedNotify,
 };
+use crate::observability::push_latency::{PushKind, PUSH_LATENCY};
 use crate::protocol::diff::snapshot::SnapshotManager;
 use crate::protocol::diff::types::{DiffAccount, DiffTrade};
+use crate::risk::RejectReason;
 use crate::storage::wal::manager::WalManager;
 use crate::storage::wal::record::{WalEntry, WalRecord};
 use crate::ExchangeError;
-use chrono::Utc;
 use crossbeam::channel::{unbounded, Receiver, Sender};
 use dashmap::DashMap;
 use parking_lot::RwLock;
@@ -38,6 +44,9 @@ pub struct TradeNotification {
     pub volume: f64,
     pub timestamp: i64,
     pub commission: f64,
+    /// 交易所成交回报标准字段
+    #[serde(default)]
+    pub standard: StandardTradeFields,
 }
 
 /// 账户更新通知
@@ -76,6 +85,14 @@ pub struct OrderStatusNotification {
 
     /// 附加原因（用于拒绝/撤单失败等场景）
     pub reason: Option<String>,
+
+    /// 拒单原因分类（RejectReason::as_str，仅 REJECTED 回报）
+    #[serde(default)]
+    pub reject_category: Option<String>,
+
+    /// 拒单错误码（仅 REJECTED 回报）
+    #[serde(default)]
+    pub error_code: Option<u32>,
 }
 
 /// 通知类型
@@ -86,6 +103,17 @@ pub enum Notification {
     OrderStatus(OrderStatusNotification),
 }
 
+impl Notification {
+    /// 推送延迟测量的消息类别与产生时刻（通知 timestamp 即产生时刻，纳秒）
+    pub fn push_origin(&self) -> (PushKind, i64) {
+        match self {
+            Notification::Trade(t) => (PushKind::Trade, t.timestamp),
+            Notification::AccountUpdate(a) => (PushKind::Account, a.timestamp),
+            Notification::OrderStatus(o) => (PushKind::OrderStatus, o.timestamp),
+        }
+    }
+}
+
 /// 成交回报网关
 pub struct TradeGateway {
     /// 账户管理器
@@ -97,8 +125,9 @@ pub struct TradeGateway {
     /// 成交通知接收器
     trade_receiver: Receiver<Notification>,
 
-    /// 订阅者映射 (user_id -> Vec<Sender<Notification>>)
-    subscribers: DashMap<String, Arc<RwLock<Vec<Sender<Notification>>>>>,
+    /// 订阅者映射 (user_id -> Vec<(session_id, Sender<Notification>)>)
+    /// 同一用户多终端登录时每个会话一个独立队列
+    subscribers: DashMap<String, Arc<RwLock<Vec<(String, Sender<Notification>)>>>>,
 
     /// 全局订阅者 (接收所有通知) - crossbeam channel
     global_subscribers: Arc<RwLock<Vec<Sender<Notification>>>>,
@@ -126,14 +155,14 @@ pub struct TradeGateway {
     /// Phase 5: 存储分离 - 账户回报数据
     account_wal_managers: DashMap<String, Arc<WalManager>>,
 
-    /// 成交记录器（可选，用于查询历史成交）
-    trade_recorder: Option<Arc<crate::matching::trade_recorder::TradeRecorder>>,
-
     /// WAL 根目录
     wal_root: String,
 
-    /// 市场数据服务（用于更新快照统计）
-    market_data_service: Option<Arc<crate::market::MarketDataService>>,
+    /// 订单累计成交 (qa_order_id -> 成交量/成交额)，用于计算订单成交均价
+    order_fills: DashMap<String, FillAverage>,
+
+    /// 回报时间来源（确定性模式下为虚拟时钟）
+    clock: ExchangeClock,
 }
 
 impl TradeGateway {
@@ -154,28 +183,18 @@ impl TradeGateway {
             instrument_wal_managers: DashMap::new(),
             account_wal_managers: DashMap::new(),
             wal_root: "./data/wal".to_string(), // 默认 WAL 根目录
-            trade_recorder: None,
-            market_data_service: None,
+            order_fills: DashMap::new(),
+            clock: ExchangeClock::System,
         }
     }
 
-    /// 设置成交记录器
-    pub fn set_trade_recorder(
-        mut self,
-        trade_recorder: Arc<crate::matching::trade_recorder::TradeRecorder>,
-    ) -> Self {
-        self.trade_recorder = Some(trade_recorder);
+    /// 设置时钟（确定性模式），ID生成器随之改用该时钟
+    pub fn with_clock(mut self, clock: ExchangeClock) -> Self {
+        self.id_generator = Arc::new(ExchangeIdGenerator::with_clock(clock.clone()));
+        self.clock = clock;
         self
     }
 
-    /// 设置市场数据服务（用于更新快照统计）
-    pub fn set_market_data_service(
-        &mut self,
-        market_data_service: Arc<crate::market::MarketDataService>,
-    ) {
-        self.market_data_service = Some(market_data_service);
-    }
-
     /// 设置 WAL 根目录 (Phase 5)
     pub fn with_wal_root(mut self, wal_root: impl Into<String>) -> Self {
         self.wal_root = wal_root.into();
@@ -203,6 +222,11 @@ impl TradeGateway {
         self.snapshot_mgr.as_ref()
     }
 
+    /// 获取交易所ID生成器（结算换日时设置交易日）
+    pub fn id_generator(&self) -> &Arc<ExchangeIdGenerator> {
+        &self.id_generator
+    }
+
     /// 处理撮合结果 (已废弃 - OrderRouter 直接调用 handle_filled/handle_partially_filled)
     ///
     /// ⚠️ 此方法已废弃，因为缺少交易所回报必需的字段（exchange_id, exchange_order_id, price_type）
@@ -275,10 +299,12 @@ impl TradeGateway {
             volume: volume_left, // 剩余未成交量
             price,
             status: order_status.clone(), // 实际状态：ALIVE 或 FINISHED
-            timestamp: Utc::now().timestamp_nanos_opt().unwrap_or(0),
+            timestamp: self.clock.now_nanos(),
             order_id: order_id.to_string(),
             user_id: user_id.to_string(),
             reason: None,
+            reject_category: None,
+            error_code: None,
         };
         self.send_notification(Notification::OrderStatus(order_status_notification.clone()))?;
 
@@ -388,10 +414,12 @@ impl TradeGateway {
             volume: volume_left, // 剩余未成交量
             price,
             status: order_status.clone(), // 实际状态：ALIVE 或 FINISHED
-            timestamp: Utc::now().timestamp_nanos_opt().unwrap_or(0),
+            timestamp: self.clock.now_nanos(),
             order_id: order_id.to_string(),
             user_id: user_id.to_string(),
             reason: None,
+            reject_category: None,
+            error_code: None,
         };
         self.send_notification(Notification::OrderStatus(order_status_notification.clone()))?;
 
@@ -499,11 +527,13 @@ impl TradeGateway {
             volume, // 委托量
             price,  // 委托价格
             status: "ACCEPTED".to_string(),
-            timestamp: Utc::now().timestamp_nanos_opt().unwrap_or(0),
+            timestamp: self.clock.now_nanos(),
             // 内部映射字段
             order_id: order_id.to_string(),
             user_id: user_id.to_string(),
             reason: None,
+            reject_category: None,
+            error_code: None,
         };
 
         self.send_notification(Notification::OrderStatus(order_status))?;
@@ -537,11 +567,13 @@ impl TradeGateway {
             volume, // 撤单时的剩余量
             price,  // 委托价格
             status: "CANCELLED".to_string(),
-            timestamp: Utc::now().timestamp_nanos_opt().unwrap_or(0),
+            timestamp: self.clock.now_nanos(),
             // 内部映射字段
             order_id: order_id.to_string(),
             user_id: user_id.to_string(),
             reason: None,
+            reject_category: None,
+            error_code: None,
         };
 
         self.send_notification(Notification::OrderStatus(order_status))?;
@@ -578,7 +610,7 @@ impl TradeGateway {
     ) -> Result<i64, ExchangeError> {
         // 生成交易所订单号（统一事件序列）
         let exchange_order_id = self.id_generator.next_sequence(instrument_id);
-        let timestamp = Utc::now().timestamp_nanos_opt().unwrap_or(0);
+        let timestamp = self.clock.now_nanos();
 
         // Phase 5: 存储 ExchangeOrderRecord 到 {instrument_id}/orders/
         let order_record = WalRecord::ExchangeOrderRecord {
@@ -651,6 +683,8 @@ impl TradeGateway {
             order_id: order_id.to_string(),
             user_id: user_id.to_string(),
             reason: None,
+            reject_category: None,
+            error_code: None,
         };
 
         self.emit_order_status(order_status)?;
@@ -727,7 +761,7 @@ impl TradeGateway {
     ) -> Result<i64, ExchangeError> {
         // 生成交易所订单号（统一事件序列）
         let exchange_order_id = self.id_generator.next_sequence(instrument_id);
-        let timestamp = Utc::now().timestamp_nanos_opt().unwrap_or(0);
+        let timestamp = self.clock.now_nanos();
 
         let order_status = OrderStatusNotification {
             exchange_id: exchange.to_string(),
@@ -743,6 +777,8 @@ impl TradeGateway {
             order_id: order_id.to_string(),
             user_id: user_id.to_string(),
             reason: Some(reason.to_string()),
+            reject_category: Some(RejectReason::MatchingRejected.as_str().to_string()),
+            error_code: Some(RejectReason::MatchingRejected.error_code()),
         };
 
         self.emit_order_status(order_status)?;
@@ -797,7 +833,8 @@ impl TradeGateway {
     /// 交易所成交，推送Trade回报给账户（不判断FILLED/PARTIAL_FILLED）
     /// 账户端收到TRADE后自己计算 volume_left 判断状态
     /// @yutiansut @quantaxis
-    /// is_taker: 是否为主动方（taker），只有 taker 才记录到 TradeRecorder（避免重复记录）
+    /// 成交记录与行情由撮合层经成交事件总线（`TradeEventBus`）发布，这里只处理账户与回报；
+    /// timestamp 由撮合层取交易所统一时钟，与成交事件一致
     pub fn handle_trade_new(
         &self,
         exchange: &str, // 交易所代码
@@ -810,14 +847,29 @@ impl TradeGateway {
         volume: f64,
         price: f64,
         opposite_order_id: Option<i64>, // 对手方订单号（如果可用）
-        opposite_user_id: Option<&str>, // ✨ 对手方user_id，用于成交记录正确区分买卖方 @yutiansut @quantaxis
         qa_order_id: &str, // ✨ qars内部订单ID，用于调用receive_deal_sim @yutiansut @quantaxis
-        opposite_order_id_str: Option<&str>, // ✨ 对手方订单ID字符串，用于成交记录 @yutiansut @quantaxis
-        is_taker: bool, // ✨ 是否为主动方，只有 taker 记录到 TradeRecorder @yutiansut @quantaxis
+        hedge_flag: HedgeFlag, // 投机套保标志（从订单透传）
+        match_batch_id: i64, // 撮合批次号（同一次撮合产生的成交相同）
+        timestamp: i64,    // 成交时间（纳秒）
     ) -> Result<i64, ExchangeError> {
         // 生成成交ID（统一事件序列）
         let trade_id = self.id_generator.next_sequence(instrument_id);
-        let timestamp = Utc::now().timestamp_nanos_opt().unwrap_or(0);
+
+        // 成交回报标准字段
+        let (trade_date, trade_time) = ExchangeIdGenerator::format_exchange_time(timestamp);
+        let standard = StandardTradeFields {
+            exchange_id: exchange.to_string(),
+            exchange_trade_id: self.id_generator.next_trade_sys_id(),
+            order_sys_id: ExchangeIdGenerator::order_sys_id(instrument_id, exchange_order_id),
+            trading_day: self.id_generator.trading_day(),
+            trade_date,
+            trade_time,
+            sequence_no: trade_id,
+            direction_flag: direction_code(direction),
+            hedge_flag,
+            trade_type: TradeType::Normal,
+            match_batch_id,
+        };
 
         // Phase 5: 存储 ExchangeTradeRecord 到 {instrument_id}/trades/
         // 根据 direction 确定买卖方订单号
@@ -836,6 +888,7 @@ impl TradeGateway {
             deal_volume: volume,
             time: timestamp,
             trade_id,
+            match_batch_id,
         };
 
         // 获取或创建 instrument WAL manager
@@ -870,71 +923,6 @@ impl TradeGateway {
             ))
         })?;
 
-        // 记录成交到 TradeRecorder（用于查询）
-        // ✨ 修复：只有 taker 才记录成交，避免重复记录 @yutiansut @quantaxis
-        // taker 是主动方（新下单的一方），maker 是被动方（挂在订单簿上的一方）
-        if is_taker {
-            if let Some(recorder) = &self.trade_recorder {
-                let trading_day = chrono::Utc::now().format("%Y-%m-%d").to_string();
-
-                // 根据 direction 确定买卖方的 user_id
-                let (buy_user_id, sell_user_id) = match direction {
-                    "BUY" => {
-                        // 当前方（taker）是买方，对手方（maker）是卖方
-                        let sell_id = opposite_user_id.unwrap_or(user_id).to_string();
-                        (user_id.to_string(), sell_id)
-                    }
-                    "SELL" => {
-                        // 当前方（taker）是卖方，对手方（maker）是买方
-                        let buy_id = opposite_user_id.unwrap_or(user_id).to_string();
-                        (buy_id, user_id.to_string())
-                    }
-                    _ => (user_id.to_string(), user_id.to_string()), // fallback
-                };
-
-                // ✨ 根据 direction 确定 buy_order_id 和 sell_order_id @yutiansut @quantaxis
-                let opposite_id = opposite_order_id_str
-                    .map(|s| s.to_string())
-                    .unwrap_or_else(|| format!("opposite_{}", opposite_order_id.unwrap_or(0)));
-
-                let (buy_order_id, sell_order_id) = match direction {
-                    "BUY" => (order_id.to_string(), opposite_id),
-                    "SELL" => (opposite_id, order_id.to_string()),
-                    _ => (order_id.to_string(), "unknown".to_string()),
-                };
-
-                // taker_order_id 就是当前订单ID（主动方）
-                let taker_order_id = order_id.to_string();
-
-                recorder.record_trade(
-                    instrument_id.to_string(),
-                    buy_user_id,    // ✨ 正确的买方user_id
-                    sell_user_id,   // ✨ 正确的卖方user_id
-                    buy_order_id,   // ✨ 正确的买方order_id
-                    sell_order_id,  // ✨ 正确的卖方order_id
-                    taker_order_id, // ✨ 主动方订单ID
-                    price,
-                    volume,
-                    trading_day,
-                );
-            }
-        } else {
-            log::debug!("🔍 Skipping trade record for maker (is_taker=false): order_id={}", order_id);
-        }
-
-        // 更新快照生成器的成交统计
-        if let Some(mds) = &self.market_data_service {
-            let turnover = price * volume;
-            mds.update_trade_stats(instrument_id, volume as i64, turnover);
-            mds.on_trade(instrument_id, price, volume as i64);
-            log::trace!(
-                "Updated snapshot stats: {} volume={}, turnover={:.2}",
-                instrument_id,
-                volume,
-                turnover
-            );
-        }
-
         // ✨ 关键修复：调用receive_deal_sim更新账户持仓和资金 @yutiansut @quantaxis
         log::debug!("🔧 Updating account for trade: user={}, instrument={}, {} {}, price={}, volume={}, qa_order_id={}",
             user_id, instrument_id, direction, offset, price, volume, qa_order_id);
@@ -981,6 +969,16 @@ impl TradeGateway {
             0.0
         };
 
+        // 订单成交均价：按各笔成交量加权，订单全部成交后清理
+        let avg_price = self
+            .order_fills
+            .entry(qa_order_id.to_string())
+            .or_default()
+            .add(price, volume);
+        if status_u8 == 1 {
+            self.order_fills.remove(qa_order_id);
+        }
+
         if let Err(e) = self.write_order_status_update(
             qa_order_id, // 使用 qars 内部订单ID
             user_id,
@@ -994,13 +992,13 @@ impl TradeGateway {
             direction_u8,
             offset_u8,
             price,
-            price, // avg_price (简化处理，用本次成交价)
+            avg_price,
             if status_u8 == 1 { "全部成交" } else { "部分成交" },
         ) {
             log::error!("Failed to write OrderStatusUpdate WAL after trade: {}", e);
         }
 
-        let trade_notification = self.create_trade_notification(
+        let mut trade_notification = self.create_trade_notification(
             order_id,
             user_id,
             instrument_id,
@@ -1009,13 +1007,17 @@ impl TradeGateway {
             price,
             volume,
         );
+        trade_notification.timestamp = timestamp;
+        trade_notification.standard = standard;
+        let exchange_trade_id = trade_notification.standard.exchange_trade_id.clone();
 
         self.emit_trade_notification(trade_notification)?;
         self.push_account_update(user_id)?;
 
         log::info!(
-            "Trade executed: trade_id={}, exchange_order_id={}, instrument={}, volume={}, price={}",
+            "Trade executed: trade_id={}, exchange_trade_id={}, exchange_order_id={}, instrument={}, volume={}, price={}",
             trade_id,
+            exchange_trade_id,
             exchange_order_id,
             instrument_id,
             volume,
@@ -1025,6 +1027,139 @@ impl TradeGateway {
         Ok(trade_id)
     }
 
+    /// 处理大宗交易成交（双方确认后直接成交，不经撮合）
+    ///
+    /// 调用前两腿已通过 send_order 冻结资金（buy_qa_order_id / sell_qa_order_id）。
+    /// 与连续撮合成交的区别：
+    /// - 不进入订单簿，不更新行情快照的最新价和成交统计
+    /// - 成交回报标记 `TradeType::Block`，报单编号为大宗交易编号
+    ///
+    /// 成交记录由撮合层在成交后发布大宗成交事件写入。
+    ///
+    /// 返回 (买方, 卖方) 的交易所成交编号
+    pub fn handle_block_trade(
+        &self,
+        trade: &BlockTrade,
+        buy_qa_order_id: &str,
+        sell_qa_order_id: &str,
+        timestamp: i64,
+    ) -> Result<(String, String), ExchangeError> {
+        let instrument_id = trade.instrument_id.as_str();
+        let trade_id = self.id_generator.next_sequence(instrument_id);
+
+        // 交易所成交记录（大宗成交无订单簿报单号）
+        let wal_mgr = self.get_or_create_instrument_wal(instrument_id)?;
+        wal_mgr
+            .append(WalRecord::ExchangeTradeRecord {
+                exchange: WalRecord::to_fixed_array_16(&trade.exchange_id),
+                instrument: WalRecord::to_fixed_array_16(instrument_id),
+                buy_exchange_order_id: 0,
+                sell_exchange_order_id: 0,
+                deal_price: trade.price,
+                deal_volume: trade.volume,
+                time: timestamp,
+                trade_id,
+                match_batch_id: 0,
+            })
+            .map_err(|e| {
+                ExchangeError::StorageError(format!(
+                    "Failed to append block ExchangeTradeRecord: {}",
+                    e
+                ))
+            })?;
+
+        let (trade_date, trade_time) = ExchangeIdGenerator::format_exchange_time(timestamp);
+        let mut exchange_trade_ids = [String::new(), String::new()];
+        for (i, (account_id, direction, offset, qa_order_id)) in [
+            (
+                trade.buy_account_id.as_str(),
+                "BUY",
+                trade.buy_offset.as_str(),
+                buy_qa_order_id,
+            ),
+            (
+                trade.sell_account_id.as_str(),
+                "SELL",
+                trade.sell_offset.as_str(),
+                sell_qa_order_id,
+            ),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let account_wal_mgr = self.get_or_create_account_wal(account_id)?;
+            account_wal_mgr
+                .append(WalRecord::ExchangeResponseRecord {
+                    response_type: 2, // 2=Trade
+                    exchange_order_id: 0,
+                    instrument: WalRecord::to_fixed_array_16(instrument_id),
+                    user_id: WalRecord::to_fixed_array_32(account_id),
+                    timestamp,
+                    trade_id,
+                    volume: trade.volume,
+                    price: trade.price,
+                    reason: [0u8; 128],
+                })
+                .map_err(|e| {
+                    ExchangeError::StorageError(format!(
+                        "Failed to append ExchangeResponseRecord (BlockTrade): {}",
+                        e
+                    ))
+                })?;
+
+            self.update_account(
+                account_id,
+                instrument_id,
+                direction,
+                offset,
+                trade.price,
+                trade.volume,
+                qa_order_id,
+            )?;
+
+            let mut trade_notification = self.create_trade_notification(
+                &trade.block_trade_id,
+                account_id,
+                instrument_id,
+                direction,
+                offset,
+                trade.price,
+                trade.volume,
+            );
+            trade_notification.timestamp = timestamp;
+            trade_notification.standard = StandardTradeFields {
+                exchange_id: trade.exchange_id.clone(),
+                exchange_trade_id: self.id_generator.next_trade_sys_id(),
+                order_sys_id: trade.block_trade_id.clone(),
+                trading_day: self.id_generator.trading_day(),
+                trade_date: trade_date.clone(),
+                trade_time: trade_time.clone(),
+                sequence_no: trade_id,
+                direction_flag: direction_code(direction),
+                hedge_flag: HedgeFlag::default(),
+                trade_type: TradeType::Block,
+                match_batch_id: 0,
+            };
+            exchange_trade_ids[i] = trade_notification.standard.exchange_trade_id.clone();
+
+            self.emit_trade_notification(trade_notification)?;
+            self.push_account_update(account_id)?;
+        }
+
+        log::info!(
+            "Block trade executed: block_trade_id={}, instrument={}, buy={}, sell={}, volume={}, price={}",
+            trade.block_trade_id,
+            instrument_id,
+            trade.buy_account_id,
+            trade.sell_account_id,
+            trade.volume,
+            trade.price
+        );
+
+        let [buy_trade_id, sell_trade_id] = exchange_trade_ids;
+        Ok((buy_trade_id, sell_trade_id))
+    }
+
     /// 处理撤单成功回报 (Phase 3)
     ///
     /// 交易所撤单成功，推送CANCELLED状态回报给账户，并释放冻结资金
@@ -1043,7 +1178,7 @@ impl TradeGateway {
         remaining_volume: f64,
         qa_order_id: &str, // ✨ 新增：qars 内部订单ID，用于释放冻结资金
     ) -> Result<(), ExchangeError> {
-        let timestamp = Utc::now().timestamp_nanos_opt().unwrap_or(0);
+        let timestamp = self.clock.now_nanos();
 
         // ✨ 释放冻结资金：调用 qars cancel_order @yutiansut @quantaxis
         // user_id 在 qaexchange 中实际是 account_id
@@ -1109,6 +1244,8 @@ impl TradeGateway {
             order_id: order_id.to_string(),
             user_id: user_id.to_string(),
             reason: None,
+            reject_category: None,
+            error_code: None,
         };
 
         self.emit_order_status(order_status)?;
@@ -1158,6 +1295,53 @@ impl TradeGateway {
         Ok(())
     }
 
+    /// 处理前置拒单回报
+    ///
+    /// 订单在进入撮合前被拒绝（风控、参数非法、合约暂停、无行情价等），
+    /// 不分配交易所订单号、不写 WAL，仅推送 REJECTED 回报（含原因分类与错误码）
+    ///
+    /// @yutiansut @quantaxis
+    #[allow(clippy::too_many_arguments)]
+    pub fn handle_order_rejected_pre_trade(
+        &self,
+        instrument_id: &str,
+        user_id: &str,
+        order_id: &str,
+        direction: &str,
+        offset: &str,
+        price_type: &str,
+        price: f64,
+        volume: f64,
+        reason: &str,
+        category: RejectReason,
+        error_code: u32,
+    ) -> Result<(), ExchangeError> {
+        let exchange_id = instrument_id
+            .split_once('.')
+            .map(|(exchange, _)| exchange)
+            .unwrap_or_default();
+
+        let order_status = OrderStatusNotification {
+            exchange_id: exchange_id.to_string(),
+            instrument_id: instrument_id.to_string(),
+            exchange_order_id: String::new(),
+            direction: direction.to_string(),
+            offset: offset.to_string(),
+            price_type: price_type.to_string(),
+            volume,
+            price,
+            status: "REJECTED".to_string(),
+            timestamp: self.clock.now_nanos(),
+            order_id: order_id.to_string(),
+            user_id: user_id.to_string(),
+            reason: Some(reason.to_string()),
+            reject_category: Some(category.as_str().to_string()),
+            error_code: Some(error_code),
+        };
+
+        self.emit_order_status(order_status)
+    }
+
     /// 处理撤单拒绝回报 (Phase 3)
     ///
     /// 交易所撤单失败，推送CancelRejected回报给账户
@@ -1170,7 +1354,7 @@ impl TradeGateway {
         order_id: &str,
         reason: &str,
     ) -> Result<(), ExchangeError> {
-        let timestamp = Utc::now().timestamp_nanos_opt().unwrap_or(0);
+        let timestamp = self.clock.now_nanos();
 
         let order_status = OrderStatusNotification {
             exchange_id: exchange.to_string(),
@@ -1186,6 +1370,8 @@ impl TradeGateway {
             order_id: order_id.to_string(),
             user_id: user_id.to_string(),
             reason: Some(reason.to_string()),
+            reject_category: None,
+            error_code: None,
         };
 
         self.emit_order_status(order_status)?;
@@ -1243,7 +1429,11 @@ impl TradeGateway {
         }
 
         // 生成时间戳字符串
-        let datetime = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let datetime = self
+            .clock
+            .now_local()
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string();
 
         // 计算 towards (遵循 qars 的定义)
         let towards = match (direction, offset) {
@@ -1261,9 +1451,24 @@ impl TradeGateway {
             }
         };
 
+        // 成交前持仓成本（持仓均价台账缺失或不一致时的基准）
+        let cost_before = acc
+            .get_position(instrument_id)
+            .map(|p| PositionCost::from_position(p));
+
+        // 定点台账在成交入账前建账（首次按成交前的浮点快照）
+        let fixed_ledger = self.account_mgr.fixed_ledger();
+        if let Err(e) = fixed_ledger.open(account_id, &FloatBalances::from_account(&mut acc)) {
+            log::error!(
+                "[FixedPoint] failed to open ledger for {}: {}",
+                account_id,
+                e
+            );
+        }
+
         // 处理成交 (释放冻结资金，更新持仓和余额)
         // 注意：send_order 已在订单提交时调用，此处不需要再次调用
-        let trade_id = format!("T{}", Utc::now().timestamp_nanos_opt().unwrap_or(0));
+        let trade_id = format!("T{}", self.clock.now_nanos());
 
         log::debug!(
             "🔧   Calling receive_deal_sim with qa_order_id={}",
@@ -1280,6 +1485,89 @@ impl TradeGateway {
             towards,
         );
 
+        // 统一维护持仓均价：开仓加权、平仓不变、今昨仓分开
+        // 成交推送、QIFI 导出、结算单均读取写回后的 QA_Position 字段
+        let cost = self.account_mgr.position_costs().apply_trade(
+            account_id,
+            instrument_id,
+            cost_before,
+            towards,
+            price,
+            volume,
+        );
+        let mut multiplier = 1.0;
+        if let Some(pos) = acc.get_position(instrument_id) {
+            cost.write_to(pos);
+            multiplier = pos.preset.unit_table.max(1) as f64;
+        }
+
+        // 开仓批次明细：开仓记批次，平仓优先消耗本委托指定的批次，其余按 FIFO/LIFO
+        let trade_time = self.clock.now_millis();
+        let lot_trade = self.account_mgr.position_lots().apply_trade_detail(
+            account_id,
+            instrument_id,
+            Some(qa_order_id),
+            cost_before.as_ref(),
+            towards,
+            price,
+            volume,
+            multiplier,
+            trade_time,
+        );
+        if !lot_trade.closes.is_empty() {
+            log::debug!(
+                "🔧   Lot close: {} {} lots={:?}, profit={:.2}",
+                account_id,
+                instrument_id,
+                lot_trade
+                    .closes
+                    .iter()
+                    .map(|c| c.lot_id)
+                    .collect::<Vec<_>>(),
+                lot_trade.closes.iter().map(|c| c.profit).sum::<f64>()
+            );
+        }
+
+        // 盈亏归因流水（手续费取 qars 本笔成交记录）
+        self.account_mgr.pnl_ledger().record(
+            account_id,
+            PnlFill {
+                trade_id: trade_id.clone(),
+                instrument_id: instrument_id.to_string(),
+                direction: direction.to_string(),
+                offset: offset.to_string(),
+                price,
+                volume,
+                commission: acc.dailytrades.get(&trade_id).map_or(0.0, |t| t.commission),
+                trade_time,
+                opened_lot_id: lot_trade.opened_lot_id,
+                closes: lot_trade.closes,
+            },
+        );
+
+        // 定点台账：平仓盈亏按成交前后持仓成本、手续费与成交金额按本笔成交换算后整数入账，
+        // 并以定点值写回账户浮点字段
+        // （超出定点范围时不入账，留待下次同步按浮点增量补记并由双轨比对告警）
+        let booked = FixedFill::from_trade(
+            fixed_ledger.scale(),
+            &cost_before.unwrap_or_default(),
+            &cost,
+            towards,
+            price,
+            volume,
+            multiplier,
+            acc.dailytrades.get(&trade_id).map_or(0.0, |t| t.commission),
+        )
+        .and_then(|fill| fixed_ledger.apply_account_fill(account_id, &fill, &mut acc));
+        if let Err(e) = booked {
+            log::error!(
+                "[FixedPoint] failed to book trade {} for {}: {}",
+                trade_id,
+                account_id,
+                e
+            );
+        }
+
         // 检查成交后的持仓
         let pos_after = acc
             .get_position(instrument_id)
@@ -1324,6 +1612,15 @@ impl TradeGateway {
             ("ALIVE".to_string(), volume, volume)
         };
 
+        // 委托全部成交，指定平仓登记随之失效
+        if volume_left <= 0.0 {
+            self.account_mgr.position_lots().cancel_designation(
+                account_id,
+                instrument_id,
+                qa_order_id,
+            );
+        }
+
         log::debug!(
             "Account updated: {} {} {} {} @ {} x {} | qa_order_id: {} | trade_id: {} | money: {:.2} | order_status={}, volume_left={}/{}",
             account_id, direction, offset, instrument_id, price, volume, qa_order_id, trade_id, acc.money, status, volume_left, volume_orign
@@ -1422,7 +1719,7 @@ impl TradeGateway {
         avg_price: f64,
         last_msg: &str,
     ) -> Result<(), ExchangeError> {
-        let timestamp = Utc::now().timestamp_nanos_opt().unwrap_or(0);
+        let timestamp = self.clock.now_nanos();
 
         let record = WalRecord::OrderStatusUpdate {
             order_id: WalRecord::to_fixed_array_64(order_id),
@@ -1496,7 +1793,7 @@ impl TradeGateway {
         position_profit_short: f64,
         last_price: f64,
     ) -> Result<(), ExchangeError> {
-        let timestamp = Utc::now().timestamp_nanos_opt().unwrap_or(0);
+        let timestamp = self.clock.now_nanos();
 
         let record = WalRecord::PositionSnapshot {
             user_id: WalRecord::to_fixed_array_32(user_id),
@@ -1572,8 +1869,9 @@ impl TradeGateway {
             offset: offset.to_string(),
             price,
             volume,
-            timestamp: Utc::now().timestamp_nanos_opt().unwrap_or(0),
+            timestamp: self.clock.now_nanos(),
             commission,
+            standard: StandardTradeFields::default(),
         }
     }
 
@@ -1586,19 +1884,23 @@ impl TradeGateway {
         // ✨ 使用 write() 以便调用 get_margin() 动态计算 @yutiansut @quantaxis
         let mut acc = account.write();
 
+        // 金额取自定点台账（同步时双轨比对浮点账户并写回），对外输出由整数换算的浮点
+        let fixed_ledger = self.account_mgr.fixed_ledger();
+        let fixed = fixed_ledger
+            .sync_account(account_id, &mut acc)?
+            .view(fixed_ledger.scale());
+
         // ✨ 保证金 = 持仓保证金 + 冻结保证金（待成交订单）@yutiansut @quantaxis
-        let position_margin = acc.get_margin();
-        let frozen_margin = acc.get_frozen_margin();
-        let margin = position_margin + frozen_margin;
+        let margin = fixed.margin + fixed.frozen;
 
         let notification = AccountUpdateNotification {
             user_id: account_id.to_string(), // ✨ 使用 account_id @yutiansut @quantaxis
-            balance: acc.accounts.balance,
-            available: acc.money,
+            balance: fixed.balance,
+            available: fixed.available,
             margin,  // ✨ 修复: 使用动态计算的 margin
-            position_profit: acc.accounts.position_profit,
+            position_profit: fixed.position_profit,
             risk_ratio: acc.accounts.risk_ratio,
-            timestamp: Utc::now().timestamp_nanos_opt().unwrap_or(0),
+            timestamp: self.clock.now_nanos(),
         };
 
         self.send_notification(Notification::AccountUpdate(notification))?;
@@ -1608,10 +1910,10 @@ impl TradeGateway {
             let patch = serde_json::json!({
                 "accounts": {
                     account_id: {  // ✨ 使用 account_id @yutiansut @quantaxis
-                        "balance": acc.accounts.balance,
-                        "available": acc.money,
+                        "balance": fixed.balance,
+                        "available": fixed.available,
                         "margin": margin,  // ✨ 修复: 使用动态计算的 margin
-                        "position_profit": acc.accounts.position_profit,
+                        "position_profit": fixed.position_profit,
                         "risk_ratio": acc.accounts.risk_ratio,
                     }
                 }
@@ -1645,6 +1947,8 @@ impl TradeGateway {
                         "price": status.price,
                         "volume": status.volume,
                         "reason": status.reason,
+                        "reject_category": status.reject_category,
+                        "error_code": status.error_code,
                         "update_time": status.timestamp,
                     }
                 }
@@ -1678,6 +1982,12 @@ impl TradeGateway {
                         "volume": trade.volume,
                         "commission": trade.commission,
                         "timestamp": trade.timestamp,
+                        "exchange_id": trade.standard.exchange_id,
+                        "exchange_trade_id": trade.standard.exchange_trade_id,
+                        "exchange_order_id": trade.standard.order_sys_id,
+                        "hedge_flag": trade.standard.hedge_flag,
+                        "trade_type": trade.standard.trade_type,
+                        "match_batch_id": trade.standard.match_batch_id,
                     }
                 }
             });
@@ -1707,9 +2017,11 @@ impl TradeGateway {
         };
 
         if let Some(subs) = self.subscribers.get(user_id) {
-            for sender in subs.read().iter() {
-                let _ = sender.send(notification.clone()); // 忽略发送失败
-            }
+            // 扇出到该用户的每个会话，接收端已断开的顺带清理
+            subs.write()
+                .retain(|(_, sender)| sender.send(notification.clone()).is_ok());
+            let (kind, origin_ts) = notification.push_origin();
+            PUSH_LATENCY.record_enqueued(kind, origin_ts);
         }
 
         // 发送到全局订阅者 (crossbeam)
@@ -1758,6 +2070,12 @@ impl TradeGateway {
                 ))
             }
             Notification::AccountUpdate(account) => {
+                // 旧的 AccountUpdateNotification 没有 frozen / close_profit 字段，取自定点台账
+                let fixed = self
+                    .account_mgr
+                    .fixed_ledger()
+                    .view(&account.user_id)
+                    .unwrap_or_default();
                 Some(NewNotification::new(
                     NotificationType::AccountUpdate,
                     Arc::from(account.user_id.clone()),
@@ -1765,10 +2083,10 @@ impl TradeGateway {
                         user_id: account.user_id.clone(),
                         balance: account.balance,
                         available: account.available,
-                        frozen: 0.0, // 旧的 AccountUpdateNotification 没有 frozen 字段
+                        frozen: fixed.frozen,
                         margin: account.margin,
                         position_profit: account.position_profit,
-                        close_profit: 0.0, // 旧的 AccountUpdateNotification 没有 close_profit 字段
+                        close_profit: fixed.close_profit,
                         risk_ratio: account.risk_ratio,
                         timestamp: account.timestamp,
                     }),
@@ -1837,7 +2155,8 @@ impl TradeGateway {
                                 .reason
                                 .clone()
                                 .unwrap_or_else(|| "Order rejected".to_string()),
-                            error_code: 0,
+                            category: order.reject_category.clone().unwrap_or_default(),
+                            error_code: order.error_code.unwrap_or(0),
                             timestamp: order.timestamp,
                         }),
                     ),
@@ -1850,6 +2169,7 @@ impl TradeGateway {
                                 .reason
                                 .clone()
                                 .unwrap_or_else(|| "Cancel rejected".to_string()),
+                            category: String::new(),
                             error_code: 0,
                             timestamp: order.timestamp,
                         }),
@@ -1869,17 +2189,41 @@ impl TradeGateway {
 
     /// 订阅用户通知
     pub fn subscribe_user(&self, user_id: String) -> Receiver<Notification> {
+        self.subscribe_session(&user_id, &uuid::Uuid::new_v4().to_string())
+    }
+
+    /// 按会话订阅用户通知（每个会话独立队列，同一会话重复订阅会替换旧队列）
+    pub fn subscribe_session(&self, user_id: &str, session_id: &str) -> Receiver<Notification> {
         let (sender, receiver) = unbounded();
 
-        self.subscribers
-            .entry(user_id)
+        let subs = self
+            .subscribers
+            .entry(user_id.to_string())
             .or_insert_with(|| Arc::new(RwLock::new(Vec::new())))
-            .write()
-            .push(sender);
+            .clone();
+        let mut subs = subs.write();
+        subs.retain(|(sid, _)| sid != session_id);
+        subs.push((session_id.to_string(), sender));
 
         receiver
     }
 
+    /// 取消会话订阅
+    pub fn unsubscribe_session(&self, user_id: &str, session_id: &str) {
+        if let Some(subs) = self.subscribers.get(user_id) {
+            subs.write().retain(|(sid, _)| sid != session_id);
+        }
+        self.subscribers
+            .remove_if(user_id, |_, subs| subs.read().is_empty());
+    }
+
+    /// 用户当前订阅的会话数
+    pub fn session_count(&self, user_id: &str) -> usize {
+        self.subscribers
+            .get(user_id)
+            .map_or(0, |subs| subs.read().len())
+    }
+
     /// 订阅全局通知 (crossbeam channel)
     pub fn subscribe_global(&self) -> Receiver<Notification> {
         let (sender, receiver) = unbounded();
@@ -1902,7 +2246,7 @@ impl TradeGateway {
         let seq = self
             .trade_seq
             .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-        let timestamp = Utc::now().timestamp_millis();
+        let timestamp = self.clock.now_millis();
         format!("T{}{:010}", timestamp, seq)
     }
 }
@@ -1961,6 +2305,7 @@ mod tests {
             volume: 10.0,
             timestamp: 0,
             commission: 0.36,
+            standard: StandardTradeFields::default(),
         });
 
         gateway.send_notification(notification).unwrap();
@@ -2206,10 +2551,10 @@ mod tests {
                 volume,
                 price,
                 Some(2i64),
-                Some("counter_party"),
-                &qa_order_id,       // 使用真实的 qa_order_id
-                Some("O_counter"),  // opposite_order_id_str
-                true,               // is_taker
+                &qa_order_id, // 使用真实的 qa_order_id
+                HedgeFlag::Speculation,
+                1,
+                gateway.id_generator().now_nanos(),
             )
             .unwrap();
 
@@ -2237,10 +2582,10 @@ mod tests {
                 5.0,
                 50100.0,
                 Some(3i64),
-                Some("counter_party"),
-                &qa_order_id_2,     // 使用新的 qa_order_id
-                Some("O_counter2"), // opposite_order_id_str
-                true,               // is_taker
+                &qa_order_id_2, // 使用新的 qa_order_id
+                HedgeFlag::Speculation,
+                1,
+                gateway.id_generator().now_nanos(),
             )
             .unwrap();
 
@@ -2248,6 +2593,81 @@ mod tests {
         assert!(trade_id_2 > trade_id);
     }
 
+    #[test]
+    fn test_trade_report_standard_fields() {
+        let (gateway, account_mgr, account_id) = create_test_gateway();
+        gateway.id_generator().set_trading_day("20251217");
+        let receiver = gateway.subscribe_user(account_id.clone());
+
+        let instrument_id = "SHFE.cu2501";
+        let qa_order_id = {
+            let account = account_mgr.get_account(&account_id).unwrap();
+            let mut acc = account.write();
+            let order = acc.buy_open(instrument_id, 2.0, "2025-12-17 09:30:00", 50000.0).unwrap();
+            order.order_id.clone()
+        };
+
+        let trade_id = gateway
+            .handle_trade_new(
+                "SHFE",
+                instrument_id,
+                7,
+                &account_id,
+                "O_STD",
+                "BUY",
+                "OPEN",
+                2.0,
+                50000.0,
+                Some(8),
+                &qa_order_id,
+                HedgeFlag::Hedge,
+                1,
+                gateway.id_generator().now_nanos(),
+            )
+            .unwrap();
+
+        let trade = receiver
+            .try_iter()
+            .find_map(|n| match n {
+                Notification::Trade(t) => Some(t),
+                _ => None,
+            })
+            .expect("trade notification");
+
+        let fields = &trade.standard;
+        assert_eq!(fields.exchange_id, "SHFE");
+        assert_eq!(fields.exchange_trade_id, "20251217000000000001");
+        assert_eq!(fields.order_sys_id, "SHFE.cu2501.000000000007");
+        assert_eq!(fields.trading_day, "20251217");
+        assert_eq!(fields.sequence_no, trade_id);
+        assert_eq!(fields.direction_flag, '0');
+        assert_eq!(fields.hedge_flag, HedgeFlag::Hedge);
+        assert_eq!(fields.hedge_flag.code(), '3');
+
+        // 成交时间来自交易所统一时钟，与回报时间戳一致
+        let (trade_date, trade_time) = ExchangeIdGenerator::format_exchange_time(trade.timestamp);
+        assert_eq!(fields.trade_date, trade_date);
+        assert_eq!(fields.trade_time, trade_time);
+        assert_eq!(fields.trade_time.len(), "HH:MM:SS.mmm".len());
+
+        // 标准字段可完整序列化，用于生成对外成交文件
+        let json = serde_json::to_value(&trade).unwrap();
+        for key in [
+            "exchange_id",
+            "exchange_trade_id",
+            "order_sys_id",
+            "trading_day",
+            "trade_date",
+            "trade_time",
+            "sequence_no",
+            "direction_flag",
+            "hedge_flag",
+        ] {
+            assert!(json["standard"].get(key).is_some(), "missing {}", key);
+        }
+        assert_eq!(json["standard"]["hedge_flag"], "HEDGE");
+    }
+
     #[test]
     fn test_handle_cancel_accepted_new() {
         let (gateway, account_mgr, account_id) = create_test_gateway();
@@ -2345,10 +2765,10 @@ mod tests {
                 10.0,
                 50000.0,
                 Some(exchange_order_id + 1),
-                Some("counter_party"),
-                &qa_order_id,       // 使用真实的 qa_order_id
-                Some("O_counter"),  // opposite_order_id_str
-                true,               // is_taker
+                &qa_order_id, // 使用真实的 qa_order_id
+                HedgeFlag::Speculation,
+                1,
+                gateway.id_generator().now_nanos(),
             )
             .unwrap();
         assert_eq!(trade_id, 2);
@@ -2462,6 +2882,7 @@ mod tests {
             volume: 10.0,
             timestamp: 1702800000000,
             commission: 25.5,
+            standard: StandardTradeFields::default(),
         };
 
         assert_eq!(notification.trade_id, "T12345");
@@ -2490,6 +2911,7 @@ mod tests {
             volume: 5.0,
             timestamp: 1702800000000,
             commission: 12.9,
+            standard: StandardTradeFields::default(),
         };
 
         let cloned = notification.clone();
@@ -2560,6 +2982,8 @@ mod tests {
             order_id: "O12345".to_string(),
             user_id: "user1".to_string(),
             reason: None,
+            reject_category: None,
+            error_code: None,
         };
 
         assert_eq!(notification.exchange_id, "SHFE");
@@ -2593,6 +3017,8 @@ mod tests {
             order_id: "O12345".to_string(),
             user_id: "user1".to_string(),
             reason: Some("Insufficient margin".to_string()),
+            reject_category: None,
+            error_code: None,
         };
 
         assert_eq!(notification.status, "REJECTED");
@@ -2616,6 +3042,8 @@ mod tests {
             order_id: "O67890".to_string(),
             user_id: "user2".to_string(),
             reason: None,
+            reject_category: None,
+            error_code: None,
         };
 
         let cloned = notification.clone();
@@ -2640,6 +3068,7 @@ mod tests {
             volume: 10.0,
             timestamp: 1702800000000,
             commission: 25.5,
+            standard: StandardTradeFields::default(),
         };
 
         let notification = Notification::Trade(trade.clone());
@@ -2691,6 +3120,8 @@ mod tests {
             order_id: "O001".to_string(),
             user_id: "user1".to_string(),
             reason: None,
+            reject_category: None,
+            error_code: None,
         };
 
         let notification = Notification::OrderStatus(status.clone());
@@ -2714,8 +3145,6 @@ mod tests {
         // 验证初始状态
         assert!(gateway.notification_broker.is_none());
         assert!(gateway.snapshot_mgr.is_none());
-        assert!(gateway.trade_recorder.is_none());
-        assert!(gateway.market_data_service.is_none());
         assert_eq!(gateway.wal_root, "./data/wal");
     }
 
@@ -2771,7 +3200,7 @@ mod tests {
             margin: 50000.0,
             position_profit: 0.0,
             risk_ratio: 0.05,
-            timestamp: Utc::now().timestamp_nanos_opt().unwrap_or(0),
+            timestamp: chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0),
         });
 
         gateway.send_notification(notification).unwrap();
@@ -2804,6 +3233,7 @@ mod tests {
             volume: 10.0,
             timestamp: 0,
             commission: 25.5,
+            standard: StandardTradeFields::default(),
         });
 
         gateway.send_notification(notification).unwrap();
@@ -2860,6 +3290,7 @@ mod tests {
             volume: 10.0,
             timestamp: 0,
             commission: 25.5,
+            standard: StandardTradeFields::default(),
         });
         gateway.send_notification(notification1).unwrap();
 
@@ -2988,6 +3419,7 @@ mod tests {
             volume: 10.0,
             timestamp: 0,
             commission: 25.5,
+            standard: StandardTradeFields::default(),
         });
 
         gateway.send_notification(notification).unwrap();
@@ -2998,6 +3430,64 @@ mod tests {
         assert!(main_receiver.try_recv().is_ok(), "Main receiver should receive");
     }
 
+    /// 测试同一用户双会话同时在线都能收到同一笔成交
+    #[test]
+    fn test_multi_session_fan_out() {
+        let (gateway, _, account_id) = create_test_gateway();
+
+        let pc = gateway.subscribe_session(&account_id, "pc");
+        let mobile = gateway.subscribe_session(&account_id, "mobile");
+        assert_eq!(gateway.session_count(&account_id), 2);
+
+        let notification = Notification::Trade(TradeNotification {
+            trade_id: "T001".to_string(),
+            user_id: account_id.clone(),
+            order_id: "O001".to_string(),
+            instrument_id: "cu2501".to_string(),
+            direction: "BUY".to_string(),
+            offset: "OPEN".to_string(),
+            price: 85000.0,
+            volume: 10.0,
+            timestamp: 0,
+            commission: 25.5,
+            standard: StandardTradeFields::default(),
+        });
+        gateway.send_notification(notification.clone()).unwrap();
+
+        for receiver in [&pc, &mobile] {
+            match receiver.try_recv() {
+                Ok(Notification::Trade(t)) => assert_eq!(t.trade_id, "T001"),
+                other => panic!("Expected Trade notification, got {:?}", other),
+            }
+        }
+
+        // 断开的会话注销后不再推送，另一会话照常接收
+        gateway.unsubscribe_session(&account_id, "pc");
+        assert_eq!(gateway.session_count(&account_id), 1);
+        gateway.send_notification(notification).unwrap();
+        assert!(pc.try_recv().is_err());
+        assert!(mobile.try_recv().is_ok());
+
+        // 接收端被丢弃的会话在下次推送时清理
+        drop(mobile);
+        gateway
+            .send_notification(Notification::Trade(TradeNotification {
+                trade_id: "T002".to_string(),
+                user_id: account_id.clone(),
+                order_id: "O001".to_string(),
+                instrument_id: "cu2501".to_string(),
+                direction: "BUY".to_string(),
+                offset: "OPEN".to_string(),
+                price: 85000.0,
+                volume: 10.0,
+                timestamp: 0,
+                commission: 25.5,
+                standard: StandardTradeFields::default(),
+            }))
+            .unwrap();
+        assert_eq!(gateway.session_count(&account_id), 0);
+    }
+
     // ==================== 边界条件测试 @yutiansut @quantaxis ====================
 
     /// 测试零价格成交通知结构
@@ -3013,6 +3503,7 @@ mod tests {
             volume: 10.0,
             price: 0.0, // 零价格（市价单可能发生）
             commission: 0.0,
+            standard: StandardTradeFields::default(),
             timestamp: 1702800000000,
         };
 
@@ -3033,6 +3524,7 @@ mod tests {
             volume: 0.0, // 零数量
             price: 85000.0,
             commission: 0.0,
+            standard: StandardTradeFields::default(),
             timestamp: 1702800000000,
         };
 
@@ -3053,6 +3545,7 @@ mod tests {
             volume: 1000000.0, // 大数量
             price: 85000.0,
             commission: 1000.0,
+            standard: StandardTradeFields::default(),
             timestamp: 1702800000000,
         };
 
@@ -3089,6 +3582,7 @@ mod tests {
                     volume: 1.0 + i as f64,
                     timestamp: 0,
                     commission: 0.1,
+                    standard: StandardTradeFields::default(),
                 });
                 gateway_clone.send_notification(notification)
             }));
@@ -3168,6 +3662,7 @@ mod tests {
             volume: 10.0,
             timestamp: 1702800000000,
             commission: 25.5,
+            standard: StandardTradeFields::default(),
         };
 
         let old_notification = Notification::Trade(trade);
@@ -3220,6 +3715,8 @@ mod tests {
             order_id: "O001".to_string(),
             user_id: account_id.clone(),
             reason: None,
+            reject_category: None,
+            error_code: None,
         };
 
         let old_notification = Notification::OrderStatus(status);
@@ -3249,6 +3746,8 @@ mod tests {
             order_id: "O001".to_string(),
             user_id: account_id.clone(),
             reason: None,
+            reject_category: None,
+            error_code: None,
         };
 
         let old_notification = Notification::OrderStatus(status);
@@ -3278,6 +3777,8 @@ mod tests {
             order_id: "O001".to_string(),
             user_id: account_id.clone(),
             reason: Some("User cancelled".to_string()),
+            reject_category: None,
+            error_code: None,
         };
 
         let old_notification = Notification::OrderStatus(status);
@@ -3307,6 +3808,8 @@ mod tests {
             order_id: "O001".to_string(),
             user_id: account_id.clone(),
             reason: Some("Insufficient margin".to_string()),
+            reject_category: Some("insufficient_funds".to_string()),
+            error_code: Some(4001),
         };
 
         let old_notification = Notification::OrderStatus(status);
@@ -3315,6 +3818,15 @@ mod tests {
         assert!(new_notification.is_some());
         let new_notif = new_notification.unwrap();
         assert_eq!(new_notif.message_type, NotificationType::OrderRejected);
+
+        // 拒单原因分类与错误码透传到 OrderRejectedNotify
+        match new_notif.payload {
+            NotificationPayload::OrderRejected(notify) => {
+                assert_eq!(notify.category, "insufficient_funds");
+                assert_eq!(notify.error_code, 4001);
+            }
+            other => panic!("unexpected payload: {:?}", other),
+        }
     }
 
     /// 测试未知状态通知转换（返回 None）
@@ -3336,6 +3848,8 @@ mod tests {
             order_id: "O001".to_string(),
             user_id: account_id.clone(),
             reason: None,
+            reject_category: None,
+            error_code: None,
         };
 
         let old_notification = Notification::OrderStatus(status);
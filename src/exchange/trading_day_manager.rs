@@ -0,0 +1,504 @@
+//! 交易日重置（仅测试环境）
+//! @yutiansut @quantaxis
+//!
+//! 联调/测试环境需要反复重跑同一个交易日，重置流程：
+//! 1. 开盘时自动打标记：保存全部账户的完整状态与合约状态（落盘到 `{storage}/trading_day/`）
+//! 2. 重置时进入维护状态并停止接单，等待在途下单/撤单处理完成
+//! 3. 备份当日 WAL 段到归档目录
+//! 4. 清空订单簿挂单、订单索引、成交记录、当日K线与当日资金流水
+//! 5. 账户恢复到开盘标记，合约状态回到开盘前，按账户挂单重建订单簿
+//! 6. 恢复接单并退出维护状态
+//!
+//! 只有 `environment = "development"` 时可用，其他环境（如 production）一律拒绝。
+//! WAL 段只做备份、保留在原位，重置只作用于内存状态；重启后如需从 WAL 恢复到重置后的状态，
+//! 应先用归档目录替换存储目录中的 WAL。
+
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::exchange::emergency::{enter_maintenance, exit_maintenance, maintenance_status};
+use crate::exchange::instrument_registry::InstrumentStatus;
+use crate::exchange::{
+    AccountExport, AccountManager, CapitalManager, OrderRouter, TradingStateMachine,
+};
+use crate::market::kline::{discard_klines_since, KLineAggregators};
+use crate::matching::engine::ExchangeMatchingEngine;
+use crate::matching::TradingState;
+use crate::utils::time_service::{time_service, TimeService};
+use crate::ExchangeError;
+
+/// 允许交易日重置的运行环境
+pub const DEVELOPMENT_ENVIRONMENT: &str = "development";
+
+/// 开盘标记目录名（位于存储根目录）
+pub const OPENING_MARK_DIR: &str = "trading_day";
+
+/// 重置归档目录名（位于存储根目录）
+pub const RESET_ARCHIVE_DIR: &str = "archive";
+
+/// 重置期间的维护/停止接单原因
+pub const RESET_REASON: &str = "trading day reset";
+
+/// 开盘标记
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpeningMark {
+    pub trading_day: String,
+    /// 打标记时间（毫秒）
+    pub marked_at: i64,
+    /// 打标记时间（本地时间 YYYY-MM-DD HH:MM:SS，与资金流水时间格式一致）
+    pub marked_at_local: String,
+    /// 开盘时的账户完整状态
+    pub accounts: Vec<AccountExport>,
+    /// 开盘时的合约状态
+    pub instrument_status: HashMap<String, InstrumentStatus>,
+    /// 开盘时交易状态机的合约级状态覆盖（仅内存，重启后按交易日历判断）
+    #[serde(skip)]
+    pub instrument_states: HashMap<String, TradingState>,
+}
+
+/// 交易日重置结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TradingDayResetReport {
+    pub trading_day: String,
+    /// 所使用开盘标记的时间（毫秒）
+    pub opening_marked_at: i64,
+    /// WAL 备份目录
+    pub archive_dir: PathBuf,
+    pub wal_segments_archived: usize,
+    pub orders_cleared: usize,
+    pub trades_cleared: usize,
+    pub klines_discarded: usize,
+    pub fund_transactions_removed: usize,
+    /// 恢复到开盘标记的账户
+    pub accounts_restored: Vec<String>,
+    /// 开盘后新开的账户（不在标记中，保留现状）
+    pub accounts_kept: Vec<String>,
+    /// 恢复失败的账户 (account_id, 原因)
+    pub accounts_failed: Vec<(String, String)>,
+    pub instruments_restored: usize,
+    /// 完成时间（毫秒）
+    pub completed_at: i64,
+}
+
+/// 交易日管理器（开盘标记 + 测试环境交易日重置）
+pub struct TradingDayManager {
+    environment: String,
+    account_mgr: Arc<AccountManager>,
+    order_router: Arc<OrderRouter>,
+    matching_engine: Arc<ExchangeMatchingEngine>,
+    capital_mgr: Option<Arc<CapitalManager>>,
+    klines: Vec<KLineAggregators>,
+    storage_path: PathBuf,
+    /// 等待在途请求的超时
+    drain_timeout: Duration,
+    /// 时间源（测试环境可为虚拟时间）
+    time_service: Arc<TimeService>,
+    opening: RwLock<Option<OpeningMark>>,
+    resetting: AtomicBool,
+}
+
+impl TradingDayManager {
+    pub fn new(
+        environment: impl Into<String>,
+        account_mgr: Arc<AccountManager>,
+        order_router: Arc<OrderRouter>,
+        matching_engine: Arc<ExchangeMatchingEngine>,
+        storage_path: impl AsRef<Path>,
+    ) -> Self {
+        Self {
+            environment: environment.into(),
+            account_mgr,
+            order_router,
+            matching_engine,
+            capital_mgr: None,
+            klines: Vec::new(),
+            storage_path: storage_path.as_ref().to_path_buf(),
+            drain_timeout: Duration::from_secs(10),
+            time_service: time_service(),
+            opening: RwLock::new(None),
+            resetting: AtomicBool::new(false),
+        }
+    }
+
+    /// 重置时同时删除当日资金流水
+    pub fn with_capital_manager(mut self, capital_mgr: Arc<CapitalManager>) -> Self {
+        self.capital_mgr = Some(capital_mgr);
+        self
+    }
+
+    /// 重置时同时丢弃当日K线（KLineManager / KLineActor 各自的聚合器都需要登记）
+    pub fn with_klines(mut self, aggregators: KLineAggregators) -> Self {
+        self.klines.push(aggregators);
+        self
+    }
+
+    pub fn with_drain_timeout(mut self, timeout: Duration) -> Self {
+        self.drain_timeout = timeout;
+        self
+    }
+
+    /// 注入时间服务（与结算、时间控制共享时间源）
+    pub fn with_time_service(mut self, time_service: Arc<TimeService>) -> Self {
+        self.time_service = time_service;
+        self
+    }
+
+    pub fn environment(&self) -> &str {
+        &self.environment
+    }
+
+    /// 当前环境是否允许交易日重置
+    pub fn is_reset_allowed(&self) -> bool {
+        self.environment == DEVELOPMENT_ENVIRONMENT
+    }
+
+    /// 是否正在重置
+    pub fn is_resetting(&self) -> bool {
+        self.resetting.load(Ordering::SeqCst)
+    }
+
+    /// 当前开盘标记
+    pub fn opening_mark(&self) -> Option<OpeningMark> {
+        self.opening.read().clone()
+    }
+
+    /// 当前交易日（撮合引擎未设置时取本地日期）
+    fn current_trading_day(&self) -> String {
+        let trading_day = self.matching_engine.get_trading_day();
+        if trading_day.is_empty() {
+            self.time_service
+                .current_trading_day()
+                .format("%Y%m%d")
+                .to_string()
+        } else {
+            trading_day
+        }
+    }
+
+    fn mark_path(&self, trading_day: &str) -> PathBuf {
+        self.storage_path
+            .join(OPENING_MARK_DIR)
+            .join(format!("opening_{}.json", trading_day))
+    }
+
+    /// 打开盘标记（保存当前账户与合约状态）
+    pub fn mark_opening(&self) -> Result<OpeningMark, ExchangeError> {
+        let mut accounts = Vec::new();
+        for account in self.account_mgr.get_all_accounts() {
+            let account_id = account.read().account_cookie.clone();
+            accounts.push(self.account_mgr.export_account(&account_id)?);
+        }
+
+        let now = self.time_service.now();
+        let mark = OpeningMark {
+            trading_day: self.current_trading_day(),
+            marked_at: now.timestamp_millis(),
+            marked_at_local: now.format("%Y-%m-%d %H:%M:%S").to_string(),
+            accounts,
+            instrument_status: self
+                .order_router
+                .instrument_registry()
+                .list_all()
+                .into_iter()
+                .map(|info| (info.instrument_id, info.status))
+                .collect(),
+            instrument_states: self
+                .order_router
+                .get_trading_state_machine()
+                .map(|machine| machine.instrument_overrides())
+                .unwrap_or_default(),
+        };
+
+        let path = self.mark_path(&mark.trading_day);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                ExchangeError::StorageError(format!("Create opening mark dir failed: {}", e))
+            })?;
+        }
+        let content = serde_json::to_string_pretty(&mark).map_err(|e| {
+            ExchangeError::StorageError(format!("Serialize opening mark failed: {}", e))
+        })?;
+        std::fs::write(&path, content).map_err(|e| {
+            ExchangeError::StorageError(format!("Write opening mark failed: {}", e))
+        })?;
+
+        log::info!(
+            "Opening mark for trading day {}: {} accounts, {} instruments",
+            mark.trading_day,
+            mark.accounts.len(),
+            mark.instrument_status.len()
+        );
+        *self.opening.write() = Some(mark.clone());
+        Ok(mark)
+    }
+
+    /// 确保当前交易日有开盘标记：已落盘则加载，否则立即标记
+    ///
+    /// 非测试环境不打标记，返回 None
+    pub fn ensure_opening_mark(&self) -> Option<OpeningMark> {
+        if !self.is_reset_allowed() {
+            return None;
+        }
+
+        let trading_day = self.current_trading_day();
+        if let Some(mark) = self.opening_mark() {
+            if mark.trading_day == trading_day {
+                return Some(mark);
+            }
+        }
+
+        let loaded = std::fs::read_to_string(self.mark_path(&trading_day))
+            .ok()
+            .and_then(|content| serde_json::from_str::<OpeningMark>(&content).ok());
+        if let Some(mark) = loaded {
+            log::info!(
+                "Loaded opening mark for trading day {} ({} accounts)",
+                mark.trading_day,
+                mark.accounts.len()
+            );
+            *self.opening.write() = Some(mark.clone());
+            return Some(mark);
+        }
+
+        match self.mark_opening() {
+            Ok(mark) => Some(mark),
+            Err(e) => {
+                log::error!("Failed to mark opening: {}", e);
+                None
+            }
+        }
+    }
+
+    /// 监听交易状态机：合约从闭市进入交易阶段时自动打开盘标记（每个交易日一次）
+    pub fn watch_state_machine(self: &Arc<Self>, machine: &TradingStateMachine) {
+        let manager: Weak<Self> = Arc::downgrade(self);
+        machine.add_listener(move |_instrument_id, old_state, new_state| {
+            if old_state != TradingState::Closed || new_state == TradingState::Closed {
+                return;
+            }
+            let Some(manager) = manager.upgrade() else {
+                return;
+            };
+            if !manager.is_reset_allowed() || manager.is_resetting() {
+                return;
+            }
+            let trading_day = manager.current_trading_day();
+            if manager
+                .opening_mark()
+                .map_or(false, |mark| mark.trading_day == trading_day)
+            {
+                return;
+            }
+            if let Err(e) = manager.mark_opening() {
+                log::error!("Failed to mark opening: {}", e);
+            }
+        });
+    }
+
+    /// 重置当前交易日：恢复到开盘标记
+    ///
+    /// 重置期间拒绝新的下单/撤单，完成（或失败）后恢复接单
+    pub fn reset_trading_day(&self) -> Result<TradingDayResetReport, ExchangeError> {
+        if !self.is_reset_allowed() {
+            return Err(ExchangeError::PermissionDenied(format!(
+                "Trading day reset is only available in {} environment (current: {})",
+                DEVELOPMENT_ENVIRONMENT, self.environment
+            )));
+        }
+
+        let mark = self.opening_mark().ok_or_else(|| {
+            ExchangeError::ServiceError("No opening mark for current trading day".to_string())
+        })?;
+
+        if let Some(reason) = self.order_router.order_entry_halt_reason() {
+            return Err(ExchangeError::ServiceError(format!(
+                "Order entry is halted: {}",
+                reason
+            )));
+        }
+
+        if self
+            .resetting
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .is_err()
+        {
+            return Err(ExchangeError::ServiceError(
+                "Trading day reset already in progress".to_string(),
+            ));
+        }
+
+        log::warn!("Resetting trading day {} to opening mark", mark.trading_day);
+        enter_maintenance(RESET_REASON);
+        self.order_router.halt_order_entry(RESET_REASON);
+
+        let result = if self.order_router.wait_in_flight(self.drain_timeout) {
+            self.restore_opening(&mark)
+        } else {
+            Err(ExchangeError::ServiceError(format!(
+                "{} in-flight requests not finished within {:?}",
+                self.order_router.in_flight_requests(),
+                self.drain_timeout
+            )))
+        };
+
+        // 期间如有紧急停机等其他原因接管，保留其状态
+        if self.order_router.order_entry_halt_reason().as_deref() == Some(RESET_REASON) {
+            self.order_router.resume_order_entry();
+        }
+        if maintenance_status().map_or(false, |m| m.reason == RESET_REASON) {
+            exit_maintenance();
+        }
+        self.resetting.store(false, Ordering::SeqCst);
+
+        match &result {
+            Ok(report) => log::warn!(
+                "Trading day {} reset: {} orders, {} trades, {} klines, {} fund transactions cleared, {} accounts restored",
+                report.trading_day,
+                report.orders_cleared,
+                report.trades_cleared,
+                report.klines_discarded,
+                report.fund_transactions_removed,
+                report.accounts_restored.len()
+            ),
+            Err(e) => log::error!("Trading day reset failed: {}", e),
+        }
+        result
+    }
+
+    fn restore_opening(&self, mark: &OpeningMark) -> Result<TradingDayResetReport, ExchangeError> {
+        let mut report = TradingDayResetReport {
+            trading_day: mark.trading_day.clone(),
+            opening_marked_at: mark.marked_at,
+            archive_dir: self.storage_path.join(RESET_ARCHIVE_DIR).join(format!(
+                "trading_day_{}_{}",
+                mark.trading_day,
+                chrono::Utc::now().timestamp_millis()
+            )),
+            ..Default::default()
+        };
+
+        // 1. 备份当日 WAL 段
+        report.wal_segments_archived =
+            archive_wal_segments(&self.storage_path, &report.archive_dir, mark.marked_at)?;
+
+        // 2. 清空订单簿、成交记录、当日K线与资金流水
+        report.orders_cleared = self.order_router.reset_order_state();
+        let recorder = self.matching_engine.get_trade_recorder();
+        report.trades_cleared = recorder.get_trade_count();
+        recorder.clear();
+        report.klines_discarded = self
+            .klines
+            .iter()
+            .map(|aggregators| discard_klines_since(aggregators, mark.marked_at))
+            .sum();
+        report.fund_transactions_removed = self.capital_mgr.as_ref().map_or(0, |mgr| {
+            mgr.remove_transactions_since(&mark.marked_at_local)
+        });
+
+        // 3. 账户恢复到开盘标记（开盘后新开的账户保留）
+        let summary = self
+            .account_mgr
+            .import_accounts(mark.accounts.clone(), true);
+        report.accounts_restored = summary.imported;
+        report.accounts_failed = summary.failed;
+        let marked: HashSet<&str> = mark
+            .accounts
+            .iter()
+            .map(|a| a.account_id.as_str())
+            .collect();
+        report.accounts_kept = self
+            .account_mgr
+            .get_all_accounts()
+            .into_iter()
+            .map(|account| account.read().account_cookie.clone())
+            .filter(|account_id| !marked.contains(account_id.as_str()))
+            .collect();
+
+        // 4. 合约状态回到开盘前
+        let registry = self.order_router.instrument_registry();
+        for (instrument_id, status) in &mark.instrument_status {
+            if registry
+                .update(instrument_id, |info| info.status = *status)
+                .is_ok()
+            {
+                report.instruments_restored += 1;
+            }
+        }
+        if let Some(machine) = self.order_router.get_trading_state_machine() {
+            machine.restore_instrument_overrides(mark.instrument_states.clone());
+        }
+
+        // 5. 按开盘时的账户挂单重建订单簿
+        self.order_router.restore_orders_from_accounts();
+
+        report.completed_at = chrono::Utc::now().timestamp_millis();
+        Ok(report)
+    }
+}
+
+/// 复制 `since_ms` 之后有写入的 WAL 段到归档目录（保持相对路径），返回复制的段数
+fn archive_wal_segments(
+    root: &Path,
+    archive_dir: &Path,
+    since_ms: i64,
+) -> Result<usize, ExchangeError> {
+    let archive_root = root.join(RESET_ARCHIVE_DIR);
+    let mut pending = vec![root.to_path_buf()];
+    let mut archived = 0;
+
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                if path != archive_root {
+                    pending.push(path);
+                }
+                continue;
+            }
+
+            let is_segment = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .map_or(false, |name| {
+                    name.starts_with("wal_") && name.ends_with(".log")
+                });
+            let modified_ms = entry
+                .metadata()
+                .and_then(|meta| meta.modified())
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |d| d.as_millis() as i64);
+            if !is_segment || modified_ms < since_ms {
+                continue;
+            }
+
+            let Ok(relative) = path.strip_prefix(root) else {
+                continue;
+            };
+            let target = archive_dir.join(relative);
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| {
+                    ExchangeError::StorageError(format!("Create archive dir failed: {}", e))
+                })?;
+            }
+            std::fs::copy(&path, &target).map_err(|e| {
+                ExchangeError::StorageError(format!(
+                    "Archive WAL segment {} failed: {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+            archived += 1;
+        }
+    }
+
+    Ok(archived)
+}
@@ -3,7 +3,7 @@
 //! 管理交易所的交易时段、状态转换和订单处理规则。
 //! @yutiansut @quantaxis
 
-use chrono::{Local, NaiveTime};
+use chrono::NaiveTime;
 use dashmap::DashMap;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
@@ -15,6 +15,8 @@ use std::time::Duration;
 
 pub use crate::matching::TradingState;
 
+use crate::utils::time_service::now_local;
+
 /// 交易时段定义
 #[derive(Debug, Clone)]
 pub struct TradingSession {
@@ -361,7 +363,7 @@ impl TradingStateMachine {
             log::info!("Trading state machine auto-transition started");
 
             while machine.running.load(Ordering::SeqCst) {
-                let now = Local::now().time();
+                let now = now_local().time();
 
                 // 更新每个交易所的状态
                 for exchange_type in [
@@ -441,6 +443,33 @@ impl TradingStateMachine {
         );
     }
 
+    /// 清除合约级别状态覆盖（回到按交易日历判断）
+    pub fn clear_instrument_state(&self, instrument_id: &str) {
+        if let Some((_, old_state)) = self.instrument_states.remove(instrument_id) {
+            log::info!(
+                "Instrument {} state override cleared (was {:?})",
+                instrument_id,
+                old_state
+            );
+        }
+    }
+
+    /// 合约级别状态覆盖
+    pub fn instrument_overrides(&self) -> HashMap<String, TradingState> {
+        self.instrument_states
+            .iter()
+            .map(|r| (r.key().clone(), *r.value()))
+            .collect()
+    }
+
+    /// 整体替换合约级别状态覆盖（未覆盖的合约回到按交易日历判断）
+    pub fn restore_instrument_overrides(&self, overrides: HashMap<String, TradingState>) {
+        self.instrument_states.clear();
+        for (instrument_id, state) in overrides {
+            self.instrument_states.insert(instrument_id, state);
+        }
+    }
+
     /// 获取合约的交易状态
     pub fn get_instrument_state(&self, instrument_id: &str) -> TradingState {
         // 优先返回合约级别状态，否则根据交易所类型返回
@@ -450,7 +479,7 @@ impl TradingStateMachine {
 
         // 根据交易所类型判断
         if let Some(exchange) = ExchangeType::from_instrument_id(instrument_id) {
-            let now = Local::now().time();
+            let now = now_local().time();
             self.calendar.get_current_state(exchange, now)
         } else {
             *self.global_state.read()
@@ -461,7 +490,7 @@ impl TradingStateMachine {
     pub fn validate_order(&self, instrument_id: &str) -> OrderValidation {
         let state = self.get_instrument_state(instrument_id);
         let exchange = ExchangeType::from_instrument_id(instrument_id);
-        let now = Local::now().time();
+        let now = now_local().time();
 
         // 获取当前时段
         if let Some(ex) = exchange {
@@ -492,7 +521,7 @@ impl TradingStateMachine {
     pub fn validate_cancel(&self, instrument_id: &str) -> OrderValidation {
         let state = self.get_instrument_state(instrument_id);
         let exchange = ExchangeType::from_instrument_id(instrument_id);
-        let now = Local::now().time();
+        let now = now_local().time();
 
         // 获取当前时段
         if let Some(ex) = exchange {
@@ -543,7 +572,7 @@ impl TradingStateMachine {
 
     /// 检查当前是否为交易时间
     pub fn is_trading_time(&self, exchange: ExchangeType) -> bool {
-        let now = Local::now().time();
+        let now = now_local().time();
         matches!(
             self.calendar.get_current_state(exchange, now),
             TradingState::ContinuousTrading | TradingState::AuctionOrder
@@ -552,7 +581,7 @@ impl TradingStateMachine {
 
     /// 获取下一个状态转换时间
     pub fn get_next_transition_time(&self, exchange: ExchangeType) -> Option<NaiveTime> {
-        let now = Local::now().time();
+        let now = now_local().time();
         if let Some(sessions) = self.calendar.sessions.get(&exchange) {
             for session in sessions {
                 if session.start_time > now {
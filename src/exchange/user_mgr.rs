@@ -104,7 +104,9 @@ impl UserManager {
             phone: req.phone.clone(),
             password_hash,
             is_admin: is_first_user, // 第一个用户自动成为管理员
-            created_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            created_at: crate::utils::time_service::now_local()
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string(),
         };
 
         // 4. 自动创建关联的交易账户
@@ -215,7 +217,9 @@ impl UserManager {
             phone: "".to_string(),
             password_hash,
             is_admin: true,
-            created_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            created_at: crate::utils::time_service::now_local()
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string(),
         };
 
         // 创建管理员账户
@@ -220,6 +220,7 @@ impl Actor for FactorActor {
                             period,
                             kline,
                             timestamp,
+                            ..
                         } = event
                         {
                             // 检查是否需要计算该周期的因子
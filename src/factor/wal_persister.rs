@@ -22,8 +22,9 @@
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
-use crossbeam::channel::{bounded, Receiver, Sender, TryRecvError};
+use crossbeam::channel::{bounded, Receiver, RecvTimeoutError, Sender, TryRecvError};
 use parking_lot::RwLock;
 
 use crate::storage::wal::record::WalRecord;
@@ -495,6 +496,38 @@ impl FactorWalConsumer {
         }
     }
 
+    /// 运行消费循环（阻塞），空闲超过 `flush_interval` 时刷新未满批量的缓冲
+    ///
+    /// 因子更新按 K线节奏到达，批量未满时也需及时落盘，供历史查询立即可见
+    pub fn run_with_flush_interval(&mut self, flush_interval: Duration) {
+        log::info!("Factor WAL consumer started");
+
+        loop {
+            match self.rx.recv_timeout(flush_interval) {
+                Ok(FactorWalMessage::Shutdown) => {
+                    self.flush();
+                    log::info!("Factor WAL consumer shutdown");
+                    break;
+                }
+                Ok(msg) => {
+                    if let Some(record) = message_to_wal_record(&msg) {
+                        self.batch_buffer.push(record);
+
+                        if self.batch_buffer.len() >= self.batch_size {
+                            self.flush();
+                        }
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => self.flush(),
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.flush();
+                    log::info!("Factor WAL consumer channel closed");
+                    break;
+                }
+            }
+        }
+    }
+
     /// 非阻塞运行一轮
     pub fn poll(&mut self) -> bool {
         let mut processed = false;
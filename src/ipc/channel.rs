@@ -0,0 +1,221 @@
+//! 核心进程间消息通道
+//!
+//! 撮合核心（MatchingEngineCore）与账户核心（AccountSystemCore）之间的订单/成交链路：
+//! - 单进程模式：crossbeam channel（默认）
+//! - 同机多进程模式：iceoryx2 共享内存（需要 `iceoryx2` feature）
+//!
+//! 消息均为 `#[repr(C)]` 定长 POD 结构（见 `protocol::ipc_messages`），可直接零拷贝传递。
+//!
+//! @yutiansut @quantaxis
+
+use crossbeam::channel::{Receiver, RecvTimeoutError, Sender};
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "iceoryx2")]
+use super::{IceoryxPublisher, IceoryxSubscriber, IpcConfig};
+#[cfg(feature = "iceoryx2")]
+use std::sync::Arc;
+
+/// 核心链路的 iceoryx2 主题
+pub mod topics {
+    /// Gateway → 撮合核心：订单请求
+    pub const ORDER_REQUESTS: &str = "core/order_requests";
+    /// 撮合核心 → 账户核心/Gateway：成交回报
+    pub const TRADE_REPORTS: &str = "core/trade_reports";
+    /// 撮合核心 → 账户核心：订单确认
+    pub const ORDER_ACCEPTED: &str = "core/order_accepted";
+    /// 撮合核心 → 行情：订单簿快照
+    pub const ORDERBOOK_SNAPSHOTS: &str = "core/orderbook_snapshots";
+}
+
+/// 消息发送端
+pub enum MessageSender<T: Copy + std::fmt::Debug> {
+    /// 进程内 crossbeam 通道
+    Local(Sender<T>),
+    /// 跨进程 iceoryx2 发布者
+    #[cfg(feature = "iceoryx2")]
+    Ipc(Arc<IceoryxPublisher<T>>),
+}
+
+impl<T: Copy + std::fmt::Debug> MessageSender<T> {
+    /// 创建 iceoryx2 发送端
+    #[cfg(feature = "iceoryx2")]
+    pub fn ipc(config: &IpcConfig, topic: &str) -> Result<Self, String> {
+        Ok(Self::Ipc(Arc::new(IceoryxPublisher::open_or_create(
+            config, topic,
+        )?)))
+    }
+
+    /// 发送消息
+    pub fn send(&self, msg: T) -> Result<(), String> {
+        match self {
+            Self::Local(sender) => sender
+                .send(msg)
+                .map_err(|e| format!("Channel send failed: {}", e)),
+            #[cfg(feature = "iceoryx2")]
+            Self::Ipc(publisher) => publisher.publish(&msg),
+        }
+    }
+}
+
+impl<T: Copy + std::fmt::Debug> From<Sender<T>> for MessageSender<T> {
+    fn from(sender: Sender<T>) -> Self {
+        Self::Local(sender)
+    }
+}
+
+/// 消息接收端
+pub enum MessageReceiver<T: Copy + std::fmt::Debug> {
+    /// 进程内 crossbeam 通道
+    Local(Receiver<T>),
+    /// 跨进程 iceoryx2 订阅者
+    #[cfg(feature = "iceoryx2")]
+    Ipc(Arc<IceoryxSubscriber<T>>),
+}
+
+impl<T: Copy + std::fmt::Debug> MessageReceiver<T> {
+    /// 创建 iceoryx2 接收端
+    #[cfg(feature = "iceoryx2")]
+    pub fn ipc(config: &IpcConfig, topic: &str) -> Result<Self, String> {
+        Ok(Self::Ipc(Arc::new(IceoryxSubscriber::open_or_create(
+            config, topic,
+        )?)))
+    }
+
+    /// 非阻塞接收
+    pub fn try_recv(&self) -> Option<T> {
+        match self {
+            Self::Local(receiver) => receiver.try_recv().ok(),
+            #[cfg(feature = "iceoryx2")]
+            Self::Ipc(subscriber) => match subscriber.try_receive() {
+                Ok(msg) => msg,
+                Err(e) => {
+                    log::error!("[{}] {}", subscriber.service_name(), e);
+                    None
+                }
+            },
+        }
+    }
+
+    /// 带超时接收
+    ///
+    /// crossbeam 通道阻塞等待；iceoryx2 订阅者没有阻塞接口，忙等轮询以获得最低延迟
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<T> {
+        match self {
+            Self::Local(receiver) => match receiver.recv_timeout(timeout) {
+                Ok(msg) => Some(msg),
+                Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => None,
+            },
+            #[cfg(feature = "iceoryx2")]
+            Self::Ipc(_) => {
+                let deadline = Instant::now() + timeout;
+                let mut spins = 0u32;
+                loop {
+                    if let Some(msg) = self.try_recv() {
+                        return Some(msg);
+                    }
+                    if Instant::now() >= deadline {
+                        return None;
+                    }
+                    spin_wait(&mut spins);
+                }
+            }
+        }
+    }
+
+    /// 是否为进程内 crossbeam 通道
+    pub fn as_local(&self) -> Option<&Receiver<T>> {
+        match self {
+            Self::Local(receiver) => Some(receiver),
+            #[cfg(feature = "iceoryx2")]
+            Self::Ipc(_) => None,
+        }
+    }
+}
+
+impl<T: Copy + std::fmt::Debug> From<Receiver<T>> for MessageReceiver<T> {
+    fn from(receiver: Receiver<T>) -> Self {
+        Self::Local(receiver)
+    }
+}
+
+/// 轮询等待：先自旋，空转较久后让出 CPU
+pub fn spin_wait(spins: &mut u32) {
+    if *spins < 1000 {
+        *spins += 1;
+        std::hint::spin_loop();
+    } else {
+        std::thread::yield_now();
+    }
+}
+
+/// 轮询多个接收端时的空闲计时（超过 interval 视为一次空闲超时）
+pub struct IdleTimer {
+    interval: Duration,
+    last_activity: Instant,
+    spins: u32,
+}
+
+impl IdleTimer {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last_activity: Instant::now(),
+            spins: 0,
+        }
+    }
+
+    /// 收到消息时重置
+    pub fn reset(&mut self) {
+        self.last_activity = Instant::now();
+        self.spins = 0;
+    }
+
+    /// 空闲等待一次；距离上次活动超过 interval 时返回 true 并重新计时
+    pub fn wait(&mut self) -> bool {
+        if self.last_activity.elapsed() >= self.interval {
+            self.reset();
+            return true;
+        }
+        spin_wait(&mut self.spins);
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::ipc_messages::{OrderDirection, OrderOffset, OrderRequest};
+    use crossbeam::channel::unbounded;
+
+    #[test]
+    fn test_local_channel_roundtrip() {
+        let (tx, rx) = unbounded::<OrderRequest>();
+        let sender: MessageSender<OrderRequest> = tx.into();
+        let receiver: MessageReceiver<OrderRequest> = rx.into();
+
+        let req = OrderRequest::new(
+            "ORDER001",
+            "user_01",
+            "IF2501",
+            OrderDirection::BUY,
+            OrderOffset::OPEN,
+            3800.0,
+            1.0,
+        );
+        sender.send(req).unwrap();
+
+        let received = receiver.recv_timeout(Duration::from_millis(10)).unwrap();
+        assert_eq!(received.order_id, req.order_id);
+        assert!(receiver.try_recv().is_none());
+        assert!(receiver.as_local().is_some());
+    }
+
+    #[test]
+    fn test_idle_timer() {
+        let mut timer = IdleTimer::new(Duration::from_millis(1));
+        std::thread::sleep(Duration::from_millis(2));
+        assert!(timer.wait());
+        assert!(!timer.wait());
+    }
+}
@@ -11,11 +11,13 @@
 //! - manager: IPC 管理器（资源生命周期）
 //! - production: 生产级部署支持（健康检查、监控、容量规划）
 //! - publisher/subscriber: iceoryx2 发布/订阅实现
+//! - channel: 撮合核心与账户核心之间的订单/成交通道（crossbeam / iceoryx2）
 //!
 //! 注意：当前版本使用条件编译，如果iceoryx2不可用则fallback到crossbeam
 //!
 //! @yutiansut @quantaxis
 
+pub mod channel;
 pub mod manager;
 pub mod production;
 pub mod types;
@@ -30,6 +32,7 @@ pub use publisher::IceoryxPublisher;
 #[cfg(feature = "iceoryx2")]
 pub use subscriber::IceoryxSubscriber;
 
+pub use channel::{MessageReceiver, MessageSender};
 pub use manager::IceoryxManager;
 pub use production::{
     CapacityPlanner, HealthCheckResult, HealthStatus, IpcMetrics, ProductionIpcConfig,
@@ -2,6 +2,7 @@
 
 use super::{make_service_name, IpcConfig, IpcMarketData, IpcNotification};
 use iceoryx2::port::publisher::Publisher;
+use iceoryx2::port::unable_to_deliver_strategy::UnableToDeliverStrategy;
 use iceoryx2::prelude::*;
 use parking_lot::Mutex;
 use std::sync::Arc;
@@ -38,6 +39,35 @@ impl<T: Copy + std::fmt::Debug> IceoryxPublisher<T> {
         })
     }
 
+    /// 创建可靠投递的发布者（服务不存在则创建，已存在则打开）
+    ///
+    /// 用于核心进程间的订单/成交链路：禁止覆盖未读消息，订阅者缓冲满时发布者阻塞等待，
+    /// 保证不丢消息；进程启动顺序不限
+    pub fn open_or_create(config: &IpcConfig, topic: &str) -> Result<Self, String> {
+        let service_name_str = make_service_name(&config.service_prefix, topic);
+        let service_name = ServiceName::new(&service_name_str)
+            .map_err(|e| format!("Invalid service name: {:?}", e))?;
+
+        let service = ipc::Service::new(&service_name)
+            .publish_subscribe::<T>()
+            .max_subscribers(config.max_subscribers)
+            .subscriber_max_buffer_size(config.queue_capacity)
+            .enable_safe_overflow(false)
+            .open_or_create()
+            .map_err(|e| format!("Failed to open or create service: {:?}", e))?;
+
+        let publisher = service
+            .publisher_builder()
+            .unable_to_deliver_strategy(UnableToDeliverStrategy::Block)
+            .create()
+            .map_err(|e| format!("Failed to create publisher: {:?}", e))?;
+
+        Ok(Self {
+            publisher: Arc::new(Mutex::new(publisher)),
+            service_name: service_name_str,
+        })
+    }
+
     /// 发布消息（零拷贝）
     pub fn publish(&self, data: &T) -> Result<(), String> {
         let mut publisher = self.publisher.lock();
@@ -36,6 +36,34 @@ impl<T: Copy + std::fmt::Debug> IceoryxSubscriber<T> {
         })
     }
 
+    /// 创建订阅者（服务不存在则按发布者相同的配置创建）
+    ///
+    /// 与 `IceoryxPublisher::open_or_create` 配套，进程启动顺序不限
+    pub fn open_or_create(config: &IpcConfig, topic: &str) -> Result<Self, String> {
+        let service_name_str = make_service_name(&config.service_prefix, topic);
+        let service_name = ServiceName::new(&service_name_str)
+            .map_err(|e| format!("Invalid service name: {:?}", e))?;
+
+        let service = ipc::Service::new(&service_name)
+            .publish_subscribe::<T>()
+            .max_subscribers(config.max_subscribers)
+            .subscriber_max_buffer_size(config.queue_capacity)
+            .enable_safe_overflow(false)
+            .open_or_create()
+            .map_err(|e| format!("Failed to open or create service: {:?}", e))?;
+
+        let subscriber = service
+            .subscriber_builder()
+            .buffer_size(config.queue_capacity)
+            .create()
+            .map_err(|e| format!("Failed to create subscriber: {:?}", e))?;
+
+        Ok(Self {
+            subscriber: Arc::new(Mutex::new(subscriber)),
+            service_name: service_name_str,
+        })
+    }
+
     /// 接收消息（非阻塞）
     pub fn try_receive(&self) -> Result<Option<T>, String> {
         let subscriber = self.subscriber.lock();
@@ -28,20 +28,26 @@
 
 use qaexchange::exchange::instrument_registry::{InstrumentInfo, InstrumentStatus, InstrumentType};
 use qaexchange::exchange::{
-    AccountManager, CapitalManager, InstrumentRegistry, OrderRouter, SettlementEngine, TradeGateway,
+    AccountManager, CapitalManager, EmergencyShutdown, InstrumentRegistry, OrderRouter,
+    SettlementEngine, ShutdownRecord, TimeController, TradeEventBus, TradeGateway,
+    TradingDayManager,
 };
 use qaexchange::market::{MarketDataBroadcaster, SnapshotBroadcastService};
 use qaexchange::matching::engine::ExchangeMatchingEngine;
 use qaexchange::notification::broker::NotificationBroker;
-use qaexchange::storage::conversion::{ConversionManager, SchedulerConfig, WorkerConfig};
+use qaexchange::storage::conversion::{
+    ConversionManager, FactorStore, KLineConversionConfig, KLineConverter, SchedulerConfig,
+    WorkerConfig,
+};
 use qaexchange::storage::hybrid::oltp::OltpHybridConfig;
+use qaexchange::storage::maintenance::StorageMaintenance;
 use qaexchange::storage::subscriber::{StorageSubscriber, StorageSubscriberConfig};
 use qaexchange::user::UserManager;
 // use qaexchange::service::http::HttpServer;  // 未使用
 use actix::Actor;
 use actix_web::{middleware, web, App, HttpServer as ActixHttpServer};
 use chrono;
-use qaexchange::risk::RiskMonitor;
+use qaexchange::risk::{RiskMonitor, SimulatedMarginProvider};
 use qaexchange::service::http::admin::AdminAppState;
 use qaexchange::service::http::management::ManagementAppState;
 use qaexchange::service::websocket::WebSocketServer;
@@ -64,6 +70,9 @@ struct ExchangeConfig {
 
     /// 是否启用持久化
     enable_storage: bool,
+
+    /// 运行环境（development 下允许交易日重置）
+    environment: String,
 }
 
 impl ExchangeConfig {
@@ -74,6 +83,7 @@ impl ExchangeConfig {
             ws_address: toml_config.websocket.bind_address(),
             storage_path: toml_config.storage.base_path,
             enable_storage: toml_config.storage.enabled,
+            environment: toml_config.server.environment,
         }
     }
 }
@@ -85,6 +95,7 @@ impl Default for ExchangeConfig {
             ws_address: "127.0.0.1:8081".to_string(),
             storage_path: "/tmp/qaexchange/storage".to_string(),
             enable_storage: true,
+            environment: "development".to_string(),
         }
     }
 }
@@ -124,6 +135,9 @@ struct ExchangeServer {
     /// 风险监控器
     risk_monitor: Arc<RiskMonitor>,
 
+    /// 涨跌停板管理器（价格校验 + 动态扩板）
+    price_limit_manager: Arc<qaexchange::risk::PriceLimitManager>,
+
     /// 用户管理器
     user_mgr: Arc<UserManager>,
 
@@ -133,6 +147,9 @@ struct ExchangeServer {
     /// 市场数据存储（用于持久化 TickData 和 OrderBookSnapshot）
     market_data_storage: Arc<qaexchange::storage::hybrid::OltpHybridStorage>,
 
+    /// 订单/成交数据导出日志（/ws/export 数据源，存储启用时创建）
+    export_log: Option<Arc<qaexchange::storage::export::ExportLog>>,
+
     /// 存储订阅器统计信息
     storage_stats:
         Option<Arc<parking_lot::Mutex<qaexchange::storage::subscriber::SubscriberStats>>>,
@@ -149,8 +166,41 @@ struct ExchangeServer {
     /// K线WAL管理器（用于历史K线查询）@yutiansut @quantaxis
     kline_wal_manager: Arc<qaexchange::storage::wal::WalManager>,
 
+    /// 因子存储（因子值落盘与历史查询，未启用时为 None）@yutiansut @quantaxis
+    factor_store: Option<Arc<FactorStore>>,
+
+    /// OLAP 月份分区冷数据配置 @yutiansut @quantaxis
+    olap_partition_config: qaexchange::storage::conversion::OlapPartitionConfig,
+
+    /// 存储数据保留策略配置 @yutiansut @quantaxis
+    retention_config: qaexchange::storage::conversion::RetentionConfig,
+
     /// 快照生成器线程句柄
     snapshot_generator_handle: Option<std::thread::JoinHandle<()>>,
+
+    /// 紧急停机协调器 @yutiansut @quantaxis
+    emergency: Arc<EmergencyShutdown>,
+
+    /// 交易日管理（开盘标记 / 测试环境重置） @yutiansut @quantaxis
+    trading_day: Arc<TradingDayManager>,
+
+    /// 测试环境时间控制（虚拟时间快进） @yutiansut @quantaxis
+    time_control: Arc<TimeController>,
+
+    /// 新合约上市保护 @yutiansut @quantaxis
+    listing_protection: Arc<qaexchange::exchange::ListingProtection>,
+
+    /// 影子撮合引擎（未启用影子模式时为 None）@yutiansut @quantaxis
+    shadow_engine: Option<Arc<ExchangeMatchingEngine>>,
+
+    /// 用户消息中心（未启用时为 None）@yutiansut @quantaxis
+    notification_store: Option<Arc<qaexchange::notification::NotificationStore>>,
+
+    /// 重查询/导出异步任务管理器（未启用时为 None）@yutiansut @quantaxis
+    task_manager: Option<Arc<qaexchange::service::async_task::AsyncTaskManager>>,
+
+    /// HTTP / WebSocket 服务句柄（紧急停机时优雅关闭）
+    server_handles: parking_lot::Mutex<Vec<actix_web::dev::ServerHandle>>,
 }
 
 impl ExchangeServer {
@@ -161,6 +211,14 @@ impl ExchangeServer {
     ) -> Self {
         log::info!("Initializing Exchange Server...");
 
+        // 0. 交易所时区：交易日、自然日边界与时间显示统一按此时区计算
+        match qaexchange::utils::time_service::TimeService::new(&perf_config.time) {
+            Ok(service) => qaexchange::utils::time_service::set_time_service(Arc::new(service)),
+            Err(e) => log::warn!("Invalid time config, using Asia/Shanghai: {}", e),
+        }
+        // 结算、交易日管理、订单到期与时间控制注入同一实例（共享时间源）
+        let time_service = qaexchange::utils::time_service::time_service();
+
         // 1. 创建核心组件
         // 1.1 创建通知系统
         let notification_broker = Arc::new(NotificationBroker::new());
@@ -169,6 +227,24 @@ impl ExchangeServer {
         let _priority_processor_handle = notification_broker.clone().start_priority_processor();
         log::info!("✅ Notification priority processor started");
 
+        // 1.1.1 用户消息中心：高/中优先级通知异步批量落盘（按用户分区）
+        let notification_store = if perf_config.notification_store.enabled {
+            let store = Arc::new(qaexchange::notification::NotificationStore::new(
+                perf_config.notification_store.clone(),
+            ));
+            if config.enable_storage {
+                let notification_dir = format!("{}/notifications", config.storage_path);
+                match store.open_storage(&notification_dir) {
+                    Ok(count) => log::info!("✅ Notification center loaded: {} messages", count),
+                    Err(e) => log::warn!("Failed to open notification storage: {}", e),
+                }
+            }
+            store.start(&notification_broker);
+            Some(store)
+        } else {
+            None
+        };
+
         // 1.2 创建用户管理器并设置持久化存储
         let mut user_mgr_inner = UserManager::new();
 
@@ -200,41 +276,112 @@ impl ExchangeServer {
         // 这样开户时可以自动绑定到用户
         account_mgr_inner.set_user_manager(user_mgr.clone());
 
+        // 账户交易限制（只平仓 / 暂停交易）持久化，按 account_id 恢复 @yutiansut @quantaxis
+        let restriction_store = format!("{}/account_restrictions.json", config.storage_path);
+        match account_mgr_inner.set_restriction_store(&restriction_store) {
+            Ok(count) => log::info!("✅ Trading restrictions restored: {} accounts", count),
+            Err(e) => log::warn!("Failed to restore trading restrictions: {}", e),
+        }
+
+        // 账户标签（运营分类）与保证金加成持久化 @yutiansut @quantaxis
+        let tag_store = format!("{}/account_tags.json", config.storage_path);
+        match account_mgr_inner.account_tags().set_store(&tag_store) {
+            Ok(count) => log::info!("✅ Account tags restored: {} accounts", count),
+            Err(e) => log::warn!("Failed to restore account tags: {}", e),
+        }
+
+        // 资金定点核算（i64 定点台账 + 浮点双轨比对） @yutiansut @quantaxis
+        match perf_config.fixed_point.validate() {
+            Ok(()) => account_mgr_inner
+                .fixed_ledger()
+                .configure(perf_config.fixed_point.clone()),
+            Err(e) => log::warn!("Invalid fixed point config ({}), using defaults", e),
+        }
+
         // 现在可以安全地包装成 Arc
         let account_mgr = Arc::new(account_mgr_inner);
 
         let matching_engine = Arc::new(ExchangeMatchingEngine::new());
         let instrument_registry = Arc::new(InstrumentRegistry::new());
 
-        // 1.3 创建交易网关并设置通知系统和成交记录器
+        // 1.3 创建交易网关并设置通知系统（成交记录/行情由成交事件总线消费，见 3.1）
         let mut trade_gateway_inner = TradeGateway::new(account_mgr.clone());
         trade_gateway_inner.set_notification_broker(notification_broker.clone());
-
-        // 从 matching_engine 获取 trade_recorder 并设置到 trade_gateway
-        let trade_recorder = matching_engine.get_trade_recorder();
-        trade_gateway_inner = trade_gateway_inner.set_trade_recorder(trade_recorder.clone());
-
-        // 先创建 trade_gateway Arc（后续会设置 market_data_service）
         let trade_gateway = Arc::new(trade_gateway_inner);
 
         let market_broadcaster = Arc::new(MarketDataBroadcaster::new());
+        market_broadcaster.set_instrument_registry(instrument_registry.clone());
 
         // 1.3.1 创建K线WAL管理器（统一到配置路径）
         let kline_wal_dir = format!("{}/klines/wal", config.storage_path);
         std::fs::create_dir_all(&kline_wal_dir).unwrap_or_else(|e| {
             log::warn!("Failed to create K-line WAL directory: {}", e);
         });
-        let kline_wal_manager = Arc::new(qaexchange::storage::wal::WalManager::new(&kline_wal_dir));
+        // K线 WAL 按 64MB 滚动，已转换为 Parquet 的旧文件才能按保留策略清理
+        let kline_wal_manager = Arc::new(
+            qaexchange::storage::wal::WalManager::new(&kline_wal_dir)
+                .with_max_file_size(64 * 1024 * 1024),
+        );
         log::info!("✅ K-line WAL Manager initialized at {}", kline_wal_dir);
 
-        // 1.3.2 启动K线Actor（订阅tick事件，独立处理K线聚合）
+        // 1.3.2 因子值落盘：因子 WAL + 异步写入线程，FactorStore 负责转换与查询 @yutiansut @quantaxis
+        let factor_config = perf_config.factor_store.clone();
+        let mut factor_persister = None;
+        let mut factor_store = None;
+        if factor_config.enabled {
+            let factor_wal_dir = format!("{}/factors/wal", config.storage_path);
+            std::fs::create_dir_all(&factor_wal_dir).unwrap_or_else(|e| {
+                log::warn!("Failed to create factor WAL directory: {}", e);
+            });
+            let factor_wal = Arc::new(parking_lot::RwLock::new(
+                qaexchange::storage::wal::WalManager::new(&factor_wal_dir)
+                    .with_max_file_size(64 * 1024 * 1024),
+            ));
+            let factor_olap_dir = PathBuf::from(&config.storage_path)
+                .join("factors")
+                .join("olap");
+            match FactorStore::new(factor_wal.clone(), factor_olap_dir, factor_config.clone()) {
+                Ok(store) => {
+                    let (persister, rx) = qaexchange::factor::FactorWalPersister::new(
+                        qaexchange::factor::FactorWalConfig {
+                            channel_buffer_size: factor_config.channel_buffer_size,
+                            batch_size: factor_config.batch_size,
+                            ..Default::default()
+                        },
+                    );
+                    let mut consumer = qaexchange::factor::FactorWalConsumer::new(
+                        rx,
+                        factor_wal,
+                        factor_config.batch_size,
+                    );
+                    std::thread::Builder::new()
+                        .name("factor-wal".to_string())
+                        .spawn(move || {
+                            consumer.run_with_flush_interval(std::time::Duration::from_secs(1))
+                        })
+                        .expect("Failed to spawn factor WAL thread");
+                    factor_persister = Some(Arc::new(persister));
+                    factor_store = Some(Arc::new(store));
+                    log::info!("✅ Factor store initialized at {}", factor_wal_dir);
+                }
+                Err(e) => log::error!("Failed to create factor store: {}", e),
+            }
+        }
+
+        // 1.3.3 启动K线Actor（订阅tick事件，独立处理K线聚合）
         log::info!("Starting KLine Actor...");
-        let kline_actor = qaexchange::market::KLineActor::new(
+        let mut kline_actor = qaexchange::market::KLineActor::new(
             market_broadcaster.clone(),
             kline_wal_manager.clone(),
-        )
+        );
         // .with_instruments(vec![])  // 空列表表示订阅所有合约
-        .start();
+        if let Some(persister) = factor_persister {
+            kline_actor = kline_actor
+                .with_factor_compute(true)
+                .with_factor_persister(persister);
+        }
+        let kline_actor_aggregators = kline_actor.aggregators();
+        let kline_actor = kline_actor.start();
         log::info!("✅ KLine Actor started (subscribed to tick events)");
 
         // 1.4 创建 iceoryx2 管理器（如果启用）
@@ -291,8 +438,9 @@ impl ExchangeServer {
         );
 
         // 2.1 为订单路由器创建市场数据存储（用于持久化 TickData 和 OrderBookSnapshot）
+        //     按合约分区：各合约独立 WAL/MemTable/SSTable，多品种写入互不阻塞
         let market_data_storage = Arc::new(
-            qaexchange::storage::hybrid::OltpHybridStorage::create(
+            qaexchange::storage::hybrid::OltpHybridStorage::create_partitioned(
                 "market_data",
                 qaexchange::storage::hybrid::oltp::OltpHybridConfig {
                     base_path: config.storage_path.clone(),
@@ -306,11 +454,103 @@ impl ExchangeServer {
             .expect("Failed to create market data storage"),
         );
 
+        // 2.2 分片撮合：合约按一致性哈希分散到多个撮合线程（未启用时使用单撮合引擎）
+        // 分片共用主撮合引擎的深度视图，行情查询路径照常读取主引擎快照
+        let sharded_matching = &perf_config.sharded_matching;
+        if sharded_matching.enabled {
+            match qaexchange::matching::sharded::ShardedMatchingEngine::with_depth_view(
+                sharded_matching.clone(),
+                matching_engine.depth_view(),
+            ) {
+                Ok(engine) => {
+                    order_router.set_sharded_engine(Arc::new(engine));
+                    log::info!(
+                        "Sharded matching enabled: shards={}, queue_capacity={}, bind_cores={}",
+                        sharded_matching.shard_count,
+                        sharded_matching.queue_capacity,
+                        sharded_matching.bind_cores
+                    );
+                }
+                Err(e) => log::warn!(
+                    "Invalid sharded matching config, using single engine: {}",
+                    e
+                ),
+            }
+        }
+
+        // 2.3 影子撮合：独立引擎重放每笔限价单/撤单并对比结果，只计算不生效
+        let shadow_mode = &perf_config.shadow_mode;
+        let shadow_engine = if shadow_mode.enabled {
+            let engine = Arc::new(ExchangeMatchingEngine::new());
+            match qaexchange::exchange::ShadowMode::start(engine.clone(), shadow_mode.clone()) {
+                Ok(shadow) => {
+                    order_router.set_shadow_mode(Arc::new(shadow));
+                    log::info!(
+                        "Shadow matching enabled: queue_capacity={}",
+                        shadow_mode.queue_capacity
+                    );
+                    Some(engine)
+                }
+                Err(e) => {
+                    log::warn!(
+                        "Failed to start shadow matching, shadow mode disabled: {}",
+                        e
+                    );
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        // 2.4 高性能撮合通道：灰度 use_high_perf_matching 命中的订单在独立撮合线程撮合
+        // （灰度规则可热更新，通道常驻；分片模式下各分片已独占撮合线程，不启用）
+        if order_router.get_sharded_engine().is_none() {
+            let config = qaexchange::matching::high_perf::HighPerfMatchingConfig {
+                enable_cpu_affinity: false,
+                ..Default::default()
+            };
+            match qaexchange::matching::high_perf::HighPerfOrderMatcher::start(&config) {
+                Ok(matcher) => {
+                    order_router.set_high_perf_matcher(Arc::new(matcher));
+                    log::info!(
+                        "High-perf matching channel started: queue_capacity={}",
+                        config.order_queue_capacity
+                    );
+                }
+                Err(e) => log::warn!(
+                    "Failed to start high-perf matching channel, gray release disabled: {}",
+                    e
+                ),
+            }
+        }
+
         // 3. 设置市场数据广播器和存储到订单路由器
         order_router.set_market_broadcaster(market_broadcaster.clone());
         order_router.set_storage(market_data_storage.clone());
         log::info!("✅ OrderRouter market data storage initialized");
 
+        // 3.1 成交事件总线：撮合成交直接发布，成交记录/逐笔广播/行情统计各自独立队列消费
+        let trade_bus = Arc::new(
+            TradeEventBus::new(perf_config.trade_bus.clone()).unwrap_or_else(|e| {
+                log::warn!("Invalid trade bus config, using defaults: {}", e);
+                TradeEventBus::default()
+            }),
+        );
+        trade_bus
+            .subscribe("trade_recorder", matching_engine.get_trade_recorder())
+            .expect("Failed to start trade recorder consumer");
+        trade_bus
+            .subscribe("market_broadcast", market_broadcaster.clone())
+            .expect("Failed to start trade broadcast consumer");
+        order_router.set_trade_bus(trade_bus.clone());
+        order_router.set_rejection_stats(Arc::new(
+            qaexchange::risk::RejectionStats::with_persist_dir(format!(
+                "{}/rejections",
+                config.storage_path
+            )),
+        ));
+
         // 启动批量刷新线程（性能优化：tick数据批量写入）
         order_router.start_batch_flush_worker();
         log::info!("✅ Batch flush worker started (10ms interval, max 1000 records/batch)");
@@ -321,6 +561,10 @@ impl ExchangeServer {
                 perf_config.priority_queue.low_queue_limit,
                 perf_config.priority_queue.critical_amount_threshold,
             );
+            order_router.set_priority_starvation_guard(
+                perf_config.priority_queue.starvation_limit,
+                perf_config.priority_queue.max_wait_ms,
+            );
             // 添加VIP用户
             if !perf_config.priority_queue.vip_users.is_empty() {
                 order_router.add_vip_users(perf_config.priority_queue.vip_users.clone());
@@ -335,22 +579,564 @@ impl ExchangeServer {
             );
         }
 
+        // 涨跌停板管理器：订单价格校验 + 日终动态扩板
+        let price_limit_manager = Arc::new(qaexchange::risk::PriceLimitManager::new(
+            instrument_registry.clone(),
+        ));
+        price_limit_manager.set_broadcaster(market_broadcaster.clone());
+        order_router.set_price_limit_manager(price_limit_manager.clone());
+
+        // 订单簿挂单数限制
+        let limits = &perf_config.orderbook_limits;
+        if limits.enabled {
+            let non_zero = |v: usize| if v == 0 { None } else { Some(v) };
+            order_router.set_book_limiter(Arc::new(qaexchange::matching::OrderBookLimiter::new(
+                qaexchange::matching::OrderBookLimitConfig {
+                    default_max_orders: non_zero(limits.default_max_orders),
+                    instrument_max_orders: limits
+                        .instruments
+                        .iter()
+                        .map(|(id, max)| (id.clone(), *max))
+                        .collect(),
+                    global_max_orders: non_zero(limits.global_max_orders),
+                    order_size_bytes: limits.order_size_bytes,
+                    alert_ratio: limits.alert_ratio,
+                },
+            )));
+            log::info!(
+                "Order book limits enabled: default={}, global={}, overrides={}",
+                limits.default_max_orders,
+                limits.global_max_orders,
+                limits.instruments.len()
+            );
+        }
+
+        // 账户下单/撤单/改单频率限制与报撤比管控
+        let rate_limit = &perf_config.order_rate_limit;
+        if rate_limit.enabled {
+            use qaexchange::risk::{OrderRateLimitConfig, OrderRateLimiter, RateLimitRule};
+            let to_rule = |r: &qaexchange::utils::config::AccountRateLimitSettings| {
+                RateLimitRule::new(r.window_ms, r.max_orders, r.max_cancels)
+                    .with_max_amends(r.max_amends.unwrap_or(r.max_cancels))
+            };
+            let rule = |r: &qaexchange::utils::config::AccountRateLimitSettings| {
+                if r.exempt {
+                    None
+                } else {
+                    Some(to_rule(r))
+                }
+            };
+            order_router.set_rate_limiter(Arc::new(OrderRateLimiter::new(OrderRateLimitConfig {
+                individual: rule(&rate_limit.individual),
+                institutional: rule(&rate_limit.institutional),
+                market_maker: rule(&rate_limit.market_maker),
+                exempt_accounts: rate_limit.exempt_accounts.iter().cloned().collect(),
+                account_overrides: rate_limit
+                    .accounts
+                    .iter()
+                    .map(|(id, r)| (id.clone(), to_rule(r)))
+                    .collect(),
+                cancel_ratio: rate_limit.cancel_ratio,
+            })));
+            log::info!(
+                "Order rate limit enabled: exempt={}, overrides={}, cancel_ratio={}",
+                rate_limit.exempt_accounts.len(),
+                rate_limit.accounts.len(),
+                rate_limit.cancel_ratio.enabled
+            );
+        }
+
+        // 重复订单检测：始终挂载（默认关闭），全局或按账户开启
+        let duplicate_order = &perf_config.duplicate_order;
+        order_router.set_duplicate_guard(Arc::new(qaexchange::risk::DuplicateOrderGuard::new(
+            duplicate_order.clone(),
+        )));
+        if duplicate_order.enabled || !duplicate_order.accounts.is_empty() {
+            log::info!(
+                "Duplicate order guard enabled: global={}, accounts={}, window={}ms, action={:?}",
+                duplicate_order.enabled,
+                duplicate_order.accounts.len(),
+                duplicate_order.window_ms,
+                duplicate_order.action
+            );
+        }
+
+        // 单用户单合约单日成交量限额（达限后拒绝新单或只允许平仓）
+        let volume_limit = &perf_config.trade_volume_limit;
+        if volume_limit.enabled {
+            match qaexchange::risk::TradeVolumeLimiter::new(volume_limit.clone()) {
+                Ok(limiter) => {
+                    order_router.set_volume_limiter(Arc::new(limiter));
+                    log::info!(
+                        "Trade volume limit enabled: instruments={}, exempt={}",
+                        volume_limit.instruments.len(),
+                        volume_limit.exempt_accounts.len()
+                    );
+                }
+                Err(e) => log::warn!("Invalid trade volume limit config, limit disabled: {}", e),
+            }
+        }
+
+        // 持仓集中度（盘中风控计算占比，订单路由限制超限账户开仓）
+        let position_concentration = match qaexchange::risk::PositionConcentration::new(
+            perf_config.position_concentration.clone(),
+        ) {
+            Ok(concentration) => Arc::new(concentration),
+            Err(e) => {
+                log::warn!(
+                    "Invalid position concentration config, monitor disabled: {}",
+                    e
+                );
+                Arc::new(qaexchange::risk::PositionConcentration::default())
+            }
+        };
+        order_router.set_position_concentration(position_concentration.clone());
+        if position_concentration.is_enabled() {
+            log::info!(
+                "Position concentration monitor enabled: instruments={}, action={:?}",
+                perf_config.position_concentration.instruments.len(),
+                perf_config.position_concentration.action
+            );
+        }
+
+        // 持仓批次明细（默认平仓方式、QIFI 导出开仓明细）
+        account_mgr
+            .position_lots()
+            .apply_config(&perf_config.position_lots);
+
+        // 价格笼子（限价单偏离实时参考价过远拒绝）
+        let price_band = &perf_config.price_band;
+        if price_band.enabled {
+            let risk_checker = order_router.get_risk_checker();
+            risk_checker.set_default_price_band(price_band.default.clone());
+            for (instrument_id, band) in &price_band.instruments {
+                risk_checker.set_price_band(instrument_id, Some(band.clone()));
+            }
+            log::info!(
+                "Price band enabled: default={:?}, overrides={}",
+                price_band.default.as_ref().map(|b| b.width),
+                price_band.instruments.len()
+            );
+        }
+
+        // 最小变动价位校验（拒单或规整到最近的 tick）
+        let price_tick_mode = perf_config.price_tick.mode;
+        order_router
+            .get_risk_checker()
+            .set_price_tick_mode(price_tick_mode);
+        log::info!("Price tick check mode: {:?}", price_tick_mode);
+
+        // 确定性模式只用于回放/测试，服务始终使用系统时钟
+        if perf_config.deterministic.enabled {
+            log::warn!(
+                "Deterministic mode is for replay/testing only (DeterministicExchange), ignored by the exchange server"
+            );
+        }
+
+        // 账户操作租约（多实例共享存储部署时防止同一账户被两个实例同时操作）
+        let lease_config = &perf_config.account_lease;
+        let account_lease = if lease_config.enabled {
+            let lease = Arc::new(
+                qaexchange::exchange::AccountLeaseManager::from_config(
+                    lease_config,
+                    &config.storage_path,
+                )
+                .expect("Failed to initialize account lease")
+                .with_account_manager(account_mgr.clone()),
+            );
+            log::info!(
+                "Account lease enabled: instance={}, ttl={}ms",
+                lease.instance_id(),
+                lease.ttl_ms()
+            );
+            order_router.set_account_lease(lease.clone());
+            Some(lease)
+        } else {
+            None
+        };
+
+        // 订单存活时长：启用持久化时 TTL 记录落盘，重启恢复订单后仍生效
+        order_router.set_time_service(time_service.clone());
+        if config.enable_storage {
+            let ttl_dir = std::path::Path::new(&config.storage_path).join("order_ttl");
+            match qaexchange::exchange::OrderTtlManager::with_persist_dir(&ttl_dir) {
+                Ok(manager) => order_router.set_order_ttl_manager(Arc::new(manager)),
+                Err(e) => log::error!("Failed to load order TTL from {:?}: {}", ttl_dir, e),
+            }
+        }
+
+        // 订单状态历史：启用持久化时状态变更追加落盘，重启后时间线仍可查询
+        if config.enable_storage {
+            let history_dir = std::path::Path::new(&config.storage_path).join("order_history");
+            match qaexchange::exchange::OrderHistoryTracker::with_persist_dir(&history_dir) {
+                Ok(tracker) => order_router.set_order_history(Arc::new(tracker)),
+                Err(e) => log::error!("Failed to load order history from {:?}: {}", history_dir, e),
+            }
+        }
+
+        // 新合约上市保护：保护时段内拒绝市价单，可选系统做市保护性挂单
+        let listing_protection = Arc::new(qaexchange::exchange::ListingProtection::new(
+            instrument_registry.clone(),
+        ));
+        order_router.set_listing_protection(listing_protection.clone());
+
+        // 极端行情熔断：波动超阈值进入集合竞价冷静期，到期自动恢复
+        let circuit_breaker = Arc::new(qaexchange::exchange::CircuitBreaker::default());
+        if let Err(e) = circuit_breaker.update_config(perf_config.circuit_breaker.clone()) {
+            log::warn!("Invalid circuit breaker config, breaker disabled: {}", e);
+        }
+        circuit_breaker.set_matching_engine(matching_engine.clone());
+        circuit_breaker.set_broadcaster(market_broadcaster.clone());
+        circuit_breaker.set_notification_broker(notification_broker.clone());
+        order_router.set_circuit_breaker(circuit_breaker.clone());
+
+        // 在途订单看门狗：撮合回应丢失时查询撮合引擎，确认丢失则拒单释放冻结
+        let order_watchdog = &perf_config.order_watchdog;
+        if order_watchdog.enabled {
+            match qaexchange::exchange::OrderWatchdog::new(order_watchdog.clone()) {
+                Ok(watchdog) => {
+                    order_router.set_order_watchdog(Arc::new(watchdog));
+                    log::info!(
+                        "Order watchdog enabled: ack_timeout={}ms, reconcile_interval={}s",
+                        order_watchdog.ack_timeout_ms,
+                        order_watchdog.reconcile_interval_secs
+                    );
+                }
+                Err(e) => log::warn!("Invalid order watchdog config, watchdog disabled: {}", e),
+            }
+        }
+
+        // 做市商义务考核：周期采样做市账户挂单，交易日结束生成考核报告
+        let market_maker = &perf_config.market_maker;
+        let market_maker_monitor = if market_maker.enabled {
+            match qaexchange::risk::MarketMakerMonitor::new(market_maker.clone()) {
+                Ok(monitor) => {
+                    let monitor = Arc::new(monitor);
+                    order_router.set_market_maker_monitor(monitor.clone());
+                    log::info!(
+                        "Market maker monitor enabled: makers={}, sample_interval={}ms",
+                        market_maker.makers.len(),
+                        market_maker.sample_interval_ms
+                    );
+                    Some(monitor)
+                }
+                Err(e) => {
+                    log::warn!("Invalid market maker config, monitor disabled: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        // 集合竞价指示价：竞价阶段推送虚拟开盘价，按交易日历的交易状态判断竞价阶段
+        let auction_indicator = &perf_config.auction_indicator;
+        if auction_indicator.enabled {
+            if order_router.get_trading_state_machine().is_none() {
+                let state_machine = Arc::new(qaexchange::exchange::TradingStateMachine::new());
+                state_machine.clone().start_auto_transition();
+                order_router.set_trading_state_machine(state_machine);
+            }
+            order_router.set_auction_indicator(Arc::new(
+                qaexchange::market::AuctionIndicatorPublisher::with_interval(
+                    market_broadcaster.clone(),
+                    std::time::Duration::from_millis(auction_indicator.interval_ms.max(1)),
+                ),
+            ));
+            log::info!(
+                "Auction indicator enabled: interval={}ms",
+                auction_indicator.interval_ms
+            );
+        }
+
         let order_router = Arc::new(order_router);
+        order_router.enable_matching_recovery();
+
+        // 竞价阶段定时器：推送节流期内积压的指示价，开盘时推送最终指示价
+        if auction_indicator.enabled {
+            let router = order_router.clone();
+            let period = std::time::Duration::from_millis(auction_indicator.interval_ms.max(1));
+            tokio::spawn(async move {
+                let mut ticker = tokio::time::interval(period);
+                loop {
+                    ticker.tick().await;
+                    router.flush_auction_indicators();
+                }
+            });
+        }
+        listing_protection.set_order_router(&order_router);
+        listing_protection.start_maker_task(std::time::Duration::from_secs(1));
+        if let Some(monitor) = market_maker_monitor {
+            monitor.set_order_router(&order_router);
+            monitor.start_sampler();
+        }
 
         // 4. 创建结算引擎
         let settlement_engine = Arc::new(SettlementEngine::new(account_mgr.clone()));
+        settlement_engine.set_time_service(time_service.clone());
         settlement_engine.set_order_router(order_router.clone());
+        settlement_engine.set_price_limit_manager(price_limit_manager.clone());
+
+        // 穿仓垫付：风险准备金账户与告警接收人
+        let risk_reserve = &perf_config.risk_reserve;
+        if !risk_reserve.account_id.is_empty() {
+            settlement_engine.set_risk_reserve_account(Some(risk_reserve.account_id.clone()));
+        }
+        settlement_engine.set_deficit_alert_recipients(risk_reserve.alert_recipients.clone());
+
+        // 闲置资金计息：按账户类型年化利率，结算时入账
+        if let Err(e) = perf_config.interest.validate() {
+            log::warn!("Invalid interest config ({}), interest accrual disabled", e);
+        } else {
+            settlement_engine.set_interest_config(perf_config.interest.clone());
+        }
+
+        // 结算任务：账户分片并行，分片进度落盘支持断点续跑
+        if let Err(e) = perf_config.settlement.validate() {
+            log::warn!("Invalid settlement task config ({}), using defaults", e);
+        } else {
+            let mut task_config = perf_config.settlement.clone();
+            if task_config.state_dir.is_empty() {
+                task_config.state_dir = format!("{}/settlement_tasks", config.storage_path);
+            }
+            settlement_engine.set_task_config(task_config);
+        }
+
+        // 4.1 价差单引擎：注入路由器与合约注册表，订阅行情触发两腿下单
+        {
+            let mut spread_engine = qaexchange::exchange::SPREAD_ORDER_ENGINE.write();
+            spread_engine.set_order_router(order_router.clone());
+            spread_engine.set_instrument_registry(instrument_registry.clone());
+        }
+        qaexchange::exchange::spread_order::start_spread_order_monitor(
+            market_broadcaster.clone(),
+            std::time::Duration::from_millis(500),
+        );
+
+        // 4.12 条件单引擎：注入路由器，行情/成交量/时钟分别驱动触发
+        qaexchange::exchange::CONDITIONAL_ORDER_ENGINE
+            .write()
+            .set_order_router(order_router.clone());
+        qaexchange::exchange::conditional_order::start_conditional_order_monitor(
+            market_broadcaster.clone(),
+            std::time::Duration::from_millis(500),
+        );
+
+        // 4.13 熔断监控：成交价驱动触发，时钟驱动冷静期恢复
+        if perf_config.circuit_breaker.enabled {
+            qaexchange::exchange::circuit_breaker::start_circuit_breaker_monitor(
+                circuit_breaker.clone(),
+                market_broadcaster.clone(),
+                std::time::Duration::from_secs(1),
+            );
+        }
+
+        // 4.15 大宗交易引擎：注入路由器（参考价、执行成交）
+        {
+            let mut block_engine = qaexchange::exchange::BLOCK_TRADE_ENGINE.write();
+            block_engine.set_order_router(order_router.clone());
+            block_engine.update_config(perf_config.block_trade.clone());
+        }
+
+        // 4.2 算法单引擎：按切片计划定时提交子单，推送执行进度
+        {
+            let mut algo_engine = qaexchange::exchange::ALGO_ORDER_ENGINE.write();
+            algo_engine.set_order_router(order_router.clone());
+            algo_engine.set_instrument_registry(instrument_registry.clone());
+            algo_engine.set_notification_broker(notification_broker.clone());
+        }
+        qaexchange::exchange::algo_order::start_algo_order_scheduler(
+            std::time::Duration::from_millis(500),
+        );
 
         // 5. 创建资金管理器
-        let capital_mgr = Arc::new(CapitalManager::new(account_mgr.clone()));
+        let mut capital_mgr = CapitalManager::new(account_mgr.clone());
+        if let Some(lease) = account_lease {
+            capital_mgr = capital_mgr.with_account_lease(lease);
+        }
+        let capital_mgr = Arc::new(capital_mgr);
 
         // 6. 创建风险监控器
-        let risk_monitor = Arc::new(RiskMonitor::new(account_mgr.clone()));
+        let mut risk_monitor = RiskMonitor::new(account_mgr.clone());
+        risk_monitor.set_position_concentration(position_concentration);
+        let risk_monitor = Arc::new(risk_monitor);
         settlement_engine.set_risk_monitor(risk_monitor.clone());
+        settlement_engine.set_capital_manager(capital_mgr.clone());
+        capital_mgr.set_risk_monitor(risk_monitor.clone());
+
+        // 6.1 风险历史采样：快照写入独立 WAL，启动时回放加载
+        let risk_history = &perf_config.risk_history;
+        risk_monitor
+            .risk_history()
+            .update_config(qaexchange::risk::RiskHistoryConfig {
+                enabled: risk_history.enabled,
+                sample_interval_ms: risk_history.sample_interval_ms,
+                retention_days: risk_history.retention_days,
+                max_query_points: risk_history.max_query_points,
+            });
+        if risk_history.enabled {
+            let risk_wal_dir = format!("{}/risk_history/wal", config.storage_path);
+            std::fs::create_dir_all(&risk_wal_dir).unwrap_or_else(|e| {
+                log::warn!("Failed to create risk history WAL directory: {}", e);
+            });
+            let risk_wal = Arc::new(qaexchange::storage::wal::WalManager::new(&risk_wal_dir));
+            match risk_monitor.risk_history().load_from_wal(&risk_wal) {
+                Ok(count) => log::info!("✅ Risk history loaded: {} snapshots", count),
+                Err(e) => log::warn!("Failed to load risk history: {}", e),
+            }
+            risk_monitor.risk_history().set_wal_manager(risk_wal);
+
+            // 未启动盘中监控时由该线程采样（与盘中检查共用采样点）
+            let monitor = risk_monitor.clone();
+            std::thread::spawn(move || loop {
+                std::thread::sleep(std::time::Duration::from_secs(1));
+                monitor.sample_risk_history();
+            });
+        }
+
+        // 6.1.1 强平预警阶梯：预警事件落盘到 JSON Lines，启动时加载
+        risk_monitor
+            .margin_call()
+            .update_config(perf_config.margin_call.clone());
+        {
+            let margin_call_dir = format!("{}/risk_history", config.storage_path);
+            std::fs::create_dir_all(&margin_call_dir).unwrap_or_else(|e| {
+                log::warn!("Failed to create margin call history directory: {}", e);
+            });
+            match risk_monitor
+                .margin_call()
+                .set_persist_path(format!("{}/margin_calls.jsonl", margin_call_dir))
+            {
+                Ok(count) => log::info!("✅ Margin call history loaded: {} events", count),
+                Err(e) => log::warn!("Failed to load margin call history: {}", e),
+            }
+        }
+
+        // 6.1.1.1 自动追保：未接入外部资金系统时使用模拟资金账户
+        risk_monitor
+            .auto_margin()
+            .update_config(perf_config.auto_margin.clone());
+        if perf_config.auto_margin.enabled && !risk_monitor.auto_margin().has_provider() {
+            log::warn!(
+                "Auto margin enabled without external funding provider, using simulated provider"
+            );
+            risk_monitor
+                .auto_margin()
+                .set_provider(Arc::new(SimulatedMarginProvider::new(
+                    perf_config.auto_margin.simulated_funding_balance,
+                )));
+        }
+
+        // 6.1.2 组合保证金：组合模式下盘中风控按对冲后的净风险计算风险度
+        if let Err(e) = risk_monitor
+            .portfolio_margin()
+            .update_config(perf_config.portfolio_margin.clone())
+        {
+            log::warn!(
+                "Invalid portfolio margin config ({}), using per-instrument margin",
+                e
+            );
+        }
+
+        // 6.1.3 账户止损：盘中监控未启动时由独立线程检查
+        risk_monitor.stop_loss().set_order_router(&order_router);
+        risk_monitor
+            .stop_loss()
+            .start_scanner(std::time::Duration::from_secs(1));
+
+        // 6.1.4 持仓集中度：盘中监控未启动时由独立线程检查
+        if risk_monitor.position_concentration().is_enabled() {
+            let monitor = risk_monitor.clone();
+            std::thread::spawn(move || loop {
+                std::thread::sleep(std::time::Duration::from_secs(1));
+                if !monitor.is_monitoring() {
+                    monitor.check_position_concentration();
+                }
+            });
+        }
+
+        // 6.2 追踪头部采样
+        qaexchange::observability::TRACE_SAMPLER
+            .update_config(perf_config.tracing_sampling.clone());
+        qaexchange::observability::PUSH_LATENCY.update_config(perf_config.push_latency.clone());
+
+        // 6.3 WebSocket 多终端登录策略
+        {
+            use qaexchange::service::websocket::session_registry::{
+                LoginPolicy, WS_SESSION_REGISTRY,
+            };
+            let policy_str = &perf_config.websocket.login_policy;
+            let policy = LoginPolicy::parse(policy_str).unwrap_or_else(|| {
+                log::warn!(
+                    "Unknown websocket login_policy '{}', using allow_multiple",
+                    policy_str
+                );
+                LoginPolicy::default()
+            });
+            WS_SESSION_REGISTRY.set_policy(policy);
+            for user_id in &perf_config.websocket.single_login_users {
+                WS_SESSION_REGISTRY.set_user_policy(user_id, Some(LoginPolicy::KickPrevious));
+            }
+        }
+
+        // 6.4 WebSocket 推送压缩
+        qaexchange::service::websocket::compression::WS_COMPRESSOR
+            .update_config(perf_config.websocket.compression.clone());
+
+        // 6.4.1 WebSocket 订阅配额（订阅组变更经广播器校验合约数上限）
+        {
+            use qaexchange::service::websocket::subscription_quota::WS_SUBSCRIPTION_QUOTA;
+            let quota_config = perf_config.websocket.subscription_quota.clone();
+            if let Err(e) = quota_config.validate() {
+                log::warn!("Invalid subscription quota config ({}), quota disabled", e);
+            } else {
+                WS_SUBSCRIPTION_QUOTA.update_config(quota_config);
+            }
+            market_broadcaster.set_subscription_limiter(WS_SUBSCRIPTION_QUOTA.limiter());
+        }
+
+        // 6.5 系统过载保护（订单队列取优先级队列 + 撮合分片队列，存储队列取成交事件总线积压，
+        //     通知队列取通知中心优先级队列；后台周期评估，无请求时也能降级/恢复）
+        {
+            use qaexchange::service::overload::{QueueKind, OVERLOAD_GUARD};
+            if let Err(e) = perf_config.overload.validate() {
+                log::warn!("Invalid overload config ({}), load shedding disabled", e);
+            } else {
+                OVERLOAD_GUARD.update_config(perf_config.overload.clone());
+            }
+            let router = order_router.clone();
+            OVERLOAD_GUARD.set_queue_probe(move || router.downstream_queue_depth());
+            let bus = trade_bus.clone();
+            OVERLOAD_GUARD.set_probe(QueueKind::Storage, move || bus.max_queue_len());
+            let broker = notification_broker.clone();
+            OVERLOAD_GUARD.set_probe(QueueKind::Notification, move || broker.queued_len());
+            tokio::spawn(async {
+                let mut ticker = tokio::time::interval(std::time::Duration::from_millis(200));
+                loop {
+                    ticker.tick().await;
+                    OVERLOAD_GUARD.tick();
+                }
+            });
+        }
+
+        // 6.6 功能灰度开关（新撮合路径等按账户放量，管理端可热更新）
+        if let Err(e) = qaexchange::exchange::FEATURE_GATE
+            .update_config(perf_config.feature_gate.clone())
+        {
+            log::warn!("Invalid feature gate config ({}), all features disabled", e);
+        }
 
         // 7. 创建市场数据服务（包含快照生成器）
+        // 行情异常值检测：行情服务与广播器共用，按涨跌停板区分真实涨跌停与数据错误
+        let anomaly_detector = Arc::new(qaexchange::market::PriceAnomalyDetector::new(
+            perf_config.market_anomaly.clone(),
+        ));
+        anomaly_detector.set_price_limits(price_limit_manager.clone());
+        market_broadcaster.set_anomaly_detector(anomaly_detector.clone());
+
         let market_data_service = {
-            let mut service = qaexchange::market::MarketDataService::new(matching_engine.clone());
+            let mut service = qaexchange::market::MarketDataService::new(matching_engine.clone())
+                .with_anomaly_detector(anomaly_detector);
 
             // 设置存储（用于市场数据恢复）
             service = service.with_storage(market_data_storage.clone());
@@ -358,6 +1144,7 @@ impl ExchangeServer {
             // 注入账户管理器和广播器
             service = service.with_account_manager(account_mgr.clone());
             service = service.with_broadcaster(market_broadcaster.clone());
+            service = service.with_instrument_registry(instrument_registry.clone());
 
             // 设置 iceoryx2（如果启用）
             if let Some(ref iceoryx_mgr) = iceoryx_manager {
@@ -378,14 +1165,11 @@ impl ExchangeServer {
         settlement_engine.set_market_data_service(market_data_service.clone());
         log::info!("✅ Market data service with snapshot generator initialized");
 
-        // 7.1 设置 market_data_service 到 trade_gateway（用于更新快照统计）
-        // 由于 trade_gateway 已经是 Arc，需要使用 unsafe 获取可变引用
-        // 安全性：此时 trade_gateway 只有一个引用（刚创建），可以安全修改
-        unsafe {
-            let trade_gateway_ptr = Arc::as_ptr(&trade_gateway) as *mut TradeGateway;
-            (*trade_gateway_ptr).set_market_data_service(market_data_service.clone());
-        }
-        log::info!("✅ Market data service connected to trade gateway");
+        // 7.1 行情统计与K线订阅成交事件总线
+        trade_bus
+            .subscribe("market_data", market_data_service.clone())
+            .expect("Failed to start market data consumer");
+        log::info!("✅ Market data service subscribed to trade bus");
 
         log::info!("✅ Core components initialized");
         log::info!("✅ Market data broadcaster initialized");
@@ -394,6 +1178,76 @@ impl ExchangeServer {
         log::info!("✅ Risk monitor initialized");
         log::info!("✅ User manager initialized");
 
+        // 8. 紧急停机协调器：停机时保存账户/订单簿快照并 flush 存储
+        let emergency = Arc::new(
+            EmergencyShutdown::new(
+                account_mgr.clone(),
+                order_router.clone(),
+                matching_engine.clone(),
+                &config.storage_path,
+            )
+            .with_storage("market_data", market_data_storage.clone())
+            .with_storage("users", user_storage.clone())
+            .with_timeout(std::time::Duration::from_secs(30)),
+        );
+
+        // 9. 交易日管理：开盘标记，测试环境下支持重置到开盘状态
+        let trading_day = Arc::new(
+            TradingDayManager::new(
+                config.environment.clone(),
+                account_mgr.clone(),
+                order_router.clone(),
+                matching_engine.clone(),
+                &config.storage_path,
+            )
+            .with_capital_manager(capital_mgr.clone())
+            .with_klines(market_data_service.kline_aggregators())
+            .with_klines(kline_actor_aggregators)
+            .with_time_service(time_service.clone()),
+        );
+
+        // 9.1 测试环境时间控制：快进虚拟时间，按顺序触发结算与交易日切换
+        let time_control = Arc::new(
+            TimeController::new(config.environment.clone(), settlement_engine.clone())
+                .with_config(perf_config.time_control.clone())
+                .with_order_router(order_router.clone())
+                .with_matching_engine(matching_engine.clone())
+                .with_trading_day_manager(trading_day.clone())
+                .with_time_service(time_service.clone()),
+        );
+
+        // 10. 重查询/导出异步任务（独立 worker 池，结果签名下载）
+        let task_manager = if perf_config.async_task.enabled {
+            let task_dir = if !perf_config.async_task.result_dir.is_empty() {
+                std::path::PathBuf::from(&perf_config.async_task.result_dir)
+            } else if config.enable_storage {
+                std::path::PathBuf::from(format!("{}/tasks", config.storage_path))
+            } else {
+                std::env::temp_dir().join("qaexchange_tasks")
+            };
+            match qaexchange::service::async_task::AsyncTaskManager::new(
+                perf_config.async_task.clone(),
+                &task_dir,
+            ) {
+                Ok(manager) => {
+                    let manager = Arc::new(manager);
+                    qaexchange::service::http::data_query::register_export_task(
+                        &manager,
+                        account_mgr.clone(),
+                        matching_engine.get_trade_recorder(),
+                    );
+                    manager.start();
+                    Some(manager)
+                }
+                Err(e) => {
+                    log::warn!("Failed to start async task service: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
         Self {
             config,
             account_mgr,
@@ -406,15 +1260,28 @@ impl ExchangeServer {
             settlement_engine,
             capital_mgr,
             risk_monitor,
+            price_limit_manager,
             user_mgr,
             user_storage,
             market_data_storage,
+            export_log: None,
             storage_stats: None,
             conversion_mgr: None,
             iceoryx_manager,
             kline_actor,
             kline_wal_manager,
+            factor_store,
+            olap_partition_config: perf_config.olap_partition.clone(),
+            retention_config: perf_config.retention.clone(),
             snapshot_generator_handle: None,
+            emergency,
+            trading_day,
+            time_control,
+            listing_protection,
+            shadow_engine,
+            notification_store,
+            task_manager,
+            server_handles: parking_lot::Mutex::new(Vec::new()),
         }
     }
 
@@ -422,7 +1289,9 @@ impl ExchangeServer {
     fn init_instruments(&self) {
         log::info!("Initializing instruments...");
 
-        let now = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let now = qaexchange::utils::time_service::now_local()
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string();
 
         // 注册合约：沪深300股指期货
         let instruments = vec![
@@ -432,6 +1301,8 @@ impl ExchangeServer {
                 instrument_type: InstrumentType::IndexFuture,
                 exchange: "CFFEX".to_string(),
                 contract_multiplier: 300,
+                trading_unit: "手".to_string(),
+                quote_unit: "点".to_string(),
                 price_tick: 0.2,
                 margin_rate: 0.12,
                 commission_rate: 0.0001,
@@ -449,6 +1320,8 @@ impl ExchangeServer {
                 instrument_type: InstrumentType::IndexFuture,
                 exchange: "CFFEX".to_string(),
                 contract_multiplier: 300,
+                trading_unit: "手".to_string(),
+                quote_unit: "点".to_string(),
                 price_tick: 0.2,
                 margin_rate: 0.12,
                 commission_rate: 0.0001,
@@ -466,6 +1339,8 @@ impl ExchangeServer {
                 instrument_type: InstrumentType::IndexFuture,
                 exchange: "CFFEX".to_string(),
                 contract_multiplier: 200,
+                trading_unit: "手".to_string(),
+                quote_unit: "点".to_string(),
                 price_tick: 0.2,
                 margin_rate: 0.12,
                 commission_rate: 0.0001,
@@ -483,6 +1358,8 @@ impl ExchangeServer {
                 instrument_type: InstrumentType::IndexFuture,
                 exchange: "CFFEX".to_string(),
                 contract_multiplier: 300,
+                trading_unit: "手".to_string(),
+                quote_unit: "点".to_string(),
                 price_tick: 0.2,
                 margin_rate: 0.12,
                 commission_rate: 0.0001,
@@ -516,10 +1393,41 @@ impl ExchangeServer {
                 .register_instrument(inst.instrument_id.clone(), init_price)
                 .expect("Failed to register instrument to matching engine");
 
+            // 启用分片撮合时订单在分片引擎撮合，同步注册
+            if let Some(sharded) = self.order_router.get_sharded_engine() {
+                match sharded.register_instrument(inst.instrument_id.clone(), init_price) {
+                    Ok(shard_id) => log::debug!("  {} -> shard {}", inst.instrument_id, shard_id),
+                    Err(e) => log::error!(
+                        "Failed to register {} to sharded matching engine: {}",
+                        inst.instrument_id,
+                        e
+                    ),
+                }
+            }
+            if let Some(ref shadow_engine) = self.shadow_engine {
+                if let Err(e) =
+                    shadow_engine.register_instrument(inst.instrument_id.clone(), init_price)
+                {
+                    log::error!(
+                        "Failed to register {} to shadow matching engine: {}",
+                        inst.instrument_id,
+                        e
+                    );
+                }
+            }
+
             // 设置初始结算价
             self.settlement_engine
                 .set_settlement_price(inst.instrument_id.clone(), init_price);
 
+            // 涨跌停基准价（昨结算价）
+            if let Err(e) = self
+                .price_limit_manager
+                .set_reference_price(&inst.instrument_id, init_price)
+            {
+                log::warn!("Failed to set price limit reference for {}: {}", inst.instrument_id, e);
+            }
+
             // 设置快照生成器的昨收盘价（用于涨跌幅计算）
             self.market_data_service
                 .set_pre_close(&inst.instrument_id, init_price);
@@ -567,9 +1475,25 @@ impl ExchangeServer {
             batch_size: 100,
             batch_timeout_ms: 10,
             buffer_size: 10000,
+            amount_decimals: self.account_mgr.fixed_ledger().scale().decimals(),
         };
 
-        let (subscriber, storage_sender, stats_handle) = StorageSubscriber::new(storage_config);
+        let export_config = storage_config.storage_config.clone();
+        let (mut subscriber, storage_sender, stats_handle) = StorageSubscriber::new(storage_config);
+
+        // 订单/成交/账户变更同时写入导出流（全局 sequence）
+        match qaexchange::storage::export::ExportLog::open(
+            export_config,
+            qaexchange::storage::export::DEFAULT_TAIL_CAPACITY,
+        ) {
+            Ok(export_log) => {
+                let export_log = Arc::new(export_log);
+                subscriber = subscriber.with_export_log(export_log.clone());
+                self.export_log = Some(export_log);
+                log::info!("✅ Export log attached to storage subscriber");
+            }
+            Err(e) => log::warn!("⚠️  Failed to open export log, /ws/export disabled: {}", e),
+        }
 
         // 保存统计信息句柄
         self.storage_stats = Some(stats_handle);
@@ -622,8 +1546,33 @@ impl ExchangeServer {
             source_retention_secs: 3600, // 保留 1 小时
         };
 
+        let kline_olap_dir = storage_base.join("klines").join("olap");
         match ConversionManager::new(storage_base, metadata_path, scheduler_config, worker_config) {
-            Ok(mut manager) => {
+            Ok(manager) => {
+                // K线 WAL → Parquet 转换（instrument/period 分区，供 klines 表 SQL 查询）
+                let mut manager = match KLineConverter::new(
+                    self.kline_wal_manager.clone(),
+                    kline_olap_dir,
+                    KLineConversionConfig::default(),
+                ) {
+                    Ok(converter) => manager.with_kline_converter(Arc::new(converter)),
+                    Err(e) => {
+                        log::error!("Failed to create K-line converter: {}", e);
+                        manager
+                    }
+                };
+                // 因子 WAL → Parquet 转换（factor/instrument 分区，供因子历史查询与 factors 表）
+                if let Some(ref store) = self.factor_store {
+                    manager = manager.with_factor_store(store.clone());
+                }
+                // 月份分区：超过月龄的分区定期再压缩为 cold
+                manager = manager.with_partition_config(self.olap_partition_config.clone());
+                // 保留策略：按数据类别与年龄定期清理过期的 SSTable/Parquet
+                manager = manager.with_retention_config(self.retention_config.clone());
+                // 转换失败、保留策略清理结果作为系统事件推送到运维频道
+                if let Some(broker) = self.account_mgr.notification_broker() {
+                    manager = manager.with_notification_broker(broker.clone());
+                }
                 manager.start();
                 log::info!("✅ OLAP conversion system started");
                 log::info!("   Workers: 2");
@@ -654,17 +1603,26 @@ impl ExchangeServer {
             // Phase 14: 数据查询存储组件 @yutiansut @quantaxis
             market_data_storage: Some(self.market_data_storage.clone()),
             kline_wal_manager: Some(self.kline_wal_manager.clone()),
+            factor_store: self.factor_store.clone(),
             // 服务器启动时间 @yutiansut @quantaxis
             server_start_time: chrono::Utc::now(),
             // WebSocket 连接计数器 @yutiansut @quantaxis
             ws_connection_count: std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0)),
+            // 账户止损 @yutiansut @quantaxis
+            stop_loss: Some(self.risk_monitor.stop_loss().clone()),
+            // 用户消息中心 @yutiansut @quantaxis
+            notification_store: self.notification_store.clone(),
+            // 异步任务（重查询/导出）@yutiansut @quantaxis
+            task_manager: self.task_manager.clone(),
         });
 
         // 创建市场数据服务（解耦：业务逻辑与网络层分离）
         // 传递 market_data_storage 以支持从 WAL 恢复历史行情
         let mut market_service =
             qaexchange::market::MarketDataService::new(self.matching_engine.clone())
-                .with_storage(self.market_data_storage.clone());
+                .with_storage(self.market_data_storage.clone())
+                .with_instrument_registry(self.instrument_registry.clone())
+                .with_anomaly_detector(self.market_data_service.anomaly_detector());
 
         // 如果启用了 iceoryx2，将 manager 传递给 MarketDataService
         if let Some(ref manager) = self.iceoryx_manager {
@@ -676,6 +1634,13 @@ impl ExchangeServer {
             instrument_registry: self.instrument_registry.clone(),
             settlement_engine: self.settlement_engine.clone(),
             account_mgr: self.account_mgr.clone(),
+            storage_maintenance: Arc::new(StorageMaintenance::new(
+                self.market_data_storage.clone(),
+            )),
+            emergency: self.emergency.clone(),
+            trading_day: self.trading_day.clone(),
+            time_control: self.time_control.clone(),
+            listing_protection: self.listing_protection.clone(),
         };
         let admin_data = web::Data::new(admin_state);
 
@@ -701,6 +1666,7 @@ impl ExchangeServer {
                 .app_data(web::Data::new(kline_actor_addr.clone())) // KLineActor 地址
                 .app_data(admin_data.clone())
                 .app_data(management_data.clone())
+                .wrap(qaexchange::service::http::load_shedding::LoadShedding::default())
                 .wrap(middleware::Logger::default())
                 .wrap(middleware::Compress::default())
                 .wrap(
@@ -747,14 +1713,24 @@ impl ExchangeServer {
     async fn start_websocket_server(self: Arc<Self>) -> io::Result<actix_web::dev::Server> {
         log::info!("Starting WebSocket server at {}...", self.config.ws_address);
 
-        let ws_server = Arc::new(WebSocketServer::new(
+        let mut ws_server = WebSocketServer::new(
             self.order_router.clone(),
             self.account_mgr.clone(),
             self.user_mgr.clone(),
             self.trade_gateway.clone(),
             self.market_broadcaster.clone(),
             self.kline_actor.clone(),
-        ));
+        );
+        if let Some(ref export_log) = self.export_log {
+            ws_server = ws_server.with_export_log(export_log.clone());
+        }
+        if let Some(broker) = self.account_mgr.notification_broker() {
+            ws_server = ws_server.with_notification_broker(broker.clone());
+        }
+        if let Some(ref store) = self.notification_store {
+            ws_server = ws_server.with_notification_store(store.clone());
+        }
+        let ws_server = Arc::new(ws_server);
 
         let bind_address = self.config.ws_address.clone();
 
@@ -770,7 +1746,14 @@ impl ExchangeServer {
                     "/ws/diff",
                     web::get().to(qaexchange::service::websocket::ws_diff_route),
                 )
-                .route("/health", web::get().to(|| async { "OK" }))
+                .route(
+                    "/ws/export",
+                    web::get().to(qaexchange::service::websocket::ws_export_route),
+                )
+                .route(
+                    "/health",
+                    web::get().to(qaexchange::service::http::handlers::health_check),
+                )
         })
         .bind(&bind_address)?
         .run();
@@ -784,6 +1767,7 @@ impl ExchangeServer {
             "   DIFF Protocol:   ws://{}/ws/diff?user_id=<USER_ID> (Recommended)",
             bind_address
         );
+        log::info!("   Export Stream:   ws://{}/ws/export?token=<JWT> (ExportData)", bind_address);
         log::info!("   Market Data: Subscribe to channels [orderbook, tick, last_price]");
 
         Ok(server)
@@ -943,6 +1927,56 @@ impl ExchangeServer {
         }
     }
 
+    /// 紧急停机后的恢复校验：用停机时保存的订单簿核对重建结果
+    fn verify_emergency_recovery(&self) {
+        match EmergencyShutdown::verify_recovery(
+            std::path::Path::new(&self.config.storage_path),
+            &self.matching_engine,
+        ) {
+            Ok(Some(record)) => {
+                log::warn!(
+                    "Last emergency shutdown: reason={}, started_at={}, errors={:?}",
+                    record.reason,
+                    record.started_at,
+                    record.errors
+                );
+            }
+            Ok(None) => {}
+            Err(mismatches) => {
+                log::error!(
+                    "❌ State rebuilt after emergency shutdown differs from saved orderbooks: {:?}",
+                    mismatches
+                );
+            }
+        }
+    }
+
+    /// 紧急停机：拒绝新订单、等待在途、保存快照、flush 存储，然后优雅关闭 HTTP/WebSocket
+    async fn emergency_shutdown(self: &Arc<Self>, reason: String) -> ShutdownRecord {
+        let emergency = self.emergency.clone();
+        let record = match tokio::task::spawn_blocking(move || emergency.execute(&reason)).await {
+            Ok(record) => record,
+            Err(e) => {
+                log::error!("Emergency shutdown task failed: {}", e);
+                ShutdownRecord::default()
+            }
+        };
+
+        // 优雅关闭 HTTP / WebSocket（等待连接处理完成，超时则放弃）
+        let handles: Vec<_> = self.server_handles.lock().drain(..).collect();
+        for handle in handles {
+            let stop = handle.stop(true);
+            if tokio::time::timeout(std::time::Duration::from_secs(10), stop)
+                .await
+                .is_err()
+            {
+                log::warn!("Server did not stop gracefully within 10s");
+            }
+        }
+
+        record
+    }
+
     /// 运行服务器
     async fn run(mut self) -> io::Result<()> {
         // 1. 初始化合约
@@ -963,6 +1997,21 @@ impl ExchangeServer {
         // 3.6. 从账户的 dailyorders 恢复订单索引到 order_router @yutiansut @quantaxis
         self.order_router.restore_orders_from_accounts();
 
+        // 3.6.1 订单恢复后再启动 TTL 扫描（停机期间到期的订单立即撤销）与在途订单看门狗
+        self.order_router
+            .start_order_ttl_scanner(std::time::Duration::from_millis(200));
+        self.order_router.start_order_watchdog();
+
+        // 3.7. 上次紧急停机的恢复校验
+        self.verify_emergency_recovery();
+
+        // 3.8. 开盘标记（仅测试环境：恢复完成后的状态即为当日开盘状态）
+        if self.trading_day.ensure_opening_mark().is_some() {
+            if let Some(machine) = self.order_router.get_trading_state_machine() {
+                self.trading_day.watch_state_machine(&machine);
+            }
+        }
+
         // 4. 启动存储订阅器
         let _storage_handle = self.start_storage_subscriber();
 
@@ -990,8 +2039,29 @@ impl ExchangeServer {
         // 8. 打印启动信息
         print_startup_banner(&server.config);
 
-        // 8. 等待服务器
-        tokio::try_join!(async { http_server.await }, async { ws_server.await })?;
+        server
+            .server_handles
+            .lock()
+            .extend([http_server.handle(), ws_server.handle()]);
+
+        // 8. 等待服务器；管理端触发紧急停机时保存状态后退出
+        tokio::select! {
+            result = async {
+                tokio::try_join!(async { http_server.await }, async { ws_server.await })
+            } => {
+                result?;
+            }
+            reason = server.emergency.wait_requested() => {
+                let record = server.emergency_shutdown(reason).await;
+                log::warn!(
+                    "🛑 Exchange halted: {} (accounts={}, orderbooks={}, storages={})",
+                    record.reason,
+                    record.accounts_saved,
+                    record.orderbooks_saved,
+                    record.storages_flushed
+                );
+            }
+        }
 
         Ok(())
     }
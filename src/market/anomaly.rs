@@ -0,0 +1,473 @@
+//! 行情异常值检测
+//!
+//! @yutiansut @quantaxis
+//!
+//! 撮合或数据异常产生的离谱价格（0、负数、极大值）推送给客户端会造成混乱，
+//! 成交价与盘口价在推送/查询前先过一遍检测：
+//! - 零值、负值、非有限值：数据错误（零值/负值规则可单独关闭）
+//! - 已知涨跌停板：超出板价（含 `limit_tolerance_ratio` 容差）为数据错误；
+//!   触及板价是真实异动（涨跌停），照常推送，只计数不告警
+//! - 未知涨跌停板：偏离参考价（昨结算/昨收）超过 `max_deviation_ratio` 视为可疑
+//! - 处理策略：`drop` 不推送（默认），`flag` 照常推送但记录告警
+//!
+//! 可疑数据写 warn 日志并保留最近 `max_events` 条告警，经 `/api/admin/market/anomalies` 查询。
+//! 行情服务消费成交时检测并记录告警；广播器复用同一规则只做过滤，避免同一笔成交重复告警。
+
+use dashmap::DashMap;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crate::risk::price_limit::LimitDirection;
+use crate::risk::PriceLimitManager;
+use crate::utils::timestamp::now_millis;
+
+/// 价格比较容差（浮点误差）
+const PRICE_EPSILON: f64 = 1e-8;
+
+/// 可疑数据处理策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnomalyAction {
+    /// 不推送
+    Drop,
+    /// 照常推送并告警
+    Flag,
+}
+
+/// 异常值检测配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarketAnomalyConfig {
+    /// 是否启用
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// 零价格视为异常
+    #[serde(default = "default_enabled")]
+    pub reject_zero: bool,
+    /// 负价格视为异常
+    #[serde(default = "default_enabled")]
+    pub reject_negative: bool,
+    /// 未知涨跌停板时允许偏离参考价的比例（0 表示不检查）
+    #[serde(default = "default_max_deviation_ratio")]
+    pub max_deviation_ratio: f64,
+    /// 已知涨跌停板时板价外的容差比例
+    #[serde(default)]
+    pub limit_tolerance_ratio: f64,
+    /// 处理策略
+    #[serde(default = "default_action")]
+    pub action: AnomalyAction,
+    /// 保留的告警条数
+    #[serde(default = "default_max_events")]
+    pub max_events: usize,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+fn default_max_deviation_ratio() -> f64 {
+    0.2
+}
+fn default_action() -> AnomalyAction {
+    AnomalyAction::Drop
+}
+fn default_max_events() -> usize {
+    200
+}
+
+impl Default for MarketAnomalyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            reject_zero: default_enabled(),
+            reject_negative: default_enabled(),
+            max_deviation_ratio: default_max_deviation_ratio(),
+            limit_tolerance_ratio: 0.0,
+            action: default_action(),
+            max_events: default_max_events(),
+        }
+    }
+}
+
+/// 价格来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PriceSource {
+    /// 成交价
+    Trade,
+    /// 盘口价
+    OrderBook,
+}
+
+/// 异常类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnomalyKind {
+    Zero,
+    Negative,
+    NonFinite,
+    /// 超出涨跌停板
+    LimitBreach,
+    /// 偏离参考价过大
+    Deviation,
+}
+
+/// 检测结论
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PriceVerdict {
+    Normal,
+    /// 触及涨跌停（真实异动）
+    LimitMove(LimitDirection),
+    Suspicious(AnomalyKind),
+}
+
+impl PriceVerdict {
+    pub fn is_suspicious(&self) -> bool {
+        matches!(self, Self::Suspicious(_))
+    }
+}
+
+/// 可疑数据告警
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnomalyEvent {
+    pub instrument_id: String,
+    pub source: PriceSource,
+    pub price: f64,
+    pub kind: AnomalyKind,
+    /// 参考价（昨结算/昨收）
+    pub reference_price: Option<f64>,
+    /// 当日涨跌停板 (跌停价, 涨停价)
+    pub limit_band: Option<(f64, f64)>,
+    pub action: AnomalyAction,
+    /// 检测时间（毫秒）
+    pub timestamp: i64,
+}
+
+/// 检测统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AnomalyStats {
+    pub checked: u64,
+    pub suspicious: u64,
+    pub dropped: u64,
+    pub flagged: u64,
+    /// 触及涨跌停的真实异动次数
+    pub limit_moves: u64,
+}
+
+/// 行情异常值检测器（行情服务与广播器共用）
+pub struct PriceAnomalyDetector {
+    config: RwLock<MarketAnomalyConfig>,
+    /// 参考价（昨结算/昨收），未设置时取涨跌停管理器的基准价
+    references: DashMap<String, f64>,
+    price_limits: RwLock<Option<Arc<PriceLimitManager>>>,
+    events: Mutex<VecDeque<AnomalyEvent>>,
+    checked: AtomicU64,
+    suspicious: AtomicU64,
+    dropped: AtomicU64,
+    flagged: AtomicU64,
+    limit_moves: AtomicU64,
+}
+
+impl Default for PriceAnomalyDetector {
+    fn default() -> Self {
+        Self::new(MarketAnomalyConfig::default())
+    }
+}
+
+impl PriceAnomalyDetector {
+    pub fn new(config: MarketAnomalyConfig) -> Self {
+        Self {
+            config: RwLock::new(config),
+            references: DashMap::new(),
+            price_limits: RwLock::new(None),
+            events: Mutex::new(VecDeque::new()),
+            checked: AtomicU64::new(0),
+            suspicious: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+            flagged: AtomicU64::new(0),
+            limit_moves: AtomicU64::new(0),
+        }
+    }
+
+    pub fn config(&self) -> MarketAnomalyConfig {
+        self.config.read().clone()
+    }
+
+    pub fn update_config(&self, config: MarketAnomalyConfig) {
+        *self.config.write() = config;
+    }
+
+    /// 设置涨跌停板管理器（区分涨跌停异动与数据错误）
+    pub fn set_price_limits(&self, price_limits: Arc<PriceLimitManager>) {
+        *self.price_limits.write() = Some(price_limits);
+    }
+
+    /// 设置参考价（昨结算/昨收）
+    pub fn set_reference_price(&self, instrument_id: &str, price: f64) {
+        if price > 0.0 && price.is_finite() {
+            self.references.insert(instrument_id.to_string(), price);
+        }
+    }
+
+    /// 参考价
+    pub fn reference_price(&self, instrument_id: &str) -> Option<f64> {
+        self.references
+            .get(instrument_id)
+            .map(|price| *price)
+            .or_else(|| {
+                self.price_limits
+                    .read()
+                    .as_ref()
+                    .and_then(|limits| limits.get_state(instrument_id))
+                    .map(|state| state.reference_price)
+                    .filter(|price| *price > 0.0)
+            })
+    }
+
+    fn limit_band(&self, instrument_id: &str) -> Option<(f64, f64)> {
+        self.price_limits
+            .read()
+            .as_ref()
+            .and_then(|limits| limits.limit_prices(instrument_id))
+    }
+
+    /// 判断价格（不记录告警）
+    pub fn classify(&self, instrument_id: &str, price: f64) -> PriceVerdict {
+        let config = self.config.read();
+        if !price.is_finite() {
+            return PriceVerdict::Suspicious(AnomalyKind::NonFinite);
+        }
+        if price < 0.0 && config.reject_negative {
+            return PriceVerdict::Suspicious(AnomalyKind::Negative);
+        }
+        if price == 0.0 && config.reject_zero {
+            return PriceVerdict::Suspicious(AnomalyKind::Zero);
+        }
+
+        if let Some((lower, upper)) = self.limit_band(instrument_id) {
+            let tolerance = (upper - lower).abs() / 2.0 * config.limit_tolerance_ratio;
+            if price > upper + tolerance + PRICE_EPSILON
+                || price < lower - tolerance - PRICE_EPSILON
+            {
+                return PriceVerdict::Suspicious(AnomalyKind::LimitBreach);
+            }
+            if price >= upper - PRICE_EPSILON {
+                return PriceVerdict::LimitMove(LimitDirection::Up);
+            }
+            if price <= lower + PRICE_EPSILON {
+                return PriceVerdict::LimitMove(LimitDirection::Down);
+            }
+            return PriceVerdict::Normal;
+        }
+
+        if config.max_deviation_ratio > 0.0 {
+            if let Some(reference) = self.reference_price(instrument_id) {
+                if (price - reference).abs() / reference > config.max_deviation_ratio {
+                    return PriceVerdict::Suspicious(AnomalyKind::Deviation);
+                }
+            }
+        }
+        PriceVerdict::Normal
+    }
+
+    /// 是否推送（不记录告警，供广播器过滤）
+    pub fn should_publish(&self, instrument_id: &str, price: f64) -> bool {
+        let action = {
+            let config = self.config.read();
+            if !config.enabled {
+                return true;
+            }
+            config.action
+        };
+        action == AnomalyAction::Flag || !self.classify(instrument_id, price).is_suspicious()
+    }
+
+    /// 检测价格并记录统计/告警，返回是否推送
+    pub fn inspect(&self, instrument_id: &str, source: PriceSource, price: f64) -> bool {
+        let (action, max_events) = {
+            let config = self.config.read();
+            if !config.enabled {
+                return true;
+            }
+            (config.action, config.max_events)
+        };
+        self.checked.fetch_add(1, Ordering::Relaxed);
+
+        let kind = match self.classify(instrument_id, price) {
+            PriceVerdict::Normal => return true,
+            PriceVerdict::LimitMove(direction) => {
+                self.limit_moves.fetch_add(1, Ordering::Relaxed);
+                log::info!(
+                    "[MarketAnomaly] {} {:?} price {} at limit {:?}",
+                    instrument_id,
+                    source,
+                    price,
+                    direction
+                );
+                return true;
+            }
+            PriceVerdict::Suspicious(kind) => kind,
+        };
+
+        self.suspicious.fetch_add(1, Ordering::Relaxed);
+        match action {
+            AnomalyAction::Drop => self.dropped.fetch_add(1, Ordering::Relaxed),
+            AnomalyAction::Flag => self.flagged.fetch_add(1, Ordering::Relaxed),
+        };
+        let event = AnomalyEvent {
+            instrument_id: instrument_id.to_string(),
+            source,
+            price,
+            kind,
+            reference_price: self.reference_price(instrument_id),
+            limit_band: self.limit_band(instrument_id),
+            action,
+            timestamp: now_millis(),
+        };
+        log::warn!(
+            "⚠️  [MarketAnomaly] Suspicious {:?} price {} for {} ({:?}, reference={:?}, band={:?}), {:?}",
+            source,
+            price,
+            instrument_id,
+            kind,
+            event.reference_price,
+            event.limit_band,
+            action
+        );
+
+        let mut events = self.events.lock();
+        events.push_back(event);
+        while events.len() > max_events {
+            events.pop_front();
+        }
+        action == AnomalyAction::Flag
+    }
+
+    /// 最近的告警（按时间倒序）
+    pub fn recent_events(&self, limit: usize) -> Vec<AnomalyEvent> {
+        self.events
+            .lock()
+            .iter()
+            .rev()
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    pub fn stats(&self) -> AnomalyStats {
+        AnomalyStats {
+            checked: self.checked.load(Ordering::Relaxed),
+            suspicious: self.suspicious.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            flagged: self.flagged.load(Ordering::Relaxed),
+            limit_moves: self.limit_moves.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::instrument_registry::{InstrumentInfo, InstrumentType};
+    use crate::exchange::InstrumentRegistry;
+
+    #[test]
+    fn test_zero_negative_and_deviation_rules() {
+        let detector = PriceAnomalyDetector::default();
+        // 无参考价时只检查零值/负值/非有限值
+        assert_eq!(
+            detector.classify("IF2501", 9_999_999.0),
+            PriceVerdict::Normal
+        );
+        detector.set_reference_price("IF2501", 3800.0);
+
+        assert_eq!(
+            detector.classify("IF2501", 0.0),
+            PriceVerdict::Suspicious(AnomalyKind::Zero)
+        );
+        assert_eq!(
+            detector.classify("IF2501", -1.0),
+            PriceVerdict::Suspicious(AnomalyKind::Negative)
+        );
+        assert_eq!(
+            detector.classify("IF2501", f64::NAN),
+            PriceVerdict::Suspicious(AnomalyKind::NonFinite)
+        );
+        assert_eq!(
+            detector.classify("IF2501", 9_999_999.0),
+            PriceVerdict::Suspicious(AnomalyKind::Deviation)
+        );
+        assert_eq!(detector.classify("IF2501", 4500.0), PriceVerdict::Normal);
+
+        // 规则可配：关闭零值检查、放宽偏离阈值
+        detector.update_config(MarketAnomalyConfig {
+            reject_zero: false,
+            max_deviation_ratio: 0.0,
+            ..Default::default()
+        });
+        assert_eq!(detector.classify("IF2501", 0.0), PriceVerdict::Normal);
+        assert_eq!(
+            detector.classify("IF2501", 9_999_999.0),
+            PriceVerdict::Normal
+        );
+    }
+
+    #[test]
+    fn test_limit_move_is_not_anomaly() {
+        let registry = Arc::new(InstrumentRegistry::new());
+        let mut info = InstrumentInfo::new(
+            "cu2501".to_string(),
+            "沪铜2501".to_string(),
+            InstrumentType::CommodityFuture,
+            "SHFE".to_string(),
+        );
+        info.limit_up_rate = 0.1;
+        info.limit_down_rate = 0.1;
+        info.price_tick = 1.0;
+        registry.register(info).unwrap();
+        let limits = Arc::new(PriceLimitManager::new(registry));
+        limits.set_reference_price("cu2501", 1000.0).unwrap();
+
+        let detector = PriceAnomalyDetector::default();
+        detector.set_price_limits(limits);
+        assert_eq!(detector.reference_price("cu2501"), Some(1000.0));
+
+        // 涨跌停价是真实异动，板内正常，板外是数据错误
+        assert_eq!(
+            detector.classify("cu2501", 1100.0),
+            PriceVerdict::LimitMove(LimitDirection::Up)
+        );
+        assert_eq!(
+            detector.classify("cu2501", 900.0),
+            PriceVerdict::LimitMove(LimitDirection::Down)
+        );
+        assert_eq!(detector.classify("cu2501", 1050.0), PriceVerdict::Normal);
+        assert_eq!(
+            detector.classify("cu2501", 1101.0),
+            PriceVerdict::Suspicious(AnomalyKind::LimitBreach)
+        );
+
+        assert!(detector.inspect("cu2501", PriceSource::Trade, 1100.0));
+        assert!(!detector.inspect("cu2501", PriceSource::Trade, 50_000.0));
+        let stats = detector.stats();
+        assert_eq!(
+            (stats.limit_moves, stats.suspicious, stats.dropped),
+            (1, 1, 1)
+        );
+        let events = detector.recent_events(10);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, AnomalyKind::LimitBreach);
+        assert_eq!(events[0].limit_band, Some((900.0, 1100.0)));
+
+        // flag 策略：照常推送但记录告警
+        detector.update_config(MarketAnomalyConfig {
+            action: AnomalyAction::Flag,
+            ..Default::default()
+        });
+        assert!(detector.inspect("cu2501", PriceSource::OrderBook, 0.0));
+        assert!(detector.should_publish("cu2501", 0.0));
+        assert_eq!(detector.stats().flagged, 1);
+        assert_eq!(detector.recent_events(1)[0].action, AnomalyAction::Flag);
+    }
+}
@@ -0,0 +1,340 @@
+//! 集合竞价虚拟开盘价（参考价）推送
+//!
+//! 竞价阶段每次订单簿变更后，按最大成交量原则计算虚拟集合竞价价格与虚拟匹配量
+//! （复用 `AuctionCalculator`，只计算不成交），通过 `MarketDataEvent::AuctionIndicator` 推送。
+//!
+//! - 同一合约推送频率限制为 200ms 一次，期间的变更只更新最新计算结果
+//! - 竞价阶段定时器周期调用 `OrderRouter::flush_auction_indicators`，推送节流期内积压的变更
+//! - 竞价结束时按最终订单簿强制推送一次指示价（与 `ExchangeMatchingEngine::open_auction`
+//!   同一算法，即实际开盘价），随后停止推送
+//!
+//! @yutiansut @quantaxis
+
+use super::MarketDataBroadcaster;
+use crate::matching::auction::{AuctionCalculator, AuctionResult};
+use crate::matching::engine::InstrumentAsset;
+use crate::matching::Orderbook;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// 默认推送间隔
+pub const DEFAULT_AUCTION_INDICATOR_INTERVAL: Duration = Duration::from_millis(200);
+
+/// 竞价指示价推送配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuctionIndicatorConfig {
+    /// 是否启用（仅服务启动时读取；启用时下单/撤单按交易日历的交易状态校验）
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// 同一合约推送间隔（毫秒），同时作为竞价阶段定时器周期
+    #[serde(default = "default_interval_ms")]
+    pub interval_ms: u64,
+}
+
+fn default_interval_ms() -> u64 {
+    DEFAULT_AUCTION_INDICATOR_INTERVAL.as_millis() as u64
+}
+
+impl Default for AuctionIndicatorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_ms: default_interval_ms(),
+        }
+    }
+}
+
+/// 单个合约的竞价指示状态
+#[derive(Debug, Clone, Default)]
+struct IndicatorState {
+    /// 最近一次计算结果（可能尚未推送）
+    latest: Option<AuctionResult>,
+    /// 最近一次推送时间
+    last_push: Option<Instant>,
+    /// 是否有未推送的变更
+    pending: bool,
+}
+
+/// 集合竞价指示价推送器
+pub struct AuctionIndicatorPublisher {
+    broadcaster: Arc<MarketDataBroadcaster>,
+    min_interval: Duration,
+    /// 处于竞价阶段的合约 (instrument_id -> state)
+    states: DashMap<String, IndicatorState>,
+}
+
+impl AuctionIndicatorPublisher {
+    pub fn new(broadcaster: Arc<MarketDataBroadcaster>) -> Self {
+        Self::with_interval(broadcaster, DEFAULT_AUCTION_INDICATOR_INTERVAL)
+    }
+
+    pub fn with_interval(broadcaster: Arc<MarketDataBroadcaster>, min_interval: Duration) -> Self {
+        Self {
+            broadcaster,
+            min_interval,
+            states: DashMap::new(),
+        }
+    }
+
+    /// 从订单簿提取买卖挂单 [(价格, 数量)]
+    pub fn collect_orders(
+        orderbook: &Orderbook<InstrumentAsset>,
+    ) -> (Vec<(f64, f64)>, Vec<(f64, f64)>) {
+        let bids = orderbook
+            .bid_queue
+            .get_sorted_orders()
+            .map(|orders| orders.iter().map(|o| (o.price, o.volume)).collect())
+            .unwrap_or_default();
+        let asks = orderbook
+            .ask_queue
+            .get_sorted_orders()
+            .map(|orders| orders.iter().map(|o| (o.price, o.volume)).collect())
+            .unwrap_or_default();
+        (bids, asks)
+    }
+
+    /// 订单簿变更回调：重新计算虚拟开盘价，按频率限制推送
+    ///
+    /// 返回最新的指示结果（无法撮合时为 None）
+    pub fn on_orderbook_changed(
+        &self,
+        instrument_id: &str,
+        buy_orders: &[(f64, f64)],
+        sell_orders: &[(f64, f64)],
+        reference_price: Option<f64>,
+    ) -> Option<AuctionResult> {
+        let result = AuctionCalculator::calculate_auction_price_with_reference(
+            buy_orders,
+            sell_orders,
+            reference_price,
+        );
+
+        let mut state = self.states.entry(instrument_id.to_string()).or_default();
+        state.latest = result.clone();
+        state.pending = true;
+
+        let due = state
+            .last_push
+            .map_or(true, |t| t.elapsed() >= self.min_interval);
+        if due {
+            self.push(instrument_id, state.value_mut());
+        }
+
+        result
+    }
+
+    /// 推送已到期的待推送指示（由定时器周期调用，保证节流期间的最后一次变更也能送达）
+    pub fn flush_pending(&self) -> usize {
+        let mut pushed = 0;
+        for mut entry in self.states.iter_mut() {
+            let due = entry
+                .last_push
+                .map_or(true, |t| t.elapsed() >= self.min_interval);
+            if entry.pending && due {
+                let instrument_id = entry.key().clone();
+                self.push(&instrument_id, entry.value_mut());
+                pushed += 1;
+            }
+        }
+        pushed
+    }
+
+    /// 竞价结束：按最终订单簿强制推送一次指示价并停止该合约的推送
+    ///
+    /// 返回值即集合竞价开盘价/开盘成交量
+    pub fn end_auction(
+        &self,
+        instrument_id: &str,
+        buy_orders: &[(f64, f64)],
+        sell_orders: &[(f64, f64)],
+        reference_price: Option<f64>,
+    ) -> Option<AuctionResult> {
+        let result = AuctionCalculator::calculate_auction_price_with_reference(
+            buy_orders,
+            sell_orders,
+            reference_price,
+        );
+
+        if self.states.remove(instrument_id).is_some() {
+            if let Some(ref r) = result {
+                self.broadcaster
+                    .broadcast_auction_indicator(instrument_id.to_string(), r);
+            }
+            log::info!(
+                "Auction ended for {}: indicator={:?}",
+                instrument_id,
+                result.as_ref().map(|r| (r.auction_price, r.auction_volume))
+            );
+        }
+
+        result
+    }
+
+    /// 合约是否处于竞价指示推送中
+    pub fn is_active(&self, instrument_id: &str) -> bool {
+        self.states.contains_key(instrument_id)
+    }
+
+    /// 处于竞价指示推送中的合约
+    pub fn active_instruments(&self) -> Vec<String> {
+        self.states.iter().map(|s| s.key().clone()).collect()
+    }
+
+    /// 获取最新指示结果
+    pub fn get_indicator(&self, instrument_id: &str) -> Option<AuctionResult> {
+        self.states.get(instrument_id).and_then(|s| s.latest.clone())
+    }
+
+    fn push(&self, instrument_id: &str, state: &mut IndicatorState) {
+        if let Some(ref result) = state.latest {
+            self.broadcaster
+                .broadcast_auction_indicator(instrument_id.to_string(), result);
+        }
+        state.last_push = Some(Instant::now());
+        state.pending = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::market::MarketDataEvent;
+
+    fn drain_indicators(
+        rx: &crossbeam::channel::Receiver<MarketDataEvent>,
+    ) -> Vec<(f64, f64)> {
+        let mut out = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            if let MarketDataEvent::AuctionIndicator {
+                auction_price,
+                auction_volume,
+                ..
+            } = event
+            {
+                out.push((auction_price, auction_volume));
+            }
+        }
+        out
+    }
+
+    /// 测试推送频率限制：200ms 内多次变更只推送一次
+    #[test]
+    fn test_indicator_throttled() {
+        let broadcaster = Arc::new(MarketDataBroadcaster::new());
+        let rx = broadcaster.subscribe("s1".to_string(), vec![], vec!["auction".to_string()]);
+        let publisher = AuctionIndicatorPublisher::new(broadcaster);
+
+        let mut bids = vec![(100.0, 10.0)];
+        let asks = vec![(99.0, 5.0)];
+        for i in 0..10 {
+            bids.push((100.0 + i as f64 * 0.1, 1.0));
+            publisher.on_orderbook_changed("IF2501", &bids, &asks, Some(100.0));
+        }
+
+        assert_eq!(drain_indicators(&rx).len(), 1);
+        assert!(publisher.is_active("IF2501"));
+
+        // 节流期内的最后一次变更在间隔到期后由 flush 推送
+        assert_eq!(publisher.flush_pending(), 0);
+        std::thread::sleep(DEFAULT_AUCTION_INDICATOR_INTERVAL);
+        assert_eq!(publisher.flush_pending(), 1);
+        assert_eq!(drain_indicators(&rx).len(), 1);
+    }
+
+    /// 竞价结束瞬间推送的指示价等于撮合引擎实际开盘价与开盘成交量，之后停止推送
+    #[test]
+    fn test_final_indicator_equals_opening_price() {
+        use crate::matching::engine::ExchangeMatchingEngine;
+        use crate::matching::{orders, OrderDirection};
+
+        let engine = ExchangeMatchingEngine::new();
+        engine
+            .register_instrument("IF2501".to_string(), 100.0)
+            .unwrap();
+        engine
+            .get_orderbook("IF2501")
+            .unwrap()
+            .write()
+            .start_pre_auction();
+
+        let broadcaster = Arc::new(MarketDataBroadcaster::new());
+        let rx = broadcaster.subscribe("s1".to_string(), vec![], vec![]);
+        let publisher = AuctionIndicatorPublisher::new(broadcaster);
+
+        let asset = InstrumentAsset::from_code("IF2501");
+        let book_orders = || {
+            AuctionIndicatorPublisher::collect_orders(
+                &engine.get_orderbook("IF2501").unwrap().read(),
+            )
+        };
+        // 竞价期申报：每笔申报后刷新指示价（节流期内的变更只更新最新结果）
+        for (ts, (direction, price, volume)) in [
+            (OrderDirection::BUY, 102.0, 100.0),
+            (OrderDirection::SELL, 98.0, 100.0),
+            (OrderDirection::BUY, 100.0, 50.0),
+            (OrderDirection::SELL, 100.0, 50.0),
+            (OrderDirection::BUY, 101.0, 80.0),
+            (OrderDirection::SELL, 101.0, 30.0),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            engine
+                .process_order(
+                    "IF2501",
+                    orders::new_limit_order_request(asset, direction, price, volume, ts as i64 + 1),
+                )
+                .unwrap();
+            let (bids, asks) = book_orders();
+            publisher.on_orderbook_changed("IF2501", &bids, &asks, engine.get_prev_close("IF2501"));
+        }
+        let latest = publisher.get_indicator("IF2501").unwrap();
+        drain_indicators(&rx);
+
+        // 开盘：按最终订单簿推送指示价并停止推送，随后撮合引擎执行开盘
+        let (bids, asks) = book_orders();
+        let final_indicator = publisher
+            .end_auction("IF2501", &bids, &asks, engine.get_prev_close("IF2501"))
+            .unwrap();
+        let open = engine.open_auction("IF2501").unwrap();
+        let opening = open.result.unwrap();
+
+        // 推送值即引擎实际开盘价与开盘成交量
+        let pushed = drain_indicators(&rx);
+        assert_eq!(pushed, vec![(opening.auction_price, opening.auction_volume)]);
+        assert_eq!(final_indicator.auction_price, opening.auction_price);
+        assert_eq!(final_indicator.auction_volume, opening.auction_volume);
+        assert_eq!(latest.auction_price, opening.auction_price);
+
+        // 开盘撮合明细与开盘结果一致，开盘后订单簿不再交叉
+        assert!(open.fills.iter().all(|f| f.price == opening.auction_price));
+        let filled: f64 = open.fills.iter().map(|f| f.volume).sum();
+        assert_eq!(filled, opening.auction_volume);
+        let depth = engine.get_depth_snapshot("IF2501").unwrap();
+        assert_eq!(depth.last_price, opening.auction_price);
+        if let (Some(bid), Some(ask)) = (depth.best_bid(), depth.best_ask()) {
+            assert!(bid < ask);
+        }
+
+        // 竞价结束后不再推送
+        assert!(!publisher.is_active("IF2501"));
+        assert_eq!(publisher.flush_pending(), 0);
+        assert!(drain_indicators(&rx).is_empty());
+    }
+
+    #[test]
+    fn test_no_cross_no_indicator() {
+        let broadcaster = Arc::new(MarketDataBroadcaster::new());
+        let rx = broadcaster.subscribe("s1".to_string(), vec![], vec![]);
+        let publisher = AuctionIndicatorPublisher::new(broadcaster);
+
+        let result =
+            publisher.on_orderbook_changed("IF2501", &[(90.0, 1.0)], &[(100.0, 1.0)], None);
+        assert!(result.is_none());
+        assert!(publisher.get_indicator("IF2501").is_none());
+        assert!(drain_indicators(&rx).is_empty());
+    }
+}
@@ -8,52 +8,26 @@
 //! - **慢订阅者检测**: 自动降级或断开慢速消费者
 //! - **指标统计**: 实时监控广播质量
 //! - **零阻塞广播**: 使用 `try_send` 避免阻塞生产者
+//! - **原子换订阅**: 订阅内容原地替换，与投递互斥，切换以投递序号为界（见 `subscription` 模块）
 //!
 //! @author @yutiansut @quantaxis
 
+use super::anomaly::PriceAnomalyDetector;
+use super::subscription::{SubscriptionGroupRegistry, SubscriptionLimiter};
 use super::PriceLevel;
+use crate::exchange::instrument_code::InstrumentCode;
+use crate::exchange::InstrumentRegistry;
+use crate::observability::push_latency::{PushKind, PUSH_LATENCY};
 use crate::ExchangeError;
 use crossbeam::channel::{bounded, Receiver, Sender, TrySendError};
 use dashmap::DashMap;
+use parking_lot::{Mutex, RwLock};
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Instant;
 
-/// 从合约ID中提取基础合约代码（去除交易所前缀）
-/// @yutiansut @quantaxis
-///
-/// 支持两种格式：
-/// - "CFFEX.IF2501" -> "IF2501"
-/// - "IF2501" -> "IF2501"
-fn extract_base_instrument_id(instrument_id: &str) -> &str {
-    if let Some(pos) = instrument_id.find('.') {
-        &instrument_id[pos + 1..]
-    } else {
-        instrument_id
-    }
-}
-
-/// 检查两个合约ID是否匹配（支持带/不带交易所前缀的格式）
-/// @yutiansut @quantaxis
-///
-/// 匹配规则：
-/// - "IF2501" matches "IF2501" (精确匹配)
-/// - "CFFEX.IF2501" matches "IF2501" (带前缀匹配不带前缀)
-/// - "IF2501" matches "CFFEX.IF2501" (不带前缀匹配带前缀)
-/// - "CFFEX.IF2501" matches "CFFEX.IF2501" (精确匹配)
-fn instrument_matches(subscribed: &str, broadcast: &str) -> bool {
-    // 精确匹配
-    if subscribed == broadcast {
-        return true;
-    }
-    // 比较基础合约代码
-    let base_subscribed = extract_base_instrument_id(subscribed);
-    let base_broadcast = extract_base_instrument_id(broadcast);
-    base_subscribed == base_broadcast
-}
-
 /// 市场数据事件类型
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -82,6 +56,12 @@ pub enum MarketDataEvent {
         volume: f64,
         direction: String, // "buy" or "sell"
         timestamp: i64,
+        /// 事件产生时刻（纳秒，用于推送延迟测量）
+        #[serde(default)]
+        origin_ts: i64,
+        /// 撮合批次号（同一次撮合的逐笔成交相同，0 表示未关联批次；不含账户/订单信息）
+        #[serde(default)]
+        match_batch_id: i64,
     },
 
     /// 最新价更新
@@ -97,6 +77,9 @@ pub enum MarketDataEvent {
         period: i32, // HQChart周期格式 (0=日线, 4=1分钟, 5=5分钟等)
         kline: super::kline::KLine,
         timestamp: i64,
+        /// 事件产生时刻（纳秒，用于推送延迟测量）
+        #[serde(default)]
+        origin_ts: i64,
     },
 
     /// 因子更新事件（K线完成后触发）
@@ -109,6 +92,62 @@ pub enum MarketDataEvent {
         period: i32,
         timestamp: i64,
     },
+
+    /// 集合竞价虚拟开盘价（参考价）指示
+    /// @yutiansut @quantaxis
+    AuctionIndicator {
+        instrument_id: String,
+        /// 虚拟集合竞价价格
+        auction_price: f64,
+        /// 虚拟匹配量
+        auction_volume: f64,
+        /// 虚拟未匹配买量
+        unfilled_buy_volume: f64,
+        /// 虚拟未匹配卖量
+        unfilled_sell_volume: f64,
+        timestamp: i64,
+    },
+
+    /// 涨跌停板调整（扩板/恢复）通知
+    /// @yutiansut @quantaxis
+    PriceLimitChanged {
+        instrument_id: String,
+        /// 次日涨停价
+        upper_limit: f64,
+        /// 次日跌停价
+        lower_limit: f64,
+        limit_up_rate: f64,
+        limit_down_rate: f64,
+        /// 同步调整后的保证金率
+        margin_rate: f64,
+        /// 扩板级数（0 表示恢复基础幅度）
+        level: u32,
+        timestamp: i64,
+    },
+
+    /// 合约交易状态变化（熔断进入冷静期 / 恢复连续交易）
+    /// @yutiansut @quantaxis
+    TradingStateChanged {
+        instrument_id: String,
+        /// 新的交易状态（如 AuctionOrder、ContinuousTrading）
+        state: String,
+        /// 变化原因（如 circuit_breaker_triggered）
+        reason: String,
+        /// 预计恢复连续交易时间（毫秒）
+        resume_at: Option<i64>,
+        timestamp: i64,
+    },
+}
+
+impl MarketDataEvent {
+    /// 参与推送延迟测量的事件类别与产生时刻（tick、K线）
+    pub fn push_origin(&self) -> Option<(PushKind, i64)> {
+        match self {
+            MarketDataEvent::Tick { origin_ts, .. } => Some((PushKind::Tick, *origin_ts)),
+            MarketDataEvent::KLineFinished { origin_ts, .. } => Some((PushKind::Kline, *origin_ts)),
+            _ => None,
+        }
+    }
 }
 
 /// 广播器配置
@@ -195,12 +234,37 @@ struct Subscription {
     instruments: Vec<String>,
     /// 订阅的频道（orderbook, tick, etc.）
     channels: Vec<String>,
+    /// 暂停推送（订阅组全部删除时保留通道与投递序号）
+    suspended: bool,
+}
+
+impl Subscription {
+    fn new(instruments: Vec<String>, channels: Vec<String>) -> Self {
+        Self {
+            instruments,
+            channels,
+            suspended: false,
+        }
+    }
+
+    /// 是否订阅了该合约与频道（空列表表示全部，未归一的代码按 `InstrumentCode::matches` 匹配）
+    fn matches(&self, instrument_id: &str, channel: &str) -> bool {
+        !self.suspended
+            && (self.instruments.is_empty()
+                || self
+                    .instruments
+                    .iter()
+                    .any(|id| InstrumentCode::matches(id, instrument_id)))
+            && (self.channels.is_empty() || self.channels.iter().any(|ch| ch == channel))
+    }
 }
 
 /// 订阅者完整信息
+///
+/// 投递与换订阅都在 `subscription` 锁内进行，`stats.sent_count` 即该订阅者的投递序号
 struct SubscriberInfo {
     sender: Sender<MarketDataEvent>,
-    subscription: Subscription,
+    subscription: Arc<Mutex<Subscription>>,
     stats: Arc<SubscriberStats>,
 }
 
@@ -277,6 +341,14 @@ pub struct MarketDataBroadcaster {
     stats: Arc<BroadcastStats>,
     /// 待清理的慢订阅者列表
     pending_cleanup: Arc<DashMap<String, ()>>,
+    /// 命名订阅组
+    subscription_groups: SubscriptionGroupRegistry,
+    /// 合约注册表（订阅代码归一）
+    instrument_registry: RwLock<Option<Arc<InstrumentRegistry>>>,
+    /// 订阅配额检查
+    subscription_limiter: RwLock<Option<SubscriptionLimiter>>,
+    /// 行情异常值检测（可疑成交价不广播）
+    anomaly_detector: RwLock<Option<Arc<PriceAnomalyDetector>>>,
 }
 
 impl MarketDataBroadcaster {
@@ -292,9 +364,68 @@ impl MarketDataBroadcaster {
             subscribers: Arc::new(DashMap::new()),
             stats: Arc::new(BroadcastStats::default()),
             pending_cleanup: Arc::new(DashMap::new()),
+            subscription_groups: SubscriptionGroupRegistry::default(),
+            instrument_registry: RwLock::new(None),
+            subscription_limiter: RwLock::new(None),
+            anomaly_detector: RwLock::new(None),
+        }
+    }
+
+    /// 设置合约注册表：订阅组中的合约代码先归一为注册代码
+    pub fn set_instrument_registry(&self, registry: Arc<InstrumentRegistry>) {
+        *self.instrument_registry.write() = Some(registry);
+    }
+
+    /// 设置行情异常值检测器（与行情服务共用，告警由行情服务记录）
+    pub fn set_anomaly_detector(&self, detector: Arc<PriceAnomalyDetector>) {
+        *self.anomaly_detector.write() = Some(detector);
+    }
+
+    /// 成交价是否可广播（未设置检测器时全部放行）
+    pub fn should_publish_price(&self, instrument_id: &str, price: f64) -> bool {
+        match self.anomaly_detector.read().as_ref() {
+            Some(detector) => detector.should_publish(instrument_id, price),
+            None => true,
+        }
+    }
+
+    /// 订阅合约代码归一（`SHFE.cu2501` 与旧代码 `cu2501` 归为同一合约），未设置注册表时原样返回
+    pub fn resolve_instruments(
+        &self,
+        instruments: &[String],
+    ) -> Result<Vec<String>, ExchangeError> {
+        match self.instrument_registry.read().as_ref() {
+            Some(registry) => instruments
+                .iter()
+                .map(|code| registry.normalize(code))
+                .collect(),
+            None => Ok(instruments.to_vec()),
         }
     }
 
+    /// 设置订阅配额检查（订阅组变更前校验变更后的合约数）
+    pub fn set_subscription_limiter(&self, limiter: SubscriptionLimiter) {
+        *self.subscription_limiter.write() = Some(limiter);
+    }
+
+    /// 订阅配额检查，未设置时放行
+    pub fn check_subscription_limit(
+        &self,
+        subscriber_id: &str,
+        before: usize,
+        after: usize,
+    ) -> Result<(), ExchangeError> {
+        match self.subscription_limiter.read().as_ref() {
+            Some(limiter) => limiter(subscriber_id, before, after),
+            None => Ok(()),
+        }
+    }
+
+    /// 获取订阅组登记表
+    pub fn subscription_groups(&self) -> &SubscriptionGroupRegistry {
+        &self.subscription_groups
+    }
+
     /// 订阅市场数据
     ///
     /// # 参数
@@ -313,16 +444,13 @@ impl MarketDataBroadcaster {
         // 使用有界通道
         let (sender, receiver) = bounded(self.config.channel_capacity);
 
-        let subscription = Subscription {
-            instruments: instruments.clone(),
-            channels: channels.clone(),
-        };
+        let subscription = Subscription::new(instruments.clone(), channels.clone());
 
         let stats = Arc::new(SubscriberStats::new());
 
         let info = SubscriberInfo {
             sender,
-            subscription,
+            subscription: Arc::new(Mutex::new(subscription)),
             stats: stats.clone(),
         };
 
@@ -348,16 +476,13 @@ impl MarketDataBroadcaster {
     ) -> (Receiver<MarketDataEvent>, Arc<SubscriberStats>) {
         let (sender, receiver) = bounded(self.config.channel_capacity);
 
-        let subscription = Subscription {
-            instruments: instruments.clone(),
-            channels: channels.clone(),
-        };
+        let subscription = Subscription::new(instruments.clone(), channels.clone());
 
         let stats = Arc::new(SubscriberStats::new());
 
         let info = SubscriberInfo {
             sender,
-            subscription,
+            subscription: Arc::new(Mutex::new(subscription)),
             stats: stats.clone(),
         };
 
@@ -383,6 +508,7 @@ impl MarketDataBroadcaster {
             );
         }
         self.pending_cleanup.remove(subscriber_id);
+        self.subscription_groups.remove(subscriber_id);
     }
 
     /// 更新订阅
@@ -392,18 +518,60 @@ impl MarketDataBroadcaster {
         instruments: Vec<String>,
         channels: Vec<String>,
     ) -> Result<(), ExchangeError> {
-        if let Some(mut entry) = self.subscribers.get_mut(subscriber_id) {
-            entry.subscription.instruments = instruments;
-            entry.subscription.channels = channels;
-            // 重置连续失败计数
-            entry.stats.consecutive_failures.store(0, Ordering::Relaxed);
-            Ok(())
-        } else {
-            Err(ExchangeError::InternalError(format!(
-                "Subscriber not found: {}",
-                subscriber_id
-            )))
-        }
+        self.replace_subscription(subscriber_id, instruments, channels)
+            .map(|_| ())
+    }
+
+    /// 是否存在订阅者
+    pub fn has_subscriber(&self, subscriber_id: &str) -> bool {
+        self.subscribers.contains_key(subscriber_id)
+    }
+
+    /// 原子替换订阅内容（保留原通道）
+    ///
+    /// 返回切换序号：切换前已投递给该订阅者的数据条数，此后投递的数据均按新订阅过滤
+    pub fn replace_subscription(
+        &self,
+        subscriber_id: &str,
+        instruments: Vec<String>,
+        channels: Vec<String>,
+    ) -> Result<u64, ExchangeError> {
+        self.swap_subscription(subscriber_id, |subscription| {
+            *subscription = Subscription::new(instruments, channels);
+        })
+    }
+
+    /// 暂停推送（保留通道与投递序号），返回切换序号
+    pub fn suspend_subscription(&self, subscriber_id: &str) -> Result<u64, ExchangeError> {
+        self.swap_subscription(subscriber_id, |subscription| {
+            subscription.suspended = true;
+        })
+    }
+
+    /// 订阅者当前投递序号（已投递数据条数）
+    pub fn delivered_seq(&self, subscriber_id: &str) -> Option<u64> {
+        self.subscribers
+            .get(subscriber_id)
+            .map(|entry| entry.stats.sent_count.load(Ordering::Relaxed))
+    }
+
+    fn swap_subscription<F>(&self, subscriber_id: &str, f: F) -> Result<u64, ExchangeError>
+    where
+        F: FnOnce(&mut Subscription),
+    {
+        let (subscription, stats) = self
+            .subscribers
+            .get(subscriber_id)
+            .map(|entry| (entry.subscription.clone(), entry.stats.clone()))
+            .ok_or_else(|| {
+                ExchangeError::InternalError(format!("Subscriber not found: {}", subscriber_id))
+            })?;
+
+        let mut subscription = subscription.lock();
+        f(&mut subscription);
+        // 重置连续失败计数
+        stats.consecutive_failures.store(0, Ordering::Relaxed);
+        Ok(stats.sent_count.load(Ordering::Relaxed))
     }
 
     /// 获取订阅者统计信息
@@ -459,6 +627,9 @@ impl MarketDataBroadcaster {
             MarketDataEvent::LastPrice { instrument_id, .. } => instrument_id,
             MarketDataEvent::KLineFinished { instrument_id, .. } => instrument_id,
             MarketDataEvent::FactorUpdate { instrument_id, .. } => instrument_id,
+            MarketDataEvent::AuctionIndicator { instrument_id, .. } => instrument_id,
+            MarketDataEvent::PriceLimitChanged { instrument_id, .. } => instrument_id,
+            MarketDataEvent::TradingStateChanged { instrument_id, .. } => instrument_id,
         };
 
         let channel = match &event {
@@ -469,6 +640,9 @@ impl MarketDataBroadcaster {
             MarketDataEvent::LastPrice { .. } => "last_price",
             MarketDataEvent::KLineFinished { .. } => "kline_finished",
             MarketDataEvent::FactorUpdate { .. } => "factor",
+            MarketDataEvent::AuctionIndicator { .. } => "auction",
+            MarketDataEvent::PriceLimitChanged { .. } => "price_limit",
+            MarketDataEvent::TradingStateChanged { .. } => "trading_state",
         };
 
         let mut sent_count = 0u64;
@@ -479,20 +653,10 @@ impl MarketDataBroadcaster {
             let subscriber_id = entry.key();
             let info = entry.value();
 
-            // 检查是否订阅了该合约（支持带/不带交易所前缀的格式匹配）
+            // 检查是否订阅了该合约与频道，持锁投递保证换订阅原子性
             // @yutiansut @quantaxis
-            let subscribed_instrument = info.subscription.instruments.is_empty()
-                || info
-                    .subscription
-                    .instruments
-                    .iter()
-                    .any(|id| instrument_matches(id, instrument_id));
-
-            // 检查是否订阅了该频道
-            let subscribed_channel = info.subscription.channels.is_empty()
-                || info.subscription.channels.iter().any(|ch| ch == channel);
-
-            if subscribed_instrument && subscribed_channel {
+            let subscription = info.subscription.lock();
+            if subscription.matches(instrument_id, channel) {
                 match info.sender.try_send(event.clone()) {
                     Ok(()) => {
                         info.stats.record_success();
@@ -520,6 +684,12 @@ impl MarketDataBroadcaster {
             }
         }
 
+        if sent_count > 0 {
+            if let Some((kind, origin_ts)) = event.push_origin() {
+                PUSH_LATENCY.record_enqueued(kind, origin_ts);
+            }
+        }
+
         // 更新全局统计
         let elapsed_us = start.elapsed().as_micros() as u64;
         self.stats.total_broadcasts.fetch_add(1, Ordering::Relaxed);
@@ -547,6 +717,9 @@ impl MarketDataBroadcaster {
                 MarketDataEvent::LastPrice { instrument_id, .. } => instrument_id.clone(),
                 MarketDataEvent::KLineFinished { instrument_id, .. } => instrument_id.clone(),
                 MarketDataEvent::FactorUpdate { instrument_id, .. } => instrument_id.clone(),
+                MarketDataEvent::AuctionIndicator { instrument_id, .. } => instrument_id.clone(),
+                MarketDataEvent::PriceLimitChanged { instrument_id, .. } => instrument_id.clone(),
+                MarketDataEvent::TradingStateChanged { instrument_id, .. } => instrument_id.clone(),
             };
             events_by_instrument
                 .entry(instrument_id)
@@ -562,8 +735,7 @@ impl MarketDataBroadcaster {
                 (
                     entry.key().clone(),
                     entry.value().sender.clone(),
-                    entry.value().subscription.instruments.clone(),
-                    entry.value().subscription.channels.clone(),
+                    entry.value().subscription.clone(),
                     entry.value().stats.clone(),
                 )
             })
@@ -572,21 +744,14 @@ impl MarketDataBroadcaster {
         // 并行发送到每个订阅者
         let results: Vec<(u64, u64, Vec<String>)> = subscribers
             .par_iter()
-            .map(|(subscriber_id, sender, instruments, channels, stats)| {
+            .map(|(subscriber_id, sender, subscription, stats)| {
                 let mut sent = 0u64;
                 let mut dropped = 0u64;
                 let mut to_cleanup = Vec::new();
 
+                // 持锁投递该订阅者的整批数据，保证换订阅原子性
+                let subscription = subscription.lock();
                 for (instrument_id, events_for_instrument) in &events_by_instrument {
-                    // 检查订阅（支持带/不带交易所前缀的格式匹配）
-                    // @yutiansut @quantaxis
-                    let subscribed = instruments.is_empty()
-                        || instruments.iter().any(|id| instrument_matches(id, instrument_id));
-
-                    if !subscribed {
-                        continue;
-                    }
-
                     for event in events_for_instrument {
                         let channel = match &event {
                             MarketDataEvent::OrderBookSnapshot { .. }
@@ -595,12 +760,14 @@ impl MarketDataBroadcaster {
                             MarketDataEvent::LastPrice { .. } => "last_price",
                             MarketDataEvent::KLineFinished { .. } => "kline_finished",
                             MarketDataEvent::FactorUpdate { .. } => "factor",
+                            MarketDataEvent::AuctionIndicator { .. } => "auction",
+                            MarketDataEvent::PriceLimitChanged { .. } => "price_limit",
+                            MarketDataEvent::TradingStateChanged { .. } => "trading_state",
                         };
 
-                        let channel_match =
-                            channels.is_empty() || channels.iter().any(|ch| ch == channel);
-
-                        if !channel_match {
+                        // 检查订阅（支持带/不带交易所前缀的格式匹配）
+                        // @yutiansut @quantaxis
+                        if !subscription.matches(instrument_id, channel) {
                             continue;
                         }
 
@@ -689,6 +856,18 @@ impl MarketDataBroadcaster {
         price: f64,
         volume: f64,
         direction: String,
+    ) {
+        self.broadcast_trade_tick(instrument_id, price, volume, direction, 0);
+    }
+
+    /// 广播撮合逐笔成交（携带撮合批次号）
+    pub fn broadcast_trade_tick(
+        &self,
+        instrument_id: String,
+        price: f64,
+        volume: f64,
+        direction: String,
+        match_batch_id: i64,
     ) {
         let event = MarketDataEvent::Tick {
             instrument_id,
@@ -696,6 +875,8 @@ impl MarketDataBroadcaster {
             volume,
             direction,
             timestamp: chrono::Utc::now().timestamp_millis(),
+            origin_ts: chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0),
+            match_batch_id,
         };
 
         self.broadcast(event);
@@ -712,6 +893,24 @@ impl MarketDataBroadcaster {
         self.broadcast(event);
     }
 
+    /// 广播集合竞价指示价
+    pub fn broadcast_auction_indicator(
+        &self,
+        instrument_id: String,
+        result: &crate::matching::auction::AuctionResult,
+    ) {
+        let event = MarketDataEvent::AuctionIndicator {
+            instrument_id,
+            auction_price: result.auction_price,
+            auction_volume: result.auction_volume,
+            unfilled_buy_volume: result.unfilled_buy_volume,
+            unfilled_sell_volume: result.unfilled_sell_volume,
+            timestamp: chrono::Utc::now().timestamp_millis(),
+        };
+
+        self.broadcast(event);
+    }
+
     /// 获取订阅者数量
     pub fn subscriber_count(&self) -> usize {
         self.subscribers.len()
@@ -722,12 +921,13 @@ impl MarketDataBroadcaster {
         self.subscribers
             .iter()
             .filter(|entry| {
-                let subscription = &entry.value().subscription;
-                subscription.instruments.is_empty()
+                let subscription = entry.value().subscription.lock();
+                !subscription.suspended
+                    && (subscription.instruments.is_empty()
                     || subscription
                         .instruments
                         .iter()
-                        .any(|id| id == instrument_id)
+                        .any(|id| id == instrument_id))
             })
             .count()
     }
@@ -934,6 +1134,8 @@ mod tests {
                 volume: 1.0,
                 direction: "buy".to_string(),
                 timestamp: chrono::Utc::now().timestamp_millis(),
+                origin_ts: chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0),
+                match_batch_id: 0,
             })
             .collect();
 
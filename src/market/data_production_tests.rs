@@ -17,6 +17,7 @@ mod tests {
     use crate::market::MarketDataService;
     use crate::matching::engine::{ExchangeMatchingEngine, InstrumentAsset};
     use crate::matching::{orders, OrderDirection, Success};
+    use crate::utils::timestamp::now_nanos;
     use std::sync::Arc;
 
     // ============================================================
@@ -798,7 +799,7 @@ mod tests {
         }
 
         // 触发 on_trade（正常情况下由 TradeGateway 调用）
-        market_service.on_trade("E2E001", 100.0, 10);
+        market_service.on_trade("E2E001", 100.0, 10, now_nanos());
 
         // 接收广播
         let event = receiver.try_recv();
@@ -863,7 +864,7 @@ mod tests {
             }
 
             // 触发数据处理
-            market_service.on_trade("T2K001", 100.0 + i as f64, 10);
+            market_service.on_trade("T2K001", 100.0 + i as f64, 10, now_nanos());
         }
 
         // 验证K线数据存在
@@ -936,7 +937,7 @@ mod tests {
                     let _ = ob.process_order(buy_order);
                 }
 
-                market_service.on_trade(id, 100.0 + i as f64, 1);
+                market_service.on_trade(id, 100.0 + i as f64, 1, now_nanos());
             }
         }
 
@@ -1011,7 +1012,7 @@ mod tests {
 
             // 触发数据生产（每10笔触发一次以模拟批量处理）
             if i % 10 == 0 {
-                market_service.on_trade("HT001", 100.0, 10);
+                market_service.on_trade("HT001", 100.0, 10, now_nanos());
             }
         }
 
@@ -1120,7 +1121,7 @@ mod tests {
                 let _ = ob.process_order(buy_order);
             }
 
-            market_service.on_trade("CONS001", price, volume);
+            market_service.on_trade("CONS001", price, volume, now_nanos());
         }
 
         // 验证K线数据
@@ -1134,4 +1135,125 @@ mod tests {
         assert_eq!(k.close, 102.0, "收盘价应为最后一笔成交价");
         assert_eq!(k.volume, 42, "总成交量应为42");
     }
+
+    // ============================================================
+    // 7. 批量行情查询测试
+    // ============================================================
+
+    /// 7.1 多合约批量 Tick 查询测试
+    ///
+    /// 场景：两个合约分别成交并挂单，批量查询两个合约与一个不存在的合约
+    /// 验证点：
+    /// - 结果按请求顺序返回且去重
+    /// - 最新价、涨跌幅、买卖盘口、累计成交量正确
+    /// - 不存在的合约单独标记错误，不影响其他合约
+    /// - 合约数超过上限时拒绝
+    #[test]
+    fn test_batch_tick_query() {
+        use crate::market::MAX_BATCH_TICK_INSTRUMENTS;
+
+        let engine = Arc::new(ExchangeMatchingEngine::new());
+        let market_service = MarketDataService::new(engine.clone());
+        let ts = chrono::Utc::now().timestamp_nanos_opt().unwrap();
+
+        // (合约, 昨收, 成交价, 成交量, 买一, 卖一)
+        let cases = [
+            ("BATCH001", 100.0, 102.0, 5.0, 101.0, 103.0),
+            ("BATCH002", 200.0, 190.0, 3.0, 189.0, 191.0),
+        ];
+        for (instrument_id, pre_close, price, volume, bid, ask) in cases {
+            engine
+                .register_instrument(instrument_id.to_string(), pre_close)
+                .unwrap();
+            let orderbook = engine.get_orderbook(instrument_id).unwrap();
+            let asset = InstrumentAsset::from_code(instrument_id);
+            let mut ob = orderbook.write();
+            let _ = ob.process_order(orders::new_limit_order_request(
+                asset,
+                OrderDirection::SELL,
+                price,
+                volume,
+                ts,
+            ));
+            let _ = ob.process_order(orders::new_limit_order_request(
+                asset,
+                OrderDirection::BUY,
+                price,
+                volume,
+                ts + 1,
+            ));
+            let _ = ob.process_order(orders::new_limit_order_request(
+                asset,
+                OrderDirection::BUY,
+                bid,
+                2.0,
+                ts + 2,
+            ));
+            let _ = ob.process_order(orders::new_limit_order_request(
+                asset,
+                OrderDirection::SELL,
+                ask,
+                4.0,
+                ts + 3,
+            ));
+            engine.get_trade_recorder().record_trade(
+                instrument_id.to_string(),
+                "buyer".to_string(),
+                "seller".to_string(),
+                "B1".to_string(),
+                "S1".to_string(),
+                "B1".to_string(),
+                price,
+                volume,
+                "20250101".to_string(),
+            );
+        }
+
+        let request: Vec<String> = ["BATCH002", "MISSING", "BATCH001", "BATCH002"]
+            .iter()
+            .map(|id| id.to_string())
+            .collect();
+        let results = market_service.get_ticks_batch(&request, 5).unwrap();
+
+        let ids: Vec<&str> = results.iter().map(|r| r.instrument_id.as_str()).collect();
+        assert_eq!(ids, vec!["BATCH002", "MISSING", "BATCH001"]);
+
+        let missing = &results[1];
+        assert!(!missing.success);
+        assert!(missing.tick.is_none());
+        assert!(missing.error.as_deref().unwrap().contains("MISSING"));
+
+        let tick = results[2].tick.as_ref().unwrap();
+        assert!(results[2].success);
+        assert_eq!(tick.last_price, 102.0);
+        assert_eq!(tick.pre_close, Some(100.0));
+        assert!((tick.change.unwrap() - 2.0).abs() < 1e-9);
+        assert!((tick.change_percent.unwrap() - 2.0).abs() < 1e-9);
+        assert_eq!(tick.bid_price, Some(101.0));
+        assert_eq!(tick.ask_price, Some(103.0));
+        assert_eq!(tick.bids.len(), 1);
+        assert_eq!((tick.bids[0].price, tick.bids[0].volume), (101.0, 2));
+        assert_eq!((tick.asks[0].price, tick.asks[0].volume), (103.0, 4));
+        assert_eq!(tick.volume, 5);
+
+        let tick = results[0].tick.as_ref().unwrap();
+        assert_eq!(tick.last_price, 190.0);
+        assert!((tick.change_percent.unwrap() + 5.0).abs() < 1e-9);
+        assert_eq!(tick.bid_price, Some(189.0));
+        assert_eq!(tick.ask_price, Some(191.0));
+        assert_eq!(tick.volume, 3);
+
+        // 第二次查询命中缓存，结果一致
+        let hits_before = market_service.get_cache_stats().tick_hits;
+        let again = market_service.get_ticks_batch(&request, 5).unwrap();
+        assert!(market_service.get_cache_stats().tick_hits >= hits_before + 2);
+        assert_eq!(again[2].tick.as_ref().unwrap().last_price, 102.0);
+
+        // 超过上限拒绝，空列表拒绝
+        let too_many: Vec<String> = (0..=MAX_BATCH_TICK_INSTRUMENTS)
+            .map(|i| format!("BATCH{:03}", i))
+            .collect();
+        assert!(market_service.get_ticks_batch(&too_many, 5).is_err());
+        assert!(market_service.get_ticks_batch(&[], 5).is_err());
+    }
 }
@@ -2,13 +2,21 @@
 //!
 //! 从 Tick 数据实时聚合成各种周期的 K线数据
 //!
+//! - 3秒、1分钟 K线直接由 Tick 统计
+//! - 5/15/30/60 分钟和日线由 1 分钟 bar 向上聚合，保证高周期 OHLCV 与其包含的 1 分钟 bar 一致
+//! - 分钟级高周期按整点对齐，交易时段间的休市自然截断 bar；
+//!   日线按交易日归属（夜盘归属下一交易日，见 [`trading_day_of`]），以交易所时区 0 点为边界
+//!
 //! @yutiansut @quantaxis
 
+use chrono::NaiveDate;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
 
+use crate::utils::time_service::time_service;
+
 /// K线数据
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KLine {
@@ -81,6 +89,41 @@ impl KLine {
     pub fn finish(&mut self) {
         self.is_finished = true;
     }
+
+    /// 以一根低周期K线开始新的高周期K线
+    pub fn from_bar(timestamp: i64, bar: &KLine) -> Self {
+        Self {
+            timestamp,
+            is_finished: false,
+            ..bar.clone()
+        }
+    }
+
+    /// 合并一根低周期K线（高周期由 1 分钟 bar 向上聚合）
+    pub fn merge(&mut self, bar: &KLine) {
+        self.high = self.high.max(bar.high);
+        self.low = self.low.min(bar.low);
+        self.close = bar.close;
+        self.volume += bar.volume;
+        self.amount += bar.amount;
+        if self.open_oi == 0 {
+            self.open_oi = bar.open_oi;
+        }
+        self.close_oi = bar.close_oi;
+    }
+}
+
+/// 计算时间戳（毫秒）所属的交易日（按交易所时区，见 [`TimeService::trading_day_of`]）
+///
+/// - 夜盘（默认本地 18:00 之后）归属下一个工作日，周五夜盘归属下周一
+/// - 夜盘跨零点部分（如周六凌晨）同样顺延到下一个工作日
+/// - 其余时间归属当日
+///
+/// 节假日由交易日历在外部处理，这里只跳过周末
+///
+/// [`TimeService::trading_day_of`]: crate::utils::time_service::TimeService::trading_day_of
+pub fn trading_day_of(timestamp_ms: i64) -> NaiveDate {
+    time_service().trading_day_of(timestamp_ms)
 }
 
 /// K线周期
@@ -168,6 +211,11 @@ impl KLinePeriod {
         *self as i64
     }
 
+    /// 是否由 1 分钟 bar 向上聚合（而非直接由 Tick 统计）
+    pub fn is_derived(&self) -> bool {
+        !matches!(self, KLinePeriod::Sec3 | KLinePeriod::Min1)
+    }
+
     /// 计算K线周期的起始时间戳
     pub fn align_timestamp(&self, timestamp_ms: i64) -> i64 {
         let ts_sec = timestamp_ms / 1000;
@@ -175,8 +223,9 @@ impl KLinePeriod {
 
         match self {
             KLinePeriod::Day => {
-                // 日线：按UTC 0点对齐
-                (ts_sec / 86400) * 86400 * 1000
+                // 日线：按交易日对齐（交易日在交易所时区的 0 点）
+                let time_service = time_service();
+                time_service.day_start_millis(time_service.trading_day_of(timestamp_ms))
             }
             _ => {
                 // 分钟线：按周期对齐
@@ -207,8 +256,23 @@ pub struct KLineAggregator {
 
     /// 各周期最后一次处理的时间戳（用于检测跨周期）
     last_period_timestamps: HashMap<KLinePeriod, i64>,
+
+    /// 高周期已累积的 1 分钟 bar（不含当前未完成的 1 分钟 bar）
+    derived_klines: HashMap<KLinePeriod, KLine>,
 }
 
+/// 直接由 Tick 统计的基础周期
+const BASE_PERIODS: [KLinePeriod; 2] = [KLinePeriod::Sec3, KLinePeriod::Min1];
+
+/// 由 1 分钟 bar 向上聚合的周期
+const DERIVED_PERIODS: [KLinePeriod; 5] = [
+    KLinePeriod::Min5,
+    KLinePeriod::Min15,
+    KLinePeriod::Min30,
+    KLinePeriod::Min60,
+    KLinePeriod::Day,
+];
+
 impl KLineAggregator {
     /// 创建新的K线聚合器
     pub fn new(instrument_id: String) -> Self {
@@ -219,6 +283,7 @@ impl KLineAggregator {
             max_history: 1000,
             last_price: None,
             last_period_timestamps: HashMap::new(),
+            derived_klines: HashMap::new(),
         }
     }
 
@@ -235,18 +300,8 @@ impl KLineAggregator {
         // 更新最新价格
         self.last_price = Some(price);
 
-        // 所有周期（分级采样：3s → 1min → 5min → 15min → 30min → 60min → Day）
-        let periods = vec![
-            KLinePeriod::Sec3,
-            KLinePeriod::Min1,
-            KLinePeriod::Min5,
-            KLinePeriod::Min15,
-            KLinePeriod::Min30,
-            KLinePeriod::Min60,
-            KLinePeriod::Day,
-        ];
-
-        for period in periods {
+        // 基础周期（3s、1min）直接由 Tick 统计
+        for period in BASE_PERIODS {
             let period_start = period.align_timestamp(timestamp_ms);
 
             // 更新最后处理的周期时间戳
@@ -261,18 +316,8 @@ impl KLineAggregator {
 
             if need_new_kline {
                 // 完成旧K线
-                if let Some(mut old_kline) = self.current_klines.remove(&period) {
-                    old_kline.finish();
-                    finished_klines.push((period, old_kline.clone()));
-
-                    // 加入历史
-                    let history = self.history_klines.entry(period).or_default();
-                    history.push(old_kline);
-
-                    // 限制历史数量
-                    if history.len() > self.max_history {
-                        history.remove(0);
-                    }
+                if let Some(old_kline) = self.current_klines.remove(&period) {
+                    self.archive_kline(period, old_kline, &mut finished_klines);
                 }
 
                 // 创建新K线
@@ -286,6 +331,9 @@ impl KLineAggregator {
             }
         }
 
+        // 高周期（5min → Day）由 1 分钟 bar 向上聚合
+        self.sync_derived_periods(&mut finished_klines);
+
         finished_klines
     }
 
@@ -309,42 +357,18 @@ impl KLineAggregator {
             None => return finished_klines,
         };
 
-        // 所有周期
-        let periods = vec![
-            KLinePeriod::Sec3,
-            KLinePeriod::Min1,
-            KLinePeriod::Min5,
-            KLinePeriod::Min15,
-            KLinePeriod::Min30,
-            KLinePeriod::Min60,
-            KLinePeriod::Day,
-        ];
-
-        for period in periods {
+        for period in BASE_PERIODS {
             let current_period_start = period.align_timestamp(current_timestamp_ms);
             let period_ms = period.seconds() * 1000;
 
-            // 获取上次处理的时间戳
-            let last_period_ts = self.last_period_timestamps.get(&period).copied();
-
             // 检查当前K线是否已过期（时间戳不是当前周期）
             if let Some(current_kline) = self.current_klines.get(&period) {
                 if current_kline.timestamp != current_period_start {
                     let old_ts = current_kline.timestamp;
 
                     // 当前K线已过期，需要完成它
-                    if let Some(mut old_kline) = self.current_klines.remove(&period) {
-                        old_kline.finish();
-                        finished_klines.push((period, old_kline.clone()));
-
-                        // 加入历史
-                        let history = self.history_klines.entry(period).or_default();
-                        history.push(old_kline);
-
-                        // 限制历史数量
-                        if history.len() > self.max_history {
-                            history.remove(0);
-                        }
+                    if let Some(old_kline) = self.current_klines.remove(&period) {
+                        self.archive_kline(period, old_kline, &mut finished_klines);
                     }
 
                     // 填补中间跳过的周期（多个周期无交易的情况）
@@ -353,18 +377,8 @@ impl KLineAggregator {
                     let mut gap_count = 0;
                     while gap_ts < current_period_start && gap_count < 100 {
                         // 创建空K线（OHLC = last_price, volume = 0）
-                        let mut gap_kline = KLine::new(gap_ts, last_price);
-                        gap_kline.finish();
-                        finished_klines.push((period, gap_kline.clone()));
-
-                        // 加入历史
-                        let history = self.history_klines.entry(period).or_default();
-                        history.push(gap_kline);
-
-                        // 限制历史数量
-                        if history.len() > self.max_history {
-                            history.remove(0);
-                        }
+                        let gap_kline = KLine::new(gap_ts, last_price);
+                        self.archive_kline(period, gap_kline, &mut finished_klines);
 
                         gap_ts += period_ms;
                         gap_count += 1;
@@ -391,9 +405,118 @@ impl KLineAggregator {
             self.last_period_timestamps.insert(period, current_period_start);
         }
 
+        // 1 分钟 bar 推进后同步高周期（区间结束的高周期K线随之完成）
+        self.sync_derived_periods(&mut finished_klines);
+
         finished_klines
     }
 
+    /// 完成K线：标记完成、输出并加入历史
+    fn archive_kline(
+        &mut self,
+        period: KLinePeriod,
+        mut kline: KLine,
+        finished_klines: &mut Vec<(KLinePeriod, KLine)>,
+    ) {
+        kline.finish();
+        finished_klines.push((period, kline.clone()));
+
+        let history = self.history_klines.entry(period).or_default();
+        history.push(kline);
+
+        // 限制历史数量
+        if history.len() > self.max_history {
+            history.remove(0);
+        }
+    }
+
+    /// 将本轮完成的 1 分钟 bar 向上聚合到高周期，并刷新高周期的当前K线
+    ///
+    /// - 完成的 1 分钟 bar 并入所属高周期区间，跨区间时完成旧的高周期K线
+    /// - 当前 1 分钟 bar 已进入新区间时，旧区间已完整，立即完成
+    /// - 高周期当前K线 = 已累积的 1 分钟 bar + 当前 1 分钟 bar
+    /// - 定时器填充的无成交 bar 不参与聚合，避免休市期间的填充价污染高周期开盘/高低价
+    fn sync_derived_periods(&mut self, finished_klines: &mut Vec<(KLinePeriod, KLine)>) {
+        let finished_min1: Vec<KLine> = finished_klines
+            .iter()
+            .filter(|(p, k)| *p == KLinePeriod::Min1 && k.volume > 0)
+            .map(|(_, k)| k.clone())
+            .collect();
+
+        for bar in &finished_min1 {
+            for period in DERIVED_PERIODS {
+                self.fold_min1_bar(period, bar, finished_klines);
+            }
+        }
+
+        let current_min1 = self.current_klines.get(&KLinePeriod::Min1).cloned();
+
+        for period in DERIVED_PERIODS {
+            if let Some(ref current) = current_min1 {
+                let bucket = period.align_timestamp(current.timestamp);
+                let stale = self
+                    .derived_klines
+                    .get(&period)
+                    .is_some_and(|k| k.timestamp != bucket);
+                if stale {
+                    if let Some(done) = self.derived_klines.remove(&period) {
+                        self.archive_kline(period, done, finished_klines);
+                    }
+                }
+            }
+
+            let live = match (
+                self.derived_klines.get(&period),
+                current_min1.as_ref().filter(|k| k.volume > 0),
+            ) {
+                (Some(acc), Some(current)) => {
+                    let mut kline = acc.clone();
+                    kline.merge(current);
+                    Some(kline)
+                }
+                (Some(acc), None) => Some(acc.clone()),
+                (None, Some(current)) => Some(KLine::from_bar(
+                    period.align_timestamp(current.timestamp),
+                    current,
+                )),
+                (None, None) => None,
+            };
+
+            match live {
+                Some(kline) => {
+                    self.last_period_timestamps.insert(period, kline.timestamp);
+                    self.current_klines.insert(period, kline);
+                }
+                None => {
+                    self.current_klines.remove(&period);
+                }
+            }
+        }
+    }
+
+    /// 将一根完成的 1 分钟 bar 并入高周期
+    fn fold_min1_bar(
+        &mut self,
+        period: KLinePeriod,
+        bar: &KLine,
+        finished_klines: &mut Vec<(KLinePeriod, KLine)>,
+    ) {
+        let bucket = period.align_timestamp(bar.timestamp);
+
+        if let Some(acc) = self.derived_klines.get_mut(&period) {
+            if acc.timestamp == bucket {
+                acc.merge(bar);
+                return;
+            }
+        }
+
+        if let Some(done) = self.derived_klines.remove(&period) {
+            self.archive_kline(period, done, finished_klines);
+        }
+        self.derived_klines
+            .insert(period, KLine::from_bar(bucket, bar));
+    }
+
     /// 获取当前K线（未完成）
     pub fn get_current_kline(&self, period: KLinePeriod) -> Option<&KLine> {
         self.current_klines.get(&period)
@@ -413,6 +536,20 @@ impl KLineAggregator {
         }
     }
 
+    /// 丢弃 `since_ms`（含）之后开始的K线，返回丢弃的历史K线数量
+    pub fn discard_since(&mut self, since_ms: i64) -> usize {
+        let mut discarded = 0;
+        for bars in self.history_klines.values_mut() {
+            let before = bars.len();
+            bars.retain(|k| k.timestamp < since_ms);
+            discarded += before - bars.len();
+        }
+        self.current_klines.retain(|_, k| k.timestamp < since_ms);
+        self.derived_klines.retain(|_, k| k.timestamp < since_ms);
+        self.last_period_timestamps.retain(|_, ts| *ts < since_ms);
+        discarded
+    }
+
     /// 获取最近N根K线（包括当前未完成的）
     pub fn get_recent_klines(&self, period: KLinePeriod, count: usize) -> Vec<KLine> {
         let mut klines = self.get_history_klines(period, count);
@@ -426,10 +563,22 @@ impl KLineAggregator {
     }
 }
 
+/// 各合约的K线聚合器（KLineManager 与 KLineActor 共用）
+pub type KLineAggregators = Arc<RwLock<HashMap<String, KLineAggregator>>>;
+
+/// 丢弃所有合约 `since_ms`（含）之后的K线，返回丢弃的历史K线数量
+pub fn discard_klines_since(aggregators: &KLineAggregators, since_ms: i64) -> usize {
+    aggregators
+        .write()
+        .values_mut()
+        .map(|agg| agg.discard_since(since_ms))
+        .sum()
+}
+
 /// K线管理器（所有合约）
 pub struct KLineManager {
     /// 各合约的K线聚合器
-    aggregators: Arc<RwLock<HashMap<String, KLineAggregator>>>,
+    aggregators: KLineAggregators,
 }
 
 impl KLineManager {
@@ -468,6 +617,11 @@ impl KLineManager {
         }
     }
 
+    /// K线聚合器句柄
+    pub fn aggregators(&self) -> KLineAggregators {
+        self.aggregators.clone()
+    }
+
     /// 获取当前K线
     pub fn get_current_kline(&self, instrument_id: &str, period: KLinePeriod) -> Option<KLine> {
         let aggregators = self.aggregators.read();
@@ -531,6 +685,146 @@ mod tests {
         assert!(!current.is_finished);
     }
 
+    /// 北京时间 -> 毫秒时间戳
+    fn cst_ms(y: i32, m: u32, d: u32, h: u32, mi: u32, sec: u32) -> i64 {
+        use chrono::{FixedOffset, TimeZone};
+        FixedOffset::east_opt(8 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(y, m, d, h, mi, sec)
+            .unwrap()
+            .timestamp_millis()
+    }
+
+    /// 依次合并K线，作为高周期的期望值
+    fn fold(bars: &[KLine]) -> KLine {
+        let mut acc = KLine::from_bar(bars[0].timestamp, &bars[0]);
+        for bar in &bars[1..] {
+            acc.merge(bar);
+        }
+        acc
+    }
+
+    #[test]
+    fn test_min1_aggregate_to_min5() {
+        let mut agg = KLineAggregator::new("IF2501".to_string());
+        let base = cst_ms(2025, 1, 6, 9, 0, 0);
+
+        // 5 分钟内每分钟 3 笔成交
+        let prices = [
+            [100.0, 103.0, 101.0],
+            [101.5, 99.0, 100.5],
+            [100.5, 104.5, 104.0],
+            [103.0, 102.0, 102.5],
+            [102.5, 98.5, 99.5],
+        ];
+        for (i, minute) in prices.iter().enumerate() {
+            for (j, price) in minute.iter().enumerate() {
+                let ts = base + i as i64 * 60_000 + j as i64 * 15_000 + 1000;
+                agg.on_tick(*price, (i + j + 1) as i64, ts);
+            }
+        }
+
+        // 进入下一个 5 分钟区间：最后一根 1 分钟 bar 与 5 分钟 bar 同时完成
+        let finished = agg.on_tick(99.0, 1, base + 5 * 60_000 + 1000);
+        let (_, min5) = finished
+            .iter()
+            .find(|(p, _)| *p == KLinePeriod::Min5)
+            .expect("5-minute K-line should finish");
+
+        let min1_bars = agg.get_history_klines(KLinePeriod::Min1, 10);
+        assert_eq!(min1_bars.len(), 5);
+        let expected = fold(&min1_bars);
+
+        assert_eq!(min5.timestamp, base);
+        assert_eq!(min5.open, 100.0);
+        assert_eq!(min5.high, 104.5);
+        assert_eq!(min5.low, 98.5);
+        assert_eq!(min5.close, 99.5);
+        assert_eq!(min5.volume, expected.volume);
+        assert_eq!(min5.volume, (1..=5).map(|i| i * 3 + 3).sum::<i64>());
+        assert!((min5.amount - expected.amount).abs() < 1e-9);
+        assert_eq!(
+            (min5.open, min5.high, min5.low, min5.close),
+            (expected.open, expected.high, expected.low, expected.close)
+        );
+
+        // 新区间的 5 分钟当前K线与当前 1 分钟K线一致
+        let current_min5 = agg.get_current_kline(KLinePeriod::Min5).unwrap();
+        assert_eq!(current_min5.timestamp, base + 5 * 60_000);
+        assert_eq!(current_min5.open, 99.0);
+        assert_eq!(current_min5.volume, 1);
+    }
+
+    #[test]
+    fn test_day_kline_follows_trading_day() {
+        // 2025-01-03 为周五，其夜盘（含周六凌晨）归属下周一 2025-01-06
+        assert_eq!(
+            trading_day_of(cst_ms(2025, 1, 3, 21, 0, 0)),
+            NaiveDate::from_ymd_opt(2025, 1, 6).unwrap()
+        );
+        assert_eq!(
+            trading_day_of(cst_ms(2025, 1, 4, 1, 0, 0)),
+            NaiveDate::from_ymd_opt(2025, 1, 6).unwrap()
+        );
+        assert_eq!(
+            trading_day_of(cst_ms(2025, 1, 6, 14, 59, 0)),
+            NaiveDate::from_ymd_opt(2025, 1, 6).unwrap()
+        );
+        assert_eq!(
+            trading_day_of(cst_ms(2025, 1, 6, 21, 0, 0)),
+            NaiveDate::from_ymd_opt(2025, 1, 7).unwrap()
+        );
+
+        let mut agg = KLineAggregator::new("cu2502".to_string());
+        let ticks = [
+            (cst_ms(2025, 1, 3, 21, 0, 30), 100.0, 2), // 周五夜盘
+            (cst_ms(2025, 1, 3, 23, 59, 30), 105.0, 3),
+            (cst_ms(2025, 1, 4, 1, 0, 30), 98.0, 1), // 夜盘跨零点
+            (cst_ms(2025, 1, 6, 9, 0, 30), 102.0, 4), // 周一日盘
+            (cst_ms(2025, 1, 6, 11, 29, 30), 103.0, 2),
+            (cst_ms(2025, 1, 6, 13, 30, 30), 100.5, 5), // 午休后
+            (cst_ms(2025, 1, 6, 14, 59, 30), 101.0, 1),
+        ];
+        for (ts, price, volume) in ticks {
+            agg.on_tick(price, volume, ts);
+        }
+
+        // 周一夜盘开始：周一交易日的日线完成
+        let finished = agg.on_tick(101.5, 1, cst_ms(2025, 1, 6, 21, 0, 30));
+        let (_, day) = finished
+            .iter()
+            .find(|(p, _)| *p == KLinePeriod::Day)
+            .expect("day K-line should finish");
+
+        let day_start = cst_ms(2025, 1, 6, 0, 0, 0);
+        assert_eq!(day.timestamp, day_start);
+        assert_eq!(day.open, 100.0);
+        assert_eq!(day.high, 105.0);
+        assert_eq!(day.low, 98.0);
+        assert_eq!(day.close, 101.0);
+        assert_eq!(day.volume, 18);
+
+        // 与该交易日内的 1 分钟 bar 一致
+        let min1_bars: Vec<KLine> = agg
+            .get_history_klines(KLinePeriod::Min1, 100)
+            .into_iter()
+            .filter(|k| KLinePeriod::Day.align_timestamp(k.timestamp) == day_start)
+            .collect();
+        assert_eq!(min1_bars.len(), ticks.len());
+        let expected = fold(&min1_bars);
+        assert_eq!(day.volume, expected.volume);
+        assert!((day.amount - expected.amount).abs() < 1e-9);
+
+        // 60 分钟线被午休截断：11:00 与 13:00 分属不同 bar
+        let min60: Vec<i64> = agg
+            .get_history_klines(KLinePeriod::Min60, 100)
+            .iter()
+            .map(|k| k.timestamp)
+            .collect();
+        assert!(min60.contains(&cst_ms(2025, 1, 6, 11, 0, 0)));
+        assert!(min60.contains(&cst_ms(2025, 1, 6, 13, 0, 0)));
+    }
+
     #[test]
     fn test_kline_manager() {
         let manager = KLineManager::new();
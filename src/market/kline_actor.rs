@@ -2,6 +2,7 @@
 //!
 //! 独立的Actix Actor，负责K线实时聚合和历史查询
 //! 订阅MarketDataBroadcaster的tick事件，实现分级采样
+//! （3秒/1分钟由tick统计，5/15/30/60分钟与日线由1分钟bar向上聚合）
 //! 支持持久化和恢复
 //!
 //! ## 方案A: 集成因子引擎 (2025-12-16)
@@ -9,18 +10,21 @@
 //! K线完成时自动触发因子计算并广播:
 //! - StreamFactorEngine 增量计算 MA, EMA, RSI, MACD 等
 //! - 广播 FactorUpdate 事件给 WebSocket 订阅者
+//! - 配置 FactorWalPersister 时将因子值异步写入因子 WAL（由 FactorStore 落盘查询）
 //!
 //! @yutiansut @quantaxis
 
 use actix::{Actor, Addr, AsyncContext, Context, Handler, Message};
 use parking_lot::RwLock;
+use rayon::prelude::*;
 use std::collections::HashMap;
 use std::sync::Arc;
 
-use super::kline::{KLine, KLineAggregator, KLinePeriod};
+use super::kline::{KLine, KLineAggregator, KLineAggregators, KLinePeriod};
 use super::MarketDataBroadcaster;
 use super::MarketDataEvent;
-use crate::factor::{FactorRegistry, StreamFactorEngine};
+use crate::exchange::instrument_code::InstrumentCode;
+use crate::factor::{FactorRegistry, FactorWalPersister, StreamFactorEngine};
 use crate::storage::wal::{WalManager, WalRecord};
 
 /// K线Actor - 独立处理K线聚合，避免阻塞交易流程
@@ -33,7 +37,7 @@ use crate::storage::wal::{WalManager, WalRecord};
 /// ```
 pub struct KLineActor {
     /// 各合约的K线聚合器
-    aggregators: Arc<RwLock<HashMap<String, KLineAggregator>>>,
+    aggregators: KLineAggregators,
 
     /// 市场数据广播器（用于订阅tick和推送K线完成事件）
     broadcaster: Arc<MarketDataBroadcaster>,
@@ -53,6 +57,9 @@ pub struct KLineActor {
 
     /// 是否启用因子计算
     enable_factor_compute: bool,
+
+    /// 因子值落盘（异步写入因子 WAL，None 表示不落盘）
+    factor_persister: Option<Arc<FactorWalPersister>>,
 }
 
 /// 默认启用的因子列表
@@ -70,6 +77,7 @@ impl KLineActor {
             factor_engines: Arc::new(RwLock::new(HashMap::new())),
             enabled_factors: DEFAULT_FACTORS.iter().map(|s| s.to_string()).collect(),
             enable_factor_compute: false, // 默认关闭，需要显式启用
+            factor_persister: None,
         }
     }
 
@@ -102,6 +110,17 @@ impl KLineActor {
         self
     }
 
+    /// 设置因子值落盘（K线完成时以 K线时间戳写入因子 WAL）
+    pub fn with_factor_persister(mut self, persister: Arc<FactorWalPersister>) -> Self {
+        self.factor_persister = Some(persister);
+        self
+    }
+
+    /// K线聚合器句柄（启动 Actor 前获取，交易日重置时清理当日K线）
+    pub fn aggregators(&self) -> KLineAggregators {
+        self.aggregators.clone()
+    }
+
     /// 获取或创建合约的因子引擎
     /// @yutiansut @quantaxis
     fn get_or_create_factor_engine(&self, instrument_id: &str) -> Option<()> {
@@ -266,9 +285,10 @@ impl Actor for KLineActor {
         let timer_factor_engines = self.factor_engines.clone();
         let timer_enabled_factors = self.enabled_factors.clone();
         let timer_enable_factor_compute = self.enable_factor_compute;
+        let timer_factor_persister = self.factor_persister.clone();
 
         ctx.run_interval(std::time::Duration::from_secs(1), move |_act, _ctx| {
-            let current_timestamp_ms = chrono::Utc::now().timestamp_millis();
+            let current_timestamp_ms = crate::utils::time_service::time_service().now_millis();
             let mut all_finished_klines = Vec::new();
 
             // 遍历所有聚合器，检查是否有K线需要完成
@@ -295,6 +315,7 @@ impl Actor for KLineActor {
                     period: period.to_int(),
                     kline: kline.clone(),
                     timestamp: current_timestamp_ms,
+                    origin_ts: chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0),
                 });
 
                 // ✨ 方案A: K线完成时计算因子并广播
@@ -319,6 +340,16 @@ impl Actor for KLineActor {
                         let factor_values = engine.update_all(kline.close, &factor_ids);
 
                         if !factor_values.is_empty() {
+                            if let Some(ref persister) = timer_factor_persister {
+                                if let Err(e) = persister.persist_batch(
+                                    &instrument_id,
+                                    &factor_values,
+                                    kline.timestamp,
+                                ) {
+                                    log::warn!("📈 [KLineActor Timer] Factor persist failed: {}", e);
+                                }
+                            }
+
                             timer_broadcaster.broadcast(MarketDataEvent::FactorUpdate {
                                 instrument_id: instrument_id.clone(),
                                 factors: factor_values,
@@ -374,6 +405,7 @@ impl Actor for KLineActor {
         let factor_engines = self.factor_engines.clone();
         let enabled_factors = self.enabled_factors.clone();
         let enable_factor_compute = self.enable_factor_compute;
+        let factor_persister = self.factor_persister.clone();
         let _addr = ctx.address();
 
         let fut = async move {
@@ -429,6 +461,9 @@ impl Actor for KLineActor {
                                     period: period.to_int(),
                                     kline: kline.clone(),
                                     timestamp,
+                                    origin_ts: chrono::Utc::now()
+                                        .timestamp_nanos_opt()
+                                        .unwrap_or(0),
                                 });
 
                                 // ✨ 方案A: K线完成时计算因子并广播
@@ -465,6 +500,20 @@ impl Actor for KLineActor {
                                                 instrument_id, factor_values
                                             );
 
+                                            // 因子值落盘（异步，不阻塞K线处理）
+                                            if let Some(ref persister) = factor_persister {
+                                                if let Err(e) = persister.persist_batch(
+                                                    &instrument_id,
+                                                    &factor_values,
+                                                    kline.timestamp,
+                                                ) {
+                                                    log::warn!(
+                                                        "📈 [KLineActor] Factor persist failed: {}",
+                                                        e
+                                                    );
+                                                }
+                                            }
+
                                             // 广播因子更新事件
                                             broadcaster.broadcast(MarketDataEvent::FactorUpdate {
                                                 instrument_id: instrument_id.clone(),
@@ -542,20 +591,6 @@ pub struct GetKLines {
     pub count: usize,
 }
 
-/// 从合约ID中提取基础合约代码（去除交易所前缀）
-/// @yutiansut @quantaxis
-///
-/// 支持两种格式：
-/// - "CFFEX.IF2501" -> "IF2501"
-/// - "IF2501" -> "IF2501"
-fn extract_base_instrument_id(instrument_id: &str) -> &str {
-    if let Some(pos) = instrument_id.find('.') {
-        &instrument_id[pos + 1..]
-    } else {
-        instrument_id
-    }
-}
-
 impl Handler<GetKLines> for KLineActor {
     type Result = Vec<KLine>;
 
@@ -579,27 +614,24 @@ impl Handler<GetKLines> for KLineActor {
             return klines;
         }
 
-        // 如果直接匹配失败，尝试用基础合约代码匹配
+        // 如果直接匹配失败，按带/不带交易所前缀的代码匹配（不同交易所的同名合约不匹配）
         // @yutiansut @quantaxis
-        let base_id = extract_base_instrument_id(&msg.instrument_id);
         log::info!(
-            "📊 [KLineActor GetKLines] Direct match failed, trying base_id: {}",
-            base_id
+            "📊 [KLineActor GetKLines] Direct match failed, trying code match: {}",
+            msg.instrument_id
         );
 
         // 遍历所有aggregator，找到匹配的
         for (key, aggregator) in aggregators.iter() {
-            let key_base = extract_base_instrument_id(key);
-            if key_base == base_id {
+            if InstrumentCode::matches(key, &msg.instrument_id) {
                 log::info!(
-                    "📊 [KLineActor GetKLines] Found matching aggregator: {} -> {} for query {}",
+                    "📊 [KLineActor GetKLines] Found matching aggregator: {} -> {}",
                     msg.instrument_id,
-                    key,
-                    base_id
+                    key
                 );
                 let klines = aggregator.get_recent_klines(msg.period, msg.count);
                 log::info!(
-                    "📊 [KLineActor GetKLines] Returning {} K-lines via base_id match",
+                    "📊 [KLineActor GetKLines] Returning {} K-lines via code match",
                     klines.len()
                 );
                 return klines;
@@ -607,13 +639,61 @@ impl Handler<GetKLines> for KLineActor {
         }
 
         log::warn!(
-            "📊 [KLineActor GetKLines] No aggregator found for instrument: {} (base: {})",
-            msg.instrument_id, base_id
+            "📊 [KLineActor GetKLines] No aggregator found for instrument: {}",
+            msg.instrument_id
         );
         Vec::new()
     }
 }
 
+/// 批量查询K线消息 - 用于 HTTP 批量预取接口
+/// @yutiansut @quantaxis
+///
+/// 结果与请求一一对应，合约不存在时为 None
+#[derive(Message)]
+#[rtype(result = "Vec<Option<Vec<KLine>>>")]
+pub struct GetKLinesBatch {
+    pub requests: Vec<GetKLines>,
+}
+
+/// 查找合约的K线聚合器（先直接匹配，再按带/不带交易所前缀的代码匹配）
+fn find_aggregator<'a>(
+    aggregators: &'a HashMap<String, KLineAggregator>,
+    instrument_id: &str,
+) -> Option<&'a KLineAggregator> {
+    aggregators.get(instrument_id).or_else(|| {
+        aggregators
+            .iter()
+            .find(|(key, _)| InstrumentCode::matches(key, instrument_id))
+            .map(|(_, aggregator)| aggregator)
+    })
+}
+
+impl Handler<GetKLinesBatch> for KLineActor {
+    type Result = Vec<Option<Vec<KLine>>>;
+
+    fn handle(&mut self, msg: GetKLinesBatch, _ctx: &mut Context<Self>) -> Self::Result {
+        let aggregators = self.aggregators.read();
+
+        // 各合约K线互不依赖，在读锁下并行截取
+        let results: Vec<Option<Vec<KLine>>> = msg
+            .requests
+            .par_iter()
+            .map(|req| {
+                find_aggregator(&aggregators, &req.instrument_id)
+                    .map(|aggregator| aggregator.get_recent_klines(req.period, req.count))
+            })
+            .collect();
+
+        log::debug!(
+            "📊 [KLineActor GetKLinesBatch] {} queries, {} found",
+            results.len(),
+            results.iter().filter(|r| r.is_some()).count()
+        );
+        results
+    }
+}
+
 /// 获取当前K线消息（未完成的K线）
 #[derive(Message)]
 #[rtype(result = "Option<KLine>")]
@@ -633,18 +713,12 @@ impl Handler<GetCurrentKLine> for KLineActor {
             return agg.get_current_kline(msg.period).cloned();
         }
 
-        // 如果直接匹配失败，尝试用基础合约代码匹配
+        // 如果直接匹配失败，按带/不带交易所前缀的代码匹配
         // @yutiansut @quantaxis
-        let base_id = extract_base_instrument_id(&msg.instrument_id);
-
-        for (key, aggregator) in aggregators.iter() {
-            let key_base = extract_base_instrument_id(key);
-            if key_base == base_id {
-                return aggregator.get_current_kline(msg.period).cloned();
-            }
-        }
-
-        None
+        aggregators
+            .iter()
+            .find(|(key, _)| InstrumentCode::matches(key, &msg.instrument_id))
+            .and_then(|(_, aggregator)| aggregator.get_current_kline(msg.period).cloned())
     }
 }
 
@@ -3,6 +3,8 @@
 //! 提供市场数据的业务逻辑，包括订单簿查询、行情数据、成交数据等
 //! 遵循解耦原则：业务逻辑与网络层分离
 
+pub mod anomaly;
+pub mod auction_indicator;
 pub mod broadcaster;
 pub mod cache;
 pub mod kline;
@@ -10,6 +12,7 @@ pub mod kline_actor;
 pub mod recovery;
 pub mod snapshot_broadcaster;
 pub mod snapshot_generator;
+pub mod subscription;
 
 #[cfg(test)]
 mod data_production_tests;
@@ -19,9 +22,10 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
 
-use crate::exchange::AccountManager;
+use crate::exchange::{AccountManager, InstrumentRegistry};
 use crate::matching::engine::ExchangeMatchingEngine;
 use crate::utils::config::InstrumentConfig;
+use crate::utils::timestamp::{nanos_to_millis, now_millis, now_nanos, secs_to_nanos};
 use crate::ExchangeError;
 
 pub type Result<T> = std::result::Result<T, ExchangeError>;
@@ -30,6 +34,7 @@ pub type Result<T> = std::result::Result<T, ExchangeError>;
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OrderBookSnapshot {
     pub instrument_id: String,
+    /// 快照时间（毫秒，对外 API 单位）
     pub timestamp: i64,
     pub bids: Vec<PriceLevel>, // 买盘（降序）
     pub asks: Vec<PriceLevel>, // 卖盘（升序）
@@ -50,6 +55,7 @@ pub struct RecentTrade {
     pub instrument_id: String,
     pub price: f64,
     pub volume: i64,
+    /// 成交时间（纳秒，与成交回报一致）
     pub timestamp: i64,
     pub direction: String, // "BUY" or "SELL"
 }
@@ -69,6 +75,7 @@ pub struct InstrumentInfo {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TickData {
     pub instrument_id: String,
+    /// 行情时间（毫秒，对外 API 单位）
     pub timestamp: i64,
     pub last_price: f64,
     pub bid_price: Option<f64>,
@@ -76,6 +83,38 @@ pub struct TickData {
     pub volume: i64,
 }
 
+/// 单次批量行情查询的合约数上限
+pub const MAX_BATCH_TICK_INSTRUMENTS: usize = 100;
+
+/// 批量行情中的单合约行情
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchTickQuote {
+    pub instrument_id: String,
+    /// 行情时间（毫秒）
+    pub timestamp: i64,
+    pub last_price: f64,
+    /// 昨收（未设置时无涨跌幅）
+    pub pre_close: Option<f64>,
+    pub change: Option<f64>,
+    /// 涨跌幅（%）
+    pub change_percent: Option<f64>,
+    pub bid_price: Option<f64>,
+    pub ask_price: Option<f64>,
+    pub bids: Vec<PriceLevel>,
+    pub asks: Vec<PriceLevel>,
+    /// 累计成交量
+    pub volume: i64,
+}
+
+/// 批量行情查询结果（合约不存在时只标记该合约失败）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchTickItem {
+    pub instrument_id: String,
+    pub success: bool,
+    pub tick: Option<BatchTickQuote>,
+    pub error: Option<String>,
+}
+
 /// 市场数据服务（业务逻辑层）
 #[derive(Clone)]
 pub struct MarketDataService {
@@ -94,6 +133,10 @@ pub struct MarketDataService {
     account_manager: Option<Arc<AccountManager>>,
     /// 市场数据广播器
     market_broadcaster: Option<Arc<MarketDataBroadcaster>>,
+    /// 合约注册表（查询入口的合约代码归一）
+    instrument_registry: Option<Arc<InstrumentRegistry>>,
+    /// 行情异常值检测（成交价/盘口价推送前过滤）
+    anomaly_detector: Arc<anomaly::PriceAnomalyDetector>,
 }
 
 impl MarketDataService {
@@ -109,6 +152,8 @@ impl MarketDataService {
             kline_manager: Arc::new(kline::KLineManager::new()),
             account_manager: None,
             market_broadcaster: None,
+            instrument_registry: None,
+            anomaly_detector: Arc::new(anomaly::PriceAnomalyDetector::default()),
         }
     }
 
@@ -130,6 +175,40 @@ impl MarketDataService {
         self
     }
 
+    /// 设置合约注册表（查询前将 `SHFE.cu2501` / 旧代码 `cu2501` 归一为注册代码）
+    pub fn with_instrument_registry(mut self, registry: Arc<InstrumentRegistry>) -> Self {
+        self.instrument_registry = Some(registry);
+        self
+    }
+
+    /// 设置行情异常值检测器（与广播器共用同一实例）
+    pub fn with_anomaly_detector(mut self, detector: Arc<anomaly::PriceAnomalyDetector>) -> Self {
+        self.anomaly_detector = detector;
+        self
+    }
+
+    /// 行情异常值检测器
+    pub fn anomaly_detector(&self) -> Arc<anomaly::PriceAnomalyDetector> {
+        self.anomaly_detector.clone()
+    }
+
+    /// 合约代码归一（未设置注册表时原样返回）
+    pub fn normalize_instrument(&self, code: &str) -> Result<String> {
+        match &self.instrument_registry {
+            Some(registry) => registry.normalize(code),
+            None => Ok(code.trim().to_string()),
+        }
+    }
+
+    /// 成交金额 = 价格 × 手数 × 每手数量（未设置注册表时按 1）
+    pub fn trade_turnover(&self, instrument_id: &str, price: f64, volume: f64) -> f64 {
+        let multiple = self
+            .instrument_registry
+            .as_ref()
+            .map_or(1.0, |registry| registry.volume_multiple(instrument_id));
+        price * volume * multiple
+    }
+
     /// 设置 iceoryx2 管理器（零拷贝 IPC）
     pub fn with_iceoryx(mut self, manager: Arc<RwLock<crate::ipc::IceoryxManager>>) -> Self {
         self.iceoryx_manager = Some(manager);
@@ -197,6 +276,8 @@ impl MarketDataService {
 
     /// 设置昨收盘价（启动时调用）
     pub fn set_pre_close(&self, instrument_id: &str, pre_close: f64) {
+        self.anomaly_detector
+            .set_reference_price(instrument_id, pre_close);
         if let Some(generator) = &self.snapshot_generator {
             generator.set_pre_close(instrument_id, pre_close);
         }
@@ -255,6 +336,8 @@ impl MarketDataService {
             kline_manager: Arc::new(kline::KLineManager::new()),
             account_manager: None,
             market_broadcaster: None,
+            instrument_registry: None,
+            anomaly_detector: Arc::new(anomaly::PriceAnomalyDetector::default()),
         }
     }
 
@@ -315,75 +398,47 @@ impl MarketDataService {
             log::trace!("⚠️  [L2 Storage] Storage not configured");
         }
 
-        // L3 缓存未命中，从 Orderbook 实时计算（降低日志级别）
+        // L3 缓存未命中，从撮合引擎深度快照实时计算（降低日志级别）
         log::trace!(
             "🔍 [L3 Realtime] Computing orderbook from matching engine for {}",
             instrument_id
         );
-        let engine = &self.matching_engine;
-
-        // 获取指定合约的订单簿
-        let orderbook = engine.get_orderbook(instrument_id).ok_or_else(|| {
-            ExchangeError::MatchingError(format!("Instrument not found: {}", instrument_id))
-        })?;
-
-        let ob = orderbook.read();
-
-        // 获取买盘（降序排列）
-        let bids = if let Some(bid_orders) = ob.bid_queue.get_sorted_orders() {
-            use std::collections::HashMap;
-            let mut price_map: HashMap<String, f64> = HashMap::new();
-            for order in bid_orders.iter().take(depth * 10) {
-                // 获取足够的订单以聚合
-                *price_map.entry(order.price.to_string()).or_insert(0.0) += order.volume;
-            }
-
-            let mut levels: Vec<PriceLevel> = price_map
-                .into_iter()
-                .filter_map(|(price_str, volume)| {
-                    price_str.parse::<f64>().ok().map(|price| PriceLevel {
-                        price,
-                        volume: volume as i64,
-                    })
-                })
-                .collect();
-            levels.sort_by(|a, b| b.price.partial_cmp(&a.price).unwrap());
-            levels.truncate(depth);
+        // 读取只读深度快照（撮合后发布，不获取订单簿锁；最多 DEFAULT_DEPTH_LEVELS 档）
+        let depth_snapshot = self
+            .matching_engine
+            .get_depth_snapshot(instrument_id)
+            .ok_or_else(|| {
+                ExchangeError::MatchingError(format!("Instrument not found: {}", instrument_id))
+            })?;
+        // 可疑价位（零/负/超出涨跌停）不对外展示
+        let to_levels = |levels: &[crate::matching::DepthLevel]| -> Vec<PriceLevel> {
             levels
-        } else {
-            Vec::new()
-        };
-
-        // 获取卖盘（升序排列）
-        let asks = if let Some(ask_orders) = ob.ask_queue.get_sorted_orders() {
-            use std::collections::HashMap;
-            let mut price_map: HashMap<String, f64> = HashMap::new();
-            for order in ask_orders.iter().take(depth * 10) {
-                *price_map.entry(order.price.to_string()).or_insert(0.0) += order.volume;
-            }
-
-            let mut levels: Vec<PriceLevel> = price_map
-                .into_iter()
-                .filter_map(|(price_str, volume)| {
-                    price_str.parse::<f64>().ok().map(|price| PriceLevel {
-                        price,
-                        volume: volume as i64,
-                    })
+                .iter()
+                .filter(|level| {
+                    self.anomaly_detector.inspect(
+                        instrument_id,
+                        anomaly::PriceSource::OrderBook,
+                        level.price,
+                    )
                 })
-                .collect();
-            levels.sort_by(|a, b| a.price.partial_cmp(&b.price).unwrap());
-            levels.truncate(depth);
-            levels
-        } else {
-            Vec::new()
+                .take(depth)
+                .map(|level| PriceLevel {
+                    price: level.price,
+                    volume: level.volume as i64,
+                })
+                .collect()
         };
 
+        // 买盘（降序排列）/ 卖盘（升序排列）
+        let bids = to_levels(&depth_snapshot.bids);
+        let asks = to_levels(&depth_snapshot.asks);
+
         // 获取最新成交价
-        let last_price = Some(ob.lastprice);
+        let last_price = Some(depth_snapshot.last_price);
 
         let snapshot = OrderBookSnapshot {
             instrument_id: instrument_id.to_string(),
-            timestamp: chrono::Utc::now().timestamp_millis(),
+            timestamp: now_millis(),
             bids,
             asks,
             last_price,
@@ -460,33 +515,29 @@ impl MarketDataService {
             log::trace!("⚠️  [L2 Storage] Storage not configured");
         }
 
-        // L3 缓存未命中，从 Orderbook 实时计算（降低日志级别）
+        // L3 缓存未命中，从撮合引擎深度快照实时计算（降低日志级别）
         log::trace!(
             "🔍 [L3 Realtime] Computing tick from orderbook for {}",
             instrument_id
         );
-        let engine = &self.matching_engine;
-
-        // 检查合约是否存在
-        let orderbook = engine.get_orderbook(instrument_id).ok_or_else(|| {
-            ExchangeError::MatchingError(format!("Instrument not found: {}", instrument_id))
-        })?;
-
-        let ob = orderbook.read();
+        // 检查合约是否存在（读取只读深度快照，不获取订单簿锁）
+        let depth = self
+            .matching_engine
+            .get_depth_snapshot(instrument_id)
+            .ok_or_else(|| {
+                ExchangeError::MatchingError(format!("Instrument not found: {}", instrument_id))
+            })?;
 
         // 获取最新成交价
-        let last_price = ob.lastprice;
+        let last_price = depth.last_price;
 
-        // 获取最优买卖价（从排序订单中获取第一个）
-        let bid_price = ob
-            .bid_queue
-            .get_sorted_orders()
-            .and_then(|orders| orders.first().map(|o| o.price));
-
-        let ask_price = ob
-            .ask_queue
-            .get_sorted_orders()
-            .and_then(|orders| orders.first().map(|o| o.price));
+        // 获取最优买卖价（可疑价位不对外展示）
+        let publishable = |price: f64| {
+            self.anomaly_detector
+                .inspect(instrument_id, anomaly::PriceSource::OrderBook, price)
+        };
+        let bid_price = depth.best_bid().filter(|price| publishable(*price));
+        let ask_price = depth.best_ask().filter(|price| publishable(*price));
 
         let trade_recorder = self.matching_engine.get_trade_recorder();
         let volume = trade_recorder
@@ -497,7 +548,7 @@ impl MarketDataService {
 
         let tick = TickData {
             instrument_id: instrument_id.to_string(),
-            timestamp: chrono::Utc::now().timestamp_millis(),
+            timestamp: now_millis(),
             last_price,
             bid_price,
             ask_price,
@@ -511,6 +562,82 @@ impl MarketDataService {
         Ok(tick)
     }
 
+    /// 批量获取多个合约的行情（最新价、涨跌幅、买卖盘口、成交量）
+    ///
+    /// 合约去重后并行查询，Tick 与盘口走 L1 缓存；单个合约失败不影响其他合约
+    pub fn get_ticks_batch(
+        &self,
+        instrument_ids: &[String],
+        depth: usize,
+    ) -> Result<Vec<BatchTickItem>> {
+        use rayon::prelude::*;
+
+        let mut seen = std::collections::HashSet::new();
+        let instrument_ids: Vec<&String> = instrument_ids
+            .iter()
+            .filter(|id| seen.insert(id.as_str()))
+            .collect();
+        if instrument_ids.is_empty() {
+            return Err(ExchangeError::InvalidParameter(
+                "instrument_ids is empty".to_string(),
+            ));
+        }
+        if instrument_ids.len() > MAX_BATCH_TICK_INSTRUMENTS {
+            return Err(ExchangeError::InvalidParameter(format!(
+                "Too many instruments: {} (max {})",
+                instrument_ids.len(),
+                MAX_BATCH_TICK_INSTRUMENTS
+            )));
+        }
+
+        Ok(instrument_ids
+            .par_iter()
+            .map(|instrument_id| {
+                let result = self.get_batch_quote(instrument_id, depth);
+                BatchTickItem {
+                    instrument_id: instrument_id.to_string(),
+                    success: result.is_ok(),
+                    error: result.as_ref().err().map(|e| e.to_string()),
+                    tick: result.ok(),
+                }
+            })
+            .collect())
+    }
+
+    /// 组装单合约批量行情
+    fn get_batch_quote(&self, instrument_id: &str, depth: usize) -> Result<BatchTickQuote> {
+        let tick = self.get_tick_data(instrument_id)?;
+        let snapshot = self.get_orderbook_snapshot(instrument_id, depth)?;
+
+        let pre_close = self
+            .matching_engine
+            .get_prev_close(instrument_id)
+            .filter(|price| *price > 0.0);
+        let change = pre_close.map(|pre| tick.last_price - pre);
+        let change_percent = pre_close
+            .zip(change)
+            .map(|(pre, change)| change / pre * 100.0);
+        let volume = self
+            .matching_engine
+            .get_trade_recorder()
+            .get_trade_stats(instrument_id)
+            .total_volume as i64;
+
+        Ok(BatchTickQuote {
+            instrument_id: instrument_id.to_string(),
+            timestamp: tick.timestamp,
+            last_price: tick.last_price,
+            pre_close,
+            change,
+            change_percent,
+            bid_price: tick.bid_price,
+            ask_price: tick.ask_price,
+            bids: snapshot.bids.into_iter().take(depth).collect(),
+            asks: snapshot.asks.into_iter().take(depth).collect(),
+            volume,
+        })
+    }
+
     /// 获取最近成交记录
     pub fn get_recent_trades(&self, instrument_id: &str, limit: usize) -> Result<Vec<RecentTrade>> {
         let trade_recorder = self.matching_engine.get_trade_recorder();
@@ -540,8 +667,8 @@ impl MarketDataService {
     fn load_tick_from_storage(&self, instrument_id: &str) -> Result<TickData> {
         if let Some(ref storage) = self.storage {
             // 查询最近1小时的数据
-            let end_ts = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
-            let start_ts = end_ts - (3600 * 1_000_000_000); // 1小时
+            let end_ts = now_nanos();
+            let start_ts = end_ts - secs_to_nanos(3600); // 1小时
 
             log::debug!(
                 "📂 [Storage] range_query for tick: {} - {} (1 hour)",
@@ -550,7 +677,7 @@ impl MarketDataService {
             );
 
             let records = storage
-                .range_query(start_ts, end_ts)
+                .range_query_instrument(instrument_id, start_ts, end_ts)
                 .map_err(|e| ExchangeError::InternalError(format!("Failed to query WAL: {}", e)))?;
 
             log::debug!(
@@ -582,7 +709,7 @@ impl MarketDataService {
                         );
                         return Ok(TickData {
                             instrument_id: inst_str,
-                            timestamp: timestamp / 1_000_000, // 纳秒转毫秒
+                            timestamp: nanos_to_millis(*timestamp),
                             last_price: *last_price,
                             bid_price: if *bid_price > 0.0 {
                                 Some(*bid_price)
@@ -617,11 +744,11 @@ impl MarketDataService {
     fn load_orderbook_from_storage(&self, instrument_id: &str) -> Result<OrderBookSnapshot> {
         if let Some(ref storage) = self.storage {
             // 查询最近1小时的数据
-            let end_ts = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
-            let start_ts = end_ts - (3600 * 1_000_000_000); // 1小时
+            let end_ts = now_nanos();
+            let start_ts = end_ts - secs_to_nanos(3600); // 1小时
 
             let records = storage
-                .range_query(start_ts, end_ts)
+                .range_query_instrument(instrument_id, start_ts, end_ts)
                 .map_err(|e| ExchangeError::InternalError(format!("Failed to query WAL: {}", e)))?;
 
             // 从后往前找最新的OrderBookSnapshot
@@ -658,7 +785,7 @@ impl MarketDataService {
 
                         return Ok(OrderBookSnapshot {
                             instrument_id: inst_str,
-                            timestamp: timestamp / 1_000_000, // 纳秒转毫秒
+                            timestamp: nanos_to_millis(*timestamp),
                             bids: bids_vec,
                             asks: asks_vec,
                             last_price: if *last_price > 0.0 {
@@ -688,19 +815,9 @@ impl MarketDataService {
         let mut total_asks = 0;
 
         for instrument_id in instruments {
-            if let Some(orderbook) = engine.get_orderbook(&instrument_id) {
-                let ob = orderbook.read();
-
-                let bid_count = ob
-                    .bid_queue
-                    .get_sorted_orders()
-                    .map(|v| v.len())
-                    .unwrap_or(0);
-                let ask_count = ob
-                    .ask_queue
-                    .get_sorted_orders()
-                    .map(|v| v.len())
-                    .unwrap_or(0);
+            if let Some(depth) = engine.get_depth_snapshot(&instrument_id) {
+                let bid_count = depth.bid_order_count;
+                let ask_count = depth.ask_order_count;
 
                 total_bids += bid_count;
                 total_asks += ask_count;
@@ -717,8 +834,10 @@ impl MarketDataService {
 
     /// 处理Tick数据并更新K线（成交时调用）
     /// @yutiansut @quantaxis
-    pub fn on_trade(&self, instrument_id: &str, price: f64, volume: i64) {
-        let timestamp_ms = chrono::Utc::now().timestamp_millis();
+    ///
+    /// trade_ts: 成交时间（纳秒），K线按成交时间归入周期，而不是按处理到达时间
+    pub fn on_trade(&self, instrument_id: &str, price: f64, volume: i64, trade_ts: i64) {
+        let timestamp_ms = nanos_to_millis(trade_ts);
 
         // ✨ Phase 10: 广播 Tick 事件给 KLineActor
         // KLineActor 订阅了这个事件来聚合 K 线数据
@@ -733,6 +852,8 @@ impl MarketDataService {
                 volume: volume as f64,
                 direction: "".to_string(),
                 timestamp: timestamp_ms,
+                origin_ts: now_nanos(),
+                match_batch_id: 0,
             });
         }
 
@@ -760,6 +881,7 @@ impl MarketDataService {
                     period: period.to_int(),
                     kline: kline.clone(),
                     timestamp: timestamp_ms,
+                    origin_ts: now_nanos(),
                 });
             }
         }
@@ -783,12 +905,22 @@ impl MarketDataService {
     ) -> Option<kline::KLine> {
         self.kline_manager.get_current_kline(instrument_id, period)
     }
+
+    /// K线聚合器句柄（交易日重置时清理当日K线）
+    pub fn kline_aggregators(&self) -> kline::KLineAggregators {
+        self.kline_manager.aggregators()
+    }
 }
 
 // 重新导出
+pub use anomaly::{
+    AnomalyAction, AnomalyEvent, AnomalyKind, AnomalyStats, MarketAnomalyConfig,
+    PriceAnomalyDetector, PriceSource, PriceVerdict,
+};
+pub use auction_indicator::AuctionIndicatorPublisher;
 pub use broadcaster::{MarketDataBroadcaster, MarketDataEvent};
 pub use cache::{CacheStatsSnapshot, MarketDataCache};
-pub use kline_actor::{GetCurrentKLine, GetKLines, KLineActor};
+pub use kline_actor::{GetCurrentKLine, GetKLines, GetKLinesBatch, KLineActor};
 pub use recovery::{MarketDataRecovery, RecoveredMarketData, RecoveryStats};
 pub use snapshot_broadcaster::SnapshotBroadcastService;
 pub use snapshot_generator::{MarketSnapshot, MarketSnapshotGenerator, SnapshotGeneratorConfig};
@@ -5,6 +5,7 @@
 use crate::market::{MarketDataCache, OrderBookSnapshot, PriceLevel, TickData};
 use crate::storage::hybrid::OltpHybridStorage;
 use crate::storage::wal::record::WalRecord;
+use crate::utils::timestamp::{nanos_to_millis, now_nanos, secs_to_nanos};
 use crate::ExchangeError;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -84,7 +85,7 @@ impl MarketDataRecovery {
                     let inst_str = WalRecord::from_fixed_array(&instrument_id);
                     let tick = TickData {
                         instrument_id: inst_str.clone(),
-                        timestamp: timestamp / 1_000_000, // 纳秒转毫秒
+                        timestamp: nanos_to_millis(timestamp),
                         last_price,
                         bid_price: if bid_price > 0.0 {
                             Some(bid_price)
@@ -142,7 +143,7 @@ impl MarketDataRecovery {
 
                     let snapshot = OrderBookSnapshot {
                         instrument_id: inst_str.clone(),
-                        timestamp: timestamp / 1_000_000, // 纳秒转毫秒
+                        timestamp: nanos_to_millis(timestamp),
                         bids: bids_vec,
                         asks: asks_vec,
                         last_price: if last_price > 0.0 {
@@ -214,8 +215,8 @@ impl MarketDataRecovery {
 
     /// 恢复最近N分钟的行情数据
     pub fn recover_recent_minutes(&self, minutes: i64) -> Result<RecoveryStats> {
-        let end_ts = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
-        let start_ts = end_ts - (minutes * 60 * 1_000_000_000);
+        let end_ts = now_nanos();
+        let start_ts = end_ts - secs_to_nanos(minutes * 60);
 
         self.recover_to_cache(start_ts, end_ts)
     }
@@ -9,6 +9,7 @@
 
 use crate::exchange::AccountManager;
 use crate::matching::engine::ExchangeMatchingEngine;
+use crate::service::overload::OVERLOAD_GUARD;
 use crossbeam::channel::{unbounded, Receiver, Sender};
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
@@ -249,7 +250,12 @@ impl MarketSnapshotGenerator {
             );
 
             loop {
-                std::thread::sleep(interval);
+                // 过载时快照广播降频，交易请求也被拒绝时暂停（优先保障撮合与成交）
+                let paced = OVERLOAD_GUARD.non_critical_interval(interval);
+                std::thread::sleep(paced.unwrap_or(interval));
+                if paced.is_none() {
+                    continue;
+                }
 
                 // 为每个合约生成快照
                 for instrument_id in &instruments {
@@ -277,87 +283,29 @@ impl MarketSnapshotGenerator {
 
     /// 生成单个合约的快照
     fn generate_snapshot(&self, instrument_id: &str) -> Result<MarketSnapshot, String> {
-        // 获取订单簿
-        let orderbook = self
+        // 读取只读深度快照（不获取订单簿锁，与撮合互不阻塞）
+        let depth = self
             .matching_engine
-            .get_orderbook(instrument_id)
+            .get_depth_snapshot(instrument_id)
             .ok_or_else(|| format!("Orderbook not found for {}", instrument_id))?;
 
-        let mut ob = orderbook.write();
-
-        // 获取买卖五档（qars 的 get_depth 不带参数，需要手动提取前5档）
-        //ob.get_depth(); // 更新内部深度
-
-        // 从 qars orderbook 手动提取买卖5档（聚合相同价格的订单数量）
-        let bids = if let Some(bid_orders) = ob.bid_queue.get_sorted_orders() {
-            use std::collections::HashMap;
-            let mut price_map: HashMap<String, f64> = HashMap::new();
-
-            // 聚合相同价格的订单数量
-            for order in bid_orders.iter().take(50) {
-                // 取足够多的订单以便聚合
-                *price_map.entry(order.price.to_string()).or_insert(0.0) += order.volume;
-            }
-
-            // 转换为 PriceLevel 并排序
-            let mut levels: Vec<super::PriceLevel> = price_map
-                .into_iter()
-                .filter_map(|(price_str, volume)| {
-                    price_str
-                        .parse::<f64>()
-                        .ok()
-                        .map(|price| super::PriceLevel {
-                            price,
-                            volume: volume as i64,
-                        })
-                })
-                .collect();
-
-            // 买盘按价格降序排列（价格从高到低）
-            levels.sort_by(|a, b| b.price.partial_cmp(&a.price).unwrap());
-
-            // 取前5档
-            levels.into_iter().take(5).collect()
-        } else {
-            Vec::new()
-        };
-
-        let asks = if let Some(ask_orders) = ob.ask_queue.get_sorted_orders() {
-            use std::collections::HashMap;
-            let mut price_map: HashMap<String, f64> = HashMap::new();
-
-            // 聚合相同价格的订单数量
-            for order in ask_orders.iter().take(50) {
-                // 取足够多的订单以便聚合
-                *price_map.entry(order.price.to_string()).or_insert(0.0) += order.volume;
-            }
-
-            // 转换为 PriceLevel 并排序
-            let mut levels: Vec<super::PriceLevel> = price_map
-                .into_iter()
-                .filter_map(|(price_str, volume)| {
-                    price_str
-                        .parse::<f64>()
-                        .ok()
-                        .map(|price| super::PriceLevel {
-                            price,
-                            volume: volume as i64,
-                        })
+        // 买卖五档（快照已按价位聚合并排序）
+        let to_levels = |levels: &[crate::matching::DepthLevel]| -> Vec<super::PriceLevel> {
+            levels
+                .iter()
+                .take(5)
+                .map(|level| super::PriceLevel {
+                    price: level.price,
+                    volume: level.volume as i64,
                 })
-                .collect();
-
-            // 卖盘按价格升序排列（价格从低到高）
-            levels.sort_by(|a, b| a.price.partial_cmp(&b.price).unwrap());
-
-            // 取前5档
-            levels.into_iter().take(5).collect()
-        } else {
-            Vec::new()
+                .collect()
         };
+        let bids = to_levels(&depth.bids);
+        let asks = to_levels(&depth.asks);
 
         // 获取最新价（qars 的 lastprice 是 f64，不是 Option<f64>）
-        let last_price = if ob.lastprice > 0.0 {
-            ob.lastprice
+        let last_price = if depth.last_price > 0.0 {
+            depth.last_price
         } else {
             0.0
         };
@@ -401,7 +349,10 @@ impl MarketSnapshotGenerator {
         let mut snapshot = MarketSnapshot {
             instrument_id: instrument_id.to_string(),
             timestamp: chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0),
-            trading_day: chrono::Local::now().format("%Y%m%d").to_string(),
+            trading_day: crate::utils::time_service::time_service()
+                .current_trading_day()
+                .format("%Y%m%d")
+                .to_string(),
             last_price,
             change_percent,
             change_amount,
@@ -0,0 +1,455 @@
+//! 行情订阅组管理
+//!
+//! @yutiansut @quantaxis
+//!
+//! 每个订阅者（WebSocket 会话 / DIFF 用户）可维护多个命名订阅组，
+//! 实际推送的合约为所有组的并集：
+//!
+//! - `create_group` / `replace_group` / `delete_group`：组级操作
+//! - 旧协议 subscribe/unsubscribe 增减默认组，DIFF `subscribe_quote` 全量替换默认组
+//! - 组内合约代码先经合约注册表归一，同一合约的新旧两种写法共用一个数据流
+//! - 设置了订阅配额检查（[`SubscriptionLimiter`]）时，变更后的合约数超限则拒绝整次变更
+//!
+//! ## 原子切换
+//! 组内容变化后在广播器上原地替换订阅（保留原通道），替换与投递互斥，
+//! 返回切换序号 `switch_seq`：该订阅者第 1..=switch_seq 条数据属于旧订阅，
+//! 第 switch_seq+1 条起属于新订阅，切换期间数据不重不漏。
+
+use super::broadcaster::{MarketDataBroadcaster, MarketDataEvent};
+use crate::ExchangeError;
+use crossbeam::channel::Receiver;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// 默认订阅组（旧协议 subscribe/unsubscribe 与 DIFF subscribe_quote 使用）
+pub const DEFAULT_GROUP: &str = "default";
+
+/// 单个订阅组
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubscriptionGroup {
+    pub name: String,
+    pub instruments: Vec<String>,
+}
+
+/// 订阅者的订阅组集合
+#[derive(Debug, Clone, Default)]
+pub struct SubscriberGroups {
+    /// 订阅频道（为空表示全部频道）
+    pub channels: Vec<String>,
+    /// 组名 -> 合约列表
+    pub groups: BTreeMap<String, Vec<String>>,
+}
+
+impl SubscriberGroups {
+    /// 所有组的合约并集（按组名顺序去重）
+    pub fn instruments(&self) -> Vec<String> {
+        let mut instruments: Vec<String> = Vec::new();
+        for instrument in self.groups.values().flatten() {
+            if !instruments.contains(instrument) {
+                instruments.push(instrument.clone());
+            }
+        }
+        instruments
+    }
+}
+
+/// 订阅配额检查：(订阅者, 变更前合约数, 变更后合约数)，超限时返回错误拒绝该次变更
+pub type SubscriptionLimiter =
+    Arc<dyn Fn(&str, usize, usize) -> Result<(), ExchangeError> + Send + Sync>;
+
+/// 订阅变更结果
+#[derive(Debug)]
+pub struct SubscriptionChange {
+    pub subscriber_id: String,
+    /// 变更后的合约并集
+    pub instruments: Vec<String>,
+    /// 新增合约
+    pub added: Vec<String>,
+    /// 移除合约
+    pub removed: Vec<String>,
+    /// 切换序号（切换前已投递给该订阅者的数据条数）
+    pub switch_seq: u64,
+    /// 首次订阅时创建的行情接收端（调用方需启动监听）；已有订阅时为 None，继续使用原接收端
+    pub receiver: Option<Receiver<MarketDataEvent>>,
+}
+
+/// 订阅组登记表
+#[derive(Default)]
+pub struct SubscriptionGroupRegistry {
+    subscribers: DashMap<String, SubscriberGroups>,
+}
+
+impl SubscriptionGroupRegistry {
+    /// 修改订阅者的订阅组，并在广播器上原子应用合约并集
+    ///
+    /// 持有该订阅者的登记项直到应用完成，同一订阅者的并发变更串行执行
+    pub fn modify<F>(
+        &self,
+        broadcaster: &MarketDataBroadcaster,
+        subscriber_id: &str,
+        f: F,
+    ) -> Result<SubscriptionChange, ExchangeError>
+    where
+        F: FnOnce(&mut SubscriberGroups) -> Result<(), ExchangeError>,
+    {
+        let mut entry = self
+            .subscribers
+            .entry(subscriber_id.to_string())
+            .or_default();
+        let before = entry.instruments();
+        let mut updated = entry.clone();
+        f(&mut updated)?;
+        let instruments = updated.instruments();
+        broadcaster.check_subscription_limit(subscriber_id, before.len(), instruments.len())?;
+
+        let (switch_seq, receiver) = if broadcaster.has_subscriber(subscriber_id) {
+            let seq = if instruments.is_empty() {
+                broadcaster.suspend_subscription(subscriber_id)?
+            } else {
+                broadcaster.replace_subscription(
+                    subscriber_id,
+                    instruments.clone(),
+                    updated.channels.clone(),
+                )?
+            };
+            (seq, None)
+        } else if instruments.is_empty() {
+            (0, None)
+        } else {
+            let receiver = broadcaster.subscribe(
+                subscriber_id.to_string(),
+                instruments.clone(),
+                updated.channels.clone(),
+            );
+            (0, Some(receiver))
+        };
+        *entry = updated;
+
+        Ok(SubscriptionChange {
+            subscriber_id: subscriber_id.to_string(),
+            added: instruments
+                .iter()
+                .filter(|i| !before.contains(i))
+                .cloned()
+                .collect(),
+            removed: before
+                .iter()
+                .filter(|i| !instruments.contains(i))
+                .cloned()
+                .collect(),
+            instruments,
+            switch_seq,
+            receiver,
+        })
+    }
+
+    /// 订阅者的订阅组
+    pub fn groups(&self, subscriber_id: &str) -> Vec<SubscriptionGroup> {
+        self.subscribers
+            .get(subscriber_id)
+            .map(|entry| {
+                entry
+                    .groups
+                    .iter()
+                    .map(|(name, instruments)| SubscriptionGroup {
+                        name: name.clone(),
+                        instruments: instruments.clone(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// 移除订阅者的所有订阅组
+    pub fn remove(&self, subscriber_id: &str) {
+        self.subscribers.remove(subscriber_id);
+    }
+}
+
+/// 规范化合约列表（去空白、去重，保持顺序）
+pub fn normalize_instruments(instruments: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::with_capacity(instruments.len());
+    for instrument in instruments {
+        let instrument = instrument.trim();
+        if !instrument.is_empty() && !normalized.iter().any(|i| i == instrument) {
+            normalized.push(instrument.to_string());
+        }
+    }
+    normalized
+}
+
+impl MarketDataBroadcaster {
+    /// 创建订阅组（组已存在时报错）
+    ///
+    /// `channels` 非空时同时设置该订阅者的订阅频道
+    pub fn create_group(
+        &self,
+        subscriber_id: &str,
+        group: &str,
+        instruments: Vec<String>,
+        channels: Vec<String>,
+    ) -> Result<SubscriptionChange, ExchangeError> {
+        let instruments = self.resolve_instruments(&instruments)?;
+        self.subscription_groups()
+            .modify(self, subscriber_id, |groups| {
+                if groups.groups.contains_key(group) {
+                    return Err(ExchangeError::InvalidParameter(format!(
+                        "Subscription group already exists: {}",
+                        group
+                    )));
+                }
+                if !channels.is_empty() {
+                    groups.channels = channels;
+                }
+                groups
+                    .groups
+                    .insert(group.to_string(), normalize_instruments(&instruments));
+                Ok(())
+            })
+    }
+
+    /// 原子替换订阅组内容（组不存在时创建）
+    pub fn replace_group(
+        &self,
+        subscriber_id: &str,
+        group: &str,
+        instruments: Vec<String>,
+    ) -> Result<SubscriptionChange, ExchangeError> {
+        let instruments = self.resolve_instruments(&instruments)?;
+        self.subscription_groups()
+            .modify(self, subscriber_id, |groups| {
+                groups
+                    .groups
+                    .insert(group.to_string(), normalize_instruments(&instruments));
+                Ok(())
+            })
+    }
+
+    /// 删除订阅组
+    pub fn delete_group(
+        &self,
+        subscriber_id: &str,
+        group: &str,
+    ) -> Result<SubscriptionChange, ExchangeError> {
+        self.subscription_groups()
+            .modify(self, subscriber_id, |groups| {
+                groups.groups.remove(group).map(|_| ()).ok_or_else(|| {
+                    ExchangeError::InvalidParameter(format!(
+                        "Subscription group not found: {}",
+                        group
+                    ))
+                })
+            })
+    }
+
+    /// 增减默认组的合约与订阅频道（旧协议 subscribe/unsubscribe）
+    pub fn update_default_group(
+        &self,
+        subscriber_id: &str,
+        add_instruments: &[String],
+        remove_instruments: &[String],
+        add_channels: &[String],
+        remove_channels: &[String],
+    ) -> Result<SubscriptionChange, ExchangeError> {
+        let add_instruments = self.resolve_instruments(add_instruments)?;
+        let remove_instruments = self.resolve_instruments(remove_instruments)?;
+        self.subscription_groups()
+            .modify(self, subscriber_id, |groups| {
+                for channel in add_channels {
+                    if !groups.channels.contains(channel) {
+                        groups.channels.push(channel.clone());
+                    }
+                }
+                groups.channels.retain(|ch| !remove_channels.contains(ch));
+
+                let default = groups.groups.entry(DEFAULT_GROUP.to_string()).or_default();
+                let mut instruments = default.clone();
+                instruments.extend(add_instruments.iter().cloned());
+                instruments.retain(|i| !remove_instruments.contains(i));
+                *default = normalize_instruments(&instruments);
+                if default.is_empty() {
+                    groups.groups.remove(DEFAULT_GROUP);
+                }
+                Ok(())
+            })
+    }
+
+    /// 订阅者的订阅组
+    pub fn list_groups(&self, subscriber_id: &str) -> Vec<SubscriptionGroup> {
+        self.subscription_groups().groups(subscriber_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn tick_instrument(event: &MarketDataEvent) -> &str {
+        match event {
+            MarketDataEvent::Tick { instrument_id, .. } => instrument_id,
+            _ => panic!("Expected Tick"),
+        }
+    }
+
+    /// 测试移仓换月切换：旧合约最后一条与新合约第一条数据序号连续
+    #[test]
+    fn test_replace_group_switch_is_gapless() {
+        let broadcaster = Arc::new(MarketDataBroadcaster::new());
+        let change = broadcaster
+            .create_group(
+                "strategy",
+                "main",
+                vec!["IF2501".to_string()],
+                vec!["tick".to_string()],
+            )
+            .unwrap();
+        let receiver = change.receiver.expect("首次订阅应创建接收端");
+
+        // 后台线程交替推送新旧合约行情
+        let producer = {
+            let broadcaster = broadcaster.clone();
+            std::thread::spawn(move || {
+                for i in 0..2000 {
+                    let instrument = if i % 2 == 0 { "IF2501" } else { "IF2502" };
+                    broadcaster.broadcast_tick(
+                        instrument.to_string(),
+                        4000.0,
+                        1.0,
+                        "buy".to_string(),
+                    );
+                }
+            })
+        };
+        broadcaster.broadcast_tick("IF2501".to_string(), 4000.0, 1.0, "buy".to_string());
+        std::thread::sleep(std::time::Duration::from_millis(1));
+
+        let change = broadcaster
+            .replace_group("strategy", "main", vec!["IF2502".to_string()])
+            .unwrap();
+        assert!(change.receiver.is_none(), "替换应复用原通道");
+        assert_eq!(change.added, vec!["IF2502".to_string()]);
+        assert_eq!(change.removed, vec!["IF2501".to_string()]);
+        broadcaster.broadcast_tick("IF2502".to_string(), 4001.0, 1.0, "buy".to_string());
+        producer.join().unwrap();
+
+        // 接收端按到达顺序编号，与投递序号一致
+        let events: Vec<(u64, String)> = receiver
+            .try_iter()
+            .enumerate()
+            .map(|(i, event)| (i as u64 + 1, tick_instrument(&event).to_string()))
+            .collect();
+        let last_old = events.iter().rev().find(|(_, i)| i == "IF2501").unwrap().0;
+        let first_new = events.iter().find(|(_, i)| i == "IF2502").unwrap().0;
+
+        assert_eq!(last_old, change.switch_seq);
+        assert_eq!(first_new, last_old + 1);
+        assert!(events[..change.switch_seq as usize]
+            .iter()
+            .all(|(_, i)| i == "IF2501"));
+        assert!(events[change.switch_seq as usize..]
+            .iter()
+            .all(|(_, i)| i == "IF2502"));
+    }
+
+    #[test]
+    fn test_group_union_and_delete() {
+        let broadcaster = MarketDataBroadcaster::new();
+        let receiver = broadcaster
+            .create_group("s1", "cffex", vec!["IF2501".to_string()], vec![])
+            .unwrap()
+            .receiver
+            .unwrap();
+        let change = broadcaster
+            .create_group(
+                "s1",
+                "shfe",
+                vec!["cu2501".to_string(), "IF2501".to_string()],
+                vec![],
+            )
+            .unwrap();
+        assert_eq!(
+            change.instruments,
+            vec!["IF2501".to_string(), "cu2501".to_string()]
+        );
+        assert!(broadcaster
+            .create_group("s1", "shfe", vec!["au2512".to_string()], vec![])
+            .is_err());
+
+        // 删除一个组后另一组共有的合约仍保留
+        let change = broadcaster.delete_group("s1", "shfe").unwrap();
+        assert_eq!(change.removed, vec!["cu2501".to_string()]);
+        broadcaster.broadcast_tick("cu2501".to_string(), 80000.0, 1.0, "buy".to_string());
+        broadcaster.broadcast_tick("IF2501".to_string(), 4000.0, 1.0, "buy".to_string());
+        assert_eq!(tick_instrument(&receiver.try_recv().unwrap()), "IF2501");
+        assert!(receiver.try_recv().is_err());
+
+        // 删除全部组后暂停推送但保留通道
+        broadcaster.delete_group("s1", "cffex").unwrap();
+        broadcaster.broadcast_tick("IF2501".to_string(), 4000.0, 1.0, "buy".to_string());
+        assert!(receiver.try_recv().is_err());
+        let change = broadcaster
+            .update_default_group("s1", &["IF2502".to_string()], &[], &[], &[])
+            .unwrap();
+        assert!(change.receiver.is_none());
+        assert_eq!(change.switch_seq, 1);
+        assert_eq!(broadcaster.list_groups("s1").len(), 1);
+        assert!(broadcaster.delete_group("s1", "missing").is_err());
+    }
+
+    /// 测试新旧合约代码订阅同一数据流
+    #[test]
+    fn test_legacy_and_canonical_codes_share_stream() {
+        use crate::exchange::instrument_registry::{InstrumentInfo, InstrumentType};
+        use crate::exchange::InstrumentRegistry;
+
+        let registry = Arc::new(InstrumentRegistry::new());
+        registry
+            .register(InstrumentInfo::new(
+                "SHFE.cu2501".to_string(),
+                "沪铜2501".to_string(),
+                InstrumentType::CommodityFuture,
+                "SHFE".to_string(),
+            ))
+            .unwrap();
+        let broadcaster = MarketDataBroadcaster::new();
+        broadcaster.set_instrument_registry(registry.clone());
+
+        let canonical = broadcaster
+            .replace_group("new", "main", vec!["shfe.cu2501".to_string()])
+            .unwrap()
+            .receiver
+            .unwrap();
+        let legacy = broadcaster
+            .update_default_group("old", &["cu2501".to_string()], &[], &[], &[])
+            .unwrap()
+            .receiver
+            .unwrap();
+        assert_eq!(
+            broadcaster.list_groups("old")[0].instruments,
+            vec!["SHFE.cu2501".to_string()]
+        );
+
+        broadcaster.broadcast_tick("SHFE.cu2501".to_string(), 80000.0, 1.0, "buy".to_string());
+        assert_eq!(
+            tick_instrument(&canonical.try_recv().unwrap()),
+            "SHFE.cu2501"
+        );
+        assert_eq!(tick_instrument(&legacy.try_recv().unwrap()), "SHFE.cu2501");
+
+        // 另一交易所上市同名合约后旧代码有歧义，订阅被拒绝
+        registry
+            .register(InstrumentInfo::new(
+                "DCE.cu2501".to_string(),
+                "cu2501".to_string(),
+                InstrumentType::CommodityFuture,
+                "DCE".to_string(),
+            ))
+            .unwrap();
+        assert!(broadcaster
+            .update_default_group("old", &["cu2501".to_string()], &[], &[], &[])
+            .is_err());
+    }
+}
@@ -0,0 +1,376 @@
+//! 订单簿挂单数与内存占用限制
+//!
+//! 大量挂单会持续占用撮合引擎内存。本模块在订单路由层跟踪每个合约的挂单（已进入订单簿、
+//! 尚未完全成交/撤销的订单），并提供：
+//! - 每合约挂单数与估算内存占用（挂单数 × 单订单估算大小），同步到 Prometheus
+//! - 单合约最大挂单数（可按合约配置）与全局挂单总数限制，超限拒绝新挂单
+//! - 撮合与撤单不受限制：成交/撤单会释放额度，恢复接单
+//! - 使用率达到告警阈值、以及触发限制时输出告警日志
+//!
+//! @yutiansut @quantaxis
+
+use crate::observability::metrics::{ORDERBOOK_MEMORY_BYTES, PENDING_ORDERS};
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// 单个挂单的默认估算内存（撮合订单节点 + 价格队列索引 + 路由层订单信息）
+pub const DEFAULT_ORDER_SIZE_BYTES: usize = 512;
+
+/// 订单簿限制配置
+#[derive(Debug, Clone)]
+pub struct OrderBookLimitConfig {
+    /// 单合约默认最大挂单数（None 表示不限制）
+    pub default_max_orders: Option<usize>,
+
+    /// 按合约覆盖的最大挂单数
+    pub instrument_max_orders: Vec<(String, usize)>,
+
+    /// 全局挂单总数上限（None 表示不限制）
+    pub global_max_orders: Option<usize>,
+
+    /// 单个挂单的估算内存（字节）
+    pub order_size_bytes: usize,
+
+    /// 告警阈值（使用率，0.0-1.0）
+    pub alert_ratio: f64,
+}
+
+impl Default for OrderBookLimitConfig {
+    fn default() -> Self {
+        Self {
+            default_max_orders: None,
+            instrument_max_orders: Vec::new(),
+            global_max_orders: None,
+            order_size_bytes: DEFAULT_ORDER_SIZE_BYTES,
+            alert_ratio: 0.9,
+        }
+    }
+}
+
+/// 单合约订单簿占用
+#[derive(Debug, Clone, Serialize)]
+pub struct OrderBookUsage {
+    pub instrument_id: String,
+    /// 当前挂单数
+    pub resting_orders: usize,
+    /// 估算内存占用（字节）
+    pub memory_bytes: usize,
+    /// 最大挂单数（None 表示不限制）
+    pub max_orders: Option<usize>,
+}
+
+/// 全局订单簿占用汇总
+#[derive(Debug, Clone, Serialize)]
+pub struct OrderBookUsageSummary {
+    /// 全局挂单总数
+    pub total_resting_orders: usize,
+    /// 全局估算内存占用（字节）
+    pub total_memory_bytes: usize,
+    /// 全局挂单上限
+    pub global_max_orders: Option<usize>,
+    /// 单订单估算大小（字节）
+    pub order_size_bytes: usize,
+    /// 按合约明细（按挂单数降序）
+    pub instruments: Vec<OrderBookUsage>,
+}
+
+/// 单合约挂单计数
+struct InstrumentBook {
+    resting: AtomicUsize,
+    /// 是否已发出使用率告警（回落到阈值以下后重置）
+    alerted: AtomicBool,
+}
+
+impl InstrumentBook {
+    fn new() -> Self {
+        Self {
+            resting: AtomicUsize::new(0),
+            alerted: AtomicBool::new(false),
+        }
+    }
+}
+
+/// 订单簿挂单限制器
+pub struct OrderBookLimiter {
+    config: OrderBookLimitConfig,
+
+    /// 合约 -> 最大挂单数（运行时可调整）
+    instrument_limits: DashMap<String, usize>,
+
+    /// 合约 -> 挂单计数
+    books: DashMap<String, InstrumentBook>,
+
+    /// 挂单 order_id -> 合约（保证进出订单簿的计数幂等）
+    resting_orders: DashMap<String, String>,
+
+    /// 全局挂单总数
+    total_resting: AtomicUsize,
+
+    /// 是否已发出全局使用率告警
+    global_alerted: AtomicBool,
+}
+
+impl OrderBookLimiter {
+    pub fn new(config: OrderBookLimitConfig) -> Self {
+        let instrument_limits = DashMap::new();
+        for (instrument_id, max_orders) in &config.instrument_max_orders {
+            instrument_limits.insert(instrument_id.clone(), *max_orders);
+        }
+
+        Self {
+            config,
+            instrument_limits,
+            books: DashMap::new(),
+            resting_orders: DashMap::new(),
+            total_resting: AtomicUsize::new(0),
+            global_alerted: AtomicBool::new(false),
+        }
+    }
+
+    /// 设置单合约最大挂单数
+    pub fn set_instrument_limit(&self, instrument_id: &str, max_orders: usize) {
+        self.instrument_limits
+            .insert(instrument_id.to_string(), max_orders);
+    }
+
+    /// 取消单合约覆盖配置（回落到默认上限）
+    pub fn remove_instrument_limit(&self, instrument_id: &str) {
+        self.instrument_limits.remove(instrument_id);
+    }
+
+    /// 合约的最大挂单数
+    pub fn max_orders(&self, instrument_id: &str) -> Option<usize> {
+        self.instrument_limits
+            .get(instrument_id)
+            .map(|v| *v)
+            .or(self.config.default_max_orders)
+    }
+
+    /// 合约当前挂单数
+    pub fn resting_orders(&self, instrument_id: &str) -> usize {
+        self.books
+            .get(instrument_id)
+            .map(|b| b.resting.load(Ordering::Relaxed))
+            .unwrap_or(0)
+    }
+
+    /// 全局挂单总数
+    pub fn total_resting_orders(&self) -> usize {
+        self.total_resting.load(Ordering::Relaxed)
+    }
+
+    /// 合约估算内存占用（字节）
+    pub fn memory_bytes(&self, instrument_id: &str) -> usize {
+        self.resting_orders(instrument_id) * self.config.order_size_bytes
+    }
+
+    /// 新挂单准入检查
+    ///
+    /// 仅限制会进入订单簿的新订单；撮合与撤单不经过此检查。
+    pub fn check_new_order(&self, instrument_id: &str) -> Result<(), String> {
+        if let Some(max_orders) = self.max_orders(instrument_id) {
+            let resting = self.resting_orders(instrument_id);
+            if resting >= max_orders {
+                log::warn!(
+                    "[OrderBookLimit] {} resting orders reached limit ({}/{}), rejecting new order",
+                    instrument_id,
+                    resting,
+                    max_orders
+                );
+                return Err(format!(
+                    "Order book full for {}: {} resting orders (limit {})",
+                    instrument_id, resting, max_orders
+                ));
+            }
+        }
+
+        if let Some(global_max) = self.config.global_max_orders {
+            let total = self.total_resting_orders();
+            if total >= global_max {
+                log::warn!(
+                    "[OrderBookLimit] global resting orders reached limit ({}/{}), rejecting new order on {}",
+                    total,
+                    global_max,
+                    instrument_id
+                );
+                return Err(format!(
+                    "Global order book limit reached: {} resting orders (limit {})",
+                    total, global_max
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 订单进入订单簿（撮合引擎 Accepted）
+    pub fn on_order_resting(&self, order_id: &str, instrument_id: &str) {
+        match self.resting_orders.entry(order_id.to_string()) {
+            Entry::Occupied(_) => return,
+            Entry::Vacant(entry) => {
+                entry.insert(instrument_id.to_string());
+            }
+        }
+
+        let resting = {
+            let book = self
+                .books
+                .entry(instrument_id.to_string())
+                .or_insert_with(InstrumentBook::new);
+            let resting = book.resting.fetch_add(1, Ordering::Relaxed) + 1;
+            if let Some(max_orders) = self.max_orders(instrument_id) {
+                if Self::over_alert(resting, max_orders, self.config.alert_ratio)
+                    && !book.alerted.swap(true, Ordering::Relaxed)
+                {
+                    log::warn!(
+                        "[OrderBookLimit] {} resting orders at {}/{} (~{} bytes)",
+                        instrument_id,
+                        resting,
+                        max_orders,
+                        resting * self.config.order_size_bytes
+                    );
+                }
+            }
+            resting
+        };
+        let total = self.total_resting.fetch_add(1, Ordering::Relaxed) + 1;
+        if let Some(global_max) = self.config.global_max_orders {
+            if Self::over_alert(total, global_max, self.config.alert_ratio)
+                && !self.global_alerted.swap(true, Ordering::Relaxed)
+            {
+                log::warn!(
+                    "[OrderBookLimit] global resting orders at {}/{} (~{} bytes)",
+                    total,
+                    global_max,
+                    total * self.config.order_size_bytes
+                );
+            }
+        }
+
+        self.update_metrics(instrument_id, resting);
+    }
+
+    /// 订单离开订单簿（全部成交 / 撤单 / 拒绝）
+    pub fn on_order_removed(&self, order_id: &str) {
+        let Some((_, instrument_id)) = self.resting_orders.remove(order_id) else {
+            return;
+        };
+
+        let resting = match self.books.get(&instrument_id) {
+            Some(book) => {
+                let resting = book.resting.fetch_sub(1, Ordering::Relaxed) - 1;
+                if let Some(max_orders) = self.max_orders(&instrument_id) {
+                    if !Self::over_alert(resting, max_orders, self.config.alert_ratio) {
+                        book.alerted.store(false, Ordering::Relaxed);
+                    }
+                }
+                resting
+            }
+            None => return,
+        };
+        let total = self.total_resting.fetch_sub(1, Ordering::Relaxed) - 1;
+        if let Some(global_max) = self.config.global_max_orders {
+            if !Self::over_alert(total, global_max, self.config.alert_ratio) {
+                self.global_alerted.store(false, Ordering::Relaxed);
+            }
+        }
+
+        self.update_metrics(&instrument_id, resting);
+    }
+
+    /// 汇总各合约订单簿占用
+    pub fn summary(&self) -> OrderBookUsageSummary {
+        let mut instruments: Vec<OrderBookUsage> = self
+            .books
+            .iter()
+            .map(|entry| {
+                let resting = entry.value().resting.load(Ordering::Relaxed);
+                OrderBookUsage {
+                    instrument_id: entry.key().clone(),
+                    resting_orders: resting,
+                    memory_bytes: resting * self.config.order_size_bytes,
+                    max_orders: self.max_orders(entry.key()),
+                }
+            })
+            .collect();
+        instruments.sort_by(|a, b| b.resting_orders.cmp(&a.resting_orders));
+
+        let total = self.total_resting_orders();
+        OrderBookUsageSummary {
+            total_resting_orders: total,
+            total_memory_bytes: total * self.config.order_size_bytes,
+            global_max_orders: self.config.global_max_orders,
+            order_size_bytes: self.config.order_size_bytes,
+            instruments,
+        }
+    }
+
+    fn over_alert(count: usize, max: usize, ratio: f64) -> bool {
+        count as f64 >= max as f64 * ratio
+    }
+
+    fn update_metrics(&self, instrument_id: &str, resting: usize) {
+        PENDING_ORDERS
+            .with_label_values(&[instrument_id])
+            .set(resting as i64);
+        ORDERBOOK_MEMORY_BYTES
+            .with_label_values(&[instrument_id])
+            .set((resting * self.config.order_size_bytes) as i64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instrument_limit_and_release() {
+        let limiter = OrderBookLimiter::new(OrderBookLimitConfig {
+            default_max_orders: Some(2),
+            ..Default::default()
+        });
+
+        limiter.on_order_resting("O1", "IF2501");
+        assert!(limiter.check_new_order("IF2501").is_ok());
+        limiter.on_order_resting("O2", "IF2501");
+        // 重复进入不重复计数
+        limiter.on_order_resting("O2", "IF2501");
+        assert_eq!(limiter.resting_orders("IF2501"), 2);
+        assert!(limiter.check_new_order("IF2501").is_err());
+        // 其他合约不受影响
+        assert!(limiter.check_new_order("IC2501").is_ok());
+
+        limiter.on_order_removed("O1");
+        limiter.on_order_removed("O1");
+        assert_eq!(limiter.resting_orders("IF2501"), 1);
+        assert!(limiter.check_new_order("IF2501").is_ok());
+        assert_eq!(limiter.memory_bytes("IF2501"), DEFAULT_ORDER_SIZE_BYTES);
+    }
+
+    #[test]
+    fn test_per_instrument_override_and_global_limit() {
+        let limiter = OrderBookLimiter::new(OrderBookLimitConfig {
+            default_max_orders: Some(10),
+            instrument_max_orders: vec![("IF2501".to_string(), 1)],
+            global_max_orders: Some(3),
+            order_size_bytes: 100,
+            ..Default::default()
+        });
+        assert_eq!(limiter.max_orders("IF2501"), Some(1));
+        assert_eq!(limiter.max_orders("IC2501"), Some(10));
+
+        limiter.on_order_resting("O1", "IF2501");
+        assert!(limiter.check_new_order("IF2501").is_err());
+
+        limiter.on_order_resting("O2", "IC2501");
+        limiter.on_order_resting("O3", "IH2501");
+        assert!(limiter.check_new_order("IC2501").is_err());
+
+        let summary = limiter.summary();
+        assert_eq!(summary.total_resting_orders, 3);
+        assert_eq!(summary.total_memory_bytes, 300);
+        assert_eq!(summary.instruments.len(), 3);
+    }
+}
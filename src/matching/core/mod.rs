@@ -2,16 +2,18 @@
 //!
 //! 设计原则：
 //! 1. 单进程多线程 - 每个品种一个线程
-//! 2. 零拷贝通信 - 通过 iceoryx2 接收订单和发送成交
+//! 2. 零拷贝通信 - 通过 iceoryx2 接收订单和发送成交（`from_ipc`），
+//!    单进程模式下使用 crossbeam（`new`）
 //! 3. 无状态撮合 - 不维护账户信息，只负责订单匹配
 //! 4. 内存池 - 预分配订单对象，避免 GC
 
 use crate::matching::engine::InstrumentAsset;
-use crate::matching::Orderbook;
+use crate::matching::{DepthSnapshot, DepthView, Orderbook, TrackedOrderbook};
+use crate::ipc::{MessageReceiver, MessageSender};
 use crate::protocol::ipc_messages::{OrderAccepted, OrderRequest, OrderbookSnapshot, TradeReport};
-use crossbeam::channel::{bounded, Receiver, Sender};
+use crossbeam::channel::{Receiver, Sender};
 use dashmap::DashMap;
-use parking_lot::RwLock;
+use std::sync::atomic::AtomicI64;
 use std::sync::Arc;
 
 /// 撮合引擎核心
@@ -19,38 +21,76 @@ use std::sync::Arc;
 /// 运行在独立进程中，通过 iceoryx2 接收订单请求，发送成交回报
 pub struct MatchingEngineCore {
     /// 订单簿池（每个品种独立）
-    orderbooks: DashMap<String, Arc<RwLock<Orderbook<InstrumentAsset>>>>,
+    orderbooks: DashMap<String, Arc<TrackedOrderbook>>,
 
-    /// 订单接收通道（暂时用 crossbeam，后续替换为 iceoryx2）
-    order_receiver: Receiver<OrderRequest>,
+    /// 只读深度视图（撮合释放订单簿写锁前发布）
+    depth_view: Arc<DepthView>,
 
-    /// 成交发送通道（暂时用 crossbeam，后续替换为 iceoryx2）
-    trade_sender: Sender<TradeReport>,
+    /// 订单接收通道（crossbeam / iceoryx2）
+    order_receiver: MessageReceiver<OrderRequest>,
 
-    /// 行情发送通道（暂时用 crossbeam，后续替换为 iceoryx2）
-    market_sender: Sender<OrderbookSnapshot>,
+    /// 成交发送通道（crossbeam / iceoryx2）
+    trade_sender: MessageSender<TradeReport>,
+
+    /// 行情发送通道（crossbeam / iceoryx2）
+    market_sender: MessageSender<OrderbookSnapshot>,
 
     /// 订单确认发送通道（用于 sim 模式的 on_order_confirm）
-    accepted_sender: Sender<OrderAccepted>,
+    accepted_sender: MessageSender<OrderAccepted>,
+
+    /// 撮合批次序号（每次撮合调用分配一个）
+    match_batch_seq: AtomicI64,
 
     /// 运行标志
     running: Arc<std::sync::atomic::AtomicBool>,
 }
 
 impl MatchingEngineCore {
-    /// 创建撮合引擎核心
+    /// 创建撮合引擎核心（单进程模式，crossbeam 通道）
     pub fn new(
         order_receiver: Receiver<OrderRequest>,
         trade_sender: Sender<TradeReport>,
         market_sender: Sender<OrderbookSnapshot>,
         accepted_sender: Sender<OrderAccepted>,
+    ) -> Self {
+        Self::with_channels(
+            order_receiver.into(),
+            trade_sender.into(),
+            market_sender.into(),
+            accepted_sender.into(),
+        )
+    }
+
+    /// 创建撮合引擎核心（同机多进程模式，iceoryx2 共享内存）
+    ///
+    /// 订阅 `core/order_requests`，发布 `core/trade_reports`、`core/order_accepted`、
+    /// `core/orderbook_snapshots`
+    #[cfg(feature = "iceoryx2")]
+    pub fn from_ipc(config: &crate::ipc::IpcConfig) -> Result<Self, String> {
+        use crate::ipc::channel::topics;
+
+        Ok(Self::with_channels(
+            MessageReceiver::ipc(config, topics::ORDER_REQUESTS)?,
+            MessageSender::ipc(config, topics::TRADE_REPORTS)?,
+            MessageSender::ipc(config, topics::ORDERBOOK_SNAPSHOTS)?,
+            MessageSender::ipc(config, topics::ORDER_ACCEPTED)?,
+        ))
+    }
+
+    fn with_channels(
+        order_receiver: MessageReceiver<OrderRequest>,
+        trade_sender: MessageSender<TradeReport>,
+        market_sender: MessageSender<OrderbookSnapshot>,
+        accepted_sender: MessageSender<OrderAccepted>,
     ) -> Self {
         Self {
             orderbooks: DashMap::new(),
+            depth_view: Arc::new(DepthView::default()),
             order_receiver,
             trade_sender,
             market_sender,
             accepted_sender,
+            match_batch_seq: AtomicI64::new(0),
             running: Arc::new(std::sync::atomic::AtomicBool::new(false)),
         }
     }
@@ -58,14 +98,20 @@ impl MatchingEngineCore {
     /// 注册品种
     pub fn register_instrument(&self, instrument_id: String, init_price: f64) {
         let orderbook = Orderbook::new(InstrumentAsset::from_code(&instrument_id), init_price);
+        let orderbook = TrackedOrderbook::new(&instrument_id, orderbook, self.depth_view.clone());
         self.orderbooks
-            .insert(instrument_id.clone(), Arc::new(RwLock::new(orderbook)));
+            .insert(instrument_id.clone(), Arc::new(orderbook));
         log::info!(
             "Registered instrument in MatchingEngineCore: {}",
             instrument_id
         );
     }
 
+    /// 获取合约最新深度快照（不获取订单簿锁）
+    pub fn get_depth_snapshot(&self, instrument_id: &str) -> Option<Arc<DepthSnapshot>> {
+        self.depth_view.get(instrument_id)
+    }
+
     /// 启动撮合引擎主循环
     pub fn run(&self) {
         use std::sync::atomic::Ordering;
@@ -79,10 +125,10 @@ impl MatchingEngineCore {
                 .order_receiver
                 .recv_timeout(std::time::Duration::from_millis(10))
             {
-                Ok(order_req) => {
+                Some(order_req) => {
                     self.process_order(order_req);
                 }
-                Err(_) => {
+                None => {
                     // 超时，继续循环
                     continue;
                 }
@@ -112,16 +158,20 @@ impl MatchingEngineCore {
         // 3. 转换为撮合引擎订单
         let match_order = self.convert_to_match_order(&order_req);
 
-        // 4. 执行撮合（核心操作）
+        // 4. 执行撮合（核心操作，释放写锁前发布深度快照）
         let mut ob = orderbook.write();
         let results = ob.process_order(match_order);
         drop(ob); // 尽早释放锁
+        let match_batch_id = self
+            .match_batch_seq
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+            + 1;
 
-        // 5. 处理撮合结果
+        // 5. 处理撮合结果（同一批次的成交回报携带相同批次号）
         for result in results {
             match result {
                 Ok(success) => {
-                    self.handle_success(success, &order_req);
+                    self.handle_success(success, &order_req, match_batch_id);
                 }
                 Err(failed) => {
                     log::warn!("Matching failed: {:?}", failed);
@@ -157,7 +207,12 @@ impl MatchingEngineCore {
     }
 
     /// 处理成功的撮合结果
-    fn handle_success(&self, success: crate::matching::Success, req: &OrderRequest) {
+    fn handle_success(
+        &self,
+        success: crate::matching::Success,
+        req: &OrderRequest,
+        match_batch_id: i64,
+    ) {
         use crate::matching::Success;
 
         match success {
@@ -165,7 +220,8 @@ impl MatchingEngineCore {
                 price, volume, ts, ..
             } => {
                 // 发送成交回报
-                let trade = self.create_trade_report(req, price, volume, ts, 0); // 0=完全成交
+                // 0=完全成交
+                let trade = self.create_trade_report(req, price, volume, ts, 0, match_batch_id);
                 let _ = self.trade_sender.send(trade);
 
                 log::debug!(
@@ -179,7 +235,8 @@ impl MatchingEngineCore {
                 price, volume, ts, ..
             } => {
                 // 发送部分成交回报
-                let trade = self.create_trade_report(req, price, volume, ts, 1); // 1=部分成交
+                // 1=部分成交
+                let trade = self.create_trade_report(req, price, volume, ts, 1, match_batch_id);
                 let _ = self.trade_sender.send(trade);
 
                 log::debug!(
@@ -211,6 +268,7 @@ impl MatchingEngineCore {
         volume: f64,
         timestamp: i64,
         fill_type: u8,
+        match_batch_id: i64,
     ) -> TradeReport {
         let mut trade = TradeReport {
             trade_id: [0; 32],
@@ -226,6 +284,7 @@ impl MatchingEngineCore {
             volume,
             commission: price * volume * 0.0003, // 万三手续费
             timestamp,
+            match_batch_id,
             opposite_order_id: [0; 32],
             gateway_id: req.gateway_id,
             session_id: req.session_id,
@@ -326,5 +385,10 @@ mod tests {
 
         // 验证是否产生成交回报（如果有对手盘）
         // 由于是第一个订单，不会立即成交
+
+        // 撮合后深度快照已刷新
+        let depth = engine.get_depth_snapshot("IX2401").unwrap();
+        assert_eq!(depth.sequence, 1);
+        assert_eq!(depth.best_bid(), Some(100.0));
     }
 }
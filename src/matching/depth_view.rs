@@ -0,0 +1,362 @@
+//! 订单簿只读深度视图
+//!
+//! @yutiansut @quantaxis
+//!
+//! 撮合在持有订单簿写锁、完成一次撮合事件后，把深度摘要（前 N 档、最新价、买卖总量）
+//! 生成不可变的 [`DepthSnapshot`] 并通过 `ArcSwap` 原子替换；查询/监控/广播路径只读快照，
+//! 不再获取订单簿锁，与撮合线程互不阻塞。
+//!
+//! 快照在同一把写锁内发布，因此相对订单簿最多滞后一次撮合事件。
+//!
+//! 订单簿以 [`TrackedOrderbook`] 持有：任何经 `write()` 取得写锁的修改（引擎入口、分片线程、
+//! 高性能撮合线程、直接操作订单簿的调用方）都在释放写锁前刷新快照，不会绕过快照发布。
+
+use arc_swap::ArcSwap;
+use dashmap::DashMap;
+use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use serde::{Deserialize, Serialize};
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+
+use super::engine::InstrumentAsset;
+use super::Orderbook;
+
+/// 快照默认保留的档位数
+pub const DEFAULT_DEPTH_LEVELS: usize = 20;
+
+/// 单个价位的聚合深度
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DepthLevel {
+    pub price: f64,
+    pub volume: f64,
+    /// 该价位挂单笔数
+    pub order_count: usize,
+}
+
+/// 订单簿深度快照（不可变）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DepthSnapshot {
+    pub instrument_id: String,
+    /// 撮合事件序号（每次发布 +1，注册合约时为 0）
+    pub sequence: u64,
+    pub last_price: f64,
+    /// 买盘（价格降序，最多 N 档）
+    pub bids: Vec<DepthLevel>,
+    /// 卖盘（价格升序，最多 N 档）
+    pub asks: Vec<DepthLevel>,
+    /// 买盘全部挂单总量（不限档位）
+    pub total_bid_volume: f64,
+    /// 卖盘全部挂单总量（不限档位）
+    pub total_ask_volume: f64,
+    pub bid_order_count: usize,
+    pub ask_order_count: usize,
+}
+
+impl DepthSnapshot {
+    /// 从订单簿生成快照（调用方需持有订单簿锁）
+    pub fn from_orderbook(
+        instrument_id: &str,
+        ob: &Orderbook<InstrumentAsset>,
+        levels: usize,
+        sequence: u64,
+    ) -> Self {
+        let bid_orders = ob.bid_queue.get_sorted_orders().unwrap_or_default();
+        let ask_orders = ob.ask_queue.get_sorted_orders().unwrap_or_default();
+        let (bids, total_bid_volume) =
+            Self::aggregate(bid_orders.iter().map(|o| (o.price, o.volume)), levels);
+        let (asks, total_ask_volume) =
+            Self::aggregate(ask_orders.iter().map(|o| (o.price, o.volume)), levels);
+
+        Self {
+            instrument_id: instrument_id.to_string(),
+            sequence,
+            last_price: ob.lastprice,
+            bids,
+            asks,
+            total_bid_volume,
+            total_ask_volume,
+            bid_order_count: bid_orders.len(),
+            ask_order_count: ask_orders.len(),
+        }
+    }
+
+    /// 按价位聚合已排序的挂单（同价位订单相邻），返回前 N 档及全部挂单总量
+    fn aggregate(
+        orders: impl Iterator<Item = (f64, f64)>,
+        levels: usize,
+    ) -> (Vec<DepthLevel>, f64) {
+        let mut result: Vec<DepthLevel> = Vec::with_capacity(levels);
+        let mut total_volume = 0.0;
+
+        for (price, volume) in orders {
+            total_volume += volume;
+            match result.last_mut() {
+                Some(level) if level.price == price => {
+                    level.volume += volume;
+                    level.order_count += 1;
+                }
+                _ if result.len() < levels => result.push(DepthLevel {
+                    price,
+                    volume,
+                    order_count: 1,
+                }),
+                _ => {}
+            }
+        }
+        (result, total_volume)
+    }
+
+    /// 买一价
+    pub fn best_bid(&self) -> Option<f64> {
+        self.bids.first().map(|l| l.price)
+    }
+
+    /// 卖一价
+    pub fn best_ask(&self) -> Option<f64> {
+        self.asks.first().map(|l| l.price)
+    }
+}
+
+/// 各合约的只读深度视图
+pub struct DepthView {
+    levels: usize,
+    snapshots: DashMap<String, Arc<ArcSwap<DepthSnapshot>>>,
+}
+
+impl DepthView {
+    pub fn new(levels: usize) -> Self {
+        Self {
+            levels: levels.max(1),
+            snapshots: DashMap::new(),
+        }
+    }
+
+    /// 快照保留的档位数
+    pub fn levels(&self) -> usize {
+        self.levels
+    }
+
+    /// 撮合事件完成后发布新快照（调用方需持有该合约订单簿写锁，保证同一合约的发布串行）
+    pub fn publish(&self, instrument_id: &str, ob: &Orderbook<InstrumentAsset>) {
+        let slot = self.snapshots.get(instrument_id).map(|s| s.value().clone());
+        match slot {
+            Some(slot) => {
+                let sequence = slot.load().sequence + 1;
+                slot.store(Arc::new(DepthSnapshot::from_orderbook(
+                    instrument_id,
+                    ob,
+                    self.levels,
+                    sequence,
+                )));
+            }
+            None => {
+                let snapshot = DepthSnapshot::from_orderbook(instrument_id, ob, self.levels, 0);
+                self.snapshots.insert(
+                    instrument_id.to_string(),
+                    Arc::new(ArcSwap::from_pointee(snapshot)),
+                );
+            }
+        }
+    }
+
+    /// 读取最新快照（无锁，不触碰订单簿）
+    pub fn get(&self, instrument_id: &str) -> Option<Arc<DepthSnapshot>> {
+        self.snapshots
+            .get(instrument_id)
+            .map(|s| s.value().load_full())
+    }
+
+    /// 移除合约快照（合约卸载时调用）
+    pub fn remove(&self, instrument_id: &str) {
+        self.snapshots.remove(instrument_id);
+    }
+}
+
+impl Default for DepthView {
+    fn default() -> Self {
+        Self::new(DEFAULT_DEPTH_LEVELS)
+    }
+}
+
+/// 释放写锁前自动发布深度快照的订单簿
+pub struct TrackedOrderbook {
+    instrument_id: String,
+    book: RwLock<Orderbook<InstrumentAsset>>,
+    /// 快照发布目标（分片迁移时切换到目标分片的视图）
+    view: ArcSwap<DepthView>,
+}
+
+impl TrackedOrderbook {
+    /// 包装订单簿并发布初始快照
+    pub fn new(
+        instrument_id: impl Into<String>,
+        orderbook: Orderbook<InstrumentAsset>,
+        view: Arc<DepthView>,
+    ) -> Self {
+        let instrument_id = instrument_id.into();
+        view.publish(&instrument_id, &orderbook);
+        Self {
+            instrument_id,
+            book: RwLock::new(orderbook),
+            view: ArcSwap::new(view),
+        }
+    }
+
+    pub fn instrument_id(&self) -> &str {
+        &self.instrument_id
+    }
+
+    /// 只读访问（不发布快照）
+    pub fn read(&self) -> RwLockReadGuard<'_, Orderbook<InstrumentAsset>> {
+        self.book.read()
+    }
+
+    /// 写访问，守卫释放时在写锁内发布快照
+    pub fn write(&self) -> TrackedWriteGuard<'_> {
+        TrackedWriteGuard {
+            guard: self.book.write(),
+            book: self,
+        }
+    }
+
+    /// 切换快照发布目标并立即发布（分片迁移时使用）
+    pub fn rebind(&self, view: Arc<DepthView>) {
+        let ob = self.book.write();
+        view.publish(&self.instrument_id, &ob);
+        self.view.store(view);
+    }
+}
+
+/// [`TrackedOrderbook`] 写锁守卫
+pub struct TrackedWriteGuard<'a> {
+    guard: RwLockWriteGuard<'a, Orderbook<InstrumentAsset>>,
+    book: &'a TrackedOrderbook,
+}
+
+impl Deref for TrackedWriteGuard<'_> {
+    type Target = Orderbook<InstrumentAsset>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.guard
+    }
+}
+
+impl DerefMut for TrackedWriteGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.guard
+    }
+}
+
+impl Drop for TrackedWriteGuard<'_> {
+    fn drop(&mut self) {
+        // 字段（写锁）在此之后才释放，发布仍在写锁内
+        self.book
+            .view
+            .load()
+            .publish(&self.book.instrument_id, &self.guard);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matching::{orders, OrderDirection};
+
+    fn limit(
+        direction: OrderDirection,
+        price: f64,
+        volume: f64,
+        ts: i64,
+    ) -> orders::OrderRequest<InstrumentAsset> {
+        orders::new_limit_order_request(
+            InstrumentAsset::from_code("DV001"),
+            direction,
+            price,
+            volume,
+            ts,
+        )
+    }
+
+    #[test]
+    fn test_publish_aggregates_levels_and_totals() {
+        let view = DepthView::new(2);
+        let mut ob = Orderbook::new(InstrumentAsset::from_code("DV001"), 100.0);
+        view.publish("DV001", &ob);
+        assert_eq!(view.get("DV001").unwrap().sequence, 0);
+
+        for (i, (direction, price, volume)) in [
+            (OrderDirection::BUY, 99.0, 2.0),
+            (OrderDirection::BUY, 99.0, 3.0),
+            (OrderDirection::BUY, 98.0, 1.0),
+            (OrderDirection::BUY, 97.0, 4.0),
+            (OrderDirection::SELL, 101.0, 5.0),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let _ = ob.process_order(limit(direction, price, volume, i as i64 + 1));
+            view.publish("DV001", &ob);
+        }
+
+        let snapshot = view.get("DV001").unwrap();
+        assert_eq!(snapshot.sequence, 5);
+        assert_eq!(snapshot.bids.len(), 2);
+        assert_eq!(
+            snapshot.bids[0],
+            DepthLevel {
+                price: 99.0,
+                volume: 5.0,
+                order_count: 2
+            }
+        );
+        assert_eq!(snapshot.bids[1].price, 98.0);
+        // 总量不受档位限制
+        assert_eq!(snapshot.total_bid_volume, 10.0);
+        assert_eq!(snapshot.bid_order_count, 4);
+        assert_eq!(snapshot.best_ask(), Some(101.0));
+        assert_eq!(snapshot.total_ask_volume, 5.0);
+
+        // 已取出的旧快照不受后续发布影响
+        let _ = ob.process_order(limit(OrderDirection::SELL, 99.0, 5.0, 10));
+        view.publish("DV001", &ob);
+        let latest = view.get("DV001").unwrap();
+        assert_eq!(latest.last_price, 99.0);
+        assert_eq!(latest.best_bid(), Some(98.0));
+        assert_eq!(snapshot.best_bid(), Some(99.0));
+
+        view.remove("DV001");
+        assert!(view.get("DV001").is_none());
+    }
+
+    #[test]
+    fn test_tracked_orderbook_publishes_on_write_guard_release() {
+        let view = Arc::new(DepthView::default());
+        let book = TrackedOrderbook::new(
+            "DV001",
+            Orderbook::new(InstrumentAsset::from_code("DV001"), 100.0),
+            view.clone(),
+        );
+        assert_eq!(view.get("DV001").unwrap().sequence, 0);
+
+        // 直接修改订单簿，释放写锁后快照即包含该修改
+        let _ = book
+            .write()
+            .process_order(limit(OrderDirection::BUY, 99.0, 2.0, 1));
+        let snapshot = view.get("DV001").unwrap();
+        assert_eq!(snapshot.sequence, 1);
+        assert_eq!(snapshot.best_bid(), Some(99.0));
+
+        // 读锁不发布
+        assert_eq!(book.read().bid_queue.get_sorted_orders().unwrap().len(), 1);
+        assert_eq!(view.get("DV001").unwrap().sequence, 1);
+
+        // 切换视图后发布到新视图
+        let other = Arc::new(DepthView::default());
+        book.rebind(other.clone());
+        let _ = book
+            .write()
+            .process_order(limit(OrderDirection::SELL, 101.0, 1.0, 2));
+        assert_eq!(other.get("DV001").unwrap().best_ask(), Some(101.0));
+        assert_eq!(view.get("DV001").unwrap().best_ask(), None);
+    }
+}
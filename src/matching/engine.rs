@@ -1,10 +1,22 @@
 //! 交易所撮合引擎
 //!
 //! 基于 qars::Orderbook 的封装，添加成交记录和行情推送功能
+//!
+//! 订单簿写锁释放前同步发布只读深度快照（见 `depth_view::TrackedOrderbook`），
+//! 查询/监控路径通过 [`ExchangeMatchingEngine::get_depth_snapshot`] 读取，不再获取订单簿锁
+//!
+//! 新订单进入订单簿前经 [`ExchangeMatchingEngine::order_sequencer`] 按合约排队（见 `sequencer`），
+//! 同一合约的订单严格按到达顺序撮合
 
 use crate::core::Order;
+use crate::exchange::deterministic::ExchangeClock;
+use crate::matching::auction::{AuctionCalculator, AuctionResult};
+use crate::matching::depth_view::{DepthSnapshot, DepthView, TrackedOrderbook};
+use crate::matching::sequencer::OrderSequencer;
 use crate::matching::trade_recorder::TradeRecorder;
-use crate::matching::{Failed, OrderProcessingResult, Orderbook, Success, TradingState};
+use crate::matching::{
+    orders, Failed, OrderDirection, OrderProcessingResult, Orderbook, Success, TradingState,
+};
 use crate::ExchangeError;
 use dashmap::DashMap;
 use parking_lot::RwLock;
@@ -24,10 +36,111 @@ impl InstrumentAsset {
     }
 }
 
+/// 订单簿中的挂单（撮合引擎视角，用于订单状态查询与对账）
+#[derive(Debug, Clone, PartialEq)]
+pub struct RestingOrder {
+    /// 撮合引擎订单ID
+    pub engine_order_id: u64,
+    /// BUY / SELL
+    pub direction: &'static str,
+    pub price: f64,
+    /// 剩余挂单量
+    pub volume: f64,
+}
+
+/// 订单簿全部挂单（调用方需持有订单簿锁）
+pub fn resting_orders(ob: &Orderbook<InstrumentAsset>) -> Vec<RestingOrder> {
+    let bids = ob.bid_queue.get_sorted_orders().unwrap_or_default();
+    let asks = ob.ask_queue.get_sorted_orders().unwrap_or_default();
+    bids.iter()
+        .map(|o| ("BUY", o))
+        .chain(asks.iter().map(|o| ("SELL", o)))
+        .map(|(direction, o)| RestingOrder {
+            engine_order_id: o.order_id,
+            direction,
+            price: o.price,
+            volume: o.volume,
+        })
+        .collect()
+}
+
+/// 集合竞价开盘的一笔撮合（撮合引擎订单ID）
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuctionFill {
+    pub buy_order_id: u64,
+    pub sell_order_id: u64,
+    pub price: f64,
+    pub volume: f64,
+}
+
+/// 集合竞价开盘结果
+#[derive(Debug, Clone)]
+pub struct AuctionOpen {
+    /// 开盘价与开盘成交量（竞价期挂单无法撮合时为 None）
+    pub result: Option<AuctionResult>,
+    /// 开盘撮合明细，成交量合计等于开盘成交量
+    pub fills: Vec<AuctionFill>,
+    /// 转入连续交易订单簿的剩余挂单ID映射 (原ID, 新ID)
+    pub remapped: Vec<(u64, u64)>,
+}
+
+/// 按记录顺序把挂单重新提交到订单簿，返回撮合引擎订单ID映射 (原ID, 新ID)
+///
+/// 记录之间发生成交说明挂单记录自相矛盾，返回错误
+fn resubmit_orders(
+    ob: &mut Orderbook<InstrumentAsset>,
+    instrument_id: &str,
+    orders: &[RestingOrder],
+) -> Result<Vec<(u64, u64)>, ExchangeError> {
+    let asset = InstrumentAsset::from_code(instrument_id);
+    let mut remapped = Vec::with_capacity(orders.len());
+    for (seq, order) in orders.iter().enumerate() {
+        let direction = match order.direction {
+            "BUY" => OrderDirection::BUY,
+            _ => OrderDirection::SELL,
+        };
+        let request = orders::new_limit_order_request(
+            asset,
+            direction,
+            order.price,
+            order.volume,
+            seq as i64 + 1,
+        );
+        let mut new_id = None;
+        for result in ob.process_order(request) {
+            match result {
+                Ok(Success::Accepted { id, .. }) => new_id = Some(id),
+                Ok(_) => {
+                    return Err(ExchangeError::MatchingError(format!(
+                        "Resting order {} of {} crosses the rebuilt book",
+                        order.engine_order_id, instrument_id
+                    )))
+                }
+                Err(e) => {
+                    return Err(ExchangeError::MatchingError(format!(
+                        "Resting order {} of {} rejected during rebuild: {:?}",
+                        order.engine_order_id, instrument_id, e
+                    )))
+                }
+            }
+        }
+        match new_id {
+            Some(id) => remapped.push((order.engine_order_id, id)),
+            None => {
+                return Err(ExchangeError::MatchingError(format!(
+                    "Resting order {} of {} not accepted during rebuild",
+                    order.engine_order_id, instrument_id
+                )))
+            }
+        }
+    }
+    Ok(remapped)
+}
+
 /// 交易所撮合引擎
 pub struct ExchangeMatchingEngine {
     /// 合约代码 -> 订单簿映射
-    orderbooks: DashMap<String, Arc<RwLock<Orderbook<InstrumentAsset>>>>,
+    orderbooks: DashMap<String, Arc<TrackedOrderbook>>,
 
     /// 成交记录器
     trade_recorder: Arc<TradeRecorder>,
@@ -37,15 +150,33 @@ pub struct ExchangeMatchingEngine {
 
     /// 当前交易日
     trading_day: Arc<RwLock<String>>,
+
+    /// 只读深度视图（撮合后发布，查询路径无锁读取）
+    depth_view: Arc<DepthView>,
+
+    /// 合约订单序列通道（同合约按到达顺序进入订单簿）
+    sequencer: Arc<OrderSequencer>,
 }
 
 impl ExchangeMatchingEngine {
     pub fn new() -> Self {
+        Self::with_clock(ExchangeClock::System)
+    }
+
+    /// 使用指定时钟创建（成交记录时间取该时钟）
+    pub fn with_clock(clock: ExchangeClock) -> Self {
+        Self::with_depth_view(clock, Arc::new(DepthView::default()))
+    }
+
+    /// 使用指定深度视图创建（多个引擎共用同一视图时，查询路径只需读取该视图）
+    pub fn with_depth_view(clock: ExchangeClock, depth_view: Arc<DepthView>) -> Self {
         Self {
             orderbooks: DashMap::new(),
-            trade_recorder: Arc::new(TradeRecorder::new()),
+            trade_recorder: Arc::new(TradeRecorder::with_clock(clock)),
             prev_close_map: DashMap::new(),
             trading_day: Arc::new(RwLock::new(String::new())),
+            depth_view,
+            sequencer: Arc::new(OrderSequencer::new()),
         }
     }
 
@@ -57,8 +188,9 @@ impl ExchangeMatchingEngine {
     ) -> Result<(), ExchangeError> {
         let asset = InstrumentAsset::from_code(&instrument_id);
         let orderbook = Orderbook::new(asset, prev_close);
+        let orderbook = TrackedOrderbook::new(&instrument_id, orderbook, self.depth_view.clone());
         self.orderbooks
-            .insert(instrument_id.clone(), Arc::new(RwLock::new(orderbook)));
+            .insert(instrument_id.clone(), Arc::new(orderbook));
         self.prev_close_map
             .insert(instrument_id.clone(), prev_close);
         log::info!(
@@ -69,16 +201,79 @@ impl ExchangeMatchingEngine {
         Ok(())
     }
 
-    /// 获取订单簿
-    pub fn get_orderbook(
+    /// 挂载已有订单簿（分片迁移时使用，保留订单簿内的挂单）
+    pub fn attach_orderbook(
         &self,
-        instrument_id: &str,
-    ) -> Option<Arc<RwLock<Orderbook<InstrumentAsset>>>> {
+        instrument_id: String,
+        orderbook: Arc<TrackedOrderbook>,
+        prev_close: f64,
+    ) {
+        orderbook.rebind(self.depth_view.clone());
+        self.orderbooks.insert(instrument_id.clone(), orderbook);
+        self.prev_close_map.insert(instrument_id, prev_close);
+    }
+
+    /// 卸载合约订单簿，返回订单簿及昨收盘价（分片迁移时使用）
+    pub fn detach_orderbook(&self, instrument_id: &str) -> Option<(Arc<TrackedOrderbook>, f64)> {
+        let (_, orderbook) = self.orderbooks.remove(instrument_id)?;
+        self.depth_view.remove(instrument_id);
+        let prev_close = self
+            .prev_close_map
+            .remove(instrument_id)
+            .map(|(_, v)| v)
+            .unwrap_or(0.0);
+        Some((orderbook, prev_close))
+    }
+
+    /// 获取订单簿（写锁释放时自动发布深度快照）
+    pub fn get_orderbook(&self, instrument_id: &str) -> Option<Arc<TrackedOrderbook>> {
         self.orderbooks
             .get(instrument_id)
             .map(|r| r.value().clone())
     }
 
+    /// 在合约订单簿写锁内执行操作，完成后发布深度快照
+    pub fn execute<R, F>(&self, instrument_id: &str, f: F) -> Result<R, ExchangeError>
+    where
+        F: FnOnce(&mut Orderbook<InstrumentAsset>) -> R,
+    {
+        let orderbook = self.get_orderbook(instrument_id).ok_or_else(|| {
+            ExchangeError::MatchingError(format!(
+                "Orderbook not found for instrument: {}",
+                instrument_id
+            ))
+        })?;
+
+        let mut ob = orderbook.write();
+        Ok(f(&mut ob))
+    }
+
+    /// 撮合订单请求并发布深度快照
+    pub fn process_order(
+        &self,
+        instrument_id: &str,
+        request: orders::OrderRequest<InstrumentAsset>,
+    ) -> Result<Vec<Result<Success, Failed>>, ExchangeError> {
+        self.execute(instrument_id, move |ob| {
+            ob.process_order(request).into_iter().collect()
+        })
+    }
+
+    /// 获取合约最新深度快照（不获取订单簿锁）
+    pub fn get_depth_snapshot(&self, instrument_id: &str) -> Option<Arc<DepthSnapshot>> {
+        self.depth_view.get(instrument_id)
+    }
+
+    /// 合约订单序列通道（新订单按到达顺序领取撮合时间戳并排队进入订单簿）
+    pub fn order_sequencer(&self) -> Arc<OrderSequencer> {
+        self.sequencer.clone()
+    }
+
+    /// 获取只读深度视图
+    pub fn depth_view(&self) -> Arc<DepthView> {
+        self.depth_view.clone()
+    }
+
     /// 获取所有合约列表
     pub fn get_instruments(&self) -> Vec<String> {
         self.orderbooks.iter().map(|r| r.key().clone()).collect()
@@ -130,10 +325,162 @@ impl ExchangeMatchingEngine {
         }
     }
 
-    /// 获取最新价格
+    /// 获取最新价格（读取深度快照）
     pub fn get_last_price(&self, instrument_id: &str) -> Option<f64> {
-        self.get_orderbook(instrument_id)
-            .map(|ob| ob.read().lastprice)
+        self.get_depth_snapshot(instrument_id)
+            .map(|snapshot| snapshot.last_price)
+    }
+
+    /// 查询挂单（按撮合引擎订单ID），不在订单簿中（已成交/已撤/未收到）返回 None
+    pub fn query_order(
+        &self,
+        instrument_id: &str,
+        engine_order_id: u64,
+    ) -> Result<Option<RestingOrder>, ExchangeError> {
+        Ok(self
+            .resting_orders(instrument_id)?
+            .into_iter()
+            .find(|o| o.engine_order_id == engine_order_id))
+    }
+
+    /// 从挂单记录重建合约订单簿（撮合故障恢复使用），返回撮合引擎订单ID映射 (原ID, 新ID)
+    ///
+    /// 挂单按记录顺序重新提交（调用方按价格-时间优先排序），重建后保留最新价；
+    /// 记录之间发生成交说明挂单记录自相矛盾，放弃重建并保留原订单簿
+    pub fn rebuild_orderbook(
+        &self,
+        instrument_id: &str,
+        orders: &[RestingOrder],
+    ) -> Result<Vec<(u64, u64)>, ExchangeError> {
+        let orderbook = self.get_orderbook(instrument_id).ok_or_else(|| {
+            ExchangeError::MatchingError(format!(
+                "Orderbook not found for instrument: {}",
+                instrument_id
+            ))
+        })?;
+        let asset = InstrumentAsset::from_code(instrument_id);
+        let prev_close = self.get_prev_close(instrument_id).unwrap_or(0.0);
+
+        let mut rebuilt = Orderbook::new(asset, prev_close);
+        if let Some(last_price) = self.get_last_price(instrument_id) {
+            rebuilt.lastprice = last_price;
+        }
+
+        let remapped = resubmit_orders(&mut rebuilt, instrument_id, orders)?;
+
+        // 原地替换订单簿内容，持有该订单簿引用的调用方无需重新获取
+        *orderbook.write() = rebuilt;
+        log::warn!(
+            "Rebuilt orderbook {} from {} resting orders",
+            instrument_id,
+            orders.len()
+        );
+        Ok(remapped)
+    }
+
+    /// 集合竞价开盘
+    ///
+    /// 按最大成交量原则（与竞价指示价同一算法，昨收盘价为参考价）确定开盘价，
+    /// 开盘价可成交的买卖挂单按价格-时间优先在开盘价撮合，剩余挂单按原时间顺序转入
+    /// 连续交易订单簿（撮合引擎订单ID随之变化，见 `remapped`），最新价置为开盘价
+    pub fn open_auction(&self, instrument_id: &str) -> Result<AuctionOpen, ExchangeError> {
+        let asset = InstrumentAsset::from_code(instrument_id);
+        let prev_close = self.get_prev_close(instrument_id);
+
+        self.execute(instrument_id, |ob| {
+            let mut orders = resting_orders(ob);
+            let levels = |direction: &str| -> Vec<(f64, f64)> {
+                orders
+                    .iter()
+                    .filter(|o| o.direction == direction)
+                    .map(|o| (o.price, o.volume))
+                    .collect()
+            };
+            let result = AuctionCalculator::calculate_auction_price_with_reference(
+                &levels("BUY"),
+                &levels("SELL"),
+                prev_close,
+            );
+
+            let mut fills = Vec::new();
+            if let Some(ref result) = result {
+                let price = result.auction_price;
+                // 可成交挂单：买方价格 >= 开盘价，卖方价格 <= 开盘价，按价格-时间优先排列
+                let mut buys: Vec<usize> = (0..orders.len())
+                    .filter(|&i| orders[i].direction == "BUY" && orders[i].price >= price - 1e-9)
+                    .collect();
+                buys.sort_by(|&a, &b| {
+                    orders[b]
+                        .price
+                        .total_cmp(&orders[a].price)
+                        .then(orders[a].engine_order_id.cmp(&orders[b].engine_order_id))
+                });
+                let mut sells: Vec<usize> = (0..orders.len())
+                    .filter(|&i| orders[i].direction == "SELL" && orders[i].price <= price + 1e-9)
+                    .collect();
+                sells.sort_by(|&a, &b| {
+                    orders[a]
+                        .price
+                        .total_cmp(&orders[b].price)
+                        .then(orders[a].engine_order_id.cmp(&orders[b].engine_order_id))
+                });
+
+                let mut unmatched = result.auction_volume;
+                let (mut bi, mut si) = (0, 0);
+                while unmatched > 1e-9 && bi < buys.len() && si < sells.len() {
+                    let (b, s) = (buys[bi], sells[si]);
+                    let volume = orders[b].volume.min(orders[s].volume).min(unmatched);
+                    fills.push(AuctionFill {
+                        buy_order_id: orders[b].engine_order_id,
+                        sell_order_id: orders[s].engine_order_id,
+                        price,
+                        volume,
+                    });
+                    orders[b].volume -= volume;
+                    orders[s].volume -= volume;
+                    unmatched -= volume;
+                    if orders[b].volume <= 1e-9 {
+                        bi += 1;
+                    }
+                    if orders[s].volume <= 1e-9 {
+                        si += 1;
+                    }
+                }
+            }
+
+            // 剩余挂单按原时间顺序转入连续交易订单簿
+            orders.retain(|o| o.volume > 1e-9);
+            orders.sort_by_key(|o| o.engine_order_id);
+            let mut continuous = Orderbook::new(asset, prev_close.unwrap_or(0.0));
+            continuous.lastprice = result.as_ref().map_or(ob.lastprice, |r| r.auction_price);
+            let remapped = resubmit_orders(&mut continuous, instrument_id, &orders)?;
+            *ob = continuous;
+
+            log::info!(
+                "Auction opened for {}: price={:?}, volume={:?}, fills={}",
+                instrument_id,
+                result.as_ref().map(|r| r.auction_price),
+                result.as_ref().map(|r| r.auction_volume),
+                fills.len()
+            );
+            Ok(AuctionOpen {
+                result,
+                fills,
+                remapped,
+            })
+        })?
+    }
+
+    /// 合约订单簿全部挂单
+    pub fn resting_orders(&self, instrument_id: &str) -> Result<Vec<RestingOrder>, ExchangeError> {
+        let orderbook = self.get_orderbook(instrument_id).ok_or_else(|| {
+            ExchangeError::MatchingError(format!(
+                "Orderbook not found for instrument: {}",
+                instrument_id
+            ))
+        })?;
+        let ob = orderbook.read();
+        Ok(resting_orders(&ob))
     }
 }
 
@@ -173,6 +520,156 @@ mod tests {
         assert!(last_price.is_some());
     }
 
+    #[test]
+    fn test_process_order_publishes_depth_snapshot() {
+        let engine = ExchangeMatchingEngine::new();
+        engine
+            .register_instrument("DEPTH2301".to_string(), 100.0)
+            .unwrap();
+        let asset = InstrumentAsset::from_code("DEPTH2301");
+        assert_eq!(engine.get_depth_snapshot("DEPTH2301").unwrap().sequence, 0);
+
+        let sell = orders::new_limit_order_request(asset, OrderDirection::SELL, 101.0, 10.0, 1);
+        engine.process_order("DEPTH2301", sell).unwrap();
+        let snapshot = engine.get_depth_snapshot("DEPTH2301").unwrap();
+        assert_eq!(snapshot.sequence, 1);
+        assert_eq!(snapshot.best_ask(), Some(101.0));
+
+        let buy = orders::new_limit_order_request(asset, OrderDirection::BUY, 101.0, 4.0, 2);
+        engine.process_order("DEPTH2301", buy).unwrap();
+        // 每次撮合事件完成即可见，不滞后
+        let snapshot = engine.get_depth_snapshot("DEPTH2301").unwrap();
+        assert_eq!(snapshot.sequence, 2);
+        assert_eq!(snapshot.total_ask_volume, 6.0);
+        assert_eq!(engine.get_last_price("DEPTH2301"), Some(101.0));
+
+        assert!(engine
+            .process_order(
+                "NON_EXISTENT",
+                orders::new_limit_order_request(asset, OrderDirection::BUY, 1.0, 1.0, 3)
+            )
+            .is_err());
+        engine.detach_orderbook("DEPTH2301").unwrap();
+        assert!(engine.get_depth_snapshot("DEPTH2301").is_none());
+    }
+
+    #[test]
+    fn test_query_resting_order() {
+        let engine = ExchangeMatchingEngine::new();
+        engine
+            .register_instrument("QUERY2301".to_string(), 100.0)
+            .unwrap();
+        let asset = InstrumentAsset::from_code("QUERY2301");
+
+        let sell = orders::new_limit_order_request(asset, OrderDirection::SELL, 101.0, 10.0, 1);
+        let engine_order_id = engine
+            .process_order("QUERY2301", sell)
+            .unwrap()
+            .into_iter()
+            .find_map(|r| match r {
+                Ok(Success::Accepted { id, .. }) => Some(id),
+                _ => None,
+            })
+            .unwrap();
+
+        let resting = engine.query_order("QUERY2301", engine_order_id).unwrap();
+        assert_eq!(
+            resting,
+            Some(RestingOrder {
+                engine_order_id,
+                direction: "SELL",
+                price: 101.0,
+                volume: 10.0,
+            })
+        );
+
+        // 全部成交后不在订单簿中
+        let buy = orders::new_limit_order_request(asset, OrderDirection::BUY, 101.0, 10.0, 2);
+        engine.process_order("QUERY2301", buy).unwrap();
+        assert_eq!(
+            engine.query_order("QUERY2301", engine_order_id).unwrap(),
+            None
+        );
+        assert!(engine.resting_orders("QUERY2301").unwrap().is_empty());
+        assert!(engine.query_order("NON_EXISTENT", engine_order_id).is_err());
+    }
+
+    #[test]
+    fn test_rebuild_orderbook_from_resting_orders() {
+        let engine = ExchangeMatchingEngine::new();
+        engine
+            .register_instrument("REBUILD2301".to_string(), 100.0)
+            .unwrap();
+        let asset = InstrumentAsset::from_code("REBUILD2301");
+        for (ts, (direction, price)) in [
+            (OrderDirection::BUY, 99.0),
+            (OrderDirection::BUY, 99.0),
+            (OrderDirection::SELL, 101.0),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let request = orders::new_limit_order_request(asset, direction, price, 2.0, ts as i64);
+            engine.process_order("REBUILD2301", request).unwrap();
+        }
+        let orderbook = engine.get_orderbook("REBUILD2301").unwrap();
+        let before = engine.resting_orders("REBUILD2301").unwrap();
+        assert_eq!(before.len(), 3);
+
+        // 挂单记录（剩余量以记录为准），同价位保持原时间优先
+        let mut records = before.clone();
+        records.sort_by_key(|o| o.engine_order_id);
+        records[0].volume = 1.0;
+        let remapped = engine.rebuild_orderbook("REBUILD2301", &records).unwrap();
+        assert_eq!(remapped.len(), 3);
+        assert_eq!(
+            remapped.iter().map(|(old, _)| *old).collect::<Vec<_>>(),
+            records
+                .iter()
+                .map(|o| o.engine_order_id)
+                .collect::<Vec<_>>()
+        );
+        let after = resting_orders(&orderbook.read());
+        assert_eq!(after.len(), 3);
+        assert_eq!(
+            engine
+                .get_depth_snapshot("REBUILD2301")
+                .unwrap()
+                .total_bid_volume,
+            3.0
+        );
+
+        // 卖单吃买一：先成交时间优先的第一笔（剩余 1 手）
+        let sell = orders::new_limit_order_request(asset, OrderDirection::SELL, 99.0, 1.0, 10);
+        engine.process_order("REBUILD2301", sell).unwrap();
+        let bids: Vec<_> = engine
+            .resting_orders("REBUILD2301")
+            .unwrap()
+            .into_iter()
+            .filter(|o| o.direction == "BUY")
+            .collect();
+        assert_eq!(bids.len(), 1);
+        assert_eq!(bids[0].engine_order_id, remapped[1].1);
+
+        // 自相矛盾的挂单记录（买价高于卖价）拒绝重建
+        let crossed = vec![
+            RestingOrder {
+                engine_order_id: 1,
+                direction: "BUY",
+                price: 102.0,
+                volume: 1.0,
+            },
+            RestingOrder {
+                engine_order_id: 2,
+                direction: "SELL",
+                price: 101.0,
+                volume: 1.0,
+            },
+        ];
+        assert!(engine.rebuild_orderbook("REBUILD2301", &crossed).is_err());
+        assert_eq!(engine.resting_orders("REBUILD2301").unwrap().len(), 2);
+    }
+
     // ==================== InstrumentAsset 测试 @yutiansut @quantaxis ====================
 
     /// 测试 InstrumentAsset::from_code 哈希生成
@@ -13,14 +13,13 @@
 //! - 零内存分配（热路径）
 
 use crate::matching::engine::InstrumentAsset;
-use crate::matching::Orderbook;
+use crate::matching::{DepthSnapshot, DepthView, Orderbook, TrackedOrderbook};
 use crate::perf::{
     bind_to_core, spawn_on_core, spsc_channel, CpuAffinityConfig, OrderPool, PerfConfig,
     PerfContext, PoolConfig, PooledOrder, PooledTradeReport, SpscReceiver, SpscSender,
     TradeReportPool,
 };
 use dashmap::DashMap;
-use parking_lot::RwLock;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::thread::{self, JoinHandle};
@@ -194,7 +193,10 @@ pub struct HighPerfMatchingEngine {
     config: HighPerfMatchingConfig,
 
     /// 订单簿池
-    orderbooks: Arc<DashMap<String, Arc<RwLock<Orderbook<InstrumentAsset>>>>>,
+    orderbooks: Arc<DashMap<String, Arc<TrackedOrderbook>>>,
+
+    /// 只读深度视图（撮合线程释放订单簿写锁前发布）
+    depth_view: Arc<DepthView>,
 
     /// 订单池
     order_pool: Arc<OrderPool>,
@@ -242,6 +244,7 @@ impl HighPerfMatchingEngine {
         Self {
             config,
             orderbooks: Arc::new(DashMap::new()),
+            depth_view: Arc::new(DepthView::default()),
             order_pool,
             trade_pool,
             order_sender,
@@ -257,11 +260,17 @@ impl HighPerfMatchingEngine {
     /// 注册品种
     pub fn register_instrument(&self, instrument_id: &str, init_price: f64) {
         let orderbook = Orderbook::new(InstrumentAsset::from_code(instrument_id), init_price);
+        let orderbook = TrackedOrderbook::new(instrument_id, orderbook, self.depth_view.clone());
         self.orderbooks
-            .insert(instrument_id.to_string(), Arc::new(RwLock::new(orderbook)));
+            .insert(instrument_id.to_string(), Arc::new(orderbook));
         log::info!("HighPerfMatchingEngine: registered {}", instrument_id);
     }
 
+    /// 获取合约最新深度快照（不获取订单簿锁）
+    pub fn get_depth_snapshot(&self, instrument_id: &str) -> Option<Arc<DepthSnapshot>> {
+        self.depth_view.get(instrument_id)
+    }
+
     /// 启动撮合引擎
     pub fn start(&mut self) -> Result<(), String> {
         if self.running.load(Ordering::SeqCst) {
@@ -386,7 +395,7 @@ impl Drop for HighPerfMatchingEngine {
 
 /// 撮合主循环（运行在独立线程中）
 fn run_matching_loop(
-    orderbooks: Arc<DashMap<String, Arc<RwLock<Orderbook<InstrumentAsset>>>>>,
+    orderbooks: Arc<DashMap<String, Arc<TrackedOrderbook>>>,
     order_receiver: SpscReceiver<PooledOrder>,
     trade_sender: SpscSender<PooledTradeReport>,
     trade_pool: Arc<TradeReportPool>,
@@ -426,10 +435,36 @@ fn run_matching_loop(
     log::info!("Matching loop stopped");
 }
 
+/// 在订单簿上撮合池化订单（撮合线程与订单路由的高性能路径共用）
+///
+/// `timestamp` 为纳秒时间戳，决定同价位的时间优先级
+pub fn match_pooled_order(
+    ob: &mut Orderbook<InstrumentAsset>,
+    order: &PooledOrder,
+    timestamp: i64,
+) -> Vec<Result<crate::matching::Success, crate::matching::Failed>> {
+    use crate::matching::{orders, OrderDirection};
+
+    let direction = if order.direction == 0 {
+        OrderDirection::BUY
+    } else {
+        OrderDirection::SELL
+    };
+
+    let order_request = orders::new_limit_order_request(
+        InstrumentAsset::from_code(&order.instrument_id),
+        direction,
+        order.price,
+        order.volume,
+        timestamp,
+    );
+    ob.process_order(order_request).into_iter().collect()
+}
+
 /// 处理单个订单
 fn process_single_order(
     order: &PooledOrder,
-    orderbooks: &DashMap<String, Arc<RwLock<Orderbook<InstrumentAsset>>>>,
+    orderbooks: &DashMap<String, Arc<TrackedOrderbook>>,
     trade_sender: &SpscSender<PooledTradeReport>,
     _trade_pool: &TradeReportPool,
     stats: &MatchingStats,
@@ -443,29 +478,11 @@ fn process_single_order(
         }
     };
 
-    // 转换方向
-    use crate::matching::{orders, OrderDirection, Success};
+    use crate::matching::Success;
 
-    let direction = if order.direction == 0 {
-        OrderDirection::BUY
-    } else {
-        OrderDirection::SELL
-    };
-
-    // 创建订单请求
-    let asset = InstrumentAsset::from_code(&order.instrument_id);
+    // 执行撮合（写锁释放前发布深度快照）
     let timestamp = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
-    let order_request = orders::new_limit_order_request(
-        asset,
-        direction,
-        order.price,
-        order.volume,
-        timestamp,
-    );
-
-    // 执行撮合
-    let mut ob = orderbook.write();
-    let results = ob.process_order(order_request);
+    let results = match_pooled_order(&mut orderbook.write(), order, timestamp);
 
     // 处理撮合结果
     for result in results {
@@ -508,6 +525,84 @@ fn process_single_order(
     }
 }
 
+// ═══════════════════════════════════════════════════════════════════════════
+// 订单路由高性能撮合通道
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 撮合通道任务
+type MatchTask = Box<dyn FnOnce() + Send + 'static>;
+
+/// 订单路由高性能撮合通道（功能灰度 `use_high_perf_matching` 命中的订单走此路径）
+///
+/// 与单引擎路径共享订单簿，区别在执行位置：订单以池化订单形式投递到独立撮合线程（可绑核），
+/// 撮合线程在订单簿写锁内撮合，调用方阻塞等待撮合结果。
+/// 同一合约的先后顺序由调用方持有的订单序列通道保证，队列满时直接拒绝不阻塞。
+pub struct HighPerfOrderMatcher {
+    task_tx: crossbeam::channel::Sender<MatchTask>,
+    stats: Arc<MatchingStats>,
+}
+
+impl HighPerfOrderMatcher {
+    /// 启动撮合线程（使用配置中的核心绑定与订单队列容量）
+    pub fn start(config: &HighPerfMatchingConfig) -> Result<Self, String> {
+        let (task_tx, task_rx) =
+            crossbeam::channel::bounded::<MatchTask>(config.order_queue_capacity.max(1));
+        let stats = Arc::new(MatchingStats::default());
+
+        let worker_stats = Arc::clone(&stats);
+        let run = move || {
+            // 所有发送端释放后退出
+            for task in task_rx.iter() {
+                let start = Instant::now();
+                // 撮合 panic 只影响本单（回执通道随之关闭，调用方收到错误），撮合线程继续运行
+                if std::panic::catch_unwind(std::panic::AssertUnwindSafe(task)).is_err() {
+                    log::error!("High-perf matching task panicked");
+                }
+                worker_stats.record_latency(start.elapsed().as_nanos() as u64);
+                worker_stats
+                    .orders_processed
+                    .fetch_add(1, Ordering::Relaxed);
+            }
+        };
+        let handle = if config.enable_cpu_affinity {
+            spawn_on_core(config.matching_core, "high-perf-matcher", run)
+        } else {
+            thread::Builder::new()
+                .name("high-perf-matcher".to_string())
+                .spawn(run)
+        };
+        handle.map_err(|e| format!("Failed to spawn thread: {}", e))?;
+
+        Ok(Self { task_tx, stats })
+    }
+
+    /// 在撮合线程上执行撮合并等待结果
+    pub fn execute<R, F>(&self, f: F) -> Result<R, crate::ExchangeError>
+    where
+        R: Send + 'static,
+        F: FnOnce() -> R + Send + 'static,
+    {
+        let (reply_tx, reply_rx) = crossbeam::channel::bounded(1);
+        let task: MatchTask = Box::new(move || {
+            let _ = reply_tx.send(f());
+        });
+        if self.task_tx.try_send(task).is_err() {
+            self.stats.queue_drops.fetch_add(1, Ordering::Relaxed);
+            return Err(crate::ExchangeError::MatchingError(
+                "High-perf matching queue full".to_string(),
+            ));
+        }
+        reply_rx.recv().map_err(|_| {
+            crate::ExchangeError::MatchingError("High-perf matching task failed".to_string())
+        })
+    }
+
+    /// 获取统计信息
+    pub fn stats(&self) -> &MatchingStats {
+        &self.stats
+    }
+}
+
 // ═══════════════════════════════════════════════════════════════════════════
 // 测试
 // ═══════════════════════════════════════════════════════════════════════════
@@ -590,6 +685,11 @@ mod tests {
 
         // 应该有成交
         assert!(trades.len() > 0 || engine.stats().trades_generated.load(Ordering::Relaxed) > 0);
+
+        // 撮合线程每次撮合后发布深度快照：两笔订单完全成交，订单簿为空
+        let depth = engine.get_depth_snapshot("TEST001").unwrap();
+        assert_eq!(depth.sequence, 2);
+        assert!(depth.bids.is_empty() && depth.asks.is_empty());
     }
 
     #[test]
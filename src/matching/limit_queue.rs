@@ -0,0 +1,264 @@
+//! 停板价位排队（涨跌停排队）
+//!
+//! @yutiansut @quantaxis
+//!
+//! 涨跌停时大量订单在停板价排队，订单簿同价位按时间优先成交（`get_sorted_orders`
+//! 的顺序即撮合顺序）。本模块给出订单在所在价位的排队信息（排第几位、前面挂单总量），
+//! 供用户判断成交概率：
+//! - 一次遍历生成整个价位的 [`LevelQueue`]（撮合引擎订单ID → 排位），
+//!   按深度快照序号缓存，订单簿未变化时停板价海量挂单的重复查询为 O(1)
+//! - 开板时对手单在一次撮合中按排队顺序逐笔成交，之后订单簿变化，缓存随序号失效重建
+
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::engine::InstrumentAsset;
+use super::{OrderDirection, Orderbook};
+
+/// 价格比较精度（与涨跌停校验一致）
+const PRICE_EPSILON: f64 = 1e-8;
+
+/// 订单在所在价位的排队信息
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueuePosition {
+    /// BUY / SELL
+    pub direction: String,
+    pub price: f64,
+    /// 排队位次（1 表示排在最前）
+    pub position: usize,
+    /// 前面挂单笔数
+    pub orders_ahead: usize,
+    /// 前面挂单总量
+    pub volume_ahead: f64,
+    /// 本单剩余挂单量
+    pub volume: f64,
+    /// 该价位挂单笔数
+    pub level_orders: usize,
+    /// 该价位挂单总量
+    pub level_volume: f64,
+    /// 是否在停板价排队（涨停价买单 / 跌停价卖单）
+    pub at_limit: bool,
+    /// 订单簿版本（深度快照序号）
+    pub sequence: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct QueueEntry {
+    position: usize,
+    volume_ahead: f64,
+    volume: f64,
+}
+
+/// 单个价位的排队快照
+#[derive(Debug, Clone)]
+pub struct LevelQueue {
+    pub direction: &'static str,
+    pub price: f64,
+    /// 生成时的订单簿版本
+    pub sequence: u64,
+    pub total_orders: usize,
+    pub total_volume: f64,
+    entries: HashMap<u64, QueueEntry>,
+}
+
+impl LevelQueue {
+    /// 从订单簿生成价位排队快照（调用方需持有订单簿锁）
+    pub fn from_orderbook(
+        ob: &Orderbook<InstrumentAsset>,
+        direction: OrderDirection,
+        price: f64,
+        sequence: u64,
+    ) -> Self {
+        let (direction, orders) = match direction {
+            OrderDirection::BUY => ("BUY", ob.bid_queue.get_sorted_orders()),
+            OrderDirection::SELL => ("SELL", ob.ask_queue.get_sorted_orders()),
+        };
+
+        let mut entries = HashMap::new();
+        let mut total_volume = 0.0;
+        for order in orders
+            .unwrap_or_default()
+            .iter()
+            .filter(|o| (o.price - price).abs() < PRICE_EPSILON)
+        {
+            entries.insert(
+                order.order_id,
+                QueueEntry {
+                    position: entries.len() + 1,
+                    volume_ahead: total_volume,
+                    volume: order.volume,
+                },
+            );
+            total_volume += order.volume;
+        }
+
+        Self {
+            direction,
+            price,
+            sequence,
+            total_orders: entries.len(),
+            total_volume,
+            entries,
+        }
+    }
+
+    /// 订单的排队信息，不在该价位返回 None
+    pub fn position_of(&self, engine_order_id: u64) -> Option<QueuePosition> {
+        self.entries.get(&engine_order_id).map(|e| QueuePosition {
+            direction: self.direction.to_string(),
+            price: self.price,
+            position: e.position,
+            orders_ahead: e.position - 1,
+            volume_ahead: e.volume_ahead,
+            volume: e.volume,
+            level_orders: self.total_orders,
+            level_volume: self.total_volume,
+            at_limit: false,
+            sequence: self.sequence,
+        })
+    }
+}
+
+/// 价位排队缓存键：(合约, 方向, 价格)
+type LevelKey = (String, &'static str, i64);
+
+/// 价位排队索引（按订单簿版本缓存）
+#[derive(Default)]
+pub struct LimitQueueIndex {
+    levels: DashMap<LevelKey, Arc<LevelQueue>>,
+}
+
+impl LimitQueueIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 价位排队快照：`sequence` 与缓存一致时直接复用，否则重建并清理该合约的过期缓存
+    ///
+    /// 调用方需持有订单簿锁，并在同一把锁内读取 `sequence`（深度快照序号）
+    pub fn level(
+        &self,
+        instrument_id: &str,
+        ob: &Orderbook<InstrumentAsset>,
+        sequence: u64,
+        direction: OrderDirection,
+        price: f64,
+    ) -> Arc<LevelQueue> {
+        let side = match direction {
+            OrderDirection::BUY => "BUY",
+            OrderDirection::SELL => "SELL",
+        };
+        let key = (
+            instrument_id.to_string(),
+            side,
+            (price / PRICE_EPSILON).round() as i64,
+        );
+        if let Some(level) = self.levels.get(&key) {
+            if level.sequence == sequence {
+                return level.clone();
+            }
+        }
+
+        let level = Arc::new(LevelQueue::from_orderbook(ob, direction, price, sequence));
+        self.levels
+            .retain(|k, v| k.0 != instrument_id || v.sequence == sequence);
+        self.levels.insert(key, level.clone());
+        level
+    }
+
+    /// 缓存的价位数
+    pub fn cached_levels(&self) -> usize {
+        self.levels.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matching::engine::ExchangeMatchingEngine;
+    use crate::matching::{orders, Success};
+
+    fn accepted_id(results: Vec<Result<Success, crate::matching::Failed>>) -> u64 {
+        results
+            .iter()
+            .find_map(|r| match r {
+                Ok(Success::Accepted { id, .. }) => Some(*id),
+                _ => None,
+            })
+            .expect("order accepted")
+    }
+
+    #[test]
+    fn test_level_queue_time_priority_and_cache() {
+        let engine = ExchangeMatchingEngine::new();
+        engine
+            .register_instrument("LQ2501".to_string(), 100.0)
+            .unwrap();
+        let asset = InstrumentAsset::from_code("LQ2501");
+        let mut ids = Vec::new();
+        for (i, (price, volume)) in [(110.0, 3.0), (109.0, 5.0), (110.0, 2.0), (110.0, 4.0)]
+            .into_iter()
+            .enumerate()
+        {
+            let request = orders::new_limit_order_request(
+                asset,
+                OrderDirection::BUY,
+                price,
+                volume,
+                i as i64,
+            );
+            ids.push(accepted_id(
+                engine.process_order("LQ2501", request).unwrap(),
+            ));
+        }
+
+        let index = LimitQueueIndex::new();
+        let orderbook = engine.get_orderbook("LQ2501").unwrap();
+        let sequence = || engine.get_depth_snapshot("LQ2501").unwrap().sequence;
+        let level = index.level(
+            "LQ2501",
+            &orderbook.read(),
+            sequence(),
+            OrderDirection::BUY,
+            110.0,
+        );
+        assert_eq!(level.total_orders, 3);
+        assert_eq!(level.total_volume, 9.0);
+
+        // 同价位按时间优先排队，其他价位的订单不计入
+        let last = level.position_of(ids[3]).unwrap();
+        assert_eq!(last.position, 3);
+        assert_eq!(last.orders_ahead, 2);
+        assert_eq!(last.volume_ahead, 5.0);
+        assert_eq!(level.position_of(ids[0]).unwrap().position, 1);
+        assert!(level.position_of(ids[1]).is_none());
+
+        // 订单簿未变化时复用缓存
+        let cached = index.level(
+            "LQ2501",
+            &orderbook.read(),
+            sequence(),
+            OrderDirection::BUY,
+            110.0,
+        );
+        assert!(Arc::ptr_eq(&level, &cached));
+
+        // 卖单成交排在最前的订单后，后续订单排位前移
+        let sell = orders::new_limit_order_request(asset, OrderDirection::SELL, 110.0, 3.0, 10);
+        engine.process_order("LQ2501", sell).unwrap();
+        let level = index.level(
+            "LQ2501",
+            &orderbook.read(),
+            sequence(),
+            OrderDirection::BUY,
+            110.0,
+        );
+        assert!(level.position_of(ids[0]).is_none());
+        let last = level.position_of(ids[3]).unwrap();
+        assert_eq!(last.position, 2);
+        assert_eq!(last.volume_ahead, 2.0);
+        assert_eq!(index.cached_levels(), 1);
+    }
+}
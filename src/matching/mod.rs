@@ -24,4 +24,27 @@ pub mod core;
 /// 高性能撮合引擎（Phase 5.2 优化）
 pub mod high_perf;
 
-pub use high_perf::{HighPerfMatchingConfig, HighPerfMatchingEngine, MatchingStats};
+/// 撮合引擎分片部署（按合约一致性哈希）
+pub mod sharded;
+
+/// 订单簿挂单数与内存占用限制
+pub mod book_limits;
+
+/// 订单簿只读深度视图（撮合后发布 ArcSwap 快照）
+pub mod depth_view;
+
+/// 停板价位排队（排队位次与前方挂单量）
+pub mod limit_queue;
+
+/// 合约订单序列通道（同合约按到达顺序撮合）
+pub mod sequencer;
+
+pub use book_limits::{OrderBookLimitConfig, OrderBookLimiter, OrderBookUsage, OrderBookUsageSummary};
+pub use depth_view::{DepthLevel, DepthSnapshot, DepthView, TrackedOrderbook, TrackedWriteGuard, DEFAULT_DEPTH_LEVELS};
+pub use limit_queue::{LevelQueue, LimitQueueIndex, QueuePosition};
+pub use sequencer::{OrderSequencer, SequenceLaneStats, SequenceTurn};
+pub use high_perf::{HighPerfMatchingConfig, HighPerfMatchingEngine, HighPerfOrderMatcher, MatchingStats};
+pub use sharded::{
+    MatchingFaultEvent, MatchingFaultState, MatchingRecoveryHandler, PoisonedTask, ShardMigration, ShardStatus,
+    ShardedMatchingConfig, ShardedMatchingEngine, ShardedMatchingStats,
+};
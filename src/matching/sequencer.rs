@@ -0,0 +1,195 @@
+//! 合约订单序列通道（撮合公平性）
+//!
+//! @yutiansut @quantaxis
+//!
+//! 多个账户并发向同一合约下单时，订单簿写锁的获取顺序由锁调度决定，可能与到达顺序不一致；
+//! 撮合时间戳又在取锁之前生成，导致时间戳与进入订单簿的顺序不符。
+//!
+//! 每个合约维护一条序列通道（票号锁）：订单到达撮合阶段时原子地领取序号并分配撮合时间戳，
+//! 只有轮到自己的序号才进入订单簿，离开后放行下一个序号。同一合约严格 FIFO，
+//! 时间戳与处理顺序一致且单调递增；不同合约的通道互不影响。
+
+use dashmap::DashMap;
+use parking_lot::{Condvar, Mutex};
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// 单个合约的序列通道状态
+#[derive(Debug, Default)]
+struct LaneState {
+    /// 下一个待领取的序号
+    next_ticket: u64,
+    /// 当前放行的序号
+    serving: u64,
+    /// 最近分配的撮合时间戳（纳秒）
+    last_timestamp: i64,
+}
+
+/// 单个合约的序列通道
+#[derive(Debug, Default)]
+struct SequenceLane {
+    state: Mutex<LaneState>,
+    turn: Condvar,
+    /// 累计通过的订单数
+    sequenced: AtomicU64,
+    /// 观测到的最大排队长度
+    max_waiting: AtomicU64,
+}
+
+/// 轮到本单的凭证：持有期间独占该合约的撮合顺序，释放后放行下一个序号
+#[derive(Debug)]
+pub struct SequenceTurn {
+    lane: Arc<SequenceLane>,
+    ticket: u64,
+    timestamp: i64,
+}
+
+impl SequenceTurn {
+    /// 合约内到达序号（从 0 开始）
+    pub fn ticket(&self) -> u64 {
+        self.ticket
+    }
+
+    /// 按到达顺序分配的撮合时间戳（纳秒，合约内严格递增）
+    pub fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+}
+
+impl Drop for SequenceTurn {
+    fn drop(&mut self) {
+        let mut state = self.lane.state.lock();
+        state.serving += 1;
+        drop(state);
+        self.lane.sequenced.fetch_add(1, Ordering::Relaxed);
+        self.lane.turn.notify_all();
+    }
+}
+
+/// 合约序列通道统计
+#[derive(Debug, Clone, Serialize)]
+pub struct SequenceLaneStats {
+    pub instrument_id: String,
+    /// 累计通过的订单数
+    pub sequenced: u64,
+    /// 当前排队等待的订单数
+    pub waiting: u64,
+    /// 观测到的最大排队长度
+    pub max_waiting: u64,
+}
+
+/// 合约订单序列器（每合约一条序列通道）
+#[derive(Debug, Default)]
+pub struct OrderSequencer {
+    lanes: DashMap<String, Arc<SequenceLane>>,
+}
+
+impl OrderSequencer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 到达撮合阶段：领取序号并分配时间戳，阻塞直到轮到本单
+    ///
+    /// `now_nanos` 在领取序号的同一临界区内调用，保证时间戳顺序与序号一致；
+    /// 时钟回拨或同一纳秒内到达时时间戳顺延 1ns。
+    pub fn enter(&self, instrument_id: &str, now_nanos: impl FnOnce() -> i64) -> SequenceTurn {
+        let lane = match self.lanes.get(instrument_id) {
+            Some(lane) => lane.value().clone(),
+            None => self
+                .lanes
+                .entry(instrument_id.to_string())
+                .or_default()
+                .value()
+                .clone(),
+        };
+
+        let mut state = lane.state.lock();
+        let ticket = state.next_ticket;
+        state.next_ticket += 1;
+        let timestamp = now_nanos().max(state.last_timestamp + 1);
+        state.last_timestamp = timestamp;
+
+        let waiting = ticket - state.serving;
+        lane.max_waiting.fetch_max(waiting, Ordering::Relaxed);
+        while state.serving != ticket {
+            lane.turn.wait(&mut state);
+        }
+        drop(state);
+
+        SequenceTurn {
+            lane,
+            ticket,
+            timestamp,
+        }
+    }
+
+    /// 各合约序列通道统计（按合约代码排序）
+    pub fn stats(&self) -> Vec<SequenceLaneStats> {
+        let mut stats: Vec<SequenceLaneStats> = self
+            .lanes
+            .iter()
+            .map(|entry| {
+                let lane = entry.value();
+                let waiting = {
+                    let state = lane.state.lock();
+                    // 正在撮合的订单不计入排队
+                    (state.next_ticket - state.serving).saturating_sub(1)
+                };
+                SequenceLaneStats {
+                    instrument_id: entry.key().clone(),
+                    sequenced: lane.sequenced.load(Ordering::Relaxed),
+                    waiting,
+                    max_waiting: lane.max_waiting.load(Ordering::Relaxed),
+                }
+            })
+            .collect();
+        stats.sort_by(|a, b| a.instrument_id.cmp(&b.instrument_id));
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_sequencer_orders_by_arrival() {
+        let sequencer = Arc::new(OrderSequencer::new());
+
+        // 持有 0 号时后到的订单必须等待
+        let first = sequencer.enter("IF2501", || 100);
+        assert_eq!(first.ticket(), 0);
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let handle = {
+            let sequencer = sequencer.clone();
+            std::thread::spawn(move || {
+                // 时钟回拨：时间戳仍需晚于先到订单
+                let turn = sequencer.enter("IF2501", || 50);
+                tx.send((turn.ticket(), turn.timestamp())).unwrap();
+            })
+        };
+        assert!(rx.recv_timeout(Duration::from_millis(50)).is_err());
+
+        // 其他合约的通道不受影响
+        let other = sequencer.enter("IC2501", || 100);
+        assert_eq!(other.ticket(), 0);
+        drop(other);
+
+        drop(first);
+        let (ticket, timestamp) = rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        handle.join().unwrap();
+        assert_eq!(ticket, 1);
+        assert_eq!(timestamp, 101);
+
+        let stats = sequencer.stats();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[1].instrument_id, "IF2501");
+        assert_eq!(stats[1].sequenced, 2);
+        assert_eq!(stats[1].waiting, 0);
+        assert_eq!(stats[1].max_waiting, 1);
+    }
+}
@@ -0,0 +1,1214 @@
+//! 撮合引擎分片部署
+//!
+//! @yutiansut @quantaxis
+//!
+//! 单进程单撮合引擎是吞吐瓶颈，本模块把合约分散到多个 `ExchangeMatchingEngine` 实例：
+//! - 合约 → 分片 使用一致性哈希（复用 `cluster::ConsistentHashRing`），同一合约始终落在同一分片
+//! - 每个分片拥有独立撮合线程（可选绑核），订单簿操作在分片线程内串行执行
+//! - 分片增减时只迁移受影响的合约，订单簿整体搬迁（挂单不丢失）
+//! - 提供跨分片的统一监控统计
+//! - 故障隔离：分片线程内任务 panic 时捕获并跳过该任务（写入 poison 队列），按挂单记录重建
+//!   该合约订单簿后重启撮合循环；恢复期间该合约暂停接单，连续失败达到上限永久停牌，
+//!   同分片的其他合约不受影响
+//!
+//! 成交回报仍由调用方（`OrderRouter`）汇聚到统一的 `TradeGateway`。
+//! 各分片共用同一个深度视图（可与单撮合引擎共用），合约迁移后查询路径无需感知分片。
+
+use crate::cluster::{ConsistentHashRing, PhysicalNode};
+use crate::exchange::deterministic::ExchangeClock;
+use crate::matching::engine::{ExchangeMatchingEngine, InstrumentAsset, RestingOrder};
+use crate::matching::{
+    DepthSnapshot, DepthView, Failed, OrderRequest, Orderbook, Success, TrackedOrderbook,
+};
+use crate::ExchangeError;
+use crossbeam::channel::{bounded, Receiver, Sender};
+use dashmap::DashMap;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::collections::VecDeque;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Weak};
+use std::thread::JoinHandle;
+
+/// poison 队列保留条数
+const MAX_POISONED_TASKS: usize = 1_000;
+
+/// 分片线程执行的任务
+struct ShardTask {
+    instrument_id: String,
+    /// 任务标识（订单ID 等，panic 时写入 poison 队列）
+    label: String,
+    run: Box<dyn FnOnce() + Send + 'static>,
+}
+
+/// 分片撮合配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShardedMatchingConfig {
+    /// 是否启用分片撮合（仅服务启动时读取，未启用时使用单撮合引擎）
+    pub enabled: bool,
+    /// 初始分片数量
+    pub shard_count: usize,
+    /// 每个分片的虚拟节点数（一致性哈希）
+    pub virtual_nodes: usize,
+    /// 分片任务队列容量
+    pub queue_capacity: usize,
+    /// 是否将分片线程绑定到 CPU 核心
+    pub bind_cores: bool,
+    /// 合约连续撮合失败（panic 或订单簿重建失败）达到该次数后永久停牌
+    pub max_restart_failures: u32,
+}
+
+impl Default for ShardedMatchingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            shard_count: 4,
+            virtual_nodes: 150,
+            queue_capacity: 10_000,
+            bind_cores: false,
+            max_restart_failures: 3,
+        }
+    }
+}
+
+/// 合约迁移记录
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShardMigration {
+    pub instrument_id: String,
+    pub from_shard: String,
+    pub to_shard: String,
+}
+
+/// 单个分片的运行状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShardStatus {
+    pub shard_id: String,
+    pub instruments: Vec<String>,
+    pub processed_tasks: u64,
+    pub pending_tasks: usize,
+}
+
+/// 分片撮合整体统计
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShardedMatchingStats {
+    pub shard_count: usize,
+    pub instrument_count: usize,
+    pub total_processed: u64,
+    pub migrations: u64,
+    /// poison 队列中的任务数
+    pub poisoned_tasks: usize,
+    /// 因连续撮合失败停牌的合约
+    pub halted_instruments: Vec<String>,
+    pub shards: Vec<ShardStatus>,
+}
+
+/// 合约撮合故障状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchingFaultState {
+    /// 撮合 panic，暂停接单并重建订单簿
+    Recovering,
+    /// 订单簿已重建，恢复接单
+    Recovered,
+    /// 连续失败达到上限，永久停牌
+    Halted,
+}
+
+/// 撮合故障事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchingFaultEvent {
+    pub instrument_id: String,
+    pub shard_id: String,
+    pub state: MatchingFaultState,
+    /// 连续失败次数（成功撮合一次后清零）
+    pub consecutive_failures: u32,
+    pub reason: String,
+    /// 事件时间（毫秒）
+    pub timestamp: i64,
+}
+
+/// poison 队列条目：导致撮合 panic 的任务（已跳过，不再重试）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoisonedTask {
+    pub instrument_id: String,
+    pub shard_id: String,
+    /// 任务标识（订单ID 等）
+    pub task: String,
+    pub panic_message: String,
+    /// 捕获时间（毫秒）
+    pub timestamp: i64,
+}
+
+/// 撮合故障恢复回调（由 `OrderRouter` 实现）
+pub trait MatchingRecoveryHandler: Send + Sync {
+    /// 合约挂单记录，按撮合引擎订单ID升序（同价位内即时间优先顺序）
+    fn resting_orders(&self, instrument_id: &str) -> Result<Vec<RestingOrder>, ExchangeError>;
+
+    /// 订单簿重建后撮合引擎订单ID变化 (原ID, 新ID)
+    fn on_orderbook_rebuilt(&self, instrument_id: &str, remapped: &[(u64, u64)]);
+
+    /// 合约故障状态变化（暂停接单 / 恢复 / 停牌）
+    fn on_fault_event(&self, event: &MatchingFaultEvent);
+}
+
+/// 提取 panic 信息
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        msg.to_string()
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+/// 故障隔离：按合约记录连续失败次数与 poison 队列，各分片线程共享
+struct FaultSupervisor {
+    max_restart_failures: u32,
+    /// 故障合约 -> (状态, 连续失败次数)，恢复后首次成功撮合即移除
+    faults: DashMap<String, (MatchingFaultState, u32)>,
+    poisoned: Mutex<VecDeque<PoisonedTask>>,
+    handler: RwLock<Option<Weak<dyn MatchingRecoveryHandler>>>,
+}
+
+impl FaultSupervisor {
+    fn new(max_restart_failures: u32) -> Self {
+        Self {
+            max_restart_failures: max_restart_failures.max(1),
+            faults: DashMap::new(),
+            poisoned: Mutex::new(VecDeque::new()),
+            handler: RwLock::new(None),
+        }
+    }
+
+    fn handler(&self) -> Option<Arc<dyn MatchingRecoveryHandler>> {
+        self.handler.read().as_ref().and_then(|h| h.upgrade())
+    }
+
+    /// 合约是否可接收撮合任务（恢复中/停牌时拒绝）
+    fn check_available(&self, instrument_id: &str) -> Result<(), ExchangeError> {
+        match self.faults.get(instrument_id).map(|f| f.0) {
+            Some(MatchingFaultState::Recovering) => Err(ExchangeError::MatchingError(format!(
+                "Instrument {} is recovering from a matching fault",
+                instrument_id
+            ))),
+            Some(MatchingFaultState::Halted) => Err(ExchangeError::MatchingError(format!(
+                "Instrument {} is halted after repeated matching faults",
+                instrument_id
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// 任务成功执行：已恢复的合约清零连续失败次数
+    fn on_success(&self, instrument_id: &str) {
+        if !self.faults.is_empty() {
+            self.faults.remove_if(instrument_id, |_, (state, _)| {
+                *state == MatchingFaultState::Recovered
+            });
+        }
+    }
+
+    fn emit(
+        &self,
+        instrument_id: &str,
+        shard_id: &str,
+        state: MatchingFaultState,
+        failures: u32,
+        reason: String,
+    ) {
+        self.faults
+            .insert(instrument_id.to_string(), (state, failures));
+        let event = MatchingFaultEvent {
+            instrument_id: instrument_id.to_string(),
+            shard_id: shard_id.to_string(),
+            state,
+            consecutive_failures: failures,
+            reason,
+            timestamp: chrono::Utc::now().timestamp_millis(),
+        };
+        if let Some(handler) = self.handler() {
+            handler.on_fault_event(&event);
+        }
+    }
+
+    /// 处理分片线程捕获的 panic：记录 poison 任务、暂停合约、重建订单簿
+    fn handle_panic(
+        &self,
+        shard_id: &str,
+        engine: &ExchangeMatchingEngine,
+        instrument_id: &str,
+        task: &str,
+        message: String,
+    ) {
+        log::error!(
+            "Matching task {} on {} (shard {}) panicked: {}",
+            task,
+            instrument_id,
+            shard_id,
+            message
+        );
+        {
+            let mut poisoned = self.poisoned.lock();
+            if poisoned.len() >= MAX_POISONED_TASKS {
+                poisoned.pop_front();
+            }
+            poisoned.push_back(PoisonedTask {
+                instrument_id: instrument_id.to_string(),
+                shard_id: shard_id.to_string(),
+                task: task.to_string(),
+                panic_message: message.clone(),
+                timestamp: chrono::Utc::now().timestamp_millis(),
+            });
+        }
+
+        let mut failures = self.faults.get(instrument_id).map_or(0, |f| f.1) + 1;
+        self.emit(
+            instrument_id,
+            shard_id,
+            MatchingFaultState::Recovering,
+            failures,
+            format!("matching panic: {}", message),
+        );
+
+        while failures < self.max_restart_failures {
+            match self.rebuild(engine, instrument_id) {
+                Ok(count) => {
+                    self.emit(
+                        instrument_id,
+                        shard_id,
+                        MatchingFaultState::Recovered,
+                        failures,
+                        format!("orderbook rebuilt from {} resting orders", count),
+                    );
+                    return;
+                }
+                Err(e) => {
+                    failures += 1;
+                    log::error!(
+                        "Rebuild orderbook {} failed ({}/{}): {}",
+                        instrument_id,
+                        failures,
+                        self.max_restart_failures,
+                        e
+                    );
+                    self.faults.insert(
+                        instrument_id.to_string(),
+                        (MatchingFaultState::Recovering, failures),
+                    );
+                }
+            }
+        }
+
+        log::error!(
+            "Instrument {} halted after {} consecutive matching failures",
+            instrument_id,
+            failures
+        );
+        self.emit(
+            instrument_id,
+            shard_id,
+            MatchingFaultState::Halted,
+            failures,
+            format!("{} consecutive matching failures", failures),
+        );
+    }
+
+    /// 按挂单记录重建订单簿（未设置回调时取故障订单簿现有挂单，尽力恢复）
+    fn rebuild(
+        &self,
+        engine: &ExchangeMatchingEngine,
+        instrument_id: &str,
+    ) -> Result<usize, ExchangeError> {
+        let handler = self.handler();
+        let mut records = match handler {
+            Some(ref handler) => handler.resting_orders(instrument_id)?,
+            None => engine.resting_orders(instrument_id)?,
+        };
+        records.sort_by_key(|o| o.engine_order_id);
+
+        let remapped = panic::catch_unwind(AssertUnwindSafe(|| {
+            engine.rebuild_orderbook(instrument_id, &records)
+        }))
+        .map_err(|payload| {
+            ExchangeError::MatchingError(format!(
+                "Rebuild panicked: {}",
+                panic_message(payload.as_ref())
+            ))
+        })??;
+
+        if let Some(handler) = handler {
+            handler.on_orderbook_rebuilt(instrument_id, &remapped);
+        }
+        Ok(records.len())
+    }
+}
+
+/// 撮合分片：一个撮合引擎实例 + 一个专属撮合线程
+struct MatchingShard {
+    id: String,
+    engine: Arc<ExchangeMatchingEngine>,
+    task_tx: Mutex<Option<Sender<ShardTask>>>,
+    handle: Mutex<Option<JoinHandle<()>>>,
+    processed: Arc<AtomicU64>,
+    pending: Arc<AtomicUsize>,
+}
+
+impl MatchingShard {
+    fn spawn(
+        id: String,
+        queue_capacity: usize,
+        core_id: Option<usize>,
+        supervisor: Arc<FaultSupervisor>,
+        depth_view: Arc<DepthView>,
+    ) -> Result<Self, ExchangeError> {
+        let (task_tx, task_rx): (Sender<ShardTask>, Receiver<ShardTask>) = bounded(queue_capacity);
+        let engine = Arc::new(ExchangeMatchingEngine::with_depth_view(
+            ExchangeClock::System,
+            depth_view,
+        ));
+        let processed = Arc::new(AtomicU64::new(0));
+        let pending = Arc::new(AtomicUsize::new(0));
+
+        let worker_engine = engine.clone();
+        let worker_processed = processed.clone();
+        let worker_pending = pending.clone();
+        let worker_id = id.clone();
+        let thread_name = format!("matching-{}", id);
+        let handle = std::thread::Builder::new()
+            .name(thread_name.clone())
+            .spawn(move || {
+                if let Some(core) = core_id {
+                    if let Err(e) = crate::perf::cpu_affinity::bind_to_core(core) {
+                        log::warn!("{} failed to bind core {}: {:?}", thread_name, core, e);
+                    }
+                }
+                // 当前执行的任务 (合约, 任务标识)，panic 后用于定位故障合约
+                let mut current: Option<(String, String)> = None;
+                loop {
+                    let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+                        // 发送端全部释放后 recv 返回 Err，线程退出
+                        while let Ok(task) = task_rx.recv() {
+                            current = Some((task.instrument_id, task.label));
+                            (task.run)();
+                            if let Some((instrument_id, _)) = current.take() {
+                                supervisor.on_success(&instrument_id);
+                            }
+                            worker_pending.fetch_sub(1, Ordering::Relaxed);
+                            worker_processed.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }));
+                    match outcome {
+                        Ok(()) => break,
+                        Err(payload) => {
+                            // 跳过导致 panic 的任务，重建订单簿后重启撮合循环
+                            worker_pending.fetch_sub(1, Ordering::Relaxed);
+                            let (instrument_id, label) = current.take().unwrap_or_default();
+                            supervisor.handle_panic(
+                                &worker_id,
+                                &worker_engine,
+                                &instrument_id,
+                                &label,
+                                panic_message(payload.as_ref()),
+                            );
+                            log::warn!("{} matching loop restarted", thread_name);
+                        }
+                    }
+                }
+                log::info!("{} stopped", thread_name);
+            })
+            .map_err(|e| ExchangeError::InternalError(format!("Spawn shard thread failed: {}", e)))?;
+
+        Ok(Self {
+            id,
+            engine,
+            task_tx: Mutex::new(Some(task_tx)),
+            handle: Mutex::new(Some(handle)),
+            processed,
+            pending,
+        })
+    }
+
+    /// 投递任务到分片线程
+    fn dispatch(&self, task: ShardTask) -> Result<(), ExchangeError> {
+        let tx = self
+            .task_tx
+            .lock()
+            .clone()
+            .ok_or_else(|| ExchangeError::MatchingError(format!("Shard {} is stopped", self.id)))?;
+        self.pending.fetch_add(1, Ordering::Relaxed);
+        tx.send(task).map_err(|_| {
+            self.pending.fetch_sub(1, Ordering::Relaxed);
+            ExchangeError::MatchingError(format!("Shard {} channel closed", self.id))
+        })
+    }
+
+    /// 等待此前已投递的任务全部执行完毕（单线程按 FIFO 消费，屏障任务执行即表示队列已排空）
+    fn drain(&self) -> Result<(), ExchangeError> {
+        let (done_tx, done_rx) = bounded(1);
+        self.dispatch(ShardTask {
+            instrument_id: String::new(),
+            label: "drain".to_string(),
+            run: Box::new(move || {
+                let _ = done_tx.send(());
+            }),
+        })?;
+        done_rx.recv().map_err(|_| {
+            ExchangeError::MatchingError(format!("Shard {} dropped drain barrier", self.id))
+        })
+    }
+
+    /// 停止分片线程（等待队列中的任务执行完毕）
+    fn stop(&self) {
+        self.task_tx.lock().take();
+        if let Some(handle) = self.handle.lock().take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// 分片撮合引擎
+pub struct ShardedMatchingEngine {
+    /// 合约 → 分片的一致性哈希环
+    ring: ConsistentHashRing,
+
+    /// 分片表 (shard_id -> shard)
+    shards: DashMap<String, Arc<MatchingShard>>,
+
+    /// 合约当前所在分片 (instrument_id -> shard_id)
+    assignments: DashMap<String, String>,
+
+    /// 分片拓扑锁：迁移时持有写锁，路由时持有读锁
+    topology: RwLock<()>,
+
+    /// 累计迁移次数
+    migrations: AtomicU64,
+
+    /// 已分配的核心序号（绑核时使用）
+    next_core: AtomicUsize,
+
+    /// 故障隔离（poison 队列、合约故障状态）
+    supervisor: Arc<FaultSupervisor>,
+
+    /// 各分片共用的深度视图
+    depth_view: Arc<DepthView>,
+
+    config: ShardedMatchingConfig,
+}
+
+impl ShardedMatchingEngine {
+    pub fn new(config: ShardedMatchingConfig) -> Result<Self, ExchangeError> {
+        Self::with_depth_view(config, Arc::new(DepthView::default()))
+    }
+
+    /// 使用指定深度视图创建（传入单撮合引擎的视图时，按单引擎读取深度的查询路径同样可见分片撮合结果）
+    pub fn with_depth_view(
+        config: ShardedMatchingConfig,
+        depth_view: Arc<DepthView>,
+    ) -> Result<Self, ExchangeError> {
+        let engine = Self {
+            ring: ConsistentHashRing::new(config.virtual_nodes),
+            shards: DashMap::new(),
+            assignments: DashMap::new(),
+            topology: RwLock::new(()),
+            migrations: AtomicU64::new(0),
+            next_core: AtomicUsize::new(0),
+            supervisor: Arc::new(FaultSupervisor::new(config.max_restart_failures)),
+            depth_view,
+            config,
+        };
+        for i in 0..engine.config.shard_count.max(1) {
+            engine.add_shard(format!("shard-{}", i))?;
+        }
+        Ok(engine)
+    }
+
+    fn next_core_id(&self) -> Option<usize> {
+        if !self.config.bind_cores {
+            return None;
+        }
+        let cores = crate::perf::cpu_affinity::get_core_count().max(1);
+        Some(self.next_core.fetch_add(1, Ordering::Relaxed) % cores)
+    }
+
+    /// 增加分片，返回因此迁移的合约
+    pub fn add_shard(&self, shard_id: impl Into<String>) -> Result<Vec<ShardMigration>, ExchangeError> {
+        let shard_id = shard_id.into();
+        let _guard = self.topology.write();
+
+        if self.shards.contains_key(&shard_id) {
+            return Err(ExchangeError::MatchingError(format!(
+                "Shard already exists: {}",
+                shard_id
+            )));
+        }
+
+        let shard = MatchingShard::spawn(
+            shard_id.clone(),
+            self.config.queue_capacity,
+            self.next_core_id(),
+            self.supervisor.clone(),
+            self.depth_view.clone(),
+        )?;
+        self.shards.insert(shard_id.clone(), Arc::new(shard));
+        self.ring.add_node(PhysicalNode::new(shard_id.clone(), shard_id.clone()));
+
+        let migrations = self.rebalance();
+        log::info!(
+            "Added matching shard {} ({} instruments migrated)",
+            shard_id,
+            migrations.len()
+        );
+        Ok(migrations)
+    }
+
+    /// 移除分片，其上的合约迁移到剩余分片
+    pub fn remove_shard(&self, shard_id: &str) -> Result<Vec<ShardMigration>, ExchangeError> {
+        let _guard = self.topology.write();
+
+        if !self.shards.contains_key(shard_id) {
+            return Err(ExchangeError::MatchingError(format!(
+                "Shard not found: {}",
+                shard_id
+            )));
+        }
+        if self.shards.len() <= 1 {
+            return Err(ExchangeError::MatchingError(
+                "Cannot remove the last matching shard".to_string(),
+            ));
+        }
+
+        self.ring.remove_node(shard_id);
+        let migrations = self.rebalance();
+
+        if let Some((_, shard)) = self.shards.remove(shard_id) {
+            shard.stop();
+        }
+        log::info!(
+            "Removed matching shard {} ({} instruments migrated)",
+            shard_id,
+            migrations.len()
+        );
+        Ok(migrations)
+    }
+
+    /// 按哈希环重新计算合约归属，搬迁变化的订单簿（调用方需持有拓扑写锁）
+    ///
+    /// 路由在持有拓扑读锁期间完成投递，写锁下不会再有新任务进入原分片；搬迁前先排空原分片队列，
+    /// 已排队的任务执行完毕后再移交订单簿，避免两个分片线程同时操作同一订单簿
+    fn rebalance(&self) -> Vec<ShardMigration> {
+        let current: Vec<(String, String)> = self
+            .assignments
+            .iter()
+            .map(|e| (e.key().clone(), e.value().clone()))
+            .collect();
+
+        let mut migrations = Vec::new();
+        for (instrument_id, from_shard) in current {
+            let to_shard = match self.ring.get_node(&instrument_id) {
+                Some(node) => node.id,
+                None => continue,
+            };
+            if to_shard == from_shard {
+                continue;
+            }
+
+            let (from, to) = match (self.shards.get(&from_shard), self.shards.get(&to_shard)) {
+                (Some(f), Some(t)) => (f.clone(), t.clone()),
+                _ => continue,
+            };
+
+            // 分片线程已退出时队列不再有任务执行
+            if let Err(e) = from.drain() {
+                log::warn!(
+                    "Drain shard {} before migrating {} failed: {}",
+                    from_shard,
+                    instrument_id,
+                    e
+                );
+            }
+            if let Some((orderbook, prev_close)) = from.engine.detach_orderbook(&instrument_id) {
+                to.engine
+                    .attach_orderbook(instrument_id.clone(), orderbook, prev_close);
+            }
+            self.assignments
+                .insert(instrument_id.clone(), to_shard.clone());
+            self.migrations.fetch_add(1, Ordering::Relaxed);
+
+            migrations.push(ShardMigration {
+                instrument_id,
+                from_shard,
+                to_shard,
+            });
+        }
+        migrations
+    }
+
+    /// 注册合约，返回其所在分片
+    pub fn register_instrument(
+        &self,
+        instrument_id: String,
+        prev_close: f64,
+    ) -> Result<String, ExchangeError> {
+        let _guard = self.topology.read();
+
+        let shard_id = self
+            .ring
+            .get_node(&instrument_id)
+            .map(|n| n.id)
+            .ok_or_else(|| ExchangeError::MatchingError("No matching shard available".to_string()))?;
+        let shard = self
+            .shards
+            .get(&shard_id)
+            .map(|s| s.clone())
+            .ok_or_else(|| ExchangeError::MatchingError(format!("Shard not found: {}", shard_id)))?;
+
+        shard
+            .engine
+            .register_instrument(instrument_id.clone(), prev_close)?;
+        self.assignments.insert(instrument_id, shard_id.clone());
+        Ok(shard_id)
+    }
+
+    /// 查询合约所在分片
+    pub fn shard_of(&self, instrument_id: &str) -> Option<String> {
+        self.assignments.get(instrument_id).map(|s| s.value().clone())
+    }
+
+    /// 获取合约所在分片的撮合引擎
+    pub fn engine_for(&self, instrument_id: &str) -> Option<Arc<ExchangeMatchingEngine>> {
+        let _guard = self.topology.read();
+        let shard_id = self.assignments.get(instrument_id)?.value().clone();
+        self.shards.get(&shard_id).map(|s| s.engine.clone())
+    }
+
+    /// 获取合约订单簿（只读查询使用，撮合请走 `execute`/`process_order`）
+    pub fn get_orderbook(&self, instrument_id: &str) -> Option<Arc<TrackedOrderbook>> {
+        self.engine_for(instrument_id)?.get_orderbook(instrument_id)
+    }
+
+    /// 获取合约最新深度快照（不获取订单簿锁）
+    pub fn get_depth_snapshot(&self, instrument_id: &str) -> Option<Arc<DepthSnapshot>> {
+        self.depth_view.get(instrument_id)
+    }
+
+    /// 获取各分片共用的深度视图
+    pub fn depth_view(&self) -> Arc<DepthView> {
+        self.depth_view.clone()
+    }
+
+    /// 所有已注册合约
+    pub fn get_instruments(&self) -> Vec<String> {
+        self.assignments.iter().map(|e| e.key().clone()).collect()
+    }
+
+    /// 在合约所在分片线程上执行订单簿操作，阻塞等待结果
+    pub fn execute<R, F>(&self, instrument_id: &str, f: F) -> Result<R, ExchangeError>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut Orderbook<InstrumentAsset>) -> R + Send + 'static,
+    {
+        self.execute_labeled(instrument_id, String::new(), f)
+    }
+
+    /// 同 [`Self::execute`]，`label` 标识任务（订单ID 等），任务 panic 时写入 poison 队列
+    ///
+    /// 合约恢复中或已停牌时直接拒绝；任务 panic 时返回错误，调用方按撮合失败处理
+    pub fn execute_labeled<R, F>(
+        &self,
+        instrument_id: &str,
+        label: impl Into<String>,
+        f: F,
+    ) -> Result<R, ExchangeError>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut Orderbook<InstrumentAsset>) -> R + Send + 'static,
+    {
+        self.supervisor.check_available(instrument_id)?;
+        // 读锁持有到任务入队：入队前分片不会被移除，订单簿也不会被迁走
+        let guard = self.topology.read();
+        let (shard, orderbook) = {
+            let shard_id = self
+                .assignments
+                .get(instrument_id)
+                .map(|s| s.value().clone())
+                .ok_or_else(|| {
+                    ExchangeError::MatchingError(format!(
+                        "Orderbook not found for instrument: {}",
+                        instrument_id
+                    ))
+                })?;
+            let shard = self
+                .shards
+                .get(&shard_id)
+                .map(|s| s.clone())
+                .ok_or_else(|| ExchangeError::MatchingError(format!("Shard not found: {}", shard_id)))?;
+            let orderbook = shard.engine.get_orderbook(instrument_id).ok_or_else(|| {
+                ExchangeError::MatchingError(format!(
+                    "Orderbook not found for instrument: {}",
+                    instrument_id
+                ))
+            })?;
+            (shard, orderbook)
+        };
+
+        let (result_tx, result_rx) = bounded(1);
+        shard.dispatch(ShardTask {
+            instrument_id: instrument_id.to_string(),
+            label: label.into(),
+            run: Box::new(move || {
+                // 写锁释放前发布深度快照，查询路径读取快照不再与分片线程争锁
+                let result = f(&mut orderbook.write());
+                let _ = result_tx.send(result);
+            }),
+        })?;
+        drop(guard);
+
+        result_rx.recv().map_err(|_| {
+            ExchangeError::MatchingError(format!(
+                "Shard {} dropped task result for {}",
+                shard.id, instrument_id
+            ))
+        })
+    }
+
+    /// 在合约所在分片撮合订单
+    pub fn process_order(
+        &self,
+        instrument_id: &str,
+        request: OrderRequest<InstrumentAsset>,
+    ) -> Result<Vec<Result<Success, Failed>>, ExchangeError> {
+        self.execute(instrument_id, move |ob| {
+            ob.process_order(request).into_iter().collect::<Vec<_>>()
+        })
+    }
+
+    /// 设置撮合故障恢复回调（持弱引用，避免与持有分片引擎的路由器循环引用）
+    pub fn set_recovery_handler(&self, handler: Weak<dyn MatchingRecoveryHandler>) {
+        *self.supervisor.handler.write() = Some(handler);
+    }
+
+    /// poison 队列（最近的在后）
+    pub fn poisoned_tasks(&self) -> Vec<PoisonedTask> {
+        self.supervisor.poisoned.lock().iter().cloned().collect()
+    }
+
+    /// 合约撮合故障状态（未发生故障或已恢复并成功撮合时返回 None）
+    pub fn fault_state(&self, instrument_id: &str) -> Option<MatchingFaultState> {
+        self.supervisor.faults.get(instrument_id).map(|f| f.0)
+    }
+
+    /// 设置所有分片的交易日
+    pub fn set_trading_day(&self, trading_day: String) {
+        for shard in self.shards.iter() {
+            shard.engine.set_trading_day(trading_day.clone());
+        }
+    }
+
+    /// 分片数量
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// 各分片排队待处理的任务总数
+    pub fn pending_tasks(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|s| s.pending.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    /// 跨分片统一监控
+    pub fn get_stats(&self) -> ShardedMatchingStats {
+        let mut shards: Vec<ShardStatus> = self
+            .shards
+            .iter()
+            .map(|s| {
+                let mut instruments = s.engine.get_instruments();
+                instruments.sort();
+                ShardStatus {
+                    shard_id: s.id.clone(),
+                    instruments,
+                    processed_tasks: s.processed.load(Ordering::Relaxed),
+                    pending_tasks: s.pending.load(Ordering::Relaxed),
+                }
+            })
+            .collect();
+        shards.sort_by(|a, b| a.shard_id.cmp(&b.shard_id));
+
+        ShardedMatchingStats {
+            shard_count: shards.len(),
+            instrument_count: self.assignments.len(),
+            total_processed: shards.iter().map(|s| s.processed_tasks).sum(),
+            migrations: self.migrations.load(Ordering::Relaxed),
+            poisoned_tasks: self.supervisor.poisoned.lock().len(),
+            halted_instruments: {
+                let mut halted: Vec<String> = self
+                    .supervisor
+                    .faults
+                    .iter()
+                    .filter(|f| f.value().0 == MatchingFaultState::Halted)
+                    .map(|f| f.key().clone())
+                    .collect();
+                halted.sort();
+                halted
+            },
+            shards,
+        }
+    }
+
+    /// 停止所有分片线程
+    pub fn shutdown(&self) {
+        for shard in self.shards.iter() {
+            shard.stop();
+        }
+    }
+}
+
+impl Drop for ShardedMatchingEngine {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matching::{orders, OrderDirection};
+
+    fn create_engine(shards: usize) -> ShardedMatchingEngine {
+        ShardedMatchingEngine::new(ShardedMatchingConfig {
+            shard_count: shards,
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    fn limit(instrument: &str, direction: OrderDirection, price: f64, volume: f64, ts: i64) -> OrderRequest<InstrumentAsset> {
+        orders::new_limit_order_request(
+            InstrumentAsset::from_code(instrument),
+            direction,
+            price,
+            volume,
+            ts,
+        )
+    }
+
+    /// 同一合约始终路由到同一分片
+    #[test]
+    fn test_instrument_routing_is_stable() {
+        let engine = create_engine(4);
+        let shard = engine.register_instrument("cu2501".to_string(), 85000.0).unwrap();
+
+        for _ in 0..100 {
+            assert_eq!(engine.shard_of("cu2501"), Some(shard.clone()));
+        }
+        assert!(engine.get_orderbook("cu2501").is_some());
+        assert_eq!(engine.shard_count(), 4);
+    }
+
+    /// 多分片下不同合约并行撮合互不干扰
+    #[test]
+    fn test_parallel_matching_across_shards() {
+        let engine = Arc::new(create_engine(4));
+        let instruments: Vec<String> = (0..16).map(|i| format!("IF25{:02}", i)).collect();
+        for inst in &instruments {
+            engine.register_instrument(inst.clone(), 100.0).unwrap();
+        }
+
+        let used_shards: std::collections::HashSet<String> = instruments
+            .iter()
+            .filter_map(|i| engine.shard_of(i))
+            .collect();
+        assert!(used_shards.len() > 1, "合约应分散到多个分片");
+
+        let handles: Vec<_> = instruments
+            .iter()
+            .cloned()
+            .map(|inst| {
+                let engine = engine.clone();
+                std::thread::spawn(move || {
+                    let mut filled = 0;
+                    for i in 0..50 {
+                        let ts = i * 2;
+                        engine
+                            .process_order(&inst, limit(&inst, OrderDirection::SELL, 100.0, 1.0, ts))
+                            .unwrap();
+                        let results = engine
+                            .process_order(&inst, limit(&inst, OrderDirection::BUY, 100.0, 1.0, ts + 1))
+                            .unwrap();
+                        if results.iter().any(|r| matches!(r, Ok(Success::Filled { .. }))) {
+                            filled += 1;
+                        }
+                    }
+                    filled
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), 50, "每个合约的订单只与本合约对手盘成交");
+        }
+
+        for inst in &instruments {
+            let ob = engine.get_orderbook(inst).unwrap();
+            let ob = ob.read();
+            assert_eq!(ob.lastprice, 100.0);
+            assert!(ob.bid_queue.get_sorted_orders().map_or(true, |o| o.is_empty()));
+            assert!(ob.ask_queue.get_sorted_orders().map_or(true, |o| o.is_empty()));
+
+            // 分片线程撮合后同步发布深度快照
+            let snapshot = engine.get_depth_snapshot(inst).unwrap();
+            assert_eq!(snapshot.sequence, 100);
+            assert_eq!(snapshot.last_price, 100.0);
+            assert_eq!(snapshot.bid_order_count + snapshot.ask_order_count, 0);
+        }
+
+        let stats = engine.get_stats();
+        assert_eq!(stats.instrument_count, 16);
+        assert_eq!(stats.total_processed, 16 * 100);
+    }
+
+    /// 分片增加时合约迁移，挂单随订单簿一起迁移
+    #[test]
+    fn test_add_shard_migrates_orderbooks() {
+        let engine = create_engine(1);
+        let instruments: Vec<String> = (0..32).map(|i| format!("rb25{:02}", i)).collect();
+        for inst in &instruments {
+            engine.register_instrument(inst.clone(), 3500.0).unwrap();
+            engine
+                .process_order(inst, limit(inst, OrderDirection::BUY, 3500.0, 2.0, 1))
+                .unwrap();
+        }
+
+        let migrations = engine.add_shard("shard-new").unwrap();
+        assert!(!migrations.is_empty(), "新增分片应迁移部分合约");
+        for m in &migrations {
+            assert_eq!(m.to_shard, "shard-new");
+            assert_eq!(engine.shard_of(&m.instrument_id).as_deref(), Some("shard-new"));
+        }
+
+        // 迁移后挂单仍在，且可以在新分片继续撮合
+        for inst in &instruments {
+            let results = engine
+                .process_order(inst, limit(inst, OrderDirection::SELL, 3500.0, 2.0, 2))
+                .unwrap();
+            assert!(results.iter().any(|r| matches!(r, Ok(Success::Filled { .. }))));
+        }
+        assert_eq!(engine.get_stats().migrations, migrations.len() as u64);
+    }
+
+    /// 撮合任务与分片增删并发：任务不丢失，同一订单簿不会被两个分片线程同时操作
+    #[test]
+    fn test_topology_changes_during_matching() {
+        use std::sync::atomic::AtomicBool;
+
+        let engine = Arc::new(create_engine(2));
+        let instruments: Vec<String> = (0..8).map(|i| format!("ni25{:02}", i)).collect();
+        for inst in &instruments {
+            engine.register_instrument(inst.clone(), 130000.0).unwrap();
+        }
+
+        let overlaps = Arc::new(AtomicUsize::new(0));
+        let handles: Vec<_> = instruments
+            .iter()
+            .cloned()
+            .map(|inst| {
+                let engine = engine.clone();
+                let overlaps = overlaps.clone();
+                let busy = Arc::new(AtomicBool::new(false));
+                std::thread::spawn(move || {
+                    for _ in 0..200 {
+                        let busy = busy.clone();
+                        let overlaps = overlaps.clone();
+                        engine
+                            .execute(&inst, move |_| {
+                                if busy.swap(true, Ordering::SeqCst) {
+                                    overlaps.fetch_add(1, Ordering::SeqCst);
+                                }
+                                std::thread::sleep(std::time::Duration::from_micros(50));
+                                busy.store(false, Ordering::SeqCst);
+                            })
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+
+        for i in 0..10 {
+            let shard_id = format!("extra-{}", i);
+            engine.add_shard(shard_id.clone()).unwrap();
+            engine.remove_shard(&shard_id).unwrap();
+        }
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(overlaps.load(Ordering::SeqCst), 0);
+        assert_eq!(engine.shard_count(), 2);
+    }
+
+    /// 移除分片时合约迁移到剩余分片
+    #[test]
+    fn test_remove_shard() {
+        let engine = create_engine(3);
+        for i in 0..20 {
+            engine.register_instrument(format!("au25{:02}", i), 600.0).unwrap();
+        }
+
+        let migrations = engine.remove_shard("shard-0").unwrap();
+        assert_eq!(engine.shard_count(), 2);
+        for m in &migrations {
+            assert_eq!(m.from_shard, "shard-0");
+        }
+        for inst in engine.get_instruments() {
+            assert_ne!(engine.shard_of(&inst).as_deref(), Some("shard-0"));
+            assert!(engine.get_orderbook(&inst).is_some());
+        }
+
+        assert!(engine.remove_shard("shard-0").is_err());
+        engine.remove_shard("shard-1").unwrap();
+        assert!(engine.remove_shard("shard-2").is_err(), "不能移除最后一个分片");
+    }
+
+    /// 测试用恢复回调：挂单记录来自订单状态，记录故障事件
+    #[derive(Default)]
+    struct RecordingHandler {
+        resting: Mutex<Vec<RestingOrder>>,
+        fail_records: std::sync::atomic::AtomicBool,
+        remapped: Mutex<Vec<(u64, u64)>>,
+        events: Mutex<Vec<MatchingFaultEvent>>,
+    }
+
+    impl MatchingRecoveryHandler for RecordingHandler {
+        fn resting_orders(&self, instrument_id: &str) -> Result<Vec<RestingOrder>, ExchangeError> {
+            if self.fail_records.load(Ordering::SeqCst) {
+                return Err(ExchangeError::InternalError(format!("No records for {}", instrument_id)));
+            }
+            Ok(self.resting.lock().clone())
+        }
+
+        fn on_orderbook_rebuilt(&self, _instrument_id: &str, remapped: &[(u64, u64)]) {
+            self.remapped.lock().extend_from_slice(remapped);
+        }
+
+        fn on_fault_event(&self, event: &MatchingFaultEvent) {
+            self.events.lock().push(event.clone());
+        }
+    }
+
+    fn inject_panic(engine: &ShardedMatchingEngine, instrument: &str, label: &str) -> Result<(), ExchangeError> {
+        engine
+            .execute_labeled(instrument, label, |_ob| -> bool { panic!("injected matching fault") })
+            .map(|_| ())
+    }
+
+    /// 合约撮合 panic：跳过毒任务、重建订单簿后恢复，同分片其他合约照常撮合
+    #[test]
+    fn test_panic_isolated_and_orderbook_rebuilt() {
+        let engine = create_engine(1);
+        engine.register_instrument("cu2501".to_string(), 85000.0).unwrap();
+        engine.register_instrument("al2501".to_string(), 20000.0).unwrap();
+        assert_eq!(engine.shard_of("cu2501"), engine.shard_of("al2501"));
+
+        let handler = Arc::new(RecordingHandler::default());
+        let recovery: Arc<dyn MatchingRecoveryHandler> = handler.clone();
+        engine.set_recovery_handler(Arc::downgrade(&recovery));
+
+        let results = engine
+            .process_order("cu2501", limit("cu2501", OrderDirection::BUY, 85000.0, 2.0, 1))
+            .unwrap();
+        let engine_order_id = match results[0] {
+            Ok(Success::Accepted { id, .. }) => id,
+            _ => panic!("expected accepted"),
+        };
+        // 订单状态中的挂单记录（剩余量以记录为准）
+        *handler.resting.lock() = vec![RestingOrder {
+            engine_order_id,
+            direction: "BUY",
+            price: 85000.0,
+            volume: 2.0,
+        }];
+
+        assert!(inject_panic(&engine, "cu2501", "O-POISON").is_err());
+
+        // 同分片的其他合约照常撮合（任务排在恢复之后执行）
+        engine
+            .process_order("al2501", limit("al2501", OrderDirection::SELL, 20000.0, 1.0, 1))
+            .unwrap();
+        let results = engine
+            .process_order("al2501", limit("al2501", OrderDirection::BUY, 20000.0, 1.0, 2))
+            .unwrap();
+        assert!(results.iter().any(|r| matches!(r, Ok(Success::Filled { .. }))));
+
+        let poisoned = engine.poisoned_tasks();
+        assert_eq!(poisoned.len(), 1);
+        assert_eq!(poisoned[0].instrument_id, "cu2501");
+        assert_eq!(poisoned[0].task, "O-POISON");
+        assert!(poisoned[0].panic_message.contains("injected matching fault"));
+        assert_eq!(engine.fault_state("cu2501"), Some(MatchingFaultState::Recovered));
+        let states: Vec<_> = handler.events.lock().iter().map(|e| e.state).collect();
+        assert_eq!(states, vec![MatchingFaultState::Recovering, MatchingFaultState::Recovered]);
+        let remapped = handler.remapped.lock().clone();
+        assert_eq!(remapped.len(), 1);
+        assert_eq!(remapped[0].0, engine_order_id);
+
+        // 重建后的挂单继续参与撮合，成功撮合后清除故障状态
+        let results = engine
+            .process_order("cu2501", limit("cu2501", OrderDirection::SELL, 85000.0, 2.0, 2))
+            .unwrap();
+        assert!(results.iter().any(|r| matches!(r, Ok(Success::Filled { .. }))));
+        assert_eq!(engine.fault_state("cu2501"), None);
+        assert_eq!(engine.get_stats().poisoned_tasks, 1);
+    }
+
+    /// 连续失败达到上限后合约永久停牌，其他合约不受影响
+    #[test]
+    fn test_repeated_failures_halt_instrument() {
+        let engine = ShardedMatchingEngine::new(ShardedMatchingConfig {
+            shard_count: 1,
+            max_restart_failures: 3,
+            ..Default::default()
+        })
+        .unwrap();
+        engine.register_instrument("ni2501".to_string(), 130000.0).unwrap();
+        engine.register_instrument("zn2501".to_string(), 24000.0).unwrap();
+
+        let handler = Arc::new(RecordingHandler::default());
+        handler.fail_records.store(true, Ordering::SeqCst);
+        let recovery: Arc<dyn MatchingRecoveryHandler> = handler.clone();
+        engine.set_recovery_handler(Arc::downgrade(&recovery));
+
+        assert!(inject_panic(&engine, "ni2501", "O-1").is_err());
+        engine
+            .process_order("zn2501", limit("zn2501", OrderDirection::BUY, 24000.0, 1.0, 1))
+            .unwrap();
+
+        assert_eq!(engine.fault_state("ni2501"), Some(MatchingFaultState::Halted));
+        let events = handler.events.lock().clone();
+        assert_eq!(events.last().unwrap().state, MatchingFaultState::Halted);
+        assert_eq!(events.last().unwrap().consecutive_failures, 3);
+        assert_eq!(engine.get_stats().halted_instruments, vec!["ni2501".to_string()]);
+
+        // 停牌合约拒绝撮合，其他合约照常成交
+        assert!(engine
+            .process_order("ni2501", limit("ni2501", OrderDirection::BUY, 130000.0, 1.0, 2))
+            .is_err());
+        let results = engine
+            .process_order("zn2501", limit("zn2501", OrderDirection::SELL, 24000.0, 1.0, 2))
+            .unwrap();
+        assert!(results.iter().any(|r| matches!(r, Ok(Success::Filled { .. }))));
+        assert_eq!(engine.poisoned_tasks().len(), 1);
+    }
+
+    #[test]
+    fn test_unknown_instrument() {
+        let engine = create_engine(2);
+        let result = engine.process_order("XX0000", limit("XX0000", OrderDirection::BUY, 1.0, 1.0, 1));
+        assert!(result.is_err());
+        assert!(engine.add_shard("shard-0").is_err());
+    }
+}
@@ -3,7 +3,8 @@
 //! 记录所有撮合成交记录，供查询和统计使用
 
 use crate::core::Trade;
-use chrono::Utc;
+use crate::exchange::deterministic::{Clock, ExchangeClock};
+use crate::exchange::TradeType;
 use dashmap::DashMap;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
@@ -24,8 +25,15 @@ pub struct TradeRecord {
     pub taker_order_id: String,
     pub price: f64,
     pub volume: f64,
+    /// 成交时间（UTC 纳秒）
     pub timestamp: i64,
     pub trading_day: String,
+    /// 成交类型（大宗交易单独标记）
+    #[serde(default)]
+    pub trade_type: TradeType,
+    /// 撮合批次号（同一次撮合产生的成交相同，0 表示未关联批次）
+    #[serde(default)]
+    pub match_batch_id: i64,
 }
 
 /// 成交记录器
@@ -39,17 +47,30 @@ pub struct TradeRecorder {
     /// 按账户索引 (user_id -> Vec<trade_id>)
     by_user: DashMap<String, Arc<RwLock<Vec<String>>>>,
 
+    /// 按撮合批次索引 (match_batch_id -> Vec<trade_id>)
+    by_batch: DashMap<i64, Arc<RwLock<Vec<String>>>>,
+
     /// 成交序号生成器
     sequence: Arc<RwLock<u64>>,
+
+    /// 成交时间来源（确定性模式下为虚拟时钟）
+    clock: ExchangeClock,
 }
 
 impl TradeRecorder {
     pub fn new() -> Self {
+        Self::with_clock(ExchangeClock::System)
+    }
+
+    /// 使用指定时钟创建
+    pub fn with_clock(clock: ExchangeClock) -> Self {
         Self {
             trades: DashMap::new(),
             by_instrument: DashMap::new(),
             by_user: DashMap::new(),
+            by_batch: DashMap::new(),
             sequence: Arc::new(RwLock::new(1)),
+            clock,
         }
     }
 
@@ -67,9 +88,39 @@ impl TradeRecorder {
         price: f64,
         volume: f64,
         trading_day: String,
+    ) -> String {
+        self.record_trade_at(
+            instrument_id,
+            buy_user_id,
+            sell_user_id,
+            buy_order_id,
+            sell_order_id,
+            taker_order_id,
+            price,
+            volume,
+            trading_day,
+            self.clock.now_nanos(),
+        )
+    }
+
+    /// 按指定成交时间（纳秒）记录成交
+    ///
+    /// 成交回报、WAL 与成交记录必须使用同一个成交时间，调用方已取得成交时间时走这里，
+    /// 避免两次取时导致查询到的成交时间与回报/存储错位
+    pub fn record_trade_at(
+        &self,
+        instrument_id: String,
+        buy_user_id: String,
+        sell_user_id: String,
+        buy_order_id: String,
+        sell_order_id: String,
+        taker_order_id: String,
+        price: f64,
+        volume: f64,
+        trading_day: String,
+        timestamp: i64,
     ) -> String {
         let trade_id = self.generate_trade_id();
-        let timestamp = Utc::now().timestamp_nanos_opt().unwrap_or(0);
 
         let record = TradeRecord {
             trade_id: trade_id.clone(),
@@ -83,6 +134,8 @@ impl TradeRecorder {
             volume,
             timestamp,
             trading_day,
+            trade_type: TradeType::Normal,
+            match_batch_id: 0,
         };
 
         // 存储成交记录
@@ -112,6 +165,76 @@ impl TradeRecorder {
         trade_id
     }
 
+    /// 记录撮合成交并归入撮合批次
+    ///
+    /// timestamp: 成交时间（纳秒），与成交回报/WAL 中的成交时间一致
+    pub fn record_matched_trade(
+        &self,
+        instrument_id: String,
+        buy_user_id: String,
+        sell_user_id: String,
+        buy_order_id: String,
+        sell_order_id: String,
+        taker_order_id: String,
+        price: f64,
+        volume: f64,
+        trading_day: String,
+        match_batch_id: i64,
+        timestamp: i64,
+    ) -> String {
+        let trade_id = self.record_trade_at(
+            instrument_id,
+            buy_user_id,
+            sell_user_id,
+            buy_order_id,
+            sell_order_id,
+            taker_order_id,
+            price,
+            volume,
+            trading_day,
+            timestamp,
+        );
+        if let Some(mut record) = self.trades.get_mut(&trade_id) {
+            record.match_batch_id = match_batch_id;
+        }
+        self.by_batch
+            .entry(match_batch_id)
+            .or_insert_with(|| Arc::new(RwLock::new(Vec::new())))
+            .write()
+            .push(trade_id.clone());
+        trade_id
+    }
+
+    /// 记录大宗交易成交（双方订单号均为大宗交易编号，无主动方）
+    pub fn record_block_trade(
+        &self,
+        instrument_id: String,
+        buy_user_id: String,
+        sell_user_id: String,
+        block_trade_id: String,
+        price: f64,
+        volume: f64,
+        trading_day: String,
+        timestamp: i64,
+    ) -> String {
+        let trade_id = self.record_trade_at(
+            instrument_id,
+            buy_user_id,
+            sell_user_id,
+            block_trade_id.clone(),
+            block_trade_id,
+            String::new(),
+            price,
+            volume,
+            trading_day,
+            timestamp,
+        );
+        if let Some(mut record) = self.trades.get_mut(&trade_id) {
+            record.trade_type = TradeType::Block;
+        }
+        trade_id
+    }
+
     /// 查询成交记录
     pub fn get_trade(&self, trade_id: &str) -> Option<TradeRecord> {
         self.trades.get(trade_id).map(|r| r.value().clone())
@@ -143,6 +266,36 @@ impl TradeRecorder {
         }
     }
 
+    /// 按撮合批次聚合查询：该批次全部成交（按成交顺序）与成交量加权均价
+    pub fn get_batch_summary(&self, match_batch_id: i64) -> Option<MatchBatchSummary> {
+        let trades: Vec<TradeRecord> = self
+            .by_batch
+            .get(&match_batch_id)?
+            .read()
+            .iter()
+            .filter_map(|id| self.get_trade(id))
+            .collect();
+        let first = trades.first()?;
+
+        let total_volume: f64 = trades.iter().map(|t| t.volume).sum();
+        let total_amount: f64 = trades.iter().map(|t| t.price * t.volume).sum();
+        let avg_price = if total_volume > 0.0 {
+            total_amount / total_volume
+        } else {
+            0.0
+        };
+
+        Some(MatchBatchSummary {
+            match_batch_id,
+            instrument_id: first.instrument_id.clone(),
+            taker_order_id: first.taker_order_id.clone(),
+            total_volume,
+            total_amount,
+            avg_price,
+            trades,
+        })
+    }
+
     /// 获取成交统计
     pub fn get_trade_stats(&self, instrument_id: &str) -> TradeStats {
         let trades = self.get_trades_by_instrument(instrument_id);
@@ -199,6 +352,7 @@ impl TradeRecorder {
         self.trades.clear();
         self.by_instrument.clear();
         self.by_user.clear();
+        self.by_batch.clear();
         *self.sequence.write() = 1;
     }
 }
@@ -220,6 +374,20 @@ pub struct TradeStats {
     pub lowest_price: f64,
 }
 
+/// 撮合批次聚合（一笔委托一次撮合吃多档产生的全部成交）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchBatchSummary {
+    pub match_batch_id: i64,
+    pub instrument_id: String,
+    /// 主动方订单ID
+    pub taker_order_id: String,
+    pub total_volume: f64,
+    pub total_amount: f64,
+    /// 成交量加权均价
+    pub avg_price: f64,
+    pub trades: Vec<TradeRecord>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -6,8 +6,10 @@
 //! 3. 消息去重（基于message_id）
 //! 4. 消息持久化（可选，支持断线重连）
 //! 5. 优先级队列管理
+//! 6. 系统事件路由（非用户定向，按类别推送给运维订阅者）
 
-use super::message::Notification;
+use super::message::{Notification, NotificationPayload, NotificationType};
+use super::system_event::{SystemEvent, SystemEventCategory};
 use crossbeam::queue::ArrayQueue;
 use dashmap::DashMap;
 use parking_lot::Mutex;
@@ -29,6 +31,15 @@ pub struct NotificationBroker {
     /// 使用 DashMap 存储，key 为 gateway_id
     global_subscribers: DashMap<Arc<str>, mpsc::UnboundedSender<Notification>>,
 
+    /// 系统事件订阅者：subscriber_id -> (Sender, 订阅类别，空集合表示全部)
+    system_subscribers: DashMap<
+        Arc<str>,
+        (
+            mpsc::UnboundedSender<Notification>,
+            HashSet<SystemEventCategory>,
+        ),
+    >,
+
     /// 消息去重缓存（最近1小时的消息ID）
     /// 使用 Mutex<HashSet> 保护（短暂锁定）
     dedup_cache: Arc<Mutex<HashSet<Arc<str>>>>,
@@ -61,6 +72,7 @@ impl NotificationBroker {
             user_gateways: DashMap::new(),
             gateway_senders: DashMap::new(),
             global_subscribers: DashMap::new(),
+            system_subscribers: DashMap::new(),
             dedup_cache: Arc::new(Mutex::new(HashSet::new())),
             priority_queues: [
                 Arc::new(ArrayQueue::new(10000)),  // P0队列
@@ -139,6 +151,42 @@ impl NotificationBroker {
         log::info!("Global subscriber unregistered: {}", subscriber_id);
     }
 
+    /// 订阅系统事件（调用方负责校验 ViewMonitoring 权限）
+    ///
+    /// # 参数
+    /// - `subscriber_id`: 订阅者ID（如：WebSocket 会话ID）
+    /// - `categories`: 订阅的事件类别，空集合表示全部类别
+    /// - `sender`: 发送通道
+    pub fn subscribe_system_events(
+        &self,
+        subscriber_id: impl Into<Arc<str>>,
+        categories: HashSet<SystemEventCategory>,
+        sender: mpsc::UnboundedSender<Notification>,
+    ) {
+        let subscriber_id = subscriber_id.into();
+        log::info!(
+            "System event subscriber registered: {} (categories: {:?})",
+            subscriber_id,
+            categories
+        );
+        self.system_subscribers
+            .insert(subscriber_id, (sender, categories));
+    }
+
+    /// 取消系统事件订阅
+    pub fn unsubscribe_system_events(&self, subscriber_id: &str) {
+        if self.system_subscribers.remove(subscriber_id).is_some() {
+            log::info!("System event subscriber unregistered: {}", subscriber_id);
+        }
+    }
+
+    /// 发布系统事件
+    pub fn publish_system_event(&self, event: SystemEvent, source: &str) {
+        if let Err(e) = self.publish(event.into_notification(source)) {
+            log::error!("Failed to publish system event: {}", e);
+        }
+    }
+
     /// 取消订阅
     pub fn unsubscribe(&self, user_id: &str, gateway_id: &str) {
         if let Some(mut gateways) = self.user_gateways.get_mut(user_id) {
@@ -186,6 +234,13 @@ impl NotificationBroker {
 
     /// 路由通知到Gateway
     fn route_notification(&self, notification: &Notification) {
+        // 系统事件不面向用户，只发送到系统事件订阅者（及全局订阅者）
+        if notification.message_type == NotificationType::SystemEvent {
+            self.route_system_event(notification);
+            self.route_global(notification);
+            return;
+        }
+
         // 1. 发送到用户特定的 Gateway
         if let Some(gateways) = self.user_gateways.get(notification.user_id.as_ref()) {
             for gateway_id in gateways.iter() {
@@ -207,6 +262,37 @@ impl NotificationBroker {
         }
 
         // 2. 发送到所有全局订阅者
+        self.route_global(notification);
+    }
+
+    /// 路由系统事件到订阅了对应类别的订阅者
+    fn route_system_event(&self, notification: &Notification) {
+        let category = match &notification.payload {
+            NotificationPayload::SystemEvent(event) => SystemEventCategory::parse(&event.category),
+            _ => None,
+        };
+
+        for entry in self.system_subscribers.iter() {
+            let (sender, categories) = entry.value();
+            let matched = match category {
+                Some(category) => categories.is_empty() || categories.contains(&category),
+                None => categories.is_empty(),
+            };
+            if !matched {
+                continue;
+            }
+            if let Err(e) = sender.send(notification.clone()) {
+                log::error!(
+                    "Failed to send system event to subscriber {}: {}",
+                    entry.key(),
+                    e
+                );
+            }
+        }
+    }
+
+    /// 发送到所有全局订阅者
+    fn route_global(&self, notification: &Notification) {
         for entry in self.global_subscribers.iter() {
             let subscriber_id = entry.key();
             let sender = entry.value();
@@ -283,6 +369,11 @@ impl NotificationBroker {
         })
     }
 
+    /// 优先级队列中待分发的通知总数
+    pub fn queued_len(&self) -> usize {
+        self.priority_queues.iter().map(|q| q.len()).sum()
+    }
+
     /// 获取统计信息
     pub fn get_stats(&self) -> BrokerStatsSnapshot {
         BrokerStatsSnapshot {
@@ -336,9 +427,8 @@ pub struct BrokerStatsSnapshot {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::notification::message::{
-        AccountUpdateNotify, NotificationPayload, NotificationType,
-    };
+    use crate::notification::message::AccountUpdateNotify;
+    use crate::notification::system_event::SystemEventLevel;
 
     #[tokio::test]
     async fn test_broker_creation() {
@@ -496,4 +586,60 @@ mod tests {
         let stats = broker.get_stats();
         println!("Queue sizes: {:?}", stats.queue_sizes);
     }
+
+    #[tokio::test]
+    async fn test_system_event_routing_by_category() {
+        let broker = Arc::new(NotificationBroker::new());
+        let (user_tx, mut user_rx) = mpsc::unbounded_channel();
+        let (all_tx, mut all_rx) = mpsc::unbounded_channel();
+        let (storage_tx, mut storage_rx) = mpsc::unbounded_channel();
+
+        broker.register_gateway("gateway_01", user_tx);
+        broker.subscribe("user_01", "gateway_01");
+        broker.subscribe_system_events("admin_all", HashSet::new(), all_tx);
+        broker.subscribe_system_events(
+            "admin_storage",
+            HashSet::from([SystemEventCategory::Storage]),
+            storage_tx,
+        );
+
+        let _processor = broker.clone().start_priority_processor();
+
+        broker.publish_system_event(
+            SystemEvent::new(
+                SystemEventCategory::Settlement,
+                SystemEventLevel::Info,
+                "结算开始",
+            ),
+            "Test",
+        );
+
+        let received = tokio::time::timeout(std::time::Duration::from_millis(100), all_rx.recv())
+            .await
+            .expect("Timeout waiting for system event")
+            .unwrap();
+        assert_eq!(received.message_type, NotificationType::SystemEvent);
+
+        // 类别不匹配的订阅者与普通用户网关都收不到
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        assert!(storage_rx.try_recv().is_err());
+        assert!(user_rx.try_recv().is_err());
+
+        broker.unsubscribe_system_events("admin_all");
+        broker.publish_system_event(
+            SystemEvent::new(
+                SystemEventCategory::Storage,
+                SystemEventLevel::Error,
+                "保留策略清理失败",
+            ),
+            "Test",
+        );
+        let received =
+            tokio::time::timeout(std::time::Duration::from_millis(100), storage_rx.recv())
+                .await
+                .expect("Timeout waiting for system event")
+                .unwrap();
+        assert_eq!(received.message_type, NotificationType::SystemEvent);
+        assert!(all_rx.try_recv().is_err());
+    }
 }
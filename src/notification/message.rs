@@ -199,11 +199,17 @@ pub enum NotificationType {
     MarginCall,
     PositionLimit,
 
+    // 算法单相关（P2 - 中优先级）
+    AlgoOrderProgress,
+
     // 系统相关（P3 - 低优先级）
     SystemNotice,
     TradingSessionStart,
     TradingSessionEnd,
     MarketHalt,
+
+    // 系统事件（运维监控，非用户定向）
+    SystemEvent,
 }
 
 impl NotificationType {
@@ -224,13 +230,15 @@ impl NotificationType {
             Self::AccountOpen
             | Self::AccountUpdate
             | Self::PositionUpdate
-            | Self::PositionProfit => 2,
+            | Self::PositionProfit
+            | Self::AlgoOrderProgress => 2,
 
             // P3 - 低优先级（<1s）
             Self::SystemNotice
             | Self::TradingSessionStart
             | Self::TradingSessionEnd
             | Self::MarketHalt
+            | Self::SystemEvent
             | Self::OrderExpired
             | Self::TradeCanceled
             | Self::PositionLimit => 3,
@@ -250,7 +258,8 @@ impl NotificationType {
             | Self::OrderCanceled
             | Self::OrderExpired
             | Self::TradeExecuted
-            | Self::TradeCanceled => "trade",
+            | Self::TradeCanceled
+            | Self::AlgoOrderProgress => "trade",
 
             // 账户频道
             Self::AccountOpen | Self::AccountUpdate => "account",
@@ -266,9 +275,41 @@ impl NotificationType {
             | Self::TradingSessionStart
             | Self::TradingSessionEnd
             | Self::MarketHalt => "system",
+
+            // 系统事件频道（需要 ViewMonitoring 权限）
+            Self::SystemEvent => "system_events",
         }
     }
 
+    /// 从类型名称解析（`as_str` 的逆操作）
+    pub fn parse(name: &str) -> Option<Self> {
+        let message_type = match name {
+            "order_accepted" => Self::OrderAccepted,
+            "order_rejected" => Self::OrderRejected,
+            "order_partially_filled" => Self::OrderPartiallyFilled,
+            "order_filled" => Self::OrderFilled,
+            "order_canceled" => Self::OrderCanceled,
+            "order_expired" => Self::OrderExpired,
+            "trade_executed" => Self::TradeExecuted,
+            "trade_canceled" => Self::TradeCanceled,
+            "account_open" => Self::AccountOpen,
+            "account_update" => Self::AccountUpdate,
+            "position_update" => Self::PositionUpdate,
+            "position_profit" => Self::PositionProfit,
+            "risk_alert" => Self::RiskAlert,
+            "margin_call" => Self::MarginCall,
+            "position_limit" => Self::PositionLimit,
+            "algo_order_progress" => Self::AlgoOrderProgress,
+            "system_notice" => Self::SystemNotice,
+            "trading_session_start" => Self::TradingSessionStart,
+            "trading_session_end" => Self::TradingSessionEnd,
+            "market_halt" => Self::MarketHalt,
+            "system_event" => Self::SystemEvent,
+            _ => return None,
+        };
+        Some(message_type)
+    }
+
     /// 返回类型名称（静态字符串，零分配）
     pub fn as_str(&self) -> &'static str {
         match self {
@@ -287,10 +328,12 @@ impl NotificationType {
             Self::RiskAlert => "risk_alert",
             Self::MarginCall => "margin_call",
             Self::PositionLimit => "position_limit",
+            Self::AlgoOrderProgress => "algo_order_progress",
             Self::SystemNotice => "system_notice",
             Self::TradingSessionStart => "trading_session_start",
             Self::TradingSessionEnd => "trading_session_end",
             Self::MarketHalt => "market_halt",
+            Self::SystemEvent => "system_event",
         }
     }
 }
@@ -311,7 +354,9 @@ pub enum NotificationPayload {
     PositionUpdate(PositionUpdateNotify),
     RiskAlert(RiskAlertNotify),
     MarginCall(MarginCallNotify),
+    AlgoOrderProgress(AlgoOrderProgressNotify),
     SystemNotice(SystemNoticeNotify),
+    SystemEvent(SystemEventNotify),
 }
 
 // ============================================================================
@@ -366,6 +411,10 @@ pub struct OrderRejectedNotify {
     /// 拒绝原因
     pub reason: String,
 
+    /// 拒绝原因分类（如 insufficient_funds、price_out_of_limit）
+    #[serde(default)]
+    pub category: String,
+
     /// 错误代码
     pub error_code: u32,
 
@@ -622,6 +671,10 @@ pub struct MarginCallNotify {
     /// 需要追加的保证金
     pub required_margin: f64,
 
+    /// 预警级别：reminder / warning / margin_call / liquidation，回落解除时为回落后的级别
+    #[serde(default)]
+    pub level: String,
+
     /// 截止时间
     pub deadline: i64,
 
@@ -632,6 +685,48 @@ pub struct MarginCallNotify {
     pub timestamp: i64,
 }
 
+// ============================================================================
+// 算法单相关通知
+// ============================================================================
+
+/// 算法单执行进度通知
+#[derive(Debug, Clone, Serialize, Deserialize, Archive, RkyvSerialize, RkyvDeserialize)]
+#[archive(check_bytes)]
+pub struct AlgoOrderProgressNotify {
+    /// 算法单ID
+    pub algo_order_id: String,
+
+    /// 账户ID
+    pub user_id: String,
+
+    /// 合约代码
+    pub instrument_id: String,
+
+    /// 算法类型：TWAP/VWAP
+    pub algo_type: String,
+
+    /// 状态：PENDING/RUNNING/COMPLETED/CANCELLED/FAILED
+    pub status: String,
+
+    /// 母单总量
+    pub total_volume: f64,
+
+    /// 已下单数量
+    pub submitted_volume: f64,
+
+    /// 已成交数量
+    pub filled_volume: f64,
+
+    /// 切片总数
+    pub slices_total: u32,
+
+    /// 已下单切片数
+    pub slices_submitted: u32,
+
+    /// 时间戳
+    pub timestamp: i64,
+}
+
 // ============================================================================
 // 系统相关通知
 // ============================================================================
@@ -653,6 +748,26 @@ pub struct SystemNoticeNotify {
     pub timestamp: i64,
 }
 
+/// 系统事件（合约状态变更、结算进度、存储告警、风控强平等）
+#[derive(Debug, Clone, Serialize, Deserialize, Archive, RkyvSerialize, RkyvDeserialize)]
+#[archive(check_bytes)]
+pub struct SystemEventNotify {
+    /// 事件类别：instrument/settlement/storage/liquidation
+    pub category: String,
+
+    /// 事件级别：INFO/WARNING/ERROR
+    pub level: String,
+
+    /// 事件标题
+    pub title: String,
+
+    /// 事件详情（JSON 对象文本）
+    pub details: String,
+
+    /// 时间戳
+    pub timestamp: i64,
+}
+
 // ============================================================================
 // 辅助函数
 // ============================================================================
@@ -666,6 +781,7 @@ impl NotificationPayload {
             Self::PositionUpdate(notify) => Some(&notify.user_id),
             Self::RiskAlert(notify) => Some(&notify.user_id),
             Self::MarginCall(notify) => Some(&notify.user_id),
+            Self::AlgoOrderProgress(notify) => Some(&notify.user_id),
             _ => None,
         }
     }
@@ -687,8 +803,8 @@ impl NotificationPayload {
                 n.timestamp
             ),
             Self::OrderRejected(n) => format!(
-                r#"{{"type":"order_rejected","order_id":"{}","instrument_id":"{}","reason":"{}","error_code":{},"timestamp":{}}}"#,
-                n.order_id, n.instrument_id, n.reason, n.error_code, n.timestamp
+                r#"{{"type":"order_rejected","order_id":"{}","instrument_id":"{}","reason":"{}","category":"{}","error_code":{},"timestamp":{}}}"#,
+                n.order_id, n.instrument_id, n.reason, n.category, n.error_code, n.timestamp
             ),
             Self::OrderPartiallyFilled(n) => format!(
                 r#"{{"type":"order_partially_filled","order_id":"{}","exchange_order_id":"{}","instrument_id":"{}","filled_volume":{},"remaining_volume":{},"average_price":{},"timestamp":{}}}"#,
@@ -766,13 +882,45 @@ impl NotificationPayload {
                 n.timestamp
             ),
             Self::MarginCall(n) => format!(
-                r#"{{"type":"margin_call","user_id":"{}","current_margin":{},"required_margin":{},"deadline":{},"message":"{}","timestamp":{}}}"#,
-                n.user_id, n.current_margin, n.required_margin, n.deadline, n.message, n.timestamp
+                r#"{{"type":"margin_call","user_id":"{}","current_margin":{},"required_margin":{},"level":"{}","deadline":{},"message":"{}","timestamp":{}}}"#,
+                n.user_id,
+                n.current_margin,
+                n.required_margin,
+                n.level,
+                n.deadline,
+                n.message,
+                n.timestamp
+            ),
+            Self::AlgoOrderProgress(n) => format!(
+                r#"{{"type":"algo_order_progress","algo_order_id":"{}","user_id":"{}","instrument_id":"{}","algo_type":"{}","status":"{}","total_volume":{},"submitted_volume":{},"filled_volume":{},"slices_total":{},"slices_submitted":{},"timestamp":{}}}"#,
+                n.algo_order_id,
+                n.user_id,
+                n.instrument_id,
+                n.algo_type,
+                n.status,
+                n.total_volume,
+                n.submitted_volume,
+                n.filled_volume,
+                n.slices_total,
+                n.slices_submitted,
+                n.timestamp
             ),
             Self::SystemNotice(n) => format!(
                 r#"{{"type":"system_notice","title":"{}","content":"{}","level":"{}","timestamp":{}}}"#,
                 n.title, n.content, n.level, n.timestamp
             ),
+            Self::SystemEvent(n) => format!(
+                r#"{{"type":"system_event","category":"{}","level":"{}","title":"{}","details":{},"timestamp":{}}}"#,
+                n.category,
+                n.level,
+                n.title,
+                if n.details.is_empty() {
+                    "{}"
+                } else {
+                    n.details.as_str()
+                },
+                n.timestamp
+            ),
         }
     }
 }
@@ -4,6 +4,8 @@
 //! - 消息定义和序列化
 //! - 消息路由和分发（Broker）
 //! - 消息推送网关（Gateway）
+//! - 用户消息中心（Store，历史通知与已读状态）
+//! - 系统事件（SystemEvent，运维监控频道 system_events）
 //!
 //! # 架构
 //!
@@ -84,11 +86,15 @@
 pub mod broker;
 pub mod gateway;
 pub mod message;
+pub mod store;
+pub mod system_event;
 
 // 导出核心类型
 pub use message::{
     // 账户相关
     AccountUpdateNotify,
+    // 算法单相关
+    AlgoOrderProgressNotify,
     MarginCallNotify,
     Notification,
     NotificationPayload,
@@ -104,6 +110,7 @@ pub use message::{
     // 风控相关
     RiskAlertNotify,
     // 系统相关
+    SystemEventNotify,
     SystemNoticeNotify,
     // 成交相关
     TradeExecutedNotify,
@@ -111,3 +118,10 @@ pub use message::{
 
 pub use broker::{BrokerStatsSnapshot, NotificationBroker};
 pub use gateway::{GatewayStatsSnapshot, NotificationGateway, SessionInfo};
+pub use store::{
+    NotificationPage, NotificationStore, NotificationStoreConfig, NotificationStoreStats,
+    StoredNotification, UnreadCount,
+};
+pub use system_event::{
+    SystemEvent, SystemEventCategory, SystemEventLevel, SYSTEM_EVENTS_CHANNEL, SYSTEM_EVENT_USER,
+};
@@ -0,0 +1,745 @@
+//! 用户消息中心（NotificationStore）
+//!
+//! 高/中优先级通知在推送的同时落盘，供前端"消息中心"查询历史通知并标记已读：
+//!
+//! 1. **异步批量写入**: 作为 Broker 全局订阅者接收通知，后台任务按批次写入存储，
+//!    推送路径只做一次无界通道发送，不受磁盘写入影响
+//! 2. **按用户分区**: 通知按接收用户哈希到固定数量的 `OltpHybridStorage` 分区
+//! 3. **已读状态**: 标记已读写入 `NotificationRead` 记录，重启后回放恢复
+//! 4. **TTL 清理**: 超过保留期的通知定期从索引中清理，加载时跳过
+//!    （WAL/SSTable 只追加，过期记录随 checkpoint 截断）
+//!
+//! 落盘范围：`channels` 中的频道且优先级不低于 `max_priority`；
+//! 公告（system 频道）默认 P3，不论优先级均落盘。
+//!
+//! @yutiansut @quantaxis
+
+use dashmap::DashMap;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use super::broker::NotificationBroker;
+use super::message::{Notification, NotificationType};
+use crate::exchange::AccountManager;
+use crate::storage::hybrid::oltp::{OltpHybridConfig, OltpHybridStorage};
+use crate::storage::wal::WalRecord;
+use crate::utils::timestamp::{millis_to_nanos, nanos_to_millis};
+
+const MS_PER_DAY: i64 = 24 * 60 * 60 * 1000;
+
+/// Broker 全局订阅者ID
+pub const NOTIFICATION_STORE_SUBSCRIBER: &str = "notification_store";
+
+/// 消息中心配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationStoreConfig {
+    /// 是否启用
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// 落盘的最低优先级（数值越小优先级越高，默认 P0~P2）
+    #[serde(default = "default_max_priority")]
+    pub max_priority: u8,
+    /// 落盘的频道（账户/持仓频道为高频状态推送，默认不落盘）
+    #[serde(default = "default_channels")]
+    pub channels: Vec<String>,
+    /// 保留天数
+    #[serde(default = "default_ttl_days")]
+    pub ttl_days: u32,
+    /// 每用户最多保留条数
+    #[serde(default = "default_max_per_user")]
+    pub max_per_user: usize,
+    /// 批量写入条数
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    /// 批量写入间隔（毫秒）
+    #[serde(default = "default_flush_interval_ms")]
+    pub flush_interval_ms: u64,
+    /// 用户分区数
+    #[serde(default = "default_user_partitions")]
+    pub user_partitions: usize,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+fn default_max_priority() -> u8 {
+    2
+}
+fn default_channels() -> Vec<String> {
+    vec![
+        "trade".to_string(),
+        "risk".to_string(),
+        "system".to_string(),
+    ]
+}
+fn default_ttl_days() -> u32 {
+    30
+}
+fn default_max_per_user() -> usize {
+    1000
+}
+fn default_batch_size() -> usize {
+    256
+}
+fn default_flush_interval_ms() -> u64 {
+    200
+}
+fn default_user_partitions() -> usize {
+    16
+}
+
+impl Default for NotificationStoreConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            max_priority: default_max_priority(),
+            channels: default_channels(),
+            ttl_days: default_ttl_days(),
+            max_per_user: default_max_per_user(),
+            batch_size: default_batch_size(),
+            flush_interval_ms: default_flush_interval_ms(),
+            user_partitions: default_user_partitions(),
+        }
+    }
+}
+
+/// 消息中心中的一条通知
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredNotification {
+    pub message_id: String,
+    pub user_id: String,
+    pub message_type: NotificationType,
+    pub channel: String,
+    pub priority: u8,
+    /// 消息负载（JSON）
+    pub content: String,
+    /// 通知时间（毫秒）
+    pub timestamp: i64,
+    pub read: bool,
+}
+
+/// 分页查询结果（按时间倒序）
+#[derive(Debug, Clone, Serialize)]
+pub struct NotificationPage {
+    pub items: Vec<StoredNotification>,
+    pub total: usize,
+    pub unread: usize,
+    pub page: usize,
+    pub page_size: usize,
+}
+
+/// 未读计数
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UnreadCount {
+    pub total: usize,
+    /// 按频道统计
+    pub by_channel: BTreeMap<String, usize>,
+}
+
+/// 消息中心统计
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct NotificationStoreStats {
+    pub users: usize,
+    pub notifications: usize,
+    pub persisted: u64,
+    pub write_errors: u64,
+    pub expired: u64,
+}
+
+/// 用户消息中心
+pub struct NotificationStore {
+    config: RwLock<NotificationStoreConfig>,
+    /// 接收者 → 通知（按时间升序）
+    inbox: DashMap<String, VecDeque<StoredNotification>>,
+    /// 用户分区存储（未设置时只保存在内存）
+    partitions: RwLock<Vec<Arc<OltpHybridStorage>>>,
+    persisted: AtomicU64,
+    write_errors: AtomicU64,
+    expired: AtomicU64,
+}
+
+impl Default for NotificationStore {
+    fn default() -> Self {
+        Self::new(NotificationStoreConfig::default())
+    }
+}
+
+impl NotificationStore {
+    pub fn new(config: NotificationStoreConfig) -> Self {
+        Self {
+            config: RwLock::new(config),
+            inbox: DashMap::new(),
+            partitions: RwLock::new(Vec::new()),
+            persisted: AtomicU64::new(0),
+            write_errors: AtomicU64::new(0),
+            expired: AtomicU64::new(0),
+        }
+    }
+
+    pub fn config(&self) -> NotificationStoreConfig {
+        self.config.read().clone()
+    }
+
+    /// 打开 `{base_path}/user_XX` 用户分区并加载未过期的通知，返回加载条数
+    pub fn open_storage(&self, base_path: &str) -> Result<usize, String> {
+        let count = self.config.read().user_partitions.max(1);
+        let storage_config = OltpHybridConfig {
+            base_path: base_path.to_string(),
+            memtable_size_bytes: 8 * 1024 * 1024,
+            estimated_entry_size: 1280,
+            enable_olap_conversion: false,
+            ..Default::default()
+        };
+
+        let mut partitions = Vec::with_capacity(count);
+        for index in 0..count {
+            let storage =
+                OltpHybridStorage::create(&format!("user_{:02}", index), storage_config.clone())?;
+            storage.recover()?;
+            partitions.push(Arc::new(storage));
+        }
+        *self.partitions.write() = partitions;
+
+        self.load(chrono::Utc::now().timestamp_millis())
+    }
+
+    /// 是否落盘
+    pub fn should_store(&self, notification: &Notification) -> bool {
+        let config = self.config.read();
+        let channel = notification.message_type.channel();
+        config.enabled
+            && config.channels.iter().any(|c| c == channel)
+            && (notification.priority <= config.max_priority || channel == "system")
+    }
+
+    /// 启动后台写入任务：注册为 Broker 全局订阅者，按批次异步落盘并定期清理过期通知
+    pub fn start(self: &Arc<Self>, broker: &NotificationBroker) -> tokio::task::JoinHandle<()> {
+        let (sender, mut receiver) = mpsc::unbounded_channel::<Notification>();
+        broker.subscribe_global(NOTIFICATION_STORE_SUBSCRIBER, sender);
+
+        let store = self.clone();
+        tokio::spawn(async move {
+            let config = store.config();
+            let mut batch = Vec::with_capacity(config.batch_size);
+            let mut flush_timer =
+                tokio::time::interval(Duration::from_millis(config.flush_interval_ms.max(1)));
+            let mut prune_timer = tokio::time::interval(Duration::from_secs(60));
+
+            loop {
+                tokio::select! {
+                    received = receiver.recv() => {
+                        let Some(notification) = received else {
+                            break;
+                        };
+                        if store.should_store(&notification) {
+                            batch.push(notification);
+                        }
+                        if batch.len() >= config.batch_size {
+                            store.flush_async(&mut batch).await;
+                        }
+                    }
+                    _ = flush_timer.tick() => {
+                        store.flush_async(&mut batch).await;
+                    }
+                    _ = prune_timer.tick() => {
+                        store.prune(chrono::Utc::now().timestamp_millis());
+                    }
+                }
+            }
+            store.flush_async(&mut batch).await;
+        })
+    }
+
+    async fn flush_async(self: &Arc<Self>, batch: &mut Vec<Notification>) {
+        if batch.is_empty() {
+            return;
+        }
+        let notifications = std::mem::take(batch);
+        let store = self.clone();
+        if let Err(e) = tokio::task::spawn_blocking(move || store.ingest(notifications)).await {
+            log::error!("[NotificationStore] Flush task failed: {}", e);
+        }
+    }
+
+    /// 写入一批通知（不满足落盘条件的跳过），返回写入条数
+    pub fn ingest(&self, notifications: Vec<Notification>) -> usize {
+        let mut records: HashMap<usize, Vec<WalRecord>> = HashMap::new();
+        let mut stored = 0;
+
+        for notification in notifications {
+            if !self.should_store(&notification) {
+                continue;
+            }
+            let item = StoredNotification {
+                message_id: notification.message_id.to_string(),
+                user_id: notification.user_id.to_string(),
+                message_type: notification.message_type,
+                channel: notification.message_type.channel().to_string(),
+                priority: notification.priority,
+                content: notification.payload.to_json(),
+                timestamp: nanos_to_millis(notification.timestamp),
+                read: false,
+            };
+            if let Some(partition) = self.partition_index(&item.user_id) {
+                records
+                    .entry(partition)
+                    .or_default()
+                    .push(Self::to_wal_record(&item, notification.timestamp));
+            }
+            self.insert(item);
+            stored += 1;
+        }
+
+        for (partition, records) in records {
+            self.persist(partition, records);
+        }
+        stored
+    }
+
+    /// 分页查询（page 从 1 开始，按时间倒序）
+    ///
+    /// `keys` 为接收者列表（用户ID及其账户ID），`type_filter` 可为通知类型或频道
+    pub fn list(
+        &self,
+        keys: &[String],
+        type_filter: Option<&str>,
+        page: usize,
+        page_size: usize,
+    ) -> NotificationPage {
+        let mut items: Vec<StoredNotification> = keys
+            .iter()
+            .filter_map(|key| self.inbox.get(key))
+            .flat_map(|inbox| inbox.iter().cloned().collect::<Vec<_>>())
+            .filter(|n| {
+                type_filter.map_or(true, |t| n.message_type.as_str() == t || n.channel == t)
+            })
+            .collect();
+        items.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+
+        let page = page.max(1);
+        let page_size = page_size.max(1);
+        let total = items.len();
+        let unread = items.iter().filter(|n| !n.read).count();
+        let items = items
+            .into_iter()
+            .skip((page - 1) * page_size)
+            .take(page_size)
+            .collect();
+
+        NotificationPage {
+            items,
+            total,
+            unread,
+            page,
+            page_size,
+        }
+    }
+
+    /// 未读计数
+    pub fn unread_count(&self, keys: &[String]) -> UnreadCount {
+        let mut count = UnreadCount::default();
+        for key in keys {
+            if let Some(inbox) = self.inbox.get(key) {
+                for n in inbox.iter().filter(|n| !n.read) {
+                    count.total += 1;
+                    *count.by_channel.entry(n.channel.clone()).or_insert(0) += 1;
+                }
+            }
+        }
+        count
+    }
+
+    /// 批量标记已读（`message_ids` 为空时标记全部），返回新标记条数
+    pub fn mark_read(&self, keys: &[String], message_ids: &[String], now_ms: i64) -> usize {
+        let mut records: HashMap<usize, Vec<WalRecord>> = HashMap::new();
+        let mut marked = 0;
+
+        for key in keys {
+            let Some(mut inbox) = self.inbox.get_mut(key) else {
+                continue;
+            };
+            let mut key_marked = Vec::new();
+            for n in inbox.iter_mut().filter(|n| !n.read) {
+                if message_ids.is_empty() || message_ids.contains(&n.message_id) {
+                    n.read = true;
+                    key_marked.push(n.message_id.clone());
+                }
+            }
+            drop(inbox);
+            if key_marked.is_empty() {
+                continue;
+            }
+            marked += key_marked.len();
+
+            let Some(partition) = self.partition_index(key) else {
+                continue;
+            };
+            let entry = records.entry(partition).or_default();
+            if message_ids.is_empty() {
+                entry.push(Self::read_record(key, "", now_ms));
+            } else {
+                entry.extend(
+                    key_marked
+                        .iter()
+                        .map(|message_id| Self::read_record(key, message_id, now_ms)),
+                );
+            }
+        }
+
+        for (partition, records) in records {
+            self.persist(partition, records);
+        }
+        marked
+    }
+
+    /// 清理超出保留期的通知，返回清理条数
+    pub fn prune(&self, now_ms: i64) -> usize {
+        let cutoff = now_ms - self.config.read().ttl_days as i64 * MS_PER_DAY;
+        let mut removed = 0;
+        self.inbox.retain(|_, inbox| {
+            while inbox.front().map_or(false, |n| n.timestamp < cutoff) {
+                inbox.pop_front();
+                removed += 1;
+            }
+            !inbox.is_empty()
+        });
+        self.expired.fetch_add(removed as u64, Ordering::Relaxed);
+        removed
+    }
+
+    /// 从用户分区加载未过期的通知与已读状态，返回加载条数
+    pub fn load(&self, now_ms: i64) -> Result<usize, String> {
+        let cutoff_ms = now_ms - self.config.read().ttl_days as i64 * MS_PER_DAY;
+        let partitions = self.partitions.read().clone();
+        let mut reads = Vec::new();
+        let mut loaded = 0;
+
+        for partition in partitions {
+            for (_, _, record) in partition.range_query(millis_to_nanos(cutoff_ms), i64::MAX)? {
+                match record {
+                    WalRecord::UserNotification {
+                        message_id,
+                        user_id,
+                        message_type,
+                        priority,
+                        content,
+                        timestamp,
+                    } => {
+                        let Some(message_type) =
+                            NotificationType::parse(&WalRecord::from_fixed_array(&message_type))
+                        else {
+                            continue;
+                        };
+                        self.insert(StoredNotification {
+                            message_id: WalRecord::from_fixed_array(&message_id),
+                            user_id: WalRecord::from_fixed_array(&user_id),
+                            channel: message_type.channel().to_string(),
+                            message_type,
+                            priority,
+                            content: WalRecord::from_fixed_array(&content),
+                            timestamp: nanos_to_millis(timestamp),
+                            read: false,
+                        });
+                        loaded += 1;
+                    }
+                    WalRecord::NotificationRead {
+                        user_id,
+                        message_id,
+                        timestamp,
+                    } => reads.push((
+                        WalRecord::from_fixed_array(&user_id),
+                        WalRecord::from_fixed_array(&message_id),
+                        nanos_to_millis(timestamp),
+                    )),
+                    _ => {}
+                }
+            }
+        }
+
+        // 已读标记在通知之后回放（标记全部已读只覆盖标记时刻之前的通知）
+        for (user_id, message_id, read_ms) in reads {
+            if let Some(mut inbox) = self.inbox.get_mut(&user_id) {
+                for n in inbox.iter_mut() {
+                    if message_id.is_empty() {
+                        n.read |= n.timestamp <= read_ms;
+                    } else if n.message_id == message_id {
+                        n.read = true;
+                    }
+                }
+            }
+        }
+
+        self.prune(now_ms);
+        Ok(loaded)
+    }
+
+    pub fn stats(&self) -> NotificationStoreStats {
+        NotificationStoreStats {
+            users: self.inbox.len(),
+            notifications: self.inbox.iter().map(|inbox| inbox.len()).sum(),
+            persisted: self.persisted.load(Ordering::Relaxed),
+            write_errors: self.write_errors.load(Ordering::Relaxed),
+            expired: self.expired.load(Ordering::Relaxed),
+        }
+    }
+
+    fn insert(&self, item: StoredNotification) {
+        let max_per_user = self.config.read().max_per_user.max(1);
+        let mut inbox = self.inbox.entry(item.user_id.clone()).or_default();
+        if inbox.iter().any(|n| n.message_id == item.message_id) {
+            return;
+        }
+        // 回放或乱序写入时保持升序
+        let pos = inbox.partition_point(|n| n.timestamp <= item.timestamp);
+        inbox.insert(pos, item);
+        while inbox.len() > max_per_user {
+            inbox.pop_front();
+        }
+    }
+
+    fn partition_index(&self, user_id: &str) -> Option<usize> {
+        let count = self.partitions.read().len();
+        (count > 0).then(|| crc32fast::hash(user_id.as_bytes()) as usize % count)
+    }
+
+    fn persist(&self, partition: usize, records: Vec<WalRecord>) {
+        let Some(storage) = self.partitions.read().get(partition).cloned() else {
+            return;
+        };
+        match storage.write_batch(records) {
+            Ok(sequences) => {
+                self.persisted
+                    .fetch_add(sequences.len() as u64, Ordering::Relaxed);
+            }
+            Err(e) => {
+                self.write_errors.fetch_add(1, Ordering::Relaxed);
+                log::error!(
+                    "[NotificationStore] Failed to persist partition {}: {}",
+                    partition,
+                    e
+                );
+            }
+        }
+    }
+
+    fn to_wal_record(item: &StoredNotification, timestamp_ns: i64) -> WalRecord {
+        WalRecord::UserNotification {
+            message_id: WalRecord::to_fixed_array_40(&item.message_id),
+            user_id: WalRecord::to_fixed_array_40(&item.user_id),
+            message_type: WalRecord::to_fixed_array_32(item.message_type.as_str()),
+            priority: item.priority,
+            content: WalRecord::to_fixed_array_1024(&item.content),
+            timestamp: timestamp_ns,
+        }
+    }
+
+    fn read_record(user_id: &str, message_id: &str, now_ms: i64) -> WalRecord {
+        WalRecord::NotificationRead {
+            user_id: WalRecord::to_fixed_array_40(user_id),
+            message_id: WalRecord::to_fixed_array_40(message_id),
+            timestamp: millis_to_nanos(now_ms),
+        }
+    }
+}
+
+/// 用户消息中心的接收者列表：用户ID及其名下账户ID
+/// （成交/风控通知按账户推送，公告按用户推送）
+pub fn inbox_keys(account_mgr: &AccountManager, user_id: &str) -> Vec<String> {
+    let mut keys = vec![user_id.to_string()];
+    for account in account_mgr.get_accounts_by_user(user_id) {
+        let account_id = account.read().account_cookie.clone();
+        if !keys.contains(&account_id) {
+            keys.push(account_id);
+        }
+    }
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notification::message::{
+        AccountUpdateNotify, NotificationPayload, RiskAlertNotify, SystemNoticeNotify,
+    };
+
+    fn risk_alert(user_id: &str, timestamp_ms: i64) -> Notification {
+        let mut notification = Notification::new(
+            NotificationType::RiskAlert,
+            user_id,
+            NotificationPayload::RiskAlert(RiskAlertNotify {
+                user_id: user_id.to_string(),
+                alert_type: "MARGIN_INSUFFICIENT".to_string(),
+                severity: "WARNING".to_string(),
+                message: "risk ratio above 90%".to_string(),
+                risk_ratio: 0.9,
+                suggestion: "reduce position".to_string(),
+                timestamp: timestamp_ms * 1_000_000,
+            }),
+            "RiskMonitor",
+        );
+        notification.timestamp = timestamp_ms * 1_000_000;
+        notification
+    }
+
+    fn notice(user_id: &str, timestamp_ms: i64) -> Notification {
+        let mut notification = Notification::new(
+            NotificationType::SystemNotice,
+            user_id,
+            NotificationPayload::SystemNotice(SystemNoticeNotify {
+                title: "维护公告".to_string(),
+                content: "今晚系统维护".to_string(),
+                level: "INFO".to_string(),
+                timestamp: timestamp_ms * 1_000_000,
+            }),
+            "Admin",
+        );
+        notification.timestamp = timestamp_ms * 1_000_000;
+        notification
+    }
+
+    #[test]
+    fn test_ingest_filters_and_paginates() {
+        let store = NotificationStore::default();
+        let now = chrono::Utc::now().timestamp_millis();
+        let keys = vec!["user_01".to_string()];
+
+        let mut batch: Vec<Notification> =
+            (0..25).map(|i| risk_alert("user_01", now + i)).collect();
+        batch.push(notice("user_01", now + 100));
+        // 账户频道不落盘
+        batch.push(Notification::new(
+            NotificationType::AccountUpdate,
+            "user_01",
+            NotificationPayload::AccountUpdate(AccountUpdateNotify {
+                user_id: "user_01".to_string(),
+                balance: 1000000.0,
+                available: 980000.0,
+                frozen: 0.0,
+                margin: 20000.0,
+                position_profit: 500.0,
+                close_profit: 1000.0,
+                risk_ratio: 0.02,
+                timestamp: now * 1_000_000,
+            }),
+            "AccountSystem",
+        ));
+        assert_eq!(store.ingest(batch), 26);
+
+        let page = store.list(&keys, None, 1, 10);
+        assert_eq!(page.total, 26);
+        assert_eq!(page.unread, 26);
+        assert_eq!(page.items.len(), 10);
+        // 倒序：最新的公告在第一条
+        assert_eq!(page.items[0].message_type, NotificationType::SystemNotice);
+
+        let last = store.list(&keys, None, 3, 10);
+        assert_eq!(last.items.len(), 6);
+        assert_eq!(last.items.last().unwrap().timestamp, now);
+
+        // 按频道/类型过滤
+        assert_eq!(store.list(&keys, Some("system"), 1, 10).total, 1);
+        assert_eq!(store.list(&keys, Some("risk_alert"), 1, 10).total, 25);
+        assert_eq!(store.unread_count(&keys).by_channel["risk"], 25);
+    }
+
+    #[test]
+    fn test_read_state_persisted_across_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let base_path = dir.path().to_str().unwrap();
+        let now = chrono::Utc::now().timestamp_millis();
+        let keys = vec!["user_01".to_string(), "ACC_01".to_string()];
+
+        let message_ids = {
+            let store = NotificationStore::new(NotificationStoreConfig {
+                user_partitions: 4,
+                ..Default::default()
+            });
+            store.open_storage(base_path).unwrap();
+            store.ingest(vec![
+                risk_alert("ACC_01", now - 3),
+                risk_alert("ACC_01", now - 2),
+                notice("user_01", now - 1),
+            ]);
+
+            let page = store.list(&keys, None, 1, 10);
+            let first = page.items[2].message_id.clone();
+            assert_eq!(store.mark_read(&keys, &[first.clone()], now), 1);
+            // 已读的不重复计数
+            assert_eq!(store.mark_read(&keys, &[first.clone()], now), 0);
+            assert_eq!(store.unread_count(&keys).total, 2);
+            page.items
+                .iter()
+                .map(|n| n.message_id.clone())
+                .collect::<Vec<_>>()
+        };
+
+        // 模拟重启：从存储回放通知与已读状态
+        let store = NotificationStore::new(NotificationStoreConfig {
+            user_partitions: 4,
+            ..Default::default()
+        });
+        assert_eq!(store.open_storage(base_path).unwrap(), 3);
+        let page = store.list(&keys, None, 1, 10);
+        assert_eq!(
+            page.items
+                .iter()
+                .map(|n| n.message_id.clone())
+                .collect::<Vec<_>>(),
+            message_ids
+        );
+        assert_eq!(page.unread, 2);
+        assert!(page.items[2].read);
+        assert!(page.items[2].content.contains("risk ratio above 90%"));
+
+        // 全部已读后再次重启
+        assert_eq!(store.mark_read(&keys, &[], now), 2);
+        drop(store);
+        let store = NotificationStore::new(NotificationStoreConfig {
+            user_partitions: 4,
+            ..Default::default()
+        });
+        store.open_storage(base_path).unwrap();
+        assert_eq!(store.unread_count(&keys).total, 0);
+    }
+
+    #[test]
+    fn test_ttl_cleanup() {
+        let dir = tempfile::tempdir().unwrap();
+        let base_path = dir.path().to_str().unwrap();
+        let now = chrono::Utc::now().timestamp_millis();
+        let keys = vec!["user_01".to_string()];
+        let config = NotificationStoreConfig {
+            ttl_days: 7,
+            user_partitions: 2,
+            ..Default::default()
+        };
+
+        let store = NotificationStore::new(config.clone());
+        store.open_storage(base_path).unwrap();
+        store.ingest(vec![
+            risk_alert("user_01", now - 10 * MS_PER_DAY),
+            risk_alert("user_01", now - 3 * MS_PER_DAY),
+            risk_alert("user_01", now),
+        ]);
+        assert_eq!(store.list(&keys, None, 1, 10).total, 3);
+
+        assert_eq!(store.prune(now), 1);
+        assert_eq!(store.list(&keys, None, 1, 10).total, 2);
+        assert_eq!(store.stats().expired, 1);
+        drop(store);
+
+        // 过期通知加载时跳过
+        let reloaded = NotificationStore::new(config);
+        reloaded.open_storage(base_path).unwrap();
+        assert_eq!(reloaded.list(&keys, None, 1, 10).total, 2);
+
+        // 保留期推进后全部过期
+        assert_eq!(reloaded.prune(now + 8 * MS_PER_DAY), 2);
+        assert_eq!(reloaded.stats().users, 0);
+    }
+}
@@ -0,0 +1,203 @@
+//! 系统事件（运维监控频道）
+//!
+//! 合约状态变更、结算进度、存储告警、风控强平等系统级事件不面向具体用户，
+//! 由 NotificationBroker 路由到 `system_events` 频道，仅推送给具备 ViewMonitoring 权限的会话。
+//!
+//! 订阅时可按类别过滤，客户端以 `system_events`（全部类别）或 `system_events:<category>`
+//! 作为频道名订阅。
+//!
+//! @yutiansut @quantaxis
+
+use super::message::{Notification, NotificationPayload, NotificationType, SystemEventNotify};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// 系统事件频道名
+pub const SYSTEM_EVENTS_CHANNEL: &str = "system_events";
+
+/// 系统事件的路由用户（非用户定向）
+pub const SYSTEM_EVENT_USER: &str = "__system__";
+
+/// 系统事件类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SystemEventCategory {
+    /// 合约状态变更（停牌/复牌/下市）
+    Instrument,
+    /// 结算进度
+    Settlement,
+    /// 存储告警（OLAP 转换、保留策略清理）
+    Storage,
+    /// 风控强平
+    Liquidation,
+}
+
+impl SystemEventCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Instrument => "instrument",
+            Self::Settlement => "settlement",
+            Self::Storage => "storage",
+            Self::Liquidation => "liquidation",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "instrument" => Some(Self::Instrument),
+            "settlement" => Some(Self::Settlement),
+            "storage" => Some(Self::Storage),
+            "liquidation" => Some(Self::Liquidation),
+            _ => None,
+        }
+    }
+}
+
+/// 系统事件级别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum SystemEventLevel {
+    Info,
+    Warning,
+    Error,
+}
+
+impl SystemEventLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Info => "INFO",
+            Self::Warning => "WARNING",
+            Self::Error => "ERROR",
+        }
+    }
+}
+
+/// 系统事件
+#[derive(Debug, Clone)]
+pub struct SystemEvent {
+    pub category: SystemEventCategory,
+    pub level: SystemEventLevel,
+    pub title: String,
+    pub details: serde_json::Value,
+}
+
+impl SystemEvent {
+    pub fn new(
+        category: SystemEventCategory,
+        level: SystemEventLevel,
+        title: impl Into<String>,
+    ) -> Self {
+        Self {
+            category,
+            level,
+            title: title.into(),
+            details: serde_json::Value::Object(Default::default()),
+        }
+    }
+
+    /// 附带事件详情（JSON 对象）
+    pub fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.details = details;
+        self
+    }
+
+    /// 转换为系统事件通知
+    pub fn into_notification(self, source: impl Into<String>) -> Notification {
+        Notification::new(
+            NotificationType::SystemEvent,
+            SYSTEM_EVENT_USER,
+            NotificationPayload::SystemEvent(SystemEventNotify {
+                category: self.category.as_str().to_string(),
+                level: self.level.as_str().to_string(),
+                title: self.title,
+                details: self.details.to_string(),
+                timestamp: chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0),
+            }),
+            source,
+        )
+    }
+}
+
+/// 解析订阅频道中的系统事件频道
+///
+/// # 返回
+/// - `Ok(None)`: 未订阅系统事件
+/// - `Ok(Some(categories))`: 订阅的类别，空集合表示全部类别
+/// - `Err(String)`: 未知类别
+pub fn parse_system_event_channels(
+    channels: &[String],
+) -> Result<Option<HashSet<SystemEventCategory>>, String> {
+    let mut subscribed = false;
+    let mut all = false;
+    let mut categories = HashSet::new();
+    for channel in channels {
+        if channel == SYSTEM_EVENTS_CHANNEL {
+            subscribed = true;
+            all = true;
+        } else if let Some(name) = channel
+            .strip_prefix(SYSTEM_EVENTS_CHANNEL)
+            .and_then(|rest| rest.strip_prefix(':'))
+        {
+            let category = SystemEventCategory::parse(name)
+                .ok_or_else(|| format!("Unknown system event category: {}", name))?;
+            subscribed = true;
+            categories.insert(category);
+        }
+    }
+
+    if !subscribed {
+        return Ok(None);
+    }
+    if all {
+        categories.clear();
+    }
+    Ok(Some(categories))
+}
+
+/// 是否为系统事件频道（`system_events` 或 `system_events:<category>`）
+pub fn is_system_event_channel(channel: &str) -> bool {
+    channel == SYSTEM_EVENTS_CHANNEL
+        || channel
+            .strip_prefix(SYSTEM_EVENTS_CHANNEL)
+            .is_some_and(|rest| rest.starts_with(':'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_system_event_channels() {
+        let channels = |names: &[&str]| names.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(parse_system_event_channels(&channels(&["tick"])), Ok(None));
+        assert_eq!(
+            parse_system_event_channels(&channels(&["system_events:settlement", "tick"])),
+            Ok(Some(HashSet::from([SystemEventCategory::Settlement])))
+        );
+        // 同时订阅全部类别时忽略类别过滤
+        assert_eq!(
+            parse_system_event_channels(&channels(&["system_events", "system_events:storage"])),
+            Ok(Some(HashSet::new()))
+        );
+        assert!(parse_system_event_channels(&channels(&["system_events:unknown"])).is_err());
+        assert!(!is_system_event_channel("system_events_extra"));
+    }
+
+    #[test]
+    fn test_system_event_notification() {
+        let notification = SystemEvent::new(
+            SystemEventCategory::Settlement,
+            SystemEventLevel::Info,
+            "结算开始",
+        )
+        .with_details(serde_json::json!({ "settlement_date": "2026-10-16" }))
+        .into_notification("SettlementEngine");
+
+        assert_eq!(notification.message_type, NotificationType::SystemEvent);
+        assert_eq!(notification.user_id.as_ref(), SYSTEM_EVENT_USER);
+        let json: serde_json::Value = serde_json::from_str(&notification.to_json()).unwrap();
+        assert_eq!(json["payload"]["category"], "settlement");
+        assert_eq!(json["payload"]["details"]["settlement_date"], "2026-10-16");
+    }
+}
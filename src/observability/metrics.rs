@@ -45,6 +45,60 @@ lazy_static! {
         &["instrument_id"]
     ).expect("Failed to create PENDING_ORDERS metric");
 
+    /// 订单簿挂单估算内存占用 (bytes)
+    pub static ref ORDERBOOK_MEMORY_BYTES: IntGaugeVec = IntGaugeVec::new(
+        Opts::new("qaexchange_orderbook_memory_bytes", "Estimated order book memory usage in bytes")
+            .namespace("qaexchange"),
+        &["instrument_id"]
+    ).expect("Failed to create ORDERBOOK_MEMORY_BYTES metric");
+
+    /// 拒单总数（按拒单原因）
+    pub static ref ORDERS_REJECTED_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new("orders_rejected_total", "Total number of rejected orders by reason")
+            .namespace("qaexchange"),
+        &["reason"]
+    ).expect("Failed to create ORDERS_REJECTED_TOTAL metric");
+
+    /// 账户下单/撤单频率超限次数（按操作、账户类型）
+    pub static ref ACCOUNT_RATE_LIMITED_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new("qaexchange_account_rate_limited_total", "Orders/cancels rejected by per-account rate limit")
+            .namespace("qaexchange"),
+        &["action", "account_type"]
+    ).expect("Failed to create ACCOUNT_RATE_LIMITED_TOTAL metric");
+
+    /// 报撤比超限次数（outcome: alert 仅告警 / restrict 拒绝撤改单）
+    pub static ref CANCEL_RATIO_EXCEEDED_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new("qaexchange_cancel_ratio_exceeded_total", "Cancels/amends exceeding the per-account order-to-cancel ratio")
+            .namespace("qaexchange"),
+        &["outcome", "account_type"]
+    ).expect("Failed to create CANCEL_RATIO_EXCEEDED_TOTAL metric");
+
+    /// 单日成交量达限次数（按账户类型）
+    pub static ref TRADE_VOLUME_LIMIT_REACHED_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new("qaexchange_trade_volume_limit_reached_total", "Accounts reaching the daily per-instrument trade volume limit")
+            .namespace("qaexchange"),
+        &["account_type"]
+    ).expect("Failed to create TRADE_VOLUME_LIMIT_REACHED_TOTAL metric");
+
+    /// 做市商考核未达标次数（按合约）
+    pub static ref MARKET_MAKER_OBLIGATION_FAILED_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new("qaexchange_market_maker_obligation_failed_total", "Market maker daily obligation assessments that failed")
+            .namespace("qaexchange"),
+        &["instrument_id"]
+    ).expect("Failed to create MARKET_MAKER_OBLIGATION_FAILED_TOTAL metric");
+
+    /// 撮合回应超时的可疑订单（按处理结果 recovered/rejected）
+    pub static ref ORDER_ACK_TIMEOUT_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new("qaexchange_order_ack_timeout_total", "Orders without matching engine acknowledgement before timeout")
+            .namespace("qaexchange"),
+        &["outcome"]
+    ).expect("Failed to create ORDER_ACK_TIMEOUT_TOTAL metric");
+
+    /// 最近一次路由器与撮合引擎对账的差异数
+    pub static ref ORDER_RECONCILE_DIFFS: IntGauge = IntGauge::new(
+        "qaexchange_order_reconcile_diffs", "Differences found by the latest router/matching engine reconciliation"
+    ).expect("Failed to create ORDER_RECONCILE_DIFFS metric");
+
     // ═══════════════════════════════════════════════════════════════════
     // 成交指标
     // ═══════════════════════════════════════════════════════════════════
@@ -63,6 +117,20 @@ lazy_static! {
         &["instrument_id"]
     ).expect("Failed to create TRADE_VOLUME metric");
 
+    /// 成交事件总线各消费者的队列深度
+    pub static ref TRADE_BUS_QUEUE_DEPTH: IntGaugeVec = IntGaugeVec::new(
+        Opts::new("qaexchange_trade_bus_queue_depth", "Pending trade events per trade bus consumer")
+            .namespace("qaexchange"),
+        &["consumer"]
+    ).expect("Failed to create TRADE_BUS_QUEUE_DEPTH metric");
+
+    /// 成交事件总线因消费者队列满而丢弃的事件数
+    pub static ref TRADE_BUS_DROPPED_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new("qaexchange_trade_bus_dropped_total", "Trade events dropped because a consumer queue was full")
+            .namespace("qaexchange"),
+        &["consumer"]
+    ).expect("Failed to create TRADE_BUS_DROPPED_TOTAL metric");
+
     // ═══════════════════════════════════════════════════════════════════
     // 因子计算指标
     // ═══════════════════════════════════════════════════════════════════
@@ -124,6 +192,13 @@ lazy_static! {
             .buckets(vec![10.0, 25.0, 50.0, 100.0, 250.0, 500.0])
     ).expect("Failed to create SSTABLE_QUERY_LATENCY metric");
 
+    /// 查询结果缓存请求数（按查询引擎、命中/未命中）
+    pub static ref QUERY_CACHE_REQUESTS: IntCounterVec = IntCounterVec::new(
+        Opts::new("qaexchange_query_cache_requests_total", "Query result cache lookups")
+            .namespace("qaexchange"),
+        &["engine", "result"]
+    ).expect("Failed to create QUERY_CACHE_REQUESTS metric");
+
     /// Compaction 次数
     pub static ref COMPACTION_TOTAL: IntCounterVec = IntCounterVec::new(
         Opts::new("qaexchange_compaction_total", "Total number of compactions")
@@ -154,6 +229,51 @@ lazy_static! {
             .buckets(vec![1.0, 2.0, 5.0, 10.0, 25.0, 50.0, 100.0])
     ).expect("Failed to create TICK_LATENCY metric");
 
+    /// 推送端到端延迟 (微秒，origin_ts → 客户端接收，来自客户端 latency_report 回执)
+    pub static ref PUSH_E2E_LATENCY: HistogramVec = HistogramVec::new(
+        HistogramOpts::new("qaexchange_push_e2e_latency_us", "Push end-to-end latency reported by clients")
+            .namespace("qaexchange")
+            .buckets(vec![50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0, 25000.0, 50000.0, 100000.0]),
+        &["kind"]
+    ).expect("Failed to create PUSH_E2E_LATENCY metric");
+
+    /// 推送服务端阶段耗时 (微秒，origin_ts → 各阶段的累计耗时)
+    pub static ref PUSH_STAGE_LATENCY: HistogramVec = HistogramVec::new(
+        HistogramOpts::new("qaexchange_push_stage_latency_us", "Push server-side latency from origin to stage")
+            .namespace("qaexchange")
+            .buckets(vec![5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0, 50000.0]),
+        &["kind", "stage"]
+    ).expect("Failed to create PUSH_STAGE_LATENCY metric");
+
+    /// WebSocket 压缩字节数（raw=压缩前，compressed=压缩后，仅统计实际压缩的消息）
+    pub static ref WEBSOCKET_COMPRESSION_BYTES: IntCounterVec = IntCounterVec::new(
+        Opts::new("qaexchange_websocket_compression_bytes_total", "WebSocket bytes before/after compression")
+            .namespace("qaexchange"),
+        &["protocol", "kind"]
+    ).expect("Failed to create WEBSOCKET_COMPRESSION_BYTES metric");
+
+    /// WebSocket 压缩比（压缩后/压缩前）
+    pub static ref WEBSOCKET_COMPRESSION_RATIO: HistogramVec = HistogramVec::new(
+        HistogramOpts::new("qaexchange_websocket_compression_ratio", "WebSocket compressed size / raw size")
+            .namespace("qaexchange")
+            .buckets(vec![0.05, 0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.8, 1.0]),
+        &["protocol"]
+    ).expect("Failed to create WEBSOCKET_COMPRESSION_RATIO metric");
+
+    /// 协商了压缩但未压缩的消息数（small=低于阈值，ineffective=压缩后不变小）
+    pub static ref WEBSOCKET_COMPRESSION_SKIPPED: IntCounterVec = IntCounterVec::new(
+        Opts::new("qaexchange_websocket_compression_skipped_total", "WebSocket messages sent uncompressed")
+            .namespace("qaexchange"),
+        &["protocol", "reason"]
+    ).expect("Failed to create WEBSOCKET_COMPRESSION_SKIPPED metric");
+
+    /// WebSocket 订阅配额超限事件（subscription_rejected / rate_exceeded / disconnected）
+    pub static ref WEBSOCKET_QUOTA_EVENTS: IntCounterVec = IntCounterVec::new(
+        Opts::new("qaexchange_websocket_quota_events_total", "WebSocket subscription quota violations")
+            .namespace("qaexchange"),
+        &["protocol", "kind"]
+    ).expect("Failed to create WEBSOCKET_QUOTA_EVENTS metric");
+
     // ═══════════════════════════════════════════════════════════════════
     // 系统资源指标
     // ═══════════════════════════════════════════════════════════════════
@@ -190,6 +310,93 @@ lazy_static! {
             .namespace("qaexchange"),
         &["node_id"]
     ).expect("Failed to create NODE_ROLE metric");
+
+    // ═══════════════════════════════════════════════════════════════════
+    // 追踪采样指标
+    // ═══════════════════════════════════════════════════════════════════
+
+    /// 请求采样结果（sampled/dropped/forced_slow/forced_error）
+    pub static ref TRACE_SAMPLING_DECISIONS: IntCounterVec = IntCounterVec::new(
+        Opts::new("qaexchange_trace_sampling_decisions_total", "Trace sampling decisions at request entry")
+            .namespace("qaexchange"),
+        &["decision"]
+    ).expect("Failed to create TRACE_SAMPLING_DECISIONS metric");
+
+    /// 当前头部采样率
+    pub static ref TRACE_SAMPLING_RATE: Gauge = Gauge::new(
+        "qaexchange_trace_sampling_rate", "Configured head sampling rate (0.0 - 1.0)"
+    ).expect("Failed to create TRACE_SAMPLING_RATE metric");
+
+    // ═══════════════════════════════════════════════════════════════════
+    // 网关过载保护指标
+    // ═══════════════════════════════════════════════════════════════════
+
+    /// 当前保护级别（0=正常, 1=拒绝非关键请求, 2=交易请求也拒绝）
+    pub static ref OVERLOAD_LEVEL: IntGauge = IntGauge::new(
+        "qaexchange_overload_level", "Gateway load shedding level (0=normal, 1=shed non-critical, 2=shed all)"
+    ).expect("Failed to create OVERLOAD_LEVEL metric");
+
+    /// 当前复合负载（在途请求与下游队列占用率取大）
+    pub static ref OVERLOAD_LOAD_RATIO: Gauge = Gauge::new(
+        "qaexchange_overload_load_ratio", "Gateway composite load ratio (in-flight / queue depth)"
+    ).expect("Failed to create OVERLOAD_LOAD_RATIO metric");
+
+    /// 过载拒绝请求数（按请求类别）
+    pub static ref OVERLOAD_REJECTED_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new("qaexchange_overload_rejected_total", "Requests rejected by gateway load shedding")
+            .namespace("qaexchange"),
+        &["class"]
+    ).expect("Failed to create OVERLOAD_REJECTED_TOTAL metric");
+
+    /// 网关在途请求数
+    pub static ref GATEWAY_IN_FLIGHT: IntGauge = IntGauge::new(
+        "qaexchange_gateway_in_flight", "Gateway in-flight requests"
+    ).expect("Failed to create GATEWAY_IN_FLIGHT metric");
+
+    /// 下游队列深度（优先级队列 + 撮合队列）
+    pub static ref GATEWAY_QUEUE_DEPTH: IntGauge = IntGauge::new(
+        "qaexchange_gateway_queue_depth", "Downstream queue depth seen by the gateway"
+    ).expect("Failed to create GATEWAY_QUEUE_DEPTH metric");
+
+    /// 过载信号占用率（按信号：在途请求、各队列深度、处理延迟）
+    pub static ref OVERLOAD_SIGNAL_RATIO: GaugeVec = GaugeVec::new(
+        Opts::new("qaexchange_overload_signal_ratio", "Overload signal ratio against its capacity")
+            .namespace("qaexchange"),
+        &["signal"]
+    ).expect("Failed to create OVERLOAD_SIGNAL_RATIO metric");
+
+    /// 过载级别切换次数（degrade=降级, recover=恢复）
+    pub static ref OVERLOAD_TRANSITIONS_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new("qaexchange_overload_transitions_total", "Overload protection level transitions")
+            .namespace("qaexchange"),
+        &["direction"]
+    ).expect("Failed to create OVERLOAD_TRANSITIONS_TOTAL metric");
+
+    // ═══════════════════════════════════════════════════════════════════
+    // 功能灰度指标
+    // ═══════════════════════════════════════════════════════════════════
+
+    /// 灰度决策次数（按 feature 与路径）
+    pub static ref FEATURE_GATE_DECISIONS_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new("qaexchange_feature_gate_decisions_total", "Feature gate decisions by feature and path")
+            .namespace("qaexchange"),
+        &["feature", "path"]
+    ).expect("Failed to create FEATURE_GATE_DECISIONS_TOTAL metric");
+
+    /// 灰度路径执行延迟 (微秒)
+    pub static ref FEATURE_PATH_LATENCY_US: HistogramVec = HistogramVec::new(
+        HistogramOpts::new("qaexchange_feature_path_latency_us", "Execution latency of feature-gated paths in microseconds")
+            .namespace("qaexchange")
+            .buckets(vec![1.0, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 5000.0]),
+        &["feature", "path"]
+    ).expect("Failed to create FEATURE_PATH_LATENCY_US metric");
+
+    /// 灰度路径执行错误数
+    pub static ref FEATURE_PATH_ERRORS_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new("qaexchange_feature_path_errors_total", "Errors on feature-gated paths")
+            .namespace("qaexchange"),
+        &["feature", "path"]
+    ).expect("Failed to create FEATURE_PATH_ERRORS_TOTAL metric");
 }
 
 /// 初始化所有指标到 Registry
@@ -198,10 +405,20 @@ pub fn init_metrics() {
     REGISTRY.register(Box::new(ORDER_TOTAL.clone())).ok();
     REGISTRY.register(Box::new(ORDER_LATENCY.clone())).ok();
     REGISTRY.register(Box::new(PENDING_ORDERS.clone())).ok();
+    REGISTRY.register(Box::new(ORDERBOOK_MEMORY_BYTES.clone())).ok();
+    REGISTRY.register(Box::new(ORDERS_REJECTED_TOTAL.clone())).ok();
+    REGISTRY.register(Box::new(ACCOUNT_RATE_LIMITED_TOTAL.clone())).ok();
+    REGISTRY.register(Box::new(CANCEL_RATIO_EXCEEDED_TOTAL.clone())).ok();
+    REGISTRY.register(Box::new(TRADE_VOLUME_LIMIT_REACHED_TOTAL.clone())).ok();
+    REGISTRY.register(Box::new(MARKET_MAKER_OBLIGATION_FAILED_TOTAL.clone())).ok();
+    REGISTRY.register(Box::new(ORDER_ACK_TIMEOUT_TOTAL.clone())).ok();
+    REGISTRY.register(Box::new(ORDER_RECONCILE_DIFFS.clone())).ok();
 
     // 成交指标
     REGISTRY.register(Box::new(TRADE_TOTAL.clone())).ok();
     REGISTRY.register(Box::new(TRADE_VOLUME.clone())).ok();
+    REGISTRY.register(Box::new(TRADE_BUS_QUEUE_DEPTH.clone())).ok();
+    REGISTRY.register(Box::new(TRADE_BUS_DROPPED_TOTAL.clone())).ok();
 
     // 因子指标
     REGISTRY.register(Box::new(FACTOR_UPDATE_TOTAL.clone())).ok();
@@ -215,11 +432,26 @@ pub fn init_metrics() {
     REGISTRY.register(Box::new(MEMTABLE_QUERY_LATENCY.clone())).ok();
     REGISTRY.register(Box::new(SSTABLE_QUERY_LATENCY.clone())).ok();
     REGISTRY.register(Box::new(COMPACTION_TOTAL.clone())).ok();
+    REGISTRY.register(Box::new(QUERY_CACHE_REQUESTS.clone())).ok();
 
     // 网络指标
     REGISTRY.register(Box::new(WEBSOCKET_CONNECTIONS.clone())).ok();
     REGISTRY.register(Box::new(WEBSOCKET_MESSAGES.clone())).ok();
     REGISTRY.register(Box::new(TICK_LATENCY.clone())).ok();
+    REGISTRY.register(Box::new(PUSH_E2E_LATENCY.clone())).ok();
+    REGISTRY.register(Box::new(PUSH_STAGE_LATENCY.clone())).ok();
+    REGISTRY
+        .register(Box::new(WEBSOCKET_COMPRESSION_BYTES.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(WEBSOCKET_COMPRESSION_RATIO.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(WEBSOCKET_COMPRESSION_SKIPPED.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(WEBSOCKET_QUOTA_EVENTS.clone()))
+        .ok();
 
     // 系统指标
     REGISTRY.register(Box::new(MEMORY_USAGE.clone())).ok();
@@ -230,6 +462,34 @@ pub fn init_metrics() {
     REGISTRY.register(Box::new(REPLICATION_LAG.clone())).ok();
     REGISTRY.register(Box::new(NODE_ROLE.clone())).ok();
 
+    // 追踪采样指标
+    REGISTRY.register(Box::new(TRACE_SAMPLING_DECISIONS.clone())).ok();
+    REGISTRY.register(Box::new(TRACE_SAMPLING_RATE.clone())).ok();
+
+    // 过载保护指标
+    REGISTRY.register(Box::new(OVERLOAD_LEVEL.clone())).ok();
+    REGISTRY
+        .register(Box::new(OVERLOAD_LOAD_RATIO.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(OVERLOAD_REJECTED_TOTAL.clone()))
+        .ok();
+    REGISTRY.register(Box::new(GATEWAY_IN_FLIGHT.clone())).ok();
+    REGISTRY
+        .register(Box::new(GATEWAY_QUEUE_DEPTH.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(OVERLOAD_SIGNAL_RATIO.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(OVERLOAD_TRANSITIONS_TOTAL.clone()))
+        .ok();
+
+    // 功能灰度指标
+    REGISTRY.register(Box::new(FEATURE_GATE_DECISIONS_TOTAL.clone())).ok();
+    REGISTRY.register(Box::new(FEATURE_PATH_LATENCY_US.clone())).ok();
+    REGISTRY.register(Box::new(FEATURE_PATH_ERRORS_TOTAL.clone())).ok();
+
     log::info!("Prometheus metrics initialized");
 }
 
@@ -5,13 +5,25 @@
 //! 提供完整的可观测性支持：
 //! - Prometheus 指标导出
 //! - OpenTelemetry 分布式追踪
+//! - 追踪头部采样（可热更新采样率，慢请求/错误请求必采）
+//! - 推送端到端延迟采样（客户端回执，按会话聚合 P50/P99）
 //! - 结构化日志
 //! - 性能分析
 
 pub mod metrics;
+pub mod push_latency;
+pub mod sampling;
 pub mod tracing;
 
 pub use metrics::*;
+pub use push_latency::{
+    PushKind, PushLatencyConfig, PushLatencyStats, PushLatencyTracker, PushSample,
+    SessionLatencySummary, PUSH_LATENCY,
+};
+pub use sampling::{
+    current_trace_sampled, RequestTrace, SamplingConfig, SamplingOutcome, SamplingStats,
+    TraceSampler, TRACE_SAMPLER,
+};
 pub use tracing::{
     TracingConfig, TracingInitializer, TracingError, ExporterType, BatchExportConfig,
     init_dev_tracing, init_prod_tracing, init_test_tracing, shutdown_tracer,
@@ -0,0 +1,564 @@
+//! 推送端到端延迟测量
+//!
+//! @yutiansut @quantaxis
+//!
+//! 推送消息（成交/委托/账户通知、tick、K线）携带 `origin_ts`（业务事件产生时刻，纳秒）。
+//! 按采样率抽取部分消息附加 `msg_id`，客户端收到后回发 `latency_report`
+//! （msg_id + 客户端接收时刻），服务端按会话聚合端到端延迟分布（P50/P99），
+//! 同时计入 Prometheus 直方图 `qaexchange_push_e2e_latency_us`。
+//!
+//! 服务端内部阶段耗时记为相对 origin_ts 的累计值（`qaexchange_push_stage_latency_us`），
+//! 相邻阶段相减即为该段耗时：
+//! - `enqueued`: 产生 → 进入会话发送队列
+//! - `dequeued`: 产生 → 会话从队列取出
+//! - `written`: 产生 → 序列化完成并交给 socket 写缓冲
+//!
+//! 只有采样命中的消息才记录阶段耗时和附加 msg_id，客户端回执量与采样率成正比（默认 1%）。
+//! 端到端延迟依赖客户端与服务端时钟一致，跨机部署需 NTP/PTP 对时；
+//! 时钟回拨导致的负延迟单独计数，不进入分布。
+//!
+//! 本机回环基线由 `test_loopback_baseline` 校验（`cargo test --release push_latency -- --ignored`，P99 超过 10ms 时失败并给出实测值），
+//! 排查线上延迟时先在同一台机器上跑一次作为对照。
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use parking_lot::RwLock;
+
+use super::metrics::{PUSH_E2E_LATENCY, PUSH_STAGE_LATENCY};
+
+lazy_static::lazy_static! {
+    /// 全局推送延迟追踪器（WebSocket 会话与推送生产方共用）
+    pub static ref PUSH_LATENCY: Arc<PushLatencyTracker> = Arc::new(PushLatencyTracker::new(PushLatencyConfig::default()));
+}
+
+/// 当前时刻（纳秒）
+pub fn now_nanos() -> i64 {
+    crate::utils::timestamp::now_nanos()
+}
+
+/// 推送延迟采样配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PushLatencyConfig {
+    /// 采样率 (0.0 - 1.0)
+    #[serde(default = "default_sample_rate")]
+    pub sample_rate: f64,
+    /// 每个会话保留的最近样本数（P50/P99 基于该窗口计算）
+    #[serde(default = "default_window")]
+    pub window: usize,
+    /// 未收到回执的样本保留时间（毫秒），超时视为丢失
+    #[serde(default = "default_pending_ttl_ms")]
+    pub pending_ttl_ms: u64,
+}
+
+fn default_sample_rate() -> f64 {
+    0.01
+}
+fn default_window() -> usize {
+    1024
+}
+fn default_pending_ttl_ms() -> u64 {
+    10_000
+}
+
+impl Default for PushLatencyConfig {
+    fn default() -> Self {
+        Self {
+            sample_rate: default_sample_rate(),
+            window: default_window(),
+            pending_ttl_ms: default_pending_ttl_ms(),
+        }
+    }
+}
+
+/// 推送消息类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PushKind {
+    Trade,
+    OrderStatus,
+    Account,
+    Tick,
+    Kline,
+}
+
+impl PushKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            PushKind::Trade => "trade",
+            PushKind::OrderStatus => "order_status",
+            PushKind::Account => "account",
+            PushKind::Tick => "tick",
+            PushKind::Kline => "kline",
+        }
+    }
+}
+
+/// 服务端内部阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushStage {
+    Enqueued,
+    Dequeued,
+    Written,
+}
+
+impl PushStage {
+    fn label(&self) -> &'static str {
+        match self {
+            PushStage::Enqueued => "enqueued",
+            PushStage::Dequeued => "dequeued",
+            PushStage::Written => "written",
+        }
+    }
+}
+
+/// 会话侧采样命中的消息（写出后通过 [`PushLatencyTracker::written`] 登记等待回执）
+#[derive(Debug, Clone, Copy)]
+pub struct PushSample {
+    pub msg_id: u64,
+    pub kind: PushKind,
+    pub origin_ts: i64,
+    dequeued_ts: i64,
+}
+
+/// 等待客户端回执的样本
+#[derive(Debug, Clone, Copy)]
+struct PendingSample {
+    kind: PushKind,
+    origin_ts: i64,
+    sent_at: Instant,
+}
+
+/// 单个会话的延迟窗口
+#[derive(Debug, Default)]
+struct SessionLatency {
+    /// 最近的端到端延迟（纳秒）
+    samples: VecDeque<u64>,
+    pending: HashMap<u64, PendingSample>,
+    reported: u64,
+    expired: u64,
+}
+
+/// 会话延迟分布
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionLatencySummary {
+    pub session_id: String,
+    /// 累计收到的有效回执数
+    pub reported: u64,
+    /// 窗口内样本数
+    pub window_samples: usize,
+    /// 等待回执的样本数
+    pub pending: usize,
+    /// 超时未回执的样本数
+    pub expired: u64,
+    pub p50_us: f64,
+    pub p99_us: f64,
+    pub max_us: f64,
+}
+
+/// 全局统计
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PushLatencyStats {
+    pub config: PushLatencyConfig,
+    /// 采样命中并发出的消息数
+    pub sampled: u64,
+    /// 有效回执数
+    pub reported: u64,
+    /// 找不到对应样本的回执数（已过期或伪造的 msg_id）
+    pub unmatched: u64,
+    /// 客户端接收时刻早于 origin_ts 的回执数（时钟不同步）
+    pub clock_skew: u64,
+    pub sessions: Vec<SessionLatencySummary>,
+}
+
+/// 推送延迟追踪器
+pub struct PushLatencyTracker {
+    config: RwLock<PushLatencyConfig>,
+    /// 采样率（f64 位表示，热路径无锁读取）
+    rate_bits: AtomicU64,
+    next_seq: AtomicU64,
+    next_msg_id: AtomicU64,
+    sessions: DashMap<String, SessionLatency>,
+    sampled: AtomicU64,
+    reported: AtomicU64,
+    unmatched: AtomicU64,
+    clock_skew: AtomicU64,
+}
+
+impl PushLatencyTracker {
+    pub fn new(config: PushLatencyConfig) -> Self {
+        let tracker = Self {
+            config: RwLock::new(PushLatencyConfig::default()),
+            rate_bits: AtomicU64::new(0),
+            next_seq: AtomicU64::new(0),
+            next_msg_id: AtomicU64::new(1),
+            sessions: DashMap::new(),
+            sampled: AtomicU64::new(0),
+            reported: AtomicU64::new(0),
+            unmatched: AtomicU64::new(0),
+            clock_skew: AtomicU64::new(0),
+        };
+        tracker.update_config(config);
+        tracker
+    }
+
+    /// 热更新配置（对之后推送的消息生效）
+    pub fn update_config(&self, mut config: PushLatencyConfig) {
+        config.sample_rate = if config.sample_rate.is_finite() {
+            config.sample_rate.clamp(0.0, 1.0)
+        } else {
+            default_sample_rate()
+        };
+        config.window = config.window.max(1);
+        self.rate_bits
+            .store(config.sample_rate.to_bits(), Ordering::Relaxed);
+        *self.config.write() = config;
+    }
+
+    pub fn config(&self) -> PushLatencyConfig {
+        self.config.read().clone()
+    }
+
+    pub fn sample_rate(&self) -> f64 {
+        f64::from_bits(self.rate_bits.load(Ordering::Relaxed))
+    }
+
+    /// 采样决策（序号经 splitmix64 打散，长期命中率等于采样率）
+    pub fn should_sample(&self) -> bool {
+        let rate = self.sample_rate();
+        if rate >= 1.0 {
+            return true;
+        }
+        if rate <= 0.0 {
+            return false;
+        }
+        let mut z = self
+            .next_seq
+            .fetch_add(1, Ordering::Relaxed)
+            .wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        ((z >> 11) as f64 / (1u64 << 53) as f64) < rate
+    }
+
+    fn observe_stage(kind: PushKind, stage: PushStage, origin_ts: i64, now: i64) {
+        if origin_ts > 0 && now >= origin_ts {
+            PUSH_STAGE_LATENCY
+                .with_label_values(&[kind.label(), stage.label()])
+                .observe((now - origin_ts) as f64 / 1000.0);
+        }
+    }
+
+    /// 生产方投递到会话队列时调用（按采样率记录 enqueued 阶段）
+    pub fn record_enqueued(&self, kind: PushKind, origin_ts: i64) {
+        if self.should_sample() {
+            Self::observe_stage(kind, PushStage::Enqueued, origin_ts, now_nanos());
+        }
+    }
+
+    /// 会话从队列取出消息时调用，采样命中时分配 msg_id
+    pub fn sample(&self, kind: PushKind, origin_ts: i64) -> Option<PushSample> {
+        if !self.should_sample() {
+            return None;
+        }
+        Some(PushSample {
+            msg_id: self.next_msg_id.fetch_add(1, Ordering::Relaxed),
+            kind,
+            origin_ts,
+            dequeued_ts: now_nanos(),
+        })
+    }
+
+    /// 消息写出后调用：记录 dequeued/written 阶段，登记等待客户端回执
+    pub fn written(&self, session_id: &str, sample: PushSample) {
+        Self::observe_stage(
+            sample.kind,
+            PushStage::Dequeued,
+            sample.origin_ts,
+            sample.dequeued_ts,
+        );
+        Self::observe_stage(
+            sample.kind,
+            PushStage::Written,
+            sample.origin_ts,
+            now_nanos(),
+        );
+        self.sampled.fetch_add(1, Ordering::Relaxed);
+
+        let (window, ttl) = {
+            let config = self.config.read();
+            (config.window, Duration::from_millis(config.pending_ttl_ms))
+        };
+        let mut session = self.sessions.entry(session_id.to_string()).or_default();
+        if session.pending.len() >= window {
+            // 清理超时未回执的样本，仍超限时丢弃本样本，避免客户端不回执导致无限增长
+            let before = session.pending.len();
+            session.pending.retain(|_, p| p.sent_at.elapsed() < ttl);
+            session.expired += (before - session.pending.len()) as u64;
+            if session.pending.len() >= window {
+                session.expired += 1;
+                return;
+            }
+        }
+        session.pending.insert(
+            sample.msg_id,
+            PendingSample {
+                kind: sample.kind,
+                origin_ts: sample.origin_ts,
+                sent_at: Instant::now(),
+            },
+        );
+    }
+
+    /// 处理客户端回执，返回端到端延迟（纳秒）
+    pub fn report(&self, session_id: &str, msg_id: u64, client_recv_ts: i64) -> Option<u64> {
+        let window = self.config.read().window;
+        let Some(mut session) = self.sessions.get_mut(session_id) else {
+            self.unmatched.fetch_add(1, Ordering::Relaxed);
+            return None;
+        };
+        let Some(pending) = session.pending.remove(&msg_id) else {
+            self.unmatched.fetch_add(1, Ordering::Relaxed);
+            return None;
+        };
+        if client_recv_ts < pending.origin_ts {
+            self.clock_skew.fetch_add(1, Ordering::Relaxed);
+            return None;
+        }
+
+        let latency_ns = (client_recv_ts - pending.origin_ts) as u64;
+        session.samples.push_back(latency_ns);
+        while session.samples.len() > window {
+            session.samples.pop_front();
+        }
+        session.reported += 1;
+        self.reported.fetch_add(1, Ordering::Relaxed);
+        PUSH_E2E_LATENCY
+            .with_label_values(&[pending.kind.label()])
+            .observe(latency_ns as f64 / 1000.0);
+        Some(latency_ns)
+    }
+
+    /// 会话延迟分布
+    pub fn session_summary(&self, session_id: &str) -> Option<SessionLatencySummary> {
+        self.sessions
+            .get(session_id)
+            .map(|session| Self::summarize(session_id, &session))
+    }
+
+    fn summarize(session_id: &str, session: &SessionLatency) -> SessionLatencySummary {
+        let mut sorted: Vec<u64> = session.samples.iter().copied().collect();
+        sorted.sort_unstable();
+        let percentile_us = |q: f64| -> f64 {
+            if sorted.is_empty() {
+                return 0.0;
+            }
+            let idx = ((sorted.len() - 1) as f64 * q).round() as usize;
+            sorted[idx] as f64 / 1000.0
+        };
+        SessionLatencySummary {
+            session_id: session_id.to_string(),
+            reported: session.reported,
+            window_samples: sorted.len(),
+            pending: session.pending.len(),
+            expired: session.expired,
+            p50_us: percentile_us(0.5),
+            p99_us: percentile_us(0.99),
+            max_us: sorted.last().map_or(0.0, |v| *v as f64 / 1000.0),
+        }
+    }
+
+    /// 全局统计（会话按 P99 倒序）
+    pub fn stats(&self) -> PushLatencyStats {
+        let mut sessions: Vec<SessionLatencySummary> = self
+            .sessions
+            .iter()
+            .map(|entry| Self::summarize(entry.key(), entry.value()))
+            .collect();
+        sessions.sort_by(|a, b| b.p99_us.total_cmp(&a.p99_us));
+        PushLatencyStats {
+            config: self.config(),
+            sampled: self.sampled.load(Ordering::Relaxed),
+            reported: self.reported.load(Ordering::Relaxed),
+            unmatched: self.unmatched.load(Ordering::Relaxed),
+            clock_skew: self.clock_skew.load(Ordering::Relaxed),
+            sessions,
+        }
+    }
+
+    /// 会话断开时清理
+    pub fn remove_session(&self, session_id: &str) {
+        self.sessions.remove(session_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracker(sample_rate: f64) -> PushLatencyTracker {
+        PushLatencyTracker::new(PushLatencyConfig {
+            sample_rate,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_default_rate_samples_about_one_percent() {
+        let tracker = PushLatencyTracker::new(PushLatencyConfig::default());
+        let sampled = (0..100_000)
+            .filter_map(|_| tracker.sample(PushKind::Tick, now_nanos()))
+            .count();
+        assert!((900..=1100).contains(&sampled), "sampled={}", sampled);
+    }
+
+    #[test]
+    fn test_report_aggregates_per_session() {
+        let tracker = tracker(1.0);
+        let base = now_nanos();
+
+        // 1..=100 微秒的端到端延迟
+        for i in 1..=100i64 {
+            let sample = tracker.sample(PushKind::Trade, base).unwrap();
+            tracker.written("s1", sample);
+            let latency = tracker
+                .report("s1", sample.msg_id, base + i * 1000)
+                .unwrap();
+            assert_eq!(latency, (i * 1000) as u64);
+        }
+
+        let summary = tracker.session_summary("s1").unwrap();
+        assert_eq!(summary.reported, 100);
+        assert_eq!(summary.pending, 0);
+        assert_eq!(summary.p50_us, 51.0);
+        assert_eq!(summary.p99_us, 99.0);
+        assert_eq!(summary.max_us, 100.0);
+
+        // 重复回执、其他会话的 msg_id、时钟回拨都不计入分布
+        let sample = tracker.sample(PushKind::Tick, base).unwrap();
+        tracker.written("s1", sample);
+        assert!(tracker.report("s1", 1, base + 1000).is_none());
+        assert!(tracker.report("s2", sample.msg_id, base + 1000).is_none());
+        assert!(tracker.report("s1", sample.msg_id, base - 1).is_none());
+
+        let stats = tracker.stats();
+        assert_eq!(stats.sampled, 101);
+        assert_eq!(stats.reported, 100);
+        assert_eq!(stats.unmatched, 2);
+        assert_eq!(stats.clock_skew, 1);
+
+        tracker.remove_session("s1");
+        assert!(tracker.session_summary("s1").is_none());
+    }
+
+    #[test]
+    fn test_zero_rate_disables_sampling() {
+        let tracker = tracker(0.0);
+        assert!(tracker.sample(PushKind::Kline, now_nanos()).is_none());
+        tracker.update_config(PushLatencyConfig {
+            sample_rate: 5.0,
+            ..Default::default()
+        });
+        assert_eq!(tracker.sample_rate(), 1.0);
+        assert!(tracker.sample(PushKind::Kline, now_nanos()).is_some());
+    }
+
+    /// 本机 TCP 回环跑通 产生 → 入队 → 出队 → 写 socket → 客户端回执 全链路
+    fn run_loopback(
+        count: usize,
+        interval: Duration,
+    ) -> (Arc<PushLatencyTracker>, SessionLatencySummary) {
+        use std::io::{BufRead, BufReader, Write};
+        use std::net::{TcpListener, TcpStream};
+
+        let tracker = Arc::new(tracker(1.0));
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // 客户端：逐行读取推送，记录接收时刻后经回执通道回报
+        let (report_tx, report_rx) = crossbeam::channel::unbounded::<(u64, i64)>();
+        let client = std::thread::spawn(move || {
+            let stream = TcpStream::connect(addr).unwrap();
+            stream.set_nodelay(true).unwrap();
+            for line in BufReader::new(stream).lines().take(count) {
+                let line = line.unwrap();
+                let recv_ts = now_nanos();
+                let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+                report_tx
+                    .send((value["msg_id"].as_u64().unwrap(), recv_ts))
+                    .unwrap();
+            }
+        });
+
+        // 服务端：产生 → 入队 → 出队 → 序列化写 socket
+        let (mut socket, _) = listener.accept().unwrap();
+        socket.set_nodelay(true).unwrap();
+        let (queue_tx, queue_rx) = crossbeam::channel::bounded::<i64>(1024);
+        let producer = {
+            let tracker = tracker.clone();
+            std::thread::spawn(move || {
+                for _ in 0..count {
+                    let origin_ts = now_nanos();
+                    tracker.record_enqueued(PushKind::Tick, origin_ts);
+                    queue_tx.send(origin_ts).unwrap();
+                    std::thread::sleep(interval);
+                }
+            })
+        };
+        for origin_ts in queue_rx.iter().take(count) {
+            let sample = tracker.sample(PushKind::Tick, origin_ts).unwrap();
+            let line = serde_json::json!({
+                "type": "tick",
+                "instrument_id": "IF2501",
+                "price": 4000.0,
+                "origin_ts": origin_ts,
+                "msg_id": sample.msg_id,
+            });
+            socket.write_all(format!("{}\n", line).as_bytes()).unwrap();
+            tracker.written("loopback", sample);
+        }
+        producer.join().unwrap();
+        client.join().unwrap();
+
+        for (msg_id, recv_ts) in report_rx.try_iter() {
+            tracker.report("loopback", msg_id, recv_ts);
+        }
+
+        let summary = tracker.session_summary("loopback").unwrap();
+        (tracker, summary)
+    }
+
+    #[test]
+    fn test_loopback_reports_every_sample() {
+        let count = 200;
+        let (tracker, summary) = run_loopback(count, Duration::ZERO);
+
+        // 每条推送都回执且只计一次，分位数单调
+        assert_eq!(summary.reported, count as u64);
+        assert_eq!(summary.pending, 0);
+        assert!(summary.p50_us <= summary.p99_us);
+        assert!(summary.p99_us <= summary.max_us);
+
+        let stats = tracker.stats();
+        assert_eq!(stats.sampled, count as u64);
+        assert_eq!(stats.reported, count as u64);
+        assert_eq!(stats.unmatched, 0);
+        assert_eq!(stats.clock_skew, 0);
+    }
+
+    #[test]
+    #[ignore] // 环境相关的性能测试，在 CI 中跳过
+    fn test_loopback_baseline() {
+        let count = 10_000;
+        let (_, summary) = run_loopback(count, Duration::from_micros(100));
+
+        assert_eq!(summary.reported, count as u64);
+        // 本机回环 P99 应远低于 10ms
+        assert!(summary.p99_us < 10_000.0, "P99 {} μs", summary.p99_us);
+    }
+}
@@ -0,0 +1,423 @@
+//! 追踪头部采样
+//!
+//! @yutiansut @quantaxis
+//!
+//! 全量追踪开销大，生产环境按比例采样：
+//! - 头部采样：在订单入口按 trace_id 做一次采样决策，决策通过线程局部上下文贯穿全链路，
+//!   未采样的请求不创建 span（`trace_span!` / `trace_operation!` 返回空 span）
+//! - 条件采样：未被头部采样的慢请求、错误请求在结束时强制补采，保证问题请求必有 trace
+//! - 采样率、慢请求阈值可热更新，采样决策计入 Prometheus 指标
+//!
+//! 采样只决定是否产生 trace，不影响业务处理结果。
+
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::metrics::{TRACE_SAMPLING_DECISIONS, TRACE_SAMPLING_RATE};
+
+lazy_static::lazy_static! {
+    /// 全局追踪采样器（订单入口使用）
+    pub static ref TRACE_SAMPLER: Arc<TraceSampler> = Arc::new(TraceSampler::new(SamplingConfig::default()));
+}
+
+thread_local! {
+    /// 当前线程所处请求的采样决策（None 表示不在请求上下文中）
+    static CURRENT_SAMPLED: Cell<Option<bool>> = const { Cell::new(None) };
+}
+
+/// 当前请求是否被采样（不在请求上下文中时返回 true，由订阅器自行决定）
+pub fn current_trace_sampled() -> bool {
+    CURRENT_SAMPLED.with(|c| c.get()).unwrap_or(true)
+}
+
+/// 采样配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SamplingConfig {
+    /// 头部采样率 (0.0 - 1.0)
+    #[serde(default = "default_rate")]
+    pub rate: f64,
+    /// 慢请求阈值（毫秒，超过则强制采样，0 表示不按耗时补采）
+    #[serde(default = "default_slow_threshold_ms")]
+    pub slow_threshold_ms: u64,
+    /// 错误请求强制采样
+    #[serde(default = "default_true")]
+    pub always_sample_errors: bool,
+}
+
+fn default_rate() -> f64 {
+    1.0
+}
+fn default_slow_threshold_ms() -> u64 {
+    100
+}
+fn default_true() -> bool {
+    true
+}
+
+impl Default for SamplingConfig {
+    fn default() -> Self {
+        Self {
+            rate: default_rate(),
+            slow_threshold_ms: default_slow_threshold_ms(),
+            always_sample_errors: true,
+        }
+    }
+}
+
+/// 请求的最终采样结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SamplingOutcome {
+    /// 头部采样命中
+    Sampled,
+    /// 未采样
+    Dropped,
+    /// 慢请求补采
+    ForcedSlow,
+    /// 错误请求补采
+    ForcedError,
+}
+
+impl SamplingOutcome {
+    fn label(&self) -> &'static str {
+        match self {
+            SamplingOutcome::Sampled => "sampled",
+            SamplingOutcome::Dropped => "dropped",
+            SamplingOutcome::ForcedSlow => "forced_slow",
+            SamplingOutcome::ForcedError => "forced_error",
+        }
+    }
+
+    /// 是否产生 trace
+    pub fn is_traced(&self) -> bool {
+        !matches!(self, SamplingOutcome::Dropped)
+    }
+}
+
+/// 采样统计
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SamplingStats {
+    pub config: SamplingConfig,
+    /// 请求总数
+    pub requests: u64,
+    /// 头部采样命中数
+    pub head_sampled: u64,
+    /// 慢请求补采数
+    pub forced_slow: u64,
+    /// 错误请求补采数
+    pub forced_error: u64,
+    /// 实际产生 trace 的比例
+    pub effective_rate: f64,
+}
+
+/// 追踪采样器
+pub struct TraceSampler {
+    /// 采样率（f64 位表示，支持无锁热更新）
+    rate_bits: AtomicU64,
+    slow_threshold_ms: AtomicU64,
+    always_sample_errors: AtomicBool,
+    next_id: AtomicU64,
+    requests: AtomicU64,
+    head_sampled: AtomicU64,
+    forced_slow: AtomicU64,
+    forced_error: AtomicU64,
+}
+
+impl TraceSampler {
+    pub fn new(config: SamplingConfig) -> Self {
+        let sampler = Self {
+            rate_bits: AtomicU64::new(0),
+            slow_threshold_ms: AtomicU64::new(0),
+            always_sample_errors: AtomicBool::new(true),
+            next_id: AtomicU64::new(0),
+            requests: AtomicU64::new(0),
+            head_sampled: AtomicU64::new(0),
+            forced_slow: AtomicU64::new(0),
+            forced_error: AtomicU64::new(0),
+        };
+        sampler.update_config(config);
+        sampler
+    }
+
+    /// 热更新采样配置（对之后进入的请求生效）
+    pub fn update_config(&self, config: SamplingConfig) {
+        let rate = if config.rate.is_finite() {
+            config.rate.clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        self.rate_bits.store(rate.to_bits(), Ordering::Relaxed);
+        self.slow_threshold_ms
+            .store(config.slow_threshold_ms, Ordering::Relaxed);
+        self.always_sample_errors
+            .store(config.always_sample_errors, Ordering::Relaxed);
+        TRACE_SAMPLING_RATE.set(rate);
+    }
+
+    /// 当前配置
+    pub fn config(&self) -> SamplingConfig {
+        SamplingConfig {
+            rate: self.rate(),
+            slow_threshold_ms: self.slow_threshold_ms.load(Ordering::Relaxed),
+            always_sample_errors: self.always_sample_errors.load(Ordering::Relaxed),
+        }
+    }
+
+    /// 当前采样率
+    pub fn rate(&self) -> f64 {
+        f64::from_bits(self.rate_bits.load(Ordering::Relaxed))
+    }
+
+    /// 生成 trace_id（splitmix64 打散，高位均匀分布）
+    fn next_trace_id(&self) -> u64 {
+        let mut z = self
+            .next_id
+            .fetch_add(1, Ordering::Relaxed)
+            .wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// 按 trace_id 做头部采样决策（同一 trace_id 决策确定）
+    pub fn should_sample(&self, trace_id: u64) -> bool {
+        let rate = self.rate();
+        if rate >= 1.0 {
+            return true;
+        }
+        if rate <= 0.0 {
+            return false;
+        }
+        ((trace_id >> 11) as f64 / (1u64 << 53) as f64) < rate
+    }
+
+    /// 请求入口：做出采样决策，采样命中时创建根 span
+    pub fn start(self: &Arc<Self>, operation: &'static str) -> RequestTrace {
+        let trace_id = self.next_trace_id();
+        let sampled = self.should_sample(trace_id);
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        if sampled {
+            self.head_sampled.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let span = if sampled {
+            tracing::info_span!("request", operation, trace_id = %format!("{:016x}", trace_id))
+        } else {
+            tracing::Span::none()
+        };
+
+        RequestTrace {
+            sampler: self.clone(),
+            operation,
+            trace_id,
+            sampled,
+            started: Instant::now(),
+            span,
+        }
+    }
+
+    /// 请求结束：未采样的慢请求/错误请求强制补采，返回最终采样结果
+    fn record_outcome(&self, sampled: bool, elapsed: Duration, is_error: bool) -> SamplingOutcome {
+        let slow_threshold_ms = self.slow_threshold_ms.load(Ordering::Relaxed);
+        let outcome = if sampled {
+            SamplingOutcome::Sampled
+        } else if is_error && self.always_sample_errors.load(Ordering::Relaxed) {
+            self.forced_error.fetch_add(1, Ordering::Relaxed);
+            SamplingOutcome::ForcedError
+        } else if slow_threshold_ms > 0 && elapsed >= Duration::from_millis(slow_threshold_ms) {
+            self.forced_slow.fetch_add(1, Ordering::Relaxed);
+            SamplingOutcome::ForcedSlow
+        } else {
+            SamplingOutcome::Dropped
+        };
+        TRACE_SAMPLING_DECISIONS
+            .with_label_values(&[outcome.label()])
+            .inc();
+        outcome
+    }
+
+    /// 采样统计
+    pub fn stats(&self) -> SamplingStats {
+        let requests = self.requests.load(Ordering::Relaxed);
+        let head_sampled = self.head_sampled.load(Ordering::Relaxed);
+        let forced_slow = self.forced_slow.load(Ordering::Relaxed);
+        let forced_error = self.forced_error.load(Ordering::Relaxed);
+        let traced = head_sampled + forced_slow + forced_error;
+        SamplingStats {
+            config: self.config(),
+            requests,
+            head_sampled,
+            forced_slow,
+            forced_error,
+            effective_rate: if requests > 0 {
+                traced as f64 / requests as f64
+            } else {
+                0.0
+            },
+        }
+    }
+}
+
+/// 单个请求的追踪上下文
+pub struct RequestTrace {
+    sampler: Arc<TraceSampler>,
+    operation: &'static str,
+    trace_id: u64,
+    sampled: bool,
+    started: Instant,
+    span: tracing::Span,
+}
+
+impl RequestTrace {
+    pub fn trace_id(&self) -> u64 {
+        self.trace_id
+    }
+
+    /// 头部采样是否命中
+    pub fn is_sampled(&self) -> bool {
+        self.sampled
+    }
+
+    /// 进入请求上下文：期间创建的 span 遵循本请求的采样决策
+    pub fn enter(&self) -> TraceScope<'_> {
+        let previous = CURRENT_SAMPLED.with(|c| c.replace(Some(self.sampled)));
+        TraceScope {
+            _entered: self.span.enter(),
+            previous,
+        }
+    }
+
+    /// 结束请求
+    pub fn finish(self, is_error: bool) -> SamplingOutcome {
+        let elapsed = self.started.elapsed();
+        let outcome = self.sampler.record_outcome(self.sampled, elapsed, is_error);
+
+        let elapsed_us = elapsed.as_micros() as u64;
+        match outcome {
+            SamplingOutcome::Sampled => {
+                self.span
+                    .in_scope(|| tracing::info!(elapsed_us, error = is_error, "request completed"));
+            }
+            SamplingOutcome::ForcedSlow | SamplingOutcome::ForcedError => {
+                // 补采：请求已结束，仅记录根 span 与耗时
+                let span = tracing::info_span!(
+                    "request",
+                    operation = self.operation,
+                    trace_id = %format!("{:016x}", self.trace_id),
+                    forced = outcome.label()
+                );
+                span.in_scope(|| tracing::warn!(elapsed_us, error = is_error, "request completed"));
+            }
+            SamplingOutcome::Dropped => {}
+        }
+        outcome
+    }
+}
+
+/// 请求上下文作用域（drop 时恢复外层采样决策）
+pub struct TraceScope<'a> {
+    _entered: tracing::span::Entered<'a>,
+    previous: Option<bool>,
+}
+
+impl Drop for TraceScope<'_> {
+    fn drop(&mut self) {
+        CURRENT_SAMPLED.with(|c| c.set(self.previous));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sampler(rate: f64) -> Arc<TraceSampler> {
+        Arc::new(TraceSampler::new(SamplingConfig {
+            rate,
+            slow_threshold_ms: 0,
+            always_sample_errors: true,
+        }))
+    }
+
+    #[test]
+    fn test_ten_percent_rate_traces_about_ten_percent() {
+        let sampler = sampler(0.1);
+        let traced = (0..10_000)
+            .map(|_| sampler.start("submit_order").finish(false))
+            .filter(|o| o.is_traced())
+            .count();
+
+        assert!((900..=1100).contains(&traced), "traced={}", traced);
+        let stats = sampler.stats();
+        assert_eq!(stats.requests, 10_000);
+        assert_eq!(stats.head_sampled as usize, traced);
+        assert!((stats.effective_rate - 0.1).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_errors_and_slow_requests_always_traced() {
+        let sampler = sampler(0.0);
+
+        // 错误请求必采
+        let trace = sampler.start("submit_order");
+        assert!(!trace.is_sampled());
+        assert_eq!(trace.finish(true), SamplingOutcome::ForcedError);
+        assert_eq!(
+            sampler.start("submit_order").finish(false),
+            SamplingOutcome::Dropped
+        );
+
+        // 慢请求必采（热更新阈值）
+        sampler.update_config(SamplingConfig {
+            rate: 0.0,
+            slow_threshold_ms: 5,
+            always_sample_errors: true,
+        });
+        assert_eq!(
+            sampler.record_outcome(false, Duration::from_millis(5), false),
+            SamplingOutcome::ForcedSlow
+        );
+        assert_eq!(
+            sampler.record_outcome(false, Duration::from_millis(1), false),
+            SamplingOutcome::Dropped
+        );
+
+        let stats = sampler.stats();
+        assert_eq!((stats.forced_error, stats.forced_slow), (1, 1));
+    }
+
+    #[test]
+    fn test_decision_propagates_and_hot_update() {
+        let sampler = sampler(0.0);
+        assert!(current_trace_sampled());
+        {
+            let trace = sampler.start("submit_order");
+            let _scope = trace.enter();
+            assert!(!current_trace_sampled());
+            assert!(crate::trace_span!("match_order").is_none());
+        }
+        assert!(current_trace_sampled());
+
+        // 热更新为全量采样
+        sampler.update_config(SamplingConfig {
+            rate: 1.0,
+            ..SamplingConfig::default()
+        });
+        let trace = sampler.start("submit_order");
+        assert!(trace.is_sampled());
+        {
+            let _scope = trace.enter();
+            assert!(current_trace_sampled());
+        }
+        assert_eq!(trace.finish(false), SamplingOutcome::Sampled);
+
+        // 非法采样率被截断
+        sampler.update_config(SamplingConfig {
+            rate: 3.0,
+            ..SamplingConfig::default()
+        });
+        assert_eq!(sampler.rate(), 1.0);
+    }
+}
@@ -11,12 +11,14 @@
 //! 性能目标：
 //! - Span 创建开销: < 100ns
 //! - 批量导出: 异步非阻塞
-//! - 采样率: 可配置（生产环境建议 1-10%）
+//! - 采样率: 可配置（生产环境建议 1-10%），在请求入口做头部采样，见 [`super::sampling`]
 
 use opentelemetry::trace::TracerProvider as _;
 use opentelemetry::{global, KeyValue};
 use opentelemetry_sdk::trace::{RandomIdGenerator, Sampler, TracerProvider};
 use opentelemetry_sdk::Resource;
+
+use super::sampling::{SamplingConfig, TRACE_SAMPLER};
 use std::time::Duration;
 use thiserror::Error;
 use tracing_subscriber::layer::SubscriberExt;
@@ -57,6 +59,10 @@ pub struct TracingConfig {
     pub endpoint: String,
     /// 采样率 (0.0 - 1.0)
     pub sampling_rate: f64,
+    /// 慢请求阈值（毫秒，未采样的慢请求强制采样）
+    pub slow_request_threshold_ms: u64,
+    /// 错误请求强制采样
+    pub always_sample_errors: bool,
     /// 批量导出配置
     pub batch_config: BatchExportConfig,
     /// 日志级别过滤
@@ -99,6 +105,8 @@ impl Default for TracingConfig {
             exporter: ExporterType::Console, // 默认使用控制台
             endpoint: "http://localhost:4317".to_string(),
             sampling_rate: 1.0, // 开发环境 100% 采样
+            slow_request_threshold_ms: 100,
+            always_sample_errors: true,
             batch_config: BatchExportConfig::default(),
             log_filter: "info,qaexchange=debug".to_string(),
             console_export: true,
@@ -118,6 +126,15 @@ impl Default for BatchExportConfig {
 }
 
 impl TracingConfig {
+    /// 头部采样配置
+    pub fn sampling_config(&self) -> SamplingConfig {
+        SamplingConfig {
+            rate: self.sampling_rate,
+            slow_threshold_ms: self.slow_request_threshold_ms,
+            always_sample_errors: self.always_sample_errors,
+        }
+    }
+
     /// 生产环境配置
     pub fn production(endpoint: impl Into<String>) -> Self {
         Self {
@@ -165,6 +182,9 @@ impl TracingInitializer {
 
     /// 初始化追踪系统
     pub fn init(&self) -> Result<(), TracingError> {
+        // 头部采样决策在请求入口由全局采样器完成
+        TRACE_SAMPLER.update_config(self.config.sampling_config());
+
         if !self.config.enabled {
             // 仅初始化基本日志
             self.init_basic_logging()?;
@@ -278,13 +298,14 @@ impl TracingInitializer {
     }
 
     /// 创建采样器
+    ///
+    /// 请求链路的采样已在入口完成（未采样请求不创建 span），这里对已创建的 span 全量导出，
+    /// 避免二次按比例丢弃导致实际采样率低于配置值。
     fn create_sampler(&self) -> Sampler {
-        if self.config.sampling_rate >= 1.0 {
-            Sampler::AlwaysOn
-        } else if self.config.sampling_rate <= 0.0 {
+        if self.config.sampling_rate <= 0.0 && !self.config.always_sample_errors {
             Sampler::AlwaysOff
         } else {
-            Sampler::TraceIdRatioBased(self.config.sampling_rate)
+            Sampler::ParentBased(Box::new(Sampler::AlwaysOn))
         }
     }
 
@@ -351,22 +372,30 @@ pub fn init_test_tracing() -> Result<(), TracingError> {
 // Span 工具宏
 // ═══════════════════════════════════════════════════════════════════════════
 
-/// 创建带自动计时的 span
+/// 创建带自动计时的 span（当前请求未被采样时返回空 span）
 #[macro_export]
 macro_rules! trace_span {
     ($name:expr) => {
-        tracing::info_span!($name)
+        if $crate::observability::sampling::current_trace_sampled() {
+            tracing::info_span!($name)
+        } else {
+            tracing::Span::none()
+        }
     };
     ($name:expr, $($field:tt)*) => {
-        tracing::info_span!($name, $($field)*)
+        if $crate::observability::sampling::current_trace_sampled() {
+            tracing::info_span!($name, $($field)*)
+        } else {
+            tracing::Span::none()
+        }
     };
 }
 
-/// 记录关键操作
+/// 记录关键操作（当前请求未被采样时不创建 span）
 #[macro_export]
 macro_rules! trace_operation {
     ($name:expr, $op:expr) => {{
-        let span = tracing::info_span!($name);
+        let span = $crate::trace_span!($name);
         let _guard = span.enter();
         let start = std::time::Instant::now();
         let result = $op;
@@ -390,6 +419,8 @@ mod tests {
         assert!(config.enabled);
         assert_eq!(config.service_name, "qaexchange");
         assert_eq!(config.sampling_rate, 1.0);
+        assert_eq!(config.sampling_config().slow_threshold_ms, 100);
+        assert!(config.sampling_config().always_sample_errors);
     }
 
     #[test]
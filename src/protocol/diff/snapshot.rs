@@ -8,6 +8,10 @@
 //! - **差分推送**: 生成和应用 JSON Merge Patch
 //! - **peek() 阻塞**: 实现 DIFF 协议的阻塞等待机制
 //! - **并发访问**: 线程安全的多用户并发支持
+//! - **多终端会话**: 同一用户的每个会话拥有独立 patch 队列，互不抢占
+//! - **按需全量**: 会话可随时拉取全量快照，快照版本号之后的增量继续推送
+//! - **序列号与补发**: 每个 patch 带单调递增序列号，保留有限历史；客户端检测到
+//!   序列号不连续时请求重新同步，历史可覆盖则补发，否则下发全量快照重置
 //!
 //! # 架构设计
 //!
@@ -18,6 +22,9 @@
 //! │  │ user_snapshots: DashMap<user_id, UserSnapshotState>   │ │
 //! │  │   ├─ snapshot: BusinessSnapshot                        │ │
 //! │  │   ├─ pending_patches: Vec<Value>                       │ │
+//! │  │   ├─ session_queues: session_id -> SessionStream       │ │
+//! │  │   ├─ version: AtomicU64 (已推送 patch 数 = 最新序列号) │ │
+//! │  │   ├─ history: VecDeque<(seq, patch)> (有界补发历史)    │ │
 //! │  │   └─ notifier: Arc<Notify>                             │ │
 //! │  └────────────────────────────────────────────────────────┘ │
 //! │                                                              │
@@ -26,6 +33,7 @@
 //! │  - peek()             → 阻塞等待新 patch                     │
 //! │  - apply_patches()    → 应用 patch 到快照                   │
 //! │  - get_snapshot()     → 获取当前快照                        │
+//! │  - resync_session()   → 按序列号补发或全量重置              │
 //! └──────────────────────────────────────────────────────────────┘
 //! ```
 //!
@@ -64,6 +72,8 @@
 
 use dashmap::DashMap;
 use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::Notify;
@@ -71,39 +81,172 @@ use tokio::time::timeout;
 
 use super::merge::merge_patch;
 
+/// 默认保留的补发历史 patch 数（每用户）
+pub const DEFAULT_PATCH_HISTORY: usize = 1024;
+
+/// 一次下发的 patch 批次（对应一条 rtn_data）
+///
+/// 序列号为用户级 patch 序号：`seq` 为批次中最后一个 patch 的序号，`prev_seq` 为客户端
+/// 应用本批次前应处于的序号。`prev_seq` 为空而 `seq` 不为空表示全量重置，
+/// 客户端应丢弃本地状态后再合并。未登记会话的共享队列不带序列号。
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PatchBatch {
+    pub seq: Option<u64>,
+    pub prev_seq: Option<u64>,
+    pub patches: Vec<Value>,
+}
+
+/// 重新同步结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResyncOutcome {
+    /// 历史可覆盖，补发 `(from_seq, to_seq]` 区间的 patch
+    Replayed {
+        from_seq: u64,
+        to_seq: u64,
+        count: usize,
+    },
+    /// 历史不足（或客户端无基线），下发全量快照重置
+    Reset { seq: u64 },
+}
+
+/// 客户端序列号校验结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeqCheck {
+    /// 序列号连续，合并本批次
+    Apply,
+    /// 全量重置，丢弃本地状态后合并本批次
+    Reset,
+    /// 检测到丢包，应以 `last_seq` 发送 resync（0 表示无基线）
+    Gap { last_seq: u64, prev_seq: u64 },
+    /// 等待重新同步期间收到的不连续批次，直接丢弃
+    Discard,
+    /// 不带序列号的消息（错误通知等带外消息），直接合并
+    Unsequenced,
+}
+
+/// 客户端 rtn_data 序列号跟踪器
+///
+/// 按 `prev_seq` 是否等于本地已确认序号判断连续性；检测到丢包后只报告一次 `Gap`，
+/// 在补发或全量重置到达之前的批次一律丢弃。
+#[derive(Debug, Default)]
+pub struct SeqTracker {
+    last_seq: Option<u64>,
+    awaiting_resync: bool,
+}
+
+impl SeqTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 本地已确认的序列号（重连时用于协商）
+    pub fn last_seq(&self) -> Option<u64> {
+        self.last_seq
+    }
+
+    /// 校验一条 rtn_data 的序列号
+    pub fn check(&mut self, prev_seq: Option<u64>, seq: Option<u64>) -> SeqCheck {
+        let Some(seq) = seq else {
+            return SeqCheck::Unsequenced;
+        };
+        match prev_seq {
+            None => {
+                self.last_seq = Some(seq);
+                self.awaiting_resync = false;
+                SeqCheck::Reset
+            }
+            Some(prev) if self.last_seq == Some(prev) => {
+                self.last_seq = Some(seq);
+                self.awaiting_resync = false;
+                SeqCheck::Apply
+            }
+            Some(_) if self.awaiting_resync => SeqCheck::Discard,
+            Some(prev) => {
+                self.awaiting_resync = true;
+                SeqCheck::Gap {
+                    last_seq: self.last_seq.unwrap_or(0),
+                    prev_seq: prev,
+                }
+            }
+        }
+    }
+}
+
+/// 会话推送流
+#[derive(Debug, Default)]
+struct SessionStream {
+    /// 待发送 patch
+    queue: Vec<Value>,
+    /// 已下发到的序列号
+    delivered_seq: u64,
+    /// 下一批次为全量重置（新会话或补发失败）
+    reset_pending: bool,
+}
+
 /// 用户快照状态
 #[derive(Debug)]
 struct UserSnapshotState {
     /// 业务快照（完整状态）
     snapshot: parking_lot::RwLock<Value>,
 
-    /// 待发送的 patch 队列
+    /// 待发送的 patch 队列（未登记会话时使用）
     pending_patches: parking_lot::RwLock<Vec<Value>>,
 
+    /// 会话独立队列 (session_id -> 推送流)
+    /// 同一用户多终端登录时每个会话各自消费，互不抢占
+    session_queues: parking_lot::RwLock<HashMap<String, SessionStream>>,
+
+    /// 快照版本号（每推送一个 patch 加 1，与入队在同一把锁内递增，即 patch 序列号）
+    version: AtomicU64,
+
+    /// 补发历史 (seq, patch)，超过上限丢弃最旧的
+    history: parking_lot::RwLock<VecDeque<(u64, Value)>>,
+
+    /// 补发历史上限
+    history_limit: usize,
+
     /// 通知器（用于 peek 阻塞）
     notifier: Arc<Notify>,
 }
 
 impl UserSnapshotState {
     /// 创建新的用户快照状态
-    fn new() -> Self {
+    fn new(history_limit: usize) -> Self {
         Self {
             snapshot: parking_lot::RwLock::new(Value::Object(serde_json::Map::new())),
             pending_patches: parking_lot::RwLock::new(Vec::new()),
+            session_queues: parking_lot::RwLock::new(HashMap::new()),
+            version: AtomicU64::new(0),
+            history: parking_lot::RwLock::new(VecDeque::new()),
+            history_limit,
             notifier: Arc::new(Notify::new()),
         }
     }
 
-    /// 推送 patch 到待发送队列
+    /// 推送 patch 到待发送队列（有登记会话时扇出到每个会话）
     fn push_patch(&self, patch: Value) {
-        let mut patches = self.pending_patches.write();
-        patches.push(patch.clone());
-        drop(patches); // 提前释放锁
+        let mut sessions = self.session_queues.write();
+        if sessions.is_empty() {
+            self.pending_patches.write().push(patch.clone());
+        } else {
+            for stream in sessions.values_mut() {
+                stream.queue.push(patch.clone());
+            }
+        }
 
-        // 应用 patch 到快照
+        // 应用 patch 到快照（持有会话锁，全量快照与队列中的增量不会交错）
         let mut snapshot = self.snapshot.write();
         merge_patch(&mut snapshot, &patch);
         drop(snapshot);
+        let seq = self.version.fetch_add(1, Ordering::SeqCst) + 1;
+        if self.history_limit > 0 {
+            let mut history = self.history.write();
+            if history.len() >= self.history_limit {
+                history.pop_front();
+            }
+            history.push_back((seq, patch));
+        }
+        drop(sessions);
 
         // 通知等待的客户端
         self.notifier.notify_waiters();
@@ -120,6 +263,26 @@ impl UserSnapshotState {
         !self.pending_patches.read().is_empty()
     }
 
+    /// 获取会话的待发送 patch 批次并清空（会话未登记返回 None，无 patch 返回空批次）
+    fn take_session_patches(&self, session_id: &str) -> Option<PatchBatch> {
+        let mut sessions = self.session_queues.write();
+        let stream = sessions.get_mut(session_id)?;
+        if stream.queue.is_empty() {
+            return Some(PatchBatch::default());
+        }
+
+        // 入队与版本号递增在同一把锁内，此时的版本号即队尾 patch 的序列号
+        let seq = self.version.load(Ordering::SeqCst);
+        let prev_seq = (!stream.reset_pending).then_some(stream.delivered_seq);
+        stream.delivered_seq = seq;
+        stream.reset_pending = false;
+        Some(PatchBatch {
+            seq: Some(seq),
+            prev_seq,
+            patches: std::mem::take(&mut stream.queue),
+        })
+    }
+
     /// 获取当前快照的副本
     fn get_snapshot_clone(&self) -> Value {
         self.snapshot.read().clone()
@@ -135,6 +298,9 @@ pub struct SnapshotManager {
 
     /// peek() 超时时间（默认 30 秒）
     peek_timeout: Duration,
+
+    /// 每用户保留的补发历史 patch 数
+    history_limit: usize,
 }
 
 impl SnapshotManager {
@@ -151,6 +317,7 @@ impl SnapshotManager {
         Self {
             user_snapshots: DashMap::new(),
             peek_timeout: Duration::from_secs(30),
+            history_limit: DEFAULT_PATCH_HISTORY,
         }
     }
 
@@ -172,9 +339,25 @@ impl SnapshotManager {
         Self {
             user_snapshots: DashMap::new(),
             peek_timeout,
+            history_limit: DEFAULT_PATCH_HISTORY,
         }
     }
 
+    /// 设置每用户保留的补发历史 patch 数（0 表示不保留，丢包后一律全量重置）
+    ///
+    /// 只影响之后创建的用户快照。
+    pub fn with_history_limit(mut self, history_limit: usize) -> Self {
+        self.history_limit = history_limit;
+        self
+    }
+
+    fn user_state(&self, user_id: &str) -> Arc<UserSnapshotState> {
+        self.user_snapshots
+            .entry(user_id.to_string())
+            .or_insert_with(|| Arc::new(UserSnapshotState::new(self.history_limit)))
+            .clone()
+    }
+
     /// 初始化用户快照
     ///
     /// 为新用户创建空的业务快照。
@@ -194,9 +377,7 @@ impl SnapshotManager {
     /// # }
     /// ```
     pub async fn initialize_user(&self, user_id: &str) {
-        self.user_snapshots
-            .entry(user_id.to_string())
-            .or_insert_with(|| Arc::new(UserSnapshotState::new()));
+        self.user_state(user_id);
     }
 
     /// 推送 patch 到用户快照
@@ -231,13 +412,7 @@ impl SnapshotManager {
     /// # }
     /// ```
     pub async fn push_patch(&self, user_id: &str, patch: Value) {
-        let state = self
-            .user_snapshots
-            .entry(user_id.to_string())
-            .or_insert_with(|| Arc::new(UserSnapshotState::new()))
-            .clone();
-
-        state.push_patch(patch);
+        self.user_state(user_id).push_patch(patch);
     }
 
     /// peek() 阻塞等待新 patch
@@ -297,6 +472,204 @@ impl SnapshotManager {
         }
     }
 
+    /// 登记会话
+    ///
+    /// 登记后该会话拥有独立的 patch 队列，同一用户的多个会话都能收到每个 patch。
+    /// 新会话的首个 patch 为当前完整快照，保证后登录的终端也能拿到全量数据；
+    /// 该批次按全量重置下发（不带 `prev_seq`）。
+    ///
+    /// # 参数
+    ///
+    /// * `user_id` - 用户ID
+    /// * `session_id` - 会话ID
+    pub fn register_session(&self, user_id: &str, session_id: &str) {
+        let state = self.user_state(user_id);
+
+        let mut sessions = state.session_queues.write();
+        if sessions.is_empty() {
+            // 共享队列中的 patch 已合并进快照，随全量快照下发
+            state.pending_patches.write().clear();
+        }
+        let snapshot = state.get_snapshot_clone();
+        let initial = match snapshot.as_object() {
+            Some(obj) if !obj.is_empty() => vec![snapshot],
+            _ => Vec::new(),
+        };
+        sessions.insert(
+            session_id.to_string(),
+            SessionStream {
+                queue: initial,
+                delivered_seq: state.version.load(Ordering::SeqCst),
+                reset_pending: true,
+            },
+        );
+    }
+
+    /// 用全量快照重置会话队列
+    ///
+    /// 在会话锁内读取当前版本号并调用 `build` 生成全量 patch，随后替换该会话的待发送队列：
+    /// 队列中尚未发送的增量已包含在全量快照里，直接丢弃；之后推送的增量版本号都大于快照版本号，
+    /// 按原顺序排在全量快照之后，客户端依次合并即可与后续增量无缝衔接。
+    /// 全量 patch 同时合并进用户快照，之后登记的会话也能拿到。
+    ///
+    /// # 返回
+    ///
+    /// `Some(version)` - 全量快照对应的版本号
+    /// `None` - 会话未登记
+    pub fn reset_session_snapshot(
+        &self,
+        user_id: &str,
+        session_id: &str,
+        build: impl FnOnce(u64) -> Vec<Value>,
+    ) -> Option<u64> {
+        let state = self.user_snapshots.get(user_id)?.clone();
+
+        let mut sessions = state.session_queues.write();
+        let stream = sessions.get_mut(session_id)?;
+        let version = state.version.load(Ordering::SeqCst);
+        let patches = build(version);
+
+        let mut snapshot = state.snapshot.write();
+        for patch in &patches {
+            merge_patch(&mut snapshot, patch);
+        }
+        drop(snapshot);
+        stream.queue = patches;
+        drop(sessions);
+
+        // 唤醒该会话挂起的 peek，立即下发全量快照
+        state.notifier.notify_waiters();
+        Some(version)
+    }
+
+    /// 获取用户当前快照版本号（用户不存在返回 None）
+    pub fn snapshot_version(&self, user_id: &str) -> Option<u64> {
+        self.user_snapshots
+            .get(user_id)
+            .map(|state| state.version.load(Ordering::SeqCst))
+    }
+
+    /// 注销会话，返回该用户剩余的会话数
+    pub fn unregister_session(&self, user_id: &str, session_id: &str) -> usize {
+        self.user_snapshots.get(user_id).map_or(0, |state| {
+            let mut sessions = state.session_queues.write();
+            sessions.remove(session_id);
+            sessions.len()
+        })
+    }
+
+    /// 获取用户已登记的会话数
+    pub fn session_count(&self, user_id: &str) -> usize {
+        self.user_snapshots
+            .get(user_id)
+            .map_or(0, |state| state.session_queues.read().len())
+    }
+
+    /// 按会话 peek() 阻塞等待新 patch
+    ///
+    /// 会话未登记时退化为 [`peek`](Self::peek)（用户共享队列）。
+    ///
+    /// # 返回
+    ///
+    /// `Some(patches)` - 该会话待发送的 patch 数组
+    /// `None` - 超时或用户不存在
+    pub async fn peek_session(&self, user_id: &str, session_id: &str) -> Option<Vec<Value>> {
+        self.peek_session_batch(user_id, session_id)
+            .await
+            .map(|batch| batch.patches)
+    }
+
+    /// 按会话 peek() 阻塞等待新 patch，返回带序列号的批次
+    ///
+    /// 会话未登记时退化为 [`peek`](Self::peek)，批次不带序列号。
+    pub async fn peek_session_batch(&self, user_id: &str, session_id: &str) -> Option<PatchBatch> {
+        let state = self.user_snapshots.get(user_id)?.clone();
+
+        // 快速路径：会话队列已有 patch
+        match state.take_session_patches(session_id) {
+            None => {
+                return self.peek(user_id).await.map(|patches| PatchBatch {
+                    patches,
+                    ..Default::default()
+                })
+            }
+            Some(batch) if !batch.patches.is_empty() => return Some(batch),
+            Some(_) => {}
+        }
+
+        // 慢速路径：阻塞等待新 patch
+        let notifier = state.notifier.clone();
+        drop(state);
+
+        match timeout(self.peek_timeout, notifier.notified()).await {
+            Ok(_) => self
+                .user_snapshots
+                .get(user_id)
+                .and_then(|state| state.take_session_patches(session_id)),
+            Err(_) => None,
+        }
+    }
+
+    /// 按客户端已确认的序列号重新同步会话
+    ///
+    /// 客户端检测到序列号不连续（丢包）或重连后携带上次确认的 `last_seq` 调用：
+    /// - 补发历史覆盖 `(last_seq, 当前序列号]` 时，用历史 patch 替换会话队列，
+    ///   下一批次的 `prev_seq` 为 `last_seq`，客户端在原状态上继续合并；
+    /// - `last_seq` 为 0（客户端无基线）、早于历史最旧序号或超过当前序列号
+    ///   （用户快照已重建）时，用完整快照替换会话队列，下一批次按全量重置下发。
+    ///
+    /// 未经 [`push_patch`](Self::push_patch) 产生的快照变更（`apply_patches`、
+    /// 会话级全量快照）不在补发历史中，无基线的客户端只能走全量重置。
+    ///
+    /// # 返回
+    ///
+    /// `None` - 会话未登记
+    pub fn resync_session(
+        &self,
+        user_id: &str,
+        session_id: &str,
+        last_seq: u64,
+    ) -> Option<ResyncOutcome> {
+        let state = self.user_snapshots.get(user_id)?.clone();
+
+        let mut sessions = state.session_queues.write();
+        let stream = sessions.get_mut(session_id)?;
+        let version = state.version.load(Ordering::SeqCst);
+
+        let history = state.history.read();
+        let covered = last_seq > 0
+            && last_seq <= version
+            && (last_seq == version
+                || history
+                    .front()
+                    .is_some_and(|(oldest, _)| *oldest <= last_seq + 1));
+
+        let outcome = if covered {
+            stream.queue = history
+                .iter()
+                .filter(|(seq, _)| *seq > last_seq)
+                .map(|(_, patch)| patch.clone())
+                .collect();
+            stream.delivered_seq = last_seq;
+            stream.reset_pending = false;
+            ResyncOutcome::Replayed {
+                from_seq: last_seq,
+                to_seq: version,
+                count: stream.queue.len(),
+            }
+        } else {
+            stream.queue = vec![state.get_snapshot_clone()];
+            stream.reset_pending = true;
+            ResyncOutcome::Reset { seq: version }
+        };
+        drop(history);
+        drop(sessions);
+
+        // 唤醒该会话挂起的 peek，立即下发补发/全量数据
+        state.notifier.notify_waiters();
+        Some(outcome)
+    }
+
     /// 获取用户当前快照
     ///
     /// 返回用户当前业务快照的副本。
@@ -694,4 +1067,185 @@ mod tests {
         assert_eq!(snapshot["counter"], 999);
         assert_eq!(update_count.load(Ordering::SeqCst), 1000);
     }
+
+    #[tokio::test]
+    async fn test_multi_session_fan_out() {
+        let manager = Arc::new(SnapshotManager::with_timeout(Duration::from_secs(1)));
+        manager
+            .push_patch(
+                "user123",
+                json!({"accounts": {"ACC001": {"balance": 100000.0}}}),
+            )
+            .await;
+
+        // PC 与手机同时登录
+        manager.register_session("user123", "pc");
+        manager.register_session("user123", "mobile");
+        assert_eq!(manager.session_count("user123"), 2);
+
+        // 新会话首先收到全量快照
+        for session_id in ["pc", "mobile"] {
+            let initial = manager.peek_session("user123", session_id).await.unwrap();
+            assert_eq!(initial.len(), 1);
+            assert_eq!(initial[0]["accounts"]["ACC001"]["balance"], 100000.0);
+        }
+
+        // 手机会话阻塞等待，同一笔成交两个会话都能收到
+        let manager_clone = manager.clone();
+        let mobile_task = tokio::spawn(async move {
+            manager_clone
+                .peek_session("user123", "mobile")
+                .await
+                .unwrap()
+        });
+        sleep(Duration::from_millis(50)).await;
+        let trade = json!({"trades": {"T1": {"volume": 1}}});
+        manager.push_patch("user123", trade.clone()).await;
+
+        let pc_patches = manager.peek_session("user123", "pc").await.unwrap();
+        assert_eq!(pc_patches, vec![trade.clone()]);
+        let mobile_patches = mobile_task.await.unwrap();
+        assert_eq!(mobile_patches, vec![trade]);
+
+        // 注销一个会话后另一个会话不受影响
+        assert_eq!(manager.unregister_session("user123", "pc"), 1);
+        manager.push_patch("user123", json!({"counter": 1})).await;
+        let mobile_patches = manager.peek_session("user123", "mobile").await.unwrap();
+        assert_eq!(mobile_patches, vec![json!({"counter": 1})]);
+    }
+
+    #[tokio::test]
+    async fn test_packet_loss_detected_and_replayed() {
+        let manager = SnapshotManager::with_timeout(Duration::from_millis(200));
+        manager
+            .push_patch("user123", json!({"balance": 100.0}))
+            .await;
+        manager.register_session("user123", "s1");
+
+        // 客户端本地状态与序列号跟踪
+        let mut client_state = json!({});
+        let mut tracker = SeqTracker::new();
+        fn deliver(tracker: &mut SeqTracker, batch: PatchBatch, state: &mut Value) -> SeqCheck {
+            let check = tracker.check(batch.prev_seq, batch.seq);
+            match check {
+                SeqCheck::Reset => {
+                    *state = json!({});
+                    batch.patches.iter().for_each(|p| merge_patch(state, p));
+                }
+                SeqCheck::Apply | SeqCheck::Unsequenced => {
+                    batch.patches.iter().for_each(|p| merge_patch(state, p));
+                }
+                SeqCheck::Gap { .. } | SeqCheck::Discard => {}
+            }
+            check
+        }
+
+        // 首个批次为全量重置
+        let batch = manager.peek_session_batch("user123", "s1").await.unwrap();
+        assert_eq!(batch.prev_seq, None);
+        assert_eq!(batch.seq, Some(1));
+        assert_eq!(
+            deliver(&mut tracker, batch, &mut client_state),
+            SeqCheck::Reset
+        );
+
+        // 第二个批次在网络中丢失
+        manager
+            .push_patch("user123", json!({"balance": 200.0}))
+            .await;
+        let lost = manager.peek_session_batch("user123", "s1").await.unwrap();
+        assert_eq!((lost.prev_seq, lost.seq), (Some(1), Some(2)));
+
+        // 第三个批次到达时客户端检测到不连续
+        manager.push_patch("user123", json!({"frozen": 5.0})).await;
+        let batch = manager.peek_session_batch("user123", "s1").await.unwrap();
+        assert_eq!(
+            deliver(&mut tracker, batch, &mut client_state),
+            SeqCheck::Gap {
+                last_seq: 1,
+                prev_seq: 2
+            }
+        );
+        // 等待重新同步期间的后续批次被丢弃
+        manager
+            .push_patch("user123", json!({"available": 50.0}))
+            .await;
+        let batch = manager.peek_session_batch("user123", "s1").await.unwrap();
+        assert_eq!(
+            deliver(&mut tracker, batch, &mut client_state),
+            SeqCheck::Discard
+        );
+
+        // 历史可覆盖：从序号 1 之后补发
+        let outcome = manager.resync_session("user123", "s1", 1).unwrap();
+        assert_eq!(
+            outcome,
+            ResyncOutcome::Replayed {
+                from_seq: 1,
+                to_seq: 4,
+                count: 3
+            }
+        );
+        let batch = manager.peek_session_batch("user123", "s1").await.unwrap();
+        assert_eq!(
+            deliver(&mut tracker, batch, &mut client_state),
+            SeqCheck::Apply
+        );
+        assert_eq!(tracker.last_seq(), Some(4));
+        assert_eq!(client_state, manager.get_snapshot("user123").await.unwrap());
+
+        // 后续增量继续连续
+        manager
+            .push_patch("user123", json!({"balance": 300.0}))
+            .await;
+        let batch = manager.peek_session_batch("user123", "s1").await.unwrap();
+        assert_eq!(
+            deliver(&mut tracker, batch, &mut client_state),
+            SeqCheck::Apply
+        );
+        assert_eq!(client_state["balance"], 300.0);
+    }
+
+    #[tokio::test]
+    async fn test_resync_beyond_history_resets() {
+        let manager =
+            SnapshotManager::with_timeout(Duration::from_millis(200)).with_history_limit(2);
+        manager.register_session("user123", "s1");
+        for i in 0..5 {
+            manager.push_patch("user123", json!({"counter": i})).await;
+        }
+
+        // 客户端停在序号 1，历史只保留 4、5
+        let outcome = manager.resync_session("user123", "s1", 1).unwrap();
+        assert_eq!(outcome, ResyncOutcome::Reset { seq: 5 });
+        let batch = manager.peek_session_batch("user123", "s1").await.unwrap();
+        assert_eq!(batch.prev_seq, None);
+        assert_eq!(batch.seq, Some(5));
+        assert_eq!(batch.patches, vec![json!({"counter": 4})]);
+
+        // 重连协商：新会话携带上次确认的序号，历史覆盖时只补发增量
+        manager.register_session("user123", "s2");
+        let outcome = manager.resync_session("user123", "s2", 4).unwrap();
+        assert_eq!(
+            outcome,
+            ResyncOutcome::Replayed {
+                from_seq: 4,
+                to_seq: 5,
+                count: 1
+            }
+        );
+        let batch = manager.peek_session_batch("user123", "s2").await.unwrap();
+        assert_eq!((batch.prev_seq, batch.seq), (Some(4), Some(5)));
+
+        // 无基线或序号超前（用户快照已重建）一律全量重置
+        assert_eq!(
+            manager.resync_session("user123", "s2", 0),
+            Some(ResyncOutcome::Reset { seq: 5 })
+        );
+        assert_eq!(
+            manager.resync_session("user123", "s2", 9),
+            Some(ResyncOutcome::Reset { seq: 5 })
+        );
+        assert!(manager.resync_session("user123", "unknown", 1).is_none());
+    }
 }
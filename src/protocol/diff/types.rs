@@ -156,6 +156,12 @@ pub struct Quote {
     pub volume: i64,            // 成交量
     pub open_interest: i64,     // 持仓量
     pub pre_open_interest: i64, // 昨持仓量
+
+    /// 集合竞价（仅竞价阶段有值）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auction_price: Option<f64>, // 虚拟开盘价
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auction_volume: Option<f64>, // 虚拟匹配量
 }
 
 /// K线柱
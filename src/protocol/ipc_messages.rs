@@ -129,6 +129,9 @@ pub struct TradeReport {
     /// 时间戳（纳秒）
     pub timestamp: i64,
 
+    /// 撮合批次号（同一次撮合产生的成交相同）
+    pub match_batch_id: i64,
+
     /// 对手订单ID（用于调试）
     pub opposite_order_id: [u8; 32],
 
@@ -0,0 +1,462 @@
+//! 查询结果缓存
+//!
+//! @yutiansut @quantaxis
+//!
+//! 管理后台图表会以固定周期轮询相同的统计查询，缓存避免每次全量扫描：
+//! - Key：规范化后的查询参数哈希
+//! - TTL：按查询类型配置（统计类默认 5s，历史类默认 60s）
+//! - 失效：数据写入时按 instrument 粗粒度失效（未限定 instrument 的查询任意写入都会失效）
+//! - 代数：每次失效递增合约的失效代数，查询执行前取快照，写入缓存时代数已变化则丢弃结果
+//!   （查询期间发生写入，结果可能已过时）
+//! - 容量：超过上限按 LRU 淘汰
+
+use crate::observability::metrics::QUERY_CACHE_REQUESTS;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// 未限定 instrument 的查询使用的失效标签
+const ALL_INSTRUMENTS: &str = "*";
+
+/// 查询类型（决定缓存 TTL）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheQueryKind {
+    /// 统计类查询（聚合、时间序列）
+    Stats,
+    /// 历史明细查询
+    History,
+}
+
+/// 查询缓存配置
+#[derive(Debug, Clone)]
+pub struct QueryCacheConfig {
+    /// 是否启用缓存
+    pub enabled: bool,
+    /// 最大缓存条目数（超出按 LRU 淘汰）
+    pub capacity: usize,
+    /// 统计类查询 TTL
+    pub stats_ttl: Duration,
+    /// 历史类查询 TTL
+    pub history_ttl: Duration,
+}
+
+impl Default for QueryCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            capacity: 1024,
+            stats_ttl: Duration::from_secs(5),
+            history_ttl: Duration::from_secs(60),
+        }
+    }
+}
+
+impl QueryCacheConfig {
+    fn ttl(&self, kind: CacheQueryKind) -> Duration {
+        match kind {
+            CacheQueryKind::Stats => self.stats_ttl,
+            CacheQueryKind::History => self.history_ttl,
+        }
+    }
+}
+
+/// 缓存查询结果
+#[derive(Debug, Clone)]
+pub struct CachedResult<T> {
+    pub value: T,
+    /// 是否命中缓存
+    pub cache_hit: bool,
+    /// 数据截止时间（结果计算时刻，纳秒时间戳）
+    pub data_as_of: i64,
+}
+
+/// 缓存统计
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub hit_rate: f64,
+    pub entries: usize,
+    pub evictions: u64,
+    pub invalidations: u64,
+}
+
+struct CacheEntry<T> {
+    value: T,
+    data_as_of: i64,
+    expires_at: Instant,
+    instruments: Vec<String>,
+    /// LRU 访问序号
+    tick: u64,
+}
+
+struct CacheInner<T> {
+    entries: HashMap<u64, CacheEntry<T>>,
+    /// 访问序号 -> key（最小序号为最久未使用）
+    lru: BTreeMap<u64, u64>,
+    /// instrument -> key（按合约失效）
+    by_instrument: HashMap<String, HashSet<u64>>,
+    /// instrument -> 失效代数（含未限定合约标签）
+    generations: HashMap<String, u64>,
+    /// 清空次数（计入所有查询的代数）
+    clear_epoch: u64,
+    next_tick: u64,
+}
+
+impl<T> CacheInner<T> {
+    fn touch(&mut self, key: u64) {
+        let tick = self.next_tick;
+        self.next_tick += 1;
+        if let Some(entry) = self.entries.get_mut(&key) {
+            self.lru.remove(&entry.tick);
+            entry.tick = tick;
+            self.lru.insert(tick, key);
+        }
+    }
+
+    /// 查询涉及合约的失效代数之和（各代数只增不减，任一失效都会改变结果）
+    fn generation(&self, instruments: &[String]) -> u64 {
+        instruments
+            .iter()
+            .map(|instrument| self.generations.get(instrument).copied().unwrap_or(0))
+            .fold(self.clear_epoch, u64::wrapping_add)
+    }
+
+    fn remove(&mut self, key: u64) -> Option<CacheEntry<T>> {
+        let entry = self.entries.remove(&key)?;
+        self.lru.remove(&entry.tick);
+        for instrument in &entry.instruments {
+            if let Some(keys) = self.by_instrument.get_mut(instrument) {
+                keys.remove(&key);
+                if keys.is_empty() {
+                    self.by_instrument.remove(instrument);
+                }
+            }
+        }
+        Some(entry)
+    }
+}
+
+/// 查询结果缓存
+pub struct QueryCache<T: Clone> {
+    /// 指标标签（区分不同查询引擎）
+    name: &'static str,
+    config: QueryCacheConfig,
+    inner: Mutex<CacheInner<T>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+    invalidations: AtomicU64,
+}
+
+impl<T: Clone> QueryCache<T> {
+    pub fn new(name: &'static str, config: QueryCacheConfig) -> Self {
+        Self {
+            name,
+            config,
+            inner: Mutex::new(CacheInner {
+                entries: HashMap::new(),
+                lru: BTreeMap::new(),
+                by_instrument: HashMap::new(),
+                generations: HashMap::new(),
+                clear_epoch: 0,
+                next_tick: 0,
+            }),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+            invalidations: AtomicU64::new(0),
+        }
+    }
+
+    /// 计算规范化查询参数的缓存 key
+    pub fn key_of<K: Hash + ?Sized>(params: &K) -> u64 {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        params.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// 空合约列表表示未限定合约
+    fn instrument_tags(instruments: Vec<String>) -> Vec<String> {
+        if instruments.is_empty() {
+            vec![ALL_INSTRUMENTS.to_string()]
+        } else {
+            instruments
+        }
+    }
+
+    /// 查询执行前的失效代数快照，传给 [`QueryCache::insert`]
+    pub fn generation(&self, instruments: &[String]) -> u64 {
+        let tags = Self::instrument_tags(instruments.to_vec());
+        self.inner.lock().generation(&tags)
+    }
+
+    /// 查找未过期的缓存结果
+    pub fn get(&self, key: u64) -> Option<CachedResult<T>> {
+        if !self.config.enabled {
+            return None;
+        }
+
+        let mut inner = self.inner.lock();
+        let hit = match inner.entries.get(&key) {
+            Some(entry) if entry.expires_at > Instant::now() => {
+                Some((entry.value.clone(), entry.data_as_of))
+            }
+            Some(_) => {
+                inner.remove(key);
+                None
+            }
+            None => None,
+        };
+
+        match hit {
+            Some((value, data_as_of)) => {
+                inner.touch(key);
+                drop(inner);
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                QUERY_CACHE_REQUESTS
+                    .with_label_values(&[self.name, "hit"])
+                    .inc();
+                Some(CachedResult {
+                    value,
+                    cache_hit: true,
+                    data_as_of,
+                })
+            }
+            None => {
+                drop(inner);
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                QUERY_CACHE_REQUESTS
+                    .with_label_values(&[self.name, "miss"])
+                    .inc();
+                None
+            }
+        }
+    }
+
+    /// 写入查询结果
+    ///
+    /// `instruments` 为查询涉及的合约，空表示未限定（任意写入都会使其失效）；
+    /// `generation` 为查询执行前 [`QueryCache::generation`] 的快照，其后发生过失效时结果不缓存
+    pub fn insert(
+        &self,
+        key: u64,
+        kind: CacheQueryKind,
+        instruments: Vec<String>,
+        generation: u64,
+        value: T,
+    ) -> CachedResult<T> {
+        let data_as_of = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
+        let result = CachedResult {
+            value: value.clone(),
+            cache_hit: false,
+            data_as_of,
+        };
+        if !self.config.enabled || self.config.capacity == 0 {
+            return result;
+        }
+
+        let instruments = Self::instrument_tags(instruments);
+
+        let mut inner = self.inner.lock();
+        if inner.generation(&instruments) != generation {
+            return result;
+        }
+        inner.remove(key);
+        while inner.entries.len() >= self.config.capacity {
+            let Some((_, oldest)) = inner.lru.pop_first() else {
+                break;
+            };
+            inner.remove(oldest);
+            self.evictions.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let tick = inner.next_tick;
+        inner.next_tick += 1;
+        inner.lru.insert(tick, key);
+        for instrument in &instruments {
+            inner
+                .by_instrument
+                .entry(instrument.clone())
+                .or_default()
+                .insert(key);
+        }
+        inner.entries.insert(
+            key,
+            CacheEntry {
+                value,
+                data_as_of,
+                expires_at: Instant::now() + self.config.ttl(kind),
+                instruments,
+                tick,
+            },
+        );
+
+        result
+    }
+
+    /// 合约数据写入后失效相关缓存（含未限定合约的查询）
+    pub fn invalidate_instrument(&self, instrument_id: &str) {
+        let mut inner = self.inner.lock();
+        for tag in [instrument_id, ALL_INSTRUMENTS] {
+            *inner.generations.entry(tag.to_string()).or_insert(0) += 1;
+        }
+        if inner.entries.is_empty() {
+            return;
+        }
+        let mut stale: HashSet<u64> = HashSet::new();
+        for tag in [instrument_id, ALL_INSTRUMENTS] {
+            if let Some(keys) = inner.by_instrument.get(tag) {
+                stale.extend(keys.iter().copied());
+            }
+        }
+        for key in &stale {
+            inner.remove(*key);
+        }
+        self.invalidations
+            .fetch_add(stale.len() as u64, Ordering::Relaxed);
+    }
+
+    /// 清空缓存
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock();
+        let count = inner.entries.len() as u64;
+        inner.clear_epoch += 1;
+        inner.entries.clear();
+        inner.lru.clear();
+        inner.by_instrument.clear();
+        self.invalidations.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// 缓存统计
+    pub fn stats(&self) -> QueryCacheStats {
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        let total = hits + misses;
+        QueryCacheStats {
+            hits,
+            misses,
+            hit_rate: if total == 0 {
+                0.0
+            } else {
+                hits as f64 / total as f64
+            },
+            entries: self.inner.lock().entries.len(),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            invalidations: self.invalidations.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(capacity: usize) -> QueryCache<u32> {
+        QueryCache::new(
+            "test",
+            QueryCacheConfig {
+                capacity,
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn test_hit_and_invalidate_by_instrument() {
+        let cache = cache(16);
+        let k1 = QueryCache::<u32>::key_of("IF2501|0|100");
+        let k2 = QueryCache::<u32>::key_of("IC2501|0|100");
+        let k_all = QueryCache::<u32>::key_of("sql:select count(*) from data");
+
+        assert!(cache.get(k1).is_none());
+        let first = cache.insert(k1, CacheQueryKind::Stats, vec!["IF2501".into()], 0, 1);
+        assert!(!first.cache_hit);
+        cache.insert(k2, CacheQueryKind::Stats, vec!["IC2501".into()], 0, 2);
+        cache.insert(k_all, CacheQueryKind::History, Vec::new(), 0, 3);
+
+        let hit = cache.get(k1).unwrap();
+        assert!(hit.cache_hit);
+        assert_eq!(hit.value, 1);
+        assert_eq!(hit.data_as_of, first.data_as_of);
+
+        // IF2501 写入：失效 IF2501 与未限定合约的查询
+        cache.invalidate_instrument("IF2501");
+        assert!(cache.get(k1).is_none());
+        assert!(cache.get(k_all).is_none());
+        assert_eq!(cache.get(k2).unwrap().value, 2);
+
+        let stats = cache.stats();
+        assert_eq!(stats.hits, 2);
+        assert_eq!(stats.misses, 3);
+        assert_eq!(stats.invalidations, 2);
+    }
+
+    #[test]
+    fn test_insert_dropped_after_concurrent_invalidation() {
+        let cache = cache(16);
+        let instruments = vec!["IF2501".to_string()];
+
+        // 查询开始后 IF2501 写入：结果照常返回但不缓存
+        let generation = cache.generation(&instruments);
+        cache.invalidate_instrument("IF2501");
+        let result = cache.insert(1, CacheQueryKind::Stats, instruments.clone(), generation, 1);
+        assert_eq!(result.value, 1);
+        assert!(cache.get(1).is_none());
+
+        // 未限定合约的查询：任意合约写入都使快照失效
+        let generation = cache.generation(&[]);
+        cache.invalidate_instrument("IC2501");
+        cache.insert(2, CacheQueryKind::Stats, Vec::new(), generation, 2);
+        assert!(cache.get(2).is_none());
+
+        // 其他合约写入不影响
+        let generation = cache.generation(&instruments);
+        cache.invalidate_instrument("IC2501");
+        cache.insert(3, CacheQueryKind::Stats, instruments.clone(), generation, 3);
+        assert_eq!(cache.get(3).unwrap().value, 3);
+
+        let generation = cache.generation(&instruments);
+        cache.clear();
+        cache.insert(4, CacheQueryKind::Stats, instruments, generation, 4);
+        assert!(cache.get(4).is_none());
+    }
+
+    #[test]
+    fn test_ttl_by_kind() {
+        let cache = QueryCache::new(
+            "test",
+            QueryCacheConfig {
+                stats_ttl: Duration::from_millis(0),
+                history_ttl: Duration::from_secs(60),
+                ..Default::default()
+            },
+        );
+        cache.insert(1, CacheQueryKind::Stats, Vec::new(), 0, 1u32);
+        cache.insert(2, CacheQueryKind::History, Vec::new(), 0, 2u32);
+        assert!(cache.get(1).is_none());
+        assert!(cache.get(2).is_some());
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        let cache = cache(2);
+        cache.insert(1, CacheQueryKind::History, Vec::new(), 0, 1);
+        cache.insert(2, CacheQueryKind::History, Vec::new(), 0, 2);
+        // 访问 1，使 2 成为最久未使用
+        assert!(cache.get(1).is_some());
+        cache.insert(3, CacheQueryKind::History, Vec::new(), 0, 3);
+
+        assert!(cache.get(2).is_none());
+        assert!(cache.get(1).is_some());
+        assert!(cache.get(3).is_some());
+        assert_eq!(cache.stats().evictions, 1);
+    }
+}
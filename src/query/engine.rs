@@ -1,11 +1,16 @@
 // 查询引擎 - 基于 Polars DataFrame
 
+use super::cache::{CacheQueryKind, QueryCache, QueryCacheConfig, QueryCacheStats};
 use super::scanner::SSTableScanner;
 use super::types::*;
+use crate::storage::conversion::factor::FACTOR_TABLE;
+use crate::storage::conversion::kline::{list_kline_parquet_files, KLINE_TABLE};
+use crate::storage::conversion::partition::{prune_partitions, PrunedFiles};
+use crate::utils::timestamp::secs_to_nanos;
 use polars::io::SerWriter;
 use polars::prelude::*;
 use polars::sql::SQLContext;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// 查询引擎
 ///
@@ -14,48 +19,215 @@ use std::path::Path;
 /// - 结构化查询
 /// - 时间序列查询
 /// - 聚合分析
+/// - 查询结果缓存（统计类 / 历史类分别 TTL）
+/// - 按月分区的 OLAP 目录按查询时间范围裁剪分区
 pub struct QueryEngine {
     /// SSTable 扫描器
     scanner: SSTableScanner,
+
+    /// 查询结果缓存
+    cache: QueryCache<QueryResponse>,
+
+    /// K线 OLAP 目录（注册为 `klines` 表，查询时扫描新转换的文件）
+    kline_dirs: Vec<PathBuf>,
+
+    /// 因子 OLAP 目录（注册为 `factors` 表）
+    factor_dirs: Vec<PathBuf>,
+
+    /// 按月分区的合约 OLAP 目录（{instrument}/olap，并入 `data` 表，查询时扫描新分区）
+    olap_dirs: Vec<PathBuf>,
 }
 
 impl QueryEngine {
     /// 创建新的查询引擎
     pub fn new() -> Self {
+        Self::with_cache_config(QueryCacheConfig::default())
+    }
+
+    /// 使用指定缓存配置创建查询引擎
+    pub fn with_cache_config(cache: QueryCacheConfig) -> Self {
         Self {
             scanner: SSTableScanner::new(),
+            cache: QueryCache::new("query_engine", cache),
+            kline_dirs: Vec::new(),
+            factor_dirs: Vec::new(),
+            olap_dirs: Vec::new(),
         }
     }
 
-    /// 添加数据目录
+    /// 添加数据目录（数据集变化，清空缓存）
     pub fn add_data_dir<P: AsRef<Path>>(&mut self, dir: P) -> Result<(), String> {
+        self.cache.clear();
         self.scanner.scan_directory(dir)
     }
 
-    /// 添加 Parquet 文件
+    /// 添加 Parquet 文件（数据集变化，清空缓存）
     pub fn add_parquet_file<P: AsRef<Path>>(&mut self, path: P) {
+        self.cache.clear();
         self.scanner.add_olap_sstable(path);
     }
 
-    /// 执行查询
+    /// 添加 K线 OLAP 目录（数据集变化，清空缓存）
+    pub fn add_kline_dir<P: AsRef<Path>>(&mut self, dir: P) {
+        self.cache.clear();
+        self.kline_dirs.push(dir.as_ref().to_path_buf());
+    }
+
+    /// 添加因子 OLAP 目录（数据集变化，清空缓存）
+    pub fn add_factor_dir<P: AsRef<Path>>(&mut self, dir: P) {
+        self.cache.clear();
+        self.factor_dirs.push(dir.as_ref().to_path_buf());
+    }
+
+    /// 添加按月分区的合约 OLAP 目录（数据集变化，清空缓存）
+    pub fn add_olap_dir<P: AsRef<Path>>(&mut self, dir: P) {
+        self.cache.clear();
+        self.olap_dirs.push(dir.as_ref().to_path_buf());
+    }
+
+    /// `data` 表需要扫描的文件：已登记的 Parquet + 按时间范围裁剪后的分区文件
+    fn data_files(&self, time_range: Option<&TimeRange>) -> PrunedFiles {
+        let mut result = PrunedFiles {
+            files: self.scanner.get_parquet_paths(),
+            ..Default::default()
+        };
+        let range = time_range.map(|r| (r.start, r.end));
+        for dir in &self.olap_dirs {
+            let pruned = prune_partitions(dir, range);
+            result.files.extend(pruned.files);
+            result.cold_partitions.extend(pruned.cold_partitions);
+            result.pruned_partitions += pruned.pruned_partitions;
+        }
+        if result.pruned_partitions > 0 {
+            log::debug!(
+                "Query pruned {} OLAP partitions, scanning {} files",
+                result.pruned_partitions,
+                result.files.len()
+            );
+        }
+        result
+    }
+
+    /// 合约有新数据写入时失效相关查询缓存
+    pub fn invalidate_instrument(&self, instrument_id: &str) {
+        self.cache.invalidate_instrument(instrument_id);
+    }
+
+    /// 查询缓存统计
+    pub fn cache_stats(&self) -> QueryCacheStats {
+        self.cache.stats()
+    }
+
+    /// 执行查询（相同参数在 TTL 内直接返回缓存结果）
     pub fn execute(&self, request: QueryRequest) -> Result<QueryResponse, String> {
+        let cache_key = self.cache_key(&request);
+        if let Some(key) = cache_key {
+            if let Some(cached) = self.cache.get(key) {
+                return Ok(QueryResponse {
+                    cache_hit: true,
+                    data_as_of: cached.data_as_of,
+                    elapsed_ms: 0,
+                    ..cached.value
+                });
+            }
+        }
+
+        let kind = Self::cache_kind(&request);
+        let instruments = Self::request_instruments(&request);
+        // 查询执行前取失效代数，执行期间有写入时结果不入缓存
+        let generation = self.cache.generation(&instruments);
+        let response = self.execute_uncached(request)?;
+
+        match cache_key {
+            Some(key) => {
+                let cached = self
+                    .cache
+                    .insert(key, kind, instruments, generation, response);
+                Ok(QueryResponse {
+                    data_as_of: cached.data_as_of,
+                    ..cached.value
+                })
+            }
+            None => Ok(response),
+        }
+    }
+
+    /// 规范化查询参数后计算缓存 key（SQL 空白折叠、过滤条件按列排序）
+    fn cache_key(&self, request: &QueryRequest) -> Option<u64> {
+        if !self.cache.is_enabled() {
+            return None;
+        }
+        let mut normalized = request.clone();
+        if let QueryType::Sql { query } = &mut normalized.query_type {
+            *query = query.split_whitespace().collect::<Vec<_>>().join(" ");
+        }
+        if let Some(filters) = normalized.filters.as_mut() {
+            filters.sort_by(|a, b| a.column.cmp(&b.column));
+        }
+        let params = serde_json::to_string(&normalized).ok()?;
+        Some(QueryCache::<QueryResponse>::key_of(&params))
+    }
+
+    /// 统计类（聚合、时间序列）与历史明细查询使用不同 TTL
+    fn cache_kind(request: &QueryRequest) -> CacheQueryKind {
+        let has_aggregations = request
+            .aggregations
+            .as_ref()
+            .is_some_and(|aggs| !aggs.is_empty());
+        if has_aggregations || matches!(request.query_type, QueryType::TimeSeries { .. }) {
+            CacheQueryKind::Stats
+        } else {
+            CacheQueryKind::History
+        }
+    }
+
+    /// 查询限定的合约（来自 instrument_id 过滤条件，空表示未限定）
+    fn request_instruments(request: &QueryRequest) -> Vec<String> {
+        let mut instruments = Vec::new();
+        for filter in request.filters.iter().flatten() {
+            if filter.column != "instrument_id" {
+                continue;
+            }
+            match (&filter.op, &filter.value) {
+                (FilterOp::Eq, FilterValue::String(id)) => instruments.push(id.clone()),
+                (FilterOp::In, FilterValue::StringList(ids)) => {
+                    instruments.extend(ids.iter().cloned())
+                }
+                // 其它条件无法确定合约范围，按未限定处理
+                _ => return Vec::new(),
+            }
+        }
+        instruments
+    }
+
+    fn execute_uncached(&self, request: QueryRequest) -> Result<QueryResponse, String> {
         let start = std::time::Instant::now();
 
+        // SQL 的时间条件在语句中，不做分区裁剪
+        let data = match &request.query_type {
+            QueryType::Sql { .. } => self.data_files(None),
+            _ => self.data_files(request.time_range.as_ref()),
+        };
+
         let df = match &request.query_type {
-            QueryType::Sql { query } => self.execute_sql(query)?,
+            QueryType::Sql { query } => self.execute_sql_on(query, &data.files)?,
             QueryType::Structured { select, from } => {
-                self.execute_structured(select, from, &request)?
+                self.execute_structured(select, from, &data.files, &request)?
             }
             QueryType::TimeSeries {
                 metrics,
                 dimensions,
                 granularity,
-            } => self.execute_timeseries(metrics, dimensions, *granularity, &request)?,
+            } => {
+                self.execute_timeseries(metrics, dimensions, *granularity, &data.files, &request)?
+            }
         };
 
         let elapsed_ms = start.elapsed().as_millis() as u64;
 
-        self.dataframe_to_response(df, elapsed_ms)
+        let mut response = self.dataframe_to_response(df, elapsed_ms)?;
+        response.cold_partitions = data.cold_partitions;
+        Ok(response)
     }
 
     /// 执行 SQL 查询并返回 DataFrame
@@ -70,26 +242,59 @@ impl QueryEngine {
         self.execute_sql(query)
     }
 
-    /// 执行 SQL 查询 (内部实现)
+    /// 执行 SQL 查询 (内部实现，`data` 表包含全部分区)
     fn execute_sql(&self, query: &str) -> Result<DataFrame, String> {
-        // 获取 Parquet 文件路径
-        let parquet_paths = self.scanner.get_parquet_paths();
+        let data = self.data_files(None);
+        self.execute_sql_on(query, &data.files)
+    }
 
-        if parquet_paths.is_empty() {
+    /// 在指定 `data` 文件集上执行 SQL
+    ///
+    /// 注册的表：`data`（OLTP 记录转换的 Parquet）、`klines`（K线 Parquet）、`factors`（因子 Parquet）
+    fn execute_sql_on(&self, query: &str, parquet_paths: &[PathBuf]) -> Result<DataFrame, String> {
+        let kline_paths: Vec<PathBuf> = self
+            .kline_dirs
+            .iter()
+            .flat_map(|dir| list_kline_parquet_files(dir))
+            .collect();
+        let factor_paths: Vec<PathBuf> = self
+            .factor_dirs
+            .iter()
+            .flat_map(|dir| list_kline_parquet_files(dir))
+            .collect();
+
+        if parquet_paths.is_empty() && kline_paths.is_empty() && factor_paths.is_empty() {
             return Err("No data files found".to_string());
         }
 
         // 使用 Polars LazyFrame + SQL
-        // 读取第一个 Parquet 文件作为表
-        let df = LazyFrame::scan_parquet(
-            PlPath::new(parquet_paths[0].to_str().unwrap()),
+        let mut ctx = SQLContext::new();
+        if !parquet_paths.is_empty() {
+            ctx.register("data", Self::scan_parquet_files(parquet_paths)?);
+        }
+        if !kline_paths.is_empty() {
+            ctx.register(KLINE_TABLE, Self::scan_parquet_files(&kline_paths)?);
+        }
+        if !factor_paths.is_empty() {
+            ctx.register(FACTOR_TABLE, Self::scan_parquet_files(&factor_paths)?);
+        }
+
+        // 执行 SQL
+        ctx.execute(query)
+            .map_err(|e| format!("SQL execution failed: {}", e))?
+            .collect()
+            .map_err(|e| format!("Collect failed: {}", e))
+    }
+
+    /// 扫描多个 Parquet 文件并合并为一张表
+    fn scan_parquet_files(paths: &[PathBuf]) -> Result<LazyFrame, String> {
+        let mut df = LazyFrame::scan_parquet(
+            PlPath::new(paths[0].to_str().unwrap()),
             ScanArgsParquet::default(),
         )
         .map_err(|e| format!("Scan parquet failed: {}", e))?;
 
-        // 如果有多个文件，合并它们
-        let mut df = df;
-        for path in &parquet_paths[1..] {
+        for path in &paths[1..] {
             let other = LazyFrame::scan_parquet(
                 PlPath::new(path.to_str().unwrap()),
                 ScanArgsParquet::default(),
@@ -99,16 +304,7 @@ impl QueryEngine {
             df = concat(vec![df, other], UnionArgs::default())
                 .map_err(|e| format!("Concat failed: {}", e))?;
         }
-
-        // 注册表
-        let mut ctx = SQLContext::new();
-        ctx.register("data", df);
-
-        // 执行 SQL
-        ctx.execute(query)
-            .map_err(|e| format!("SQL execution failed: {}", e))?
-            .collect()
-            .map_err(|e| format!("Collect failed: {}", e))
+        Ok(df)
     }
 
     /// 执行结构化查询
@@ -116,10 +312,9 @@ impl QueryEngine {
         &self,
         select: &[String],
         _from: &str,
+        parquet_paths: &[PathBuf],
         request: &QueryRequest,
     ) -> Result<DataFrame, String> {
-        let parquet_paths = self.scanner.get_parquet_paths();
-
         if parquet_paths.is_empty() {
             return Err("No data files found".to_string());
         }
@@ -198,10 +393,9 @@ impl QueryEngine {
         metrics: &[String],
         dimensions: &[String],
         granularity: Option<i64>,
+        parquet_paths: &[PathBuf],
         request: &QueryRequest,
     ) -> Result<DataFrame, String> {
-        let parquet_paths = self.scanner.get_parquet_paths();
-
         if parquet_paths.is_empty() {
             return Err("No data files found".to_string());
         }
@@ -235,7 +429,7 @@ impl QueryEngine {
 
         // 时间粒度聚合
         if let Some(granularity_secs) = granularity {
-            let granularity_ns = granularity_secs * 1_000_000_000;
+            let granularity_ns = secs_to_nanos(granularity_secs);
             df = df.with_column(
                 (col("timestamp") / lit(granularity_ns) * lit(granularity_ns)).alias("time_bucket"),
             );
@@ -362,6 +556,9 @@ impl QueryEngine {
             data,
             row_count,
             elapsed_ms,
+            cache_hit: false,
+            data_as_of: chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0),
+            cold_partitions: Vec::new(),
         })
     }
 }
@@ -483,4 +680,126 @@ mod tests {
         assert!(response.columns.contains(&"total_count".to_string()));
         assert!(response.columns.contains(&"avg_price".to_string()));
     }
+
+    #[test]
+    fn test_query_engine_cache_hit_and_invalidate() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let file_path = create_test_data(&tmp_dir);
+
+        let mut engine = QueryEngine::new();
+        engine.add_parquet_file(&file_path);
+
+        let sql = |query: &str| QueryRequest {
+            query_type: QueryType::Sql {
+                query: query.to_string(),
+            },
+            time_range: None,
+            filters: None,
+            aggregations: None,
+            order_by: None,
+            limit: None,
+        };
+
+        let first = engine
+            .execute(sql("SELECT COUNT(*) AS cnt FROM data"))
+            .unwrap();
+        assert!(!first.cache_hit);
+
+        // 空白差异规范化后命中同一缓存
+        let second = engine
+            .execute(sql("SELECT  COUNT(*) AS cnt\n FROM data"))
+            .unwrap();
+        assert!(second.cache_hit);
+        assert_eq!(second.data_as_of, first.data_as_of);
+        assert_eq!(second.row_count, first.row_count);
+
+        // 未限定合约的查询在任意合约写入后失效
+        engine.invalidate_instrument("IF2501");
+        let third = engine
+            .execute(sql("SELECT COUNT(*) AS cnt FROM data"))
+            .unwrap();
+        assert!(!third.cache_hit);
+
+        let stats = engine.cache_stats();
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 2);
+    }
+
+    #[test]
+    fn test_query_engine_prunes_month_partitions() {
+        use crate::storage::conversion::partition::{month_of, COLD_MARKER};
+        use chrono::TimeZone;
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let olap_dir = tmp_dir.path().join("IF2502").join("olap");
+        let feb_start = chrono::FixedOffset::east_opt(8 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2025, 2, 1, 0, 0, 0)
+            .unwrap()
+            .timestamp_nanos_opt()
+            .unwrap();
+
+        // 跨月边界的 10 条记录按月份写入分区（前 5 条属于 1 月）
+        for (month, range) in [("202501", 0..5i64), ("202502", 5..10i64)] {
+            let records: Vec<(MemTableKey, WalRecord)> = range
+                .map(|i| {
+                    let timestamp = feb_start - 5 + i;
+                    assert_eq!(month_of(timestamp), month);
+                    let record = WalRecord::OrderInsert {
+                        order_id: i as u64,
+                        user_id: [1u8; 32],
+                        instrument_id: [2u8; 16],
+                        direction: 0,
+                        offset: 0,
+                        price: 100.0 + i as f64,
+                        volume: 10.0,
+                        timestamp,
+                    };
+                    (MemTableKey::new(timestamp, i as u64), record)
+                })
+                .collect();
+            let memtable = OlapMemTable::from_records(records);
+            let mut writer = ParquetSSTableWriter::create(
+                olap_dir.join(month).join("batch_1.parquet"),
+                Arc::new(create_olap_schema()),
+            )
+            .unwrap();
+            writer.write_chunk(memtable.chunk()).unwrap();
+            writer.finish().unwrap();
+        }
+        std::fs::write(olap_dir.join("202501").join(COLD_MARKER), "{}").unwrap();
+
+        let mut engine = QueryEngine::new();
+        engine.add_olap_dir(&olap_dir);
+
+        let request = |start: i64, end: i64| QueryRequest {
+            query_type: QueryType::Structured {
+                select: vec!["timestamp".to_string()],
+                from: "data".to_string(),
+            },
+            time_range: Some(TimeRange { start, end }),
+            filters: None,
+            aggregations: None,
+            order_by: None,
+            limit: None,
+        };
+
+        // 只查 2 月：1 月分区被裁剪，不带 cold 提示
+        let feb = engine
+            .execute(request(feb_start, feb_start + 1_000))
+            .unwrap();
+        assert_eq!(feb.row_count, 5);
+        assert!(feb.cold_partitions.is_empty());
+
+        // 跨月查询：两个分区都扫描，结果正确并提示命中 cold 分区
+        let across = engine
+            .execute(request(feb_start - 3, feb_start + 1))
+            .unwrap();
+        assert_eq!(across.row_count, 5);
+        assert_eq!(across.cold_partitions, vec!["IF2502/202501".to_string()]);
+
+        // SQL 不裁剪分区
+        let all = engine.sql("SELECT timestamp FROM data").unwrap();
+        assert_eq!(all.height(), 10);
+    }
 }
@@ -71,6 +71,10 @@ pub struct QueryResult {
     pub source: DataSource,
     pub execution_time: Duration,
     pub is_complete: bool,
+    /// 是否命中查询结果缓存
+    pub cache_hit: bool,
+    /// 数据截止时间（纳秒时间戳，命中缓存时为缓存结果的计算时刻）
+    pub data_as_of: i64,
 }
 
 /// 数据来源
@@ -241,6 +245,10 @@ pub enum AggregateOp {
 #[derive(Debug, Clone, Default)]
 pub struct AggregateResult {
     pub values: HashMap<String, f64>,
+    /// 是否命中查询结果缓存
+    pub cache_hit: bool,
+    /// 数据截止时间（纳秒时间戳）
+    pub data_as_of: i64,
 }
 
 // ═══════════════════════════════════════════════════════════════════════════
@@ -385,6 +393,8 @@ impl HybridQueryEngine {
             source: DataSource::Merged,
             execution_time: start_time.elapsed(),
             is_complete: true,
+            cache_hit: false,
+            data_as_of: chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0),
         })
     }
 
@@ -20,6 +20,7 @@
 //
 // @yutiansut @quantaxis
 
+pub mod cache;
 pub mod engine;
 pub mod hybrid;
 pub mod router;
@@ -27,6 +28,7 @@ pub mod scanner;
 pub mod types;
 pub mod unified;
 
+pub use cache::{CacheQueryKind, CachedResult, QueryCache, QueryCacheConfig, QueryCacheStats};
 pub use engine::QueryEngine;
 pub use scanner::SSTableScanner;
 
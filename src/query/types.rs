@@ -141,6 +141,18 @@ pub struct QueryResponse {
 
     /// 查询耗时（毫秒）
     pub elapsed_ms: u64,
+
+    /// 是否命中查询缓存
+    #[serde(default)]
+    pub cache_hit: bool,
+
+    /// 数据截止时间（结果计算时刻，纳秒时间戳）
+    #[serde(default)]
+    pub data_as_of: i64,
+
+    /// 查询命中的 cold 分区（{instrument}/{yyyymm}，再压缩的历史数据，读取较慢）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cold_partitions: Vec<String>,
 }
 
 /// 聚合结果
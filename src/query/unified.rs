@@ -25,8 +25,12 @@
 // 3. 历史数据 (> olap_cutoff): OLAP Parquet
 // 4. 跨边界查询: 自动合并多个数据源
 //
+// 查询结果缓存：相同参数的 query/aggregate 在 TTL 内直接返回缓存结果，
+// push_stream / invalidate_instrument 按合约失效
+//
 // @yutiansut @quantaxis
 
+use crate::query::cache::{CacheQueryKind, QueryCache, QueryCacheConfig, QueryCacheStats};
 use crate::query::engine::QueryEngine;
 use crate::query::hybrid::{
     AggregateOp, AggregateResult, Aggregation, BatchDataSource, BatchQueryError, HybridConfig,
@@ -34,6 +38,7 @@ use crate::query::hybrid::{
 };
 use crate::storage::hybrid::batch_source::OltpBatchAdapter;
 use crate::storage::hybrid::oltp::OltpHybridStorage;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -53,8 +58,8 @@ pub struct UnifiedQueryConfig {
     /// 是否启用 SQL 查询
     pub enable_sql: bool,
 
-    /// 查询结果缓存时间（毫秒）
-    pub cache_ttl_ms: u64,
+    /// 查询结果缓存配置
+    pub cache: QueryCacheConfig,
 }
 
 impl Default for UnifiedQueryConfig {
@@ -64,7 +69,7 @@ impl Default for UnifiedQueryConfig {
             stream_window_seconds: 60, // 1 分钟内优先使用流数据
             batch_timeout: Duration::from_secs(30),
             enable_sql: true,
-            cache_ttl_ms: 100, // 100ms 缓存
+            cache: QueryCacheConfig::default(), // 统计类 5s / 历史类 60s
         }
     }
 }
@@ -86,6 +91,15 @@ pub struct UnifiedQueryEngine {
     /// SQL 查询引擎
     sql_engine: Option<QueryEngine>,
 
+    /// 明细查询结果缓存
+    query_cache: QueryCache<QueryResult>,
+
+    /// 聚合查询结果缓存
+    aggregate_cache: QueryCache<AggregateResult>,
+
+    /// 批数据源（OLTP/OLAP）扫描次数
+    batch_scans: Arc<AtomicU64>,
+
     /// 配置
     config: UnifiedQueryConfig,
 }
@@ -108,6 +122,9 @@ impl UnifiedQueryEngine {
             } else {
                 None
             },
+            query_cache: QueryCache::new("unified_query", config.cache.clone()),
+            aggregate_cache: QueryCache::new("unified_aggregate", config.cache.clone()),
+            batch_scans: Arc::new(AtomicU64::new(0)),
             config,
         }
     }
@@ -120,7 +137,10 @@ impl UnifiedQueryEngine {
         let adapter = OltpBatchAdapter::new_with_olap(storage);
 
         // 将适配器设置为混合引擎的批数据源
-        let boxed: Arc<dyn BatchDataSource> = Arc::new(BatchAdapterWrapper(adapter.clone()));
+        let boxed: Arc<dyn BatchDataSource> = Arc::new(BatchAdapterWrapper {
+            adapter: adapter.clone(),
+            scans: self.batch_scans.clone(),
+        });
         self.hybrid_engine = self.hybrid_engine.with_batch_source(boxed);
         self.batch_adapter = Some(adapter);
 
@@ -132,9 +152,17 @@ impl UnifiedQueryEngine {
         self.hybrid_engine.stream_buffer()
     }
 
-    /// 推送实时数据到流缓存
+    /// 推送实时数据到流缓存（同时失效该合约的查询缓存）
     pub fn push_stream(&self, record: Record) {
+        let key = record.key.clone();
         self.hybrid_engine.push_stream(record);
+        self.invalidate_instrument(&key);
+    }
+
+    /// 合约有新数据写入时失效相关查询缓存
+    pub fn invalidate_instrument(&self, instrument_id: &str) {
+        self.query_cache.invalidate_instrument(instrument_id);
+        self.aggregate_cache.invalidate_instrument(instrument_id);
     }
 
     /// 执行时间范围查询
@@ -156,10 +184,40 @@ impl UnifiedQueryEngine {
         end_ts: i64,
         fields: &[String],
     ) -> Result<QueryResult, UnifiedQueryError> {
-        self.hybrid_engine
+        // 规范化：字段去重排序
+        let mut normalized_fields: Vec<&str> = fields.iter().map(|f| f.as_str()).collect();
+        normalized_fields.sort_unstable();
+        normalized_fields.dedup();
+        let cache_key =
+            QueryCache::<QueryResult>::key_of(&("query", key, start_ts, end_ts, normalized_fields));
+
+        if let Some(cached) = self.query_cache.get(cache_key) {
+            return Ok(QueryResult {
+                cache_hit: true,
+                data_as_of: cached.data_as_of,
+                ..cached.value
+            });
+        }
+
+        let instruments = vec![key.to_string()];
+        let generation = self.query_cache.generation(&instruments);
+        let result = self
+            .hybrid_engine
             .query(key, start_ts, end_ts, fields)
             .await
-            .map_err(|e| UnifiedQueryError::HybridError(format!("{}", e)))
+            .map_err(|e| UnifiedQueryError::HybridError(format!("{}", e)))?;
+        let cached = self.query_cache.insert(
+            cache_key,
+            CacheQueryKind::History,
+            instruments,
+            generation,
+            result,
+        );
+
+        Ok(QueryResult {
+            data_as_of: cached.data_as_of,
+            ..cached.value
+        })
     }
 
     /// 执行聚合查询
@@ -177,10 +235,47 @@ impl UnifiedQueryEngine {
             .as_ref()
             .ok_or_else(|| UnifiedQueryError::NotConfigured("Storage not configured".to_string()))?;
 
-        adapter
+        // 规范化：聚合项按 (字段, 操作, 别名) 排序
+        let mut normalized_aggs: Vec<(&str, String, &str)> = aggregations
+            .iter()
+            .map(|a| (a.field.as_str(), format!("{:?}", a.op), a.alias.as_str()))
+            .collect();
+        normalized_aggs.sort();
+        let cache_key = QueryCache::<AggregateResult>::key_of(&(
+            "aggregate",
+            key,
+            start_ts,
+            end_ts,
+            normalized_aggs,
+        ));
+
+        if let Some(cached) = self.aggregate_cache.get(cache_key) {
+            return Ok(AggregateResult {
+                cache_hit: true,
+                data_as_of: cached.data_as_of,
+                ..cached.value
+            });
+        }
+
+        self.batch_scans.fetch_add(1, Ordering::Relaxed);
+        let instruments = vec![key.to_string()];
+        let generation = self.aggregate_cache.generation(&instruments);
+        let result = adapter
             .aggregate(key, start_ts, end_ts, aggregations)
             .await
-            .map_err(|e| UnifiedQueryError::BatchError(format!("{}", e)))
+            .map_err(|e| UnifiedQueryError::BatchError(format!("{}", e)))?;
+        let cached = self.aggregate_cache.insert(
+            cache_key,
+            CacheQueryKind::Stats,
+            instruments,
+            generation,
+            result,
+        );
+
+        Ok(AggregateResult {
+            data_as_of: cached.data_as_of,
+            ..cached.value
+        })
     }
 
     /// 执行 SQL 查询
@@ -216,6 +311,11 @@ impl UnifiedQueryEngine {
         }
     }
 
+    /// 查询缓存统计（明细查询, 聚合查询）
+    pub fn cache_stats(&self) -> (QueryCacheStats, QueryCacheStats) {
+        (self.query_cache.stats(), self.aggregate_cache.stats())
+    }
+
     /// 获取统计信息
     pub fn stats(&self) -> UnifiedQueryStats {
         let stream_buffer = self.hybrid_engine.stream_buffer();
@@ -225,6 +325,9 @@ impl UnifiedQueryEngine {
             stream_buffer_keys: stream_buffer.keys().len(),
             has_oltp_olap: self.batch_adapter.is_some(),
             sql_enabled: self.sql_engine.is_some(),
+            batch_scans: self.batch_scans.load(Ordering::Relaxed),
+            query_cache: self.query_cache.stats(),
+            aggregate_cache: self.aggregate_cache.stats(),
         }
     }
 }
@@ -260,12 +363,19 @@ pub struct UnifiedQueryStats {
     pub stream_buffer_keys: usize,
     pub has_oltp_olap: bool,
     pub sql_enabled: bool,
+    /// 批数据源扫描次数（缓存未命中才会扫描）
+    pub batch_scans: u64,
+    pub query_cache: QueryCacheStats,
+    pub aggregate_cache: QueryCacheStats,
 }
 
 /// BatchDataSource 包装器
 ///
-/// 将 OltpBatchAdapter 包装为 Arc<dyn BatchDataSource>
-struct BatchAdapterWrapper(OltpBatchAdapter);
+/// 将 OltpBatchAdapter 包装为 Arc<dyn BatchDataSource>，并统计扫描次数
+struct BatchAdapterWrapper {
+    adapter: OltpBatchAdapter,
+    scans: Arc<AtomicU64>,
+}
 
 #[async_trait::async_trait]
 impl BatchDataSource for BatchAdapterWrapper {
@@ -276,7 +386,8 @@ impl BatchDataSource for BatchAdapterWrapper {
         end_ts: i64,
         fields: &[String],
     ) -> Result<Vec<Record>, BatchQueryError> {
-        self.0.query(key, start_ts, end_ts, fields).await
+        self.scans.fetch_add(1, Ordering::Relaxed);
+        self.adapter.query(key, start_ts, end_ts, fields).await
     }
 
     async fn aggregate(
@@ -286,7 +397,10 @@ impl BatchDataSource for BatchAdapterWrapper {
         end_ts: i64,
         aggregations: &[Aggregation],
     ) -> Result<AggregateResult, BatchQueryError> {
-        self.0.aggregate(key, start_ts, end_ts, aggregations).await
+        self.scans.fetch_add(1, Ordering::Relaxed);
+        self.adapter
+            .aggregate(key, start_ts, end_ts, aggregations)
+            .await
     }
 }
 
@@ -338,9 +452,16 @@ impl UnifiedQueryEngineBuilder {
         self
     }
 
-    /// 设置缓存 TTL（毫秒）
+    /// 设置缓存 TTL（毫秒，统计类与历史类统一）
     pub fn cache_ttl(mut self, ttl_ms: u64) -> Self {
-        self.config.cache_ttl_ms = ttl_ms;
+        self.config.cache.stats_ttl = Duration::from_millis(ttl_ms);
+        self.config.cache.history_ttl = Duration::from_millis(ttl_ms);
+        self
+    }
+
+    /// 设置查询缓存配置（按查询类型 TTL、容量上限）
+    pub fn cache_config(mut self, cache: QueryCacheConfig) -> Self {
+        self.config.cache = cache;
         self
     }
 
@@ -445,6 +566,58 @@ mod tests {
         assert!(result.values.contains_key("total_volume"));
     }
 
+    #[tokio::test]
+    async fn test_aggregate_cache_reduces_scans_under_polling() {
+        let tmp_dir = tempdir().unwrap();
+        let config = OltpHybridConfig {
+            base_path: tmp_dir.path().to_str().unwrap().to_string(),
+            enable_olap_conversion: false,
+            ..Default::default()
+        };
+
+        let storage = Arc::new(OltpHybridStorage::create("IF2501", config).unwrap());
+        for i in 0..10 {
+            let record = create_order_record(i, 1000 + i as i64);
+            storage.write(record).unwrap();
+        }
+
+        let engine = unified_query_engine(storage).with_sql(false).build();
+        let aggs = [Aggregation {
+            field: "price".to_string(),
+            op: AggregateOp::Avg,
+            alias: "avg_price".to_string(),
+        }];
+
+        // 8 个面板并发轮询同一统计查询，每个 20 次
+        let pollers = (0..8).map(|_| async {
+            for _ in 0..20 {
+                engine.aggregate("IF2501", 1000, 1010, &aggs).await.unwrap();
+            }
+        });
+        futures::future::join_all(pollers).await;
+
+        let stats = engine.stats();
+        assert!(
+            stats.batch_scans <= 8,
+            "expected at most one scan per poller, got {}",
+            stats.batch_scans
+        );
+        assert!(stats.aggregate_cache.hits >= 160 - 8);
+
+        let cached = engine.aggregate("IF2501", 1000, 1010, &aggs).await.unwrap();
+        assert!(cached.cache_hit);
+        assert!(cached.data_as_of > 0);
+
+        // 合约有新数据写入后重新扫描
+        let scans_before = engine.stats().batch_scans;
+        engine.push_stream(
+            Record::new("IF2501", 1005).with_value("price", RecordValue::Float(4100.0)),
+        );
+        let fresh = engine.aggregate("IF2501", 1000, 1010, &aggs).await.unwrap();
+        assert!(!fresh.cache_hit);
+        assert_eq!(engine.stats().batch_scans, scans_before + 1);
+    }
+
     #[test]
     fn test_stream_buffer_push() {
         let engine = UnifiedQueryEngine::new(UnifiedQueryConfig::default());
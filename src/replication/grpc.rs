@@ -1027,8 +1027,10 @@ pub fn internal_to_proto_log_entry(internal: &InternalLogEntry) -> LogEntry {
 
     let record_type = match &internal.record {
         crate::storage::wal::WalRecord::OrderInsert { .. } => RecordType::OrderInsert,
-        crate::storage::wal::WalRecord::TradeExecuted { .. } => RecordType::TradeExecuted,
-        crate::storage::wal::WalRecord::AccountUpdate { .. } => RecordType::AccountUpdate,
+        crate::storage::wal::WalRecord::TradeExecuted { .. }
+        | crate::storage::wal::WalRecord::TradeExecutedV2 { .. } => RecordType::TradeExecuted,
+        crate::storage::wal::WalRecord::AccountUpdate { .. }
+        | crate::storage::wal::WalRecord::AccountUpdateV2 { .. } => RecordType::AccountUpdate,
         crate::storage::wal::WalRecord::TickData { .. } => RecordType::TickData,
         crate::storage::wal::WalRecord::OrderBookSnapshot { .. } => RecordType::OrderbookSnapshot,
         crate::storage::wal::WalRecord::Checkpoint { .. } => RecordType::Checkpoint,
@@ -0,0 +1,417 @@
+//! 自动追保入金
+//!
+//! @yutiansut @quantaxis
+//!
+//! 与外部资金系统对接：盘中风控发现账户风险度进入追保区间时，先通过可插拔的
+//! [`AutoMarginProvider`] 从绑定的资金账户（如银行账户）划转资金入金，补足保证金后
+//! 按入金后的风险度继续评估预警阶梯，从而避免强平：
+//!
+//! - **追保额度**: 补足到风险度回落至 `target_risk_ratio`，单次不超过 `max_amount_per_call`
+//! - **部分到账**: 资金账户余额不足时按实际划转金额入金，风险度仍超标则继续追保/强平
+//! - **失败回退**: 划转失败不入金，按原流程追保或强平；失败后冷却期内不再重复请求
+//! - **追保记录**: 每次尝试（成功/部分到账/失败）均留存记录
+
+use dashmap::DashMap;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// 自动追保配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutoMarginConfig {
+    /// 是否启用
+    #[serde(default)]
+    pub enabled: bool,
+    /// 追保目标风险度（入金后风险度回落到该值）
+    #[serde(default = "default_target_risk_ratio")]
+    pub target_risk_ratio: f64,
+    /// 单次最大划转金额（0 表示不限）
+    #[serde(default)]
+    pub max_amount_per_call: f64,
+    /// 划转失败或部分到账后的冷却时间（毫秒），冷却期内不再请求资金系统
+    #[serde(default = "default_failure_cooldown_ms")]
+    pub failure_cooldown_ms: i64,
+    /// 未接入外部资金系统时使用模拟资金账户，每个账户的初始可划转余额
+    #[serde(default)]
+    pub simulated_funding_balance: f64,
+    /// 每账户保留的追保记录数
+    #[serde(default = "default_max_records_per_account")]
+    pub max_records_per_account: usize,
+}
+
+fn default_target_risk_ratio() -> f64 {
+    0.7
+}
+fn default_failure_cooldown_ms() -> i64 {
+    60_000
+}
+fn default_max_records_per_account() -> usize {
+    200
+}
+
+impl Default for AutoMarginConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            target_risk_ratio: default_target_risk_ratio(),
+            max_amount_per_call: 0.0,
+            failure_cooldown_ms: default_failure_cooldown_ms(),
+            simulated_funding_balance: 0.0,
+            max_records_per_account: default_max_records_per_account(),
+        }
+    }
+}
+
+impl AutoMarginConfig {
+    /// 风险度回落到目标值所需追加的保证金（受单次上限约束）
+    pub fn required_amount(&self, balance: f64, margin: f64) -> f64 {
+        if self.target_risk_ratio <= 0.0 {
+            return 0.0;
+        }
+        let amount = (margin / self.target_risk_ratio - balance).max(0.0);
+        if self.max_amount_per_call > 0.0 {
+            amount.min(self.max_amount_per_call)
+        } else {
+            amount
+        }
+    }
+}
+
+/// 划转请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutoMarginRequest {
+    pub request_id: String,
+    pub account_id: String,
+    /// 请求划转金额
+    pub amount: f64,
+    /// 触发时风险度
+    pub risk_ratio: f64,
+}
+
+/// 外部资金划转接口（银行转账、资金池等），可对接外部系统或模拟
+pub trait AutoMarginProvider: Send + Sync {
+    /// 提供方名称（写入追保记录）
+    fn name(&self) -> &str;
+
+    /// 从账户绑定的资金账户划转，返回实际划转金额（余额不足时可小于请求金额）
+    fn transfer(&self, request: &AutoMarginRequest) -> Result<f64, String>;
+}
+
+/// 模拟资金账户（测试环境/未接入外部资金系统时使用）
+pub struct SimulatedMarginProvider {
+    /// account_id -> 资金账户余额
+    balances: DashMap<String, f64>,
+    /// 未单独绑定的账户的初始余额
+    default_balance: f64,
+}
+
+impl SimulatedMarginProvider {
+    pub fn new(default_balance: f64) -> Self {
+        Self {
+            balances: DashMap::new(),
+            default_balance,
+        }
+    }
+
+    /// 绑定账户的资金账户余额
+    pub fn bind(&self, account_id: &str, balance: f64) {
+        self.balances.insert(account_id.to_string(), balance);
+    }
+
+    /// 资金账户当前余额
+    pub fn balance(&self, account_id: &str) -> f64 {
+        self.balances
+            .get(account_id)
+            .map(|b| *b)
+            .unwrap_or(self.default_balance)
+    }
+}
+
+impl AutoMarginProvider for SimulatedMarginProvider {
+    fn name(&self) -> &str {
+        "simulated"
+    }
+
+    fn transfer(&self, request: &AutoMarginRequest) -> Result<f64, String> {
+        let mut balance = self
+            .balances
+            .entry(request.account_id.clone())
+            .or_insert(self.default_balance);
+        let amount = request.amount.min(*balance);
+        if amount <= 0.0 {
+            return Err(format!(
+                "资金账户余额不足: account={}, balance={:.2}",
+                request.account_id, *balance
+            ));
+        }
+        *balance -= amount;
+        Ok(amount)
+    }
+}
+
+/// 追保结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AutoMarginStatus {
+    /// 全额到账
+    Succeeded,
+    /// 部分到账
+    Partial,
+    /// 划转失败（回退到追保/强平流程）
+    Failed,
+}
+
+/// 追保记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutoMarginRecord {
+    pub record_id: String,
+    pub account_id: String,
+    /// 资金提供方
+    pub provider: String,
+    pub status: AutoMarginStatus,
+    /// 请求划转金额
+    pub requested_amount: f64,
+    /// 实际入金金额
+    pub transferred_amount: f64,
+    pub risk_ratio_before: f64,
+    /// 入金后风险度（失败时与入金前相同）
+    pub risk_ratio_after: f64,
+    /// 失败原因
+    pub error: Option<String>,
+    /// 记录时间（毫秒）
+    pub timestamp: i64,
+}
+
+/// 自动追保
+pub struct AutoMarginTopUp {
+    config: RwLock<AutoMarginConfig>,
+    provider: RwLock<Option<Arc<dyn AutoMarginProvider>>>,
+    /// account_id -> 追保记录
+    records: DashMap<String, Vec<AutoMarginRecord>>,
+    /// account_id -> 冷却截止时间（毫秒）
+    cooldown_until: DashMap<String, i64>,
+    record_seq: AtomicU64,
+}
+
+impl Default for AutoMarginTopUp {
+    fn default() -> Self {
+        Self::new(AutoMarginConfig::default())
+    }
+}
+
+impl AutoMarginTopUp {
+    pub fn new(config: AutoMarginConfig) -> Self {
+        Self {
+            config: RwLock::new(config),
+            provider: RwLock::new(None),
+            records: DashMap::new(),
+            cooldown_until: DashMap::new(),
+            record_seq: AtomicU64::new(1),
+        }
+    }
+
+    /// 获取配置
+    pub fn config(&self) -> AutoMarginConfig {
+        self.config.read().clone()
+    }
+
+    /// 更新配置
+    pub fn update_config(&self, config: AutoMarginConfig) {
+        log::info!(
+            "[AutoMargin] Config updated: enabled={}, target={:.0}%, max_per_call={:.2}",
+            config.enabled,
+            config.target_risk_ratio * 100.0,
+            config.max_amount_per_call
+        );
+        *self.config.write() = config;
+    }
+
+    /// 设置资金划转提供方
+    pub fn set_provider(&self, provider: Arc<dyn AutoMarginProvider>) {
+        log::info!("[AutoMargin] Provider set: {}", provider.name());
+        *self.provider.write() = Some(provider);
+    }
+
+    /// 是否已设置资金划转提供方
+    pub fn has_provider(&self) -> bool {
+        self.provider.read().is_some()
+    }
+
+    /// 尝试自动追保入金
+    ///
+    /// `deposit` 将实际划转金额入金到交易账户并返回入金后的风险度。
+    /// 未启用、未设置提供方、处于冷却期或无需追加时返回 None。
+    pub fn try_top_up(
+        &self,
+        account_id: &str,
+        risk_ratio: f64,
+        balance: f64,
+        margin: f64,
+        now_ms: i64,
+        deposit: impl FnOnce(f64) -> f64,
+    ) -> Option<AutoMarginRecord> {
+        let config = self.config.read().clone();
+        if !config.enabled {
+            return None;
+        }
+        let provider = self.provider.read().clone()?;
+        if self
+            .cooldown_until
+            .get(account_id)
+            .is_some_and(|until| now_ms < *until)
+        {
+            return None;
+        }
+        let requested_amount = config.required_amount(balance, margin);
+        if requested_amount <= 0.0 {
+            return None;
+        }
+
+        let seq = self.record_seq.fetch_add(1, Ordering::SeqCst);
+        let request = AutoMarginRequest {
+            request_id: format!("AM{}{:08}", now_ms, seq),
+            account_id: account_id.to_string(),
+            amount: requested_amount,
+            risk_ratio,
+        };
+
+        let (status, transferred_amount, risk_ratio_after, error) =
+            match provider.transfer(&request) {
+                Ok(amount) if amount > 0.0 => {
+                    let risk_ratio_after = deposit(amount);
+                    let status = if amount + 1e-6 >= requested_amount {
+                        AutoMarginStatus::Succeeded
+                    } else {
+                        AutoMarginStatus::Partial
+                    };
+                    (status, amount, risk_ratio_after, None)
+                }
+                Ok(_) => (
+                    AutoMarginStatus::Failed,
+                    0.0,
+                    risk_ratio,
+                    Some("资金账户未划转任何金额".to_string()),
+                ),
+                Err(e) => (AutoMarginStatus::Failed, 0.0, risk_ratio, Some(e)),
+            };
+
+        if status == AutoMarginStatus::Succeeded {
+            self.cooldown_until.remove(account_id);
+        } else {
+            self.cooldown_until
+                .insert(account_id.to_string(), now_ms + config.failure_cooldown_ms);
+        }
+
+        let record = AutoMarginRecord {
+            record_id: request.request_id,
+            account_id: account_id.to_string(),
+            provider: provider.name().to_string(),
+            status,
+            requested_amount,
+            transferred_amount,
+            risk_ratio_before: risk_ratio,
+            risk_ratio_after,
+            error,
+            timestamp: now_ms,
+        };
+        match record.status {
+            AutoMarginStatus::Failed => log::warn!(
+                "[AutoMargin] Top-up failed: account={}, requested={:.2}, error={:?}",
+                account_id,
+                requested_amount,
+                record.error
+            ),
+            _ => log::info!(
+                "[AutoMargin] Top-up {:?}: account={}, transferred={:.2}/{:.2}, risk {:.2}% -> {:.2}%",
+                record.status,
+                account_id,
+                transferred_amount,
+                requested_amount,
+                risk_ratio * 100.0,
+                risk_ratio_after * 100.0
+            ),
+        }
+
+        let mut history = self.records.entry(account_id.to_string()).or_default();
+        history.push(record.clone());
+        let excess = history.len().saturating_sub(config.max_records_per_account);
+        if excess > 0 {
+            history.drain(..excess);
+        }
+        Some(record)
+    }
+
+    /// 账户追保记录
+    pub fn get_records(&self, account_id: &str) -> Vec<AutoMarginRecord> {
+        self.records
+            .get(account_id)
+            .map(|r| r.value().clone())
+            .unwrap_or_default()
+    }
+
+    /// 全部追保记录（按时间倒序）
+    pub fn all_records(&self) -> Vec<AutoMarginRecord> {
+        let mut records: Vec<AutoMarginRecord> = self
+            .records
+            .iter()
+            .flat_map(|r| r.value().clone())
+            .collect();
+        records.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        records
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enabled_config() -> AutoMarginConfig {
+        AutoMarginConfig {
+            enabled: true,
+            target_risk_ratio: 0.5,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_partial_transfer_and_cooldown() {
+        let top_up = AutoMarginTopUp::new(enabled_config());
+        let provider = Arc::new(SimulatedMarginProvider::new(0.0));
+        provider.bind("acc", 30_000.0);
+        top_up.set_provider(provider.clone());
+
+        // 保证金 60000、权益 50000：补足到 50% 风险度需追加 70000，资金账户只有 30000
+        let record = top_up
+            .try_top_up("acc", 1.2, 50_000.0, 60_000.0, 1_000, |amount| {
+                60_000.0 / (50_000.0 + amount)
+            })
+            .unwrap();
+        assert_eq!(record.status, AutoMarginStatus::Partial);
+        assert_eq!(record.requested_amount, 70_000.0);
+        assert_eq!(record.transferred_amount, 30_000.0);
+        assert_eq!(record.risk_ratio_after, 0.75);
+        assert_eq!(provider.balance("acc"), 0.0);
+
+        // 冷却期内不再请求，冷却结束后余额为 0 划转失败
+        assert!(top_up
+            .try_top_up("acc", 0.75, 80_000.0, 60_000.0, 2_000, |_| unreachable!())
+            .is_none());
+        let record = top_up
+            .try_top_up("acc", 0.75, 80_000.0, 60_000.0, 62_000, |_| unreachable!())
+            .unwrap();
+        assert_eq!(record.status, AutoMarginStatus::Failed);
+        assert!(record.error.is_some());
+        assert_eq!(top_up.get_records("acc").len(), 2);
+    }
+
+    #[test]
+    fn test_required_amount_capped_per_call() {
+        let config = AutoMarginConfig {
+            max_amount_per_call: 10_000.0,
+            ..enabled_config()
+        };
+        assert_eq!(config.required_amount(50_000.0, 20_000.0), 0.0);
+        assert_eq!(config.required_amount(50_000.0, 60_000.0), 10_000.0);
+    }
+}
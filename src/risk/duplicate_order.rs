@@ -0,0 +1,501 @@
+//! 重复订单检测
+//!
+//! 客户端 bug 把同一笔单连发多次会造成成倍仓位。撮合前按账户检测重复报单：
+//! - 同一账户在 `window_ms` 内出现合约、方向、开平、价格、数量完全相同的订单视为重复
+//! - 订单带幂等键（`client_order_id`）时以幂等键为准：相同键即重复（不限窗口），
+//!   不同键的同参数订单不视为重复
+//! - 每账户一个容量为 `buffer_size` 的环形缓冲保存最近的订单，检测只扫描该缓冲，
+//!   与账户历史订单量无关
+//! - 处理策略：`reject` 拒绝后续重复订单，`alert` 仅告警放行
+//! - 默认关闭，避免误伤合法的高频重复报单策略；可全局开启或按账户开启
+//!
+//! 检测与登记在账户条目锁内一次完成，并发到达的相同订单只有第一笔通过；
+//! 通过检测的订单若随后被拒（资金不足等），由订单路由调用 `release` 移出缓冲，重试不受影响。
+//!
+//! @yutiansut @quantaxis
+
+use dashmap::DashMap;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// 保留的拦截事件条数
+const MAX_EVENTS: usize = 200;
+
+/// 重复订单处理策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateOrderAction {
+    /// 拒绝后续重复订单
+    #[default]
+    Reject,
+    /// 仅告警，订单照常处理
+    Alert,
+}
+
+fn default_window_ms() -> u64 {
+    500
+}
+
+fn default_buffer_size() -> usize {
+    32
+}
+
+/// 重复订单检测配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateOrderConfig {
+    /// 全局开启（关闭时仅对 `accounts` 中的账户检测）
+    #[serde(default)]
+    pub enabled: bool,
+    /// 检测窗口（毫秒）
+    #[serde(default = "default_window_ms")]
+    pub window_ms: u64,
+    /// 每账户环形缓冲容量（最近订单数）
+    #[serde(default = "default_buffer_size")]
+    pub buffer_size: usize,
+    /// 处理策略
+    #[serde(default)]
+    pub action: DuplicateOrderAction,
+    /// 单独开启检测的账户
+    #[serde(default)]
+    pub accounts: HashSet<String>,
+    /// 豁免账户（全局开启时不检测）
+    #[serde(default)]
+    pub exempt_accounts: HashSet<String>,
+}
+
+impl Default for DuplicateOrderConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            window_ms: default_window_ms(),
+            buffer_size: default_buffer_size(),
+            action: DuplicateOrderAction::Reject,
+            accounts: HashSet::new(),
+            exempt_accounts: HashSet::new(),
+        }
+    }
+}
+
+impl DuplicateOrderConfig {
+    /// 账户是否需要检测
+    pub fn applies_to(&self, account_id: &str) -> bool {
+        if self.exempt_accounts.contains(account_id) {
+            return false;
+        }
+        self.enabled || self.accounts.contains(account_id)
+    }
+}
+
+/// 订单参数指纹（价格/数量按位比较）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderFingerprint {
+    pub instrument_id: String,
+    pub direction: String,
+    pub offset: String,
+    price_bits: u64,
+    volume_bits: u64,
+}
+
+impl OrderFingerprint {
+    pub fn new(
+        instrument_id: &str,
+        direction: &str,
+        offset: &str,
+        price: f64,
+        volume: f64,
+    ) -> Self {
+        Self {
+            instrument_id: instrument_id.to_string(),
+            direction: direction.to_ascii_uppercase(),
+            offset: offset.to_ascii_uppercase(),
+            price_bits: price.to_bits(),
+            volume_bits: volume.to_bits(),
+        }
+    }
+
+    pub fn price(&self) -> f64 {
+        f64::from_bits(self.price_bits)
+    }
+
+    pub fn volume(&self) -> f64 {
+        f64::from_bits(self.volume_bits)
+    }
+}
+
+/// 重复判定依据
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateMatch {
+    /// 窗口内参数完全相同
+    SameParams,
+    /// 幂等键相同
+    SameClientOrderId,
+}
+
+/// 环形缓冲中的一笔订单
+#[derive(Debug, Clone)]
+struct RecentOrder {
+    order_id: String,
+    client_order_id: Option<String>,
+    fingerprint: OrderFingerprint,
+    timestamp_ms: i64,
+}
+
+/// 单账户状态
+#[derive(Debug, Default)]
+struct AccountRecent {
+    orders: VecDeque<RecentOrder>,
+    hits: u64,
+}
+
+impl AccountRecent {
+    fn find(
+        &self,
+        fingerprint: &OrderFingerprint,
+        client_order_id: Option<&str>,
+        window_ms: u64,
+        now_ms: i64,
+    ) -> Option<(&RecentOrder, DuplicateMatch)> {
+        match client_order_id {
+            Some(key) => self
+                .orders
+                .iter()
+                .rev()
+                .find(|o| o.client_order_id.as_deref() == Some(key))
+                .map(|o| (o, DuplicateMatch::SameClientOrderId)),
+            None => self
+                .orders
+                .iter()
+                .rev()
+                .take_while(|o| now_ms.saturating_sub(o.timestamp_ms) < window_ms as i64)
+                .find(|o| o.client_order_id.is_none() && &o.fingerprint == fingerprint)
+                .map(|o| (o, DuplicateMatch::SameParams)),
+        }
+    }
+}
+
+/// 重复订单拦截事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateOrderEvent {
+    pub account_id: String,
+    pub instrument_id: String,
+    pub direction: String,
+    pub offset: String,
+    pub price: f64,
+    pub volume: f64,
+    /// 本次订单ID
+    pub order_id: String,
+    /// 被判定重复的先前订单ID
+    pub duplicate_of: String,
+    pub client_order_id: Option<String>,
+    pub matched_by: DuplicateMatch,
+    pub action: DuplicateOrderAction,
+    pub timestamp_ms: i64,
+}
+
+/// 单账户拦截次数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountDuplicateHits {
+    pub account_id: String,
+    pub hits: u64,
+}
+
+/// 重复订单检测统计
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateOrderStats {
+    pub checked: u64,
+    /// 拒绝的重复订单
+    pub rejected: u64,
+    /// 仅告警放行的重复订单
+    pub alerted: u64,
+    pub tracked_accounts: usize,
+    /// 命中次数最多的账户（降序）
+    pub top_accounts: Vec<AccountDuplicateHits>,
+    /// 最近的拦截事件（按时间倒序）
+    pub recent_events: Vec<DuplicateOrderEvent>,
+}
+
+/// 重复订单检测器
+pub struct DuplicateOrderGuard {
+    config: RwLock<DuplicateOrderConfig>,
+    accounts: DashMap<String, AccountRecent>,
+    events: Mutex<VecDeque<DuplicateOrderEvent>>,
+    checked: AtomicU64,
+    rejected: AtomicU64,
+    alerted: AtomicU64,
+}
+
+impl DuplicateOrderGuard {
+    pub fn new(config: DuplicateOrderConfig) -> Self {
+        Self {
+            config: RwLock::new(config),
+            accounts: DashMap::new(),
+            events: Mutex::new(VecDeque::new()),
+            checked: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+            alerted: AtomicU64::new(0),
+        }
+    }
+
+    /// 当前配置
+    pub fn config(&self) -> DuplicateOrderConfig {
+        self.config.read().clone()
+    }
+
+    /// 更新配置（已登记的订单保留）
+    pub fn update_config(&self, config: DuplicateOrderConfig) {
+        *self.config.write() = config;
+    }
+
+    /// 按账户开启/关闭检测
+    pub fn set_account_enabled(&self, account_id: &str, enabled: bool) {
+        let mut config = self.config.write();
+        if enabled {
+            config.exempt_accounts.remove(account_id);
+            config.accounts.insert(account_id.to_string());
+        } else {
+            config.accounts.remove(account_id);
+            if config.enabled {
+                config.exempt_accounts.insert(account_id.to_string());
+            }
+        }
+    }
+
+    /// 检测并登记订单：重复且策略为拒绝时返回 Err（不登记），否则登记后返回 Ok
+    pub fn check(
+        &self,
+        account_id: &str,
+        order_id: &str,
+        client_order_id: Option<&str>,
+        fingerprint: OrderFingerprint,
+        now_ms: i64,
+    ) -> Result<(), String> {
+        let (window_ms, buffer_size, action) = {
+            let config = self.config.read();
+            if !config.applies_to(account_id) {
+                return Ok(());
+            }
+            (config.window_ms, config.buffer_size.max(1), config.action)
+        };
+        self.checked.fetch_add(1, Ordering::Relaxed);
+
+        let mut entry = self.accounts.entry(account_id.to_string()).or_default();
+        let state = entry.value_mut();
+        let duplicate = state
+            .find(&fingerprint, client_order_id, window_ms, now_ms)
+            .map(|(prev, matched_by)| (prev.order_id.clone(), matched_by));
+
+        if let Some((duplicate_of, matched_by)) = duplicate {
+            state.hits += 1;
+            let counter = match action {
+                DuplicateOrderAction::Reject => &self.rejected,
+                DuplicateOrderAction::Alert => &self.alerted,
+            };
+            counter.fetch_add(1, Ordering::Relaxed);
+            log::warn!(
+                "Duplicate order {} from {} ({} {} {} {}@{}), same as {} by {:?}, action={:?}",
+                order_id,
+                account_id,
+                fingerprint.instrument_id,
+                fingerprint.direction,
+                fingerprint.offset,
+                fingerprint.volume(),
+                fingerprint.price(),
+                duplicate_of,
+                matched_by,
+                action
+            );
+            self.push_event(DuplicateOrderEvent {
+                account_id: account_id.to_string(),
+                instrument_id: fingerprint.instrument_id.clone(),
+                direction: fingerprint.direction.clone(),
+                offset: fingerprint.offset.clone(),
+                price: fingerprint.price(),
+                volume: fingerprint.volume(),
+                order_id: order_id.to_string(),
+                duplicate_of: duplicate_of.clone(),
+                client_order_id: client_order_id.map(str::to_string),
+                matched_by,
+                action,
+                timestamp_ms: now_ms,
+            });
+            if action == DuplicateOrderAction::Reject {
+                return Err(match matched_by {
+                    DuplicateMatch::SameParams => format!(
+                        "Duplicate order: same parameters as {} within {}ms",
+                        duplicate_of, window_ms
+                    ),
+                    DuplicateMatch::SameClientOrderId => format!(
+                        "Duplicate order: client_order_id already used by {}",
+                        duplicate_of
+                    ),
+                });
+            }
+        }
+
+        state.orders.push_back(RecentOrder {
+            order_id: order_id.to_string(),
+            client_order_id: client_order_id.map(str::to_string),
+            fingerprint,
+            timestamp_ms: now_ms,
+        });
+        while state.orders.len() > buffer_size {
+            state.orders.pop_front();
+        }
+        Ok(())
+    }
+
+    /// 移出已登记订单（订单在后续校验中被拒，重试不应判为重复）
+    pub fn release(&self, account_id: &str, order_id: &str) {
+        if let Some(mut state) = self.accounts.get_mut(account_id) {
+            state.orders.retain(|o| o.order_id != order_id);
+        }
+    }
+
+    fn push_event(&self, event: DuplicateOrderEvent) {
+        let mut events = self.events.lock();
+        events.push_back(event);
+        while events.len() > MAX_EVENTS {
+            events.pop_front();
+        }
+    }
+
+    /// 统计与最近拦截事件
+    pub fn stats(&self, top_n: usize, recent: usize) -> DuplicateOrderStats {
+        let mut top_accounts: Vec<AccountDuplicateHits> = self
+            .accounts
+            .iter()
+            .filter(|e| e.value().hits > 0)
+            .map(|e| AccountDuplicateHits {
+                account_id: e.key().clone(),
+                hits: e.value().hits,
+            })
+            .collect();
+        top_accounts.sort_by(|a, b| b.hits.cmp(&a.hits).then(a.account_id.cmp(&b.account_id)));
+        top_accounts.truncate(top_n);
+
+        DuplicateOrderStats {
+            checked: self.checked.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+            alerted: self.alerted.load(Ordering::Relaxed),
+            tracked_accounts: self.accounts.len(),
+            top_accounts,
+            recent_events: self
+                .events
+                .lock()
+                .iter()
+                .rev()
+                .take(recent)
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn enabled(action: DuplicateOrderAction) -> DuplicateOrderGuard {
+        DuplicateOrderGuard::new(DuplicateOrderConfig {
+            enabled: true,
+            window_ms: 100,
+            action,
+            ..Default::default()
+        })
+    }
+
+    fn fp(price: f64) -> OrderFingerprint {
+        OrderFingerprint::new("cu2501", "BUY", "OPEN", price, 1.0)
+    }
+
+    #[test]
+    fn test_window_boundary_and_params() {
+        let guard = enabled(DuplicateOrderAction::Reject);
+        assert!(guard.check("acc", "O1", None, fp(100.0), 1_000).is_ok());
+        // 窗口内同参数：拒绝；价格/方向/账户不同：放行
+        assert!(guard.check("acc", "O2", None, fp(100.0), 1_099).is_err());
+        assert!(guard.check("acc", "O3", None, fp(100.5), 1_050).is_ok());
+        let sell = OrderFingerprint::new("cu2501", "SELL", "OPEN", 100.0, 1.0);
+        assert!(guard.check("acc", "O4", None, sell, 1_050).is_ok());
+        assert!(guard.check("other", "O5", None, fp(100.0), 1_050).is_ok());
+        // 恰好到达窗口边界不再视为重复
+        assert!(guard.check("acc", "O6", None, fp(100.0), 1_100).is_ok());
+
+        // 被拒订单释放后重试不算重复
+        guard.release("acc", "O6");
+        assert!(guard.check("acc", "O7", None, fp(100.0), 1_101).is_ok());
+
+        let stats = guard.stats(10, 10);
+        assert_eq!((stats.checked, stats.rejected, stats.alerted), (7, 1, 0));
+        assert_eq!(stats.recent_events[0].order_id, "O2");
+        assert_eq!(stats.recent_events[0].duplicate_of, "O1");
+        assert_eq!(stats.top_accounts[0].account_id, "acc");
+    }
+
+    #[test]
+    fn test_client_order_id_takes_precedence() {
+        let guard = enabled(DuplicateOrderAction::Reject);
+        // 不同幂等键的同参数订单是合法的重复报单
+        assert!(guard.check("acc", "O1", Some("c1"), fp(100.0), 0).is_ok());
+        assert!(guard.check("acc", "O2", Some("c2"), fp(100.0), 1).is_ok());
+        // 相同幂等键即使超出窗口、参数不同也拒绝
+        let err = guard
+            .check("acc", "O3", Some("c1"), fp(101.0), 10_000)
+            .unwrap_err();
+        assert!(err.contains("O1"));
+        assert_eq!(
+            guard.stats(1, 1).recent_events[0].matched_by,
+            DuplicateMatch::SameClientOrderId
+        );
+    }
+
+    #[test]
+    fn test_alert_mode_and_per_account_switch() {
+        let guard = DuplicateOrderGuard::new(DuplicateOrderConfig {
+            window_ms: 100,
+            action: DuplicateOrderAction::Alert,
+            ..Default::default()
+        });
+        // 默认关闭：不检测
+        assert!(guard.check("acc", "O1", None, fp(100.0), 0).is_ok());
+        assert!(guard.check("acc", "O2", None, fp(100.0), 1).is_ok());
+        assert_eq!(guard.stats(10, 10).checked, 0);
+
+        // 按账户开启，告警放行
+        guard.set_account_enabled("acc", true);
+        assert!(guard.check("acc", "O3", None, fp(100.0), 2).is_ok());
+        assert!(guard.check("acc", "O4", None, fp(100.0), 3).is_ok());
+        assert!(guard.check("other", "O5", None, fp(100.0), 3).is_ok());
+        let stats = guard.stats(10, 10);
+        assert_eq!((stats.checked, stats.rejected, stats.alerted), (2, 0, 1));
+        assert_eq!(stats.recent_events[0].action, DuplicateOrderAction::Alert);
+    }
+
+    /// 并发：同参数订单只放行一笔；不同参数的订单全部放行
+    #[test]
+    fn test_concurrent_submissions() {
+        let guard = Arc::new(enabled(DuplicateOrderAction::Reject));
+        let handles: Vec<_> = (0..16)
+            .map(|i| {
+                let guard = guard.clone();
+                std::thread::spawn(move || {
+                    let same = guard
+                        .check("acc", &format!("S{}", i), None, fp(100.0), 0)
+                        .is_ok();
+                    let distinct = guard
+                        .check("acc", &format!("D{}", i), None, fp(200.0 + i as f64), 0)
+                        .is_ok();
+                    (same, distinct)
+                })
+            })
+            .collect();
+        let results: Vec<(bool, bool)> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        assert_eq!(results.iter().filter(|(same, _)| *same).count(), 1);
+        assert!(results.iter().all(|(_, distinct)| *distinct));
+        assert_eq!(guard.stats(10, 0).rejected, 15);
+    }
+}
@@ -0,0 +1,628 @@
+//! 强平预警阶梯
+//!
+//! @yutiansut @quantaxis
+//!
+//! 账户接近强平线时分级预警，而不是直接强平：
+//!
+//! ```text
+//! 风险度   80%        90%        100%              110%
+//!   ──────┼──────────┼──────────┼─────────────────┼──────────
+//!  Normal │ Reminder │ Warning  │ MarginCall      │ Liquidation
+//!         │ 提醒     │ 警告     │ 追保（带截止时间） │ 立即强平
+//! ```
+//!
+//! - 每跨越一级产生一条预警事件（含所需追加保证金），同级别不重复推送
+//! - **防抖**: 风险度需回落到阈值以下 `hysteresis` 才降级，降级时产生解除事件
+//! - **追保期限**: 进入追保级别时设定截止时间，逾期仍未回落才触发强平
+//! - **落盘**: 预警事件逐条追加写入 JSON Lines 文件，启动时加载
+
+use dashmap::DashMap;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// 预警级别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MarginCallLevel {
+    /// 正常
+    Normal,
+    /// 提醒
+    Reminder,
+    /// 警告
+    Warning,
+    /// 追加保证金
+    MarginCall,
+    /// 强平
+    Liquidation,
+}
+
+impl MarginCallLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MarginCallLevel::Normal => "normal",
+            MarginCallLevel::Reminder => "reminder",
+            MarginCallLevel::Warning => "warning",
+            MarginCallLevel::MarginCall => "margin_call",
+            MarginCallLevel::Liquidation => "liquidation",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            MarginCallLevel::Normal => "正常",
+            MarginCallLevel::Reminder => "提醒",
+            MarginCallLevel::Warning => "警告",
+            MarginCallLevel::MarginCall => "追保",
+            MarginCallLevel::Liquidation => "强平",
+        }
+    }
+}
+
+/// 预警阶梯配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarginCallConfig {
+    /// 是否启用（关闭时按 RiskMonitorConfig.liquidation_threshold 直接强平）
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// 提醒阈值
+    #[serde(default = "default_reminder_threshold")]
+    pub reminder_threshold: f64,
+    /// 警告阈值
+    #[serde(default = "default_warning_threshold")]
+    pub warning_threshold: f64,
+    /// 追保阈值
+    #[serde(default = "default_margin_call_threshold")]
+    pub margin_call_threshold: f64,
+    /// 强平阈值（超过立即强平）
+    #[serde(default = "default_liquidation_threshold")]
+    pub liquidation_threshold: f64,
+    /// 降级回差（风险度低于阈值减回差才降级）
+    #[serde(default = "default_hysteresis")]
+    pub hysteresis: f64,
+    /// 追保期限（毫秒）
+    #[serde(default = "default_margin_call_deadline_ms")]
+    pub margin_call_deadline_ms: i64,
+    /// 每账户保留的预警事件数
+    #[serde(default = "default_max_events_per_account")]
+    pub max_events_per_account: usize,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+fn default_reminder_threshold() -> f64 {
+    0.8
+}
+fn default_warning_threshold() -> f64 {
+    0.9
+}
+fn default_margin_call_threshold() -> f64 {
+    1.0
+}
+fn default_liquidation_threshold() -> f64 {
+    1.1
+}
+fn default_hysteresis() -> f64 {
+    0.02
+}
+fn default_margin_call_deadline_ms() -> i64 {
+    2 * 60 * 60 * 1000
+}
+fn default_max_events_per_account() -> usize {
+    200
+}
+
+impl Default for MarginCallConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            reminder_threshold: default_reminder_threshold(),
+            warning_threshold: default_warning_threshold(),
+            margin_call_threshold: default_margin_call_threshold(),
+            liquidation_threshold: default_liquidation_threshold(),
+            hysteresis: default_hysteresis(),
+            margin_call_deadline_ms: default_margin_call_deadline_ms(),
+            max_events_per_account: default_max_events_per_account(),
+        }
+    }
+}
+
+impl MarginCallConfig {
+    /// 风险度对应的阶梯级别
+    pub fn level_of(&self, risk_ratio: f64) -> MarginCallLevel {
+        if risk_ratio >= self.liquidation_threshold {
+            MarginCallLevel::Liquidation
+        } else if risk_ratio >= self.margin_call_threshold {
+            MarginCallLevel::MarginCall
+        } else if risk_ratio >= self.warning_threshold {
+            MarginCallLevel::Warning
+        } else if risk_ratio >= self.reminder_threshold {
+            MarginCallLevel::Reminder
+        } else {
+            MarginCallLevel::Normal
+        }
+    }
+
+    /// 风险度回落到提醒线以下所需追加的保证金
+    pub fn required_margin(&self, balance: f64, margin: f64) -> f64 {
+        if self.reminder_threshold <= 0.0 {
+            return 0.0;
+        }
+        (margin / self.reminder_threshold - balance).max(0.0)
+    }
+}
+
+/// 预警事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarginCallEvent {
+    pub event_id: String,
+    pub account_id: String,
+    /// 原级别
+    pub from_level: MarginCallLevel,
+    /// 新级别
+    pub level: MarginCallLevel,
+    pub risk_ratio: f64,
+    pub balance: f64,
+    pub margin: f64,
+    /// 所需追加保证金
+    pub required_margin: f64,
+    /// 追保截止时间（毫秒，仅追保级别）
+    pub deadline: Option<i64>,
+    /// 是否为解除事件（级别回落）
+    pub released: bool,
+    /// 是否触发强平
+    pub liquidate: bool,
+    pub message: String,
+    /// 事件时间（毫秒）
+    pub timestamp: i64,
+}
+
+/// 账户当前预警状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarginCallState {
+    pub account_id: String,
+    pub level: MarginCallLevel,
+    pub risk_ratio: f64,
+    /// 追保截止时间（毫秒）
+    pub deadline: Option<i64>,
+    /// 本轮是否已触发强平（降到追保以下后复位）
+    pub liquidation_triggered: bool,
+    pub updated_at: i64,
+}
+
+/// 强平预警阶梯
+pub struct MarginCallLadder {
+    config: RwLock<MarginCallConfig>,
+    /// account_id -> 当前状态
+    states: DashMap<String, MarginCallState>,
+    /// account_id -> 预警事件历史
+    history: DashMap<String, Vec<MarginCallEvent>>,
+    event_seq: AtomicU64,
+    /// 落盘文件（JSON Lines）
+    persist_path: Mutex<Option<PathBuf>>,
+}
+
+impl Default for MarginCallLadder {
+    fn default() -> Self {
+        Self::new(MarginCallConfig::default())
+    }
+}
+
+impl MarginCallLadder {
+    pub fn new(config: MarginCallConfig) -> Self {
+        Self {
+            config: RwLock::new(config),
+            states: DashMap::new(),
+            history: DashMap::new(),
+            event_seq: AtomicU64::new(1),
+            persist_path: Mutex::new(None),
+        }
+    }
+
+    /// 获取配置
+    pub fn config(&self) -> MarginCallConfig {
+        self.config.read().clone()
+    }
+
+    /// 更新配置
+    pub fn update_config(&self, config: MarginCallConfig) {
+        log::info!(
+            "[MarginCall] Ladder updated: reminder={:.0}%, warning={:.0}%, margin_call={:.0}%, liquidation={:.0}%",
+            config.reminder_threshold * 100.0,
+            config.warning_threshold * 100.0,
+            config.margin_call_threshold * 100.0,
+            config.liquidation_threshold * 100.0
+        );
+        *self.config.write() = config;
+    }
+
+    /// 设置落盘文件并加载已有预警历史，返回加载的事件数
+    pub fn set_persist_path<P: AsRef<Path>>(&self, path: P) -> Result<usize, String> {
+        let path = path.as_ref().to_path_buf();
+        let mut loaded = 0;
+        if path.exists() {
+            let file = std::fs::File::open(&path).map_err(|e| format!("open {:?}: {}", path, e))?;
+            for line in std::io::BufReader::new(file).lines() {
+                let line = line.map_err(|e| format!("read {:?}: {}", path, e))?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str::<MarginCallEvent>(&line) {
+                    Ok(event) => {
+                        self.restore_state(&event);
+                        self.push_history(event);
+                        loaded += 1;
+                    }
+                    Err(e) => log::warn!("[MarginCall] Skip corrupted event in {:?}: {}", path, e),
+                }
+            }
+        }
+        *self.persist_path.lock() = Some(path);
+        Ok(loaded)
+    }
+
+    /// 评估账户风险度，级别变化时返回预警事件
+    ///
+    /// - 级别上升：每跨越一级返回新级别事件，进入追保级别时设定截止时间
+    /// - 级别回落：低于阈值减回差才降级，返回解除事件
+    /// - 达到强平阈值或追保逾期：事件 `liquidate = true`，同一轮只触发一次
+    pub fn evaluate(
+        &self,
+        account_id: &str,
+        risk_ratio: f64,
+        balance: f64,
+        margin: f64,
+        now_ms: i64,
+    ) -> Option<MarginCallEvent> {
+        let config = self.config.read().clone();
+        let raw_level = config.level_of(risk_ratio);
+
+        let mut state = self
+            .states
+            .entry(account_id.to_string())
+            .or_insert_with(|| MarginCallState {
+                account_id: account_id.to_string(),
+                level: MarginCallLevel::Normal,
+                risk_ratio,
+                deadline: None,
+                liquidation_triggered: false,
+                updated_at: now_ms,
+            });
+        let from_level = state.level;
+
+        // 防抖：降级需越过回差
+        let level = if raw_level >= from_level {
+            raw_level
+        } else {
+            config
+                .level_of(risk_ratio + config.hysteresis)
+                .min(from_level)
+        };
+        state.risk_ratio = risk_ratio;
+        state.updated_at = now_ms;
+
+        if level < MarginCallLevel::MarginCall {
+            state.deadline = None;
+            state.liquidation_triggered = false;
+        } else if state.deadline.is_none() {
+            state.deadline = Some(now_ms + config.margin_call_deadline_ms);
+        }
+
+        let overdue = level == MarginCallLevel::MarginCall
+            && state.deadline.is_some_and(|deadline| now_ms >= deadline);
+        let liquidate =
+            (level == MarginCallLevel::Liquidation || overdue) && !state.liquidation_triggered;
+        if liquidate {
+            state.liquidation_triggered = true;
+        }
+
+        if level == from_level && !liquidate {
+            return None;
+        }
+        state.level = level;
+        let deadline = state.deadline;
+        drop(state);
+
+        let released = level < from_level;
+        let required_margin = if released && level == MarginCallLevel::Normal {
+            0.0
+        } else {
+            config.required_margin(balance, margin)
+        };
+        let message = if overdue && liquidate {
+            format!(
+                "追保逾期未补足，风险度 {:.2}%，执行强平",
+                risk_ratio * 100.0
+            )
+        } else if released {
+            format!(
+                "风险度回落至 {:.2}%，解除{}预警（当前级别: {}）",
+                risk_ratio * 100.0,
+                from_level.label(),
+                level.label()
+            )
+        } else if level == MarginCallLevel::MarginCall {
+            format!(
+                "风险度 {:.2}% 达到追保线，请在截止时间前追加保证金 {:.2}，逾期将强制平仓",
+                risk_ratio * 100.0,
+                required_margin
+            )
+        } else {
+            format!(
+                "风险度 {:.2}% 达到{}线，建议追加保证金 {:.2}",
+                risk_ratio * 100.0,
+                level.label(),
+                required_margin
+            )
+        };
+
+        let seq = self.event_seq.fetch_add(1, Ordering::SeqCst);
+        let event = MarginCallEvent {
+            event_id: format!("MC{}{:08}", now_ms, seq),
+            account_id: account_id.to_string(),
+            from_level,
+            level,
+            risk_ratio,
+            balance,
+            margin,
+            required_margin,
+            deadline: if level >= MarginCallLevel::MarginCall {
+                deadline
+            } else {
+                None
+            },
+            released,
+            liquidate,
+            message,
+            timestamp: now_ms,
+        };
+
+        self.persist(&event);
+        self.push_history(event.clone());
+        Some(event)
+    }
+
+    /// 账户当前预警状态
+    pub fn get_state(&self, account_id: &str) -> Option<MarginCallState> {
+        self.states.get(account_id).map(|s| s.value().clone())
+    }
+
+    /// 处于预警中的账户（级别高于 Normal，按风险度降序）
+    pub fn active_states(&self) -> Vec<MarginCallState> {
+        let mut states: Vec<MarginCallState> = self
+            .states
+            .iter()
+            .filter(|s| s.level > MarginCallLevel::Normal)
+            .map(|s| s.value().clone())
+            .collect();
+        states.sort_by(|a, b| {
+            b.risk_ratio
+                .partial_cmp(&a.risk_ratio)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        states
+    }
+
+    /// 追保中的账户（追保及以上级别，按截止时间升序，最先到期的排在前面）
+    pub fn margin_call_states(&self) -> Vec<MarginCallState> {
+        let mut states: Vec<MarginCallState> = self
+            .states
+            .iter()
+            .filter(|s| s.level >= MarginCallLevel::MarginCall)
+            .map(|s| s.value().clone())
+            .collect();
+        states.sort_by_key(|s| s.deadline.unwrap_or(i64::MAX));
+        states
+    }
+
+    /// 账户预警历史
+    pub fn get_history(&self, account_id: &str) -> Vec<MarginCallEvent> {
+        self.history
+            .get(account_id)
+            .map(|h| h.value().clone())
+            .unwrap_or_default()
+    }
+
+    /// 按最近一条事件恢复账户预警状态（重启后不重复推送同级别预警）
+    fn restore_state(&self, event: &MarginCallEvent) {
+        self.states.insert(
+            event.account_id.clone(),
+            MarginCallState {
+                account_id: event.account_id.clone(),
+                level: event.level,
+                risk_ratio: event.risk_ratio,
+                deadline: event.deadline,
+                liquidation_triggered: event.liquidate,
+                updated_at: event.timestamp,
+            },
+        );
+    }
+
+    fn push_history(&self, event: MarginCallEvent) {
+        let max_events = self.config.read().max_events_per_account;
+        let mut events = self.history.entry(event.account_id.clone()).or_default();
+        events.push(event);
+        if events.len() > max_events {
+            let drop = events.len() - max_events;
+            events.drain(0..drop);
+        }
+    }
+
+    fn persist(&self, event: &MarginCallEvent) {
+        let guard = self.persist_path.lock();
+        let Some(ref path) = *guard else {
+            return;
+        };
+        let result = serde_json::to_string(event)
+            .map_err(|e| e.to_string())
+            .and_then(|line| {
+                let mut file = std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .map_err(|e| e.to_string())?;
+                writeln!(file, "{}", line).map_err(|e| e.to_string())
+            });
+        if let Err(e) = result {
+            log::error!("[MarginCall] Failed to persist event to {:?}: {}", path, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ladder_escalates_level_by_level_with_release() {
+        let ladder = MarginCallLadder::default();
+        let base = 1_700_000_000_000i64;
+        // 保证金 100000，权益逐步下降 → 风险度逐级上升
+        let step = |ratio: f64, t: i64| {
+            ladder.evaluate("acc", ratio, 100000.0 / ratio, 100000.0, base + t)
+        };
+
+        assert!(step(0.5, 0).is_none());
+
+        let reminder = step(0.82, 1000).unwrap();
+        assert_eq!(reminder.level, MarginCallLevel::Reminder);
+        assert_eq!(reminder.from_level, MarginCallLevel::Normal);
+        // 追加到风险度 80%: 100000 / 0.8 - 100000 / 0.82
+        assert!((reminder.required_margin - (125000.0 - 100000.0 / 0.82)).abs() < 1e-6);
+        assert!(reminder.deadline.is_none());
+
+        // 同级别不重复推送
+        assert!(step(0.85, 2000).is_none());
+
+        let warning = step(0.91, 3000).unwrap();
+        assert_eq!(warning.level, MarginCallLevel::Warning);
+
+        let margin_call = step(1.01, 4000).unwrap();
+        assert_eq!(margin_call.level, MarginCallLevel::MarginCall);
+        assert_eq!(
+            margin_call.deadline,
+            Some(base + 4000 + default_margin_call_deadline_ms())
+        );
+        assert!(!margin_call.liquidate);
+
+        // 回差内小幅回落不降级
+        assert!(step(0.99, 5000).is_none());
+        let released = step(0.97, 6000).unwrap();
+        assert!(released.released);
+        assert_eq!(released.from_level, MarginCallLevel::MarginCall);
+        assert_eq!(released.level, MarginCallLevel::Warning);
+        assert!(released.deadline.is_none());
+
+        let liquidation = step(1.12, 7000).unwrap();
+        assert_eq!(liquidation.level, MarginCallLevel::Liquidation);
+        assert!(liquidation.liquidate);
+        // 同一轮只触发一次强平
+        assert!(step(1.15, 8000).is_none());
+
+        let levels: Vec<_> = ladder.get_history("acc").iter().map(|e| e.level).collect();
+        assert_eq!(
+            levels,
+            vec![
+                MarginCallLevel::Reminder,
+                MarginCallLevel::Warning,
+                MarginCallLevel::MarginCall,
+                MarginCallLevel::Warning,
+                MarginCallLevel::Liquidation,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_margin_call_liquidates_only_after_deadline() {
+        let ladder = MarginCallLadder::new(MarginCallConfig {
+            margin_call_deadline_ms: 60_000,
+            ..Default::default()
+        });
+        let base = 1_700_000_000_000i64;
+
+        let event = ladder
+            .evaluate("acc", 1.02, 98000.0, 100000.0, base)
+            .unwrap();
+        assert_eq!(event.level, MarginCallLevel::MarginCall);
+        assert!(ladder
+            .evaluate("acc", 1.03, 97000.0, 100000.0, base + 59_999)
+            .is_none());
+
+        let overdue = ladder
+            .evaluate("acc", 1.03, 97000.0, 100000.0, base + 60_000)
+            .unwrap();
+        assert!(overdue.liquidate);
+        assert_eq!(overdue.level, MarginCallLevel::MarginCall);
+        assert!(ladder.get_state("acc").unwrap().liquidation_triggered);
+    }
+
+    #[test]
+    fn test_history_persisted_and_reloaded() {
+        let path = std::env::temp_dir().join(format!(
+            "qaexchange_margin_call_{}.jsonl",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+
+        let ladder = MarginCallLadder::default();
+        assert_eq!(ladder.set_persist_path(&path).unwrap(), 0);
+        ladder.evaluate("acc", 0.92, 108000.0, 100000.0, 1000);
+        ladder.evaluate("acc", 0.5, 200000.0, 100000.0, 2000);
+
+        let reloaded = MarginCallLadder::default();
+        assert_eq!(reloaded.set_persist_path(&path).unwrap(), 2);
+        let history = reloaded.get_history("acc");
+        assert_eq!(history[0].level, MarginCallLevel::Warning);
+        assert!(history[1].released);
+        assert_eq!(history[1].level, MarginCallLevel::Normal);
+        assert_eq!(
+            reloaded.get_state("acc").unwrap().level,
+            MarginCallLevel::Normal
+        );
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_grace_period_survives_restart() {
+        let path = std::env::temp_dir().join(format!(
+            "qaexchange_margin_call_grace_{}.jsonl",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        let config = MarginCallConfig {
+            margin_call_deadline_ms: 60_000,
+            ..Default::default()
+        };
+        let base = 1_700_000_000_000i64;
+
+        let ladder = MarginCallLadder::new(config.clone());
+        ladder.set_persist_path(&path).unwrap();
+        let event = ladder
+            .evaluate("acc", 1.02, 98000.0, 100000.0, base)
+            .unwrap();
+        assert_eq!(event.deadline, Some(base + 60_000));
+
+        // 重启后沿用原截止时间，不重新计时
+        let reloaded = MarginCallLadder::new(config);
+        assert_eq!(reloaded.set_persist_path(&path).unwrap(), 1);
+        let states = reloaded.margin_call_states();
+        assert_eq!(states.len(), 1);
+        assert_eq!(states[0].deadline, Some(base + 60_000));
+        assert!(reloaded
+            .evaluate("acc", 1.03, 97000.0, 100000.0, base + 30_000)
+            .is_none());
+
+        // 宽限期结束仍超标，转入强平
+        let overdue = reloaded
+            .evaluate("acc", 1.03, 97000.0, 100000.0, base + 60_000)
+            .unwrap();
+        assert!(overdue.liquidate);
+        assert_eq!(overdue.deadline, Some(base + 60_000));
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
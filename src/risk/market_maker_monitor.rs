@@ -0,0 +1,728 @@
+//! 做市商义务监测与考核
+//!
+//! 交易所对做市商有报价义务（在盘时间、价差、深度），本模块按配置的义务参数
+//! 周期采样指定做市账户在各合约的双边报价并按交易日汇总考核：
+//! - **报价采样**: 取做市账户在该合约的未成交挂单，最优买价/卖价及对应剩余量构成一次双边报价
+//! - **义务时段**: 只在配置的时段（交易所时区，支持跨午夜的夜盘时段）内采样，未配置时全天考核
+//! - **在盘时间占比**: 双边都有报价、价差不超过上限且两侧深度达标的采样数 / 义务时段内采样数
+//! - **报价质量**: 双边报价采样的平均价差（绝对值与相对中间价的比例）、平均买卖深度
+//! - **考核报告**: 交易日结束（出现下一交易日的采样或调用 `finalize_before`）时生成，
+//!   未达标告警并递增 `qaexchange_market_maker_obligation_failed_total{instrument_id}`
+//!
+//! @yutiansut @quantaxis
+
+use crate::exchange::order_router::OrderStatus;
+use crate::exchange::OrderRouter;
+use crate::observability::metrics::MARKET_MAKER_OBLIGATION_FAILED_TOTAL;
+use crate::risk::portfolio_margin::product_of;
+use crate::utils::time_service::time_service;
+use crate::ExchangeError;
+use chrono::{NaiveDate, NaiveTime};
+use dashmap::DashMap;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+/// 保留的考核报告条数
+const MAX_REPORTS: usize = 10_000;
+
+/// 义务时段（交易所本地时间，`HH:MM` 或 `HH:MM:SS`，start > end 表示跨午夜）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ObligationSession {
+    pub start: String,
+    pub end: String,
+}
+
+impl ObligationSession {
+    pub fn new(start: &str, end: &str) -> Self {
+        Self {
+            start: start.to_string(),
+            end: end.to_string(),
+        }
+    }
+
+    fn parse(&self) -> Result<(NaiveTime, NaiveTime), ExchangeError> {
+        let parse = |s: &str| {
+            NaiveTime::parse_from_str(s, "%H:%M:%S")
+                .or_else(|_| NaiveTime::parse_from_str(s, "%H:%M"))
+                .map_err(|_| {
+                    ExchangeError::InvalidParameter(format!(
+                        "Invalid market maker obligation session time: {}",
+                        s
+                    ))
+                })
+        };
+        Ok((parse(&self.start)?, parse(&self.end)?))
+    }
+
+    fn contains(&self, time: NaiveTime) -> bool {
+        match self.parse() {
+            Ok((start, end)) if start <= end => time >= start && time < end,
+            Ok((start, end)) => time >= start || time < end,
+            Err(_) => false,
+        }
+    }
+}
+
+/// 做市义务参数
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MarketMakerObligation {
+    /// 最大买卖价差（相对中间价的比例，如 0.005 = 0.5%）
+    pub max_spread_ratio: f64,
+    /// 每侧最小报价量（手）
+    pub min_depth: f64,
+    /// 最小在盘时间占比（0~1）
+    pub min_uptime_ratio: f64,
+    /// 义务时段（为空表示全天考核）
+    #[serde(default)]
+    pub sessions: Vec<ObligationSession>,
+}
+
+impl Default for MarketMakerObligation {
+    fn default() -> Self {
+        Self {
+            max_spread_ratio: 0.005,
+            min_depth: 1.0,
+            min_uptime_ratio: 0.8,
+            sessions: Vec::new(),
+        }
+    }
+}
+
+impl MarketMakerObligation {
+    fn validate(&self, scope: &str) -> Result<(), ExchangeError> {
+        if self.max_spread_ratio.is_nan() || self.max_spread_ratio <= 0.0 {
+            return Err(ExchangeError::InvalidParameter(format!(
+                "Market maker max_spread_ratio of {} must be greater than 0, got {}",
+                scope, self.max_spread_ratio
+            )));
+        }
+        if self.min_depth.is_nan() || self.min_depth < 0.0 {
+            return Err(ExchangeError::InvalidParameter(format!(
+                "Market maker min_depth of {} must not be negative, got {}",
+                scope, self.min_depth
+            )));
+        }
+        if !(self.min_uptime_ratio > 0.0 && self.min_uptime_ratio <= 1.0) {
+            return Err(ExchangeError::InvalidParameter(format!(
+                "Market maker min_uptime_ratio of {} must be in (0, 1], got {}",
+                scope, self.min_uptime_ratio
+            )));
+        }
+        for session in &self.sessions {
+            session.parse()?;
+        }
+        Ok(())
+    }
+
+    /// 是否处于义务时段
+    pub fn in_session(&self, time: NaiveTime) -> bool {
+        self.sessions.is_empty() || self.sessions.iter().any(|s| s.contains(time))
+    }
+}
+
+fn default_sample_interval_ms() -> u64 {
+    1_000
+}
+
+/// 做市商考核配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarketMakerMonitorConfig {
+    /// 是否启用
+    #[serde(default)]
+    pub enabled: bool,
+    /// 采样间隔（毫秒）
+    #[serde(default = "default_sample_interval_ms")]
+    pub sample_interval_ms: u64,
+    /// 默认义务参数（未单独配置的合约使用）
+    #[serde(default)]
+    pub default: MarketMakerObligation,
+    /// 按合约或品种（如 IF）配置的义务参数，合约优先于品种
+    #[serde(default)]
+    pub instruments: HashMap<String, MarketMakerObligation>,
+    /// 做市账户 -> 负有做市义务的合约
+    #[serde(default)]
+    pub makers: HashMap<String, Vec<String>>,
+}
+
+impl Default for MarketMakerMonitorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sample_interval_ms: default_sample_interval_ms(),
+            default: MarketMakerObligation::default(),
+            instruments: HashMap::new(),
+            makers: HashMap::new(),
+        }
+    }
+}
+
+impl MarketMakerMonitorConfig {
+    pub fn validate(&self) -> Result<(), ExchangeError> {
+        if self.sample_interval_ms == 0 {
+            return Err(ExchangeError::InvalidParameter(
+                "Market maker sample_interval_ms must be greater than 0".to_string(),
+            ));
+        }
+        self.default.validate("default")?;
+        for (instrument_id, obligation) in &self.instruments {
+            obligation.validate(instrument_id)?;
+        }
+        Ok(())
+    }
+
+    /// 合约适用的义务参数
+    pub fn obligation_for(&self, instrument_id: &str) -> &MarketMakerObligation {
+        self.instruments
+            .get(instrument_id)
+            .or_else(|| self.instruments.get(product_of(instrument_id)))
+            .unwrap_or(&self.default)
+    }
+}
+
+/// 做市商某一时刻在某合约上的报价（无挂单的一侧为 None）
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct MakerQuote {
+    /// (最优买价, 该价位剩余量)
+    pub bid: Option<(f64, f64)>,
+    /// (最优卖价, 该价位剩余量)
+    pub ask: Option<(f64, f64)>,
+}
+
+impl MakerQuote {
+    pub fn two_sided(bid_price: f64, bid_volume: f64, ask_price: f64, ask_volume: f64) -> Self {
+        Self {
+            bid: Some((bid_price, bid_volume)),
+            ask: Some((ask_price, ask_volume)),
+        }
+    }
+
+    /// 从做市账户的挂单汇总报价 (direction, price, remaining_volume)
+    pub fn from_orders<'a>(orders: impl IntoIterator<Item = (&'a str, f64, f64)>) -> Self {
+        let mut quote = Self::default();
+        for (direction, price, remaining) in orders {
+            if remaining <= 0.0 {
+                continue;
+            }
+            let is_bid = match direction {
+                "BUY" => true,
+                "SELL" => false,
+                _ => continue,
+            };
+            let side = if is_bid {
+                &mut quote.bid
+            } else {
+                &mut quote.ask
+            };
+            match side {
+                Some((best, volume)) if *best == price => *volume += remaining,
+                Some((best, _)) if (is_bid && price < *best) || (!is_bid && price > *best) => {}
+                _ => *side = Some((price, remaining)),
+            }
+        }
+        quote
+    }
+}
+
+/// 单账户单合约当日采样累计
+#[derive(Debug)]
+struct QuoteCounter {
+    trading_day: NaiveDate,
+    /// 义务时段内采样数
+    samples: u64,
+    /// 达标（在盘）采样数
+    qualified_samples: u64,
+    /// 双边报价采样数（报价质量的分母）
+    two_sided_samples: u64,
+    spread_sum: f64,
+    spread_ratio_sum: f64,
+    bid_depth_sum: f64,
+    ask_depth_sum: f64,
+}
+
+impl QuoteCounter {
+    fn new(trading_day: NaiveDate) -> Self {
+        Self {
+            trading_day,
+            samples: 0,
+            qualified_samples: 0,
+            two_sided_samples: 0,
+            spread_sum: 0.0,
+            spread_ratio_sum: 0.0,
+            bid_depth_sum: 0.0,
+            ask_depth_sum: 0.0,
+        }
+    }
+
+    fn record(&mut self, quote: &MakerQuote, obligation: &MarketMakerObligation) {
+        self.samples += 1;
+        let (Some((bid, bid_volume)), Some((ask, ask_volume))) = (quote.bid, quote.ask) else {
+            return;
+        };
+        let mid = (bid + ask) / 2.0;
+        if mid <= 0.0 {
+            return;
+        }
+        let spread = ask - bid;
+        let spread_ratio = spread / mid;
+        self.two_sided_samples += 1;
+        self.spread_sum += spread;
+        self.spread_ratio_sum += spread_ratio;
+        self.bid_depth_sum += bid_volume;
+        self.ask_depth_sum += ask_volume;
+
+        if spread_ratio <= obligation.max_spread_ratio
+            && bid_volume >= obligation.min_depth
+            && ask_volume >= obligation.min_depth
+        {
+            self.qualified_samples += 1;
+        }
+    }
+
+    fn report(
+        &self,
+        account_id: &str,
+        instrument_id: &str,
+        obligation: &MarketMakerObligation,
+    ) -> MarketMakerReport {
+        let ratio = |sum: f64, n: u64| if n == 0 { 0.0 } else { sum / n as f64 };
+        let uptime_ratio = ratio(self.qualified_samples as f64, self.samples);
+        let avg_spread_ratio = ratio(self.spread_ratio_sum, self.two_sided_samples);
+        let avg_bid_depth = ratio(self.bid_depth_sum, self.two_sided_samples);
+        let avg_ask_depth = ratio(self.ask_depth_sum, self.two_sided_samples);
+
+        let mut failures = Vec::new();
+        if uptime_ratio < obligation.min_uptime_ratio {
+            failures.push(format!(
+                "uptime {:.2}% below {:.2}%",
+                uptime_ratio * 100.0,
+                obligation.min_uptime_ratio * 100.0
+            ));
+        }
+        if self.two_sided_samples > 0 && avg_spread_ratio > obligation.max_spread_ratio {
+            failures.push(format!(
+                "average spread {:.4}% above {:.4}%",
+                avg_spread_ratio * 100.0,
+                obligation.max_spread_ratio * 100.0
+            ));
+        }
+        if self.two_sided_samples > 0 && avg_bid_depth.min(avg_ask_depth) < obligation.min_depth {
+            failures.push(format!(
+                "average depth bid={:.2}/ask={:.2} below {}",
+                avg_bid_depth, avg_ask_depth, obligation.min_depth
+            ));
+        }
+
+        MarketMakerReport {
+            account_id: account_id.to_string(),
+            instrument_id: instrument_id.to_string(),
+            trading_day: self.trading_day.format("%Y-%m-%d").to_string(),
+            samples: self.samples,
+            qualified_samples: self.qualified_samples,
+            uptime_ratio,
+            avg_spread: ratio(self.spread_sum, self.two_sided_samples),
+            avg_spread_ratio,
+            avg_bid_depth,
+            avg_ask_depth,
+            passed: failures.is_empty(),
+            failures,
+        }
+    }
+}
+
+/// 做市商考核报告（单账户单合约单交易日）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarketMakerReport {
+    pub account_id: String,
+    pub instrument_id: String,
+    pub trading_day: String,
+    /// 义务时段内采样数
+    pub samples: u64,
+    /// 达标（在盘）采样数
+    pub qualified_samples: u64,
+    /// 在盘时间占比
+    pub uptime_ratio: f64,
+    /// 平均价差（双边报价采样）
+    pub avg_spread: f64,
+    /// 平均价差占中间价比例
+    pub avg_spread_ratio: f64,
+    pub avg_bid_depth: f64,
+    pub avg_ask_depth: f64,
+    /// 是否达标
+    pub passed: bool,
+    /// 未达标项
+    pub failures: Vec<String>,
+}
+
+/// 做市商义务监测
+pub struct MarketMakerMonitor {
+    config: RwLock<MarketMakerMonitorConfig>,
+    /// 订单路由器（弱引用，避免循环引用）
+    order_router: RwLock<Option<Weak<OrderRouter>>>,
+    counters: DashMap<(String, String), QuoteCounter>,
+    /// 已结束交易日的考核报告
+    reports: Mutex<VecDeque<MarketMakerReport>>,
+}
+
+impl MarketMakerMonitor {
+    pub fn new(config: MarketMakerMonitorConfig) -> Result<Self, ExchangeError> {
+        config.validate()?;
+        Ok(Self {
+            config: RwLock::new(config),
+            order_router: RwLock::new(None),
+            counters: DashMap::new(),
+            reports: Mutex::new(VecDeque::new()),
+        })
+    }
+
+    /// 设置订单路由器（读取做市账户挂单）
+    pub fn set_order_router(&self, router: &Arc<OrderRouter>) {
+        *self.order_router.write() = Some(Arc::downgrade(router));
+    }
+
+    fn router(&self) -> Option<Arc<OrderRouter>> {
+        self.order_router.read().as_ref().and_then(|w| w.upgrade())
+    }
+
+    /// 当前配置
+    pub fn config(&self) -> MarketMakerMonitorConfig {
+        self.config.read().clone()
+    }
+
+    /// 更新配置（当日已采样数据保留）
+    pub fn update_config(&self, config: MarketMakerMonitorConfig) -> Result<(), ExchangeError> {
+        config.validate()?;
+        log::info!(
+            "[MarketMaker] Config updated: enabled={}, makers={}, instruments={}",
+            config.enabled,
+            config.makers.len(),
+            config.instruments.len()
+        );
+        *self.config.write() = config;
+        Ok(())
+    }
+
+    /// 记录一次报价采样（义务时段外的采样忽略）
+    ///
+    /// 出现新交易日的采样时，上一交易日的累计生成考核报告
+    pub fn sample_at(&self, account_id: &str, instrument_id: &str, quote: MakerQuote, now_ms: i64) {
+        let config = self.config.read();
+        if !config.enabled {
+            return;
+        }
+        let obligation = config.obligation_for(instrument_id);
+        let service = time_service();
+        if !obligation.in_session(service.to_local_millis(now_ms).time()) {
+            return;
+        }
+
+        let trading_day = service.trading_day_of(now_ms);
+        let key = (account_id.to_string(), instrument_id.to_string());
+        let finished = {
+            let mut entry = self
+                .counters
+                .entry(key)
+                .or_insert_with(|| QuoteCounter::new(trading_day));
+            let counter = entry.value_mut();
+            let finished = (counter.trading_day < trading_day).then(|| {
+                std::mem::replace(counter, QuoteCounter::new(trading_day)).report(
+                    account_id,
+                    instrument_id,
+                    obligation,
+                )
+            });
+            counter.record(&quote, obligation);
+            finished
+        };
+        drop(config);
+
+        if let Some(report) = finished {
+            self.archive(report);
+        }
+    }
+
+    /// 按配置采样全部做市账户（报价取自订单路由器中的未成交挂单）
+    pub fn sample_all_at(&self, now_ms: i64) -> usize {
+        let Some(router) = self.router() else {
+            return 0;
+        };
+        let makers = {
+            let config = self.config.read();
+            if !config.enabled {
+                return 0;
+            }
+            config.makers.clone()
+        };
+
+        let mut sampled = 0;
+        for (account_id, instruments) in &makers {
+            let orders = router.get_user_order_details(account_id);
+            for instrument_id in instruments {
+                let quote = MakerQuote::from_orders(
+                    orders
+                        .iter()
+                        .filter(|(_, order, status, _, _, _)| {
+                            &order.instrument_id == instrument_id
+                                && matches!(
+                                    status,
+                                    OrderStatus::Submitted | OrderStatus::PartiallyFilled
+                                )
+                        })
+                        .map(|(_, order, _, _, _, filled)| {
+                            (
+                                order.direction.as_str(),
+                                order.limit_price,
+                                order.volume_orign - filled,
+                            )
+                        }),
+                );
+                self.sample_at(account_id, instrument_id, quote, now_ms);
+                sampled += 1;
+            }
+        }
+        sampled
+    }
+
+    /// 结束早于指定交易日的累计，生成考核报告，返回其中未达标的报告
+    pub fn finalize_before(&self, trading_day: NaiveDate) -> Vec<MarketMakerReport> {
+        let stale: Vec<(String, String)> = self
+            .counters
+            .iter()
+            .filter(|c| c.trading_day < trading_day)
+            .map(|c| c.key().clone())
+            .collect();
+
+        let config = self.config.read().clone();
+        let mut failed = Vec::new();
+        for key in stale {
+            let Some(((account_id, instrument_id), counter)) = self
+                .counters
+                .remove_if(&key, |_, c| c.trading_day < trading_day)
+            else {
+                continue;
+            };
+            let report = counter.report(
+                &account_id,
+                &instrument_id,
+                config.obligation_for(&instrument_id),
+            );
+            if !report.passed {
+                failed.push(report.clone());
+            }
+            self.archive(report);
+        }
+        failed
+    }
+
+    /// 当前交易日截至目前的考核情况
+    pub fn current_reports(&self) -> Vec<MarketMakerReport> {
+        let config = self.config.read();
+        let mut reports: Vec<MarketMakerReport> = self
+            .counters
+            .iter()
+            .map(|c| {
+                let (account_id, instrument_id) = c.key();
+                c.report(
+                    account_id,
+                    instrument_id,
+                    config.obligation_for(instrument_id),
+                )
+            })
+            .collect();
+        reports.sort_by(|a, b| {
+            (&a.account_id, &a.instrument_id).cmp(&(&b.account_id, &b.instrument_id))
+        });
+        reports
+    }
+
+    /// 已结束交易日的考核报告（可按账户、交易日 YYYY-MM-DD 过滤，新的在前）
+    pub fn reports(
+        &self,
+        account_id: Option<&str>,
+        trading_day: Option<&str>,
+        limit: usize,
+    ) -> Vec<MarketMakerReport> {
+        self.reports
+            .lock()
+            .iter()
+            .rev()
+            .filter(|r| account_id.map_or(true, |a| r.account_id == a))
+            .filter(|r| trading_day.map_or(true, |d| r.trading_day == d))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    /// 保存报告，未达标告警
+    fn archive(&self, report: MarketMakerReport) {
+        if report.passed {
+            log::info!(
+                "[MarketMaker] {} on {} passed obligations for {}: uptime={:.2}%",
+                report.account_id,
+                report.instrument_id,
+                report.trading_day,
+                report.uptime_ratio * 100.0
+            );
+        } else {
+            MARKET_MAKER_OBLIGATION_FAILED_TOTAL
+                .with_label_values(&[&report.instrument_id])
+                .inc();
+            log::warn!(
+                "[MarketMaker] {} on {} failed obligations for {}: {}",
+                report.account_id,
+                report.instrument_id,
+                report.trading_day,
+                report.failures.join("; ")
+            );
+        }
+
+        let mut reports = self.reports.lock();
+        if reports.len() >= MAX_REPORTS {
+            reports.pop_front();
+        }
+        reports.push_back(report);
+    }
+
+    /// 启动后台采样线程（按配置的采样间隔）
+    pub fn start_sampler(self: &Arc<Self>) -> std::thread::JoinHandle<()> {
+        let monitor = Arc::downgrade(self);
+        std::thread::spawn(move || loop {
+            let interval = match monitor.upgrade() {
+                Some(monitor) => {
+                    let now_ms = chrono::Utc::now().timestamp_millis();
+                    monitor.sample_all_at(now_ms);
+                    monitor.finalize_before(time_service().trading_day_of(now_ms));
+                    monitor.config.read().sample_interval_ms
+                }
+                None => break,
+            };
+            std::thread::sleep(Duration::from_millis(interval));
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, TimeZone};
+
+    fn cst_ms(d: u32, h: u32, m: u32) -> i64 {
+        FixedOffset::east_opt(8 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2025, 1, d, h, m, 0)
+            .unwrap()
+            .timestamp_millis()
+    }
+
+    fn monitor() -> MarketMakerMonitor {
+        let mut instruments = HashMap::new();
+        instruments.insert(
+            "IF".to_string(),
+            MarketMakerObligation {
+                max_spread_ratio: 0.001,
+                min_depth: 5.0,
+                min_uptime_ratio: 0.8,
+                sessions: vec![ObligationSession::new("09:30", "11:30")],
+            },
+        );
+        MarketMakerMonitor::new(MarketMakerMonitorConfig {
+            enabled: true,
+            instruments,
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_quote_from_orders_and_sessions() {
+        let quote = MakerQuote::from_orders(vec![
+            ("BUY", 99.0, 3.0),
+            ("BUY", 100.0, 2.0),
+            ("BUY", 100.0, 4.0),
+            ("SELL", 101.0, 0.0),
+            ("SELL", 102.0, 5.0),
+            ("SELL", 103.0, 1.0),
+        ]);
+        assert_eq!(quote.bid, Some((100.0, 6.0)));
+        assert_eq!(quote.ask, Some((102.0, 5.0)));
+        assert_eq!(MakerQuote::from_orders(vec![("SELL", 1.0, 1.0)]).bid, None);
+
+        let night = ObligationSession::new("21:00", "02:30");
+        let at = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        assert!(night.contains(at(23, 59)));
+        assert!(night.contains(at(1, 0)));
+        assert!(!night.contains(at(10, 0)));
+
+        assert!(MarketMakerMonitor::new(MarketMakerMonitorConfig {
+            default: MarketMakerObligation {
+                sessions: vec![ObligationSession::new("9点", "11:30")],
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .is_err());
+    }
+
+    /// 在盘时间不足的做市商被标记未达标，达标合约通过
+    #[test]
+    fn test_insufficient_uptime_marked_failed() {
+        let monitor = monitor();
+        let good = MakerQuote::two_sided(4000.0, 10.0, 4002.0, 10.0);
+        let wide = MakerQuote::two_sided(4000.0, 10.0, 4020.0, 10.0);
+        let one_sided = MakerQuote {
+            bid: Some((4000.0, 10.0)),
+            ask: None,
+        };
+
+        // IF2501：10 次采样中 6 次达标（价差过宽 2 次、单边 2 次）
+        for i in 0..10 {
+            let quote = match i {
+                0 | 1 => wide,
+                2 | 3 => one_sided,
+                _ => good,
+            };
+            monitor.sample_at("mm1", "IF2501", quote, cst_ms(8, 10, i));
+        }
+        // 义务时段外的采样不计入
+        monitor.sample_at("mm1", "IF2501", MakerQuote::default(), cst_ms(8, 12, 0));
+        // cu2501 使用默认义务参数（全天、0.5% 价差），全部达标
+        for i in 0..5 {
+            monitor.sample_at(
+                "mm1",
+                "cu2501",
+                MakerQuote::two_sided(70000.0, 2.0, 70100.0, 2.0),
+                cst_ms(8, 14, i),
+            );
+        }
+
+        let current = monitor.current_reports();
+        assert_eq!(current.len(), 2);
+        let if_report = current
+            .iter()
+            .find(|r| r.instrument_id == "IF2501")
+            .unwrap();
+        assert_eq!(if_report.samples, 10);
+        assert_eq!(if_report.qualified_samples, 6);
+        assert!((if_report.uptime_ratio - 0.6).abs() < 1e-9);
+        assert!(!if_report.passed);
+
+        // 日切生成报告：IF2501 未达标，cu2501 达标
+        let next_day = time_service().trading_day_of(cst_ms(8, 21, 0));
+        let failed = monitor.finalize_before(next_day);
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].instrument_id, "IF2501");
+        assert_eq!(failed[0].trading_day, "2025-01-08");
+        assert!(failed[0].failures[0].starts_with("uptime"));
+        // 价差过宽的采样拉高平均价差
+        assert!(failed[0].avg_spread_ratio > 0.001);
+
+        let reports = monitor.reports(Some("mm1"), Some("2025-01-08"), 10);
+        assert_eq!(reports.len(), 2);
+        assert!(reports
+            .iter()
+            .any(|r| r.instrument_id == "cu2501" && r.passed));
+        assert!(monitor.current_reports().is_empty());
+    }
+}
@@ -3,13 +3,78 @@
 //! ## 功能概述
 //! - **盘前风控**: PreTradeCheck - 订单提交前的资金、持仓、风险检查
 //! - **盘中风控**: RiskMonitor - 实时监控账户风险，自动预警和强平触发
+//! - **拒单统计**: RejectionStats - 统一拒单原因码，按原因/合约/小时聚合
+//! - **涨跌停板**: PriceLimitManager - 涨跌停价格校验与连续停板动态扩板
+//! - **频率限制**: OrderRateLimiter - 按账户类型配置的下单/撤单令牌桶限流
+//! - **风险回放**: RiskHistoryStore - 风险率/保证金时序采样、降采样查询
+//! - **预警阶梯**: MarginCallLadder - 提醒/警告/追保/强平分级预警，追保逾期才强平
+//! - **账户止损**: AccountStopLoss - 当日亏损达用户止损线时暂停交易并市价平仓，次日恢复
+//! - **组合保证金**: PortfolioMargin - 跨期价差/跨品种对冲按净风险收取保证金，可与逐合约模式切换
+//! - **成交量限额**: TradeVolumeLimiter - 单用户单合约单日成交量达上限后拒绝新单或只允许平仓
+//! - **做市商考核**: MarketMakerMonitor - 双边报价在盘时间、价差、深度按义务参数逐日考核
+//! - **持仓集中度**: PositionConcentration - 单账户占合约全市场总持仓比例超阈值时告警并限制开仓
+//! - **重复订单**: DuplicateOrderGuard - 同一账户短时间内同参数/同幂等键的订单拒绝或告警
+//! - **自动追保**: AutoMarginTopUp - 进入追保区间时经可插拔资金接口自动入金补足保证金，失败回退强平
 //!
 //! @yutiansut @quantaxis
 
+pub mod auto_margin;
+pub mod duplicate_order;
+pub mod margin_call;
+pub mod market_maker_monitor;
+pub mod order_rate_limit;
+pub mod portfolio_margin;
+pub mod position_concentration;
 pub mod pre_trade_check;
+pub mod price_limit;
+pub mod rejection_stats;
+pub mod risk_history;
 pub mod risk_monitor;
+pub mod stop_loss;
+pub mod trade_volume_limit;
 
-pub use pre_trade_check::PreTradeCheck;
+pub use auto_margin::{
+    AutoMarginConfig, AutoMarginProvider, AutoMarginRecord, AutoMarginRequest, AutoMarginStatus,
+    AutoMarginTopUp, SimulatedMarginProvider,
+};
+pub use duplicate_order::{
+    AccountDuplicateHits, DuplicateMatch, DuplicateOrderAction, DuplicateOrderConfig,
+    DuplicateOrderEvent, DuplicateOrderGuard, DuplicateOrderStats, OrderFingerprint,
+};
+pub use margin_call::{
+    MarginCallConfig, MarginCallEvent, MarginCallLadder, MarginCallLevel, MarginCallState,
+};
+pub use market_maker_monitor::{
+    MakerQuote, MarketMakerMonitor, MarketMakerMonitorConfig, MarketMakerObligation,
+    MarketMakerReport, ObligationSession,
+};
+pub use order_rate_limit::{
+    AccountRateLimitHits, CancelRatioAction, CancelRatioConfig, CancelRatioSnapshot,
+    OrderRateLimitConfig, OrderRateLimiter, RateLimitAction, RateLimitRule, RateLimitStats,
+};
+pub use portfolio_margin::{
+    CrossHedgeOffset, CrossHedgeRule, MarginLeg, MarginMode, PortfolioMargin,
+    PortfolioMarginConfig, PortfolioMarginResult, ProductMargin,
+};
+pub use position_concentration::{
+    ConcentrationAction, ConcentrationBreach, PositionConcentration, PositionConcentrationConfig,
+    PositionHolding,
+};
+pub use pre_trade_check::{
+    normalize_to_tick, PreTradeCheck, PriceBandConfig, PriceBandWidth, PriceTickMode,
+    ReferenceQuote,
+};
+pub use price_limit::{
+    LimitBandAction, LimitBandEvent, LimitBandState, LimitDirection, LimitExpansionConfig,
+    PriceLimitManager,
+};
+pub use rejection_stats::{
+    RejectReason, RejectionGroup, RejectionGroupBy, RejectionStats, RejectionSummary,
+};
+pub use risk_history::{
+    RiskHistoryConfig, RiskHistoryPoint, RiskHistoryStats, RiskHistoryStore, RiskSnapshot,
+    MARKET_RISK_KEY,
+};
 pub use risk_monitor::{
     LiquidationCallback,
     LiquidationRecord,
@@ -22,3 +87,11 @@ pub use risk_monitor::{
     RiskMonitor,
     RiskMonitorConfig,
 };
+pub use stop_loss::{
+    AccountStopLoss, StopLossConfig, StopLossLine, StopLossState, StopLossStatus,
+    STOP_LOSS_OPERATOR,
+};
+pub use trade_volume_limit::{
+    AccountTypeVolumeRules, TradeVolumeAlert, TradeVolumeLimitConfig, TradeVolumeLimiter,
+    TradeVolumeRule, TradeVolumeUsage, VolumeLimitAction,
+};
@@ -0,0 +1,877 @@
+//! 账户级下单/撤单/改单频率限制与报撤比管控
+//!
+//! 业务层限流，独立于接入层（HTTP/WebSocket）的连接限流：
+//! - 每个账户分别维护下单、撤单、改单三个令牌桶，容量为窗口内允许的最大次数，
+//!   按 `max / window` 的速率匀速回补，突发与平均速率同时受控
+//! - 限流规则按账户类型（个人/机构/做市商）配置，做市商等 VIP 账户可配置更高阈值或豁免
+//! - 单账户可单独覆盖规则或加入豁免名单
+//! - 超限时拒绝并告警（同一账户每秒最多一条告警日志），同时递增
+//!   `qaexchange_account_rate_limited_total{action, account_type}`
+//! - 报撤比：按秒分桶的滑动窗口统计下单数与撤改单数，撤改单时若
+//!   `(撤单数 + 改单数) / 下单数` 超过阈值则告警或拒绝（`CancelRatioAction`），
+//!   窗口内撤改单数不足 `min_cancels` 时不判定，避免少量撤单即触发；做市商豁免
+//!
+//! @yutiansut @quantaxis
+
+use crate::core::account_ext::AccountType;
+use crate::observability::metrics::{ACCOUNT_RATE_LIMITED_TOTAL, CANCEL_RATIO_EXCEEDED_TOTAL};
+use dashmap::DashMap;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// 告警日志最小间隔（同一账户）
+const ALERT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 限流操作类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitAction {
+    /// 下单
+    Order,
+    /// 撤单
+    Cancel,
+    /// 改单
+    Amend,
+}
+
+impl RateLimitAction {
+    pub fn label(&self) -> &'static str {
+        match self {
+            RateLimitAction::Order => "order",
+            RateLimitAction::Cancel => "cancel",
+            RateLimitAction::Amend => "amend",
+        }
+    }
+}
+
+/// 限流规则：`window_ms` 窗口内最多 `max_orders` 次下单、`max_cancels` 次撤单、
+/// `max_amends` 次改单
+///
+/// 上限为 0 表示该操作不限制
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RateLimitRule {
+    pub window_ms: u64,
+    pub max_orders: u32,
+    pub max_cancels: u32,
+    pub max_amends: u32,
+}
+
+impl RateLimitRule {
+    /// 改单上限默认与撤单上限相同，可通过 `with_max_amends` 单独设置
+    pub fn new(window_ms: u64, max_orders: u32, max_cancels: u32) -> Self {
+        Self {
+            window_ms,
+            max_orders,
+            max_cancels,
+            max_amends: max_cancels,
+        }
+    }
+
+    pub fn with_max_amends(mut self, max_amends: u32) -> Self {
+        self.max_amends = max_amends;
+        self
+    }
+
+    fn limit_of(&self, action: RateLimitAction) -> u32 {
+        match action {
+            RateLimitAction::Order => self.max_orders,
+            RateLimitAction::Cancel => self.max_cancels,
+            RateLimitAction::Amend => self.max_amends,
+        }
+    }
+}
+
+fn default_ratio_window_secs() -> u64 {
+    60
+}
+
+fn default_ratio_min_cancels() -> u32 {
+    50
+}
+
+fn default_max_cancel_ratio() -> f64 {
+    10.0
+}
+
+/// 报撤比超限处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CancelRatioAction {
+    /// 仅告警，撤改单照常执行
+    #[default]
+    Alert,
+    /// 拒绝撤改单，直到窗口内报撤比回落
+    Restrict,
+}
+
+/// 报撤比管控配置
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CancelRatioConfig {
+    /// 是否启用
+    #[serde(default)]
+    pub enabled: bool,
+    /// 滑动窗口长度（秒）
+    #[serde(default = "default_ratio_window_secs")]
+    pub window_secs: u64,
+    /// 窗口内撤改单数达到该值才判定报撤比
+    #[serde(default = "default_ratio_min_cancels")]
+    pub min_cancels: u32,
+    /// 报撤比上限（撤改单数 / 下单数）
+    #[serde(default = "default_max_cancel_ratio")]
+    pub max_ratio: f64,
+    /// 超限处理方式
+    #[serde(default)]
+    pub action: CancelRatioAction,
+}
+
+impl Default for CancelRatioConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            window_secs: default_ratio_window_secs(),
+            min_cancels: default_ratio_min_cancels(),
+            max_ratio: default_max_cancel_ratio(),
+            action: CancelRatioAction::Alert,
+        }
+    }
+}
+
+/// 频率限制配置
+#[derive(Debug, Clone)]
+pub struct OrderRateLimitConfig {
+    /// 个人账户规则（None 表示豁免）
+    pub individual: Option<RateLimitRule>,
+    /// 机构账户规则（None 表示豁免）
+    pub institutional: Option<RateLimitRule>,
+    /// 做市商账户规则（None 表示豁免）
+    pub market_maker: Option<RateLimitRule>,
+    /// 豁免账户
+    pub exempt_accounts: HashSet<String>,
+    /// 按账户覆盖的规则（优先于账户类型规则）
+    pub account_overrides: HashMap<String, RateLimitRule>,
+    /// 报撤比管控（做市商与豁免账户不受限）
+    pub cancel_ratio: CancelRatioConfig,
+}
+
+impl Default for OrderRateLimitConfig {
+    fn default() -> Self {
+        Self {
+            individual: Some(RateLimitRule::new(1000, 20, 20)),
+            institutional: Some(RateLimitRule::new(1000, 100, 100)),
+            market_maker: Some(RateLimitRule::new(1000, 500, 500)),
+            exempt_accounts: HashSet::new(),
+            account_overrides: HashMap::new(),
+            cancel_ratio: CancelRatioConfig::default(),
+        }
+    }
+}
+
+impl OrderRateLimitConfig {
+    /// 解析账户适用的规则，None 表示豁免
+    pub fn rule_for(&self, account_id: &str, account_type: AccountType) -> Option<RateLimitRule> {
+        if self.exempt_accounts.contains(account_id) {
+            return None;
+        }
+        if let Some(rule) = self.account_overrides.get(account_id) {
+            return Some(*rule);
+        }
+        match account_type {
+            AccountType::Individual => self.individual,
+            AccountType::Institutional => self.institutional,
+            AccountType::MarketMaker => self.market_maker,
+        }
+    }
+}
+
+/// 令牌桶
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(capacity: f64, now: Instant) -> Self {
+        Self {
+            tokens: capacity,
+            last_refill: now,
+        }
+    }
+
+    /// 回补后尝试取一个令牌
+    fn try_acquire(&mut self, capacity: f64, window_ms: u64, now: Instant) -> bool {
+        // 按亚毫秒精度回补，间隔不足 1ms 的连续请求不丢失回补量
+        let elapsed_ms = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64()
+            * 1000.0;
+        let window = window_ms.max(1) as f64;
+        self.tokens = (self.tokens + elapsed_ms * capacity / window).min(capacity);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// 报撤比窗口中的一秒
+#[derive(Debug, Clone, Copy)]
+struct RatioSlot {
+    sec: u64,
+    orders: u64,
+    cancels: u64,
+}
+
+/// 报撤比滑动窗口（按秒分桶，撤单与改单合计为撤改单数）
+#[derive(Debug, Default)]
+struct RatioWindow {
+    slots: VecDeque<RatioSlot>,
+}
+
+impl RatioWindow {
+    /// 窗口内 (下单数, 撤改单数)
+    fn totals(&self, now_sec: u64, window_secs: u64) -> (u64, u64) {
+        self.slots
+            .iter()
+            .filter(|slot| slot.sec + window_secs > now_sec)
+            .fold((0, 0), |(orders, cancels), slot| {
+                (orders + slot.orders, cancels + slot.cancels)
+            })
+    }
+
+    fn record(&mut self, now_sec: u64, window_secs: u64, action: RateLimitAction) {
+        while self
+            .slots
+            .front()
+            .is_some_and(|slot| slot.sec + window_secs <= now_sec)
+        {
+            self.slots.pop_front();
+        }
+        if self.slots.back().map_or(true, |slot| slot.sec != now_sec) {
+            self.slots.push_back(RatioSlot {
+                sec: now_sec,
+                orders: 0,
+                cancels: 0,
+            });
+        }
+        if let Some(slot) = self.slots.back_mut() {
+            match action {
+                RateLimitAction::Order => slot.orders += 1,
+                RateLimitAction::Cancel | RateLimitAction::Amend => slot.cancels += 1,
+            }
+        }
+    }
+}
+
+/// 单账户状态
+#[derive(Debug)]
+struct AccountBuckets {
+    orders: Option<TokenBucket>,
+    cancels: Option<TokenBucket>,
+    amends: Option<TokenBucket>,
+    order_hits: u64,
+    cancel_hits: u64,
+    amend_hits: u64,
+    ratio_hits: u64,
+    ratio: RatioWindow,
+    last_alert: Option<Instant>,
+}
+
+impl AccountBuckets {
+    fn new() -> Self {
+        Self {
+            orders: None,
+            cancels: None,
+            amends: None,
+            order_hits: 0,
+            cancel_hits: 0,
+            amend_hits: 0,
+            ratio_hits: 0,
+            ratio: RatioWindow::default(),
+            last_alert: None,
+        }
+    }
+
+    fn total_hits(&self) -> u64 {
+        self.order_hits + self.cancel_hits + self.amend_hits + self.ratio_hits
+    }
+
+    /// 告警日志限频：距上次告警超过 `ALERT_INTERVAL` 时返回 true 并记下本次时间
+    fn should_alert(&mut self, now: Instant) -> bool {
+        let due = self
+            .last_alert
+            .map_or(true, |t| now.saturating_duration_since(t) >= ALERT_INTERVAL);
+        if due {
+            self.last_alert = Some(now);
+        }
+        due
+    }
+}
+
+/// 单账户命中统计
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountRateLimitHits {
+    pub account_id: String,
+    pub order_hits: u64,
+    pub cancel_hits: u64,
+    pub amend_hits: u64,
+    /// 报撤比超限次数（含仅告警）
+    pub ratio_hits: u64,
+}
+
+/// 单账户报撤比快照
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CancelRatioSnapshot {
+    pub account_id: String,
+    pub window_secs: u64,
+    /// 窗口内下单数
+    pub orders: u64,
+    /// 窗口内撤改单数
+    pub cancels: u64,
+    /// 撤改单数 / 下单数（无下单时按 1 单计）
+    pub ratio: f64,
+}
+
+/// 频率限制统计
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimitStats {
+    pub orders_allowed: u64,
+    pub orders_limited: u64,
+    pub cancels_allowed: u64,
+    pub cancels_limited: u64,
+    pub amends_allowed: u64,
+    pub amends_limited: u64,
+    /// 报撤比超限仅告警次数
+    pub cancel_ratio_alerts: u64,
+    /// 报撤比超限被拒次数
+    pub cancel_ratio_limited: u64,
+    pub tracked_accounts: usize,
+    /// 命中次数最多的账户（降序）
+    pub top_accounts: Vec<AccountRateLimitHits>,
+}
+
+/// 账户级下单/撤单/改单频率限制器
+pub struct OrderRateLimiter {
+    config: RwLock<OrderRateLimitConfig>,
+    accounts: DashMap<String, AccountBuckets>,
+    /// 报撤比分桶的时间起点
+    epoch: Instant,
+    orders_allowed: AtomicU64,
+    orders_limited: AtomicU64,
+    cancels_allowed: AtomicU64,
+    cancels_limited: AtomicU64,
+    amends_allowed: AtomicU64,
+    amends_limited: AtomicU64,
+    ratio_alerts: AtomicU64,
+    ratio_limited: AtomicU64,
+}
+
+impl OrderRateLimiter {
+    pub fn new(config: OrderRateLimitConfig) -> Self {
+        Self {
+            config: RwLock::new(config),
+            accounts: DashMap::new(),
+            epoch: Instant::now(),
+            orders_allowed: AtomicU64::new(0),
+            orders_limited: AtomicU64::new(0),
+            cancels_allowed: AtomicU64::new(0),
+            cancels_limited: AtomicU64::new(0),
+            amends_allowed: AtomicU64::new(0),
+            amends_limited: AtomicU64::new(0),
+            ratio_alerts: AtomicU64::new(0),
+            ratio_limited: AtomicU64::new(0),
+        }
+    }
+
+    /// 当前配置
+    pub fn config(&self) -> OrderRateLimitConfig {
+        self.config.read().clone()
+    }
+
+    /// 更新配置（已有令牌桶按新容量继续回补）
+    pub fn update_config(&self, config: OrderRateLimitConfig) {
+        *self.config.write() = config;
+    }
+
+    /// 下单频率检查
+    pub fn check_order(&self, account_id: &str, account_type: AccountType) -> Result<(), String> {
+        self.check_at(
+            account_id,
+            account_type,
+            RateLimitAction::Order,
+            Instant::now(),
+        )
+    }
+
+    /// 撤单频率检查
+    pub fn check_cancel(&self, account_id: &str, account_type: AccountType) -> Result<(), String> {
+        self.check_at(
+            account_id,
+            account_type,
+            RateLimitAction::Cancel,
+            Instant::now(),
+        )
+    }
+
+    /// 改单频率检查（改单计入报撤比的撤改单数）
+    pub fn check_amend(&self, account_id: &str, account_type: AccountType) -> Result<(), String> {
+        self.check_at(
+            account_id,
+            account_type,
+            RateLimitAction::Amend,
+            Instant::now(),
+        )
+    }
+
+    fn check_at(
+        &self,
+        account_id: &str,
+        account_type: AccountType,
+        action: RateLimitAction,
+        now: Instant,
+    ) -> Result<(), String> {
+        let (rule, ratio) = {
+            let config = self.config.read();
+            match config.rule_for(account_id, account_type) {
+                Some(rule) => (rule, config.cancel_ratio),
+                None => return Ok(()),
+            }
+        };
+        let track_ratio = ratio.enabled && account_type != AccountType::MarketMaker;
+        let limit = rule.limit_of(action);
+        if limit == 0 && !track_ratio {
+            return Ok(());
+        }
+
+        let mut entry = self
+            .accounts
+            .entry(account_id.to_string())
+            .or_insert_with(AccountBuckets::new);
+        let state = entry.value_mut();
+        let now_sec = now.saturating_duration_since(self.epoch).as_secs();
+
+        // 1. 报撤比：计入本次撤改单后超过阈值则告警或拒绝
+        if track_ratio && action != RateLimitAction::Order {
+            let (orders, cancels) = state.ratio.totals(now_sec, ratio.window_secs);
+            let cancels = cancels + 1;
+            let current = cancels as f64 / orders.max(1) as f64;
+            if cancels >= ratio.min_cancels as u64 && current > ratio.max_ratio {
+                state.ratio_hits += 1;
+                let (counter, outcome) = match ratio.action {
+                    CancelRatioAction::Alert => (&self.ratio_alerts, "alert"),
+                    CancelRatioAction::Restrict => (&self.ratio_limited, "restrict"),
+                };
+                counter.fetch_add(1, Ordering::Relaxed);
+                CANCEL_RATIO_EXCEEDED_TOTAL
+                    .with_label_values(&[outcome, account_type_label(account_type)])
+                    .inc();
+                if state.should_alert(now) {
+                    log::warn!(
+                        "Account {} cancel ratio {:.2} exceeds {:.2} in {}s window \
+                         (orders={}, cancels+amends={}), action={}",
+                        account_id,
+                        current,
+                        ratio.max_ratio,
+                        ratio.window_secs,
+                        orders,
+                        cancels,
+                        outcome
+                    );
+                }
+                if ratio.action == CancelRatioAction::Restrict {
+                    return Err(format!(
+                        "cancel ratio exceeded: {:.2} > {:.2} within {}s",
+                        current, ratio.max_ratio, ratio.window_secs
+                    ));
+                }
+            }
+        }
+
+        // 2. 令牌桶
+        if limit > 0 {
+            let capacity = limit as f64;
+            let bucket = match action {
+                RateLimitAction::Order => &mut state.orders,
+                RateLimitAction::Cancel => &mut state.cancels,
+                RateLimitAction::Amend => &mut state.amends,
+            };
+            let allowed = bucket
+                .get_or_insert_with(|| TokenBucket::new(capacity, now))
+                .try_acquire(capacity, rule.window_ms, now);
+
+            let (allowed_counter, limited_counter) = match action {
+                RateLimitAction::Order => (&self.orders_allowed, &self.orders_limited),
+                RateLimitAction::Cancel => (&self.cancels_allowed, &self.cancels_limited),
+                RateLimitAction::Amend => (&self.amends_allowed, &self.amends_limited),
+            };
+
+            if !allowed {
+                limited_counter.fetch_add(1, Ordering::Relaxed);
+                match action {
+                    RateLimitAction::Order => state.order_hits += 1,
+                    RateLimitAction::Cancel => state.cancel_hits += 1,
+                    RateLimitAction::Amend => state.amend_hits += 1,
+                }
+                ACCOUNT_RATE_LIMITED_TOTAL
+                    .with_label_values(&[action.label(), account_type_label(account_type)])
+                    .inc();
+
+                if state.should_alert(now) {
+                    log::warn!(
+                        "Account {} exceeded {} rate limit: {} per {}ms \
+                         (hits: orders={}, cancels={}, amends={})",
+                        account_id,
+                        action.label(),
+                        limit,
+                        rule.window_ms,
+                        state.order_hits,
+                        state.cancel_hits,
+                        state.amend_hits
+                    );
+                }
+
+                return Err(format!(
+                    "{} rate limit exceeded: max {} per {}ms",
+                    action.label(),
+                    limit,
+                    rule.window_ms
+                ));
+            }
+            allowed_counter.fetch_add(1, Ordering::Relaxed);
+        }
+
+        if track_ratio {
+            state.ratio.record(now_sec, ratio.window_secs, action);
+        }
+        Ok(())
+    }
+
+    /// 账户当前窗口内的报撤比（未启用报撤比或账户无记录时返回 None）
+    pub fn cancel_ratio(&self, account_id: &str) -> Option<CancelRatioSnapshot> {
+        let ratio = self.config.read().cancel_ratio;
+        if !ratio.enabled {
+            return None;
+        }
+        let now_sec = Instant::now()
+            .saturating_duration_since(self.epoch)
+            .as_secs();
+        self.accounts.get(account_id).map(|state| {
+            let (orders, cancels) = state.ratio.totals(now_sec, ratio.window_secs);
+            CancelRatioSnapshot {
+                account_id: account_id.to_string(),
+                window_secs: ratio.window_secs,
+                orders,
+                cancels,
+                ratio: cancels as f64 / orders.max(1) as f64,
+            }
+        })
+    }
+
+    /// 统计信息，`top_n` 为返回的命中账户数
+    pub fn get_stats(&self, top_n: usize) -> RateLimitStats {
+        let mut top_accounts: Vec<AccountRateLimitHits> = self
+            .accounts
+            .iter()
+            .filter(|e| e.total_hits() > 0)
+            .map(|e| AccountRateLimitHits {
+                account_id: e.key().clone(),
+                order_hits: e.order_hits,
+                cancel_hits: e.cancel_hits,
+                amend_hits: e.amend_hits,
+                ratio_hits: e.ratio_hits,
+            })
+            .collect();
+        let total =
+            |h: &AccountRateLimitHits| h.order_hits + h.cancel_hits + h.amend_hits + h.ratio_hits;
+        top_accounts.sort_by(|a, b| {
+            total(b)
+                .cmp(&total(a))
+                .then_with(|| a.account_id.cmp(&b.account_id))
+        });
+        top_accounts.truncate(top_n);
+
+        RateLimitStats {
+            orders_allowed: self.orders_allowed.load(Ordering::Relaxed),
+            orders_limited: self.orders_limited.load(Ordering::Relaxed),
+            cancels_allowed: self.cancels_allowed.load(Ordering::Relaxed),
+            cancels_limited: self.cancels_limited.load(Ordering::Relaxed),
+            amends_allowed: self.amends_allowed.load(Ordering::Relaxed),
+            amends_limited: self.amends_limited.load(Ordering::Relaxed),
+            cancel_ratio_alerts: self.ratio_alerts.load(Ordering::Relaxed),
+            cancel_ratio_limited: self.ratio_limited.load(Ordering::Relaxed),
+            tracked_accounts: self.accounts.len(),
+            top_accounts,
+        }
+    }
+
+    /// 清除账户状态（销户等场景）
+    pub fn remove_account(&self, account_id: &str) {
+        self.accounts.remove(account_id);
+    }
+}
+
+impl Default for OrderRateLimiter {
+    fn default() -> Self {
+        Self::new(OrderRateLimitConfig::default())
+    }
+}
+
+fn account_type_label(account_type: AccountType) -> &'static str {
+    match account_type {
+        AccountType::Individual => "individual",
+        AccountType::Institutional => "institutional",
+        AccountType::MarketMaker => "market_maker",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter() -> OrderRateLimiter {
+        OrderRateLimiter::new(OrderRateLimitConfig {
+            individual: Some(RateLimitRule::new(1000, 3, 2)),
+            institutional: Some(RateLimitRule::new(1000, 10, 10)),
+            market_maker: None,
+            exempt_accounts: HashSet::new(),
+            account_overrides: HashMap::new(),
+            cancel_ratio: CancelRatioConfig::default(),
+        })
+    }
+
+    #[test]
+    fn test_order_limited_then_recovers() {
+        let limiter = limiter();
+        let t0 = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter
+                .check_at("acc1", AccountType::Individual, RateLimitAction::Order, t0)
+                .is_ok());
+        }
+        assert!(limiter
+            .check_at("acc1", AccountType::Individual, RateLimitAction::Order, t0)
+            .is_err());
+
+        // 撤单桶独立
+        assert!(limiter
+            .check_at("acc1", AccountType::Individual, RateLimitAction::Cancel, t0)
+            .is_ok());
+
+        // 回补 1/3 窗口 → 1 个令牌
+        let t1 = t0 + Duration::from_millis(340);
+        assert!(limiter
+            .check_at("acc1", AccountType::Individual, RateLimitAction::Order, t1)
+            .is_ok());
+        assert!(limiter
+            .check_at("acc1", AccountType::Individual, RateLimitAction::Order, t1)
+            .is_err());
+
+        // 整窗口后恢复满额
+        let t2 = t1 + Duration::from_millis(1000);
+        for _ in 0..3 {
+            assert!(limiter
+                .check_at("acc1", AccountType::Individual, RateLimitAction::Order, t2)
+                .is_ok());
+        }
+
+        let stats = limiter.get_stats(10);
+        assert_eq!(stats.orders_limited, 2);
+        assert_eq!(stats.orders_allowed, 7);
+        assert_eq!(stats.top_accounts[0].account_id, "acc1");
+        assert_eq!(stats.top_accounts[0].order_hits, 2);
+    }
+
+    #[test]
+    fn test_sub_millisecond_calls_still_refill() {
+        let limiter = limiter();
+        let t0 = Instant::now();
+
+        // 机构账户每秒 10 单：先耗尽令牌
+        for _ in 0..10 {
+            assert!(limiter
+                .check_at(
+                    "inst",
+                    AccountType::Institutional,
+                    RateLimitAction::Order,
+                    t0
+                )
+                .is_ok());
+        }
+
+        // 之后 1 秒内每 0.5ms 请求一次，回补量按配置速率累计，约放行 10 单
+        let allowed = (1..=2000u64)
+            .filter(|i| {
+                let now = t0 + Duration::from_micros(i * 500);
+                limiter
+                    .check_at(
+                        "inst",
+                        AccountType::Institutional,
+                        RateLimitAction::Order,
+                        now,
+                    )
+                    .is_ok()
+            })
+            .count();
+        assert!((9..=10).contains(&allowed), "allowed {}", allowed);
+    }
+
+    #[test]
+    fn test_exemptions_and_overrides() {
+        let mut config = limiter().config();
+        config.exempt_accounts.insert("vip".to_string());
+        config
+            .account_overrides
+            .insert("hf".to_string(), RateLimitRule::new(1000, 1, 0));
+        let limiter = OrderRateLimiter::new(config);
+        let t0 = Instant::now();
+
+        // 做市商类型未配置规则 → 豁免
+        for _ in 0..100 {
+            assert!(limiter
+                .check_at("mm", AccountType::MarketMaker, RateLimitAction::Order, t0)
+                .is_ok());
+            assert!(limiter
+                .check_at("vip", AccountType::Individual, RateLimitAction::Order, t0)
+                .is_ok());
+        }
+
+        // 账户覆盖规则：下单 1 次，撤单不限
+        assert!(limiter
+            .check_at("hf", AccountType::Institutional, RateLimitAction::Order, t0)
+            .is_ok());
+        assert!(limiter
+            .check_at("hf", AccountType::Institutional, RateLimitAction::Order, t0)
+            .is_err());
+        for _ in 0..20 {
+            assert!(limiter
+                .check_at(
+                    "hf",
+                    AccountType::Institutional,
+                    RateLimitAction::Cancel,
+                    t0
+                )
+                .is_ok());
+        }
+    }
+
+    #[test]
+    fn test_amend_bucket_independent() {
+        let mut config = limiter().config();
+        config.individual = Some(RateLimitRule::new(1000, 3, 2).with_max_amends(1));
+        let limiter = OrderRateLimiter::new(config);
+        let t0 = Instant::now();
+
+        assert!(limiter
+            .check_at("acc1", AccountType::Individual, RateLimitAction::Amend, t0)
+            .is_ok());
+        assert!(limiter
+            .check_at("acc1", AccountType::Individual, RateLimitAction::Amend, t0)
+            .is_err());
+        // 改单超限不影响撤单
+        for _ in 0..2 {
+            assert!(limiter
+                .check_at("acc1", AccountType::Individual, RateLimitAction::Cancel, t0)
+                .is_ok());
+        }
+
+        let stats = limiter.get_stats(10);
+        assert_eq!(stats.amends_allowed, 1);
+        assert_eq!(stats.amends_limited, 1);
+        assert_eq!(stats.cancels_allowed, 2);
+        assert_eq!(stats.top_accounts[0].amend_hits, 1);
+    }
+
+    #[test]
+    fn test_cancel_ratio_restrict_and_window() {
+        let mut config = limiter().config();
+        config.individual = Some(RateLimitRule::new(1000, 0, 0));
+        config.market_maker = Some(RateLimitRule::new(1000, 0, 0));
+        config.cancel_ratio = CancelRatioConfig {
+            enabled: true,
+            window_secs: 10,
+            min_cancels: 5,
+            max_ratio: 2.0,
+            action: CancelRatioAction::Restrict,
+        };
+        let limiter = OrderRateLimiter::new(config);
+        let t0 = limiter.epoch;
+        let hft = |action, at| {
+            limiter
+                .check_at("hft", AccountType::Individual, action, at)
+                .is_ok()
+        };
+
+        // 2 单 → 最多 4 次撤改单；第 5 次撤单达到 min_cancels 且比值 2.5 > 2.0
+        for _ in 0..2 {
+            assert!(hft(RateLimitAction::Order, t0));
+        }
+        for _ in 0..3 {
+            assert!(hft(RateLimitAction::Cancel, t0));
+        }
+        assert!(hft(RateLimitAction::Amend, t0));
+        assert!(!hft(RateLimitAction::Cancel, t0));
+        assert!(!hft(RateLimitAction::Amend, t0));
+
+        let snapshot = limiter.cancel_ratio("hft").unwrap();
+        assert_eq!(snapshot.orders, 2);
+        assert_eq!(snapshot.cancels, 4);
+
+        // 新下单拉低报撤比后恢复
+        assert!(hft(RateLimitAction::Order, t0));
+        assert!(hft(RateLimitAction::Cancel, t0));
+
+        // 窗口滑过后历史计数清零
+        let t1 = t0 + Duration::from_secs(10);
+        assert_eq!(
+            limiter.accounts.get("hft").unwrap().ratio.totals(10, 10),
+            (0, 0)
+        );
+        assert!(hft(RateLimitAction::Cancel, t1));
+
+        // 做市商豁免
+        for _ in 0..20 {
+            assert!(limiter
+                .check_at("mm", AccountType::MarketMaker, RateLimitAction::Cancel, t0)
+                .is_ok());
+        }
+
+        let stats = limiter.get_stats(10);
+        assert_eq!(stats.cancel_ratio_limited, 2);
+        assert_eq!(stats.cancel_ratio_alerts, 0);
+        assert_eq!(stats.top_accounts[0].account_id, "hft");
+        assert_eq!(stats.top_accounts[0].ratio_hits, 2);
+    }
+
+    #[test]
+    fn test_cancel_ratio_alert_only() {
+        let mut config = limiter().config();
+        config.individual = Some(RateLimitRule::new(1000, 0, 0));
+        config.cancel_ratio = CancelRatioConfig {
+            enabled: true,
+            window_secs: 10,
+            min_cancels: 3,
+            max_ratio: 1.0,
+            action: CancelRatioAction::Alert,
+        };
+        let limiter = OrderRateLimiter::new(config);
+        let t0 = limiter.epoch;
+
+        for _ in 0..5 {
+            assert!(limiter
+                .check_at("acc1", AccountType::Individual, RateLimitAction::Cancel, t0)
+                .is_ok());
+        }
+        let stats = limiter.get_stats(10);
+        assert_eq!(stats.cancel_ratio_alerts, 3);
+        assert_eq!(stats.cancel_ratio_limited, 0);
+        assert_eq!(limiter.cancel_ratio("acc1").unwrap().cancels, 5);
+    }
+}
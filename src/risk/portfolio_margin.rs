@@ -0,0 +1,455 @@
+//! 组合保证金（Portfolio Margin）
+//!
+//! @yutiansut @quantaxis
+//!
+//! 按账户整体持仓的对冲后净风险计算保证金，替代逐合约保证金（各合约多空保证金直接相加）：
+//!
+//! 1. **跨期价差**: 同品种不同月份（含同合约锁仓）的多空持仓视为价差组合，
+//!    收取大边保证金，小边按 `calendar_spread_rate` 收取价差保证金
+//! 2. **跨品种对冲**: 配置了相关系数的品种对，净敞口方向相反时按
+//!    `相关系数 × cross_offset_rate × 较小净敞口` 抵扣；每个品种的净敞口只参与一次抵扣
+//! 3. **下限**: 组合保证金不低于逐合约保证金的 `floor_ratio`，且不高于逐合约保证金
+//!
+//! 逐合约保证金取持仓实时保证金（`margin_long` / `margin_short`），组合模式下账户风险度
+//! 按 组合保证金 / 逐合约保证金 的比例折算，其余口径（冻结保证金等）保持不变。
+
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+use crate::core::QA_Account;
+use crate::ExchangeError;
+
+/// 保证金计算模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MarginMode {
+    /// 逐合约保证金
+    #[default]
+    PerInstrument,
+    /// 组合保证金
+    Portfolio,
+}
+
+/// 跨品种对冲规则
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrossHedgeRule {
+    pub product_a: String,
+    pub product_b: String,
+    /// 价格相关系数 [0, 1]
+    pub correlation: f64,
+}
+
+/// 组合保证金配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortfolioMarginConfig {
+    #[serde(default)]
+    pub mode: MarginMode,
+    /// 跨期价差小边收取比例 [0, 1]
+    #[serde(default = "default_calendar_spread_rate")]
+    pub calendar_spread_rate: f64,
+    /// 跨品种抵扣系数 [0, 1]（抵扣额 = 相关系数 × 系数 × 较小净敞口）
+    #[serde(default = "default_cross_offset_rate")]
+    pub cross_offset_rate: f64,
+    /// 相关系数低于该值的规则不抵扣
+    #[serde(default = "default_min_correlation")]
+    pub min_correlation: f64,
+    /// 组合保证金下限（占逐合约保证金比例，(0, 1]）
+    #[serde(default = "default_floor_ratio")]
+    pub floor_ratio: f64,
+    /// 跨品种对冲规则
+    #[serde(default)]
+    pub cross_hedges: Vec<CrossHedgeRule>,
+}
+
+fn default_calendar_spread_rate() -> f64 {
+    0.25
+}
+fn default_cross_offset_rate() -> f64 {
+    0.5
+}
+fn default_min_correlation() -> f64 {
+    0.5
+}
+fn default_floor_ratio() -> f64 {
+    0.5
+}
+
+impl Default for PortfolioMarginConfig {
+    fn default() -> Self {
+        Self {
+            mode: MarginMode::default(),
+            calendar_spread_rate: default_calendar_spread_rate(),
+            cross_offset_rate: default_cross_offset_rate(),
+            min_correlation: default_min_correlation(),
+            floor_ratio: default_floor_ratio(),
+            cross_hedges: Vec::new(),
+        }
+    }
+}
+
+impl PortfolioMarginConfig {
+    pub fn validate(&self) -> Result<(), ExchangeError> {
+        let unit = |name: &str, v: f64| {
+            if (0.0..=1.0).contains(&v) {
+                Ok(())
+            } else {
+                Err(ExchangeError::InvalidParameter(format!(
+                    "{} must be in [0, 1], got {}",
+                    name, v
+                )))
+            }
+        };
+        unit("calendar_spread_rate", self.calendar_spread_rate)?;
+        unit("cross_offset_rate", self.cross_offset_rate)?;
+        unit("min_correlation", self.min_correlation)?;
+        if self.floor_ratio <= 0.0 || self.floor_ratio > 1.0 {
+            return Err(ExchangeError::InvalidParameter(format!(
+                "floor_ratio must be in (0, 1], got {}",
+                self.floor_ratio
+            )));
+        }
+        for rule in &self.cross_hedges {
+            unit("correlation", rule.correlation)?;
+            if rule.product_a == rule.product_b {
+                return Err(ExchangeError::InvalidParameter(format!(
+                    "Cross hedge rule needs two different products: {}",
+                    rule.product_a
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 单合约持仓保证金
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarginLeg {
+    pub instrument_id: String,
+    pub long_margin: f64,
+    pub short_margin: f64,
+}
+
+/// 品种保证金（跨期价差优惠后）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProductMargin {
+    pub product: String,
+    pub long_margin: f64,
+    pub short_margin: f64,
+    /// 跨期价差优惠
+    pub calendar_offset: f64,
+    /// 优惠后保证金
+    pub margin: f64,
+    /// 净敞口（正为多头，负为空头）
+    pub net_exposure: f64,
+}
+
+/// 跨品种对冲抵扣
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrossHedgeOffset {
+    pub product_a: String,
+    pub product_b: String,
+    pub correlation: f64,
+    pub offset: f64,
+}
+
+/// 组合保证金计算结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortfolioMarginResult {
+    /// 逐合约保证金
+    pub per_instrument_margin: f64,
+    /// 组合保证金
+    pub portfolio_margin: f64,
+    pub calendar_offset: f64,
+    pub cross_offset: f64,
+    /// 是否按下限收取
+    pub floor_applied: bool,
+    pub products: Vec<ProductMargin>,
+    pub cross_hedges: Vec<CrossHedgeOffset>,
+}
+
+/// 合约所属品种（合约代码的字母前缀，如 IF2501 -> IF，cu2501 -> cu）
+pub fn product_of(instrument_id: &str) -> &str {
+    let end = instrument_id
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(instrument_id.len());
+    if end == 0 {
+        instrument_id
+    } else {
+        &instrument_id[..end]
+    }
+}
+
+/// 组合保证金计算器
+pub struct PortfolioMargin {
+    config: RwLock<PortfolioMarginConfig>,
+}
+
+impl Default for PortfolioMargin {
+    fn default() -> Self {
+        Self {
+            config: RwLock::new(PortfolioMarginConfig::default()),
+        }
+    }
+}
+
+impl PortfolioMargin {
+    pub fn new(config: PortfolioMarginConfig) -> Result<Self, ExchangeError> {
+        config.validate()?;
+        Ok(Self {
+            config: RwLock::new(config),
+        })
+    }
+
+    pub fn config(&self) -> PortfolioMarginConfig {
+        self.config.read().clone()
+    }
+
+    /// 更新配置（含逐合约/组合模式切换）
+    pub fn update_config(&self, config: PortfolioMarginConfig) -> Result<(), ExchangeError> {
+        config.validate()?;
+        log::info!(
+            "[PortfolioMargin] Config updated: mode={:?}, calendar_spread={:.0}%, cross_offset={:.0}%, floor={:.0}%, rules={}",
+            config.mode,
+            config.calendar_spread_rate * 100.0,
+            config.cross_offset_rate * 100.0,
+            config.floor_ratio * 100.0,
+            config.cross_hedges.len()
+        );
+        *self.config.write() = config;
+        Ok(())
+    }
+
+    pub fn mode(&self) -> MarginMode {
+        self.config.read().mode
+    }
+
+    /// 计算一组持仓的组合保证金（与当前模式无关）
+    pub fn compute(&self, legs: &[MarginLeg]) -> PortfolioMarginResult {
+        let config = self.config.read().clone();
+        let per_instrument_margin: f64 = legs.iter().map(|l| l.long_margin + l.short_margin).sum();
+
+        // 1. 同品种跨期：大边全额，小边按价差比例
+        let mut grouped: BTreeMap<&str, (f64, f64)> = BTreeMap::new();
+        for leg in legs {
+            let entry = grouped.entry(product_of(&leg.instrument_id)).or_default();
+            entry.0 += leg.long_margin;
+            entry.1 += leg.short_margin;
+        }
+        let mut products: Vec<ProductMargin> = grouped
+            .into_iter()
+            .map(|(product, (long_margin, short_margin))| {
+                let small = long_margin.min(short_margin);
+                let calendar_offset = small * (1.0 - config.calendar_spread_rate);
+                ProductMargin {
+                    product: product.to_string(),
+                    long_margin,
+                    short_margin,
+                    calendar_offset,
+                    margin: long_margin + short_margin - calendar_offset,
+                    net_exposure: long_margin - short_margin,
+                }
+            })
+            .collect();
+        let calendar_offset: f64 = products.iter().map(|p| p.calendar_offset).sum();
+
+        // 2. 跨品种对冲：相关性高的规则优先，每个品种净敞口只抵扣一次
+        let mut rules: Vec<&CrossHedgeRule> = config
+            .cross_hedges
+            .iter()
+            .filter(|r| r.correlation >= config.min_correlation)
+            .collect();
+        rules.sort_by(|a, b| b.correlation.total_cmp(&a.correlation));
+        let mut used: HashSet<String> = HashSet::new();
+        let mut cross_hedges = Vec::new();
+        for rule in rules {
+            if used.contains(&rule.product_a) || used.contains(&rule.product_b) {
+                continue;
+            }
+            let net = |name: &str| {
+                products
+                    .iter()
+                    .find(|p| p.product == name)
+                    .map_or(0.0, |p| p.net_exposure)
+            };
+            let (a, b) = (net(&rule.product_a), net(&rule.product_b));
+            // 正相关品种只有反向持仓才构成对冲
+            if a * b >= 0.0 {
+                continue;
+            }
+            let offset = rule.correlation * config.cross_offset_rate * a.abs().min(b.abs());
+            used.insert(rule.product_a.clone());
+            used.insert(rule.product_b.clone());
+            cross_hedges.push(CrossHedgeOffset {
+                product_a: rule.product_a.clone(),
+                product_b: rule.product_b.clone(),
+                correlation: rule.correlation,
+                offset,
+            });
+        }
+        let cross_offset: f64 = cross_hedges.iter().map(|c| c.offset).sum();
+
+        // 3. 下限：不低于逐合约保证金的 floor_ratio，不高于逐合约保证金
+        let raw: f64 = products.iter().map(|p| p.margin).sum::<f64>() - cross_offset;
+        let floor = per_instrument_margin * config.floor_ratio;
+        let floor_applied = raw < floor;
+        let portfolio_margin = raw.max(floor).min(per_instrument_margin);
+
+        products.retain(|p| p.long_margin > 0.0 || p.short_margin > 0.0);
+        PortfolioMarginResult {
+            per_instrument_margin,
+            portfolio_margin,
+            calendar_offset,
+            cross_offset,
+            floor_applied,
+            products,
+            cross_hedges,
+        }
+    }
+
+    /// 账户持仓的保证金腿
+    pub fn legs_of(acc: &QA_Account) -> Vec<MarginLeg> {
+        acc.hold
+            .iter()
+            .filter(|(_, pos)| pos.margin_long > 0.0 || pos.margin_short > 0.0)
+            .map(|(instrument_id, pos)| MarginLeg {
+                instrument_id: instrument_id.clone(),
+                long_margin: pos.margin_long,
+                short_margin: pos.margin_short,
+            })
+            .collect()
+    }
+
+    /// 计算账户的组合保证金
+    pub fn compute_account(&self, acc: &QA_Account) -> PortfolioMarginResult {
+        self.compute(&Self::legs_of(acc))
+    }
+
+    /// 按当前模式计算账户持仓保证金
+    pub fn account_margin(&self, acc: &mut QA_Account) -> f64 {
+        let margin = acc.get_margin();
+        match self.mode() {
+            MarginMode::PerInstrument => margin,
+            MarginMode::Portfolio => margin * self.discount_of(acc),
+        }
+    }
+
+    /// 按当前模式计算账户风险度
+    pub fn risk_ratio(&self, acc: &mut QA_Account) -> f64 {
+        let risk_ratio = acc.get_riskratio();
+        match self.mode() {
+            MarginMode::PerInstrument => risk_ratio,
+            MarginMode::Portfolio => risk_ratio * self.discount_of(acc),
+        }
+    }
+
+    /// 组合保证金 / 逐合约保证金（无持仓时为 1）
+    fn discount_of(&self, acc: &QA_Account) -> f64 {
+        let result = self.compute_account(acc);
+        if result.per_instrument_margin > 0.0 {
+            result.portfolio_margin / result.per_instrument_margin
+        } else {
+            1.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leg(instrument_id: &str, long_margin: f64, short_margin: f64) -> MarginLeg {
+        MarginLeg {
+            instrument_id: instrument_id.to_string(),
+            long_margin,
+            short_margin,
+        }
+    }
+
+    fn calculator() -> PortfolioMargin {
+        PortfolioMargin::new(PortfolioMarginConfig {
+            mode: MarginMode::Portfolio,
+            cross_hedges: vec![
+                CrossHedgeRule {
+                    product_a: "cu".to_string(),
+                    product_b: "al".to_string(),
+                    correlation: 0.8,
+                },
+                CrossHedgeRule {
+                    product_a: "cu".to_string(),
+                    product_b: "zn".to_string(),
+                    correlation: 0.3,
+                },
+            ],
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_product_of() {
+        assert_eq!(product_of("IF2501"), "IF");
+        assert_eq!(product_of("cu2501"), "cu");
+        assert_eq!(product_of("au2512P968"), "au");
+        assert_eq!(product_of("2501"), "2501");
+    }
+
+    /// 对冲组合：组合保证金低于逐合约保证金
+    #[test]
+    fn test_hedged_portfolio_margin_below_per_instrument() {
+        let pm = calculator();
+        let result = pm.compute(&[
+            // 跨期价差：IF 近月多、远月空
+            leg("IF2501", 100_000.0, 0.0),
+            leg("IF2503", 0.0, 95_000.0),
+            // 跨品种：cu 多、al 空（相关 0.8）
+            leg("cu2501", 50_000.0, 0.0),
+            leg("al2501", 0.0, 40_000.0),
+        ]);
+
+        assert_eq!(result.per_instrument_margin, 285_000.0);
+        // IF: 大边 100k + 小边 95k × 25%
+        assert!((result.calendar_offset - 71_250.0).abs() < 1e-6);
+        // cu/al: 0.8 × 0.5 × min(50k, 40k)
+        assert!((result.cross_offset - 16_000.0).abs() < 1e-6);
+        assert!((result.portfolio_margin - 197_750.0).abs() < 1e-6);
+        assert!(result.portfolio_margin < result.per_instrument_margin);
+        assert!(!result.floor_applied);
+    }
+
+    /// 不构成对冲的持仓不打折，下限兜底
+    #[test]
+    fn test_no_discount_without_hedge_and_floor() {
+        let pm = calculator();
+
+        // 同向持仓、低相关规则均不抵扣
+        let result = pm.compute(&[
+            leg("cu2501", 50_000.0, 0.0),
+            leg("al2501", 40_000.0, 0.0),
+            leg("zn2501", 0.0, 30_000.0),
+        ]);
+        assert_eq!(result.cross_offset, 0.0);
+        assert_eq!(result.portfolio_margin, result.per_instrument_margin);
+
+        // 几乎完全对冲的价差组合按下限收取
+        pm.update_config(PortfolioMarginConfig {
+            mode: MarginMode::Portfolio,
+            calendar_spread_rate: 0.0,
+            floor_ratio: 0.6,
+            ..Default::default()
+        })
+        .unwrap();
+        let result = pm.compute(&[leg("IF2501", 100_000.0, 0.0), leg("IF2503", 0.0, 100_000.0)]);
+        assert!(result.floor_applied);
+        assert_eq!(result.portfolio_margin, 120_000.0);
+
+        // 非法配置被拒绝，原配置保留
+        assert!(pm
+            .update_config(PortfolioMarginConfig {
+                floor_ratio: 0.0,
+                ..Default::default()
+            })
+            .is_err());
+        assert_eq!(pm.mode(), MarginMode::Portfolio);
+    }
+}
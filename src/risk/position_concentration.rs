@@ -0,0 +1,428 @@
+//! 持仓集中度监测（单账户占合约全市场总持仓比例）
+//!
+//! 防范市场操纵：盘中风控每轮汇总全部账户持仓，维护各合约全市场总持仓（多空双边合计），
+//! 计算每个账户在各合约上的持仓占比：
+//!
+//! ```text
+//! 持仓占比 = (账户多头持仓 + 账户空头持仓) / (全市场多头持仓 + 全市场空头持仓)
+//! ```
+//!
+//! - 阈值按合约（或品种）配置，未单独配置的合约使用默认阈值
+//! - 全市场总持仓低于 `min_open_interest` 时不判定（新合约初期单账户占比天然偏高）
+//! - 进入超限状态时告警一次；`restrict_open` 下限制该账户在该合约继续开仓，平仓不受限
+//! - 占比回落到阈值以下后自动解除限制
+//!
+//! @yutiansut @quantaxis
+
+use crate::risk::portfolio_margin::product_of;
+use crate::ExchangeError;
+use dashmap::DashMap;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// 超限后的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConcentrationAction {
+    /// 只告警
+    AlertOnly,
+    /// 告警并限制该账户在该合约继续开仓
+    #[default]
+    RestrictOpen,
+}
+
+/// 持仓集中度配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PositionConcentrationConfig {
+    /// 是否启用
+    #[serde(default)]
+    pub enabled: bool,
+    /// 默认占比阈值（None 表示未单独配置的合约不监测）
+    #[serde(default)]
+    pub default_max_ratio: Option<f64>,
+    /// 按合约或品种（如 IF）配置的占比阈值，合约优先于品种
+    #[serde(default)]
+    pub instruments: HashMap<String, f64>,
+    /// 全市场总持仓（双边，手）低于该值时不判定
+    #[serde(default)]
+    pub min_open_interest: f64,
+    /// 超限后的处理方式
+    #[serde(default)]
+    pub action: ConcentrationAction,
+    /// 豁免账户（如做市商）
+    #[serde(default)]
+    pub exempt_accounts: Vec<String>,
+}
+
+impl PositionConcentrationConfig {
+    pub fn validate(&self) -> Result<(), ExchangeError> {
+        let ratios = self
+            .default_max_ratio
+            .iter()
+            .map(|ratio| ("default", *ratio))
+            .chain(
+                self.instruments
+                    .iter()
+                    .map(|(id, ratio)| (id.as_str(), *ratio)),
+            );
+        for (scope, ratio) in ratios {
+            if ratio.is_nan() || ratio <= 0.0 || ratio > 1.0 {
+                return Err(ExchangeError::InvalidParameter(format!(
+                    "Position concentration ratio of {} must be within (0, 1], got {}",
+                    scope, ratio
+                )));
+            }
+        }
+        if self.min_open_interest.is_nan() || self.min_open_interest < 0.0 {
+            return Err(ExchangeError::InvalidParameter(format!(
+                "Position concentration min_open_interest must be >= 0, got {}",
+                self.min_open_interest
+            )));
+        }
+        Ok(())
+    }
+
+    /// 合约适用的占比阈值，None 表示不监测
+    pub fn max_ratio_for(&self, instrument_id: &str) -> Option<f64> {
+        self.instruments
+            .get(instrument_id)
+            .or_else(|| self.instruments.get(product_of(instrument_id)))
+            .copied()
+            .or(self.default_max_ratio)
+    }
+
+    fn is_exempt(&self, account_id: &str) -> bool {
+        self.exempt_accounts.iter().any(|a| a == account_id)
+    }
+}
+
+/// 单账户单合约持仓（多空合计，手）
+#[derive(Debug, Clone, PartialEq)]
+pub struct PositionHolding {
+    pub account_id: String,
+    pub instrument_id: String,
+    pub volume: f64,
+}
+
+/// 持仓集中度超限
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConcentrationBreach {
+    pub account_id: String,
+    pub instrument_id: String,
+    /// 账户持仓（多空合计）
+    pub position: f64,
+    /// 全市场总持仓（多空合计）
+    pub open_interest: f64,
+    /// 持仓占比
+    pub ratio: f64,
+    /// 占比阈值
+    pub max_ratio: f64,
+    /// 处理方式
+    pub action: ConcentrationAction,
+    /// 进入超限状态的时间（毫秒）
+    pub since: i64,
+}
+
+impl ConcentrationBreach {
+    pub fn describe(&self) -> String {
+        format!(
+            "合约 {} 持仓占全市场 {:.2}%（{}/{} 手），超过阈值 {:.2}%",
+            self.instrument_id,
+            self.ratio * 100.0,
+            self.position,
+            self.open_interest,
+            self.max_ratio * 100.0
+        )
+    }
+}
+
+/// 持仓集中度监测器
+pub struct PositionConcentration {
+    config: RwLock<PositionConcentrationConfig>,
+    /// 合约 -> 全市场总持仓（多空合计，最近一轮扫描结果）
+    open_interest: DashMap<String, f64>,
+    /// (账户, 合约) -> 当前超限状态
+    breaches: DashMap<(String, String), ConcentrationBreach>,
+}
+
+impl Default for PositionConcentration {
+    fn default() -> Self {
+        Self {
+            config: RwLock::new(PositionConcentrationConfig::default()),
+            open_interest: DashMap::new(),
+            breaches: DashMap::new(),
+        }
+    }
+}
+
+impl PositionConcentration {
+    pub fn new(config: PositionConcentrationConfig) -> Result<Self, ExchangeError> {
+        config.validate()?;
+        let monitor = Self::default();
+        *monitor.config.write() = config;
+        Ok(monitor)
+    }
+
+    /// 当前配置
+    pub fn config(&self) -> PositionConcentrationConfig {
+        self.config.read().clone()
+    }
+
+    /// 更新配置（下一轮扫描按新阈值重新判定）
+    pub fn update_config(&self, config: PositionConcentrationConfig) -> Result<(), ExchangeError> {
+        config.validate()?;
+        log::info!(
+            "[PositionConcentration] Config updated: enabled={}, default={:?}, instruments={}, action={:?}",
+            config.enabled,
+            config.default_max_ratio,
+            config.instruments.len(),
+            config.action
+        );
+        *self.config.write() = config;
+        Ok(())
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.read().enabled
+    }
+
+    /// 合约全市场总持仓（多空合计）
+    pub fn open_interest(&self, instrument_id: &str) -> f64 {
+        self.open_interest
+            .get(instrument_id)
+            .map(|v| *v)
+            .unwrap_or(0.0)
+    }
+
+    /// 当前全部超限记录
+    pub fn breaches(&self) -> Vec<ConcentrationBreach> {
+        let mut breaches: Vec<_> = self.breaches.iter().map(|e| e.value().clone()).collect();
+        breaches.sort_by(|a, b| {
+            (&a.instrument_id, &a.account_id).cmp(&(&b.instrument_id, &b.account_id))
+        });
+        breaches
+    }
+
+    /// 按全部账户持仓重新计算总持仓与占比，返回本轮新进入超限状态的记录
+    pub fn evaluate_at(
+        &self,
+        holdings: &[PositionHolding],
+        now_ms: i64,
+    ) -> Vec<ConcentrationBreach> {
+        let config = self.config.read().clone();
+        if !config.enabled {
+            self.breaches.clear();
+            self.open_interest.clear();
+            return Vec::new();
+        }
+
+        // 全市场总持仓与账户持仓（同一账户同一合约可能多条，合并）
+        let mut totals: HashMap<&str, f64> = HashMap::new();
+        let mut positions: HashMap<(&str, &str), f64> = HashMap::new();
+        for holding in holdings.iter().filter(|h| h.volume > 0.0) {
+            *totals.entry(holding.instrument_id.as_str()).or_default() += holding.volume;
+            *positions
+                .entry((holding.account_id.as_str(), holding.instrument_id.as_str()))
+                .or_default() += holding.volume;
+        }
+        self.open_interest.clear();
+        for (instrument_id, total) in &totals {
+            self.open_interest.insert(instrument_id.to_string(), *total);
+        }
+
+        let mut triggered = Vec::new();
+        let mut breached_keys = HashSet::new();
+        for ((account_id, instrument_id), position) in positions {
+            if config.is_exempt(account_id) {
+                continue;
+            }
+            let Some(max_ratio) = config.max_ratio_for(instrument_id) else {
+                continue;
+            };
+            let open_interest = totals[instrument_id];
+            if open_interest < config.min_open_interest {
+                continue;
+            }
+            let ratio = position / open_interest;
+            if ratio <= max_ratio {
+                continue;
+            }
+
+            let key = (account_id.to_string(), instrument_id.to_string());
+            breached_keys.insert(key.clone());
+            match self.breaches.get_mut(&key) {
+                Some(mut breach) => {
+                    breach.position = position;
+                    breach.open_interest = open_interest;
+                    breach.ratio = ratio;
+                    breach.max_ratio = max_ratio;
+                    breach.action = config.action;
+                }
+                None => {
+                    let breach = ConcentrationBreach {
+                        account_id: key.0.clone(),
+                        instrument_id: key.1.clone(),
+                        position,
+                        open_interest,
+                        ratio,
+                        max_ratio,
+                        action: config.action,
+                        since: now_ms,
+                    };
+                    log::warn!(
+                        "[PositionConcentration] Account {}: {}",
+                        breach.account_id,
+                        breach.describe()
+                    );
+                    self.breaches.insert(key, breach.clone());
+                    triggered.push(breach);
+                }
+            }
+        }
+
+        // 占比回落（或持仓清空、阈值调整）的账户解除限制
+        self.breaches.retain(|key, breach| {
+            let keep = breached_keys.contains(key);
+            if !keep {
+                log::info!(
+                    "[PositionConcentration] Account {} concentration on {} back to normal",
+                    breach.account_id,
+                    breach.instrument_id
+                );
+            }
+            keep
+        });
+
+        triggered
+    }
+
+    /// 下单检查：超限账户在该合约上的开仓单拒绝，平仓放行
+    pub fn check_order(
+        &self,
+        account_id: &str,
+        instrument_id: &str,
+        offset: &str,
+    ) -> Result<(), String> {
+        if offset != "OPEN" {
+            return Ok(());
+        }
+        match self
+            .breaches
+            .get(&(account_id.to_string(), instrument_id.to_string()))
+        {
+            Some(breach) if breach.action == ConcentrationAction::RestrictOpen => {
+                Err(format!("持仓集中度超限，暂停开仓：{}", breach.describe()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn holding(account_id: &str, instrument_id: &str, volume: f64) -> PositionHolding {
+        PositionHolding {
+            account_id: account_id.to_string(),
+            instrument_id: instrument_id.to_string(),
+            volume,
+        }
+    }
+
+    #[test]
+    fn test_breach_alerts_once_and_restricts_open() {
+        let monitor = PositionConcentration::new(PositionConcentrationConfig {
+            enabled: true,
+            default_max_ratio: Some(0.5),
+            instruments: HashMap::from([("IF".to_string(), 0.25)]),
+            min_open_interest: 100.0,
+            ..Default::default()
+        })
+        .unwrap();
+
+        // IF2501 双边总持仓 200 手：A、B 各占 30%，C、D 各占 20%
+        let holdings = vec![
+            holding("A", "IF2501", 60.0),
+            holding("B", "IF2501", 60.0),
+            holding("C", "IF2501", 40.0),
+            holding("D", "IF2501", 40.0),
+            // cu2501 使用默认阈值 50%，A 占 40% 未超限
+            holding("A", "cu2501", 40.0),
+            holding("B", "cu2501", 60.0),
+        ];
+        let triggered = monitor.evaluate_at(&holdings, 1_000);
+        let mut accounts: Vec<_> = triggered.iter().map(|b| b.account_id.as_str()).collect();
+        accounts.sort();
+        assert_eq!(accounts, vec!["A", "B", "B"]);
+        assert_eq!(monitor.open_interest("IF2501"), 200.0);
+        let breach = monitor
+            .breaches()
+            .into_iter()
+            .find(|b| b.account_id == "A")
+            .unwrap();
+        assert_eq!(breach.ratio, 0.3);
+        assert_eq!(breach.max_ratio, 0.25);
+
+        // 超限期间只在进入时告警一次
+        assert!(monitor.evaluate_at(&holdings, 2_000).is_empty());
+        assert_eq!(monitor.breaches()[0].since, 1_000);
+
+        // 超限账户在该合约禁止开仓，平仓与其他合约、其他账户不受限
+        assert!(monitor.check_order("A", "IF2501", "OPEN").is_err());
+        assert!(monitor.check_order("A", "IF2501", "CLOSE").is_ok());
+        assert!(monitor.check_order("A", "cu2501", "OPEN").is_ok());
+        assert!(monitor.check_order("C", "IF2501", "OPEN").is_ok());
+
+        // A 平仓至 20 手后占比回落（20/160 = 12.5%），自动解除
+        let holdings = vec![
+            holding("A", "IF2501", 20.0),
+            holding("B", "IF2501", 60.0),
+            holding("C", "IF2501", 40.0),
+            holding("D", "IF2501", 40.0),
+        ];
+        assert!(monitor.evaluate_at(&holdings, 3_000).is_empty());
+        assert!(monitor.check_order("A", "IF2501", "OPEN").is_ok());
+        // B: 60/160 = 37.5% 仍超限
+        assert!(monitor.check_order("B", "IF2501", "OPEN").is_err());
+    }
+
+    #[test]
+    fn test_min_open_interest_exempt_and_alert_only() {
+        let mut config = PositionConcentrationConfig {
+            enabled: true,
+            default_max_ratio: Some(0.3),
+            min_open_interest: 100.0,
+            exempt_accounts: vec!["MM".to_string()],
+            ..Default::default()
+        };
+        let monitor = PositionConcentration::new(config.clone()).unwrap();
+
+        // 全市场总持仓不足 100 手不判定
+        let small = vec![holding("A", "IF2501", 10.0), holding("B", "IF2501", 10.0)];
+        assert!(monitor.evaluate_at(&small, 1_000).is_empty());
+
+        // 豁免账户不判定
+        let holdings = vec![
+            holding("MM", "IF2501", 80.0),
+            holding("A", "IF2501", 10.0),
+            holding("B", "IF2501", 10.0),
+        ];
+        assert!(monitor.evaluate_at(&holdings, 1_000).is_empty());
+
+        // 只告警模式不限制开仓
+        config.exempt_accounts.clear();
+        config.action = ConcentrationAction::AlertOnly;
+        monitor.update_config(config).unwrap();
+        assert_eq!(monitor.evaluate_at(&holdings, 2_000).len(), 1);
+        assert!(monitor.check_order("MM", "IF2501", "OPEN").is_ok());
+
+        // 阈值校验
+        let invalid = PositionConcentrationConfig {
+            default_max_ratio: Some(1.5),
+            ..Default::default()
+        };
+        assert!(PositionConcentration::new(invalid).is_err());
+    }
+}
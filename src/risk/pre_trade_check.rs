@@ -4,10 +4,12 @@
 //! - 资金充足性检查
 //! - 持仓限额检查
 //! - 订单合法性检查
+//! - 价格笼子（限价单偏离参考价过远拒绝，防胖手指）
+//! - 最小变动价位（价格不在 tick 上时拒绝或规整到最近的 tick）
 //! - 自成交防范
 
 use crate::core::{Order, QA_Account};
-use crate::exchange::AccountManager;
+use crate::exchange::{AccountManager, InstrumentRegistry};
 use crate::ExchangeError;
 use dashmap::DashMap;
 use parking_lot::RwLock;
@@ -42,6 +44,163 @@ pub enum RiskCheckCode {
     InstrumentNotFound = 1007,
     /// 订单参数非法
     InvalidOrderParams = 1008,
+    /// 价格超出价格笼子
+    PriceOutOfBand = 1009,
+}
+
+/// 价格笼子幅度
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", content = "value", rename_all = "lowercase")]
+pub enum PriceBandWidth {
+    /// 按参考价百分比（0.02 表示 ±2%）
+    Percent(f64),
+    /// 按绝对价格（±value）
+    Absolute(f64),
+}
+
+/// 价格笼子配置
+///
+/// 与涨跌停不同：笼子以实时参考价（对手盘/最新价）为中心，幅度更窄，随行情移动
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceBandConfig {
+    /// 允许偏离参考价的幅度
+    pub width: PriceBandWidth,
+
+    /// 盘口价差超过中间价的该比例时视为盘口极端，改用最新成交价作参考
+    #[serde(default = "default_max_spread_ratio")]
+    pub max_spread_ratio: f64,
+}
+
+fn default_max_spread_ratio() -> f64 {
+    0.05
+}
+
+impl PriceBandConfig {
+    pub fn percent(rate: f64) -> Self {
+        Self {
+            width: PriceBandWidth::Percent(rate),
+            max_spread_ratio: default_max_spread_ratio(),
+        }
+    }
+
+    pub fn absolute(value: f64) -> Self {
+        Self {
+            width: PriceBandWidth::Absolute(value),
+            max_spread_ratio: default_max_spread_ratio(),
+        }
+    }
+
+    /// 以参考价为中心的合理价格区间
+    pub fn range(&self, reference: f64) -> (f64, f64) {
+        let half = match self.width {
+            PriceBandWidth::Percent(rate) => reference * rate.abs(),
+            PriceBandWidth::Absolute(value) => value.abs(),
+        };
+        ((reference - half).max(0.0), reference + half)
+    }
+}
+
+/// 价格不是最小变动价位整数倍时的处理方式
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PriceTickMode {
+    /// 不校验
+    #[default]
+    Off,
+    /// 拒单
+    Reject,
+    /// 四舍五入到最近的 tick（调整后的价格随下单响应返回）
+    Round,
+}
+
+/// 判定价格在 tick 上的容差（以 tick 为单位）
+///
+/// 吸收 0.2 这类无法精确表示的 tick 带来的浮点误差；价格/tick 很大时按相对误差放宽
+const TICK_EPSILON: f64 = 1e-6;
+
+/// tick 的小数位数（0.2 -> 1，0.0005 -> 4，10 -> 0），最多 10 位
+fn tick_decimals(tick: f64) -> i32 {
+    (0..10)
+        .find(|d| {
+            let scaled = tick * 10f64.powi(*d);
+            (scaled - scaled.round()).abs() < 1e-9 * scaled.max(1.0)
+        })
+        .unwrap_or(10)
+}
+
+/// 按最小变动价位规整价格
+///
+/// 返回清理浮点误差后的价格（如 3000.6000000000004 -> 3000.6）。价格不在 tick 上时：
+/// `Reject` 返回错误，`Round` 取最近的 tick；规整后不为正的价格返回错误。
+/// tick 非法（非正/NaN）或 `Off` 时原样返回，规整后超出涨跌停由后续涨跌停校验拒绝
+pub fn normalize_to_tick(price: f64, tick: f64, mode: PriceTickMode) -> Result<f64, String> {
+    if mode == PriceTickMode::Off || tick.is_nan() || tick <= 0.0 || !price.is_finite() {
+        return Ok(price);
+    }
+
+    let ratio = price / tick;
+    let ticks = ratio.round();
+    let on_tick = (ratio - ticks).abs() <= TICK_EPSILON.max(ratio.abs() * 1e-12);
+    if !on_tick && mode == PriceTickMode::Reject {
+        return Err(format!(
+            "Price {} is not a multiple of price tick {}",
+            price, tick
+        ));
+    }
+
+    let scale = 10f64.powi(tick_decimals(tick));
+    let normalized = (ticks * tick * scale).round() / scale;
+    if normalized <= 0.0 {
+        return Err(format!(
+            "Price {} rounds to {} with price tick {}",
+            price, normalized, tick
+        ));
+    }
+    Ok(normalized)
+}
+
+/// 参考行情（最新成交价与盘口一档）
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReferenceQuote {
+    /// 最新成交价（0 表示无成交）
+    pub last_price: f64,
+    pub best_bid: Option<f64>,
+    pub best_ask: Option<f64>,
+}
+
+impl ReferenceQuote {
+    /// 选取价格笼子参考价，返回 (参考价, 来源)
+    ///
+    /// 优先使用对手盘一档（买单用卖一、卖单用买一）；盘口价差过大或无对手盘时
+    /// 使用最新成交价；两者都不可用时返回 None（不做笼子校验，由涨跌停兜底）
+    pub fn reference_price(
+        &self,
+        direction: &str,
+        max_spread_ratio: f64,
+    ) -> Option<(f64, &'static str)> {
+        let bid = self.best_bid.filter(|p| *p > 0.0);
+        let ask = self.best_ask.filter(|p| *p > 0.0);
+
+        let extreme = match (bid, ask) {
+            (Some(bid), Some(ask)) => {
+                let mid = (bid + ask) / 2.0;
+                (ask - bid) / mid > max_spread_ratio
+            }
+            _ => false,
+        };
+
+        let counter = match direction {
+            "BUY" => ask.map(|p| (p, "best_ask")),
+            "SELL" => bid.map(|p| (p, "best_bid")),
+            _ => None,
+        };
+
+        match counter {
+            Some(reference) if !extreme => Some(reference),
+            _ if self.last_price > 0.0 => Some((self.last_price, "last_price")),
+            _ => None,
+        }
+    }
 }
 
 /// 风控配置
@@ -116,6 +275,18 @@ pub struct PreTradeCheck {
 
     /// 活动订单追踪 (user_id -> Vec<ActiveOrderInfo>)
     active_orders: DashMap<String, Arc<RwLock<Vec<ActiveOrderInfo>>>>,
+
+    /// 默认价格笼子（None 表示未启用）
+    default_price_band: RwLock<Option<PriceBandConfig>>,
+
+    /// 按合约配置的价格笼子（覆盖默认配置）
+    price_bands: DashMap<String, PriceBandConfig>,
+
+    /// 最小变动价位校验方式
+    price_tick_mode: RwLock<PriceTickMode>,
+
+    /// 合约注册表（金额按每手数量换算，未设置时按 1）
+    instrument_registry: RwLock<Option<Arc<InstrumentRegistry>>>,
 }
 
 impl PreTradeCheck {
@@ -124,6 +295,10 @@ impl PreTradeCheck {
             account_mgr,
             config: Arc::new(RwLock::new(RiskConfig::default())),
             active_orders: DashMap::new(),
+            default_price_band: RwLock::new(None),
+            price_bands: DashMap::new(),
+            price_tick_mode: RwLock::new(PriceTickMode::Off),
+            instrument_registry: RwLock::new(None),
         }
     }
 
@@ -133,14 +308,34 @@ impl PreTradeCheck {
             account_mgr,
             config: Arc::new(RwLock::new(config)),
             active_orders: DashMap::new(),
+            default_price_band: RwLock::new(None),
+            price_bands: DashMap::new(),
+            price_tick_mode: RwLock::new(PriceTickMode::Off),
+            instrument_registry: RwLock::new(None),
         }
     }
 
     /// 执行完整风控检查
     pub fn check(&self, req: &OrderCheckRequest) -> Result<RiskCheckResult, ExchangeError> {
+        self.check_with_quote(req, None)
+    }
+
+    /// 执行完整风控检查（带参考行情，启用价格笼子校验）
+    pub fn check_with_quote(
+        &self,
+        req: &OrderCheckRequest,
+        quote: Option<&ReferenceQuote>,
+    ) -> Result<RiskCheckResult, ExchangeError> {
         // 1. 基础参数检查
         self.check_order_params(req)?;
 
+        // 1.5 价格笼子检查
+        if let Some(quote) = quote {
+            if let Some(reject) = self.check_price_band(req, quote) {
+                return Ok(reject);
+            }
+        }
+
         // 2. 账户存在性检查（交易系统只关心account_id）
         let account = self.account_mgr.get_account(&req.account_id)?;
 
@@ -200,7 +395,7 @@ impl PreTradeCheck {
         if req.price_type == "MARKET" {
             return Ok(()); // 市价单跳过金额检查
         }
-        let order_amount = req.price * req.volume;
+        let order_amount = self.order_amount(req);
         if order_amount > config.max_order_amount {
             return Err(ExchangeError::RiskCheckFailed(format!(
                 "Order amount {} exceeds limit {}",
@@ -211,6 +406,32 @@ impl PreTradeCheck {
         Ok(())
     }
 
+    /// 检查价格笼子：限价单价格须落在参考价 ± 幅度内
+    fn check_price_band(
+        &self,
+        req: &OrderCheckRequest,
+        quote: &ReferenceQuote,
+    ) -> Option<RiskCheckResult> {
+        if req.price_type == "MARKET" {
+            return None;
+        }
+        let band = self.get_price_band(&req.instrument_id)?;
+        let (reference, source) = quote.reference_price(&req.direction, band.max_spread_ratio)?;
+        let (lower, upper) = band.range(reference);
+
+        if req.limit_price < lower || req.limit_price > upper {
+            return Some(RiskCheckResult::Reject {
+                reason: format!(
+                    "Order price {} outside price band [{:.4}, {:.4}] (reference {} from {})",
+                    req.limit_price, lower, upper, reference, source
+                ),
+                code: RiskCheckCode::PriceOutOfBand,
+            });
+        }
+
+        None
+    }
+
     /// 检查资金充足性
     fn check_funds(
         &self,
@@ -219,14 +440,22 @@ impl PreTradeCheck {
     ) -> Result<Option<RiskCheckResult>, ExchangeError> {
         let acc = account.read();
 
-        // 计算所需资金 (简化: 价格 * 数量 + 手续费估算)
-        let estimated_commission = req.price * req.volume * 0.0003; // 万3手续费
+        // 账户保证金加成（运营按标签批量设置）
+        let surcharge = 1.0
+            + self
+                .account_mgr
+                .account_tags()
+                .margin_surcharge(&req.account_id);
+
+        // 计算所需资金 (简化: 订单金额 + 手续费估算)
+        let order_amount = self.order_amount(req);
+        let estimated_commission = order_amount * 0.0003; // 万3手续费
         let required_funds = if req.direction == "BUY" && req.offset == "OPEN" {
             // 买开仓需要全额资金
-            req.price * req.volume + estimated_commission
+            order_amount * surcharge + estimated_commission
         } else if req.direction == "SELL" && req.offset == "OPEN" {
             // 卖开仓需要保证金 (简化: 20%)
-            req.price * req.volume * 0.2 + estimated_commission
+            order_amount * 0.2 * surcharge + estimated_commission
         } else {
             // 平仓只需手续费
             estimated_commission
@@ -476,6 +705,61 @@ impl PreTradeCheck {
     pub fn get_config(&self) -> RiskConfig {
         self.config.read().clone()
     }
+
+    /// 设置默认价格笼子（None 表示关闭）
+    pub fn set_default_price_band(&self, band: Option<PriceBandConfig>) {
+        *self.default_price_band.write() = band;
+    }
+
+    /// 设置合约价格笼子（None 表示移除合约配置，回落到默认配置）
+    pub fn set_price_band(&self, instrument_id: &str, band: Option<PriceBandConfig>) {
+        match band {
+            Some(band) => {
+                self.price_bands.insert(instrument_id.to_string(), band);
+            }
+            None => {
+                self.price_bands.remove(instrument_id);
+            }
+        }
+    }
+
+    /// 合约生效的价格笼子配置
+    pub fn get_price_band(&self, instrument_id: &str) -> Option<PriceBandConfig> {
+        self.price_bands
+            .get(instrument_id)
+            .map(|band| band.clone())
+            .or_else(|| self.default_price_band.read().clone())
+    }
+
+    /// 设置合约注册表（订单金额、资金检查按每手数量换算）
+    pub fn set_instrument_registry(&self, registry: Arc<InstrumentRegistry>) {
+        *self.instrument_registry.write() = Some(registry);
+    }
+
+    /// 订单金额 = 价格 × 手数 × 每手数量
+    fn order_amount(&self, req: &OrderCheckRequest) -> f64 {
+        let multiple = self
+            .instrument_registry
+            .read()
+            .as_ref()
+            .map_or(1.0, |registry| registry.volume_multiple(&req.instrument_id));
+        req.price * req.volume * multiple
+    }
+
+    /// 设置最小变动价位校验方式
+    pub fn set_price_tick_mode(&self, mode: PriceTickMode) {
+        *self.price_tick_mode.write() = mode;
+    }
+
+    /// 当前最小变动价位校验方式
+    pub fn price_tick_mode(&self) -> PriceTickMode {
+        *self.price_tick_mode.read()
+    }
+
+    /// 按当前校验方式规整订单价格（见 [`normalize_to_tick`]）
+    pub fn normalize_price(&self, price: f64, tick: f64) -> Result<f64, String> {
+        normalize_to_tick(price, tick, self.price_tick_mode())
+    }
 }
 
 #[cfg(test)]
@@ -1156,4 +1440,149 @@ mod tests {
 
         assert!(checker.check_order_params(&req).is_ok());
     }
+
+    /// 测试价格笼子：远离市价的限价单被拒绝并提示区间，正常价格通过
+    #[test]
+    fn test_price_band_rejects_far_limit_order() {
+        let account_mgr = create_test_account_manager();
+        let checker = PreTradeCheck::new(account_mgr);
+        checker.set_default_price_band(Some(PriceBandConfig::percent(0.02)));
+
+        let quote = ReferenceQuote {
+            last_price: 100.0,
+            best_bid: Some(99.8),
+            best_ask: Some(100.2),
+        };
+        let req = OrderCheckRequest {
+            account_id: "test_user".to_string(),
+            instrument_id: "IX2301".to_string(),
+            direction: "BUY".to_string(),
+            offset: "OPEN".to_string(),
+            volume: 1.0,
+            price: 100.5,
+            limit_price: 100.5,
+            price_type: "LIMIT".to_string(),
+        };
+
+        // 正常价格通过（买单参考卖一 100.2，区间 [98.196, 102.204]）
+        let result = checker.check_with_quote(&req, Some(&quote)).unwrap();
+        assert!(matches!(result, RiskCheckResult::Pass));
+
+        // 胖手指：买价远高于市价
+        let fat_finger = OrderCheckRequest {
+            price: 110.0,
+            limit_price: 110.0,
+            ..req.clone()
+        };
+        match checker.check_with_quote(&fat_finger, Some(&quote)).unwrap() {
+            RiskCheckResult::Reject { reason, code } => {
+                assert_eq!(code, RiskCheckCode::PriceOutOfBand);
+                assert!(reason.contains("[98.1960, 102.2040]"), "{}", reason);
+            }
+            RiskCheckResult::Pass => panic!("far limit order should be rejected"),
+        }
+
+        // 无参考行情时不做笼子校验
+        let result = checker.check(&fat_finger).unwrap();
+        assert!(matches!(result, RiskCheckResult::Pass));
+
+        // 合约绝对值配置覆盖默认配置
+        checker.set_price_band("IX2301", Some(PriceBandConfig::absolute(20.0)));
+        let result = checker.check_with_quote(&fat_finger, Some(&quote)).unwrap();
+        assert!(matches!(result, RiskCheckResult::Pass));
+    }
+
+    /// 测试价格笼子参考价选取：对手盘 → 最新价，盘口极端/缺失时回退
+    #[test]
+    fn test_price_band_reference_fallbacks() {
+        let normal = ReferenceQuote {
+            last_price: 100.0,
+            best_bid: Some(99.9),
+            best_ask: Some(100.1),
+        };
+        assert_eq!(
+            normal.reference_price("BUY", 0.05),
+            Some((100.1, "best_ask"))
+        );
+        assert_eq!(
+            normal.reference_price("SELL", 0.05),
+            Some((99.9, "best_bid"))
+        );
+
+        // 盘口极端（价差 > 5%）：使用最新价
+        let extreme = ReferenceQuote {
+            last_price: 100.0,
+            best_bid: Some(50.0),
+            best_ask: Some(150.0),
+        };
+        assert_eq!(
+            extreme.reference_price("BUY", 0.05),
+            Some((100.0, "last_price"))
+        );
+
+        // 无对手盘：使用最新价
+        let one_sided = ReferenceQuote {
+            last_price: 100.0,
+            best_bid: Some(99.0),
+            best_ask: None,
+        };
+        assert_eq!(
+            one_sided.reference_price("BUY", 0.05),
+            Some((100.0, "last_price"))
+        );
+
+        // 盘口极端且无成交：无参考价
+        let no_reference = ReferenceQuote {
+            last_price: 0.0,
+            ..extreme
+        };
+        assert_eq!(no_reference.reference_price("SELL", 0.05), None);
+        assert_eq!(ReferenceQuote::default().reference_price("BUY", 0.05), None);
+
+        // 区间下限不为负
+        assert_eq!(PriceBandConfig::absolute(5.0).range(3.0), (0.0, 8.0));
+    }
+
+    #[test]
+    fn test_price_tick_reject_mode() {
+        let reject = PriceTickMode::Reject;
+
+        // 0.2 的倍数带浮点误差时视为在 tick 上，并清理误差
+        let price = 3000.0 + 0.2 * 3.0;
+        assert_eq!(normalize_to_tick(price, 0.2, reject), Ok(3000.6));
+        assert_eq!(normalize_to_tick(3000.4, 0.2, reject), Ok(3000.4));
+        assert_eq!(normalize_to_tick(0.0015, 0.0005, reject), Ok(0.0015));
+        assert_eq!(normalize_to_tick(71230.0, 10.0, reject), Ok(71230.0));
+
+        assert!(normalize_to_tick(3000.3, 0.2, reject).is_err());
+        assert!(normalize_to_tick(0.00155, 0.0005, reject).is_err());
+        assert!(normalize_to_tick(71235.0, 10.0, reject).is_err());
+
+        // 关闭或 tick 非法时不校验
+        assert_eq!(
+            normalize_to_tick(3000.3, 0.2, PriceTickMode::Off),
+            Ok(3000.3)
+        );
+        assert_eq!(normalize_to_tick(3000.3, 0.0, reject), Ok(3000.3));
+    }
+
+    #[test]
+    fn test_price_tick_round_mode() {
+        let round = PriceTickMode::Round;
+
+        assert_eq!(normalize_to_tick(3000.3, 0.2, round), Ok(3000.4));
+        assert_eq!(normalize_to_tick(3000.29, 0.2, round), Ok(3000.2));
+        assert_eq!(normalize_to_tick(3000.5, 0.2, round), Ok(3000.6));
+        assert_eq!(normalize_to_tick(0.00174, 0.0005, round), Ok(0.0015));
+        assert_eq!(normalize_to_tick(71236.0, 10.0, round), Ok(71240.0));
+
+        // 规整后不为正：拒绝
+        assert!(normalize_to_tick(0.05, 0.2, round).is_err());
+
+        let checker = PreTradeCheck::new(create_test_account_manager());
+        assert_eq!(checker.normalize_price(3000.3, 0.2), Ok(3000.3));
+        checker.set_price_tick_mode(round);
+        assert_eq!(checker.price_tick_mode(), PriceTickMode::Round);
+        assert_eq!(checker.normalize_price(3000.3, 0.2), Ok(3000.4));
+    }
 }
@@ -0,0 +1,470 @@
+//! 涨跌停板管理与动态扩板
+//!
+//! - 以昨结算价为基准计算涨跌停价，供订单价格校验
+//! - 连续 N 个交易日单边收于涨跌停时，次日涨跌停幅度逐级扩大，保证金率同步上调
+//!   （保证金率 = max(基础保证金率, 新涨跌停幅度 + margin_buffer)）
+//! - 未收于涨跌停的交易日恢复基础幅度与保证金率
+//! - 扩板/恢复写入 InstrumentRegistry 并记录历史，通过 `MarketDataEvent::PriceLimitChanged` 广播
+//!
+//! @yutiansut @quantaxis
+
+use crate::exchange::InstrumentRegistry;
+use crate::market::{MarketDataBroadcaster, MarketDataEvent};
+use crate::ExchangeError;
+use dashmap::DashMap;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// 历史记录保留条数
+const MAX_HISTORY: usize = 1000;
+
+/// 价格比较容差（浮点误差）
+const PRICE_EPSILON: f64 = 1e-8;
+
+/// 扩板规则
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LimitExpansionConfig {
+    /// 连续单边涨跌停 N 个交易日后触发扩板
+    pub trigger_days: u32,
+    /// 每级扩板的涨跌停幅度增量
+    pub limit_step: f64,
+    /// 扩板后保证金率至少高出涨跌停幅度的部分
+    pub margin_buffer: f64,
+    /// 最大扩板级数
+    pub max_level: u32,
+}
+
+impl Default for LimitExpansionConfig {
+    fn default() -> Self {
+        Self {
+            trigger_days: 1,     // 单边停板次日即扩板
+            limit_step: 0.03,    // 每级 +3%
+            margin_buffer: 0.02, // 保证金 = 涨跌停幅度 + 2%
+            max_level: 2,
+        }
+    }
+}
+
+/// 停板方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LimitDirection {
+    Up,
+    Down,
+}
+
+/// 合约扩板状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LimitBandState {
+    pub instrument_id: String,
+    /// 基础涨停幅度（未扩板）
+    pub base_limit_up_rate: f64,
+    /// 基础跌停幅度（未扩板）
+    pub base_limit_down_rate: f64,
+    /// 基础保证金率（未扩板）
+    pub base_margin_rate: f64,
+    /// 涨跌停基准价（昨结算价）
+    pub reference_price: f64,
+    /// 当前连续停板方向
+    pub direction: Option<LimitDirection>,
+    /// 连续单边停板天数
+    pub consecutive_days: u32,
+    /// 当前扩板级数（0 表示未扩板）
+    pub level: u32,
+}
+
+/// 扩板动作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LimitBandAction {
+    /// 扩板
+    Expanded,
+    /// 恢复基础幅度
+    Restored,
+}
+
+/// 扩板历史记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LimitBandEvent {
+    pub instrument_id: String,
+    /// 触发判断的交易日
+    pub trading_day: String,
+    pub action: LimitBandAction,
+    pub direction: Option<LimitDirection>,
+    pub level: u32,
+    pub limit_up_rate: f64,
+    pub limit_down_rate: f64,
+    pub margin_rate: f64,
+    /// 次日涨跌停基准价
+    pub reference_price: f64,
+    pub timestamp: i64,
+}
+
+/// 涨跌停板管理器
+pub struct PriceLimitManager {
+    instrument_registry: Arc<InstrumentRegistry>,
+    config: RwLock<LimitExpansionConfig>,
+    states: DashMap<String, LimitBandState>,
+    history: RwLock<Vec<LimitBandEvent>>,
+    broadcaster: RwLock<Option<Arc<MarketDataBroadcaster>>>,
+}
+
+impl PriceLimitManager {
+    pub fn new(instrument_registry: Arc<InstrumentRegistry>) -> Self {
+        Self::with_config(instrument_registry, LimitExpansionConfig::default())
+    }
+
+    pub fn with_config(
+        instrument_registry: Arc<InstrumentRegistry>,
+        config: LimitExpansionConfig,
+    ) -> Self {
+        Self {
+            instrument_registry,
+            config: RwLock::new(config),
+            states: DashMap::new(),
+            history: RwLock::new(Vec::new()),
+            broadcaster: RwLock::new(None),
+        }
+    }
+
+    /// 设置行情广播器（扩板通知）
+    pub fn set_broadcaster(&self, broadcaster: Arc<MarketDataBroadcaster>) {
+        *self.broadcaster.write() = Some(broadcaster);
+    }
+
+    /// 更新扩板规则
+    pub fn update_config(&self, config: LimitExpansionConfig) {
+        *self.config.write() = config;
+    }
+
+    /// 设置涨跌停基准价（昨结算价）
+    pub fn set_reference_price(
+        &self,
+        instrument_id: &str,
+        reference_price: f64,
+    ) -> Result<(), ExchangeError> {
+        let mut state = self.state_entry(instrument_id)?;
+        state.reference_price = reference_price;
+        Ok(())
+    }
+
+    /// 当日涨跌停价 (跌停价, 涨停价)，未设置基准价时返回 None
+    pub fn limit_prices(&self, instrument_id: &str) -> Option<(f64, f64)> {
+        let info = self.instrument_registry.get(instrument_id)?;
+        let reference = self.states.get(instrument_id)?.reference_price;
+        if reference <= 0.0 {
+            return None;
+        }
+        Some(Self::band(reference, info.limit_down_rate, info.limit_up_rate, info.price_tick))
+    }
+
+    /// 订单价格校验：价格需在当日涨跌停价之间
+    pub fn check_price(&self, instrument_id: &str, price: f64) -> Result<(), String> {
+        match self.limit_prices(instrument_id) {
+            Some((lower, upper))
+                if price < lower - PRICE_EPSILON || price > upper + PRICE_EPSILON =>
+            {
+                Err(format!(
+                    "Price {} out of limit range [{}, {}] for {}",
+                    price, lower, upper, instrument_id
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// 日终处理：按收盘价判断是否单边停板，更新扩板状态
+    ///
+    /// `close_price` 同时作为次日涨跌停基准价；发生扩板或恢复时返回事件
+    pub fn on_daily_close(
+        &self,
+        instrument_id: &str,
+        trading_day: &str,
+        close_price: f64,
+    ) -> Result<Option<LimitBandEvent>, ExchangeError> {
+        let info = self.instrument_registry.get(instrument_id).ok_or_else(|| {
+            ExchangeError::InstrumentError(format!("Instrument {} not found", instrument_id))
+        })?;
+        let config = self.config.read().clone();
+
+        let event = {
+            let mut state = self.state_entry(instrument_id)?;
+
+            let hit = if state.reference_price > 0.0 {
+                let (lower, upper) = Self::band(
+                    state.reference_price,
+                    info.limit_down_rate,
+                    info.limit_up_rate,
+                    info.price_tick,
+                );
+                let tolerance = info.price_tick / 2.0;
+                if close_price >= upper - tolerance {
+                    Some(LimitDirection::Up)
+                } else if close_price <= lower + tolerance {
+                    Some(LimitDirection::Down)
+                } else {
+                    None
+                }
+            } else {
+                None
+            };
+
+            match hit {
+                Some(direction) if state.direction == Some(direction) => {
+                    state.consecutive_days += 1;
+                }
+                Some(direction) => {
+                    state.direction = Some(direction);
+                    state.consecutive_days = 1;
+                }
+                None => {
+                    state.direction = None;
+                    state.consecutive_days = 0;
+                }
+            }
+            state.reference_price = close_price;
+
+            let target_level = if state.consecutive_days >= config.trigger_days.max(1) {
+                (state.consecutive_days - config.trigger_days.max(1) + 1).min(config.max_level)
+            } else {
+                0
+            };
+
+            if target_level == state.level {
+                None
+            } else {
+                let action = if target_level > 0 {
+                    LimitBandAction::Expanded
+                } else {
+                    LimitBandAction::Restored
+                };
+                state.level = target_level;
+
+                let step = config.limit_step * target_level as f64;
+                let limit_up_rate = state.base_limit_up_rate + step;
+                let limit_down_rate = state.base_limit_down_rate + step;
+                let margin_rate = if target_level == 0 {
+                    state.base_margin_rate
+                } else {
+                    state
+                        .base_margin_rate
+                        .max(limit_up_rate.max(limit_down_rate) + config.margin_buffer)
+                };
+
+                Some(LimitBandEvent {
+                    instrument_id: instrument_id.to_string(),
+                    trading_day: trading_day.to_string(),
+                    action,
+                    direction: state.direction,
+                    level: target_level,
+                    limit_up_rate,
+                    limit_down_rate,
+                    margin_rate,
+                    reference_price: close_price,
+                    timestamp: chrono::Utc::now().timestamp_millis(),
+                })
+            }
+        };
+
+        if let Some(ref e) = event {
+            self.instrument_registry.update(instrument_id, |info| {
+                info.limit_up_rate = e.limit_up_rate;
+                info.limit_down_rate = e.limit_down_rate;
+                info.margin_rate = e.margin_rate;
+            })?;
+            self.record_event(e.clone());
+
+            log::warn!(
+                "Price limit {:?} for {} on {}: level={}, limit=+{:.2}%/-{:.2}%, margin={:.2}%",
+                e.action,
+                instrument_id,
+                trading_day,
+                e.level,
+                e.limit_up_rate * 100.0,
+                e.limit_down_rate * 100.0,
+                e.margin_rate * 100.0
+            );
+
+            if let Some(ref broadcaster) = *self.broadcaster.read() {
+                let (lower, upper) =
+                    Self::band(close_price, e.limit_down_rate, e.limit_up_rate, info.price_tick);
+                broadcaster.broadcast(MarketDataEvent::PriceLimitChanged {
+                    instrument_id: instrument_id.to_string(),
+                    upper_limit: upper,
+                    lower_limit: lower,
+                    limit_up_rate: e.limit_up_rate,
+                    limit_down_rate: e.limit_down_rate,
+                    margin_rate: e.margin_rate,
+                    level: e.level,
+                    timestamp: e.timestamp,
+                });
+            }
+        }
+
+        Ok(event)
+    }
+
+    /// 获取合约扩板状态
+    pub fn get_state(&self, instrument_id: &str) -> Option<LimitBandState> {
+        self.states.get(instrument_id).map(|s| s.clone())
+    }
+
+    /// 获取扩板历史（指定合约或全部）
+    pub fn get_history(&self, instrument_id: Option<&str>) -> Vec<LimitBandEvent> {
+        self.history
+            .read()
+            .iter()
+            .filter(|e| instrument_id.map_or(true, |id| e.instrument_id == id))
+            .cloned()
+            .collect()
+    }
+
+    fn record_event(&self, event: LimitBandEvent) {
+        let mut history = self.history.write();
+        history.push(event);
+        if history.len() > MAX_HISTORY {
+            let drop = history.len() - MAX_HISTORY;
+            history.drain(0..drop);
+        }
+    }
+
+    /// 获取或初始化合约状态（基础参数取首次登记时的合约参数）
+    fn state_entry(
+        &self,
+        instrument_id: &str,
+    ) -> Result<dashmap::mapref::one::RefMut<'_, String, LimitBandState>, ExchangeError> {
+        if let Some(state) = self.states.get_mut(instrument_id) {
+            return Ok(state);
+        }
+        let info = self.instrument_registry.get(instrument_id).ok_or_else(|| {
+            ExchangeError::InstrumentError(format!("Instrument {} not found", instrument_id))
+        })?;
+        Ok(self
+            .states
+            .entry(instrument_id.to_string())
+            .or_insert_with(|| LimitBandState {
+                instrument_id: instrument_id.to_string(),
+                base_limit_up_rate: info.limit_up_rate,
+                base_limit_down_rate: info.limit_down_rate,
+                base_margin_rate: info.margin_rate,
+                reference_price: 0.0,
+                direction: None,
+                consecutive_days: 0,
+                level: 0,
+            }))
+    }
+
+    /// 按最小变动价位取整的涨跌停价：涨停向下取整，跌停向上取整
+    fn band(reference: f64, down_rate: f64, up_rate: f64, price_tick: f64) -> (f64, f64) {
+        let upper = reference * (1.0 + up_rate);
+        let lower = reference * (1.0 - down_rate);
+        if price_tick > 0.0 {
+            (
+                (lower / price_tick - 1e-9).ceil() * price_tick,
+                (upper / price_tick + 1e-9).floor() * price_tick,
+            )
+        } else {
+            (lower, upper)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::instrument_registry::{InstrumentInfo, InstrumentType};
+
+    fn setup() -> (Arc<InstrumentRegistry>, PriceLimitManager) {
+        let registry = Arc::new(InstrumentRegistry::new());
+        let mut info = InstrumentInfo::new(
+            "cu2501".to_string(),
+            "沪铜2501".to_string(),
+            InstrumentType::CommodityFuture,
+            "SHFE".to_string(),
+        );
+        info.price_tick = 10.0;
+        info.limit_up_rate = 0.06;
+        info.limit_down_rate = 0.06;
+        info.margin_rate = 0.08;
+        registry.register(info).unwrap();
+
+        let manager = PriceLimitManager::new(registry.clone());
+        manager.set_reference_price("cu2501", 70000.0).unwrap();
+        (registry, manager)
+    }
+
+    /// 模拟连续涨停：逐级扩板、保证金同步上调、价格校验按新幅度执行
+    #[test]
+    fn test_consecutive_limit_up_expands_band() {
+        let (registry, manager) = setup();
+        let broadcaster = Arc::new(MarketDataBroadcaster::new());
+        let rx = broadcaster.subscribe("s1".to_string(), vec![], vec!["price_limit".to_string()]);
+        manager.set_broadcaster(broadcaster);
+
+        // 当日涨停价 70000 * 1.06 = 74200
+        assert_eq!(manager.limit_prices("cu2501"), Some((65800.0, 74200.0)));
+        assert!(manager.check_price("cu2501", 74210.0).is_err());
+
+        // D1 收于涨停 -> D2 扩板一级: 9%, 保证金 11%
+        let e1 = manager.on_daily_close("cu2501", "20250102", 74200.0).unwrap().unwrap();
+        assert_eq!(e1.action, LimitBandAction::Expanded);
+        assert_eq!(e1.level, 1);
+        let info = registry.get("cu2501").unwrap();
+        assert!((info.limit_up_rate - 0.09).abs() < 1e-9);
+        assert!((info.margin_rate - 0.11).abs() < 1e-9);
+        assert!(manager.check_price("cu2501", 80870.0).is_ok());
+        assert!(manager.check_price("cu2501", 80880.0).is_err());
+
+        // D2 继续涨停 (74200 * 1.09 = 80878 -> 80870) -> 扩板二级: 12%, 保证金 14%
+        let e2 = manager.on_daily_close("cu2501", "20250103", 80870.0).unwrap().unwrap();
+        assert_eq!(e2.level, 2);
+        let info = registry.get("cu2501").unwrap();
+        assert!((info.limit_up_rate - 0.12).abs() < 1e-9);
+        assert!((info.margin_rate - 0.14).abs() < 1e-9);
+
+        // D3 仍涨停：已达最大级数，不再扩板
+        let (_, upper) = manager.limit_prices("cu2501").unwrap();
+        assert!(manager.on_daily_close("cu2501", "20250106", upper).unwrap().is_none());
+        assert_eq!(manager.get_state("cu2501").unwrap().consecutive_days, 3);
+
+        // D4 行情回归 -> 恢复基础幅度与保证金
+        let e4 = manager.on_daily_close("cu2501", "20250107", upper * 0.99).unwrap().unwrap();
+        assert_eq!(e4.action, LimitBandAction::Restored);
+        let info = registry.get("cu2501").unwrap();
+        assert!((info.limit_up_rate - 0.06).abs() < 1e-9);
+        assert!((info.margin_rate - 0.08).abs() < 1e-9);
+
+        assert_eq!(manager.get_history(Some("cu2501")).len(), 3);
+        let mut notices = 0;
+        while let Ok(event) = rx.try_recv() {
+            if let MarketDataEvent::PriceLimitChanged { level, .. } = event {
+                assert!(level <= 2);
+                notices += 1;
+            }
+        }
+        assert_eq!(notices, 3);
+    }
+
+    /// 触发天数 N=2：仅连续两日同向停板才扩板，方向反转重新计数
+    #[test]
+    fn test_trigger_days_and_direction_reset() {
+        let (registry, manager) = setup();
+        manager.update_config(LimitExpansionConfig {
+            trigger_days: 2,
+            ..Default::default()
+        });
+
+        assert!(manager.on_daily_close("cu2501", "20250102", 74200.0).unwrap().is_none());
+        // 反向跌停：74200 * 0.94 = 69748 -> 69750
+        assert!(manager.on_daily_close("cu2501", "20250103", 69750.0).unwrap().is_none());
+        assert_eq!(manager.get_state("cu2501").unwrap().consecutive_days, 1);
+
+        // 连续第二个跌停 -> 扩板
+        let (lower, _) = manager.limit_prices("cu2501").unwrap();
+        let e = manager.on_daily_close("cu2501", "20250106", lower).unwrap().unwrap();
+        assert_eq!(e.direction, Some(LimitDirection::Down));
+        assert_eq!(e.level, 1);
+        assert!((registry.get("cu2501").unwrap().limit_down_rate - 0.09).abs() < 1e-9);
+    }
+}
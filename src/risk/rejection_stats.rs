@@ -0,0 +1,499 @@
+//! 拒单原因统计
+//!
+//! 统一 OrderRouter / PreTradeCheck 所有拒单分支的原因码（`RejectReason`），
+//! 按 日期 × 合约 × 小时 × 原因 分桶计数，供运营看板查询拒单占比。
+//!
+//! - 每次拒单同时递增 Prometheus 计数器 `qaexchange_orders_rejected_total{reason}`
+//! - 日切时将前一交易日的统计落盘为 `rejections_YYYYMMDD.json`，`flush()` 可随时落盘当日统计
+//! - 启动时自动加载当日已落盘的统计，避免重启丢失
+//!
+//! @yutiansut @quantaxis
+
+use crate::observability::metrics::ORDERS_REJECTED_TOTAL;
+use crate::risk::pre_trade_check::RiskCheckCode;
+use crate::utils::time_service::{now_local, time_service};
+use chrono::{NaiveDate, NaiveDateTime, Timelike};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// 拒单原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RejectReason {
+    /// 资金不足
+    InsufficientFunds,
+    /// 超过持仓限额
+    ExceedPositionLimit,
+    /// 订单金额超限
+    ExceedOrderLimit,
+    /// 风险度过高
+    HighRiskRatio,
+    /// 自成交风险
+    SelfTradingRisk,
+    /// 账户不存在
+    AccountNotFound,
+    /// 合约不存在
+    InstrumentNotFound,
+    /// 订单参数非法
+    InvalidOrderParams,
+    /// 价格超出涨跌停板
+    PriceOutOfLimit,
+    /// 价格超出价格笼子
+    PriceOutOfBand,
+    /// 市价单无行情
+    NoMarketPrice,
+    /// 交易状态不允许（停牌、非交易时段等）
+    TradingStateRejected,
+    /// FOK 无法全部成交
+    FokUnfillable,
+    /// 订单簿挂单数已达上限
+    OrderBookFull,
+    /// 账户下单/撤单频率超限
+    RateLimited,
+    /// 账户交易受限（只平仓 / 暂停交易）
+    TradingRestricted,
+    /// 账户由其他网关实例持有操作租约
+    AccountLeaseHeld,
+    /// 新合约上市保护时段内不接受市价单
+    ListingProtection,
+    /// 单日成交量达限
+    TradeVolumeLimited,
+    /// 价格不是最小变动价位的整数倍
+    InvalidPriceTick,
+    /// 持仓集中度超限，限制开仓
+    PositionConcentration,
+    /// 短时间内重复提交的相同订单
+    DuplicateOrder,
+    /// 风控检查异常
+    RiskCheckError,
+    /// 路由到撮合引擎失败
+    RoutingError,
+    /// 撮合引擎拒绝
+    MatchingRejected,
+    /// 撮合引擎超时未回应且订单簿中不存在（看门狗自动拒单）
+    MatchingAckTimeout,
+}
+
+impl RejectReason {
+    /// 原因标签（用于 Prometheus label 与统计 key）
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RejectReason::InsufficientFunds => "insufficient_funds",
+            RejectReason::ExceedPositionLimit => "exceed_position_limit",
+            RejectReason::ExceedOrderLimit => "exceed_order_limit",
+            RejectReason::HighRiskRatio => "high_risk_ratio",
+            RejectReason::SelfTradingRisk => "self_trading_risk",
+            RejectReason::AccountNotFound => "account_not_found",
+            RejectReason::InstrumentNotFound => "instrument_not_found",
+            RejectReason::InvalidOrderParams => "invalid_order_params",
+            RejectReason::PriceOutOfLimit => "price_out_of_limit",
+            RejectReason::PriceOutOfBand => "price_out_of_band",
+            RejectReason::NoMarketPrice => "no_market_price",
+            RejectReason::TradingStateRejected => "trading_state_rejected",
+            RejectReason::FokUnfillable => "fok_unfillable",
+            RejectReason::OrderBookFull => "order_book_full",
+            RejectReason::RateLimited => "rate_limited",
+            RejectReason::TradingRestricted => "trading_restricted",
+            RejectReason::AccountLeaseHeld => "account_lease_held",
+            RejectReason::ListingProtection => "listing_protection",
+            RejectReason::TradeVolumeLimited => "trade_volume_limited",
+            RejectReason::InvalidPriceTick => "invalid_price_tick",
+            RejectReason::PositionConcentration => "position_concentration",
+            RejectReason::DuplicateOrder => "duplicate_order",
+            RejectReason::RiskCheckError => "risk_check_error",
+            RejectReason::RoutingError => "routing_error",
+            RejectReason::MatchingRejected => "matching_rejected",
+            RejectReason::MatchingAckTimeout => "matching_ack_timeout",
+        }
+    }
+
+    /// 订单路由返回的错误码
+    ///
+    /// 风控拒单（PreTradeCheck）沿用 `RiskCheckCode` 的 1001-1008，由调用方直接回传
+    pub fn error_code(&self) -> u32 {
+        match self {
+            RejectReason::InsufficientFunds => 4001,
+            RejectReason::ExceedPositionLimit => RiskCheckCode::ExceedPositionLimit as u32,
+            RejectReason::ExceedOrderLimit => RiskCheckCode::ExceedOrderLimit as u32,
+            RejectReason::HighRiskRatio => RiskCheckCode::HighRiskRatio as u32,
+            RejectReason::SelfTradingRisk => RiskCheckCode::SelfTradingRisk as u32,
+            RejectReason::AccountNotFound => 4000,
+            RejectReason::InstrumentNotFound => RiskCheckCode::InstrumentNotFound as u32,
+            RejectReason::InvalidOrderParams => RiskCheckCode::InvalidOrderParams as u32,
+            RejectReason::PriceOutOfLimit => 4003,
+            RejectReason::PriceOutOfBand => RiskCheckCode::PriceOutOfBand as u32,
+            RejectReason::NoMarketPrice => 4002,
+            RejectReason::TradingStateRejected => 4100,
+            RejectReason::FokUnfillable => 4010,
+            RejectReason::OrderBookFull => 4011,
+            RejectReason::RateLimited => 4012,
+            RejectReason::TradingRestricted => 4013,
+            RejectReason::AccountLeaseHeld => 4014,
+            RejectReason::ListingProtection => 4015,
+            RejectReason::TradeVolumeLimited => 4016,
+            RejectReason::InvalidPriceTick => 4017,
+            RejectReason::PositionConcentration => 4018,
+            RejectReason::DuplicateOrder => 4019,
+            RejectReason::RiskCheckError => 9999,
+            RejectReason::RoutingError => 5000,
+            RejectReason::MatchingRejected => 5001,
+            RejectReason::MatchingAckTimeout => 5002,
+        }
+    }
+
+    /// 风控拒绝代码 -> 拒单原因
+    pub fn from_risk_code(code: RiskCheckCode) -> Self {
+        match code {
+            RiskCheckCode::InsufficientFunds => RejectReason::InsufficientFunds,
+            RiskCheckCode::ExceedPositionLimit => RejectReason::ExceedPositionLimit,
+            RiskCheckCode::ExceedOrderLimit => RejectReason::ExceedOrderLimit,
+            RiskCheckCode::HighRiskRatio => RejectReason::HighRiskRatio,
+            RiskCheckCode::SelfTradingRisk => RejectReason::SelfTradingRisk,
+            RiskCheckCode::AccountNotFound => RejectReason::AccountNotFound,
+            RiskCheckCode::InstrumentNotFound => RejectReason::InstrumentNotFound,
+            RiskCheckCode::InvalidOrderParams => RejectReason::InvalidOrderParams,
+            RiskCheckCode::PriceOutOfBand => RejectReason::PriceOutOfBand,
+        }
+    }
+}
+
+impl std::fmt::Display for RejectReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 聚合维度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RejectionGroupBy {
+    #[default]
+    Reason,
+    Instrument,
+    Hour,
+}
+
+/// 单个统计桶（日期内 合约 × 小时 × 原因）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RejectionBucket {
+    pub instrument_id: String,
+    pub hour: u32,
+    pub reason: RejectReason,
+    pub count: u64,
+}
+
+/// 落盘格式
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct DayRejections {
+    date: String,
+    buckets: Vec<RejectionBucket>,
+}
+
+/// 聚合结果条目
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RejectionGroup {
+    pub key: String,
+    pub count: u64,
+    /// 占当日拒单总数的比例 (0.0-1.0)
+    pub ratio: f64,
+}
+
+/// 聚合统计结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RejectionSummary {
+    pub date: String,
+    pub group_by: RejectionGroupBy,
+    pub total: u64,
+    pub groups: Vec<RejectionGroup>,
+}
+
+type BucketKey = (String, u32, RejectReason);
+
+/// 拒单统计器
+pub struct RejectionStats {
+    /// 落盘目录（None 表示仅内存统计）
+    persist_dir: Option<PathBuf>,
+    /// date -> (instrument, hour, reason) -> count
+    days: Mutex<HashMap<NaiveDate, HashMap<BucketKey, u64>>>,
+    /// 当前统计日期（用于检测日切）
+    current_date: Mutex<Option<NaiveDate>>,
+}
+
+impl Default for RejectionStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RejectionStats {
+    /// 创建仅内存统计器
+    pub fn new() -> Self {
+        Self {
+            persist_dir: None,
+            days: Mutex::new(HashMap::new()),
+            current_date: Mutex::new(None),
+        }
+    }
+
+    /// 创建带日终落盘的统计器，并加载当日已落盘的统计
+    pub fn with_persist_dir<P: AsRef<Path>>(dir: P) -> Self {
+        let dir = dir.as_ref().to_path_buf();
+        if let Err(e) = std::fs::create_dir_all(&dir) {
+            log::warn!("Failed to create rejection stats dir {:?}: {}", dir, e);
+        }
+
+        let stats = Self {
+            persist_dir: Some(dir),
+            days: Mutex::new(HashMap::new()),
+            current_date: Mutex::new(None),
+        };
+
+        let today = time_service().today();
+        if let Some(buckets) = stats.load_day(today) {
+            stats.days.lock().insert(today, buckets);
+        }
+        stats
+    }
+
+    /// 记录一次拒单（当前交易所本地时间）
+    pub fn record(&self, reason: RejectReason, instrument_id: &str) {
+        self.record_at(reason, instrument_id, now_local().naive_local());
+    }
+
+    /// 记录一次拒单（指定时间）
+    pub fn record_at(&self, reason: RejectReason, instrument_id: &str, at: NaiveDateTime) {
+        ORDERS_REJECTED_TOTAL
+            .with_label_values(&[reason.as_str()])
+            .inc();
+
+        let date = at.date();
+        self.roll_day(date);
+
+        let mut days = self.days.lock();
+        *days
+            .entry(date)
+            .or_default()
+            .entry((instrument_id.to_string(), at.hour(), reason))
+            .or_insert(0) += 1;
+    }
+
+    /// 日切：新日期首次出现时落盘前一日统计
+    fn roll_day(&self, date: NaiveDate) {
+        let previous = {
+            let mut current = self.current_date.lock();
+            match *current {
+                Some(d) if d >= date => return,
+                prev => {
+                    *current = Some(date);
+                    prev
+                }
+            }
+        };
+
+        if let Some(prev) = previous {
+            if let Err(e) = self.persist_day(prev) {
+                log::error!("Failed to persist rejection stats for {}: {}", prev, e);
+            }
+        }
+    }
+
+    /// 落盘当日（及内存中所有日期）的统计，用于日终结算或停机前调用
+    pub fn flush(&self) -> Result<usize, String> {
+        let dates: Vec<NaiveDate> = self.days.lock().keys().copied().collect();
+        for date in &dates {
+            self.persist_day(*date)?;
+        }
+        Ok(dates.len())
+    }
+
+    /// 落盘指定日期的统计
+    pub fn persist_day(&self, date: NaiveDate) -> Result<(), String> {
+        let Some(ref dir) = self.persist_dir else {
+            return Ok(());
+        };
+
+        let day = DayRejections {
+            date: date.format("%Y-%m-%d").to_string(),
+            buckets: self.buckets_of(date).unwrap_or_default(),
+        };
+        let json = serde_json::to_vec_pretty(&day).map_err(|e| e.to_string())?;
+
+        let path = Self::day_file(dir, date);
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, json).map_err(|e| format!("write {:?}: {}", tmp, e))?;
+        std::fs::rename(&tmp, &path).map_err(|e| format!("rename {:?}: {}", path, e))?;
+
+        log::info!("Rejection stats persisted: {:?}", path);
+        Ok(())
+    }
+
+    /// 查询指定日期的聚合统计（内存中没有时从落盘文件加载）
+    pub fn query(&self, date: NaiveDate, group_by: RejectionGroupBy) -> RejectionSummary {
+        let buckets = self
+            .buckets_of(date)
+            .or_else(|| {
+                self.load_day(date).map(|m| {
+                    m.into_iter()
+                        .map(|((instrument_id, hour, reason), count)| RejectionBucket {
+                            instrument_id,
+                            hour,
+                            reason,
+                            count,
+                        })
+                        .collect()
+                })
+            })
+            .unwrap_or_default();
+
+        let total: u64 = buckets.iter().map(|b| b.count).sum();
+        let mut grouped: HashMap<String, u64> = HashMap::new();
+        for b in &buckets {
+            let key = match group_by {
+                RejectionGroupBy::Reason => b.reason.as_str().to_string(),
+                RejectionGroupBy::Instrument => b.instrument_id.clone(),
+                RejectionGroupBy::Hour => format!("{:02}", b.hour),
+            };
+            *grouped.entry(key).or_insert(0) += b.count;
+        }
+
+        let mut groups: Vec<RejectionGroup> = grouped
+            .into_iter()
+            .map(|(key, count)| RejectionGroup {
+                key,
+                count,
+                ratio: if total > 0 { count as f64 / total as f64 } else { 0.0 },
+            })
+            .collect();
+        match group_by {
+            RejectionGroupBy::Hour => groups.sort_by(|a, b| a.key.cmp(&b.key)),
+            _ => groups.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.key.cmp(&b.key))),
+        }
+
+        RejectionSummary {
+            date: date.format("%Y-%m-%d").to_string(),
+            group_by,
+            total,
+            groups,
+        }
+    }
+
+    /// 指定日期某原因的拒单数
+    pub fn count(&self, date: NaiveDate, reason: RejectReason) -> u64 {
+        self.days
+            .lock()
+            .get(&date)
+            .map(|m| {
+                m.iter()
+                    .filter(|((_, _, r), _)| *r == reason)
+                    .map(|(_, c)| *c)
+                    .sum()
+            })
+            .unwrap_or(0)
+    }
+
+    fn buckets_of(&self, date: NaiveDate) -> Option<Vec<RejectionBucket>> {
+        self.days.lock().get(&date).map(|m| {
+            m.iter()
+                .map(|((instrument_id, hour, reason), count)| RejectionBucket {
+                    instrument_id: instrument_id.clone(),
+                    hour: *hour,
+                    reason: *reason,
+                    count: *count,
+                })
+                .collect()
+        })
+    }
+
+    fn load_day(&self, date: NaiveDate) -> Option<HashMap<BucketKey, u64>> {
+        let dir = self.persist_dir.as_ref()?;
+        let path = Self::day_file(dir, date);
+        let data = std::fs::read(&path).ok()?;
+        match serde_json::from_slice::<DayRejections>(&data) {
+            Ok(day) => Some(
+                day.buckets
+                    .into_iter()
+                    .map(|b| ((b.instrument_id, b.hour, b.reason), b.count))
+                    .collect(),
+            ),
+            Err(e) => {
+                log::warn!("Failed to parse rejection stats {:?}: {}", path, e);
+                None
+            }
+        }
+    }
+
+    fn day_file(dir: &Path, date: NaiveDate) -> PathBuf {
+        dir.join(format!("rejections_{}.json", date.format("%Y%m%d")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(date: &str, hour: u32) -> NaiveDateTime {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .unwrap()
+            .and_hms_opt(hour, 15, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_group_by_reason_instrument_hour() {
+        let stats = RejectionStats::new();
+        stats.record_at(RejectReason::InsufficientFunds, "IF2501", at("2025-01-02", 9));
+        stats.record_at(RejectReason::InsufficientFunds, "IC2501", at("2025-01-02", 10));
+        stats.record_at(RejectReason::InsufficientFunds, "IF2501", at("2025-01-02", 10));
+        stats.record_at(RejectReason::TradingStateRejected, "IF2501", at("2025-01-02", 9));
+
+        let date = NaiveDate::from_ymd_opt(2025, 1, 2).unwrap();
+        let by_reason = stats.query(date, RejectionGroupBy::Reason);
+        assert_eq!(by_reason.total, 4);
+        assert_eq!(by_reason.groups[0].key, "insufficient_funds");
+        assert_eq!(by_reason.groups[0].count, 3);
+        assert!((by_reason.groups[0].ratio - 0.75).abs() < 1e-9);
+
+        let by_instrument = stats.query(date, RejectionGroupBy::Instrument);
+        assert_eq!(by_instrument.groups[0].key, "IF2501");
+        assert_eq!(by_instrument.groups[0].count, 3);
+
+        let by_hour = stats.query(date, RejectionGroupBy::Hour);
+        let keys: Vec<_> = by_hour.groups.iter().map(|g| g.key.as_str()).collect();
+        assert_eq!(keys, vec!["09", "10"]);
+
+        assert_eq!(stats.count(date, RejectReason::TradingStateRejected), 1);
+    }
+
+    /// 日切落盘后，重启的统计器仍能查询前一日统计
+    #[test]
+    fn test_persist_on_day_rollover() {
+        let dir = tempfile::tempdir().unwrap();
+        let stats = RejectionStats::with_persist_dir(dir.path());
+        stats.record_at(RejectReason::FokUnfillable, "IF2501", at("2025-01-02", 14));
+        stats.record_at(RejectReason::FokUnfillable, "IF2501", at("2025-01-02", 14));
+        assert!(!dir.path().join("rejections_20250102.json").exists());
+
+        stats.record_at(RejectReason::NoMarketPrice, "IF2501", at("2025-01-03", 9));
+        assert!(dir.path().join("rejections_20250102.json").exists());
+
+        let reloaded = RejectionStats::with_persist_dir(dir.path());
+        let summary = reloaded.query(
+            NaiveDate::from_ymd_opt(2025, 1, 2).unwrap(),
+            RejectionGroupBy::Reason,
+        );
+        assert_eq!(summary.total, 2);
+        assert_eq!(summary.groups[0].key, "fok_unfillable");
+    }
+
+    #[test]
+    fn test_reason_codes_compatible() {
+        assert_eq!(RejectReason::InsufficientFunds.error_code(), 4001);
+        assert_eq!(RejectReason::AccountNotFound.error_code(), 4000);
+        assert_eq!(
+            RejectReason::from_risk_code(RiskCheckCode::ExceedPositionLimit).error_code(),
+            1002
+        );
+    }
+}
@@ -0,0 +1,533 @@
+//! 风险历史时序存储
+//!
+//! @yutiansut @quantaxis
+//!
+//! 盘中风控按采样间隔（默认 1 分钟）记录账户风险快照，支持历史风险回放：
+//!
+//! - **采样**: RiskMonitor 每轮检查时询问是否到达采样点，到达则记录全部账户快照
+//! - **持久化**: 快照写入 WAL（`WalRecord::RiskSnapshot`），重启后可回放加载
+//! - **全市场曲线**: 每个采样点同时汇总全市场权益/保证金，记为 `__MARKET__`
+//! - **降采样**: 查询时按 interval 分桶，数值取桶内均值，风险率额外给出桶内峰值
+//! - **保留策略**: 超过 `retention_days` 的快照在采样时裁剪（WAL 文件由 checkpoint 截断）
+
+use dashmap::DashMap;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+
+use crate::storage::wal::{WalManager, WalRecord};
+use crate::utils::timestamp::{millis_to_nanos, nanos_to_millis};
+
+/// 全市场汇总曲线的键
+pub const MARKET_RISK_KEY: &str = "__MARKET__";
+
+const MS_PER_DAY: i64 = 24 * 60 * 60 * 1000;
+
+/// 风险快照（时间戳为毫秒）
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RiskSnapshot {
+    /// 采样时间（毫秒）
+    pub timestamp: i64,
+    /// 账户权益
+    pub balance: f64,
+    /// 保证金占用（持仓 + 冻结）
+    pub margin: f64,
+    /// 风险率
+    pub risk_ratio: f64,
+    /// 持仓市值
+    pub position_value: f64,
+}
+
+/// 降采样后的历史点
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RiskHistoryPoint {
+    /// 桶起始时间（毫秒）
+    pub timestamp: i64,
+    /// 桶内平均权益
+    pub balance: f64,
+    /// 桶内平均保证金
+    pub margin: f64,
+    /// 桶内平均风险率
+    pub risk_ratio: f64,
+    /// 桶内最高风险率
+    pub max_risk_ratio: f64,
+    /// 桶内平均持仓市值
+    pub position_value: f64,
+    /// 桶内样本数
+    pub samples: usize,
+}
+
+/// 风险历史配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RiskHistoryConfig {
+    /// 是否启用采样
+    pub enabled: bool,
+    /// 采样间隔（毫秒）
+    pub sample_interval_ms: i64,
+    /// 保留天数
+    pub retention_days: u32,
+    /// 单次查询最多返回点数（超出时自动放大降采样间隔）
+    pub max_query_points: usize,
+}
+
+impl Default for RiskHistoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            sample_interval_ms: 60_000, // 1分钟采样一次
+            retention_days: 30,
+            max_query_points: 2000,
+        }
+    }
+}
+
+/// 存储统计
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RiskHistoryStats {
+    /// 有历史的账户数（不含全市场曲线）
+    pub account_count: usize,
+    /// 账户快照总数
+    pub account_points: usize,
+    /// 全市场曲线点数
+    pub market_points: usize,
+    /// 估算内存占用（字节）
+    pub estimated_bytes: usize,
+    /// 最早快照时间（毫秒）
+    pub oldest_timestamp: Option<i64>,
+    /// 采样间隔（毫秒）
+    pub sample_interval_ms: i64,
+    /// 保留天数
+    pub retention_days: u32,
+}
+
+/// 按 interval 分桶降采样（输入需按时间升序）
+pub fn downsample(points: &[RiskSnapshot], interval_ms: i64) -> Vec<RiskHistoryPoint> {
+    let mut result: Vec<RiskHistoryPoint> = Vec::new();
+    if interval_ms <= 0 {
+        return result;
+    }
+
+    for p in points {
+        let bucket = p.timestamp.div_euclid(interval_ms) * interval_ms;
+        match result.last_mut() {
+            Some(last) if last.timestamp == bucket => {
+                // 先累加，收尾时统一取均值
+                last.balance += p.balance;
+                last.margin += p.margin;
+                last.risk_ratio += p.risk_ratio;
+                last.max_risk_ratio = last.max_risk_ratio.max(p.risk_ratio);
+                last.position_value += p.position_value;
+                last.samples += 1;
+            }
+            _ => result.push(RiskHistoryPoint {
+                timestamp: bucket,
+                balance: p.balance,
+                margin: p.margin,
+                risk_ratio: p.risk_ratio,
+                max_risk_ratio: p.risk_ratio,
+                position_value: p.position_value,
+                samples: 1,
+            }),
+        }
+    }
+
+    for point in result.iter_mut() {
+        let n = point.samples as f64;
+        point.balance /= n;
+        point.margin /= n;
+        point.risk_ratio /= n;
+        point.position_value /= n;
+    }
+    result
+}
+
+/// 解析查询间隔：纯数字为秒，支持 s/m/h/d 后缀
+pub fn parse_interval_ms(interval: &str) -> Option<i64> {
+    let interval = interval.trim();
+    let (num, unit_ms) = match interval.chars().last()? {
+        's' => (&interval[..interval.len() - 1], 1_000),
+        'm' => (&interval[..interval.len() - 1], 60_000),
+        'h' => (&interval[..interval.len() - 1], 3_600_000),
+        'd' => (&interval[..interval.len() - 1], MS_PER_DAY),
+        _ => (interval, 1_000),
+    };
+    num.parse::<i64>()
+        .ok()
+        .filter(|n| *n > 0)
+        .map(|n| n * unit_ms)
+}
+
+/// 风险历史存储
+/// @yutiansut @quantaxis
+pub struct RiskHistoryStore {
+    config: RwLock<RiskHistoryConfig>,
+    /// 时序数据 (account_id / __MARKET__ -> 按时间升序的快照)
+    series: DashMap<String, VecDeque<RiskSnapshot>>,
+    /// 上次采样时间（毫秒）
+    last_sample_ms: AtomicI64,
+    /// WAL 持久化（可选）
+    wal: RwLock<Option<Arc<WalManager>>>,
+}
+
+impl Default for RiskHistoryStore {
+    fn default() -> Self {
+        Self::new(RiskHistoryConfig::default())
+    }
+}
+
+impl RiskHistoryStore {
+    pub fn new(config: RiskHistoryConfig) -> Self {
+        Self {
+            config: RwLock::new(config),
+            series: DashMap::new(),
+            last_sample_ms: AtomicI64::new(i64::MIN),
+            wal: RwLock::new(None),
+        }
+    }
+
+    /// 设置 WAL 管理器
+    pub fn set_wal_manager(&self, wal: Arc<WalManager>) {
+        *self.wal.write() = Some(wal);
+    }
+
+    /// 获取配置
+    pub fn config(&self) -> RiskHistoryConfig {
+        self.config.read().clone()
+    }
+
+    /// 更新配置
+    pub fn update_config(&self, config: RiskHistoryConfig) {
+        log::info!(
+            "[RiskHistory] Config updated: enabled={}, interval={}ms, retention={}d",
+            config.enabled,
+            config.sample_interval_ms,
+            config.retention_days
+        );
+        *self.config.write() = config;
+    }
+
+    /// 判断当前是否到达采样点，到达则占用该采样点
+    pub fn try_begin_sample(&self, now_ms: i64) -> bool {
+        let config = self.config.read();
+        if !config.enabled {
+            return false;
+        }
+        let interval = config.sample_interval_ms.max(1);
+        self.last_sample_ms
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |last| {
+                if last == i64::MIN || now_ms - last >= interval {
+                    // 对齐到采样网格，避免检查周期抖动导致采样点漂移
+                    Some(now_ms - now_ms.rem_euclid(interval))
+                } else {
+                    None
+                }
+            })
+            .is_ok()
+    }
+
+    /// 记录一个采样点的全部账户快照，并汇总全市场曲线
+    pub fn record_sample(&self, timestamp: i64, accounts: Vec<(String, RiskSnapshot)>) {
+        let mut total_balance = 0.0;
+        let mut total_margin = 0.0;
+        let mut total_position_value = 0.0;
+        let mut wal_records = Vec::with_capacity(accounts.len() + 1);
+
+        for (account_id, mut snapshot) in accounts {
+            snapshot.timestamp = timestamp;
+            total_balance += snapshot.balance;
+            total_margin += snapshot.margin;
+            total_position_value += snapshot.position_value;
+            wal_records.push(Self::to_wal_record(&account_id, &snapshot));
+            self.insert(account_id, snapshot);
+        }
+
+        let market = RiskSnapshot {
+            timestamp,
+            balance: total_balance,
+            margin: total_margin,
+            risk_ratio: if total_balance > 0.0 {
+                total_margin / total_balance
+            } else {
+                0.0
+            },
+            position_value: total_position_value,
+        };
+        wal_records.push(Self::to_wal_record(MARKET_RISK_KEY, &market));
+        self.insert(MARKET_RISK_KEY.to_string(), market);
+
+        if let Some(wal) = self.wal.read().as_ref() {
+            if let Err(e) = wal.append_batch(wal_records) {
+                log::error!("[RiskHistory] Failed to persist risk snapshots: {}", e);
+            }
+        }
+
+        self.prune(timestamp);
+    }
+
+    fn insert(&self, key: String, snapshot: RiskSnapshot) {
+        let mut series = self.series.entry(key).or_default();
+        // 回放或乱序写入时保持升序
+        let pos = series.partition_point(|p| p.timestamp <= snapshot.timestamp);
+        series.insert(pos, snapshot);
+    }
+
+    fn to_wal_record(account_id: &str, snapshot: &RiskSnapshot) -> WalRecord {
+        WalRecord::RiskSnapshot {
+            account_id: WalRecord::to_fixed_array_64(account_id),
+            balance: snapshot.balance,
+            margin: snapshot.margin,
+            risk_ratio: snapshot.risk_ratio,
+            position_value: snapshot.position_value,
+            timestamp: millis_to_nanos(snapshot.timestamp),
+        }
+    }
+
+    /// 裁剪超出保留期的快照，返回裁剪数量
+    pub fn prune(&self, now_ms: i64) -> usize {
+        let cutoff = now_ms - self.config.read().retention_days as i64 * MS_PER_DAY;
+        let mut removed = 0;
+        self.series.retain(|_, series| {
+            while series.front().map_or(false, |p| p.timestamp < cutoff) {
+                series.pop_front();
+                removed += 1;
+            }
+            !series.is_empty()
+        });
+        removed
+    }
+
+    /// 从 WAL 加载历史快照，返回加载条数
+    pub fn load_from_wal(&self, wal: &WalManager) -> Result<usize, String> {
+        let mut loaded = 0;
+        wal.replay(|entry| {
+            if let WalRecord::RiskSnapshot {
+                account_id,
+                balance,
+                margin,
+                risk_ratio,
+                position_value,
+                timestamp,
+            } = entry.record
+            {
+                let snapshot = RiskSnapshot {
+                    timestamp: nanos_to_millis(timestamp),
+                    balance,
+                    margin,
+                    risk_ratio,
+                    position_value,
+                };
+                self.last_sample_ms
+                    .fetch_max(snapshot.timestamp, Ordering::SeqCst);
+                self.insert(WalRecord::from_fixed_array(&account_id), snapshot);
+                loaded += 1;
+            }
+            Ok(())
+        })?;
+        self.prune(chrono::Utc::now().timestamp_millis());
+        Ok(loaded)
+    }
+
+    /// 查询账户风险历史（interval_ms 为 None 时返回原始采样点）
+    pub fn query(
+        &self,
+        account_id: &str,
+        start_ms: i64,
+        end_ms: i64,
+        interval_ms: Option<i64>,
+    ) -> Vec<RiskHistoryPoint> {
+        let points: Vec<RiskSnapshot> = match self.series.get(account_id) {
+            Some(series) => {
+                let from = series.partition_point(|p| p.timestamp < start_ms);
+                series
+                    .range(from..)
+                    .take_while(|p| p.timestamp <= end_ms)
+                    .copied()
+                    .collect()
+            }
+            None => return Vec::new(),
+        };
+
+        let config = self.config.read();
+        let mut interval = interval_ms.unwrap_or(config.sample_interval_ms).max(1);
+        // 点数超过上限时放大间隔
+        let max_points = config.max_query_points.max(1) as i64;
+        if let (Some(first), Some(last)) = (points.first(), points.last()) {
+            let span = last.timestamp - first.timestamp + 1;
+            let min_interval = (span + max_points - 1) / max_points;
+            interval = interval.max(min_interval);
+        }
+        downsample(&points, interval)
+    }
+
+    /// 查询全市场风险曲线
+    pub fn query_market(
+        &self,
+        start_ms: i64,
+        end_ms: i64,
+        interval_ms: Option<i64>,
+    ) -> Vec<RiskHistoryPoint> {
+        self.query(MARKET_RISK_KEY, start_ms, end_ms, interval_ms)
+    }
+
+    /// 区间内日均可用资金（权益 - 保证金占用，按原始采样点等权平均，无采样点返回 None）
+    pub fn average_available(&self, account_id: &str, start_ms: i64, end_ms: i64) -> Option<f64> {
+        let series = self.series.get(account_id)?;
+        let from = series.partition_point(|p| p.timestamp < start_ms);
+        let (sum, count) = series
+            .range(from..)
+            .take_while(|p| p.timestamp <= end_ms)
+            .fold((0.0, 0usize), |(sum, count), p| {
+                (sum + (p.balance - p.margin).max(0.0), count + 1)
+            });
+        if count == 0 {
+            None
+        } else {
+            Some(sum / count as f64)
+        }
+    }
+
+    /// 移除账户历史（销户时调用）
+    pub fn remove_account(&self, account_id: &str) {
+        self.series.remove(account_id);
+    }
+
+    /// 存储统计
+    pub fn stats(&self) -> RiskHistoryStats {
+        let config = self.config.read();
+        let mut account_count = 0;
+        let mut account_points = 0;
+        let mut market_points = 0;
+        let mut oldest: Option<i64> = None;
+
+        for entry in self.series.iter() {
+            if entry.key() == MARKET_RISK_KEY {
+                market_points = entry.value().len();
+            } else {
+                account_count += 1;
+                account_points += entry.value().len();
+            }
+            if let Some(first) = entry.value().front() {
+                oldest = Some(oldest.map_or(first.timestamp, |o| o.min(first.timestamp)));
+            }
+        }
+
+        RiskHistoryStats {
+            account_count,
+            account_points,
+            market_points,
+            estimated_bytes: (account_points + market_points) * std::mem::size_of::<RiskSnapshot>(),
+            oldest_timestamp: oldest,
+            sample_interval_ms: config.sample_interval_ms,
+            retention_days: config.retention_days,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(timestamp: i64, balance: f64, margin: f64) -> RiskSnapshot {
+        RiskSnapshot {
+            timestamp,
+            balance,
+            margin,
+            risk_ratio: margin / balance,
+            position_value: margin * 10.0,
+        }
+    }
+
+    #[test]
+    fn test_downsample_averages_and_peak() {
+        let points = vec![
+            snapshot(0, 100_000.0, 20_000.0),
+            snapshot(60_000, 100_000.0, 40_000.0),
+            snapshot(120_000, 80_000.0, 60_000.0),
+            snapshot(300_000, 90_000.0, 9_000.0),
+            snapshot(360_000, 110_000.0, 11_000.0),
+        ];
+
+        let result = downsample(&points, 300_000);
+        assert_eq!(result.len(), 2);
+
+        assert_eq!(result[0].timestamp, 0);
+        assert_eq!(result[0].samples, 3);
+        assert!((result[0].balance - 93_333.333_333).abs() < 1e-3);
+        assert!((result[0].margin - 40_000.0).abs() < 1e-9);
+        assert!((result[0].risk_ratio - (0.2 + 0.4 + 0.75) / 3.0).abs() < 1e-12);
+        assert_eq!(result[0].max_risk_ratio, 0.75);
+        assert!((result[0].position_value - 400_000.0).abs() < 1e-6);
+
+        assert_eq!(result[1].timestamp, 300_000);
+        assert_eq!(result[1].samples, 2);
+        assert_eq!(result[1].balance, 100_000.0);
+        assert_eq!(result[1].margin, 10_000.0);
+        assert!((result[1].risk_ratio - 0.1).abs() < 1e-12);
+
+        assert_eq!(parse_interval_ms("5m"), Some(300_000));
+        assert_eq!(parse_interval_ms("30"), Some(30_000));
+        assert_eq!(parse_interval_ms("1d"), Some(MS_PER_DAY));
+        assert_eq!(parse_interval_ms("0"), None);
+        assert_eq!(parse_interval_ms("abc"), None);
+    }
+
+    #[test]
+    fn test_sampling_market_curve_and_retention() {
+        let store = RiskHistoryStore::new(RiskHistoryConfig {
+            retention_days: 1,
+            ..Default::default()
+        });
+
+        // 监控每秒检查一次，持续 10 分钟 → 恰好 10 个 1 分钟采样点
+        let base = 1_700_000_400_000i64; // 对齐到 10 分钟
+        let mut sampled = 0;
+        for sec in 0..600 {
+            let now = base + sec * 1000 + 37;
+            if store.try_begin_sample(now) {
+                sampled += 1;
+                store.record_sample(
+                    now,
+                    vec![
+                        ("acc_a".to_string(), snapshot(0, 100_000.0, 30_000.0)),
+                        ("acc_b".to_string(), snapshot(0, 50_000.0, 20_000.0)),
+                    ],
+                );
+            }
+        }
+        assert_eq!(sampled, 10);
+
+        let raw = store.query("acc_a", base, base + 600_000, None);
+        assert_eq!(raw.len(), 10);
+        for (i, p) in raw.iter().enumerate() {
+            assert_eq!(p.timestamp, base + i as i64 * 60_000);
+        }
+
+        let market = store.query_market(base, base + 600_000, Some(600_000));
+        assert_eq!(market.len(), 1);
+        assert_eq!(market[0].samples, 10);
+        assert_eq!(market[0].balance, 150_000.0);
+        assert_eq!(market[0].margin, 50_000.0);
+        assert!((market[0].risk_ratio - 50_000.0 / 150_000.0).abs() < 1e-12);
+
+        let stats = store.stats();
+        assert_eq!(stats.account_count, 2);
+        assert_eq!(stats.account_points, 20);
+        assert_eq!(stats.market_points, 10);
+
+        // 日均可用资金：每个采样点可用 100,000 - 30,000 = 70,000
+        assert_eq!(
+            store.average_available("acc_a", base, base + 600_000),
+            Some(70_000.0)
+        );
+        assert_eq!(
+            store.average_available("acc_a", base + 600_001, i64::MAX),
+            None
+        );
+
+        // 超出保留期后全部裁剪
+        assert_eq!(store.prune(base + 2 * MS_PER_DAY), 30);
+        assert!(store.query("acc_a", 0, i64::MAX, None).is_empty());
+    }
+}
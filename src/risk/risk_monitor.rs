@@ -8,10 +8,26 @@
 //! - **实时监控循环**: 后台线程持续监控所有账户风险
 //! - **风险预警**: 达到阈值时自动告警
 //! - **自动强平触发**: 风险超限时自动触发强平流程
-
+//! - **风险历史采样**: 按采样间隔记录风险快照到 RiskHistoryStore，支持历史回放
+//! - **强平预警阶梯**: MarginCallLadder 分级推送追保通知，逾期未追保才强平
+//! - **账户止损**: AccountStopLoss 当日亏损达用户止损线时暂停交易并市价平仓
+//! - **组合保证金**: PortfolioMargin 组合模式下按对冲后的净风险计算风险度
+//! - **持仓集中度**: PositionConcentration 单账户占合约全市场总持仓比例超阈值时告警并限制开仓
+
+use super::auto_margin::{AutoMarginStatus, AutoMarginTopUp};
+use super::margin_call::{MarginCallEvent, MarginCallLadder};
+use super::portfolio_margin::PortfolioMargin;
+use super::position_concentration::{ConcentrationBreach, PositionConcentration, PositionHolding};
+use super::risk_history::{RiskHistoryStore, RiskSnapshot};
+use super::stop_loss::AccountStopLoss;
+use crate::core::QA_Account;
 use crate::exchange::AccountManager;
+use crate::notification::message::{
+    MarginCallNotify, Notification, NotificationPayload, NotificationType,
+};
+use crate::utils::timestamp::millis_to_nanos;
 use crate::ExchangeError;
-use chrono::Local;
+use chrono::Utc;
 use dashmap::DashMap;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
@@ -145,6 +161,12 @@ pub enum RiskAlertType {
     MarginInsufficient,
     /// 可用资金为负
     NegativeAvailable,
+    /// 触发账户止损线
+    StopLossTriggered,
+    /// 单账户持仓占合约全市场总持仓比例超限
+    PositionConcentration,
+    /// 自动追保入金（成功或失败）
+    AutoMarginTopUp,
 }
 
 /// 盘中风控配置
@@ -219,12 +241,23 @@ pub struct RiskMonitor {
     monitor_running: AtomicBool,
     /// 强平回调（可选，用于触发外部强平流程）
     liquidation_callback: RwLock<Option<LiquidationCallback>>,
+    /// 风险历史时序存储
+    risk_history: Arc<RiskHistoryStore>,
+    /// 强平预警阶梯
+    margin_call: Arc<MarginCallLadder>,
+    /// 账户级止损
+    stop_loss: Arc<AccountStopLoss>,
+    /// 组合保证金（逐合约/组合模式切换）
+    portfolio_margin: Arc<PortfolioMargin>,
+    /// 持仓集中度监测
+    position_concentration: Arc<PositionConcentration>,
+    /// 自动追保入金
+    auto_margin: Arc<AutoMarginTopUp>,
 }
 
 impl RiskMonitor {
     pub fn new(account_mgr: Arc<AccountManager>) -> Self {
         Self {
-            account_mgr,
             liquidation_records: DashMap::new(),
             liquidation_seq: AtomicU64::new(1),
             risk_alerts: DashMap::new(),
@@ -234,9 +267,51 @@ impl RiskMonitor {
             last_risk_levels: DashMap::new(),
             monitor_running: AtomicBool::new(false),
             liquidation_callback: RwLock::new(None),
+            risk_history: Arc::new(RiskHistoryStore::default()),
+            margin_call: Arc::new(MarginCallLadder::default()),
+            stop_loss: Arc::new(AccountStopLoss::new(account_mgr.clone())),
+            portfolio_margin: Arc::new(PortfolioMargin::default()),
+            position_concentration: Arc::new(PositionConcentration::default()),
+            auto_margin: Arc::new(AutoMarginTopUp::default()),
+            account_mgr,
         }
     }
 
+    /// 获取风险历史存储
+    pub fn risk_history(&self) -> &Arc<RiskHistoryStore> {
+        &self.risk_history
+    }
+
+    /// 获取强平预警阶梯
+    pub fn margin_call(&self) -> &Arc<MarginCallLadder> {
+        &self.margin_call
+    }
+
+    /// 获取账户级止损
+    pub fn stop_loss(&self) -> &Arc<AccountStopLoss> {
+        &self.stop_loss
+    }
+
+    /// 获取组合保证金计算器
+    pub fn portfolio_margin(&self) -> &Arc<PortfolioMargin> {
+        &self.portfolio_margin
+    }
+
+    /// 获取持仓集中度监测器
+    pub fn position_concentration(&self) -> &Arc<PositionConcentration> {
+        &self.position_concentration
+    }
+
+    /// 获取自动追保
+    pub fn auto_margin(&self) -> &Arc<AutoMarginTopUp> {
+        &self.auto_margin
+    }
+
+    /// 设置持仓集中度监测器（与订单路由共用同一实例，超限账户下单时被限制开仓）
+    pub fn set_position_concentration(&mut self, concentration: Arc<PositionConcentration>) {
+        self.position_concentration = concentration;
+    }
+
     /// 设置强平回调
     pub fn set_liquidation_callback(&self, callback: LiquidationCallback) {
         *self.liquidation_callback.write() = Some(callback);
@@ -302,11 +377,69 @@ impl RiskMonitor {
         self.monitor_running.load(Ordering::SeqCst)
     }
 
+    /// 立即重新评估单个账户的预警阶梯（入金等资金变动后调用，无需等下一轮盘中检查）
+    ///
+    /// 风险度回落时解除追保，追保逾期仍超标时触发强平，返回产生的预警事件
+    pub fn reevaluate_margin_call(&self, account_id: &str) -> Option<MarginCallEvent> {
+        self.reevaluate_margin_call_at(account_id, Utc::now().timestamp_millis())
+    }
+
+    fn reevaluate_margin_call_at(&self, account_id: &str, now_ms: i64) -> Option<MarginCallEvent> {
+        if !self.margin_call.config().enabled {
+            return None;
+        }
+        let account = self.account_mgr.get_account(account_id).ok()?;
+        let (event, risk_ratio) = {
+            let mut acc = account.write();
+            let risk_ratio = self.portfolio_margin.risk_ratio(&mut acc);
+            let balance = acc.get_balance();
+            let margin = self.portfolio_margin.account_margin(&mut acc);
+            let event = self
+                .margin_call
+                .evaluate(account_id, risk_ratio, balance, margin, now_ms);
+            (event?, risk_ratio)
+        };
+        self.notify_margin_call(&event);
+
+        if event.liquidate && self.config.read().auto_liquidation_enabled {
+            self.create_alert(
+                account_id,
+                RiskAlertType::LiquidationTriggered,
+                RiskLevel::from_risk_ratio(risk_ratio),
+                risk_ratio,
+                format!("触发强平，风险率: {:.2}%", risk_ratio * 100.0),
+            );
+            if let Some(ref callback) = *self.liquidation_callback.read() {
+                log::warn!(
+                    "[RiskMonitor] Triggering liquidation for account {}, risk_ratio={:.2}%",
+                    account_id,
+                    risk_ratio * 100.0
+                );
+                callback(account_id, risk_ratio);
+            }
+        }
+        Some(event)
+    }
+
     /// 执行一次风险检查
     fn do_risk_check(&self) {
+        self.do_risk_check_at(Utc::now().timestamp_millis());
+    }
+
+    /// 执行一次风险检查（now_ms 用于风险历史采样）
+    fn do_risk_check_at(&self, now_ms: i64) {
         let start = Instant::now();
         let config = self.config.read().clone();
+        let ladder_enabled = self.margin_call.config().enabled;
+        // 自动追保触发线：启用预警阶梯时为追保线，否则为强平线
+        let auto_margin_trigger = if ladder_enabled {
+            self.margin_call.config().margin_call_threshold
+        } else {
+            config.liquidation_threshold
+        };
         let accounts = self.account_mgr.get_all_accounts();
+        let sampling = self.risk_history.try_begin_sample(now_ms);
+        let mut samples = Vec::new();
 
         let mut high_risk_count = 0u64;
         let mut liquidation_count = 0u64;
@@ -315,10 +448,24 @@ impl RiskMonitor {
         for account in accounts.iter() {
             let mut acc = account.write();
             let account_id = acc.account_cookie.clone();
-            let risk_ratio = acc.get_riskratio();
+            let risk_ratio = self.portfolio_margin.risk_ratio(&mut acc);
+            // 自动追保：进入追保区间时先从绑定资金账户入金，后续按入金后的风险度评估
+            let risk_ratio = if risk_ratio >= auto_margin_trigger {
+                self.try_auto_margin(&account_id, &mut acc, risk_ratio, now_ms)
+            } else {
+                risk_ratio
+            };
             let available = acc.money;
             let current_level = RiskLevel::from_risk_ratio(risk_ratio);
 
+            // 到达采样点时记录风险快照
+            if sampling {
+                samples.push((
+                    account_id.clone(),
+                    Self::risk_snapshot_of(&mut acc, risk_ratio, now_ms),
+                ));
+            }
+
             // 检测风险等级变化
             let last_level = self.last_risk_levels
                 .get(&account_id)
@@ -369,8 +516,27 @@ impl RiskMonitor {
                 alert_count += 1;
             }
 
+            // 强平预警阶梯：逐级推送追保通知，超过强平线或追保逾期才强平
+            let should_liquidate = if ladder_enabled {
+                let balance = acc.get_balance();
+                let margin = self.portfolio_margin.account_margin(&mut acc);
+                match self
+                    .margin_call
+                    .evaluate(&account_id, risk_ratio, balance, margin, now_ms)
+                {
+                    Some(event) => {
+                        self.notify_margin_call(&event);
+                        alert_count += 1;
+                        event.liquidate
+                    }
+                    None => false,
+                }
+            } else {
+                risk_ratio >= config.liquidation_threshold
+            };
+
             // 触发强平检测
-            if risk_ratio >= config.liquidation_threshold && config.auto_liquidation_enabled {
+            if should_liquidate && config.auto_liquidation_enabled {
                 self.create_alert(
                     &account_id,
                     RiskAlertType::LiquidationTriggered,
@@ -390,6 +556,34 @@ impl RiskMonitor {
             }
         }
 
+        // 账户止损（需在释放账户锁后执行：盯市与平仓会再次获取账户锁）
+        for state in self.stop_loss.check_all_at(now_ms) {
+            let account_id = state.account_id.as_str();
+            let risk_ratio = self
+                .account_mgr
+                .get_account(account_id)
+                .map(|account| self.portfolio_margin.risk_ratio(&mut account.write()))
+                .unwrap_or(0.0);
+            self.create_alert(
+                account_id,
+                RiskAlertType::StopLossTriggered,
+                RiskLevel::from_risk_ratio(risk_ratio),
+                risk_ratio,
+                format!(
+                    "触发账户止损线，当日盈亏: {:.2}，已暂停交易并平仓",
+                    state.trigger_pnl.unwrap_or(0.0)
+                ),
+            );
+            alert_count += 1;
+        }
+
+        // 持仓集中度（汇总全市场持仓后逐账户计算占比）
+        alert_count += self.check_position_concentration_at(now_ms).len() as u64;
+
+        if sampling {
+            self.risk_history.record_sample(now_ms, samples);
+        }
+
         // 更新统计
         let elapsed = start.elapsed();
         {
@@ -398,7 +592,11 @@ impl RiskMonitor {
             stats.high_risk_detected += high_risk_count;
             stats.liquidations_triggered += liquidation_count;
             stats.alerts_sent += alert_count;
-            stats.last_check_time = Some(Local::now().format("%Y-%m-%d %H:%M:%S").to_string());
+            stats.last_check_time = Some(
+                crate::utils::time_service::now_local()
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string(),
+            );
 
             // 更新平均耗时
             let new_duration = elapsed.as_micros() as u64;
@@ -415,6 +613,166 @@ impl RiskMonitor {
         }
     }
 
+    /// 执行一次持仓集中度检查，返回新超限的记录（已发出预警）
+    pub fn check_position_concentration(&self) -> Vec<ConcentrationBreach> {
+        self.check_position_concentration_at(Utc::now().timestamp_millis())
+    }
+
+    fn check_position_concentration_at(&self, now_ms: i64) -> Vec<ConcentrationBreach> {
+        let mut holdings = Vec::new();
+        if self.position_concentration.is_enabled() {
+            for account in self.account_mgr.get_all_accounts().iter() {
+                let acc = account.read();
+                for (instrument_id, pos) in acc.hold.iter() {
+                    holdings.push(PositionHolding {
+                        account_id: acc.account_cookie.clone(),
+                        instrument_id: instrument_id.clone(),
+                        volume: pos.volume_long_unmut() + pos.volume_short_unmut(),
+                    });
+                }
+            }
+        }
+
+        let breaches = self.position_concentration.evaluate_at(&holdings, now_ms);
+        for breach in &breaches {
+            let risk_ratio = self
+                .account_mgr
+                .get_account(&breach.account_id)
+                .map(|account| self.portfolio_margin.risk_ratio(&mut account.write()))
+                .unwrap_or(0.0);
+            self.create_alert(
+                &breach.account_id,
+                RiskAlertType::PositionConcentration,
+                RiskLevel::from_risk_ratio(risk_ratio),
+                risk_ratio,
+                breach.describe(),
+            );
+        }
+        breaches
+    }
+
+    /// 尝试自动追保入金，返回入金后的风险度（未入金时返回原风险度）
+    ///
+    /// 划转失败不入金，由预警阶梯继续追保或强平
+    fn try_auto_margin(
+        &self,
+        account_id: &str,
+        acc: &mut QA_Account,
+        risk_ratio: f64,
+        now_ms: i64,
+    ) -> f64 {
+        let balance = acc.get_balance();
+        let margin = self.portfolio_margin.account_margin(acc);
+        let Some(record) = self.auto_margin.try_top_up(
+            account_id,
+            risk_ratio,
+            balance,
+            margin,
+            now_ms,
+            |amount| {
+                acc.deposit(amount);
+                self.portfolio_margin.risk_ratio(acc)
+            },
+        ) else {
+            return risk_ratio;
+        };
+
+        let message = match record.status {
+            AutoMarginStatus::Succeeded => format!(
+                "自动追保入金 {:.2}，风险率 {:.2}% -> {:.2}%",
+                record.transferred_amount,
+                risk_ratio * 100.0,
+                record.risk_ratio_after * 100.0
+            ),
+            AutoMarginStatus::Partial => format!(
+                "自动追保部分到账 {:.2}/{:.2}，风险率 {:.2}% -> {:.2}%",
+                record.transferred_amount,
+                record.requested_amount,
+                risk_ratio * 100.0,
+                record.risk_ratio_after * 100.0
+            ),
+            AutoMarginStatus::Failed => format!(
+                "自动追保失败: {}",
+                record.error.as_deref().unwrap_or("unknown")
+            ),
+        };
+        self.create_alert(
+            account_id,
+            RiskAlertType::AutoMarginTopUp,
+            RiskLevel::from_risk_ratio(record.risk_ratio_after),
+            record.risk_ratio_after,
+            message,
+        );
+        record.risk_ratio_after
+    }
+
+    /// 推送预警阶梯通知（级别上升、回落解除、强平）
+    fn notify_margin_call(&self, event: &MarginCallEvent) {
+        let Some(broker) = self.account_mgr.notification_broker() else {
+            return;
+        };
+
+        let notification = Notification::new(
+            NotificationType::MarginCall,
+            Arc::from(event.account_id.as_str()),
+            NotificationPayload::MarginCall(MarginCallNotify {
+                user_id: event.account_id.clone(),
+                current_margin: event.margin,
+                required_margin: event.required_margin,
+                level: event.level.as_str().to_string(),
+                deadline: event.deadline.map_or(0, millis_to_nanos),
+                message: event.message.clone(),
+                timestamp: millis_to_nanos(event.timestamp),
+            }),
+            "RiskMonitor",
+        );
+        if let Err(e) = broker.publish(notification) {
+            log::error!("Failed to publish MarginCall notification: {}", e);
+        }
+    }
+
+    /// 采集账户风险快照
+    fn risk_snapshot_of(acc: &mut QA_Account, risk_ratio: f64, now_ms: i64) -> RiskSnapshot {
+        let position_value: f64 = acc
+            .hold
+            .values()
+            .map(|pos| (pos.volume_long_unmut() + pos.volume_short_unmut()) * pos.lastest_price)
+            .sum();
+        RiskSnapshot {
+            timestamp: now_ms,
+            balance: acc.get_balance(),
+            margin: acc.get_margin() + acc.get_frozen_margin(),
+            risk_ratio,
+            position_value,
+        }
+    }
+
+    /// 单独执行一次风险历史采样（未启动盘中监控时由定时线程调用）
+    ///
+    /// 与盘中检查共用采样点，同一采样间隔内只记录一次
+    pub fn sample_risk_history(&self) -> bool {
+        let now_ms = Utc::now().timestamp_millis();
+        if !self.risk_history.try_begin_sample(now_ms) {
+            return false;
+        }
+
+        let samples = self
+            .account_mgr
+            .get_all_accounts()
+            .iter()
+            .map(|account| {
+                let mut acc = account.write();
+                let risk_ratio = acc.get_riskratio();
+                (
+                    acc.account_cookie.clone(),
+                    Self::risk_snapshot_of(&mut acc, risk_ratio, now_ms),
+                )
+            })
+            .collect();
+        self.risk_history.record_sample(now_ms, samples);
+        true
+    }
+
     /// 判断风险等级是否上升
     fn is_level_escalation(&self, old: RiskLevel, new: RiskLevel) -> bool {
         let level_value = |l: RiskLevel| match l {
@@ -436,7 +794,7 @@ impl RiskMonitor {
         message: String,
     ) -> RiskAlert {
         let seq = self.alert_seq.fetch_add(1, Ordering::SeqCst);
-        let now = Local::now();
+        let now = crate::utils::time_service::now_local();
         let config = self.config.read();
 
         let alert = RiskAlert {
@@ -517,11 +875,11 @@ impl RiskMonitor {
                 let balance = acc.get_balance();
                 let available = acc.money;
                 // ✨ 保证金 = 持仓保证金 + 冻结保证金（待成交订单）@yutiansut @quantaxis
-                let position_margin = acc.get_margin();
+                let position_margin = self.portfolio_margin.account_margin(&mut acc);
                 let frozen_margin = acc.get_frozen_margin();
                 let margin_used = position_margin + frozen_margin;
                 let unrealized_pnl = acc.get_positionprofit();
-                let risk_ratio = acc.get_riskratio();
+                let risk_ratio = self.portfolio_margin.risk_ratio(&mut acc);
                 let position_count = acc.hold.len();
                 let risk_level = RiskLevel::from_risk_ratio(risk_ratio);
 
@@ -605,7 +963,7 @@ impl RiskMonitor {
         let seq = self
             .liquidation_seq
             .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-        let now = Local::now();
+        let now = crate::utils::time_service::now_local();
 
         let record = LiquidationRecord {
             record_id: format!("LIQ{}{:08}", now.format("%Y%m%d"), seq),
@@ -1156,8 +1514,12 @@ mod tests {
         );
 
         // 使用当前日期范围查询
-        let today = Local::now().format("%Y-%m-%d").to_string();
-        let tomorrow = (Local::now() + chrono::Duration::days(1)).format("%Y-%m-%d").to_string();
+        let today = crate::utils::time_service::now_local()
+            .format("%Y-%m-%d")
+            .to_string();
+        let tomorrow = (crate::utils::time_service::now_local() + chrono::Duration::days(1))
+            .format("%Y-%m-%d")
+            .to_string();
 
         let records = monitor.get_liquidation_records_by_date_range(&today, &tomorrow);
         assert_eq!(records.len(), 1);
@@ -1477,4 +1839,138 @@ mod tests {
         assert_eq!(summary.high_risk_count, 2);
         assert_eq!(summary.critical_risk_count, 1);
     }
+
+    // ==================== 风险历史采样测试 @yutiansut @quantaxis ====================
+
+    /// 测试盘中检查按采样间隔记录每个账户的风险快照
+    #[test]
+    fn test_risk_check_samples_every_account() {
+        let account_mgr = Arc::new(AccountManager::new());
+        let monitor = RiskMonitor::new(account_mgr.clone());
+
+        for i in 0..3 {
+            let req = OpenAccountRequest {
+                user_id: format!("user_{}", i),
+                account_id: Some(format!("user_{}", i)),
+                account_name: format!("User {}", i),
+                init_cash: 100000.0 * (i + 1) as f64,
+                account_type: AccountType::Individual,
+            };
+            account_mgr.open_account(req).unwrap();
+        }
+
+        // 每秒检查一次，持续 5 分钟 → 每个账户 5 个采样点
+        let base = 1_700_000_400_000i64;
+        for sec in 0..300 {
+            monitor.do_risk_check_at(base + sec * 1000);
+        }
+        assert_eq!(monitor.get_monitor_stats().total_checks, 300);
+
+        let history = monitor.risk_history();
+        for i in 0..3 {
+            let points = history.query(&format!("user_{}", i), base, base + 300_000, None);
+            assert_eq!(points.len(), 5);
+            assert!(points.iter().all(|p| p.samples == 1));
+            assert_eq!(points[0].balance, 100000.0 * (i + 1) as f64);
+            assert_eq!(points[0].margin, 0.0);
+        }
+
+        let market = history.query_market(base, base + 300_000, None);
+        assert_eq!(market.len(), 5);
+        assert_eq!(market[4].timestamp, base + 240_000);
+        assert_eq!(market[4].balance, 600000.0);
+        assert_eq!(market[4].risk_ratio, 0.0);
+    }
+
+    // ==================== 自动追保测试 @yutiansut @quantaxis ====================
+
+    /// 测试风险度超过强平线时自动入金补足保证金避免强平，资金账户不足时回退强平
+    #[test]
+    fn test_auto_margin_top_up_avoids_liquidation() {
+        use crate::risk::auto_margin::{AutoMarginConfig, SimulatedMarginProvider};
+
+        let account_mgr = Arc::new(AccountManager::new());
+        let monitor = RiskMonitor::new(account_mgr.clone());
+        monitor.auto_margin().update_config(AutoMarginConfig {
+            enabled: true,
+            target_risk_ratio: 0.7,
+            ..Default::default()
+        });
+        let provider = Arc::new(SimulatedMarginProvider::new(0.0));
+        provider.bind("funded", 1_000_000.0);
+        monitor.auto_margin().set_provider(provider.clone());
+
+        let liquidated = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        {
+            let liquidated = liquidated.clone();
+            monitor.set_liquidation_callback(Arc::new(
+                move |account_id: &str, _risk_ratio: f64| {
+                    liquidated.lock().push(account_id.to_string());
+                },
+            ));
+        }
+
+        for account_id in ["funded", "unfunded"] {
+            account_mgr
+                .open_account(OpenAccountRequest {
+                    user_id: account_id.to_string(),
+                    account_id: Some(account_id.to_string()),
+                    account_name: account_id.to_string(),
+                    init_cash: 1_000_000.0,
+                    account_type: AccountType::Individual,
+                })
+                .unwrap();
+            let account = account_mgr.get_account(account_id).unwrap();
+            let mut acc = account.write();
+            let order = acc
+                .send_order("IF2501", 1.0, "2025-01-02 09:30:00", 2, 4000.0, "", "LIMIT")
+                .unwrap();
+            acc.receive_deal_sim(
+                "IF2501".to_string(),
+                1.0,
+                4000.0,
+                "2025-01-02 09:30:00".to_string(),
+                order.order_id.clone(),
+                format!("T_{}", account_id),
+                order.order_id.clone(),
+                2,
+            );
+
+            // 出金使风险度达到 120%，超过强平线
+            let margin = monitor.portfolio_margin().account_margin(&mut acc);
+            assert!(margin > 0.0);
+            let balance = acc.get_balance();
+            acc.withdraw(balance - margin / 1.2);
+            assert!(monitor.portfolio_margin().risk_ratio(&mut acc) > 1.1);
+        }
+
+        monitor.do_risk_check_at(1_700_000_000_000);
+
+        // 绑定资金账户的账户：自动入金后风险度回落到目标值，未触发强平
+        let records = monitor.auto_margin().get_records("funded");
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].status, AutoMarginStatus::Succeeded);
+        assert!(records[0].risk_ratio_after < 0.71);
+        let funded = account_mgr.get_account("funded").unwrap();
+        assert!(monitor.portfolio_margin().risk_ratio(&mut funded.write()) < 0.71);
+        assert_eq!(
+            provider.balance("funded"),
+            1_000_000.0 - records[0].transferred_amount
+        );
+        assert!(monitor.margin_call().get_history("funded").is_empty());
+
+        // 资金账户无余额：追保失败，回退强平
+        let records = monitor.auto_margin().get_records("unfunded");
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].status, AutoMarginStatus::Failed);
+        assert_eq!(*liquidated.lock(), vec!["unfunded".to_string()]);
+
+        let alerts = monitor.get_risk_alerts("funded");
+        assert!(alerts
+            .iter()
+            .any(|a| a.alert_type == RiskAlertType::AutoMarginTopUp));
+        assert!(!alerts
+            .iter()
+            .any(|a| a.alert_type == RiskAlertType::LiquidationTriggered));
+    }
 }
@@ -0,0 +1,891 @@
+//! 账户级止损（当日风控线）
+//!
+//! @yutiansut @quantaxis
+//!
+//! 用户为账户设置当日最大亏损（金额和/或占日初权益比例），盯市计算当日盈亏，达线时：
+//!
+//! 1. 暂停账户交易（`TradingRestriction::Suspended`，截止到下一交易日开始）
+//! 2. 撤销账户全部未成交挂单
+//! 3. 通过 `OrderRouter::submit_force_order` 市价平掉全部持仓（不受交易限制）
+//!
+//! - **盯市**: 用最新价（`OrderRouter::reference_price`）刷新持仓最新价后计算权益，
+//!   当日盈亏 = 动态权益 - 静态权益（昨日结算权益 + 当日出入金）
+//! - **原子性**: 在 `states.entry()` 内由 `Armed` 切换状态，同一交易日只触发一次
+//! - **平仓失败**: 记录失败原因，后续检查按间隔撤掉未成交平仓单后重试，
+//!   超过重试次数标记 `CloseFailed`，账户保持暂停，需人工处理
+//! - **次日恢复**: 交易日切换时解除本模块设置的暂停（人工设置的限制保留）并重新布防
+
+use dashmap::DashMap;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+use crate::exchange::order_router::{CancelOrderRequest, OrderStatus, SubmitOrderRequest};
+use crate::exchange::{AccountManager, OrderRouter, TradingRestriction};
+use crate::market::kline::trading_day_of;
+use crate::utils::time_service::time_service;
+use crate::ExchangeError;
+
+/// 止损设置的交易限制的操作人（交易日切换时只解除该操作人设置的限制）
+pub const STOP_LOSS_OPERATOR: &str = "stop_loss";
+
+/// 止损配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StopLossConfig {
+    /// 平仓最多尝试次数（含首次）
+    #[serde(default = "default_max_close_attempts")]
+    pub max_close_attempts: u32,
+    /// 平仓重试间隔（毫秒）
+    #[serde(default = "default_retry_interval_ms")]
+    pub retry_interval_ms: i64,
+}
+
+fn default_max_close_attempts() -> u32 {
+    3
+}
+fn default_retry_interval_ms() -> i64 {
+    5_000
+}
+
+impl Default for StopLossConfig {
+    fn default() -> Self {
+        Self {
+            max_close_attempts: default_max_close_attempts(),
+            retry_interval_ms: default_retry_interval_ms(),
+        }
+    }
+}
+
+/// 用户设置的止损线（金额与比例任一达到即触发）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StopLossLine {
+    pub account_id: String,
+    /// 当日最大亏损金额
+    pub max_loss_amount: Option<f64>,
+    /// 当日最大亏损占静态权益比例 (0, 1]
+    pub max_loss_ratio: Option<f64>,
+    /// 设置时间（毫秒）
+    pub updated_at: i64,
+}
+
+impl StopLossLine {
+    /// 校验止损线参数
+    pub fn validate(&self) -> Result<(), ExchangeError> {
+        if self.max_loss_amount.is_none() && self.max_loss_ratio.is_none() {
+            return Err(ExchangeError::InvalidParameter(
+                "Either max_loss_amount or max_loss_ratio is required".to_string(),
+            ));
+        }
+        if self.max_loss_amount.is_some_and(|v| v <= 0.0) {
+            return Err(ExchangeError::InvalidParameter(
+                "max_loss_amount must be positive".to_string(),
+            ));
+        }
+        if self.max_loss_ratio.is_some_and(|v| v <= 0.0 || v > 1.0) {
+            return Err(ExchangeError::InvalidParameter(
+                "max_loss_ratio must be in (0, 1]".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// 当日盈亏是否达线（`base` 为静态权益）
+    pub fn is_breached(&self, daily_pnl: f64, base: f64) -> bool {
+        let loss = -daily_pnl;
+        if loss <= 0.0 {
+            return false;
+        }
+        self.max_loss_amount.is_some_and(|limit| loss >= limit)
+            || self
+                .max_loss_ratio
+                .is_some_and(|ratio| base > 0.0 && loss >= base * ratio)
+    }
+}
+
+/// 止损状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopLossStatus {
+    /// 已布防，未触发
+    Armed,
+    /// 已触发，平仓进行中
+    Closing,
+    /// 已触发，持仓已全部平掉
+    Closed,
+    /// 已触发，重试次数用尽仍有持仓（需人工处理）
+    CloseFailed,
+}
+
+/// 账户当日止损状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StopLossState {
+    pub account_id: String,
+    /// 所属交易日 (YYYYMMDD)
+    pub trading_day: String,
+    pub status: StopLossStatus,
+    /// 触发时间（毫秒）
+    pub triggered_at: Option<i64>,
+    /// 触发时的当日盈亏
+    pub trigger_pnl: Option<f64>,
+    /// 已提交的平仓单
+    pub close_order_ids: Vec<String>,
+    /// 平仓失败原因（按尝试顺序）
+    pub close_failures: Vec<String>,
+    /// 已尝试平仓次数
+    pub close_attempts: u32,
+    /// 最近一次平仓尝试时间（毫秒）
+    pub last_attempt_at: Option<i64>,
+}
+
+impl StopLossState {
+    fn armed(account_id: &str, trading_day: &str) -> Self {
+        Self {
+            account_id: account_id.to_string(),
+            trading_day: trading_day.to_string(),
+            status: StopLossStatus::Armed,
+            triggered_at: None,
+            trigger_pnl: None,
+            close_order_ids: Vec::new(),
+            close_failures: Vec::new(),
+            close_attempts: 0,
+            last_attempt_at: None,
+        }
+    }
+}
+
+/// 平仓计划
+struct ClosePlan {
+    instrument_id: String,
+    direction: &'static str,
+    volume: f64,
+}
+
+/// 账户级止损
+pub struct AccountStopLoss {
+    config: RwLock<StopLossConfig>,
+    account_mgr: Arc<AccountManager>,
+    /// 订单路由器（弱引用，避免循环引用）
+    order_router: RwLock<Option<Weak<OrderRouter>>>,
+    /// account_id -> 止损线
+    lines: DashMap<String, StopLossLine>,
+    /// account_id -> 当日状态
+    states: DashMap<String, StopLossState>,
+    /// 当前交易日 (YYYYMMDD)
+    trading_day: RwLock<Option<String>>,
+}
+
+impl AccountStopLoss {
+    pub fn new(account_mgr: Arc<AccountManager>) -> Self {
+        Self {
+            config: RwLock::new(StopLossConfig::default()),
+            account_mgr,
+            order_router: RwLock::new(None),
+            lines: DashMap::new(),
+            states: DashMap::new(),
+            trading_day: RwLock::new(None),
+        }
+    }
+
+    /// 设置订单路由器（平仓与盯市取价）
+    pub fn set_order_router(&self, router: &Arc<OrderRouter>) {
+        *self.order_router.write() = Some(Arc::downgrade(router));
+    }
+
+    /// 获取配置
+    pub fn config(&self) -> StopLossConfig {
+        self.config.read().clone()
+    }
+
+    /// 更新配置
+    pub fn update_config(&self, config: StopLossConfig) {
+        *self.config.write() = config;
+    }
+
+    fn router(&self) -> Option<Arc<OrderRouter>> {
+        self.order_router.read().as_ref().and_then(|w| w.upgrade())
+    }
+
+    /// 设置止损线
+    pub fn set_line(
+        &self,
+        account_id: &str,
+        max_loss_amount: Option<f64>,
+        max_loss_ratio: Option<f64>,
+    ) -> Result<StopLossLine, ExchangeError> {
+        self.account_mgr.get_account(account_id)?;
+        let line = StopLossLine {
+            account_id: account_id.to_string(),
+            max_loss_amount,
+            max_loss_ratio,
+            updated_at: chrono::Utc::now().timestamp_millis(),
+        };
+        line.validate()?;
+        self.lines.insert(account_id.to_string(), line.clone());
+
+        log::info!(
+            "[StopLoss] Line of {} set: amount={:?}, ratio={:?}",
+            account_id,
+            max_loss_amount,
+            max_loss_ratio
+        );
+        Ok(line)
+    }
+
+    /// 取消止损线（已触发的当日状态保留）
+    pub fn remove_line(&self, account_id: &str) -> Option<StopLossLine> {
+        self.lines.remove(account_id).map(|(_, line)| line)
+    }
+
+    pub fn get_line(&self, account_id: &str) -> Option<StopLossLine> {
+        self.lines.get(account_id).map(|l| l.clone())
+    }
+
+    pub fn get_state(&self, account_id: &str) -> Option<StopLossState> {
+        self.states.get(account_id).map(|s| s.clone())
+    }
+
+    /// 盯市计算当日盈亏，返回 (当日盈亏, 静态权益)
+    ///
+    /// 有最新价的合约先刷新持仓最新价，无行情的合约沿用上次价格
+    pub fn daily_pnl(&self, account_id: &str) -> Result<(f64, f64), ExchangeError> {
+        let account = self.account_mgr.get_account(account_id)?;
+        let instruments: Vec<String> = account.read().hold.keys().cloned().collect();
+        let marks: Vec<(String, f64)> = match self.router() {
+            Some(router) => instruments
+                .into_iter()
+                .filter_map(|id| router.reference_price(&id).map(|price| (id, price)))
+                .collect(),
+            None => Vec::new(),
+        };
+
+        let mut acc = account.write();
+        for (instrument_id, price) in marks {
+            if let Some(pos) = acc.hold.get_mut(&instrument_id) {
+                pos.lastest_price = price;
+            }
+        }
+        let base = acc.accounts.static_balance;
+        Ok((acc.get_balance() - base, base))
+    }
+
+    /// 检查所有设置了止损线的账户，返回本次触发的账户状态
+    pub fn check_all_at(&self, now_ms: i64) -> Vec<StopLossState> {
+        self.roll_trading_day(now_ms);
+
+        let account_ids: Vec<String> = self.lines.iter().map(|l| l.key().clone()).collect();
+        account_ids
+            .iter()
+            .filter_map(
+                |account_id| match self.check_account_at(account_id, now_ms) {
+                    Ok(triggered) => triggered,
+                    Err(e) => {
+                        log::warn!("[StopLoss] Check {} failed: {}", account_id, e);
+                        None
+                    }
+                },
+            )
+            .collect()
+    }
+
+    /// 检查单个账户：未触发时盯市判断是否达线，已触发时推进平仓重试
+    ///
+    /// 本次触发时返回触发后的状态
+    pub fn check_account_at(
+        &self,
+        account_id: &str,
+        now_ms: i64,
+    ) -> Result<Option<StopLossState>, ExchangeError> {
+        let trading_day = self.roll_trading_day(now_ms);
+
+        let status = self.states.get(account_id).map(|s| s.status);
+        match status {
+            Some(StopLossStatus::Closing) => {
+                self.retry_close(account_id, now_ms);
+                return Ok(None);
+            }
+            Some(StopLossStatus::Closed | StopLossStatus::CloseFailed) => return Ok(None),
+            _ => {}
+        }
+
+        let Some(line) = self.get_line(account_id) else {
+            return Ok(None);
+        };
+        let (pnl, base) = self.daily_pnl(account_id)?;
+        if !line.is_breached(pnl, base) {
+            return Ok(None);
+        }
+
+        // 原子切换：只有 Armed -> Closing 的调用方执行触发动作
+        {
+            let mut state = self
+                .states
+                .entry(account_id.to_string())
+                .or_insert_with(|| StopLossState::armed(account_id, &trading_day));
+            if state.status != StopLossStatus::Armed {
+                return Ok(None);
+            }
+            state.status = StopLossStatus::Closing;
+            state.triggered_at = Some(now_ms);
+            state.trigger_pnl = Some(pnl);
+        }
+
+        log::warn!(
+            "[StopLoss] Account {} hit stop loss line: daily_pnl={:.2}, base={:.2}, line={:?}/{:?}",
+            account_id,
+            pnl,
+            base,
+            line.max_loss_amount,
+            line.max_loss_ratio
+        );
+
+        let reason = format!("触发账户止损线，当日盈亏 {:.2}", pnl);
+        match self.account_mgr.set_trading_restriction(
+            account_id,
+            TradingRestriction::Suspended,
+            reason,
+            Some(next_trading_day_start(now_ms)),
+            STOP_LOSS_OPERATOR,
+        ) {
+            Ok(info) => self.account_mgr.notify_trading_restriction(&info),
+            Err(e) => log::error!("[StopLoss] Suspend {} failed: {}", account_id, e),
+        }
+
+        self.cancel_open_orders(account_id);
+        self.close_positions(account_id, now_ms);
+        Ok(self.get_state(account_id))
+    }
+
+    /// 启动后台检查线程（盘中风控监控未启动时使用）
+    pub fn start_scanner(self: &Arc<Self>, interval: Duration) -> std::thread::JoinHandle<()> {
+        let stop_loss = Arc::downgrade(self);
+        std::thread::spawn(move || {
+            log::info!(
+                "Stop loss scanner started (interval: {}ms)",
+                interval.as_millis()
+            );
+            loop {
+                std::thread::sleep(interval);
+                match stop_loss.upgrade() {
+                    Some(stop_loss) => {
+                        stop_loss.check_all_at(chrono::Utc::now().timestamp_millis());
+                    }
+                    None => break,
+                }
+            }
+        })
+    }
+
+    /// 交易日切换：解除止损设置的暂停，清空当日状态
+    ///
+    /// 人工覆盖过的限制（操作人不是止损）保持不变，返回恢复交易的账户
+    pub fn reset_for_trading_day(&self, trading_day: &str) -> Vec<String> {
+        *self.trading_day.write() = Some(trading_day.to_string());
+
+        let stale: Vec<String> = self
+            .states
+            .iter()
+            .filter(|s| s.trading_day != trading_day)
+            .map(|s| s.key().clone())
+            .collect();
+
+        let mut restored = Vec::new();
+        for account_id in stale {
+            let Some((_, state)) = self.states.remove(&account_id) else {
+                continue;
+            };
+            if state.status == StopLossStatus::Armed {
+                continue;
+            }
+            let ours = self
+                .account_mgr
+                .get_trading_restriction(&account_id)
+                .is_some_and(|info| info.updated_by == STOP_LOSS_OPERATOR);
+            if ours {
+                match self.account_mgr.set_trading_restriction(
+                    &account_id,
+                    TradingRestriction::Normal,
+                    "新交易日恢复交易".to_string(),
+                    None,
+                    STOP_LOSS_OPERATOR,
+                ) {
+                    Ok(_) => restored.push(account_id),
+                    Err(e) => log::error!("[StopLoss] Restore {} failed: {}", account_id, e),
+                }
+            }
+        }
+
+        if !restored.is_empty() {
+            log::info!(
+                "[StopLoss] Trading day {}: {} accounts restored",
+                trading_day,
+                restored.len()
+            );
+        }
+        restored
+    }
+
+    /// 按时间戳推进交易日，返回当前交易日
+    fn roll_trading_day(&self, now_ms: i64) -> String {
+        let trading_day = trading_day_of(now_ms).format("%Y%m%d").to_string();
+        if self.trading_day.read().as_deref() != Some(trading_day.as_str()) {
+            self.reset_for_trading_day(&trading_day);
+        }
+        trading_day
+    }
+
+    /// 撤销账户未成交挂单（避免暂停后挂单继续成交）
+    fn cancel_open_orders(&self, account_id: &str) {
+        let Some(router) = self.router() else {
+            return;
+        };
+        for (order_id, _, status, ..) in router.get_user_order_details(account_id) {
+            if !matches!(
+                status,
+                OrderStatus::Submitted | OrderStatus::PartiallyFilled
+            ) {
+                continue;
+            }
+            if let Err(e) = router.cancel_order(CancelOrderRequest {
+                account_id: account_id.to_string(),
+                order_id: order_id.clone(),
+            }) {
+                log::warn!(
+                    "[StopLoss] Cancel {} of {} failed: {}",
+                    order_id,
+                    account_id,
+                    e
+                );
+            }
+        }
+    }
+
+    /// 重试间隔到期后撤掉未成交平仓单并重新平仓
+    fn retry_close(&self, account_id: &str, now_ms: i64) {
+        let config = self.config();
+        let (order_ids, due) = match self.states.get(account_id) {
+            Some(state) => (
+                state.close_order_ids.clone(),
+                state
+                    .last_attempt_at
+                    .map_or(true, |t| now_ms - t >= config.retry_interval_ms),
+            ),
+            None => return,
+        };
+        if !due {
+            return;
+        }
+        if let Some(router) = self.router() {
+            for order_id in order_ids {
+                if matches!(
+                    router.get_order_status(&order_id),
+                    Some(OrderStatus::Submitted | OrderStatus::PartiallyFilled)
+                ) {
+                    let _ = router.cancel_order(CancelOrderRequest {
+                        account_id: account_id.to_string(),
+                        order_id,
+                    });
+                }
+            }
+        }
+        self.close_positions(account_id, now_ms);
+    }
+
+    /// 市价平掉账户全部持仓并更新状态
+    fn close_positions(&self, account_id: &str, now_ms: i64) {
+        let plans = self.close_plans(account_id);
+        let max_attempts = self.config().max_close_attempts.max(1);
+
+        if plans.is_empty() {
+            if let Some(mut state) = self.states.get_mut(account_id) {
+                state.status = StopLossStatus::Closed;
+            }
+            log::info!("[StopLoss] Account {} has no positions left", account_id);
+            return;
+        }
+
+        let router = self.router();
+        let mut order_ids = Vec::new();
+        let mut failures = Vec::new();
+        for plan in plans {
+            let Some(router) = router.as_ref() else {
+                failures.push("OrderRouter not configured".to_string());
+                break;
+            };
+            let response = router.submit_force_order(SubmitOrderRequest {
+                account_id: account_id.to_string(),
+                instrument_id: plan.instrument_id.clone(),
+                direction: plan.direction.to_string(),
+                offset: "CLOSE".to_string(),
+                volume: plan.volume,
+                price: 0.0,
+                order_type: "MARKET".to_string(),
+                time_condition: None,
+                volume_condition: None,
+                hedge_flag: None,
+                ttl_secs: None,
+                client_order_id: None,
+                close_lot_id: None,
+            });
+            match (response.success, response.order_id) {
+                (true, Some(order_id)) => order_ids.push(order_id),
+                _ => failures.push(format!(
+                    "{} {} {}: {}",
+                    plan.instrument_id,
+                    plan.direction,
+                    plan.volume,
+                    response
+                        .error_message
+                        .unwrap_or_else(|| "unknown error".to_string())
+                )),
+            }
+        }
+
+        let remaining = !self.close_plans(account_id).is_empty();
+        let Some(mut state) = self.states.get_mut(account_id) else {
+            return;
+        };
+        state.close_attempts += 1;
+        state.last_attempt_at = Some(now_ms);
+        state.close_order_ids.extend(order_ids);
+        state.status = if !remaining {
+            StopLossStatus::Closed
+        } else if state.close_attempts >= max_attempts {
+            StopLossStatus::CloseFailed
+        } else {
+            StopLossStatus::Closing
+        };
+        for failure in &failures {
+            log::error!(
+                "[StopLoss] Close position of {} failed (attempt {}): {}",
+                account_id,
+                state.close_attempts,
+                failure
+            );
+        }
+        state.close_failures.extend(failures);
+        if state.status == StopLossStatus::CloseFailed {
+            log::error!(
+                "[StopLoss] Account {} still has positions after {} attempts, manual handling required",
+                account_id,
+                state.close_attempts
+            );
+        }
+    }
+
+    /// 生成平仓计划：多头卖平、空头买平
+    fn close_plans(&self, account_id: &str) -> Vec<ClosePlan> {
+        let Ok(account) = self.account_mgr.get_account(account_id) else {
+            return Vec::new();
+        };
+        let acc = account.read();
+        let mut plans = Vec::new();
+        for (instrument_id, pos) in acc.hold.iter() {
+            let long = pos.volume_long_today + pos.volume_long_his;
+            if long > 0.0 {
+                plans.push(ClosePlan {
+                    instrument_id: instrument_id.clone(),
+                    direction: "SELL",
+                    volume: long,
+                });
+            }
+            let short = pos.volume_short_today + pos.volume_short_his;
+            if short > 0.0 {
+                plans.push(ClosePlan {
+                    instrument_id: instrument_id.clone(),
+                    direction: "BUY",
+                    volume: short,
+                });
+            }
+        }
+        plans
+    }
+}
+
+/// 下一交易日开始时间（毫秒）：此后第一个归属新交易日的夜盘开始时刻
+pub fn next_trading_day_start(now_ms: i64) -> i64 {
+    time_service().next_trading_day_start(now_ms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::account_ext::{AccountType, OpenAccountRequest};
+    use crate::exchange::instrument_registry::{InstrumentInfo, InstrumentStatus, InstrumentType};
+    use crate::exchange::{InstrumentRegistry, TradeGateway};
+    use crate::matching::engine::ExchangeMatchingEngine;
+
+    /// 2025-01-06 (周一) 10:00 北京时间
+    const MONDAY_10AM: i64 = 1_736_128_800_000;
+    const HOUR_MS: i64 = 60 * 60 * 1000;
+
+    fn open_account(account_mgr: &AccountManager, account_id: &str) {
+        account_mgr
+            .open_account(OpenAccountRequest {
+                user_id: account_id.to_string(),
+                account_id: Some(account_id.to_string()),
+                account_name: account_id.to_string(),
+                init_cash: 1_000_000.0,
+                account_type: AccountType::Individual,
+            })
+            .unwrap();
+    }
+
+    fn create_router(account_mgr: Arc<AccountManager>) -> Arc<OrderRouter> {
+        let matching_engine = Arc::new(ExchangeMatchingEngine::new());
+        matching_engine
+            .register_instrument("SL2501".to_string(), 120.0)
+            .unwrap();
+        let instrument_registry = Arc::new(InstrumentRegistry::new());
+        instrument_registry
+            .register(InstrumentInfo {
+                instrument_id: "SL2501".to_string(),
+                instrument_name: "SL2501".to_string(),
+                instrument_type: InstrumentType::CommodityFuture,
+                exchange: "SHFE".to_string(),
+                contract_multiplier: 1,
+                trading_unit: "手".to_string(),
+                quote_unit: String::new(),
+                price_tick: 0.01,
+                margin_rate: 0.1,
+                commission_rate: 0.0005,
+                limit_up_rate: 0.1,
+                limit_down_rate: 0.1,
+                status: InstrumentStatus::Active,
+                list_date: None,
+                expire_date: None,
+                created_at: "2025-01-01T00:00:00Z".to_string(),
+                updated_at: "2025-01-01T00:00:00Z".to_string(),
+            })
+            .unwrap();
+        let trade_gateway = Arc::new(TradeGateway::new(account_mgr.clone()));
+        Arc::new(OrderRouter::new(
+            account_mgr,
+            matching_engine,
+            instrument_registry,
+            trade_gateway,
+        ))
+    }
+
+    fn order(
+        account_id: &str,
+        direction: &str,
+        offset: &str,
+        volume: f64,
+        price: f64,
+    ) -> SubmitOrderRequest {
+        SubmitOrderRequest {
+            account_id: account_id.to_string(),
+            instrument_id: "SL2501".to_string(),
+            direction: direction.to_string(),
+            offset: offset.to_string(),
+            volume,
+            price,
+            order_type: "LIMIT".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn long_volume(account_mgr: &AccountManager, account_id: &str) -> f64 {
+        account_mgr
+            .get_account(account_id)
+            .unwrap()
+            .write()
+            .get_position("SL2501")
+            .map_or(0.0, |pos| pos.volume_long())
+    }
+
+    #[test]
+    fn test_line_validation_and_breach() {
+        let line = |amount, ratio| StopLossLine {
+            account_id: "a".to_string(),
+            max_loss_amount: amount,
+            max_loss_ratio: ratio,
+            updated_at: 0,
+        };
+        assert!(line(None, None).validate().is_err());
+        assert!(line(Some(-1.0), None).validate().is_err());
+        assert!(line(None, Some(1.5)).validate().is_err());
+
+        let both = line(Some(5_000.0), Some(0.01));
+        assert!(both.validate().is_ok());
+        assert!(!both.is_breached(1_000.0, 100_000.0));
+        assert!(!both.is_breached(-999.0, 100_000.0));
+        // 比例先达线
+        assert!(both.is_breached(-1_000.0, 100_000.0));
+        // 金额先达线
+        assert!(both.is_breached(-5_000.0, 1_000_000.0));
+    }
+
+    #[test]
+    fn test_next_trading_day_start() {
+        // 周一日盘 -> 周一 18:00
+        assert_eq!(
+            next_trading_day_start(MONDAY_10AM),
+            MONDAY_10AM + 8 * HOUR_MS
+        );
+        // 周五夜盘（归属下周一）-> 下周一 18:00
+        let friday_9pm = MONDAY_10AM + 4 * 24 * HOUR_MS + 11 * HOUR_MS;
+        assert_eq!(
+            next_trading_day_start(friday_9pm),
+            MONDAY_10AM + 7 * 24 * HOUR_MS + 8 * HOUR_MS
+        );
+    }
+
+    /// 账户亏损达线：自动市价平仓并暂停交易，同日不重复触发，次日恢复
+    #[test]
+    fn test_stop_loss_liquidates_and_suspends_account() {
+        let account_mgr = Arc::new(AccountManager::new());
+        for account_id in ["trader", "maker", "taker"] {
+            open_account(&account_mgr, account_id);
+        }
+        let router = create_router(account_mgr.clone());
+        let stop_loss = AccountStopLoss::new(account_mgr.clone());
+        stop_loss.set_order_router(&router);
+
+        // trader 120 买开 10 手
+        assert!(
+            router
+                .submit_order(order("trader", "BUY", "OPEN", 10.0, 120.0))
+                .success
+        );
+        assert!(
+            router
+                .submit_order(order("maker", "SELL", "OPEN", 10.0, 120.0))
+                .success
+        );
+        assert_eq!(long_volume(&account_mgr, "trader"), 10.0);
+        // trader 未成交的开仓挂单，触发后应被撤销
+        let resting = router
+            .submit_order(order("trader", "BUY", "OPEN", 1.0, 110.0))
+            .order_id
+            .unwrap();
+        let (pnl_before, _) = stop_loss.daily_pnl("trader").unwrap();
+
+        // 价格跌到 112
+        assert!(
+            router
+                .submit_order(order("taker", "BUY", "OPEN", 1.0, 112.0))
+                .success
+        );
+        assert!(
+            router
+                .submit_order(order("maker", "SELL", "OPEN", 1.0, 112.0))
+                .success
+        );
+        let (pnl_after, _) = stop_loss.daily_pnl("trader").unwrap();
+        assert!(pnl_after < pnl_before);
+
+        // 止损线设在两者之间；taker 在 111 挂买单承接平仓
+        let limit = -(pnl_before + pnl_after) / 2.0;
+        stop_loss.set_line("trader", Some(limit), None).unwrap();
+        assert!(
+            router
+                .submit_order(order("taker", "BUY", "OPEN", 10.0, 111.0))
+                .success
+        );
+
+        // 暂停截止到下一交易日，需用当前时间检查
+        let now = chrono::Utc::now().timestamp_millis();
+        let state = stop_loss
+            .check_account_at("trader", now)
+            .unwrap()
+            .expect("stop loss should trigger");
+        assert_eq!(state.status, StopLossStatus::Closed);
+        assert_eq!(state.close_attempts, 1);
+        assert_eq!(state.close_order_ids.len(), 1);
+        assert!(state.trigger_pnl.unwrap() <= -limit);
+        assert_eq!(long_volume(&account_mgr, "trader"), 0.0);
+        assert_eq!(
+            router.get_order_status(&resting),
+            Some(OrderStatus::Cancelled)
+        );
+
+        // 账户暂停交易
+        let restriction = account_mgr.get_trading_restriction("trader").unwrap();
+        assert_eq!(restriction.restriction, TradingRestriction::Suspended);
+        assert_eq!(restriction.updated_by, STOP_LOSS_OPERATOR);
+        let rejected = router.submit_order(order("trader", "BUY", "OPEN", 1.0, 111.0));
+        assert!(!rejected.success);
+        assert_eq!(rejected.error_code, Some(4013));
+
+        // 同一交易日不重复触发
+        assert!(stop_loss.check_all_at(now + 1).is_empty());
+        assert_eq!(stop_loss.get_state("trader").unwrap().close_attempts, 1);
+
+        // 次日恢复交易并重新布防
+        assert!(stop_loss
+            .check_all_at(next_trading_day_start(now))
+            .is_empty());
+        assert!(account_mgr.get_trading_restriction("trader").is_none());
+        assert!(stop_loss.get_state("trader").is_none());
+        assert!(
+            router
+                .submit_order(order("trader", "BUY", "OPEN", 1.0, 111.0))
+                .success
+        );
+    }
+
+    #[test]
+    fn test_close_failure_retried_until_attempts_exhausted() {
+        let account_mgr = Arc::new(AccountManager::new());
+        for account_id in ["trader", "maker", "taker"] {
+            open_account(&account_mgr, account_id);
+        }
+        let router = create_router(account_mgr.clone());
+        let stop_loss = AccountStopLoss::new(account_mgr.clone());
+        stop_loss.update_config(StopLossConfig {
+            max_close_attempts: 2,
+            retry_interval_ms: 1_000,
+        });
+        stop_loss.set_order_router(&router);
+
+        assert!(
+            router
+                .submit_order(order("trader", "BUY", "OPEN", 5.0, 120.0))
+                .success
+        );
+        assert!(
+            router
+                .submit_order(order("maker", "SELL", "OPEN", 5.0, 120.0))
+                .success
+        );
+        // 价格跌到 112 后无买盘，平仓单无法成交
+        assert!(
+            router
+                .submit_order(order("taker", "BUY", "OPEN", 1.0, 112.0))
+                .success
+        );
+        assert!(
+            router
+                .submit_order(order("maker", "SELL", "OPEN", 1.0, 112.0))
+                .success
+        );
+        stop_loss.set_line("trader", Some(1.0), None).unwrap();
+
+        let now = chrono::Utc::now().timestamp_millis();
+        let state = stop_loss
+            .check_account_at("trader", now)
+            .unwrap()
+            .expect("stop loss should trigger");
+        assert_eq!(state.status, StopLossStatus::Closing);
+        assert_eq!(state.close_attempts, 1);
+
+        // 未到重试间隔不重试
+        stop_loss.check_account_at("trader", now + 500).unwrap();
+        assert_eq!(stop_loss.get_state("trader").unwrap().close_attempts, 1);
+
+        stop_loss.check_account_at("trader", now + 1_000).unwrap();
+        let state = stop_loss.get_state("trader").unwrap();
+        assert_eq!(state.close_attempts, 2);
+        assert_eq!(state.status, StopLossStatus::CloseFailed);
+        assert_eq!(long_volume(&account_mgr, "trader"), 5.0);
+        assert_eq!(
+            account_mgr
+                .get_trading_restriction("trader")
+                .unwrap()
+                .restriction,
+            TradingRestriction::Suspended
+        );
+    }
+}
@@ -0,0 +1,520 @@
+//! 单用户单合约单日成交量限制
+//!
+//! 合规风控：防止过度交易或操纵，按交易日累计每个账户在每个合约上的成交量，
+//! 达到上限后拒绝新单或只允许平仓：
+//! - 成交时实时累计（开仓、平仓分别统计，由规则决定平仓成交是否计入限额）
+//! - 限额按合约（或品种）与账户类型（个人/机构/做市商）配置，未单独配置的合约使用默认规则
+//! - 交易日切换（交易所时区，夜盘归属下一交易日）后累计量自动清零
+//! - 首次达限时告警（每账户每合约每交易日一次），同时递增
+//!   `qaexchange_trade_volume_limit_reached_total{account_type}`
+//!
+//! 检查只比较已成交累计量，下单时不预占；达限前已提交的挂单成交后可能略超上限。
+//!
+//! @yutiansut @quantaxis
+
+use crate::core::account_ext::AccountType;
+use crate::observability::metrics::TRADE_VOLUME_LIMIT_REACHED_TOTAL;
+use crate::risk::portfolio_margin::product_of;
+use crate::utils::time_service::time_service;
+use crate::ExchangeError;
+use chrono::NaiveDate;
+use dashmap::DashMap;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+/// 保留的达限告警条数
+const MAX_ALERTS: usize = 1_000;
+
+/// 达限后的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VolumeLimitAction {
+    /// 只允许平仓
+    #[default]
+    CloseOnly,
+    /// 拒绝全部新单
+    Reject,
+}
+
+/// 成交量限额规则
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TradeVolumeRule {
+    /// 单日成交量上限（手）
+    pub max_volume: f64,
+    /// 平仓成交是否计入限额（默认只累计开仓成交）
+    #[serde(default)]
+    pub include_close: bool,
+    /// 达限后的处理方式
+    #[serde(default)]
+    pub action: VolumeLimitAction,
+}
+
+impl TradeVolumeRule {
+    pub fn new(max_volume: f64, include_close: bool, action: VolumeLimitAction) -> Self {
+        Self {
+            max_volume,
+            include_close,
+            action,
+        }
+    }
+
+    /// 计入限额的成交量
+    fn counted(&self, open_volume: f64, close_volume: f64) -> f64 {
+        if self.include_close {
+            open_volume + close_volume
+        } else {
+            open_volume
+        }
+    }
+}
+
+/// 按账户类型区分的规则（None 表示该类账户不限制）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AccountTypeVolumeRules {
+    #[serde(default)]
+    pub individual: Option<TradeVolumeRule>,
+    #[serde(default)]
+    pub institutional: Option<TradeVolumeRule>,
+    #[serde(default)]
+    pub market_maker: Option<TradeVolumeRule>,
+}
+
+impl AccountTypeVolumeRules {
+    pub fn rule_for(&self, account_type: AccountType) -> Option<TradeVolumeRule> {
+        match account_type {
+            AccountType::Individual => self.individual,
+            AccountType::Institutional => self.institutional,
+            AccountType::MarketMaker => self.market_maker,
+        }
+    }
+
+    fn validate(&self, scope: &str) -> Result<(), ExchangeError> {
+        for rule in [self.individual, self.institutional, self.market_maker]
+            .iter()
+            .flatten()
+        {
+            if rule.max_volume.is_nan() || rule.max_volume <= 0.0 {
+                return Err(ExchangeError::InvalidParameter(format!(
+                    "Trade volume limit of {} must be greater than 0, got {}",
+                    scope, rule.max_volume
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 成交量限制配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TradeVolumeLimitConfig {
+    /// 是否启用
+    #[serde(default)]
+    pub enabled: bool,
+    /// 默认规则（未单独配置的合约使用）
+    #[serde(default)]
+    pub default: AccountTypeVolumeRules,
+    /// 按合约或品种（如 IF）配置的规则，合约优先于品种
+    #[serde(default)]
+    pub instruments: HashMap<String, AccountTypeVolumeRules>,
+    /// 豁免账户
+    #[serde(default)]
+    pub exempt_accounts: Vec<String>,
+}
+
+impl TradeVolumeLimitConfig {
+    pub fn validate(&self) -> Result<(), ExchangeError> {
+        self.default.validate("default")?;
+        for (instrument_id, rules) in &self.instruments {
+            rules.validate(instrument_id)?;
+        }
+        Ok(())
+    }
+
+    /// 解析账户在合约上适用的规则，None 表示不限制
+    pub fn rule_for(
+        &self,
+        account_id: &str,
+        account_type: AccountType,
+        instrument_id: &str,
+    ) -> Option<TradeVolumeRule> {
+        if self.exempt_accounts.iter().any(|a| a == account_id) {
+            return None;
+        }
+        self.instruments
+            .get(instrument_id)
+            .or_else(|| self.instruments.get(product_of(instrument_id)))
+            .unwrap_or(&self.default)
+            .rule_for(account_type)
+    }
+}
+
+/// 单账户单合约当日累计
+#[derive(Debug)]
+struct VolumeCounter {
+    trading_day: NaiveDate,
+    open_volume: f64,
+    close_volume: f64,
+    alerted: bool,
+}
+
+impl VolumeCounter {
+    fn new(trading_day: NaiveDate) -> Self {
+        Self {
+            trading_day,
+            open_volume: 0.0,
+            close_volume: 0.0,
+            alerted: false,
+        }
+    }
+
+    /// 交易日切换后清零
+    fn roll(&mut self, trading_day: NaiveDate) {
+        if self.trading_day != trading_day {
+            *self = Self::new(trading_day);
+        }
+    }
+}
+
+/// 当日成交量累计
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TradeVolumeUsage {
+    pub account_id: String,
+    pub instrument_id: String,
+    pub trading_day: String,
+    pub open_volume: f64,
+    pub close_volume: f64,
+}
+
+/// 达限告警
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TradeVolumeAlert {
+    pub account_id: String,
+    pub instrument_id: String,
+    pub trading_day: String,
+    /// 计入限额的累计成交量
+    pub volume: f64,
+    pub max_volume: f64,
+    pub action: VolumeLimitAction,
+    pub timestamp: i64,
+}
+
+/// 单用户单合约单日成交量限制器
+pub struct TradeVolumeLimiter {
+    config: RwLock<TradeVolumeLimitConfig>,
+    counters: DashMap<(String, String), VolumeCounter>,
+    alerts: Mutex<VecDeque<TradeVolumeAlert>>,
+}
+
+impl TradeVolumeLimiter {
+    pub fn new(config: TradeVolumeLimitConfig) -> Result<Self, ExchangeError> {
+        config.validate()?;
+        Ok(Self {
+            config: RwLock::new(config),
+            counters: DashMap::new(),
+            alerts: Mutex::new(VecDeque::new()),
+        })
+    }
+
+    /// 当前配置
+    pub fn config(&self) -> TradeVolumeLimitConfig {
+        self.config.read().clone()
+    }
+
+    /// 更新配置（当日已累计的成交量保留）
+    pub fn update_config(&self, config: TradeVolumeLimitConfig) -> Result<(), ExchangeError> {
+        config.validate()?;
+        log::info!(
+            "[TradeVolumeLimit] Config updated: enabled={}, instruments={}, exempt={}",
+            config.enabled,
+            config.instruments.len(),
+            config.exempt_accounts.len()
+        );
+        *self.config.write() = config;
+        Ok(())
+    }
+
+    /// 成交累计（成交回报时调用，开平仓分别统计）
+    pub fn record_trade_at(
+        &self,
+        account_id: &str,
+        account_type: AccountType,
+        instrument_id: &str,
+        offset: &str,
+        volume: f64,
+        now_ms: i64,
+    ) {
+        let trading_day = time_service().trading_day_of(now_ms);
+        let mut entry = self
+            .counters
+            .entry((account_id.to_string(), instrument_id.to_string()))
+            .or_insert_with(|| VolumeCounter::new(trading_day));
+        let counter = entry.value_mut();
+        counter.roll(trading_day);
+        if offset == "OPEN" {
+            counter.open_volume += volume;
+        } else {
+            counter.close_volume += volume;
+        }
+
+        let config = self.config.read();
+        if !config.enabled || counter.alerted {
+            return;
+        }
+        let Some(rule) = config.rule_for(account_id, account_type, instrument_id) else {
+            return;
+        };
+        let counted = rule.counted(counter.open_volume, counter.close_volume);
+        if counted < rule.max_volume {
+            return;
+        }
+
+        // 达限告警（每账户每合约每交易日一次）
+        counter.alerted = true;
+        TRADE_VOLUME_LIMIT_REACHED_TOTAL
+            .with_label_values(&[account_type_label(account_type)])
+            .inc();
+        log::warn!(
+            "[TradeVolumeLimit] Account {} reached daily trade volume limit on {}: {} >= {} (open={}, close={}), action={:?}",
+            account_id,
+            instrument_id,
+            counted,
+            rule.max_volume,
+            counter.open_volume,
+            counter.close_volume,
+            rule.action
+        );
+        let mut alerts = self.alerts.lock();
+        if alerts.len() >= MAX_ALERTS {
+            alerts.pop_front();
+        }
+        alerts.push_back(TradeVolumeAlert {
+            account_id: account_id.to_string(),
+            instrument_id: instrument_id.to_string(),
+            trading_day: trading_day.format("%Y-%m-%d").to_string(),
+            volume: counted,
+            max_volume: rule.max_volume,
+            action: rule.action,
+            timestamp: now_ms,
+        });
+    }
+
+    /// 下单检查：累计成交量达上限后拒绝新单，`CloseOnly` 规则下平仓放行
+    pub fn check_order_at(
+        &self,
+        account_id: &str,
+        account_type: AccountType,
+        instrument_id: &str,
+        offset: &str,
+        now_ms: i64,
+    ) -> Result<(), String> {
+        let config = self.config.read();
+        if !config.enabled {
+            return Ok(());
+        }
+        let Some(rule) = config.rule_for(account_id, account_type, instrument_id) else {
+            return Ok(());
+        };
+        if rule.action == VolumeLimitAction::CloseOnly && offset != "OPEN" {
+            return Ok(());
+        }
+
+        let trading_day = time_service().trading_day_of(now_ms);
+        let counted = match self
+            .counters
+            .get(&(account_id.to_string(), instrument_id.to_string()))
+        {
+            Some(counter) if counter.trading_day == trading_day => {
+                rule.counted(counter.open_volume, counter.close_volume)
+            }
+            _ => 0.0,
+        };
+        if counted < rule.max_volume {
+            return Ok(());
+        }
+
+        Err(match rule.action {
+            VolumeLimitAction::CloseOnly => format!(
+                "Daily trade volume limit reached on {}: {} >= {}, only closing orders are allowed",
+                instrument_id, counted, rule.max_volume
+            ),
+            VolumeLimitAction::Reject => format!(
+                "Daily trade volume limit reached on {}: {} >= {}",
+                instrument_id, counted, rule.max_volume
+            ),
+        })
+    }
+
+    /// 账户当日各合约成交量
+    pub fn usage(&self, account_id: &str) -> Vec<TradeVolumeUsage> {
+        let trading_day = time_service().current_trading_day();
+        let mut usage: Vec<TradeVolumeUsage> = self
+            .counters
+            .iter()
+            .filter(|e| e.key().0 == account_id && e.trading_day == trading_day)
+            .map(|e| TradeVolumeUsage {
+                account_id: e.key().0.clone(),
+                instrument_id: e.key().1.clone(),
+                trading_day: e.trading_day.format("%Y-%m-%d").to_string(),
+                open_volume: e.open_volume,
+                close_volume: e.close_volume,
+            })
+            .collect();
+        usage.sort_by(|a, b| a.instrument_id.cmp(&b.instrument_id));
+        usage
+    }
+
+    /// 最近的达限告警（新的在前）
+    pub fn recent_alerts(&self, limit: usize) -> Vec<TradeVolumeAlert> {
+        self.alerts
+            .lock()
+            .iter()
+            .rev()
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    /// 清除过期交易日的累计（日切后调用，释放内存）
+    pub fn purge_before(&self, trading_day: NaiveDate) -> usize {
+        let before = self.counters.len();
+        self.counters.retain(|_, c| c.trading_day >= trading_day);
+        before - self.counters.len()
+    }
+}
+
+fn account_type_label(account_type: AccountType) -> &'static str {
+    match account_type {
+        AccountType::Individual => "individual",
+        AccountType::Institutional => "institutional",
+        AccountType::MarketMaker => "market_maker",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, TimeZone};
+
+    fn cst_ms(d: u32, h: u32) -> i64 {
+        FixedOffset::east_opt(8 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2025, 1, d, h, 0, 0)
+            .unwrap()
+            .timestamp_millis()
+    }
+
+    fn limiter() -> TradeVolumeLimiter {
+        let mut instruments = HashMap::new();
+        instruments.insert(
+            "IF".to_string(),
+            AccountTypeVolumeRules {
+                individual: Some(TradeVolumeRule::new(
+                    10.0,
+                    false,
+                    VolumeLimitAction::CloseOnly,
+                )),
+                ..Default::default()
+            },
+        );
+        TradeVolumeLimiter::new(TradeVolumeLimitConfig {
+            enabled: true,
+            default: AccountTypeVolumeRules {
+                individual: Some(TradeVolumeRule::new(5.0, true, VolumeLimitAction::Reject)),
+                institutional: Some(TradeVolumeRule::new(100.0, true, VolumeLimitAction::Reject)),
+                market_maker: None,
+            },
+            instruments,
+            exempt_accounts: vec!["vip".to_string()],
+        })
+        .unwrap()
+    }
+
+    /// 累计开仓成交达上限后新开仓被拒、平仓放行，日切后恢复
+    #[test]
+    fn test_close_only_after_limit_reached() {
+        let limiter = limiter();
+        let t = cst_ms(8, 10);
+        let check = |offset: &str, now_ms: i64| {
+            limiter.check_order_at("u1", AccountType::Individual, "IF2501", offset, now_ms)
+        };
+
+        limiter.record_trade_at("u1", AccountType::Individual, "IF2501", "OPEN", 6.0, t);
+        // 平仓成交不计入 IF 的限额
+        limiter.record_trade_at("u1", AccountType::Individual, "IF2501", "CLOSE", 5.0, t);
+        assert!(check("OPEN", t).is_ok());
+        assert!(limiter.recent_alerts(10).is_empty());
+
+        limiter.record_trade_at("u1", AccountType::Individual, "IF2501", "OPEN", 4.0, t);
+        assert!(check("OPEN", t).is_err());
+        assert!(check("CLOSE", t).is_ok());
+        assert!(check("CLOSETODAY", t).is_ok());
+
+        let alerts = limiter.recent_alerts(10);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].volume, 10.0);
+        assert_eq!(alerts[0].trading_day, "2025-01-08");
+        // 同一交易日只告警一次
+        limiter.record_trade_at("u1", AccountType::Individual, "IF2501", "OPEN", 1.0, t);
+        assert_eq!(limiter.recent_alerts(10).len(), 1);
+
+        // 其他合约、其他账户不受影响
+        assert!(limiter
+            .check_order_at("u1", AccountType::Individual, "IF2503", "OPEN", t)
+            .is_ok());
+        assert!(limiter
+            .check_order_at("u2", AccountType::Individual, "IF2501", "OPEN", t)
+            .is_ok());
+
+        // 当晚夜盘归属下一交易日，累计清零
+        let night = cst_ms(8, 21);
+        assert!(check("OPEN", night).is_ok());
+        limiter.record_trade_at("u1", AccountType::Individual, "IF2501", "OPEN", 1.0, night);
+        assert!(check("OPEN", night).is_ok());
+    }
+
+    /// Reject 规则下平仓同样被拒，平仓成交计入；账户类型与豁免名单生效
+    #[test]
+    fn test_reject_action_and_account_types() {
+        let limiter = limiter();
+        let t = cst_ms(8, 10);
+
+        limiter.record_trade_at("u1", AccountType::Individual, "cu2501", "OPEN", 3.0, t);
+        limiter.record_trade_at("u1", AccountType::Individual, "cu2501", "CLOSE", 2.0, t);
+        assert!(limiter
+            .check_order_at("u1", AccountType::Individual, "cu2501", "CLOSE", t)
+            .is_err());
+
+        // 机构账户上限更高，做市商与豁免账户不限制
+        limiter.record_trade_at("inst", AccountType::Institutional, "cu2501", "OPEN", 5.0, t);
+        assert!(limiter
+            .check_order_at("inst", AccountType::Institutional, "cu2501", "OPEN", t)
+            .is_ok());
+        limiter.record_trade_at("mm", AccountType::MarketMaker, "cu2501", "OPEN", 500.0, t);
+        assert!(limiter
+            .check_order_at("mm", AccountType::MarketMaker, "cu2501", "OPEN", t)
+            .is_ok());
+        limiter.record_trade_at("vip", AccountType::Individual, "cu2501", "OPEN", 50.0, t);
+        assert!(limiter
+            .check_order_at("vip", AccountType::Individual, "cu2501", "OPEN", t)
+            .is_ok());
+
+        // 日切后清除上一交易日的累计
+        assert_eq!(
+            limiter.purge_before(time_service().trading_day_of(cst_ms(8, 21))),
+            4
+        );
+
+        // 非法配置被拒绝
+        assert!(limiter
+            .update_config(TradeVolumeLimitConfig {
+                enabled: true,
+                default: AccountTypeVolumeRules {
+                    individual: Some(TradeVolumeRule::new(0.0, false, VolumeLimitAction::Reject)),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .is_err());
+    }
+}
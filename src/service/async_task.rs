@@ -0,0 +1,970 @@
+//! 重查询/导出的异步任务框架
+//!
+//! @yutiansut @quantaxis
+//!
+//! 导出一个月成交这类请求同步执行时经常超过网关超时，改为"提交 → 轮询 → 下载"：
+//! - `submit` 立即返回 task_id，任务进入队列由独立 worker 池执行，worker 数即并发任务上限
+//! - 单用户同时排队/执行中的任务数受 `per_user_quota` 限制，超出直接拒绝
+//! - 执行器按任务类型注册，向结果文件写数据并上报进度，执行中周期性检查取消标志
+//! - 结果先写 `{task_id}.part`，成功后改名为正式文件；下载 URL 携带过期时间与
+//!   HMAC-SHA256 签名（密钥 `signing_secret` 必须配置，重启与多实例间链接保持有效），
+//!   签名按常数时间比对，有效期 `url_ttl_secs` 可配置
+//! - 任务元数据每次状态变更落盘到 `tasks.json`，重启后未完成任务按 `resume_interrupted`
+//!   重新排队续跑或标记失败
+//! - 已结束任务超过 `result_retention_secs` 后连同结果文件清理
+
+use crossbeam::channel::{unbounded, Receiver, RecvTimeoutError, Sender};
+use dashmap::DashMap;
+use hmac::{Hmac, Mac};
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::ExchangeError;
+
+/// 任务元数据落盘文件名
+pub const TASK_METADATA_FILE: &str = "tasks.json";
+
+/// worker 空闲时清理过期任务的间隔
+const PURGE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// 异步任务配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AsyncTaskConfig {
+    /// 是否启用
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// worker 数（同时执行的任务上限）
+    #[serde(default = "default_workers")]
+    pub workers: usize,
+    /// 单用户同时排队/执行中的任务上限
+    #[serde(default = "default_per_user_quota")]
+    pub per_user_quota: usize,
+    /// 结果与元数据目录（为空时使用 `{storage_path}/tasks`，未启用存储时使用系统临时目录）
+    #[serde(default)]
+    pub result_dir: String,
+    /// 下载链接有效期（秒）
+    #[serde(default = "default_url_ttl_secs")]
+    pub url_ttl_secs: u64,
+    /// 下载链接 HMAC 签名密钥（必须配置，为空时拒绝创建管理器）
+    #[serde(default)]
+    pub signing_secret: String,
+    /// 重启后未完成任务重新排队续跑（false 时标记失败）
+    #[serde(default)]
+    pub resume_interrupted: bool,
+    /// 已结束任务及结果文件保留时长（秒）
+    #[serde(default = "default_result_retention_secs")]
+    pub result_retention_secs: u64,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+fn default_workers() -> usize {
+    2
+}
+fn default_per_user_quota() -> usize {
+    3
+}
+fn default_url_ttl_secs() -> u64 {
+    3600
+}
+fn default_result_retention_secs() -> u64 {
+    86400
+}
+
+impl Default for AsyncTaskConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            workers: default_workers(),
+            per_user_quota: default_per_user_quota(),
+            result_dir: String::new(),
+            url_ttl_secs: default_url_ttl_secs(),
+            signing_secret: String::new(),
+            resume_interrupted: false,
+            result_retention_secs: default_result_retention_secs(),
+        }
+    }
+}
+
+/// 任务状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AsyncTaskStatus {
+    /// 排队中
+    Pending,
+    /// 执行中
+    Running,
+    /// 已完成（结果可下载）
+    Succeeded,
+    /// 失败
+    Failed,
+    /// 已取消
+    Cancelled,
+}
+
+impl AsyncTaskStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Running => "running",
+            Self::Succeeded => "succeeded",
+            Self::Failed => "failed",
+            Self::Cancelled => "cancelled",
+        }
+    }
+
+    /// 是否已结束
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Self::Succeeded | Self::Failed | Self::Cancelled)
+    }
+}
+
+/// 任务元数据
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AsyncTaskInfo {
+    pub task_id: String,
+    pub user_id: String,
+    /// 任务类型（对应注册的执行器）
+    pub kind: String,
+    /// 任务参数（由执行器解析）
+    pub params: serde_json::Value,
+    pub status: AsyncTaskStatus,
+    /// 已处理条数
+    pub processed: u64,
+    /// 总条数（执行器未上报时为 0）
+    pub total: u64,
+    pub error: Option<String>,
+    /// 结果文件名（位于结果目录下）
+    pub result_file: Option<String>,
+    pub content_type: Option<String>,
+    /// 结果文件字节数
+    pub result_size: u64,
+    /// 提交时间（毫秒）
+    pub created_at: i64,
+    pub started_at: Option<i64>,
+    pub finished_at: Option<i64>,
+}
+
+impl AsyncTaskInfo {
+    /// 进度 (0.0 - 1.0)
+    pub fn progress(&self) -> f64 {
+        if self.status == AsyncTaskStatus::Succeeded {
+            1.0
+        } else if self.total == 0 {
+            0.0
+        } else {
+            (self.processed as f64 / self.total as f64).min(1.0)
+        }
+    }
+}
+
+/// 执行器输出描述
+#[derive(Debug, Clone)]
+pub struct TaskOutput {
+    /// 结果文件扩展名（如 csv、jsonl）
+    pub extension: String,
+    pub content_type: String,
+}
+
+/// 执行中任务的上下文（参数、进度上报、取消标志）
+#[derive(Clone)]
+pub struct TaskContext {
+    task_id: String,
+    user_id: String,
+    params: serde_json::Value,
+    cancelled: Arc<AtomicBool>,
+    processed: Arc<AtomicU64>,
+    total: Arc<AtomicU64>,
+}
+
+impl TaskContext {
+    fn new(info: &AsyncTaskInfo) -> Self {
+        Self {
+            task_id: info.task_id.clone(),
+            user_id: info.user_id.clone(),
+            params: info.params.clone(),
+            cancelled: Arc::new(AtomicBool::new(false)),
+            processed: Arc::new(AtomicU64::new(0)),
+            total: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn task_id(&self) -> &str {
+        &self.task_id
+    }
+
+    pub fn user_id(&self) -> &str {
+        &self.user_id
+    }
+
+    pub fn params(&self) -> &serde_json::Value {
+        &self.params
+    }
+
+    /// 是否已请求取消（执行器应周期性检查并尽快返回）
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Acquire)
+    }
+
+    /// 上报总条数
+    pub fn set_total(&self, total: u64) {
+        self.total.store(total, Ordering::Relaxed);
+    }
+
+    /// 上报已处理条数
+    pub fn set_processed(&self, processed: u64) {
+        self.processed.store(processed, Ordering::Relaxed);
+    }
+}
+
+/// 任务执行器：向 writer 写入结果，返回输出描述；取消时返回任意错误即可
+pub type TaskHandler =
+    Arc<dyn Fn(&TaskContext, &mut dyn Write) -> Result<TaskOutput, String> + Send + Sync>;
+
+/// 异步任务管理器
+pub struct AsyncTaskManager {
+    config: AsyncTaskConfig,
+    dir: PathBuf,
+    secret: String,
+    handlers: RwLock<HashMap<String, TaskHandler>>,
+    tasks: DashMap<String, AsyncTaskInfo>,
+    /// 排队/执行中任务的上下文
+    contexts: DashMap<String, TaskContext>,
+    queue_tx: Sender<String>,
+    queue_rx: Receiver<String>,
+    /// 配额检查与入队的互斥
+    submit_lock: Mutex<()>,
+    /// 元数据落盘互斥
+    persist_lock: Mutex<()>,
+    started: AtomicBool,
+}
+
+impl AsyncTaskManager {
+    /// 创建管理器，加载已有任务元数据并处理重启前未完成的任务
+    pub fn new(config: AsyncTaskConfig, dir: impl AsRef<Path>) -> Result<Self, ExchangeError> {
+        let dir = dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(&dir).map_err(|e| {
+            ExchangeError::StorageError(format!("Create task dir {:?} failed: {}", dir, e))
+        })?;
+
+        if config.signing_secret.is_empty() {
+            return Err(ExchangeError::ConfigError(
+                "async_task.signing_secret must be configured".to_string(),
+            ));
+        }
+        let secret = config.signing_secret.clone();
+        let (queue_tx, queue_rx) = unbounded();
+        let manager = Self {
+            config,
+            dir,
+            secret,
+            handlers: RwLock::new(HashMap::new()),
+            tasks: DashMap::new(),
+            contexts: DashMap::new(),
+            queue_tx,
+            queue_rx,
+            submit_lock: Mutex::new(()),
+            persist_lock: Mutex::new(()),
+            started: AtomicBool::new(false),
+        };
+        manager.recover()?;
+        Ok(manager)
+    }
+
+    fn recover(&self) -> Result<(), ExchangeError> {
+        let path = self.dir.join(TASK_METADATA_FILE);
+        if !path.exists() {
+            return Ok(());
+        }
+        let content = std::fs::read_to_string(&path).map_err(|e| {
+            ExchangeError::StorageError(format!("Read task metadata failed: {}", e))
+        })?;
+        let mut tasks: Vec<AsyncTaskInfo> = serde_json::from_str(&content).map_err(|e| {
+            ExchangeError::SerializationError(format!("Parse task metadata failed: {}", e))
+        })?;
+        tasks.sort_by_key(|task| task.created_at);
+
+        let now = now_millis();
+        let (mut resumed, mut failed) = (0usize, 0usize);
+        for mut task in tasks {
+            if !task.status.is_finished() {
+                let _ = std::fs::remove_file(self.part_path(&task.task_id));
+                task.processed = 0;
+                task.started_at = None;
+                if self.config.resume_interrupted {
+                    task.status = AsyncTaskStatus::Pending;
+                    self.contexts
+                        .insert(task.task_id.clone(), TaskContext::new(&task));
+                    let _ = self.queue_tx.send(task.task_id.clone());
+                    resumed += 1;
+                } else {
+                    task.status = AsyncTaskStatus::Failed;
+                    task.error = Some("interrupted by restart".to_string());
+                    task.finished_at = Some(now);
+                    failed += 1;
+                }
+            }
+            self.tasks.insert(task.task_id.clone(), task);
+        }
+        log::info!(
+            "Loaded {} async tasks ({} resumed, {} marked failed)",
+            self.tasks.len(),
+            resumed,
+            failed
+        );
+        if resumed + failed > 0 {
+            self.persist();
+        }
+        Ok(())
+    }
+
+    /// 注册任务类型的执行器
+    pub fn register_handler(&self, kind: &str, handler: TaskHandler) {
+        self.handlers.write().insert(kind.to_string(), handler);
+    }
+
+    /// 启动 worker 池（重复调用无效）
+    pub fn start(self: &Arc<Self>) {
+        if self.started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        for i in 0..self.config.workers.max(1) {
+            let manager = self.clone();
+            std::thread::Builder::new()
+                .name(format!("async-task-{}", i))
+                .spawn(move || loop {
+                    match manager.queue_rx.recv_timeout(PURGE_INTERVAL) {
+                        Ok(task_id) => manager.run(&task_id),
+                        Err(RecvTimeoutError::Timeout) => {
+                            manager.purge_expired(now_millis());
+                        }
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                })
+                .expect("Failed to spawn async task worker");
+        }
+        log::info!(
+            "Async task workers started: {} workers, quota {} per user, dir {:?}",
+            self.config.workers.max(1),
+            self.config.per_user_quota,
+            self.dir
+        );
+    }
+
+    /// 提交任务
+    pub fn submit(
+        &self,
+        user_id: &str,
+        kind: &str,
+        params: serde_json::Value,
+    ) -> Result<AsyncTaskInfo, ExchangeError> {
+        if !self.handlers.read().contains_key(kind) {
+            return Err(ExchangeError::InvalidParameter(format!(
+                "Unknown task kind: {}",
+                kind
+            )));
+        }
+
+        let _guard = self.submit_lock.lock();
+        let active = self
+            .tasks
+            .iter()
+            .filter(|task| task.user_id == user_id && !task.status.is_finished())
+            .count();
+        if active >= self.config.per_user_quota {
+            return Err(ExchangeError::ServiceError(format!(
+                "Task quota exceeded: {} active tasks (max {})",
+                active, self.config.per_user_quota
+            )));
+        }
+
+        let info = AsyncTaskInfo {
+            task_id: format!("task_{}", Uuid::new_v4().simple()),
+            user_id: user_id.to_string(),
+            kind: kind.to_string(),
+            params,
+            status: AsyncTaskStatus::Pending,
+            processed: 0,
+            total: 0,
+            error: None,
+            result_file: None,
+            content_type: None,
+            result_size: 0,
+            created_at: now_millis(),
+            started_at: None,
+            finished_at: None,
+        };
+        self.contexts
+            .insert(info.task_id.clone(), TaskContext::new(&info));
+        self.tasks.insert(info.task_id.clone(), info.clone());
+        self.persist();
+        let _ = self.queue_tx.send(info.task_id.clone());
+        log::info!(
+            "Async task {} submitted: user={}, kind={}",
+            info.task_id,
+            user_id,
+            kind
+        );
+        Ok(info)
+    }
+
+    /// 查询任务（执行中任务带实时进度）
+    pub fn get(&self, task_id: &str) -> Option<AsyncTaskInfo> {
+        let mut info = self.tasks.get(task_id)?.clone();
+        if let Some(ctx) = self.contexts.get(task_id) {
+            info.processed = ctx.processed.load(Ordering::Relaxed);
+            info.total = ctx.total.load(Ordering::Relaxed);
+        }
+        Some(info)
+    }
+
+    /// 用户的任务列表（按提交时间倒序）
+    pub fn list(&self, user_id: &str) -> Vec<AsyncTaskInfo> {
+        let mut tasks: Vec<_> = self
+            .tasks
+            .iter()
+            .filter(|task| task.user_id == user_id)
+            .filter_map(|task| self.get(&task.task_id))
+            .collect();
+        tasks.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        tasks
+    }
+
+    /// 取消任务
+    ///
+    /// 排队中的任务立即取消；执行中的任务设置取消标志，由执行器检查后结束并清理结果文件
+    pub fn cancel(&self, task_id: &str, user_id: &str) -> Result<AsyncTaskInfo, ExchangeError> {
+        let mut task = self.tasks.get_mut(task_id).ok_or_else(|| {
+            ExchangeError::InvalidParameter(format!("Task not found: {}", task_id))
+        })?;
+        if task.user_id != user_id {
+            return Err(ExchangeError::PermissionDenied(format!(
+                "Task {} does not belong to {}",
+                task_id, user_id
+            )));
+        }
+        if task.status.is_finished() {
+            return Err(ExchangeError::InvalidParameter(format!(
+                "Task {} already {}",
+                task_id,
+                task.status.as_str()
+            )));
+        }
+
+        if let Some(ctx) = self.contexts.get(task_id) {
+            ctx.cancelled.store(true, Ordering::Release);
+        }
+        if task.status == AsyncTaskStatus::Pending {
+            task.status = AsyncTaskStatus::Cancelled;
+            task.finished_at = Some(now_millis());
+            drop(task);
+            self.contexts.remove(task_id);
+            self.persist();
+        } else {
+            drop(task);
+        }
+        log::info!("Async task {} cancel requested by {}", task_id, user_id);
+        Ok(self.get(task_id).expect("task exists"))
+    }
+
+    /// 生成结果下载 URL（仅已完成任务）
+    pub fn download_url(&self, task_id: &str, now_secs: i64) -> Option<String> {
+        let task = self.tasks.get(task_id)?;
+        if task.status != AsyncTaskStatus::Succeeded {
+            return None;
+        }
+        let expires = now_secs + self.config.url_ttl_secs as i64;
+        Some(format!(
+            "/api/tasks/{}/download?expires={}&signature={}",
+            task_id,
+            expires,
+            self.sign(task_id, expires)
+        ))
+    }
+
+    /// 校验下载签名并返回结果文件路径
+    pub fn resolve_download(
+        &self,
+        task_id: &str,
+        expires: i64,
+        signature: &str,
+        now_secs: i64,
+    ) -> Result<(PathBuf, AsyncTaskInfo), ExchangeError> {
+        if !self.verify(task_id, expires, signature) {
+            return Err(ExchangeError::PermissionDenied(
+                "Invalid download signature".to_string(),
+            ));
+        }
+        if expires < now_secs {
+            return Err(ExchangeError::PermissionDenied(
+                "Download link expired".to_string(),
+            ));
+        }
+        let task = self.get(task_id).ok_or_else(|| {
+            ExchangeError::InvalidParameter(format!("Task not found: {}", task_id))
+        })?;
+        match (&task.status, &task.result_file) {
+            (AsyncTaskStatus::Succeeded, Some(file)) => Ok((self.dir.join(file), task)),
+            _ => Err(ExchangeError::InvalidParameter(format!(
+                "Task {} has no result",
+                task_id
+            ))),
+        }
+    }
+
+    /// 清理结束时间早于保留期的任务及其结果文件，返回清理数
+    pub fn purge_expired(&self, now_ms: i64) -> usize {
+        let cutoff = now_ms - (self.config.result_retention_secs as i64) * 1000;
+        let expired: Vec<String> = self
+            .tasks
+            .iter()
+            .filter(|task| task.finished_at.is_some_and(|ts| ts < cutoff))
+            .map(|task| task.task_id.clone())
+            .collect();
+        for task_id in &expired {
+            if let Some((_, task)) = self.tasks.remove(task_id) {
+                if let Some(file) = task.result_file {
+                    let _ = std::fs::remove_file(self.dir.join(file));
+                }
+            }
+        }
+        if !expired.is_empty() {
+            self.persist();
+            log::info!("Purged {} expired async tasks", expired.len());
+        }
+        expired.len()
+    }
+
+    /// 结果与元数据目录
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn run(&self, task_id: &str) {
+        // 排队期间已取消的任务没有上下文
+        let Some(ctx) = self.contexts.get(task_id).map(|ctx| ctx.clone()) else {
+            return;
+        };
+        let Some(kind) = self.tasks.get(task_id).map(|task| task.kind.clone()) else {
+            return;
+        };
+        let handler = self.handlers.read().get(&kind).cloned();
+
+        self.update(task_id, |task| {
+            task.status = AsyncTaskStatus::Running;
+            task.started_at = Some(now_millis());
+        });
+
+        let part = self.part_path(task_id);
+        let result = match handler {
+            Some(handler) => std::fs::File::create(&part)
+                .map_err(|e| format!("Create result file failed: {}", e))
+                .and_then(|file| {
+                    let mut writer = BufWriter::new(file);
+                    let output = handler(&ctx, &mut writer)?;
+                    writer
+                        .flush()
+                        .map_err(|e| format!("Write result file failed: {}", e))?;
+                    Ok(output)
+                }),
+            None => Err(format!("No handler registered for {}", kind)),
+        };
+
+        let processed = ctx.processed.load(Ordering::Relaxed);
+        let total = ctx.total.load(Ordering::Relaxed);
+        let finished_at = Some(now_millis());
+        if ctx.is_cancelled() {
+            let _ = std::fs::remove_file(&part);
+            self.update(task_id, |task| {
+                task.status = AsyncTaskStatus::Cancelled;
+                task.processed = processed;
+                task.total = total;
+                task.finished_at = finished_at;
+            });
+            log::info!(
+                "Async task {} cancelled after {} records",
+                task_id,
+                processed
+            );
+        } else {
+            let result = result.and_then(|output| {
+                let file_name = format!("{}.{}", task_id, output.extension);
+                let path = self.dir.join(&file_name);
+                std::fs::rename(&part, &path)
+                    .map_err(|e| format!("Finalize result file failed: {}", e))?;
+                let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+                Ok((file_name, output.content_type, size))
+            });
+            match result {
+                Ok((file_name, content_type, size)) => {
+                    self.update(task_id, |task| {
+                        task.status = AsyncTaskStatus::Succeeded;
+                        task.processed = processed;
+                        task.total = total.max(processed);
+                        task.result_file = Some(file_name);
+                        task.content_type = Some(content_type);
+                        task.result_size = size;
+                        task.finished_at = finished_at;
+                    });
+                    log::info!(
+                        "Async task {} succeeded: {} records, {} bytes",
+                        task_id,
+                        processed,
+                        size
+                    );
+                }
+                Err(e) => {
+                    let _ = std::fs::remove_file(&part);
+                    log::warn!("Async task {} failed: {}", task_id, e);
+                    self.update(task_id, |task| {
+                        task.status = AsyncTaskStatus::Failed;
+                        task.processed = processed;
+                        task.total = total;
+                        task.error = Some(e);
+                        task.finished_at = finished_at;
+                    });
+                }
+            }
+        }
+        self.contexts.remove(task_id);
+    }
+
+    fn update(&self, task_id: &str, f: impl FnOnce(&mut AsyncTaskInfo)) {
+        if let Some(mut task) = self.tasks.get_mut(task_id) {
+            f(&mut task);
+        }
+        self.persist();
+    }
+
+    fn part_path(&self, task_id: &str) -> PathBuf {
+        self.dir.join(format!("{}.part", task_id))
+    }
+
+    fn mac(&self, task_id: &str, expires: i64) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.secret.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(task_id.as_bytes());
+        mac.update(b":");
+        mac.update(expires.to_string().as_bytes());
+        mac
+    }
+
+    fn sign(&self, task_id: &str, expires: i64) -> String {
+        self.mac(task_id, expires)
+            .finalize()
+            .into_bytes()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    /// 常数时间校验十六进制签名
+    fn verify(&self, task_id: &str, expires: i64, signature: &str) -> bool {
+        if signature.len() % 2 != 0 || !signature.is_ascii() {
+            return false;
+        }
+        let bytes: Option<Vec<u8>> = (0..signature.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&signature[i..i + 2], 16).ok())
+            .collect();
+        match bytes {
+            Some(bytes) => self.mac(task_id, expires).verify_slice(&bytes).is_ok(),
+            None => false,
+        }
+    }
+
+    /// 元数据整体写入临时文件后改名，避免写一半时崩溃留下损坏文件
+    fn persist(&self) {
+        let _guard = self.persist_lock.lock();
+        let mut tasks: Vec<AsyncTaskInfo> = self.tasks.iter().map(|t| t.clone()).collect();
+        tasks.sort_by_key(|task| task.created_at);
+
+        let path = self.dir.join(TASK_METADATA_FILE);
+        let tmp = self.dir.join(format!("{}.tmp", TASK_METADATA_FILE));
+        let written = serde_json::to_vec_pretty(&tasks)
+            .map_err(|e| e.to_string())
+            .and_then(|bytes| std::fs::write(&tmp, bytes).map_err(|e| e.to_string()))
+            .and_then(|_| std::fs::rename(&tmp, &path).map_err(|e| e.to_string()));
+        if let Err(e) = written {
+            log::error!("Persist async task metadata failed: {}", e);
+        }
+    }
+}
+
+fn now_millis() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::time::Instant;
+
+    fn config() -> AsyncTaskConfig {
+        AsyncTaskConfig {
+            signing_secret: "test-signing-secret".to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// 逐行写出 rows 行，每行前检查取消；hold_half 时写到一半停住，直到被取消
+    fn register_export(manager: &AsyncTaskManager, hold_half: bool) {
+        manager.register_handler(
+            "export",
+            Arc::new(move |ctx: &TaskContext, out: &mut dyn Write| {
+                let rows = ctx.params()["rows"].as_u64().unwrap_or(0);
+                ctx.set_total(rows);
+                writeln!(out, "seq,value").map_err(|e| e.to_string())?;
+                for i in 0..rows {
+                    if ctx.is_cancelled() {
+                        return Err("cancelled".to_string());
+                    }
+                    while hold_half && i == rows / 2 {
+                        if ctx.is_cancelled() {
+                            return Err("cancelled".to_string());
+                        }
+                        std::thread::sleep(Duration::from_millis(5));
+                    }
+                    writeln!(out, "{},{}", i, i * 2).map_err(|e| e.to_string())?;
+                    ctx.set_processed(i + 1);
+                }
+                Ok(TaskOutput {
+                    extension: "csv".to_string(),
+                    content_type: "text/csv".to_string(),
+                })
+            }),
+        );
+    }
+
+    fn wait_for(
+        manager: &AsyncTaskManager,
+        task_id: &str,
+        done: impl Fn(&AsyncTaskInfo) -> bool,
+    ) -> AsyncTaskInfo {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            let info = manager.get(task_id).unwrap();
+            if done(&info) {
+                return info;
+            }
+            assert!(
+                Instant::now() < deadline,
+                "task {} stuck: {:?}",
+                task_id,
+                info
+            );
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn test_large_export_submit_poll_download() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = Arc::new(AsyncTaskManager::new(config(), dir.path()).unwrap());
+        register_export(&manager, false);
+        manager.start();
+
+        let rows = 200_000u64;
+        let task = manager
+            .submit("user1", "export", serde_json::json!({ "rows": rows }))
+            .unwrap();
+        assert_eq!(task.status, AsyncTaskStatus::Pending);
+        assert!(manager.download_url(&task.task_id, 0).is_none());
+
+        let info = wait_for(&manager, &task.task_id, |t| t.status.is_finished());
+        assert_eq!(info.status, AsyncTaskStatus::Succeeded);
+        assert_eq!((info.processed, info.total), (rows, rows));
+        assert_eq!(info.progress(), 1.0);
+
+        // 签名下载链接：解析参数后校验
+        let now = 1_700_000_000;
+        let url = manager.download_url(&task.task_id, now).unwrap();
+        let query: HashMap<&str, &str> = url
+            .split_once('?')
+            .unwrap()
+            .1
+            .split('&')
+            .filter_map(|kv| kv.split_once('='))
+            .collect();
+        let expires: i64 = query["expires"].parse().unwrap();
+        assert_eq!(expires, now + 3600);
+
+        let (path, resolved) = manager
+            .resolve_download(&task.task_id, expires, query["signature"], now)
+            .unwrap();
+        assert_eq!(resolved.content_type.as_deref(), Some("text/csv"));
+        let mut content = String::new();
+        std::fs::File::open(&path)
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content.lines().count() as u64, rows + 1);
+        assert_eq!(content.lines().last(), Some("199999,399998"));
+        assert_eq!(resolved.result_size, content.len() as u64);
+
+        // 过期、篡改签名、篡改过期时间均拒绝
+        assert!(matches!(
+            manager.resolve_download(&task.task_id, expires, query["signature"], expires + 1),
+            Err(ExchangeError::PermissionDenied(_))
+        ));
+        assert!(manager
+            .resolve_download(&task.task_id, expires, "deadbeef", now)
+            .is_err());
+        assert!(manager
+            .resolve_download(&task.task_id, expires + 3600, query["signature"], now)
+            .is_err());
+
+        // 同一密钥的新实例（重启/多实例）可校验已签发的链接，其他密钥拒绝
+        let restarted = AsyncTaskManager::new(config(), dir.path()).unwrap();
+        assert!(restarted
+            .resolve_download(&task.task_id, expires, query["signature"], now)
+            .is_ok());
+        let other = AsyncTaskManager::new(
+            AsyncTaskConfig {
+                signing_secret: "another-secret".to_string(),
+                ..config()
+            },
+            dir.path(),
+        )
+        .unwrap();
+        assert!(matches!(
+            other.resolve_download(&task.task_id, expires, query["signature"], now),
+            Err(ExchangeError::PermissionDenied(_))
+        ));
+    }
+
+    #[test]
+    fn test_signing_secret_required() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(matches!(
+            AsyncTaskManager::new(AsyncTaskConfig::default(), dir.path()),
+            Err(ExchangeError::ConfigError(_))
+        ));
+    }
+
+    #[test]
+    fn test_cancel_pending_and_running_tasks() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = AsyncTaskConfig {
+            workers: 1,
+            ..config()
+        };
+        let manager = Arc::new(AsyncTaskManager::new(config, dir.path()).unwrap());
+        register_export(&manager, true);
+        manager.start();
+
+        // 唯一的 worker 被第一个任务占住，第二个任务排队
+        let running = manager
+            .submit("user1", "export", serde_json::json!({ "rows": 1000 }))
+            .unwrap();
+        let pending = manager
+            .submit("user1", "export", serde_json::json!({ "rows": 1000 }))
+            .unwrap();
+        let info = wait_for(&manager, &running.task_id, |t| t.processed == 500);
+        assert_eq!(info.status, AsyncTaskStatus::Running);
+        assert_eq!(info.progress(), 0.5);
+
+        // 只能取消自己的任务
+        assert!(matches!(
+            manager.cancel(&pending.task_id, "user2"),
+            Err(ExchangeError::PermissionDenied(_))
+        ));
+        let cancelled = manager.cancel(&pending.task_id, "user1").unwrap();
+        assert_eq!(cancelled.status, AsyncTaskStatus::Cancelled);
+
+        manager.cancel(&running.task_id, "user1").unwrap();
+        let info = wait_for(&manager, &running.task_id, |t| t.status.is_finished());
+        assert_eq!(info.status, AsyncTaskStatus::Cancelled);
+        assert_eq!(info.processed, 500);
+        assert!(info.result_file.is_none());
+        assert!(manager.download_url(&running.task_id, 0).is_none());
+        assert!(manager.cancel(&running.task_id, "user1").is_err());
+
+        // 取消后没有残留的结果文件
+        let leftovers: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .filter(|name| !name.starts_with(TASK_METADATA_FILE))
+            .collect();
+        assert!(leftovers.is_empty(), "{:?}", leftovers);
+    }
+
+    #[test]
+    fn test_quota_and_restart_recovery() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = AsyncTaskConfig {
+            per_user_quota: 2,
+            ..config()
+        };
+        {
+            // 未启动 worker：任务停留在排队状态，模拟执行中途重启
+            let manager = AsyncTaskManager::new(config.clone(), dir.path()).unwrap();
+            register_export(&manager, false);
+            assert!(matches!(
+                manager.submit("user1", "unknown", serde_json::Value::Null),
+                Err(ExchangeError::InvalidParameter(_))
+            ));
+            manager
+                .submit("user1", "export", serde_json::json!({ "rows": 10 }))
+                .unwrap();
+            manager
+                .submit("user1", "export", serde_json::json!({ "rows": 10 }))
+                .unwrap();
+            assert!(matches!(
+                manager.submit("user1", "export", serde_json::json!({ "rows": 10 })),
+                Err(ExchangeError::ServiceError(_))
+            ));
+            // 配额按用户计算
+            manager
+                .submit("user2", "export", serde_json::json!({ "rows": 10 }))
+                .unwrap();
+        }
+
+        // 默认重启后未完成任务标记失败，配额随之释放
+        let manager = AsyncTaskManager::new(config.clone(), dir.path()).unwrap();
+        register_export(&manager, false);
+        let tasks = manager.list("user1");
+        assert_eq!(tasks.len(), 2);
+        assert!(tasks.iter().all(|t| t.status == AsyncTaskStatus::Failed
+            && t.error.as_deref() == Some("interrupted by restart")));
+        let resumable = manager
+            .submit("user1", "export", serde_json::json!({ "rows": 10 }))
+            .unwrap();
+        drop(manager);
+
+        // 开启续跑：未完成任务重新排队并执行完成
+        let manager = Arc::new(
+            AsyncTaskManager::new(
+                AsyncTaskConfig {
+                    resume_interrupted: true,
+                    ..config
+                },
+                dir.path(),
+            )
+            .unwrap(),
+        );
+        register_export(&manager, false);
+        manager.start();
+        let info = wait_for(&manager, &resumable.task_id, |t| t.status.is_finished());
+        assert_eq!(info.status, AsyncTaskStatus::Succeeded);
+        assert_eq!(info.processed, 10);
+
+        // 过期清理删除元数据与结果文件
+        let result = dir.path().join(info.result_file.unwrap());
+        assert!(result.exists());
+        assert_eq!(manager.purge_expired(now_millis() + 86_400_001), 4);
+        assert!(!result.exists());
+        assert!(manager.get(&resumable.task_id).is_none());
+    }
+}
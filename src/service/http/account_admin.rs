@@ -10,7 +10,7 @@ use uuid::Uuid;
 use tokio::sync::OnceCell;
 
 use super::models::*;
-use crate::exchange::account_mgr::AccountManager;
+use crate::exchange::account_mgr::{AccountManager, TradingRestriction, TradingRestrictionInfo};
 use crate::protocol::diff::snapshot::SnapshotManager;
 
 // ==================== 内存存储（生产环境应使用数据库） ====================
@@ -412,7 +412,7 @@ pub async fn get_margin_summary(
                     margin_rate_long: margin_rate,
                     margin_rate_short: margin_rate,
                     last_price: pos.lastest_price,
-                    multiplier: 1.0,  // 实际应从合约信息中获取
+                    multiplier: pos.preset.unit_table.max(1) as f64,
                 });
             }
 
@@ -540,6 +540,254 @@ pub async fn get_account_status(
     HttpResponse::Ok().json(ApiResponse::success(status_info))
 }
 
+/// 设置账户交易限制（只平仓 / 暂停交易 / 恢复正常）
+pub async fn set_trading_restriction(
+    req: web::Json<SetTradingRestrictionRequest>,
+    account_mgr: web::Data<Arc<AccountManager>>,
+) -> HttpResponse {
+    if !verify_admin_token(&req.admin_token) {
+        return HttpResponse::Unauthorized().json(ApiResponse::<()>::error(4010, "管理员认证失败".to_string()));
+    }
+
+    let details = format!(
+        "限制: {:?}, 原因: {}, 期限: {:?}",
+        req.restriction, req.reason, req.expires_at
+    );
+
+    match account_mgr.set_trading_restriction(
+        &req.account_id,
+        req.restriction,
+        req.reason.clone(),
+        req.expires_at,
+        "admin",
+    ) {
+        Ok(info) => {
+            log_audit(
+                req.account_id.clone(),
+                "admin".to_string(),
+                AuditLogType::TradingRestriction,
+                "设置交易限制".to_string(),
+                details,
+                None,
+                AuditResult::Success,
+            );
+            if info.is_active(current_timestamp()) {
+                account_mgr.notify_trading_restriction(&info);
+            }
+            HttpResponse::Ok().json(ApiResponse::success(info))
+        }
+        Err(e) => {
+            log_audit(
+                req.account_id.clone(),
+                "admin".to_string(),
+                AuditLogType::TradingRestriction,
+                "设置交易限制".to_string(),
+                format!("{}, 错误: {}", details, e),
+                None,
+                AuditResult::Failed,
+            );
+            HttpResponse::BadRequest().json(ApiResponse::<()>::error(4000, e.to_string()))
+        }
+    }
+}
+
+/// 查询账户交易限制（未限制时返回 Normal）
+pub async fn get_trading_restriction(
+    path: web::Path<String>,
+    account_mgr: web::Data<Arc<AccountManager>>,
+) -> HttpResponse {
+    let account_id = path.into_inner();
+
+    let info = account_mgr
+        .get_trading_restriction(&account_id)
+        .unwrap_or(TradingRestrictionInfo {
+            account_id: account_id.clone(),
+            restriction: TradingRestriction::Normal,
+            reason: String::new(),
+            expires_at: None,
+            updated_by: String::new(),
+            updated_at: 0,
+        });
+
+    HttpResponse::Ok().json(ApiResponse::success(info))
+}
+
+/// 查询所有生效中的交易限制
+pub async fn list_trading_restrictions(
+    account_mgr: web::Data<Arc<AccountManager>>,
+) -> HttpResponse {
+    HttpResponse::Ok().json(ApiResponse::success(account_mgr.list_trading_restrictions()))
+}
+
+// ==================== 账户标签（运营分类） ====================
+// @yutiansut @quantaxis
+
+/// 给一批账户打标签
+pub async fn add_account_tags(
+    req: web::Json<AccountTagsRequest>,
+    account_mgr: web::Data<Arc<AccountManager>>,
+) -> HttpResponse {
+    if !verify_admin_token(&req.admin_token) {
+        return HttpResponse::Unauthorized().json(ApiResponse::<()>::error(4010, "管理员认证失败".to_string()));
+    }
+
+    match account_mgr.add_account_tags(&req.account_ids, &req.tags, "admin") {
+        Ok(result) => HttpResponse::Ok().json(ApiResponse::success(result)),
+        Err(e) => HttpResponse::BadRequest().json(ApiResponse::<()>::error(4000, e.to_string())),
+    }
+}
+
+/// 移除一批账户的标签
+pub async fn remove_account_tags(
+    req: web::Json<AccountTagsRequest>,
+    account_mgr: web::Data<Arc<AccountManager>>,
+) -> HttpResponse {
+    if !verify_admin_token(&req.admin_token) {
+        return HttpResponse::Unauthorized().json(ApiResponse::<()>::error(4010, "管理员认证失败".to_string()));
+    }
+
+    match account_mgr.remove_account_tags(&req.account_ids, &req.tags, "admin") {
+        Ok(result) => HttpResponse::Ok().json(ApiResponse::success(result)),
+        Err(e) => HttpResponse::BadRequest().json(ApiResponse::<()>::error(4000, e.to_string())),
+    }
+}
+
+/// 标签重命名（返回涉及的账户）
+pub async fn rename_account_tag(
+    req: web::Json<RenameTagRequest>,
+    account_mgr: web::Data<Arc<AccountManager>>,
+) -> HttpResponse {
+    if !verify_admin_token(&req.admin_token) {
+        return HttpResponse::Unauthorized().json(ApiResponse::<()>::error(4010, "管理员认证失败".to_string()));
+    }
+
+    match account_mgr.account_tags().rename_tag(&req.from, &req.to, "admin") {
+        Ok(accounts) => HttpResponse::Ok().json(ApiResponse::success(accounts)),
+        Err(e) => HttpResponse::BadRequest().json(ApiResponse::<()>::error(4000, e.to_string())),
+    }
+}
+
+/// 删除标签（从所有账户移除，返回涉及的账户）
+pub async fn delete_account_tag(
+    req: web::Json<DeleteTagRequest>,
+    account_mgr: web::Data<Arc<AccountManager>>,
+) -> HttpResponse {
+    if !verify_admin_token(&req.admin_token) {
+        return HttpResponse::Unauthorized().json(ApiResponse::<()>::error(4010, "管理员认证失败".to_string()));
+    }
+
+    match account_mgr.account_tags().delete_tag(&req.tag, "admin") {
+        Ok(accounts) => HttpResponse::Ok().json(ApiResponse::success(accounts)),
+        Err(e) => HttpResponse::BadRequest().json(ApiResponse::<()>::error(4000, e.to_string())),
+    }
+}
+
+/// 标签列表与各标签账户数
+pub async fn list_account_tags(
+    account_mgr: web::Data<Arc<AccountManager>>,
+) -> HttpResponse {
+    HttpResponse::Ok().json(ApiResponse::success(account_mgr.account_tags().tag_stats()))
+}
+
+/// 查询账户的标签
+pub async fn get_account_tags(
+    path: web::Path<String>,
+    account_mgr: web::Data<Arc<AccountManager>>,
+) -> HttpResponse {
+    let account_id = path.into_inner();
+    HttpResponse::Ok().json(ApiResponse::success(serde_json::json!({
+        "account_id": account_id,
+        "tags": account_mgr.account_tags().tags_of(&account_id),
+        "margin_surcharge": account_mgr.account_tags().margin_surcharge(&account_id),
+    })))
+}
+
+/// 按标签筛选账户
+pub async fn get_accounts_by_tags(
+    query: web::Query<TagAccountsQuery>,
+    account_mgr: web::Data<Arc<AccountManager>>,
+) -> HttpResponse {
+    let tags: Vec<String> = query
+        .tags
+        .split(',')
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .collect();
+    if tags.is_empty() {
+        return HttpResponse::BadRequest().json(ApiResponse::<()>::error(4000, "tags 不能为空".to_string()));
+    }
+
+    HttpResponse::Ok().json(ApiResponse::success(
+        account_mgr.get_accounts_by_tags(&tags, query.match_all),
+    ))
+}
+
+/// 按标签批量设置交易限制（批量冻结 / 解冻）
+pub async fn set_restriction_by_tags(
+    req: web::Json<TagRestrictionRequest>,
+    account_mgr: web::Data<Arc<AccountManager>>,
+) -> HttpResponse {
+    if !verify_admin_token(&req.admin_token) {
+        return HttpResponse::Unauthorized().json(ApiResponse::<()>::error(4010, "管理员认证失败".to_string()));
+    }
+
+    match account_mgr.set_trading_restriction_by_tags(
+        &req.tags,
+        req.match_all,
+        req.restriction,
+        req.reason.clone(),
+        req.expires_at,
+        "admin",
+    ) {
+        Ok(result) => {
+            for account_id in &result.accounts {
+                log_audit(
+                    account_id.clone(),
+                    "admin".to_string(),
+                    AuditLogType::TradingRestriction,
+                    "按标签设置交易限制".to_string(),
+                    format!(
+                        "标签: {:?}, 限制: {:?}, 原因: {}",
+                        req.tags, req.restriction, req.reason
+                    ),
+                    None,
+                    AuditResult::Success,
+                );
+            }
+            HttpResponse::Ok().json(ApiResponse::success(result))
+        }
+        Err(e) => HttpResponse::BadRequest().json(ApiResponse::<()>::error(4000, e.to_string())),
+    }
+}
+
+/// 按标签批量设置保证金加成
+pub async fn set_margin_surcharge_by_tags(
+    req: web::Json<TagMarginSurchargeRequest>,
+    account_mgr: web::Data<Arc<AccountManager>>,
+) -> HttpResponse {
+    if !verify_admin_token(&req.admin_token) {
+        return HttpResponse::Unauthorized().json(ApiResponse::<()>::error(4010, "管理员认证失败".to_string()));
+    }
+
+    match account_mgr.set_margin_surcharge_by_tags(&req.tags, req.match_all, req.surcharge, "admin") {
+        Ok(result) => HttpResponse::Ok().json(ApiResponse::success(result)),
+        Err(e) => HttpResponse::BadRequest().json(ApiResponse::<()>::error(4000, e.to_string())),
+    }
+}
+
+/// 标签变更审计（新 → 旧）
+pub async fn get_account_tag_audit(
+    query: web::Query<TagAuditQuery>,
+    account_mgr: web::Data<Arc<AccountManager>>,
+) -> HttpResponse {
+    let limit = query.limit.unwrap_or(100).min(1000);
+    HttpResponse::Ok().json(ApiResponse::success(
+        account_mgr
+            .account_tags()
+            .audit_records(query.account_id.as_deref(), limit),
+    ))
+}
+
 /// 检查账户是否可以交易
 pub fn can_trade(account_id: &str) -> bool {
     if let Some(status) = ACCOUNT_STATUS.get(account_id) {
@@ -2,13 +2,23 @@
 //!
 //! 提供合约管理、风控监控、结算管理等管理员功能的 HTTP API
 
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpRequest, HttpResponse};
 use log;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use std::time::Instant;
 
+use super::account_admin::log_audit;
+use super::models::{AuditLogType, AuditResult};
+use crate::core::account_ext::{AccountType, OpenAccountRequest};
 use crate::exchange::instrument_registry::{InstrumentInfo, InstrumentStatus, InstrumentType};
-use crate::exchange::{AccountManager, InstrumentRegistry, SettlementEngine};
+use crate::exchange::{
+    AccountManager, EmergencyShutdown, InstrumentRegistry, ListingProtection,
+    ListingProtectionConfig, SettlementEngine, TimeController, TradingDayManager,
+};
+use crate::notification::{SystemEvent, SystemEventCategory, SystemEventLevel};
+use crate::storage::maintenance::{MaintenanceKind, StorageMaintenance};
+use crate::user::UserManager;
 use crate::ExchangeError;
 
 // ============================================================================
@@ -19,6 +29,16 @@ pub struct AdminAppState {
     pub instrument_registry: Arc<InstrumentRegistry>,
     pub settlement_engine: Arc<SettlementEngine>,
     pub account_mgr: Arc<AccountManager>,
+    /// 行情存储运维（手动 flush / compaction）
+    pub storage_maintenance: Arc<StorageMaintenance>,
+    /// 紧急停机协调器
+    pub emergency: Arc<EmergencyShutdown>,
+    /// 交易日管理（开盘标记 / 测试环境重置）
+    pub trading_day: Arc<TradingDayManager>,
+    /// 测试环境时间控制（虚拟时间快进）
+    pub time_control: Arc<TimeController>,
+    /// 新合约上市保护
+    pub listing_protection: Arc<ListingProtection>,
 }
 
 // ============================================================================
@@ -62,7 +82,12 @@ pub struct CreateInstrumentRequest {
     pub instrument_name: String,
     pub instrument_type: InstrumentType,
     pub exchange: String,
+    /// 每手数量
     pub contract_multiplier: i32,
+    /// 交易单位（默认 手）
+    pub trading_unit: Option<String>,
+    /// 报价单位（如 元/吨、点）
+    pub quote_unit: Option<String>,
     pub price_tick: f64,
     pub margin_rate: f64,
     pub commission_rate: f64,
@@ -70,6 +95,9 @@ pub struct CreateInstrumentRequest {
     pub limit_down_rate: f64,
     pub list_date: Option<String>,
     pub expire_date: Option<String>,
+    /// 注册为待上市（需调用上市接口后才开放交易）
+    #[serde(default)]
+    pub pending: bool,
 }
 
 // 合约更新请求
@@ -77,6 +105,8 @@ pub struct CreateInstrumentRequest {
 pub struct UpdateInstrumentRequest {
     pub instrument_name: Option<String>,
     pub contract_multiplier: Option<i32>,
+    pub trading_unit: Option<String>,
+    pub quote_unit: Option<String>,
     pub price_tick: Option<f64>,
     pub margin_rate: Option<f64>,
     pub commission_rate: Option<f64>,
@@ -99,6 +129,17 @@ pub async fn get_all_instruments(
     Ok(HttpResponse::Ok().json(ApiResponse::success(instruments)))
 }
 
+/// 同名合约冲突（不带交易所前缀的旧代码对应多个合约，旧代码无法解析）
+pub async fn get_alias_conflicts(
+    state: web::Data<AdminAppState>,
+) -> Result<HttpResponse, actix_web::Error> {
+    log::debug!("GET /api/admin/instruments/alias-conflicts");
+
+    let conflicts = state.instrument_registry.alias_conflicts();
+
+    Ok(HttpResponse::Ok().json(ApiResponse::success(conflicts)))
+}
+
 /// 创建/上市新合约
 pub async fn create_instrument(
     state: web::Data<AdminAppState>,
@@ -115,6 +156,12 @@ pub async fn create_instrument(
 
     // 设置参数
     instrument.contract_multiplier = req.contract_multiplier;
+    if let Some(unit) = &req.trading_unit {
+        instrument.trading_unit = unit.clone();
+    }
+    if let Some(unit) = &req.quote_unit {
+        instrument.quote_unit = unit.clone();
+    }
     instrument.price_tick = req.price_tick;
     instrument.margin_rate = req.margin_rate;
     instrument.commission_rate = req.commission_rate;
@@ -122,6 +169,9 @@ pub async fn create_instrument(
     instrument.limit_down_rate = req.limit_down_rate;
     instrument.list_date = req.list_date.clone();
     instrument.expire_date = req.expire_date.clone();
+    if req.pending {
+        instrument.status = InstrumentStatus::Pending;
+    }
 
     match state.instrument_registry.register(instrument) {
         Ok(_) => Ok(HttpResponse::Ok().json(ApiResponse::success(()))),
@@ -129,6 +179,19 @@ pub async fn create_instrument(
     }
 }
 
+/// 从 CTP 合约参数文件导入合约（请求体为 instrument.csv 原始内容，UTF-8/GBK）
+pub async fn import_ctp_instruments(
+    state: web::Data<AdminAppState>,
+    body: web::Bytes,
+) -> Result<HttpResponse, actix_web::Error> {
+    log::info!("POST /api/admin/instruments/import-ctp: {} bytes", body.len());
+
+    match state.instrument_registry.import_from_ctp_bytes(&body) {
+        Ok(report) => Ok(HttpResponse::Ok().json(ApiResponse::success(report))),
+        Err(e) => Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(e.to_string()))),
+    }
+}
+
 /// 更新合约信息
 pub async fn update_instrument(
     state: web::Data<AdminAppState>,
@@ -138,6 +201,11 @@ pub async fn update_instrument(
     let instrument_id = path.into_inner();
     log::info!("PUT /api/admin/instrument/{}/update", instrument_id);
 
+    if let Some(mult) = req.contract_multiplier.filter(|m| *m <= 0) {
+        let message = format!("contract_multiplier must be positive: {}", mult);
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(message)));
+    }
+
     let result = state.instrument_registry.update(&instrument_id, |info| {
         if let Some(name) = &req.instrument_name {
             info.instrument_name = name.clone();
@@ -145,6 +213,12 @@ pub async fn update_instrument(
         if let Some(mult) = req.contract_multiplier {
             info.contract_multiplier = mult;
         }
+        if let Some(unit) = &req.trading_unit {
+            info.trading_unit = unit.clone();
+        }
+        if let Some(unit) = &req.quote_unit {
+            info.quote_unit = unit.clone();
+        }
         if let Some(tick) = req.price_tick {
             info.price_tick = tick;
         }
@@ -168,6 +242,29 @@ pub async fn update_instrument(
     }
 }
 
+/// 发布合约状态变更系统事件
+fn publish_instrument_event(
+    state: &AdminAppState,
+    instrument_id: &str,
+    status: &str,
+    level: SystemEventLevel,
+) {
+    if let Some(broker) = state.account_mgr.notification_broker() {
+        broker.publish_system_event(
+            SystemEvent::new(
+                SystemEventCategory::Instrument,
+                level,
+                format!("合约 {} 状态变更为 {}", instrument_id, status),
+            )
+            .with_details(serde_json::json!({
+                "instrument_id": instrument_id,
+                "status": status,
+            })),
+            "AdminApi",
+        );
+    }
+}
+
 /// 暂停合约交易
 pub async fn suspend_instrument(
     state: web::Data<AdminAppState>,
@@ -177,7 +274,15 @@ pub async fn suspend_instrument(
     log::info!("PUT /api/admin/instrument/{}/suspend", instrument_id);
 
     match state.instrument_registry.suspend(&instrument_id) {
-        Ok(_) => Ok(HttpResponse::Ok().json(ApiResponse::success(()))),
+        Ok(_) => {
+            publish_instrument_event(
+                &state,
+                &instrument_id,
+                "suspended",
+                SystemEventLevel::Warning,
+            );
+            Ok(HttpResponse::Ok().json(ApiResponse::success(())))
+        }
         Err(e) => Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(e.to_string()))),
     }
 }
@@ -191,11 +296,89 @@ pub async fn resume_instrument(
     log::info!("PUT /api/admin/instrument/{}/resume", instrument_id);
 
     match state.instrument_registry.resume(&instrument_id) {
+        Ok(_) => {
+            publish_instrument_event(&state, &instrument_id, "active", SystemEventLevel::Info);
+            Ok(HttpResponse::Ok().json(ApiResponse::success(())))
+        }
+        Err(e) => Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(e.to_string()))),
+    }
+}
+
+/// 上市待上市合约（已配置上市保护时开启保护时段）
+pub async fn activate_instrument(
+    state: web::Data<AdminAppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let instrument_id = path.into_inner();
+    log::info!("PUT /api/admin/instrument/{}/activate", instrument_id);
+
+    let now = chrono::Utc::now().timestamp_millis();
+    match state.listing_protection.activate_at(&instrument_id, now) {
+        Ok(window) => Ok(HttpResponse::Ok().json(ApiResponse::success(window))),
+        Err(e) => Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(e.to_string()))),
+    }
+}
+
+/// 查询合约上市保护配置与进行中的保护时段
+pub async fn get_listing_protection(
+    state: web::Data<AdminAppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let instrument_id = path.into_inner();
+    let protection = serde_json::json!({
+        "instrument_id": instrument_id,
+        "config": state.listing_protection.get_config(&instrument_id),
+        "window": state.listing_protection.get_window(&instrument_id),
+    });
+
+    Ok(HttpResponse::Ok().json(ApiResponse::success(protection)))
+}
+
+/// 开启/修改合约上市保护
+pub async fn set_listing_protection(
+    state: web::Data<AdminAppState>,
+    path: web::Path<String>,
+    req: web::Json<ListingProtectionConfig>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let instrument_id = path.into_inner();
+    log::info!(
+        "PUT /api/admin/instrument/{}/listing-protection",
+        instrument_id
+    );
+
+    if state.instrument_registry.get(&instrument_id).is_none() {
+        let message = format!("Instrument {} not found", instrument_id);
+        return Ok(HttpResponse::NotFound().json(ApiResponse::<()>::error(message)));
+    }
+    match state
+        .listing_protection
+        .set_config(&instrument_id, req.into_inner())
+    {
         Ok(_) => Ok(HttpResponse::Ok().json(ApiResponse::success(()))),
         Err(e) => Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(e.to_string()))),
     }
 }
 
+/// 关闭合约上市保护（不影响进行中的保护时段）
+pub async fn remove_listing_protection(
+    state: web::Data<AdminAppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let instrument_id = path.into_inner();
+    log::info!(
+        "DELETE /api/admin/instrument/{}/listing-protection",
+        instrument_id
+    );
+
+    match state.listing_protection.remove_config(&instrument_id) {
+        Some(config) => Ok(HttpResponse::Ok().json(ApiResponse::success(config))),
+        None => {
+            let message = format!("Listing protection of {} not configured", instrument_id);
+            Ok(HttpResponse::NotFound().json(ApiResponse::<()>::error(message)))
+        }
+    }
+}
+
 /// 下市合约
 pub async fn delist_instrument(
     state: web::Data<AdminAppState>,
@@ -242,6 +425,12 @@ pub async fn delist_instrument(
     match state.instrument_registry.delist(&instrument_id) {
         Ok(_) => {
             log::info!("Instrument {} delisted successfully", instrument_id);
+            publish_instrument_event(
+                &state,
+                &instrument_id,
+                "delisted",
+                SystemEventLevel::Warning,
+            );
             Ok(HttpResponse::Ok().json(ApiResponse::success(())))
         }
         Err(e) => Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(e.to_string()))),
@@ -314,6 +503,42 @@ pub async fn execute_settlement(
     }
 }
 
+/// 结算进度查询参数
+#[derive(Debug, Deserialize)]
+pub struct SettlementProgressQuery {
+    /// 结算日期（YYYY-MM-DD），为空时取最近一次结算任务
+    pub date: Option<String>,
+}
+
+/// 获取结算进度（完成百分比、预计剩余时间、失败账户）
+pub async fn get_settlement_progress(
+    state: web::Data<AdminAppState>,
+    query: web::Query<SettlementProgressQuery>,
+) -> Result<HttpResponse, actix_web::Error> {
+    log::debug!("GET /api/admin/settlement/progress?date={:?}", query.date);
+
+    match state
+        .settlement_engine
+        .get_settlement_progress(query.date.as_deref())
+    {
+        Some(progress) => Ok(HttpResponse::Ok().json(ApiResponse::success(progress))),
+        None => Ok(HttpResponse::NotFound().json(ApiResponse::<()>::error(
+            "Settlement task not found".to_string(),
+        ))),
+    }
+}
+
+/// 获取资金定点核算双轨差异（迁移期浮点与定点比对告警）
+pub async fn get_fixed_point_drift(
+    state: web::Data<AdminAppState>,
+) -> Result<HttpResponse, actix_web::Error> {
+    log::debug!("GET /api/admin/fixed-point/drift");
+
+    let stats = state.account_mgr.fixed_ledger().drift_stats();
+
+    Ok(HttpResponse::Ok().json(ApiResponse::success(stats)))
+}
+
 /// 获取结算历史
 pub async fn get_settlement_history(
     state: web::Data<AdminAppState>,
@@ -340,6 +565,593 @@ pub async fn get_settlement_detail(
     }
 }
 
+/// 对账报告查询参数
+#[derive(Debug, Deserialize)]
+pub struct ReconciliationQuery {
+    /// 结算日期（YYYY-MM-DD），为空时取今日
+    pub date: Option<String>,
+}
+
+/// 获取结算对账报告
+pub async fn get_settlement_reconciliation(
+    state: web::Data<AdminAppState>,
+    query: web::Query<ReconciliationQuery>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let date = query.date.clone().unwrap_or_else(|| {
+        crate::utils::time_service::now_local()
+            .format("%Y-%m-%d")
+            .to_string()
+    });
+    log::debug!("GET /api/admin/settlement/reconciliation?date={}", date);
+
+    match state.settlement_engine.get_reconciliation_report(&date) {
+        Some(report) => Ok(HttpResponse::Ok().json(ApiResponse::success(report))),
+        None => Ok(HttpResponse::NotFound().json(ApiResponse::<()>::error(
+            "Reconciliation report not found".to_string(),
+        ))),
+    }
+}
+
+/// 穿仓记录查询参数
+#[derive(Debug, Deserialize)]
+pub struct DeficitQuery {
+    /// 结算日期（YYYY-MM-DD），为空时返回全部
+    pub date: Option<String>,
+}
+
+/// 查询穿仓记录
+pub async fn get_settlement_deficits(
+    state: web::Data<AdminAppState>,
+    query: web::Query<DeficitQuery>,
+) -> Result<HttpResponse, actix_web::Error> {
+    log::debug!("GET /api/admin/settlement/deficits?date={:?}", query.date);
+
+    let records = state
+        .settlement_engine
+        .get_deficit_records(query.date.as_deref());
+
+    Ok(HttpResponse::Ok().json(ApiResponse::success(records)))
+}
+
+/// 查询风险准备金余额与累计垫付
+pub async fn get_risk_reserve(
+    state: web::Data<AdminAppState>,
+) -> Result<HttpResponse, actix_web::Error> {
+    log::debug!("GET /api/admin/settlement/risk-reserve");
+
+    let status = state.settlement_engine.get_risk_reserve_status();
+
+    Ok(HttpResponse::Ok().json(ApiResponse::success(status)))
+}
+
+// ============================================================================
+// 紧急停机
+// ============================================================================
+
+/// 紧急停机请求
+#[derive(Debug, Deserialize)]
+pub struct EmergencyShutdownRequest {
+    pub reason: String,
+    pub operator_id: Option<String>,
+}
+
+/// 触发紧急停机：立即停止接单并进入维护状态，随后由服务主流程保存状态并退出
+pub async fn emergency_shutdown(
+    state: web::Data<AdminAppState>,
+    req: web::Json<EmergencyShutdownRequest>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let req = req.into_inner();
+    log::warn!("POST /api/admin/emergency-shutdown: {:?}", req);
+
+    if req.reason.trim().is_empty() {
+        return Ok(HttpResponse::BadRequest()
+            .json(ApiResponse::<()>::error("停机原因不能为空".to_string())));
+    }
+
+    let accepted = state.emergency.request(&req.reason);
+    log_audit(
+        "SYSTEM".to_string(),
+        req.operator_id.unwrap_or_else(|| "admin".to_string()),
+        AuditLogType::EmergencyShutdown,
+        "紧急停机".to_string(),
+        req.reason.clone(),
+        None,
+        if accepted {
+            AuditResult::Success
+        } else {
+            AuditResult::Failed
+        },
+    );
+
+    if !accepted {
+        return Ok(HttpResponse::Conflict()
+            .json(ApiResponse::<()>::error("紧急停机已在进行中".to_string())));
+    }
+    Ok(HttpResponse::Accepted().json(ApiResponse::success(
+        crate::exchange::emergency::maintenance_status(),
+    )))
+}
+
+/// 查询维护状态与最近一次停机记录
+pub async fn get_emergency_status(
+    state: web::Data<AdminAppState>,
+) -> Result<HttpResponse, actix_web::Error> {
+    Ok(
+        HttpResponse::Ok().json(ApiResponse::success(serde_json::json!({
+            "maintenance": crate::exchange::emergency::maintenance_status(),
+            "last_shutdown": state.emergency.last_record(),
+        }))),
+    )
+}
+
+// ============================================================================
+// 交易日重置（仅测试环境）
+// ============================================================================
+
+/// 交易日重置请求
+#[derive(Debug, Default, Deserialize)]
+pub struct ResetTradingDayRequest {
+    pub operator_id: Option<String>,
+}
+
+/// 重置当前交易日：账户、订单簿、成交、当日K线与资金流水恢复到开盘标记
+///
+/// 仅 development 环境可用，生产配置下返回 403
+pub async fn reset_trading_day(
+    state: web::Data<AdminAppState>,
+    req: Option<web::Json<ResetTradingDayRequest>>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let req = req.map(|r| r.into_inner()).unwrap_or_default();
+    log::warn!("POST /api/admin/reset-trading-day: {:?}", req);
+
+    let manager = state.trading_day.clone();
+    let result = web::block(move || manager.reset_trading_day())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    log_audit(
+        "SYSTEM".to_string(),
+        req.operator_id.unwrap_or_else(|| "admin".to_string()),
+        AuditLogType::TradingDayReset,
+        "交易日重置".to_string(),
+        match &result {
+            Ok(report) => format!("trading_day={}", report.trading_day),
+            Err(e) => e.to_string(),
+        },
+        None,
+        if result.is_ok() {
+            AuditResult::Success
+        } else {
+            AuditResult::Failed
+        },
+    );
+
+    match result {
+        Ok(report) => Ok(HttpResponse::Ok().json(ApiResponse::success(report))),
+        Err(ExchangeError::PermissionDenied(msg)) => {
+            Ok(HttpResponse::Forbidden().json(ApiResponse::<()>::error(msg)))
+        }
+        Err(e) => Ok(HttpResponse::Conflict().json(ApiResponse::<()>::error(e.to_string()))),
+    }
+}
+
+// ============================================================================
+// 时间控制（仅测试环境）
+// ============================================================================
+
+/// 时间控制请求：`time` 与 `hours` 二选一
+#[derive(Debug, Default, Deserialize)]
+pub struct AdvanceTimeRequest {
+    /// 设置虚拟当前时间（交易所本地时间 YYYY-MM-DD HH:MM:SS）
+    pub time: Option<String>,
+    /// 快进小时数
+    pub hours: Option<f64>,
+    pub operator_id: Option<String>,
+}
+
+/// 设置虚拟当前时间或快进 N 小时，按顺序触发区间内的结算与交易日切换
+///
+/// 仅 development 环境可用，生产配置下返回 404
+pub async fn advance_time(
+    state: web::Data<AdminAppState>,
+    req: Option<web::Json<AdvanceTimeRequest>>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if !state.time_control.is_allowed() {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    let req = req.map(|r| r.into_inner()).unwrap_or_default();
+    log::warn!("POST /api/admin/testing/advance-time: {:?}", req);
+
+    let controller = state.time_control.clone();
+    let target = (req.time.clone(), req.hours);
+    let result = web::block(move || match target {
+        (Some(time), None) => controller.set_local_time(&time),
+        (None, Some(hours)) => controller.advance_hours(hours),
+        _ => Err(ExchangeError::InvalidParameter(
+            "Exactly one of time or hours is required".to_string(),
+        )),
+    })
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    log_audit(
+        "SYSTEM".to_string(),
+        req.operator_id.unwrap_or_else(|| "admin".to_string()),
+        AuditLogType::TimeAdvance,
+        "虚拟时间快进".to_string(),
+        match &result {
+            Ok(report) => format!(
+                "{} -> {}, events={}",
+                report.from_local,
+                report.to_local,
+                report.events.len()
+            ),
+            Err(e) => e.to_string(),
+        },
+        None,
+        if result.is_ok() {
+            AuditResult::Success
+        } else {
+            AuditResult::Failed
+        },
+    );
+
+    match result {
+        Ok(report) => Ok(HttpResponse::Ok().json(ApiResponse::success(report))),
+        Err(e) => Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(e.to_string()))),
+    }
+}
+
+// ============================================================================
+// 存储运维
+// ============================================================================
+
+/// 手动 flush / compaction 请求
+#[derive(Debug, Default, Deserialize)]
+pub struct StorageMaintenanceRequest {
+    /// 目标合约（为空时全局执行）
+    pub instrument_id: Option<String>,
+    /// 是否等待执行完成（默认异步执行，返回任务 ID）
+    #[serde(default)]
+    pub wait: bool,
+}
+
+/// 手动触发 MemTable flush
+pub async fn flush_storage(
+    state: web::Data<AdminAppState>,
+    req: Option<web::Json<StorageMaintenanceRequest>>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let req = req.map(|r| r.into_inner()).unwrap_or_default();
+    log::info!("POST /api/admin/storage/flush: {:?}", req);
+
+    run_storage_maintenance(&state.storage_maintenance, MaintenanceKind::Flush, req).await
+}
+
+/// 手动触发 compaction
+pub async fn compact_storage(
+    state: web::Data<AdminAppState>,
+    req: Option<web::Json<StorageMaintenanceRequest>>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let req = req.map(|r| r.into_inner()).unwrap_or_default();
+    log::info!("POST /api/admin/storage/compact: {:?}", req);
+
+    run_storage_maintenance(&state.storage_maintenance, MaintenanceKind::Compact, req).await
+}
+
+async fn run_storage_maintenance(
+    maintenance: &Arc<StorageMaintenance>,
+    kind: MaintenanceKind,
+    req: StorageMaintenanceRequest,
+) -> Result<HttpResponse, actix_web::Error> {
+    let task = match maintenance.submit(kind, req.instrument_id) {
+        Ok(task) => task,
+        Err(e) => return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(e))),
+    };
+
+    if !req.wait {
+        maintenance.spawn(task.task_id.clone());
+        return Ok(HttpResponse::Accepted().json(ApiResponse::success(task)));
+    }
+
+    let runner = maintenance.clone();
+    let task = web::block(move || runner.run(&task.task_id))
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(task)))
+}
+
+/// 查询存储运维任务
+pub async fn get_storage_task(
+    state: web::Data<AdminAppState>,
+    task_id: web::Path<String>,
+) -> Result<HttpResponse, actix_web::Error> {
+    match state.storage_maintenance.get_task(&task_id) {
+        Some(task) => Ok(HttpResponse::Ok().json(ApiResponse::success(task))),
+        None => Ok(
+            HttpResponse::NotFound().json(ApiResponse::<()>::error(format!(
+                "Storage task not found: {}",
+                task_id
+            ))),
+        ),
+    }
+}
+
+/// 最近的存储运维任务
+pub async fn list_storage_tasks(
+    state: web::Data<AdminAppState>,
+) -> Result<HttpResponse, actix_web::Error> {
+    Ok(HttpResponse::Ok().json(ApiResponse::success(state.storage_maintenance.list_tasks())))
+}
+
+// ============================================================================
+// 批量开户
+// ============================================================================
+
+/// 单次批量开户的最大行数
+const MAX_BATCH_OPEN_ROWS: usize = 10_000;
+
+/// 批量开户查询参数
+#[derive(Debug, Default, Deserialize)]
+pub struct BatchOpenAccountsQuery {
+    /// 仅校验，不实际开户
+    #[serde(default)]
+    pub dry_run: bool,
+    /// 操作员（写入审计日志）
+    pub operator_id: Option<String>,
+}
+
+/// 批量开户行（JSON 数组元素 / CSV 行，CSV 表头同字段名）
+#[derive(Debug, Clone, Deserialize)]
+pub struct BatchOpenAccountRow {
+    pub user_name: String,
+    pub init_cash: f64,
+    /// individual / institutional / market_maker，默认 individual
+    #[serde(default = "default_batch_account_type")]
+    pub account_type: String,
+    /// 账户名称（为空时使用 user_name）
+    #[serde(default)]
+    pub account_name: Option<String>,
+}
+
+fn default_batch_account_type() -> String {
+    "individual".to_string()
+}
+
+/// 批量开户逐行结果
+#[derive(Debug, Serialize)]
+pub struct BatchOpenRowResult {
+    /// 行号（从 1 开始，不含 CSV 表头）
+    pub row: usize,
+    pub user_name: String,
+    pub success: bool,
+    /// 开户成功的账户ID（dry_run 时为空）
+    pub account_id: Option<String>,
+    pub error: Option<String>,
+}
+
+/// 批量开户结果
+#[derive(Debug, Serialize)]
+pub struct BatchOpenAccountsResponse {
+    pub dry_run: bool,
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub elapsed_ms: u64,
+    pub results: Vec<BatchOpenRowResult>,
+}
+
+/// 解析批量开户请求体：`Content-Type` 为 CSV 时按 CSV 解析，否则按 JSON 数组解析
+///
+/// 单行格式错误只记为该行失败；请求体整体无法解析时返回错误
+fn parse_batch_open_rows(
+    body: &[u8],
+    is_csv: bool,
+) -> Result<Vec<Result<BatchOpenAccountRow, String>>, String> {
+    if !is_csv {
+        let rows: Vec<serde_json::Value> =
+            serde_json::from_slice(body).map_err(|e| format!("Invalid JSON array: {}", e))?;
+        return Ok(rows
+            .into_iter()
+            .map(|v| serde_json::from_value(v).map_err(|e| e.to_string()))
+            .collect());
+    }
+
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(body);
+    let headers = reader
+        .headers()
+        .map_err(|e| format!("Invalid CSV header: {}", e))?;
+    for column in ["user_name", "init_cash"] {
+        if !headers.iter().any(|h| h == column) {
+            return Err(format!("CSV missing required column {}", column));
+        }
+    }
+    Ok(reader
+        .deserialize::<BatchOpenAccountRow>()
+        .map(|r| r.map_err(|e| e.to_string()))
+        .collect())
+}
+
+fn parse_batch_account_type(value: &str) -> Result<AccountType, String> {
+    match value {
+        "individual" => Ok(AccountType::Individual),
+        "institutional" => Ok(AccountType::Institutional),
+        "market_maker" => Ok(AccountType::MarketMaker),
+        other => Err(format!("Invalid account type: {}", other)),
+    }
+}
+
+/// 将批量开户行转换为开户请求（解析账户类型，按用户名查找用户）
+fn prepare_batch_open_request(
+    row: BatchOpenAccountRow,
+    user_mgr: Option<&Arc<UserManager>>,
+) -> Result<OpenAccountRequest, String> {
+    let account_type = parse_batch_account_type(&row.account_type)?;
+    // 未配置 UserManager 时 user_name 直接作为 user_id
+    let user_id = match user_mgr {
+        Some(user_mgr) => {
+            user_mgr
+                .get_user_by_username(&row.user_name)
+                .map_err(|e| e.to_string())?
+                .user_id
+        }
+        None => row.user_name.clone(),
+    };
+    Ok(OpenAccountRequest {
+        user_id,
+        account_id: None,
+        account_name: row.account_name.unwrap_or(row.user_name),
+        init_cash: row.init_cash,
+        account_type,
+    })
+}
+
+/// 批量开户并入金（请求体为 JSON 数组或 CSV）
+///
+/// 逐行返回结果，部分失败不影响其它行；`dry_run=true` 时只校验。
+/// 写一条汇总审计日志，并为每行写一条明细。
+pub async fn batch_open_accounts(
+    state: web::Data<AdminAppState>,
+    http_req: HttpRequest,
+    query: web::Query<BatchOpenAccountsQuery>,
+    body: web::Bytes,
+) -> Result<HttpResponse, actix_web::Error> {
+    let started = Instant::now();
+    let query = query.into_inner();
+    let is_csv = http_req
+        .headers()
+        .get(actix_web::http::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("csv"));
+    log::info!(
+        "POST /api/admin/accounts/batch-open: {} bytes, csv={}, dry_run={}",
+        body.len(),
+        is_csv,
+        query.dry_run
+    );
+
+    let rows = match parse_batch_open_rows(&body, is_csv) {
+        Ok(rows) => rows,
+        Err(e) => return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(e))),
+    };
+    if rows.len() > MAX_BATCH_OPEN_ROWS {
+        return Ok(
+            HttpResponse::BadRequest().json(ApiResponse::<()>::error(format!(
+                "Too many rows: {} (max {})",
+                rows.len(),
+                MAX_BATCH_OPEN_ROWS
+            ))),
+        );
+    }
+
+    // 行解析与用户名解析失败的行不进入开户
+    let mut results: Vec<BatchOpenRowResult> = Vec::with_capacity(rows.len());
+    let mut pending = Vec::new();
+    let mut requests = Vec::new();
+    let user_mgr = state.account_mgr.user_manager();
+    for (i, row) in rows.into_iter().enumerate() {
+        let (user_name, prepared) = match row {
+            Ok(row) => (
+                row.user_name.clone(),
+                prepare_batch_open_request(row, user_mgr),
+            ),
+            Err(e) => (String::new(), Err(e)),
+        };
+        let error = match prepared {
+            Ok(req) => {
+                pending.push(i);
+                requests.push(req);
+                None
+            }
+            Err(e) => Some(e),
+        };
+        results.push(BatchOpenRowResult {
+            row: i + 1,
+            user_name,
+            success: false,
+            account_id: None,
+            error,
+        });
+    }
+
+    let opened = state
+        .account_mgr
+        .open_accounts_batch(requests, query.dry_run);
+    for (i, result) in pending.into_iter().zip(opened) {
+        match result {
+            Ok(account_id) => {
+                results[i].success = true;
+                results[i].account_id = account_id;
+            }
+            Err(e) => results[i].error = Some(e.to_string()),
+        }
+    }
+
+    let succeeded = results.iter().filter(|r| r.success).count();
+    let response = BatchOpenAccountsResponse {
+        dry_run: query.dry_run,
+        total: results.len(),
+        succeeded,
+        failed: results.len() - succeeded,
+        elapsed_ms: started.elapsed().as_millis() as u64,
+        results,
+    };
+
+    let operator = query.operator_id.unwrap_or_else(|| "admin".to_string());
+    let action = if response.dry_run {
+        "批量开户校验"
+    } else {
+        "批量开户"
+    };
+    log_audit(
+        "BATCH".to_string(),
+        operator.clone(),
+        AuditLogType::AccountOpen,
+        action.to_string(),
+        format!(
+            "total={}, succeeded={}, failed={}",
+            response.total, response.succeeded, response.failed
+        ),
+        None,
+        if response.failed == 0 {
+            AuditResult::Success
+        } else {
+            AuditResult::Failed
+        },
+    );
+    for row in &response.results {
+        log_audit(
+            row.account_id.clone().unwrap_or_default(),
+            operator.clone(),
+            AuditLogType::AccountOpen,
+            action.to_string(),
+            format!(
+                "row={}, user_name={}, error={}",
+                row.row,
+                row.user_name,
+                row.error.as_deref().unwrap_or("")
+            ),
+            None,
+            if row.success {
+                AuditResult::Success
+            } else {
+                AuditResult::Failed
+            },
+        );
+    }
+
+    log::info!(
+        "Batch open accounts: total={}, succeeded={}, failed={}, dry_run={}, elapsed={}ms",
+        response.total,
+        response.succeeded,
+        response.failed,
+        response.dry_run,
+        response.elapsed_ms
+    );
+    Ok(HttpResponse::Ok().json(ApiResponse::success(response)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -354,4 +1166,43 @@ mod tests {
         assert!(!error_response.success);
         assert!(error_response.error.is_some());
     }
+
+    #[test]
+    fn test_parse_batch_open_rows_json() {
+        let body = br#"[
+            {"user_name": "alice", "init_cash": 100000.0, "account_type": "institutional"},
+            {"user_name": "bob", "init_cash": "oops"},
+            {"user_name": "carol", "init_cash": 50000.0}
+        ]"#;
+        let rows = parse_batch_open_rows(body, false).unwrap();
+
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].as_ref().unwrap().account_type, "institutional");
+        assert!(rows[1].is_err());
+        assert_eq!(rows[2].as_ref().unwrap().account_type, "individual");
+        assert!(parse_batch_open_rows(b"{}", false).is_err());
+    }
+
+    #[test]
+    fn test_parse_batch_open_rows_csv() {
+        let body = b"user_name,init_cash,account_type,account_name\n\
+alice,100000,individual,\n\
+bob,abc,individual,\n\
+carol,50000,market_maker,Carol MM\n";
+        let rows = parse_batch_open_rows(body, true).unwrap();
+
+        assert_eq!(rows.len(), 3);
+        let alice = rows[0].as_ref().unwrap();
+        assert_eq!(alice.init_cash, 100000.0);
+        assert!(alice.account_name.is_none());
+        assert!(rows[1].is_err());
+        assert_eq!(
+            rows[2].as_ref().unwrap().account_name.as_deref(),
+            Some("Carol MM")
+        );
+
+        assert!(parse_batch_open_rows(b"name,cash\nalice,1\n", true).is_err());
+        assert!(parse_batch_account_type("market_maker").is_ok());
+        assert!(parse_batch_account_type("vip").is_err());
+    }
 }
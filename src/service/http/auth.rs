@@ -6,10 +6,11 @@ use actix_web::{web, HttpResponse, Result};
 use serde::Deserialize;
 use std::sync::Arc;
 
+use super::account_admin::log_audit;
 use super::handlers::AppState;
-use super::models::ApiResponse;
+use super::models::{ApiResponse, AuditLogType, AuditResult};
 use crate::core::account_ext::{AccountType, OpenAccountRequest as CoreOpenAccountRequest};
-use crate::user::{UserLoginRequest, UserRegisterRequest, UserRole};
+use crate::user::{personal_identifiers, UserLoginRequest, UserRegisterRequest, UserRole};
 
 /// 用户注册 @yutiansut @quantaxis
 /// 注册成功后自动创建一个默认交易账户
@@ -273,3 +274,91 @@ pub async fn make_admin(
         }
     }
 }
+
+/// 用户注销请求
+#[derive(Debug, Deserialize, Default)]
+pub struct EraseUserRequest {
+    /// 操作人（审计日志）
+    #[serde(default)]
+    pub operator_id: Option<String>,
+}
+
+/// 注销用户并匿名化个人信息（GDPR 式删除）
+/// POST /api/auth/user/{user_id}/erase
+///
+/// 1. 用户个人信息替换为不可逆摘要并写入匿名化 WAL 记录
+/// 2. 名下账户名称替换为匿名用户名（账户与交易数据保留）
+/// 3. 配置了 OLAP 转换时，一次性重写 Parquet 中的历史用户标识
+pub async fn erase_user(
+    user_id: web::Path<String>,
+    req: Option<web::Json<EraseUserRequest>>,
+    state: web::Data<Arc<AppState>>,
+) -> Result<HttpResponse> {
+    let user_id = user_id.into_inner();
+    let operator = req
+        .and_then(|r| r.into_inner().operator_id)
+        .unwrap_or_else(|| "admin".to_string());
+
+    // 注销前记录原始标识，用于重写 OLAP 历史数据
+    let identifiers: Vec<String> = match state.user_mgr.get_user(&user_id) {
+        Ok(user) => personal_identifiers(&user),
+        Err(e) => {
+            return Ok(HttpResponse::NotFound().json(ApiResponse::<()>::error(404, e.to_string())));
+        }
+    };
+
+    let mut report = match state.user_mgr.erase_user(&user_id) {
+        Ok(report) => report,
+        Err(e) => {
+            log::error!("Failed to erase user {}: {:?}", user_id, e);
+            log_audit(
+                user_id.clone(),
+                operator,
+                AuditLogType::UserErasure,
+                "用户注销".to_string(),
+                e.to_string(),
+                None,
+                AuditResult::Failed,
+            );
+            return Ok(
+                HttpResponse::BadRequest().json(ApiResponse::<()>::error(400, e.to_string()))
+            );
+        }
+    };
+
+    report.retained_account_ids = state
+        .account_mgr
+        .anonymize_user_accounts(&user_id, &report.anonymized_username);
+
+    if let Some(conversion_mgr) = state.conversion_mgr.clone() {
+        let replacement = report.anonymized_username.clone();
+        match web::block(move || {
+            conversion_mgr
+                .lock()
+                .anonymize_olap(&identifiers, &replacement)
+        })
+        .await
+        {
+            Ok(olap_report) => report.olap_rewrite = Some(olap_report),
+            Err(e) => log::error!("OLAP anonymize task for user {} failed: {}", user_id, e),
+        }
+    }
+
+    log_audit(
+        user_id.clone(),
+        operator,
+        AuditLogType::UserErasure,
+        "用户注销".to_string(),
+        format!(
+            "匿名用户名: {}, 字段: {:?}, 保留账户: {}",
+            report.anonymized_username,
+            report.erased_fields,
+            report.retained_account_ids.len()
+        ),
+        None,
+        AuditResult::Success,
+    );
+    log::info!("User {} erased as {}", user_id, report.anonymized_username);
+
+    Ok(HttpResponse::Ok().json(ApiResponse::success(report)))
+}
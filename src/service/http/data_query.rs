@@ -12,8 +12,14 @@ use actix_web::{web, HttpResponse};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use std::collections::HashMap;
-use crate::service::http::handlers::AppState;
+use std::io::Write;
+use crate::core::QA_Account;
+use crate::exchange::AccountManager;
+use crate::matching::trade_recorder::TradeRecorder;
+use crate::service::async_task::{AsyncTaskManager, TaskContext, TaskOutput};
+use crate::service::http::handlers::{AppState, UserTradeView};
 use crate::storage::wal::record::{WalRecord, WalEntry};
+use crate::utils::timestamp::millis_to_nanos;
 use rkyv::Deserialize as RkyvDeserialize;
 
 // ==================== 请求/响应结构 ====================
@@ -27,6 +33,18 @@ pub struct HistoryTickQuery {
     pub limit: Option<usize>,
 }
 
+/// 订单簿历史快照查询请求
+///
+/// - `timestamp`: 查询该时刻的盘口（不晚于该时刻的最近快照）
+/// - `start_time` / `end_time` / `limit`: 查询时间段内的快照序列（盘口回放）
+#[derive(Debug, Deserialize)]
+pub struct OrderBookHistoryQuery {
+    pub timestamp: Option<i64>,
+    pub start_time: Option<i64>,
+    pub end_time: Option<i64>,
+    pub limit: Option<usize>,
+}
+
 /// 交易统计请求
 #[derive(Debug, Deserialize)]
 pub struct TradeStatisticsQuery {
@@ -95,6 +113,24 @@ pub struct TickDataItem {
     pub timestamp: i64,
 }
 
+/// 盘口档位
+#[derive(Debug, Serialize)]
+pub struct OrderBookLevelItem {
+    pub price: f64,
+    pub volume: i64,
+}
+
+/// 订单簿历史快照
+#[derive(Debug, Serialize)]
+pub struct OrderBookSnapshotItem {
+    pub instrument_id: String,
+    pub datetime: String,
+    pub last_price: f64,
+    pub bids: Vec<OrderBookLevelItem>,
+    pub asks: Vec<OrderBookLevelItem>,
+    pub timestamp: i64,
+}
+
 /// K线数据
 #[derive(Debug, Serialize)]
 pub struct KlineDataItem {
@@ -228,6 +264,8 @@ pub struct SettlementStatement {
     pub commission: f64,
     pub close_profit: f64,
     pub position_profit: f64,
+    /// 结算计息（负数为收取，未结算时为 0）
+    pub interest: f64,
     pub balance: f64,
     pub margin: f64,
     pub available: f64,
@@ -268,32 +306,25 @@ fn extract_string(arr: &[u8]) -> String {
 
 /// 纳秒时间戳转日期时间字符串
 fn timestamp_to_datetime(ts: i64) -> String {
-    let secs = ts / 1_000_000_000;
-    let nanos = (ts % 1_000_000_000) as u32;
-    if let Some(dt) = chrono::DateTime::from_timestamp(secs, nanos) {
-        dt.format("%Y-%m-%d %H:%M:%S%.3f").to_string()
-    } else {
-        format!("{}", ts)
-    }
+    crate::utils::time_service::time_service()
+        .to_local_nanos(ts)
+        .format("%Y-%m-%d %H:%M:%S%.3f")
+        .to_string()
 }
 
-/// 从纳秒时间戳提取时间段键值 @yutiansut @quantaxis
+/// 从纳秒时间戳提取时间段键值（按交易所本地时间分组） @yutiansut @quantaxis
 /// 支持的分组粒度: hour, day, week, month
 fn extract_period_key(timestamp_ns: i64, group_by: &str) -> String {
-    let secs = timestamp_ns / 1_000_000_000;
-    if let Some(dt) = chrono::DateTime::from_timestamp(secs, 0) {
-        match group_by {
-            "hour" => dt.format("%Y-%m-%d %H:00").to_string(),
-            "day" => dt.format("%Y-%m-%d").to_string(),
-            "week" => {
-                // ISO 周格式: YYYY-Www
-                dt.format("%G-W%V").to_string()
-            }
-            "month" => dt.format("%Y-%m").to_string(),
-            _ => dt.format("%Y-%m-%d").to_string(), // 默认按日
+    let dt = crate::utils::time_service::time_service().to_local_nanos(timestamp_ns);
+    match group_by {
+        "hour" => dt.format("%Y-%m-%d %H:00").to_string(),
+        "day" => dt.format("%Y-%m-%d").to_string(),
+        "week" => {
+            // ISO 周格式: YYYY-Www
+            dt.format("%G-W%V").to_string()
         }
-    } else {
-        "unknown".to_string()
+        "month" => dt.format("%Y-%m").to_string(),
+        _ => dt.format("%Y-%m-%d").to_string(), // 默认按日
     }
 }
 
@@ -325,6 +356,48 @@ fn string_to_period(s: &str) -> i32 {
     }
 }
 
+/// 委托导出行（同步导出与异步导出任务共用）
+fn order_export_rows(account: &QA_Account) -> Vec<serde_json::Value> {
+    account.dailyorders.values()
+        .map(|o| serde_json::json!({
+            "order_id": o.order_id,
+            "instrument_id": o.instrument_id,
+            "direction": o.direction,
+            "offset": o.offset,
+            "price": o.limit_price,
+            "volume": o.volume_orign,
+            "volume_left": o.volume_left,
+            "status": o.status,
+            "insert_time": o.insert_date_time
+        }))
+        .collect()
+}
+
+/// 持仓导出行（需要写锁调用 qars 的 volume_long()/volume_short()）
+fn position_export_rows(account: &mut QA_Account) -> Vec<serde_json::Value> {
+    account.hold.iter_mut()
+        .map(|(id, p)| {
+            let volume_long = p.volume_long();
+            let volume_short = p.volume_short();
+            let pnl_long = p.float_profit_long();
+            let pnl_short = p.float_profit_short();
+            serde_json::json!({
+                "instrument_id": id,
+                "exchange_id": p.exchange_id,
+                "volume_long": volume_long,
+                "volume_short": volume_short,
+                "open_price_long": p.open_price_long,
+                "open_price_short": p.open_price_short,
+                "last_price": p.lastest_price,
+                "margin_long": p.margin_long,
+                "margin_short": p.margin_short,
+                "pnl_long": pnl_long,
+                "pnl_short": pnl_short
+            })
+        })
+        .collect()
+}
+
 // ==================== API 处理函数 ====================
 
 /// 查询历史Tick数据（从WAL真实读取）
@@ -340,15 +413,41 @@ pub async fn query_history_ticks(
 
     let mut ticks: Vec<TickDataItem> = Vec::new();
 
-    // 从 market_data_storage WAL 读取真实数据
+    // 从 market_data_storage 读取真实数据（分区存储按合约只扫描对应分区）
+    // 历史回放属于分析查询：受存储分析通道配额限制，在阻塞线程池中执行
     if let Some(ref storage) = state.market_data_storage {
-        // 回放WAL获取TickData记录
-        let wal_mgr = storage.get_wal_manager();
+        let storage = storage.clone();
+        let instrument = instrument_id.clone();
+        let scan = web::block(move || {
+            let _permit = storage.query_gate().acquire()?;
+            let records = if instrument == "*" {
+                storage.scan_range(start_time, end_time)
+            } else {
+                storage.scan_range_instrument(&instrument, start_time, end_time)
+            };
+            Ok::<_, crate::storage::hybrid::QueryGateError>(records.unwrap_or_default())
+        })
+        .await;
 
-        let _ = wal_mgr.replay(|entry: WalEntry| {
-            // 检查时间范围
-            if entry.timestamp < start_time || entry.timestamp > end_time {
-                return Ok(());
+        let records = match scan {
+            Ok(Ok(records)) => records,
+            Ok(Err(e)) => {
+                return HttpResponse::TooManyRequests().json(serde_json::json!({
+                    "success": false,
+                    "error": e.to_string()
+                }));
+            }
+            Err(e) => {
+                return HttpResponse::InternalServerError().json(serde_json::json!({
+                    "success": false,
+                    "error": format!("History tick query failed: {}", e)
+                }));
+            }
+        };
+
+        for (_, _, record) in records {
+            if ticks.len() >= limit {
+                break;
             }
 
             // 只处理TickData记录
@@ -359,25 +458,23 @@ pub async fn query_history_ticks(
                 ask_price,
                 volume,
                 timestamp
-            } = entry.record {
+            } = record {
                 let inst_str = extract_string(&inst_id);
 
                 // 过滤合约
-                if (inst_str == *instrument_id || instrument_id == "*")
-                    && ticks.len() < limit {
-                        ticks.push(TickDataItem {
-                            instrument_id: inst_str,
-                            datetime: timestamp_to_datetime(timestamp),
-                            last_price,
-                            volume,
-                            bid_price,
-                            ask_price,
-                            timestamp,
-                        });
-                    }
+                if inst_str == *instrument_id || instrument_id == "*" {
+                    ticks.push(TickDataItem {
+                        instrument_id: inst_str,
+                        datetime: timestamp_to_datetime(timestamp),
+                        last_price,
+                        volume,
+                        bid_price,
+                        ask_price,
+                        timestamp,
+                    });
+                }
             }
-            Ok(())
-        });
+        }
     }
 
     // 按时间排序（最新在前）
@@ -395,6 +492,108 @@ pub async fn query_history_ticks(
     }))
 }
 
+/// 快照记录转响应结构（过滤空档位）
+fn snapshot_to_item(timestamp: i64, record: &WalRecord) -> Option<OrderBookSnapshotItem> {
+    if let WalRecord::OrderBookSnapshot {
+        instrument_id,
+        bids,
+        asks,
+        last_price,
+        ..
+    } = record
+    {
+        let levels = |side: &[(f64, i64); 10]| {
+            side.iter()
+                .filter(|(_, volume)| *volume > 0)
+                .map(|(price, volume)| OrderBookLevelItem {
+                    price: *price,
+                    volume: *volume,
+                })
+                .collect()
+        };
+        Some(OrderBookSnapshotItem {
+            instrument_id: extract_string(instrument_id),
+            datetime: timestamp_to_datetime(timestamp),
+            last_price: *last_price,
+            bids: levels(bids),
+            asks: levels(asks),
+            timestamp,
+        })
+    } else {
+        None
+    }
+}
+
+/// 查询订单簿历史快照（某时刻的盘口 / 时间段回放）
+/// @yutiansut @quantaxis
+///
+/// GET /api/market/orderbook/{instrument_id}/history?timestamp=
+/// GET /api/market/orderbook/{instrument_id}/history?start_time=&end_time=&limit=
+pub async fn query_orderbook_history(
+    instrument_id: web::Path<String>,
+    query: web::Query<OrderBookHistoryQuery>,
+    state: web::Data<Arc<AppState>>,
+) -> HttpResponse {
+    let instrument_id = instrument_id.into_inner();
+
+    let storage = match state.market_data_storage {
+        Some(ref storage) => storage,
+        None => {
+            return HttpResponse::ServiceUnavailable().json(serde_json::json!({
+                "success": false,
+                "error": "Market data storage not available"
+            }));
+        }
+    };
+
+    // 单时刻查询
+    if let Some(timestamp) = query.timestamp {
+        return match storage.orderbook_snapshot_at(&instrument_id, timestamp) {
+            Ok(Some((ts, record))) => HttpResponse::Ok().json(serde_json::json!({
+                "success": true,
+                "data": snapshot_to_item(ts, &record)
+            })),
+            Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
+                "success": false,
+                "error": format!("No orderbook snapshot for {} before {}", instrument_id, timestamp)
+            })),
+            Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "error": format!("Failed to query orderbook history: {}", e)
+            })),
+        };
+    }
+
+    // 时间段查询（盘口回放）
+    let start_time = query.start_time.unwrap_or(0);
+    let end_time = query.end_time.unwrap_or(i64::MAX);
+    let limit = query.limit.unwrap_or(1000);
+
+    match storage.orderbook_snapshots_between(&instrument_id, start_time, end_time, limit) {
+        Ok(records) => {
+            let snapshots: Vec<OrderBookSnapshotItem> = records
+                .iter()
+                .filter_map(|(ts, record)| snapshot_to_item(*ts, record))
+                .collect();
+
+            HttpResponse::Ok().json(serde_json::json!({
+                "success": true,
+                "data": {
+                    "instrument_id": instrument_id,
+                    "count": snapshots.len(),
+                    "start_time": start_time,
+                    "end_time": end_time,
+                    "snapshots": snapshots
+                }
+            }))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "success": false,
+            "error": format!("Failed to query orderbook history: {}", e)
+        })),
+    }
+}
+
 /// 查询批量K线数据（从WAL真实读取）
 /// @yutiansut @quantaxis
 pub async fn query_batch_klines(
@@ -436,7 +635,7 @@ pub async fn query_batch_klines(
                 }
 
                 // 检查时间范围（K线时间戳是毫秒）
-                let ts_ns = kline_timestamp * 1_000_000; // 转换为纳秒
+                let ts_ns = millis_to_nanos(kline_timestamp);
                 if ts_ns < start_time || ts_ns > end_time {
                     return Ok(());
                 }
@@ -451,7 +650,7 @@ pub async fn query_batch_klines(
                         if klines.len() < limit {
                             klines.push(KlineDataItem {
                                 instrument_id: inst_str.clone(),
-                                datetime: timestamp_to_datetime(kline_timestamp * 1_000_000),
+                                datetime: timestamp_to_datetime(millis_to_nanos(kline_timestamp)),
                                 open,
                                 high,
                                 low,
@@ -468,7 +667,7 @@ pub async fn query_batch_klines(
                         // 新合约
                         result.insert(inst_str.clone(), vec![KlineDataItem {
                             instrument_id: inst_str.clone(),
-                            datetime: timestamp_to_datetime(kline_timestamp * 1_000_000),
+                            datetime: timestamp_to_datetime(millis_to_nanos(kline_timestamp)),
                             open,
                             high,
                             low,
@@ -740,7 +939,7 @@ pub async fn get_pnl_analysis(
         account_id: account_id.clone(),
         period: format!("{} ~ {}",
             query.start_date.clone().unwrap_or_else(|| "2024-01-01".to_string()),
-            query.end_date.clone().unwrap_or_else(|| chrono::Local::now().format("%Y-%m-%d").to_string())
+            query.end_date.clone().unwrap_or_else(|| crate::utils::time_service::now_local().format("%Y-%m-%d").to_string())
         ),
         realized_pnl,
         unrealized_pnl,
@@ -929,6 +1128,16 @@ pub async fn get_settlement_statement(
             });
         }
 
+        // 当日结算计息
+        let interest = state
+            .settlement_engine
+            .get_account_settlements(account_id)
+            .iter()
+            .rev()
+            .find(|s| s.date == query.date)
+            .map(|s| s.interest)
+            .unwrap_or(0.0);
+
         // 访问账户数据（在循环之后）
         let acc = &account_write.accounts;
         let statement = SettlementStatement {
@@ -940,6 +1149,7 @@ pub async fn get_settlement_statement(
             commission: acc.commission,
             close_profit: acc.close_profit,
             position_profit: acc.position_profit,
+            interest,
             balance: acc.balance,
             margin: acc.margin,
             available: acc.available,
@@ -975,19 +1185,7 @@ pub async fn export_data(
 
         match data_type.as_str() {
             "orders" => {
-                let orders: Vec<serde_json::Value> = account_read.dailyorders.values()
-                    .map(|o| serde_json::json!({
-                        "order_id": o.order_id,
-                        "instrument_id": o.instrument_id,
-                        "direction": o.direction,
-                        "offset": o.offset,
-                        "price": o.limit_price,
-                        "volume": o.volume_orign,
-                        "volume_left": o.volume_left,
-                        "status": o.status,
-                        "insert_time": o.insert_date_time
-                    }))
-                    .collect();
+                let orders = order_export_rows(&account_read);
 
                 if format == "csv" {
                     let mut csv = "order_id,instrument_id,direction,offset,price,volume,volume_left,status,insert_time\n".to_string();
@@ -1072,27 +1270,7 @@ pub async fn export_data(
                 // @yutiansut @quantaxis: 释放读锁，获取写锁以调用 qars 的 volume_long()/volume_short()
                 drop(account_read);
                 let mut account_write = account.write();
-                let positions: Vec<serde_json::Value> = account_write.hold.iter_mut()
-                    .map(|(id, p)| {
-                        let volume_long = p.volume_long();
-                        let volume_short = p.volume_short();
-                        let pnl_long = p.float_profit_long();
-                        let pnl_short = p.float_profit_short();
-                        serde_json::json!({
-                            "instrument_id": id,
-                            "exchange_id": p.exchange_id,
-                            "volume_long": volume_long,
-                            "volume_short": volume_short,
-                            "open_price_long": p.open_price_long,
-                            "open_price_short": p.open_price_short,
-                            "last_price": p.lastest_price,
-                            "margin_long": p.margin_long,
-                            "margin_short": p.margin_short,
-                            "pnl_long": pnl_long,
-                            "pnl_short": pnl_short
-                        })
-                    })
-                    .collect();
+                let positions = position_export_rows(&mut account_write);
 
                 if format == "csv" {
                     let mut csv = "instrument_id,exchange_id,volume_long,volume_short,open_price_long,open_price_short,last_price,margin_long,margin_short,pnl_long,pnl_short\n".to_string();
@@ -1142,6 +1320,189 @@ pub async fn export_data(
     }))
 }
 
+// ==================== 异步导出任务 @yutiansut @quantaxis ====================
+
+/// 异步导出任务类型
+pub const EXPORT_TASK_KIND: &str = "data_export";
+
+const ORDER_EXPORT_COLUMNS: &[&str] = &[
+    "order_id",
+    "instrument_id",
+    "direction",
+    "offset",
+    "price",
+    "volume",
+    "volume_left",
+    "status",
+    "insert_time",
+];
+const TRADE_EXPORT_COLUMNS: &[&str] = &[
+    "trade_id",
+    "order_id",
+    "opposite_order_id",
+    "instrument_id",
+    "direction",
+    "price",
+    "volume",
+    "is_taker",
+    "trading_day",
+    "trade_time",
+];
+const POSITION_EXPORT_COLUMNS: &[&str] = &[
+    "instrument_id",
+    "exchange_id",
+    "volume_long",
+    "volume_short",
+    "open_price_long",
+    "open_price_short",
+    "last_price",
+    "margin_long",
+    "margin_short",
+    "pnl_long",
+    "pnl_short",
+];
+
+/// 交易日归一化为 YYYYMMDD，便于 "2024-03-15" 与 "20240315" 混用比较
+fn normalize_trading_day(day: &str) -> String {
+    day.replace('-', "")
+}
+
+/// 历史成交导出行：从成交记录器读取（不限当日），按交易日区间过滤、按成交时间排序
+fn trade_export_rows(
+    trade_recorder: &TradeRecorder,
+    account_id: &str,
+    start_date: Option<&str>,
+    end_date: Option<&str>,
+) -> Vec<serde_json::Value> {
+    let start = start_date.map(normalize_trading_day);
+    let end = end_date.map(normalize_trading_day);
+    let mut trades: Vec<_> = trade_recorder
+        .get_trades_by_user(account_id)
+        .into_iter()
+        .filter(|t| {
+            let day = normalize_trading_day(&t.trading_day);
+            !matches!(&start, Some(s) if &day < s) && !matches!(&end, Some(e) if &day > e)
+        })
+        .collect();
+    trades.sort_by_key(|t| t.timestamp);
+    trades
+        .iter()
+        .map(|t| {
+            let view = UserTradeView::from_trade_record(t, account_id);
+            serde_json::json!({
+                "trade_id": view.trade_id,
+                "order_id": view.user_order_id,
+                "opposite_order_id": view.opposite_order_id,
+                "instrument_id": view.instrument_id,
+                "direction": view.user_direction,
+                "price": view.price,
+                "volume": view.volume,
+                "is_taker": view.is_taker,
+                "trading_day": view.trading_day,
+                "trade_time": timestamp_to_datetime(view.timestamp)
+            })
+        })
+        .collect()
+}
+
+/// CSV 单元格（字符串原样输出，含逗号/引号时加引号转义）
+fn csv_cell(value: &serde_json::Value) -> String {
+    let raw = match value {
+        serde_json::Value::Null => return String::new(),
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    if raw.contains([',', '"', '\n']) {
+        format!("\"{}\"", raw.replace('"', "\"\""))
+    } else {
+        raw
+    }
+}
+
+/// 注册异步导出任务执行器
+///
+/// 参数同 `DataExportRequest`：trades 从成交记录器按 start_date/end_date（交易日）导出历史成交，
+/// orders/positions 导出账户当前委托与持仓；format 为 csv 或 json（JSON Lines）
+pub fn register_export_task(
+    task_manager: &AsyncTaskManager,
+    account_mgr: Arc<AccountManager>,
+    trade_recorder: Arc<TradeRecorder>,
+) {
+    task_manager.register_handler(
+        EXPORT_TASK_KIND,
+        Arc::new(move |ctx: &TaskContext, out: &mut dyn Write| {
+            let req: DataExportRequest = serde_json::from_value(ctx.params().clone())
+                .map_err(|e| format!("Invalid export params: {}", e))?;
+            if req.account_id != ctx.user_id()
+                && account_mgr.get_account_owner(&req.account_id).as_deref() != Some(ctx.user_id())
+            {
+                return Err(format!(
+                    "Account {} does not belong to {}",
+                    req.account_id,
+                    ctx.user_id()
+                ));
+            }
+
+            let (columns, rows) = match req.data_type.as_str() {
+                "trades" => (
+                    TRADE_EXPORT_COLUMNS,
+                    trade_export_rows(
+                        &trade_recorder,
+                        &req.account_id,
+                        req.start_date.as_deref(),
+                        req.end_date.as_deref(),
+                    ),
+                ),
+                "orders" | "positions" => {
+                    let account = account_mgr
+                        .get_account(&req.account_id)
+                        .map_err(|e| e.to_string())?;
+                    if req.data_type == "orders" {
+                        (ORDER_EXPORT_COLUMNS, order_export_rows(&account.read()))
+                    } else {
+                        (
+                            POSITION_EXPORT_COLUMNS,
+                            position_export_rows(&mut account.write()),
+                        )
+                    }
+                }
+                other => return Err(format!("不支持的数据类型: {}", other)),
+            };
+            ctx.set_total(rows.len() as u64);
+
+            let csv = req.format.as_deref() == Some("csv");
+            let io_err = |e: std::io::Error| format!("Write export failed: {}", e);
+            if csv {
+                writeln!(out, "{}", columns.join(",")).map_err(io_err)?;
+            }
+            for (i, row) in rows.iter().enumerate() {
+                if ctx.is_cancelled() {
+                    return Err("cancelled".to_string());
+                }
+                if csv {
+                    let cells: Vec<String> = columns.iter().map(|c| csv_cell(&row[*c])).collect();
+                    writeln!(out, "{}", cells.join(",")).map_err(io_err)?;
+                } else {
+                    writeln!(out, "{}", row).map_err(io_err)?;
+                }
+                ctx.set_processed(i as u64 + 1);
+            }
+
+            Ok(if csv {
+                TaskOutput {
+                    extension: "csv".to_string(),
+                    content_type: "text/csv; charset=utf-8".to_string(),
+                }
+            } else {
+                TaskOutput {
+                    extension: "jsonl".to_string(),
+                    content_type: "application/x-ndjson".to_string(),
+                }
+            })
+        }),
+    );
+}
+
 /// 获取风险度统计（真实数据）
 /// @yutiansut @quantaxis
 pub async fn get_risk_statistics(
@@ -1345,8 +1706,8 @@ pub async fn get_market_overview(
             "total_positions": total_positions,
             "avg_balance": if !accounts.is_empty() { total_balance / accounts.len() as f64 } else { 0.0 },
             "market_status": "Trading",
-            "trading_day": chrono::Local::now().format("%Y-%m-%d").to_string(),
-            "update_time": chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string()
+            "trading_day": crate::utils::time_service::time_service().current_trading_day().format("%Y-%m-%d").to_string(),
+            "update_time": crate::utils::time_service::now_local().format("%Y-%m-%d %H:%M:%S").to_string()
         }
     }))
 }
@@ -1762,4 +2123,99 @@ mod tests {
         assert_eq!(string_to_period("5min"), 5);
         assert_eq!(string_to_period("1h"), 8);
     }
+
+    /// 异步导出：一个月成交按交易日区间导出，提交 → 轮询 → 签名下载，非本人账户任务失败
+    #[test]
+    fn test_async_trade_export_end_to_end() {
+        use crate::core::account_ext::{AccountType, OpenAccountRequest};
+        use crate::service::async_task::{AsyncTaskConfig, AsyncTaskStatus};
+
+        let account_mgr = Arc::new(AccountManager::new());
+        account_mgr
+            .open_account(OpenAccountRequest {
+                user_id: "user_exp".to_string(),
+                account_id: Some("ACC_EXP".to_string()),
+                account_name: "Export".to_string(),
+                init_cash: 1_000_000.0,
+                account_type: AccountType::Individual,
+            })
+            .unwrap();
+        let trade_recorder = Arc::new(TradeRecorder::new());
+        for day in 1..=31 {
+            for i in 0..500 {
+                trade_recorder.record_trade(
+                    "IF2403".to_string(),
+                    "ACC_EXP".to_string(),
+                    "ACC_OTHER".to_string(),
+                    format!("B{}_{}", day, i),
+                    format!("S{}_{}", day, i),
+                    format!("B{}_{}", day, i),
+                    3800.0 + i as f64,
+                    1.0,
+                    format!("202403{:02}", day),
+                );
+            }
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let config = AsyncTaskConfig {
+            signing_secret: "test-signing-secret".to_string(),
+            ..Default::default()
+        };
+        let manager = Arc::new(AsyncTaskManager::new(config, dir.path()).unwrap());
+        register_export_task(&manager, account_mgr, trade_recorder);
+        manager.start();
+
+        let wait = |task_id: &str| {
+            let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+            loop {
+                let info = manager.get(task_id).unwrap();
+                if info.status.is_finished() {
+                    return info;
+                }
+                assert!(std::time::Instant::now() < deadline);
+                std::thread::sleep(std::time::Duration::from_millis(5));
+            }
+        };
+
+        let params = serde_json::json!({
+            "account_id": "ACC_EXP",
+            "data_type": "trades",
+            "format": "csv",
+            "start_date": "2024-03-02",
+            "end_date": "2024-03-31"
+        });
+        let task = manager.submit("user_exp", EXPORT_TASK_KIND, params.clone()).unwrap();
+        let info = wait(&task.task_id);
+        assert_eq!(info.status, AsyncTaskStatus::Succeeded);
+        assert_eq!(info.processed, 30 * 500);
+
+        let now = chrono::Utc::now().timestamp();
+        let url = manager.download_url(&task.task_id, now).unwrap();
+        let (expires, signature) = url
+            .split_once("?expires=")
+            .unwrap()
+            .1
+            .split_once("&signature=")
+            .unwrap();
+        let (path, _) = manager
+            .resolve_download(&task.task_id, expires.parse().unwrap(), signature, now)
+            .unwrap();
+        let content = std::fs::read_to_string(path).unwrap();
+        let mut lines = content.lines();
+        assert_eq!(lines.next(), Some(TRADE_EXPORT_COLUMNS.join(",").as_str()));
+        let first: Vec<&str> = lines.next().unwrap().split(',').collect();
+        assert_eq!(first[1], "B2_0");
+        assert_eq!(first[4], "BUY");
+        assert_eq!(first[7], "true");
+        assert_eq!(first[8], "20240302");
+        assert_eq!(lines.count(), 30 * 500 - 1);
+
+        // 他人账户不可导出
+        let denied = manager.submit("intruder", EXPORT_TASK_KIND, params).unwrap();
+        let info = wait(&denied.task_id);
+        assert_eq!(info.status, AsyncTaskStatus::Failed);
+        assert!(info.error.unwrap().contains("does not belong"));
+        assert!(manager.download_url(&denied.task_id, now).is_none());
+    }
 }
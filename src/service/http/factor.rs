@@ -0,0 +1,91 @@
+//! 因子历史 HTTP API
+//!
+//! 查询因子引擎落盘的历史因子序列（OLAP 分区 + 尚未转换的 WAL），以及批量回填因子值
+//!
+//! @yutiansut @quantaxis
+
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::handlers::AppState;
+use super::models::ApiResponse;
+use crate::storage::conversion::{FactorConversionReport, FactorPoint, FactorValue};
+
+/// 因子序列查询参数
+#[derive(Debug, Deserialize)]
+pub struct FactorHistoryQuery {
+    /// 起始时间（毫秒，含）
+    pub start: Option<i64>,
+    /// 结束时间（毫秒，含）
+    pub end: Option<i64>,
+}
+
+/// 因子序列
+#[derive(Debug, Serialize)]
+pub struct FactorHistoryData {
+    pub factor_name: String,
+    pub instrument_id: String,
+    pub values: Vec<FactorPoint>,
+}
+
+/// 因子回填请求
+#[derive(Debug, Deserialize)]
+pub struct FactorBackfillRequest {
+    pub values: Vec<FactorValue>,
+}
+
+fn factor_store_unavailable() -> HttpResponse {
+    HttpResponse::ServiceUnavailable().json(ApiResponse::<()>::error(
+        503,
+        "Factor store not enabled".to_string(),
+    ))
+}
+
+/// 查询历史因子序列
+///
+/// GET /api/factor/{factor_name}/{instrument_id}?start=&end=
+pub async fn get_factor_history(
+    path: web::Path<(String, String)>,
+    query: web::Query<FactorHistoryQuery>,
+    state: web::Data<Arc<AppState>>,
+) -> HttpResponse {
+    let (factor_name, instrument_id) = path.into_inner();
+    let Some(ref store) = state.factor_store else {
+        return factor_store_unavailable();
+    };
+
+    match store.query(&factor_name, &instrument_id, query.start, query.end) {
+        Ok(values) => HttpResponse::Ok().json(ApiResponse::success(FactorHistoryData {
+            factor_name,
+            instrument_id,
+            values,
+        })),
+        Err(e) => HttpResponse::BadRequest().json(ApiResponse::<()>::error(400, e)),
+    }
+}
+
+/// 批量回填因子值
+///
+/// POST /api/factor/backfill
+pub async fn backfill_factors(
+    req: web::Json<FactorBackfillRequest>,
+    state: web::Data<Arc<AppState>>,
+) -> HttpResponse {
+    let Some(ref store) = state.factor_store else {
+        return factor_store_unavailable();
+    };
+
+    let store = store.clone();
+    let values = req.into_inner().values;
+    match web::block(move || store.backfill(values)).await {
+        Ok(Ok(report)) => {
+            HttpResponse::Ok().json(ApiResponse::<FactorConversionReport>::success(report))
+        }
+        Ok(Err(e)) => HttpResponse::BadRequest().json(ApiResponse::<()>::error(400, e)),
+        Err(e) => HttpResponse::InternalServerError().json(ApiResponse::<()>::error(
+            500,
+            format!("Factor backfill failed: {}", e),
+        )),
+    }
+}
@@ -1,5 +1,6 @@
 //! HTTP API 请求处理器
 
+use actix_web::http::StatusCode;
 use actix_web::{web, HttpResponse, Result};
 use chrono::{DateTime, Utc};
 use log;
@@ -13,16 +14,25 @@ use super::models::{
     // Phase 11: 批量下单/条件单/订单修改 @yutiansut @quantaxis
     BatchOrderRequest, BatchOrderResponse, SingleOrderResult,
     BatchCancelRequest, BatchCancelResponse,
-    ModifyOrderRequest, CreateConditionalOrderRequest,
+    ModifyOrderRequest, CreateConditionalOrderRequest, CreateSpreadOrderRequest,
+    CreateAlgoOrderRequest, RollPositionRequest,
     // Phase 14: 入金流水记录 @yutiansut @quantaxis
     TransferRecord,
+    // 持仓批次明细 @yutiansut @quantaxis
+    PositionLotQuery, SetLotMethodRequest,
+    // 盈亏归因 @yutiansut @quantaxis
+    PnlAttributionParams,
 };
+use super::versioning::{ApiResource, ApiVersionContext};
 use crate::core::account_ext::{AccountType, OpenAccountRequest as CoreOpenAccountRequest};
+use crate::exchange::block_trade::{BlockTradeActionRequest, CreateBlockTradeRequest};
 use crate::exchange::order_router::{
     CancelOrderRequest as CoreCancelOrderRequest, SubmitOrderRequest as CoreSubmitOrderRequest,
 };
 use crate::exchange::settlement::AccountSettlement;
-use crate::exchange::{AccountManager, OrderRouter, SettlementEngine};
+use crate::exchange::{
+    AccountManager, InstrumentMark, OrderRouter, PnlAttributionQuery, SettlementEngine,
+};
 use crate::matching::trade_recorder::{TradeRecord, TradeRecorder};
 use crate::protocol::diff::snapshot::SnapshotManager;
 use crate::storage::conversion::ConversionManager;
@@ -42,12 +52,20 @@ pub struct AppState {
     pub market_data_storage: Option<Arc<crate::storage::hybrid::OltpHybridStorage>>,
     /// K线WAL管理器 用于历史K线查询 @yutiansut @quantaxis
     pub kline_wal_manager: Option<Arc<crate::storage::wal::WalManager>>,
+    /// 因子存储 用于历史因子序列查询与回填 @yutiansut @quantaxis
+    pub factor_store: Option<Arc<crate::storage::conversion::FactorStore>>,
     /// 服务器启动时间 用于计算运行时间 @yutiansut @quantaxis
     pub server_start_time: DateTime<Utc>,
     /// WebSocket 连接数计数器 @yutiansut @quantaxis
     pub ws_connection_count: Arc<AtomicUsize>,
     /// SnapshotManager 用于广播公告到所有连接的用户 @yutiansut @quantaxis
     pub snapshot_mgr: Option<Arc<SnapshotManager>>,
+    /// 账户级止损 用于用户设置止损线 @yutiansut @quantaxis
+    pub stop_loss: Option<Arc<crate::risk::AccountStopLoss>>,
+    /// 用户消息中心 用于历史通知列表与已读状态 @yutiansut @quantaxis
+    pub notification_store: Option<Arc<crate::notification::NotificationStore>>,
+    /// 异步任务管理器 用于重查询/导出任务提交与结果下载 @yutiansut @quantaxis
+    pub task_manager: Option<Arc<crate::service::async_task::AsyncTaskManager>>,
 }
 
 /// 用户成交视图 - 包含用户方向信息
@@ -102,8 +120,17 @@ impl UserTradeView {
     }
 }
 
-/// 健康检查
+/// 健康检查（紧急停机期间返回 503 维护状态）
 pub async fn health_check() -> HttpResponse {
+    if let Some(info) = crate::exchange::emergency::maintenance_status() {
+        return HttpResponse::ServiceUnavailable().json(serde_json::json!({
+            "status": "maintenance",
+            "service": "qaexchange",
+            "reason": info.reason,
+            "since": info.since
+        }));
+    }
+
     HttpResponse::Ok().json(serde_json::json!({
         "status": "ok",
         "service": "qaexchange"
@@ -153,10 +180,11 @@ pub async fn open_account(
     }
 }
 
-/// 查询账户（按 account_id 查询单个账户）
+/// 查询账户（按 account_id 查询单个账户，响应按 API 版本转换）
 pub async fn query_account(
     account_id: web::Path<String>, // 修复: 改为 account_id
     state: web::Data<Arc<AppState>>,
+    version: ApiVersionContext,
 ) -> Result<HttpResponse> {
     match state.account_mgr.get_account(&account_id) {
         Ok(account) => {
@@ -208,14 +236,18 @@ pub async fn query_account(
                 created_at,
             };
 
-            Ok(HttpResponse::Ok().json(ApiResponse::success(info)))
+            Ok(version.response(StatusCode::OK).json(ApiResponse::success(
+                version.convert(ApiResource::Account, &info),
+            )))
         }
         Err(e) => {
             log::error!("Failed to query account: {:?}", e);
-            Ok(HttpResponse::NotFound().json(ApiResponse::<()>::error(
-                404,
-                format!("Account not found: {:?}", e),
-            )))
+            Ok(version
+                .response(StatusCode::NOT_FOUND)
+                .json(ApiResponse::<()>::error(
+                    404,
+                    format!("Account not found: {:?}", e),
+                )))
         }
     }
 }
@@ -265,7 +297,7 @@ pub async fn submit_order(
     };
 
     let core_req = CoreSubmitOrderRequest {
-        account_id, // 交易层只关心 account_id
+        account_id: account_id.clone(), // 交易层只关心 account_id
         instrument_id: req.instrument_id.clone(),
         direction: req.direction.clone(),
         offset: req.offset.clone(),
@@ -274,14 +306,21 @@ pub async fn submit_order(
         order_type: req.order_type.clone(),
         time_condition: None,
         volume_condition: None,
+        hedge_flag: None,
+        ttl_secs: req.ttl_secs,
+        client_order_id: req.client_order_id.clone(),
+        close_lot_id: req.close_lot_id,
     };
 
-    let response = state.order_router.submit_order(core_req);
+    let response = state
+        .order_router
+        .submit_order_prioritized(core_req, req.priority);
 
     if response.success {
         let resp = SubmitOrderResponse {
             order_id: response.order_id.unwrap_or_default(),
             status: response.status.unwrap_or_else(|| "submitted".to_string()),
+            adjusted_price: response.adjusted_price,
         };
         Ok(HttpResponse::Ok().json(ApiResponse::success(resp)))
     } else {
@@ -349,10 +388,11 @@ pub async fn cancel_order(
     }
 }
 
-/// 查询订单
+/// 查询订单（响应按 API 版本转换）
 pub async fn query_order(
     order_id: web::Path<String>,
     state: web::Data<Arc<AppState>>,
+    version: ApiVersionContext,
 ) -> Result<HttpResponse> {
     match state.order_router.get_order_detail(&order_id) {
         Some((order, status, submit_time, update_time, filled_volume)) => {
@@ -370,22 +410,71 @@ pub async fn query_order(
                 update_time,
             };
 
-            Ok(HttpResponse::Ok().json(ApiResponse::success(info)))
+            Ok(version.response(StatusCode::OK).json(ApiResponse::success(
+                version.convert(ApiResource::Order, &info),
+            )))
         }
         None => {
             log::error!("Order not found: {}", order_id);
-            Ok(HttpResponse::NotFound().json(ApiResponse::<()>::error(
-                404,
-                format!("Order not found: {}", order_id),
-            )))
+            Ok(version
+                .response(StatusCode::NOT_FOUND)
+                .json(ApiResponse::<()>::error(
+                    404,
+                    format!("Order not found: {}", order_id),
+                )))
+        }
+    }
+}
+
+/// 查询订单排队位次（所在价位排第几位、前面挂单总量，涨跌停排队时标记 at_limit）
+/// @yutiansut @quantaxis
+pub async fn get_order_queue_position(
+    order_id: web::Path<String>,
+    state: web::Data<Arc<AppState>>,
+) -> Result<HttpResponse> {
+    match state.order_router.queue_position(&order_id) {
+        Ok(position) => Ok(
+            HttpResponse::Ok().json(ApiResponse::success(serde_json::json!({
+                "order_id": order_id.as_str(),
+                "queue": position,
+            }))),
+        ),
+        Err(e) => Ok(HttpResponse::NotFound().json(ApiResponse::<()>::error(404, e.to_string()))),
+    }
+}
+
+/// 查询订单状态变迁时间线（待报 → 已报 → 部分成交 → 终态，每次变更含时间戳与原因）
+/// @yutiansut @quantaxis
+pub async fn get_order_history(
+    order_id: web::Path<String>,
+    state: web::Data<Arc<AppState>>,
+) -> Result<HttpResponse> {
+    let tracker = state.order_router.order_history();
+    match tracker.history(&order_id) {
+        Some(timeline) => {
+            let current = timeline.last().map(|change| change.to);
+            Ok(
+                HttpResponse::Ok().json(ApiResponse::success(serde_json::json!({
+                    "order_id": order_id.as_str(),
+                    "current_state": current,
+                    "current_state_label": current.map(|s| s.label()),
+                    "terminal": current.is_some_and(|s| s.is_terminal()),
+                    "timeline": timeline,
+                }))),
+            )
         }
+        None => Ok(HttpResponse::NotFound().json(ApiResponse::<()>::error(
+            404,
+            format!("Order history not found: {}", order_id),
+        ))),
     }
 }
 
-/// 查询用户订单列表
+/// 查询用户订单列表（响应按 API 版本转换）
 pub async fn query_user_orders(
     user_id: web::Path<String>,
     state: web::Data<Arc<AppState>>,
+    version: ApiVersionContext,
 ) -> Result<HttpResponse> {
     let order_details = state.order_router.get_user_order_details(&user_id);
 
@@ -408,12 +497,12 @@ pub async fn query_user_orders(
         )
         .collect();
 
-    Ok(
-        HttpResponse::Ok().json(ApiResponse::success(serde_json::json!({
-            "orders": order_infos,
+    Ok(version
+        .response(StatusCode::OK)
+        .json(ApiResponse::success(serde_json::json!({
+            "orders": version.convert(ApiResource::Order, &order_infos),
             "total": order_infos.len()
-        }))),
-    )
+        }))))
 }
 
 /// 获取账户权益曲线
@@ -458,7 +547,9 @@ pub async fn get_equity_curve(
                 balance
             );
             // 使用当前真实数据作为今天的数据点
-            let today = chrono::Utc::now().format("%Y-%m-%d").to_string();
+            let today = crate::utils::time_service::now_local()
+                .format("%Y-%m-%d")
+                .to_string();
             points.push(EquityCurvePoint {
                 date: today,
                 balance,
@@ -489,6 +580,110 @@ pub async fn get_equity_curve(
     Ok(HttpResponse::Ok().json(ApiResponse::success(response)))
 }
 
+/// 盈亏归因：按合约拆分平仓盈亏/浮动盈亏/手续费，按交易拆分单笔盈亏
+/// @yutiansut @quantaxis
+///
+/// GET /api/account/{user_id}/pnl-attribution?start=&end=&top=
+pub async fn get_pnl_attribution(
+    user_id: web::Path<String>,
+    query: web::Query<PnlAttributionParams>,
+    state: web::Data<Arc<AppState>>,
+) -> Result<HttpResponse> {
+    let user_id = user_id.into_inner();
+    if let (Some(start), Some(end)) = (query.start, query.end) {
+        if start > end {
+            return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(
+                400,
+                format!("Invalid time range: start {} > end {}", start, end),
+            )));
+        }
+    }
+
+    let attribution_query = PnlAttributionQuery {
+        start: query.start,
+        end: query.end,
+        top: query.top.unwrap_or(PnlAttributionQuery::default().top),
+    };
+
+    let mut accounts = Vec::new();
+    for account in state.account_mgr.get_accounts_by_user(&user_id) {
+        // 浮动盈亏按持仓最新价与合约乘数计算
+        let (account_id, marks) = {
+            let acc = account.read();
+            let marks: std::collections::HashMap<String, InstrumentMark> = acc
+                .hold
+                .iter()
+                .filter(|(_, pos)| pos.lastest_price > 0.0)
+                .map(|(code, pos)| {
+                    (
+                        code.clone(),
+                        InstrumentMark {
+                            price: pos.lastest_price,
+                            multiplier: pos.preset.unit_table.max(1) as f64,
+                        },
+                    )
+                })
+                .collect();
+            (acc.account_cookie.clone(), marks)
+        };
+
+        accounts.push(state.account_mgr.pnl_ledger().attribution(
+            &account_id,
+            state.account_mgr.position_lots(),
+            &marks,
+            &attribution_query,
+        ));
+    }
+
+    Ok(
+        HttpResponse::Ok().json(ApiResponse::success(serde_json::json!({
+            "user_id": user_id,
+            "accounts": accounts,
+        }))),
+    )
+}
+
+/// 冻结资金明细：按挂单列出冻结保证金/手续费，区分出金冻结与其他冻结，并与账户总冻结对账
+/// @yutiansut @quantaxis
+///
+/// GET /api/account/{user_id}/frozen
+pub async fn get_frozen_breakdown(
+    user_id: web::Path<String>,
+    state: web::Data<Arc<AppState>>,
+) -> Result<HttpResponse> {
+    let user_id = user_id.into_inner();
+    let account_ids: Vec<String> = state
+        .account_mgr
+        .get_accounts_by_user(&user_id)
+        .iter()
+        .map(|account| account.read().account_cookie.clone())
+        .collect();
+    if account_ids.is_empty() {
+        return Ok(HttpResponse::NotFound().json(ApiResponse::<()>::error(
+            404,
+            format!("No account found for {}", user_id),
+        )));
+    }
+
+    let mut accounts = Vec::with_capacity(account_ids.len());
+    for account_id in &account_ids {
+        match state.order_router.frozen_breakdown(account_id) {
+            Ok(breakdown) => accounts.push(breakdown),
+            Err(e) => {
+                return Ok(HttpResponse::InternalServerError()
+                    .json(ApiResponse::<()>::error(500, e.to_string())))
+            }
+        }
+    }
+
+    Ok(
+        HttpResponse::Ok().json(ApiResponse::success(serde_json::json!({
+            "user_id": user_id,
+            "accounts": accounts,
+        }))),
+    )
+}
+
 #[derive(Debug, Clone, Serialize)]
 struct EquityCurvePoint {
     date: String,
@@ -660,10 +855,11 @@ fn compute_statistics(points: &[EquityCurvePoint]) -> EquityStatistics {
     }
 }
 
-/// 查询持仓（按account_id查询单个账户）
+/// 查询持仓（按account_id查询单个账户，响应按 API 版本转换）
 pub async fn query_position(
     account_id: web::Path<String>, // 修复: 改为account_id
     state: web::Data<Arc<AppState>>,
+    version: ApiVersionContext,
 ) -> Result<HttpResponse> {
     match state.account_mgr.get_account(&account_id) {
         Ok(account) => {
@@ -685,30 +881,37 @@ pub async fn query_position(
                 });
             }
 
-            Ok(HttpResponse::Ok().json(ApiResponse::success(positions)))
+            Ok(version.response(StatusCode::OK).json(ApiResponse::success(
+                version.convert(ApiResource::Position, &positions),
+            )))
         }
         Err(e) => {
             log::error!("Failed to query position by account_id: {:?}", e);
-            Ok(HttpResponse::NotFound().json(ApiResponse::<()>::error(
-                404,
-                format!("Account not found: {:?}", e),
-            )))
+            Ok(version
+                .response(StatusCode::NOT_FOUND)
+                .json(ApiResponse::<()>::error(
+                    404,
+                    format!("Account not found: {:?}", e),
+                )))
         }
     }
 }
 
-/// 查询持仓（按user_id查询该用户所有账户的持仓）
+/// 查询持仓（按user_id查询该用户所有账户的持仓，响应按 API 版本转换）
 pub async fn query_positions_by_user(
     user_id: web::Path<String>,
     state: web::Data<Arc<AppState>>,
+    version: ApiVersionContext,
 ) -> Result<HttpResponse> {
     let accounts = state.account_mgr.get_accounts_by_user(&user_id);
 
     if accounts.is_empty() {
-        return Ok(HttpResponse::NotFound().json(ApiResponse::<()>::error(
-            404,
-            format!("No accounts found for user: {}", user_id),
-        )));
+        return Ok(version
+            .response(StatusCode::NOT_FOUND)
+            .json(ApiResponse::<()>::error(
+                404,
+                format!("No accounts found for user: {}", user_id),
+            )));
     }
 
     // @yutiansut @quantaxis: 直接用 qars 的 volume_long()/volume_short()
@@ -732,7 +935,105 @@ pub async fn query_positions_by_user(
         }
     }
 
-    Ok(HttpResponse::Ok().json(ApiResponse::success(all_positions)))
+    Ok(version.response(StatusCode::OK).json(ApiResponse::success(
+        version.convert(ApiResource::Position, &all_positions),
+    )))
+}
+
+/// 查询持仓批次明细（开仓批次、锁仓量、合并展示）
+/// @yutiansut @quantaxis
+pub async fn query_position_lots(
+    account_id: web::Path<String>,
+    query: web::Query<PositionLotQuery>,
+    state: web::Data<Arc<AppState>>,
+) -> Result<HttpResponse> {
+    if let Err(e) = state.account_mgr.get_account(&account_id) {
+        return Ok(HttpResponse::NotFound().json(ApiResponse::<()>::error(
+            404,
+            format!("Account not found: {:?}", e),
+        )));
+    }
+
+    let lots = state
+        .account_mgr
+        .position_lots()
+        .lots(&account_id, query.instrument_id.as_deref());
+    Ok(HttpResponse::Ok().json(ApiResponse::success(lots)))
+}
+
+/// 查询用户持仓明细（逐笔开仓明细：开仓价、时间、剩余/可平量、逐笔浮动盈亏）
+/// @yutiansut @quantaxis
+///
+/// GET /api/position/{user_id}/detail
+///
+/// 每个账户附带批次明细与汇总持仓的对账结果（`mismatches` 为空表示一致）
+pub async fn query_position_detail(
+    user_id: web::Path<String>,
+    state: web::Data<Arc<AppState>>,
+) -> Result<HttpResponse> {
+    let account_ids: Vec<String> = state
+        .account_mgr
+        .get_accounts_by_user(&user_id)
+        .iter()
+        .map(|account| account.read().account_cookie.clone())
+        .collect();
+    if account_ids.is_empty() {
+        return Ok(HttpResponse::NotFound().json(ApiResponse::<()>::error(
+            404,
+            format!("No accounts found for user: {}", user_id),
+        )));
+    }
+
+    let mut accounts = Vec::new();
+    for account_id in account_ids {
+        let positions = state
+            .account_mgr
+            .position_detail(&account_id)
+            .unwrap_or_default();
+        let mismatches = state
+            .account_mgr
+            .reconcile_position_lots(&account_id)
+            .unwrap_or_default();
+        accounts.push(serde_json::json!({
+            "account_id": account_id,
+            "positions": positions,
+            "mismatches": mismatches,
+        }));
+    }
+
+    Ok(
+        HttpResponse::Ok().json(ApiResponse::success(serde_json::json!({
+            "user_id": user_id.as_str(),
+            "accounts": accounts,
+        }))),
+    )
+}
+
+/// 设置账户批次平仓方式（FIFO/LIFO），对之后的平仓生效
+/// @yutiansut @quantaxis
+pub async fn set_position_lot_method(
+    account_id: web::Path<String>,
+    req: web::Json<SetLotMethodRequest>,
+    state: web::Data<Arc<AppState>>,
+) -> Result<HttpResponse> {
+    if let Err(e) = state.account_mgr.get_account(&account_id) {
+        return Ok(HttpResponse::NotFound().json(ApiResponse::<()>::error(
+            404,
+            format!("Account not found: {:?}", e),
+        )));
+    }
+
+    state
+        .account_mgr
+        .position_lots()
+        .set_method(&account_id, req.method);
+    log::info!("Lot method for {} set to {:?}", account_id, req.method);
+    Ok(
+        HttpResponse::Ok().json(ApiResponse::success(serde_json::json!({
+            "account_id": account_id.as_str(),
+            "method": req.method,
+        }))),
+    )
 }
 
 /// 入金
@@ -1124,6 +1425,10 @@ pub async fn batch_submit_orders(
             order_type: order.order_type.clone(),
             time_condition: None,
             volume_condition: None,
+            hedge_flag: None,
+            ttl_secs: None,
+            client_order_id: None,
+            close_lot_id: None,
         };
 
         let response = state.order_router.submit_order(core_req);
@@ -1231,9 +1536,7 @@ pub async fn modify_order(
     req: web::Json<ModifyOrderRequest>,
     state: web::Data<Arc<AppState>>,
 ) -> Result<HttpResponse> {
-    use crate::exchange::order_router::{
-        CancelOrderRequest as CoreCancelOrderRequest, SubmitOrderRequest as CoreSubmitOrderRequest,
-    };
+    use crate::exchange::order_router::{AmendOrderRequest, OrderStatus};
 
     let order_id = order_id.into_inner();
     log::info!(
@@ -1245,7 +1548,7 @@ pub async fn modify_order(
     // 1. 获取原订单信息
     let original = match state.order_router.get_order_detail(&order_id) {
         Some((order, status, _, _, filled)) => {
-            if format!("{:?}", status) != "ALIVE" && format!("{:?}", status) != "Alive" {
+            if status != OrderStatus::Submitted {
                 return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(
                     4005,
                     format!("订单状态不允许修改: {:?}", status),
@@ -1267,37 +1570,25 @@ pub async fn modify_order(
         }
     };
 
-    // 2. 撤销原订单
-    let cancel_req = CoreCancelOrderRequest {
-        account_id: req.account_id.clone(),
-        order_id: order_id.clone(),
-    };
-
-    if let Err(e) = state.order_router.cancel_order(cancel_req) {
-        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(
-            4007,
-            format!("撤单失败: {:?}", e),
-        )));
-    }
-
-    // 3. 重新下单（使用新价格/数量）
+    // 2. 撤销原订单并重新下单（改单单独计频率并计入报撤比）
     let new_price = req.new_price.unwrap_or(original.limit_price);
     let new_volume = req.new_volume.unwrap_or(original.volume_orign);
 
-    let submit_req = CoreSubmitOrderRequest {
+    let response = match state.order_router.amend_order(AmendOrderRequest {
         account_id: req.account_id.clone(),
-        instrument_id: original.instrument_id.clone(),
-        direction: original.direction.clone(),
-        offset: original.offset.clone(),
-        volume: new_volume,
-        price: new_price,
-        order_type: original.price_type.clone(),
-        time_condition: None,
-        volume_condition: None,
+        order_id: order_id.clone(),
+        new_price: Some(new_price),
+        new_volume: Some(new_volume),
+    }) {
+        Ok(response) => response,
+        Err(e) => {
+            return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(
+                4007,
+                format!("改单失败（原订单未变）: {:?}", e),
+            )));
+        }
     };
 
-    let response = state.order_router.submit_order(submit_req);
-
     if response.success {
         log::info!(
             "📝 订单修改成功: {} -> {:?}",
@@ -1441,3 +1732,396 @@ pub async fn get_conditional_order_statistics(
 
     Ok(HttpResponse::Ok().json(ApiResponse::success(stats)))
 }
+
+/// 创建价差单
+/// POST /api/order/spread
+pub async fn create_spread_order(
+    req: web::Json<CreateSpreadOrderRequest>,
+    state: web::Data<Arc<AppState>>,
+) -> Result<HttpResponse> {
+    use crate::exchange::spread_order::SPREAD_ORDER_ENGINE;
+
+    log::info!(
+        "📋 创建价差单: account_id={}, legs={}/{}, target={}",
+        req.account_id,
+        req.legs[0].instrument_id,
+        req.legs[1].instrument_id,
+        req.target_spread
+    );
+
+    // 验证账户存在
+    if state.account_mgr.get_account(&req.account_id).is_err() {
+        return Ok(HttpResponse::NotFound().json(ApiResponse::<()>::error(
+            404,
+            format!("账户不存在: {}", req.account_id),
+        )));
+    }
+
+    let engine = SPREAD_ORDER_ENGINE.read();
+    match engine.create_order(req.into_inner()) {
+        Ok(order_info) => {
+            log::info!("📋 价差单创建成功: {}", order_info.spread_order_id);
+            Ok(HttpResponse::Ok().json(ApiResponse::success(order_info)))
+        }
+        Err(e) => {
+            log::error!("📋 价差单创建失败: {}", e);
+            Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(
+                4009,
+                e,
+            )))
+        }
+    }
+}
+
+/// 查询价差单列表（含实时价差与两腿成交情况）
+/// GET /api/order/spread/list?account_id=xxx
+pub async fn get_spread_orders(
+    query: web::Query<std::collections::HashMap<String, String>>,
+    _state: web::Data<Arc<AppState>>,
+) -> Result<HttpResponse> {
+    use crate::exchange::spread_order::SPREAD_ORDER_ENGINE;
+
+    let account_id = match query.get("account_id") {
+        Some(id) => id,
+        None => {
+            return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(
+                400,
+                "缺少 account_id 参数".to_string(),
+            )));
+        }
+    };
+
+    let engine = SPREAD_ORDER_ENGINE.read();
+    let orders = engine.get_orders_by_account(account_id);
+
+    Ok(HttpResponse::Ok().json(ApiResponse::success(serde_json::json!({
+        "orders": orders,
+        "total": orders.len()
+    }))))
+}
+
+/// 取消价差单（已下单的撤销两腿未成交部分）
+/// DELETE /api/order/spread/{spread_order_id}
+pub async fn cancel_spread_order(
+    spread_order_id: web::Path<String>,
+    _state: web::Data<Arc<AppState>>,
+) -> Result<HttpResponse> {
+    use crate::exchange::spread_order::SPREAD_ORDER_ENGINE;
+
+    let order_id = spread_order_id.into_inner();
+    log::info!("📋 取消价差单: {}", order_id);
+
+    let engine = SPREAD_ORDER_ENGINE.read();
+    match engine.cancel_order(&order_id) {
+        Ok(order_info) => Ok(HttpResponse::Ok().json(ApiResponse::success(order_info))),
+        Err(e) => {
+            log::error!("📋 价差单取消失败: {} - {}", order_id, e);
+            Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(
+                4010,
+                e,
+            )))
+        }
+    }
+}
+
+/// 获取价差单统计
+/// GET /api/order/spread/statistics
+pub async fn get_spread_order_statistics(
+    _state: web::Data<Arc<AppState>>,
+) -> Result<HttpResponse> {
+    use crate::exchange::spread_order::SPREAD_ORDER_ENGINE;
+
+    let engine = SPREAD_ORDER_ENGINE.read();
+    Ok(HttpResponse::Ok().json(ApiResponse::success(engine.get_statistics())))
+}
+
+/// 创建算法单（TWAP/VWAP）
+/// POST /api/order/algo
+pub async fn create_algo_order(
+    req: web::Json<CreateAlgoOrderRequest>,
+    state: web::Data<Arc<AppState>>,
+) -> Result<HttpResponse> {
+    use crate::exchange::algo_order::ALGO_ORDER_ENGINE;
+
+    log::info!(
+        "📋 创建算法单: account_id={}, instrument={}, {:?} {} 手, {} 个切片",
+        req.account_id,
+        req.instrument_id,
+        req.algo_type,
+        req.volume,
+        req.slice_count
+    );
+
+    // 验证账户存在
+    if state.account_mgr.get_account(&req.account_id).is_err() {
+        return Ok(HttpResponse::NotFound().json(ApiResponse::<()>::error(
+            404,
+            format!("账户不存在: {}", req.account_id),
+        )));
+    }
+
+    let engine = ALGO_ORDER_ENGINE.read();
+    match engine.create_order(req.into_inner()) {
+        Ok(order_info) => {
+            log::info!("📋 算法单创建成功: {}", order_info.algo_order_id);
+            Ok(HttpResponse::Ok().json(ApiResponse::success(order_info)))
+        }
+        Err(e) => {
+            log::error!("📋 算法单创建失败: {}", e);
+            Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(
+                4009,
+                e,
+            )))
+        }
+    }
+}
+
+/// 查询算法单列表
+/// GET /api/order/algo/list?account_id=xxx
+pub async fn get_algo_orders(
+    query: web::Query<std::collections::HashMap<String, String>>,
+    _state: web::Data<Arc<AppState>>,
+) -> Result<HttpResponse> {
+    use crate::exchange::algo_order::ALGO_ORDER_ENGINE;
+
+    let account_id = match query.get("account_id") {
+        Some(id) => id,
+        None => {
+            return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(
+                400,
+                "缺少 account_id 参数".to_string(),
+            )));
+        }
+    };
+
+    let engine = ALGO_ORDER_ENGINE.read();
+    let orders = engine.get_orders_by_account(account_id);
+
+    Ok(HttpResponse::Ok().json(ApiResponse::success(serde_json::json!({
+        "orders": orders,
+        "total": orders.len()
+    }))))
+}
+
+/// 查询算法单执行进度（含各子单切片）
+/// GET /api/order/algo/{algo_order_id}
+pub async fn get_algo_order(
+    algo_order_id: web::Path<String>,
+    _state: web::Data<Arc<AppState>>,
+) -> Result<HttpResponse> {
+    use crate::exchange::algo_order::ALGO_ORDER_ENGINE;
+
+    let order_id = algo_order_id.into_inner();
+    let engine = ALGO_ORDER_ENGINE.read();
+    match engine.get_order(&order_id) {
+        Some(order_info) => Ok(HttpResponse::Ok().json(ApiResponse::success(order_info))),
+        None => Ok(HttpResponse::NotFound().json(ApiResponse::<()>::error(
+            404,
+            format!("算法单不存在: {}", order_id),
+        ))),
+    }
+}
+
+/// 撤销算法单（撤掉未完成子单，不再下单）
+/// DELETE /api/order/algo/{algo_order_id}
+pub async fn cancel_algo_order(
+    algo_order_id: web::Path<String>,
+    _state: web::Data<Arc<AppState>>,
+) -> Result<HttpResponse> {
+    use crate::exchange::algo_order::ALGO_ORDER_ENGINE;
+
+    let order_id = algo_order_id.into_inner();
+    log::info!("📋 撤销算法单: {}", order_id);
+
+    let engine = ALGO_ORDER_ENGINE.read();
+    match engine.cancel_order(&order_id) {
+        Ok(order_info) => Ok(HttpResponse::Ok().json(ApiResponse::success(order_info))),
+        Err(e) => {
+            log::error!("📋 算法单撤销失败: {} - {}", order_id, e);
+            Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(
+                4010,
+                e,
+            )))
+        }
+    }
+}
+
+/// 获取算法单统计
+/// GET /api/order/algo/statistics
+pub async fn get_algo_order_statistics(
+    _state: web::Data<Arc<AppState>>,
+) -> Result<HttpResponse> {
+    use crate::exchange::algo_order::ALGO_ORDER_ENGINE;
+
+    let engine = ALGO_ORDER_ENGINE.read();
+    Ok(HttpResponse::Ok().json(ApiResponse::success(engine.get_statistics())))
+}
+
+// ==================== 移仓换月 API ====================
+// @yutiansut @quantaxis
+
+/// 登记大宗交易（发起方自动确认，等待对手方确认）
+/// POST /api/order/block
+pub async fn create_block_trade(
+    req: web::Json<CreateBlockTradeRequest>,
+    state: web::Data<Arc<AppState>>,
+) -> Result<HttpResponse> {
+    use crate::exchange::block_trade::BLOCK_TRADE_ENGINE;
+
+    log::info!(
+        "📋 登记大宗交易: initiator={}, {} 买方={} 卖方={} {} 手 @ {}",
+        req.initiator_account_id,
+        req.instrument_id,
+        req.buy_account_id,
+        req.sell_account_id,
+        req.volume,
+        req.price
+    );
+
+    // 验证双方账户存在
+    for account_id in [&req.buy_account_id, &req.sell_account_id] {
+        if state.account_mgr.get_account(account_id).is_err() {
+            return Ok(HttpResponse::NotFound().json(ApiResponse::<()>::error(
+                404,
+                format!("账户不存在: {}", account_id),
+            )));
+        }
+    }
+
+    let engine = BLOCK_TRADE_ENGINE.read();
+    match engine.create(req.into_inner()) {
+        Ok(trade) => Ok(HttpResponse::Ok().json(ApiResponse::success(trade))),
+        Err(e) => {
+            log::error!("📋 大宗交易登记失败: {}", e);
+            Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(4009, e)))
+        }
+    }
+}
+
+/// 对手方确认大宗交易（双方确认后立即成交）
+/// POST /api/order/block/{block_trade_id}/confirm
+pub async fn confirm_block_trade(
+    block_trade_id: web::Path<String>,
+    req: web::Json<BlockTradeActionRequest>,
+    _state: web::Data<Arc<AppState>>,
+) -> Result<HttpResponse> {
+    use crate::exchange::block_trade::BLOCK_TRADE_ENGINE;
+
+    let engine = BLOCK_TRADE_ENGINE.read();
+    match engine.confirm(&block_trade_id, &req.account_id) {
+        Ok(trade) => Ok(HttpResponse::Ok().json(ApiResponse::success(trade))),
+        Err(e) => {
+            log::error!("📋 大宗交易确认失败: {} - {}", block_trade_id, e);
+            Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(4010, e)))
+        }
+    }
+}
+
+/// 对手方拒绝大宗交易
+/// POST /api/order/block/{block_trade_id}/reject
+pub async fn reject_block_trade(
+    block_trade_id: web::Path<String>,
+    req: web::Json<BlockTradeActionRequest>,
+    _state: web::Data<Arc<AppState>>,
+) -> Result<HttpResponse> {
+    use crate::exchange::block_trade::BLOCK_TRADE_ENGINE;
+
+    let req = req.into_inner();
+    let engine = BLOCK_TRADE_ENGINE.read();
+    match engine.reject(&block_trade_id, &req.account_id, req.reason) {
+        Ok(trade) => Ok(HttpResponse::Ok().json(ApiResponse::success(trade))),
+        Err(e) => Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(4010, e))),
+    }
+}
+
+/// 发起方撤回大宗交易
+/// POST /api/order/block/{block_trade_id}/cancel
+pub async fn cancel_block_trade(
+    block_trade_id: web::Path<String>,
+    req: web::Json<BlockTradeActionRequest>,
+    _state: web::Data<Arc<AppState>>,
+) -> Result<HttpResponse> {
+    use crate::exchange::block_trade::BLOCK_TRADE_ENGINE;
+
+    let engine = BLOCK_TRADE_ENGINE.read();
+    match engine.cancel(&block_trade_id, &req.account_id) {
+        Ok(trade) => Ok(HttpResponse::Ok().json(ApiResponse::success(trade))),
+        Err(e) => Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(4010, e))),
+    }
+}
+
+/// 查询账户参与的大宗交易
+/// GET /api/order/block/list?account_id=xxx
+pub async fn get_block_trades(
+    query: web::Query<std::collections::HashMap<String, String>>,
+    _state: web::Data<Arc<AppState>>,
+) -> Result<HttpResponse> {
+    use crate::exchange::block_trade::BLOCK_TRADE_ENGINE;
+
+    let account_id = match query.get("account_id") {
+        Some(id) => id,
+        None => {
+            return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(
+                400,
+                "缺少 account_id 参数".to_string(),
+            )));
+        }
+    };
+
+    let engine = BLOCK_TRADE_ENGINE.read();
+    engine.expire_stale();
+    let trades = engine.list_by_account(account_id);
+
+    Ok(HttpResponse::Ok().json(ApiResponse::success(serde_json::json!({
+        "trades": trades,
+        "total": trades.len()
+    }))))
+}
+
+/// 获取大宗交易统计
+/// GET /api/order/block/statistics
+pub async fn get_block_trade_statistics(
+    _state: web::Data<Arc<AppState>>,
+) -> Result<HttpResponse> {
+    use crate::exchange::block_trade::BLOCK_TRADE_ENGINE;
+
+    let engine = BLOCK_TRADE_ENGINE.read();
+    engine.expire_stale();
+    Ok(HttpResponse::Ok().json(ApiResponse::success(engine.get_statistics())))
+}
+
+/// 移仓：旧合约平仓、新合约等量开仓，返回两腿结果
+/// POST /api/order/roll
+pub async fn roll_position(
+    req: web::Json<RollPositionRequest>,
+    state: web::Data<Arc<AppState>>,
+) -> Result<HttpResponse> {
+    use crate::exchange::PositionRoller;
+
+    log::info!(
+        "📋 移仓: account_id={}, {} -> {}, {} {:?} 手, {:?}",
+        req.account_id,
+        req.from_instrument,
+        req.to_instrument,
+        req.direction,
+        req.volume,
+        req.sequence
+    );
+
+    // 验证账户存在
+    if state.account_mgr.get_account(&req.account_id).is_err() {
+        return Ok(HttpResponse::NotFound().json(ApiResponse::<()>::error(
+            404,
+            format!("账户不存在: {}", req.account_id),
+        )));
+    }
+
+    let roller = PositionRoller::new(state.order_router.clone(), state.account_mgr.clone());
+    match roller.roll(req.into_inner()) {
+        Ok(result) => Ok(HttpResponse::Ok().json(ApiResponse::success(result))),
+        Err(e) => {
+            log::error!("📋 移仓失败: {}", e);
+            Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(4009, e)))
+        }
+    }
+}
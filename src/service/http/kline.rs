@@ -5,11 +5,17 @@
 //! @yutiansut @quantaxis
 
 use actix::Addr;
-use actix_web::{web, HttpResponse, Result};
+use actix_web::{http::header, web, HttpResponse, Result};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
-use crate::market::{kline, GetKLines, KLineActor};
+use crate::market::{kline, GetKLines, GetKLinesBatch, KLineActor};
+
+/// 批量K线请求最多合约/周期组合数
+pub const MAX_KLINE_BATCH_ITEMS: usize = 50;
+
+/// 批量K线响应超过该字节数才压缩（小响应压缩收益低于开销）
+pub const KLINE_BATCH_COMPRESS_THRESHOLD: usize = 16 * 1024;
 
 /// K线查询参数
 #[derive(Debug, Deserialize)]
@@ -54,6 +60,51 @@ pub struct KLineItem {
     pub amount: f64,
 }
 
+impl From<kline::KLine> for KLineItem {
+    fn from(k: kline::KLine) -> Self {
+        Self {
+            datetime: k.timestamp,
+            open: k.open,
+            high: k.high,
+            low: k.low,
+            close: k.close,
+            volume: k.volume,
+            amount: k.amount,
+        }
+    }
+}
+
+/// 批量K线请求项
+#[derive(Debug, Deserialize)]
+pub struct KLineBatchItem {
+    /// 合约代码
+    pub instrument: String,
+
+    /// K线周期（默认5分钟）
+    pub period: Option<i32>,
+
+    /// 数据条数（默认500）
+    pub count: Option<usize>,
+}
+
+/// 批量K线单项结果（失败不影响其他项）
+#[derive(Debug, Serialize)]
+pub struct KLineBatchResult {
+    pub instrument: String,
+    pub period: i32,
+    pub success: bool,
+    pub klines: Vec<KLineItem>,
+    pub error: Option<String>,
+}
+
+/// 批量K线响应
+#[derive(Debug, Serialize)]
+pub struct KLineBatchResponse {
+    pub code: i32,
+    pub message: String,
+    pub data: Option<Vec<KLineBatchResult>>,
+}
+
 /// 获取K线数据
 ///
 /// GET /api/market/kline/{instrument_id}?period=5&count=500
@@ -99,18 +150,7 @@ pub async fn get_kline_data(
     };
 
     // 转换为响应格式
-    let kline_items: Vec<KLineItem> = klines
-        .into_iter()
-        .map(|k| KLineItem {
-            datetime: k.timestamp,
-            open: k.open,
-            high: k.high,
-            low: k.low,
-            close: k.close,
-            volume: k.volume,
-            amount: k.amount,
-        })
-        .collect();
+    let kline_items: Vec<KLineItem> = klines.into_iter().map(KLineItem::from).collect();
 
     log::info!(
         "📊 [KLine API] {} period={:?} count={} -> {} bars",
@@ -131,6 +171,117 @@ pub async fn get_kline_data(
     }))
 }
 
+/// 批量获取K线数据（多合约多周期一次返回）
+///
+/// POST /api/market/kline/batch
+/// Body: [{"instrument": "IF2501", "period": 5, "count": 500}, ...]
+///
+/// 各项独立成功/失败，合约不存在或周期非法只影响该项
+pub async fn get_kline_batch(
+    req: web::Json<Vec<KLineBatchItem>>,
+    kline_actor: web::Data<Addr<KLineActor>>,
+) -> Result<HttpResponse> {
+    let items = req.into_inner();
+    if items.is_empty() || items.len() > MAX_KLINE_BATCH_ITEMS {
+        return Ok(HttpResponse::BadRequest().json(KLineBatchResponse {
+            code: 400,
+            message: format!(
+                "Batch size must be between 1 and {}, got {}",
+                MAX_KLINE_BATCH_ITEMS,
+                items.len()
+            ),
+            data: None,
+        }));
+    }
+
+    // 周期非法的项直接失败，其余一次性交给 KLineActor 并行查询
+    let mut results: Vec<KLineBatchResult> = Vec::with_capacity(items.len());
+    let mut requests = Vec::new();
+    let mut pending = Vec::new(); // 待查询项在 results 中的下标
+    for item in items {
+        let period_int = item.period.unwrap_or(5);
+        let result = KLineBatchResult {
+            instrument: item.instrument.clone(),
+            period: period_int,
+            success: false,
+            klines: Vec::new(),
+            error: None,
+        };
+        match kline::KLinePeriod::from_int(period_int) {
+            Some(period) => {
+                pending.push(results.len());
+                requests.push(GetKLines {
+                    instrument_id: item.instrument,
+                    period,
+                    count: item.count.unwrap_or(500),
+                });
+                results.push(result);
+            }
+            None => results.push(KLineBatchResult {
+                error: Some(format!("Invalid period: {}", period_int)),
+                ..result
+            }),
+        }
+    }
+
+    let found = match kline_actor.send(GetKLinesBatch { requests }).await {
+        Ok(found) => found,
+        Err(e) => {
+            log::error!("Failed to query K-line batch: {}", e);
+            return Ok(
+                HttpResponse::InternalServerError().json(KLineBatchResponse {
+                    code: 500,
+                    message: format!("Failed to query K-line data: {}", e),
+                    data: None,
+                }),
+            );
+        }
+    };
+    for (index, klines) in pending.into_iter().zip(found) {
+        let result = &mut results[index];
+        match klines {
+            Some(klines) => {
+                result.success = true;
+                result.klines = klines.into_iter().map(KLineItem::from).collect();
+            }
+            None => {
+                result.error = Some(format!("Instrument not found: {}", result.instrument));
+            }
+        }
+    }
+
+    log::info!(
+        "📊 [KLine API] batch {} items, {} succeeded",
+        results.len(),
+        results.iter().filter(|r| r.success).count()
+    );
+
+    let body = match serde_json::to_vec(&KLineBatchResponse {
+        code: 0,
+        message: "success".to_string(),
+        data: Some(results),
+    }) {
+        Ok(body) => body,
+        Err(e) => {
+            return Ok(
+                HttpResponse::InternalServerError().json(KLineBatchResponse {
+                    code: 500,
+                    message: format!("Failed to serialize K-line data: {}", e),
+                    data: None,
+                }),
+            );
+        }
+    };
+
+    // 小响应标记 identity，Compress 中间件跳过；超过阈值按客户端 Accept-Encoding 压缩
+    let mut response = HttpResponse::Ok();
+    response.content_type("application/json");
+    if body.len() < KLINE_BATCH_COMPRESS_THRESHOLD {
+        response.insert_header((header::CONTENT_ENCODING, "identity"));
+    }
+    Ok(response.body(body))
+}
+
 // K线路由将在 routes.rs 中直接集成到 /api/market scope
 
 #[cfg(test)]
@@ -147,4 +298,135 @@ mod tests {
         assert_eq!(query.period.unwrap_or(5), 5);
         assert_eq!(query.count.unwrap_or(500), 500);
     }
+
+    use crate::market::MarketDataBroadcaster;
+    use crate::storage::wal::WalManager;
+    use actix::Actor;
+    use actix_web::{middleware, test, App};
+
+    /// 启动带 `instruments` 个合约K线（每个 600 分钟）的 KLineActor
+    fn start_kline_actor(instruments: usize) -> (Addr<KLineActor>, tempfile::TempDir) {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let wal_manager = Arc::new(WalManager::new(tmp_dir.path().to_str().unwrap()));
+        let actor = KLineActor::new(Arc::new(MarketDataBroadcaster::new()), wal_manager);
+        {
+            let aggregators = actor.aggregators();
+            let mut aggregators = aggregators.write();
+            for i in 0..instruments {
+                let instrument_id = format!("IF25{:02}", i);
+                let mut aggregator = kline::KLineAggregator::new(instrument_id.clone());
+                for minute in 0..600 {
+                    aggregator.on_tick(3800.0 + minute as f64, 1, minute * 60_000);
+                }
+                aggregators.insert(instrument_id, aggregator);
+            }
+        }
+        (actor.start(), tmp_dir)
+    }
+
+    fn batch_request(body: serde_json::Value, accept_encoding: &str) -> test::TestRequest {
+        test::TestRequest::post()
+            .uri("/kline/batch")
+            .insert_header((header::ACCEPT_ENCODING, accept_encoding))
+            .set_json(body)
+    }
+
+    #[actix::test]
+    async fn test_kline_batch_50_items_latency() {
+        let (addr, _tmp_dir) = start_kline_actor(MAX_KLINE_BATCH_ITEMS);
+        let app = test::init_service(
+            App::new()
+                .wrap(middleware::Compress::default())
+                .app_data(web::Data::new(addr))
+                .route("/kline/batch", web::post().to(get_kline_batch)),
+        )
+        .await;
+        let body: Vec<serde_json::Value> = (0..MAX_KLINE_BATCH_ITEMS)
+            .map(|i| {
+                serde_json::json!({
+                    "instrument": format!("IF25{:02}", i),
+                    "period": 4,
+                    "count": 100,
+                })
+            })
+            .collect();
+
+        let start = std::time::Instant::now();
+        let resp = test::call_service(
+            &app,
+            batch_request(body.clone().into(), "identity").to_request(),
+        )
+        .await;
+        let elapsed = start.elapsed();
+        assert!(resp.status().is_success());
+        let result: serde_json::Value = test::read_body_json(resp).await;
+        let data = result["data"].as_array().unwrap();
+        assert_eq!(data.len(), MAX_KLINE_BATCH_ITEMS);
+        assert!(data.iter().all(|item| item["success"] == true));
+        assert_eq!(data[7]["instrument"], "IF2507");
+        assert_eq!(data[7]["klines"].as_array().unwrap().len(), 100);
+        assert!(elapsed.as_millis() < 200, "50 项批量K线耗时 {:?}", elapsed);
+
+        // 大响应按客户端支持压缩
+        let resp = test::call_service(&app, batch_request(body.into(), "gzip").to_request()).await;
+        assert!(resp.status().is_success());
+        assert_eq!(
+            resp.headers().get(header::CONTENT_ENCODING).unwrap(),
+            "gzip"
+        );
+
+        // 超过上限整体拒绝
+        let body: Vec<serde_json::Value> = (0..=MAX_KLINE_BATCH_ITEMS)
+            .map(|_| serde_json::json!({ "instrument": "IF2500" }))
+            .collect();
+        let resp =
+            test::call_service(&app, batch_request(body.into(), "identity").to_request()).await;
+        assert_eq!(resp.status(), 400);
+    }
+
+    #[actix::test]
+    async fn test_kline_batch_partial_failure() {
+        let (addr, _tmp_dir) = start_kline_actor(2);
+        let app = test::init_service(
+            App::new()
+                .wrap(middleware::Compress::default())
+                .app_data(web::Data::new(addr))
+                .route("/kline/batch", web::post().to(get_kline_batch)),
+        )
+        .await;
+        let body = serde_json::json!([
+            { "instrument": "IF2500", "period": 4, "count": 10 },
+            { "instrument": "NOTEXIST", "period": 4, "count": 10 },
+            { "instrument": "IF2501", "period": 99, "count": 10 },
+            { "instrument": "CFFEX.IF2501", "period": 5, "count": 10 },
+        ]);
+
+        let resp = test::call_service(&app, batch_request(body, "gzip").to_request()).await;
+        assert!(resp.status().is_success());
+        // 小响应不压缩
+        assert_eq!(
+            resp.headers().get(header::CONTENT_ENCODING).unwrap(),
+            "identity"
+        );
+        let result: serde_json::Value = test::read_body_json(resp).await;
+        let data = result["data"].as_array().unwrap();
+        assert_eq!(data.len(), 4);
+
+        assert_eq!(data[0]["success"], true);
+        assert_eq!(data[0]["klines"].as_array().unwrap().len(), 10);
+        assert_eq!(data[1]["success"], false);
+        assert!(data[1]["error"]
+            .as_str()
+            .unwrap()
+            .contains("Instrument not found"));
+        assert_eq!(data[2]["success"], false);
+        assert!(data[2]["error"]
+            .as_str()
+            .unwrap()
+            .contains("Invalid period"));
+        // 带交易所前缀按基础合约代码匹配
+        assert_eq!(data[3]["success"], true);
+        assert_eq!(data[3]["period"], 5);
+        assert_eq!(data[3]["klines"].as_array().unwrap().len(), 10);
+    }
 }
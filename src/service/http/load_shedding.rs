@@ -0,0 +1,152 @@
+//! HTTP 过载保护中间件
+//!
+//! @yutiansut @quantaxis
+//!
+//! 按路径将请求分为交易/非关键/豁免三类，交给 [`OverloadGuard`] 判断是否接纳；
+//! 被拒绝的请求直接返回 503 + `Retry-After`，不进入下游处理。
+
+use actix_web::body::EitherBody;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header;
+use actix_web::{Error, HttpResponse};
+use futures::future::{ready, LocalBoxFuture, Ready};
+use std::sync::Arc;
+
+use super::models::ApiResponse;
+use crate::service::overload::{OverloadGuard, RequestClass, OVERLOAD_GUARD};
+
+/// 过载保护中间件
+#[derive(Clone)]
+pub struct LoadShedding {
+    guard: Arc<OverloadGuard>,
+}
+
+impl LoadShedding {
+    pub fn new(guard: Arc<OverloadGuard>) -> Self {
+        Self { guard }
+    }
+}
+
+impl Default for LoadShedding {
+    /// 使用全局过载保护器
+    fn default() -> Self {
+        Self::new(OVERLOAD_GUARD.clone())
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for LoadShedding
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = LoadSheddingMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(LoadSheddingMiddleware {
+            service,
+            guard: self.guard.clone(),
+        }))
+    }
+}
+
+pub struct LoadSheddingMiddleware<S> {
+    service: S,
+    guard: Arc<OverloadGuard>,
+}
+
+impl<S, B> Service<ServiceRequest> for LoadSheddingMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let class = RequestClass::from_path(req.path());
+        match self.guard.try_acquire(class) {
+            Ok(permit) => {
+                let fut = self.service.call(req);
+                Box::pin(async move {
+                    let res = fut.await;
+                    drop(permit);
+                    res.map(ServiceResponse::map_into_left_body)
+                })
+            }
+            Err(rejection) => {
+                log::debug!(
+                    "Load shedding {} {} ({:?}, load={:.2})",
+                    req.method(),
+                    req.path(),
+                    rejection.level,
+                    rejection.load
+                );
+                let response = HttpResponse::ServiceUnavailable()
+                    .insert_header((header::RETRY_AFTER, rejection.retry_after_secs.to_string()))
+                    .json(ApiResponse::<()>::error(
+                        503,
+                        format!(
+                            "Server overloaded, retry after {}s",
+                            rejection.retry_after_secs
+                        ),
+                    ));
+                let res = req.into_response(response).map_into_right_body();
+                Box::pin(async move { Ok(res) })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::overload::OverloadConfig;
+    use actix_web::{test, web, App};
+
+    #[actix::test]
+    async fn test_rejects_non_critical_with_retry_after() {
+        let guard = Arc::new(OverloadGuard::new(OverloadConfig {
+            enabled: true,
+            max_in_flight: 4,
+            high_water: 0.5,
+            critical_high_water: 1.0,
+            low_water: 0.25,
+            retry_after_secs: 3,
+            ..Default::default()
+        }));
+        let app = test::init_service(
+            App::new()
+                .wrap(LoadShedding::new(guard.clone()))
+                .route("/api/market/tick", web::get().to(HttpResponse::Ok))
+                .route("/api/order/submit", web::post().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        // 模拟 2 个在途交易请求，负载达到高水位
+        let _held: Vec<_> = (0..2)
+            .map(|_| guard.try_acquire(RequestClass::Critical).unwrap())
+            .collect();
+
+        let req = test::TestRequest::get()
+            .uri("/api/market/tick")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 503);
+        assert_eq!(resp.headers().get(header::RETRY_AFTER).unwrap(), "3");
+
+        let req = test::TestRequest::post()
+            .uri("/api/order/submit")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+        // 请求结束后归还在途计数
+        assert_eq!(guard.in_flight(), 2);
+    }
+}
@@ -7,10 +7,25 @@ use actix_web::{web, HttpResponse, Result};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
-use super::models::ApiResponse;
-use crate::exchange::{AccountManager, CapitalManager, FundTransaction, OrderRouter, SettlementEngine};
+use super::account_admin::log_audit;
+use super::models::{ApiResponse, AuditLogType, AuditResult};
+use crate::exchange::{
+    AccountManager, CapitalManager, CostTrade, FeatureGateConfig, FundTransaction, OrderRouter,
+    SettlementEngine, FEATURE_GATE,
+};
 use crate::matching::trade_recorder::TradeRecorder;
-use crate::risk::{LiquidationRecord, MarginSummary, RiskAccount, RiskLevel, RiskMonitor};
+use crate::observability::push_latency::{PushLatencyConfig, PUSH_LATENCY};
+use crate::observability::sampling::{SamplingConfig, TRACE_SAMPLER};
+use crate::risk::risk_history::parse_interval_ms;
+use crate::risk::{
+    LiquidationRecord, MarginSummary, RiskAccount, RiskHistoryPoint, RiskLevel, RiskMonitor,
+    MARKET_RISK_KEY,
+};
+use crate::service::overload::{OverloadConfig, OVERLOAD_GUARD};
+use crate::service::websocket::session_registry::{LoginPolicy, WS_SESSION_REGISTRY};
+use crate::service::websocket::subscription_quota::{
+    SubscriptionQuotaConfig, WS_SUBSCRIPTION_QUOTA,
+};
 
 /// 管理端应用状态
 /// @yutiansut @quantaxis
@@ -234,6 +249,7 @@ pub async fn get_transactions(
     };
 
     // 2. 从 TransferStore 获取记录（通过 user 的所有账户）
+    let time_service = crate::utils::time_service::time_service();
     let accounts = state.account_mgr.get_accounts_by_user(&user_id);
     for account in accounts {
         let account_id = account.read().account_cookie.clone();
@@ -259,12 +275,8 @@ pub async fn get_transactions(
                 },
                 method: Some(record.bank_name),
                 remark: Some(record.error_msg),
-                created_at: chrono::DateTime::from_timestamp_millis(record.datetime)
-                    .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
-                    .unwrap_or_default(),
-                updated_at: chrono::DateTime::from_timestamp_millis(record.datetime)
-                    .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
-                    .unwrap_or_default(),
+                created_at: time_service.format_millis(record.datetime),
+                updated_at: time_service.format_millis(record.datetime),
             };
             transactions.push(transaction);
         }
@@ -306,6 +318,7 @@ pub async fn get_all_transactions(
         .collect();
 
     // 使用 rayon 并行处理每个账户的 TransferStore 记录
+    let time_service = crate::utils::time_service::time_service();
     let transfer_transactions: Vec<FundTransaction> = account_infos
         .par_iter()
         .flat_map(|(account_id, user_id)| {
@@ -330,12 +343,8 @@ pub async fn get_all_transactions(
                     },
                     method: Some(record.bank_name),
                     remark: Some(format!("{} (账户: {})", record.error_msg, account_id)),
-                    created_at: chrono::DateTime::from_timestamp_millis(record.datetime)
-                        .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
-                        .unwrap_or_default(),
-                    updated_at: chrono::DateTime::from_timestamp_millis(record.datetime)
-                        .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
-                        .unwrap_or_default(),
+                    created_at: time_service.format_millis(record.datetime),
+                    updated_at: time_service.format_millis(record.datetime),
                 }
             }).collect::<Vec<_>>()
         })
@@ -360,6 +369,7 @@ pub async fn get_all_transactions(
                 match tx_type_clone.as_str() {
                     "deposit" => matches!(t.transaction_type, TransactionType::Deposit),
                     "withdrawal" => matches!(t.transaction_type, TransactionType::Withdrawal),
+                    "interest" => matches!(t.transaction_type, TransactionType::Interest),
                     _ => true,
                 }
             })
@@ -389,7 +399,7 @@ pub async fn get_all_transactions(
 /// 全部流水查询参数
 #[derive(Debug, Deserialize)]
 pub struct AllTransactionsQuery {
-    pub transaction_type: Option<String>,  // deposit / withdrawal
+    pub transaction_type: Option<String>,  // deposit / withdrawal / interest
     pub page: Option<u32>,
     pub page_size: Option<u32>,
 }
@@ -444,6 +454,33 @@ pub async fn get_margin_summary(state: web::Data<ManagementAppState>) -> Result<
     Ok(HttpResponse::Ok().json(ApiResponse::success(summary)))
 }
 
+/// 获取追保中的账户（含追保截止时间，最先到期的排在前面）
+pub async fn get_margin_call_accounts(
+    state: web::Data<ManagementAppState>,
+) -> Result<HttpResponse> {
+    let accounts = state.risk_monitor.margin_call().margin_call_states();
+    Ok(HttpResponse::Ok().json(ApiResponse::success(accounts)))
+}
+
+/// 自动追保记录查询参数
+#[derive(Debug, Deserialize)]
+pub struct AutoMarginQuery {
+    pub account_id: Option<String>,
+}
+
+/// 获取自动追保记录（不指定账户时返回全部，按时间倒序）
+pub async fn get_auto_margin_records(
+    query: web::Query<AutoMarginQuery>,
+    state: web::Data<ManagementAppState>,
+) -> Result<HttpResponse> {
+    let auto_margin = state.risk_monitor.auto_margin();
+    let records = match query.account_id {
+        Some(ref account_id) => auto_margin.get_records(account_id),
+        None => auto_margin.all_records(),
+    };
+    Ok(HttpResponse::Ok().json(ApiResponse::success(records)))
+}
+
 /// 获取强平记录
 pub async fn get_liquidation_records(
     query: web::Query<LiquidationQuery>,
@@ -477,6 +514,614 @@ pub async fn force_liquidate_account(
     }
 }
 
+/// 管理端手动强平请求
+/// @yutiansut @quantaxis
+#[derive(Debug, Deserialize)]
+pub struct ManualLiquidateRequest {
+    pub user_id: String,
+    /// 用户有多个账户时指定账户
+    pub account_id: Option<String>,
+    /// 不填则强平全部持仓
+    pub instrument_id: Option<String>,
+    /// 部分强平数量（需指定合约）
+    pub volume: Option<f64>,
+    /// 操作员（需具备 ForceLiquidate 权限）
+    pub operator_id: Option<String>,
+    pub reason: Option<String>,
+}
+
+/// 解析强平目标账户
+fn resolve_liquidation_account(
+    account_mgr: &AccountManager,
+    req: &ManualLiquidateRequest,
+) -> std::result::Result<String, String> {
+    if let Some(account_id) = &req.account_id {
+        return Ok(account_id.clone());
+    }
+    if account_mgr.get_account(&req.user_id).is_ok() {
+        return Ok(req.user_id.clone());
+    }
+    let accounts = account_mgr.get_accounts_by_user(&req.user_id);
+    match accounts.as_slice() {
+        [] => Err(format!("User {} has no account", req.user_id)),
+        [account] => Ok(account.read().account_cookie.clone()),
+        _ => Err(format!(
+            "User {} has {} accounts, account_id is required",
+            req.user_id,
+            accounts.len()
+        )),
+    }
+}
+
+/// 管理端手动强平：按对手价（或涨跌停保护价）生成强平单
+/// POST /api/management/liquidate
+pub async fn manual_liquidate(
+    req: web::Json<ManualLiquidateRequest>,
+    state: web::Data<ManagementAppState>,
+) -> Result<HttpResponse> {
+    let operator = req
+        .operator_id
+        .clone()
+        .unwrap_or_else(|| "admin".to_string());
+
+    // 启用用户体系时校验 ForceLiquidate 权限
+    if let Some(user_mgr) = state.account_mgr.user_manager() {
+        let allowed = req.operator_id.as_ref().is_some_and(|id| {
+            user_mgr
+                .user_has_permission(id, crate::user::Permission::ForceLiquidate)
+                .unwrap_or(false)
+        });
+        if !allowed {
+            log_audit(
+                req.account_id
+                    .clone()
+                    .unwrap_or_else(|| req.user_id.clone()),
+                operator.clone(),
+                AuditLogType::ForceLiquidation,
+                "强制平仓".to_string(),
+                "操作员无 ForceLiquidate 权限".to_string(),
+                None,
+                AuditResult::Blocked,
+            );
+            return Ok(HttpResponse::Forbidden().json(ApiResponse::<()>::error(
+                403,
+                format!("Operator {} lacks ForceLiquidate permission", operator),
+            )));
+        }
+    }
+
+    let account_id = match resolve_liquidation_account(&state.account_mgr, &req) {
+        Ok(id) => id,
+        Err(e) => {
+            return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(400, e)));
+        }
+    };
+
+    let details = format!(
+        "instrument={}, volume={}, reason={}",
+        req.instrument_id.as_deref().unwrap_or("ALL"),
+        req.volume
+            .map(|v| v.to_string())
+            .unwrap_or_else(|| "ALL".to_string()),
+        req.reason.as_deref().unwrap_or("-")
+    );
+
+    match state.settlement_engine.manual_liquidate(
+        &account_id,
+        req.instrument_id.as_deref(),
+        req.volume,
+        &operator,
+        req.reason.clone(),
+    ) {
+        Ok(result) => {
+            log_audit(
+                account_id,
+                operator,
+                AuditLogType::ForceLiquidation,
+                "强制平仓".to_string(),
+                format!(
+                    "{}, liquidation_id={}, orders={}",
+                    details,
+                    result.liquidation_id,
+                    result.orders.len()
+                ),
+                None,
+                AuditResult::Success,
+            );
+            Ok(HttpResponse::Ok().json(ApiResponse::success(result)))
+        }
+        Err(e) => {
+            log_audit(
+                account_id,
+                operator,
+                AuditLogType::ForceLiquidation,
+                "强制平仓".to_string(),
+                format!("{}, error={}", details, e),
+                None,
+                AuditResult::Failed,
+            );
+            Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(
+                400,
+                format!("Force liquidation failed: {}", e),
+            )))
+        }
+    }
+}
+
+/// 查询强平任务执行状态（每笔强平单的已报/部分成交/完成/失败原因）
+/// GET /api/management/liquidate/{liquidation_id}
+pub async fn get_liquidation_status(
+    liquidation_id: web::Path<String>,
+    state: web::Data<ManagementAppState>,
+) -> Result<HttpResponse> {
+    match state.settlement_engine.refresh_liquidation(&liquidation_id) {
+        Some(result) => Ok(HttpResponse::Ok().json(ApiResponse::success(result))),
+        None => Ok(HttpResponse::NotFound().json(ApiResponse::<()>::error(
+            404,
+            format!("Liquidation not found: {}", liquidation_id),
+        ))),
+    }
+}
+
+// ============================================================================
+// 持仓均价重算 API
+// ============================================================================
+
+/// 持仓均价重算请求
+/// @yutiansut @quantaxis
+#[derive(Debug, Deserialize)]
+pub struct RebuildPositionCostRequest {
+    pub account_id: String,
+    /// 按时间排序的成交流水（不填则使用账户当日成交）
+    pub trades: Option<Vec<CostTrade>>,
+    pub operator_id: Option<String>,
+}
+
+/// 按成交流水重算账户持仓均价（一次性修复工具）
+/// POST /api/management/positions/rebuild-cost
+pub async fn rebuild_position_cost(
+    req: web::Json<RebuildPositionCostRequest>,
+    state: web::Data<ManagementAppState>,
+) -> Result<HttpResponse> {
+    let req = req.into_inner();
+    let operator = req.operator_id.unwrap_or_else(|| "admin".to_string());
+
+    match state
+        .account_mgr
+        .rebuild_position_costs(&req.account_id, req.trades)
+    {
+        Ok(rebuilt) => {
+            log_audit(
+                req.account_id.clone(),
+                operator,
+                AuditLogType::SettingsChange,
+                "持仓均价重算".to_string(),
+                format!("instruments={:?}", rebuilt),
+                None,
+                AuditResult::Success,
+            );
+            Ok(
+                HttpResponse::Ok().json(ApiResponse::success(serde_json::json!({
+                    "account_id": req.account_id,
+                    "rebuilt": rebuilt,
+                }))),
+            )
+        }
+        Err(e) => Ok(
+            HttpResponse::BadRequest().json(ApiResponse::<()>::error(400, e.to_string())),
+        ),
+    }
+}
+
+// ============================================================================
+// 风险历史回放 API
+// ============================================================================
+
+/// 风险历史查询参数
+/// @yutiansut @quantaxis
+#[derive(Debug, Deserialize)]
+pub struct RiskHistoryQuery {
+    /// 起始时间（毫秒时间戳，默认结束前24小时）
+    pub start: Option<i64>,
+    /// 结束时间（毫秒时间戳，默认当前）
+    pub end: Option<i64>,
+    /// 降采样间隔：纯数字为秒，支持 s/m/h/d 后缀（默认采样间隔）
+    pub interval: Option<String>,
+}
+
+/// 风险历史响应
+#[derive(Debug, Serialize)]
+pub struct RiskHistoryResponse {
+    pub account_id: String,
+    pub start: i64,
+    pub end: i64,
+    pub points: Vec<RiskHistoryPoint>,
+}
+
+/// 解析风险历史查询参数 -> (start, end, interval_ms)
+fn parse_risk_history_query(
+    query: &RiskHistoryQuery,
+) -> std::result::Result<(i64, i64, Option<i64>), String> {
+    let end = query
+        .end
+        .unwrap_or_else(|| chrono::Utc::now().timestamp_millis());
+    let start = query.start.unwrap_or(end - 24 * 60 * 60 * 1000);
+    if start > end {
+        return Err("start must not be later than end".to_string());
+    }
+    let interval = match &query.interval {
+        Some(interval) => Some(
+            parse_interval_ms(interval).ok_or_else(|| format!("Invalid interval: {}", interval))?,
+        ),
+        None => None,
+    };
+    Ok((start, end, interval))
+}
+
+/// 获取账户风险历史
+pub async fn get_risk_history(
+    account_id: web::Path<String>,
+    query: web::Query<RiskHistoryQuery>,
+    state: web::Data<ManagementAppState>,
+) -> Result<HttpResponse> {
+    let (start, end, interval) = match parse_risk_history_query(&query) {
+        Ok(params) => params,
+        Err(e) => return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(400, e))),
+    };
+
+    let points = state
+        .risk_monitor
+        .risk_history()
+        .query(&account_id, start, end, interval);
+    let response = RiskHistoryResponse {
+        account_id: account_id.into_inner(),
+        start,
+        end,
+        points,
+    };
+    Ok(HttpResponse::Ok().json(ApiResponse::success(response)))
+}
+
+/// 获取全市场风险曲线
+pub async fn get_market_risk_history(
+    query: web::Query<RiskHistoryQuery>,
+    state: web::Data<ManagementAppState>,
+) -> Result<HttpResponse> {
+    let (start, end, interval) = match parse_risk_history_query(&query) {
+        Ok(params) => params,
+        Err(e) => return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(400, e))),
+    };
+
+    let points = state
+        .risk_monitor
+        .risk_history()
+        .query_market(start, end, interval);
+    let response = RiskHistoryResponse {
+        account_id: MARKET_RISK_KEY.to_string(),
+        start,
+        end,
+        points,
+    };
+    Ok(HttpResponse::Ok().json(ApiResponse::success(response)))
+}
+
+/// 获取风险历史存储统计
+pub async fn get_risk_history_stats(state: web::Data<ManagementAppState>) -> Result<HttpResponse> {
+    let stats = state.risk_monitor.risk_history().stats();
+    Ok(HttpResponse::Ok().json(ApiResponse::success(stats)))
+}
+
+// ============================================================================
+// 追踪采样 API (管理端)
+// ============================================================================
+
+/// 查询追踪采样配置与统计
+/// GET /api/management/tracing/sampling
+/// @yutiansut @quantaxis
+pub async fn get_tracing_sampling() -> Result<HttpResponse> {
+    let stats = TRACE_SAMPLER.stats();
+    Ok(HttpResponse::Ok().json(ApiResponse::success(stats)))
+}
+
+/// 热更新追踪采样配置（对之后进入的请求生效）
+/// PUT /api/management/tracing/sampling
+/// @yutiansut @quantaxis
+pub async fn update_tracing_sampling(req: web::Json<SamplingConfig>) -> Result<HttpResponse> {
+    if !(0.0..=1.0).contains(&req.rate) {
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(
+            400,
+            format!("Invalid sampling rate {}, expected 0.0 - 1.0", req.rate),
+        )));
+    }
+
+    TRACE_SAMPLER.update_config(req.into_inner());
+    log::info!("Tracing sampling updated: {:?}", TRACE_SAMPLER.config());
+    let stats = TRACE_SAMPLER.stats();
+    Ok(HttpResponse::Ok().json(ApiResponse::success(stats)))
+}
+
+// ============================================================================
+// 推送延迟 API (管理端)
+// ============================================================================
+
+/// 查询推送端到端延迟（各会话 P50/P99，按 P99 倒序）
+/// GET /api/management/push-latency
+/// @yutiansut @quantaxis
+pub async fn get_push_latency() -> Result<HttpResponse> {
+    let stats = PUSH_LATENCY.stats();
+    Ok(HttpResponse::Ok().json(ApiResponse::success(stats)))
+}
+
+/// 查询单个会话的推送延迟分布
+/// GET /api/management/push-latency/{session_id}
+/// @yutiansut @quantaxis
+pub async fn get_session_push_latency(session_id: web::Path<String>) -> Result<HttpResponse> {
+    match PUSH_LATENCY.session_summary(&session_id) {
+        Some(summary) => Ok(HttpResponse::Ok().json(ApiResponse::success(summary))),
+        None => Ok(HttpResponse::NotFound().json(ApiResponse::<()>::error(
+            404,
+            format!("No push latency samples for session: {}", session_id),
+        ))),
+    }
+}
+
+/// 热更新推送延迟采样配置
+/// PUT /api/management/push-latency/config
+/// @yutiansut @quantaxis
+pub async fn update_push_latency_config(req: web::Json<PushLatencyConfig>) -> Result<HttpResponse> {
+    if !(0.0..=1.0).contains(&req.sample_rate) {
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(
+            400,
+            format!(
+                "Invalid sample rate {}, expected 0.0 - 1.0",
+                req.sample_rate
+            ),
+        )));
+    }
+
+    PUSH_LATENCY.update_config(req.into_inner());
+    log::info!("Push latency sampling updated: {:?}", PUSH_LATENCY.config());
+    Ok(HttpResponse::Ok().json(ApiResponse::success(PUSH_LATENCY.config())))
+}
+
+// ============================================================================
+// 网关过载保护 API (管理端)
+// ============================================================================
+
+/// 查询过载保护当前水位（负载、在途请求、下游队列深度、拒绝计数）
+/// GET /api/management/overload
+/// @yutiansut @quantaxis
+pub async fn get_overload_status() -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(ApiResponse::success(OVERLOAD_GUARD.status())))
+}
+
+/// 热更新过载保护阈值
+/// PUT /api/management/overload/config
+/// @yutiansut @quantaxis
+pub async fn update_overload_config(req: web::Json<OverloadConfig>) -> Result<HttpResponse> {
+    if let Err(e) = req.validate() {
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(400, e)));
+    }
+
+    OVERLOAD_GUARD.update_config(req.into_inner());
+    log::info!("Overload protection updated: {:?}", OVERLOAD_GUARD.config());
+    Ok(HttpResponse::Ok().json(ApiResponse::success(OVERLOAD_GUARD.config())))
+}
+
+// ============================================================================
+// 功能灰度开关 API (管理端)
+// ============================================================================
+
+/// 查询功能灰度配置
+/// GET /api/management/feature-gates
+/// @yutiansut @quantaxis
+pub async fn get_feature_gates() -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(ApiResponse::success(FEATURE_GATE.config())))
+}
+
+/// 热更新功能灰度配置（只影响之后的下单，已下单的订单沿用原决策）
+/// PUT /api/management/feature-gates
+/// @yutiansut @quantaxis
+pub async fn update_feature_gates(req: web::Json<FeatureGateConfig>) -> Result<HttpResponse> {
+    if let Err(e) = FEATURE_GATE.update_config(req.into_inner()) {
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(400, e.to_string())));
+    }
+    Ok(HttpResponse::Ok().json(ApiResponse::success(FEATURE_GATE.config())))
+}
+
+/// 查询某账户在某 feature 上的灰度决策
+/// GET /api/management/feature-gates/{feature}/{user_id}
+/// @yutiansut @quantaxis
+pub async fn get_feature_decision(path: web::Path<(String, String)>) -> Result<HttpResponse> {
+    let (feature, user_id) = path.into_inner();
+    let enabled = FEATURE_GATE.evaluate(&feature, &user_id);
+    let response = serde_json::json!({
+        "feature": feature,
+        "user_id": user_id,
+        "enabled": enabled,
+    });
+    Ok(HttpResponse::Ok().json(ApiResponse::success(response)))
+}
+
+// ============================================================================
+// 在途订单看门狗 API (管理端)
+// ============================================================================
+
+/// 查询看门狗统计（等待回应订单数、超时处理次数、最近一次对账结果）
+/// GET /api/management/orders/watchdog
+/// @yutiansut @quantaxis
+pub async fn get_order_watchdog(state: web::Data<ManagementAppState>) -> Result<HttpResponse> {
+    match state.order_router.order_watchdog() {
+        Some(watchdog) => Ok(HttpResponse::Ok().json(ApiResponse::success(watchdog.stats()))),
+        None => Ok(HttpResponse::NotFound().json(ApiResponse::<()>::error(
+            404,
+            "Order watchdog is not enabled".to_string(),
+        ))),
+    }
+}
+
+/// 立即执行路由器与撮合引擎全量对账，返回差异
+/// POST /api/management/orders/reconcile
+/// @yutiansut @quantaxis
+pub async fn reconcile_orders(state: web::Data<ManagementAppState>) -> Result<HttpResponse> {
+    let report = state.order_router.reconcile_with_engine();
+    Ok(HttpResponse::Ok().json(ApiResponse::success(report)))
+}
+
+/// 查询成交事件总线统计（各消费者投递/消费/丢弃数与队列深度）
+/// GET /api/management/trades/bus
+/// @yutiansut @quantaxis
+pub async fn get_trade_bus_stats(state: web::Data<ManagementAppState>) -> Result<HttpResponse> {
+    let stats = state.order_router.trade_bus().stats();
+    Ok(HttpResponse::Ok().json(ApiResponse::success(stats)))
+}
+
+// ============================================================================
+// WebSocket 多终端会话 API (管理端)
+// ============================================================================
+
+/// 登录策略设置请求
+/// @yutiansut @quantaxis
+#[derive(Debug, Deserialize)]
+pub struct SetLoginPolicyRequest {
+    /// allow_multiple / kick_previous
+    pub policy: String,
+}
+
+/// 查询在线用户的 WebSocket 会话
+/// GET /api/management/sessions
+/// @yutiansut @quantaxis
+pub async fn get_ws_sessions() -> Result<HttpResponse> {
+    let stats = WS_SESSION_REGISTRY.stats();
+    Ok(HttpResponse::Ok().json(ApiResponse::success(stats)))
+}
+
+/// 设置多终端登录策略（仅影响之后的登录）
+/// PUT /api/management/sessions/policy
+/// @yutiansut @quantaxis
+pub async fn set_login_policy(req: web::Json<SetLoginPolicyRequest>) -> Result<HttpResponse> {
+    match LoginPolicy::parse(&req.policy) {
+        Some(policy) => {
+            WS_SESSION_REGISTRY.set_policy(policy);
+            let response = serde_json::json!({ "policy": policy });
+            Ok(HttpResponse::Ok().json(ApiResponse::success(response)))
+        }
+        None => Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(
+            400,
+            format!(
+                "Invalid login policy '{}', expected allow_multiple or kick_previous",
+                req.policy
+            ),
+        ))),
+    }
+}
+
+/// 按用户设置登录策略（policy 为 "default" 时恢复使用全局策略）
+/// PUT /api/management/sessions/policy/{user_id}
+/// @yutiansut @quantaxis
+pub async fn set_user_login_policy(
+    path: web::Path<String>,
+    req: web::Json<SetLoginPolicyRequest>,
+) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    let policy = match req.policy.as_str() {
+        "default" => None,
+        other => match LoginPolicy::parse(other) {
+            Some(policy) => Some(policy),
+            None => return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(
+                400,
+                format!(
+                    "Invalid login policy '{}', expected allow_multiple, kick_previous or default",
+                    req.policy
+                ),
+            ))),
+        },
+    };
+
+    WS_SESSION_REGISTRY.set_user_policy(&user_id, policy);
+    let response = serde_json::json!({
+        "user_id": user_id,
+        "policy": WS_SESSION_REGISTRY.effective_policy(&user_id),
+        "overridden": policy.is_some(),
+    });
+    Ok(HttpResponse::Ok().json(ApiResponse::success(response)))
+}
+
+/// 查询用户的活跃会话（终端、IP、登录时间）
+/// GET /api/management/sessions/user/{user_id}
+/// @yutiansut @quantaxis
+pub async fn get_user_ws_sessions(path: web::Path<String>) -> Result<HttpResponse> {
+    let user_id = path.into_inner();
+    let response = serde_json::json!({
+        "user_id": user_id,
+        "policy": WS_SESSION_REGISTRY.effective_policy(&user_id),
+        "sessions": WS_SESSION_REGISTRY.user_sessions(&user_id),
+    });
+    Ok(HttpResponse::Ok().json(ApiResponse::success(response)))
+}
+
+/// 踢出会话请求
+/// @yutiansut @quantaxis
+#[derive(Debug, Default, Deserialize)]
+pub struct KickSessionRequest {
+    /// 下线原因（推送给被踢终端）
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// 主动踢出指定会话
+/// DELETE /api/management/sessions/{session_id}
+/// @yutiansut @quantaxis
+pub async fn kick_ws_session(
+    path: web::Path<String>,
+    req: Option<web::Json<KickSessionRequest>>,
+) -> Result<HttpResponse> {
+    let session_id = path.into_inner();
+    let reason = req
+        .and_then(|r| r.into_inner().reason)
+        .unwrap_or_else(|| "已被管理员强制下线".to_string());
+
+    match WS_SESSION_REGISTRY.kick_session(&session_id, &reason) {
+        Some(user_id) => {
+            let response = serde_json::json!({
+                "session_id": session_id,
+                "user_id": user_id,
+                "reason": reason,
+            });
+            Ok(HttpResponse::Ok().json(ApiResponse::success(response)))
+        }
+        None => Ok(HttpResponse::NotFound().json(ApiResponse::<()>::error(
+            404,
+            format!("Session not found: {}", session_id),
+        ))),
+    }
+}
+
+/// 查询 WebSocket 订阅配额（各连接订阅合约数、推送速率与超限统计）
+/// GET /api/management/sessions/quota
+/// @yutiansut @quantaxis
+pub async fn get_subscription_quota() -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(ApiResponse::success(WS_SUBSCRIPTION_QUOTA.stats())))
+}
+
+/// 热更新 WebSocket 订阅配额（已有订阅不回收，之后的订阅变更与推送按新配额检查）
+/// PUT /api/management/sessions/quota/config
+/// @yutiansut @quantaxis
+pub async fn update_subscription_quota(
+    req: web::Json<SubscriptionQuotaConfig>,
+) -> Result<HttpResponse> {
+    if let Err(e) = req.validate() {
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(400, e)));
+    }
+
+    WS_SUBSCRIPTION_QUOTA.update_config(req.into_inner());
+    log::info!(
+        "WebSocket subscription quota updated: {:?}",
+        WS_SUBSCRIPTION_QUOTA.config()
+    );
+    Ok(HttpResponse::Ok().json(ApiResponse::success(WS_SUBSCRIPTION_QUOTA.config())))
+}
+
 // ============================================================================
 // 全市场订单/成交查询 API (管理端)
 // ============================================================================
@@ -6,7 +6,7 @@ use actix_web::{web, HttpResponse, Result};
 use serde::{Deserialize, Serialize};
 
 use super::models::ApiResponse;
-use crate::market::MarketDataService;
+use crate::market::{AnomalyEvent, AnomalyStats, MarketDataService, MAX_BATCH_TICK_INSTRUMENTS};
 
 /// 订单簿查询请求
 #[derive(Debug, Deserialize)]
@@ -19,6 +19,16 @@ fn default_depth() -> usize {
     5 // 默认五档
 }
 
+/// 合约代码归一（`SHFE.cu2501` / 旧代码 `cu2501`），同名合约有歧义时返回 400
+pub(crate) fn normalize_instrument(
+    market_service: &MarketDataService,
+    code: &str,
+) -> std::result::Result<String, HttpResponse> {
+    market_service
+        .normalize_instrument(code)
+        .map_err(|e| HttpResponse::BadRequest().json(ApiResponse::<()>::error(400, e.to_string())))
+}
+
 /// 获取订单簿（买卖盘）
 ///
 /// GET /api/market/orderbook/{instrument_id}?depth=5
@@ -32,6 +42,10 @@ pub async fn get_orderbook(
         instrument_id,
         query.depth
     );
+    let instrument_id = match normalize_instrument(&market_service, &instrument_id) {
+        Ok(id) => id,
+        Err(resp) => return Ok(resp),
+    };
 
     match market_service.get_orderbook_snapshot(&instrument_id, query.depth) {
         Ok(snapshot) => {
@@ -85,6 +99,10 @@ pub async fn get_tick(
     market_service: web::Data<MarketDataService>,
 ) -> Result<HttpResponse> {
     log::info!("🔍 [HTTP API] GET /api/market/tick/{}", instrument_id);
+    let instrument_id = match normalize_instrument(&market_service, &instrument_id) {
+        Ok(id) => id,
+        Err(resp) => return Ok(resp),
+    };
 
     match market_service.get_tick_data(&instrument_id) {
         Ok(tick) => {
@@ -112,6 +130,51 @@ pub async fn get_tick(
     }
 }
 
+/// 批量 Tick 查询请求
+#[derive(Debug, Deserialize)]
+pub struct BatchTickRequest {
+    pub instrument_ids: Vec<String>,
+    #[serde(default = "default_depth")]
+    pub depth: usize,
+}
+
+/// 批量获取多个合约的 Tick 数据
+///
+/// POST /api/market/ticks/batch
+///
+/// 单次最多 `MAX_BATCH_TICK_INSTRUMENTS` 个合约；不存在的合约在结果中标记错误
+pub async fn get_ticks_batch(
+    req: web::Json<BatchTickRequest>,
+    market_service: web::Data<MarketDataService>,
+) -> Result<HttpResponse> {
+    if req.instrument_ids.len() > MAX_BATCH_TICK_INSTRUMENTS {
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(
+            400,
+            format!(
+                "Too many instruments: {} (max {})",
+                req.instrument_ids.len(),
+                MAX_BATCH_TICK_INSTRUMENTS
+            ),
+        )));
+    }
+
+    let mut instrument_ids = Vec::with_capacity(req.instrument_ids.len());
+    for code in &req.instrument_ids {
+        match normalize_instrument(&market_service, code) {
+            Ok(id) => instrument_ids.push(id),
+            Err(resp) => return Ok(resp),
+        }
+    }
+
+    match market_service.get_ticks_batch(&instrument_ids, req.depth) {
+        Ok(ticks) => Ok(HttpResponse::Ok().json(ApiResponse::success(ticks))),
+        Err(e) => Ok(HttpResponse::BadRequest().json(ApiResponse::<()>::error(
+            400,
+            format!("Failed to get ticks: {}", e),
+        ))),
+    }
+}
+
 /// 获取最近成交记录
 ///
 /// GET /api/market/trades/{instrument_id}?limit=20
@@ -130,6 +193,10 @@ pub async fn get_recent_trades(
     query: web::Query<TradesQuery>,
     market_service: web::Data<MarketDataService>,
 ) -> Result<HttpResponse> {
+    let instrument_id = match normalize_instrument(&market_service, &instrument_id) {
+        Ok(id) => id,
+        Err(resp) => return Ok(resp),
+    };
     match market_service.get_recent_trades(&instrument_id, query.limit) {
         Ok(trades) => Ok(HttpResponse::Ok().json(ApiResponse::success(trades))),
         Err(e) => {
@@ -144,6 +211,38 @@ pub async fn get_recent_trades(
     }
 }
 
+/// 异常行情告警查询参数
+#[derive(Debug, Deserialize)]
+pub struct AnomalyQuery {
+    #[serde(default = "default_anomaly_limit")]
+    pub limit: usize,
+}
+
+fn default_anomaly_limit() -> usize {
+    50
+}
+
+/// 异常行情检测统计与最近告警
+#[derive(Debug, Serialize)]
+pub struct AnomalyReport {
+    pub stats: AnomalyStats,
+    pub events: Vec<AnomalyEvent>,
+}
+
+/// 管理员功能：查询异常行情（零/负/超出涨跌停/偏离过大的成交价与盘口价）
+///
+/// GET /api/admin/market/anomalies?limit=50
+pub async fn get_market_anomalies(
+    query: web::Query<AnomalyQuery>,
+    market_service: web::Data<MarketDataService>,
+) -> Result<HttpResponse> {
+    let detector = market_service.anomaly_detector();
+    Ok(HttpResponse::Ok().json(ApiResponse::success(AnomalyReport {
+        stats: detector.stats(),
+        events: detector.recent_events(query.limit),
+    })))
+}
+
 /// 管理员功能：获取市场订单统计
 ///
 /// GET /api/admin/market/order-stats
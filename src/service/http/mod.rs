@@ -6,14 +6,20 @@ pub mod admin;
 pub mod account_admin;  // Phase 12-13: 密码/手续费/保证金/冻结/审计/公告 @yutiansut @quantaxis
 pub mod auth;
 pub mod data_query;  // 数据查询和导出 @yutiansut @quantaxis
+pub mod factor;  // 因子历史查询与回填 @yutiansut @quantaxis
 pub mod handlers;
 pub mod kline;
+pub mod load_shedding;  // 过载保护中间件 @yutiansut @quantaxis
 pub mod management;
 pub mod market;
 pub mod models;
 pub mod monitoring;
+pub mod notification;  // 用户消息中心 @yutiansut @quantaxis
 pub mod routes;
+pub mod stop_loss;  // 账户止损线 @yutiansut @quantaxis
+pub mod tasks;  // 重查询/导出异步任务 @yutiansut @quantaxis
 pub mod transfer;  // 银期转账 @yutiansut @quantaxis
+pub mod versioning;  // API 版本化（/api/v1, /api/v2）@yutiansut @quantaxis
 
 use actix_web::{middleware, web, App, HttpServer as ActixHttpServer};
 use std::io;
@@ -61,12 +67,19 @@ impl HttpServer {
             // 数据查询存储组件（在HttpServer::new中初始化为None，由main.rs完整初始化）@yutiansut @quantaxis
             market_data_storage: None,
             kline_wal_manager: None,
+            factor_store: None,
             // 服务器启动时间 @yutiansut @quantaxis
             server_start_time: chrono::Utc::now(),
             // WebSocket 连接计数器 @yutiansut @quantaxis
             ws_connection_count: Arc::new(AtomicUsize::new(0)),
             // SnapshotManager（由WebSocketServer设置）@yutiansut @quantaxis
             snapshot_mgr: None,
+            // 账户止损（由main.rs设置）@yutiansut @quantaxis
+            stop_loss: None,
+            // 用户消息中心（由main.rs设置）@yutiansut @quantaxis
+            notification_store: None,
+            // 异步任务管理器（由main.rs设置）@yutiansut @quantaxis
+            task_manager: None,
         });
 
         let market_service = Arc::new(MarketDataService::new(matching_engine));
@@ -92,6 +105,7 @@ impl HttpServer {
                 .app_data(web::Data::new(app_state.clone()))
                 .app_data(web::Data::new(market_service.clone()))
                 // 中间件
+                .wrap(load_shedding::LoadShedding::default())
                 .wrap(middleware::Logger::default())
                 .wrap(middleware::Compress::default())
                 // CORS 支持
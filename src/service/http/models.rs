@@ -73,6 +73,18 @@ pub struct SubmitOrderRequest {
     pub volume: f64,
     pub price: f64,
     pub order_type: String, // LIMIT/MARKET
+    /// 存活时长（秒），到期未成交部分自动撤销
+    #[serde(default)]
+    pub ttl_secs: Option<u64>,
+    /// 客户端幂等键（重复订单检测以其为准）
+    #[serde(default)]
+    pub client_order_id: Option<String>,
+    /// 自定义优先级标记（Low/Normal/Critical），只影响进入撮合的顺序
+    #[serde(default)]
+    pub priority: Option<crate::exchange::OrderPriority>,
+    /// 指定平仓批次（仅平仓委托），本委托的成交优先消耗该批次，不足部分按 FIFO/LIFO
+    #[serde(default)]
+    pub close_lot_id: Option<u64>,
 }
 
 /// 订单提交响应
@@ -80,6 +92,9 @@ pub struct SubmitOrderRequest {
 pub struct SubmitOrderResponse {
     pub order_id: String,
     pub status: String,
+    /// 价格按最小变动价位规整后的实际委托价（未调整时不返回）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub adjusted_price: Option<f64>,
 }
 
 /// 撤单请求（外部 HTTP API）
@@ -123,6 +138,30 @@ pub struct PositionInfo {
     pub profit_short: f64,
 }
 
+/// 持仓批次明细查询参数
+#[derive(Debug, Clone, Deserialize)]
+pub struct PositionLotQuery {
+    /// 合约过滤（为空时返回全部合约）
+    pub instrument_id: Option<String>,
+}
+
+/// 设置批次平仓方式请求
+#[derive(Debug, Clone, Deserialize)]
+pub struct SetLotMethodRequest {
+    pub method: crate::exchange::LotMethod,
+}
+
+/// 盈亏归因查询参数
+#[derive(Debug, Clone, Deserialize)]
+pub struct PnlAttributionParams {
+    /// 起始时间（毫秒，含）
+    pub start: Option<i64>,
+    /// 结束时间（毫秒，含）
+    pub end: Option<i64>,
+    /// 盈利/亏损排行数量（默认 5）
+    pub top: Option<usize>,
+}
+
 /// 成交查询响应
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TradeInfo {
@@ -234,11 +273,15 @@ pub enum ConditionType {
     StopLoss,      // 止损
     TakeProfit,    // 止盈
     PriceTouch,    // 触价
+    Scheduled,     // 定时
+    VolumeTouch,   // 量触发
+    Composite,     // 组合条件
 }
 
 /// 触发条件
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 pub enum TriggerCondition {
+    #[default]
     #[serde(rename = "GE")]
     GreaterOrEqual,  // >=
     #[serde(rename = "LE")]
@@ -256,6 +299,33 @@ pub enum ConditionalOrderStatus {
     Failed,      // 触发失败
 }
 
+/// 条件单触发规则（可组合）
+///
+/// 省略 instrument_id 时取条件单自身合约
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TriggerSpec {
+    /// 价格触发：最新价与触发价比较
+    Price {
+        #[serde(default)]
+        instrument_id: Option<String>,
+        trigger_price: f64,
+        trigger_condition: TriggerCondition,
+    },
+    /// 时间触发：到达指定时刻（时间戳，毫秒）
+    Time { trigger_time: i64 },
+    /// 成交量触发：条件单创建后合约累计成交量达到阈值
+    Volume {
+        #[serde(default)]
+        instrument_id: Option<String>,
+        volume_threshold: f64,
+    },
+    /// 所有子条件同时满足
+    And { conditions: Vec<TriggerSpec> },
+    /// 任一子条件满足
+    Or { conditions: Vec<TriggerSpec> },
+}
+
 /// 创建条件单请求
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateConditionalOrderRequest {
@@ -267,9 +337,13 @@ pub struct CreateConditionalOrderRequest {
     pub order_type: String,          // LIMIT/MARKET
     pub limit_price: Option<f64>,
     pub condition_type: ConditionType,
+    #[serde(default)]
     pub trigger_price: f64,          // 触发价格
+    #[serde(default)]
     pub trigger_condition: TriggerCondition, // GE (>=) / LE (<=)
     pub valid_until: Option<i64>,    // 有效期（时间戳，毫秒）
+    #[serde(default)]
+    pub trigger: Option<TriggerSpec>, // 时间/量/组合触发，缺省为价格触发
 }
 
 /// 条件单信息
@@ -286,6 +360,7 @@ pub struct ConditionalOrderInfo {
     pub condition_type: ConditionType,
     pub trigger_price: f64,
     pub trigger_condition: TriggerCondition,
+    pub trigger: TriggerSpec,
     pub valid_until: Option<i64>,
     pub status: ConditionalOrderStatus,
     pub created_at: i64,
@@ -293,6 +368,223 @@ pub struct ConditionalOrderInfo {
     pub result_order_id: Option<String>,  // 触发后生成的订单ID
 }
 
+// ==================== 价差单（跨合约套利）API Models ====================
+// @yutiansut @quantaxis
+
+/// 价差单腿定义
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpreadLeg {
+    pub instrument_id: String,
+    pub direction: String,           // BUY/SELL
+    pub offset: String,              // OPEN/CLOSE
+    pub ratio: f64,                  // 数量比例（每组价差对应的手数）
+}
+
+/// 价差单状态
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SpreadOrderStatus {
+    Pending,     // 等待价差触发
+    Working,     // 两腿已下单，等待成交
+    Filled,      // 两腿全部成交
+    LegRisk,     // 两腿成交不一致（存在未对冲敞口）
+    RolledBack,  // 单腿失败，已撤销另一腿
+    Cancelled,   // 已取消
+    Expired,     // 已过期
+    Failed,      // 下单失败
+}
+
+/// 创建价差单请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateSpreadOrderRequest {
+    pub account_id: String,
+    pub legs: [SpreadLeg; 2],        // 两腿：价差 = 腿1价格 - 腿2价格
+    pub volume: f64,                 // 价差组数
+    pub target_spread: f64,          // 目标价差
+    pub trigger_condition: TriggerCondition, // GE (>=) / LE (<=)
+    #[serde(default)]
+    pub leg_timeout_ms: Option<i64>, // 一腿成交后另一腿的最长等待时间
+    pub valid_until: Option<i64>,    // 有效期（时间戳，毫秒）
+}
+
+/// 价差单腿状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpreadLegInfo {
+    pub instrument_id: String,
+    pub direction: String,
+    pub offset: String,
+    pub ratio: f64,
+    pub volume: f64,                 // 该腿下单手数
+    pub order_id: Option<String>,
+    pub price: Option<f64>,
+    pub filled_volume: f64,
+}
+
+/// 价差单信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpreadOrderInfo {
+    pub spread_order_id: String,
+    pub account_id: String,
+    pub legs: Vec<SpreadLegInfo>,
+    pub volume: f64,
+    pub target_spread: f64,
+    pub trigger_condition: TriggerCondition,
+    pub current_spread: Option<f64>, // 最新可成交价差
+    pub triggered_spread: Option<f64>,
+    pub status: SpreadOrderStatus,
+    pub created_at: i64,
+    pub triggered_at: Option<i64>,
+    pub message: Option<String>,     // 失败/腿风险说明
+}
+
+/// 算法单类型
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AlgoType {
+    Twap,        // 时间加权：时段内均匀拆分
+    Vwap,        // 量加权：按历史成交量分布拆分
+}
+
+/// 算法单状态
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AlgoOrderStatus {
+    Pending,     // 等待执行时段开始
+    Running,     // 执行中
+    Completed,   // 执行时段结束
+    Cancelled,   // 已撤销
+    Failed,      // 子单全部下单失败
+}
+
+/// 创建算法单请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateAlgoOrderRequest {
+    pub account_id: String,
+    pub instrument_id: String,
+    pub direction: String,           // BUY/SELL
+    pub offset: String,              // OPEN/CLOSE
+    pub volume: f64,                 // 母单总手数
+    pub algo_type: AlgoType,
+    pub start_time: i64,             // 执行开始时间（时间戳，毫秒）
+    pub end_time: i64,               // 执行结束时间（时间戳，毫秒）
+    pub slice_count: u32,            // 切片数
+    #[serde(default)]
+    pub limit_price: Option<f64>,    // 子单限价（为空时以市价单提交）
+    #[serde(default)]
+    pub volume_profile: Option<Vec<f64>>, // VWAP 成交量分布（为空时使用合约历史分布）
+}
+
+/// 算法单子单切片
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlgoSliceInfo {
+    pub index: usize,
+    pub scheduled_at: i64,           // 计划下单时间（毫秒）
+    pub volume: f64,
+    pub order_id: Option<String>,
+    pub filled_volume: f64,
+    pub status: String,              // SCHEDULED/SUBMITTED/FILLED/CANCELLED/REJECTED/SKIPPED
+}
+
+/// 算法单信息（执行进度）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlgoOrderInfo {
+    pub algo_order_id: String,
+    pub account_id: String,
+    pub instrument_id: String,
+    pub direction: String,
+    pub offset: String,
+    pub algo_type: AlgoType,
+    pub volume: f64,
+    pub submitted_volume: f64,
+    pub filled_volume: f64,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub limit_price: Option<f64>,
+    pub slices: Vec<AlgoSliceInfo>,
+    pub status: AlgoOrderStatus,
+    pub created_at: i64,
+    pub message: Option<String>,
+}
+
+// ==================== 移仓换月 API Models ====================
+// @yutiansut @quantaxis
+
+/// 移仓执行顺序
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RollSequence {
+    #[default]
+    CloseFirst,  // 先平后开：平仓释放保证金后按平仓成交量开仓
+    OpenFirst,   // 先开后平：需要账户能同时承担两腿保证金
+}
+
+/// 移仓状态
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RollStatus {
+    Completed,       // 全部移仓
+    PartiallyRolled, // 部分移仓，两腿手数一致
+    LegRisk,         // 两腿成交手数不一致，存在敞口
+    Failed,          // 首腿未成交，持仓未变化
+}
+
+/// 移仓请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RollPositionRequest {
+    pub account_id: String,
+    pub from_instrument: String,     // 旧合约
+    pub to_instrument: String,       // 新合约
+    pub direction: String,           // 持仓方向 LONG/SHORT
+    #[serde(default)]
+    pub volume: Option<f64>,         // 移仓手数（为空时移全部持仓）
+    #[serde(default = "default_roll_order_type")]
+    pub order_type: String,          // LIMIT/MARKET
+    #[serde(default)]
+    pub close_price: Option<f64>,    // 旧合约平仓限价
+    #[serde(default)]
+    pub open_price: Option<f64>,     // 新合约开仓限价
+    #[serde(default)]
+    pub spread: Option<f64>,         // 价差（新 - 旧），设置后第二腿按首腿委托价 ± 价差限价下单
+    #[serde(default)]
+    pub sequence: RollSequence,
+}
+
+fn default_roll_order_type() -> String {
+    "MARKET".to_string()
+}
+
+/// 移仓单腿结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RollLegResult {
+    pub instrument_id: String,
+    pub direction: String,
+    pub offset: String,
+    pub order_id: Option<String>,
+    pub price: Option<f64>,          // 委托价（市价单为转换后的价格）
+    pub volume: f64,
+    pub filled_volume: f64,
+    pub status: String,              // FILLED/PARTIALLY_FILLED/CANCELLED/REJECTED/SKIPPED
+    pub message: Option<String>,
+}
+
+/// 移仓结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RollPositionResult {
+    pub roll_id: String,
+    pub account_id: String,
+    pub from_instrument: String,
+    pub to_instrument: String,
+    pub direction: String,
+    pub sequence: RollSequence,
+    pub volume: f64,                 // 请求移仓手数
+    pub rolled_volume: f64,          // 两腿均成交的手数
+    pub close_leg: RollLegResult,
+    pub open_leg: RollLegResult,
+    pub spread: Option<f64>,         // 两腿委托价差（新 - 旧）
+    pub status: RollStatus,
+    pub message: Option<String>,
+}
+
 // ==================== Phase 11: 批量下单 API Models ====================
 // @yutiansut @quantaxis
 
@@ -519,6 +811,82 @@ pub struct UnfreezeAccountRequest {
     pub admin_token: String,
 }
 
+/// 设置账户交易限制请求（只平仓 / 暂停交易 / 恢复正常）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetTradingRestrictionRequest {
+    pub account_id: String,
+    pub restriction: crate::exchange::TradingRestriction,
+    pub reason: String,
+    /// 限制截止时间（毫秒时间戳，不填表示直到解除）
+    #[serde(default)]
+    pub expires_at: Option<i64>,
+    pub admin_token: String,
+}
+
+/// 给一批账户打标签 / 移除标签请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountTagsRequest {
+    pub account_ids: Vec<String>,
+    pub tags: Vec<String>,
+    pub admin_token: String,
+}
+
+/// 标签重命名请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenameTagRequest {
+    pub from: String,
+    pub to: String,
+    pub admin_token: String,
+}
+
+/// 删除标签请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeleteTagRequest {
+    pub tag: String,
+    pub admin_token: String,
+}
+
+/// 按标签批量设置交易限制请求（批量冻结 / 解冻）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagRestrictionRequest {
+    pub tags: Vec<String>,
+    /// true: 需带全部标签；false: 带任一标签
+    #[serde(default)]
+    pub match_all: bool,
+    pub restriction: crate::exchange::TradingRestriction,
+    pub reason: String,
+    #[serde(default)]
+    pub expires_at: Option<i64>,
+    pub admin_token: String,
+}
+
+/// 按标签批量设置保证金加成请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagMarginSurchargeRequest {
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub match_all: bool,
+    /// 保证金加成（0.2 表示保证金占用上浮 20%，0 表示取消）
+    pub surcharge: f64,
+    pub admin_token: String,
+}
+
+/// 按标签查询账户参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagAccountsQuery {
+    /// 逗号分隔的标签
+    pub tags: String,
+    #[serde(default)]
+    pub match_all: bool,
+}
+
+/// 标签审计查询参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagAuditQuery {
+    pub account_id: Option<String>,
+    pub limit: Option<usize>,
+}
+
 /// 账户状态信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AccountStatusInfo {
@@ -544,6 +912,7 @@ pub struct AccountStatusInfo {
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum AuditLogType {
     Login,           // 登录
+    AccountOpen,      // 开户
     Logout,          // 登出
     OrderSubmit,     // 下单
     OrderCancel,     // 撤单
@@ -555,6 +924,14 @@ pub enum AuditLogType {
     AccountUnfreeze, // 账户解冻
     SettingsChange,  // 设置修改
     RiskAlert,       // 风险警报
+    ForceLiquidation, // 强制平仓
+    TradingRestriction, // 交易限制（只平仓/暂停交易）
+    EmergencyShutdown,  // 紧急停机
+    TradingDayReset,    // 交易日重置（测试环境）
+    UserErasure,        // 用户注销（个人信息匿名化）
+    CircuitBreaker,     // 合约熔断/恢复
+    MatchingFault,      // 撮合故障恢复/停牌
+    TimeAdvance,        // 虚拟时间快进（测试环境）
 }
 
 /// 审计日志条目
@@ -213,6 +213,68 @@ pub async fn get_trades_monitoring(app_state: web::Data<Arc<AppState>>) -> impl
     HttpResponse::Ok().json(stats)
 }
 
+/// 拒单统计查询参数
+#[derive(Debug, Deserialize)]
+pub struct RejectionQuery {
+    /// 日期 YYYY-MM-DD（默认当日）
+    pub date: Option<String>,
+    /// 聚合维度 reason | instrument | hour（默认 reason）
+    #[serde(default)]
+    pub group_by: crate::risk::RejectionGroupBy,
+}
+
+/// 查询拒单原因统计 @yutiansut @quantaxis
+///
+/// GET /api/monitoring/rejections?date=2025-01-02&group_by=reason|instrument|hour
+pub async fn get_rejections_monitoring(
+    app_state: web::Data<Arc<AppState>>,
+    query: web::Query<RejectionQuery>,
+) -> impl Responder {
+    let date = match query.date.as_deref() {
+        Some(d) => match chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d") {
+            Ok(date) => date,
+            Err(_) => {
+                return HttpResponse::BadRequest().json(serde_json::json!({
+                    "error": format!("Invalid date '{}', expected YYYY-MM-DD", d)
+                }))
+            }
+        },
+        None => crate::utils::time_service::time_service().today(),
+    };
+
+    let summary = app_state
+        .order_router
+        .rejection_stats()
+        .query(date, query.group_by);
+    HttpResponse::Ok().json(summary)
+}
+
+/// 查询重复订单拦截统计与最近事件 @yutiansut @quantaxis
+///
+/// GET /api/monitoring/duplicate-orders
+pub async fn get_duplicate_orders_monitoring(
+    app_state: web::Data<Arc<AppState>>,
+) -> impl Responder {
+    match app_state.order_router.duplicate_guard() {
+        Some(guard) => HttpResponse::Ok().json(guard.stats(20, 100)),
+        None => HttpResponse::ServiceUnavailable().json(serde_json::json!({
+            "error": "Duplicate order guard not enabled"
+        })),
+    }
+}
+
+/// 查询订单簿挂单数与估算内存占用 @yutiansut @quantaxis
+///
+/// GET /api/monitoring/orderbooks
+pub async fn get_orderbooks_monitoring(app_state: web::Data<Arc<AppState>>) -> impl Responder {
+    match app_state.order_router.book_limiter() {
+        Some(limiter) => HttpResponse::Ok().json(limiter.summary()),
+        None => HttpResponse::ServiceUnavailable().json(serde_json::json!({
+            "error": "Order book limiter not enabled"
+        })),
+    }
+}
+
 /// 查询存储统计
 ///
 /// GET /api/monitoring/storage
@@ -261,6 +323,26 @@ pub async fn get_storage_monitoring(app_state: web::Data<Arc<AppState>>) -> impl
     HttpResponse::Ok().json(stats)
 }
 
+/// 查询 OLAP 月份分区统计（每月行数、大小、冷热状态）
+///
+/// GET /api/monitoring/storage/partitions
+pub async fn get_olap_partitions(app_state: web::Data<Arc<AppState>>) -> impl Responder {
+    match app_state.conversion_mgr {
+        Some(ref mgr) => {
+            let partitions = mgr.lock().olap_partition_stats();
+            HttpResponse::Ok().json(serde_json::json!({
+                "total_rows": partitions.iter().map(|p| p.rows).sum::<u64>(),
+                "total_bytes": partitions.iter().map(|p| p.size_bytes).sum::<u64>(),
+                "cold_partitions": partitions.iter().filter(|p| p.cold).count(),
+                "partitions": partitions,
+            }))
+        }
+        None => HttpResponse::ServiceUnavailable().json(serde_json::json!({
+            "error": "OLAP conversion not enabled"
+        })),
+    }
+}
+
 // ============= 内部辅助函数 =============
 
 fn get_account_stats(account_mgr: &AccountManager) -> AccountStats {
@@ -452,7 +534,7 @@ Generated at: {}
         trades.total_count,
         trades.total_amount,
         trades.total_volume,
-        chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC"),
+        crate::utils::time_service::now_local().format("%Y-%m-%d %H:%M:%S %:z"),
     );
 
     HttpResponse::Ok()
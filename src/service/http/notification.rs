@@ -0,0 +1,122 @@
+//! 用户消息中心 HTTP API
+//!
+//! 历史通知列表（成交、风控、公告）、批量标记已读、未读计数
+//!
+//! @yutiansut @quantaxis
+
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::handlers::AppState;
+use super::models::ApiResponse;
+use crate::notification::store::inbox_keys;
+
+/// 单页最多条数
+const MAX_PAGE_SIZE: usize = 100;
+
+/// 通知列表查询参数
+#[derive(Debug, Deserialize)]
+pub struct ListNotificationsQuery {
+    pub user_id: String,
+    /// 通知类型（如 risk_alert）或频道（trade/risk/system）
+    #[serde(rename = "type")]
+    pub notification_type: Option<String>,
+    #[serde(default = "default_page")]
+    pub page: usize,
+    #[serde(default = "default_page_size")]
+    pub page_size: usize,
+}
+
+fn default_page() -> usize {
+    1
+}
+
+fn default_page_size() -> usize {
+    20
+}
+
+/// 标记已读请求（message_ids 为空时标记全部）
+#[derive(Debug, Deserialize)]
+pub struct MarkReadRequest {
+    pub user_id: String,
+    #[serde(default)]
+    pub message_ids: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MarkReadResponse {
+    pub marked: usize,
+    pub unread: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UnreadCountQuery {
+    pub user_id: String,
+}
+
+fn notification_store_unavailable() -> HttpResponse {
+    HttpResponse::ServiceUnavailable().json(ApiResponse::<()>::error(
+        503,
+        "Notification center not enabled".to_string(),
+    ))
+}
+
+/// 历史通知列表（按时间倒序分页）
+///
+/// GET /api/notification/list?user_id=&type=&page=&page_size=
+pub async fn list_notifications(
+    query: web::Query<ListNotificationsQuery>,
+    state: web::Data<Arc<AppState>>,
+) -> HttpResponse {
+    let Some(ref store) = state.notification_store else {
+        return notification_store_unavailable();
+    };
+
+    let keys = inbox_keys(&state.account_mgr, &query.user_id);
+    let page = store.list(
+        &keys,
+        query.notification_type.as_deref(),
+        query.page,
+        query.page_size.min(MAX_PAGE_SIZE),
+    );
+    HttpResponse::Ok().json(ApiResponse::success(page))
+}
+
+/// 批量标记已读
+///
+/// POST /api/notification/read
+pub async fn mark_notifications_read(
+    req: web::Json<MarkReadRequest>,
+    state: web::Data<Arc<AppState>>,
+) -> HttpResponse {
+    let Some(ref store) = state.notification_store else {
+        return notification_store_unavailable();
+    };
+
+    let keys = inbox_keys(&state.account_mgr, &req.user_id);
+    let marked = store.mark_read(
+        &keys,
+        &req.message_ids,
+        chrono::Utc::now().timestamp_millis(),
+    );
+    HttpResponse::Ok().json(ApiResponse::success(MarkReadResponse {
+        marked,
+        unread: store.unread_count(&keys).total,
+    }))
+}
+
+/// 未读计数（总数与按频道统计）
+///
+/// GET /api/notification/unread_count?user_id=
+pub async fn get_unread_count(
+    query: web::Query<UnreadCountQuery>,
+    state: web::Data<Arc<AppState>>,
+) -> HttpResponse {
+    let Some(ref store) = state.notification_store else {
+        return notification_store_unavailable();
+    };
+
+    let keys = inbox_keys(&state.account_mgr, &query.user_id);
+    HttpResponse::Ok().json(ApiResponse::success(store.unread_count(&keys)))
+}
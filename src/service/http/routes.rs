@@ -4,19 +4,50 @@ use super::admin;
 use super::account_admin;  // Phase 12-13: 密码/手续费/保证金/冻结/审计/公告 @yutiansut @quantaxis
 use super::auth;
 use super::data_query;  // 数据查询和导出 @yutiansut @quantaxis
+use super::factor;  // 因子历史查询与回填 @yutiansut @quantaxis
+use super::stop_loss;  // 账户止损线 @yutiansut @quantaxis
+use super::tasks;  // 重查询/导出异步任务 @yutiansut @quantaxis
 use super::handlers;
 use super::kline;
 use super::management;
 use super::market;
 use super::monitoring;
+use super::notification;  // 用户消息中心 @yutiansut @quantaxis
 use super::transfer;  // 银期转账 @yutiansut @quantaxis
+use super::versioning::ApiVersion;  // API 版本化 @yutiansut @quantaxis
 use actix_web::web;
 
+/// 带版本前缀的查询路由（/api/v1、/api/v2 共用 handler，响应由版本转换层区分）
+/// @yutiansut @quantaxis
+fn versioned_scope(version: ApiVersion) -> actix_web::Scope {
+    web::scope(version.prefix())
+        .route(
+            "/account/{account_id}",
+            web::get().to(handlers::query_account),
+        )
+        .route(
+            "/order/user/{user_id}",
+            web::get().to(handlers::query_user_orders),
+        )
+        .route("/order/{order_id}", web::get().to(handlers::query_order))
+        .route(
+            "/position/account/{account_id}",
+            web::get().to(handlers::query_position),
+        )
+        .route(
+            "/position/user/{user_id}",
+            web::get().to(handlers::query_positions_by_user),
+        )
+}
+
 /// 配置所有路由
 pub fn configure(cfg: &mut web::ServiceConfig) {
     cfg
         // 健康检查
         .route("/health", web::get().to(handlers::health_check))
+        // 版本化 API（账户/订单/持仓查询）@yutiansut @quantaxis
+        .service(versioned_scope(ApiVersion::V1))
+        .service(versioned_scope(ApiVersion::V2))
         // 用户认证 @yutiansut @quantaxis
         .service(
             web::scope("/api/auth")
@@ -27,7 +58,8 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
                 // 角色管理 API @yutiansut @quantaxis
                 .route("/user/roles", web::post().to(auth::set_user_roles)) // 设置用户角色
                 .route("/user/role/add", web::post().to(auth::add_user_role)) // 添加用户角色
-                .route("/user/{user_id}/make-admin", web::post().to(auth::make_admin)), // 升级为管理员
+                .route("/user/{user_id}/make-admin", web::post().to(auth::make_admin)) // 升级为管理员
+                .route("/user/{user_id}/erase", web::post().to(auth::erase_user)), // 注销并匿名化个人信息
         )
         // 用户账户管理 (Phase 10)
         .service(
@@ -52,10 +84,24 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
                     "/{user_id}/equity-curve",
                     web::get().to(handlers::get_equity_curve),
                 )
+                // 盈亏归因 @yutiansut @quantaxis
+                .route(
+                    "/{user_id}/pnl-attribution",
+                    web::get().to(handlers::get_pnl_attribution),
+                )
+                // 冻结资金明细 @yutiansut @quantaxis
+                .route(
+                    "/{user_id}/frozen",
+                    web::get().to(handlers::get_frozen_breakdown),
+                )
                 // Phase 11: 银期转账 @yutiansut @quantaxis
                 .route("/transfer", web::post().to(transfer::do_transfer))  // 执行转账
                 .route("/{account_id}/banks", web::get().to(transfer::get_banks))  // 签约银行
-                .route("/{account_id}/transfers", web::get().to(transfer::get_transfers)),  // 转账记录
+                .route("/{account_id}/transfers", web::get().to(transfer::get_transfers))  // 转账记录
+                // 账户止损线 @yutiansut @quantaxis
+                .route("/{account_id}/stop-loss", web::get().to(stop_loss::get_stop_loss))
+                .route("/{account_id}/stop-loss", web::put().to(stop_loss::set_stop_loss))
+                .route("/{account_id}/stop-loss", web::delete().to(stop_loss::remove_stop_loss)),
         )
         // 订单管理
         .service(
@@ -63,6 +109,14 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
                 .route("/submit", web::post().to(handlers::submit_order))
                 .route("/cancel", web::post().to(handlers::cancel_order))
                 .route("/{order_id}", web::get().to(handlers::query_order))
+                .route(
+                    "/{order_id}/queue",
+                    web::get().to(handlers::get_order_queue_position),
+                )
+                .route(
+                    "/{order_id}/history",
+                    web::get().to(handlers::get_order_history),
+                )
                 .route(
                     "/user/{user_id}",
                     web::get().to(handlers::query_user_orders),
@@ -76,7 +130,27 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
                 .route("/conditional", web::post().to(handlers::create_conditional_order))
                 .route("/conditional/list", web::get().to(handlers::get_conditional_orders))
                 .route("/conditional/statistics", web::get().to(handlers::get_conditional_order_statistics))
-                .route("/conditional/{conditional_order_id}", web::delete().to(handlers::cancel_conditional_order)),
+                .route("/conditional/{conditional_order_id}", web::delete().to(handlers::cancel_conditional_order))
+                // 价差单（跨合约套利） @yutiansut @quantaxis
+                .route("/spread", web::post().to(handlers::create_spread_order))
+                .route("/spread/list", web::get().to(handlers::get_spread_orders))
+                .route("/spread/statistics", web::get().to(handlers::get_spread_order_statistics))
+                .route("/spread/{spread_order_id}", web::delete().to(handlers::cancel_spread_order))
+                // 算法单（TWAP/VWAP） @yutiansut @quantaxis
+                .route("/algo", web::post().to(handlers::create_algo_order))
+                .route("/algo/list", web::get().to(handlers::get_algo_orders))
+                .route("/algo/statistics", web::get().to(handlers::get_algo_order_statistics))
+                .route("/algo/{algo_order_id}", web::get().to(handlers::get_algo_order))
+                .route("/algo/{algo_order_id}", web::delete().to(handlers::cancel_algo_order))
+                // 移仓换月 @yutiansut @quantaxis
+                .route("/roll", web::post().to(handlers::roll_position))
+                // 大宗交易协商成交 @yutiansut @quantaxis
+                .route("/block", web::post().to(handlers::create_block_trade))
+                .route("/block/list", web::get().to(handlers::get_block_trades))
+                .route("/block/statistics", web::get().to(handlers::get_block_trade_statistics))
+                .route("/block/{block_trade_id}/confirm", web::post().to(handlers::confirm_block_trade))
+                .route("/block/{block_trade_id}/reject", web::post().to(handlers::reject_block_trade))
+                .route("/block/{block_trade_id}/cancel", web::post().to(handlers::cancel_block_trade)),
         )
         // 持仓查询
         .service(
@@ -85,10 +159,22 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
                     "/account/{account_id}",
                     web::get().to(handlers::query_position),
                 ) // 按account_id查询
+                .route(
+                    "/account/{account_id}/lots",
+                    web::get().to(handlers::query_position_lots),
+                ) // 开仓批次明细 @yutiansut @quantaxis
+                .route(
+                    "/account/{account_id}/lot-method",
+                    web::put().to(handlers::set_position_lot_method),
+                ) // 批次平仓方式（FIFO/LIFO）
                 .route(
                     "/user/{user_id}",
                     web::get().to(handlers::query_positions_by_user),
-                ), // 按user_id查询所有
+                ) // 按user_id查询所有
+                .route(
+                    "/{user_id}/detail",
+                    web::get().to(handlers::query_position_detail),
+                ), // 逐笔开仓明细（含对账）
         )
         // 成交记录查询
         .service(
@@ -110,16 +196,47 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
                     "/orderbook/{instrument_id}",
                     web::get().to(market::get_orderbook),
                 )
+                .route(
+                    "/orderbook/{instrument_id}/history",
+                    web::get().to(data_query::query_orderbook_history),
+                ) // 历史盘口快照 @yutiansut @quantaxis
                 .route("/tick/{instrument_id}", web::get().to(market::get_tick))
+                .route("/ticks/batch", web::post().to(market::get_ticks_batch)) // 批量行情
                 .route(
                     "/trades/{instrument_id}",
                     web::get().to(market::get_recent_trades),
                 )
+                .route("/kline/batch", web::post().to(kline::get_kline_batch)) // 批量K线
                 .route(
                     "/kline/{instrument_id}",
                     web::get().to(kline::get_kline_data),
                 ), // K线数据
         )
+        // 因子历史（OLAP 落盘 + 未转换 WAL）@yutiansut @quantaxis
+        .service(
+            web::scope("/api/factor")
+                .route("/backfill", web::post().to(factor::backfill_factors))
+                .route(
+                    "/{factor_name}/{instrument_id}",
+                    web::get().to(factor::get_factor_history),
+                ),
+        )
+        // 用户消息中心 @yutiansut @quantaxis
+        .service(
+            web::scope("/api/notification")
+                .route("/list", web::get().to(notification::list_notifications))
+                .route("/read", web::post().to(notification::mark_notifications_read))
+                .route("/unread_count", web::get().to(notification::get_unread_count)),
+        )
+        // 重查询/导出异步任务 @yutiansut @quantaxis
+        .service(
+            web::scope("/api/tasks")
+                .route("", web::post().to(tasks::submit_task))
+                .route("", web::get().to(tasks::list_tasks))
+                .route("/{task_id}", web::get().to(tasks::get_task))
+                .route("/{task_id}/cancel", web::post().to(tasks::cancel_task))
+                .route("/{task_id}/download", web::get().to(tasks::download_task_result)),
+        )
         // 监控和统计
         .service(
             web::scope("/api/monitoring")
@@ -131,22 +248,50 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
                 )
                 .route("/orders", web::get().to(monitoring::get_orders_monitoring))
                 .route("/trades", web::get().to(monitoring::get_trades_monitoring))
+                .route(
+                    "/rejections",
+                    web::get().to(monitoring::get_rejections_monitoring),
+                )
+                .route(
+                    "/orderbooks",
+                    web::get().to(monitoring::get_orderbooks_monitoring),
+                )
+                .route(
+                    "/duplicate-orders",
+                    web::get().to(monitoring::get_duplicate_orders_monitoring),
+                )
                 .route(
                     "/storage",
                     web::get().to(monitoring::get_storage_monitoring),
                 )
+                .route(
+                    "/storage/partitions",
+                    web::get().to(monitoring::get_olap_partitions),
+                )
                 .route("/report", web::get().to(monitoring::generate_report)),
         )
         // 管理员功能 - 市场统计
-        .service(web::scope("/api/admin/market").route(
-            "/order-stats",
-            web::get().to(market::get_market_order_stats),
-        ))
+        .service(
+            web::scope("/api/admin/market")
+                .route(
+                    "/order-stats",
+                    web::get().to(market::get_market_order_stats),
+                )
+                .route("/anomalies", web::get().to(market::get_market_anomalies)),
+        )
         // 管理端路由 - 合约管理和结算管理
         .service(
             web::scope("/api/admin")
                 // 合约管理
                 .route("/instruments", web::get().to(admin::get_all_instruments))
+                .route(
+                    "/instruments/alias-conflicts",
+                    web::get().to(admin::get_alias_conflicts),
+                )
+                .route(
+                    "/instruments/import-ctp",
+                    web::post().to(admin::import_ctp_instruments),
+                )
                 .route(
                     "/instrument/create",
                     web::post().to(admin::create_instrument),
@@ -167,6 +312,28 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
                     "/instrument/{id}/delist",
                     web::delete().to(admin::delist_instrument),
                 )
+                // 新合约上市与上市保护 @yutiansut @quantaxis
+                .route(
+                    "/instrument/{id}/activate",
+                    web::put().to(admin::activate_instrument),
+                )
+                .route(
+                    "/instrument/{id}/listing-protection",
+                    web::get().to(admin::get_listing_protection),
+                )
+                .route(
+                    "/instrument/{id}/listing-protection",
+                    web::put().to(admin::set_listing_protection),
+                )
+                .route(
+                    "/instrument/{id}/listing-protection",
+                    web::delete().to(admin::remove_listing_protection),
+                )
+                // 批量开户
+                .route(
+                    "/accounts/batch-open",
+                    web::post().to(admin::batch_open_accounts),
+                )
                 // 结算管理
                 .route(
                     "/settlement/set-price",
@@ -180,14 +347,58 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
                     "/settlement/execute",
                     web::post().to(admin::execute_settlement),
                 )
+                .route(
+                    "/settlement/progress",
+                    web::get().to(admin::get_settlement_progress),
+                )
                 .route(
                     "/settlement/history",
                     web::get().to(admin::get_settlement_history),
                 )
+                .route(
+                    "/fixed-point/drift",
+                    web::get().to(admin::get_fixed_point_drift),
+                )
                 .route(
                     "/settlement/detail/{date}",
                     web::get().to(admin::get_settlement_detail),
-                ),
+                )
+                .route(
+                    "/settlement/reconciliation",
+                    web::get().to(admin::get_settlement_reconciliation),
+                )
+                .route(
+                    "/settlement/deficits",
+                    web::get().to(admin::get_settlement_deficits),
+                )
+                .route(
+                    "/settlement/risk-reserve",
+                    web::get().to(admin::get_risk_reserve),
+                )
+                // 存储运维（手动 flush / compaction） @yutiansut @quantaxis
+                .route("/storage/flush", web::post().to(admin::flush_storage))
+                .route("/storage/compact", web::post().to(admin::compact_storage))
+                .route("/storage/tasks", web::get().to(admin::list_storage_tasks))
+                .route(
+                    "/storage/tasks/{task_id}",
+                    web::get().to(admin::get_storage_task),
+                )
+                // 紧急停机 @yutiansut @quantaxis
+                .route(
+                    "/emergency-shutdown",
+                    web::post().to(admin::emergency_shutdown),
+                )
+                .route(
+                    "/emergency-shutdown",
+                    web::get().to(admin::get_emergency_status),
+                )
+                // 交易日重置（仅测试环境） @yutiansut @quantaxis
+                .route(
+                    "/reset-trading-day",
+                    web::post().to(admin::reset_trading_day),
+                )
+                // 虚拟时间快进（仅测试环境，生产配置下 404） @yutiansut @quantaxis
+                .route("/testing/advance-time", web::post().to(admin::advance_time)),
         )
         // 管理端路由 - 账户管理、资金管理、风控监控
         .service(
@@ -200,7 +411,19 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
                 )
                 // 全市场订单/成交查询 (管理端) @yutiansut @quantaxis
                 .route("/orders", web::get().to(management::list_all_orders))
+                .route(
+                    "/orders/watchdog",
+                    web::get().to(management::get_order_watchdog),
+                )
+                .route(
+                    "/orders/reconcile",
+                    web::post().to(management::reconcile_orders),
+                )
                 .route("/trades", web::get().to(management::list_all_trades))
+                .route(
+                    "/trades/bus",
+                    web::get().to(management::get_trade_bus_stats),
+                )
                 // 资金管理
                 .route("/deposit", web::post().to(management::deposit))
                 .route("/withdraw", web::post().to(management::withdraw))
@@ -219,6 +442,14 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
                     "/risk/margin-summary",
                     web::get().to(management::get_margin_summary),
                 )
+                .route(
+                    "/risk/margin-calls",
+                    web::get().to(management::get_margin_call_accounts),
+                )
+                .route(
+                    "/risk/auto-margin",
+                    web::get().to(management::get_auto_margin_records),
+                )
                 .route(
                     "/risk/liquidations",
                     web::get().to(management::get_liquidation_records),
@@ -226,6 +457,94 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
                 .route(
                     "/risk/force-liquidate",
                     web::post().to(management::force_liquidate_account),
+                )
+                // 风险历史回放 @yutiansut @quantaxis
+                .route(
+                    "/risk/history/market",
+                    web::get().to(management::get_market_risk_history),
+                )
+                .route(
+                    "/risk/history/stats",
+                    web::get().to(management::get_risk_history_stats),
+                )
+                .route(
+                    "/risk/history/{user_id}",
+                    web::get().to(management::get_risk_history),
+                )
+                // 追踪采样（可热更新） @yutiansut @quantaxis
+                .route(
+                    "/tracing/sampling",
+                    web::get().to(management::get_tracing_sampling),
+                )
+                .route(
+                    "/tracing/sampling",
+                    web::put().to(management::update_tracing_sampling),
+                )
+                // 推送端到端延迟 @yutiansut @quantaxis
+                .route("/push-latency", web::get().to(management::get_push_latency))
+                .route(
+                    "/push-latency/config",
+                    web::put().to(management::update_push_latency_config),
+                )
+                .route(
+                    "/push-latency/{session_id}",
+                    web::get().to(management::get_session_push_latency),
+                )
+                // 网关过载保护 @yutiansut @quantaxis
+                .route("/overload", web::get().to(management::get_overload_status))
+                .route(
+                    "/overload/config",
+                    web::put().to(management::update_overload_config),
+                )
+                // 功能灰度开关 @yutiansut @quantaxis
+                .route(
+                    "/feature-gates",
+                    web::get().to(management::get_feature_gates),
+                )
+                .route(
+                    "/feature-gates",
+                    web::put().to(management::update_feature_gates),
+                )
+                .route(
+                    "/feature-gates/{feature}/{user_id}",
+                    web::get().to(management::get_feature_decision),
+                )
+                // WebSocket 多终端会话 @yutiansut @quantaxis
+                .route("/sessions", web::get().to(management::get_ws_sessions))
+                .route(
+                    "/sessions/policy",
+                    web::put().to(management::set_login_policy),
+                )
+                .route(
+                    "/sessions/policy/{user_id}",
+                    web::put().to(management::set_user_login_policy),
+                )
+                .route(
+                    "/sessions/user/{user_id}",
+                    web::get().to(management::get_user_ws_sessions),
+                )
+                .route(
+                    "/sessions/quota",
+                    web::get().to(management::get_subscription_quota),
+                )
+                .route(
+                    "/sessions/quota/config",
+                    web::put().to(management::update_subscription_quota),
+                )
+                .route(
+                    "/sessions/{session_id}",
+                    web::delete().to(management::kick_ws_session),
+                )
+                // 管理端手动强平 + 执行跟踪 @yutiansut @quantaxis
+                .route("/liquidate", web::post().to(management::manual_liquidate))
+                .route(
+                    "/liquidate/{liquidation_id}",
+                    web::get().to(management::get_liquidation_status),
+                )
+                // 持仓均价重算（一次性修复） @yutiansut @quantaxis
+                .route(
+                    "/positions/rebuild-cost",
+                    web::post().to(management::rebuild_position_cost),
                 ),
         )
         // ==================== Phase 12-13: 账户管理扩展功能 ====================
@@ -245,6 +564,21 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
                 .route("/status/{account_id}", web::get().to(account_admin::get_account_status))
                 .route("/freeze", web::post().to(account_admin::freeze_account))
                 .route("/unfreeze", web::post().to(account_admin::unfreeze_account))
+                // 交易限制（只平仓 / 暂停交易）
+                .route("/restriction", web::post().to(account_admin::set_trading_restriction))
+                .route("/restriction/{account_id}", web::get().to(account_admin::get_trading_restriction))
+                .route("/restrictions", web::get().to(account_admin::list_trading_restrictions))
+                // 账户标签（运营分类与按标签批量操作）
+                .route("/tags", web::get().to(account_admin::list_account_tags))
+                .route("/tags/add", web::post().to(account_admin::add_account_tags))
+                .route("/tags/remove", web::post().to(account_admin::remove_account_tags))
+                .route("/tags/rename", web::post().to(account_admin::rename_account_tag))
+                .route("/tags/delete", web::post().to(account_admin::delete_account_tag))
+                .route("/tags/accounts", web::get().to(account_admin::get_accounts_by_tags))
+                .route("/tags/account/{account_id}", web::get().to(account_admin::get_account_tags))
+                .route("/tags/restriction", web::post().to(account_admin::set_restriction_by_tags))
+                .route("/tags/margin-surcharge", web::post().to(account_admin::set_margin_surcharge_by_tags))
+                .route("/tags/audit", web::get().to(account_admin::get_account_tag_audit))
         )
         // Phase 13: 审计日志
         .service(
@@ -0,0 +1,91 @@
+//! 账户止损线 HTTP API
+//!
+//! 用户设置/取消账户当日止损线，查询当日止损状态
+//!
+//! @yutiansut @quantaxis
+
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::handlers::AppState;
+use super::models::ApiResponse;
+use crate::risk::{StopLossLine, StopLossState};
+
+/// 设置止损线请求（金额与比例至少填一个）
+#[derive(Debug, Deserialize)]
+pub struct SetStopLossRequest {
+    pub max_loss_amount: Option<f64>,
+    pub max_loss_ratio: Option<f64>,
+}
+
+/// 止损线与当日状态
+#[derive(Debug, Serialize)]
+pub struct StopLossData {
+    pub account_id: String,
+    pub line: Option<StopLossLine>,
+    pub state: Option<StopLossState>,
+}
+
+fn stop_loss_unavailable() -> HttpResponse {
+    HttpResponse::ServiceUnavailable().json(ApiResponse::<()>::error(
+        503,
+        "Stop loss not enabled".to_string(),
+    ))
+}
+
+/// 查询止损线与当日状态
+///
+/// GET /api/account/{account_id}/stop-loss
+pub async fn get_stop_loss(
+    account_id: web::Path<String>,
+    state: web::Data<Arc<AppState>>,
+) -> HttpResponse {
+    let Some(ref stop_loss) = state.stop_loss else {
+        return stop_loss_unavailable();
+    };
+
+    HttpResponse::Ok().json(ApiResponse::success(StopLossData {
+        line: stop_loss.get_line(&account_id),
+        state: stop_loss.get_state(&account_id),
+        account_id: account_id.into_inner(),
+    }))
+}
+
+/// 设置止损线
+///
+/// PUT /api/account/{account_id}/stop-loss
+pub async fn set_stop_loss(
+    account_id: web::Path<String>,
+    req: web::Json<SetStopLossRequest>,
+    state: web::Data<Arc<AppState>>,
+) -> HttpResponse {
+    let Some(ref stop_loss) = state.stop_loss else {
+        return stop_loss_unavailable();
+    };
+
+    match stop_loss.set_line(&account_id, req.max_loss_amount, req.max_loss_ratio) {
+        Ok(line) => HttpResponse::Ok().json(ApiResponse::success(line)),
+        Err(e) => HttpResponse::BadRequest().json(ApiResponse::<()>::error(400, e.to_string())),
+    }
+}
+
+/// 取消止损线（当日已触发的暂停不受影响）
+///
+/// DELETE /api/account/{account_id}/stop-loss
+pub async fn remove_stop_loss(
+    account_id: web::Path<String>,
+    state: web::Data<Arc<AppState>>,
+) -> HttpResponse {
+    let Some(ref stop_loss) = state.stop_loss else {
+        return stop_loss_unavailable();
+    };
+
+    match stop_loss.remove_line(&account_id) {
+        Some(line) => HttpResponse::Ok().json(ApiResponse::success(line)),
+        None => HttpResponse::NotFound().json(ApiResponse::<()>::error(
+            404,
+            format!("No stop loss line for account {}", account_id),
+        )),
+    }
+}
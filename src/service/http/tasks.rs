@@ -0,0 +1,210 @@
+//! 异步任务 HTTP API
+//!
+//! 重查询/导出提交为后台任务：提交返回 task_id，轮询进度，完成后凭签名链接下载结果，支持取消
+//!
+//! @yutiansut @quantaxis
+
+use actix_files::NamedFile;
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::{web, HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::handlers::AppState;
+use super::models::ApiResponse;
+use crate::service::async_task::{AsyncTaskInfo, AsyncTaskManager};
+use crate::ExchangeError;
+
+/// 提交任务请求
+#[derive(Debug, Deserialize)]
+pub struct SubmitTaskRequest {
+    pub user_id: String,
+    /// 任务类型（如 data_export）
+    pub kind: String,
+    /// 任务参数（data_export 同 `/api/data/export` 的查询参数）
+    #[serde(default)]
+    pub params: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListTasksQuery {
+    pub user_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CancelTaskRequest {
+    pub user_id: String,
+}
+
+/// 下载链接参数（由 `download_url` 生成）
+#[derive(Debug, Deserialize)]
+pub struct DownloadQuery {
+    pub expires: i64,
+    pub signature: String,
+}
+
+/// 任务视图（元数据 + 进度 + 下载链接）
+#[derive(Debug, Serialize)]
+pub struct TaskView {
+    #[serde(flatten)]
+    pub task: AsyncTaskInfo,
+    pub progress: f64,
+    /// 已完成任务的签名下载链接
+    pub download_url: Option<String>,
+}
+
+impl TaskView {
+    fn new(manager: &AsyncTaskManager, task: AsyncTaskInfo) -> Self {
+        let download_url = manager.download_url(&task.task_id, chrono::Utc::now().timestamp());
+        Self {
+            progress: task.progress(),
+            task,
+            download_url,
+        }
+    }
+}
+
+fn task_manager_unavailable() -> HttpResponse {
+    HttpResponse::ServiceUnavailable().json(ApiResponse::<()>::error(
+        503,
+        "Async task service not enabled".to_string(),
+    ))
+}
+
+fn task_not_found(task_id: &str) -> HttpResponse {
+    HttpResponse::NotFound().json(ApiResponse::<()>::error(
+        404,
+        format!("Task not found: {}", task_id),
+    ))
+}
+
+fn task_error(e: ExchangeError) -> HttpResponse {
+    match e {
+        ExchangeError::InvalidParameter(msg) => {
+            HttpResponse::BadRequest().json(ApiResponse::<()>::error(400, msg))
+        }
+        ExchangeError::PermissionDenied(msg) => {
+            HttpResponse::Forbidden().json(ApiResponse::<()>::error(403, msg))
+        }
+        ExchangeError::ServiceError(msg) => {
+            HttpResponse::TooManyRequests().json(ApiResponse::<()>::error(429, msg))
+        }
+        e => HttpResponse::InternalServerError().json(ApiResponse::<()>::error(500, e.to_string())),
+    }
+}
+
+/// 提交任务
+///
+/// POST /api/tasks
+pub async fn submit_task(
+    req: web::Json<SubmitTaskRequest>,
+    state: web::Data<Arc<AppState>>,
+) -> HttpResponse {
+    let Some(ref manager) = state.task_manager else {
+        return task_manager_unavailable();
+    };
+
+    let req = req.into_inner();
+    match manager.submit(&req.user_id, &req.kind, req.params) {
+        Ok(task) => {
+            HttpResponse::Accepted().json(ApiResponse::success(TaskView::new(manager, task)))
+        }
+        Err(e) => task_error(e),
+    }
+}
+
+/// 用户的任务列表
+///
+/// GET /api/tasks?user_id=
+pub async fn list_tasks(
+    query: web::Query<ListTasksQuery>,
+    state: web::Data<Arc<AppState>>,
+) -> HttpResponse {
+    let Some(ref manager) = state.task_manager else {
+        return task_manager_unavailable();
+    };
+
+    let tasks: Vec<TaskView> = manager
+        .list(&query.user_id)
+        .into_iter()
+        .map(|task| TaskView::new(manager, task))
+        .collect();
+    HttpResponse::Ok().json(ApiResponse::success(tasks))
+}
+
+/// 查询任务进度与状态
+///
+/// GET /api/tasks/{task_id}
+pub async fn get_task(task_id: web::Path<String>, state: web::Data<Arc<AppState>>) -> HttpResponse {
+    let Some(ref manager) = state.task_manager else {
+        return task_manager_unavailable();
+    };
+
+    match manager.get(&task_id) {
+        Some(task) => HttpResponse::Ok().json(ApiResponse::success(TaskView::new(manager, task))),
+        None => task_not_found(&task_id),
+    }
+}
+
+/// 取消任务（排队中立即取消，执行中由 worker 检查取消标志后结束）
+///
+/// POST /api/tasks/{task_id}/cancel
+pub async fn cancel_task(
+    task_id: web::Path<String>,
+    req: web::Json<CancelTaskRequest>,
+    state: web::Data<Arc<AppState>>,
+) -> HttpResponse {
+    let Some(ref manager) = state.task_manager else {
+        return task_manager_unavailable();
+    };
+    if manager.get(&task_id).is_none() {
+        return task_not_found(&task_id);
+    }
+
+    match manager.cancel(&task_id, &req.user_id) {
+        Ok(task) => HttpResponse::Ok().json(ApiResponse::success(TaskView::new(manager, task))),
+        Err(e) => task_error(e),
+    }
+}
+
+/// 下载任务结果（签名校验通过且未过期）
+///
+/// GET /api/tasks/{task_id}/download?expires=&signature=
+pub async fn download_task_result(
+    http_req: HttpRequest,
+    task_id: web::Path<String>,
+    query: web::Query<DownloadQuery>,
+    state: web::Data<Arc<AppState>>,
+) -> HttpResponse {
+    let Some(ref manager) = state.task_manager else {
+        return task_manager_unavailable();
+    };
+
+    let (path, task) = match manager.resolve_download(
+        &task_id,
+        query.expires,
+        &query.signature,
+        chrono::Utc::now().timestamp(),
+    ) {
+        Ok(resolved) => resolved,
+        Err(e) => return task_error(e),
+    };
+
+    match NamedFile::open_async(&path).await {
+        Ok(file) => {
+            let file_name = task.result_file.unwrap_or_else(|| task.task_id.clone());
+            let mut file = file.set_content_disposition(ContentDisposition {
+                disposition: DispositionType::Attachment,
+                parameters: vec![DispositionParam::Filename(file_name)],
+            });
+            if let Some(mime) = task.content_type.and_then(|ct| ct.parse().ok()) {
+                file = file.set_content_type(mime);
+            }
+            file.into_response(&http_req)
+        }
+        Err(e) => HttpResponse::InternalServerError().json(ApiResponse::<()>::error(
+            500,
+            format!("Open task result failed: {}", e),
+        )),
+    }
+}
@@ -77,9 +77,10 @@ impl TransferStore {
         let filtered: Vec<TransferRecord> = records
             .into_iter()
             .filter(|r| {
-                let date_str = chrono::DateTime::from_timestamp_millis(r.datetime)
-                    .map(|dt| dt.format("%Y-%m-%d").to_string())
-                    .unwrap_or_default();
+                let date_str = crate::utils::time_service::time_service()
+                    .natural_day_of(r.datetime)
+                    .format("%Y-%m-%d")
+                    .to_string();
 
                 let after_start = start_date
                     .map(|s| date_str.as_str() >= s)
@@ -0,0 +1,508 @@
+//! HTTP API 版本化与响应字段转换
+//!
+//! @yutiansut @quantaxis
+//!
+//! 账户/订单/持仓查询接口按路径前缀区分版本：
+//! - `/api/v1/...`：冻结的旧结构，字段与历史版本完全一致
+//! - `/api/v2/...`：新结构，按 [`V1_TO_V2_FIELD_CHANGES`] 改名/裁剪字段
+//! - `/api/...`（不带版本）：默认按 v1 返回，可用 `X-API-Version` 头协商版本，
+//!   响应带 `Deprecation` / `Link` 头提示迁移到带版本前缀的路径
+//!
+//! handler 只构造 v1 模型，由 [`ApiVersionContext::convert`] 统一转换为对应版本的 DTO；
+//! 字段变更清单同时用于生成文档（[`field_changes_markdown`]），保证文档与实际输出一致。
+
+use actix_web::dev::Payload;
+use actix_web::error::InternalError;
+use actix_web::http::{header, StatusCode};
+use actix_web::{Error, FromRequest, HttpRequest, HttpResponse, HttpResponseBuilder};
+use futures::future::{ready, Ready};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::fmt;
+
+use super::models::ApiResponse;
+
+/// 版本协商请求头（仅对不带版本前缀的路径生效）
+pub const API_VERSION_HEADER: &str = "X-API-Version";
+
+/// API 版本
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiVersion {
+    V1,
+    V2,
+}
+
+impl ApiVersion {
+    /// 不带版本前缀时的默认版本
+    pub const DEFAULT: ApiVersion = ApiVersion::V1;
+    /// 最新版本
+    pub const LATEST: ApiVersion = ApiVersion::V2;
+    /// 所有支持的版本
+    pub const ALL: [ApiVersion; 2] = [ApiVersion::V1, ApiVersion::V2];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiVersion::V1 => "v1",
+            ApiVersion::V2 => "v2",
+        }
+    }
+
+    /// 路径前缀，如 `/api/v1`
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            ApiVersion::V1 => "/api/v1",
+            ApiVersion::V2 => "/api/v2",
+        }
+    }
+
+    /// 解析版本号，接受 `v2` / `V2` / `2`
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        let number = value
+            .strip_prefix('v')
+            .or_else(|| value.strip_prefix('V'))
+            .unwrap_or(value);
+        match number {
+            "1" => Some(ApiVersion::V1),
+            "2" => Some(ApiVersion::V2),
+            _ => None,
+        }
+    }
+
+    /// 从请求路径解析版本前缀，不带版本前缀时返回 None
+    pub fn from_path(path: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|version| {
+            path.strip_prefix(version.prefix())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        })
+    }
+}
+
+impl fmt::Display for ApiVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 版本化的响应资源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiResource {
+    Account,
+    Order,
+    Position,
+}
+
+impl ApiResource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiResource::Account => "account",
+            ApiResource::Order => "order",
+            ApiResource::Position => "position",
+        }
+    }
+}
+
+/// 字段变更类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldChangeKind {
+    /// 改名（值不变）
+    Renamed,
+    /// 裁剪（v2 不再返回）
+    Removed,
+}
+
+/// v1 → v2 单个字段变更
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct FieldChange {
+    pub resource: ApiResource,
+    pub kind: FieldChangeKind,
+    /// v1 字段名
+    pub v1_field: &'static str,
+    /// v2 字段名（Removed 时为 None）
+    pub v2_field: Option<&'static str>,
+    /// 变更说明
+    pub note: &'static str,
+}
+
+impl FieldChange {
+    const fn renamed(
+        resource: ApiResource,
+        v1_field: &'static str,
+        v2_field: &'static str,
+        note: &'static str,
+    ) -> Self {
+        Self {
+            resource,
+            kind: FieldChangeKind::Renamed,
+            v1_field,
+            v2_field: Some(v2_field),
+            note,
+        }
+    }
+
+    const fn removed(resource: ApiResource, v1_field: &'static str, note: &'static str) -> Self {
+        Self {
+            resource,
+            kind: FieldChangeKind::Removed,
+            v1_field,
+            v2_field: None,
+            note,
+        }
+    }
+}
+
+/// v1 → v2 字段变更清单（转换层与文档生成共用，新增变更只改这里）
+pub const V1_TO_V2_FIELD_CHANGES: &[FieldChange] = &[
+    FieldChange::renamed(
+        ApiResource::Account,
+        "user_id",
+        "account_id",
+        "v1 中实际返回的是账户 ID",
+    ),
+    FieldChange::renamed(
+        ApiResource::Account,
+        "user_name",
+        "account_name",
+        "v1 中实际返回的是账户名称",
+    ),
+    FieldChange::renamed(
+        ApiResource::Account,
+        "profit",
+        "close_profit",
+        "平仓盈亏，与浮动盈亏区分",
+    ),
+    FieldChange::removed(
+        ApiResource::Account,
+        "frozen",
+        "冗余字段，等于 balance - available",
+    ),
+    FieldChange::renamed(ApiResource::Order, "volume", "volume_orign", "委托数量"),
+    FieldChange::renamed(ApiResource::Order, "price", "limit_price", "委托价格"),
+    FieldChange::renamed(
+        ApiResource::Order,
+        "submit_time",
+        "insert_date_time",
+        "下单时间，与 DIFF 协议字段一致",
+    ),
+    FieldChange::renamed(
+        ApiResource::Position,
+        "cost_long",
+        "open_price_long",
+        "多头开仓均价",
+    ),
+    FieldChange::renamed(
+        ApiResource::Position,
+        "cost_short",
+        "open_price_short",
+        "空头开仓均价",
+    ),
+    FieldChange::renamed(
+        ApiResource::Position,
+        "profit_long",
+        "float_profit_long",
+        "多头浮动盈亏",
+    ),
+    FieldChange::renamed(
+        ApiResource::Position,
+        "profit_short",
+        "float_profit_short",
+        "空头浮动盈亏",
+    ),
+];
+
+/// 资源对应的字段变更
+pub fn field_changes(resource: ApiResource) -> impl Iterator<Item = &'static FieldChange> {
+    V1_TO_V2_FIELD_CHANGES
+        .iter()
+        .filter(move |change| change.resource == resource)
+}
+
+/// 将 v1 结构的 JSON 转换为目标版本（数组逐项转换）
+pub fn convert_value(resource: ApiResource, version: ApiVersion, value: Value) -> Value {
+    match (version, value) {
+        (ApiVersion::V1, value) => value,
+        (ApiVersion::V2, Value::Array(items)) => Value::Array(
+            items
+                .into_iter()
+                .map(|item| convert_value(resource, version, item))
+                .collect(),
+        ),
+        (ApiVersion::V2, Value::Object(object)) => Value::Object(apply_changes(resource, object)),
+        (ApiVersion::V2, value) => value,
+    }
+}
+
+fn apply_changes(resource: ApiResource, mut object: Map<String, Value>) -> Map<String, Value> {
+    for change in field_changes(resource) {
+        if let Some(value) = object.remove(change.v1_field) {
+            if let Some(v2_field) = change.v2_field {
+                object.insert(v2_field.to_string(), value);
+            }
+        }
+    }
+    object
+}
+
+/// 生成 v1 → v2 字段变更的 Markdown 表格（用于 API 文档）
+pub fn field_changes_markdown() -> String {
+    let mut out =
+        String::from("| 资源 | v1 字段 | v2 字段 | 说明 |\n|------|---------|---------|------|\n");
+    for change in V1_TO_V2_FIELD_CHANGES {
+        out.push_str(&format!(
+            "| {} | `{}` | {} | {} |\n",
+            change.resource.as_str(),
+            change.v1_field,
+            change
+                .v2_field
+                .map(|field| format!("`{}`", field))
+                .unwrap_or_else(|| "（移除）".to_string()),
+            change.note
+        ));
+    }
+    out
+}
+
+/// 请求的版本上下文（actix 提取器）
+///
+/// 路径带版本前缀时以路径为准；不带前缀时读取 `X-API-Version` 头，缺省为 v1，并标记为已弃用路径。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiVersionContext {
+    pub version: ApiVersion,
+    /// 请求是否走的不带版本前缀的旧路径
+    pub deprecated: bool,
+    /// 旧路径对应的带版本前缀的新路径
+    successor: Option<String>,
+}
+
+impl ApiVersionContext {
+    pub fn negotiate(req: &HttpRequest) -> Result<Self, String> {
+        let path = req.path();
+        if let Some(version) = ApiVersion::from_path(path) {
+            return Ok(Self {
+                version,
+                deprecated: false,
+                successor: None,
+            });
+        }
+
+        let version = match req.headers().get(API_VERSION_HEADER) {
+            Some(value) => value
+                .to_str()
+                .ok()
+                .and_then(ApiVersion::parse)
+                .ok_or_else(|| format!("Unsupported API version: {:?}", value))?,
+            None => ApiVersion::DEFAULT,
+        };
+        let successor = path
+            .strip_prefix("/api")
+            .map(|rest| format!("{}{}", version.prefix(), rest));
+        Ok(Self {
+            version,
+            deprecated: true,
+            successor,
+        })
+    }
+
+    /// 带版本响应头的响应构造器
+    pub fn response(&self, status: StatusCode) -> HttpResponseBuilder {
+        let mut builder = HttpResponseBuilder::new(status);
+        builder.insert_header((API_VERSION_HEADER, self.version.as_str()));
+        if self.deprecated {
+            builder.insert_header(("Deprecation", "true"));
+            if let Some(successor) = &self.successor {
+                builder.insert_header((
+                    header::LINK,
+                    format!("<{}>; rel=\"successor-version\"", successor),
+                ));
+            }
+        }
+        builder
+    }
+
+    /// 将 v1 模型转换为当前版本的 JSON
+    pub fn convert<T: Serialize>(&self, resource: ApiResource, data: &T) -> Value {
+        let value = serde_json::to_value(data).unwrap_or(Value::Null);
+        convert_value(resource, self.version, value)
+    }
+}
+
+impl FromRequest for ApiVersionContext {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(Self::negotiate(req).map_err(|message| {
+            let response =
+                HttpResponse::BadRequest().json(ApiResponse::<()>::error(400, message.clone()));
+            InternalError::from_response(message, response).into()
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::http::models::{AccountInfo, PositionInfo};
+    use actix_web::{test, web, App};
+
+    fn sample_account() -> AccountInfo {
+        AccountInfo {
+            user_id: "ACC001".to_string(),
+            user_name: "主账户".to_string(),
+            balance: 100_000.0,
+            available: 80_000.0,
+            frozen: 20_000.0,
+            margin: 20_000.0,
+            profit: 150.0,
+            risk_ratio: 0.2,
+            account_type: "individual".to_string(),
+            created_at: 0,
+        }
+    }
+
+    async fn account_handler(version: ApiVersionContext) -> HttpResponse {
+        let data = version.convert(ApiResource::Account, &sample_account());
+        version
+            .response(StatusCode::OK)
+            .json(ApiResponse::success(data))
+    }
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(ApiVersion::parse("v2"), Some(ApiVersion::V2));
+        assert_eq!(ApiVersion::parse(" 1 "), Some(ApiVersion::V1));
+        assert_eq!(ApiVersion::parse("v3"), None);
+        assert_eq!(
+            ApiVersion::from_path("/api/v2/account/A1"),
+            Some(ApiVersion::V2)
+        );
+        assert_eq!(ApiVersion::from_path("/api/v1"), Some(ApiVersion::V1));
+        assert_eq!(ApiVersion::from_path("/api/v10/account"), None);
+        assert_eq!(ApiVersion::from_path("/api/account/A1"), None);
+    }
+
+    #[test]
+    fn test_convert_applies_field_changes() {
+        let v1 = serde_json::to_value(sample_account()).unwrap();
+        assert_eq!(
+            convert_value(ApiResource::Account, ApiVersion::V1, v1.clone()),
+            v1
+        );
+
+        let v2 = convert_value(ApiResource::Account, ApiVersion::V2, v1.clone());
+        for change in field_changes(ApiResource::Account) {
+            assert!(
+                v2.get(change.v1_field).is_none(),
+                "{} still present",
+                change.v1_field
+            );
+            if let Some(v2_field) = change.v2_field {
+                assert_eq!(v2[v2_field], v1[change.v1_field]);
+            }
+        }
+        // 未列入变更清单的字段原样保留
+        assert_eq!(v2["balance"], 100_000.0);
+
+        let positions = vec![PositionInfo {
+            account_id: "ACC001".to_string(),
+            instrument_id: "SHFE.cu2501".to_string(),
+            volume_long: 2.0,
+            volume_short: 0.0,
+            volume_long_frozen: 0.0,
+            volume_short_frozen: 0.0,
+            cost_long: 70_000.0,
+            cost_short: 0.0,
+            profit_long: 500.0,
+            profit_short: 0.0,
+        }];
+        let v2 = convert_value(
+            ApiResource::Position,
+            ApiVersion::V2,
+            serde_json::to_value(&positions).unwrap(),
+        );
+        assert_eq!(v2[0]["open_price_long"], 70_000.0);
+        assert_eq!(v2[0]["float_profit_long"], 500.0);
+        assert!(v2[0].get("cost_long").is_none());
+    }
+
+    #[test]
+    fn test_docs_list_all_field_changes() {
+        let doc = include_str!("../../../docs/04_api/http/user_api.md");
+        for line in field_changes_markdown().lines() {
+            assert!(
+                doc.contains(line),
+                "user_api.md missing field change row: {}",
+                line
+            );
+        }
+    }
+
+    #[actix::test]
+    async fn test_version_negotiation() {
+        let app = test::init_service(
+            App::new()
+                .route("/api/v1/account/{id}", web::get().to(account_handler))
+                .route("/api/v2/account/{id}", web::get().to(account_handler))
+                .route("/api/account/{id}", web::get().to(account_handler)),
+        )
+        .await;
+
+        // v1：冻结结构，无弃用提示
+        let req = test::TestRequest::get()
+            .uri("/api/v1/account/ACC001")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get(API_VERSION_HEADER).unwrap(), "v1");
+        assert!(resp.headers().get("Deprecation").is_none());
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["data"]["user_id"], "ACC001");
+        assert_eq!(body["data"]["frozen"], 20_000.0);
+
+        // v2：路径优先于请求头
+        let req = test::TestRequest::get()
+            .uri("/api/v2/account/ACC001")
+            .insert_header((API_VERSION_HEADER, "1"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get(API_VERSION_HEADER).unwrap(), "v2");
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["data"]["account_id"], "ACC001");
+        assert!(body["data"].get("user_id").is_none());
+        assert!(body["data"].get("frozen").is_none());
+
+        // 不带版本：默认 v1 + 弃用提示
+        let req = test::TestRequest::get()
+            .uri("/api/account/ACC001")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get(API_VERSION_HEADER).unwrap(), "v1");
+        assert_eq!(resp.headers().get("Deprecation").unwrap(), "true");
+        assert_eq!(
+            resp.headers().get(header::LINK).unwrap(),
+            "</api/v1/account/ACC001>; rel=\"successor-version\""
+        );
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["data"]["user_id"], "ACC001");
+
+        // 不带版本 + 请求头协商 v2
+        let req = test::TestRequest::get()
+            .uri("/api/account/ACC001")
+            .insert_header((API_VERSION_HEADER, "v2"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get("Deprecation").unwrap(), "true");
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["data"]["account_id"], "ACC001");
+
+        // 不支持的版本
+        let req = test::TestRequest::get()
+            .uri("/api/account/ACC001")
+            .insert_header((API_VERSION_HEADER, "v9"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+    }
+}
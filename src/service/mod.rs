@@ -1,4 +1,6 @@
 //! 服务层模块
 
+pub mod async_task;
 pub mod http;
+pub mod overload;
 pub mod websocket;
@@ -0,0 +1,817 @@
+//! 系统过载保护（load shedding）
+//!
+//! @yutiansut @quantaxis
+//!
+//! 过载时主动分级降级，避免超过容量后所有请求一起变慢直至超时：
+//! - 负载 = 各过载信号占用率取大：在途请求数 / `max_in_flight`、
+//!   订单队列（优先级队列 + 撮合分片队列）/ `max_queue_depth`、
+//!   存储队列（成交事件总线积压）/ `max_storage_queue_depth`、
+//!   通知队列（通知中心优先级队列）/ `max_notification_queue_depth`、
+//!   请求处理延迟（EWMA）/ `max_latency_ms`；未设置探针的队列不参与
+//! - 负载达到 `high_water`：拒绝非关键请求（行情查询、历史查询），返回 503 + Retry-After，
+//!   快照广播等非关键任务降频（`non_critical_slowdown` 倍间隔）
+//! - 负载达到 `critical_high_water`：交易请求也开始拒绝，非关键任务暂停；撤单始终放行
+//! - 回落到 `high_water` 以下恢复交易请求，回落到 `low_water` 以下完全解除（滞回，避免抖动），
+//!   降级后需持续低于恢复水位 `recover_hold_ms` 才恢复
+//! - 健康检查、监控、管理端请求不计入在途数，也不会被拒绝
+//!
+//! 降级/恢复记入事件列表并告警（降级 warn 日志 + `qaexchange_overload_transitions_total`）。
+//! 当前水位通过 `/api/management/overload` 与 Prometheus 指标 `qaexchange_overload_*` 暴露。
+
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::observability::metrics::{
+    GATEWAY_IN_FLIGHT, GATEWAY_QUEUE_DEPTH, OVERLOAD_LEVEL, OVERLOAD_LOAD_RATIO,
+    OVERLOAD_REJECTED_TOTAL, OVERLOAD_SIGNAL_RATIO, OVERLOAD_TRANSITIONS_TOTAL,
+};
+
+/// 保留的降级/恢复事件数
+const MAX_EVENTS: usize = 100;
+
+lazy_static::lazy_static! {
+    /// 全局过载保护器（HTTP 中间件与 WebSocket 会话共用）
+    pub static ref OVERLOAD_GUARD: Arc<OverloadGuard> = Arc::new(OverloadGuard::new(OverloadConfig::default()));
+}
+
+/// 过载保护配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OverloadConfig {
+    /// 是否启用
+    #[serde(default)]
+    pub enabled: bool,
+    /// 在途请求容量（信号量大小）
+    #[serde(default = "default_max_in_flight")]
+    pub max_in_flight: usize,
+    /// 下游队列容量（优先级队列 + 撮合队列）
+    #[serde(default = "default_max_queue_depth")]
+    pub max_queue_depth: usize,
+    /// 高水位：超过后拒绝非关键请求 (0.0 - 1.0)
+    #[serde(default = "default_high_water")]
+    pub high_water: f64,
+    /// 更高水位：超过后交易请求也拒绝
+    #[serde(default = "default_critical_high_water")]
+    pub critical_high_water: f64,
+    /// 低水位：回落到此以下解除保护
+    #[serde(default = "default_low_water")]
+    pub low_water: f64,
+    /// 503 响应的 Retry-After（秒）
+    #[serde(default = "default_retry_after_secs")]
+    pub retry_after_secs: u64,
+    /// 存储队列容量（成交事件总线积压）
+    #[serde(default = "default_max_storage_queue_depth")]
+    pub max_storage_queue_depth: usize,
+    /// 通知队列容量（通知中心优先级队列）
+    #[serde(default = "default_max_notification_queue_depth")]
+    pub max_notification_queue_depth: usize,
+    /// 请求处理延迟上限（毫秒，EWMA），0 表示不按延迟降级
+    #[serde(default = "default_max_latency_ms")]
+    pub max_latency_ms: u64,
+    /// 降级后负载需持续低于恢复水位的时长（毫秒）才恢复
+    #[serde(default = "default_recover_hold_ms")]
+    pub recover_hold_ms: u64,
+    /// 拒绝非关键请求期间，快照广播等非关键任务的间隔放大倍数
+    #[serde(default = "default_non_critical_slowdown")]
+    pub non_critical_slowdown: u32,
+}
+
+fn default_max_in_flight() -> usize {
+    1024
+}
+fn default_max_queue_depth() -> usize {
+    10_000
+}
+fn default_high_water() -> f64 {
+    0.8
+}
+fn default_critical_high_water() -> f64 {
+    0.95
+}
+fn default_low_water() -> f64 {
+    0.6
+}
+fn default_retry_after_secs() -> u64 {
+    1
+}
+fn default_max_storage_queue_depth() -> usize {
+    65_536
+}
+fn default_max_notification_queue_depth() -> usize {
+    50_000
+}
+fn default_max_latency_ms() -> u64 {
+    500
+}
+fn default_recover_hold_ms() -> u64 {
+    3_000
+}
+fn default_non_critical_slowdown() -> u32 {
+    5
+}
+
+impl Default for OverloadConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_in_flight: default_max_in_flight(),
+            max_queue_depth: default_max_queue_depth(),
+            high_water: default_high_water(),
+            critical_high_water: default_critical_high_water(),
+            low_water: default_low_water(),
+            retry_after_secs: default_retry_after_secs(),
+            max_storage_queue_depth: default_max_storage_queue_depth(),
+            max_notification_queue_depth: default_max_notification_queue_depth(),
+            max_latency_ms: default_max_latency_ms(),
+            recover_hold_ms: default_recover_hold_ms(),
+            non_critical_slowdown: default_non_critical_slowdown(),
+        }
+    }
+}
+
+impl OverloadConfig {
+    /// 校验水位：0 < low_water < high_water <= critical_high_water <= 1，容量非零
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_in_flight == 0
+            || self.max_queue_depth == 0
+            || self.max_storage_queue_depth == 0
+            || self.max_notification_queue_depth == 0
+        {
+            return Err("max_in_flight and queue capacities must be positive".to_string());
+        }
+        if self.non_critical_slowdown == 0 {
+            return Err("non_critical_slowdown must be at least 1".to_string());
+        }
+        if !(self.low_water > 0.0
+            && self.low_water < self.high_water
+            && self.high_water <= self.critical_high_water
+            && self.critical_high_water <= 1.0)
+        {
+            return Err(format!(
+                "Invalid water marks: low={}, high={}, critical_high={}",
+                self.low_water, self.high_water, self.critical_high_water
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// 请求类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestClass {
+    /// 交易及账户请求，更高水位才拒绝
+    Critical,
+    /// 行情查询、历史查询，高水位即拒绝
+    NonCritical,
+    /// 健康检查、监控、管理端，不受限制
+    Exempt,
+}
+
+impl RequestClass {
+    /// 按 HTTP 路径分类（撤单释放挂单与冻结，过载时也放行）
+    pub fn from_path(path: &str) -> Self {
+        const EXEMPT: [&str; 7] = [
+            "/health",
+            "/metrics",
+            "/api/monitoring",
+            "/api/management",
+            "/api/admin",
+            "/api/order/cancel",
+            "/api/order/batch-cancel",
+        ];
+        const NON_CRITICAL: [&str; 4] = ["/api/market", "/api/data", "/api/factor", "/api/trades"];
+
+        if EXEMPT.iter().any(|prefix| path.starts_with(prefix)) {
+            RequestClass::Exempt
+        } else if NON_CRITICAL.iter().any(|prefix| path.starts_with(prefix)) {
+            RequestClass::NonCritical
+        } else {
+            RequestClass::Critical
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            RequestClass::Critical => "critical",
+            RequestClass::NonCritical => "non_critical",
+            RequestClass::Exempt => "exempt",
+        }
+    }
+}
+
+/// 保护级别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShedLevel {
+    /// 正常服务
+    Normal = 0,
+    /// 拒绝非关键请求
+    ShedNonCritical = 1,
+    /// 交易请求也拒绝
+    ShedAll = 2,
+}
+
+impl ShedLevel {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => ShedLevel::Normal,
+            1 => ShedLevel::ShedNonCritical,
+            _ => ShedLevel::ShedAll,
+        }
+    }
+
+    /// 该级别下是否拒绝指定类别的请求
+    pub fn rejects(&self, class: RequestClass) -> bool {
+        match class {
+            RequestClass::Exempt => false,
+            RequestClass::NonCritical => *self >= ShedLevel::ShedNonCritical,
+            RequestClass::Critical => *self == ShedLevel::ShedAll,
+        }
+    }
+}
+
+/// 被拒绝的请求
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OverloadRejection {
+    pub class: RequestClass,
+    pub level: ShedLevel,
+    pub load: f64,
+    pub retry_after_secs: u64,
+}
+
+/// 过载信号（占用率 = 当前值 / 容量）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OverloadSignal {
+    pub name: &'static str,
+    pub value: f64,
+    pub capacity: f64,
+    pub ratio: f64,
+}
+
+/// 降级/恢复事件
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OverloadEvent {
+    /// 毫秒时间戳
+    pub timestamp: i64,
+    pub from: ShedLevel,
+    pub to: ShedLevel,
+    pub load: f64,
+    /// 占用率最高的信号
+    pub trigger: &'static str,
+}
+
+/// 过载保护当前状态（监控接口）
+#[derive(Debug, Clone, Serialize)]
+pub struct OverloadStatus {
+    pub enabled: bool,
+    pub level: ShedLevel,
+    pub load: f64,
+    pub in_flight: usize,
+    /// 订单队列深度
+    pub queue_depth: usize,
+    /// 请求处理延迟（毫秒，EWMA）
+    pub latency_ms: f64,
+    pub signals: Vec<OverloadSignal>,
+    pub rejected_non_critical: u64,
+    pub rejected_critical: u64,
+    /// 最近的降级/恢复事件（由旧到新）
+    pub events: Vec<OverloadEvent>,
+    pub config: OverloadConfig,
+}
+
+/// 下游队列深度探针
+pub type QueueDepthProbe = Arc<dyn Fn() -> usize + Send + Sync>;
+
+/// 被监测的队列
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QueueKind {
+    /// 订单队列（优先级队列 + 撮合分片队列）
+    Order,
+    /// 存储队列（成交记录等成交事件总线消费者积压）
+    Storage,
+    /// 通知队列（通知中心优先级队列）
+    Notification,
+}
+
+impl QueueKind {
+    const ALL: [QueueKind; 3] = [
+        QueueKind::Order,
+        QueueKind::Storage,
+        QueueKind::Notification,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            QueueKind::Order => "order_queue",
+            QueueKind::Storage => "storage_queue",
+            QueueKind::Notification => "notification_queue",
+        }
+    }
+
+    fn capacity(&self, config: &OverloadConfig) -> usize {
+        match self {
+            QueueKind::Order => config.max_queue_depth,
+            QueueKind::Storage => config.max_storage_queue_depth,
+            QueueKind::Notification => config.max_notification_queue_depth,
+        }
+    }
+}
+
+/// 过载保护器
+pub struct OverloadGuard {
+    config: RwLock<OverloadConfig>,
+    in_flight: AtomicUsize,
+    level: AtomicU8,
+    queue_probes: RwLock<HashMap<QueueKind, QueueDepthProbe>>,
+    /// 请求处理延迟 EWMA（微秒）
+    latency_us: AtomicU64,
+    /// 上次 tick 以来是否有新的延迟样本
+    latency_sampled: AtomicBool,
+    /// 负载开始低于恢复水位的时刻（恢复迟滞计时）
+    recover_since: Mutex<Option<Instant>>,
+    events: Mutex<VecDeque<OverloadEvent>>,
+    rejected_non_critical: AtomicU64,
+    rejected_critical: AtomicU64,
+}
+
+impl OverloadGuard {
+    pub fn new(config: OverloadConfig) -> Self {
+        Self {
+            config: RwLock::new(config),
+            in_flight: AtomicUsize::new(0),
+            level: AtomicU8::new(ShedLevel::Normal as u8),
+            queue_probes: RwLock::new(HashMap::new()),
+            latency_us: AtomicU64::new(0),
+            latency_sampled: AtomicBool::new(false),
+            recover_since: Mutex::new(None),
+            events: Mutex::new(VecDeque::new()),
+            rejected_non_critical: AtomicU64::new(0),
+            rejected_critical: AtomicU64::new(0),
+        }
+    }
+
+    pub fn config(&self) -> OverloadConfig {
+        self.config.read().clone()
+    }
+
+    /// 热更新配置（关闭时立即恢复正常级别）
+    pub fn update_config(&self, config: OverloadConfig) {
+        if !config.enabled {
+            self.level.store(ShedLevel::Normal as u8, Ordering::SeqCst);
+            OVERLOAD_LEVEL.set(0);
+        }
+        *self.config.write() = config;
+    }
+
+    /// 设置订单队列深度探针
+    pub fn set_queue_probe(&self, probe: impl Fn() -> usize + Send + Sync + 'static) {
+        self.set_probe(QueueKind::Order, probe);
+    }
+
+    /// 设置指定队列的深度探针
+    pub fn set_probe(&self, kind: QueueKind, probe: impl Fn() -> usize + Send + Sync + 'static) {
+        self.queue_probes.write().insert(kind, Arc::new(probe));
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    pub fn level(&self) -> ShedLevel {
+        ShedLevel::from_u8(self.level.load(Ordering::SeqCst))
+    }
+
+    fn queue_depth(&self) -> usize {
+        self.queue_probes
+            .read()
+            .get(&QueueKind::Order)
+            .map_or(0, |probe| probe())
+    }
+
+    /// 请求处理延迟（毫秒，EWMA）
+    pub fn latency_ms(&self) -> f64 {
+        self.latency_us.load(Ordering::Relaxed) as f64 / 1000.0
+    }
+
+    /// 记录一次请求处理耗时（EWMA，新样本权重 1/5）
+    pub fn record_latency(&self, elapsed: Duration) {
+        let sample = elapsed.as_micros() as u64;
+        let _ = self
+            .latency_us
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |prev| {
+                Some(if prev == 0 {
+                    sample
+                } else {
+                    (prev * 4 + sample) / 5
+                })
+            });
+        self.latency_sampled.store(true, Ordering::Relaxed);
+    }
+
+    /// 采集各过载信号，返回 (负载, 信号列表)
+    fn measure(&self, config: &OverloadConfig, in_flight: usize) -> (f64, Vec<OverloadSignal>) {
+        let signal = |name: &'static str, value: f64, capacity: f64| OverloadSignal {
+            name,
+            value,
+            capacity,
+            ratio: value / capacity,
+        };
+
+        let mut signals = vec![signal(
+            "in_flight",
+            in_flight as f64,
+            config.max_in_flight as f64,
+        )];
+        {
+            let probes = self.queue_probes.read();
+            for kind in QueueKind::ALL {
+                if let Some(probe) = probes.get(&kind) {
+                    signals.push(signal(
+                        kind.label(),
+                        probe() as f64,
+                        kind.capacity(config) as f64,
+                    ));
+                }
+            }
+        }
+        if config.max_latency_ms > 0 {
+            signals.push(signal(
+                "latency",
+                self.latency_ms(),
+                config.max_latency_ms as f64,
+            ));
+        }
+
+        let load = signals.iter().map(|s| s.ratio).fold(0.0, f64::max);
+        (load, signals)
+    }
+
+    /// 占用率最高的信号
+    fn trigger_of(signals: &[OverloadSignal]) -> &'static str {
+        signals
+            .iter()
+            .max_by(|a, b| a.ratio.total_cmp(&b.ratio))
+            .map_or("none", |s| s.name)
+    }
+
+    /// 按当前负载推进保护级别（水位滞回 + 恢复保持时间）
+    fn evaluate(&self, config: &OverloadConfig, load: f64, trigger: &'static str) -> ShedLevel {
+        let current = self.level();
+        let target = match current {
+            _ if load >= config.critical_high_water => ShedLevel::ShedAll,
+            _ if load <= config.low_water => ShedLevel::Normal,
+            ShedLevel::Normal if load >= config.high_water => ShedLevel::ShedNonCritical,
+            ShedLevel::ShedAll if load < config.high_water => ShedLevel::ShedNonCritical,
+            level => level,
+        };
+
+        let next = if target < current {
+            let mut since = self.recover_since.lock();
+            let start = *since.get_or_insert_with(Instant::now);
+            if start.elapsed() >= Duration::from_millis(config.recover_hold_ms) {
+                *since = None;
+                target
+            } else {
+                current
+            }
+        } else {
+            if current != ShedLevel::Normal {
+                *self.recover_since.lock() = None;
+            }
+            target
+        };
+
+        if next != current
+            && self
+                .level
+                .compare_exchange(
+                    current as u8,
+                    next as u8,
+                    Ordering::SeqCst,
+                    Ordering::SeqCst,
+                )
+                .is_ok()
+        {
+            OVERLOAD_LEVEL.set(next as i64);
+            if next > current {
+                OVERLOAD_TRANSITIONS_TOTAL
+                    .with_label_values(&["degrade"])
+                    .inc();
+                log::warn!(
+                    "Overload protection degraded: {:?} -> {:?} (load={:.2}, trigger={})",
+                    current,
+                    next,
+                    load,
+                    trigger
+                );
+            } else {
+                OVERLOAD_TRANSITIONS_TOTAL
+                    .with_label_values(&["recover"])
+                    .inc();
+                log::info!(
+                    "Overload protection recovered: {:?} -> {:?} (load={:.2})",
+                    current,
+                    next,
+                    load
+                );
+            }
+
+            let mut events = self.events.lock();
+            if events.len() >= MAX_EVENTS {
+                events.pop_front();
+            }
+            events.push_back(OverloadEvent {
+                timestamp: chrono::Utc::now().timestamp_millis(),
+                from: current,
+                to: next,
+                load,
+                trigger,
+            });
+        }
+        OVERLOAD_LOAD_RATIO.set(load);
+        self.level()
+    }
+
+    /// 周期评估（无请求时也推进级别）：导出各信号占用率，长时间无新请求时延迟 EWMA 逐步衰减
+    pub fn tick(&self) -> ShedLevel {
+        if !self.latency_sampled.swap(false, Ordering::Relaxed) {
+            let _ = self
+                .latency_us
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |prev| {
+                    Some(prev * 4 / 5)
+                });
+        }
+
+        let config = self.config.read().clone();
+        if !config.enabled {
+            return ShedLevel::Normal;
+        }
+        let (load, signals) = self.measure(&config, self.in_flight());
+        for signal in &signals {
+            OVERLOAD_SIGNAL_RATIO
+                .with_label_values(&[signal.name])
+                .set(signal.ratio);
+        }
+        self.evaluate(&config, load, Self::trigger_of(&signals))
+    }
+
+    /// 非关键任务（快照广播等）当前应使用的间隔：正常为 `base`，
+    /// 拒绝非关键请求时放大 `non_critical_slowdown` 倍，交易请求也拒绝时返回 None（暂停）
+    pub fn non_critical_interval(&self, base: Duration) -> Option<Duration> {
+        match self.level() {
+            ShedLevel::Normal => Some(base),
+            ShedLevel::ShedNonCritical => {
+                Some(base * self.config.read().non_critical_slowdown.max(1))
+            }
+            ShedLevel::ShedAll => None,
+        }
+    }
+
+    /// 最近的降级/恢复事件
+    pub fn events(&self) -> Vec<OverloadEvent> {
+        self.events.lock().iter().cloned().collect()
+    }
+
+    /// 尝试接纳请求
+    ///
+    /// 接纳后返回的许可在请求结束（drop）时归还在途计数；豁免请求不计数。
+    pub fn try_acquire(
+        self: &Arc<Self>,
+        class: RequestClass,
+    ) -> Result<OverloadPermit, OverloadRejection> {
+        let config = self.config.read().clone();
+        if !config.enabled || class == RequestClass::Exempt {
+            return Ok(OverloadPermit { guard: None });
+        }
+
+        let (load, signals) = self.measure(&config, self.in_flight());
+        if let Some(order_queue) = signals.iter().find(|s| s.name == QueueKind::Order.label()) {
+            GATEWAY_QUEUE_DEPTH.set(order_queue.value as i64);
+        }
+        let level = self.evaluate(&config, load, Self::trigger_of(&signals));
+        if level.rejects(class) {
+            match class {
+                RequestClass::Critical => self.rejected_critical.fetch_add(1, Ordering::Relaxed),
+                _ => self.rejected_non_critical.fetch_add(1, Ordering::Relaxed),
+            };
+            OVERLOAD_REJECTED_TOTAL
+                .with_label_values(&[class.label()])
+                .inc();
+            return Err(OverloadRejection {
+                class,
+                level,
+                load,
+                retry_after_secs: config.retry_after_secs,
+            });
+        }
+
+        let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        GATEWAY_IN_FLIGHT.set(in_flight as i64);
+        Ok(OverloadPermit {
+            guard: Some((self.clone(), Instant::now())),
+        })
+    }
+
+    fn release(&self, elapsed: Duration) {
+        let in_flight = self.in_flight.fetch_sub(1, Ordering::SeqCst) - 1;
+        GATEWAY_IN_FLIGHT.set(in_flight as i64);
+        self.record_latency(elapsed);
+
+        // 请求结束时也推进级别，负载回落后无需等下一个请求即可解除
+        let config = self.config.read().clone();
+        if config.enabled && self.level() != ShedLevel::Normal {
+            let (load, signals) = self.measure(&config, in_flight);
+            self.evaluate(&config, load, Self::trigger_of(&signals));
+        }
+    }
+
+    pub fn status(&self) -> OverloadStatus {
+        let config = self.config();
+        let in_flight = self.in_flight();
+        let (load, signals) = self.measure(&config, in_flight);
+        OverloadStatus {
+            enabled: config.enabled,
+            level: self.level(),
+            load,
+            in_flight,
+            queue_depth: self.queue_depth(),
+            latency_ms: self.latency_ms(),
+            signals,
+            rejected_non_critical: self.rejected_non_critical.load(Ordering::Relaxed),
+            rejected_critical: self.rejected_critical.load(Ordering::Relaxed),
+            events: self.events(),
+            config,
+        }
+    }
+}
+
+/// 在途请求许可（drop 时归还，并记录处理耗时）
+pub struct OverloadPermit {
+    guard: Option<(Arc<OverloadGuard>, Instant)>,
+}
+
+impl Drop for OverloadPermit {
+    fn drop(&mut self) {
+        if let Some((guard, acquired_at)) = self.guard.take() {
+            guard.release(acquired_at.elapsed());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    fn config() -> OverloadConfig {
+        OverloadConfig {
+            enabled: true,
+            max_in_flight: 10,
+            max_queue_depth: 100,
+            high_water: 0.5,
+            critical_high_water: 0.9,
+            low_water: 0.3,
+            retry_after_secs: 2,
+            max_storage_queue_depth: 100,
+            max_notification_queue_depth: 100,
+            max_latency_ms: 0,
+            recover_hold_ms: 0,
+            non_critical_slowdown: 5,
+        }
+    }
+
+    #[test]
+    fn test_classify_path() {
+        assert_eq!(
+            RequestClass::from_path("/api/order/submit"),
+            RequestClass::Critical
+        );
+        assert_eq!(
+            RequestClass::from_path("/api/market/tick/IF2501"),
+            RequestClass::NonCritical
+        );
+        assert_eq!(
+            RequestClass::from_path("/api/data/history/ticks"),
+            RequestClass::NonCritical
+        );
+        assert_eq!(RequestClass::from_path("/health"), RequestClass::Exempt);
+        assert_eq!(
+            RequestClass::from_path("/api/management/overload"),
+            RequestClass::Exempt
+        );
+        assert_eq!(
+            RequestClass::from_path("/api/order/cancel"),
+            RequestClass::Exempt
+        );
+    }
+
+    #[test]
+    fn test_water_marks_with_hysteresis() {
+        let guard = Arc::new(OverloadGuard::new(config()));
+        let depth = Arc::new(AtomicUsize::new(0));
+        let probe_depth = depth.clone();
+        guard.set_queue_probe(move || probe_depth.load(Ordering::SeqCst));
+
+        // 5 个在途请求达到高水位，非关键请求被拒绝，交易请求仍接纳
+        let mut permits: Vec<_> = (0..5)
+            .map(|_| guard.try_acquire(RequestClass::Critical).unwrap())
+            .collect();
+        let rejection = guard.try_acquire(RequestClass::NonCritical).unwrap_err();
+        assert_eq!(rejection.level, ShedLevel::ShedNonCritical);
+        assert_eq!(rejection.retry_after_secs, 2);
+        let critical = guard.try_acquire(RequestClass::Critical).unwrap();
+        assert!(guard.try_acquire(RequestClass::Exempt).is_ok());
+        assert_eq!(guard.in_flight(), 6);
+
+        // 下游队列积压到更高水位，交易请求也拒绝
+        depth.store(95, Ordering::SeqCst);
+        assert_eq!(
+            guard.try_acquire(RequestClass::Critical).unwrap_err().level,
+            ShedLevel::ShedAll
+        );
+        // 队列回落到高水位以下恢复交易，但未到低水位前仍拒绝非关键请求
+        depth.store(40, Ordering::SeqCst);
+        drop(critical);
+        permits.pop();
+        assert_eq!(guard.level(), ShedLevel::ShedNonCritical);
+        assert!(guard.try_acquire(RequestClass::NonCritical).is_err());
+
+        // 回落到低水位解除
+        depth.store(0, Ordering::SeqCst);
+        drop(permits);
+        assert_eq!(guard.in_flight(), 0);
+        assert_eq!(guard.level(), ShedLevel::Normal);
+        assert!(guard.try_acquire(RequestClass::NonCritical).is_ok());
+
+        let status = guard.status();
+        assert_eq!(status.rejected_non_critical, 2);
+        assert_eq!(status.rejected_critical, 1);
+    }
+
+    #[test]
+    fn test_system_signals_degrade_and_recover() {
+        let guard = Arc::new(OverloadGuard::new(OverloadConfig {
+            max_latency_ms: 100,
+            recover_hold_ms: 50,
+            ..config()
+        }));
+        let storage = Arc::new(AtomicUsize::new(0));
+        let notification = Arc::new(AtomicUsize::new(0));
+        let probe = storage.clone();
+        guard.set_probe(QueueKind::Storage, move || probe.load(Ordering::SeqCst));
+        let probe = notification.clone();
+        guard.set_probe(QueueKind::Notification, move || {
+            probe.load(Ordering::SeqCst)
+        });
+        let base = Duration::from_millis(100);
+
+        // 存储队列积压：降级，非关键请求拒绝、快照广播降频
+        storage.store(60, Ordering::SeqCst);
+        assert_eq!(guard.tick(), ShedLevel::ShedNonCritical);
+        assert_eq!(
+            guard.non_critical_interval(base),
+            Some(Duration::from_millis(500))
+        );
+        assert!(guard.try_acquire(RequestClass::NonCritical).is_err());
+
+        // 通知队列接近满载：交易请求也拒绝，非关键任务暂停，撤单仍放行
+        notification.store(95, Ordering::SeqCst);
+        assert_eq!(guard.tick(), ShedLevel::ShedAll);
+        assert_eq!(guard.non_critical_interval(base), None);
+        assert!(guard.try_acquire(RequestClass::Critical).is_err());
+        assert!(guard
+            .try_acquire(RequestClass::from_path("/api/order/cancel"))
+            .is_ok());
+
+        // 负载回落：保持时间内不恢复（迟滞），持续低于低水位后恢复
+        storage.store(0, Ordering::SeqCst);
+        notification.store(0, Ordering::SeqCst);
+        assert_eq!(guard.tick(), ShedLevel::ShedAll);
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(guard.tick(), ShedLevel::Normal);
+        assert_eq!(guard.non_critical_interval(base), Some(base));
+
+        // 处理延迟超过上限同样触发降级
+        guard.record_latency(Duration::from_millis(200));
+        assert_eq!(guard.tick(), ShedLevel::ShedAll);
+
+        let events = guard.events();
+        let transitions: Vec<_> = events.iter().map(|e| (e.from, e.to)).collect();
+        assert_eq!(
+            transitions,
+            vec![
+                (ShedLevel::Normal, ShedLevel::ShedNonCritical),
+                (ShedLevel::ShedNonCritical, ShedLevel::ShedAll),
+                (ShedLevel::ShedAll, ShedLevel::Normal),
+                (ShedLevel::Normal, ShedLevel::ShedAll),
+            ]
+        );
+        assert_eq!(events[0].trigger, "storage_queue");
+        assert_eq!(events[1].trigger, "notification_queue");
+        assert_eq!(events[3].trigger, "latency");
+    }
+}
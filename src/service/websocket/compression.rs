@@ -0,0 +1,331 @@
+//! WebSocket 应用层压缩
+//!
+//! @yutiansut @quantaxis
+//!
+//! 行情快照、大 patch 体积大，按会话协商启用 zstd 压缩：
+//! - 客户端握手时通过查询参数 `compression=zstd` 或请求头 `X-QAExchange-Compression: zstd` 声明支持，
+//!   服务端启用时在握手响应头 `X-QAExchange-Compression: zstd` 中确认
+//! - 协商成功后，不小于 `min_size` 的消息以 Binary 帧发送 zstd 压缩后的 JSON，
+//!   小消息和压缩后不变小的消息仍以 Text 帧发送原始 JSON（客户端按帧类型区分）
+//! - 未协商的会话行为不变
+//!
+//! actix-web-actors 不支持 permessage-deflate，因此在应用层压缩。
+
+use actix::Actor;
+use actix_web::HttpRequest;
+use actix_web_actors::ws;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crate::observability::metrics::{
+    WEBSOCKET_COMPRESSION_BYTES, WEBSOCKET_COMPRESSION_RATIO, WEBSOCKET_COMPRESSION_SKIPPED,
+};
+
+/// 压缩协商请求头/响应头（X-QAExchange-Compression）
+pub const COMPRESSION_HEADER: &str = "x-qaexchange-compression";
+
+/// 当前支持的压缩算法
+pub const COMPRESSION_ZSTD: &str = "zstd";
+
+lazy_static::lazy_static! {
+    /// 全局 WebSocket 压缩器（所有会话共享配置与统计）
+    pub static ref WS_COMPRESSOR: Arc<WsCompressor> = Arc::new(WsCompressor::new(WsCompressionConfig::default()));
+}
+
+/// 压缩配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WsCompressionConfig {
+    /// 是否允许客户端协商压缩
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// 压缩阈值（字节），小于该大小的消息不压缩
+    #[serde(default = "default_min_size")]
+    pub min_size: usize,
+    /// zstd 压缩级别（1-22，行情推送建议 1-3）
+    #[serde(default = "default_level")]
+    pub level: i32,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+fn default_min_size() -> usize {
+    1024
+}
+fn default_level() -> i32 {
+    3
+}
+
+impl Default for WsCompressionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            min_size: default_min_size(),
+            level: default_level(),
+        }
+    }
+}
+
+/// 编码后的帧
+#[derive(Debug)]
+pub enum WsPayload {
+    /// 原始 JSON
+    Text(String),
+    /// zstd 压缩后的 JSON
+    Binary(Vec<u8>),
+}
+
+/// 压缩统计
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WsCompressionStats {
+    pub config: WsCompressionConfig,
+    /// 压缩发送的消息数
+    pub compressed_messages: u64,
+    /// 压缩前字节数（仅压缩发送的消息）
+    pub raw_bytes: u64,
+    /// 压缩后字节数
+    pub compressed_bytes: u64,
+    /// 低于阈值未压缩的消息数
+    pub skipped_small: u64,
+    /// 压缩后不变小、按原文发送的消息数
+    pub skipped_ineffective: u64,
+    /// 整体压缩比（压缩后/压缩前）
+    pub ratio: f64,
+}
+
+/// WebSocket 压缩器
+pub struct WsCompressor {
+    config: RwLock<WsCompressionConfig>,
+    compressed_messages: AtomicU64,
+    raw_bytes: AtomicU64,
+    compressed_bytes: AtomicU64,
+    skipped_small: AtomicU64,
+    skipped_ineffective: AtomicU64,
+}
+
+impl WsCompressor {
+    pub fn new(config: WsCompressionConfig) -> Self {
+        Self {
+            config: RwLock::new(config),
+            compressed_messages: AtomicU64::new(0),
+            raw_bytes: AtomicU64::new(0),
+            compressed_bytes: AtomicU64::new(0),
+            skipped_small: AtomicU64::new(0),
+            skipped_ineffective: AtomicU64::new(0),
+        }
+    }
+
+    pub fn update_config(&self, config: WsCompressionConfig) {
+        *self.config.write() = config;
+    }
+
+    pub fn config(&self) -> WsCompressionConfig {
+        self.config.read().clone()
+    }
+
+    /// 握手协商：客户端声明支持 zstd 且服务端启用压缩
+    pub fn negotiate(&self, req: &HttpRequest) -> bool {
+        if !self.config.read().enabled {
+            return false;
+        }
+
+        let from_query = req.uri().query().is_some_and(|q| {
+            q.split('&')
+                .filter_map(|s| s.strip_prefix("compression="))
+                .any(|v| v.eq_ignore_ascii_case(COMPRESSION_ZSTD))
+        });
+        let from_header = req
+            .headers()
+            .get(COMPRESSION_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| {
+                v.split(',')
+                    .any(|s| s.trim().eq_ignore_ascii_case(COMPRESSION_ZSTD))
+            });
+        from_query || from_header
+    }
+
+    /// 编码待发送的 JSON（未协商、低于阈值或压缩无收益时原样返回）
+    pub fn encode(&self, protocol: &str, json: String, negotiated: bool) -> WsPayload {
+        if !negotiated {
+            return WsPayload::Text(json);
+        }
+
+        let (min_size, level) = {
+            let config = self.config.read();
+            (config.min_size, config.level)
+        };
+        if json.len() < min_size {
+            self.skipped_small.fetch_add(1, Ordering::Relaxed);
+            WEBSOCKET_COMPRESSION_SKIPPED
+                .with_label_values(&[protocol, "small"])
+                .inc();
+            return WsPayload::Text(json);
+        }
+
+        match zstd::bulk::compress(json.as_bytes(), level) {
+            Ok(compressed) if compressed.len() < json.len() => {
+                let raw_len = json.len() as u64;
+                let compressed_len = compressed.len() as u64;
+                self.compressed_messages.fetch_add(1, Ordering::Relaxed);
+                self.raw_bytes.fetch_add(raw_len, Ordering::Relaxed);
+                self.compressed_bytes
+                    .fetch_add(compressed_len, Ordering::Relaxed);
+                WEBSOCKET_COMPRESSION_BYTES
+                    .with_label_values(&[protocol, "raw"])
+                    .inc_by(raw_len);
+                WEBSOCKET_COMPRESSION_BYTES
+                    .with_label_values(&[protocol, "compressed"])
+                    .inc_by(compressed_len);
+                WEBSOCKET_COMPRESSION_RATIO
+                    .with_label_values(&[protocol])
+                    .observe(compressed_len as f64 / raw_len as f64);
+                WsPayload::Binary(compressed)
+            }
+            Ok(_) => {
+                self.skipped_ineffective.fetch_add(1, Ordering::Relaxed);
+                WEBSOCKET_COMPRESSION_SKIPPED
+                    .with_label_values(&[protocol, "ineffective"])
+                    .inc();
+                WsPayload::Text(json)
+            }
+            Err(e) => {
+                log::warn!("WebSocket zstd compression failed: {}", e);
+                WsPayload::Text(json)
+            }
+        }
+    }
+
+    pub fn stats(&self) -> WsCompressionStats {
+        let raw_bytes = self.raw_bytes.load(Ordering::Relaxed);
+        let compressed_bytes = self.compressed_bytes.load(Ordering::Relaxed);
+        WsCompressionStats {
+            config: self.config(),
+            compressed_messages: self.compressed_messages.load(Ordering::Relaxed),
+            raw_bytes,
+            compressed_bytes,
+            skipped_small: self.skipped_small.load(Ordering::Relaxed),
+            skipped_ineffective: self.skipped_ineffective.load(Ordering::Relaxed),
+            ratio: if raw_bytes > 0 {
+                compressed_bytes as f64 / raw_bytes as f64
+            } else {
+                1.0
+            },
+        }
+    }
+}
+
+/// 通过会话发送 JSON（按协商结果压缩）
+pub fn send_json<A>(
+    ctx: &mut ws::WebsocketContext<A>,
+    protocol: &str,
+    json: String,
+    negotiated: bool,
+) where
+    A: Actor<Context = ws::WebsocketContext<A>>,
+{
+    match WS_COMPRESSOR.encode(protocol, json, negotiated) {
+        WsPayload::Text(text) => ctx.text(text),
+        WsPayload::Binary(bytes) => ctx.binary(bytes),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::websocket::diff_messages::DiffServerMessage;
+    use actix_web::test::TestRequest;
+
+    fn large_snapshot() -> String {
+        let quotes: serde_json::Map<String, serde_json::Value> = (0..200)
+            .map(|i| {
+                let instrument_id = format!("SHFE.cu25{:02}", i % 12 + 1);
+                (
+                    format!("{}_{}", instrument_id, i),
+                    serde_json::json!({
+                        "instrument_id": instrument_id,
+                        "datetime": "2025-01-02 10:15:30.500000",
+                        "last_price": 75000.0 + i as f64 * 10.0,
+                        "bid_price1": 74990.0 + i as f64 * 10.0,
+                        "ask_price1": 75010.0 + i as f64 * 10.0,
+                        "bid_volume1": 12,
+                        "ask_volume1": 8,
+                        "volume": 123456,
+                        "open_interest": 654321,
+                        "upper_limit": 82500.0,
+                        "lower_limit": 67500.0,
+                    }),
+                )
+            })
+            .collect();
+        let msg = DiffServerMessage::rtn_data(vec![serde_json::json!({ "quotes": quotes })]);
+        serde_json::to_string(&msg).unwrap()
+    }
+
+    #[test]
+    fn test_large_snapshot_compressed_significantly() {
+        let compressor = WsCompressor::new(WsCompressionConfig::default());
+        let json = large_snapshot();
+        let raw_len = json.len();
+        assert!(raw_len > 50_000);
+
+        let WsPayload::Binary(compressed) = compressor.encode("diff", json.clone(), true) else {
+            panic!("large snapshot should be compressed");
+        };
+        // 压缩比 < 20%
+        let ratio = compressed.len() as f64 / raw_len as f64;
+        assert!(ratio < 0.2, "compression ratio {:.3}", ratio);
+        assert_eq!(
+            zstd::bulk::decompress(&compressed, raw_len).unwrap(),
+            json.as_bytes()
+        );
+
+        let stats = compressor.stats();
+        assert_eq!(stats.compressed_messages, 1);
+        assert_eq!(stats.raw_bytes, raw_len as u64);
+        assert_eq!(stats.compressed_bytes, compressed.len() as u64);
+        assert!((stats.ratio - ratio).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_small_or_unnegotiated_messages_stay_text() {
+        let compressor = WsCompressor::new(WsCompressionConfig::default());
+
+        let small = r#"{"aid":"rtn_data","data":[{"balance":100000.0}]}"#.to_string();
+        assert!(matches!(
+            compressor.encode("diff", small.clone(), true),
+            WsPayload::Text(text) if text == small
+        ));
+        assert!(matches!(
+            compressor.encode("diff", large_snapshot(), false),
+            WsPayload::Text(_)
+        ));
+
+        let stats = compressor.stats();
+        assert_eq!(stats.compressed_messages, 0);
+        assert_eq!(stats.skipped_small, 1);
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let compressor = WsCompressor::new(WsCompressionConfig::default());
+        let by_query =
+            TestRequest::with_uri("/ws/diff?user_id=u1&compression=zstd").to_http_request();
+        let by_header = TestRequest::with_uri("/ws")
+            .insert_header((COMPRESSION_HEADER, "gzip, zstd"))
+            .to_http_request();
+        let plain = TestRequest::with_uri("/ws?user_id=u1").to_http_request();
+
+        assert!(compressor.negotiate(&by_query));
+        assert!(compressor.negotiate(&by_header));
+        assert!(!compressor.negotiate(&plain));
+
+        compressor.update_config(WsCompressionConfig {
+            enabled: false,
+            ..Default::default()
+        });
+        assert!(!compressor.negotiate(&by_query));
+    }
+}
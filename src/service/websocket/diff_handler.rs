@@ -3,6 +3,9 @@
 //! 实现 DIFF 协议的 WebSocket 消息处理逻辑：
 //! - peek_message 阻塞等待机制
 //! - rtn_data 差分推送
+//! - query_snapshot 按需拉取账户全量快照
+//! - resync 按 rtn_data 序列号补发或全量重置
+//! - 登录态状态机：req_login / req_password / req_logout，未登录的业务请求回权限错误
 //! - 零拷贝优化
 //!
 //! # 性能优化
@@ -33,11 +36,32 @@ use log;
 use std::sync::Arc;
 use std::time::Duration;
 
+use super::compression;
 use super::diff_messages::{DiffClientMessage, DiffServerMessage};
+use super::session_registry::{ClientInfo, KickSession, WS_SESSION_REGISTRY};
+use super::subscription_quota::{PushVerdict, WS_SUBSCRIPTION_QUOTA};
 use crate::exchange::{AccountManager, OrderRouter};
+use crate::market::subscription::DEFAULT_GROUP;
 use crate::market::{kline_actor::KLineActor, MarketDataBroadcaster};
+use crate::notification::store::{inbox_keys, NotificationStore};
 use crate::protocol::diff::snapshot::SnapshotManager;
+use crate::service::overload::OVERLOAD_GUARD;
 use crate::user::UserManager;
+use crate::utils::timestamp::millis_to_nanos;
+use crate::ExchangeError;
+
+/// 会话登录态变化（`DiffHandler::handle_session_message` 的结果）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionTransition {
+    /// 登录态不变，报文按当前用户分发
+    Dispatch,
+    /// 登录态不变，报文已处理完毕
+    Handled,
+    /// 登录成功，会话绑定到该用户
+    LoggedIn { user_id: String, username: String },
+    /// 登出，会话解绑用户
+    LoggedOut,
+}
 
 /// DIFF 协议消息处理器
 pub struct DiffHandler {
@@ -58,6 +82,9 @@ pub struct DiffHandler {
 
     /// K线Actor地址（用于查询历史K线）
     pub(crate) kline_actor: Option<Addr<KLineActor>>,
+
+    /// 用户消息中心（登录成功后推送未读计数）
+    pub(crate) notification_store: parking_lot::RwLock<Option<Arc<NotificationStore>>>,
 }
 
 impl DiffHandler {
@@ -70,6 +97,7 @@ impl DiffHandler {
             order_router: None,
             market_broadcaster: None,
             kline_actor: None,
+            notification_store: parking_lot::RwLock::new(None),
         }
     }
 
@@ -100,31 +128,67 @@ impl DiffHandler {
         self
     }
 
+    /// 设置用户消息中心（处理器已共享后设置）
+    pub fn set_notification_store(&self, store: Arc<NotificationStore>) {
+        *self.notification_store.write() = Some(store);
+    }
+
     /// 处理 DIFF 客户端消息
     ///
     /// # 参数
     ///
     /// * `user_id` - 用户ID
+    /// * `session_id` - 会话ID（peek 按会话取各自的 patch 队列）
     /// * `msg` - DIFF 客户端消息
     /// * `ctx` - WebSocket 上下文
     pub async fn handle_diff_message(
         &self,
         user_id: &str,
+        session_id: &str,
         msg: DiffClientMessage,
         ctx_addr: Addr<DiffWebsocketSession>,
     ) {
+        // 过载保护：许可持有到消息处理结束
+        let _permit = match OVERLOAD_GUARD.try_acquire(msg.request_class()) {
+            Ok(permit) => permit,
+            Err(rejection) => {
+                let notify_patch = serde_json::json!({
+                    "notify": {
+                        "server_overloaded": {
+                            "type": "MESSAGE",
+                            "level": "WARNING",
+                            "code": 503,
+                            "content": format!(
+                                "Server overloaded, retry after {}s",
+                                rejection.retry_after_secs
+                            )
+                        }
+                    }
+                });
+                ctx_addr.do_send(SendDiffMessage {
+                    message: DiffServerMessage::rtn_data(vec![notify_patch]),
+                });
+                log::debug!(
+                    "DIFF message from user {} shed ({:?}, load={:.2})",
+                    user_id,
+                    rejection.level,
+                    rejection.load
+                );
+                return;
+            }
+        };
+
         match msg {
             DiffClientMessage::PeekMessage => {
-                self.handle_peek_message(user_id, ctx_addr).await;
+                self.handle_peek_message(user_id, session_id, ctx_addr)
+                    .await;
             }
 
-            DiffClientMessage::ReqLogin {
-                bid,
-                user_name,
-                password,
-            } => {
-                log::info!("DIFF login request: user_name={}, bid={:?}", user_name, bid);
-                self.handle_login(user_name, password, ctx_addr).await;
+            DiffClientMessage::ReqLogin { .. }
+            | DiffClientMessage::ReqPassword { .. }
+            | DiffClientMessage::ReqLogout => {
+                // 登录态报文由会话状态机（handle_session_message）处理，不会分发到这里
+                log::debug!("DIFF session message ignored in dispatch: user={}", user_id);
             }
 
             DiffClientMessage::SubscribeQuote { ins_list } => {
@@ -146,6 +210,7 @@ impl DiffHandler {
                 limit_price,
                 volume_condition,
                 time_condition,
+                close_lot_id,
             } => {
                 log::info!(
                     "DIFF insert order: user_id={}, account_id={:?}, order_id={:?}, time_cond={:?}",
@@ -168,6 +233,7 @@ impl DiffHandler {
                     limit_price,
                     time_condition,  // ✨ IOC/FOK/GTC 支持 @yutiansut @quantaxis
                     volume_condition, // ✨ ANY/MIN/ALL 支持 @yutiansut @quantaxis
+                    close_lot_id,
                     ctx_addr,
                 )
                 .await;
@@ -205,6 +271,19 @@ impl DiffHandler {
                     .await;
             }
 
+            DiffClientMessage::QuerySnapshot { account_id } => {
+                log::info!(
+                    "DIFF query snapshot: user_id={}, account_id={:?}",
+                    user_id,
+                    account_id
+                );
+                self.handle_query_snapshot(user_id, session_id, account_id, ctx_addr);
+            }
+
+            DiffClientMessage::Resync { last_seq } => {
+                self.handle_resync(user_id, session_id, last_seq, ctx_addr);
+            }
+
             DiffClientMessage::Ping => {
                 // 心跳响应: 收到 ping 立即返回 pong @yutiansut @quantaxis
                 log::debug!("DIFF ping received from user={}, sending pong", user_id);
@@ -214,203 +293,309 @@ impl DiffHandler {
         }
     }
 
-    /// 处理登录请求
-    async fn handle_login(
+    /// 会话登录态状态机 @yutiansut @quantaxis
+    ///
+    /// 处理 req_login / req_password / req_logout，并拦截未登录会话的业务请求。
+    /// 返回登录态变化与需直接下发给本会话的通知 patch（不经用户快照，未登录会话也能收到）。
+    /// URL 参数登录与 req_login 先到先得：已登录的会话再次 req_login 需先 req_logout。
+    pub fn handle_session_message(
         &self,
-        username: String,
+        session_user: Option<&str>,
+        msg: &DiffClientMessage,
+    ) -> (SessionTransition, Vec<serde_json::Value>) {
+        match (session_user, msg) {
+            (
+                None,
+                DiffClientMessage::ReqLogin {
+                    bid,
+                    user_name,
+                    password,
+                },
+            ) => {
+                log::info!("DIFF login request: user_name={}, bid={:?}", user_name, bid);
+                self.login(user_name, password.clone())
+            }
+
+            (Some(current), DiffClientMessage::ReqLogin { user_name, .. }) => {
+                log::warn!(
+                    "DIFF login of {} rejected: session already logged in as {}",
+                    user_name,
+                    current
+                );
+                (
+                    SessionTransition::Handled,
+                    vec![notify_patch(
+                        "already_logged_in",
+                        "WARNING",
+                        1005,
+                        format!("Already logged in as {}, send req_logout first", current),
+                    )],
+                )
+            }
+
+            (
+                Some(user_id),
+                DiffClientMessage::ReqPassword {
+                    old_password,
+                    new_password,
+                },
+            ) => (
+                SessionTransition::Handled,
+                vec![self.change_password(user_id, old_password, new_password)],
+            ),
+
+            (Some(user_id), DiffClientMessage::ReqLogout) => {
+                log::info!("DIFF logout: user_id={}", user_id);
+                (
+                    SessionTransition::LoggedOut,
+                    vec![notify_patch("logout", "INFO", 0, "Logged out")],
+                )
+            }
+
+            (Some(_), _) | (None, DiffClientMessage::Ping) => (SessionTransition::Dispatch, vec![]),
+
+            (None, _) => (
+                SessionTransition::Handled,
+                vec![notify_patch(
+                    "permission_denied",
+                    "ERROR",
+                    1004,
+                    "Not logged in, send req_login first",
+                )],
+            ),
+        }
+    }
+
+    /// 验证登录凭证，返回登录态变化与失败通知
+    fn login(
+        &self,
+        username: &str,
         password: String,
-        ctx_addr: Addr<DiffWebsocketSession>,
-    ) {
-        if let Some(ref user_mgr) = self.user_manager {
-            // 尝试两种认证方式：
-            // 1. username + password（原始密码）
-            // 2. user_id + token（JWT 或其他 token）
-
-            // 先尝试 token 认证（检查 username 是否为 UUID 格式的 user_id）
-            let login_result = if username.len() == 36 && username.contains('-') {
-                log::info!("Detected UUID format username, attempting token authentication for user_id: {}", username);
-
-                // username 是 UUID 格式，可能是 user_id，尝试 token 认证
-                match user_mgr.verify_token(&password) {
-                    Ok(verified_user_id) => {
-                        log::info!(
-                            "Token verification successful, verified_user_id: {}",
-                            verified_user_id
-                        );
+    ) -> (SessionTransition, Vec<serde_json::Value>) {
+        let Some(ref user_mgr) = self.user_manager else {
+            log::error!("DIFF login failed: UserManager not available");
+            return (
+                SessionTransition::Handled,
+                vec![notify_patch(
+                    "service_error",
+                    "ERROR",
+                    1003,
+                    "User management service is not available",
+                )],
+            );
+        };
 
-                        // Token 验证成功，检查 user_id 匹配
-                        if verified_user_id == username {
-                            match user_mgr.get_user(&username) {
-                                Ok(user) => {
-                                    log::info!("Token auth successful for user: {}", user.username);
-                                    // RBAC: 返回角色和权限信息 @yutiansut @quantaxis
-                                    let permissions: Vec<String> = user
-                                        .get_permissions()
-                                        .iter()
-                                        .map(|p| format!("{:?}", p))
-                                        .collect();
-                                    Ok(crate::user::UserLoginResponse {
-                                        success: true,
-                                        user_id: Some(user.user_id.clone()),
-                                        username: Some(user.username.clone()),
-                                        token: Some(password.clone()),
-                                        message: "Token authentication successful".to_string(),
-                                        roles: Some(user.roles.clone()),
-                                        is_admin: Some(user.is_admin()),
-                                        permissions: Some(permissions),
-                                    })
-                                }
-                                Err(e) => {
-                                    log::warn!("Token auth failed: user not found: {}", e);
-                                    Ok(crate::user::UserLoginResponse {
-                                        success: false,
-                                        user_id: None,
-                                        username: None,
-                                        token: None,
-                                        message: format!("User not found: {}", e),
-                                        roles: None,
-                                        is_admin: None,
-                                        permissions: None,
-                                    })
-                                }
-                            }
-                        } else {
-                            log::warn!(
-                                "Token auth failed: user_id mismatch (expected: {}, got: {})",
-                                username,
-                                verified_user_id
-                            );
-                            Ok(crate::user::UserLoginResponse {
-                                success: false,
-                                user_id: None,
-                                username: None,
-                                token: None,
-                                message: "Token user_id mismatch".to_string(),
-                                roles: None,
-                                is_admin: None,
-                                permissions: None,
-                            })
-                        }
-                    }
-                    Err(e) => {
-                        log::warn!(
-                            "Token verification failed: {}, falling back to password auth",
-                            e
-                        );
-                        // Token 验证失败，尝试常规密码认证（向后兼容）
-                        user_mgr.login(crate::user::UserLoginRequest {
-                            username: username.clone(),
-                            password,
-                        })
-                    }
-                }
-            } else {
+        match self.authenticate(user_mgr, username, password) {
+            Ok(login_resp) if login_resp.success => {
+                let user_id = login_resp.user_id.unwrap_or_default();
                 log::info!(
-                    "Standard password authentication for username: {}",
-                    username
+                    "DIFF login successful: user={}, user_id={}",
+                    username,
+                    user_id
                 );
-                // 常规密码认证
-                user_mgr.login(crate::user::UserLoginRequest {
-                    username: username.clone(),
-                    password,
-                })
-            };
-
-            match login_result {
-                Ok(login_resp) => {
-                    if login_resp.success {
-                        // 登录成功，初始化用户快照
-                        let user_id = login_resp.user_id.clone().unwrap_or_default();
-                        self.snapshot_mgr.initialize_user(&user_id).await;
+                (
+                    SessionTransition::LoggedIn {
+                        user_id,
+                        username: username.to_string(),
+                    },
+                    vec![],
+                )
+            }
+            Ok(login_resp) => {
+                log::warn!(
+                    "DIFF login failed for user: {}, reason: {}",
+                    username,
+                    login_resp.message
+                );
+                (
+                    SessionTransition::Handled,
+                    vec![notify_patch(
+                        "login_failed",
+                        "ERROR",
+                        1001,
+                        login_resp.message,
+                    )],
+                )
+            }
+            Err(e) => {
+                log::error!("DIFF login error for user {}: {}", username, e);
+                (
+                    SessionTransition::Handled,
+                    vec![notify_patch(
+                        "login_error",
+                        "ERROR",
+                        1002,
+                        format!("Login error: {}", e),
+                    )],
+                )
+            }
+        }
+    }
 
-                        // ✅ 设置 session 的 user_id（认证成功）
-                        ctx_addr.do_send(SetUserId {
-                            user_id: user_id.clone(),
-                        });
+    /// 验证登录凭证
+    ///
+    /// 尝试两种认证方式：
+    /// 1. username + password（原始密码）
+    /// 2. user_id + token（JWT 或其他 token）
+    fn authenticate(
+        &self,
+        user_mgr: &UserManager,
+        username: &str,
+        password: String,
+    ) -> Result<crate::user::UserLoginResponse, ExchangeError> {
+        // 先尝试 token 认证（检查 username 是否为 UUID 格式的 user_id）
+        if username.len() == 36 && username.contains('-') {
+            log::info!(
+                "Detected UUID format username, attempting token authentication for user_id: {}",
+                username
+            );
 
-                        // 发送登录成功通知（通过 SnapshotManager 推送）
-                        let notify_patch = serde_json::json!({
-                            "notify": {
-                                "login_success": {
-                                    "type": "MESSAGE",
-                                    "level": "INFO",
-                                    "code": 0,
-                                    "content": format!("Login successful for user: {}", username)
-                                }
-                            },
-                            "user_id": user_id.clone(),
-                            "username": username
-                        });
+            // username 是 UUID 格式，可能是 user_id，尝试 token 认证
+            match user_mgr.verify_token(&password) {
+                Ok(verified_user_id) => {
+                    log::info!(
+                        "Token verification successful, verified_user_id: {}",
+                        verified_user_id
+                    );
 
-                        // ✅ 通过 SnapshotManager 推送（触发 peek_message）
-                        self.snapshot_mgr.push_patch(&user_id, notify_patch).await;
-                        log::info!(
-                            "DIFF login successful: user={}, user_id={}",
-                            username,
-                            user_id
-                        );
-                    } else {
-                        // 登录失败（也通过 SnapshotManager 推送，确保客户端能收到）
-                        let notify_patch = serde_json::json!({
-                            "notify": {
-                                "login_failed": {
-                                    "type": "MESSAGE",
-                                    "level": "ERROR",
-                                    "code": 1001,
-                                    "content": login_resp.message.clone()
-                                }
+                    // Token 验证成功，检查 user_id 匹配
+                    if verified_user_id == username {
+                        match user_mgr.get_user(&username) {
+                            Ok(user) => {
+                                log::info!("Token auth successful for user: {}", user.username);
+                                // RBAC: 返回角色和权限信息 @yutiansut @quantaxis
+                                let permissions: Vec<String> = user
+                                    .get_permissions()
+                                    .iter()
+                                    .map(|p| format!("{:?}", p))
+                                    .collect();
+                                Ok(crate::user::UserLoginResponse {
+                                    success: true,
+                                    user_id: Some(user.user_id.clone()),
+                                    username: Some(user.username.clone()),
+                                    token: Some(password.clone()),
+                                    message: "Token authentication successful".to_string(),
+                                    roles: Some(user.roles.clone()),
+                                    is_admin: Some(user.is_admin()),
+                                    permissions: Some(permissions),
+                                })
                             }
-                        });
-
-                        // 使用 "anonymous" 作为临时 user_id 推送失败消息
-                        self.snapshot_mgr
-                            .push_patch("anonymous", notify_patch)
-                            .await;
+                            Err(e) => {
+                                log::warn!("Token auth failed: user not found: {}", e);
+                                Ok(crate::user::UserLoginResponse {
+                                    success: false,
+                                    user_id: None,
+                                    username: None,
+                                    token: None,
+                                    message: format!("User not found: {}", e),
+                                    roles: None,
+                                    is_admin: None,
+                                    permissions: None,
+                                })
+                            }
+                        }
+                    } else {
                         log::warn!(
-                            "DIFF login failed for user: {}, reason: {}",
+                            "Token auth failed: user_id mismatch (expected: {}, got: {})",
                             username,
-                            login_resp.message
+                            verified_user_id
                         );
+                        Ok(crate::user::UserLoginResponse {
+                            success: false,
+                            user_id: None,
+                            username: None,
+                            token: None,
+                            message: "Token user_id mismatch".to_string(),
+                            roles: None,
+                            is_admin: None,
+                            permissions: None,
+                        })
                     }
                 }
                 Err(e) => {
-                    // 登录错误（也通过 SnapshotManager 推送）
-                    let notify_patch = serde_json::json!({
-                        "notify": {
-                            "login_error": {
-                                "type": "MESSAGE",
-                                "level": "ERROR",
-                                "code": 1002,
-                                "content": format!("Login error: {}", e)
-                            }
-                        }
-                    });
-
-                    // 使用 "anonymous" 作为临时 user_id 推送错误消息
-                    self.snapshot_mgr
-                        .push_patch("anonymous", notify_patch)
-                        .await;
-                    log::error!("DIFF login error for user {}: {}", username, e);
+                    log::warn!(
+                        "Token verification failed: {}, falling back to password auth",
+                        e
+                    );
+                    // Token 验证失败，尝试常规密码认证（向后兼容）
+                    user_mgr.login(crate::user::UserLoginRequest {
+                        username: username.to_string(),
+                        password,
+                    })
                 }
             }
         } else {
-            // UserManager 不可用（也通过 SnapshotManager 推送）
-            let notify_patch = serde_json::json!({
-                "notify": {
-                    "service_error": {
-                        "type": "MESSAGE",
-                        "level": "ERROR",
-                        "code": 1003,
-                        "content": "User management service is not available"
-                    }
+            log::info!(
+                "Standard password authentication for username: {}",
+                username
+            );
+            // 常规密码认证
+            user_mgr.login(crate::user::UserLoginRequest {
+                username: username.to_string(),
+                password,
+            })
+        }
+    }
+
+    /// 修改密码，返回结果通知
+    fn change_password(
+        &self,
+        user_id: &str,
+        old_password: &str,
+        new_password: &str,
+    ) -> serde_json::Value {
+        let Some(ref user_mgr) = self.user_manager else {
+            return notify_patch(
+                "service_error",
+                "ERROR",
+                1003,
+                "User management service is not available",
+            );
+        };
+
+        match user_mgr.change_password(user_id, old_password, new_password) {
+            Ok(()) => {
+                log::info!("DIFF password changed: user_id={}", user_id);
+                notify_patch("password_changed", "INFO", 0, "Password changed")
+            }
+            Err(e) => {
+                log::warn!("DIFF password change failed for {}: {}", user_id, e);
+                notify_patch("password_change_failed", "ERROR", 1006, e.to_string())
+            }
+        }
+    }
+
+    /// 登录成功后初始化用户快照，推送登录成功通知与消息中心未读计数
+    async fn on_login(&self, user_id: &str, username: &str) {
+        self.snapshot_mgr.initialize_user(user_id).await;
+
+        let notify_patch = serde_json::json!({
+            "notify": {
+                "login_success": {
+                    "type": "MESSAGE",
+                    "level": "INFO",
+                    "code": 0,
+                    "content": format!("Login successful for user: {}", username)
                 }
-            });
+            },
+            "user_id": user_id,
+            "username": username
+        });
 
-            // 使用 "anonymous" 作为临时 user_id 推送错误消息
-            self.snapshot_mgr
-                .push_patch("anonymous", notify_patch)
-                .await;
-            log::error!("DIFF login failed: UserManager not available");
+        // ✅ 通过 SnapshotManager 推送（触发 peek_message）
+        self.snapshot_mgr.push_patch(user_id, notify_patch).await;
+
+        // 推送消息中心未读计数
+        let notification_store = self.notification_store.read().clone();
+        if let Some(store) = notification_store {
+            let unread = store.unread_count(&inbox_keys(&self.account_mgr, user_id));
+            let unread_patch = serde_json::json!({
+                "notification_center": {
+                    "unread": unread.total,
+                    "by_channel": unread.by_channel
+                }
+            });
+            self.snapshot_mgr.push_patch(user_id, unread_patch).await;
         }
     }
 
@@ -429,7 +614,13 @@ impl DiffHandler {
             .collect();
 
         if instruments.is_empty() {
-            // 空列表表示取消订阅
+            // 空列表表示取消订阅（清空默认组，暂停推送但保留推送任务）
+            if let Some(ref broadcaster) = self.market_broadcaster {
+                if let Err(e) = broadcaster.replace_group(user_id, DEFAULT_GROUP, Vec::new()) {
+                    log::warn!("Failed to clear quote subscription for {}: {}", user_id, e);
+                }
+            }
+
             let notify_patch = serde_json::json!({
                 "notify": {
                     "unsubscribe": {
@@ -442,9 +633,7 @@ impl DiffHandler {
                 "ins_list": ""
             });
 
-            let rtn_data = DiffServerMessage::RtnData {
-                data: vec![notify_patch],
-            };
+            let rtn_data = DiffServerMessage::rtn_data(vec![notify_patch]);
 
             ctx_addr.do_send(SendDiffMessage { message: rtn_data });
             log::info!("User {} unsubscribed from all quotes", user_id);
@@ -483,21 +672,52 @@ impl DiffHandler {
 
         log::info!("User {} subscribed to quotes: {:?}", user_id, instruments);
 
-        // ✅ 从 MarketDataBroadcaster 订阅并启动推送任务
+        // ✅ 全量替换默认订阅组（原子切换，仅首次订阅时启动推送任务）
         if let Some(ref broadcaster) = self.market_broadcaster {
-            let receiver = broadcaster.subscribe(
-                user_id.to_string(),
-                instruments.clone(),
-                vec![
-                    "orderbook".to_string(),
-                    "tick".to_string(),
-                    "last_price".to_string(),
-                    "kline".to_string(), // ✨ 新增：订阅K线完成事件
-                ],
-            );
+            let result = if broadcaster.has_subscriber(user_id) {
+                broadcaster.replace_group(user_id, DEFAULT_GROUP, instruments.clone())
+            } else {
+                broadcaster.create_group(
+                    user_id,
+                    DEFAULT_GROUP,
+                    instruments.clone(),
+                    vec![
+                        "orderbook".to_string(),
+                        "tick".to_string(),
+                        "last_price".to_string(),
+                        "kline".to_string(), // ✨ 新增：订阅K线完成事件
+                    ],
+                )
+            };
+
+            let receiver = match result {
+                Ok(change) => {
+                    log::debug!(
+                        "User {} quote subscription switched at seq {}",
+                        user_id,
+                        change.switch_seq
+                    );
+                    match change.receiver {
+                        Some(receiver) => receiver,
+                        // 推送任务已在运行，订阅已原地替换
+                        None => return,
+                    }
+                }
+                Err(e) => {
+                    log::error!("Failed to update quote subscription for {}: {}", user_id, e);
+                    self.snapshot_mgr
+                        .push_patch(
+                            user_id,
+                            notify_patch("subscribe_failed", "ERROR", 9004, e.to_string()),
+                        )
+                        .await;
+                    return;
+                }
+            };
 
             // 启动异步任务持续推送行情数据
             let snapshot_mgr = self.snapshot_mgr.clone();
+            let broadcaster = broadcaster.clone();
             let user_id_clone = user_id.to_string();
 
             tokio::spawn(async move {
@@ -511,6 +731,22 @@ impl DiffHandler {
                     let receiver_clone = receiver.clone();
                     match tokio::task::spawn_blocking(move || receiver_clone.recv()).await {
                         Ok(Ok(event)) => {
+                            // 推送速率配额：持续超速时停止推送并断开该用户的 DIFF 会话
+                            if WS_SUBSCRIPTION_QUOTA.record_push(&user_id_clone, 1)
+                                == PushVerdict::Disconnect
+                            {
+                                broadcaster.unsubscribe(&user_id_clone);
+                                for session in WS_SESSION_REGISTRY.user_sessions(&user_id_clone) {
+                                    if session.protocol == "diff" {
+                                        WS_SESSION_REGISTRY.kick_session(
+                                            &session.session_id,
+                                            "行情推送速率超限，连接已断开",
+                                        );
+                                    }
+                                }
+                                break;
+                            }
+
                             // 将 MarketDataEvent 转换为 DIFF quote 格式
                             if let Some(quote_patch) = Self::convert_market_event_to_diff(&event) {
                                 snapshot_mgr.push_patch(&user_id_clone, quote_patch).await;
@@ -557,8 +793,11 @@ impl DiffHandler {
         limit_price: Option<f64>,
         time_condition: Option<String>,    // ✨ IOC/FOK/GTC 支持 @yutiansut @quantaxis
         volume_condition: Option<String>,  // ✨ ANY/MIN/ALL 支持 @yutiansut @quantaxis
+        close_lot_id: Option<u64>,      // 指定平仓批次
         ctx_addr: Addr<DiffWebsocketSession>,
     ) {
+        // 客户端提供的 order_id 作为幂等键（重复订单检测）
+        let client_order_id = order_id.clone();
         // ✅ 自动生成 order_id（如果客户端未提供）
         let order_id = order_id.unwrap_or_else(|| {
             let id = uuid::Uuid::new_v4().to_string();
@@ -584,9 +823,7 @@ impl DiffHandler {
                     }
                 });
 
-                let rtn_data = DiffServerMessage::RtnData {
-                    data: vec![notify_patch],
-                };
+                let rtn_data = DiffServerMessage::rtn_data(vec![notify_patch]);
 
                 ctx_addr.do_send(SendDiffMessage { message: rtn_data });
                 log::warn!(
@@ -629,9 +866,7 @@ impl DiffHandler {
                         }
                     });
 
-                    let rtn_data = DiffServerMessage::RtnData {
-                        data: vec![notify_patch],
-                    };
+                    let rtn_data = DiffServerMessage::rtn_data(vec![notify_patch]);
 
                     ctx_addr.do_send(SendDiffMessage { message: rtn_data });
                     log::warn!(
@@ -685,6 +920,10 @@ impl DiffHandler {
                 order_type: order_type.to_string(),
                 time_condition: time_cond,
                 volume_condition: volume_cond,
+                hedge_flag: None,
+                ttl_secs: None,
+                client_order_id,
+                close_lot_id,
             };
 
             // 提交订单
@@ -705,7 +944,7 @@ impl DiffHandler {
                                     "offset": offset.clone(),
                                     "volume_orign": volume,
                                     "price_type": price_type.clone(),
-                                    "limit_price": limit_price.unwrap_or(0.0),
+                                    "limit_price": response.adjusted_price.or(limit_price).unwrap_or(0.0),
                                     "status": response.status.unwrap_or("SUBMITTED".to_string())
                                 }
                             }
@@ -713,9 +952,7 @@ impl DiffHandler {
                     }
                 });
 
-                let rtn_data = DiffServerMessage::RtnData {
-                    data: vec![notify_patch],
-                };
+                let rtn_data = DiffServerMessage::rtn_data(vec![notify_patch]);
 
                 ctx_addr.do_send(SendDiffMessage { message: rtn_data });
                 log::info!("DIFF insert order success: order_id={}", order_id);
@@ -732,9 +969,7 @@ impl DiffHandler {
                     }
                 });
 
-                let rtn_data = DiffServerMessage::RtnData {
-                    data: vec![notify_patch],
-                };
+                let rtn_data = DiffServerMessage::rtn_data(vec![notify_patch]);
 
                 ctx_addr.do_send(SendDiffMessage { message: rtn_data });
                 log::warn!("DIFF insert order rejected: order_id={}", order_id);
@@ -752,9 +987,7 @@ impl DiffHandler {
                 }
             });
 
-            let rtn_data = DiffServerMessage::RtnData {
-                data: vec![notify_patch],
-            };
+            let rtn_data = DiffServerMessage::rtn_data(vec![notify_patch]);
 
             ctx_addr.do_send(SendDiffMessage { message: rtn_data });
             log::error!("DIFF insert order failed: OrderRouter not available");
@@ -783,9 +1016,7 @@ impl DiffHandler {
                 }
             });
 
-            let rtn_data = DiffServerMessage::RtnData {
-                data: vec![notify_patch],
-            };
+            let rtn_data = DiffServerMessage::rtn_data(vec![notify_patch]);
 
             ctx_addr.do_send(SendDiffMessage { message: rtn_data });
             log::warn!("DIFF cancel order failed: user mismatch");
@@ -811,9 +1042,7 @@ impl DiffHandler {
                         }
                     });
 
-                    let rtn_data = DiffServerMessage::RtnData {
-                        data: vec![notify_patch],
-                    };
+                    let rtn_data = DiffServerMessage::rtn_data(vec![notify_patch]);
 
                     ctx_addr.do_send(SendDiffMessage { message: rtn_data });
                     log::warn!(
@@ -869,9 +1098,7 @@ impl DiffHandler {
                         }
                     });
 
-                    let rtn_data = DiffServerMessage::RtnData {
-                        data: vec![notify_patch],
-                    };
+                    let rtn_data = DiffServerMessage::rtn_data(vec![notify_patch]);
 
                     ctx_addr.do_send(SendDiffMessage { message: rtn_data });
                     log::info!("DIFF cancel order success: order_id={}", order_id);
@@ -889,9 +1116,7 @@ impl DiffHandler {
                         }
                     });
 
-                    let rtn_data = DiffServerMessage::RtnData {
-                        data: vec![notify_patch],
-                    };
+                    let rtn_data = DiffServerMessage::rtn_data(vec![notify_patch]);
 
                     ctx_addr.do_send(SendDiffMessage { message: rtn_data });
                     log::warn!(
@@ -914,9 +1139,7 @@ impl DiffHandler {
                 }
             });
 
-            let rtn_data = DiffServerMessage::RtnData {
-                data: vec![notify_patch],
-            };
+            let rtn_data = DiffServerMessage::rtn_data(vec![notify_patch]);
 
             ctx_addr.do_send(SendDiffMessage { message: rtn_data });
             log::error!("DIFF cancel order failed: OrderRouter not available");
@@ -1050,11 +1273,11 @@ impl DiffHandler {
 
                         for kline in klines.iter() {
                             // K线ID使用时间戳除以周期得到序列号
-                            let kline_id = (kline.timestamp * 1_000_000) / duration; // 毫秒转纳秒后除以周期
+                            let kline_id = millis_to_nanos(kline.timestamp) / duration;
 
                             // DIFF协议要求datetime为UnixNano（纳秒）
                             // @yutiansut @quantaxis: 使用字符串传输避免JavaScript精度丢失
-                            let datetime_ns = kline.timestamp * 1_000_000; // 毫秒转纳秒
+                            let datetime_ns = millis_to_nanos(kline.timestamp);
 
                             kline_data.insert(
                                 kline_id.to_string(),
@@ -1211,6 +1434,7 @@ impl DiffHandler {
                 volume,
                 direction: _,
                 timestamp,
+                ..
             } => Some(serde_json::json!({
                 "quotes": {
                     instrument_id: {
@@ -1239,7 +1463,7 @@ impl DiffHandler {
                 instrument_id,
                 period,
                 kline,
-                timestamp: _,
+                ..
             } => {
                 // 转换为 DIFF klines 格式（增量推送新K线）
                 // @yutiansut @quantaxis
@@ -1248,11 +1472,11 @@ impl DiffHandler {
                     .unwrap_or(0);
 
                 // K线ID使用时间戳除以周期得到序列号
-                let kline_id = (kline.timestamp * 1_000_000) / duration_ns; // 毫秒转纳秒后除以周期
+                let kline_id = millis_to_nanos(kline.timestamp) / duration_ns;
 
                 // DIFF协议要求datetime为UnixNano（纳秒）
                 // @yutiansut @quantaxis: 使用字符串传输避免JavaScript精度丢失
-                let datetime_ns = kline.timestamp * 1_000_000; // 毫秒转纳秒
+                let datetime_ns = millis_to_nanos(kline.timestamp);
 
                 Some(serde_json::json!({
                     "klines": {
@@ -1294,30 +1518,270 @@ impl DiffHandler {
                     }
                 }))
             }
+
+            MarketDataEvent::AuctionIndicator {
+                instrument_id,
+                auction_price,
+                auction_volume,
+                unfilled_buy_volume: _,
+                unfilled_sell_volume: _,
+                timestamp,
+            } => {
+                // 竞价阶段填充 quote 的 auction_price/auction_volume @yutiansut @quantaxis
+                Some(serde_json::json!({
+                    "quotes": {
+                        instrument_id: {
+                            "instrument_id": instrument_id,
+                            "auction_price": auction_price,
+                            "auction_volume": auction_volume,
+                            "datetime": timestamp,
+                        }
+                    }
+                }))
+            }
+
+            MarketDataEvent::PriceLimitChanged {
+                instrument_id,
+                upper_limit,
+                lower_limit,
+                timestamp,
+                ..
+            } => {
+                // 扩板/恢复后更新 quote 的涨跌停价 @yutiansut @quantaxis
+                Some(serde_json::json!({
+                    "quotes": {
+                        instrument_id: {
+                            "instrument_id": instrument_id,
+                            "upper_limit": upper_limit,
+                            "lower_limit": lower_limit,
+                            "datetime": timestamp,
+                        }
+                    }
+                }))
+            }
+
+            MarketDataEvent::TradingStateChanged {
+                instrument_id,
+                state,
+                reason,
+                resume_at,
+                timestamp,
+            } => {
+                // 熔断/恢复时更新 quote 的交易状态 @yutiansut @quantaxis
+                Some(serde_json::json!({
+                    "quotes": {
+                        instrument_id: {
+                            "instrument_id": instrument_id,
+                            "trading_state": state,
+                            "trading_state_reason": reason,
+                            "resume_at": resume_at,
+                            "datetime": timestamp,
+                        }
+                    }
+                }))
+            }
+        }
+    }
+
+    /// 处理全量快照请求
+    ///
+    /// 全量快照放入会话队列头部，随下一次（或正在挂起的）peek_message 下发，
+    /// 失败时直接推送错误通知。
+    fn handle_query_snapshot(
+        &self,
+        user_id: &str,
+        session_id: &str,
+        account_id: Option<String>,
+        ctx_addr: Addr<DiffWebsocketSession>,
+    ) {
+        match self.load_account_snapshot(user_id, session_id, account_id.as_deref()) {
+            Ok(version) => {
+                log::info!(
+                    "DIFF snapshot queued: user={}, session={}, version={}",
+                    user_id,
+                    session_id,
+                    version
+                );
+            }
+            Err(e) => {
+                let code = match e {
+                    ExchangeError::PermissionDenied(_) => 6001,
+                    _ => 6002,
+                };
+                let notify_patch = serde_json::json!({
+                    "notify": {
+                        "snapshot_error": {
+                            "type": "MESSAGE",
+                            "level": "ERROR",
+                            "code": code,
+                            "content": format!("Query snapshot failed: {}", e)
+                        }
+                    }
+                });
+
+                let rtn_data = DiffServerMessage::rtn_data(vec![notify_patch]);
+                ctx_addr.do_send(SendDiffMessage { message: rtn_data });
+                log::warn!("DIFF query snapshot failed for user {}: {}", user_id, e);
+            }
         }
     }
 
+    /// 处理重新同步请求
+    ///
+    /// 客户端检测到 rtn_data 序列号不连续，或重连登录后携带上次确认的序列号发送。
+    /// 补发数据或全量快照放入会话队列，随下一次（或正在挂起的）peek_message 下发。
+    fn handle_resync(
+        &self,
+        user_id: &str,
+        session_id: &str,
+        last_seq: u64,
+        ctx_addr: Addr<DiffWebsocketSession>,
+    ) {
+        match self
+            .snapshot_mgr
+            .resync_session(user_id, session_id, last_seq)
+        {
+            Some(outcome) => {
+                log::info!(
+                    "DIFF resync: user={}, session={}, last_seq={}, outcome={:?}",
+                    user_id,
+                    session_id,
+                    last_seq,
+                    outcome
+                );
+            }
+            None => {
+                let notify_patch = serde_json::json!({
+                    "notify": {
+                        "resync_error": {
+                            "type": "MESSAGE",
+                            "level": "ERROR",
+                            "code": 6003,
+                            "content": "Resync failed: session is not logged in"
+                        }
+                    }
+                });
+                ctx_addr.do_send(SendDiffMessage {
+                    message: DiffServerMessage::rtn_data(vec![notify_patch]),
+                });
+                log::warn!(
+                    "DIFF resync for unregistered session: user={}, session={}",
+                    user_id,
+                    session_id
+                );
+            }
+        }
+    }
+
+    /// 生成账户全量快照并重置会话队列，返回快照版本号
+    ///
+    /// 用户只能拉取自己名下的账户。快照由两个 patch 组成：先清空
+    /// `trade.{user_id}` 下的 accounts/positions/orders，再写入当前账户、持仓与未成交订单，
+    /// 并带上 `snapshot_version`；其后的增量在 SnapshotManager 中按版本号排在快照之后。
+    pub(crate) fn load_account_snapshot(
+        &self,
+        user_id: &str,
+        session_id: &str,
+        account_id: Option<&str>,
+    ) -> Result<u64, ExchangeError> {
+        let account = match account_id {
+            Some(account_id) => {
+                self.account_mgr
+                    .verify_account_ownership(account_id, user_id)?;
+                self.account_mgr.get_account(account_id)?
+            }
+            None => {
+                let default_account = self
+                    .account_mgr
+                    .get_accounts_by_user(user_id)
+                    .into_iter()
+                    .next();
+                match default_account {
+                    Some(account) => account,
+                    None => self.account_mgr.get_account(user_id)?,
+                }
+            }
+        };
+
+        // 账户读取放在会话锁内：快照与队列中的增量按同一顺序排列
+        self.snapshot_mgr
+            .reset_session_snapshot(user_id, session_id, |version| {
+                let qifi = account.write().get_qifi_slice();
+                let open_orders: serde_json::Map<String, serde_json::Value> = qifi
+                    .orders
+                    .iter()
+                    .filter(|(_, order)| order.status == "SUBMITTED" || order.status == "ALIVE")
+                    .filter_map(|(order_id, order)| {
+                        serde_json::to_value(order)
+                            .ok()
+                            .map(|value| (order_id.clone(), value))
+                    })
+                    .collect();
+
+                vec![
+                    serde_json::json!({
+                        "trade": {
+                            user_id: {
+                                "accounts": null,
+                                "positions": null,
+                                "orders": null
+                            }
+                        }
+                    }),
+                    serde_json::json!({
+                        "trade": {
+                            user_id: {
+                                "user_id": user_id,
+                                "snapshot_version": version,
+                                "accounts": {
+                                    qifi.account_cookie.clone(): qifi.accounts
+                                },
+                                "positions": qifi.positions,
+                                "orders": open_orders
+                            }
+                        }
+                    }),
+                ]
+            })
+            .ok_or_else(|| {
+                ExchangeError::InternalError(format!(
+                    "Session {} is not registered for user {}",
+                    session_id, user_id
+                ))
+            })
+    }
+
     /// 处理 peek_message（阻塞等待新数据）
     ///
     /// 实现 DIFF 协议的核心同步机制：
     /// 1. 调用 SnapshotManager::peek() 阻塞等待
-    /// 2. 收到 patch 后发送 rtn_data 消息
+    /// 2. 收到 patch 后发送带序列号（seq/prev_seq）的 rtn_data 消息
     ///
     /// # 性能特点
     ///
     /// - 零轮询：使用 Tokio Notify 异步等待
     /// - 低延迟：patch 产生后立即唤醒
     /// - 零拷贝：Arc 共享 SnapshotManager
-    async fn handle_peek_message(&self, user_id: &str, ctx_addr: Addr<DiffWebsocketSession>) {
+    async fn handle_peek_message(
+        &self,
+        user_id: &str,
+        session_id: &str,
+        ctx_addr: Addr<DiffWebsocketSession>,
+    ) {
         let snapshot_mgr = self.snapshot_mgr.clone();
         let user_id = user_id.to_string();
+        let session_id = session_id.to_string();
 
-        // 启动异步任务等待 peek
+        // 启动异步任务等待 peek（多终端登录时每个会话独立取 patch）
         tokio::spawn(async move {
-            match snapshot_mgr.peek(&user_id).await {
-                Some(patches) => {
-                    // 收到 patch，发送 rtn_data
-                    let rtn_data = DiffServerMessage::RtnData { data: patches };
+            match snapshot_mgr.peek_session_batch(&user_id, &session_id).await {
+                Some(batch) => {
+                    // 收到 patch，发送带序列号的 rtn_data
+                    let rtn_data = DiffServerMessage::RtnData {
+                        data: batch.patches,
+                        seq: batch.seq,
+                        prev_seq: batch.prev_seq,
+                    };
 
                     // 发送到 WebSocket session
                     ctx_addr.do_send(SendDiffMessage { message: rtn_data });
@@ -1346,6 +1810,12 @@ pub struct DiffWebsocketSession {
 
     /// 最后心跳时间
     pub heartbeat: std::time::Instant,
+
+    /// 握手时协商了 zstd 压缩（大快照/大 patch 以 Binary 帧压缩发送）
+    pub compression: bool,
+
+    /// 客户端终端信息（终端、IP）
+    pub client: ClientInfo,
 }
 
 impl DiffWebsocketSession {
@@ -1356,6 +1826,73 @@ impl DiffWebsocketSession {
             user_id: None,
             diff_handler,
             heartbeat: std::time::Instant::now(),
+            compression: false,
+            client: ClientInfo::default(),
+        }
+    }
+
+    /// 绑定用户：登记快照会话队列与会话登记表
+    fn bind_user(&self, user_id: &str, ctx: &mut ws::WebsocketContext<Self>) {
+        self.diff_handler
+            .snapshot_mgr
+            .register_session(user_id, &self.session_id);
+        WS_SESSION_REGISTRY.register(
+            user_id,
+            &self.session_id,
+            "diff",
+            self.client.clone(),
+            Some(ctx.address().recipient()),
+        );
+        // DIFF 行情订阅按用户共享，订阅配额也按用户计
+        WS_SUBSCRIPTION_QUOTA.attach_user(
+            user_id,
+            "diff",
+            Some(user_id),
+            self.diff_handler.user_manager.as_deref(),
+        );
+    }
+
+    /// 解绑用户，最后一个会话断开时清理用户快照
+    fn unbind_user(&self, user_id: &str) {
+        WS_SESSION_REGISTRY.unregister(user_id, &self.session_id);
+
+        let snapshot_mgr = self.diff_handler.snapshot_mgr.clone();
+        if snapshot_mgr.unregister_session(user_id, &self.session_id) == 0 {
+            WS_SUBSCRIPTION_QUOTA.detach(user_id);
+            let user_id = user_id.to_string();
+            tokio::spawn(async move {
+                snapshot_mgr.remove_user(&user_id).await;
+            });
+        }
+    }
+
+    /// 设置会话的认证用户（替换原有绑定）
+    fn authenticate_as(&mut self, user_id: String, ctx: &mut ws::WebsocketContext<Self>) {
+        if self.user_id.as_deref() == Some(user_id.as_str()) {
+            return;
+        }
+        if let Some(previous) = self.user_id.take() {
+            self.unbind_user(&previous);
+        }
+
+        self.bind_user(&user_id, ctx);
+        log::info!(
+            "Session {} authenticated as user {}",
+            self.session_id,
+            user_id
+        );
+        self.user_id = Some(user_id);
+    }
+
+    /// 直接向本会话发送 DIFF 消息
+    fn send_message(&self, ctx: &mut ws::WebsocketContext<Self>, message: &DiffServerMessage) {
+        match serde_json::to_string(message) {
+            Ok(json) => {
+                compression::send_json(ctx, "diff", json, self.compression);
+            }
+            Err(e) => {
+                log::error!("Failed to serialize DIFF message: {}", e);
+            }
         }
     }
 
@@ -1390,19 +1927,18 @@ impl Actor for DiffWebsocketSession {
     fn started(&mut self, ctx: &mut Self::Context) {
         log::info!("DIFF WebSocket session {} started", self.session_id);
         self.start_heartbeat(ctx);
+
+        if let Some(user_id) = self.user_id.clone() {
+            self.bind_user(&user_id, ctx);
+        }
     }
 
     fn stopped(&mut self, _ctx: &mut Self::Context) {
         log::info!("DIFF WebSocket session {} stopped", self.session_id);
 
-        // 清理用户快照
+        // 注销会话（其他终端仍在线时保留用户快照）
         if let Some(ref user_id) = self.user_id {
-            let snapshot_mgr = self.diff_handler.snapshot_mgr.clone();
-            let user_id = user_id.clone();
-
-            tokio::spawn(async move {
-                snapshot_mgr.remove_user(&user_id).await;
-            });
+            self.unbind_user(user_id);
         }
     }
 }
@@ -1414,43 +1950,61 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for DiffWebsocketSess
                 // 解析 DIFF 协议消息
                 match serde_json::from_str::<DiffClientMessage>(&text) {
                     Ok(diff_msg) => {
-                        // DIFF 协议消息
-                        if let Some(ref user_id) = self.user_id {
-                            let handler = self.diff_handler.clone();
-                            let user_id = user_id.clone();
-                            let ctx_addr = ctx.address();
-
-                            // 异步处理 DIFF 消息
-                            ctx.spawn(
-                                async move {
-                                    handler
-                                        .handle_diff_message(&user_id, diff_msg, ctx_addr)
-                                        .await;
-                                }
-                                .into_actor(self),
-                            );
-                        } else {
-                            // 未认证，只允许 ReqLogin 消息
-                            if matches!(diff_msg, DiffClientMessage::ReqLogin { .. }) {
+                        // 登录态状态机：登录/改密/登出在此处理，未登录的业务请求直接回权限错误
+                        let (transition, notifies) = self
+                            .diff_handler
+                            .handle_session_message(self.user_id.as_deref(), &diff_msg);
+                        if !notifies.is_empty() {
+                            self.send_message(ctx, &DiffServerMessage::rtn_data(notifies));
+                        }
+
+                        match transition {
+                            SessionTransition::Dispatch => {
                                 let handler = self.diff_handler.clone();
-                                let user_id = "anonymous".to_string(); // 临时用户ID
+                                // 未登录会话只会分发心跳
+                                let user_id = self
+                                    .user_id
+                                    .clone()
+                                    .unwrap_or_else(|| "anonymous".to_string());
+                                let session_id = self.session_id.clone();
                                 let ctx_addr = ctx.address();
 
+                                // 异步处理 DIFF 消息
                                 ctx.spawn(
                                     async move {
                                         handler
-                                            .handle_diff_message(&user_id, diff_msg, ctx_addr)
+                                            .handle_diff_message(
+                                                &user_id,
+                                                &session_id,
+                                                diff_msg,
+                                                ctx_addr,
+                                            )
                                             .await;
                                     }
                                     .into_actor(self),
                                 );
-                            } else {
-                                log::warn!(
-                                    "Unauthenticated DIFF message from session {}, rejecting: {:?}",
-                                    self.session_id,
-                                    diff_msg
+                            }
+                            SessionTransition::Handled => {}
+                            SessionTransition::LoggedIn { user_id, username } => {
+                                self.authenticate_as(user_id.clone(), ctx);
+                                let handler = self.diff_handler.clone();
+                                ctx.spawn(
+                                    async move {
+                                        handler.on_login(&user_id, &username).await;
+                                    }
+                                    .into_actor(self),
                                 );
                             }
+                            SessionTransition::LoggedOut => {
+                                if let Some(user_id) = self.user_id.take() {
+                                    self.unbind_user(&user_id);
+                                    log::info!(
+                                        "Session {} logged out from user {}",
+                                        self.session_id,
+                                        user_id
+                                    );
+                                }
+                            }
                         }
                     }
 
@@ -1493,14 +2047,7 @@ impl ActixHandler<SendDiffMessage> for DiffWebsocketSession {
     type Result = ();
 
     fn handle(&mut self, msg: SendDiffMessage, ctx: &mut Self::Context) {
-        match serde_json::to_string(&msg.message) {
-            Ok(json) => {
-                ctx.text(json);
-            }
-            Err(e) => {
-                log::error!("Failed to serialize DIFF message: {}", e);
-            }
-        }
+        self.send_message(ctx, &msg.message);
     }
 }
 
@@ -1517,16 +2064,62 @@ impl ActixMessage for SetUserId {
 impl ActixHandler<SetUserId> for DiffWebsocketSession {
     type Result = ();
 
-    fn handle(&mut self, msg: SetUserId, _ctx: &mut Self::Context) {
-        self.user_id = Some(msg.user_id.clone());
-        log::info!(
-            "Session {} authenticated as user {}",
+    fn handle(&mut self, msg: SetUserId, ctx: &mut Self::Context) {
+        self.authenticate_as(msg.user_id, ctx);
+    }
+}
+
+/// 踢下线：推送通知后断开（kick_previous 登录策略）
+impl ActixHandler<KickSession> for DiffWebsocketSession {
+    type Result = ();
+
+    fn handle(&mut self, msg: KickSession, ctx: &mut Self::Context) {
+        log::warn!(
+            "DIFF session {} of user {} kicked",
             self.session_id,
             msg.user_id
         );
+
+        let notify = DiffServerMessage::rtn_data(vec![serde_json::json!({
+            "notify": {
+                "session_kicked": {
+                    "type": "MESSAGE",
+                    "level": "WARNING",
+                    "code": 1003,
+                    "content": msg.reason
+                }
+            }
+        })]);
+        if let Ok(json) = serde_json::to_string(&notify) {
+            compression::send_json(ctx, "diff", json, self.compression);
+        }
+        ctx.close(Some(ws::CloseReason {
+            code: ws::CloseCode::Policy,
+            description: Some(msg.reason),
+        }));
+        ctx.stop();
     }
 }
 
+/// DIFF 标准通知 patch：`{"notify": {key: {type, level, code, content}}}`
+fn notify_patch(
+    key: &str,
+    level: &str,
+    code: i32,
+    content: impl Into<String>,
+) -> serde_json::Value {
+    serde_json::json!({
+        "notify": {
+            key: {
+                "type": "MESSAGE",
+                "level": level,
+                "code": code,
+                "content": content.into()
+            }
+        }
+    })
+}
+
 /// 生成模拟K线数据用于测试 @yutiansut @quantaxis
 ///
 /// 当没有真实交易数据时，生成模拟K线供前端测试图表渲染
@@ -1665,4 +2258,203 @@ mod tests {
         assert!(patches.is_some());
         assert_eq!(patches.unwrap().len(), 1);
     }
+
+    #[tokio::test]
+    async fn test_query_snapshot_then_increments() {
+        use crate::core::account_ext::{AccountType, OpenAccountRequest};
+
+        let snapshot_mgr = Arc::new(SnapshotManager::with_timeout(Duration::from_millis(100)));
+        let account_mgr = Arc::new(AccountManager::new());
+        for (user_id, account_id) in [("u1", "u1"), ("u2", "acc_u2")] {
+            account_mgr
+                .open_account(OpenAccountRequest {
+                    user_id: user_id.to_string(),
+                    account_id: Some(account_id.to_string()),
+                    account_name: account_id.to_string(),
+                    init_cash: 100000.0,
+                    account_type: AccountType::Individual,
+                })
+                .unwrap();
+        }
+        let handler = DiffHandler::new(snapshot_mgr.clone(), account_mgr);
+        snapshot_mgr.register_session("u1", "s1");
+
+        // 拉取快照前已有未发送的增量：包含在全量快照内，不再单独下发
+        snapshot_mgr
+            .push_patch("u1", serde_json::json!({"notify": {"n1": {"code": 0}}}))
+            .await;
+        let version = handler.load_account_snapshot("u1", "s1", None).unwrap();
+        assert_eq!(version, 1);
+
+        let patches = snapshot_mgr.peek_session("u1", "s1").await.unwrap();
+        assert_eq!(patches.len(), 2);
+        assert!(patches[0]["trade"]["u1"]["orders"].is_null());
+        let trade = &patches[1]["trade"]["u1"];
+        assert_eq!(trade["snapshot_version"], 1);
+        assert_eq!(trade["accounts"]["u1"]["balance"], 100000.0);
+        assert!(trade["positions"].as_object().unwrap().is_empty());
+        assert!(trade["orders"].as_object().unwrap().is_empty());
+
+        // 之后的增量按顺序继续推送
+        let increment = serde_json::json!({
+            "trade": {"u1": {"accounts": {"u1": {"balance": 100500.0}}}}
+        });
+        snapshot_mgr.push_patch("u1", increment.clone()).await;
+        assert_eq!(
+            snapshot_mgr.peek_session("u1", "s1").await.unwrap(),
+            vec![increment]
+        );
+        assert_eq!(snapshot_mgr.snapshot_version("u1"), Some(2));
+        let merged = snapshot_mgr.get_snapshot("u1").await.unwrap();
+        assert_eq!(merged["trade"]["u1"]["accounts"]["u1"]["balance"], 100500.0);
+
+        // 只能拉取自己的账户；未登记的会话无法拉取
+        assert!(matches!(
+            handler.load_account_snapshot("u1", "s1", Some("acc_u2")),
+            Err(ExchangeError::PermissionDenied(_))
+        ));
+        assert!(handler.load_account_snapshot("u1", "s2", None).is_err());
+        assert!(snapshot_mgr.peek_session("u1", "s1").await.is_none());
+    }
+
+    /// 按会话登录态处理一条原始报文，返回登录态变化与通知（key, code）
+    fn session_step(
+        handler: &DiffHandler,
+        session_user: &mut Option<String>,
+        raw: &str,
+    ) -> (SessionTransition, Option<(String, i64)>) {
+        let msg: DiffClientMessage = serde_json::from_str(raw).unwrap();
+        let (transition, notifies) = handler.handle_session_message(session_user.as_deref(), &msg);
+        match &transition {
+            SessionTransition::LoggedIn { user_id, .. } => *session_user = Some(user_id.clone()),
+            SessionTransition::LoggedOut => *session_user = None,
+            _ => {}
+        }
+        let notify = notifies.first().map(|patch| {
+            let (key, body) = patch["notify"].as_object().unwrap().iter().next().unwrap();
+            (key.clone(), body["code"].as_i64().unwrap())
+        });
+        (transition, notify)
+    }
+
+    /// 会话状态机：未登录 → 登录 → 改密 → 登出 → 旧密码登录失败
+    #[test]
+    fn test_session_login_password_state_machine() {
+        use crate::user::UserRegisterRequest;
+
+        let user_mgr = Arc::new(UserManager::new());
+        let user = user_mgr
+            .register(UserRegisterRequest {
+                username: "diff_user".to_string(),
+                password: "old_pass".to_string(),
+                phone: None,
+                email: None,
+                real_name: None,
+                id_card: None,
+            })
+            .unwrap();
+        let handler = DiffHandler::new(
+            Arc::new(SnapshotManager::new()),
+            Arc::new(AccountManager::new()),
+        )
+        .with_user_manager(user_mgr);
+
+        let insert_order = r#"{"aid":"insert_order","user_id":"diff_user","exchange_id":"SHFE","instrument_id":"cu2501","direction":"BUY","offset":"OPEN","volume":1,"price_type":"LIMIT","limit_price":70000.0}"#;
+        let login = |password: &str| {
+            format!(
+                r#"{{"aid":"req_login","bid":"quantaxis","user_name":"diff_user","password":"{}"}}"#,
+                password
+            )
+        };
+        let change_password = |old: &str, new: &str| {
+            format!(
+                r#"{{"aid":"req_password","old_password":"{}","new_password":"{}"}}"#,
+                old, new
+            )
+        };
+        let notified = |key: &str, code: i64| Some((key.to_string(), code));
+        let mut session = None;
+
+        // 未登录：交易与改密请求回权限错误，心跳照常分发
+        assert_eq!(
+            session_step(&handler, &mut session, insert_order),
+            (
+                SessionTransition::Handled,
+                notified("permission_denied", 1004)
+            )
+        );
+        assert_eq!(
+            session_step(&handler, &mut session, &change_password("old_pass", "x")).1,
+            notified("permission_denied", 1004)
+        );
+        assert_eq!(
+            session_step(&handler, &mut session, r#"{"aid":"ping"}"#),
+            (SessionTransition::Dispatch, None)
+        );
+
+        // 登录：密码错误回标准错误通知，正确后会话绑定用户
+        assert_eq!(
+            session_step(&handler, &mut session, &login("wrong")).1,
+            notified("login_failed", 1001)
+        );
+        assert!(session.is_none());
+        let (transition, notify) = session_step(&handler, &mut session, &login("old_pass"));
+        assert!(matches!(transition, SessionTransition::LoggedIn { .. }));
+        assert!(notify.is_none());
+        assert_eq!(session.as_deref(), Some(user.user_id.as_str()));
+        assert_eq!(
+            session_step(&handler, &mut session, insert_order),
+            (SessionTransition::Dispatch, None)
+        );
+
+        // 先到先得：已登录的会话不能再次登录
+        assert_eq!(
+            session_step(&handler, &mut session, &login("old_pass")),
+            (
+                SessionTransition::Handled,
+                notified("already_logged_in", 1005)
+            )
+        );
+
+        // 改密：原密码错误失败，正确后成功
+        assert_eq!(
+            session_step(
+                &handler,
+                &mut session,
+                &change_password("wrong", "new_pass")
+            )
+            .1,
+            notified("password_change_failed", 1006)
+        );
+        assert_eq!(
+            session_step(
+                &handler,
+                &mut session,
+                &change_password("old_pass", "new_pass")
+            )
+            .1,
+            notified("password_changed", 0)
+        );
+
+        // 登出后交易请求重新需要登录
+        assert_eq!(
+            session_step(&handler, &mut session, r#"{"aid":"req_logout"}"#),
+            (SessionTransition::LoggedOut, notified("logout", 0))
+        );
+        assert!(session.is_none());
+        assert_eq!(
+            session_step(&handler, &mut session, insert_order).1,
+            notified("permission_denied", 1004)
+        );
+
+        // 旧密码登录失败，新密码登录成功
+        assert_eq!(
+            session_step(&handler, &mut session, &login("old_pass")).1,
+            notified("login_failed", 1001)
+        );
+        assert!(matches!(
+            session_step(&handler, &mut session, &login("new_pass")).0,
+            SessionTransition::LoggedIn { .. }
+        ));
+    }
 }
@@ -15,6 +15,8 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use crate::service::overload::RequestClass;
+
 /// DIFF 协议客户端消息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "aid", rename_all = "snake_case")]
@@ -33,6 +35,15 @@ pub enum DiffClientMessage {
         password: String,
     },
 
+    /// 修改密码（需已登录）
+    ReqPassword {
+        old_password: String,
+        new_password: String,
+    },
+
+    /// 登出：会话解绑用户，之后的交易类请求需重新登录
+    ReqLogout,
+
     /// 订阅行情
     SubscribeQuote {
         ins_list: String, // 逗号分隔的合约列表，如 "SHFE.cu1612,CFFEX.IF1701"
@@ -59,6 +70,9 @@ pub enum DiffClientMessage {
         volume_condition: Option<String>, // ANY/MIN/ALL
         #[serde(skip_serializing_if = "Option::is_none")]
         time_condition: Option<String>, // IOC/GFS/GFD/GTD/GTC/GFA
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        close_lot_id: Option<u64>, // 指定平仓批次（仅平仓委托）
     },
 
     /// 撤单
@@ -77,6 +91,41 @@ pub enum DiffClientMessage {
         duration: i64,    // 周期(ns)，tick=0, 日线=86400000000000
         view_width: i32,  // 图表宽度
     },
+
+    /// 按需拉取账户全量快照（账户、持仓、未成交订单）
+    QuerySnapshot {
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        account_id: Option<String>, // 未提供时取用户的默认账户
+    },
+
+    /// 重新同步：检测到 rtn_data 序列号不连续或重连后发送，
+    /// 服务端从 last_seq 之后补发，历史不足时下发全量快照重置
+    Resync {
+        #[serde(default)]
+        last_seq: u64, // 已确认的序列号，0 表示无基线
+    },
+}
+
+impl DiffClientMessage {
+    /// 过载保护分类：行情订阅/图表为非关键请求，交易与账户快照为关键请求，
+    /// peek_message 长轮询、登录态报文、心跳不受限制
+    pub fn request_class(&self) -> RequestClass {
+        match self {
+            DiffClientMessage::SubscribeQuote { .. } | DiffClientMessage::SetChart { .. } => {
+                RequestClass::NonCritical
+            }
+            DiffClientMessage::InsertOrder { .. }
+            | DiffClientMessage::CancelOrder { .. }
+            | DiffClientMessage::QuerySnapshot { .. }
+            | DiffClientMessage::Resync { .. } => RequestClass::Critical,
+            DiffClientMessage::Ping
+            | DiffClientMessage::PeekMessage
+            | DiffClientMessage::ReqLogin { .. }
+            | DiffClientMessage::ReqPassword { .. }
+            | DiffClientMessage::ReqLogout => RequestClass::Exempt,
+        }
+    }
 }
 
 /// DIFF 协议服务端消息
@@ -87,11 +136,32 @@ pub enum DiffServerMessage {
     Pong,
 
     /// 业务信息截面更新（rtn_data）
+    ///
+    /// peek_message 下发的批次带序列号：`prev_seq` 与客户端已确认序号不一致即为丢包，
+    /// 客户端发送 resync；`prev_seq` 缺省而 `seq` 存在表示全量重置。
+    /// 错误通知等带外消息不带序列号，不参与连续性校验。
     RtnData {
         data: Vec<Value>, // JSON Merge Patch 数组
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        seq: Option<u64>, // 本批次最后一个 patch 的序列号
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        prev_seq: Option<u64>, // 应用本批次前客户端应处于的序列号
     },
 }
 
+impl DiffServerMessage {
+    /// 不带序列号的 rtn_data（错误通知、踢线等带外消息）
+    pub fn rtn_data(data: Vec<Value>) -> Self {
+        DiffServerMessage::RtnData {
+            data,
+            seq: None,
+            prev_seq: None,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -141,21 +211,19 @@ mod tests {
         // 账户数据更新示例:
         //   balance = static_balance + float_profit (QIFI 自恰性规则)
         // -------------------------------------------------------------------------
-        let msg = DiffServerMessage::RtnData {
-            data: vec![
-                // 第一个 patch: 更新账户余额
-                json!({
-                    "balance": 100000.0,
-                    "available": 80000.0,
-                    "margin": 20000.0
-                }),
-                // 第二个 patch: 更新持仓浮盈
-                json!({
-                    "float_profit": 1500.0,
-                    "position_profit": 1200.0
-                }),
-            ],
-        };
+        let msg = DiffServerMessage::rtn_data(vec![
+            // 第一个 patch: 更新账户余额
+            json!({
+                "balance": 100000.0,
+                "available": 80000.0,
+                "margin": 20000.0
+            }),
+            // 第二个 patch: 更新持仓浮盈
+            json!({
+                "float_profit": 1500.0,
+                "position_profit": 1200.0
+            }),
+        ]);
         let json_str = serde_json::to_string(&msg).unwrap();
 
         // 验证 aid 字段
@@ -212,9 +280,7 @@ mod tests {
             }
         });
 
-        let msg = DiffServerMessage::RtnData {
-            data: vec![account_update],
-        };
+        let msg = DiffServerMessage::rtn_data(vec![account_update]);
 
         let json_str = serde_json::to_string(&msg).unwrap();
         let parsed: serde_json::Value = serde_json::from_str(&json_str).unwrap();
@@ -269,6 +335,7 @@ mod tests {
             limit_price: Some(75230.0),
             volume_condition: None,
             time_condition: None,
+            close_lot_id: None,
         };
 
         let json = serde_json::to_value(&msg).unwrap();
@@ -311,6 +378,7 @@ mod tests {
             limit_price: Some(4500.0),
             volume_condition: Some("ANY".to_string()),  // 任意数量
             time_condition: Some("IOC".to_string()),    // 立即成交否则撤销
+            close_lot_id: None,
         };
 
         let json = serde_json::to_value(&msg).unwrap();
@@ -392,9 +460,7 @@ mod tests {
             }
         });
 
-        let msg = DiffServerMessage::RtnData {
-            data: vec![order_update],
-        };
+        let msg = DiffServerMessage::rtn_data(vec![order_update]);
 
         let json_str = serde_json::to_string(&msg).unwrap();
         let parsed: serde_json::Value = serde_json::from_str(&json_str).unwrap();
@@ -483,9 +549,7 @@ mod tests {
             }
         });
 
-        let msg = DiffServerMessage::RtnData {
-            data: vec![position_update],
-        };
+        let msg = DiffServerMessage::rtn_data(vec![position_update]);
 
         let json_str = serde_json::to_string(&msg).unwrap();
         let parsed: serde_json::Value = serde_json::from_str(&json_str).unwrap();
@@ -605,6 +669,45 @@ mod tests {
     // 行情订阅测试
     // ═══════════════════════════════════════════════════════════════════════════
 
+    #[test]
+    fn test_query_snapshot_serialization() {
+        let parsed: DiffClientMessage =
+            serde_json::from_str(r#"{"aid":"query_snapshot","account_id":"ACC001"}"#).unwrap();
+        assert!(matches!(
+            parsed,
+            DiffClientMessage::QuerySnapshot { account_id: Some(ref id) } if id == "ACC001"
+        ));
+
+        let parsed: DiffClientMessage =
+            serde_json::from_str(r#"{"aid":"query_snapshot"}"#).unwrap();
+        assert!(matches!(
+            parsed,
+            DiffClientMessage::QuerySnapshot { account_id: None }
+        ));
+    }
+
+    #[test]
+    fn test_resync_and_rtn_data_seq_serialization() {
+        let parsed: DiffClientMessage =
+            serde_json::from_str(r#"{"aid":"resync","last_seq":42}"#).unwrap();
+        assert!(matches!(parsed, DiffClientMessage::Resync { last_seq: 42 }));
+
+        // 带序列号的批次
+        let msg = DiffServerMessage::RtnData {
+            data: vec![json!({"balance": 1.0})],
+            seq: Some(45),
+            prev_seq: Some(42),
+        };
+        let json = serde_json::to_value(&msg).unwrap();
+        assert_eq!(json["seq"], 45);
+        assert_eq!(json["prev_seq"], 42);
+
+        // 带外消息不输出序列号字段
+        let json = serde_json::to_value(DiffServerMessage::rtn_data(vec![])).unwrap();
+        assert!(json.get("seq").is_none());
+        assert!(json.get("prev_seq").is_none());
+    }
+
     #[test]
     fn test_subscribe_quote_serialization() {
         // -------------------------------------------------------------------------
@@ -678,9 +781,7 @@ mod tests {
             }
         });
 
-        let msg = DiffServerMessage::RtnData {
-            data: vec![quote_update],
-        };
+        let msg = DiffServerMessage::rtn_data(vec![quote_update]);
 
         let json_str = serde_json::to_string(&msg).unwrap();
         let parsed: serde_json::Value = serde_json::from_str(&json_str).unwrap();
@@ -743,9 +844,7 @@ mod tests {
             }
         });
 
-        let msg = DiffServerMessage::RtnData {
-            data: vec![trade_notification],
-        };
+        let msg = DiffServerMessage::rtn_data(vec![trade_notification]);
 
         let json_str = serde_json::to_string(&msg).unwrap();
         let parsed: serde_json::Value = serde_json::from_str(&json_str).unwrap();
@@ -0,0 +1,268 @@
+//! 数据导出 WebSocket 会话
+//!
+//! 路由: `/ws/export?token=<JWT>`（需要 ExportData 权限）
+//!
+//! 客户端订阅:
+//! ```json
+//! {"aid": "subscribe_export", "types": ["orders", "trades", "account_changes"], "last_sequence": 0}
+//! ```
+//! `last_sequence` 为客户端最后收到的全局 sequence（首次订阅传 0 从头开始），
+//! 服务端从其后按 sequence 顺序推送 `export_data`，断线重连后凭它续传。
+//!
+//! @yutiansut @quantaxis
+
+use crate::storage::export::{ExportLog, ExportRecordKind, ExportSource};
+use actix::{Actor, ActorContext, AsyncContext, StreamHandler};
+use actix_web_actors::ws;
+use serde::Deserialize;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// 推送检查间隔
+const EXPORT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 单条 WebSocket 消息最多携带的记录数
+const MAX_RECORDS_PER_MESSAGE: usize = 1000;
+
+/// 单连接每秒最多读取的记录数（可通过环境变量配置），避免补数拖慢在线存储
+fn get_max_records_per_sec() -> usize {
+    std::env::var("QAEXCHANGE_EXPORT_MAX_RECORDS_PER_SEC")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(50_000)
+}
+
+/// 客户端消息
+#[derive(Debug, Deserialize)]
+#[serde(tag = "aid", rename_all = "snake_case")]
+pub enum ExportClientMessage {
+    /// 订阅导出流
+    SubscribeExport {
+        types: Vec<ExportRecordKind>,
+        #[serde(default)]
+        last_sequence: u64,
+    },
+    /// 取消订阅
+    UnsubscribeExport,
+}
+
+/// 导出订阅状态
+struct ExportSubscription {
+    kinds: Vec<ExportRecordKind>,
+    /// 已推送（扫描）到的 sequence
+    cursor: u64,
+    /// 上一批的读取来源（切换时记录日志）
+    source: Option<ExportSource>,
+}
+
+/// 数据导出 WebSocket 会话
+pub struct ExportWsSession {
+    /// 会话 ID
+    pub id: String,
+
+    /// 已认证的用户 ID
+    pub user_id: String,
+
+    /// 最后心跳时间
+    pub heartbeat: Instant,
+
+    /// 导出日志
+    export_log: Arc<ExportLog>,
+
+    /// 当前订阅
+    subscription: Option<ExportSubscription>,
+
+    /// 单连接读取速率上限（条/秒）
+    max_records_per_sec: usize,
+}
+
+impl ExportWsSession {
+    pub fn new(session_id: String, user_id: String, export_log: Arc<ExportLog>) -> Self {
+        Self {
+            id: session_id,
+            user_id,
+            heartbeat: Instant::now(),
+            export_log,
+            subscription: None,
+            max_records_per_sec: get_max_records_per_sec(),
+        }
+    }
+
+    /// 启动心跳检查（与普通 WebSocket 会话使用相同配置）
+    fn start_heartbeat(&self, ctx: &mut ws::WebsocketContext<Self>) {
+        ctx.run_interval(super::session::get_heartbeat_interval(), |act, ctx| {
+            if Instant::now().duration_since(act.heartbeat) > super::session::get_client_timeout() {
+                log::warn!("Export session {} timed out, disconnecting", act.id);
+                ctx.stop();
+                return;
+            }
+            ctx.ping(b"");
+        });
+    }
+
+    /// 启动推送循环：每个周期按速率上限读取并推送
+    fn start_export_pump(&self, ctx: &mut ws::WebsocketContext<Self>) {
+        ctx.run_interval(EXPORT_POLL_INTERVAL, |act, ctx| {
+            act.pump(ctx);
+        });
+    }
+
+    fn pump(&mut self, ctx: &mut ws::WebsocketContext<Self>) {
+        let Some(subscription) = self.subscription.as_mut() else {
+            return;
+        };
+
+        let ticks_per_sec = (1000 / EXPORT_POLL_INTERVAL.as_millis()).max(1) as usize;
+        let mut budget = (self.max_records_per_sec / ticks_per_sec).max(1);
+
+        while budget > 0 && subscription.cursor < self.export_log.latest_sequence() {
+            let batch_limit = budget.min(MAX_RECORDS_PER_MESSAGE);
+            let batch = match self.export_log.read_after(
+                subscription.cursor,
+                &subscription.kinds,
+                batch_limit,
+            ) {
+                Ok(batch) => batch,
+                Err(e) => {
+                    log::error!("Export session {} read failed: {}", self.id, e);
+                    ctx.text(
+                        serde_json::json!({
+                            "aid": "export_error",
+                            "message": format!("Read export log failed: {}", e),
+                        })
+                        .to_string(),
+                    );
+                    return;
+                }
+            };
+
+            let scanned = (batch.last_sequence - subscription.cursor) as usize;
+            if scanned == 0 {
+                break;
+            }
+            budget = budget.saturating_sub(scanned);
+            subscription.cursor = batch.last_sequence;
+
+            if subscription.source != Some(batch.source) {
+                log::info!(
+                    "Export session {} switched to {:?} at sequence {}",
+                    self.id,
+                    batch.source,
+                    batch.last_sequence
+                );
+                subscription.source = Some(batch.source);
+            }
+
+            if batch.records.is_empty() {
+                continue;
+            }
+
+            let records: Vec<serde_json::Value> =
+                batch.records.iter().map(|r| r.to_json()).collect();
+            ctx.text(
+                serde_json::json!({
+                    "aid": "export_data",
+                    "source": batch.source,
+                    "last_sequence": batch.last_sequence,
+                    "records": records,
+                })
+                .to_string(),
+            );
+        }
+    }
+
+    fn handle_client_message(
+        &mut self,
+        msg: ExportClientMessage,
+        ctx: &mut ws::WebsocketContext<Self>,
+    ) {
+        match msg {
+            ExportClientMessage::SubscribeExport {
+                types,
+                last_sequence,
+            } => {
+                log::info!(
+                    "Export session {} (user {}) subscribed {:?} from sequence {}",
+                    self.id,
+                    self.user_id,
+                    types,
+                    last_sequence
+                );
+                ctx.text(
+                    serde_json::json!({
+                        "aid": "export_subscribed",
+                        "types": types,
+                        "last_sequence": last_sequence,
+                        "latest_sequence": self.export_log.latest_sequence(),
+                    })
+                    .to_string(),
+                );
+                self.subscription = Some(ExportSubscription {
+                    kinds: types,
+                    cursor: last_sequence,
+                    source: None,
+                });
+                self.pump(ctx);
+            }
+            ExportClientMessage::UnsubscribeExport => {
+                self.subscription = None;
+            }
+        }
+    }
+}
+
+impl Actor for ExportWsSession {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        log::info!(
+            "Export session {} started for user {}",
+            self.id,
+            self.user_id
+        );
+        self.start_heartbeat(ctx);
+        self.start_export_pump(ctx);
+    }
+
+    fn stopped(&mut self, _ctx: &mut Self::Context) {
+        log::info!(
+            "Export session {} stopped at sequence {:?}",
+            self.id,
+            self.subscription.as_ref().map(|s| s.cursor)
+        );
+    }
+}
+
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for ExportWsSession {
+    fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        match msg {
+            Ok(ws::Message::Ping(msg)) => {
+                self.heartbeat = Instant::now();
+                ctx.pong(&msg);
+            }
+            Ok(ws::Message::Pong(_)) => {
+                self.heartbeat = Instant::now();
+            }
+            Ok(ws::Message::Text(text)) => {
+                self.heartbeat = Instant::now();
+                match serde_json::from_str::<ExportClientMessage>(&text) {
+                    Ok(client_msg) => self.handle_client_message(client_msg, ctx),
+                    Err(e) => {
+                        ctx.text(
+                            serde_json::json!({
+                                "aid": "export_error",
+                                "message": format!("Invalid message format: {}", e),
+                            })
+                            .to_string(),
+                        );
+                    }
+                }
+            }
+            Ok(ws::Message::Close(reason)) => {
+                log::info!("Export session {} closed: {:?}", self.id, reason);
+                ctx.stop();
+            }
+            _ => {}
+        }
+    }
+}
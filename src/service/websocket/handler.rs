@@ -115,6 +115,7 @@ impl WsMessageHandler {
                             order_id: None,
                             error_code: Some(4003),
                             error_message: Some(format!("Account verification failed: {}", e)),
+                            adjusted_price: None,
                         };
                         session_addr.do_send(SendMessage(server_msg));
                         return Ok(());
@@ -138,6 +139,7 @@ impl WsMessageHandler {
                                     "Account not found for user {}: {}",
                                     user_id, e
                                 )),
+                                adjusted_price: None,
                             };
                             session_addr.do_send(SendMessage(server_msg));
                             return Ok(());
@@ -155,6 +157,10 @@ impl WsMessageHandler {
                     order_type,
                     time_condition: None,
                     volume_condition: None,
+                    hedge_flag: None,
+                    ttl_secs: None,
+                    client_order_id: None,
+                    close_lot_id: None,
                 };
 
                 let response = self.order_router.submit_order(req);
@@ -164,6 +170,7 @@ impl WsMessageHandler {
                     order_id: response.order_id,
                     error_code: response.error_code,
                     error_message: response.error_message,
+                    adjusted_price: response.adjusted_price,
                 };
 
                 session_addr.do_send(SendMessage(server_msg));
@@ -182,6 +189,7 @@ impl WsMessageHandler {
                             order_id: Some(order_id.clone()),
                             error_code: Some(4003),
                             error_message: Some(format!("Account verification failed: {}", e)),
+                            adjusted_price: None,
                         };
                         session_addr.do_send(SendMessage(server_msg));
                         return Ok(());
@@ -205,6 +213,7 @@ impl WsMessageHandler {
                                     "Account not found for user {}: {}",
                                     user_id, e
                                 )),
+                                adjusted_price: None,
                             };
                             session_addr.do_send(SendMessage(server_msg));
                             return Ok(());
@@ -224,6 +233,7 @@ impl WsMessageHandler {
                             order_id: Some(order_id),
                             error_code: None,
                             error_message: None,
+                            adjusted_price: None,
                         };
                         session_addr.do_send(SendMessage(server_msg));
                     }
@@ -233,6 +243,7 @@ impl WsMessageHandler {
                             order_id: Some(order_id),
                             error_code: Some(1001),
                             error_message: Some(format!("{:?}", e)),
+                            adjusted_price: None,
                         };
                         session_addr.do_send(SendMessage(server_msg));
                     }
@@ -21,6 +21,23 @@ pub enum ClientMessage {
         instruments: Vec<String>,
     },
 
+    /// 创建命名订阅组（推送合约为所有组的并集）
+    SubscribeGroup {
+        group: String,
+        instruments: Vec<String>,
+        #[serde(default)]
+        channels: Vec<String>,
+    },
+
+    /// 原子替换订阅组内容（移仓换月：新旧合约数据以 switch_seq 为界，不重不漏）
+    ReplaceGroup {
+        group: String,
+        instruments: Vec<String>,
+    },
+
+    /// 删除订阅组
+    DeleteGroup { group: String },
+
     /// 提交订单
     SubmitOrder {
         #[serde(default)]
@@ -51,6 +68,9 @@ pub enum ClientMessage {
     /// 查询持仓
     QueryPosition { instrument_id: Option<String> },
 
+    /// 推送延迟回执（对带 msg_id 的采样推送回报客户端接收时刻，纳秒）
+    LatencyReport { msg_id: u64, client_recv_ts: i64 },
+
     /// Ping（心跳）
     Ping,
 }
@@ -74,12 +94,26 @@ pub enum ServerMessage {
         message: String,
     },
 
+    /// 订阅组操作响应
+    SubscriptionGroupResponse {
+        success: bool,
+        group: String,
+        /// 变更后推送的合约（所有组并集）
+        instruments: Vec<String>,
+        /// 切换序号：本会话第 1..=switch_seq 条行情属于旧订阅，之后属于新订阅
+        switch_seq: u64,
+        message: String,
+    },
+
     /// 订单提交响应
     OrderResponse {
         success: bool,
         order_id: Option<String>,
         error_code: Option<u32>,
         error_message: Option<String>,
+        /// 价格按最小变动价位规整后的实际委托价（未调整时不返回）
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        adjusted_price: Option<f64>,
     },
 
     /// 成交推送
@@ -92,6 +126,11 @@ pub enum ServerMessage {
         price: f64,
         volume: f64,
         timestamp: i64,
+        /// 业务事件产生时刻（纳秒）
+        origin_ts: i64,
+        /// 延迟采样消息 ID（仅采样命中的消息携带，客户端据此回发 latency_report）
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        msg_id: Option<u64>,
     },
 
     /// 订单状态推送（交易所回报格式）
@@ -107,6 +146,11 @@ pub enum ServerMessage {
         price: f64,  // 价格
         status: String,
         timestamp: i64,
+        /// 业务事件产生时刻（纳秒）
+        origin_ts: i64,
+        /// 延迟采样消息 ID（仅采样命中的消息携带，客户端据此回发 latency_report）
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        msg_id: Option<u64>,
     },
 
     /// 账户更新推送
@@ -118,6 +162,11 @@ pub enum ServerMessage {
         profit: f64,
         risk_ratio: f64,
         timestamp: i64,
+        /// 业务事件产生时刻（纳秒）
+        origin_ts: i64,
+        /// 延迟采样消息 ID（仅采样命中的消息携带，客户端据此回发 latency_report）
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        msg_id: Option<u64>,
     },
 
     /// 订单簿推送（Level2）
@@ -145,6 +194,22 @@ pub enum ServerMessage {
     /// 错误消息
     Error { code: u32, message: String },
 
+    /// 会话被踢下线（同一用户在其他终端登录，随后断开连接）
+    SessionKicked { message: String },
+
+    /// 系统事件推送（system_events 频道，需要 ViewMonitoring 权限）
+    SystemEvent {
+        /// 事件类别：instrument/settlement/storage/liquidation
+        category: String,
+        /// 事件级别：INFO/WARNING/ERROR
+        level: String,
+        title: String,
+        details: serde_json::Value,
+        /// 事件来源模块
+        source: String,
+        timestamp: i64,
+    },
+
     /// Pong（心跳响应）
     Pong,
 }
@@ -6,14 +6,25 @@
 //!
 //! 1. **原有消息协议**: 向后兼容的 type-based 消息
 //! 2. **DIFF 协议**: 新增的 aid-based 差分推送协议
+//! 3. **数据导出流**: 订单/成交/账户变更的增量镜像（`/ws/export`）
+//!
+//! 行情/交易推送支持握手协商 zstd 压缩（`?compression=zstd`），见 [`compression`]。
+//! 每个连接的订阅合约数与推送速率按用户等级限制，见 [`subscription_quota`]。
+//! 具备 ViewMonitoring 权限的会话可订阅 `system_events` 运维频道，见 [`system_events`]。
 
+pub mod compression;
 pub mod diff_handler;
 pub mod diff_messages;
+pub mod export_session;
 pub mod handler;
 pub mod messages;
 pub mod session;
+pub mod session_registry;
+pub mod subscription_quota;
+pub mod system_events;
 
 use actix::Addr;
+use actix_web::http::header::{HeaderName, HeaderValue};
 use actix_web::{web, Error, HttpRequest, HttpResponse};
 use actix_web_actors::ws;
 use crossbeam::channel::Sender;
@@ -21,13 +32,18 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use uuid::Uuid;
 
+use self::compression::{COMPRESSION_HEADER, COMPRESSION_ZSTD, WS_COMPRESSOR};
 use self::diff_handler::{DiffHandler, DiffWebsocketSession};
+use self::export_session::ExportWsSession;
 use self::handler::{create_handler, WsMessageHandler};
 use self::session::{WsSession, WsSessionMessage};
+use self::session_registry::ClientInfo;
 use crate::exchange::{AccountManager, OrderRouter, TradeGateway};
 use crate::market::MarketDataBroadcaster;
+use crate::notification::{NotificationBroker, NotificationStore};
 use crate::protocol::diff::snapshot::SnapshotManager;
-use crate::user::UserManager;
+use crate::storage::export::ExportLog;
+use crate::user::{Permission, UserManager};
 
 /// WebSocket 服务器
 pub struct WebSocketServer {
@@ -51,6 +67,12 @@ pub struct WebSocketServer {
 
     /// SnapshotManager - 用于广播公告等系统通知 @yutiansut @quantaxis
     snapshot_mgr: Arc<SnapshotManager>,
+
+    /// 数据导出日志（可选，未设置时 /ws/export 不可用）
+    export_log: Option<Arc<ExportLog>>,
+
+    /// 通知路由中心（可选，未设置时 system_events 频道不可用）
+    notification_broker: Option<Arc<NotificationBroker>>,
 }
 
 impl WebSocketServer {
@@ -98,9 +120,29 @@ impl WebSocketServer {
             market_broadcaster,
             diff_handler,
             snapshot_mgr,
+            export_log: None,
+            notification_broker: None,
         }
     }
 
+    /// 设置数据导出日志
+    pub fn with_export_log(mut self, export_log: Arc<ExportLog>) -> Self {
+        self.export_log = Some(export_log);
+        self
+    }
+
+    /// 设置通知路由中心（提供 system_events 运维频道）
+    pub fn with_notification_broker(mut self, broker: Arc<NotificationBroker>) -> Self {
+        self.notification_broker = Some(broker);
+        self
+    }
+
+    /// 设置用户消息中心（DIFF 登录成功后推送未读计数）
+    pub fn with_notification_store(self, store: Arc<NotificationStore>) -> Self {
+        self.diff_handler.set_notification_store(store);
+        self
+    }
+
     /// 获取 SnapshotManager 用于广播系统通知 @yutiansut @quantaxis
     pub fn get_snapshot_manager(&self) -> Arc<SnapshotManager> {
         self.snapshot_mgr.clone()
@@ -114,23 +156,28 @@ impl WebSocketServer {
         user_id: Option<String>,
     ) -> Result<HttpResponse, Error> {
         let session_id = Uuid::new_v4().to_string();
+        let compression = WS_COMPRESSOR.negotiate(&req);
 
         // 创建会话并设置 sessions 引用
         let mut session = WsSession::new(session_id.clone(), self.message_sender.clone())
             .with_sessions(self.sessions.clone())
             .with_user_manager(self.user_manager.clone())
-            .with_market_broadcaster(self.market_broadcaster.clone());
+            .with_market_broadcaster(self.market_broadcaster.clone())
+            .with_compression(compression)
+            .with_client(ClientInfo::from_request(&req));
+        if let Some(ref broker) = self.notification_broker {
+            session = session.with_notification_broker(broker.clone());
+        }
 
-        // 如果提供了 user_id，订阅成交通知
-        if let Some(ref uid) = user_id {
-            let receiver = self.trade_gateway.subscribe_user(uid.clone());
-            session.notification_receiver = Some(receiver);
+        // 如果提供了 user_id，按会话订阅成交通知（多终端各自独立队列）
+        if let Some(uid) = user_id {
+            session = session.with_user_notifications(self.trade_gateway.clone(), uid);
         }
 
         // 启动 WebSocket（session 会在 Actor::started() 中自动注册）
         let resp = ws::start(session, &req, stream)?;
 
-        Ok(resp)
+        Ok(confirm_compression(resp, compression))
     }
 
     /// 处理 DIFF 协议 WebSocket 连接
@@ -149,9 +196,12 @@ impl WebSocketServer {
         user_id: Option<String>,
     ) -> Result<HttpResponse, Error> {
         let session_id = Uuid::new_v4().to_string();
+        let compression = WS_COMPRESSOR.negotiate(&req);
 
         // 创建 DIFF WebSocket 会话（零拷贝共享 DiffHandler）
         let mut session = DiffWebsocketSession::new(session_id.clone(), self.diff_handler.clone());
+        session.compression = compression;
+        session.client = ClientInfo::from_request(&req);
 
         // 如果提供了 user_id，设置认证状态
         if let Some(uid) = user_id {
@@ -167,10 +217,52 @@ impl WebSocketServer {
         // 启动 DIFF WebSocket（低延迟异步架构）
         let resp = ws::start(session, &req, stream)?;
 
-        Ok(resp)
+        Ok(confirm_compression(resp, compression))
+    }
+
+    /// 处理数据导出 WebSocket 连接
+    ///
+    /// 路由: `/ws/export?token=<JWT>`，需要 ExportData 权限
+    pub async fn handle_export_connection(
+        &self,
+        req: HttpRequest,
+        stream: web::Payload,
+        token: Option<String>,
+    ) -> Result<HttpResponse, Error> {
+        let Some(export_log) = self.export_log.clone() else {
+            return Ok(HttpResponse::ServiceUnavailable().body("Export stream not enabled"));
+        };
+
+        let user_id = match token.map(|t| self.user_manager.verify_token(&t)) {
+            Some(Ok(user_id)) => user_id,
+            Some(Err(e)) => return Ok(HttpResponse::Unauthorized().body(e.to_string())),
+            None => return Ok(HttpResponse::Unauthorized().body("Missing token")),
+        };
+
+        if !self
+            .user_manager
+            .user_has_permission(&user_id, Permission::ExportData)
+            .unwrap_or(false)
+        {
+            return Ok(HttpResponse::Forbidden().body("ExportData permission required"));
+        }
+
+        let session = ExportWsSession::new(Uuid::new_v4().to_string(), user_id, export_log);
+        ws::start(session, &req, stream)
     }
 }
 
+/// 在握手响应中确认已启用压缩
+fn confirm_compression(mut resp: HttpResponse, compression: bool) -> HttpResponse {
+    if compression {
+        resp.headers_mut().insert(
+            HeaderName::from_static(COMPRESSION_HEADER),
+            HeaderValue::from_static(COMPRESSION_ZSTD),
+        );
+    }
+    resp
+}
+
 /// WebSocket 路由处理函数
 pub async fn ws_route(
     req: HttpRequest,
@@ -213,6 +305,24 @@ pub async fn ws_diff_route(
     server.handle_diff_connection(req, stream, user_id).await
 }
 
+/// 数据导出 WebSocket 路由处理函数
+///
+/// 路由: `/ws/export?token=<JWT>`
+pub async fn ws_export_route(
+    req: HttpRequest,
+    stream: web::Payload,
+    server: web::Data<Arc<WebSocketServer>>,
+) -> Result<HttpResponse, Error> {
+    let token = req.uri().query().and_then(|q| {
+        q.split('&')
+            .find(|s| s.starts_with("token="))
+            .and_then(|s| s.strip_prefix("token="))
+            .map(|s| s.to_string())
+    });
+
+    server.handle_export_connection(req, stream, token).await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,19 +1,32 @@
 //! WebSocket 会话管理
 
+use super::compression;
 use super::messages::{ClientMessage, ServerMessage};
+use super::session_registry::{ClientInfo, KickSession, WS_SESSION_REGISTRY};
+use super::subscription_quota::{PushVerdict, WS_SUBSCRIPTION_QUOTA};
+use super::system_events::{subscribe_system_events, system_event_message};
 use crate::exchange::TradeGateway;
+use crate::market::subscription::SubscriptionChange;
 use crate::market::{MarketDataBroadcaster, MarketDataEvent};
+use crate::notification::system_event::{is_system_event_channel, parse_system_event_channels};
+use crate::notification::{NotificationBroker, SystemEventCategory};
+use crate::observability::push_latency::{PushSample, PUSH_LATENCY};
 use crate::user::UserManager;
-use actix::{Actor, ActorContext, Addr, AsyncContext, Handler, Message, StreamHandler};
+use crate::ExchangeError;
+use actix::{
+    Actor, ActorContext, Addr, AsyncContext, Handler, Message, SpawnHandle, StreamHandler,
+};
 use actix_web_actors::ws;
 use crossbeam::channel::{Receiver, Sender};
 use log;
-use std::collections::HashMap;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
 
 /// 获取心跳间隔（可通过环境变量配置）
-fn get_heartbeat_interval() -> Duration {
+pub(super) fn get_heartbeat_interval() -> Duration {
     let secs = std::env::var("QAEXCHANGE_WS_HEARTBEAT_SECS")
         .ok()
         .and_then(|s| s.parse().ok())
@@ -22,7 +35,7 @@ fn get_heartbeat_interval() -> Duration {
 }
 
 /// 获取客户端超时时间（可通过环境变量配置）
-fn get_client_timeout() -> Duration {
+pub(super) fn get_client_timeout() -> Duration {
     let secs = std::env::var("QAEXCHANGE_WS_TIMEOUT_SECS")
         .ok()
         .and_then(|s| s.parse().ok())
@@ -59,6 +72,12 @@ pub struct WsSession {
     /// 成交通知接收器（来自 TradeGateway）
     pub notification_receiver: Option<Receiver<crate::exchange::Notification>>,
 
+    /// 成交通知订阅的用户（断开时取消订阅并注销会话登记）
+    pub notify_user_id: Option<String>,
+
+    /// 成交回报网关
+    pub trade_gateway: Option<Arc<TradeGateway>>,
+
     /// 消息发送器（发送到业务逻辑处理器）
     pub message_sender: Sender<WsSessionMessage>,
 
@@ -73,6 +92,30 @@ pub struct WsSession {
 
     /// 市场数据接收器
     pub market_data_receiver: Option<Receiver<MarketDataEvent>>,
+
+    /// 握手时协商了 zstd 压缩
+    pub compression: bool,
+
+    /// 客户端终端信息（终端、IP）
+    pub client: ClientInfo,
+
+    /// 通知路由中心（system_events 运维频道）
+    pub notification_broker: Option<Arc<NotificationBroker>>,
+
+    /// 系统事件接收器（订阅 system_events 后设置）
+    pub system_event_receiver: Option<mpsc::UnboundedReceiver<crate::notification::Notification>>,
+
+    /// 系统事件轮询任务
+    system_event_poller: Option<SpawnHandle>,
+}
+
+/// 行情推送项（采样命中的 tick/K线 附带 msg_id，供客户端回发 latency_report）
+#[derive(Serialize)]
+struct MarketPush<'a> {
+    #[serde(flatten)]
+    event: &'a MarketDataEvent,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    msg_id: Option<u64>,
 }
 
 /// 会话消息（发送给业务逻辑处理器）
@@ -92,11 +135,18 @@ impl WsSession {
             subscribed_channels: Vec::new(),
             subscribed_instruments: Vec::new(),
             notification_receiver: None,
+            notify_user_id: None,
+            trade_gateway: None,
             message_sender,
             sessions: None,
             user_manager: None,
             market_broadcaster: None,
             market_data_receiver: None,
+            compression: false,
+            client: ClientInfo::default(),
+            notification_broker: None,
+            system_event_receiver: None,
+            system_event_poller: None,
         }
     }
 
@@ -121,6 +171,36 @@ impl WsSession {
         self
     }
 
+    /// 设置通知路由中心（提供 system_events 运维频道）
+    pub fn with_notification_broker(mut self, broker: Arc<NotificationBroker>) -> Self {
+        self.notification_broker = Some(broker);
+        self
+    }
+
+    /// 设置压缩协商结果
+    pub fn with_compression(mut self, compression: bool) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// 设置客户端终端信息
+    pub fn with_client(mut self, client: ClientInfo) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// 订阅用户成交通知（每个会话独立队列，多终端同时在线都能收到回报）
+    pub fn with_user_notifications(
+        mut self,
+        trade_gateway: Arc<TradeGateway>,
+        user_id: String,
+    ) -> Self {
+        self.notification_receiver = Some(trade_gateway.subscribe_session(&user_id, &self.id));
+        self.notify_user_id = Some(user_id);
+        self.trade_gateway = Some(trade_gateway);
+        self
+    }
+
     /// 启动心跳检查
     fn start_heartbeat(&self, ctx: &mut ws::WebsocketContext<Self>) {
         ctx.run_interval(get_heartbeat_interval(), |act, ctx| {
@@ -143,7 +223,7 @@ impl WsSession {
             let mut dropped_count = 0u64;
             let mut last_warn_time = std::time::Instant::now();
 
-            ctx.run_interval(Duration::from_millis(10), move |_act, ctx| {
+            ctx.run_interval(Duration::from_millis(10), move |act, ctx| {
                 // 批量接收市场数据事件
                 let mut events = Vec::new();
                 let max_batch_size = 100;
@@ -179,11 +259,50 @@ impl WsSession {
                     }
                 }
 
+                // 推送速率配额：持续超速时断开连接
+                if !events.is_empty()
+                    && WS_SUBSCRIPTION_QUOTA.record_push(&act.id, events.len() as u64)
+                        == PushVerdict::Disconnect
+                {
+                    let error = ServerMessage::Error {
+                        code: 429,
+                        message: "Push rate limit exceeded, disconnecting".to_string(),
+                    };
+                    if let Ok(json) = serde_json::to_string(&error) {
+                        act.send_json(json, ctx);
+                    }
+                    ctx.close(Some(ws::CloseReason {
+                        code: ws::CloseCode::Policy,
+                        description: Some("Push rate limit exceeded".to_string()),
+                    }));
+                    ctx.stop();
+                    return;
+                }
+
                 // 批量发送：合并为JSON数组，一次性发送
                 if !events.is_empty() {
-                    match serde_json::to_string(&events) {
+                    let samples: Vec<Option<PushSample>> = events
+                        .iter()
+                        .map(|event| {
+                            event
+                                .push_origin()
+                                .and_then(|(kind, origin_ts)| PUSH_LATENCY.sample(kind, origin_ts))
+                        })
+                        .collect();
+                    let batch: Vec<MarketPush> = events
+                        .iter()
+                        .zip(&samples)
+                        .map(|(event, sample)| MarketPush {
+                            event,
+                            msg_id: sample.map(|s| s.msg_id),
+                        })
+                        .collect();
+                    match serde_json::to_string(&batch) {
                         Ok(batch_json) => {
-                            ctx.text(batch_json);
+                            act.send_json(batch_json, ctx);
+                            for sample in samples.into_iter().flatten() {
+                                PUSH_LATENCY.written(&act.id, sample);
+                            }
                         }
                         Err(e) => {
                             log::error!("Failed to serialize market data batch: {}", e);
@@ -194,6 +313,67 @@ impl WsSession {
         }
     }
 
+    /// 应用订阅变更：首次订阅时保存接收端并启动监听
+    fn apply_subscription_change(
+        &mut self,
+        change: SubscriptionChange,
+        ctx: &mut ws::WebsocketContext<Self>,
+    ) {
+        if let Some(receiver) = change.receiver {
+            self.market_data_receiver = Some(receiver);
+            self.start_market_data_listener(ctx);
+        }
+    }
+
+    /// 回复订阅组操作结果
+    fn reply_group_change(
+        &mut self,
+        group: &str,
+        result: Option<Result<SubscriptionChange, ExchangeError>>,
+        ctx: &mut ws::WebsocketContext<Self>,
+    ) {
+        let response = match result {
+            Some(Ok(change)) => {
+                log::info!(
+                    "Session {} subscription group {} applied at seq {}: +{:?} -{:?}",
+                    self.id,
+                    group,
+                    change.switch_seq,
+                    change.added,
+                    change.removed
+                );
+                let instruments = change.instruments.clone();
+                let switch_seq = change.switch_seq;
+                self.apply_subscription_change(change, ctx);
+                ServerMessage::SubscriptionGroupResponse {
+                    success: true,
+                    group: group.to_string(),
+                    instruments,
+                    switch_seq,
+                    message: "Subscription group updated".to_string(),
+                }
+            }
+            Some(Err(e)) => ServerMessage::SubscriptionGroupResponse {
+                success: false,
+                group: group.to_string(),
+                instruments: Vec::new(),
+                switch_seq: 0,
+                message: e.to_string(),
+            },
+            None => ServerMessage::SubscriptionGroupResponse {
+                success: false,
+                group: group.to_string(),
+                instruments: Vec::new(),
+                switch_seq: 0,
+                message: "Market data not available".to_string(),
+            },
+        };
+
+        if let Ok(json) = serde_json::to_string(&response) {
+            self.send_json(json, ctx);
+        }
+    }
+
     /// 处理客户端消息
     fn handle_client_message(&mut self, msg: ClientMessage, ctx: &mut ws::WebsocketContext<Self>) {
         match &msg {
@@ -206,6 +386,12 @@ impl WsSession {
                             self.state = SessionState::Authenticated {
                                 user_id: verified_user_id.clone(),
                             };
+                            WS_SUBSCRIPTION_QUOTA.attach_user(
+                                &self.id,
+                                "ws",
+                                Some(&verified_user_id),
+                                Some(user_mgr.as_ref()),
+                            );
 
                             let response = ServerMessage::AuthResponse {
                                 success: true,
@@ -214,7 +400,7 @@ impl WsSession {
                             };
 
                             if let Ok(json) = serde_json::to_string(&response) {
-                                ctx.text(json);
+                                self.send_json(json, ctx);
                             }
 
                             log::info!(
@@ -232,7 +418,7 @@ impl WsSession {
                             };
 
                             if let Ok(json) = serde_json::to_string(&response) {
-                                ctx.text(json);
+                                self.send_json(json, ctx);
                             }
 
                             log::warn!("Session {} authentication failed: {}", self.id, e);
@@ -244,6 +430,12 @@ impl WsSession {
                         self.state = SessionState::Authenticated {
                             user_id: user_id.clone(),
                         };
+                        WS_SUBSCRIPTION_QUOTA.attach_user(
+                            &self.id,
+                            "ws",
+                            Some(user_id.as_str()),
+                            None,
+                        );
 
                         let response = ServerMessage::AuthResponse {
                             success: true,
@@ -252,7 +444,7 @@ impl WsSession {
                         };
 
                         if let Ok(json) = serde_json::to_string(&response) {
-                            ctx.text(json);
+                            self.send_json(json, ctx);
                         }
 
                         log::warn!(
@@ -267,7 +459,7 @@ impl WsSession {
                         };
 
                         if let Ok(json) = serde_json::to_string(&response) {
-                            ctx.text(json);
+                            self.send_json(json, ctx);
                         }
                     }
                 }
@@ -277,41 +469,77 @@ impl WsSession {
                 channels,
                 instruments,
             } => {
-                // 更新订阅列表
-                for channel in channels {
-                    if !self.subscribed_channels.contains(channel) {
-                        self.subscribed_channels.push(channel.clone());
-                    }
+                // 系统事件频道需要 ViewMonitoring 权限，校验失败时整次拒绝
+                let system_result = parse_system_event_channels(channels)
+                    .map_err(ExchangeError::InvalidParameter)
+                    .and_then(|categories| match categories {
+                        Some(categories) => self.subscribe_system_events(categories, ctx),
+                        None => Ok(()),
+                    });
+                if let Err(e) = system_result {
+                    log::warn!(
+                        "Session {} system_events subscription rejected: {}",
+                        self.id,
+                        e
+                    );
+                    let response = ServerMessage::SubscribeResponse {
+                        success: false,
+                        channels: channels.clone(),
+                        instruments: instruments.clone(),
+                        message: e.to_string(),
+                    };
+                    self.send_message(response, ctx);
+                    return;
                 }
+                let market_channels: Vec<String> = channels
+                    .iter()
+                    .filter(|channel| !is_system_event_channel(channel))
+                    .cloned()
+                    .collect();
+
+                // 增量加入默认订阅组，原地替换订阅（不重建通道）；超出订阅配额时整次拒绝
+                let result = self.market_broadcaster.clone().map(|broadcaster| {
+                    broadcaster.update_default_group(
+                        &self.id,
+                        instruments,
+                        &[],
+                        &market_channels,
+                        &[],
+                    )
+                });
 
-                for instrument in instruments {
-                    if !self.subscribed_instruments.contains(instrument) {
-                        self.subscribed_instruments.push(instrument.clone());
+                // 更新订阅列表
+                if !matches!(result, Some(Err(_))) {
+                    for channel in channels {
+                        if !self.subscribed_channels.contains(channel) {
+                            self.subscribed_channels.push(channel.clone());
+                        }
                     }
-                }
-
-                // 订阅市场数据
-                if let Some(ref broadcaster) = self.market_broadcaster {
-                    let receiver = broadcaster.subscribe(
-                        self.id.clone(),
-                        instruments.clone(),
-                        channels.clone(),
-                    );
-                    self.market_data_receiver = Some(receiver);
 
-                    // 启动市场数据监听
-                    self.start_market_data_listener(ctx);
+                    for instrument in instruments {
+                        if !self.subscribed_instruments.contains(instrument) {
+                            self.subscribed_instruments.push(instrument.clone());
+                        }
+                    }
                 }
+                let (success, message) = match result {
+                    Some(Ok(change)) => {
+                        self.apply_subscription_change(change, ctx);
+                        (true, "Subscribed successfully".to_string())
+                    }
+                    Some(Err(e)) => (false, e.to_string()),
+                    None => (true, "Subscribed successfully".to_string()),
+                };
 
                 let response = ServerMessage::SubscribeResponse {
-                    success: true,
+                    success,
                     channels: channels.clone(),
                     instruments: instruments.clone(),
-                    message: "Subscribed successfully".to_string(),
+                    message,
                 };
 
                 if let Ok(json) = serde_json::to_string(&response) {
-                    ctx.text(json);
+                    self.send_json(json, ctx);
                 }
 
                 log::info!(
@@ -330,24 +558,28 @@ impl WsSession {
                 self.subscribed_channels.retain(|ch| !channels.contains(ch));
                 self.subscribed_instruments
                     .retain(|inst| !instruments.contains(inst));
-
-                // 如果所有订阅都取消了，注销订阅
-                if self.subscribed_channels.is_empty() && self.subscribed_instruments.is_empty() {
-                    if let Some(ref broadcaster) = self.market_broadcaster {
-                        broadcaster.unsubscribe(&self.id);
-                    }
-                    self.market_data_receiver = None;
+                if channels.iter().any(|ch| is_system_event_channel(ch)) {
+                    self.unsubscribe_system_events(ctx);
                 }
 
+                // 从默认订阅组移除；所有组为空时暂停推送（保留通道与序号）
+                let result = self.market_broadcaster.clone().map(|broadcaster| {
+                    broadcaster.update_default_group(&self.id, &[], instruments, &[], channels)
+                });
+                let (success, message) = match result {
+                    Some(Err(e)) => (false, e.to_string()),
+                    _ => (true, "Unsubscribed successfully".to_string()),
+                };
+
                 let response = ServerMessage::SubscribeResponse {
-                    success: true,
+                    success,
                     channels: channels.clone(),
                     instruments: instruments.clone(),
-                    message: "Unsubscribed successfully".to_string(),
+                    message,
                 };
 
                 if let Ok(json) = serde_json::to_string(&response) {
-                    ctx.text(json);
+                    self.send_json(json, ctx);
                 }
 
                 log::info!(
@@ -358,13 +590,47 @@ impl WsSession {
                 );
             }
 
+            ClientMessage::SubscribeGroup {
+                group,
+                instruments,
+                channels,
+            } => {
+                let result = self.market_broadcaster.clone().map(|broadcaster| {
+                    broadcaster.create_group(&self.id, group, instruments.clone(), channels.clone())
+                });
+                self.reply_group_change(group, result, ctx);
+            }
+
+            ClientMessage::ReplaceGroup { group, instruments } => {
+                let result = self.market_broadcaster.clone().map(|broadcaster| {
+                    broadcaster.replace_group(&self.id, group, instruments.clone())
+                });
+                self.reply_group_change(group, result, ctx);
+            }
+
+            ClientMessage::DeleteGroup { group } => {
+                let result = self
+                    .market_broadcaster
+                    .clone()
+                    .map(|broadcaster| broadcaster.delete_group(&self.id, group));
+                self.reply_group_change(group, result, ctx);
+            }
+
             ClientMessage::Ping => {
                 let response = ServerMessage::Pong;
                 if let Ok(json) = serde_json::to_string(&response) {
-                    ctx.text(json);
+                    self.send_json(json, ctx);
                 }
             }
 
+            // 延迟回执不回复，避免放大流量
+            ClientMessage::LatencyReport {
+                msg_id,
+                client_recv_ts,
+            } => {
+                PUSH_LATENCY.report(&self.id, *msg_id, *client_recv_ts);
+            }
+
             _ => {
                 // 其他消息需要认证
                 if let SessionState::Authenticated { user_id } = &self.state {
@@ -385,19 +651,75 @@ impl WsSession {
                     };
 
                     if let Ok(json) = serde_json::to_string(&error) {
-                        ctx.text(json);
+                        self.send_json(json, ctx);
                     }
                 }
             }
         }
     }
 
+    /// 订阅系统事件频道（需要已认证且具备 ViewMonitoring 权限）
+    fn subscribe_system_events(
+        &mut self,
+        categories: HashSet<SystemEventCategory>,
+        ctx: &mut ws::WebsocketContext<Self>,
+    ) -> Result<(), ExchangeError> {
+        let SessionState::Authenticated { ref user_id } = self.state else {
+            return Err(ExchangeError::PermissionDenied(
+                "Authentication required for system_events".to_string(),
+            ));
+        };
+        let (Some(broker), Some(user_manager)) = (&self.notification_broker, &self.user_manager)
+        else {
+            return Err(ExchangeError::ServiceError(
+                "System events not available".to_string(),
+            ));
+        };
+
+        let receiver =
+            subscribe_system_events(broker, user_manager, user_id, &self.id, categories)?;
+        self.system_event_receiver = Some(receiver);
+
+        if self.system_event_poller.is_none() {
+            let handle = ctx.run_interval(Duration::from_millis(10), |act, ctx| {
+                let Some(receiver) = act.system_event_receiver.as_mut() else {
+                    return;
+                };
+                let mut messages = Vec::new();
+                while let Ok(notification) = receiver.try_recv() {
+                    messages.extend(system_event_message(&notification));
+                }
+                for msg in messages {
+                    act.send_message(msg, ctx);
+                }
+            });
+            self.system_event_poller = Some(handle);
+        }
+        Ok(())
+    }
+
+    /// 取消系统事件订阅
+    fn unsubscribe_system_events(&mut self, ctx: &mut ws::WebsocketContext<Self>) {
+        if let Some(ref broker) = self.notification_broker {
+            broker.unsubscribe_system_events(&self.id);
+        }
+        self.system_event_receiver = None;
+        if let Some(handle) = self.system_event_poller.take() {
+            ctx.cancel_future(handle);
+        }
+    }
+
     /// 发送服务端消息
     pub fn send_message(&self, msg: ServerMessage, ctx: &mut ws::WebsocketContext<Self>) {
         if let Ok(json) = serde_json::to_string(&msg) {
-            ctx.text(json);
+            self.send_json(json, ctx);
         }
     }
+
+    /// 发送 JSON（协商了压缩时大消息以 zstd 压缩的 Binary 帧发送）
+    fn send_json(&self, json: String, ctx: &mut ws::WebsocketContext<Self>) {
+        compression::send_json(ctx, "ws", json, self.compression);
+    }
 }
 
 impl Actor for WsSession {
@@ -413,11 +735,34 @@ impl Actor for WsSession {
 
         self.start_heartbeat(ctx);
 
+        // 订阅配额按连接用户的等级（未带用户的连接按访客）
+        WS_SUBSCRIPTION_QUOTA.attach_user(
+            &self.id,
+            "ws",
+            self.notify_user_id.as_deref(),
+            self.user_manager.as_deref(),
+        );
+
+        // 登记会话（kick_previous 策略下会踢掉该用户的旧会话）
+        if let Some(ref user_id) = self.notify_user_id {
+            WS_SESSION_REGISTRY.register(
+                user_id,
+                &self.id,
+                "ws",
+                self.client.clone(),
+                Some(ctx.address().recipient()),
+            );
+        }
+
         // 启动通知监听器（如果有的话）
         if let Some(receiver) = &self.notification_receiver {
             let receiver = receiver.clone();
             ctx.run_interval(Duration::from_millis(10), move |act, ctx| {
                 while let Ok(notification) = receiver.try_recv() {
+                    let (kind, origin_ts) = notification.push_origin();
+                    let sample = PUSH_LATENCY.sample(kind, origin_ts);
+                    let msg_id = sample.map(|s| s.msg_id);
+
                     // 转换通知为服务端消息
                     match notification {
                         crate::exchange::Notification::Trade(trade) => {
@@ -430,6 +775,8 @@ impl Actor for WsSession {
                                 price: trade.price,
                                 volume: trade.volume,
                                 timestamp: trade.timestamp,
+                                origin_ts,
+                                msg_id,
                             };
                             act.send_message(msg, ctx);
                         }
@@ -446,6 +793,8 @@ impl Actor for WsSession {
                                 price: status.price,
                                 status: status.status,
                                 timestamp: status.timestamp,
+                                origin_ts,
+                                msg_id,
                             };
                             act.send_message(msg, ctx);
                         }
@@ -459,10 +808,16 @@ impl Actor for WsSession {
                                 profit: account.position_profit,
                                 risk_ratio: account.risk_ratio,
                                 timestamp: account.timestamp,
+                                origin_ts,
+                                msg_id,
                             };
                             act.send_message(msg, ctx);
                         }
                     }
+
+                    if let Some(sample) = sample {
+                        PUSH_LATENCY.written(&act.id, sample);
+                    }
                 }
             });
         }
@@ -475,6 +830,27 @@ impl Actor for WsSession {
         if let Some(ref sessions) = self.sessions {
             sessions.write().remove(&self.id);
         }
+
+        // 注销行情订阅及订阅组
+        if let Some(ref broadcaster) = self.market_broadcaster {
+            broadcaster.unsubscribe(&self.id);
+        }
+
+        PUSH_LATENCY.remove_session(&self.id);
+        WS_SUBSCRIPTION_QUOTA.detach(&self.id);
+
+        // 注销系统事件订阅
+        if let Some(ref broker) = self.notification_broker {
+            broker.unsubscribe_system_events(&self.id);
+        }
+
+        // 取消成交通知订阅并注销会话登记
+        if let Some(ref user_id) = self.notify_user_id {
+            if let Some(ref trade_gateway) = self.trade_gateway {
+                trade_gateway.unsubscribe_session(user_id, &self.id);
+            }
+            WS_SESSION_REGISTRY.unregister(user_id, &self.id);
+        }
     }
 }
 
@@ -504,7 +880,7 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WsSession {
                             message: format!("Invalid message format: {}", e),
                         };
                         if let Ok(json) = serde_json::to_string(&error) {
-                            ctx.text(json);
+                            self.send_json(json, ctx);
                         }
                     }
                 }
@@ -533,3 +909,27 @@ impl Handler<SendMessage> for WsSession {
         self.send_message(msg.0, ctx);
     }
 }
+
+/// 处理踢下线：通知客户端后断开
+impl Handler<KickSession> for WsSession {
+    type Result = ();
+
+    fn handle(&mut self, msg: KickSession, ctx: &mut Self::Context) {
+        log::warn!(
+            "WebSocket session {} of user {} kicked",
+            self.id,
+            msg.user_id
+        );
+        self.send_message(
+            ServerMessage::SessionKicked {
+                message: msg.reason.clone(),
+            },
+            ctx,
+        );
+        ctx.close(Some(ws::CloseReason {
+            code: ws::CloseCode::Policy,
+            description: Some(msg.reason),
+        }));
+        ctx.stop();
+    }
+}
@@ -0,0 +1,542 @@
+//! WebSocket 多终端会话登记与登录策略
+//!
+//! @yutiansut @quantaxis
+//!
+//! 同一用户可在 PC、手机等多个终端同时登录：
+//! - `allow_multiple`（默认）：各会话拥有独立推送队列，成交/订单回报扇出到每个会话
+//! - `kick_previous`：新会话登录时踢掉该用户已有会话，旧会话收到被踢通知后断开
+//!
+//! 全局策略之外可按用户单独配置（如高权限账户强制单点登录），管理端可查询会话
+//! （终端、IP、登录时间）并主动踢出指定会话
+
+use actix::{Message, Recipient};
+use actix_web::HttpRequest;
+use dashmap::DashMap;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+lazy_static::lazy_static! {
+    /// 全局会话登记表（WebSocket 服务与管理端共享）
+    pub static ref WS_SESSION_REGISTRY: Arc<SessionRegistry> = Arc::new(SessionRegistry::default());
+}
+
+/// 多终端登录策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoginPolicy {
+    /// 允许多端同时在线，回报推送到所有会话
+    #[default]
+    AllowMultiple,
+    /// 新登录踢掉旧会话
+    KickPrevious,
+}
+
+impl LoginPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LoginPolicy::AllowMultiple => "allow_multiple",
+            LoginPolicy::KickPrevious => "kick_previous",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "allow_multiple" => Some(LoginPolicy::AllowMultiple),
+            "kick_previous" => Some(LoginPolicy::KickPrevious),
+            _ => None,
+        }
+    }
+}
+
+/// 踢下线消息（发送给被踢会话的 Actor）
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "()")]
+pub struct KickSession {
+    pub user_id: String,
+    pub reason: String,
+}
+
+/// 客户端终端信息（握手时采集）
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClientInfo {
+    /// 终端标识（User-Agent）
+    pub device: Option<String>,
+    /// 客户端 IP（优先取反向代理转发的真实 IP）
+    pub ip: Option<String>,
+}
+
+impl ClientInfo {
+    pub fn from_request(req: &HttpRequest) -> Self {
+        let device = req
+            .headers()
+            .get(actix_web::http::header::USER_AGENT)
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.to_string());
+        let ip = req
+            .connection_info()
+            .realip_remote_addr()
+            .map(|s| s.to_string());
+        Self { device, ip }
+    }
+
+    /// 用于通知文案的简短描述
+    fn describe(&self) -> String {
+        match (&self.device, &self.ip) {
+            (Some(device), Some(ip)) => format!("{} @ {}", device, ip),
+            (Some(device), None) => device.clone(),
+            (None, Some(ip)) => ip.clone(),
+            (None, None) => "未知终端".to_string(),
+        }
+    }
+}
+
+/// 会话信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionInfo {
+    pub session_id: String,
+    /// 协议类型（ws / diff）
+    pub protocol: String,
+    /// 连接时间（毫秒）
+    pub connected_at: i64,
+    #[serde(flatten)]
+    pub client: ClientInfo,
+}
+
+struct SessionEntry {
+    info: SessionInfo,
+    kicker: Option<Recipient<KickSession>>,
+}
+
+/// 用户会话汇总
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserSessionSummary {
+    pub user_id: String,
+    /// 该用户生效的登录策略
+    pub policy: LoginPolicy,
+    pub session_count: usize,
+    pub sessions: Vec<SessionInfo>,
+}
+
+/// 会话登记统计
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionRegistryStats {
+    pub policy: LoginPolicy,
+    /// 在线用户数
+    pub online_users: usize,
+    /// 会话总数
+    pub total_sessions: usize,
+    /// 多终端在线的用户数
+    pub multi_session_users: usize,
+    /// 按用户单独配置的登录策略
+    pub user_policies: Vec<(String, LoginPolicy)>,
+    /// 累计踢下线次数
+    pub kicked_total: u64,
+    pub users: Vec<UserSessionSummary>,
+}
+
+/// 会话登记表
+pub struct SessionRegistry {
+    policy: RwLock<LoginPolicy>,
+    /// 按用户覆盖的登录策略
+    user_policies: DashMap<String, LoginPolicy>,
+    /// user_id -> 活跃会话
+    sessions: DashMap<String, Vec<SessionEntry>>,
+    kicked_total: AtomicU64,
+}
+
+impl Default for SessionRegistry {
+    fn default() -> Self {
+        Self::new(LoginPolicy::default())
+    }
+}
+
+impl SessionRegistry {
+    pub fn new(policy: LoginPolicy) -> Self {
+        Self {
+            policy: RwLock::new(policy),
+            user_policies: DashMap::new(),
+            sessions: DashMap::new(),
+            kicked_total: AtomicU64::new(0),
+        }
+    }
+
+    /// 当前登录策略
+    pub fn policy(&self) -> LoginPolicy {
+        *self.policy.read()
+    }
+
+    /// 设置登录策略（仅影响之后的登录）
+    pub fn set_policy(&self, policy: LoginPolicy) {
+        log::info!("[SessionRegistry] Login policy set to {}", policy.as_str());
+        *self.policy.write() = policy;
+    }
+
+    /// 按用户设置登录策略，`None` 表示恢复使用全局策略
+    pub fn set_user_policy(&self, user_id: &str, policy: Option<LoginPolicy>) {
+        match policy {
+            Some(policy) => {
+                log::info!(
+                    "[SessionRegistry] Login policy of user {} set to {}",
+                    user_id,
+                    policy.as_str()
+                );
+                self.user_policies.insert(user_id.to_string(), policy);
+            }
+            None => {
+                log::info!(
+                    "[SessionRegistry] Login policy of user {} reset to global",
+                    user_id
+                );
+                self.user_policies.remove(user_id);
+            }
+        }
+    }
+
+    /// 用户生效的登录策略（用户配置优先于全局策略）
+    pub fn effective_policy(&self, user_id: &str) -> LoginPolicy {
+        self.user_policies
+            .get(user_id)
+            .map(|p| *p)
+            .unwrap_or_else(|| self.policy())
+    }
+
+    /// 登记会话，返回被踢下线的会话ID
+    ///
+    /// `kick_previous` 策略下，该用户已有的会话会收到 [`KickSession`] 并从登记表移除
+    pub fn register(
+        &self,
+        user_id: &str,
+        session_id: &str,
+        protocol: &str,
+        client: ClientInfo,
+        kicker: Option<Recipient<KickSession>>,
+    ) -> Vec<String> {
+        let policy = self.effective_policy(user_id);
+        let mut entries = self.sessions.entry(user_id.to_string()).or_default();
+
+        // 同一会话重复认证只更新登记
+        entries.retain(|e| e.info.session_id != session_id);
+
+        let mut kicked = Vec::new();
+        if policy == LoginPolicy::KickPrevious {
+            let reason = format!("账户已在其他终端登录 ({})", client.describe());
+            for entry in entries.drain(..) {
+                if let Some(ref kicker) = entry.kicker {
+                    kicker.do_send(KickSession {
+                        user_id: user_id.to_string(),
+                        reason: reason.clone(),
+                    });
+                }
+                kicked.push(entry.info.session_id);
+            }
+            self.kicked_total
+                .fetch_add(kicked.len() as u64, Ordering::Relaxed);
+        }
+
+        entries.push(SessionEntry {
+            info: SessionInfo {
+                session_id: session_id.to_string(),
+                protocol: protocol.to_string(),
+                connected_at: chrono::Utc::now().timestamp_millis(),
+                client,
+            },
+            kicker,
+        });
+
+        if !kicked.is_empty() {
+            log::warn!(
+                "[SessionRegistry] User {} logged in on session {}, kicked {:?}",
+                user_id,
+                session_id,
+                kicked
+            );
+        }
+        kicked
+    }
+
+    /// 注销会话
+    pub fn unregister(&self, user_id: &str, session_id: &str) -> bool {
+        let removed = match self.sessions.get_mut(user_id) {
+            Some(mut entries) => {
+                let before = entries.len();
+                entries.retain(|e| e.info.session_id != session_id);
+                before != entries.len()
+            }
+            None => false,
+        };
+        self.sessions
+            .remove_if(user_id, |_, entries| entries.is_empty());
+        removed
+    }
+
+    /// 主动踢出指定会话，返回会话所属用户（会话不存在时返回 None）
+    pub fn kick_session(&self, session_id: &str, reason: &str) -> Option<String> {
+        let mut kicked_user = None;
+        for mut entries in self.sessions.iter_mut() {
+            let Some(pos) = entries.iter().position(|e| e.info.session_id == session_id) else {
+                continue;
+            };
+            let entry = entries.remove(pos);
+            if let Some(ref kicker) = entry.kicker {
+                kicker.do_send(KickSession {
+                    user_id: entries.key().clone(),
+                    reason: reason.to_string(),
+                });
+            }
+            kicked_user = Some(entries.key().clone());
+            break;
+        }
+
+        let user_id = kicked_user?;
+        self.sessions
+            .remove_if(&user_id, |_, entries| entries.is_empty());
+        self.kicked_total.fetch_add(1, Ordering::Relaxed);
+        log::warn!(
+            "[SessionRegistry] Session {} of user {} kicked: {}",
+            session_id,
+            user_id,
+            reason
+        );
+        Some(user_id)
+    }
+
+    /// 用户活跃会话数
+    pub fn session_count(&self, user_id: &str) -> usize {
+        self.sessions.get(user_id).map_or(0, |e| e.len())
+    }
+
+    /// 用户活跃会话
+    pub fn user_sessions(&self, user_id: &str) -> Vec<SessionInfo> {
+        self.sessions
+            .get(user_id)
+            .map(|entries| entries.iter().map(|e| e.info.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    /// 统计（用户按 user_id 排序）
+    pub fn stats(&self) -> SessionRegistryStats {
+        let mut users: Vec<UserSessionSummary> = self
+            .sessions
+            .iter()
+            .map(|entry| UserSessionSummary {
+                user_id: entry.key().clone(),
+                policy: self.effective_policy(entry.key()),
+                session_count: entry.value().len(),
+                sessions: entry.value().iter().map(|e| e.info.clone()).collect(),
+            })
+            .collect();
+        users.sort_by(|a, b| a.user_id.cmp(&b.user_id));
+        let mut user_policies: Vec<(String, LoginPolicy)> = self
+            .user_policies
+            .iter()
+            .map(|entry| (entry.key().clone(), *entry.value()))
+            .collect();
+        user_policies.sort_by(|a, b| a.0.cmp(&b.0));
+
+        SessionRegistryStats {
+            policy: self.policy(),
+            online_users: users.len(),
+            total_sessions: users.iter().map(|u| u.session_count).sum(),
+            multi_session_users: users.iter().filter(|u| u.session_count > 1).count(),
+            user_policies,
+            kicked_total: self.kicked_total.load(Ordering::Relaxed),
+            users,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix::{Actor, ActorContext, Context, Handler};
+    use std::time::Duration;
+
+    /// 模拟会话：收到踢下线消息后记录并停止
+    struct MockSession {
+        kicked: Arc<RwLock<Vec<String>>>,
+    }
+
+    impl Actor for MockSession {
+        type Context = Context<Self>;
+    }
+
+    impl Handler<KickSession> for MockSession {
+        type Result = ();
+
+        fn handle(&mut self, msg: KickSession, ctx: &mut Self::Context) {
+            self.kicked.write().push(msg.reason);
+            ctx.stop();
+        }
+    }
+
+    #[actix::test]
+    async fn test_allow_multiple_keeps_all_sessions() {
+        let registry = SessionRegistry::default();
+        let kicked = Arc::new(RwLock::new(Vec::new()));
+        let pc = MockSession {
+            kicked: kicked.clone(),
+        }
+        .start();
+
+        assert!(registry
+            .register(
+                "user1",
+                "pc",
+                "diff",
+                ClientInfo::default(),
+                Some(pc.clone().recipient())
+            )
+            .is_empty());
+        assert!(registry
+            .register("user1", "mobile", "ws", ClientInfo::default(), None)
+            .is_empty());
+
+        assert_eq!(registry.session_count("user1"), 2);
+        let stats = registry.stats();
+        assert_eq!(stats.policy, LoginPolicy::AllowMultiple);
+        assert_eq!(stats.total_sessions, 2);
+        assert_eq!(stats.multi_session_users, 1);
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(kicked.read().is_empty());
+        assert!(pc.connected());
+
+        assert!(registry.unregister("user1", "pc"));
+        assert!(registry.unregister("user1", "mobile"));
+        assert_eq!(registry.stats().online_users, 0);
+    }
+
+    #[actix::test]
+    async fn test_kick_previous_notifies_and_stops_old_session() {
+        let registry = SessionRegistry::new(LoginPolicy::KickPrevious);
+        let kicked = Arc::new(RwLock::new(Vec::new()));
+        let pc = MockSession {
+            kicked: kicked.clone(),
+        }
+        .start();
+
+        registry.register(
+            "user1",
+            "pc",
+            "diff",
+            ClientInfo::default(),
+            Some(pc.clone().recipient()),
+        );
+        let result = registry.register("user1", "mobile", "diff", ClientInfo::default(), None);
+        assert_eq!(result, vec!["pc".to_string()]);
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(kicked.read().len(), 1);
+        assert!(!pc.connected());
+
+        assert_eq!(registry.user_sessions("user1")[0].session_id, "mobile");
+        assert_eq!(registry.stats().kicked_total, 1);
+        // 被踢会话断开时注销是无操作
+        assert!(!registry.unregister("user1", "pc"));
+        assert_eq!(registry.session_count("user1"), 1);
+    }
+
+    #[actix::test]
+    async fn test_user_single_login_kicks_old_session() {
+        // 全局允许多端，user1 单独配置为单点登录
+        let registry = SessionRegistry::default();
+        registry.set_user_policy("user1", Some(LoginPolicy::KickPrevious));
+        assert_eq!(
+            registry.effective_policy("user1"),
+            LoginPolicy::KickPrevious
+        );
+        assert_eq!(
+            registry.effective_policy("user2"),
+            LoginPolicy::AllowMultiple
+        );
+
+        let kicked = Arc::new(RwLock::new(Vec::new()));
+        let pc = MockSession {
+            kicked: kicked.clone(),
+        }
+        .start();
+        let pc_client = ClientInfo {
+            device: Some("QAStudio/1.0".to_string()),
+            ip: Some("10.0.0.1".to_string()),
+        };
+        registry.register(
+            "user1",
+            "pc",
+            "diff",
+            pc_client.clone(),
+            Some(pc.clone().recipient()),
+        );
+        registry.register("user2", "pc2", "diff", ClientInfo::default(), None);
+        registry.register("user2", "mobile2", "ws", ClientInfo::default(), None);
+
+        let mobile_client = ClientInfo {
+            device: Some("QAMobile/2.0".to_string()),
+            ip: Some("10.0.0.2".to_string()),
+        };
+        let result = registry.register("user1", "mobile", "ws", mobile_client.clone(), None);
+        assert_eq!(result, vec!["pc".to_string()]);
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!pc.connected());
+        assert_eq!(
+            kicked.read().as_slice(),
+            ["账户已在其他终端登录 (QAMobile/2.0 @ 10.0.0.2)".to_string()]
+        );
+
+        let sessions = registry.user_sessions("user1");
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].session_id, "mobile");
+        assert_eq!(sessions[0].client, mobile_client);
+        // 未配置的用户仍按全局策略多端在线
+        assert_eq!(registry.session_count("user2"), 2);
+
+        let stats = registry.stats();
+        assert_eq!(
+            stats.user_policies,
+            vec![("user1".to_string(), LoginPolicy::KickPrevious)]
+        );
+        assert_eq!(stats.users[0].policy, LoginPolicy::KickPrevious);
+
+        registry.set_user_policy("user1", None);
+        assert_eq!(
+            registry.effective_policy("user1"),
+            LoginPolicy::AllowMultiple
+        );
+    }
+
+    #[actix::test]
+    async fn test_kick_specific_session() {
+        let registry = SessionRegistry::default();
+        let kicked = Arc::new(RwLock::new(Vec::new()));
+        let pc = MockSession {
+            kicked: kicked.clone(),
+        }
+        .start();
+        registry.register(
+            "user1",
+            "pc",
+            "diff",
+            ClientInfo::default(),
+            Some(pc.clone().recipient()),
+        );
+        registry.register("user1", "mobile", "ws", ClientInfo::default(), None);
+
+        assert_eq!(
+            registry.kick_session("pc", "管理员强制下线"),
+            Some("user1".to_string())
+        );
+        assert_eq!(registry.kick_session("unknown", "管理员强制下线"), None);
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!pc.connected());
+        assert_eq!(kicked.read().as_slice(), ["管理员强制下线".to_string()]);
+        assert_eq!(registry.user_sessions("user1")[0].session_id, "mobile");
+        assert_eq!(registry.stats().kicked_total, 1);
+
+        // 最后一个会话被踢后用户下线
+        registry.kick_session("mobile", "管理员强制下线");
+        assert_eq!(registry.stats().online_users, 0);
+    }
+}
@@ -0,0 +1,588 @@
+//! WebSocket 订阅配额与推送速率限制
+//!
+//! @yutiansut @quantaxis
+//!
+//! 防止少数连接订阅大量合约、占满推送带宽而影响其他用户：
+//! - 每个订阅者（WebSocket 会话 / DIFF 用户）按等级限制订阅合约数与推送速率（条/秒）
+//! - 等级：未认证 `guest`、普通用户 `standard`、VIP `vip`（`vip_users` 中的用户或拥有 `vip_roles` 角色）
+//! - 订阅组变更后合约数超过上限时拒绝该次变更（减少订阅始终放行），推送速率超限期间同样拒绝新增订阅
+//! - 推送速率按 1 秒窗口统计，连续 `disconnect_after_windows` 个窗口超限时断开连接
+//!
+//! 超限计入统计并告警（warn 日志 + `qaexchange_websocket_quota_events_total`），
+//! 通过 `/api/management/sessions/quota` 查询与热更新。
+
+use dashmap::DashMap;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crate::market::subscription::SubscriptionLimiter;
+use crate::observability::metrics::WEBSOCKET_QUOTA_EVENTS;
+use crate::user::{UserManager, UserRole};
+use crate::ExchangeError;
+
+/// 推送速率统计窗口（毫秒）
+const RATE_WINDOW_MS: i64 = 1_000;
+
+lazy_static::lazy_static! {
+    /// 全局订阅配额（WebSocket 会话、DIFF 会话与行情广播器共享）
+    pub static ref WS_SUBSCRIPTION_QUOTA: Arc<SubscriptionQuota> = Arc::new(SubscriptionQuota::new(SubscriptionQuotaConfig::default()));
+}
+
+/// 配额等级
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaTier {
+    /// 未认证连接
+    Guest,
+    /// 普通用户
+    Standard,
+    /// VIP 用户
+    Vip,
+}
+
+impl QuotaTier {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QuotaTier::Guest => "guest",
+            QuotaTier::Standard => "standard",
+            QuotaTier::Vip => "vip",
+        }
+    }
+}
+
+/// 单个等级的配额（0 表示不限）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuotaLimit {
+    /// 订阅合约数上限
+    pub max_instruments: usize,
+    /// 推送速率上限（条/秒）
+    pub max_push_rate: u64,
+}
+
+/// 订阅配额配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubscriptionQuotaConfig {
+    /// 是否启用
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_guest")]
+    pub guest: QuotaLimit,
+    #[serde(default = "default_standard")]
+    pub standard: QuotaLimit,
+    #[serde(default = "default_vip")]
+    pub vip: QuotaLimit,
+    /// VIP 用户（用户ID）
+    #[serde(default)]
+    pub vip_users: Vec<String>,
+    /// 拥有这些角色的用户按 VIP 配额
+    #[serde(default = "default_vip_roles")]
+    pub vip_roles: Vec<UserRole>,
+    /// 连续超速窗口数达到后断开连接
+    #[serde(default = "default_disconnect_after_windows")]
+    pub disconnect_after_windows: u32,
+}
+
+fn default_guest() -> QuotaLimit {
+    QuotaLimit {
+        max_instruments: 20,
+        max_push_rate: 500,
+    }
+}
+fn default_standard() -> QuotaLimit {
+    QuotaLimit {
+        max_instruments: 200,
+        max_push_rate: 5_000,
+    }
+}
+fn default_vip() -> QuotaLimit {
+    QuotaLimit {
+        max_instruments: 1_000,
+        max_push_rate: 20_000,
+    }
+}
+fn default_vip_roles() -> Vec<UserRole> {
+    vec![UserRole::Admin]
+}
+fn default_disconnect_after_windows() -> u32 {
+    5
+}
+
+impl Default for SubscriptionQuotaConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            guest: default_guest(),
+            standard: default_standard(),
+            vip: default_vip(),
+            vip_users: Vec::new(),
+            vip_roles: default_vip_roles(),
+            disconnect_after_windows: default_disconnect_after_windows(),
+        }
+    }
+}
+
+impl SubscriptionQuotaConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.disconnect_after_windows == 0 {
+            return Err("disconnect_after_windows must be greater than 0".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn limit(&self, tier: QuotaTier) -> QuotaLimit {
+        match tier {
+            QuotaTier::Guest => self.guest,
+            QuotaTier::Standard => self.standard,
+            QuotaTier::Vip => self.vip,
+        }
+    }
+}
+
+/// 推送速率检查结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushVerdict {
+    /// 未超限
+    Allowed,
+    /// 当前窗口超速
+    Exceeded,
+    /// 连续超速，应断开连接
+    Disconnect,
+}
+
+#[derive(Debug, Clone)]
+struct SubscriberQuota {
+    protocol: &'static str,
+    user_id: Option<String>,
+    tier: QuotaTier,
+    instruments: usize,
+    window_start_ms: i64,
+    window_events: u64,
+    window_over: bool,
+    /// 上一个完整窗口的推送速率
+    last_rate: u64,
+    /// 连续超速窗口数
+    over_windows: u32,
+    disconnected: bool,
+}
+
+/// 订阅者配额状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubscriberQuotaInfo {
+    pub subscriber_id: String,
+    /// ws / diff
+    pub protocol: String,
+    pub user_id: Option<String>,
+    pub tier: QuotaTier,
+    pub instruments: usize,
+    pub max_instruments: usize,
+    /// 上一个完整窗口的推送速率（条/秒）
+    pub push_rate: u64,
+    pub max_push_rate: u64,
+    pub over_windows: u32,
+}
+
+/// 订阅配额统计
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubscriptionQuotaStats {
+    pub config: SubscriptionQuotaConfig,
+    /// 因合约数或速率超限被拒绝的订阅变更
+    pub rejected_subscriptions: u64,
+    /// 超速窗口数
+    pub rate_exceeded: u64,
+    /// 因持续超速被断开的连接
+    pub disconnected: u64,
+    pub subscribers: Vec<SubscriberQuotaInfo>,
+}
+
+/// 订阅配额
+pub struct SubscriptionQuota {
+    config: RwLock<SubscriptionQuotaConfig>,
+    subscribers: DashMap<String, SubscriberQuota>,
+    rejected_subscriptions: AtomicU64,
+    rate_exceeded: AtomicU64,
+    disconnected: AtomicU64,
+}
+
+impl SubscriptionQuota {
+    pub fn new(config: SubscriptionQuotaConfig) -> Self {
+        Self {
+            config: RwLock::new(config),
+            subscribers: DashMap::new(),
+            rejected_subscriptions: AtomicU64::new(0),
+            rate_exceeded: AtomicU64::new(0),
+            disconnected: AtomicU64::new(0),
+        }
+    }
+
+    pub fn update_config(&self, config: SubscriptionQuotaConfig) {
+        *self.config.write() = config;
+    }
+
+    pub fn config(&self) -> SubscriptionQuotaConfig {
+        self.config.read().clone()
+    }
+
+    /// 按用户与角色确定配额等级
+    pub fn tier_for(&self, user_id: Option<&str>, roles: &[UserRole]) -> QuotaTier {
+        let Some(user_id) = user_id else {
+            return QuotaTier::Guest;
+        };
+        let config = self.config.read();
+        if config.vip_users.iter().any(|u| u == user_id)
+            || roles.iter().any(|r| config.vip_roles.contains(r))
+        {
+            QuotaTier::Vip
+        } else {
+            QuotaTier::Standard
+        }
+    }
+
+    /// 登记订阅者（已登记时更新用户与等级，保留订阅计数与速率窗口）
+    pub fn attach(
+        &self,
+        subscriber_id: &str,
+        protocol: &'static str,
+        user_id: Option<&str>,
+        tier: QuotaTier,
+    ) {
+        let mut entry = self
+            .subscribers
+            .entry(subscriber_id.to_string())
+            .or_insert_with(|| SubscriberQuota {
+                protocol,
+                user_id: None,
+                tier,
+                instruments: 0,
+                window_start_ms: chrono::Utc::now().timestamp_millis(),
+                window_events: 0,
+                window_over: false,
+                last_rate: 0,
+                over_windows: 0,
+                disconnected: false,
+            });
+        entry.user_id = user_id.map(|u| u.to_string());
+        entry.tier = tier;
+    }
+
+    /// 按用户角色登记订阅者（用户不存在时按普通用户）
+    pub fn attach_user(
+        &self,
+        subscriber_id: &str,
+        protocol: &'static str,
+        user_id: Option<&str>,
+        user_manager: Option<&UserManager>,
+    ) {
+        let roles = match (user_id, user_manager) {
+            (Some(user_id), Some(user_mgr)) => user_mgr
+                .get_user(user_id)
+                .map(|user| user.roles)
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        };
+        let tier = self.tier_for(user_id, &roles);
+        self.attach(subscriber_id, protocol, user_id, tier);
+    }
+
+    /// 注销订阅者
+    pub fn detach(&self, subscriber_id: &str) {
+        self.subscribers.remove(subscriber_id);
+    }
+
+    /// 订阅者的配额等级（未登记返回 None）
+    pub fn tier(&self, subscriber_id: &str) -> Option<QuotaTier> {
+        self.subscribers.get(subscriber_id).map(|e| e.tier)
+    }
+
+    /// 订阅变更检查：变更后合约数超过等级上限或当前推送超速时拒绝新增订阅
+    ///
+    /// 未登记的订阅者（内部订阅）与不增加合约数的变更直接放行
+    pub fn check_subscription(
+        &self,
+        subscriber_id: &str,
+        before: usize,
+        after: usize,
+    ) -> Result<(), ExchangeError> {
+        self.check_subscription_at(
+            subscriber_id,
+            before,
+            after,
+            chrono::Utc::now().timestamp_millis(),
+        )
+    }
+
+    pub fn check_subscription_at(
+        &self,
+        subscriber_id: &str,
+        before: usize,
+        after: usize,
+        now_ms: i64,
+    ) -> Result<(), ExchangeError> {
+        let config = self.config.read();
+        let Some(mut entry) = self.subscribers.get_mut(subscriber_id) else {
+            return Ok(());
+        };
+        if !config.enabled || after <= before {
+            entry.instruments = after;
+            return Ok(());
+        }
+
+        let limit = config.limit(entry.tier);
+        let reason = if limit.max_instruments > 0 && after > limit.max_instruments {
+            format!(
+                "Subscription quota exceeded: {} instruments requested, {} tier allows {}",
+                after,
+                entry.tier.as_str(),
+                limit.max_instruments
+            )
+        } else if entry.over_windows > 0 && now_ms - entry.window_start_ms < 2 * RATE_WINDOW_MS {
+            format!(
+                "Push rate limit exceeded ({} tier allows {}/s), new subscriptions rejected",
+                entry.tier.as_str(),
+                limit.max_push_rate
+            )
+        } else {
+            entry.instruments = after;
+            return Ok(());
+        };
+
+        self.rejected_subscriptions.fetch_add(1, Ordering::Relaxed);
+        WEBSOCKET_QUOTA_EVENTS
+            .with_label_values(&[entry.protocol, "subscription_rejected"])
+            .inc();
+        log::warn!(
+            "Subscription of {} (user {:?}) rejected: {}",
+            subscriber_id,
+            entry.user_id,
+            reason
+        );
+        Err(ExchangeError::PermissionDenied(reason))
+    }
+
+    /// 作为行情广播器的订阅配额检查
+    pub fn limiter(self: &Arc<Self>) -> SubscriptionLimiter {
+        let quota = self.clone();
+        Arc::new(move |subscriber_id: &str, before: usize, after: usize| {
+            quota.check_subscription(subscriber_id, before, after)
+        })
+    }
+
+    /// 记录推送条数并检查推送速率
+    pub fn record_push(&self, subscriber_id: &str, events: u64) -> PushVerdict {
+        self.record_push_at(subscriber_id, events, chrono::Utc::now().timestamp_millis())
+    }
+
+    pub fn record_push_at(&self, subscriber_id: &str, events: u64, now_ms: i64) -> PushVerdict {
+        let config = self.config.read();
+        if !config.enabled || events == 0 {
+            return PushVerdict::Allowed;
+        }
+        let Some(mut entry) = self.subscribers.get_mut(subscriber_id) else {
+            return PushVerdict::Allowed;
+        };
+
+        // 窗口滚动：上一窗口未超速或中间有空闲窗口时连续超速计数清零
+        let elapsed = now_ms - entry.window_start_ms;
+        if !(0..RATE_WINDOW_MS).contains(&elapsed) {
+            entry.last_rate = if elapsed > 0 {
+                entry.window_events * RATE_WINDOW_MS as u64 / elapsed as u64
+            } else {
+                entry.window_events
+            };
+            if !entry.window_over || elapsed >= 2 * RATE_WINDOW_MS {
+                entry.over_windows = 0;
+            }
+            entry.window_start_ms = now_ms;
+            entry.window_events = 0;
+            entry.window_over = false;
+        }
+
+        entry.window_events += events;
+        let limit = config.limit(entry.tier).max_push_rate;
+        if limit == 0 || entry.window_events <= limit {
+            return PushVerdict::Allowed;
+        }
+
+        if !entry.window_over {
+            entry.window_over = true;
+            entry.over_windows += 1;
+            self.rate_exceeded.fetch_add(1, Ordering::Relaxed);
+            WEBSOCKET_QUOTA_EVENTS
+                .with_label_values(&[entry.protocol, "rate_exceeded"])
+                .inc();
+            log::warn!(
+                "Push rate of {} (user {:?}) exceeds {}/s for {} window(s)",
+                subscriber_id,
+                entry.user_id,
+                limit,
+                entry.over_windows
+            );
+        }
+
+        if entry.over_windows >= config.disconnect_after_windows && !entry.disconnected {
+            entry.disconnected = true;
+            self.disconnected.fetch_add(1, Ordering::Relaxed);
+            WEBSOCKET_QUOTA_EVENTS
+                .with_label_values(&[entry.protocol, "disconnected"])
+                .inc();
+            log::warn!(
+                "Disconnecting {} (user {:?}): push rate exceeded {}/s for {} consecutive windows",
+                subscriber_id,
+                entry.user_id,
+                limit,
+                entry.over_windows
+            );
+            return PushVerdict::Disconnect;
+        }
+        PushVerdict::Exceeded
+    }
+
+    pub fn stats(&self) -> SubscriptionQuotaStats {
+        let config = self.config();
+        let mut subscribers: Vec<SubscriberQuotaInfo> = self
+            .subscribers
+            .iter()
+            .map(|entry| {
+                let limit = config.limit(entry.tier);
+                SubscriberQuotaInfo {
+                    subscriber_id: entry.key().clone(),
+                    protocol: entry.protocol.to_string(),
+                    user_id: entry.user_id.clone(),
+                    tier: entry.tier,
+                    instruments: entry.instruments,
+                    max_instruments: limit.max_instruments,
+                    push_rate: entry.last_rate,
+                    max_push_rate: limit.max_push_rate,
+                    over_windows: entry.over_windows,
+                }
+            })
+            .collect();
+        subscribers.sort_by(|a, b| a.subscriber_id.cmp(&b.subscriber_id));
+
+        SubscriptionQuotaStats {
+            config,
+            rejected_subscriptions: self.rejected_subscriptions.load(Ordering::Relaxed),
+            rate_exceeded: self.rate_exceeded.load(Ordering::Relaxed),
+            disconnected: self.disconnected.load(Ordering::Relaxed),
+            subscribers,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::market::MarketDataBroadcaster;
+
+    fn enabled_quota() -> Arc<SubscriptionQuota> {
+        Arc::new(SubscriptionQuota::new(SubscriptionQuotaConfig {
+            enabled: true,
+            guest: QuotaLimit {
+                max_instruments: 1,
+                max_push_rate: 10,
+            },
+            standard: QuotaLimit {
+                max_instruments: 2,
+                max_push_rate: 10,
+            },
+            vip: QuotaLimit {
+                max_instruments: 5,
+                max_push_rate: 0,
+            },
+            vip_users: vec!["vip".to_string()],
+            disconnect_after_windows: 2,
+            ..Default::default()
+        }))
+    }
+
+    fn codes(n: usize) -> Vec<String> {
+        (0..n).map(|i| format!("IF25{:02}", i + 1)).collect()
+    }
+
+    #[test]
+    fn test_subscription_over_quota_rejected() {
+        let quota = enabled_quota();
+        let broadcaster = MarketDataBroadcaster::new();
+        broadcaster.set_subscription_limiter(quota.limiter());
+
+        let tier = quota.tier_for(Some("trader"), &[UserRole::Trader]);
+        assert_eq!(tier, QuotaTier::Standard);
+        assert_eq!(quota.tier_for(Some("vip"), &[]), QuotaTier::Vip);
+        assert_eq!(
+            quota.tier_for(Some("root"), &[UserRole::Admin]),
+            QuotaTier::Vip
+        );
+        assert_eq!(quota.tier_for(None, &[]), QuotaTier::Guest);
+        quota.attach("s1", "ws", Some("trader"), tier);
+
+        broadcaster
+            .update_default_group("s1", &codes(2), &[], &[], &[])
+            .unwrap();
+        // 超过上限的订阅整次拒绝，原订阅不变
+        let err = broadcaster
+            .update_default_group("s1", &codes(3), &[], &[], &[])
+            .unwrap_err();
+        assert!(err.to_string().contains("quota exceeded"));
+        assert!(broadcaster
+            .create_group("s1", "extra", vec!["cu2501".to_string()], vec![])
+            .is_err());
+        assert_eq!(broadcaster.list_groups("s1")[0].instruments, codes(2));
+        // 减少订阅不受限
+        broadcaster
+            .update_default_group("s1", &[], &codes(1), &[], &[])
+            .unwrap();
+
+        // 未登记的内部订阅者不受配额限制
+        broadcaster
+            .update_default_group("internal", &codes(3), &[], &[], &[])
+            .unwrap();
+
+        let stats = quota.stats();
+        assert_eq!(stats.rejected_subscriptions, 2);
+        assert_eq!(stats.subscribers[0].instruments, 1);
+    }
+
+    #[test]
+    fn test_push_rate_exceeded_then_disconnect() {
+        let quota = enabled_quota();
+        quota.attach("s1", "diff", Some("trader"), QuotaTier::Standard);
+        let t0 = quota.subscribers.get("s1").unwrap().window_start_ms;
+
+        assert_eq!(quota.record_push_at("s1", 10, t0), PushVerdict::Allowed);
+        assert_eq!(
+            quota.record_push_at("s1", 1, t0 + 10),
+            PushVerdict::Exceeded
+        );
+        // 超速期间拒绝新增订阅
+        assert!(quota.check_subscription_at("s1", 0, 1, t0 + 20).is_err());
+
+        // 连续第二个窗口超速：断开
+        assert_eq!(
+            quota.record_push_at("s1", 11, t0 + 1_000),
+            PushVerdict::Disconnect
+        );
+        assert_eq!(
+            quota.record_push_at("s1", 1, t0 + 1_100),
+            PushVerdict::Exceeded
+        );
+
+        // 空闲窗口后重新计数
+        quota.attach("s2", "ws", Some("trader"), QuotaTier::Standard);
+        let t1 = quota.subscribers.get("s2").unwrap().window_start_ms;
+        assert_eq!(quota.record_push_at("s2", 11, t1), PushVerdict::Exceeded);
+        assert_eq!(
+            quota.record_push_at("s2", 11, t1 + 3_000),
+            PushVerdict::Exceeded
+        );
+        assert!(quota.check_subscription_at("s2", 0, 1, t1 + 6_000).is_ok());
+
+        // VIP 不限速
+        quota.attach("v", "ws", Some("vip"), QuotaTier::Vip);
+        assert_eq!(quota.record_push_at("v", 1_000, t0), PushVerdict::Allowed);
+
+        let stats = quota.stats();
+        assert_eq!(stats.disconnected, 1);
+        assert_eq!(stats.rate_exceeded, 4);
+    }
+}
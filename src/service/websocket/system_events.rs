@@ -0,0 +1,56 @@
+//! 系统事件订阅（运维监控频道 `system_events`）
+//!
+//! 已认证且具备 ViewMonitoring 权限的会话可订阅系统事件，普通用户订阅被拒绝。
+//! 频道名 `system_events` 订阅全部类别，`system_events:<category>` 只订阅指定类别。
+//!
+//! @yutiansut @quantaxis
+
+use std::collections::HashSet;
+
+use tokio::sync::mpsc;
+
+use super::messages::ServerMessage;
+use crate::notification::{
+    Notification, NotificationBroker, NotificationPayload, SystemEventCategory,
+};
+use crate::user::{Permission, UserManager};
+use crate::ExchangeError;
+
+/// 为会话订阅系统事件（校验 ViewMonitoring 权限）
+///
+/// 同一订阅者重复订阅时替换原订阅（类别过滤以最后一次为准）
+pub fn subscribe_system_events(
+    broker: &NotificationBroker,
+    user_manager: &UserManager,
+    user_id: &str,
+    subscriber_id: &str,
+    categories: HashSet<SystemEventCategory>,
+) -> Result<mpsc::UnboundedReceiver<Notification>, ExchangeError> {
+    if !user_manager
+        .user_has_permission(user_id, Permission::ViewMonitoring)
+        .unwrap_or(false)
+    {
+        return Err(ExchangeError::PermissionDenied(
+            "ViewMonitoring permission required for system_events".to_string(),
+        ));
+    }
+
+    let (sender, receiver) = mpsc::unbounded_channel();
+    broker.subscribe_system_events(subscriber_id, categories, sender);
+    Ok(receiver)
+}
+
+/// 系统事件通知转换为服务端推送消息（非系统事件返回 None）
+pub fn system_event_message(notification: &Notification) -> Option<ServerMessage> {
+    let NotificationPayload::SystemEvent(event) = &notification.payload else {
+        return None;
+    };
+    Some(ServerMessage::SystemEvent {
+        category: event.category.clone(),
+        level: event.level.clone(),
+        title: event.title.clone(),
+        details: serde_json::from_str(&event.details).unwrap_or(serde_json::Value::Null),
+        source: notification.source.clone(),
+        timestamp: event.timestamp,
+    })
+}
@@ -20,6 +20,9 @@ pub struct CompactionScheduler {
 
     /// 是否正在运行
     running: Arc<parking_lot::RwLock<bool>>,
+
+    /// 执行互斥锁（后台调度与手动触发不并发执行 compaction）
+    compaction_lock: Arc<parking_lot::Mutex<()>>,
 }
 
 impl CompactionScheduler {
@@ -31,6 +34,7 @@ impl CompactionScheduler {
             level_sstables: Arc::new(RwLock::new(HashMap::new())),
             config,
             running: Arc::new(parking_lot::RwLock::new(false)),
+            compaction_lock: Arc::new(parking_lot::Mutex::new(())),
         }
     }
 
@@ -59,6 +63,7 @@ impl CompactionScheduler {
         let compaction = self.compaction.clone();
         let level_sstables = self.level_sstables.clone();
         let running = self.running.clone();
+        let compaction_lock = self.compaction_lock.clone();
         let check_interval = Duration::from_secs(10); // 每 10 秒检查一次
 
         tokio::spawn(async move {
@@ -77,6 +82,11 @@ impl CompactionScheduler {
                     break;
                 }
 
+                // 手动 compaction 进行中时跳过本轮
+                let Some(_guard) = compaction_lock.try_lock() else {
+                    continue;
+                };
+
                 // 检查是否需要 compaction
                 let levels = level_sstables.read().clone();
                 if let Some(task) = compaction.should_compact(&levels) {
@@ -139,6 +149,18 @@ impl CompactionScheduler {
 
     /// 手动触发 compaction
     pub fn trigger_compaction(&self) -> Result<(), String> {
+        if self.compact_once()? {
+            Ok(())
+        } else {
+            Err("No compaction needed".to_string())
+        }
+    }
+
+    /// 执行一轮 compaction，返回是否实际执行
+    ///
+    /// 与后台调度共用执行锁，后台 compaction 进行中时等待其完成
+    pub fn compact_once(&self) -> Result<bool, String> {
+        let _guard = self.compaction_lock.lock();
         let levels = self.level_sstables.read().clone();
 
         if let Some(task) = self.compaction.should_compact(&levels) {
@@ -165,9 +187,9 @@ impl CompactionScheduler {
                 .or_default()
                 .push(result.new_sstable);
 
-            Ok(())
+            Ok(true)
         } else {
-            Err("No compaction needed".to_string())
+            Ok(false)
         }
     }
 
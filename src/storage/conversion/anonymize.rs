@@ -0,0 +1,352 @@
+// OLAP 历史个人字段重写（一次性任务） @yutiansut @quantaxis
+//
+// 用户注销后 OLTP 侧通过 UserAnonymize WAL 记录在恢复时覆盖个人信息，
+// 已转换到 OLAP 的 Parquet 文件则需要重写：
+// 1. 扫描 {storage_base}/{instrument}/olap/ 下的 Parquet（含 {yyyymm} 月份分区）
+// 2. 将所有定长标识列（user_id 等 FixedSizeBinary 列）中命中注销用户原始标识
+//    （用户名/手机号/邮箱/真实姓名/身份证号，早期版本曾直接作为 user_id 写入）的值替换为匿名用户名，
+//    其余列原样保留
+// 3. 与转换 Worker 相同的提交方式：写临时文件后原子 rename，未命中的文件不改写
+
+use super::partition::list_olap_parquet_files;
+use super::ConversionManager;
+use crate::storage::sstable::olap_parquet::{ParquetSSTable, ParquetSSTableWriter};
+use crate::storage::wal::record::WalRecord;
+use arrow2::array::{Array, FixedSizeBinaryArray, MutableFixedSizeBinaryArray};
+use arrow2::chunk::Chunk;
+use arrow2::datatypes::DataType;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// OLAP 重写报告
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OlapAnonymizeReport {
+    pub files_scanned: usize,
+    pub files_rewritten: usize,
+    pub rows_rewritten: u64,
+    /// 重写失败的文件及原因
+    pub failed_files: Vec<String>,
+}
+
+/// OLAP 个人字段重写器
+pub struct OlapAnonymizer {
+    /// 原始标识（UTF-8 字节）
+    identifiers: Vec<Vec<u8>>,
+    /// 匿名用户名（UTF-8 字节）
+    replacement: Vec<u8>,
+}
+
+impl OlapAnonymizer {
+    /// `identifiers` 中的值替换为 `replacement`
+    pub fn new(identifiers: &[String], replacement: &str) -> Self {
+        Self {
+            identifiers: identifiers
+                .iter()
+                .filter(|id| !id.is_empty())
+                .map(|id| id.as_bytes().to_vec())
+                .collect(),
+            replacement: replacement.as_bytes().to_vec(),
+        }
+    }
+
+    /// 按列宽截断/补零（与 `WalRecord::to_fixed_array_*` 写入方式一致）
+    fn fixed(value: &[u8], size: usize) -> Vec<u8> {
+        let mut fixed = vec![0u8; size];
+        let len = value.len().min(size);
+        fixed[..len].copy_from_slice(&value[..len]);
+        fixed
+    }
+
+    /// 重写存储根目录下所有合约的 OLAP 文件
+    pub fn rewrite_dir(&self, storage_base_path: &Path) -> OlapAnonymizeReport {
+        let mut report = OlapAnonymizeReport::default();
+
+        for path in Self::list_parquet_files(storage_base_path) {
+            report.files_scanned += 1;
+            match self.rewrite_file(&path) {
+                Ok(0) => {}
+                Ok(rows) => {
+                    report.files_rewritten += 1;
+                    report.rows_rewritten += rows;
+                }
+                Err(e) => {
+                    log::error!("OLAP anonymize {:?} failed: {}", path, e);
+                    report
+                        .failed_files
+                        .push(format!("{}: {}", path.display(), e));
+                }
+            }
+        }
+
+        log::info!(
+            "OLAP anonymize completed: scanned={}, rewritten={}, rows={}, failed={}",
+            report.files_scanned,
+            report.files_rewritten,
+            report.rows_rewritten,
+            report.failed_files.len()
+        );
+        report
+    }
+
+    /// 重写单个 Parquet 文件，返回替换的行数（0 表示未命中，文件保持不变）
+    pub fn rewrite_file(&self, path: &Path) -> Result<u64, String> {
+        let sstable = ParquetSSTable::open(path)?;
+        let schema = sstable.schema().clone();
+        // 定长标识列：(列序号, 按列宽换算的原始标识, 按列宽换算的匿名用户名)
+        let columns: Vec<(usize, HashSet<Vec<u8>>, Vec<u8>)> = schema
+            .fields
+            .iter()
+            .enumerate()
+            .filter_map(|(index, field)| match field.data_type() {
+                DataType::FixedSizeBinary(size) => Some((
+                    index,
+                    self.identifiers
+                        .iter()
+                        .map(|id| Self::fixed(id, *size))
+                        .collect(),
+                    Self::fixed(&self.replacement, *size),
+                )),
+                _ => None,
+            })
+            .collect();
+
+        let mut rows_rewritten = 0u64;
+        let mut chunks = Vec::new();
+        for chunk in sstable.scan()? {
+            let mut hit_rows = vec![false; chunk.len()];
+            let mut arrays = chunk.into_arrays();
+            for (column, identifiers, replacement) in &columns {
+                let values = arrays[*column]
+                    .as_any()
+                    .downcast_ref::<FixedSizeBinaryArray>()
+                    .ok_or_else(|| {
+                        format!(
+                            "Column {} is not FixedSizeBinary",
+                            schema.fields[*column].name
+                        )
+                    })?;
+
+                let mut builder =
+                    MutableFixedSizeBinaryArray::with_capacity(values.size(), values.len());
+                for (row, value) in values.iter().enumerate() {
+                    if value.is_some_and(|v| identifiers.contains(v)) {
+                        builder.push(Some(replacement));
+                        hit_rows[row] = true;
+                    } else {
+                        builder.push(value);
+                    }
+                }
+                let rewritten: FixedSizeBinaryArray = builder.into();
+                arrays[*column] = Box::new(rewritten);
+            }
+            rows_rewritten += hit_rows.iter().filter(|hit| **hit).count() as u64;
+            chunks.push(Chunk::new(arrays));
+        }
+
+        if rows_rewritten == 0 {
+            return Ok(0);
+        }
+
+        // 临时文件 + 原子 rename
+        let tmp_path = path.with_extension("parquet.anonymize.tmp");
+        let result = (|| {
+            let mut writer = ParquetSSTableWriter::create(&tmp_path, Arc::new(schema))?;
+            for chunk in &chunks {
+                writer.write_chunk(chunk)?;
+            }
+            writer.finish()?;
+            std::fs::rename(&tmp_path, path)
+                .map_err(|e| format!("Rename parquet file failed: {}", e))
+        })();
+        if let Err(e) = result {
+            let _ = std::fs::remove_file(&tmp_path);
+            return Err(e);
+        }
+
+        log::info!(
+            "OLAP anonymize rewrote {} rows in {:?}",
+            rows_rewritten,
+            path
+        );
+        Ok(rows_rewritten)
+    }
+
+    /// 列出 {base}/{instrument}/olap/ 下的 Parquet（含月份分区）
+    fn list_parquet_files(storage_base_path: &Path) -> Vec<PathBuf> {
+        let Ok(instruments) = std::fs::read_dir(storage_base_path) else {
+            return Vec::new();
+        };
+
+        let mut files: Vec<PathBuf> = instruments
+            .flatten()
+            .flat_map(|entry| list_olap_parquet_files(&entry.path().join("olap")))
+            .collect();
+        files.sort();
+        files
+    }
+}
+
+impl ConversionManager {
+    /// 重写 OLAP 历史数据中的注销用户标识（一次性任务，阻塞执行）
+    pub fn anonymize_olap(&self, identifiers: &[String], replacement: &str) -> OlapAnonymizeReport {
+        OlapAnonymizer::new(identifiers, replacement).rewrite_dir(&self.scheduler.storage_base_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memtable::olap::{create_olap_schema, OlapMemTable};
+    use crate::storage::memtable::types::MemTableKey;
+    use crate::user::{personal_identifiers, User};
+    use tempfile::tempdir;
+
+    fn order(user_id: &str, order_id: u64) -> (MemTableKey, WalRecord) {
+        (
+            MemTableKey::new(order_id as i64, order_id),
+            WalRecord::OrderInsert {
+                order_id,
+                user_id: WalRecord::to_fixed_array_32(user_id),
+                instrument_id: WalRecord::to_fixed_array_16("IF2501"),
+                direction: 0,
+                offset: 0,
+                price: 3800.0,
+                volume: 1.0,
+                timestamp: order_id as i64,
+            },
+        )
+    }
+
+    fn user_ids(path: &Path) -> Vec<String> {
+        let sstable = ParquetSSTable::open(path).unwrap();
+        sstable
+            .scan()
+            .unwrap()
+            .iter()
+            .flat_map(|chunk| {
+                let array = chunk.arrays()[4]
+                    .as_any()
+                    .downcast_ref::<FixedSizeBinaryArray>()
+                    .unwrap();
+                array
+                    .iter()
+                    .map(|v| WalRecord::from_fixed_array(v.unwrap()))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_rewrite_olap_user_ids() {
+        let base = tempdir().unwrap();
+        let olap_dir = base.path().join("IF2501").join("olap");
+        std::fs::create_dir_all(&olap_dir).unwrap();
+
+        let hit = olap_dir.join("batch_1.parquet");
+        let miss = olap_dir.join("batch_2.parquet");
+        for (path, records) in [
+            (
+                &hit,
+                vec![order("alice", 1), order("bob", 2), order("alice", 3)],
+            ),
+            (&miss, vec![order("bob", 4)]),
+        ] {
+            let memtable = OlapMemTable::from_records(records);
+            let mut writer =
+                ParquetSSTableWriter::create(path, Arc::new(create_olap_schema())).unwrap();
+            writer.write_chunk(memtable.chunk()).unwrap();
+            writer.finish().unwrap();
+        }
+        let miss_modified = std::fs::metadata(&miss).unwrap().modified().unwrap();
+
+        let report = OlapAnonymizer::new(&["alice".to_string()], "deleted_0123456789abcdef")
+            .rewrite_dir(base.path());
+        assert_eq!(report.files_scanned, 2);
+        assert_eq!(report.files_rewritten, 1);
+        assert_eq!(report.rows_rewritten, 2);
+        assert!(report.failed_files.is_empty());
+
+        assert_eq!(
+            user_ids(&hit),
+            vec![
+                "deleted_0123456789abcdef",
+                "bob",
+                "deleted_0123456789abcdef"
+            ]
+        );
+        assert_eq!(user_ids(&miss), vec!["bob"]);
+        assert_eq!(
+            std::fs::metadata(&miss).unwrap().modified().unwrap(),
+            miss_modified
+        );
+        // 不残留临时文件
+        assert_eq!(std::fs::read_dir(&olap_dir).unwrap().count(), 2);
+    }
+
+    /// 所有定长标识列的值
+    fn binary_values(path: &Path) -> Vec<String> {
+        let sstable = ParquetSSTable::open(path).unwrap();
+        sstable
+            .scan()
+            .unwrap()
+            .iter()
+            .flat_map(|chunk| {
+                chunk
+                    .arrays()
+                    .iter()
+                    .filter_map(|array| array.as_any().downcast_ref::<FixedSizeBinaryArray>())
+                    .flat_map(|array| array.iter().flatten().map(WalRecord::from_fixed_array))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_erased_user_pii_absent_from_parquet() {
+        let base = tempdir().unwrap();
+        let olap_dir = base.path().join("IF2501").join("olap");
+        std::fs::create_dir_all(&olap_dir).unwrap();
+
+        let mut user = User::new("alice".to_string(), "hash".to_string());
+        user.phone = Some("13800138000".to_string());
+        user.email = Some("alice@example.com".to_string());
+        user.real_name = Some("张三".to_string());
+        user.id_card = Some("110101199001011234".to_string());
+        let identifiers = personal_identifiers(&user);
+        assert_eq!(identifiers.len(), 5);
+
+        // 早期版本以各类个人标识作为 user_id 写入
+        let path = olap_dir.join("batch_1.parquet");
+        let records = identifiers
+            .iter()
+            .chain(std::iter::once(&"bob".to_string()))
+            .enumerate()
+            .map(|(i, id)| order(id, i as u64 + 1))
+            .collect();
+        let memtable = OlapMemTable::from_records(records);
+        let mut writer =
+            ParquetSSTableWriter::create(&path, Arc::new(create_olap_schema())).unwrap();
+        writer.write_chunk(memtable.chunk()).unwrap();
+        writer.finish().unwrap();
+
+        let report =
+            OlapAnonymizer::new(&identifiers, "deleted_0123456789abcdef").rewrite_dir(base.path());
+        assert_eq!(report.rows_rewritten, 5);
+        assert!(report.failed_files.is_empty());
+
+        let values = binary_values(&path);
+        for pii in &identifiers {
+            assert!(!values.contains(pii), "{} still present", pii);
+        }
+        assert!(values.contains(&"bob".to_string()));
+        assert_eq!(
+            user_ids(&path)
+                .iter()
+                .filter(|id| *id == "deleted_0123456789abcdef")
+                .count(),
+            5
+        );
+    }
+}
@@ -0,0 +1,589 @@
+// 因子值 OLTP → OLAP 落盘与查询
+//
+// 因子引擎（KLineActor 内的 StreamFactorEngine）计算出的因子值经 FactorWalPersister
+// 异步写入独立的因子 WAL（{storage}/factors/wal），本模块负责批量落盘与历史查询：
+//
+// 因子 WAL ──(按水位增量回放)──▶ 按 factor/instrument 分组
+//         ──▶ {storage}/factors/olap/{factor}/{instrument}/factors_{起始序列}_{结束序列}.parquet
+// 批量回填 ──▶ {storage}/factors/olap/{factor}/{instrument}/factors_backfill_{时间戳}.parquet
+//
+// - 目录按 因子 → 合约 分区，文件内按时间戳排序（首列统计时间范围），
+//   查询只扫描对应分区并按时间过滤
+// - 查询合并 OLAP 历史与水位之后尚未转换的 WAL，实时落盘的因子值立即可查
+// - 同一时间戳重复写入时：后转换的文件覆盖先转换的，回填覆盖流式值，WAL 覆盖 OLAP
+// - 水位与 K线转换一致，持久化在 olap 目录的 factor_conversion.json
+// - QueryEngine 注册 `factors` 逻辑表供 SQL 查询
+//
+// @yutiansut @quantaxis
+
+use super::kline::list_kline_parquet_files;
+use crate::storage::sstable::olap_parquet::ParquetSSTableWriter;
+use crate::storage::wal::{WalManager, WalRecord};
+use arrow2::array::{Array, Float64Array, Int64Array, Utf8Array};
+use arrow2::chunk::Chunk;
+use arrow2::datatypes::{DataType, Field, Schema};
+use parking_lot::{Mutex, RwLock};
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// SQL 查询中的因子逻辑表名
+pub const FACTOR_TABLE: &str = "factors";
+
+/// 转换水位文件名
+const STATE_FILE: &str = "factor_conversion.json";
+
+/// 因子 OLAP Schema
+///
+/// `timestamp` 放在首列：ParquetSSTableWriter 以首列统计文件时间范围
+pub fn create_factor_schema() -> Schema {
+    Schema::from(vec![
+        Field::new("timestamp", DataType::Int64, false), // 因子时间戳（毫秒，触发计算的 K线时间）
+        Field::new("instrument", DataType::Utf8, false),
+        Field::new("factor", DataType::Utf8, false),
+        Field::new("value", DataType::Float64, false),
+    ])
+}
+
+/// 因子落盘配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FactorStoreConfig {
+    /// 是否启用因子计算结果落盘
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// WAL → Parquet 转换间隔（秒）
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+    /// 已转换 WAL 文件的保留时间（秒），超过后清理
+    #[serde(default = "default_wal_retention_secs")]
+    pub wal_retention_secs: u64,
+    /// 异步写入通道容量
+    #[serde(default = "default_channel_buffer_size")]
+    pub channel_buffer_size: usize,
+    /// WAL 批量写入大小
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+}
+
+fn default_enabled() -> bool {
+    false
+}
+fn default_interval_secs() -> u64 {
+    300
+}
+fn default_wal_retention_secs() -> u64 {
+    7 * 86400
+}
+fn default_channel_buffer_size() -> usize {
+    10000
+}
+fn default_batch_size() -> usize {
+    100
+}
+
+impl Default for FactorStoreConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            interval_secs: default_interval_secs(),
+            wal_retention_secs: default_wal_retention_secs(),
+            channel_buffer_size: default_channel_buffer_size(),
+            batch_size: default_batch_size(),
+        }
+    }
+}
+
+/// 单个因子值（回填输入）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FactorValue {
+    pub instrument_id: String,
+    pub factor_name: String,
+    /// 时间戳（毫秒）
+    pub timestamp: i64,
+    pub value: f64,
+}
+
+/// 因子序列中的一个点
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FactorPoint {
+    /// 时间戳（毫秒）
+    pub timestamp: i64,
+    pub value: f64,
+}
+
+/// 转换水位（持久化）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct FactorConversionState {
+    /// 已转换的最大 WAL 序列号
+    converted_sequence: u64,
+}
+
+/// 一轮转换（或一次回填）的结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FactorConversionReport {
+    /// 写入的因子值数（同一时间戳重复写入时只计最后一次）
+    pub values: usize,
+    /// 写入的 Parquet 文件
+    pub files: Vec<PathBuf>,
+    /// 转换后的水位
+    pub converted_sequence: u64,
+    /// 按保留策略清理的 WAL 文件数
+    pub purged_wal_files: usize,
+}
+
+/// 分组后的因子值：(factor, instrument) -> timestamp -> value
+type FactorGroups = BTreeMap<(String, String), BTreeMap<i64, f64>>;
+
+/// 因子值落盘与查询
+pub struct FactorStore {
+    wal: Arc<RwLock<WalManager>>,
+    olap_dir: PathBuf,
+    config: FactorStoreConfig,
+    /// 转换水位（同时串行化转换轮次与回填）
+    state: Mutex<FactorConversionState>,
+}
+
+impl FactorStore {
+    /// 创建因子存储（从 olap 目录加载水位）
+    ///
+    /// `wal` 与 FactorWalConsumer 共享，流式因子值由消费线程写入
+    pub fn new(
+        wal: Arc<RwLock<WalManager>>,
+        olap_dir: PathBuf,
+        config: FactorStoreConfig,
+    ) -> Result<Self, String> {
+        std::fs::create_dir_all(&olap_dir)
+            .map_err(|e| format!("Create factor OLAP dir failed: {}", e))?;
+
+        let state_path = olap_dir.join(STATE_FILE);
+        let state = if state_path.exists() {
+            let content = std::fs::read_to_string(&state_path)
+                .map_err(|e| format!("Read factor conversion state failed: {}", e))?;
+            serde_json::from_str(&content)
+                .map_err(|e| format!("Parse factor conversion state failed: {}", e))?
+        } else {
+            FactorConversionState::default()
+        };
+
+        Ok(Self {
+            wal,
+            olap_dir,
+            config,
+            state: Mutex::new(state),
+        })
+    }
+
+    pub fn config(&self) -> &FactorStoreConfig {
+        &self.config
+    }
+
+    /// OLAP 根目录（QueryEngine 注册 factors 表时使用）
+    pub fn olap_dir(&self) -> &Path {
+        &self.olap_dir
+    }
+
+    /// 已转换的最大 WAL 序列号
+    pub fn converted_sequence(&self) -> u64 {
+        self.state.lock().converted_sequence
+    }
+
+    /// 执行一轮转换：回放水位之后的 WAL，写入 Parquet，推进水位并按保留策略清理 WAL
+    pub fn convert_once(&self) -> Result<FactorConversionReport, String> {
+        let mut state = self.state.lock();
+        let from_sequence = state.converted_sequence + 1;
+
+        let mut groups = FactorGroups::new();
+        let max_sequence = self.replay_wal(from_sequence, &mut groups)?;
+        let max_sequence = max_sequence.max(state.converted_sequence);
+
+        let mut report = FactorConversionReport {
+            converted_sequence: max_sequence,
+            ..Default::default()
+        };
+
+        if max_sequence > state.converted_sequence {
+            let file_name = format!("factors_{:020}_{:020}.parquet", from_sequence, max_sequence);
+            for ((factor, instrument), rows) in &groups {
+                report
+                    .files
+                    .push(self.write_partition(factor, instrument, rows, &file_name)?);
+                report.values += rows.len();
+            }
+
+            state.converted_sequence = max_sequence;
+            self.save_state(&state)?;
+        }
+
+        report.purged_wal_files = self.wal.read().purge_consumed(
+            state.converted_sequence,
+            Duration::from_secs(self.config.wal_retention_secs),
+        )?;
+
+        if !report.files.is_empty() || report.purged_wal_files > 0 {
+            log::info!(
+                "[FactorStore] {} values -> {} files, watermark={}, purged {} WAL files",
+                report.values,
+                report.files.len(),
+                report.converted_sequence,
+                report.purged_wal_files
+            );
+        }
+        Ok(report)
+    }
+
+    /// 批量回填因子值（直接写入 OLAP，不经过 WAL）
+    ///
+    /// 用于历史因子重算后补齐序列，同一时间戳覆盖已落盘的流式值
+    pub fn backfill(&self, values: Vec<FactorValue>) -> Result<FactorConversionReport, String> {
+        let state = self.state.lock();
+
+        let mut groups = FactorGroups::new();
+        for v in values {
+            if !is_partition_name(&v.factor_name)
+                || !is_partition_name(&v.instrument_id)
+                || !v.value.is_finite()
+            {
+                return Err(format!(
+                    "Invalid factor value: {}/{}@{}={}",
+                    v.factor_name, v.instrument_id, v.timestamp, v.value
+                ));
+            }
+            groups
+                .entry((v.factor_name, v.instrument_id))
+                .or_default()
+                .insert(v.timestamp, v.value);
+        }
+
+        let file_name = format!(
+            "factors_backfill_{:020}.parquet",
+            chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0)
+        );
+        let mut report = FactorConversionReport {
+            converted_sequence: state.converted_sequence,
+            ..Default::default()
+        };
+        for ((factor, instrument), rows) in &groups {
+            report
+                .files
+                .push(self.write_partition(factor, instrument, rows, &file_name)?);
+            report.values += rows.len();
+        }
+
+        log::info!(
+            "[FactorStore] backfilled {} values -> {} files",
+            report.values,
+            report.files.len()
+        );
+        Ok(report)
+    }
+
+    /// 查询因子历史序列（按时间升序，`[start, end]` 闭区间，毫秒）
+    ///
+    /// 合并 OLAP 分区与水位之后尚未转换的 WAL
+    pub fn query(
+        &self,
+        factor_name: &str,
+        instrument_id: &str,
+        start: Option<i64>,
+        end: Option<i64>,
+    ) -> Result<Vec<FactorPoint>, String> {
+        if !is_partition_name(factor_name) || !is_partition_name(instrument_id) {
+            return Err(format!(
+                "Invalid factor/instrument: {}/{}",
+                factor_name, instrument_id
+            ));
+        }
+        let start = start.unwrap_or(i64::MIN);
+        let end = end.unwrap_or(i64::MAX);
+        if start > end {
+            return Err(format!("Invalid time range: start {} > end {}", start, end));
+        }
+
+        // 持有水位锁，避免转换过程中 WAL 与 Parquet 的边界变化
+        let state = self.state.lock();
+        let mut series = BTreeMap::new();
+
+        // 1. OLAP 历史（文件名有序：流式文件按序列号，回填文件排在之后）
+        let partition = self.partition_dir(factor_name, instrument_id);
+        for path in list_kline_parquet_files(&partition) {
+            let df = LazyFrame::scan_parquet(
+                PlPath::new(path.to_str().unwrap()),
+                ScanArgsParquet::default(),
+            )
+            .map_err(|e| format!("Scan factor parquet failed: {}", e))?
+            .filter(
+                col("timestamp")
+                    .gt_eq(lit(start))
+                    .and(col("timestamp").lt_eq(lit(end))),
+            )
+            .select([col("timestamp"), col("value")])
+            .collect()
+            .map_err(|e| format!("Read factor parquet failed: {}", e))?;
+
+            let timestamps = df
+                .column("timestamp")
+                .and_then(|c| c.i64())
+                .map_err(|e| format!("Invalid factor timestamp column: {}", e))?;
+            let values = df
+                .column("value")
+                .and_then(|c| c.f64())
+                .map_err(|e| format!("Invalid factor value column: {}", e))?;
+            for (ts, value) in timestamps
+                .into_no_null_iter()
+                .zip(values.into_no_null_iter())
+            {
+                series.insert(ts, value);
+            }
+        }
+
+        // 2. 尚未转换的 WAL（实时落盘的因子值）
+        let mut groups = FactorGroups::new();
+        self.replay_wal(state.converted_sequence + 1, &mut groups)?;
+        if let Some(rows) = groups.remove(&(factor_name.to_string(), instrument_id.to_string())) {
+            series.extend(rows.range(start..=end).map(|(ts, value)| (*ts, *value)));
+        }
+
+        Ok(series
+            .into_iter()
+            .map(|(timestamp, value)| FactorPoint { timestamp, value })
+            .collect())
+    }
+
+    /// 回放 WAL 中的因子更新，返回回放到的最大序列号
+    fn replay_wal(&self, from_sequence: u64, groups: &mut FactorGroups) -> Result<u64, String> {
+        let mut max_sequence = 0;
+        self.wal.read().replay_from(from_sequence, |entry| {
+            max_sequence = max_sequence.max(entry.sequence);
+            if let WalRecord::FactorUpdate {
+                instrument_id,
+                factor_id,
+                value,
+                is_valid,
+                source_timestamp,
+                ..
+            } = entry.record
+            {
+                // 向量因子取首个元素（与 FactorWalPersister 的 value 字段一致）
+                if is_valid && value.is_finite() {
+                    groups
+                        .entry((
+                            WalRecord::from_fixed_array(&factor_id),
+                            WalRecord::from_fixed_array(&instrument_id),
+                        ))
+                        .or_default()
+                        .insert(source_timestamp, value);
+                }
+            }
+            Ok(())
+        })?;
+        Ok(max_sequence)
+    }
+
+    fn partition_dir(&self, factor_name: &str, instrument_id: &str) -> PathBuf {
+        self.olap_dir.join(factor_name).join(instrument_id)
+    }
+
+    /// 写入单个 factor/instrument 分区（临时文件 + rename）
+    fn write_partition(
+        &self,
+        factor: &str,
+        instrument: &str,
+        rows: &BTreeMap<i64, f64>,
+        file_name: &str,
+    ) -> Result<PathBuf, String> {
+        let dir = self.partition_dir(factor, instrument);
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Create factor partition dir failed: {}", e))?;
+
+        let path = dir.join(file_name);
+        let tmp_path = path.with_extension("parquet.tmp");
+
+        let len = rows.len();
+        let chunk = Chunk::new(vec![
+            Box::new(Int64Array::from_vec(rows.keys().copied().collect())) as Box<dyn Array>,
+            Box::new(Utf8Array::<i32>::from_slice(vec![instrument; len])),
+            Box::new(Utf8Array::<i32>::from_slice(vec![factor; len])),
+            Box::new(Float64Array::from_vec(rows.values().copied().collect())),
+        ]);
+
+        let mut writer = ParquetSSTableWriter::create(&tmp_path, Arc::new(create_factor_schema()))?;
+        writer.write_chunk(&chunk)?;
+        let metadata = writer.finish()?;
+        if metadata.entry_count != len as u64 {
+            let _ = std::fs::remove_file(&tmp_path);
+            return Err(format!(
+                "Factor entry count mismatch: expected {}, got {}",
+                len, metadata.entry_count
+            ));
+        }
+
+        std::fs::rename(&tmp_path, &path)
+            .map_err(|e| format!("Rename factor parquet failed: {}", e))?;
+        Ok(path)
+    }
+
+    fn save_state(&self, state: &FactorConversionState) -> Result<(), String> {
+        let path = self.olap_dir.join(STATE_FILE);
+        let tmp_path = path.with_extension("json.tmp");
+        let content = serde_json::to_string_pretty(state)
+            .map_err(|e| format!("Serialize factor conversion state failed: {}", e))?;
+        std::fs::write(&tmp_path, content)
+            .map_err(|e| format!("Write factor conversion state failed: {}", e))?;
+        std::fs::rename(&tmp_path, &path)
+            .map_err(|e| format!("Rename factor conversion state failed: {}", e))
+    }
+}
+
+/// 因子名/合约名作为分区目录名，不允许为空或包含路径分隔符
+fn is_partition_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\\'])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::factor::{FactorWalConfig, FactorWalConsumer, FactorWalPersister};
+    use crate::query::QueryEngine;
+
+    const BASE_TS: i64 = 1_735_779_600_000; // 2025-01-02 09:00:00 +08:00
+    const STEP_MS: i64 = 60_000;
+
+    fn test_store(dir: &Path) -> (Arc<RwLock<WalManager>>, FactorStore) {
+        let wal = Arc::new(RwLock::new(WalManager::new(
+            dir.join("wal").to_str().unwrap(),
+        )));
+        let config = FactorStoreConfig {
+            wal_retention_secs: 0,
+            ..Default::default()
+        };
+        let store = FactorStore::new(wal.clone(), dir.join("olap"), config).unwrap();
+        (wal, store)
+    }
+
+    fn ma5(i: i64) -> f64 {
+        3800.0 + i as f64 * 0.5
+    }
+
+    /// 流式因子经 FactorWalPersister 落盘，转换前后按时间范围查询结果一致
+    #[test]
+    fn test_stream_persist_and_query_by_range() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let (wal, store) = test_store(tmp_dir.path());
+
+        let (persister, rx) = FactorWalPersister::new(FactorWalConfig::default());
+        let mut consumer = FactorWalConsumer::new(rx, wal, 16);
+        for i in 0..30 {
+            let mut factors = std::collections::HashMap::new();
+            factors.insert("ma5".to_string(), ma5(i));
+            factors.insert("rsi14".to_string(), 50.0 + i as f64);
+            persister
+                .persist_batch("IF2501", &factors, BASE_TS + i * STEP_MS)
+                .unwrap();
+            persister
+                .persist_update("IC2501", "ma5", 6000.0, BASE_TS + i * STEP_MS)
+                .unwrap();
+        }
+        consumer.poll();
+
+        let (start, end) = (BASE_TS + 5 * STEP_MS, BASE_TS + 20 * STEP_MS);
+        let expected: Vec<FactorPoint> = (5..=20)
+            .map(|i| FactorPoint {
+                timestamp: BASE_TS + i * STEP_MS,
+                value: ma5(i),
+            })
+            .collect();
+
+        // 转换前：从 WAL 读取
+        let series = store
+            .query("ma5", "IF2501", Some(start), Some(end))
+            .unwrap();
+        assert_eq!(series, expected);
+
+        // 转换后：从 OLAP 读取，WAL 已清理
+        let report = store.convert_once().unwrap();
+        assert_eq!(report.values, 90);
+        assert_eq!(report.files.len(), 3);
+        assert!(tmp_dir.path().join("olap/ma5/IF2501").exists());
+        let series = store
+            .query("ma5", "IF2501", Some(start), Some(end))
+            .unwrap();
+        assert_eq!(series, expected);
+
+        // 新的流式值在下一轮转换前即可查询
+        persister
+            .persist_update("IF2501", "ma5", 3900.0, BASE_TS + 30 * STEP_MS)
+            .unwrap();
+        consumer.poll();
+        let series = store
+            .query("ma5", "IF2501", Some(BASE_TS + 29 * STEP_MS), None)
+            .unwrap();
+        assert_eq!(series.len(), 2);
+        assert_eq!(series[1].value, 3900.0);
+
+        assert!(store
+            .query("ma5", "IF2501", Some(end), Some(start))
+            .is_err());
+        assert!(store
+            .query("ma20", "IF2501", None, None)
+            .unwrap()
+            .is_empty());
+        assert!(store.query("..", "IF2501", None, None).is_err());
+    }
+
+    /// 批量回填写入 OLAP，覆盖同一时间戳的流式值，并可通过 factors 表 SQL 查询
+    #[test]
+    fn test_backfill_overrides_and_sql() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let (wal, store) = test_store(tmp_dir.path());
+
+        let (persister, rx) = FactorWalPersister::default_new();
+        for i in 0..10 {
+            persister
+                .persist_update("IF2501", "ma5", ma5(i), BASE_TS + i * STEP_MS)
+                .unwrap();
+        }
+        FactorWalConsumer::new(rx, wal, 100).poll();
+        store.convert_once().unwrap();
+
+        let backfill: Vec<FactorValue> = (-5..3)
+            .map(|i| FactorValue {
+                instrument_id: "IF2501".to_string(),
+                factor_name: "ma5".to_string(),
+                timestamp: BASE_TS + i * STEP_MS,
+                value: -1.0,
+            })
+            .collect();
+        let report = store.backfill(backfill).unwrap();
+        assert_eq!(report.values, 8);
+
+        let series = store.query("ma5", "IF2501", None, None).unwrap();
+        assert_eq!(series.len(), 15);
+        assert!(series.windows(2).all(|w| w[0].timestamp < w[1].timestamp));
+        assert_eq!(series[0].timestamp, BASE_TS - 5 * STEP_MS);
+        assert_eq!(series[7].value, -1.0);
+        assert_eq!(series[8].value, ma5(3));
+
+        assert!(store
+            .backfill(vec![FactorValue {
+                instrument_id: "IF2501".to_string(),
+                factor_name: "ma5".to_string(),
+                timestamp: BASE_TS,
+                value: f64::NAN,
+            }])
+            .is_err());
+
+        let mut engine = QueryEngine::new();
+        engine.add_factor_dir(store.olap_dir());
+        let df = engine
+            .sql(&format!(
+                "SELECT * FROM factors WHERE factor = 'ma5' AND instrument = 'IF2501' \
+                 AND timestamp >= {} ORDER BY timestamp",
+                BASE_TS + 5 * STEP_MS
+            ))
+            .unwrap();
+        assert_eq!(df.height(), 5);
+    }
+}
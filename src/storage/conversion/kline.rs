@@ -0,0 +1,509 @@
+// K线 OLTP → OLAP 转换
+//
+// 完成的 K线由 KLineActor 写入独立的 K线 WAL（{storage}/klines/wal），
+// 研究场景批量拉取长周期历史时逐条扫描 WAL 太慢。转换流程：
+//
+// K线 WAL ──(按水位增量回放)──▶ 按 instrument/period 分组
+//        ──▶ {storage}/klines/olap/{instrument}/period={秒}/klines_{起始序列}_{结束序列}.parquet
+//        ──▶ QueryEngine 注册 `klines` 逻辑表供 SQL 查询
+//
+// - 水位（已转换的最大 WAL 序列号）持久化在 olap 目录的 kline_conversion.json，
+//   重启后从水位继续，不重复转换
+// - 条目全部不超过水位、且超过保留时间的已滚动 WAL 文件按保留策略清理
+//   （KLineActor 重启恢复只能拿到保留期内的 K线，更早的历史走 OLAP 查询）
+//
+// @yutiansut @quantaxis
+
+use crate::market::kline::KLinePeriod;
+use crate::storage::sstable::olap_parquet::ParquetSSTableWriter;
+use crate::storage::wal::{WalManager, WalRecord};
+use arrow2::array::{Array, Float64Array, Int32Array, Int64Array, Utf8Array};
+use arrow2::chunk::Chunk;
+use arrow2::datatypes::{DataType, Field, Schema};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// SQL 查询中的 K线逻辑表名
+pub const KLINE_TABLE: &str = "klines";
+
+/// 转换水位文件名
+const STATE_FILE: &str = "kline_conversion.json";
+
+/// K线 OLAP Schema
+///
+/// `datetime` 放在首列：ParquetSSTableWriter 以首列统计文件时间范围
+pub fn create_kline_schema() -> Schema {
+    Schema::from(vec![
+        Field::new("datetime", DataType::Int64, false), // K线起始时间戳（毫秒）
+        Field::new("instrument", DataType::Utf8, false),
+        Field::new("period", DataType::Int32, false), // 周期（秒，60=1分钟，86400=日线）
+        Field::new("open", DataType::Float64, false),
+        Field::new("high", DataType::Float64, false),
+        Field::new("low", DataType::Float64, false),
+        Field::new("close", DataType::Float64, false),
+        Field::new("volume", DataType::Int64, false),
+        Field::new("amount", DataType::Float64, false),
+        Field::new("open_oi", DataType::Int64, false),
+        Field::new("close_oi", DataType::Int64, false),
+    ])
+}
+
+/// K线转换配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KLineConversionConfig {
+    /// 是否启用 K线转换
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// 转换间隔（秒）
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+    /// 已转换 WAL 文件的保留时间（秒），超过后清理
+    #[serde(default = "default_wal_retention_secs")]
+    pub wal_retention_secs: u64,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+fn default_interval_secs() -> u64 {
+    300
+}
+fn default_wal_retention_secs() -> u64 {
+    7 * 86400
+}
+
+impl Default for KLineConversionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            interval_secs: default_interval_secs(),
+            wal_retention_secs: default_wal_retention_secs(),
+        }
+    }
+}
+
+/// 转换水位（持久化）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct KLineConversionState {
+    /// 已转换的最大 WAL 序列号
+    converted_sequence: u64,
+}
+
+/// 一轮转换的结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct KLineConversionReport {
+    /// 本轮转换的 K线数（同一根 K线重复写入时只计最后一次）
+    pub bars: usize,
+    /// 本轮写入的 Parquet 文件
+    pub files: Vec<PathBuf>,
+    /// 转换后的水位
+    pub converted_sequence: u64,
+    /// 按保留策略清理的 WAL 文件数
+    pub purged_wal_files: usize,
+}
+
+/// 单根 K线
+#[derive(Debug, Clone)]
+struct KLineRow {
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+    volume: i64,
+    amount: f64,
+    open_oi: i64,
+    close_oi: i64,
+}
+
+/// K线转换器
+pub struct KLineConverter {
+    wal: Arc<WalManager>,
+    olap_dir: PathBuf,
+    config: KLineConversionConfig,
+    /// 转换水位（同时串行化转换轮次）
+    state: Mutex<KLineConversionState>,
+}
+
+impl KLineConverter {
+    /// 创建转换器（从 olap 目录加载水位）
+    pub fn new(
+        wal: Arc<WalManager>,
+        olap_dir: PathBuf,
+        config: KLineConversionConfig,
+    ) -> Result<Self, String> {
+        std::fs::create_dir_all(&olap_dir)
+            .map_err(|e| format!("Create K-line OLAP dir failed: {}", e))?;
+
+        let state_path = olap_dir.join(STATE_FILE);
+        let state = if state_path.exists() {
+            let content = std::fs::read_to_string(&state_path)
+                .map_err(|e| format!("Read K-line conversion state failed: {}", e))?;
+            serde_json::from_str(&content)
+                .map_err(|e| format!("Parse K-line conversion state failed: {}", e))?
+        } else {
+            KLineConversionState::default()
+        };
+
+        Ok(Self {
+            wal,
+            olap_dir,
+            config,
+            state: Mutex::new(state),
+        })
+    }
+
+    pub fn config(&self) -> &KLineConversionConfig {
+        &self.config
+    }
+
+    /// OLAP 根目录（QueryEngine 注册 klines 表时使用）
+    pub fn olap_dir(&self) -> &Path {
+        &self.olap_dir
+    }
+
+    /// 已转换的最大 WAL 序列号
+    pub fn converted_sequence(&self) -> u64 {
+        self.state.lock().converted_sequence
+    }
+
+    /// 执行一轮转换：回放水位之后的 WAL，写入 Parquet，推进水位并按保留策略清理 WAL
+    pub fn convert_once(&self) -> Result<KLineConversionReport, String> {
+        let mut state = self.state.lock();
+        let from_sequence = state.converted_sequence + 1;
+
+        // (instrument, period秒) -> datetime -> K线（有序，重复写入取最后一次）
+        let mut groups: BTreeMap<(String, i32), BTreeMap<i64, KLineRow>> = BTreeMap::new();
+        let mut max_sequence = state.converted_sequence;
+        let mut bars = 0;
+
+        self.wal.replay_from(from_sequence, |entry| {
+            max_sequence = max_sequence.max(entry.sequence);
+            if let WalRecord::KLineFinished {
+                instrument_id,
+                period,
+                kline_timestamp,
+                open,
+                high,
+                low,
+                close,
+                volume,
+                amount,
+                open_oi,
+                close_oi,
+                ..
+            } = entry.record
+            {
+                let Some(kline_period) = KLinePeriod::from_int(period) else {
+                    log::warn!("[KLineConversion] Unknown K-line period: {}", period);
+                    return Ok(());
+                };
+                let rows = groups
+                    .entry((
+                        WalRecord::from_fixed_array(&instrument_id),
+                        kline_period.seconds() as i32,
+                    ))
+                    .or_default();
+                let row = KLineRow {
+                    open,
+                    high,
+                    low,
+                    close,
+                    volume,
+                    amount,
+                    open_oi,
+                    close_oi,
+                };
+                if rows.insert(kline_timestamp, row).is_none() {
+                    bars += 1;
+                }
+            }
+            Ok(())
+        })?;
+
+        let mut report = KLineConversionReport {
+            converted_sequence: max_sequence,
+            ..Default::default()
+        };
+
+        if max_sequence > state.converted_sequence {
+            for ((instrument, period), rows) in &groups {
+                let path =
+                    self.write_partition(instrument, *period, rows, from_sequence, max_sequence)?;
+                report.files.push(path);
+            }
+            report.bars = bars;
+
+            state.converted_sequence = max_sequence;
+            self.save_state(&state)?;
+        }
+
+        report.purged_wal_files = self.wal.purge_consumed(
+            state.converted_sequence,
+            Duration::from_secs(self.config.wal_retention_secs),
+        )?;
+
+        if !report.files.is_empty() || report.purged_wal_files > 0 {
+            log::info!(
+                "[KLineConversion] {} bars -> {} files, watermark={}, purged {} WAL files",
+                report.bars,
+                report.files.len(),
+                report.converted_sequence,
+                report.purged_wal_files
+            );
+        }
+        Ok(report)
+    }
+
+    /// 写入单个 instrument/period 分区（临时文件 + rename）
+    fn write_partition(
+        &self,
+        instrument: &str,
+        period: i32,
+        rows: &BTreeMap<i64, KLineRow>,
+        from_sequence: u64,
+        to_sequence: u64,
+    ) -> Result<PathBuf, String> {
+        let dir = self
+            .olap_dir
+            .join(instrument)
+            .join(format!("period={}", period));
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Create K-line partition dir failed: {}", e))?;
+
+        let path = dir.join(format!(
+            "klines_{:020}_{:020}.parquet",
+            from_sequence, to_sequence
+        ));
+        let tmp_path = path.with_extension("parquet.tmp");
+
+        let len = rows.len();
+        let column_f64 = |f: fn(&KLineRow) -> f64| -> Box<dyn Array> {
+            Box::new(Float64Array::from_vec(rows.values().map(f).collect()))
+        };
+        let column_i64 = |f: fn(&KLineRow) -> i64| -> Box<dyn Array> {
+            Box::new(Int64Array::from_vec(rows.values().map(f).collect()))
+        };
+        let chunk = Chunk::new(vec![
+            Box::new(Int64Array::from_vec(rows.keys().copied().collect())) as Box<dyn Array>,
+            Box::new(Utf8Array::<i32>::from_slice(vec![instrument; len])),
+            Box::new(Int32Array::from_vec(vec![period; len])),
+            column_f64(|r| r.open),
+            column_f64(|r| r.high),
+            column_f64(|r| r.low),
+            column_f64(|r| r.close),
+            column_i64(|r| r.volume),
+            column_f64(|r| r.amount),
+            column_i64(|r| r.open_oi),
+            column_i64(|r| r.close_oi),
+        ]);
+
+        let mut writer = ParquetSSTableWriter::create(&tmp_path, Arc::new(create_kline_schema()))?;
+        writer.write_chunk(&chunk)?;
+        let metadata = writer.finish()?;
+        if metadata.entry_count != len as u64 {
+            let _ = std::fs::remove_file(&tmp_path);
+            return Err(format!(
+                "K-line entry count mismatch: expected {}, got {}",
+                len, metadata.entry_count
+            ));
+        }
+
+        std::fs::rename(&tmp_path, &path)
+            .map_err(|e| format!("Rename K-line parquet failed: {}", e))?;
+        Ok(path)
+    }
+
+    fn save_state(&self, state: &KLineConversionState) -> Result<(), String> {
+        let path = self.olap_dir.join(STATE_FILE);
+        let tmp_path = path.with_extension("json.tmp");
+        let content = serde_json::to_string_pretty(state)
+            .map_err(|e| format!("Serialize K-line conversion state failed: {}", e))?;
+        std::fs::write(&tmp_path, content)
+            .map_err(|e| format!("Write K-line conversion state failed: {}", e))?;
+        std::fs::rename(&tmp_path, &path)
+            .map_err(|e| format!("Rename K-line conversion state failed: {}", e))
+    }
+}
+
+/// 递归列出 K线 OLAP 目录下的 Parquet 文件（按路径排序）
+pub fn list_kline_parquet_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&current) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if path.extension().is_some_and(|e| e == "parquet") {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::market::{KLineActor, MarketDataBroadcaster};
+    use crate::query::QueryEngine;
+    use crate::service::http::kline::get_kline_data;
+    use actix::Actor;
+    use actix_web::{test, web, App};
+    use polars::prelude::*;
+    use std::sync::Arc;
+
+    const BASE_TS: i64 = 1_735_779_600_000; // 2025-01-02 09:00:00 +08:00
+
+    fn append_bar(wal: &WalManager, instrument: &str, period: i32, index: i64, step_ms: i64) {
+        wal.append(WalRecord::KLineFinished {
+            instrument_id: WalRecord::to_fixed_array_16(instrument),
+            period,
+            kline_timestamp: BASE_TS + index * step_ms,
+            open: 3800.0 + index as f64,
+            high: 3810.0 + index as f64,
+            low: 3790.0 + index as f64,
+            close: 3805.0 + index as f64,
+            volume: 100 + index,
+            amount: (3800.0 + index as f64) * 300.0,
+            open_oi: 5000 + index,
+            close_oi: 5010 + index,
+            timestamp: chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0),
+        })
+        .unwrap();
+    }
+
+    fn test_config() -> KLineConversionConfig {
+        KLineConversionConfig {
+            wal_retention_secs: 0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_convert_incrementally_by_partition() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let wal = Arc::new(WalManager::new(
+            tmp_dir.path().join("wal").to_str().unwrap(),
+        ));
+        for i in 0..5 {
+            append_bar(&wal, "IF2501", 4, i, 60_000);
+            append_bar(&wal, "IF2501", 5, i, 300_000);
+        }
+        append_bar(&wal, "IC2501", 4, 0, 60_000);
+
+        let olap_dir = tmp_dir.path().join("olap");
+        let converter = KLineConverter::new(wal.clone(), olap_dir.clone(), test_config()).unwrap();
+        let report = converter.convert_once().unwrap();
+        assert_eq!(report.bars, 11);
+        assert_eq!(report.files.len(), 3);
+        assert!(olap_dir.join("IF2501").join("period=60").exists());
+        assert!(olap_dir.join("IF2501").join("period=300").exists());
+
+        // 无新数据时不产生文件
+        assert!(converter.convert_once().unwrap().files.is_empty());
+
+        // 重启后从持久化水位继续
+        append_bar(&wal, "IF2501", 4, 5, 60_000);
+        let converter = KLineConverter::new(wal, olap_dir.clone(), test_config()).unwrap();
+        assert_eq!(converter.converted_sequence(), report.converted_sequence);
+        let report = converter.convert_once().unwrap();
+        assert_eq!(report.bars, 1);
+        assert_eq!(list_kline_parquet_files(&olap_dir).len(), 4);
+    }
+
+    #[actix::test]
+    async fn test_sql_matches_http_kline() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let wal = Arc::new(WalManager::new(
+            tmp_dir.path().join("wal").to_str().unwrap(),
+        ));
+        for i in 0..30 {
+            append_bar(&wal, "IF2501", 4, i, 60_000);
+        }
+        for i in 0..10 {
+            append_bar(&wal, "IF2501", 5, i, 300_000);
+            append_bar(&wal, "IC2501", 4, i, 60_000);
+        }
+
+        let converter =
+            KLineConverter::new(wal.clone(), tmp_dir.path().join("olap"), test_config()).unwrap();
+        converter.convert_once().unwrap();
+
+        let (start, end) = (BASE_TS + 5 * 60_000, BASE_TS + 20 * 60_000);
+        let mut engine = QueryEngine::new();
+        engine.add_kline_dir(converter.olap_dir());
+        let df = engine
+            .sql(&format!(
+                "SELECT * FROM klines WHERE instrument = 'IF2501' AND period = 60 \
+                 AND datetime BETWEEN {} AND {} ORDER BY datetime",
+                start, end
+            ))
+            .unwrap();
+        assert_eq!(df.height(), 16);
+
+        let actor = KLineActor::new(Arc::new(MarketDataBroadcaster::new()), wal).start();
+        let app = test::init_service(App::new().app_data(web::Data::new(actor)).route(
+            "/api/market/kline/{instrument_id}",
+            web::get().to(get_kline_data),
+        ))
+        .await;
+        let req = test::TestRequest::get()
+            .uri("/api/market/kline/IF2501?period=4&count=1000")
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let http_bars: Vec<&serde_json::Value> = resp["data"]["klines"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|k| (start..=end).contains(&k["datetime"].as_i64().unwrap()))
+            .collect();
+        assert_eq!(http_bars.len(), df.height());
+
+        let datetime: Vec<i64> = df
+            .column("datetime")
+            .unwrap()
+            .i64()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        let volume: Vec<i64> = df
+            .column("volume")
+            .unwrap()
+            .i64()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        let f64_column = |name: &str| -> Vec<f64> {
+            df.column(name)
+                .unwrap()
+                .f64()
+                .unwrap()
+                .into_no_null_iter()
+                .collect()
+        };
+        let (open, high, low, close) = (
+            f64_column("open"),
+            f64_column("high"),
+            f64_column("low"),
+            f64_column("close"),
+        );
+
+        for (i, bar) in http_bars.iter().enumerate() {
+            assert_eq!(bar["datetime"].as_i64().unwrap(), datetime[i]);
+            assert_eq!(bar["open"].as_f64().unwrap(), open[i]);
+            assert_eq!(bar["high"].as_f64().unwrap(), high[i]);
+            assert_eq!(bar["low"].as_f64().unwrap(), low[i]);
+            assert_eq!(bar["close"].as_f64().unwrap(), close[i]);
+            assert_eq!(bar["volume"].as_i64().unwrap(), volume[i]);
+        }
+    }
+}
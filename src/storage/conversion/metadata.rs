@@ -10,7 +10,7 @@ use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use std::fs::{File, OpenOptions};
 use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// 转换状态
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -37,9 +37,13 @@ pub struct ConversionRecord {
     /// 源 OLTP SSTable 文件列表
     pub oltp_sstables: Vec<PathBuf>,
 
-    /// 目标 OLAP Parquet 文件
+    /// 目标 OLAP Parquet 文件（按月分区时为各分区内使用的文件名）
     pub olap_parquet: PathBuf,
 
+    /// 实际写入的月份分区文件（{olap}/{yyyymm}/{文件名}）
+    #[serde(default)]
+    pub olap_files: Vec<PathBuf>,
+
     /// 转换状态
     pub status: ConversionStatus,
 
@@ -80,6 +84,7 @@ impl ConversionRecord {
             instrument_id,
             oltp_sstables,
             olap_parquet,
+            olap_files: Vec::new(),
             status: ConversionStatus::Pending,
             entry_count: 0,
             min_timestamp: 0,
@@ -300,6 +305,45 @@ impl ConversionMetadata {
         Ok(())
     }
 
+    /// 文件是否仍被未完成的转换记录引用（源 SSTable 或目标 Parquet）
+    pub fn is_referenced(&self, path: &Path) -> bool {
+        self.records
+            .iter()
+            .filter(|r| r.status != ConversionStatus::Success)
+            .any(|r| {
+                r.olap_parquet == path
+                    || r.oltp_sstables.iter().any(|p| p == path)
+                    || r.olap_files.iter().any(|p| p == path)
+            })
+    }
+
+    /// 文件被保留策略删除后更新转换记录，返回移除的记录数
+    ///
+    /// 已转换记录的输出文件全部删除后，该记录一并移除
+    pub fn forget_files(&mut self, deleted: &[PathBuf]) -> Result<usize, String> {
+        let before = self.records.len();
+        let mut changed = false;
+        self.records.retain_mut(|r| {
+            if r.status != ConversionStatus::Success {
+                return true;
+            }
+            if r.olap_files.is_empty() {
+                let keep = !deleted.contains(&r.olap_parquet);
+                changed |= !keep;
+                return keep;
+            }
+            let files = r.olap_files.len();
+            r.olap_files.retain(|p| !deleted.contains(p));
+            changed |= r.olap_files.len() != files;
+            !r.olap_files.is_empty()
+        });
+
+        if changed {
+            self.save()?;
+        }
+        Ok(before - self.records.len())
+    }
+
     /// 获取统计信息
     pub fn get_stats(&self) -> ConversionStats {
         let mut stats = ConversionStats::default();
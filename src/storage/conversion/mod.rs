@@ -51,17 +51,46 @@
 // 3. 状态持久化：转换记录落盘
 // 4. 失败重试：指数退避（1s→2s→4s→8s）
 // 5. 源文件保护：转换完成前不删除
+//
+// K线转换（kline 模块）：K线 WAL 按水位增量转换为 instrument/period 分区的 Parquet，
+// 由独立线程定期执行，并按保留策略清理已转换的 WAL。
+//
+// 因子落盘（factor 模块）：因子 WAL 同样按水位转换为 factor/instrument 分区的 Parquet，
+// 并支持批量回填与按时间范围查询因子序列。
+//
+// 个人字段重写（anonymize 模块）：用户注销后一次性重写 OLAP Parquet 中的历史用户标识。
+//
+// 按月分区（partition 模块）：OLAP Parquet 按记录时间组织为 {instrument}/olap/{yyyymm}/，
+// 月龄超过阈值的分区定期以 ZSTD 高级别再压缩并标记为 cold，查询时按时间范围裁剪分区。
+//
+// 保留策略（retention 模块）：按数据类别（行情/成交/账户）与年龄定期清理过期的 SSTable 与
+// Parquet，跳过未完成转换仍引用的文件，删除前可选归档，删除操作写入审计日志。
 
+pub mod anonymize;
+pub mod factor;
+pub mod kline;
 pub mod metadata;
+pub mod partition;
+pub mod retention;
 pub mod scheduler;
 pub mod worker;
 
+pub use anonymize::{OlapAnonymizeReport, OlapAnonymizer};
+pub use factor::{
+    FactorConversionReport, FactorPoint, FactorStore, FactorStoreConfig, FactorValue,
+};
+pub use kline::{KLineConversionConfig, KLineConversionReport, KLineConverter};
 pub use metadata::{ConversionMetadata, ConversionRecord, ConversionStats, ConversionStatus};
+pub use partition::{OlapPartitionConfig, OlapPartitionStats, RecompressReport};
+pub use retention::{RetentionAuditEntry, RetentionClass, RetentionConfig, RetentionReport};
 pub use scheduler::{ConversionScheduler, ConversionTask, SchedulerConfig};
-pub use worker::{ConversionWorker, WorkerConfig, WorkerPool};
+pub use worker::{ConversionOutput, ConversionWorker, WorkerConfig, WorkerPool};
 
+use crate::notification::{NotificationBroker, SystemEvent, SystemEventCategory, SystemEventLevel};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 /// 转换系统管理器
 ///
@@ -71,6 +100,33 @@ pub struct ConversionManager {
     scheduler: Arc<ConversionScheduler>,
     worker_pool: Option<WorkerPool>,
     scheduler_handle: Option<std::thread::JoinHandle<()>>,
+    /// K线转换器（可选）
+    kline_converter: Option<Arc<KLineConverter>>,
+    /// 因子存储（可选）
+    factor_store: Option<Arc<FactorStore>>,
+    /// 月份分区冷数据配置
+    partition_config: OlapPartitionConfig,
+    /// 数据保留策略配置
+    retention_config: RetentionConfig,
+    /// K线/因子转换线程停止标志
+    kline_shutdown: Arc<AtomicBool>,
+    /// 通知路由中心（发布存储告警系统事件，可选）
+    notification_broker: Option<Arc<NotificationBroker>>,
+}
+
+/// 发布存储类系统事件（未配置通知路由中心时忽略）
+fn publish_storage_event(
+    broker: &Option<Arc<NotificationBroker>>,
+    level: SystemEventLevel,
+    title: &str,
+    details: serde_json::Value,
+) {
+    if let Some(broker) = broker {
+        broker.publish_system_event(
+            SystemEvent::new(SystemEventCategory::Storage, level, title).with_details(details),
+            "ConversionManager",
+        );
+    }
 }
 
 impl ConversionManager {
@@ -100,9 +156,79 @@ impl ConversionManager {
             scheduler,
             worker_pool: Some(worker_pool),
             scheduler_handle: None,
+            kline_converter: None,
+            factor_store: None,
+            partition_config: OlapPartitionConfig::default(),
+            retention_config: RetentionConfig::default(),
+            kline_shutdown: Arc::new(AtomicBool::new(false)),
+            notification_broker: None,
         })
     }
 
+    /// 启用 K线 WAL → Parquet 转换
+    pub fn with_kline_converter(mut self, converter: Arc<KLineConverter>) -> Self {
+        self.kline_converter = Some(converter);
+        self
+    }
+
+    /// K线转换器
+    pub fn kline_converter(&self) -> Option<&Arc<KLineConverter>> {
+        self.kline_converter.as_ref()
+    }
+
+    /// 启用因子 WAL → Parquet 转换
+    pub fn with_factor_store(mut self, store: Arc<FactorStore>) -> Self {
+        self.factor_store = Some(store);
+        self
+    }
+
+    /// 因子存储
+    pub fn factor_store(&self) -> Option<&Arc<FactorStore>> {
+        self.factor_store.as_ref()
+    }
+
+    /// 设置月份分区冷数据配置
+    pub fn with_partition_config(mut self, config: OlapPartitionConfig) -> Self {
+        self.partition_config = config;
+        self
+    }
+
+    /// 设置数据保留策略配置
+    pub fn with_retention_config(mut self, config: RetentionConfig) -> Self {
+        self.retention_config = config;
+        self
+    }
+
+    /// 设置通知路由中心（转换失败、保留策略清理结果作为系统事件发布）
+    pub fn with_notification_broker(mut self, broker: Arc<NotificationBroker>) -> Self {
+        self.notification_broker = Some(broker);
+        self
+    }
+
+    /// 立即执行一次保留策略，清理过期的 SSTable/Parquet（阻塞执行）
+    pub fn apply_retention(&self) -> RetentionReport {
+        retention::apply_retention(
+            &self.scheduler.storage_base_path,
+            &self.retention_config,
+            &self.metadata,
+            crate::utils::timestamp::now_nanos(),
+        )
+    }
+
+    /// 各合约月份分区统计（每月行数、大小、冷热状态）
+    pub fn olap_partition_stats(&self) -> Vec<OlapPartitionStats> {
+        partition::storage_partition_stats(&self.scheduler.storage_base_path)
+    }
+
+    /// 立即将月龄超过阈值的分区再压缩为 cold（阻塞执行）
+    pub fn compact_cold_partitions(&self) -> RecompressReport {
+        partition::compact_cold_partitions(
+            &self.scheduler.storage_base_path,
+            &self.partition_config,
+            &partition::current_month(),
+        )
+    }
+
     /// 启动转换系统
     pub fn start(&mut self) {
         log::info!("Starting conversion system...");
@@ -124,9 +250,132 @@ impl ConversionManager {
 
         self.scheduler_handle = Some(handle);
 
+        // 启动 K线转换线程
+        if let Some(converter) = self.kline_converter.clone() {
+            if converter.config().enabled {
+                let interval = converter.config().interval_secs;
+                let broker = self.notification_broker.clone();
+                self.spawn_periodic("kline-conversion", interval, move || {
+                    if let Err(e) = converter.convert_once() {
+                        log::error!("K-line conversion failed: {}", e);
+                        publish_storage_event(
+                            &broker,
+                            SystemEventLevel::Error,
+                            "K线转换失败",
+                            serde_json::json!({ "error": e }),
+                        );
+                    }
+                });
+            }
+        }
+
+        // 启动因子转换线程
+        if let Some(store) = self.factor_store.clone() {
+            if store.config().enabled {
+                let interval = store.config().interval_secs;
+                let broker = self.notification_broker.clone();
+                self.spawn_periodic("factor-conversion", interval, move || {
+                    if let Err(e) = store.convert_once() {
+                        log::error!("Factor conversion failed: {}", e);
+                        publish_storage_event(
+                            &broker,
+                            SystemEventLevel::Error,
+                            "因子转换失败",
+                            serde_json::json!({ "error": e }),
+                        );
+                    }
+                });
+            }
+        }
+
+        // 启动冷分区再压缩线程
+        if self.partition_config.cold_after_months > 0 {
+            let storage_base = self.scheduler.storage_base_path.clone();
+            let config = self.partition_config.clone();
+            let interval = config.compact_interval_secs;
+            let broker = self.notification_broker.clone();
+            self.spawn_periodic("olap-cold-compaction", interval, move || {
+                let report = partition::compact_cold_partitions(
+                    &storage_base,
+                    &config,
+                    &partition::current_month(),
+                );
+                if !report.failed.is_empty() {
+                    log::error!("OLAP cold compaction failed: {:?}", report.failed);
+                    publish_storage_event(
+                        &broker,
+                        SystemEventLevel::Warning,
+                        "冷分区再压缩失败",
+                        serde_json::json!({ "failed": report.failed }),
+                    );
+                }
+            });
+        }
+
+        // 启动保留策略清理线程
+        if self.retention_config.enabled {
+            let storage_base = self.scheduler.storage_base_path.clone();
+            let config = self.retention_config.clone();
+            let metadata = self.metadata.clone();
+            let interval = config.interval_secs;
+            let broker = self.notification_broker.clone();
+            self.spawn_periodic("storage-retention", interval, move || {
+                let report = retention::apply_retention(
+                    &storage_base,
+                    &config,
+                    &metadata,
+                    crate::utils::timestamp::now_nanos(),
+                );
+                if !report.failed.is_empty() {
+                    log::error!("Storage retention failed: {:?}", report.failed);
+                }
+                if report.files_deleted > 0 || !report.failed.is_empty() {
+                    let (level, title) = if report.failed.is_empty() {
+                        (SystemEventLevel::Info, "保留策略清理完成")
+                    } else {
+                        (SystemEventLevel::Error, "保留策略清理失败")
+                    };
+                    publish_storage_event(
+                        &broker,
+                        level,
+                        title,
+                        serde_json::json!({
+                            "files_deleted": report.files_deleted,
+                            "files_archived": report.files_archived,
+                            "bytes_reclaimed": report.bytes_reclaimed,
+                            "failed": report.failed,
+                        }),
+                    );
+                }
+            });
+        }
+
         log::info!("Conversion system started");
     }
 
+    /// 启动按固定间隔执行的转换线程（停止时最多延迟 1 秒退出）
+    fn spawn_periodic<F>(&self, name: &str, interval_secs: u64, task: F)
+    where
+        F: Fn() + Send + 'static,
+    {
+        let shutdown = self.kline_shutdown.clone();
+        let interval = interval_secs.max(1);
+        std::thread::Builder::new()
+            .name(name.to_string())
+            .spawn(move || {
+                while !shutdown.load(Ordering::Relaxed) {
+                    task();
+                    for _ in 0..interval {
+                        if shutdown.load(Ordering::Relaxed) {
+                            break;
+                        }
+                        std::thread::sleep(Duration::from_secs(1));
+                    }
+                }
+            })
+            .unwrap_or_else(|e| panic!("Failed to spawn {} thread: {}", name, e));
+    }
+
     /// 停止转换系统
     pub fn stop(mut self) {
         log::info!("Stopping conversion system...");
@@ -136,6 +385,9 @@ impl ConversionManager {
             pool.stop();
         }
 
+        // 停止 K线/因子转换线程
+        self.kline_shutdown.store(true, Ordering::Relaxed);
+
         // 调度器线程会在下次扫描后自然退出
         // 这里不主动中断，避免任务丢失
 
@@ -162,7 +414,9 @@ impl ConversionManager {
 
         std::fs::create_dir_all(&olap_dir).map_err(|e| format!("Create OLAP dir failed: {}", e))?;
 
-        let timestamp = chrono::Utc::now().format("%Y%m%d_%H%M%S").to_string();
+        let timestamp = crate::utils::time_service::now_local()
+            .format("%Y%m%d_%H%M%S")
+            .to_string();
         let olap_parquet = olap_dir.join(format!("manual_{}.parquet", timestamp));
 
         // 创建转换记录
@@ -0,0 +1,563 @@
+// OLAP 按月分区与冷数据再压缩 @yutiansut @quantaxis
+//
+// 目录结构：{storage_base}/{instrument}/olap/{yyyymm}/*.parquet
+// - 月份按记录时间戳（纳秒）在交易所时区下的自然月划分，ConversionWorker 按月拆分写入
+// - 早期未分区的 olap/*.parquet 仍然可读，不参与分区裁剪
+// - 月龄超过 `cold_after_months` 的分区用 ZSTD 高级别重写（临时文件 + 原子 rename，
+//   重写后重新读取校验行数），全部文件成功后写入 `.cold` 标记
+// - QueryEngine 按查询时间范围裁剪分区，命中 cold 分区时在响应中提示
+
+use crate::storage::sstable::olap_parquet::{ParquetSSTable, ParquetSSTableWriter};
+use crate::utils::time_service::time_service;
+use chrono::TimeZone;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// cold 分区标记文件
+pub const COLD_MARKER: &str = ".cold";
+
+fn default_cold_after_months() -> u32 {
+    12
+}
+
+fn default_compact_interval_secs() -> u64 {
+    6 * 3600
+}
+
+/// OLAP 分区配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OlapPartitionConfig {
+    /// 分区月龄达到该值（月）后再压缩并标记为 cold，0 表示不做冷数据处理
+    #[serde(default = "default_cold_after_months")]
+    pub cold_after_months: u32,
+    /// 冷分区检查间隔（秒）
+    #[serde(default = "default_compact_interval_secs")]
+    pub compact_interval_secs: u64,
+}
+
+impl Default for OlapPartitionConfig {
+    fn default() -> Self {
+        Self {
+            cold_after_months: default_cold_after_months(),
+            compact_interval_secs: default_compact_interval_secs(),
+        }
+    }
+}
+
+/// 时间戳（纳秒）所属的月份分区（yyyymm，交易所时区）
+pub fn month_of(timestamp_ns: i64) -> String {
+    time_service()
+        .to_local_nanos(timestamp_ns)
+        .format("%Y%m")
+        .to_string()
+}
+
+/// 当前月份（yyyymm，交易所时区）
+pub fn current_month() -> String {
+    time_service().now().format("%Y%m").to_string()
+}
+
+fn parse_month(month: &str) -> Option<(i32, u32)> {
+    if month.len() != 6 || !month.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let year = month[..4].parse().ok()?;
+    let month = month[4..].parse().ok()?;
+    (1..=12).contains(&month).then_some((year, month))
+}
+
+/// 月份分区覆盖的时间范围 [start, end)（纳秒），分区名非法时返回 None
+pub fn month_range(month: &str) -> Option<(i64, i64)> {
+    let (year, month) = parse_month(month)?;
+    let (next_year, next_month) = if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    };
+    let offset = time_service().offset();
+    let start = offset.with_ymd_and_hms(year, month, 1, 0, 0, 0).single()?;
+    let end = offset
+        .with_ymd_and_hms(next_year, next_month, 1, 0, 0, 0)
+        .single()?;
+    Some((start.timestamp_nanos_opt()?, end.timestamp_nanos_opt()?))
+}
+
+/// 两个月份分区之间相差的月数（`now` - `month`）
+fn months_between(month: &str, now: &str) -> Option<i64> {
+    let index = |(year, month): (i32, u32)| year as i64 * 12 + month as i64 - 1;
+    Some(index(parse_month(now)?) - index(parse_month(month)?))
+}
+
+/// 月份分区目录
+pub fn partition_dir(olap_dir: &Path, month: &str) -> PathBuf {
+    olap_dir.join(month)
+}
+
+/// 分区是否已标记为 cold
+pub fn is_cold(partition_dir: &Path) -> bool {
+    partition_dir.join(COLD_MARKER).exists()
+}
+
+fn list_parquet(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "parquet"))
+        .collect();
+    files.sort();
+    files
+}
+
+/// 合约 OLAP 目录下的月份分区 (月份, 目录)，按月份升序
+pub fn list_partitions(olap_dir: &Path) -> Vec<(String, PathBuf)> {
+    let Ok(entries) = std::fs::read_dir(olap_dir) else {
+        return Vec::new();
+    };
+    let mut partitions: Vec<(String, PathBuf)> = entries
+        .flatten()
+        .filter(|e| e.path().is_dir())
+        .filter_map(|e| {
+            let name = e.file_name().to_str()?.to_string();
+            parse_month(&name).map(|_| (name, e.path()))
+        })
+        .collect();
+    partitions.sort();
+    partitions
+}
+
+/// 合约 OLAP 目录下的全部 Parquet 文件（未分区的早期文件 + 各月份分区）
+pub fn list_olap_parquet_files(olap_dir: &Path) -> Vec<PathBuf> {
+    let mut files = list_parquet(olap_dir);
+    for (_, dir) in list_partitions(olap_dir) {
+        files.extend(list_parquet(&dir));
+    }
+    files
+}
+
+/// 合约名（OLAP 目录的上级目录名）
+fn instrument_of(olap_dir: &Path) -> String {
+    olap_dir
+        .parent()
+        .and_then(|p| p.file_name())
+        .and_then(|n| n.to_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// 分区裁剪结果
+#[derive(Debug, Clone, Default)]
+pub struct PrunedFiles {
+    /// 需要扫描的文件
+    pub files: Vec<PathBuf>,
+    /// 命中的 cold 分区（{instrument}/{yyyymm}）
+    pub cold_partitions: Vec<String>,
+    /// 被裁剪掉的分区数
+    pub pruned_partitions: usize,
+}
+
+/// 按时间范围（纳秒，闭区间）裁剪分区，未指定范围时返回全部分区
+///
+/// 未分区的早期文件无法按目录判断时间范围，总是保留
+pub fn prune_partitions(olap_dir: &Path, time_range: Option<(i64, i64)>) -> PrunedFiles {
+    let mut result = PrunedFiles {
+        files: list_parquet(olap_dir),
+        ..Default::default()
+    };
+    let instrument = instrument_of(olap_dir);
+
+    for (month, dir) in list_partitions(olap_dir) {
+        let overlaps = match (time_range, month_range(&month)) {
+            (Some((start, end)), Some((month_start, month_end))) => {
+                start < month_end && end >= month_start
+            }
+            _ => true,
+        };
+        if !overlaps {
+            result.pruned_partitions += 1;
+            continue;
+        }
+        if is_cold(&dir) {
+            result
+                .cold_partitions
+                .push(format!("{}/{}", instrument, month));
+        }
+        result.files.extend(list_parquet(&dir));
+    }
+    result
+}
+
+/// 分区统计
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OlapPartitionStats {
+    pub instrument_id: String,
+    /// 月份（yyyymm）
+    pub month: String,
+    pub files: usize,
+    pub rows: u64,
+    pub size_bytes: u64,
+    pub cold: bool,
+}
+
+/// 合约 OLAP 目录下各月份分区的统计
+pub fn partition_stats(olap_dir: &Path) -> Result<Vec<OlapPartitionStats>, String> {
+    let instrument_id = instrument_of(olap_dir);
+    list_partitions(olap_dir)
+        .into_iter()
+        .map(|(month, dir)| {
+            let files = list_parquet(&dir);
+            let mut rows = 0u64;
+            let mut size_bytes = 0u64;
+            for path in &files {
+                rows += ParquetSSTable::open(path)?.metadata().entry_count;
+                size_bytes += std::fs::metadata(path)
+                    .map_err(|e| format!("Stat {:?} failed: {}", path, e))?
+                    .len();
+            }
+            Ok(OlapPartitionStats {
+                instrument_id: instrument_id.clone(),
+                cold: is_cold(&dir),
+                month,
+                files: files.len(),
+                rows,
+                size_bytes,
+            })
+        })
+        .collect()
+}
+
+/// 合约 OLAP 目录（{storage_base}/{instrument}/olap），按合约排序
+fn instrument_olap_dirs(storage_base: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(storage_base) else {
+        return Vec::new();
+    };
+    let mut dirs: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path().join("olap"))
+        .filter(|dir| dir.is_dir())
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+/// 存储根目录下所有合约的分区统计（按合约、月份排序）
+pub fn storage_partition_stats(storage_base: &Path) -> Vec<OlapPartitionStats> {
+    instrument_olap_dirs(storage_base)
+        .iter()
+        .flat_map(|dir| {
+            partition_stats(dir).unwrap_or_else(|e| {
+                log::warn!("Read OLAP partition stats of {:?} failed: {}", dir, e);
+                Vec::new()
+            })
+        })
+        .collect()
+}
+
+/// 再压缩报告
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RecompressReport {
+    /// 标记为 cold 的分区（{instrument}/{yyyymm}）
+    pub partitions: Vec<String>,
+    pub files_rewritten: usize,
+    pub rows: u64,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+    /// 重写失败的分区及原因（未标记 cold，下次重试）
+    pub failed: Vec<String>,
+}
+
+impl RecompressReport {
+    fn merge(&mut self, other: RecompressReport) {
+        self.partitions.extend(other.partitions);
+        self.files_rewritten += other.files_rewritten;
+        self.rows += other.rows;
+        self.bytes_before += other.bytes_before;
+        self.bytes_after += other.bytes_after;
+        self.failed.extend(other.failed);
+    }
+}
+
+fn file_size(path: &Path) -> Result<u64, String> {
+    std::fs::metadata(path)
+        .map(|m| m.len())
+        .map_err(|e| format!("Stat {:?} failed: {}", path, e))
+}
+
+/// 以归档压缩（ZSTD 高级别）重写单个 Parquet 文件，返回 (行数, 原大小, 新大小)
+///
+/// 重写结果重新读取一遍，行数与原文件一致才替换原文件
+pub fn recompress_file(path: &Path) -> Result<(u64, u64, u64), String> {
+    let bytes_before = file_size(path)?;
+    let (schema, expected_rows, chunks) = {
+        let sstable = ParquetSSTable::open(path)?;
+        (
+            sstable.schema().clone(),
+            sstable.metadata().entry_count,
+            sstable.scan()?,
+        )
+    };
+
+    let tmp_path = path.with_extension("parquet.recompress.tmp");
+    let result = (|| {
+        let mut writer = ParquetSSTableWriter::create_archive(&tmp_path, Arc::new(schema))?;
+        for chunk in &chunks {
+            writer.write_chunk(chunk)?;
+        }
+        writer.finish()?;
+
+        let rows: u64 = ParquetSSTable::open(&tmp_path)?
+            .scan()?
+            .iter()
+            .map(|chunk| chunk.len() as u64)
+            .sum();
+        if rows != expected_rows {
+            return Err(format!(
+                "Row count mismatch after recompress: expected {}, got {}",
+                expected_rows, rows
+            ));
+        }
+
+        std::fs::rename(&tmp_path, path).map_err(|e| format!("Rename parquet file failed: {}", e))
+    })();
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&tmp_path);
+        return Err(e);
+    }
+
+    Ok((expected_rows, bytes_before, file_size(path)?))
+}
+
+/// 再压缩分区内所有文件并写入 cold 标记（任一文件失败则不标记）
+pub fn recompress_partition(partition_dir: &Path) -> Result<RecompressReport, String> {
+    let mut report = RecompressReport::default();
+    for path in list_parquet(partition_dir) {
+        let (rows, before, after) = recompress_file(&path)?;
+        report.files_rewritten += 1;
+        report.rows += rows;
+        report.bytes_before += before;
+        report.bytes_after += after;
+    }
+
+    let marker = serde_json::json!({
+        "recompressed_at": chrono::Utc::now().timestamp_millis(),
+        "compression": "zstd-9",
+        "files": report.files_rewritten,
+        "rows": report.rows,
+    });
+    std::fs::write(partition_dir.join(COLD_MARKER), marker.to_string())
+        .map_err(|e| format!("Write cold marker failed: {}", e))?;
+    Ok(report)
+}
+
+/// 将月龄达到 `cold_after_months` 的热分区再压缩为 cold（`now_month` 为当前月份 yyyymm）
+pub fn compact_cold_partitions(
+    storage_base: &Path,
+    config: &OlapPartitionConfig,
+    now_month: &str,
+) -> RecompressReport {
+    let mut report = RecompressReport::default();
+    if config.cold_after_months == 0 {
+        return report;
+    }
+
+    for olap_dir in instrument_olap_dirs(storage_base) {
+        let instrument = instrument_of(&olap_dir);
+        for (month, dir) in list_partitions(&olap_dir) {
+            let age = months_between(&month, now_month).unwrap_or(0);
+            if age < config.cold_after_months as i64 || is_cold(&dir) {
+                continue;
+            }
+
+            let label = format!("{}/{}", instrument, month);
+            match recompress_partition(&dir) {
+                Ok(partition_report) => {
+                    log::info!(
+                        "OLAP partition {} marked cold: {} files, {} rows, {} -> {} bytes",
+                        label,
+                        partition_report.files_rewritten,
+                        partition_report.rows,
+                        partition_report.bytes_before,
+                        partition_report.bytes_after
+                    );
+                    report.merge(partition_report);
+                    report.partitions.push(label);
+                }
+                Err(e) => {
+                    log::error!("Recompress OLAP partition {} failed: {}", label, e);
+                    report.failed.push(format!("{}: {}", label, e));
+                }
+            }
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memtable::olap::{create_olap_schema, OlapMemTable};
+    use crate::storage::memtable::types::MemTableKey;
+    use crate::storage::wal::record::WalRecord;
+    use tempfile::tempdir;
+
+    /// 北京时间对应的 UTC 纳秒时间戳
+    fn cst_nanos(y: i32, m: u32, d: u32, h: u32, mi: u32, s: u32) -> i64 {
+        chrono::FixedOffset::east_opt(8 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(y, m, d, h, mi, s)
+            .unwrap()
+            .timestamp_nanos_opt()
+            .unwrap()
+    }
+
+    fn write_parquet(path: &Path, timestamps: &[i64]) {
+        let records = timestamps
+            .iter()
+            .enumerate()
+            .map(|(i, ts)| {
+                (
+                    MemTableKey::new(*ts, i as u64),
+                    WalRecord::OrderInsert {
+                        order_id: i as u64,
+                        user_id: [1u8; 32],
+                        instrument_id: WalRecord::to_fixed_array_16("IF2501"),
+                        direction: 0,
+                        offset: 0,
+                        price: 3800.0 + i as f64,
+                        volume: 1.0,
+                        timestamp: *ts,
+                    },
+                )
+            })
+            .collect();
+        let memtable = OlapMemTable::from_records(records);
+        let mut writer =
+            ParquetSSTableWriter::create(path, Arc::new(create_olap_schema())).unwrap();
+        writer.write_chunk(memtable.chunk()).unwrap();
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn test_month_boundaries_in_exchange_timezone() {
+        // 北京时间 1 月 31 日 23:59:59 与 2 月 1 日 00:00:00（UTC 仍为 1 月 31 日）
+        let last_jan = cst_nanos(2025, 1, 31, 23, 59, 59);
+        let first_feb = cst_nanos(2025, 2, 1, 0, 0, 0);
+        assert_eq!(month_of(last_jan), "202501");
+        assert_eq!(month_of(first_feb), "202502");
+
+        let (start, end) = month_range("202502").unwrap();
+        assert_eq!(start, first_feb);
+        assert_eq!(end, cst_nanos(2025, 3, 1, 0, 0, 0));
+        assert_eq!(
+            month_range("202412").unwrap().1,
+            cst_nanos(2025, 1, 1, 0, 0, 0)
+        );
+        assert!(month_range("202513").is_none());
+        assert!(month_range("klines").is_none());
+
+        assert_eq!(months_between("202412", "202501"), Some(1));
+        assert_eq!(months_between("202401", "202501"), Some(12));
+    }
+
+    #[test]
+    fn test_prune_partitions_by_time_range() {
+        let base = tempdir().unwrap();
+        let olap_dir = base.path().join("IF2501").join("olap");
+        for month in ["202411", "202412", "202501"] {
+            std::fs::create_dir_all(olap_dir.join(month)).unwrap();
+        }
+        write_parquet(
+            &olap_dir.join("202411").join("batch_1.parquet"),
+            &[cst_nanos(2024, 11, 15, 10, 0, 0)],
+        );
+        write_parquet(
+            &olap_dir.join("202412").join("batch_1.parquet"),
+            &[cst_nanos(2024, 12, 31, 14, 0, 0)],
+        );
+        write_parquet(
+            &olap_dir.join("202501").join("batch_1.parquet"),
+            &[cst_nanos(2025, 1, 2, 9, 0, 0)],
+        );
+        // 早期未分区文件
+        write_parquet(&olap_dir.join("legacy.parquet"), &[1]);
+        std::fs::write(olap_dir.join("202412").join(COLD_MARKER), "{}").unwrap();
+
+        assert_eq!(list_olap_parquet_files(&olap_dir).len(), 4);
+
+        let range = (
+            cst_nanos(2024, 12, 31, 0, 0, 0),
+            cst_nanos(2025, 1, 1, 0, 0, 0),
+        );
+        let pruned = prune_partitions(&olap_dir, Some(range));
+        assert_eq!(pruned.pruned_partitions, 1);
+        assert_eq!(pruned.files.len(), 3);
+        assert!(pruned.files[0].ends_with("legacy.parquet"));
+        assert_eq!(pruned.cold_partitions, vec!["IF2501/202412".to_string()]);
+
+        let all = prune_partitions(&olap_dir, None);
+        assert_eq!(all.pruned_partitions, 0);
+        assert_eq!(all.files.len(), 4);
+    }
+
+    #[test]
+    fn test_compact_cold_partitions_keeps_rows_readable() {
+        let base = tempdir().unwrap();
+        let olap_dir = base.path().join("IF2501").join("olap");
+        let old_dir = olap_dir.join("202301");
+        let recent_dir = olap_dir.join("202412");
+        std::fs::create_dir_all(&old_dir).unwrap();
+        std::fs::create_dir_all(&recent_dir).unwrap();
+
+        let old_ts: Vec<i64> = (0..500)
+            .map(|i| cst_nanos(2023, 1, 10, 9, 0, 0) + i * 1_000_000)
+            .collect();
+        write_parquet(&old_dir.join("batch_1.parquet"), &old_ts[..300]);
+        write_parquet(&old_dir.join("batch_2.parquet"), &old_ts[300..]);
+        write_parquet(
+            &recent_dir.join("batch_1.parquet"),
+            &[cst_nanos(2024, 12, 1, 9, 0, 0)],
+        );
+
+        let config = OlapPartitionConfig {
+            cold_after_months: 12,
+            ..Default::default()
+        };
+        let report = compact_cold_partitions(base.path(), &config, "202501");
+        assert!(report.failed.is_empty());
+        assert_eq!(report.partitions, vec!["IF2501/202301".to_string()]);
+        assert_eq!(report.files_rewritten, 2);
+        assert_eq!(report.rows, 500);
+
+        // 再压缩后仍可读，行数与时间范围不变
+        let files = list_parquet(&old_dir);
+        assert_eq!(files.len(), 2);
+        let rows: usize = files
+            .iter()
+            .flat_map(|path| ParquetSSTable::open(path).unwrap().scan().unwrap())
+            .map(|chunk| chunk.len())
+            .sum();
+        assert_eq!(rows, 500);
+        let sstable = ParquetSSTable::open(&files[0]).unwrap();
+        assert_eq!(sstable.metadata().min_timestamp, old_ts[0]);
+
+        let stats = partition_stats(&olap_dir).unwrap();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(
+            (stats[0].month.as_str(), stats[0].rows, stats[0].cold),
+            ("202301", 500, true)
+        );
+        assert_eq!(
+            (stats[1].month.as_str(), stats[1].rows, stats[1].cold),
+            ("202412", 1, false)
+        );
+        assert_eq!(storage_partition_stats(base.path()), stats);
+
+        // 已标记 cold 的分区不再重复处理，也不残留临时文件
+        let again = compact_cold_partitions(base.path(), &config, "202501");
+        assert!(again.partitions.is_empty());
+        assert_eq!(std::fs::read_dir(&old_dir).unwrap().count(), 3);
+    }
+}
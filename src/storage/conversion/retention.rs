@@ -0,0 +1,763 @@
+// 存储数据保留策略（Retention Policy） @yutiansut @quantaxis
+//
+// 按数据类别与年龄清理过期的 OLTP SSTable / OLAP Parquet：
+// - 行情类（Tick/盘口/K线/因子）保留 `market_days` 天；成交类（订单/成交/回报）与
+//   账户类（账户/用户/持仓快照）分别按 `trade_days` / `account_days` 保留，0 表示永久保留
+// - 文件内可能混合多种记录，只有文件中出现的每一类数据都已过期（按文件最大时间戳）才删除；
+//   无法识别类别的文件（如非 OLAP Schema 的 Parquet）一律保留
+// - 未完成的转换记录（Pending/Converting/Failed）仍引用的文件不删除
+// - 配置了归档目录时先按相对路径移动到归档目录，再从存储中移除
+// - 每次删除追加一行 JSON 到审计日志，并同步清理转换元数据与空的月份分区目录
+//
+// 扫描范围：{storage_base}/{storage_id}/{sstables,olap} 以及分区模式下的
+// {storage_base}/{storage_id}/partitions/{instrument}/{sstables,olap}
+
+use super::metadata::ConversionMetadata;
+use super::partition::{list_olap_parquet_files, month_range, COLD_MARKER};
+use crate::storage::hybrid::partition::PARTITIONS_DIR;
+use crate::storage::sstable::olap_parquet::ParquetSSTable;
+use crate::storage::sstable::oltp_rkyv::RkyvSSTable;
+use crate::storage::wal::record::WalRecord;
+use crate::utils::timestamp::secs_to_nanos;
+use arrow2::array::PrimitiveArray;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// 默认审计日志文件名（位于存储根目录）
+pub const RETENTION_AUDIT_LOG: &str = "retention_audit.jsonl";
+
+/// OLAP record_type 列名
+const RECORD_TYPE_COLUMN: &str = "record_type";
+
+const SECS_PER_DAY: i64 = 86_400;
+
+fn default_interval_secs() -> u64 {
+    3600
+}
+
+fn default_market_days() -> u32 {
+    30
+}
+
+/// 保留策略配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionConfig {
+    /// 是否启用定期清理
+    #[serde(default)]
+    pub enabled: bool,
+    /// 扫描间隔（秒）
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+    /// 行情类数据保留天数（0 表示永久保留）
+    #[serde(default = "default_market_days")]
+    pub market_days: u32,
+    /// 成交类数据保留天数（0 表示永久保留）
+    #[serde(default)]
+    pub trade_days: u32,
+    /// 账户类数据保留天数（0 表示永久保留）
+    #[serde(default)]
+    pub account_days: u32,
+    /// 归档目录（为空时直接删除）
+    #[serde(default)]
+    pub archive_dir: String,
+    /// 审计日志路径（为空时使用 {storage_base}/retention_audit.jsonl）
+    #[serde(default)]
+    pub audit_log: String,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_secs: default_interval_secs(),
+            market_days: default_market_days(),
+            trade_days: 0,
+            account_days: 0,
+            archive_dir: String::new(),
+            audit_log: String::new(),
+        }
+    }
+}
+
+impl RetentionConfig {
+    /// 类别对应的保留天数（0 表示永久保留）
+    pub fn days(&self, class: RetentionClass) -> u32 {
+        match class {
+            RetentionClass::Market => self.market_days,
+            RetentionClass::Trade => self.trade_days,
+            RetentionClass::Account => self.account_days,
+            RetentionClass::Unknown => 0,
+        }
+    }
+
+    /// 类别的过期边界（纳秒，最大时间戳早于该值即过期），永久保留时返回 None
+    fn cutoff(&self, class: RetentionClass, now_ns: i64) -> Option<i64> {
+        match self.days(class) {
+            0 => None,
+            days => Some(now_ns - secs_to_nanos(days as i64 * SECS_PER_DAY)),
+        }
+    }
+
+    /// 最短的有限保留天数，全部永久保留时返回 None
+    fn min_days(&self) -> Option<u32> {
+        [self.market_days, self.trade_days, self.account_days]
+            .into_iter()
+            .filter(|days| *days > 0)
+            .min()
+    }
+
+    /// 审计日志路径
+    pub fn audit_log_path(&self, storage_base: &Path) -> PathBuf {
+        if self.audit_log.is_empty() {
+            storage_base.join(RETENTION_AUDIT_LOG)
+        } else {
+            PathBuf::from(&self.audit_log)
+        }
+    }
+}
+
+/// 数据保留类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetentionClass {
+    /// 行情：Tick / 盘口 / K线 / 因子
+    Market,
+    /// 成交：订单 / 成交 / 交易所回报
+    Trade,
+    /// 账户：账户 / 用户 / 持仓与风控快照
+    Account,
+    /// 无法识别（永久保留）
+    Unknown,
+}
+
+impl RetentionClass {
+    /// WAL 记录所属类别（Checkpoint 不携带业务数据，返回 None）
+    pub fn of_record(record: &WalRecord) -> Option<Self> {
+        match record {
+            WalRecord::TickData { .. }
+            | WalRecord::OrderBookSnapshot { .. }
+            | WalRecord::OrderBookDelta { .. }
+            | WalRecord::KLineFinished { .. }
+            | WalRecord::FactorUpdate { .. }
+            | WalRecord::FactorSnapshot { .. } => Some(Self::Market),
+            WalRecord::OrderInsert { .. }
+            | WalRecord::TradeExecuted { .. }
+            | WalRecord::TradeExecutedV2 { .. }
+            | WalRecord::OrderStatusUpdate { .. }
+            | WalRecord::ExchangeOrderRecord { .. }
+            | WalRecord::ExchangeTradeRecord { .. }
+            | WalRecord::ExchangeResponseRecord { .. } => Some(Self::Trade),
+            WalRecord::AccountOpen { .. }
+            | WalRecord::AccountUpdate { .. }
+            | WalRecord::AccountUpdateV2 { .. }
+            | WalRecord::UserRegister { .. }
+            | WalRecord::AccountBind { .. }
+            | WalRecord::UserRoleUpdate { .. }
+            | WalRecord::PositionSnapshot { .. }
+            | WalRecord::AccountSnapshot { .. }
+            | WalRecord::RiskSnapshot { .. }
+            | WalRecord::UserAnonymize { .. }
+            | WalRecord::UserNotification { .. }
+            | WalRecord::NotificationRead { .. }
+            | WalRecord::UserPasswordUpdate { .. } => Some(Self::Account),
+            WalRecord::Checkpoint { .. } => None,
+        }
+    }
+
+    /// OLAP record_type 所属类别（与 OlapMemTable 的类型 ID 对应）
+    pub fn of_record_type(record_type: u8) -> Option<Self> {
+        match record_type {
+            5 | 6 | 7 | 13 | 14 => Some(Self::Market),
+            0 | 1 | 10 | 11 | 12 => Some(Self::Trade),
+            2 | 4 | 8 | 9 | 15 => Some(Self::Account),
+            3 => None,
+            _ => Some(Self::Unknown),
+        }
+    }
+}
+
+/// 存储文件类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetentionFileKind {
+    /// OLTP rkyv SSTable（.sst）
+    Sstable,
+    /// OLAP Parquet
+    Parquet,
+}
+
+/// 文件内容概况
+#[derive(Debug, Clone)]
+struct FileProfile {
+    rows: u64,
+    max_timestamp: i64,
+    classes: BTreeSet<RetentionClass>,
+}
+
+/// 审计日志条目（JSON Lines）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionAuditEntry {
+    /// 删除时间（Unix 毫秒）
+    pub timestamp: i64,
+    pub path: String,
+    pub kind: RetentionFileKind,
+    pub classes: Vec<RetentionClass>,
+    /// 文件内最大记录时间戳（纳秒）
+    pub max_timestamp: i64,
+    pub rows: u64,
+    pub bytes: u64,
+    /// 归档位置（未归档时为 None）
+    pub archived_to: Option<String>,
+}
+
+/// 保留策略执行报告
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RetentionReport {
+    pub files_scanned: usize,
+    pub files_deleted: usize,
+    pub files_archived: usize,
+    pub rows_deleted: u64,
+    pub bytes_reclaimed: u64,
+    /// 已过期但仍被未完成转换引用而跳过的文件数
+    pub skipped_referenced: usize,
+    /// 随文件删除而移除的转换记录数
+    pub metadata_records_removed: usize,
+    /// 已删除的文件
+    pub deleted: Vec<String>,
+    /// 处理失败的文件及原因
+    pub failed: Vec<String>,
+}
+
+/// 存储根目录下的各存储目录（含分区模式下的合约分区目录）
+fn storage_roots(storage_base: &Path) -> Vec<PathBuf> {
+    let list_dirs = |dir: &Path| -> Vec<PathBuf> {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let mut dirs: Vec<PathBuf> = entries
+            .flatten()
+            .map(|e| e.path())
+            .filter(|path| path.is_dir())
+            .collect();
+        dirs.sort();
+        dirs
+    };
+
+    let mut roots = Vec::new();
+    for root in list_dirs(storage_base) {
+        let partitions = list_dirs(&root.join(PARTITIONS_DIR));
+        roots.push(root);
+        roots.extend(partitions);
+    }
+    roots
+}
+
+/// 存储目录下参与保留策略的文件
+fn storage_files(root: &Path) -> Vec<(PathBuf, RetentionFileKind)> {
+    let mut sstables: Vec<PathBuf> = std::fs::read_dir(root.join("sstables"))
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|e| e.path())
+                .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "sst"))
+                .collect()
+        })
+        .unwrap_or_default();
+    sstables.sort();
+
+    sstables
+        .into_iter()
+        .map(|path| (path, RetentionFileKind::Sstable))
+        .chain(
+            list_olap_parquet_files(&root.join("olap"))
+                .into_iter()
+                .map(|path| (path, RetentionFileKind::Parquet)),
+        )
+        .collect()
+}
+
+fn max_timestamp_of(path: &Path, kind: RetentionFileKind) -> Result<(u64, i64), String> {
+    let metadata = match kind {
+        RetentionFileKind::Sstable => RkyvSSTable::open(path)?.metadata().clone(),
+        RetentionFileKind::Parquet => ParquetSSTable::open(path)?.metadata().clone(),
+    };
+    Ok((metadata.entry_count, metadata.max_timestamp))
+}
+
+/// 读取文件中出现的数据类别
+fn classes_of(path: &Path, kind: RetentionFileKind) -> Result<BTreeSet<RetentionClass>, String> {
+    let mut classes = BTreeSet::new();
+    match kind {
+        RetentionFileKind::Sstable => {
+            for (_, _, record) in RkyvSSTable::open(path)?.range_query(i64::MIN, i64::MAX)? {
+                classes.extend(RetentionClass::of_record(&record));
+            }
+        }
+        RetentionFileKind::Parquet => {
+            let sstable = ParquetSSTable::open(path)?;
+            let Some(column) = sstable
+                .schema()
+                .fields
+                .iter()
+                .position(|f| f.name == RECORD_TYPE_COLUMN)
+            else {
+                classes.insert(RetentionClass::Unknown);
+                return Ok(classes);
+            };
+            for chunk in sstable.scan()? {
+                let record_types = chunk.arrays()[column]
+                    .as_any()
+                    .downcast_ref::<PrimitiveArray<u8>>()
+                    .ok_or_else(|| format!("Column {} is not UInt8", RECORD_TYPE_COLUMN))?;
+                for record_type in record_types.values_iter() {
+                    classes.extend(RetentionClass::of_record_type(*record_type));
+                }
+            }
+        }
+    }
+    Ok(classes)
+}
+
+/// 文件中的每一类数据都已过期时才可删除（不含任何可识别数据的文件保留）
+pub fn is_expired(
+    config: &RetentionConfig,
+    classes: &BTreeSet<RetentionClass>,
+    max_timestamp: i64,
+    now_ns: i64,
+) -> bool {
+    !classes.is_empty()
+        && classes.iter().all(|class| {
+            config
+                .cutoff(*class, now_ns)
+                .is_some_and(|cutoff| max_timestamp < cutoff)
+        })
+}
+
+/// 读取文件概况，最新数据未超过最短保留期的文件不扫描内容，返回 None
+fn inspect_file(
+    path: &Path,
+    kind: RetentionFileKind,
+    min_cutoff: i64,
+) -> Result<Option<FileProfile>, String> {
+    let (rows, max_timestamp) = max_timestamp_of(path, kind)?;
+    if max_timestamp >= min_cutoff {
+        return Ok(None);
+    }
+    Ok(Some(FileProfile {
+        rows,
+        max_timestamp,
+        classes: classes_of(path, kind)?,
+    }))
+}
+
+/// 移动文件到归档目录（保持相对存储根目录的路径），跨文件系统时退化为复制后删除
+fn archive_file(path: &Path, storage_base: &Path, archive_dir: &Path) -> Result<PathBuf, String> {
+    let relative = path.strip_prefix(storage_base).unwrap_or(path);
+    let target = archive_dir.join(relative.strip_prefix("/").unwrap_or(relative));
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Create archive dir {:?} failed: {}", parent, e))?;
+    }
+    if std::fs::rename(path, &target).is_err() {
+        std::fs::copy(path, &target)
+            .map_err(|e| format!("Copy {:?} to archive failed: {}", path, e))?;
+        std::fs::remove_file(path).map_err(|e| format!("Remove {:?} failed: {}", path, e))?;
+    }
+    Ok(target)
+}
+
+/// 追加审计日志
+fn append_audit(audit_log: &Path, entry: &RetentionAuditEntry) -> Result<(), String> {
+    if let Some(parent) = audit_log.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Create audit log dir failed: {}", e))?;
+    }
+    let line =
+        serde_json::to_string(entry).map_err(|e| format!("Serialize audit entry failed: {}", e))?;
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(audit_log)
+        .map_err(|e| format!("Open audit log {:?} failed: {}", audit_log, e))?;
+    writeln!(file, "{}", line).map_err(|e| format!("Write audit log failed: {}", e))
+}
+
+/// 删除文件后月份分区目录已无数据文件时一并移除
+fn remove_empty_partition(path: &Path) {
+    let Some(dir) = path.parent() else {
+        return;
+    };
+    let is_month = dir
+        .file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|name| month_range(name).is_some());
+    if !is_month {
+        return;
+    }
+    let empty = std::fs::read_dir(dir)
+        .map(|entries| entries.flatten().all(|e| e.file_name() == COLD_MARKER))
+        .unwrap_or(false);
+    if empty {
+        if let Err(e) = std::fs::remove_dir_all(dir) {
+            log::warn!("Remove empty OLAP partition {:?} failed: {}", dir, e);
+        }
+    }
+}
+
+/// 执行一次保留策略：删除（或归档）过期文件、写审计日志并更新转换元数据
+pub fn apply_retention(
+    storage_base: &Path,
+    config: &RetentionConfig,
+    metadata: &Mutex<ConversionMetadata>,
+    now_ns: i64,
+) -> RetentionReport {
+    let mut report = RetentionReport::default();
+    let Some(min_days) = config.min_days() else {
+        return report;
+    };
+    let min_cutoff = now_ns - secs_to_nanos(min_days as i64 * SECS_PER_DAY);
+    let audit_log = config.audit_log_path(storage_base);
+    let archive_dir = (!config.archive_dir.is_empty()).then(|| PathBuf::from(&config.archive_dir));
+    let mut removed_files = Vec::new();
+
+    for root in storage_roots(storage_base) {
+        for (path, kind) in storage_files(&root) {
+            report.files_scanned += 1;
+            let profile = match inspect_file(&path, kind, min_cutoff) {
+                Ok(Some(profile)) => profile,
+                Ok(None) => continue,
+                Err(e) => {
+                    log::warn!("Retention inspect {:?} failed: {}", path, e);
+                    report.failed.push(format!("{}: {}", path.display(), e));
+                    continue;
+                }
+            };
+            if !is_expired(config, &profile.classes, profile.max_timestamp, now_ns) {
+                continue;
+            }
+
+            // 持有元数据锁完成引用检查与删除，避免与新建的转换任务交错
+            let guard = metadata.lock().unwrap();
+            if guard.is_referenced(&path) {
+                report.skipped_referenced += 1;
+                continue;
+            }
+            let bytes = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+            let removed = match archive_dir {
+                Some(ref dir) => archive_file(&path, storage_base, dir).map(Some),
+                None => std::fs::remove_file(&path)
+                    .map(|_| None)
+                    .map_err(|e| format!("Remove failed: {}", e)),
+            };
+            drop(guard);
+
+            let archived_to = match removed {
+                Ok(archived_to) => archived_to,
+                Err(e) => {
+                    log::error!("Retention remove {:?} failed: {}", path, e);
+                    report.failed.push(format!("{}: {}", path.display(), e));
+                    continue;
+                }
+            };
+
+            let entry = RetentionAuditEntry {
+                timestamp: chrono::Utc::now().timestamp_millis(),
+                path: path.display().to_string(),
+                kind,
+                classes: profile.classes.into_iter().collect(),
+                max_timestamp: profile.max_timestamp,
+                rows: profile.rows,
+                bytes,
+                archived_to: archived_to.as_ref().map(|p| p.display().to_string()),
+            };
+            if let Err(e) = append_audit(&audit_log, &entry) {
+                log::error!("Retention audit for {:?} failed: {}", path, e);
+                report.failed.push(format!("{}: {}", path.display(), e));
+            }
+
+            if kind == RetentionFileKind::Parquet {
+                remove_empty_partition(&path);
+            }
+            report.files_deleted += 1;
+            report.files_archived += archived_to.is_some() as usize;
+            report.rows_deleted += profile.rows;
+            report.bytes_reclaimed += bytes;
+            report.deleted.push(path.display().to_string());
+            removed_files.push(path);
+        }
+    }
+
+    if !removed_files.is_empty() {
+        match metadata.lock().unwrap().forget_files(&removed_files) {
+            Ok(removed) => report.metadata_records_removed = removed,
+            Err(e) => {
+                log::error!("Update conversion metadata after retention failed: {}", e);
+                report.failed.push(format!("conversion metadata: {}", e));
+            }
+        }
+        log::info!(
+            "Retention removed {} files ({} archived, {} rows, {} bytes), skipped {} referenced",
+            report.files_deleted,
+            report.files_archived,
+            report.rows_deleted,
+            report.bytes_reclaimed,
+            report.skipped_referenced
+        );
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::conversion::metadata::{ConversionRecord, ConversionStatus};
+    use crate::storage::memtable::olap::{create_olap_schema, OlapMemTable};
+    use crate::storage::memtable::types::{MemTableKey, MemTableValue};
+    use crate::storage::sstable::olap_parquet::ParquetSSTableWriter;
+    use crate::storage::sstable::oltp_rkyv::RkyvSSTableWriter;
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    const DAY_NS: i64 = 86_400 * 1_000_000_000;
+    const NOW: i64 = 1_750_000_000 * 1_000_000_000;
+
+    fn tick(ts: i64) -> WalRecord {
+        WalRecord::TickData {
+            instrument_id: WalRecord::to_fixed_array_16("IF2501"),
+            last_price: 3800.0,
+            bid_price: 3799.8,
+            ask_price: 3800.2,
+            volume: 10,
+            timestamp: ts,
+        }
+    }
+
+    fn trade(ts: i64) -> WalRecord {
+        WalRecord::TradeExecuted {
+            trade_id: 1,
+            order_id: 1,
+            exchange_order_id: 1,
+            price: 3800.0,
+            volume: 1.0,
+            timestamp: ts,
+        }
+    }
+
+    fn account(ts: i64) -> WalRecord {
+        WalRecord::AccountUpdate {
+            user_id: [1u8; 32],
+            balance: 1_000_000.0,
+            available: 900_000.0,
+            frozen: 0.0,
+            margin: 100_000.0,
+            timestamp: ts,
+        }
+    }
+
+    fn keyed(records: Vec<WalRecord>, ts: i64) -> Vec<(MemTableKey, WalRecord)> {
+        records
+            .into_iter()
+            .enumerate()
+            .map(|(i, record)| (MemTableKey::new(ts + i as i64, i as u64), record))
+            .collect()
+    }
+
+    fn write_sst(path: &Path, records: Vec<WalRecord>, ts: i64) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let mut writer = RkyvSSTableWriter::create(path).unwrap();
+        for (key, record) in keyed(records, ts) {
+            writer.append(key, MemTableValue::new(record)).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    fn write_parquet(path: &Path, records: Vec<WalRecord>, ts: i64) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let memtable = OlapMemTable::from_records(keyed(records, ts));
+        let mut writer =
+            ParquetSSTableWriter::create(path, Arc::new(create_olap_schema())).unwrap();
+        writer.write_chunk(memtable.chunk()).unwrap();
+        writer.finish().unwrap();
+    }
+
+    fn config(archive_dir: &Path) -> RetentionConfig {
+        RetentionConfig {
+            enabled: true,
+            market_days: 30,
+            archive_dir: archive_dir.display().to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_record_classes() {
+        assert_eq!(
+            RetentionClass::of_record(&tick(1)),
+            Some(RetentionClass::Market)
+        );
+        assert_eq!(
+            RetentionClass::of_record(&trade(1)),
+            Some(RetentionClass::Trade)
+        );
+        assert_eq!(
+            RetentionClass::of_record(&account(1)),
+            Some(RetentionClass::Account)
+        );
+        assert_eq!(
+            RetentionClass::of_record(&WalRecord::Checkpoint {
+                sequence: 1,
+                timestamp: 1
+            }),
+            None
+        );
+        assert_eq!(
+            RetentionClass::of_record_type(5),
+            Some(RetentionClass::Market)
+        );
+        assert_eq!(RetentionClass::of_record_type(3), None);
+        assert_eq!(
+            RetentionClass::of_record_type(99),
+            Some(RetentionClass::Unknown)
+        );
+
+        let cfg = RetentionConfig {
+            trade_days: 365,
+            ..Default::default()
+        };
+        let classes: BTreeSet<_> = [RetentionClass::Market, RetentionClass::Trade].into();
+        assert!(!is_expired(&cfg, &classes, NOW - 100 * DAY_NS, NOW));
+        assert!(is_expired(&cfg, &classes, NOW - 400 * DAY_NS, NOW));
+        assert!(!is_expired(&cfg, &BTreeSet::new(), 0, NOW));
+        let unknown: BTreeSet<_> = [RetentionClass::Unknown].into();
+        assert!(!is_expired(&cfg, &unknown, 0, NOW));
+    }
+
+    #[test]
+    fn test_expired_market_data_removed_and_critical_data_kept() {
+        let base = tempdir().unwrap();
+        let archive = tempdir().unwrap();
+        let root = base.path().join("IF2501");
+        let old = NOW - 60 * DAY_NS;
+        let recent = NOW - DAY_NS;
+
+        let old_tick_sst = root.join("sstables").join("0000000001.sst");
+        let old_trade_sst = root.join("sstables").join("0000000002.sst");
+        let recent_tick_sst = root.join("sstables").join("0000000003.sst");
+        let old_tick_parquet = root.join("olap").join("202504").join("batch_1.parquet");
+        let old_mixed_parquet = root.join("olap").join("202505").join("batch_1.parquet");
+        let partition_tick_sst = root
+            .join(PARTITIONS_DIR)
+            .join("IF2502")
+            .join("sstables")
+            .join("0000000001.sst");
+        write_sst(&old_tick_sst, vec![tick(old), tick(old)], old);
+        write_sst(&old_trade_sst, vec![trade(old)], old);
+        write_sst(&recent_tick_sst, vec![tick(recent)], recent);
+        write_parquet(&old_tick_parquet, vec![tick(old), tick(old)], old);
+        write_parquet(&old_mixed_parquet, vec![tick(old), account(old)], old);
+        write_sst(&partition_tick_sst, vec![tick(old)], old);
+        std::fs::write(root.join("olap").join("202504").join(COLD_MARKER), "{}").unwrap();
+
+        let metadata = Mutex::new(ConversionMetadata::new(
+            base.path().join("conversion_metadata.json"),
+        ));
+        let cfg = config(archive.path());
+        let report = apply_retention(base.path(), &cfg, &metadata, NOW);
+
+        assert!(report.failed.is_empty(), "{:?}", report.failed);
+        assert_eq!(report.files_scanned, 6);
+        assert_eq!(report.files_deleted, 3);
+        assert_eq!(report.files_archived, 3);
+        assert_eq!(report.rows_deleted, 5);
+
+        // 过期行情被移出存储并归档，空的月份分区目录一并清理
+        assert!(!old_tick_sst.exists());
+        assert!(!old_tick_parquet.exists());
+        assert!(!partition_tick_sst.exists());
+        assert!(!root.join("olap").join("202504").exists());
+        assert!(archive
+            .path()
+            .join("IF2501/sstables/0000000001.sst")
+            .exists());
+        assert!(archive
+            .path()
+            .join("IF2501/olap/202504/batch_1.parquet")
+            .exists());
+
+        // 成交、账户（永久保留）与未过期行情保留
+        assert!(old_trade_sst.exists());
+        assert!(old_mixed_parquet.exists());
+        assert!(recent_tick_sst.exists());
+
+        let audit = std::fs::read_to_string(cfg.audit_log_path(base.path())).unwrap();
+        let entries: Vec<RetentionAuditEntry> = audit
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(entries.len(), 3);
+        assert!(entries
+            .iter()
+            .all(|e| e.classes == vec![RetentionClass::Market] && e.archived_to.is_some()));
+
+        // 再次执行不重复删除
+        let again = apply_retention(base.path(), &cfg, &metadata, NOW);
+        assert_eq!(again.files_deleted, 0);
+    }
+
+    #[test]
+    fn test_referenced_files_kept_and_metadata_updated() {
+        let base = tempdir().unwrap();
+        let root = base.path().join("IF2501");
+        let old = NOW - 60 * DAY_NS;
+
+        let pending_sst = root.join("sstables").join("0000000001.sst");
+        let converted = root.join("olap").join("202504").join("batch_1.parquet");
+        write_sst(&pending_sst, vec![tick(old)], old);
+        write_parquet(&converted, vec![tick(old)], old);
+
+        let metadata = Mutex::new(ConversionMetadata::new(
+            base.path().join("conversion_metadata.json"),
+        ));
+        {
+            let mut guard = metadata.lock().unwrap();
+            let pending = ConversionRecord::new(
+                1,
+                "IF2501".to_string(),
+                vec![pending_sst.clone()],
+                root.join("olap").join("batch_2.parquet"),
+            );
+            let mut done = ConversionRecord::new(
+                2,
+                "IF2501".to_string(),
+                vec![root.join("sstables").join("0000000000.sst")],
+                PathBuf::from("batch_1.parquet"),
+            );
+            done.olap_files = vec![converted.clone()];
+            done.status = ConversionStatus::Success;
+            guard.add_record(pending).unwrap();
+            guard.add_record(done).unwrap();
+        }
+
+        let cfg = RetentionConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        let report = apply_retention(base.path(), &cfg, &metadata, NOW);
+        assert!(report.failed.is_empty(), "{:?}", report.failed);
+        assert_eq!(report.skipped_referenced, 1);
+        assert_eq!(report.files_deleted, 1);
+        assert_eq!(report.files_archived, 0);
+        assert_eq!(report.metadata_records_removed, 1);
+
+        // 待转换任务引用的文件保留，已转换且全部输出被清理的记录移出元数据
+        assert!(pending_sst.exists());
+        assert!(!converted.exists());
+        let reloaded =
+            ConversionMetadata::load(base.path().join("conversion_metadata.json")).unwrap();
+        assert_eq!(reloaded.records.len(), 1);
+        assert_eq!(reloaded.records[0].id, 1);
+    }
+}
@@ -247,7 +247,9 @@ impl ConversionScheduler {
 
         std::fs::create_dir_all(&olap_dir).map_err(|e| format!("Create OLAP dir failed: {}", e))?;
 
-        let timestamp = chrono::Utc::now().format("%Y%m%d_%H%M%S").to_string();
+        let timestamp = crate::utils::time_service::now_local()
+            .format("%Y%m%d_%H%M%S")
+            .to_string();
         let olap_parquet = olap_dir.join(format!("batch_{}.parquet", timestamp));
 
         // 创建转换记录
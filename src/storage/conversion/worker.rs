@@ -3,12 +3,13 @@
 // 职责：
 // 1. 从任务队列消费转换任务
 // 2. 批量读取 OLTP SSTable
-// 3. 构建 OLAP MemTable 并写入 Parquet
+// 3. 按记录时间拆分到月份分区（{olap}/{yyyymm}/），构建 OLAP MemTable 并写入 Parquet
 // 4. 校验数据完整性
 // 5. 原子性提交（临时文件 + rename）
 // 6. 错误恢复和回滚
 
 use super::metadata::{ConversionMetadata, ConversionRecord};
+use super::partition::{month_of, partition_dir};
 use super::scheduler::ConversionTask;
 use crate::storage::memtable::olap::OlapMemTable;
 use crate::storage::memtable::types::MemTableKey;
@@ -16,6 +17,8 @@ use crate::storage::sstable::olap_parquet::ParquetSSTableWriter;
 use crate::storage::sstable::oltp_rkyv::RkyvSSTable;
 use crate::storage::wal::record::WalRecord;
 use crossbeam::channel::Receiver;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 
@@ -46,6 +49,16 @@ impl Default for WorkerConfig {
     }
 }
 
+/// 单次转换输出
+#[derive(Debug, Clone)]
+pub struct ConversionOutput {
+    pub entry_count: u64,
+    pub min_timestamp: i64,
+    pub max_timestamp: i64,
+    /// 写入的月份分区文件（按月份升序）
+    pub files: Vec<PathBuf>,
+}
+
 /// 转换工作线程
 pub struct ConversionWorker {
     worker_id: usize,
@@ -122,18 +135,24 @@ impl ConversionWorker {
 
         // 2. 执行转换
         match self.do_conversion(&record) {
-            Ok((entry_count, min_ts, max_ts)) => {
+            Ok(output) => {
                 // 3. 转换成功
                 log::info!(
-                    "Worker {} completed conversion task {}: {} entries, time range [{}, {}]",
+                    "Worker {} completed conversion task {}: {} entries in {} partitions, time range [{}, {}]",
                     self.worker_id,
                     record.id,
-                    entry_count,
-                    min_ts,
-                    max_ts
+                    output.entry_count,
+                    output.files.len(),
+                    output.min_timestamp,
+                    output.max_timestamp
                 );
 
-                record.mark_success(entry_count, min_ts, max_ts);
+                record.olap_files = output.files;
+                record.mark_success(
+                    output.entry_count,
+                    output.min_timestamp,
+                    output.max_timestamp,
+                );
 
                 let mut metadata = self.metadata.lock().unwrap();
                 metadata.update_record(record.clone())?;
@@ -160,21 +179,17 @@ impl ConversionWorker {
                 let mut metadata = self.metadata.lock().unwrap();
                 metadata.update_record(record.clone())?;
 
-                // 6. 清理临时文件
-                let tmp_path = record.temp_file_path();
-                if tmp_path.exists() {
-                    if let Err(e) = std::fs::remove_file(&tmp_path) {
-                        log::warn!("Failed to remove temp file {:?}: {}", tmp_path, e);
-                    }
-                }
-
+                // 临时文件已在 do_conversion 中清理
                 Err(e)
             }
         }
     }
 
     /// 执行转换（核心逻辑）
-    fn do_conversion(&self, record: &ConversionRecord) -> Result<(u64, i64, i64), String> {
+    ///
+    /// 记录按时间戳所属月份拆分，每个月份分区写入 `{olap}/{yyyymm}/{文件名}`；
+    /// 所有分区文件先写临时文件，全部校验通过后再逐个 rename
+    fn do_conversion(&self, record: &ConversionRecord) -> Result<ConversionOutput, String> {
         log::debug!("Worker {} reading OLTP SSTables...", self.worker_id);
 
         // 1. 批量读取所有 OLTP SSTable
@@ -191,56 +206,90 @@ impl ConversionWorker {
             record.oltp_sstables.len()
         );
 
-        // 2. 构建 OLAP MemTable
-        log::debug!("Worker {} building OLAP MemTable...", self.worker_id);
-        let olap_memtable = OlapMemTable::from_records(all_records.clone());
-
-        // 3. 写入 Parquet（临时文件）
-        let tmp_path = record.temp_file_path();
-        log::debug!(
-            "Worker {} writing Parquet to {:?}...",
-            self.worker_id,
-            tmp_path
-        );
-
-        let schema = Arc::new(crate::storage::memtable::olap::create_olap_schema());
-        let mut writer = ParquetSSTableWriter::create(&tmp_path, schema)?;
-
-        writer.write_chunk(olap_memtable.chunk())?;
-
-        let metadata = writer.finish()?;
+        let entry_count = all_records.len() as u64;
+        let min_timestamp = all_records[0].0.timestamp;
+        let max_timestamp = all_records[all_records.len() - 1].0.timestamp;
+
+        // 2. 按月份分区拆分（记录已按时间排序，分区内保持有序）
+        let mut by_month: BTreeMap<String, Vec<(MemTableKey, WalRecord)>> = BTreeMap::new();
+        for (key, wal_record) in all_records {
+            by_month
+                .entry(month_of(key.timestamp))
+                .or_default()
+                .push((key, wal_record));
+        }
 
-        log::debug!(
-            "Worker {} wrote {} entries to Parquet",
-            self.worker_id,
-            metadata.entry_count
-        );
+        let olap_dir = record
+            .olap_parquet
+            .parent()
+            .ok_or_else(|| format!("Invalid OLAP path {:?}", record.olap_parquet))?;
+        let file_name = record
+            .olap_parquet
+            .file_name()
+            .ok_or_else(|| format!("Invalid OLAP path {:?}", record.olap_parquet))?;
+
+        // 3. 各分区写入 Parquet（临时文件）并校验数据完整性
+        let mut written: Vec<(PathBuf, PathBuf)> = Vec::with_capacity(by_month.len());
+        let result = (|| {
+            for (month, records) in by_month {
+                let target = partition_dir(olap_dir, &month).join(file_name);
+                let tmp_path = target.with_extension("tmp");
+                log::debug!(
+                    "Worker {} writing {} records of partition {} to {:?}...",
+                    self.worker_id,
+                    records.len(),
+                    month,
+                    tmp_path
+                );
 
-        // 4. 校验数据完整性
-        if metadata.entry_count != all_records.len() as u64 {
-            return Err(format!(
-                "Entry count mismatch: expected {}, got {}",
-                all_records.len(),
-                metadata.entry_count
-            ));
+                let expected = records.len() as u64;
+                let olap_memtable = OlapMemTable::from_records(records);
+                let schema = Arc::new(crate::storage::memtable::olap::create_olap_schema());
+                let mut writer = ParquetSSTableWriter::create(&tmp_path, schema)?;
+                written.push((tmp_path, target));
+                writer.write_chunk(olap_memtable.chunk())?;
+                let metadata = writer.finish()?;
+
+                if metadata.entry_count != expected {
+                    return Err(format!(
+                        "Entry count mismatch in partition {}: expected {}, got {}",
+                        month, expected, metadata.entry_count
+                    ));
+                }
+            }
+            Ok(())
+        })();
+        if let Err(e) = result {
+            for (tmp_path, _) in &written {
+                if tmp_path.exists() {
+                    if let Err(e) = std::fs::remove_file(tmp_path) {
+                        log::warn!("Failed to remove temp file {:?}: {}", tmp_path, e);
+                    }
+                }
+            }
+            return Err(e);
         }
 
-        // 5. 原子性 rename
-        log::debug!(
-            "Worker {} renaming {:?} to {:?}...",
-            self.worker_id,
-            tmp_path,
-            record.olap_parquet
-        );
-
-        std::fs::rename(&tmp_path, &record.olap_parquet)
-            .map_err(|e| format!("Rename parquet file failed: {}", e))?;
+        // 4. 原子性 rename
+        let mut files = Vec::with_capacity(written.len());
+        for (tmp_path, target) in written {
+            log::debug!(
+                "Worker {} renaming {:?} to {:?}...",
+                self.worker_id,
+                tmp_path,
+                target
+            );
+            std::fs::rename(&tmp_path, &target)
+                .map_err(|e| format!("Rename parquet file failed: {}", e))?;
+            files.push(target);
+        }
 
-        Ok((
-            metadata.entry_count,
-            metadata.min_timestamp,
-            metadata.max_timestamp,
-        ))
+        Ok(ConversionOutput {
+            entry_count,
+            min_timestamp,
+            max_timestamp,
+            files,
+        })
     }
 
     /// 批量读取所有 OLTP SSTable
@@ -473,12 +522,77 @@ mod tests {
         let result = worker.do_conversion(&record);
         assert!(result.is_ok());
 
-        let (entry_count, min_ts, max_ts) = result.unwrap();
-        assert_eq!(entry_count, 100); // 2 * 50
-        assert_eq!(min_ts, 1000);
-        assert_eq!(max_ts, 1099);
+        let output = result.unwrap();
+        assert_eq!(output.entry_count, 100); // 2 * 50
+        assert_eq!(output.min_timestamp, 1000);
+        assert_eq!(output.max_timestamp, 1099);
+
+        // 验证 Parquet 文件写入时间所属的月份分区
+        assert_eq!(
+            output.files,
+            vec![tmp_dir.path().join("197001").join("output.parquet")]
+        );
+        assert!(output.files[0].exists());
+    }
+
+    #[test]
+    fn test_worker_conversion_splits_month_boundary() {
+        use crate::storage::conversion::metadata::ConversionMetadata;
+        use crate::storage::sstable::olap_parquet::ParquetSSTable;
+        use chrono::TimeZone;
 
-        // 验证 Parquet 文件存在
-        assert!(record.olap_parquet.exists());
+        let tmp_dir = tempdir().unwrap();
+
+        // 北京时间 2025-02-01 00:00:00 前后各 5 条
+        let feb_start = chrono::FixedOffset::east_opt(8 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2025, 2, 1, 0, 0, 0)
+            .unwrap()
+            .timestamp_nanos_opt()
+            .unwrap();
+        let sstable_path = tmp_dir.path().join("sstable_1.rkyv");
+        create_test_sstable(&sstable_path, 10, feb_start - 1000 - 5).unwrap();
+
+        let olap_dir = tmp_dir.path().join("IF2502").join("olap");
+        let record = ConversionRecord::new(
+            1,
+            "IF2502".to_string(),
+            vec![sstable_path],
+            olap_dir.join("batch_1.parquet"),
+        );
+        let worker = ConversionWorker {
+            worker_id: 0,
+            config: WorkerConfig::default(),
+            metadata: Arc::new(Mutex::new(ConversionMetadata::new(
+                tmp_dir.path().join("metadata.json"),
+            ))),
+            task_receiver: crossbeam::channel::unbounded().1,
+            shutdown: Arc::new(AtomicBool::new(false)),
+        };
+
+        let output = worker.do_conversion(&record).unwrap();
+        assert_eq!(output.entry_count, 10);
+        assert_eq!(
+            output.files,
+            vec![
+                olap_dir.join("202501").join("batch_1.parquet"),
+                olap_dir.join("202502").join("batch_1.parquet"),
+            ]
+        );
+
+        let jan = ParquetSSTable::open(&output.files[0]).unwrap();
+        let feb = ParquetSSTable::open(&output.files[1]).unwrap();
+        assert_eq!(jan.metadata().entry_count, 5);
+        assert_eq!(jan.metadata().max_timestamp, feb_start - 1);
+        assert_eq!(feb.metadata().entry_count, 5);
+        assert_eq!(feb.metadata().min_timestamp, feb_start);
+
+        // 分区目录中不残留临时文件
+        for file in &output.files {
+            assert_eq!(
+                std::fs::read_dir(file.parent().unwrap()).unwrap().count(),
+                1
+            );
+        }
     }
 }
@@ -0,0 +1,539 @@
+//! 订单/成交数据导出流
+//!
+//! 为合规等外部系统提供准实时镜像：StorageSubscriber 落盘的订单、成交、账户变更记录
+//! 同时追加到独立的导出日志（`__EXPORT__`），其 WAL 序列号即全局 sequence。
+//!
+//! 读取路径：
+//! - 实时：内存尾部缓冲（最近写入 WAL 的记录），客户端跟上进度时直接推送
+//! - 补数：客户端落后超出尾部缓冲时，通过序列号块索引定位时间范围，
+//!   从 MemTable/SSTable 批量读取，追上后自动衔接实时流
+//!
+//! 客户端凭最后收到的 sequence 续传，读取严格按 sequence 递增，不丢不重。
+//!
+//! @yutiansut @quantaxis
+
+use crate::storage::hybrid::oltp::{OltpHybridConfig, OltpHybridStorage};
+use crate::storage::wal::WalRecord;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// 导出日志在存储目录下的名称
+pub const EXPORT_STREAM_ID: &str = "__EXPORT__";
+
+/// 默认尾部缓冲容量（条）
+pub const DEFAULT_TAIL_CAPACITY: usize = 100_000;
+
+/// 序列号块大小（每块记录一次时间范围，用于补数定位）
+const SEQUENCE_BLOCK_SIZE: u64 = 1024;
+
+/// 导出记录类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportRecordKind {
+    /// 订单
+    Orders,
+    /// 成交
+    Trades,
+    /// 账户变更
+    AccountChanges,
+}
+
+impl ExportRecordKind {
+    /// 判断 WAL 记录是否需要导出，返回 (类型, 时间戳)
+    fn classify(record: &WalRecord) -> Option<(Self, i64)> {
+        match record {
+            WalRecord::OrderInsert { timestamp, .. } => Some((Self::Orders, *timestamp)),
+            WalRecord::TradeExecuted { timestamp, .. }
+            | WalRecord::TradeExecutedV2 { timestamp, .. } => Some((Self::Trades, *timestamp)),
+            WalRecord::AccountOpen { timestamp, .. }
+            | WalRecord::AccountUpdate { timestamp, .. }
+            | WalRecord::AccountUpdateV2 { timestamp, .. } => {
+                Some((Self::AccountChanges, *timestamp))
+            }
+            _ => None,
+        }
+    }
+
+    /// WAL 记录对应的导出类型（不需要导出时返回 None）
+    pub fn of(record: &WalRecord) -> Option<Self> {
+        Self::classify(record).map(|(kind, _)| kind)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Orders => "orders",
+            Self::Trades => "trades",
+            Self::AccountChanges => "account_changes",
+        }
+    }
+}
+
+/// 导出记录
+#[derive(Debug, Clone)]
+pub struct ExportRecord {
+    /// 全局序列号
+    pub sequence: u64,
+    pub kind: ExportRecordKind,
+    /// 纳秒时间戳
+    pub timestamp: i64,
+    pub record: WalRecord,
+}
+
+impl ExportRecord {
+    /// 转为推送给客户端的 JSON
+    pub fn to_json(&self) -> serde_json::Value {
+        let data = match &self.record {
+            WalRecord::OrderInsert {
+                order_id,
+                user_id,
+                instrument_id,
+                direction,
+                offset,
+                price,
+                volume,
+                ..
+            } => serde_json::json!({
+                "order_id": order_id,
+                "user_id": WalRecord::from_fixed_array(user_id),
+                "instrument_id": WalRecord::from_fixed_array(instrument_id),
+                "direction": if *direction == 0 { "BUY" } else { "SELL" },
+                "offset": if *offset == 0 { "OPEN" } else { "CLOSE" },
+                "price": price,
+                "volume": volume,
+            }),
+            WalRecord::TradeExecuted {
+                trade_id,
+                order_id,
+                exchange_order_id,
+                price,
+                volume,
+                ..
+            } => serde_json::json!({
+                "trade_id": trade_id,
+                "order_id": order_id,
+                "exchange_order_id": exchange_order_id,
+                "price": price,
+                "volume": volume,
+            }),
+            WalRecord::AccountOpen {
+                account_id,
+                user_id,
+                account_name,
+                init_cash,
+                account_type,
+                ..
+            } => serde_json::json!({
+                "event": "open",
+                "account_id": WalRecord::from_fixed_array(account_id),
+                "user_id": WalRecord::from_fixed_array(user_id),
+                "account_name": WalRecord::from_fixed_array(account_name),
+                "init_cash": init_cash,
+                "account_type": account_type,
+            }),
+            WalRecord::AccountUpdate {
+                user_id,
+                balance,
+                available,
+                frozen,
+                margin,
+                ..
+            } => serde_json::json!({
+                "event": "update",
+                "user_id": WalRecord::from_fixed_array(user_id),
+                "balance": balance,
+                "available": available,
+                "frozen": frozen,
+                "margin": margin,
+            }),
+            // 定点金额版本：金额由整数换算为浮点输出
+            WalRecord::TradeExecutedV2 {
+                trade_id,
+                order_id,
+                exchange_order_id,
+                price,
+                volume,
+                commission,
+                amount_decimals,
+                ..
+            } => serde_json::json!({
+                "trade_id": trade_id,
+                "order_id": order_id,
+                "exchange_order_id": exchange_order_id,
+                "price": price,
+                "volume": volume,
+                "commission": WalRecord::from_fixed_amount(*commission, *amount_decimals),
+            }),
+            WalRecord::AccountUpdateV2 {
+                user_id,
+                amount_decimals,
+                balance,
+                available,
+                frozen,
+                margin,
+                position_profit,
+                close_profit,
+                ..
+            } => {
+                let amount = |units: &i64| WalRecord::from_fixed_amount(*units, *amount_decimals);
+                serde_json::json!({
+                    "event": "update",
+                    "user_id": WalRecord::from_fixed_array(user_id),
+                    "balance": amount(balance),
+                    "available": amount(available),
+                    "frozen": amount(frozen),
+                    "margin": amount(margin),
+                    "position_profit": amount(position_profit),
+                    "close_profit": amount(close_profit),
+                })
+            }
+            _ => serde_json::Value::Null,
+        };
+
+        serde_json::json!({
+            "sequence": self.sequence,
+            "type": self.kind.as_str(),
+            "timestamp": self.timestamp,
+            "data": data,
+        })
+    }
+}
+
+/// 本批数据的读取来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportSource {
+    /// 尾部缓冲（实时流）
+    Live,
+    /// MemTable/SSTable 批量补数
+    Backfill,
+}
+
+/// 一次读取的结果
+#[derive(Debug, Clone)]
+pub struct ExportBatch {
+    /// 匹配订阅类型的记录（按 sequence 升序）
+    pub records: Vec<ExportRecord>,
+    /// 本次已扫描到的 sequence（下次从其之后继续读取）
+    pub last_sequence: u64,
+    pub source: ExportSource,
+}
+
+/// 导出日志
+pub struct ExportLog {
+    /// 底层存储（WAL 序列号即全局 sequence）
+    storage: Arc<OltpHybridStorage>,
+
+    /// 最近写入的记录（按 sequence 升序）
+    tail: RwLock<VecDeque<ExportRecord>>,
+
+    /// 尾部缓冲容量
+    tail_capacity: usize,
+
+    /// 序列号块 → (最小时间戳, 最大时间戳)
+    block_index: RwLock<BTreeMap<u64, (i64, i64)>>,
+
+    /// 最新写入的 sequence（0 表示尚无数据）
+    latest_sequence: AtomicU64,
+
+    /// 写入互斥锁（保证 sequence 顺序与尾部缓冲顺序一致）
+    append_lock: Mutex<()>,
+}
+
+impl ExportLog {
+    /// 打开导出日志（恢复已有 SSTable/WAL，并重建块索引与尾部缓冲）
+    pub fn open(config: OltpHybridConfig, tail_capacity: usize) -> Result<Self, String> {
+        let storage = Arc::new(OltpHybridStorage::create(EXPORT_STREAM_ID, config)?);
+        storage.recover()?;
+
+        let export_log = Self {
+            storage,
+            tail: RwLock::new(VecDeque::with_capacity(tail_capacity.min(DEFAULT_TAIL_CAPACITY))),
+            tail_capacity,
+            block_index: RwLock::new(BTreeMap::new()),
+            latest_sequence: AtomicU64::new(0),
+            append_lock: Mutex::new(()),
+        };
+
+        let mut entries = export_log.storage.range_query(i64::MIN, i64::MAX)?;
+        entries.sort_by_key(|(_, seq, _)| *seq);
+
+        let mut index = export_log.block_index.write();
+        let mut tail = export_log.tail.write();
+        let tail_start = entries.len().saturating_sub(tail_capacity);
+        for (i, (timestamp, sequence, record)) in entries.into_iter().enumerate() {
+            let Some(kind) = ExportRecordKind::of(&record) else {
+                continue;
+            };
+            Self::index_sequence(&mut index, sequence, timestamp);
+            export_log.latest_sequence.fetch_max(sequence, Ordering::SeqCst);
+            if i >= tail_start {
+                tail.push_back(ExportRecord {
+                    sequence,
+                    kind,
+                    timestamp,
+                    record,
+                });
+            }
+        }
+        drop(tail);
+        drop(index);
+
+        log::info!(
+            "[{}] Export log opened, latest sequence {}",
+            EXPORT_STREAM_ID,
+            export_log.latest_sequence()
+        );
+        Ok(export_log)
+    }
+
+    /// 最新写入的 sequence
+    pub fn latest_sequence(&self) -> u64 {
+        self.latest_sequence.load(Ordering::SeqCst)
+    }
+
+    /// 底层存储
+    pub fn storage(&self) -> Arc<OltpHybridStorage> {
+        self.storage.clone()
+    }
+
+    /// 批量追加记录（不需要导出的记录会被忽略）
+    ///
+    /// # Returns
+    /// 实际追加的记录数
+    pub fn append_batch(&self, records: Vec<WalRecord>) -> Result<usize, String> {
+        let records: Vec<(ExportRecordKind, i64, WalRecord)> = records
+            .into_iter()
+            .filter_map(|record| {
+                let (kind, timestamp) = ExportRecordKind::classify(&record)?;
+                Some((kind, timestamp, record))
+            })
+            .collect();
+        if records.is_empty() {
+            return Ok(0);
+        }
+
+        let _guard = self.append_lock.lock();
+        let sequences = self
+            .storage
+            .write_batch(records.iter().map(|(_, _, record)| record.clone()).collect())?;
+
+        let mut index = self.block_index.write();
+        let mut tail = self.tail.write();
+        for (sequence, (kind, timestamp, record)) in sequences.iter().zip(records) {
+            Self::index_sequence(&mut index, *sequence, timestamp);
+            tail.push_back(ExportRecord {
+                sequence: *sequence,
+                kind,
+                timestamp,
+                record,
+            });
+        }
+        while tail.len() > self.tail_capacity {
+            tail.pop_front();
+        }
+        if let Some(&last) = sequences.last() {
+            self.latest_sequence.store(last, Ordering::SeqCst);
+        }
+
+        Ok(sequences.len())
+    }
+
+    /// 读取 after_sequence 之后的记录（最多扫描 limit 条）
+    ///
+    /// 进度仍在尾部缓冲内时从缓冲读取，否则从 MemTable/SSTable 补数。
+    /// `last_sequence` 按扫描进度推进，被类型过滤掉的记录同样计入。
+    pub fn read_after(
+        &self,
+        after_sequence: u64,
+        kinds: &[ExportRecordKind],
+        limit: usize,
+    ) -> Result<ExportBatch, String> {
+        let latest = self.latest_sequence();
+        if limit == 0 || after_sequence >= latest {
+            return Ok(ExportBatch {
+                records: Vec::new(),
+                last_sequence: after_sequence,
+                source: ExportSource::Live,
+            });
+        }
+
+        {
+            let tail = self.tail.read();
+            if let Some(front) = tail.front() {
+                if after_sequence + 1 >= front.sequence {
+                    let start = tail.partition_point(|r| r.sequence <= after_sequence);
+                    let mut records = Vec::new();
+                    let mut last_sequence = after_sequence;
+                    for record in tail.range(start..).take(limit) {
+                        last_sequence = record.sequence;
+                        if kinds.contains(&record.kind) {
+                            records.push(record.clone());
+                        }
+                    }
+                    return Ok(ExportBatch {
+                        records,
+                        last_sequence,
+                        source: ExportSource::Live,
+                    });
+                }
+            }
+        }
+
+        self.read_from_storage(after_sequence, latest, kinds, limit)
+    }
+
+    /// 从 MemTable/SSTable 补数：用块索引把 sequence 区间换算成时间范围
+    fn read_from_storage(
+        &self,
+        after_sequence: u64,
+        latest: u64,
+        kinds: &[ExportRecordKind],
+        limit: usize,
+    ) -> Result<ExportBatch, String> {
+        let upto = after_sequence.saturating_add(limit as u64).min(latest);
+
+        let (min_ts, max_ts) = self
+            .block_index
+            .read()
+            .range((after_sequence + 1) / SEQUENCE_BLOCK_SIZE..=upto / SEQUENCE_BLOCK_SIZE)
+            .fold((i64::MAX, i64::MIN), |(lo, hi), (_, &(min, max))| {
+                (lo.min(min), hi.max(max))
+            });
+
+        let mut records: Vec<ExportRecord> = if min_ts <= max_ts {
+            self.storage
+                .range_query(min_ts, max_ts)?
+                .into_iter()
+                .filter(|(_, seq, _)| *seq > after_sequence && *seq <= upto)
+                .filter_map(|(timestamp, sequence, record)| {
+                    let kind = ExportRecordKind::of(&record)?;
+                    kinds.contains(&kind).then_some(ExportRecord {
+                        sequence,
+                        kind,
+                        timestamp,
+                        record,
+                    })
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        records.sort_by_key(|r| r.sequence);
+
+        Ok(ExportBatch {
+            records,
+            last_sequence: upto,
+            source: ExportSource::Backfill,
+        })
+    }
+
+    fn index_sequence(index: &mut BTreeMap<u64, (i64, i64)>, sequence: u64, timestamp: i64) {
+        index
+            .entry(sequence / SEQUENCE_BLOCK_SIZE)
+            .and_modify(|(min, max)| {
+                *min = (*min).min(timestamp);
+                *max = (*max).max(timestamp);
+            })
+            .or_insert((timestamp, timestamp));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order_record(order_id: u64) -> WalRecord {
+        WalRecord::OrderInsert {
+            order_id,
+            user_id: WalRecord::to_fixed_array_32("user_01"),
+            instrument_id: WalRecord::to_fixed_array_16("IF2501"),
+            direction: 0,
+            offset: 0,
+            price: 3800.0,
+            volume: 1.0,
+            timestamp: 1_000_000 + order_id as i64 * 1000,
+        }
+    }
+
+    fn trade_record(trade_id: u64) -> WalRecord {
+        WalRecord::TradeExecuted {
+            trade_id,
+            order_id: trade_id,
+            exchange_order_id: trade_id,
+            price: 3800.0,
+            volume: 1.0,
+            timestamp: 1_000_000 + trade_id as i64 * 1000 + 500,
+        }
+    }
+
+    fn test_config(base_path: &str) -> OltpHybridConfig {
+        OltpHybridConfig {
+            base_path: base_path.to_string(),
+            memtable_size_bytes: 2000, // 很小，补数需要跨 SSTable
+            estimated_entry_size: 100,
+            enable_olap_conversion: false,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_read_live_and_filter_by_kind() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let log = ExportLog::open(test_config(tmp_dir.path().to_str().unwrap()), 100).unwrap();
+
+        let mut records = Vec::new();
+        for i in 1..=10u64 {
+            records.push(order_record(i));
+            records.push(trade_record(i));
+        }
+        // Tick 等行情数据不导出
+        records.push(WalRecord::Checkpoint {
+            sequence: 0,
+            timestamp: 0,
+        });
+        assert_eq!(log.append_batch(records).unwrap(), 20);
+
+        let batch = log.read_after(0, &[ExportRecordKind::Trades], 8).unwrap();
+        assert_eq!(batch.source, ExportSource::Live);
+        assert_eq!(batch.records.len(), 4);
+        assert!(batch
+            .records
+            .iter()
+            .all(|r| r.kind == ExportRecordKind::Trades));
+        // 被过滤的订单也推进进度
+        let first_sequence = log.latest_sequence() - 19;
+        assert_eq!(batch.last_sequence, first_sequence + 7);
+
+        let all = [ExportRecordKind::Orders, ExportRecordKind::Trades];
+        let rest = log.read_after(batch.last_sequence, &all, 100).unwrap();
+        assert_eq!(rest.records.len(), 12);
+        assert_eq!(rest.last_sequence, log.latest_sequence());
+    }
+
+    #[test]
+    fn test_backfill_when_behind_tail() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let log = ExportLog::open(test_config(tmp_dir.path().to_str().unwrap()), 10).unwrap();
+
+        log.append_batch((1..=200).map(order_record).collect()).unwrap();
+        assert!(log.storage().stats().sstable_count > 0);
+
+        // 从头读取：超出尾部缓冲，走补数
+        let kinds = [ExportRecordKind::Orders];
+        let mut cursor = 0;
+        let mut received = Vec::new();
+        let mut saw_backfill = false;
+        while cursor < log.latest_sequence() {
+            let batch = log.read_after(cursor, &kinds, 50).unwrap();
+            saw_backfill |= batch.source == ExportSource::Backfill;
+            received.extend(batch.records.iter().map(|r| r.sequence));
+            cursor = batch.last_sequence;
+        }
+        assert!(saw_backfill);
+        assert_eq!(received.len(), 200);
+        assert!(received.windows(2).all(|w| w[0] + 1 == w[1]));
+    }
+}
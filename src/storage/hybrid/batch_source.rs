@@ -12,6 +12,7 @@
 // - OLAP: P99 < 10ms (Parquet 谓词下推)
 // - 自动路由：根据时间范围选择最优数据源
 // - 类型过滤：O(1) 位掩码过滤
+// - 分析查询限流：OLTP 部分走存储的分析通道配额，配额用尽时降级到 OLAP 副本
 //
 // @yutiansut @quantaxis
 
@@ -21,6 +22,7 @@ use crate::query::hybrid::{
 };
 use crate::storage::hybrid::oltp::OltpHybridStorage;
 use crate::storage::hybrid::query_filter::{QueryFilter, RecordType, RecordTypeSet};
+use crate::storage::hybrid::query_gate::{AnalyticalPermit, QueryGateError};
 use crate::storage::sstable::olap_parquet::ParquetSSTable;
 use crate::storage::wal::record::WalRecord;
 use std::collections::HashMap;
@@ -149,6 +151,48 @@ impl OltpBatchAdapter {
                     .with_value("frozen", RecordValue::Float(*frozen))
                     .with_value("margin", RecordValue::Float(*margin));
             }
+            // 定点金额版本：对外仍输出浮点，与旧版本记录同名同字段
+            WalRecord::TradeExecutedV2 {
+                trade_id,
+                order_id,
+                price,
+                volume,
+                commission,
+                amount_decimals,
+                ..
+            } => {
+                result = result
+                    .with_value("record_type", RecordValue::String("TradeExecuted".to_string()))
+                    .with_value("trade_id", RecordValue::Int(*trade_id as i64))
+                    .with_value("order_id", RecordValue::Int(*order_id as i64))
+                    .with_value("price", RecordValue::Float(*price))
+                    .with_value("volume", RecordValue::Float(*volume))
+                    .with_value(
+                        "commission",
+                        RecordValue::Float(WalRecord::from_fixed_amount(
+                            *commission,
+                            *amount_decimals,
+                        )),
+                    );
+            }
+            WalRecord::AccountUpdateV2 {
+                amount_decimals,
+                balance,
+                available,
+                frozen,
+                margin,
+                ..
+            } => {
+                let amount = |units: &i64| {
+                    RecordValue::Float(WalRecord::from_fixed_amount(*units, *amount_decimals))
+                };
+                result = result
+                    .with_value("record_type", RecordValue::String("AccountUpdate".to_string()))
+                    .with_value("balance", amount(balance))
+                    .with_value("available", amount(available))
+                    .with_value("frozen", amount(frozen))
+                    .with_value("margin", amount(margin));
+            }
             WalRecord::KLineFinished {
                 period,
                 kline_timestamp,
@@ -248,6 +292,7 @@ impl OltpBatchAdapter {
                 deal_price,
                 deal_volume,
                 trade_id,
+                match_batch_id,
                 ..
             } => {
                 result = result
@@ -256,7 +301,8 @@ impl OltpBatchAdapter {
                     .with_value("sell_order_id", RecordValue::Int(*sell_exchange_order_id))
                     .with_value("deal_price", RecordValue::Float(*deal_price))
                     .with_value("deal_volume", RecordValue::Float(*deal_volume))
-                    .with_value("trade_id", RecordValue::Int(*trade_id));
+                    .with_value("trade_id", RecordValue::Int(*trade_id))
+                    .with_value("match_batch_id", RecordValue::Int(*match_batch_id));
             }
 
             WalRecord::ExchangeResponseRecord {
@@ -354,7 +400,12 @@ impl OltpBatchAdapter {
             WalRecord::OrderStatusUpdate { .. }
             | WalRecord::PositionSnapshot { .. }
             | WalRecord::AccountSnapshot { .. }
-            | WalRecord::UserRoleUpdate { .. } => {
+            | WalRecord::UserRoleUpdate { .. }
+            | WalRecord::UserAnonymize { .. }
+            | WalRecord::UserNotification { .. }
+            | WalRecord::NotificationRead { .. }
+            | WalRecord::UserPasswordUpdate { .. }
+            | WalRecord::RiskSnapshot { .. } => {
                 result = result.with_value("record_type", RecordValue::String("Recovery".to_string()));
             }
         }
@@ -362,6 +413,26 @@ impl OltpBatchAdapter {
         result
     }
 
+    /// 申请分析查询配额
+    ///
+    /// 返回 `Ok(None)` 表示配额用尽且已降级：只返回 OLAP 副本中的历史数据
+    fn acquire_analytical(
+        &self,
+        olap_queried: bool,
+    ) -> Result<Option<AnalyticalPermit<'_>>, BatchQueryError> {
+        let gate = self.storage.query_gate();
+        match gate.acquire() {
+            Ok(permit) => Ok(Some(permit)),
+            Err(e) if olap_queried && gate.config().degrade_to_olap => {
+                log::warn!("[OltpBatchAdapter] {}, degraded to OLAP replica", e);
+                gate.record_degraded();
+                Ok(None)
+            }
+            Err(QueryGateError::Timeout { .. }) => Err(BatchQueryError::Timeout),
+            Err(e) => Err(BatchQueryError::Other(e.to_string())),
+        }
+    }
+
     /// 查询 OLTP 存储
     fn query_oltp(
         &self,
@@ -371,7 +442,7 @@ impl OltpBatchAdapter {
     ) -> Result<Vec<Record>, BatchQueryError> {
         let results = self
             .storage
-            .range_query(start_ts, end_ts)
+            .scan_range(start_ts, end_ts)
             .map_err(BatchQueryError::IoError)?;
 
         Ok(results
@@ -476,19 +547,22 @@ impl OltpBatchAdapter {
             .unwrap_or((i64::MIN, i64::MAX));
 
         let mut results = Vec::new();
+        let olap_queried = start_ts < self.olap_cutoff_timestamp && !self.olap_files.is_empty();
 
         // 1. 查询 OLAP (历史数据) - 使用过滤器
-        if start_ts < self.olap_cutoff_timestamp && !self.olap_files.is_empty() {
+        if olap_queried {
             let olap_end = end_ts.min(self.olap_cutoff_timestamp);
             let olap_records = self.query_olap_filtered(key, start_ts, olap_end, &filter)?;
             results.extend(olap_records);
         }
 
-        // 2. 查询 OLTP (近期数据) - 使用过滤器
+        // 2. 查询 OLTP (近期数据) - 使用过滤器，受分析查询配额限制
         if end_ts >= self.olap_cutoff_timestamp {
-            let oltp_start = start_ts.max(self.olap_cutoff_timestamp);
-            let oltp_records = self.query_oltp_filtered(key, oltp_start, end_ts, &filter)?;
-            results.extend(oltp_records);
+            if let Some(_permit) = self.acquire_analytical(olap_queried)? {
+                let oltp_start = start_ts.max(self.olap_cutoff_timestamp);
+                let oltp_records = self.query_oltp_filtered(key, oltp_start, end_ts, &filter)?;
+                results.extend(oltp_records);
+            }
         }
 
         // 3. 按时间戳排序
@@ -511,7 +585,7 @@ impl OltpBatchAdapter {
     ) -> Result<Vec<Record>, BatchQueryError> {
         let results = self
             .storage
-            .range_query(start_ts, end_ts)
+            .scan_range(start_ts, end_ts)
             .map_err(BatchQueryError::IoError)?;
 
         // 使用过滤器进行高效过滤
@@ -701,19 +775,22 @@ impl BatchDataSource for OltpBatchAdapter {
         _fields: &[String],
     ) -> Result<Vec<Record>, BatchQueryError> {
         let mut results = Vec::new();
+        let olap_queried = start_ts < self.olap_cutoff_timestamp && !self.olap_files.is_empty();
 
         // 查询 OLAP (历史数据)
-        if start_ts < self.olap_cutoff_timestamp && !self.olap_files.is_empty() {
+        if olap_queried {
             let olap_end = end_ts.min(self.olap_cutoff_timestamp);
             let olap_records = self.query_olap(key, start_ts, olap_end)?;
             results.extend(olap_records);
         }
 
-        // 查询 OLTP (近期数据)
+        // 查询 OLTP (近期数据)，受分析查询配额限制
         if end_ts >= self.olap_cutoff_timestamp {
-            let oltp_start = start_ts.max(self.olap_cutoff_timestamp);
-            let oltp_records = self.query_oltp(key, oltp_start, end_ts)?;
-            results.extend(oltp_records);
+            if let Some(_permit) = self.acquire_analytical(olap_queried)? {
+                let oltp_start = start_ts.max(self.olap_cutoff_timestamp);
+                let oltp_records = self.query_oltp(key, oltp_start, end_ts)?;
+                results.extend(oltp_records);
+            }
         }
 
         // 按时间戳排序
@@ -9,17 +9,31 @@
 // - 支持 OLTP + OLAP 混合查询
 // - 自动路由（根据时间范围）
 //
+// InstrumentPartitions：
+// - 分区模式下行情/成交按合约拆分 WAL + MemTable + SSTable
+// - 账户类数据保留在独立分区
+//
 // QueryFilter：
 // - 高性能零拷贝过滤器
 // - 位掩码类型过滤（O(1)）
 // - 支持时间/类型/合约/价格多维过滤
 //
+// QueryGate：
+// - 查询分类（在线 / 分析），在线查询直通
+// - 分析查询独立并发配额 + 有界排队，超限拒绝或降级到 OLAP 副本
+//
 // @yutiansut @quantaxis
 
 pub mod batch_source;
 pub mod oltp;
+pub mod partition;
 pub mod query_filter;
+pub mod query_gate;
 
 pub use batch_source::OltpBatchAdapter;
 pub use oltp::OltpHybridStorage;
+pub use partition::InstrumentPartitions;
 pub use query_filter::{QueryFilter, RecordType, RecordTypeSet, RecordCategory};
+pub use query_gate::{
+    AnalyticalPermit, AnalyticalQueryConfig, QueryClass, QueryGate, QueryGateError, QueryGateStats,
+};
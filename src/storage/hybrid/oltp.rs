@@ -10,18 +10,32 @@
 // - 读取延迟：P99 < 10μs (MemTable) / < 100μs (SSTable)
 // - 吞吐量：> 100K writes/s (单品种)
 
+use super::partition::InstrumentPartitions;
+use super::query_gate::{QueryClass, QueryGate};
 use crate::storage::checkpoint::CheckpointManager;
 use crate::storage::compaction::{CompactionConfig, CompactionScheduler, SSTableInfo};
+use crate::storage::index::InstrumentIndex;
+use crate::storage::conversion::partition::list_olap_parquet_files;
 use crate::storage::conversion::{ConversionManager, SchedulerConfig, WorkerConfig};
 use crate::storage::memtable::oltp::OltpMemTable;
-use crate::storage::memtable::types::MemTableValue;
+use crate::storage::memtable::types::{MemTableKey, MemTableValue};
 use crate::storage::sstable::olap_parquet::ParquetSSTable;
 use crate::storage::sstable::oltp_rkyv::{RkyvSSTable, RkyvSSTableWriter};
+use crate::storage::sstable::types::SSTableMetadata;
 use crate::storage::wal::{WalManager, WalRecord};
+use crate::utils::timestamp::{now_nanos, secs_to_nanos};
 use parking_lot::RwLock;
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
+/// 等待 flush 的 immutable MemTable 上限
+///
+/// SSTable 持续写入失败时 immutable 会不断堆积，达到上限后写入先同步 flush（背压），
+/// 仍未降到上限以下则拒绝写入，避免内存无限增长
+pub const MAX_IMMUTABLE_MEMTABLES: usize = 8;
+
 /// OLTP 混合存储配置
 #[derive(Debug, Clone)]
 pub struct OltpHybridConfig {
@@ -67,7 +81,14 @@ impl Default for OltpHybridConfig {
 ///
 /// 数据流：
 /// Write Path: WAL → MemTable → SSTable → Parquet (异步转换)
-/// Read Path:  MemTable + SSTable (OLTP) + Parquet (OLAP)
+/// Read Path:  MemTable → Immutable MemTables → SSTable (OLTP) + Parquet (OLAP)
+///
+/// Flush 过程：活跃 MemTable 先冻结进入 immutable 列表（仍可被查询），
+/// SSTable 写完并注册成功后才从 immutable 列表移除，保证任意时刻数据可见。
+///
+/// 分区模式（`create_partitioned`）：行情、成交记录按合约路由到
+/// `{instrument_id}/partitions/{合约}/` 下的独立存储，本身只保存账户类数据；
+/// 查询接口保持不变，按合约查询只扫对应分区，跨分区查询归并结果。
 pub struct OltpHybridStorage {
     /// 品种 ID
     instrument_id: String,
@@ -76,7 +97,13 @@ pub struct OltpHybridStorage {
     wal: Arc<WalManager>,
 
     /// 当前活跃的 MemTable
-    memtable: Arc<RwLock<OltpMemTable>>,
+    memtable: Arc<RwLock<Arc<OltpMemTable>>>,
+
+    /// 已冻结、等待 flush 的 MemTable（按冻结顺序，旧的在前）
+    immutable_memtables: Arc<RwLock<Vec<Arc<OltpMemTable>>>>,
+
+    /// Flush 互斥锁（同一时刻只有一个线程写 SSTable）
+    flush_lock: Arc<parking_lot::Mutex<()>>,
 
     /// 已持久化的 SSTable 列表（按时间排序）
     sstables: Arc<RwLock<Vec<Arc<RkyvSSTable>>>>,
@@ -104,6 +131,18 @@ pub struct OltpHybridStorage {
 
     /// SSTable 计数器（用于生成文件名）
     sstable_counter: Arc<parking_lot::Mutex<u64>>,
+
+    /// 订单簿快照二级索引（合约 → 时间戳 → 序列号），用于历史盘口定位
+    snapshot_index: Arc<RwLock<InstrumentIndex>>,
+
+    /// 合约分区（None 表示单分区模式）
+    partitions: Option<Arc<InstrumentPartitions>>,
+
+    /// 查询通道闸门（分析查询限并发，在线查询直通）
+    query_gate: Arc<QueryGate>,
+
+    /// 写入触发的自动 flush 失败次数
+    flush_failures: Arc<AtomicU64>,
 }
 
 impl OltpHybridStorage {
@@ -117,6 +156,33 @@ impl OltpHybridStorage {
         let wal_path = base_path.join("wal");
         let wal = Arc::new(WalManager::new(wal_path.to_str().unwrap()));
 
+        Self::create_with_wal(instrument_id, config, wal)
+    }
+
+    /// 创建按合约分区的混合存储
+    ///
+    /// 行情与成交记录（见 `WalRecord::partition_instrument`）按合约写入独立分区，
+    /// 账户类记录写入本存储；已有分区在创建时并行加载
+    pub fn create_partitioned(storage_id: &str, config: OltpHybridConfig) -> Result<Self, String> {
+        let mut storage = Self::create(storage_id, config)?;
+        let root_path = PathBuf::from(&storage.config.base_path).join(storage_id);
+        storage.partitions = Some(Arc::new(InstrumentPartitions::open(
+            &root_path,
+            &storage.config,
+        )?));
+        Ok(storage)
+    }
+
+    /// 使用指定 WAL 创建混合存储（分区存储由 PerInstrumentWalManager 提供 WAL）
+    pub(crate) fn create_with_wal(
+        instrument_id: &str,
+        config: OltpHybridConfig,
+        wal: Arc<WalManager>,
+    ) -> Result<Self, String> {
+        let base_path = PathBuf::from(&config.base_path).join(instrument_id);
+        std::fs::create_dir_all(&base_path)
+            .map_err(|e| format!("Create base path failed: {}", e))?;
+
         // 创建 SSTable 目录
         let sstable_path = base_path.join("sstables");
         std::fs::create_dir_all(&sstable_path)
@@ -132,7 +198,7 @@ impl OltpHybridStorage {
             max_size_bytes: config.memtable_size_bytes,
             estimated_entry_size: config.estimated_entry_size,
         };
-        let memtable = Arc::new(RwLock::new(OltpMemTable::new(memtable_config)));
+        let memtable = Arc::new(RwLock::new(Arc::new(OltpMemTable::new(memtable_config))));
 
         // 创建 Compaction 调度器
         let compaction_config = CompactionConfig::default();
@@ -186,6 +252,8 @@ impl OltpHybridStorage {
             instrument_id: instrument_id.to_string(),
             wal: wal.clone(),
             memtable: memtable.clone(),
+            immutable_memtables: Arc::new(RwLock::new(Vec::new())),
+            flush_lock: Arc::new(parking_lot::Mutex::new(())),
             sstables: Arc::new(RwLock::new(Vec::new())),
             olap_files,
             olap_cutoff_timestamp: Arc::new(parking_lot::Mutex::new(olap_cutoff)),
@@ -195,12 +263,17 @@ impl OltpHybridStorage {
             conversion_manager,
             config,
             sstable_counter: Arc::new(parking_lot::Mutex::new(0)),
+            snapshot_index: Arc::new(RwLock::new(InstrumentIndex::new())),
+            partitions: None,
+            query_gate: Arc::new(QueryGate::default()),
+            flush_failures: Arc::new(AtomicU64::new(0)),
         };
 
         // 从 WAL 重放数据到 MemTable（恢复时必需）
         log::info!("[{}] Replaying WAL to MemTable...", instrument_id);
         let mut replayed_count = 0;
         wal.replay(|entry| {
+            storage.index_snapshot(entry.sequence, &entry.record);
             let memtable = memtable.read();
             memtable.insert(entry.sequence, entry.record);
             replayed_count += 1;
@@ -219,7 +292,7 @@ impl OltpHybridStorage {
         Ok(storage)
     }
 
-    /// 加载 OLAP Parquet 文件
+    /// 加载 OLAP Parquet 文件（含 {yyyymm} 月份分区）
     fn load_olap_files(olap_path: &PathBuf) -> Result<Vec<Arc<ParquetSSTable>>, String> {
         let mut files = Vec::new();
 
@@ -227,18 +300,7 @@ impl OltpHybridStorage {
             return Ok(files);
         }
 
-        let entries = std::fs::read_dir(olap_path)
-            .map_err(|e| format!("Read olap dir failed: {}", e))?;
-
-        let mut parquet_paths: Vec<_> = entries
-            .filter_map(|e| e.ok())
-            .filter(|e| e.path().extension().and_then(|s| s.to_str()) == Some("parquet"))
-            .map(|e| e.path())
-            .collect();
-
-        parquet_paths.sort();
-
-        for path in parquet_paths {
+        for path in list_olap_parquet_files(olap_path) {
             match ParquetSSTable::open(&path) {
                 Ok(sstable) => {
                     log::debug!("Loaded OLAP file: {:?}", path);
@@ -265,11 +327,19 @@ impl OltpHybridStorage {
 
     /// 写入记录（WAL + MemTable）
     ///
+    /// 分区模式下行情/成交记录写入对应合约分区，返回分区内序列号
+    ///
     /// # Performance
     /// - P99 < 100μs (WAL fsync ~20-50μs + MemTable insert ~3μs)
     pub fn write(&self, record: WalRecord) -> Result<u64, String> {
+        if let Some(partition) = self.route(&record)? {
+            return partition.write(record);
+        }
+        self.check_immutable_backlog()?;
+
         // 1. 写入 WAL（持久化）
         let sequence = self.wal.append(record.clone())?;
+        self.index_snapshot(sequence, &record);
 
         // 2. 写入 MemTable（内存索引）
         let memtable = self.memtable.read();
@@ -278,7 +348,7 @@ impl OltpHybridStorage {
         // 3. 检查是否需要 flush
         if memtable.should_flush() {
             drop(memtable); // 释放读锁
-            self.try_flush()?;
+            self.try_flush();
         }
 
         Ok(sequence)
@@ -293,24 +363,66 @@ impl OltpHybridStorage {
         if records.is_empty() {
             return Ok(Vec::new());
         }
+        if self.partitions.is_some() {
+            return self.write_batch_partitioned(records);
+        }
+
+        self.write_batch_local(records)
+    }
+
+    /// 分区模式下的路由：返回记录所属的合约分区（账户类记录返回 None）
+    fn route(&self, record: &WalRecord) -> Result<Option<Arc<OltpHybridStorage>>, String> {
+        match (&self.partitions, record.partition_instrument()) {
+            (Some(partitions), Some(instrument_id)) => {
+                partitions.get_or_create(&instrument_id).map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// 分区模式批量写入：按分区分组，每个分区一次批量写入，返回值与输入顺序一致
+    fn write_batch_partitioned(&self, records: Vec<WalRecord>) -> Result<Vec<u64>, String> {
+        let mut grouped: HashMap<Option<String>, (Vec<usize>, Vec<WalRecord>)> = HashMap::new();
+        for (idx, record) in records.into_iter().enumerate() {
+            let group = grouped.entry(record.partition_instrument()).or_default();
+            group.0.push(idx);
+            group.1.push(record);
+        }
+
+        let mut sequences = vec![0u64; grouped.values().map(|(idx, _)| idx.len()).sum()];
+        for (instrument_id, (indices, group_records)) in grouped {
+            let written = match (instrument_id, &self.partitions) {
+                (Some(instrument_id), Some(partitions)) => partitions
+                    .get_or_create(&instrument_id)?
+                    .write_batch(group_records)?,
+                _ => self.write_batch_local(group_records)?,
+            };
+            for (idx, seq) in indices.into_iter().zip(written) {
+                sequences[idx] = seq;
+            }
+        }
+        Ok(sequences)
+    }
+
+    /// 批量写入本存储（不做分区路由）
+    fn write_batch_local(&self, records: Vec<WalRecord>) -> Result<Vec<u64>, String> {
+        self.check_immutable_backlog()?;
 
         // 1. 批量写入 WAL
         let sequences = self.wal.append_batch(records.clone())?;
+        for (&seq, record) in sequences.iter().zip(records.iter()) {
+            self.index_snapshot(seq, record);
+        }
 
         // 2. 批量写入 MemTable
-        let entries: Vec<_> = sequences
-            .iter()
-            .zip(records.iter())
-            .map(|(&seq, record)| (seq, record.clone()))
-            .collect();
-
+        let entries: Vec<_> = sequences.iter().copied().zip(records).collect();
         let memtable = self.memtable.read();
         memtable.insert_batch(entries);
 
         // 3. 检查是否需要 flush
         if memtable.should_flush() {
             drop(memtable);
-            self.try_flush()?;
+            self.try_flush();
         }
 
         Ok(sequences)
@@ -318,31 +430,152 @@ impl OltpHybridStorage {
 
     /// 范围查询（时间范围）
     ///
+    /// 分区模式下归并账户分区与所有合约分区的结果（序列号为各分区内序列号）
+    ///
     /// # Performance
     /// - MemTable: P99 < 10μs
     /// - SSTable: P99 < 100μs (per file)
     ///
     /// # Strategy
-    /// 1. 查询 MemTable（最新数据）
-    /// 2. 查询所有相关的 SSTable（按时间过滤）
-    /// 3. 合并结果（去重、排序）
+    /// 1. 查询活跃 MemTable（最新数据）
+    /// 2. 查询 immutable MemTable（已冻结、flush 中的数据）
+    /// 3. 查询所有相关的 SSTable（按时间过滤）
+    /// 4. 合并结果（去重、排序）
+    ///
+    /// 查询顺序必须为 active → immutables → SSTables：flush 先加入 immutable 再切换活跃表，
+    /// 先注册 SSTable 再移除 immutable，按此顺序读取不会漏掉正在 flush 的数据。
+    ///
+    /// 走在线通道（不限流），大范围扫描请使用 `query_range(QueryClass::Analytical, ..)`
     pub fn range_query(
         &self,
         start_ts: i64,
         end_ts: i64,
+    ) -> Result<Vec<(i64, u64, WalRecord)>, String> {
+        self.query_gate.record_online();
+        self.scan_range(start_ts, end_ts)
+    }
+
+    /// 按查询分类执行范围查询
+    ///
+    /// 分析查询先申请配额，配额用尽时排队，排队满或超时返回错误
+    pub fn query_range(
+        &self,
+        class: QueryClass,
+        start_ts: i64,
+        end_ts: i64,
+    ) -> Result<Vec<(i64, u64, WalRecord)>, String> {
+        match class {
+            QueryClass::Online => self.range_query(start_ts, end_ts),
+            QueryClass::Analytical => {
+                let _permit = self.query_gate.acquire().map_err(|e| e.to_string())?;
+                self.scan_range(start_ts, end_ts)
+            }
+        }
+    }
+
+    /// 查询通道闸门（分析查询配额与统计）
+    pub fn query_gate(&self) -> &Arc<QueryGate> {
+        &self.query_gate
+    }
+
+    /// 范围扫描（不区分查询通道，调用方已完成分类与准入）
+    pub(crate) fn scan_range(
+        &self,
+        start_ts: i64,
+        end_ts: i64,
+    ) -> Result<Vec<(i64, u64, WalRecord)>, String> {
+        let mut results = self.range_query_local(start_ts, end_ts)?;
+
+        if let Some(ref partitions) = self.partitions {
+            // 各分区内已去重；分区间序列号独立，只排序不去重
+            for partition in partitions.all() {
+                results.extend(partition.scan_range(start_ts, end_ts)?);
+            }
+            results.sort_by_key(|(ts, seq, _)| (*ts, *seq));
+        }
+
+        Ok(results)
+    }
+
+    /// 按合约范围查询（在线通道）
+    ///
+    /// 分区模式下只扫描该合约的分区；单分区模式下扫描全部数据并按合约过滤
+    pub fn range_query_instrument(
+        &self,
+        instrument_id: &str,
+        start_ts: i64,
+        end_ts: i64,
+    ) -> Result<Vec<(i64, u64, WalRecord)>, String> {
+        self.query_gate.record_online();
+        self.scan_range_instrument(instrument_id, start_ts, end_ts)
+    }
+
+    /// 按查询分类执行合约范围查询
+    pub fn query_range_instrument(
+        &self,
+        class: QueryClass,
+        instrument_id: &str,
+        start_ts: i64,
+        end_ts: i64,
+    ) -> Result<Vec<(i64, u64, WalRecord)>, String> {
+        match class {
+            QueryClass::Online => self.range_query_instrument(instrument_id, start_ts, end_ts),
+            QueryClass::Analytical => {
+                let _permit = self.query_gate.acquire().map_err(|e| e.to_string())?;
+                self.scan_range_instrument(instrument_id, start_ts, end_ts)
+            }
+        }
+    }
+
+    /// 合约范围扫描（不区分查询通道）
+    pub(crate) fn scan_range_instrument(
+        &self,
+        instrument_id: &str,
+        start_ts: i64,
+        end_ts: i64,
+    ) -> Result<Vec<(i64, u64, WalRecord)>, String> {
+        match self.partitions {
+            Some(ref partitions) => match partitions.get(instrument_id) {
+                Some(partition) => partition.scan_range(start_ts, end_ts),
+                None => Ok(Vec::new()),
+            },
+            None => Ok(self
+                .scan_range(start_ts, end_ts)?
+                .into_iter()
+                .filter(|(_, _, record)| {
+                    record.partition_instrument().as_deref() == Some(instrument_id)
+                })
+                .collect()),
+        }
+    }
+
+    /// 查询本存储（不含合约分区）
+    fn range_query_local(
+        &self,
+        start_ts: i64,
+        end_ts: i64,
     ) -> Result<Vec<(i64, u64, WalRecord)>, String> {
         let mut results = Vec::new();
 
-        // 1. 查询 MemTable
-        let memtable = self.memtable.read();
+        // 1. 查询活跃 MemTable
+        let memtable = self.memtable.read().clone();
         let memtable_results = memtable.range_query(start_ts, end_ts);
         // 转换 MemTable 结果格式：(MemTableKey, WalRecord) -> (i64, u64, WalRecord)
         for (key, record) in memtable_results {
             results.push((key.timestamp, key.sequence, record));
         }
 
-        // 2. 查询 SSTable（只查询时间范围重叠的文件）
-        let sstables = self.sstables.read();
+        // 2. 查询 immutable MemTable
+        let immutables = self.immutable_memtables.read().clone();
+        for immutable in immutables.iter() {
+            for (key, record) in immutable.range_query(start_ts, end_ts) {
+                results.push((key.timestamp, key.sequence, record));
+            }
+        }
+
+        // 3. 查询 SSTable（只查询时间范围重叠的文件）
+        // 先复制文件列表再扫描，长时间扫描不持有读锁，避免阻塞 flush 注册及其后排队的读
+        let sstables = self.sstables.read().clone();
         for sstable in sstables.iter() {
             let metadata = sstable.metadata();
 
@@ -351,31 +584,250 @@ impl OltpHybridStorage {
                 continue;
             }
 
+            // 已被转换或保留策略移除的文件跳过
+            if !Path::new(sstable.file_path()).exists() {
+                continue;
+            }
+
             let sstable_results = sstable.range_query(start_ts, end_ts)?;
             results.extend(sstable_results);
         }
 
-        // 3. 排序和去重（按 timestamp + sequence）
+        // 4. 排序和去重（按 timestamp + sequence）
         results.sort_by_key(|(ts, seq, _)| (*ts, *seq));
         results.dedup_by_key(|(ts, seq, _)| (*ts, *seq));
 
         Ok(results)
     }
 
+    /// 索引订单簿快照记录
+    fn index_snapshot(&self, sequence: u64, record: &WalRecord) {
+        if let WalRecord::OrderBookSnapshot {
+            instrument_id,
+            timestamp,
+            ..
+        } = record
+        {
+            let instrument = WalRecord::from_fixed_array(instrument_id);
+            self.snapshot_index
+                .write()
+                .add(&instrument, *timestamp, sequence);
+        }
+    }
+
+    /// 查询不晚于 timestamp 的最近一次订单簿快照
+    ///
+    /// 通过二级索引定位快照时间戳后，只读取覆盖该时间戳的 MemTable/SSTable；
+    /// 该时刻之前没有快照时返回 None
+    pub fn orderbook_snapshot_at(
+        &self,
+        instrument_id: &str,
+        timestamp: i64,
+    ) -> Result<Option<(i64, WalRecord)>, String> {
+        if let Some(ref partitions) = self.partitions {
+            return match partitions.get(instrument_id) {
+                Some(partition) => partition.orderbook_snapshot_at(instrument_id, timestamp),
+                None => Ok(None),
+            };
+        }
+
+        let located = self
+            .snapshot_index
+            .read()
+            .query_latest_at(instrument_id, timestamp);
+        let Some((snapshot_ts, _)) = located else {
+            return Ok(None);
+        };
+
+        Ok(self
+            .orderbook_snapshots_between(instrument_id, snapshot_ts, snapshot_ts, usize::MAX)?
+            .pop())
+    }
+
+    /// 查询时间范围内的订单簿快照序列（按时间升序，最多 limit 条），用于盘口回放
+    pub fn orderbook_snapshots_between(
+        &self,
+        instrument_id: &str,
+        start_ts: i64,
+        end_ts: i64,
+        limit: usize,
+    ) -> Result<Vec<(i64, WalRecord)>, String> {
+        if let Some(ref partitions) = self.partitions {
+            return match partitions.get(instrument_id) {
+                Some(partition) => {
+                    partition.orderbook_snapshots_between(instrument_id, start_ts, end_ts, limit)
+                }
+                None => Ok(Vec::new()),
+            };
+        }
+
+        let timestamps = self
+            .snapshot_index
+            .read()
+            .query_timestamps(instrument_id, start_ts, end_ts);
+        if timestamps.is_empty() || limit == 0 {
+            return Ok(Vec::new());
+        }
+        // 只读取前 limit 个快照覆盖的时间段
+        let first = timestamps[0];
+        let last = timestamps[limit.min(timestamps.len()) - 1];
+
+        let snapshots = self
+            .range_query(first, last)?
+            .into_iter()
+            .filter(|(_, _, record)| {
+                matches!(
+                    record,
+                    WalRecord::OrderBookSnapshot { instrument_id: id, .. }
+                        if WalRecord::from_fixed_array(id) == instrument_id
+                )
+            })
+            .map(|(ts, _, record)| (ts, record))
+            .take(limit)
+            .collect();
+        Ok(snapshots)
+    }
+
     /// Flush MemTable 到 SSTable
     ///
     /// # Performance
     /// - 写入速度：> 100 MB/s
-    /// - 阻塞时间：最小化（使用双缓冲）
-    fn try_flush(&self) -> Result<(), String> {
-        // 获取写锁（阻塞写入）
-        let memtable = self.memtable.write();
+    /// - 阻塞时间：仅冻结活跃 MemTable 时短暂持有写锁，SSTable 写入期间不阻塞读写
+    ///
+    /// 由写入路径触发，此时记录已写入 WAL 和 MemTable，flush 失败不影响写入结果：
+    /// immutable 保留在列表中（仍可查询，重启由 WAL 恢复），计入 `flush_failures`，
+    /// 下次自动 flush 或手动 [`flush`](Self::flush) 时重试
+    fn try_flush(&self) {
+        self.freeze_memtable(false);
+        if let Err(e) = self.flush_local_immutable_memtables() {
+            self.flush_failures.fetch_add(1, Ordering::Relaxed);
+            log::error!(
+                "[{}] Auto flush failed after write, pending immutable MemTables: {}, error: {}",
+                self.instrument_id,
+                self.immutable_memtables.read().len(),
+                e
+            );
+        }
+    }
 
-        // 快速检查是否真的需要 flush
-        if !memtable.should_flush() {
+    /// 写入前检查 immutable 堆积：达到 [`MAX_IMMUTABLE_MEMTABLES`] 时等待进行中的 flush 并同步重试，
+    /// 仍未降到上限以下则拒绝本次写入（记录尚未写入 WAL）
+    fn check_immutable_backlog(&self) -> Result<(), String> {
+        if self.immutable_memtables.read().len() < MAX_IMMUTABLE_MEMTABLES {
             return Ok(());
         }
 
+        let flushed = {
+            let _flush_guard = self.flush_lock.lock();
+            self.flush_immutable_locked()
+        };
+        let pending = self.immutable_memtables.read().len();
+        match flushed {
+            _ if pending < MAX_IMMUTABLE_MEMTABLES => Ok(()),
+            Ok(_) => Err(format!(
+                "[{}] {} immutable MemTables pending flush (limit {}), write rejected",
+                self.instrument_id, pending, MAX_IMMUTABLE_MEMTABLES
+            )),
+            Err(e) => {
+                self.flush_failures.fetch_add(1, Ordering::Relaxed);
+                Err(format!(
+                    "[{}] {} immutable MemTables pending flush (limit {}), write rejected: {}",
+                    self.instrument_id, pending, MAX_IMMUTABLE_MEMTABLES, e
+                ))
+            }
+        }
+    }
+
+    /// 手动 flush：冻结活跃 MemTable（不论大小）并将所有 immutable 写入 SSTable
+    ///
+    /// 与自动 flush 共用 flush 锁，自动 flush 进行中时等待其完成，不会重复写入。
+    ///
+    /// # Returns
+    /// 本次 flush 的 MemTable 数量（分区模式下含各分区）
+    pub fn flush(&self) -> Result<usize, String> {
+        let mut flushed = {
+            let _flush_guard = self.flush_lock.lock();
+            self.freeze_memtable(true);
+            self.flush_immutable_locked()?
+        };
+        if let Some(ref partitions) = self.partitions {
+            for partition in partitions.all() {
+                flushed += partition.flush()?;
+            }
+        }
+        Ok(flushed)
+    }
+
+    /// 冻结活跃 MemTable：加入 immutable 列表并切换为新的空 MemTable
+    ///
+    /// 先加入 immutable 再切换活跃表，查询路径始终能看到被冻结的数据。
+    /// `force` 为 true 时不检查大小阈值（手动 flush）
+    fn freeze_memtable(&self, force: bool) -> bool {
+        let mut memtable = self.memtable.write();
+
+        // 快速检查是否真的需要 flush
+        if (!force && !memtable.should_flush()) || memtable.is_empty() {
+            return false;
+        }
+
+        let memtable_config = crate::storage::memtable::oltp::OltpMemTableConfig {
+            max_size_bytes: self.config.memtable_size_bytes,
+            estimated_entry_size: self.config.estimated_entry_size,
+        };
+
+        self.immutable_memtables.write().push(memtable.clone());
+        *memtable = Arc::new(OltpMemTable::new(memtable_config));
+        true
+    }
+
+    /// 将所有 immutable MemTable 写入 SSTable（旧的优先）
+    ///
+    /// 失败时 immutable 保留在列表中（数据仍可查询），下次 flush 或手动调用时重试。
+    /// 已有其他线程在 flush 时直接返回，由该线程负责处理新加入的 immutable。
+    ///
+    /// # Returns
+    /// 本次成功 flush 的 MemTable 数量（分区模式下含各分区）
+    pub fn flush_immutable_memtables(&self) -> Result<usize, String> {
+        let mut flushed = self.flush_local_immutable_memtables()?;
+        if let Some(ref partitions) = self.partitions {
+            for partition in partitions.all() {
+                flushed += partition.flush_immutable_memtables()?;
+            }
+        }
+        Ok(flushed)
+    }
+
+    fn flush_local_immutable_memtables(&self) -> Result<usize, String> {
+        let _flush_guard = match self.flush_lock.try_lock() {
+            Some(guard) => guard,
+            None => return Ok(0),
+        };
+        self.flush_immutable_locked()
+    }
+
+    /// 写入所有 immutable MemTable（调用方需持有 flush 锁）
+    fn flush_immutable_locked(&self) -> Result<usize, String> {
+        let mut flushed = 0;
+        loop {
+            let immutable = match self.immutable_memtables.read().first() {
+                Some(m) => m.clone(),
+                None => break,
+            };
+
+            self.write_sstable(&immutable)?;
+
+            // SSTable 已注册，此时才从 immutable 列表移除
+            self.immutable_memtables
+                .write()
+                .retain(|m| !Arc::ptr_eq(m, &immutable));
+            flushed += 1;
+        }
+
+        Ok(flushed)
+    }
+
+    /// 将单个 MemTable 写入 SSTable 并注册到查询列表与 Compaction Scheduler
+    fn write_sstable(&self, memtable: &OltpMemTable) -> Result<(), String> {
         // 获取所有数据
         let entries = memtable.iter_all();
         if entries.is_empty() {
@@ -393,17 +845,20 @@ impl OltpHybridStorage {
             .join("sstables")
             .join(format!("{:010}.sst", sstable_id));
 
-        // 创建 SSTable Writer
-        let mut writer = RkyvSSTableWriter::create(&sstable_path)?;
-
-        // 写入数据
-        for (key, record) in entries {
-            let value = MemTableValue::new(record);
-            writer.append(key, value)?;
-        }
-
-        // 完成写入
-        let metadata = writer.finish()?;
+        let metadata = match Self::write_sstable_file(&sstable_path, entries) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                // 清理写了一半的文件，immutable 保留待重试
+                let _ = std::fs::remove_file(&sstable_path);
+                log::warn!(
+                    "[{}] Flush MemTable to {:?} failed, will retry: {}",
+                    self.instrument_id,
+                    sstable_path,
+                    e
+                );
+                return Err(e);
+            }
+        };
 
         log::info!(
             "[{}] Flushed MemTable to SSTable: {} entries, {}-{} ns",
@@ -413,9 +868,6 @@ impl OltpHybridStorage {
             metadata.max_timestamp
         );
 
-        // 清空 MemTable
-        memtable.clear();
-
         // 加载新的 SSTable
         let sstable = Arc::new(RkyvSSTable::open(&sstable_path)?);
         self.sstables.write().push(sstable);
@@ -441,13 +893,36 @@ impl OltpHybridStorage {
         Ok(())
     }
 
+    fn write_sstable_file(
+        sstable_path: &Path,
+        entries: Vec<(MemTableKey, WalRecord)>,
+    ) -> Result<SSTableMetadata, String> {
+        // 创建 SSTable Writer
+        let mut writer = RkyvSSTableWriter::create(sstable_path)?;
+
+        // 写入数据
+        for (key, record) in entries {
+            let value = MemTableValue::new(record);
+            writer.append(key, value)?;
+        }
+
+        // 完成写入
+        writer.finish()
+    }
+
     /// 恢复（从 WAL 回放）
     ///
     /// # Recovery Process
     /// 1. 加载所有 SSTable
     /// 2. 回放 WAL 到 MemTable
     /// 3. 验证数据完整性
+    ///
+    /// 分区模式下各合约分区并行恢复
     pub fn recover(&self) -> Result<(), String> {
+        if let Some(ref partitions) = self.partitions {
+            partitions.for_each_parallel(|partition| partition.recover())?;
+        }
+
         // 1. 加载所有 SSTable
         let sstable_dir = PathBuf::from(&self.config.base_path)
             .join(&self.instrument_id)
@@ -484,6 +959,14 @@ impl OltpHybridStorage {
                 };
 
                 self.compaction_scheduler.register_sstable(sstable_info);
+
+                // 重建订单簿快照索引（WAL 部分在 create 时已建立）
+                for (_, seq, record) in
+                    sstable.range_query(metadata.min_timestamp, metadata.max_timestamp)?
+                {
+                    self.index_snapshot(seq, &record);
+                }
+
                 self.sstables.write().push(sstable);
             }
 
@@ -512,17 +995,75 @@ impl OltpHybridStorage {
         Ok(())
     }
 
-    /// 获取统计信息
+    /// 获取统计信息（分区模式下为所有分区合计）
     pub fn stats(&self) -> StorageStats {
+        let mut stats = self.local_stats();
+        if let Some(ref partitions) = self.partitions {
+            for partition in partitions.all() {
+                let p = partition.stats();
+                stats.memtable_entries += p.memtable_entries;
+                stats.memtable_size_bytes += p.memtable_size_bytes;
+                stats.immutable_memtable_count += p.immutable_memtable_count;
+                stats.immutable_entries += p.immutable_entries;
+                stats.sstable_count += p.sstable_count;
+                stats.sstable_entries += p.sstable_entries;
+                stats.flush_failures += p.flush_failures;
+                stats.min_timestamp = match (stats.min_timestamp, p.min_timestamp) {
+                    (Some(a), Some(b)) => Some(a.min(b)),
+                    (a, b) => a.or(b),
+                };
+                stats.max_timestamp = match (stats.max_timestamp, p.max_timestamp) {
+                    (Some(a), Some(b)) => Some(a.max(b)),
+                    (a, b) => a.or(b),
+                };
+            }
+        }
+        stats
+    }
+
+    /// 各合约分区的统计信息（单分区模式下为空）
+    pub fn partition_stats(&self) -> Vec<StorageStats> {
+        self.partitions
+            .as_ref()
+            .map(|partitions| partitions.all().iter().map(|p| p.stats()).collect())
+            .unwrap_or_default()
+    }
+
+    /// 是否为分区模式
+    pub fn is_partitioned(&self) -> bool {
+        self.partitions.is_some()
+    }
+
+    /// 合约分区列表（单分区模式下为空）
+    pub fn partition_instruments(&self) -> Vec<String> {
+        self.partitions
+            .as_ref()
+            .map(|partitions| partitions.instruments())
+            .unwrap_or_default()
+    }
+
+    /// 获取合约分区存储（用于按合约构建 OltpBatchAdapter 等）
+    pub fn partition(&self, instrument_id: &str) -> Option<Arc<OltpHybridStorage>> {
+        self.partitions
+            .as_ref()
+            .and_then(|partitions| partitions.get(instrument_id))
+    }
+
+    /// 本存储的统计信息（不含合约分区）
+    fn local_stats(&self) -> StorageStats {
         let memtable = self.memtable.read();
+        let immutables = self.immutable_memtables.read();
         let sstables = self.sstables.read();
 
         StorageStats {
             instrument_id: self.instrument_id.clone(),
             memtable_entries: memtable.len(),
             memtable_size_bytes: memtable.size_bytes(),
+            immutable_memtable_count: immutables.len(),
+            immutable_entries: immutables.iter().map(|m| m.len()).sum(),
             sstable_count: sstables.len(),
             sstable_entries: sstables.iter().map(|s| s.metadata().entry_count).sum(),
+            flush_failures: self.flush_failures.load(Ordering::Relaxed),
             min_timestamp: memtable
                 .min_timestamp()
                 .or_else(|| sstables.first().map(|s| s.metadata().min_timestamp)),
@@ -537,13 +1078,33 @@ impl OltpHybridStorage {
         self.compaction_scheduler.get_stats()
     }
 
-    /// 手动触发 Compaction
+    /// 手动触发 Compaction（分区模式下各分区独立 compaction）
     pub fn trigger_compaction(&self) -> Result<(), String> {
+        if let Some(ref partitions) = self.partitions {
+            partitions.for_each_parallel(|partition| partition.compact().map(|_| ()))?;
+        }
         self.compaction_scheduler.trigger_compaction()
     }
 
-    /// 创建 Checkpoint（快照当前状态）
+    /// 执行一轮 Compaction，返回实际执行 compaction 的分区数
+    ///
+    /// 与后台调度互斥，无需 compaction 的分区跳过
+    pub fn compact(&self) -> Result<usize, String> {
+        let mut compacted = usize::from(self.compaction_scheduler.compact_once()?);
+        if let Some(ref partitions) = self.partitions {
+            for partition in partitions.all() {
+                compacted += partition.compact()?;
+            }
+        }
+        Ok(compacted)
+    }
+
+    /// 创建 Checkpoint（快照当前状态，分区模式下每个分区独立 checkpoint）
     pub fn create_checkpoint(&self) -> Result<(), String> {
+        if let Some(ref partitions) = self.partitions {
+            partitions.for_each_parallel(|partition| partition.create_checkpoint())?;
+        }
+
         // 获取当前 WAL 序列号
         let wal_sequence = self.wal.get_current_sequence();
 
@@ -601,8 +1162,12 @@ impl OltpHybridStorage {
         Ok(())
     }
 
-    /// 从最新的 Checkpoint 恢复
+    /// 从最新的 Checkpoint 恢复（分区模式下各分区并行恢复）
     pub fn recover_from_checkpoint(&self) -> Result<(), String> {
+        if let Some(ref partitions) = self.partitions {
+            partitions.for_each_parallel(|partition| partition.recover_from_checkpoint())?;
+        }
+
         // 加载最新 Checkpoint
         let checkpoint = match self.checkpoint_manager.load_latest_checkpoint()? {
             Some(ckpt) => ckpt,
@@ -670,11 +1235,21 @@ impl OltpHybridStorage {
     /// 获取 OLAP 文件列表
     ///
     /// 用于 OltpBatchAdapter 构建混合查询
+    ///
+    /// 分区模式下包含所有分区的文件；按合约查询建议对 `partition()` 单独构建适配器
     pub fn get_olap_files(&self) -> Vec<Arc<ParquetSSTable>> {
-        self.olap_files.read().clone()
+        let mut files = self.olap_files.read().clone();
+        if let Some(ref partitions) = self.partitions {
+            for partition in partitions.all() {
+                files.extend(partition.get_olap_files());
+            }
+        }
+        files
     }
 
     /// 获取 WAL 管理器引用（用于历史数据查询）@yutiansut @quantaxis
+    ///
+    /// 分区模式下为账户分区的 WAL，合约数据请使用 `range_query_instrument`
     pub fn get_wal_manager(&self) -> Arc<WalManager> {
         self.wal.clone()
     }
@@ -682,14 +1257,33 @@ impl OltpHybridStorage {
     /// 获取 OLAP 时间边界
     ///
     /// 早于此时间的数据应该查询 OLAP
+    ///
+    /// 分区模式下取各分区（含 OLAP 数据）边界的最小值，保证不会漏查 OLTP 数据
     pub fn get_olap_cutoff_timestamp(&self) -> i64 {
-        *self.olap_cutoff_timestamp.lock()
+        let local = *self.olap_cutoff_timestamp.lock();
+        match self.partitions {
+            Some(ref partitions) => partitions
+                .all()
+                .iter()
+                .map(|p| p.get_olap_cutoff_timestamp())
+                .chain(std::iter::once(local))
+                .filter(|cutoff| *cutoff > 0)
+                .min()
+                .unwrap_or(0),
+            None => local,
+        }
     }
 
     /// 刷新 OLAP 文件列表
     ///
     /// 在转换完成后调用以加载新的 Parquet 文件
     pub fn refresh_olap_files(&self) -> Result<(), String> {
+        if let Some(ref partitions) = self.partitions {
+            for partition in partitions.all() {
+                partition.refresh_olap_files()?;
+            }
+        }
+
         let olap_path = PathBuf::from(&self.config.base_path)
             .join(&self.instrument_id)
             .join("olap");
@@ -714,7 +1308,19 @@ impl OltpHybridStorage {
     /// 条件：
     /// 1. SSTable 数量超过阈值
     /// 2. 存在超过年龄阈值的数据
+    ///
+    /// 分区模式下逐个分区检查，任一分区触发即返回 true
     pub fn check_and_trigger_conversion(&self) -> Result<bool, String> {
+        let mut triggered = false;
+        if let Some(ref partitions) = self.partitions {
+            for partition in partitions.all() {
+                triggered |= partition.check_and_trigger_conversion()?;
+            }
+        }
+        Ok(self.check_and_trigger_local_conversion()? || triggered)
+    }
+
+    fn check_and_trigger_local_conversion(&self) -> Result<bool, String> {
         let conversion_manager = match &self.conversion_manager {
             Some(cm) => cm,
             None => return Ok(false),
@@ -727,9 +1333,8 @@ impl OltpHybridStorage {
             return Ok(false);
         }
 
-        // 条件 2：检查是否有超过年龄阈值的数据
-        let now = chrono::Utc::now().timestamp();
-        let age_threshold = now - self.config.olap_conversion_age_seconds;
+        // 条件 2：检查是否有超过年龄阈值的数据（SSTable 时间戳为纳秒，阈值换算到纳秒比较）
+        let age_threshold = now_nanos() - secs_to_nanos(self.config.olap_conversion_age_seconds);
 
         // 找到所有 max_timestamp < age_threshold 的 SSTable（完全过期）
         let old_sstables: Vec<PathBuf> = sstables
@@ -782,8 +1387,13 @@ pub struct StorageStats {
     pub instrument_id: String,
     pub memtable_entries: usize,
     pub memtable_size_bytes: usize,
+    /// 等待 flush 的 immutable MemTable 数量
+    pub immutable_memtable_count: usize,
+    pub immutable_entries: usize,
     pub sstable_count: usize,
     pub sstable_entries: u64,
+    /// 写入触发的自动 flush 失败次数（失败的 immutable 等待重试）
+    pub flush_failures: u64,
     pub min_timestamp: Option<i64>,
     pub max_timestamp: Option<i64>,
 }
@@ -859,6 +1469,302 @@ mod tests {
         println!("Stats: {:?}", stats);
     }
 
+    /// 自动 flush 失败不影响已写入 WAL/MemTable 的记录，修复后手动 flush 补写
+    #[tokio::test]
+    async fn test_write_succeeds_when_auto_flush_fails() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let config = OltpHybridConfig {
+            base_path: tmp_dir.path().to_str().unwrap().to_string(),
+            memtable_size_bytes: 1000,
+            estimated_entry_size: 100,
+            enable_olap_conversion: false,
+            ..Default::default()
+        };
+
+        let storage = OltpHybridStorage::create("IF2501", config).unwrap();
+
+        // SSTable 目录被同名文件占用，flush 必然失败
+        let sstable_dir = tmp_dir.path().join("IF2501").join("sstables");
+        let _ = std::fs::remove_dir_all(&sstable_dir);
+        std::fs::write(&sstable_dir, b"").unwrap();
+
+        for i in 0..50 {
+            storage
+                .write(create_order_record(i, 1000 + i as i64))
+                .unwrap();
+        }
+        let stats = storage.stats();
+        assert!(stats.flush_failures > 0);
+        assert_eq!(stats.sstable_count, 0);
+        assert!(stats.immutable_memtable_count > 0);
+        assert_eq!(storage.range_query(1000, 1049).unwrap().len(), 50);
+
+        // 恢复目录后重试 flush，immutable 全部落盘
+        std::fs::remove_file(&sstable_dir).unwrap();
+        std::fs::create_dir_all(&sstable_dir).unwrap();
+        storage.flush().unwrap();
+        let stats = storage.stats();
+        assert_eq!(stats.immutable_memtable_count, 0);
+        assert!(stats.sstable_count > 0);
+        assert_eq!(storage.range_query(1000, 1049).unwrap().len(), 50);
+    }
+
+    /// flush 持续失败时 immutable 堆积到上限后拒绝写入，恢复后写入先补写积压的 immutable
+    #[tokio::test]
+    async fn test_immutable_backlog_rejects_writes() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let config = OltpHybridConfig {
+            base_path: tmp_dir.path().to_str().unwrap().to_string(),
+            memtable_size_bytes: 1000,
+            estimated_entry_size: 100,
+            enable_olap_conversion: false,
+            ..Default::default()
+        };
+
+        let storage = OltpHybridStorage::create("IF2501", config).unwrap();
+        let sstable_dir = tmp_dir.path().join("IF2501").join("sstables");
+        let _ = std::fs::remove_dir_all(&sstable_dir);
+        std::fs::write(&sstable_dir, b"").unwrap();
+
+        let mut written = 0u64;
+        let rejected = (0..1000u64).find(|&i| {
+            let ok = storage
+                .write(create_order_record(i, 1000 + i as i64))
+                .is_ok();
+            written += ok as u64;
+            !ok
+        });
+        assert!(rejected.is_some());
+        assert_eq!(
+            storage.stats().immutable_memtable_count,
+            MAX_IMMUTABLE_MEMTABLES
+        );
+        // 被拒绝的记录未写入，已写入的仍可查询
+        assert_eq!(
+            storage.range_query(1000, 2000).unwrap().len() as u64,
+            written
+        );
+        assert!(storage
+            .write_batch(vec![create_order_record(5000, 5000)])
+            .is_err());
+
+        std::fs::remove_file(&sstable_dir).unwrap();
+        std::fs::create_dir_all(&sstable_dir).unwrap();
+        storage.write(create_order_record(5000, 5000)).unwrap();
+        let stats = storage.stats();
+        assert_eq!(stats.immutable_memtable_count, 0);
+        assert_eq!(
+            storage.range_query(1000, 5000).unwrap().len() as u64,
+            written + 1
+        );
+    }
+
+    /// 手动 flush：未达阈值的活跃 MemTable 也会落盘，数据仍可查询
+    #[tokio::test]
+    async fn test_manual_flush_persists_memtable() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let config = OltpHybridConfig {
+            base_path: tmp_dir.path().to_str().unwrap().to_string(),
+            enable_olap_conversion: false,
+            ..Default::default()
+        };
+
+        let storage = OltpHybridStorage::create("IF2501", config).unwrap();
+        for i in 0..5 {
+            storage
+                .write(create_order_record(i, 1000 + i as i64))
+                .unwrap();
+        }
+        let stats = storage.stats();
+        assert_eq!(stats.sstable_count, 0);
+        assert_eq!(stats.memtable_entries, 5);
+
+        assert_eq!(storage.flush().unwrap(), 1);
+        let stats = storage.stats();
+        assert_eq!(stats.sstable_count, 1);
+        assert_eq!(stats.sstable_entries, 5);
+        assert_eq!(stats.memtable_entries, 0);
+        assert_eq!(stats.immutable_memtable_count, 0);
+        assert_eq!(storage.range_query(0, i64::MAX).unwrap().len(), 5);
+
+        // 空 MemTable 不重复落盘
+        assert_eq!(storage.flush().unwrap(), 0);
+        assert_eq!(storage.stats().sstable_count, 1);
+    }
+
+    /// 高并发写入 + 持续查询：flush 期间每条写入后立刻可查
+    #[test]
+    fn test_concurrent_write_query_during_flush() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let config = OltpHybridConfig {
+            base_path: tmp_dir.path().to_str().unwrap().to_string(),
+            memtable_size_bytes: 2000, // 很小，频繁触发 flush
+            estimated_entry_size: 100,
+            enable_olap_conversion: false,
+            ..Default::default()
+        };
+
+        let storage = Arc::new(OltpHybridStorage::create("IF2501", config).unwrap());
+        let writers = 4;
+        let per_writer = 100u64;
+        let stop = Arc::new(std::sync::atomic::AtomicBool::new(false));
+
+        // 持续全量查询的线程：结果数量只增不减
+        let reader = {
+            let storage = storage.clone();
+            let stop = stop.clone();
+            std::thread::spawn(move || {
+                let mut last = 0;
+                while !stop.load(std::sync::atomic::Ordering::Relaxed) {
+                    let n = storage.range_query(0, i64::MAX).unwrap().len();
+                    assert!(n >= last, "query result shrank: {} -> {}", last, n);
+                    last = n;
+                }
+            })
+        };
+
+        let handles: Vec<_> = (0..writers)
+            .map(|w| {
+                let storage = storage.clone();
+                std::thread::spawn(move || {
+                    for i in 0..per_writer {
+                        let ts = 1_000_000 + (w * per_writer + i) as i64;
+                        storage.write(create_order_record(i, ts)).unwrap();
+                        let found = storage.range_query(ts, ts).unwrap();
+                        assert_eq!(found.len(), 1, "write at {} not visible", ts);
+                    }
+                })
+            })
+            .collect();
+
+        for h in handles {
+            h.join().unwrap();
+        }
+        stop.store(true, std::sync::atomic::Ordering::Relaxed);
+        reader.join().unwrap();
+
+        let results = storage.range_query(0, i64::MAX).unwrap();
+        assert_eq!(results.len(), (writers * per_writer) as usize);
+        assert!(storage.stats().sstable_count > 0);
+    }
+
+    /// flush 失败时 immutable 不丢失，数据仍可查询，恢复后可重试
+    #[test]
+    fn test_flush_failure_keeps_immutable() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let config = OltpHybridConfig {
+            base_path: tmp_dir.path().to_str().unwrap().to_string(),
+            memtable_size_bytes: 1000,
+            estimated_entry_size: 100,
+            enable_olap_conversion: false,
+            ..Default::default()
+        };
+
+        let storage = OltpHybridStorage::create("IF2501", config).unwrap();
+
+        // 删除 SSTable 目录，使 flush 失败
+        let sstable_dir = tmp_dir.path().join("IF2501").join("sstables");
+        std::fs::remove_dir_all(&sstable_dir).unwrap();
+
+        let mut failed = false;
+        for i in 0..20 {
+            if storage.write(create_order_record(i, 1000 + i as i64)).is_err() {
+                failed = true;
+            }
+        }
+        assert!(failed);
+
+        let stats = storage.stats();
+        assert_eq!(stats.sstable_count, 0);
+        assert!(stats.immutable_memtable_count > 0);
+        assert_eq!(storage.range_query(1000, 1019).unwrap().len(), 20);
+
+        // 恢复目录后重试
+        std::fs::create_dir_all(&sstable_dir).unwrap();
+        let flushed = storage.flush_immutable_memtables().unwrap();
+        assert_eq!(flushed, stats.immutable_memtable_count);
+
+        let stats = storage.stats();
+        assert_eq!(stats.immutable_memtable_count, 0);
+        assert!(stats.sstable_count > 0);
+        assert_eq!(storage.range_query(1000, 1019).unwrap().len(), 20);
+    }
+
+    fn create_snapshot_record(instrument_id: &str, bid: f64, timestamp: i64) -> WalRecord {
+        let mut bids = [(0.0, 0i64); 10];
+        let mut asks = [(0.0, 0i64); 10];
+        bids[0] = (bid, 10);
+        asks[0] = (bid + 1.0, 5);
+        WalRecord::OrderBookSnapshot {
+            instrument_id: WalRecord::to_fixed_array_16(instrument_id),
+            bids,
+            asks,
+            last_price: bid,
+            timestamp,
+        }
+    }
+
+    /// 历史某时刻的盘口：返回不晚于该时刻的最近快照（跨 SSTable、重启后仍可查）
+    #[test]
+    fn test_orderbook_snapshot_history() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let base_path = tmp_dir.path().to_str().unwrap().to_string();
+        let config = OltpHybridConfig {
+            base_path: base_path.clone(),
+            memtable_size_bytes: 1000, // 很小，快照分布在多个 SSTable
+            estimated_entry_size: 100,
+            enable_olap_conversion: false,
+            ..Default::default()
+        };
+
+        let bid_of = |record: &WalRecord| match record {
+            WalRecord::OrderBookSnapshot { bids, .. } => bids[0].0,
+            _ => panic!("not a snapshot"),
+        };
+
+        {
+            let storage = OltpHybridStorage::create("market_data", config.clone()).unwrap();
+            for i in 0..30i64 {
+                // IF2501 每 100ns 一个快照，IC2501 穿插写入
+                storage
+                    .write(create_snapshot_record("IF2501", 3800.0 + i as f64, 1000 + i * 100))
+                    .unwrap();
+                storage
+                    .write(create_snapshot_record("IC2501", 5600.0 + i as f64, 1050 + i * 100))
+                    .unwrap();
+            }
+            assert!(storage.stats().sstable_count > 1);
+
+            // 恰好命中快照时刻
+            let (ts, record) = storage.orderbook_snapshot_at("IF2501", 1500).unwrap().unwrap();
+            assert_eq!(ts, 1500);
+            assert_eq!(bid_of(&record), 3805.0);
+
+            // 两个快照之间：返回较早的那个，且不会取到其他合约
+            let (ts, record) = storage.orderbook_snapshot_at("IF2501", 1599).unwrap().unwrap();
+            assert_eq!(ts, 1500);
+            assert_eq!(bid_of(&record), 3805.0);
+
+            // 该时刻之前无快照
+            assert!(storage.orderbook_snapshot_at("IF2501", 999).unwrap().is_none());
+            assert!(storage.orderbook_snapshot_at("AU2501", 5000).unwrap().is_none());
+
+            // 时间段内的快照序列（回放）
+            let series = storage
+                .orderbook_snapshots_between("IC2501", 1000, 2000, 5)
+                .unwrap();
+            let timestamps: Vec<i64> = series.iter().map(|(ts, _)| *ts).collect();
+            assert_eq!(timestamps, vec![1050, 1150, 1250, 1350, 1450]);
+        }
+
+        // 重启后从 SSTable + WAL 重建索引
+        let storage = OltpHybridStorage::create("market_data", config).unwrap();
+        storage.recover().unwrap();
+        let (ts, record) = storage.orderbook_snapshot_at("IF2501", 100_000).unwrap().unwrap();
+        assert_eq!(ts, 1000 + 29 * 100);
+        assert_eq!(bid_of(&record), 3829.0);
+    }
+
     #[tokio::test]
     async fn test_batch_write() {
         let tmp_dir = tempfile::tempdir().unwrap();
@@ -961,4 +1867,203 @@ mod tests {
         // 验证 P99 < 100ms (宽松目标，适应各种存储)
         assert!(p99 < 100_000, "P99 {} μs exceeds 100ms limit", p99);
     }
+
+    fn create_tick_record(instrument_id: &str, price: f64, timestamp: i64) -> WalRecord {
+        WalRecord::TickData {
+            instrument_id: WalRecord::to_fixed_array_16(instrument_id),
+            last_price: price,
+            bid_price: price - 0.2,
+            ask_price: price + 0.2,
+            volume: 1,
+            timestamp,
+        }
+    }
+
+    fn partitioned_config(base_path: &str) -> OltpHybridConfig {
+        OltpHybridConfig {
+            base_path: base_path.to_string(),
+            enable_olap_conversion: false,
+            ..Default::default()
+        }
+    }
+
+    /// 分区模式：行情按合约写入独立分区，账户数据保留在账户分区，查询透明
+    #[test]
+    fn test_partitioned_routing_and_query() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let base_path = tmp_dir.path().to_str().unwrap();
+        let storage =
+            OltpHybridStorage::create_partitioned("market_data", partitioned_config(base_path))
+                .unwrap();
+
+        let records = vec![
+            create_tick_record("IF2501", 4000.0, 1000),
+            create_tick_record("IC2501", 6000.0, 1001),
+            create_order_record(1, 1002),
+            create_tick_record("IF2501", 4001.0, 1003),
+        ];
+        let sequences = storage.write_batch(records).unwrap();
+        // 序列号为各分区内序列号，按输入顺序返回
+        assert_eq!(sequences, vec![1, 1, 1, 2]);
+        storage
+            .write(create_tick_record("IC2501", 6001.0, 1004))
+            .unwrap();
+
+        assert!(storage.is_partitioned());
+        assert_eq!(storage.partition_instruments(), vec!["IC2501", "IF2501"]);
+        assert!(tmp_dir
+            .path()
+            .join("market_data/partitions/IF2501/wal")
+            .is_dir());
+
+        // 按合约查询只扫对应分区
+        let if_ticks = storage
+            .range_query_instrument("IF2501", 0, i64::MAX)
+            .unwrap();
+        assert_eq!(if_ticks.len(), 2);
+        assert!(storage
+            .range_query_instrument("IH2501", 0, i64::MAX)
+            .unwrap()
+            .is_empty());
+        let if_partition = storage.partition("IF2501").unwrap();
+        assert_eq!(if_partition.stats().memtable_entries, 2);
+
+        // 跨分区查询按时间归并（含账户分区）
+        let all: Vec<i64> = storage
+            .range_query(0, i64::MAX)
+            .unwrap()
+            .into_iter()
+            .map(|(ts, _, _)| ts)
+            .collect();
+        assert_eq!(all, vec![1000, 1001, 1002, 1003, 1004]);
+        assert_eq!(storage.stats().memtable_entries, 5);
+        assert_eq!(storage.local_stats().memtable_entries, 1);
+    }
+
+    /// 分区模式：重启后各分区从各自 WAL 恢复
+    #[test]
+    fn test_partitioned_recovery() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let base_path = tmp_dir.path().to_str().unwrap();
+
+        {
+            let storage =
+                OltpHybridStorage::create_partitioned("market_data", partitioned_config(base_path))
+                    .unwrap();
+            for i in 0..10 {
+                for instrument in ["IF2501", "IC2501", "IH2501"] {
+                    storage
+                        .write(create_tick_record(instrument, 100.0 + i as f64, 1000 + i))
+                        .unwrap();
+                }
+            }
+            storage.write(create_order_record(1, 2000)).unwrap();
+        }
+
+        let storage =
+            OltpHybridStorage::create_partitioned("market_data", partitioned_config(base_path))
+                .unwrap();
+        storage.recover().unwrap();
+
+        assert_eq!(storage.partition_instruments().len(), 3);
+        for instrument in ["IF2501", "IC2501", "IH2501"] {
+            let ticks = storage
+                .range_query_instrument(instrument, 0, i64::MAX)
+                .unwrap();
+            assert_eq!(ticks.len(), 10, "{}", instrument);
+        }
+        assert_eq!(storage.range_query(0, i64::MAX).unwrap().len(), 31);
+
+        // 新写入延续分区内序列号
+        let seq = storage
+            .write(create_tick_record("IF2501", 200.0, 3000))
+            .unwrap();
+        assert_eq!(seq, 11);
+    }
+
+    #[test]
+    fn test_online_latency_stable_under_analytical_load() {
+        use super::super::query_gate::AnalyticalQueryConfig;
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::time::{Duration, Instant};
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let config = OltpHybridConfig {
+            base_path: tmp_dir.path().to_str().unwrap().to_string(),
+            memtable_size_bytes: 100 * 1024 * 1024,
+            estimated_entry_size: 256,
+            enable_olap_conversion: false,
+            ..Default::default()
+        };
+        let storage = Arc::new(OltpHybridStorage::create("IF2501", config).unwrap());
+
+        // 历史数据落盘，最近数据留在 MemTable
+        let history: Vec<_> = (0..30_000)
+            .map(|i| create_order_record(i, i as i64))
+            .collect();
+        storage.write_batch(history).unwrap();
+        storage.flush().unwrap();
+        for i in 0..100 {
+            storage
+                .write(create_order_record(100_000 + i, 100_000 + i as i64))
+                .unwrap();
+        }
+
+        let online_p99 = |storage: &OltpHybridStorage| {
+            let mut latencies: Vec<Duration> = (0..200)
+                .map(|_| {
+                    let t = Instant::now();
+                    assert_eq!(storage.range_query(100_000, 100_099).unwrap().len(), 100);
+                    t.elapsed()
+                })
+                .collect();
+            latencies.sort();
+            latencies[latencies.len() * 99 / 100]
+        };
+        let baseline = online_p99(&storage);
+
+        storage.query_gate().update_config(AnalyticalQueryConfig {
+            max_concurrent: 1,
+            max_queued: 2,
+            queue_timeout_ms: 50,
+            degrade_to_olap: false,
+        });
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let analysts: Vec<_> = (0..6)
+            .map(|_| {
+                let storage = storage.clone();
+                let stop = stop.clone();
+                std::thread::spawn(move || {
+                    while !stop.load(Ordering::Relaxed) {
+                        if let Ok(rows) =
+                            storage.query_range(QueryClass::Analytical, i64::MIN, i64::MAX)
+                        {
+                            assert_eq!(rows.len(), 30_100);
+                        }
+                    }
+                })
+            })
+            .collect();
+        while storage.query_gate().stats().analytical_admitted == 0 {
+            std::thread::yield_now();
+        }
+
+        let loaded = online_p99(&storage);
+        stop.store(true, Ordering::Relaxed);
+        for analyst in analysts {
+            analyst.join().unwrap();
+        }
+
+        let stats = storage.query_gate().stats();
+        assert_eq!(stats.analytical_peak_running, 1);
+        assert!(stats.analytical_rejected > 0);
+        assert!(stats.online_queries >= 400);
+        assert!(
+            loaded < (baseline * 20).max(Duration::from_millis(20)),
+            "online p99 {:?} -> {:?}",
+            baseline,
+            loaded
+        );
+    }
 }
@@ -0,0 +1,150 @@
+// Instrument Partitions - 按合约分区的存储集合
+//
+// 分区模式下 OltpHybridStorage 按合约拆分写入路径：
+// - 行情、成交记录按合约路由到独立分区（各自的 WAL + MemTable + SSTable）
+// - 账户类记录保留在根存储（账户分区）
+//
+// 目录结构：
+// {base_path}/{storage_id}/
+//   ├── wal/ sstables/ olap/          - 账户分区
+//   └── partitions/{instrument_id}/
+//         ├── wal/                    - 合约 WAL（PerInstrumentWalManager 管理）
+//         ├── sstables/
+//         └── olap/
+//
+// 不同合约写入互不阻塞，恢复时各分区并行回放。
+//
+// @yutiansut @quantaxis
+
+use super::oltp::{OltpHybridConfig, OltpHybridStorage};
+use crate::storage::wal::PerInstrumentWalManager;
+use dashmap::DashMap;
+use rayon::prelude::*;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// 分区目录名
+pub const PARTITIONS_DIR: &str = "partitions";
+
+/// 合约分区集合
+pub struct InstrumentPartitions {
+    /// 分区配置（base_path 指向 partitions 目录）
+    config: OltpHybridConfig,
+
+    /// 合约 WAL 管理器
+    wal: PerInstrumentWalManager,
+
+    /// 合约 → 分区存储
+    storages: DashMap<String, Arc<OltpHybridStorage>>,
+}
+
+impl InstrumentPartitions {
+    /// 打开分区集合，并行加载磁盘上已有的分区（回放各自 WAL）
+    pub fn open(root_path: &std::path::Path, config: &OltpHybridConfig) -> Result<Self, String> {
+        let base_path = root_path.join(PARTITIONS_DIR);
+        std::fs::create_dir_all(&base_path)
+            .map_err(|e| format!("Create partitions path failed: {}", e))?;
+        let base_path_str = base_path.to_string_lossy().to_string();
+
+        let partitions = Self {
+            config: OltpHybridConfig {
+                base_path: base_path_str.clone(),
+                ..config.clone()
+            },
+            wal: PerInstrumentWalManager::with_wal_subdir(&base_path_str, "wal"),
+            storages: DashMap::new(),
+        };
+
+        let existing = Self::scan_partition_dirs(&base_path)?;
+        let opened = existing
+            .par_iter()
+            .map(|instrument_id| {
+                partitions
+                    .open_partition(instrument_id)
+                    .map(|storage| (instrument_id.clone(), storage))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        for (instrument_id, storage) in opened {
+            partitions.storages.insert(instrument_id, storage);
+        }
+
+        if !partitions.storages.is_empty() {
+            log::info!(
+                "Loaded {} instrument partitions from {}",
+                partitions.storages.len(),
+                base_path_str
+            );
+        }
+
+        Ok(partitions)
+    }
+
+    /// 扫描分区目录
+    fn scan_partition_dirs(base_path: &PathBuf) -> Result<Vec<String>, String> {
+        let entries = std::fs::read_dir(base_path)
+            .map_err(|e| format!("Read partitions dir failed: {}", e))?;
+
+        let mut instruments: Vec<String> = entries
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().is_dir())
+            .filter_map(|e| e.file_name().to_str().map(|s| s.to_string()))
+            .collect();
+        instruments.sort();
+        Ok(instruments)
+    }
+
+    fn open_partition(&self, instrument_id: &str) -> Result<Arc<OltpHybridStorage>, String> {
+        let wal = self.wal.manager(instrument_id);
+        OltpHybridStorage::create_with_wal(instrument_id, self.config.clone(), wal).map(Arc::new)
+    }
+
+    /// 获取或创建合约分区
+    pub fn get_or_create(&self, instrument_id: &str) -> Result<Arc<OltpHybridStorage>, String> {
+        if let Some(storage) = self.storages.get(instrument_id) {
+            return Ok(storage.clone());
+        }
+
+        match self.storages.entry(instrument_id.to_string()) {
+            dashmap::mapref::entry::Entry::Occupied(entry) => Ok(entry.get().clone()),
+            dashmap::mapref::entry::Entry::Vacant(entry) => {
+                let storage = self.open_partition(instrument_id)?;
+                log::info!("Created instrument partition: {}", instrument_id);
+                Ok(entry.insert(storage).clone())
+            }
+        }
+    }
+
+    /// 获取合约分区（不存在时返回 None）
+    pub fn get(&self, instrument_id: &str) -> Option<Arc<OltpHybridStorage>> {
+        self.storages.get(instrument_id).map(|s| s.clone())
+    }
+
+    /// 所有分区（按合约排序）
+    pub fn all(&self) -> Vec<Arc<OltpHybridStorage>> {
+        let mut storages: Vec<_> = self
+            .storages
+            .iter()
+            .map(|kv| (kv.key().clone(), kv.value().clone()))
+            .collect();
+        storages.sort_by(|a, b| a.0.cmp(&b.0));
+        storages.into_iter().map(|(_, s)| s).collect()
+    }
+
+    /// 分区合约列表（按合约排序）
+    pub fn instruments(&self) -> Vec<String> {
+        let mut instruments: Vec<String> =
+            self.storages.iter().map(|kv| kv.key().clone()).collect();
+        instruments.sort();
+        instruments
+    }
+
+    /// 各分区并行执行操作（用于恢复、checkpoint 等）
+    pub fn for_each_parallel<F>(&self, op: F) -> Result<(), String>
+    where
+        F: Fn(&OltpHybridStorage) -> Result<(), String> + Sync + Send,
+    {
+        self.all()
+            .par_iter()
+            .try_for_each(|storage| op(storage.as_ref()))
+    }
+}
@@ -23,11 +23,16 @@ pub enum RecordType {
     // 账户类型 (0x00xx)
     AccountOpen = 0x0001,
     AccountUpdate = 0x0002,
+    RiskSnapshot = 0x0003,
 
     // 用户类型 (0x01xx)
     UserRegister = 0x0100,
     AccountBind = 0x0101,
     UserRoleUpdate = 0x0102,
+    UserAnonymize = 0x0103,
+    UserNotification = 0x0104,
+    NotificationRead = 0x0105,
+    UserPasswordUpdate = 0x0106,
 
     // 订单类型 (0x02xx)
     OrderInsert = 0x0200,
@@ -63,11 +68,16 @@ impl RecordType {
     pub fn from_wal_record(record: &WalRecord) -> Self {
         match record {
             WalRecord::AccountOpen { .. } => Self::AccountOpen,
-            WalRecord::AccountUpdate { .. } => Self::AccountUpdate,
+            // 定点金额版本与旧版本同属一类 @yutiansut @quantaxis
+            WalRecord::AccountUpdate { .. } | WalRecord::AccountUpdateV2 { .. } => {
+                Self::AccountUpdate
+            }
             WalRecord::UserRegister { .. } => Self::UserRegister,
             WalRecord::AccountBind { .. } => Self::AccountBind,
             WalRecord::OrderInsert { .. } => Self::OrderInsert,
-            WalRecord::TradeExecuted { .. } => Self::TradeExecuted,
+            WalRecord::TradeExecuted { .. } | WalRecord::TradeExecutedV2 { .. } => {
+                Self::TradeExecuted
+            }
             WalRecord::TickData { .. } => Self::TickData,
             WalRecord::OrderBookSnapshot { .. } => Self::OrderBookSnapshot,
             WalRecord::OrderBookDelta { .. } => Self::OrderBookDelta,
@@ -84,6 +94,13 @@ impl RecordType {
             WalRecord::AccountSnapshot { .. } => Self::AccountSnapshot,
             // 用户角色更新 @yutiansut @quantaxis
             WalRecord::UserRoleUpdate { .. } => Self::UserRoleUpdate,
+            WalRecord::RiskSnapshot { .. } => Self::RiskSnapshot,
+            // 用户注销匿名化 @yutiansut @quantaxis
+            WalRecord::UserAnonymize { .. } => Self::UserAnonymize,
+            // 用户消息中心 @yutiansut @quantaxis
+            WalRecord::UserNotification { .. } => Self::UserNotification,
+            WalRecord::NotificationRead { .. } => Self::NotificationRead,
+            WalRecord::UserPasswordUpdate { .. } => Self::UserPasswordUpdate,
         }
     }
 
@@ -113,6 +130,11 @@ impl RecordType {
             Self::AccountSnapshot => "AccountSnapshot",
             // 用户角色更新 @yutiansut @quantaxis
             Self::UserRoleUpdate => "UserRoleUpdate",
+            Self::RiskSnapshot => "RiskSnapshot",
+            Self::UserAnonymize => "UserAnonymize",
+            Self::UserNotification => "UserNotification",
+            Self::NotificationRead => "NotificationRead",
+            Self::UserPasswordUpdate => "UserPasswordUpdate",
         }
     }
 
@@ -139,6 +161,7 @@ impl RecordType {
         match value as u16 {
             0x0001 => Some(Self::AccountOpen),
             0x0002 => Some(Self::AccountUpdate),
+            0x0003 => Some(Self::RiskSnapshot),
             0x0100 => Some(Self::UserRegister),
             0x0101 => Some(Self::AccountBind),
             0x0200 => Some(Self::OrderInsert),
@@ -200,7 +223,7 @@ impl RecordTypeSet {
 
     /// 账户相关类型
     pub const ACCOUNT: Self = Self {
-        mask: (1 << 0) | (1 << 1),
+        mask: (1 << 0) | (1 << 1) | (1 << 20),
     };
 
     /// 用户相关类型
@@ -309,6 +332,11 @@ impl RecordTypeSet {
             RecordType::AccountSnapshot => 1 << 18,
             // 用户角色更新 @yutiansut @quantaxis
             RecordType::UserRoleUpdate => 1 << 19,
+            RecordType::RiskSnapshot => 1 << 20,
+            RecordType::UserAnonymize => 1 << 21,
+            RecordType::UserNotification => 1 << 22,
+            RecordType::NotificationRead => 1 << 23,
+            RecordType::UserPasswordUpdate => 1 << 24,
         }
     }
 }
@@ -499,6 +527,7 @@ impl QueryFilter {
         match record {
             WalRecord::OrderInsert { price, .. } => Some(*price),
             WalRecord::TradeExecuted { price, .. } => Some(*price),
+            WalRecord::TradeExecutedV2 { price, .. } => Some(*price),
             WalRecord::TickData { last_price, .. } => Some(*last_price),
             WalRecord::OrderBookSnapshot { last_price, .. } => Some(*last_price),
             WalRecord::OrderBookDelta { price, .. } => Some(*price),
@@ -0,0 +1,296 @@
+// Query Gate - 存储层查询分类与分析查询限流
+//
+// 在线查询（账户/订单/盘口等低延迟读取）与分析查询（历史回放、批量扫描、聚合统计）
+// 共用同一份 MemTable/SSTable：
+// - Online：不排队、不限流，直接读取
+// - Analytical：受独立的并发配额限制，超出配额时有界排队（带超时），
+//   队列已满或等待超时则拒绝；调用方可选择降级到 OLAP 副本（Parquet）
+//
+// 分析查询被限制为少量并发后，大范围扫描不会占满 CPU/IO，在线查询延迟保持稳定
+//
+// @yutiansut @quantaxis
+
+use parking_lot::{Condvar, Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// 查询分类
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryClass {
+    /// 在线查询（低延迟优先，不限流）
+    Online,
+    /// 分析查询（受并发配额限制）
+    Analytical,
+}
+
+impl QueryClass {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QueryClass::Online => "online",
+            QueryClass::Analytical => "analytical",
+        }
+    }
+}
+
+/// 分析查询配额
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalyticalQueryConfig {
+    /// 同时执行的分析查询数
+    #[serde(default = "default_max_concurrent")]
+    pub max_concurrent: usize,
+    /// 最大排队数（超过直接拒绝）
+    #[serde(default = "default_max_queued")]
+    pub max_queued: usize,
+    /// 排队超时（毫秒）
+    #[serde(default = "default_queue_timeout_ms")]
+    pub queue_timeout_ms: u64,
+    /// 被拒绝时是否允许降级到 OLAP 副本（只返回已转换的历史数据）
+    #[serde(default = "default_degrade_to_olap")]
+    pub degrade_to_olap: bool,
+}
+
+fn default_max_concurrent() -> usize {
+    2
+}
+fn default_max_queued() -> usize {
+    8
+}
+fn default_queue_timeout_ms() -> u64 {
+    3_000
+}
+fn default_degrade_to_olap() -> bool {
+    true
+}
+
+impl Default for AnalyticalQueryConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent: default_max_concurrent(),
+            max_queued: default_max_queued(),
+            queue_timeout_ms: default_queue_timeout_ms(),
+            degrade_to_olap: default_degrade_to_olap(),
+        }
+    }
+}
+
+/// 分析查询被拒绝的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryGateError {
+    /// 排队已满
+    QueueFull { queued: usize },
+    /// 排队超时
+    Timeout { waited_ms: u64 },
+}
+
+impl std::fmt::Display for QueryGateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueryGateError::QueueFull { queued } => {
+                write!(f, "Analytical query queue is full ({} queued)", queued)
+            }
+            QueryGateError::Timeout { waited_ms } => {
+                write!(f, "Analytical query queued for {}ms, timed out", waited_ms)
+            }
+        }
+    }
+}
+
+impl std::error::Error for QueryGateError {}
+
+/// 查询通道统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QueryGateStats {
+    pub online_queries: u64,
+    /// 获得配额执行的分析查询
+    pub analytical_admitted: u64,
+    /// 经过排队的分析查询
+    pub analytical_waited: u64,
+    /// 被拒绝的分析查询
+    pub analytical_rejected: u64,
+    /// 降级到 OLAP 副本的分析查询
+    pub analytical_degraded: u64,
+    /// 当前执行中的分析查询
+    pub analytical_running: usize,
+    /// 当前排队中的分析查询
+    pub analytical_queued: usize,
+    /// 分析查询并发峰值
+    pub analytical_peak_running: usize,
+}
+
+#[derive(Default)]
+struct GateState {
+    running: usize,
+    queued: usize,
+    peak_running: usize,
+}
+
+/// 查询通道闸门
+pub struct QueryGate {
+    config: RwLock<AnalyticalQueryConfig>,
+    state: Mutex<GateState>,
+    available: Condvar,
+    online_queries: AtomicU64,
+    admitted: AtomicU64,
+    waited: AtomicU64,
+    rejected: AtomicU64,
+    degraded: AtomicU64,
+}
+
+/// 分析查询配额（drop 时归还并唤醒排队者）
+pub struct AnalyticalPermit<'a> {
+    gate: &'a QueryGate,
+}
+
+impl Drop for AnalyticalPermit<'_> {
+    fn drop(&mut self) {
+        self.gate.state.lock().running -= 1;
+        self.gate.available.notify_one();
+    }
+}
+
+impl Default for QueryGate {
+    fn default() -> Self {
+        Self::new(AnalyticalQueryConfig::default())
+    }
+}
+
+impl QueryGate {
+    pub fn new(config: AnalyticalQueryConfig) -> Self {
+        Self {
+            config: RwLock::new(config),
+            state: Mutex::new(GateState::default()),
+            available: Condvar::new(),
+            online_queries: AtomicU64::new(0),
+            admitted: AtomicU64::new(0),
+            waited: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+            degraded: AtomicU64::new(0),
+        }
+    }
+
+    pub fn config(&self) -> AnalyticalQueryConfig {
+        self.config.read().clone()
+    }
+
+    /// 更新配额（配额增大时唤醒排队者）
+    pub fn update_config(&self, config: AnalyticalQueryConfig) {
+        log::info!(
+            "[QueryGate] Analytical quota updated: concurrent={}, queued={}, timeout={}ms",
+            config.max_concurrent,
+            config.max_queued,
+            config.queue_timeout_ms
+        );
+        *self.config.write() = config;
+        self.available.notify_all();
+    }
+
+    /// 记录一次在线查询
+    pub fn record_online(&self) {
+        self.online_queries.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 记录一次降级到 OLAP 副本的分析查询
+    pub fn record_degraded(&self) {
+        self.degraded.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 申请分析查询配额：有空闲配额立即返回，否则排队等待至超时
+    pub fn acquire(&self) -> Result<AnalyticalPermit<'_>, QueryGateError> {
+        let config = self.config();
+        let mut state = self.state.lock();
+
+        if state.running >= config.max_concurrent.max(1) {
+            if state.queued >= config.max_queued {
+                self.rejected.fetch_add(1, Ordering::Relaxed);
+                return Err(QueryGateError::QueueFull {
+                    queued: state.queued,
+                });
+            }
+
+            state.queued += 1;
+            self.waited.fetch_add(1, Ordering::Relaxed);
+            let start = Instant::now();
+            let deadline = start + Duration::from_millis(config.queue_timeout_ms);
+            while state.running >= self.config.read().max_concurrent.max(1) {
+                if self.available.wait_until(&mut state, deadline).timed_out()
+                    && state.running >= self.config.read().max_concurrent.max(1)
+                {
+                    state.queued -= 1;
+                    self.rejected.fetch_add(1, Ordering::Relaxed);
+                    return Err(QueryGateError::Timeout {
+                        waited_ms: start.elapsed().as_millis() as u64,
+                    });
+                }
+            }
+            state.queued -= 1;
+        }
+
+        state.running += 1;
+        state.peak_running = state.peak_running.max(state.running);
+        self.admitted.fetch_add(1, Ordering::Relaxed);
+        Ok(AnalyticalPermit { gate: self })
+    }
+
+    pub fn stats(&self) -> QueryGateStats {
+        let state = self.state.lock();
+        QueryGateStats {
+            online_queries: self.online_queries.load(Ordering::Relaxed),
+            analytical_admitted: self.admitted.load(Ordering::Relaxed),
+            analytical_waited: self.waited.load(Ordering::Relaxed),
+            analytical_rejected: self.rejected.load(Ordering::Relaxed),
+            analytical_degraded: self.degraded.load(Ordering::Relaxed),
+            analytical_running: state.running,
+            analytical_queued: state.queued,
+            analytical_peak_running: state.peak_running,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_analytical_quota_queue_and_timeout() {
+        let gate = Arc::new(QueryGate::new(AnalyticalQueryConfig {
+            max_concurrent: 1,
+            max_queued: 1,
+            queue_timeout_ms: 100,
+            degrade_to_olap: false,
+        }));
+
+        let permit = gate.acquire().unwrap();
+
+        // 第二个排队至超时
+        assert!(matches!(
+            gate.acquire(),
+            Err(QueryGateError::Timeout { .. })
+        ));
+
+        // 排队中的请求在配额释放后获得执行
+        let waiter = {
+            let gate = gate.clone();
+            std::thread::spawn(move || gate.acquire().map(|_| ()))
+        };
+        while gate.stats().analytical_queued == 0 {
+            std::thread::yield_now();
+        }
+        // 队列已满直接拒绝
+        assert_eq!(
+            gate.acquire().err(),
+            Some(QueryGateError::QueueFull { queued: 1 })
+        );
+        drop(permit);
+        // 等待线程拿到配额或已超时，两者均需释放排队计数
+        let _ = waiter.join().unwrap();
+
+        let stats = gate.stats();
+        assert_eq!(stats.analytical_running, 0);
+        assert_eq!(stats.analytical_queued, 0);
+        assert_eq!(stats.analytical_peak_running, 1);
+        assert_eq!(stats.analytical_rejected + stats.analytical_admitted, 4);
+    }
+}
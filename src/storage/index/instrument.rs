@@ -74,6 +74,18 @@ impl InstrumentData {
         }
         result
     }
+
+    /// 不晚于 timestamp 的最近一条
+    fn latest_at(&self, timestamp: i64) -> Option<(i64, Vec<u64>)> {
+        self.entries
+            .range(..=timestamp)
+            .next_back()
+            .map(|(ts, offsets)| (*ts, offsets.clone()))
+    }
+
+    fn timestamps_in_range(&self, start_ts: i64, end_ts: i64) -> Vec<i64> {
+        self.entries.range(start_ts..=end_ts).map(|(ts, _)| *ts).collect()
+    }
 }
 
 /// 合约索引
@@ -133,6 +145,23 @@ impl InstrumentIndex {
             .unwrap_or_default()
     }
 
+    /// 查询不晚于 timestamp 的最近条目 (时间戳, 偏移量列表)
+    pub fn query_latest_at(&self, instrument_id: &str, timestamp: i64) -> Option<(i64, Vec<u64>)> {
+        let key: Arc<str> = Arc::from(instrument_id);
+        self.instruments
+            .get(&key)
+            .and_then(|data| data.latest_at(timestamp))
+    }
+
+    /// 查询时间范围内的时间戳（升序）
+    pub fn query_timestamps(&self, instrument_id: &str, start_ts: i64, end_ts: i64) -> Vec<i64> {
+        let key: Arc<str> = Arc::from(instrument_id);
+        self.instruments
+            .get(&key)
+            .map(|data| data.timestamps_in_range(start_ts, end_ts))
+            .unwrap_or_default()
+    }
+
     /// 获取合约的时间范围
     pub fn get_time_range(&self, instrument_id: &str) -> Option<TimeRange> {
         let key: Arc<str> = Arc::from(instrument_id);
@@ -0,0 +1,267 @@
+//! 存储运维操作（手动 flush / compaction）
+//!
+//! @yutiansut @quantaxis
+//!
+//! 维护窗口内由管理端手动触发，不必等待自动调度：
+//! - **flush**: 冻结活跃 MemTable 并写入 SSTable
+//! - **compact**: 执行一轮 leveled compaction
+//!
+//! 可指定合约（分区存储的单个合约分区）或全局执行。手动操作之间串行排队，
+//! 与自动 flush / compaction 通过各自的执行锁互斥；每次操作生成任务记录
+//! （状态、影响数量、耗时），长操作异步执行后按任务 ID 查询。
+
+use super::hybrid::OltpHybridStorage;
+use chrono::Utc;
+use dashmap::DashMap;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// 保留的任务记录数
+const MAX_TASKS: u64 = 200;
+
+/// 运维操作类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaintenanceKind {
+    Flush,
+    Compact,
+}
+
+/// 任务状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaintenanceStatus {
+    /// 排队中（等待前一个手动操作完成）
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+/// 运维任务记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceTask {
+    pub task_id: String,
+    pub kind: MaintenanceKind,
+    /// 目标合约（None 表示全局）
+    pub instrument_id: Option<String>,
+    pub status: MaintenanceStatus,
+    /// flush 的 MemTable 数 / 执行 compaction 的分区数
+    pub affected: usize,
+    /// 执行耗时（毫秒，不含排队时间）
+    pub elapsed_ms: u64,
+    pub error: Option<String>,
+    pub created_at: i64,
+    pub finished_at: Option<i64>,
+    #[serde(skip)]
+    seq: u64,
+}
+
+/// 存储运维执行器
+pub struct StorageMaintenance {
+    storage: Arc<OltpHybridStorage>,
+    /// task_id -> 任务记录
+    tasks: DashMap<String, MaintenanceTask>,
+    /// 手动操作串行执行
+    op_lock: Mutex<()>,
+    task_seq: AtomicU64,
+}
+
+impl StorageMaintenance {
+    pub fn new(storage: Arc<OltpHybridStorage>) -> Self {
+        Self {
+            storage,
+            tasks: DashMap::new(),
+            op_lock: Mutex::new(()),
+            task_seq: AtomicU64::new(1),
+        }
+    }
+
+    /// 提交任务（仅登记，由 [`Self::run`] 执行）
+    ///
+    /// 指定合约时校验分区存在
+    pub fn submit(
+        &self,
+        kind: MaintenanceKind,
+        instrument_id: Option<String>,
+    ) -> Result<MaintenanceTask, String> {
+        if let Some(ref instrument_id) = instrument_id {
+            self.target(Some(instrument_id))?;
+        }
+
+        let seq = self.task_seq.fetch_add(1, Ordering::SeqCst);
+        let now = Utc::now().timestamp_millis();
+        let task = MaintenanceTask {
+            task_id: format!("STG{}{:06}", now, seq),
+            kind,
+            instrument_id,
+            status: MaintenanceStatus::Queued,
+            affected: 0,
+            elapsed_ms: 0,
+            error: None,
+            created_at: now,
+            finished_at: None,
+            seq,
+        };
+        self.tasks.insert(task.task_id.clone(), task.clone());
+        self.evict(seq);
+        Ok(task)
+    }
+
+    /// 执行已提交的任务（阻塞，调用方应在阻塞线程池中调用）
+    pub fn run(&self, task_id: &str) -> Option<MaintenanceTask> {
+        let (kind, instrument_id) = {
+            let task = self.tasks.get(task_id)?;
+            (task.kind, task.instrument_id.clone())
+        };
+
+        let _guard = self.op_lock.lock();
+        self.update(task_id, |task| task.status = MaintenanceStatus::Running);
+
+        let started = Instant::now();
+        let result = self
+            .target(instrument_id.as_deref())
+            .and_then(|storage| match kind {
+                MaintenanceKind::Flush => storage.flush(),
+                MaintenanceKind::Compact => storage.compact(),
+            });
+        let elapsed_ms = started.elapsed().as_millis() as u64;
+
+        match &result {
+            Ok(affected) => log::info!(
+                "[StorageMaintenance] {:?} {} done: affected={}, elapsed={}ms",
+                kind,
+                instrument_id.as_deref().unwrap_or("ALL"),
+                affected,
+                elapsed_ms
+            ),
+            Err(e) => log::error!(
+                "[StorageMaintenance] {:?} {} failed after {}ms: {}",
+                kind,
+                instrument_id.as_deref().unwrap_or("ALL"),
+                elapsed_ms,
+                e
+            ),
+        }
+
+        self.update(task_id, |task| {
+            task.elapsed_ms = elapsed_ms;
+            task.finished_at = Some(Utc::now().timestamp_millis());
+            match result {
+                Ok(affected) => {
+                    task.status = MaintenanceStatus::Succeeded;
+                    task.affected = affected;
+                }
+                Err(e) => {
+                    task.status = MaintenanceStatus::Failed;
+                    task.error = Some(e);
+                }
+            }
+        });
+        self.get_task(task_id)
+    }
+
+    /// 在阻塞线程池中异步执行任务
+    pub fn spawn(self: &Arc<Self>, task_id: String) {
+        let maintenance = self.clone();
+        tokio::task::spawn_blocking(move || {
+            maintenance.run(&task_id);
+        });
+    }
+
+    /// 查询任务
+    pub fn get_task(&self, task_id: &str) -> Option<MaintenanceTask> {
+        self.tasks.get(task_id).map(|t| t.value().clone())
+    }
+
+    /// 最近的任务（按提交顺序倒序）
+    pub fn list_tasks(&self) -> Vec<MaintenanceTask> {
+        let mut tasks: Vec<MaintenanceTask> =
+            self.tasks.iter().map(|t| t.value().clone()).collect();
+        tasks.sort_by(|a, b| b.seq.cmp(&a.seq));
+        tasks
+    }
+
+    fn target(&self, instrument_id: Option<&str>) -> Result<Arc<OltpHybridStorage>, String> {
+        match instrument_id {
+            None => Ok(self.storage.clone()),
+            Some(_) if !self.storage.is_partitioned() => {
+                Err("Storage is not partitioned by instrument".to_string())
+            }
+            Some(instrument_id) => self
+                .storage
+                .partition(instrument_id)
+                .ok_or_else(|| format!("Instrument partition not found: {}", instrument_id)),
+        }
+    }
+
+    fn update<F: FnOnce(&mut MaintenanceTask)>(&self, task_id: &str, f: F) {
+        if let Some(mut task) = self.tasks.get_mut(task_id) {
+            f(&mut task);
+        }
+    }
+
+    /// 清理过旧的已完成任务
+    fn evict(&self, latest_seq: u64) {
+        if latest_seq <= MAX_TASKS {
+            return;
+        }
+        let threshold = latest_seq - MAX_TASKS;
+        self.tasks.retain(|_, task| {
+            task.seq > threshold
+                || matches!(
+                    task.status,
+                    MaintenanceStatus::Queued | MaintenanceStatus::Running
+                )
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::hybrid::oltp::OltpHybridConfig;
+    use crate::storage::wal::record::WalRecord;
+
+    #[tokio::test]
+    async fn test_flush_task_records_result() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let config = OltpHybridConfig {
+            base_path: tmp_dir.path().to_str().unwrap().to_string(),
+            enable_olap_conversion: false,
+            ..Default::default()
+        };
+        let storage = Arc::new(OltpHybridStorage::create("IF2501", config).unwrap());
+        storage
+            .write(WalRecord::OrderInsert {
+                order_id: 1,
+                user_id: [1u8; 32],
+                instrument_id: [1u8; 16],
+                direction: 0,
+                offset: 0,
+                price: 4000.0,
+                volume: 1.0,
+                timestamp: 1000,
+            })
+            .unwrap();
+
+        let maintenance = StorageMaintenance::new(storage.clone());
+        // 非分区存储不能指定合约
+        assert!(maintenance
+            .submit(MaintenanceKind::Flush, Some("IF2501".to_string()))
+            .is_err());
+
+        let task = maintenance.submit(MaintenanceKind::Flush, None).unwrap();
+        assert_eq!(task.status, MaintenanceStatus::Queued);
+
+        let done = maintenance.run(&task.task_id).unwrap();
+        assert_eq!(done.status, MaintenanceStatus::Succeeded);
+        assert_eq!(done.affected, 1);
+        assert!(done.finished_at.is_some());
+        assert_eq!(storage.stats().sstable_count, 1);
+        assert_eq!(maintenance.list_tasks().len(), 1);
+    }
+}
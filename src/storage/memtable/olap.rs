@@ -274,7 +274,9 @@ fn build_chunk(records: &[(MemTableKey, WalRecord)]) -> Chunk<Box<dyn Array>> {
         timestamp_builder.push(Some(key.timestamp));
         sequence_builder.push(Some(key.sequence));
 
-        match record {
+        // 定点金额版本按浮点列存储
+        let float_record = record.to_float_record();
+        match float_record.as_ref().unwrap_or(record) {
             WalRecord::OrderInsert {
                 order_id,
                 user_id,
@@ -676,7 +678,12 @@ fn build_chunk(records: &[(MemTableKey, WalRecord)]) -> Chunk<Box<dyn Array>> {
             WalRecord::OrderStatusUpdate { .. }
             | WalRecord::PositionSnapshot { .. }
             | WalRecord::AccountSnapshot { .. }
-            | WalRecord::UserRoleUpdate { .. } => {
+            | WalRecord::UserRoleUpdate { .. }
+            | WalRecord::UserAnonymize { .. }
+            | WalRecord::UserNotification { .. }
+            | WalRecord::NotificationRead { .. }
+            | WalRecord::UserPasswordUpdate { .. }
+            | WalRecord::RiskSnapshot { .. } => {
                 record_type_builder.push(Some(15)); // Recovery record type ID
 
                 // 所有字段为 null（恢复数据有独立处理路径）
@@ -697,6 +704,10 @@ fn build_chunk(records: &[(MemTableKey, WalRecord)]) -> Chunk<Box<dyn Array>> {
                 // K线字段为 null
                 push_null_kline_fields!();
             }
+
+            WalRecord::AccountUpdateV2 { .. } | WalRecord::TradeExecutedV2 { .. } => {
+                unreachable!("fixed-point records are converted by to_float_record")
+            }
         }
     }
 
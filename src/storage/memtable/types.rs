@@ -95,6 +95,14 @@ impl MemTableValue {
             WalRecord::AccountSnapshot { timestamp, .. } => *timestamp,
             // 用户角色更新 @yutiansut @quantaxis
             WalRecord::UserRoleUpdate { timestamp, .. } => *timestamp,
+            WalRecord::RiskSnapshot { timestamp, .. } => *timestamp,
+            WalRecord::UserAnonymize { timestamp, .. } => *timestamp,
+            WalRecord::UserNotification { timestamp, .. } => *timestamp,
+            WalRecord::NotificationRead { timestamp, .. } => *timestamp,
+            WalRecord::UserPasswordUpdate { timestamp, .. } => *timestamp,
+            // 定点金额版本 @yutiansut @quantaxis
+            WalRecord::AccountUpdateV2 { timestamp, .. } => *timestamp,
+            WalRecord::TradeExecutedV2 { timestamp, .. } => *timestamp,
         }
     }
 }
@@ -134,6 +142,14 @@ impl MemTableEntry {
             WalRecord::AccountSnapshot { timestamp, .. } => *timestamp,
             // 用户角色更新 @yutiansut @quantaxis
             WalRecord::UserRoleUpdate { timestamp, .. } => *timestamp,
+            WalRecord::RiskSnapshot { timestamp, .. } => *timestamp,
+            WalRecord::UserAnonymize { timestamp, .. } => *timestamp,
+            WalRecord::UserNotification { timestamp, .. } => *timestamp,
+            WalRecord::NotificationRead { timestamp, .. } => *timestamp,
+            WalRecord::UserPasswordUpdate { timestamp, .. } => *timestamp,
+            // 定点金额版本 @yutiansut @quantaxis
+            WalRecord::AccountUpdateV2 { timestamp, .. } => *timestamp,
+            WalRecord::TradeExecutedV2 { timestamp, .. } => *timestamp,
         };
 
         Self {
@@ -34,5 +34,11 @@ pub mod conversion;
 // Storage Subscriber (异步持久化)
 pub mod subscriber;
 
+// 订单/成交导出流（全局 sequence + 断点续传）
+pub mod export;
+
 // 二级索引模块
 pub mod index;
+
+// 存储运维（手动 flush / compaction）
+pub mod maintenance;
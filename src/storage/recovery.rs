@@ -100,6 +100,8 @@ impl RecoveryManager {
         record: WalRecord,
         account_states: &mut HashMap<String, AccountState>,
     ) -> Result<(), ExchangeError> {
+        // 定点金额版本按浮点版本重放（金额由整数换算，结果可重现）
+        let record = record.to_float_record().unwrap_or(record);
         match record {
             WalRecord::AccountOpen {
                 account_id,
@@ -228,6 +230,19 @@ impl RecoveryManager {
                 // 订单状态、持仓快照、账户快照由统一恢复管理器 (UnifiedRecoveryManager) 处理
                 // 此 RecoveryManager 仅处理账户基础状态恢复
             }
+
+            // 风险快照（恢复时跳过，由 RiskHistoryStore 独立加载用于历史回放）
+            WalRecord::RiskSnapshot { .. } => {}
+
+            // 用户注销匿名化、修改密码（由 UserRecovery 应用到用户数据）
+            WalRecord::UserAnonymize { .. } | WalRecord::UserPasswordUpdate { .. } => {}
+
+            // 用户消息中心（由 NotificationStore 独立加载）
+            WalRecord::UserNotification { .. } | WalRecord::NotificationRead { .. } => {}
+
+            WalRecord::AccountUpdateV2 { .. } | WalRecord::TradeExecutedV2 { .. } => {
+                unreachable!("fixed-point records are converted by to_float_record")
+            }
         }
 
         Ok(())
@@ -22,9 +22,14 @@
 //! 3. 存储故障不影响交易
 //! 4. 可扩展到 iceoryx2 跨进程分发
 
-use crate::notification::message::{Notification, NotificationPayload};
+use crate::exchange::fixed_point::{AmountScale, DEFAULT_AMOUNT_DECIMALS};
+use crate::notification::message::{
+    AccountUpdateNotify, Notification, NotificationPayload, TradeExecutedNotify,
+};
+use crate::storage::export::ExportLog;
 use crate::storage::hybrid::oltp::{OltpHybridConfig, OltpHybridStorage};
 use crate::storage::wal::record::WalRecord;
+use crate::ExchangeError;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::mpsc;
@@ -44,6 +49,9 @@ pub struct StorageSubscriberConfig {
 
     /// 缓冲区大小
     pub buffer_size: usize,
+
+    /// 账户/成交记录金额的定点小数位数（写入 V2 整数记录）
+    pub amount_decimals: u32,
 }
 
 impl Default for StorageSubscriberConfig {
@@ -53,6 +61,7 @@ impl Default for StorageSubscriberConfig {
             batch_size: 1000,     // 批量 1000 条
             batch_timeout_ms: 10, // 10ms 超时
             buffer_size: 10000,   // 缓冲 10K 条
+            amount_decimals: DEFAULT_AMOUNT_DECIMALS,
         }
     }
 }
@@ -70,6 +79,9 @@ pub struct StorageSubscriber {
 
     /// 统计信息
     stats: Arc<parking_lot::Mutex<SubscriberStats>>,
+
+    /// 导出日志（可选，订单/成交/账户变更同时追加到全局导出流）
+    export_log: Option<Arc<ExportLog>>,
 }
 
 /// 订阅器统计
@@ -101,11 +113,18 @@ impl StorageSubscriber {
             receiver,
             config,
             stats: stats.clone(),
+            export_log: None,
         };
 
         (subscriber, sender, stats)
     }
 
+    /// 设置导出日志
+    pub fn with_export_log(mut self, export_log: Arc<ExportLog>) -> Self {
+        self.export_log = Some(export_log);
+        self
+    }
+
     /// 获取或创建品种的 Storage
     fn get_or_create_storage(
         &mut self,
@@ -163,11 +182,15 @@ impl StorageSubscriber {
 
         let start = std::time::Instant::now();
 
-        // 按品种分组
+        // 按品种分组（导出流保持原始顺序）
         let mut grouped: HashMap<String, Vec<WalRecord>> = HashMap::new();
+        let mut export_records = Vec::new();
 
         for notification in batch.drain(..) {
             if let Some((instrument_id, wal_record)) = self.convert_notification(notification) {
+                if self.export_log.is_some() {
+                    export_records.push(wal_record.clone());
+                }
                 grouped.entry(instrument_id).or_default().push(wal_record);
             }
         }
@@ -202,6 +225,16 @@ impl StorageSubscriber {
             }
         }
 
+        // 追加到导出流
+        if let Some(ref export_log) = self.export_log {
+            if let Err(e) = export_log.append_batch(export_records) {
+                log::error!("Failed to append export log: {}", e);
+                let mut stats = self.stats.lock();
+                stats.total_errors += 1;
+                stats.last_error = Some(e);
+            }
+        }
+
         // 更新统计
         let mut stats = self.stats.lock();
         stats.total_persisted += total_persisted as u64;
@@ -217,6 +250,10 @@ impl StorageSubscriber {
         );
     }
 
+    fn amount_scale(&self) -> AmountScale {
+        AmountScale::new(self.config.amount_decimals)
+    }
+
     /// 转换 Notification → WalRecord
     fn convert_notification(&self, notification: Notification) -> Option<(String, WalRecord)> {
         match notification.payload {
@@ -250,38 +287,30 @@ impl StorageSubscriber {
                 Some(("__ACCOUNT__".to_string(), record))
             }
 
-            // 账户更新通知 -> WAL AccountUpdate
+            // 账户更新通知 -> WAL AccountUpdateV2（定点金额）
             NotificationPayload::AccountUpdate(account) => {
-                let mut user_id_bytes = [0u8; 32];
-                let user_bytes = account.user_id.as_bytes();
-                let copy_len = user_bytes.len().min(32);
-                user_id_bytes[..copy_len].copy_from_slice(&user_bytes[..copy_len]);
-
-                let record = WalRecord::AccountUpdate {
-                    user_id: user_id_bytes,
-                    balance: account.balance,
-                    available: account.available,
-                    frozen: account.frozen,
-                    margin: account.margin,
-                    timestamp: account.timestamp,
-                };
-
-                // AccountUpdate 使用特殊标记
-                Some(("__ACCOUNT__".to_string(), record))
+                match self.account_update_record(&account) {
+                    // AccountUpdate 使用特殊标记
+                    Ok(record) => Some(("__ACCOUNT__".to_string(), record)),
+                    Err(e) => {
+                        log::error!(
+                            "Failed to convert account update for {}: {}",
+                            account.user_id,
+                            e
+                        );
+                        None
+                    }
+                }
             }
 
-            // 成交通知 -> WAL TradeExecuted
-            NotificationPayload::TradeExecuted(trade) => {
-                let record = WalRecord::TradeExecuted {
-                    trade_id: self.parse_id(&trade.trade_id),
-                    order_id: self.parse_id(&trade.order_id),
-                    exchange_order_id: self.parse_id(&trade.exchange_order_id),
-                    price: trade.price,
-                    volume: trade.volume,
-                    timestamp: trade.timestamp,
-                };
-                Some((trade.instrument_id, record))
-            }
+            // 成交通知 -> WAL TradeExecutedV2（手续费定点）
+            NotificationPayload::TradeExecuted(trade) => match self.trade_executed_record(&trade) {
+                Ok(record) => Some((trade.instrument_id, record)),
+                Err(e) => {
+                    log::error!("Failed to convert trade {}: {}", trade.trade_id, e);
+                    None
+                }
+            },
 
             // 订单接受通知 -> WAL OrderInsert
             NotificationPayload::OrderAccepted(order) => {
@@ -316,6 +345,48 @@ impl StorageSubscriber {
     }
 
     /// 解析 ID (简化实现)
+    /// 账户更新 -> AccountUpdateV2（通知金额由定点台账换算而来，此处取整可精确还原）
+    fn account_update_record(
+        &self,
+        account: &AccountUpdateNotify,
+    ) -> Result<WalRecord, ExchangeError> {
+        let mut user_id_bytes = [0u8; 32];
+        let user_bytes = account.user_id.as_bytes();
+        let copy_len = user_bytes.len().min(32);
+        user_id_bytes[..copy_len].copy_from_slice(&user_bytes[..copy_len]);
+
+        let scale = self.amount_scale();
+        Ok(WalRecord::AccountUpdateV2 {
+            user_id: user_id_bytes,
+            amount_decimals: scale.decimals() as u8,
+            balance: scale.to_units(account.balance)?,
+            available: scale.to_units(account.available)?,
+            frozen: scale.to_units(account.frozen)?,
+            margin: scale.to_units(account.margin)?,
+            position_profit: scale.to_units(account.position_profit)?,
+            close_profit: scale.to_units(account.close_profit)?,
+            timestamp: account.timestamp,
+        })
+    }
+
+    /// 成交 -> TradeExecutedV2（手续费定点）
+    fn trade_executed_record(
+        &self,
+        trade: &TradeExecutedNotify,
+    ) -> Result<WalRecord, ExchangeError> {
+        let scale = self.amount_scale();
+        Ok(WalRecord::TradeExecutedV2 {
+            trade_id: self.parse_id(&trade.trade_id),
+            order_id: self.parse_id(&trade.order_id),
+            exchange_order_id: self.parse_id(&trade.exchange_order_id),
+            price: trade.price,
+            volume: trade.volume,
+            commission: scale.to_units(trade.commission)?,
+            amount_decimals: scale.decimals() as u8,
+            timestamp: trade.timestamp,
+        })
+    }
+
     fn parse_id(&self, id: &str) -> u64 {
         id.chars()
             .filter(|c| c.is_ascii_digit())
@@ -61,6 +61,8 @@ pub struct RecoveryStats {
     pub position_snapshot_records: u64,
     /// 账户快照记录 (Phase 14)
     pub account_snapshot_records: u64,
+    /// 风险快照记录
+    pub risk_snapshot_records: u64,
     /// 恢复耗时（毫秒）
     pub recovery_time_ms: u128,
     /// 错误数量
@@ -77,16 +79,24 @@ impl RecoveryStats {
     pub fn record(&mut self, record: &WalRecord) {
         self.total_records += 1;
         match record {
-            WalRecord::AccountOpen { .. } | WalRecord::AccountUpdate { .. } => {
+            WalRecord::AccountOpen { .. }
+            | WalRecord::AccountUpdate { .. }
+            | WalRecord::AccountUpdateV2 { .. } => {
                 self.account_records += 1;
             }
-            WalRecord::UserRegister { .. } | WalRecord::AccountBind { .. } | WalRecord::UserRoleUpdate { .. } => {
+            WalRecord::UserRegister { .. }
+            | WalRecord::AccountBind { .. }
+            | WalRecord::UserRoleUpdate { .. }
+            | WalRecord::UserAnonymize { .. }
+            | WalRecord::UserNotification { .. }
+            | WalRecord::NotificationRead { .. }
+            | WalRecord::UserPasswordUpdate { .. } => {
                 self.user_records += 1;
             }
             WalRecord::OrderInsert { .. } => {
                 self.order_records += 1;
             }
-            WalRecord::TradeExecuted { .. } => {
+            WalRecord::TradeExecuted { .. } | WalRecord::TradeExecutedV2 { .. } => {
                 self.trade_records += 1;
             }
             WalRecord::TickData { .. }
@@ -118,6 +128,9 @@ impl RecoveryStats {
             WalRecord::AccountSnapshot { .. } => {
                 self.account_snapshot_records += 1;
             }
+            WalRecord::RiskSnapshot { .. } => {
+                self.risk_snapshot_records += 1;
+            }
         }
     }
 
@@ -140,6 +153,7 @@ impl RecoveryStats {
         log::info!("订单状态更新:    {}", self.order_status_records);
         log::info!("持仓快照:        {}", self.position_snapshot_records);
         log::info!("账户快照:        {}", self.account_snapshot_records);
+        log::info!("风险快照:        {}", self.risk_snapshot_records);
         log::info!("───────────────────────────────────────────────────────────");
         log::info!("恢复耗时:        {} ms", self.recovery_time_ms);
         log::info!("错误数量:        {}", self.error_count);
@@ -179,6 +193,8 @@ pub struct RecoveredUser {
     pub email: Option<String>,
     pub created_at: i64,
     pub account_ids: Vec<String>,
+    /// 已注销（个人信息已匿名化）
+    pub deleted: bool,
 }
 
 /// K线数据（恢复用）
@@ -605,6 +621,8 @@ impl UnifiedRecoveryManager {
         record: WalRecord,
         result: &mut UnifiedRecoveryResult,
     ) {
+        // 定点金额版本按浮点版本恢复
+        let record = record.to_float_record().unwrap_or(record);
         match record {
             // ═══════════════════════════════════════════════════════════════════
             // 账户数据恢复
@@ -699,6 +717,7 @@ impl UnifiedRecoveryManager {
                         },
                         created_at,
                         account_ids: Vec::new(),
+                        deleted: false,
                     },
                 );
             }
@@ -718,6 +737,38 @@ impl UnifiedRecoveryManager {
                 }
             }
 
+            // 用户注销：覆盖历史注册记录中的个人信息，账户绑定保留
+            WalRecord::UserAnonymize {
+                user_id,
+                username,
+                phone_hash,
+                email_hash,
+                ..
+            } if self.config.recover_users => {
+                let user_id_str = WalRecord::from_fixed_array(&user_id);
+                if let Some(user) = result.users.get_mut(&user_id_str) {
+                    let phone_hash = WalRecord::from_fixed_array(&phone_hash);
+                    let email_hash = WalRecord::from_fixed_array(&email_hash);
+                    user.username = WalRecord::from_fixed_array(&username);
+                    user.password_hash.clear();
+                    user.phone = (!phone_hash.is_empty()).then_some(phone_hash);
+                    user.email = (!email_hash.is_empty()).then_some(email_hash);
+                    user.deleted = true;
+                }
+            }
+
+            // 修改密码：覆盖注册记录中的密码哈希
+            WalRecord::UserPasswordUpdate {
+                user_id,
+                password_hash,
+                ..
+            } if self.config.recover_users => {
+                let user_id_str = WalRecord::from_fixed_array(&user_id);
+                if let Some(user) = result.users.get_mut(&user_id_str) {
+                    user.password_hash = WalRecord::from_fixed_array(&password_hash);
+                }
+            }
+
             // ═══════════════════════════════════════════════════════════════════
             // Phase 14: 订单生命周期恢复
             // @yutiansut @quantaxis
@@ -17,7 +17,7 @@ use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 /// WAL 文件 Header
 #[derive(Debug, Clone)]
@@ -212,6 +212,12 @@ impl WalManager {
         manager
     }
 
+    /// 设置单个 WAL 文件大小上限（超过后滚动到新文件）
+    pub fn with_max_file_size(mut self, max_file_size: u64) -> Self {
+        self.max_file_size = max_file_size;
+        self
+    }
+
     /// 获取统计信息
     pub fn get_stats(&self) -> &WalStats {
         &self.stats
@@ -544,13 +550,29 @@ impl WalManager {
     }
 
     /// 回放 WAL（崩溃恢复）
-    pub fn replay<F>(&self, mut callback: F) -> Result<(), String>
+    pub fn replay<F>(&self, callback: F) -> Result<(), String>
+    where
+        F: FnMut(WalEntry) -> Result<(), String>,
+    {
+        self.replay_from(0, callback)
+    }
+
+    /// 从指定序列号开始回放 WAL（增量消费）
+    ///
+    /// 条目全部早于 `from_sequence` 的已滚动文件直接跳过，不读取
+    pub fn replay_from<F>(&self, from_sequence: u64, mut callback: F) -> Result<(), String>
     where
         F: FnMut(WalEntry) -> Result<(), String>,
     {
         let files = self.list_wal_files()?;
+        let next_starts = self.next_file_starts(&files)?;
+
+        for (file_path, next_start) in files.into_iter().zip(next_starts) {
+            // 文件内条目序列号都小于下一个文件的起始序列号
+            if next_start.is_some_and(|next| next <= from_sequence) {
+                continue;
+            }
 
-        for file_path in files {
             let mut file = File::open(&file_path).map_err(|e| format!("Open WAL failed: {}", e))?;
 
             // 读取 Header (128 bytes)
@@ -594,6 +616,10 @@ impl WalManager {
                     continue;
                 }
 
+                if entry.sequence < from_sequence {
+                    continue;
+                }
+
                 callback(entry)?;
             }
         }
@@ -617,6 +643,55 @@ impl WalManager {
         Ok(())
     }
 
+    /// 清理已消费的 WAL 文件（如已转换为 OLAP 的数据）
+    ///
+    /// 删除条目序列号全部不大于 `sequence` 且修改时间早于 `min_age` 的已滚动文件，
+    /// 当前写入文件始终保留。返回删除的文件数。
+    pub fn purge_consumed(&self, sequence: u64, min_age: Duration) -> Result<usize, String> {
+        let files = self.list_wal_files()?;
+        let next_starts = self.next_file_starts(&files)?;
+        let current_file = self.current_file_path.lock().clone();
+        let now = std::time::SystemTime::now();
+        let mut removed = 0;
+
+        for (file_path, next_start) in files.into_iter().zip(next_starts) {
+            let fully_consumed = next_start.is_some_and(|next| next <= sequence + 1);
+            if !fully_consumed || file_path == current_file {
+                continue;
+            }
+
+            let old_enough = std::fs::metadata(&file_path)
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|modified| now.duration_since(modified).ok())
+                .is_some_and(|age| age >= min_age);
+            if !old_enough {
+                continue;
+            }
+
+            std::fs::remove_file(&file_path).map_err(|e| format!("Purge WAL failed: {}", e))?;
+            log::info!("Purged consumed WAL: {}", file_path);
+            removed += 1;
+        }
+
+        Ok(removed)
+    }
+
+    /// 每个文件之后的文件起始序列号（最后一个文件为 None）
+    fn next_file_starts(&self, files: &[String]) -> Result<Vec<Option<u64>>, String> {
+        let mut starts = Vec::with_capacity(files.len());
+        for file_path in files {
+            let mut file = File::open(file_path).map_err(|e| format!("Open WAL failed: {}", e))?;
+            let mut header_buf = vec![0u8; 128];
+            file.read_exact(&mut header_buf)
+                .map_err(|e| format!("Read WAL header failed: {}", e))?;
+            starts.push(WalFileHeader::from_bytes(&header_buf)?.start_sequence);
+        }
+        Ok((0..files.len())
+            .map(|i| starts.get(i + 1).copied())
+            .collect())
+    }
+
     /// 滚动到新文件
     fn rotate_file(&self) -> Result<(), String> {
         let new_sequence = self.current_sequence.load(Ordering::SeqCst);
@@ -768,6 +843,43 @@ mod tests {
         assert_eq!(count, 10);
     }
 
+    #[test]
+    fn test_replay_from_and_purge_consumed() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let wal = WalManager::new(tmp_dir.path().to_str().unwrap()).with_max_file_size(600);
+
+        for i in 0..20 {
+            wal.append(WalRecord::Checkpoint {
+                sequence: i,
+                timestamp: 12345,
+            })
+            .unwrap();
+        }
+        let files_before = wal.list_wal_files().unwrap().len();
+        assert!(files_before > 2);
+
+        let mut sequences = Vec::new();
+        wal.replay_from(11, |entry| {
+            sequences.push(entry.sequence);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(sequences, (11..=20).collect::<Vec<_>>());
+
+        // 序列号 ≤ 10 的条目已消费，清理后不影响后续回放
+        let removed = wal.purge_consumed(10, Duration::ZERO).unwrap();
+        assert!(removed > 0);
+        assert_eq!(wal.list_wal_files().unwrap().len(), files_before - removed);
+
+        let mut count = 0;
+        wal.replay_from(11, |_| {
+            count += 1;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(count, 10);
+    }
+
     #[test]
     fn test_checkpoint() {
         let tmp_dir = tempfile::tempdir().unwrap();
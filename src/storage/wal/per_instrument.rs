@@ -20,6 +20,9 @@ pub struct PerInstrumentWalManager {
     /// 基础路径，每个品种创建子目录: {base_path}/{instrument_id}/
     base_path: String,
 
+    /// 品种目录下的 WAL 子目录（None 表示 WAL 文件直接位于品种目录）
+    wal_subdir: Option<String>,
+
     /// 每个品种的 WAL 管理器（无锁并发访问）
     /// Key: instrument_id (String)
     /// Value: Arc<WalManager>
@@ -45,22 +48,42 @@ impl PerInstrumentWalManager {
 
         Self {
             base_path: base_path.to_string(),
+            wal_subdir: None,
             managers: Arc::new(DashMap::new()),
         }
     }
 
+    /// 创建 WAL 位于品种子目录的管理器: {base_path}/{instrument_id}/{wal_subdir}/
+    ///
+    /// 用于分区存储，品种目录下同时存放 WAL、SSTable 等文件
+    pub fn with_wal_subdir(base_path: &str, wal_subdir: &str) -> Self {
+        let mut mgr = Self::new(base_path);
+        mgr.wal_subdir = Some(wal_subdir.to_string());
+        mgr
+    }
+
+    /// 品种 WAL 目录
+    fn wal_path(&self, instrument_id: &str) -> String {
+        match self.wal_subdir {
+            Some(ref subdir) => format!("{}/{}/{}", self.base_path, instrument_id, subdir),
+            None => format!("{}/{}", self.base_path, instrument_id),
+        }
+    }
+
     /// 获取或创建指定品种的 WAL 管理器
     fn get_or_create_manager(&self, instrument_id: &str) -> Arc<WalManager> {
         // 使用 DashMap 的 entry API 实现无锁并发插入
         self.managers
             .entry(instrument_id.to_string())
-            .or_insert_with(|| {
-                let wal_path = format!("{}/{}", self.base_path, instrument_id);
-                Arc::new(WalManager::new(&wal_path))
-            })
+            .or_insert_with(|| Arc::new(WalManager::new(&self.wal_path(instrument_id))))
             .clone()
     }
 
+    /// 获取指定品种的 WAL 管理器（不存在时创建）
+    pub fn manager(&self, instrument_id: &str) -> Arc<WalManager> {
+        self.get_or_create_manager(instrument_id)
+    }
+
     /// 追加 WAL 记录（自动路由到对应品种的 WAL）
     ///
     /// # Arguments
@@ -21,10 +21,14 @@
 //
 // 支持的记录类型：
 // - AccountOpen/AccountUpdate: 账户数据
+// - AccountUpdateV2/TradeExecutedV2: 定点金额版本（i64 整数字段，避免浮点累积误差）
 // - OrderInsert/TradeExecuted: 用户订单和成交
 // - OrderStatusUpdate: 订单状态变更（部分成交、撤单等）✨ Phase 14
 // - PositionSnapshot: 持仓快照 ✨ Phase 14
 // - AccountSnapshot: 账户完整快照（含订单、持仓、冻结）✨ Phase 14
+// - RiskSnapshot: 账户风险快照（风险率、保证金采样）
+// - UserNotification/NotificationRead: 用户消息中心（历史通知与已读状态）
+// - UserPasswordUpdate: 用户修改密码（恢复时覆盖注册记录中的密码哈希）
 // - ExchangeOrderRecord/ExchangeTradeRecord: 交易所逐笔数据
 // - TickData/OrderBookSnapshot/OrderBookDelta: 行情数据
 // - KLineFinished: K线数据（多周期）
@@ -41,6 +45,8 @@
 
 use rkyv::{Archive, Deserialize as RkyvDeserialize, Serialize as RkyvSerialize};
 
+use crate::exchange::fixed_point::AmountScale;
+
 /// WAL 记录类型（仅使用 rkyv 序列化，不需要 serde）
 #[derive(Debug, Clone, Archive, RkyvSerialize, RkyvDeserialize)]
 #[archive(check_bytes)]
@@ -173,6 +179,7 @@ pub enum WalRecord {
         deal_volume: f64,            // 成交数量
         time: i64,                   // 纳秒时间戳
         trade_id: i64,               // 成交ID（统一事件序列）
+        match_batch_id: i64,         // 撮合批次号（0 表示非撮合成交，如大宗交易）
     },
 
     /// 交易所回报记录 (Phase 5)
@@ -349,6 +356,85 @@ pub enum WalRecord {
         last_sequence: u64,      // 最后处理的WAL序列号
         timestamp: i64,          // 纳秒时间戳
     },
+
+    /// 账户风险快照 @yutiansut @quantaxis
+    /// 盘中风控按采样间隔写入，用于历史风险回放
+    /// account_id 为 "__MARKET__" 时表示全市场汇总
+    RiskSnapshot {
+        account_id: [u8; 64], // 账户ID
+        balance: f64,         // 账户权益
+        margin: f64,          // 占用保证金
+        risk_ratio: f64,      // 风险率
+        position_value: f64,  // 持仓市值
+        timestamp: i64,       // 纳秒时间戳
+    },
+
+    /// 用户注销匿名化 @yutiansut @quantaxis
+    /// 恢复时覆盖该用户历史 UserRegister 中的个人身份信息
+    /// 各摘要为加盐 SHA-256 十六进制串，全零表示原记录无该字段
+    UserAnonymize {
+        user_id: [u8; 40],        // 用户ID (UUID，保留)
+        username: [u8; 32],       // 匿名用户名
+        phone_hash: [u8; 64],     // 手机号摘要
+        email_hash: [u8; 64],     // 邮箱摘要
+        real_name_hash: [u8; 64], // 真实姓名摘要
+        id_card_hash: [u8; 64],   // 身份证号摘要
+        timestamp: i64,           // 注销时间戳
+    },
+
+    /// 用户消息中心通知 @yutiansut @quantaxis
+    /// NotificationStore 异步批量写入，按用户分区存储
+    UserNotification {
+        message_id: [u8; 40],   // 消息ID (UUID)
+        user_id: [u8; 40],      // 接收用户/账户ID
+        message_type: [u8; 32], // 通知类型 (NotificationType::as_str)
+        priority: u8,           // 优先级
+        content: [u8; 1024],    // 消息负载 JSON
+        timestamp: i64,         // 纳秒时间戳
+    },
+
+    /// 通知已读标记 @yutiansut @quantaxis
+    /// message_id 全零表示该用户 timestamp 之前的通知全部已读
+    NotificationRead {
+        user_id: [u8; 40],    // 接收用户/账户ID
+        message_id: [u8; 40], // 消息ID
+        timestamp: i64,       // 标记时间戳
+    },
+
+    /// 用户修改密码 @yutiansut @quantaxis
+    /// 恢复时按时间戳覆盖该用户注册记录中的密码哈希
+    UserPasswordUpdate {
+        user_id: [u8; 40],       // 用户ID (UUID, 36 chars + padding)
+        password_hash: [u8; 64], // 新密码哈希 (bcrypt, 60字符)
+        timestamp: i64,          // 修改时间戳
+    },
+
+    /// 账户更新（定点金额版本）@yutiansut @quantaxis
+    /// 金额为 i64 定点单位（金额 × 10^amount_decimals），替代 AccountUpdate 的 f64 字段；
+    /// 新增变体只能追加在末尾，保持旧 WAL 文件的判别值不变
+    AccountUpdateV2 {
+        user_id: [u8; 32],
+        amount_decimals: u8, // 定点小数位数
+        balance: i64,
+        available: i64,
+        frozen: i64,
+        margin: i64,
+        position_profit: i64,
+        close_profit: i64,
+        timestamp: i64,
+    },
+
+    /// 成交回报（定点金额版本）@yutiansut @quantaxis
+    TradeExecutedV2 {
+        trade_id: u64,
+        order_id: u64,
+        exchange_order_id: u64,
+        price: f64,
+        volume: f64,
+        commission: i64,     // 手续费（定点单位）
+        amount_decimals: u8, // 定点小数位数
+        timestamp: i64,
+    },
 }
 
 impl WalRecord {
@@ -397,12 +483,93 @@ impl WalRecord {
         arr
     }
 
+    /// 辅助函数：字符串转固定长度数组 [u8; 1024] (用于通知负载)
+    pub fn to_fixed_array_1024(s: &str) -> [u8; 1024] {
+        let mut arr = [0u8; 1024];
+        let bytes = s.as_bytes();
+        let len = bytes.len().min(1024);
+        arr[..len].copy_from_slice(&bytes[..len]);
+        arr
+    }
+
+    /// 辅助函数：定点金额转浮点（V2 记录）
+    pub fn from_fixed_amount(units: i64, amount_decimals: u8) -> f64 {
+        AmountScale::new(amount_decimals as u32).to_f64(units)
+    }
+
+    /// 定点金额版本记录转为对应的浮点版本（列式存储、导出等只有浮点列的场景），
+    /// 其他记录返回 None
+    pub fn to_float_record(&self) -> Option<WalRecord> {
+        match self {
+            WalRecord::AccountUpdateV2 {
+                user_id,
+                amount_decimals,
+                balance,
+                available,
+                frozen,
+                margin,
+                timestamp,
+                ..
+            } => {
+                let amount = |units: &i64| Self::from_fixed_amount(*units, *amount_decimals);
+                Some(WalRecord::AccountUpdate {
+                    user_id: *user_id,
+                    balance: amount(balance),
+                    available: amount(available),
+                    frozen: amount(frozen),
+                    margin: amount(margin),
+                    timestamp: *timestamp,
+                })
+            }
+            WalRecord::TradeExecutedV2 {
+                trade_id,
+                order_id,
+                exchange_order_id,
+                price,
+                volume,
+                timestamp,
+                ..
+            } => Some(WalRecord::TradeExecuted {
+                trade_id: *trade_id,
+                order_id: *order_id,
+                exchange_order_id: *exchange_order_id,
+                price: *price,
+                volume: *volume,
+                timestamp: *timestamp,
+            }),
+            _ => None,
+        }
+    }
+
     /// 辅助函数：固定数组转字符串
     pub fn from_fixed_array(arr: &[u8]) -> String {
         String::from_utf8_lossy(arr)
             .trim_end_matches('\0')
             .to_string()
     }
+
+    /// 分区存储的路由合约
+    ///
+    /// 行情与成交类记录返回所属合约，按合约写入独立分区；
+    /// 账户、用户、订单等记录返回 None，保留在账户分区
+    pub fn partition_instrument(&self) -> Option<String> {
+        let instrument = match self {
+            WalRecord::TickData { instrument_id, .. }
+            | WalRecord::OrderBookSnapshot { instrument_id, .. }
+            | WalRecord::OrderBookDelta { instrument_id, .. }
+            | WalRecord::KLineFinished { instrument_id, .. }
+            | WalRecord::FactorUpdate { instrument_id, .. }
+            | WalRecord::FactorSnapshot { instrument_id, .. } => {
+                Self::from_fixed_array(instrument_id)
+            }
+            WalRecord::ExchangeOrderRecord { instrument, .. }
+            | WalRecord::ExchangeTradeRecord { instrument, .. } => {
+                Self::from_fixed_array(instrument)
+            }
+            _ => return None,
+        };
+        (!instrument.is_empty()).then_some(instrument)
+    }
 }
 
 /// WAL 日志条目
@@ -418,7 +585,7 @@ pub struct WalEntry {
 impl WalEntry {
     /// 创建新的 WAL 条目
     pub fn new(sequence: u64, record: WalRecord) -> Self {
-        let timestamp = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
+        let timestamp = crate::utils::timestamp::now_nanos();
 
         Self {
             sequence,
@@ -553,4 +720,40 @@ mod tests {
         assert_eq!(recovered.crc32, entry.crc32);
         assert!(recovered.verify_crc32());
     }
+
+    #[test]
+    fn test_fixed_amount_round_trip() {
+        let record = WalRecord::AccountUpdateV2 {
+            user_id: [1u8; 32],
+            amount_decimals: 4,
+            balance: 10_000_000_123,
+            available: 9_000_000_000,
+            frozen: 0,
+            margin: 1_000_000_123,
+            position_profit: -1_234,
+            close_profit: 5_678,
+            timestamp: 12345,
+        };
+
+        let entry = WalEntry::new(1, record).with_crc32();
+        let bytes = entry.to_bytes().unwrap();
+        let archived = WalEntry::from_bytes(&bytes).unwrap();
+        let recovered: WalEntry = archived.deserialize(&mut rkyv::Infallible).unwrap();
+        assert!(recovered.verify_crc32());
+
+        match recovered.record {
+            WalRecord::AccountUpdateV2 {
+                balance,
+                amount_decimals,
+                ..
+            } => {
+                assert_eq!(balance, 10_000_000_123);
+                assert_eq!(
+                    WalRecord::from_fixed_amount(balance, amount_decimals),
+                    1_000_000.0123
+                );
+            }
+            other => panic!("unexpected record: {:?}", other),
+        }
+    }
 }
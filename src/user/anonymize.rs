@@ -0,0 +1,199 @@
+//! 用户注销与个人信息匿名化 @yutiansut @quantaxis
+//!
+//! WAL/SSTable 只追加不可删除，注销时写入一条 `WalRecord::UserAnonymize`，
+//! 恢复时在 `UserRegister` 之后应用，覆盖历史记录中的个人身份信息：
+//! - 用户名替换为 `deleted_` + 摘要前缀，手机号/邮箱/真实姓名/身份证号替换为加盐 SHA-256 摘要
+//! - 盐每次注销随机生成且不落盘，摘要无法反推原值，也无法用撞库方式关联
+//! - user_id（UUID）与账户绑定保留，订单/成交等交易数据仍按 user_id 关联但不再指向具体身份
+//! - 密码哈希清空，状态置为 `UserStatus::Deleted`，无法再登录
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use super::{User, UserStatus};
+use crate::storage::wal::record::WalRecord;
+
+/// 匿名用户名前缀
+pub const ANONYMIZED_USERNAME_PREFIX: &str = "deleted_";
+
+/// 匿名化后的身份信息（与 `WalRecord::UserAnonymize` 一一对应）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnonymizedIdentity {
+    pub user_id: String,
+    pub username: String,
+    pub phone_hash: Option<String>,
+    pub email_hash: Option<String>,
+    pub real_name_hash: Option<String>,
+    pub id_card_hash: Option<String>,
+    /// 注销时间 (Unix timestamp)
+    pub anonymized_at: i64,
+}
+
+/// 用户的原始个人标识（用户名/手机号/邮箱/真实姓名/身份证号），注销时用于重写 OLAP 历史数据
+pub fn personal_identifiers(user: &User) -> Vec<String> {
+    std::iter::once(user.username.clone())
+        .chain(user.phone.clone())
+        .chain(user.email.clone())
+        .chain(user.real_name.clone())
+        .chain(user.id_card.clone())
+        .filter(|id| !id.is_empty())
+        .collect()
+}
+
+impl AnonymizedIdentity {
+    /// 根据用户当前信息生成匿名身份（随机盐，不可逆）
+    pub fn from_user(user: &User) -> Self {
+        let salt = Uuid::new_v4();
+        let hash = |value: &str| {
+            let mut hasher = Sha256::new();
+            hasher.update(salt.as_bytes());
+            hasher.update(value.as_bytes());
+            hasher
+                .finalize()
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<String>()
+        };
+
+        let username_hash = hash(&user.username);
+        Self {
+            user_id: user.user_id.clone(),
+            username: format!("{}{}", ANONYMIZED_USERNAME_PREFIX, &username_hash[..16]),
+            phone_hash: user.phone.as_deref().map(hash),
+            email_hash: user.email.as_deref().map(hash),
+            real_name_hash: user.real_name.as_deref().map(hash),
+            id_card_hash: user.id_card.as_deref().map(hash),
+            anonymized_at: chrono::Utc::now().timestamp(),
+        }
+    }
+
+    /// 被替换的个人信息字段名
+    pub fn erased_fields(&self) -> Vec<String> {
+        let mut fields = vec!["username".to_string(), "password_hash".to_string()];
+        for (name, value) in [
+            ("phone", &self.phone_hash),
+            ("email", &self.email_hash),
+            ("real_name", &self.real_name_hash),
+            ("id_card", &self.id_card_hash),
+        ] {
+            if value.is_some() {
+                fields.push(name.to_string());
+            }
+        }
+        fields
+    }
+
+    /// 覆盖用户的个人信息并标记为已注销
+    pub fn apply(&self, user: &mut User) {
+        user.username = self.username.clone();
+        user.password_hash.clear();
+        user.phone = self.phone_hash.clone();
+        user.email = self.email_hash.clone();
+        user.real_name = self.real_name_hash.clone();
+        user.id_card = self.id_card_hash.clone();
+        user.status = UserStatus::Deleted;
+        user.updated_at = self.anonymized_at;
+    }
+
+    /// 转换为 WAL 记录
+    pub fn to_wal_record(&self) -> WalRecord {
+        let fixed = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map(WalRecord::to_fixed_array_64)
+                .unwrap_or([0u8; 64])
+        };
+        WalRecord::UserAnonymize {
+            user_id: WalRecord::to_fixed_array_40(&self.user_id),
+            username: WalRecord::to_fixed_array_32(&self.username),
+            phone_hash: fixed(&self.phone_hash),
+            email_hash: fixed(&self.email_hash),
+            real_name_hash: fixed(&self.real_name_hash),
+            id_card_hash: fixed(&self.id_card_hash),
+            timestamp: self.anonymized_at,
+        }
+    }
+
+    /// 从 WAL 记录还原（非 UserAnonymize 记录返回 None）
+    pub fn from_wal_record(record: &WalRecord) -> Option<Self> {
+        let WalRecord::UserAnonymize {
+            user_id,
+            username,
+            phone_hash,
+            email_hash,
+            real_name_hash,
+            id_card_hash,
+            timestamp,
+        } = record
+        else {
+            return None;
+        };
+        let optional = |arr: &[u8]| {
+            let value = WalRecord::from_fixed_array(arr);
+            (!value.is_empty()).then_some(value)
+        };
+        Some(Self {
+            user_id: WalRecord::from_fixed_array(user_id),
+            username: WalRecord::from_fixed_array(username),
+            phone_hash: optional(phone_hash),
+            email_hash: optional(email_hash),
+            real_name_hash: optional(real_name_hash),
+            id_card_hash: optional(id_card_hash),
+            anonymized_at: *timestamp,
+        })
+    }
+}
+
+/// 用户注销处理报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserErasureReport {
+    pub user_id: String,
+    /// 匿名用户名
+    pub anonymized_username: String,
+    /// 被替换的个人信息字段
+    pub erased_fields: Vec<String>,
+    /// 保留的账户（交易数据保留，仅与匿名身份关联）
+    pub retained_account_ids: Vec<String>,
+    /// 是否已写入匿名化 WAL 记录（未配置存储时为 false）
+    pub wal_persisted: bool,
+    /// OLAP 历史数据重写结果（未执行时为 None）
+    pub olap_rewrite: Option<crate::storage::conversion::OlapAnonymizeReport>,
+    pub processed_at: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anonymize_user_and_wal_roundtrip() {
+        let mut user = User::new("alice".to_string(), "hash".to_string());
+        user.phone = Some("13800138000".to_string());
+        user.real_name = Some("张三".to_string());
+
+        let identity = AnonymizedIdentity::from_user(&user);
+        assert!(identity.username.starts_with(ANONYMIZED_USERNAME_PREFIX));
+        assert_eq!(identity.phone_hash.as_ref().unwrap().len(), 64);
+        assert!(identity.email_hash.is_none());
+        assert_eq!(
+            identity.erased_fields(),
+            vec!["username", "password_hash", "phone", "real_name"]
+        );
+
+        // 随机盐：同一用户两次匿名化结果不同
+        assert_ne!(AnonymizedIdentity::from_user(&user), identity);
+
+        let restored = AnonymizedIdentity::from_wal_record(&identity.to_wal_record()).unwrap();
+        assert_eq!(restored, identity);
+
+        let user_id = user.user_id.clone();
+        identity.apply(&mut user);
+        assert_eq!(user.user_id, user_id);
+        assert_eq!(user.status, UserStatus::Deleted);
+        assert!(user.password_hash.is_empty());
+        assert_ne!(user.phone.as_deref(), Some("13800138000"));
+        assert_ne!(user.real_name.as_deref(), Some("张三"));
+        assert!(!user.verify_password("hash"));
+    }
+}
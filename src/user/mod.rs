@@ -4,6 +4,7 @@
 //! 用户(User) 1对多 账户(QA_Account) 的关系管理
 //! RBAC 权限体系 @yutiansut @quantaxis
 
+pub mod anonymize;
 pub mod recovery;
 pub mod user_manager;
 
@@ -629,6 +630,7 @@ pub struct AccountBindRequest {
 }
 
 // 重新导出
+pub use anonymize::{personal_identifiers, AnonymizedIdentity, UserErasureReport};
 pub use recovery::{UserRecovery, UserRecoveryStats};
 pub use user_manager::UserManager;
 
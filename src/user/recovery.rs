@@ -1,10 +1,12 @@
 //! 用户数据恢复模块
 //!
 //! 从WAL恢复用户注册和账户绑定数据
+//! 注销用户按 `UserAnonymize` 记录覆盖历史注册信息，不恢复任何原始个人信息
 
 use crate::storage::hybrid::OltpHybridStorage;
 use crate::storage::wal::record::WalRecord;
-use crate::user::{User, UserManager, UserStatus};
+use crate::user::{AnonymizedIdentity, User, UserManager, UserStatus};
+use crate::utils::timestamp::{now_nanos, secs_to_nanos};
 use crate::ExchangeError;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -17,6 +19,8 @@ pub struct UserRecoveryStats {
     pub total_records: usize,
     pub user_register_records: usize,
     pub account_bind_records: usize,
+    pub anonymize_records: usize,
+    pub password_update_records: usize,
     pub users_recovered: usize,
     pub recovery_time_ms: u128,
 }
@@ -136,6 +140,32 @@ impl UserRecovery {
                     }
                 }
 
+                // 修改密码：按时间戳覆盖注册记录中的密码哈希 @yutiansut @quantaxis
+                WalRecord::UserPasswordUpdate {
+                    user_id,
+                    password_hash,
+                    timestamp,
+                } => {
+                    stats.password_update_records += 1;
+                    let user_id_str = WalRecord::from_fixed_array(&user_id);
+                    if let Some(user) = users_map.get_mut(&user_id_str) {
+                        if timestamp >= user.updated_at {
+                            user.password_hash = WalRecord::from_fixed_array(&password_hash);
+                            user.updated_at = timestamp;
+                        }
+                    }
+                }
+
+                // 用户注销：匿名化覆盖历史注册记录 @yutiansut @quantaxis
+                WalRecord::UserAnonymize { .. } => {
+                    stats.anonymize_records += 1;
+                    if let Some(identity) = AnonymizedIdentity::from_wal_record(&record) {
+                        if let Some(user) = users_map.get_mut(&identity.user_id) {
+                            identity.apply(user);
+                        }
+                    }
+                }
+
                 _ => {
                     // 忽略其他类型的记录
                 }
@@ -154,6 +184,12 @@ impl UserRecovery {
             self.user_manager
                 .username_index
                 .insert(user.username.clone(), user_id.clone());
+
+            // 已注销用户的手机号/邮箱为摘要，不参与唯一性索引
+            if user.status == UserStatus::Deleted {
+                stats.users_recovered += 1;
+                continue;
+            }
             if let Some(ref phone) = user.phone {
                 self.user_manager
                     .phone_index
@@ -192,8 +228,8 @@ impl UserRecovery {
 
     /// 恢复最近N小时的用户数据
     pub fn recover_recent_hours(&self, hours: i64) -> Result<UserRecoveryStats> {
-        let end_ts = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
-        let start_ts = end_ts - (hours * 3600 * 1_000_000_000);
+        let end_ts = now_nanos();
+        let start_ts = end_ts - secs_to_nanos(hours * 3600);
 
         self.recover_users(start_ts, end_ts)
     }
@@ -268,6 +304,9 @@ mod tests {
         user_manager
             .bind_account(&user2.user_id, "account3".to_string())
             .unwrap();
+        user_manager
+            .change_password(&user2.user_id, "password2", "password2_new")
+            .unwrap();
 
         // 等待WAL刷盘
         tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
@@ -297,9 +336,88 @@ mod tests {
         assert_eq!(recovered_user2.email, Some("user2@example.com".to_string()));
         assert_eq!(recovered_user2.account_ids.len(), 1);
         assert_eq!(recovered_user2.user_id, user2.user_id);
+        // 修改后的密码生效
+        assert_eq!(stats.password_update_records, 1);
+        assert!(recovered_user2.verify_password("password2_new"));
+        assert!(!recovered_user2.verify_password("password2"));
 
         // 验证索引 - 通过username查询后再验证user_id
         let user_by_username = new_user_manager.get_user_by_username("user1").unwrap();
         assert_eq!(user_by_username.user_id, recovered_user1.user_id);
     }
+
+    /// 测试恢复流程应用匿名化记录：重启后注销用户的原始个人信息不会从历史注册记录中复原
+    #[tokio::test]
+    async fn test_user_recovery_applies_anonymization() {
+        let temp_dir = tempdir().unwrap();
+        let config = OltpHybridConfig {
+            base_path: temp_dir.path().to_str().unwrap().to_string(),
+            memtable_size_bytes: 1024 * 1024,
+            estimated_entry_size: 256,
+            enable_olap_conversion: false,
+            ..Default::default()
+        };
+        let storage = Arc::new(OltpHybridStorage::create("test_user", config).unwrap());
+
+        let mut user_manager = UserManager::new();
+        user_manager.set_storage(storage.clone());
+        let user_manager = Arc::new(user_manager);
+
+        let erased = user_manager
+            .register(UserRegisterRequest {
+                username: "gone".to_string(),
+                password: "password1".to_string(),
+                phone: Some("13800138009".to_string()),
+                email: Some("gone@example.com".to_string()),
+                real_name: None,
+                id_card: None,
+            })
+            .unwrap();
+        let kept = user_manager
+            .register(UserRegisterRequest {
+                username: "kept".to_string(),
+                password: "password2".to_string(),
+                phone: Some("13800138010".to_string()),
+                email: None,
+                real_name: None,
+                id_card: None,
+            })
+            .unwrap();
+        user_manager
+            .bind_account(&erased.user_id, "ACC_GONE".to_string())
+            .unwrap();
+        let report = user_manager.erase_user(&erased.user_id).unwrap();
+        assert!(report.wal_persisted);
+
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+        // 模拟重启
+        let new_user_manager = Arc::new(UserManager::new());
+        let recovery = UserRecovery::new(storage.clone(), new_user_manager.clone());
+        let stats = recovery.recover_all_users().unwrap();
+        assert_eq!(stats.users_recovered, 2);
+        assert_eq!(stats.anonymize_records, 1);
+
+        let recovered = new_user_manager.get_user(&erased.user_id).unwrap();
+        assert_eq!(recovered.status, UserStatus::Deleted);
+        assert_eq!(recovered.username, report.anonymized_username);
+        assert!(recovered.password_hash.is_empty());
+        assert_ne!(recovered.phone.as_deref(), Some("13800138009"));
+        assert_ne!(recovered.email.as_deref(), Some("gone@example.com"));
+        // 交易数据关联保留
+        assert_eq!(recovered.account_ids, vec!["ACC_GONE".to_string()]);
+
+        // 原始身份信息无法再定位到该用户
+        assert!(new_user_manager.get_user_by_username("gone").is_err());
+        assert!(!new_user_manager.phone_index.contains_key("13800138009"));
+        assert!(!new_user_manager
+            .email_index
+            .contains_key("gone@example.com"));
+
+        // 未注销用户不受影响
+        let recovered_kept = new_user_manager.get_user_by_username("kept").unwrap();
+        assert_eq!(recovered_kept.user_id, kept.user_id);
+        assert_eq!(recovered_kept.phone, Some("13800138010".to_string()));
+        assert!(recovered_kept.is_active());
+    }
 }
@@ -2,6 +2,7 @@
 //!
 //! 负责用户的注册、登录、查询、账户绑定等管理功能
 
+use super::anonymize::{AnonymizedIdentity, UserErasureReport};
 use super::{Permission, User, UserLoginRequest, UserLoginResponse, UserRegisterRequest, UserRole, UserStatus};
 use crate::ExchangeError;
 use dashmap::DashMap;
@@ -231,6 +232,52 @@ impl UserManager {
         })
     }
 
+    /// 修改密码：校验原密码后更新密码哈希并写入 WAL @yutiansut @quantaxis
+    pub fn change_password(
+        &self,
+        user_id: &str,
+        old_password: &str,
+        new_password: &str,
+    ) -> Result<()> {
+        if new_password.is_empty() {
+            return Err(ExchangeError::UserError(
+                "New password must not be empty".to_string(),
+            ));
+        }
+
+        let user_arc = self
+            .users
+            .get(user_id)
+            .ok_or_else(|| ExchangeError::UserError(format!("User not found: {}", user_id)))?;
+
+        let mut user = user_arc.write();
+        if !user.is_active() {
+            return Err(ExchangeError::AuthError(
+                "User is frozen or deleted".to_string(),
+            ));
+        }
+        if !user.verify_password(old_password) {
+            return Err(ExchangeError::AuthError("Invalid old password".to_string()));
+        }
+        user.update_password(new_password)
+            .map_err(|e| ExchangeError::InternalError(format!("Password hashing failed: {}", e)))?;
+
+        if let Some(ref storage) = self.storage {
+            use crate::storage::wal::record::WalRecord;
+            let record = WalRecord::UserPasswordUpdate {
+                user_id: WalRecord::to_fixed_array_40(user_id),
+                password_hash: WalRecord::to_fixed_array_64(&user.password_hash),
+                timestamp: user.updated_at,
+            };
+            if let Err(e) = storage.write(record) {
+                log::warn!("Failed to persist password update to WAL: {}", e);
+            }
+        }
+
+        log::info!("Password changed for user {}", user_id);
+        Ok(())
+    }
+
     /// 获取用户
     pub fn get_user(&self, user_id: &str) -> Result<User> {
         let user_arc = self
@@ -323,6 +370,60 @@ impl UserManager {
         Ok(())
     }
 
+    /// 注销用户并匿名化个人信息 @yutiansut @quantaxis
+    ///
+    /// 个人信息替换为不可逆摘要并写入匿名化 WAL 记录（恢复时覆盖历史注册记录），
+    /// user_id 与账户绑定保留，交易数据不受影响
+    pub fn erase_user(&self, user_id: &str) -> Result<UserErasureReport> {
+        let user_arc = self
+            .users
+            .get(user_id)
+            .map(|u| u.value().clone())
+            .ok_or_else(|| ExchangeError::UserError(format!("User not found: {}", user_id)))?;
+
+        let mut user = user_arc.write();
+        if user.status == UserStatus::Deleted {
+            return Err(ExchangeError::UserError(format!(
+                "User already deleted: {}",
+                user_id
+            )));
+        }
+
+        let identity = AnonymizedIdentity::from_user(&user);
+
+        // 原始用户名/手机号/邮箱释放，可被新用户重新注册
+        self.username_index.remove(&user.username);
+        if let Some(ref phone) = user.phone {
+            self.phone_index.remove(phone);
+        }
+        if let Some(ref email) = user.email {
+            self.email_index.remove(email);
+        }
+        identity.apply(&mut user);
+        self.username_index
+            .insert(identity.username.clone(), user_id.to_string());
+
+        let mut wal_persisted = false;
+        if let Some(ref storage) = self.storage {
+            match storage.write(identity.to_wal_record()) {
+                Ok(_) => wal_persisted = true,
+                Err(e) => log::error!("Failed to persist user anonymization to WAL: {}", e),
+            }
+        }
+
+        log::warn!("User erased: {} -> {}", user_id, identity.username);
+
+        Ok(UserErasureReport {
+            user_id: user_id.to_string(),
+            anonymized_username: identity.username.clone(),
+            erased_fields: identity.erased_fields(),
+            retained_account_ids: user.account_ids.clone(),
+            wal_persisted,
+            olap_rewrite: None,
+            processed_at: identity.anonymized_at,
+        })
+    }
+
     /// 解冻用户
     pub fn unfreeze_user(&self, user_id: &str) -> Result<()> {
         let user_arc = self
@@ -330,7 +431,14 @@ impl UserManager {
             .get(user_id)
             .ok_or_else(|| ExchangeError::UserError(format!("User not found: {}", user_id)))?;
 
-        user_arc.write().unfreeze();
+        let mut user = user_arc.write();
+        if user.status == UserStatus::Deleted {
+            return Err(ExchangeError::UserError(format!(
+                "User already deleted: {}",
+                user_id
+            )));
+        }
+        user.unfreeze();
 
         log::info!("User unfrozen: {}", user_id);
 
@@ -909,6 +1017,43 @@ mod tests {
         assert_eq!(resp.message, "Invalid password");
     }
 
+    #[test]
+    fn test_change_password() {
+        let mgr = UserManager::new();
+        let user = mgr
+            .register(UserRegisterRequest {
+                username: "changer".to_string(),
+                password: "old_password".to_string(),
+                phone: None,
+                email: None,
+                real_name: None,
+                id_card: None,
+            })
+            .unwrap();
+        let login = |password: &str| {
+            mgr.login(UserLoginRequest {
+                username: "changer".to_string(),
+                password: password.to_string(),
+            })
+            .unwrap()
+            .success
+        };
+
+        // 原密码错误、新密码为空均拒绝
+        assert!(mgr
+            .change_password(&user.user_id, "wrong", "new_password")
+            .is_err());
+        assert!(mgr
+            .change_password(&user.user_id, "old_password", "")
+            .is_err());
+        assert!(login("old_password"));
+
+        mgr.change_password(&user.user_id, "old_password", "new_password")
+            .unwrap();
+        assert!(!login("old_password"));
+        assert!(login("new_password"));
+    }
+
     #[test]
     fn test_login_nonexistent_user() {
         let mgr = UserManager::new();
@@ -2033,4 +2178,52 @@ mod tests {
             all_permission_count
         );
     }
+
+    // ==================== 用户注销匿名化 @yutiansut @quantaxis ====================
+
+    /// 测试注销：个人信息被替换、无法登录、原用户名/手机号可重新注册，账户绑定保留
+    #[test]
+    fn test_erase_user() {
+        let mgr = UserManager::new();
+        let user = mgr
+            .register(UserRegisterRequest {
+                username: "erase_me".to_string(),
+                password: "password".to_string(),
+                phone: Some("13900139000".to_string()),
+                email: Some("erase@example.com".to_string()),
+                real_name: Some("李四".to_string()),
+                id_card: Some("110101199001011234".to_string()),
+            })
+            .unwrap();
+        mgr.bind_account(&user.user_id, "ACC_ERASE".to_string())
+            .unwrap();
+
+        let report = mgr.erase_user(&user.user_id).unwrap();
+        assert_eq!(report.retained_account_ids, vec!["ACC_ERASE".to_string()]);
+        assert_eq!(report.erased_fields.len(), 6);
+        assert!(!report.wal_persisted);
+
+        let erased = mgr.get_user(&user.user_id).unwrap();
+        assert_eq!(erased.status, UserStatus::Deleted);
+        assert_eq!(erased.username, report.anonymized_username);
+        assert_ne!(erased.real_name.as_deref(), Some("李四"));
+        assert_ne!(erased.id_card.as_deref(), Some("110101199001011234"));
+        assert_eq!(erased.account_ids, vec!["ACC_ERASE".to_string()]);
+        assert!(mgr.get_user_by_username("erase_me").is_err());
+
+        // 重复注销、解冻均被拒绝
+        assert!(mgr.erase_user(&user.user_id).is_err());
+        assert!(mgr.unfreeze_user(&user.user_id).is_err());
+
+        // 原用户名、手机号、邮箱已释放
+        mgr.register(UserRegisterRequest {
+            username: "erase_me".to_string(),
+            password: "password".to_string(),
+            phone: Some("13900139000".to_string()),
+            email: Some("erase@example.com".to_string()),
+            real_name: None,
+            id_card: None,
+        })
+        .unwrap();
+    }
 }
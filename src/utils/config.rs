@@ -27,6 +27,116 @@ pub struct PerformanceConfig {
     pub memtable: MemTableConfig,
     #[serde(default)]
     pub iceoryx: IceoryxConfig,
+    #[serde(default)]
+    pub orderbook_limits: OrderBookLimitsConfig,
+    #[serde(default)]
+    pub order_rate_limit: OrderRateLimitSettings,
+    #[serde(default)]
+    pub risk_history: RiskHistorySettings,
+    /// 追踪头部采样（运行时可通过管理端热更新）
+    #[serde(default)]
+    pub tracing_sampling: crate::observability::sampling::SamplingConfig,
+    /// 推送端到端延迟采样（运行时可通过管理端热更新）
+    #[serde(default)]
+    pub push_latency: crate::observability::push_latency::PushLatencyConfig,
+    #[serde(default)]
+    pub risk_reserve: RiskReserveSettings,
+    /// 闲置资金计息（日终结算入账）
+    #[serde(default)]
+    pub interest: crate::exchange::interest::InterestConfig,
+    /// 日终结算任务（分片并行、断点续跑、结算期间交易限制）
+    #[serde(default)]
+    pub settlement: crate::exchange::settlement_task::SettlementTaskConfig,
+    /// 资金定点核算（精度与浮点双轨验证）
+    #[serde(default)]
+    pub fixed_point: crate::exchange::fixed_point::FixedPointConfig,
+    /// 强平预警阶梯
+    #[serde(default)]
+    pub margin_call: crate::risk::margin_call::MarginCallConfig,
+    /// 自动追保入金
+    #[serde(default)]
+    pub auto_margin: crate::risk::auto_margin::AutoMarginConfig,
+    /// 组合保证金
+    #[serde(default)]
+    pub portfolio_margin: crate::risk::portfolio_margin::PortfolioMarginConfig,
+    /// 用户消息中心
+    #[serde(default)]
+    pub notification_store: crate::notification::store::NotificationStoreConfig,
+    /// 价格笼子
+    #[serde(default)]
+    pub price_band: PriceBandSettings,
+    /// 最小变动价位校验
+    #[serde(default)]
+    pub price_tick: PriceTickSettings,
+    /// 大宗交易
+    #[serde(default)]
+    pub block_trade: crate::exchange::block_trade::BlockTradeConfig,
+    /// 因子值落盘
+    #[serde(default)]
+    pub factor_store: crate::storage::conversion::FactorStoreConfig,
+    /// OLAP 月份分区与冷数据再压缩
+    #[serde(default)]
+    pub olap_partition: crate::storage::conversion::OlapPartitionConfig,
+    /// 存储数据保留策略（按类别与年龄清理过期文件）
+    #[serde(default)]
+    pub retention: crate::storage::conversion::RetentionConfig,
+    /// 账户操作租约（多实例共享存储）
+    #[serde(default)]
+    pub account_lease: crate::exchange::account_lease::AccountLeaseConfig,
+    /// 确定性运行模式（仅回放/测试）
+    #[serde(default)]
+    pub deterministic: crate::exchange::deterministic::DeterministicConfig,
+    /// 网关过载保护（运行时可通过管理端热更新）
+    #[serde(default)]
+    pub overload: crate::service::overload::OverloadConfig,
+    /// 重查询/导出异步任务（worker 池、用户配额、签名下载链接）
+    #[serde(default)]
+    pub async_task: crate::service::async_task::AsyncTaskConfig,
+    /// 极端行情熔断
+    #[serde(default)]
+    pub circuit_breaker: crate::exchange::circuit_breaker::CircuitBreakerConfig,
+    /// 交易所时区（交易日、自然日边界、时间显示）
+    #[serde(default)]
+    pub time: crate::utils::time_service::TimeConfig,
+    /// 测试环境时间控制（虚拟时间快进）
+    #[serde(default)]
+    pub time_control: crate::exchange::time_control::TimeControlConfig,
+    /// 单用户单合约单日成交量限额
+    #[serde(default)]
+    pub trade_volume_limit: crate::risk::trade_volume_limit::TradeVolumeLimitConfig,
+    /// 重复订单检测（短时间内同参数/同幂等键订单拒绝或告警）
+    #[serde(default)]
+    pub duplicate_order: crate::risk::duplicate_order::DuplicateOrderConfig,
+    /// 持仓批次明细（默认平仓方式、QIFI 导出开仓明细）
+    #[serde(default)]
+    pub position_lots: crate::exchange::position_lots::PositionLotsConfig,
+    /// 持仓集中度（单账户占合约全市场总持仓比例）
+    #[serde(default)]
+    pub position_concentration: crate::risk::position_concentration::PositionConcentrationConfig,
+    /// 做市商义务考核
+    #[serde(default)]
+    pub market_maker: crate::risk::market_maker_monitor::MarketMakerMonitorConfig,
+    /// 功能灰度开关（运行时可通过管理端热更新）
+    #[serde(default)]
+    pub feature_gate: crate::exchange::feature_gate::FeatureGateConfig,
+    /// 在途订单看门狗（撮合回应丢失检测与对账）
+    #[serde(default)]
+    pub order_watchdog: crate::exchange::order_watchdog::OrderWatchdogConfig,
+    /// 成交事件总线（成交记录/行情消费者队列）
+    #[serde(default)]
+    pub trade_bus: crate::exchange::trade_bus::TradeEventBusConfig,
+    /// 行情异常值检测（零/负/离谱价格过滤或告警）
+    #[serde(default)]
+    pub market_anomaly: crate::market::anomaly::MarketAnomalyConfig,
+    /// 撮合引擎分片部署
+    #[serde(default)]
+    pub sharded_matching: crate::matching::sharded::ShardedMatchingConfig,
+    /// 影子撮合（新版撮合逻辑旁路验证）
+    #[serde(default)]
+    pub shadow_mode: crate::exchange::shadow_mode::ShadowModeConfig,
+    /// 集合竞价指示价推送
+    #[serde(default)]
+    pub auction_indicator: crate::market::auction_indicator::AuctionIndicatorConfig,
 }
 
 
@@ -76,6 +186,18 @@ pub struct WebSocketPerfConfig {
     pub batch_timeout_ms: u64,
     #[serde(default = "default_queue_threshold")]
     pub queue_threshold: usize,
+    /// 多终端登录策略：allow_multiple / kick_previous
+    #[serde(default = "default_login_policy")]
+    pub login_policy: String,
+    /// 强制单点登录（kick_previous）的用户，不受全局策略影响
+    #[serde(default)]
+    pub single_login_users: Vec<String>,
+    /// 推送压缩（客户端握手协商 zstd）
+    #[serde(default)]
+    pub compression: crate::service::websocket::compression::WsCompressionConfig,
+    /// 订阅配额（按用户等级限制订阅合约数与推送速率）
+    #[serde(default)]
+    pub subscription_quota: crate::service::websocket::subscription_quota::SubscriptionQuotaConfig,
 }
 
 impl Default for WebSocketPerfConfig {
@@ -84,6 +206,10 @@ impl Default for WebSocketPerfConfig {
             batch_size: 100,
             batch_timeout_ms: 10,
             queue_threshold: 500,
+            login_policy: default_login_policy(),
+            single_login_users: Vec::new(),
+            compression: Default::default(),
+            subscription_quota: Default::default(),
         }
     }
 }
@@ -98,6 +224,12 @@ pub struct PriorityQueueConfig {
     pub critical_amount_threshold: f64,
     #[serde(default)]
     pub vip_users: Vec<String>,
+    /// 防饿死：高优先级连续出队上限（0 表示不限制）
+    #[serde(default = "default_starvation_limit")]
+    pub starvation_limit: usize,
+    /// 防饿死：最长排队时间（毫秒，0 表示不老化）
+    #[serde(default = "default_priority_max_wait_ms")]
+    pub max_wait_ms: u64,
 }
 
 impl Default for PriorityQueueConfig {
@@ -107,6 +239,8 @@ impl Default for PriorityQueueConfig {
             low_queue_limit: 100,
             critical_amount_threshold: 1_000_000.0,
             vip_users: Vec::new(),
+            starvation_limit: default_starvation_limit(),
+            max_wait_ms: default_priority_max_wait_ms(),
         }
     }
 }
@@ -163,6 +297,193 @@ impl Default for IceoryxConfig {
     }
 }
 
+/// 订单簿挂单数限制配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderBookLimitsConfig {
+    /// 是否启用挂单数限制
+    #[serde(default = "default_false")]
+    pub enabled: bool,
+
+    /// 单合约默认最大挂单数（0 表示不限制）
+    #[serde(default)]
+    pub default_max_orders: usize,
+
+    /// 全局挂单总数上限（0 表示不限制）
+    #[serde(default)]
+    pub global_max_orders: usize,
+
+    /// 单个挂单估算内存（字节）
+    #[serde(default = "default_order_size_bytes")]
+    pub order_size_bytes: usize,
+
+    /// 告警阈值（使用率，0.0-1.0）
+    #[serde(default = "default_alert_ratio")]
+    pub alert_ratio: f64,
+
+    /// 按合约覆盖的最大挂单数
+    #[serde(default)]
+    pub instruments: std::collections::HashMap<String, usize>,
+}
+
+impl Default for OrderBookLimitsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            default_max_orders: 0,
+            global_max_orders: 0,
+            order_size_bytes: default_order_size_bytes(),
+            alert_ratio: default_alert_ratio(),
+            instruments: std::collections::HashMap::new(),
+        }
+    }
+}
+
+/// 账户下单/撤单/改单频率限制配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderRateLimitSettings {
+    /// 是否启用频率限制
+    #[serde(default = "default_false")]
+    pub enabled: bool,
+
+    /// 个人账户规则
+    #[serde(default = "default_individual_rate_limit")]
+    pub individual: AccountRateLimitSettings,
+
+    /// 机构账户规则
+    #[serde(default = "default_institutional_rate_limit")]
+    pub institutional: AccountRateLimitSettings,
+
+    /// 做市商账户规则
+    #[serde(default = "default_market_maker_rate_limit")]
+    pub market_maker: AccountRateLimitSettings,
+
+    /// 豁免账户
+    #[serde(default)]
+    pub exempt_accounts: Vec<String>,
+
+    /// 按账户覆盖的规则
+    #[serde(default)]
+    pub accounts: std::collections::HashMap<String, AccountRateLimitSettings>,
+
+    /// 报撤比管控（做市商豁免）
+    #[serde(default)]
+    pub cancel_ratio: crate::risk::CancelRatioConfig,
+}
+
+impl Default for OrderRateLimitSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            individual: default_individual_rate_limit(),
+            institutional: default_institutional_rate_limit(),
+            market_maker: default_market_maker_rate_limit(),
+            exempt_accounts: Vec::new(),
+            accounts: std::collections::HashMap::new(),
+            cancel_ratio: crate::risk::CancelRatioConfig::default(),
+        }
+    }
+}
+
+/// 风险历史采样配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RiskHistorySettings {
+    /// 是否启用风险快照采样
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// 采样间隔（毫秒）
+    #[serde(default = "default_risk_sample_interval_ms")]
+    pub sample_interval_ms: i64,
+
+    /// 保留天数
+    #[serde(default = "default_risk_retention_days")]
+    pub retention_days: u32,
+
+    /// 单次查询最多返回点数
+    #[serde(default = "default_risk_max_query_points")]
+    pub max_query_points: usize,
+}
+
+impl Default for RiskHistorySettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            sample_interval_ms: default_risk_sample_interval_ms(),
+            retention_days: default_risk_retention_days(),
+            max_query_points: default_risk_max_query_points(),
+        }
+    }
+}
+
+/// 风险准备金配置（结算穿仓垫付）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RiskReserveSettings {
+    /// 风险准备金账户ID（为空表示未配置，穿仓金额全部记为未覆盖）
+    #[serde(default)]
+    pub account_id: String,
+
+    /// 穿仓告警接收人（管理员用户ID）
+    #[serde(default = "default_deficit_alert_recipients")]
+    pub alert_recipients: Vec<String>,
+}
+
+impl Default for RiskReserveSettings {
+    fn default() -> Self {
+        Self {
+            account_id: String::new(),
+            alert_recipients: default_deficit_alert_recipients(),
+        }
+    }
+}
+
+/// 价格笼子配置（限价单偏离实时参考价过远拒绝）
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct PriceBandSettings {
+    /// 是否启用价格笼子
+    #[serde(default = "default_false")]
+    pub enabled: bool,
+
+    /// 默认笼子（未单独配置的合约使用，为空表示只校验已配置合约）
+    #[serde(default)]
+    pub default: Option<crate::risk::PriceBandConfig>,
+
+    /// 按合约配置的笼子
+    #[serde(default)]
+    pub instruments: std::collections::HashMap<String, crate::risk::PriceBandConfig>,
+}
+
+/// 最小变动价位校验（价格不是 tick 整数倍时拒单或规整）
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct PriceTickSettings {
+    /// off / reject / round
+    #[serde(default)]
+    pub mode: crate::risk::PriceTickMode,
+}
+
+/// 单类账户的频率限制规则
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountRateLimitSettings {
+    /// 统计窗口（毫秒）
+    #[serde(default = "default_rate_limit_window_ms")]
+    pub window_ms: u64,
+
+    /// 窗口内最大下单数（0 表示不限制）
+    #[serde(default)]
+    pub max_orders: u32,
+
+    /// 窗口内最大撤单数（0 表示不限制）
+    #[serde(default)]
+    pub max_cancels: u32,
+
+    /// 窗口内最大改单数（0 表示不限制，未配置时与撤单相同）
+    #[serde(default)]
+    pub max_amends: Option<u32>,
+
+    /// 是否豁免
+    #[serde(default = "default_false")]
+    pub exempt: bool,
+}
+
 // 默认值函数
 fn default_buffer_size() -> usize {
     1000
@@ -182,18 +503,60 @@ fn default_ws_batch_size() -> usize {
 fn default_queue_threshold() -> usize {
     500
 }
+fn default_login_policy() -> String {
+    "allow_multiple".to_string()
+}
 fn default_true() -> bool {
     true
 }
+fn default_rate_limit_window_ms() -> u64 {
+    1000
+}
+fn rate_limit(max_orders: u32, max_cancels: u32) -> AccountRateLimitSettings {
+    AccountRateLimitSettings {
+        window_ms: default_rate_limit_window_ms(),
+        max_orders,
+        max_cancels,
+        max_amends: None,
+        exempt: false,
+    }
+}
+fn default_individual_rate_limit() -> AccountRateLimitSettings {
+    rate_limit(20, 20)
+}
+fn default_institutional_rate_limit() -> AccountRateLimitSettings {
+    rate_limit(100, 100)
+}
+fn default_market_maker_rate_limit() -> AccountRateLimitSettings {
+    rate_limit(500, 500)
+}
 fn default_false() -> bool {
     false
 }
+fn default_risk_sample_interval_ms() -> i64 {
+    60_000
+}
+fn default_risk_retention_days() -> u32 {
+    30
+}
+fn default_risk_max_query_points() -> usize {
+    2000
+}
+fn default_deficit_alert_recipients() -> Vec<String> {
+    vec!["admin".to_string()]
+}
 fn default_low_queue_limit() -> usize {
     100
 }
 fn default_critical_threshold() -> f64 {
     1_000_000.0
 }
+fn default_starvation_limit() -> usize {
+    8
+}
+fn default_priority_max_wait_ms() -> u64 {
+    100
+}
 fn default_memtable_size() -> usize {
     64
 }
@@ -212,6 +575,12 @@ fn default_queue_capacity() -> usize {
 fn default_max_message_size() -> usize {
     4096
 }
+fn default_order_size_bytes() -> usize {
+    512
+}
+fn default_alert_ratio() -> f64 {
+    0.9
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerConfig {
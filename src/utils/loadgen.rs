@@ -0,0 +1,889 @@
+//! WebSocket 模拟客户端负载生成（容量压测）
+//!
+//! @yutiansut @quantaxis
+//!
+//! `qaexchange-loadgen` 的核心逻辑，走 `/ws` JSON 协议（`type` 字段区分消息）：
+//! - `market_data` 模式：每个连接只订阅行情，压推送链路
+//! - `mixed` 模式：订阅行情的同时按固定速率下单，价格在基准价上下按最小变动价位均匀分布，方向随机
+//!
+//! 统计口径：
+//! - 下单确认延迟：发送 submit_order → 收到 order_response（同一连接按发送顺序返回，FIFO 匹配）
+//! - 下单回报延迟：发送 submit_order → 收到该订单第一条 order_status / trade 推送
+//! - 推送延迟：客户端接收时刻 − 消息 `origin_ts`，依赖压测机与服务端时钟一致，负值单独计数；
+//!   带 `msg_id` 的采样消息同时回发 latency_report，服务端侧分布可对照
+//! - 错误率：错误数 / 请求数。错误含连接失败、发送失败、认证/订阅失败、服务端 error 消息、拒单、
+//!   连接异常断开；请求含建连、认证、订阅、下单
+//!
+//! 延迟样本按连接做蓄水池抽样（`max_samples_per_connection`），次数、均值、最值为精确值。
+
+use futures::{SinkExt, StreamExt};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+
+use crate::service::websocket::messages::ClientMessage;
+use crate::utils::timestamp::{nanos_to_micros, now_nanos};
+use crate::ExchangeError;
+
+fn default_url() -> String {
+    "ws://127.0.0.1:8095/ws".to_string()
+}
+
+fn default_connections() -> usize {
+    10
+}
+
+fn default_instruments_per_connection() -> usize {
+    1
+}
+
+fn default_channels() -> Vec<String> {
+    vec!["tick".to_string(), "orderbook".to_string()]
+}
+
+fn default_order_rate() -> f64 {
+    10.0
+}
+
+fn default_order_volume() -> f64 {
+    1.0
+}
+
+fn default_duration_secs() -> u64 {
+    30
+}
+
+fn default_connect_timeout_ms() -> u64 {
+    5_000
+}
+
+fn default_drain_ms() -> u64 {
+    2_000
+}
+
+fn default_max_samples() -> usize {
+    100_000
+}
+
+/// 压测模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoadGenMode {
+    /// 只订阅行情
+    #[default]
+    MarketData,
+    /// 订阅行情 + 下单
+    Mixed,
+}
+
+/// 压测账户（token 为 `/api/auth/login` 签发的 JWT）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoadGenAccount {
+    pub user_id: String,
+    pub token: String,
+    /// 交易账户，未配置时由服务端取用户默认账户
+    #[serde(default)]
+    pub account_id: Option<String>,
+}
+
+/// 下单价格分布：`base_price ± [0, spread_ticks] × price_tick` 均匀分布
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceDistribution {
+    pub base_price: f64,
+    pub price_tick: f64,
+    pub spread_ticks: u32,
+}
+
+impl Default for PriceDistribution {
+    fn default() -> Self {
+        Self {
+            base_price: 3800.0,
+            price_tick: 0.2,
+            spread_ticks: 5,
+        }
+    }
+}
+
+impl PriceDistribution {
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> f64 {
+        let spread = self.spread_ticks as i64;
+        let ticks = rng.gen_range(-spread..=spread);
+        self.base_price + ticks as f64 * self.price_tick
+    }
+}
+
+/// 压测配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoadGenConfig {
+    /// WebSocket 地址
+    #[serde(default = "default_url")]
+    pub url: String,
+    #[serde(default)]
+    pub mode: LoadGenMode,
+    /// 并发连接数
+    #[serde(default = "default_connections")]
+    pub connections: usize,
+    /// 合约池，连接 i 订阅从第 i 个开始的 `instruments_per_connection` 个合约
+    #[serde(default)]
+    pub instruments: Vec<String>,
+    #[serde(default = "default_instruments_per_connection")]
+    pub instruments_per_connection: usize,
+    /// 订阅频道
+    #[serde(default = "default_channels")]
+    pub channels: Vec<String>,
+    /// 每连接下单速率（笔/秒，mixed 模式）
+    #[serde(default = "default_order_rate")]
+    pub order_rate: f64,
+    /// 每笔下单手数
+    #[serde(default = "default_order_volume")]
+    pub order_volume: f64,
+    #[serde(default)]
+    pub price: PriceDistribution,
+    /// 运行时长（秒）
+    #[serde(default = "default_duration_secs")]
+    pub duration_secs: u64,
+    /// 建连超时（毫秒）
+    #[serde(default = "default_connect_timeout_ms")]
+    pub connect_timeout_ms: u64,
+    /// 停止下单后继续接收回报的时间（毫秒）
+    #[serde(default = "default_drain_ms")]
+    pub drain_ms: u64,
+    /// 每连接每类延迟最多保留的样本数
+    #[serde(default = "default_max_samples")]
+    pub max_samples_per_connection: usize,
+    /// 压测账户，按连接轮流分配（mixed 模式必填）
+    #[serde(default)]
+    pub accounts: Vec<LoadGenAccount>,
+}
+
+impl Default for LoadGenConfig {
+    fn default() -> Self {
+        Self {
+            url: default_url(),
+            mode: LoadGenMode::default(),
+            connections: default_connections(),
+            instruments: Vec::new(),
+            instruments_per_connection: default_instruments_per_connection(),
+            channels: default_channels(),
+            order_rate: default_order_rate(),
+            order_volume: default_order_volume(),
+            price: PriceDistribution::default(),
+            duration_secs: default_duration_secs(),
+            connect_timeout_ms: default_connect_timeout_ms(),
+            drain_ms: default_drain_ms(),
+            max_samples_per_connection: default_max_samples(),
+            accounts: Vec::new(),
+        }
+    }
+}
+
+impl LoadGenConfig {
+    /// 从 TOML 文件加载
+    pub fn from_file(path: &str) -> Result<Self, ExchangeError> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            ExchangeError::InvalidParameter(format!("Failed to read {}: {}", path, e))
+        })?;
+        toml::from_str(&content).map_err(|e| {
+            ExchangeError::InvalidParameter(format!("Failed to parse {}: {}", path, e))
+        })
+    }
+
+    pub fn validate(&self) -> Result<(), ExchangeError> {
+        if self.connections == 0 {
+            return Err(ExchangeError::InvalidParameter(
+                "Loadgen connections must be greater than 0".to_string(),
+            ));
+        }
+        if self.duration_secs == 0 {
+            return Err(ExchangeError::InvalidParameter(
+                "Loadgen duration_secs must be greater than 0".to_string(),
+            ));
+        }
+        if self.instruments.is_empty() || self.instruments_per_connection == 0 {
+            return Err(ExchangeError::InvalidParameter(
+                "Loadgen requires at least one instrument per connection".to_string(),
+            ));
+        }
+        if self.mode == LoadGenMode::Mixed {
+            if self.accounts.is_empty() {
+                return Err(ExchangeError::InvalidParameter(
+                    "Loadgen mixed mode requires accounts".to_string(),
+                ));
+            }
+            if self.order_rate <= 0.0 || self.order_volume <= 0.0 {
+                return Err(ExchangeError::InvalidParameter(
+                    "Loadgen order_rate and order_volume must be greater than 0".to_string(),
+                ));
+            }
+            if self.price.price_tick <= 0.0 {
+                return Err(ExchangeError::InvalidParameter(
+                    "Loadgen price_tick must be greater than 0".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// 连接 `index` 订阅的合约
+    pub fn instruments_for(&self, index: usize) -> Vec<String> {
+        let n = self.instruments.len();
+        (0..self.instruments_per_connection.min(n))
+            .map(|k| self.instruments[(index + k) % n].clone())
+            .collect()
+    }
+
+    /// 连接 `index` 使用的账户
+    pub fn account_for(&self, index: usize) -> Option<&LoadGenAccount> {
+        if self.accounts.is_empty() {
+            None
+        } else {
+            Some(&self.accounts[index % self.accounts.len()])
+        }
+    }
+}
+
+/// 延迟样本（微秒）：精确计数/均值/最值 + 蓄水池抽样的分位数
+#[derive(Debug, Clone, Default)]
+pub struct LatencyRecorder {
+    count: u64,
+    sum: u64,
+    min: u64,
+    max: u64,
+    samples: Vec<u64>,
+    cap: usize,
+}
+
+impl LatencyRecorder {
+    pub fn new(cap: usize) -> Self {
+        Self {
+            cap: cap.max(1),
+            ..Default::default()
+        }
+    }
+
+    pub fn record(&mut self, latency_us: u64) {
+        self.count += 1;
+        self.sum = self.sum.saturating_add(latency_us);
+        self.min = if self.count == 1 {
+            latency_us
+        } else {
+            self.min.min(latency_us)
+        };
+        self.max = self.max.max(latency_us);
+        if self.samples.len() < self.cap {
+            self.samples.push(latency_us);
+        } else {
+            let slot = rand::thread_rng().gen_range(0..self.count);
+            if (slot as usize) < self.cap {
+                self.samples[slot as usize] = latency_us;
+            }
+        }
+    }
+
+    pub fn merge(&mut self, other: LatencyRecorder) {
+        if other.count == 0 {
+            return;
+        }
+        self.min = if self.count == 0 {
+            other.min
+        } else {
+            self.min.min(other.min)
+        };
+        self.count += other.count;
+        self.sum = self.sum.saturating_add(other.sum);
+        self.max = self.max.max(other.max);
+        self.samples.extend(other.samples);
+    }
+
+    pub fn summary(&self) -> LatencySummary {
+        let mut sorted = self.samples.clone();
+        sorted.sort_unstable();
+        let percentile = |p: f64| -> u64 {
+            if sorted.is_empty() {
+                return 0;
+            }
+            let idx = ((sorted.len() as f64 * p) as usize).min(sorted.len() - 1);
+            sorted[idx]
+        };
+        LatencySummary {
+            count: self.count,
+            min: self.min,
+            mean: if self.count == 0 {
+                0.0
+            } else {
+                self.sum as f64 / self.count as f64
+            },
+            p50: percentile(0.50),
+            p90: percentile(0.90),
+            p99: percentile(0.99),
+            p999: percentile(0.999),
+            max: self.max,
+        }
+    }
+}
+
+/// 延迟分布（微秒）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LatencySummary {
+    pub count: u64,
+    pub min: u64,
+    pub mean: f64,
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+    pub p999: u64,
+    pub max: u64,
+}
+
+/// 单连接统计
+#[derive(Debug, Clone, Default)]
+struct ConnectionStats {
+    connected: bool,
+    requests: u64,
+    errors: u64,
+    orders_sent: u64,
+    orders_acked: u64,
+    orders_rejected: u64,
+    trades: u64,
+    market_messages: u64,
+    negative_push_latency: u64,
+    latency_reports_sent: u64,
+    order_ack: LatencyRecorder,
+    order_report: LatencyRecorder,
+    push: LatencyRecorder,
+}
+
+impl ConnectionStats {
+    fn new(cap: usize) -> Self {
+        Self {
+            order_ack: LatencyRecorder::new(cap),
+            order_report: LatencyRecorder::new(cap),
+            push: LatencyRecorder::new(cap),
+            ..Default::default()
+        }
+    }
+}
+
+/// 压测报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoadGenReport {
+    pub mode: LoadGenMode,
+    pub url: String,
+    /// 开始时间（UTC 毫秒）
+    pub started_at: i64,
+    /// 实际运行时长（秒，含回报接收期）
+    pub elapsed_secs: f64,
+    pub connections: usize,
+    pub connections_established: usize,
+    pub instruments: Vec<String>,
+    pub orders_sent: u64,
+    pub orders_acked: u64,
+    pub orders_rejected: u64,
+    /// 下单确认吞吐（笔/秒）
+    pub orders_per_sec: f64,
+    pub trades_received: u64,
+    /// 行情推送消息数（批量推送按条计）
+    pub market_messages: u64,
+    pub market_messages_per_sec: f64,
+    pub requests: u64,
+    pub errors: u64,
+    pub error_rate: f64,
+    /// 推送延迟为负（时钟不一致）的消息数，不计入分布
+    pub negative_push_latency: u64,
+    pub latency_reports_sent: u64,
+    /// 下单 → order_response（微秒）
+    pub order_ack_latency_us: LatencySummary,
+    /// 下单 → 首条订单回报（微秒）
+    pub order_report_latency_us: LatencySummary,
+    /// origin_ts → 客户端接收（微秒）
+    pub push_latency_us: LatencySummary,
+}
+
+/// 运行压测，所有连接结束后汇总报告
+pub async fn run(config: LoadGenConfig) -> Result<LoadGenReport, ExchangeError> {
+    config.validate()?;
+    let config = Arc::new(config);
+    let started_at = crate::utils::timestamp::now_millis();
+    let start = Instant::now();
+    let send_until = start + Duration::from_secs(config.duration_secs);
+
+    let handles: Vec<_> = (0..config.connections)
+        .map(|index| tokio::spawn(run_connection(index, config.clone(), send_until)))
+        .collect();
+
+    let cap = config.max_samples_per_connection;
+    let mut total = ConnectionStats::new(cap);
+    let mut established = 0;
+    for handle in handles {
+        let stats = handle.await.map_err(|e| {
+            ExchangeError::InternalError(format!("Loadgen connection task failed: {}", e))
+        })?;
+        if stats.connected {
+            established += 1;
+        }
+        total.requests += stats.requests;
+        total.errors += stats.errors;
+        total.orders_sent += stats.orders_sent;
+        total.orders_acked += stats.orders_acked;
+        total.orders_rejected += stats.orders_rejected;
+        total.trades += stats.trades;
+        total.market_messages += stats.market_messages;
+        total.negative_push_latency += stats.negative_push_latency;
+        total.latency_reports_sent += stats.latency_reports_sent;
+        total.order_ack.merge(stats.order_ack);
+        total.order_report.merge(stats.order_report);
+        total.push.merge(stats.push);
+    }
+
+    let elapsed_secs = start.elapsed().as_secs_f64();
+    let active_secs = (config.duration_secs as f64)
+        .min(elapsed_secs)
+        .max(f64::EPSILON);
+    Ok(LoadGenReport {
+        mode: config.mode,
+        url: config.url.clone(),
+        started_at,
+        elapsed_secs,
+        connections: config.connections,
+        connections_established: established,
+        instruments: config.instruments.clone(),
+        orders_sent: total.orders_sent,
+        orders_acked: total.orders_acked,
+        orders_rejected: total.orders_rejected,
+        orders_per_sec: total.orders_acked as f64 / active_secs,
+        trades_received: total.trades,
+        market_messages: total.market_messages,
+        market_messages_per_sec: total.market_messages as f64 / active_secs,
+        requests: total.requests,
+        errors: total.errors,
+        error_rate: if total.requests == 0 {
+            0.0
+        } else {
+            total.errors as f64 / total.requests as f64
+        },
+        negative_push_latency: total.negative_push_latency,
+        latency_reports_sent: total.latency_reports_sent,
+        order_ack_latency_us: total.order_ack.summary(),
+        order_report_latency_us: total.order_report.summary(),
+        push_latency_us: total.push.summary(),
+    })
+}
+
+/// 单个模拟客户端：建连 → 认证 → 订阅 →（下单）→ 到期后接收剩余回报并关闭
+async fn run_connection(
+    index: usize,
+    config: Arc<LoadGenConfig>,
+    send_until: Instant,
+) -> ConnectionStats {
+    let mut stats = ConnectionStats::new(config.max_samples_per_connection);
+    stats.requests += 1;
+
+    let connect = tokio::time::timeout(
+        Duration::from_millis(config.connect_timeout_ms),
+        connect_async(config.url.as_str()),
+    )
+    .await;
+    let ws_stream = match connect {
+        Ok(Ok((stream, _))) => stream,
+        Ok(Err(e)) => {
+            log::warn!("Loadgen connection {} failed: {}", index, e);
+            stats.errors += 1;
+            return stats;
+        }
+        Err(_) => {
+            log::warn!("Loadgen connection {} timed out", index);
+            stats.errors += 1;
+            return stats;
+        }
+    };
+    stats.connected = true;
+    let (mut write, mut read) = ws_stream.split();
+
+    let instruments = config.instruments_for(index);
+    let account = config.account_for(index).cloned();
+    let mut outgoing = Vec::new();
+    if let Some(ref account) = account {
+        stats.requests += 1;
+        outgoing.push(ClientMessage::Auth {
+            user_id: account.user_id.clone(),
+            token: account.token.clone(),
+        });
+    }
+    outgoing.push(ClientMessage::Subscribe {
+        channels: config.channels.clone(),
+        instruments: instruments.clone(),
+    });
+    stats.requests += 1;
+    for msg in outgoing {
+        if send_json(&mut write, &msg).await.is_err() {
+            stats.errors += 1;
+            return stats;
+        }
+    }
+
+    let trading = config.mode == LoadGenMode::Mixed && account.is_some();
+    let order_period = if trading {
+        Duration::from_secs_f64(1.0 / config.order_rate)
+    } else {
+        Duration::from_secs(3600)
+    };
+    let mut order_timer = tokio::time::interval(order_period);
+    order_timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let receive_until = send_until + Duration::from_millis(config.drain_ms);
+
+    // 已发送未确认的下单时刻（FIFO）；已确认等待首条回报的订单；先于确认到达的回报
+    let mut pending_acks: VecDeque<Instant> = VecDeque::new();
+    let mut awaiting_report: HashMap<String, Instant> = HashMap::new();
+    let mut early_reports: HashMap<String, Instant> = HashMap::new();
+
+    loop {
+        let sending = trading && Instant::now() < send_until;
+        tokio::select! {
+            _ = tokio::time::sleep_until(receive_until) => break,
+            _ = order_timer.tick(), if sending => {
+                let req = {
+                    let mut rng = rand::thread_rng();
+                    ClientMessage::SubmitOrder {
+                        account_id: account.as_ref().and_then(|a| a.account_id.clone()),
+                        instrument_id: instruments[rng.gen_range(0..instruments.len())].clone(),
+                        direction: if rng.gen_bool(0.5) { "BUY" } else { "SELL" }.to_string(),
+                        offset: "OPEN".to_string(),
+                        volume: config.order_volume,
+                        price: config.price.sample(&mut rng),
+                        order_type: "LIMIT".to_string(),
+                    }
+                };
+                stats.requests += 1;
+                stats.orders_sent += 1;
+                pending_acks.push_back(Instant::now());
+                if send_json(&mut write, &req).await.is_err() {
+                    stats.errors += 1;
+                    break;
+                }
+            }
+            frame = read.next() => {
+                let text = match frame {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | None => {
+                        // 服务端在压测结束前断开视为错误
+                        if Instant::now() < send_until {
+                            stats.errors += 1;
+                        }
+                        break;
+                    }
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => {
+                        log::warn!("Loadgen connection {} read error: {}", index, e);
+                        stats.errors += 1;
+                        break;
+                    }
+                };
+                let received = Instant::now();
+                let recv_ns = now_nanos();
+                let value: Value = match serde_json::from_str(&text) {
+                    Ok(value) => value,
+                    Err(_) => {
+                        stats.errors += 1;
+                        continue;
+                    }
+                };
+                // 行情按批次以 JSON 数组推送，交易回报为单个对象
+                let items = match value {
+                    Value::Array(items) => {
+                        stats.market_messages += items.len() as u64;
+                        items
+                    }
+                    other => vec![other],
+                };
+                for item in items {
+                    let reports = handle_message(
+                        &item,
+                        received,
+                        recv_ns,
+                        &mut stats,
+                        &mut pending_acks,
+                        &mut awaiting_report,
+                        &mut early_reports,
+                    );
+                    for msg_id in reports {
+                        let report = ClientMessage::LatencyReport {
+                            msg_id,
+                            client_recv_ts: recv_ns,
+                        };
+                        if send_json(&mut write, &report).await.is_ok() {
+                            stats.latency_reports_sent += 1;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let _ = write.send(Message::Close(None)).await;
+    stats
+}
+
+/// 处理一条服务端消息，返回需要回发 latency_report 的 msg_id
+fn handle_message(
+    item: &Value,
+    received: Instant,
+    recv_ns: i64,
+    stats: &mut ConnectionStats,
+    pending_acks: &mut VecDeque<Instant>,
+    awaiting_report: &mut HashMap<String, Instant>,
+    early_reports: &mut HashMap<String, Instant>,
+) -> Option<u64> {
+    let msg_type = item.get("type").and_then(Value::as_str).unwrap_or_default();
+    let order_id = item
+        .get("order_id")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+
+    match msg_type {
+        "order_response" => {
+            let sent = pending_acks.pop_front();
+            if let Some(sent) = sent {
+                stats
+                    .order_ack
+                    .record(received.saturating_duration_since(sent).as_micros() as u64);
+            }
+            if item.get("success").and_then(Value::as_bool) == Some(true) {
+                stats.orders_acked += 1;
+                if let (Some(order_id), Some(sent)) = (order_id, sent) {
+                    match early_reports.remove(&order_id) {
+                        Some(at) => stats
+                            .order_report
+                            .record(at.saturating_duration_since(sent).as_micros() as u64),
+                        None => {
+                            awaiting_report.insert(order_id, sent);
+                        }
+                    }
+                }
+            } else {
+                stats.orders_rejected += 1;
+                stats.errors += 1;
+            }
+        }
+        "order_status" | "trade" => {
+            if msg_type == "trade" {
+                stats.trades += 1;
+            }
+            if let Some(order_id) = order_id {
+                match awaiting_report.remove(&order_id) {
+                    Some(sent) => stats
+                        .order_report
+                        .record(received.saturating_duration_since(sent).as_micros() as u64),
+                    None => {
+                        early_reports.entry(order_id).or_insert(received);
+                    }
+                }
+            }
+        }
+        "auth_response" | "subscribe_response" => {
+            if item.get("success").and_then(Value::as_bool) != Some(true) {
+                stats.errors += 1;
+            }
+        }
+        "error" => stats.errors += 1,
+        _ => {}
+    }
+
+    // 推送延迟：带 origin_ts 的推送消息（tick / K线 / 成交 / 委托 / 账户）
+    if let Some(origin_ts) = item.get("origin_ts").and_then(Value::as_i64) {
+        if origin_ts > 0 {
+            let latency_ns = recv_ns - origin_ts;
+            if latency_ns < 0 {
+                stats.negative_push_latency += 1;
+            } else {
+                stats.push.record(nanos_to_micros(latency_ns) as u64);
+            }
+        }
+    }
+
+    item.get("msg_id").and_then(Value::as_u64)
+}
+
+async fn send_json<S>(write: &mut S, msg: &ClientMessage) -> Result<(), ()>
+where
+    S: futures::Sink<Message> + Unpin,
+{
+    let json = serde_json::to_string(msg).map_err(|_| ())?;
+    write.send(Message::Text(json)).await.map_err(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_latency_recorder_summary() {
+        let mut a = LatencyRecorder::new(1_000);
+        for us in 1..=100 {
+            a.record(us);
+        }
+        let mut b = LatencyRecorder::new(1_000);
+        b.record(1_000);
+        a.merge(b);
+        a.merge(LatencyRecorder::new(10));
+
+        let summary = a.summary();
+        assert_eq!(summary.count, 101);
+        assert_eq!(summary.min, 1);
+        assert_eq!(summary.max, 1_000);
+        assert_eq!(summary.p50, 51);
+        assert_eq!(summary.p99, 100);
+        assert!((summary.mean - 6_050.0 / 101.0).abs() < 1e-9);
+
+        // 超过容量后抽样，计数与最值仍精确
+        let mut capped = LatencyRecorder::new(10);
+        for us in 0..1_000 {
+            capped.record(us);
+        }
+        assert_eq!(capped.samples.len(), 10);
+        assert_eq!(capped.summary().count, 1_000);
+        assert_eq!(capped.summary().max, 999);
+    }
+
+    #[test]
+    fn test_config_validation_and_assignment() {
+        let mut config = LoadGenConfig {
+            instruments: vec!["IF2501".into(), "IC2501".into(), "IH2501".into()],
+            instruments_per_connection: 2,
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+        assert_eq!(config.instruments_for(2), vec!["IH2501", "IF2501"]);
+
+        config.mode = LoadGenMode::Mixed;
+        assert!(config.validate().is_err());
+        config.accounts = vec![LoadGenAccount {
+            user_id: "u1".into(),
+            token: "t1".into(),
+            account_id: None,
+        }];
+        assert!(config.validate().is_ok());
+        assert_eq!(config.account_for(3).unwrap().user_id, "u1");
+
+        let parsed: LoadGenConfig = toml::from_str(
+            r#"
+            mode = "mixed"
+            connections = 2
+            instruments = ["IF2501"]
+            [price]
+            base_price = 100.0
+            price_tick = 0.5
+            spread_ticks = 2
+            "#,
+        )
+        .unwrap();
+        assert_eq!(parsed.mode, LoadGenMode::Mixed);
+        assert_eq!(parsed.duration_secs, 30);
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let price = parsed.price.sample(&mut rng);
+            assert!((99.0..=101.0).contains(&price));
+            assert_eq!((price * 2.0).fract(), 0.0);
+        }
+    }
+
+    /// 模拟服务端：订阅后推送一批 tick，下单回 order_response + order_status
+    async fn mock_server(listener: TcpListener) {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let Ok(ws) = tokio_tungstenite::accept_async(stream).await else {
+                    return;
+                };
+                let (mut write, mut read) = ws.split();
+                let mut next_id = 0u64;
+                while let Some(Ok(Message::Text(text))) = read.next().await {
+                    let msg: Value = serde_json::from_str(&text).unwrap();
+                    let replies = match msg["type"].as_str().unwrap_or_default() {
+                        "subscribe" => vec![serde_json::json!([
+                            {"type": "tick", "instrument_id": "IF2501", "price": 1.0,
+                             "volume": 1.0, "direction": "buy", "timestamp": 0,
+                             "origin_ts": now_nanos(), "msg_id": 7},
+                            {"type": "orderbook_update", "instrument_id": "IF2501",
+                             "side": "bid", "price": 1.0, "volume": 1.0, "timestamp": 0}
+                        ])],
+                        "submit_order" => {
+                            next_id += 1;
+                            let order_id = format!("O{}", next_id);
+                            vec![
+                                serde_json::json!({"type": "order_response", "success": true,
+                                    "order_id": order_id, "error_code": null, "error_message": null}),
+                                serde_json::json!({"type": "order_status", "order_id": order_id,
+                                    "origin_ts": now_nanos()}),
+                            ]
+                        }
+                        _ => vec![],
+                    };
+                    for reply in replies {
+                        if write.send(Message::Text(reply.to_string())).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+            });
+        }
+    }
+
+    #[tokio::test]
+    async fn test_loadgen_smoke_against_mock_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(mock_server(listener));
+
+        let report = run(LoadGenConfig {
+            url: format!("ws://{}/ws", addr),
+            mode: LoadGenMode::Mixed,
+            connections: 2,
+            instruments: vec!["IF2501".to_string()],
+            order_rate: 20.0,
+            duration_secs: 1,
+            drain_ms: 300,
+            accounts: vec![LoadGenAccount {
+                user_id: "u1".to_string(),
+                token: "t1".to_string(),
+                account_id: None,
+            }],
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(report.connections_established, 2);
+        assert!(report.orders_sent >= 2);
+        assert_eq!(report.orders_acked, report.orders_sent);
+        assert_eq!(report.order_ack_latency_us.count, report.orders_sent);
+        assert_eq!(report.order_report_latency_us.count, report.orders_sent);
+        assert_eq!(report.market_messages, 4);
+        assert_eq!(report.latency_reports_sent, 2);
+        assert_eq!(report.errors, 0);
+        assert_eq!(report.error_rate, 0.0);
+        assert!(report.push_latency_us.count >= 2);
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["mode"], "mixed");
+        assert!(json["order_ack_latency_us"]["p99"].is_u64());
+
+        // 无人监听的地址：连接失败计入错误率
+        let unreachable = run(LoadGenConfig {
+            url: "ws://127.0.0.1:1/ws".to_string(),
+            connections: 1,
+            instruments: vec!["IF2501".to_string()],
+            duration_secs: 1,
+            connect_timeout_ms: 500,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        assert_eq!(unreachable.connections_established, 0);
+        assert_eq!(unreachable.error_rate, 1.0);
+    }
+}
@@ -2,5 +2,8 @@
 
 pub mod config;
 pub mod jwt;
+pub mod loadgen;
 pub mod logger;
 pub mod metrics;
+pub mod time_service;
+pub mod timestamp;
@@ -0,0 +1,426 @@
+//! 交易所时区服务
+//!
+//! @yutiansut @quantaxis
+//!
+//! 存储内部时间戳统一为 UTC（纳秒/毫秒），不做时区换算；凡涉及"交易日""自然日边界""时间显示"
+//! 的地方（K线日线对齐、结算日期、交易日切换、报表与文件名、成交日期）统一经 [`TimeService`]
+//! 转换为交易所本地时间，不依赖服务器时区（`chrono::Local`），也不隐式按 UTC 计算。
+//!
+//! - 交易所时区由 `[time]` 配置（默认 `Asia/Shanghai`），启动时通过 [`set_time_service`] 设置
+//! - 国内期货交易所无夏令时，时区以固定偏移表示；支持常用时区名与 `+08:00` 形式的偏移
+//! - 交易日规则：本地时间 `night_session_start_hour`（默认 18 点）之后的夜盘归属下一个工作日，
+//!   夜盘跨零点部分同样顺延，节假日由交易日历在外部处理
+//! - 时间源：每个 [`TimeService`] 实例自带时间源（克隆共享同一时间源），默认取系统时间；
+//!   测试环境可切换为虚拟时间（[`TimeService::set_virtual_time_millis`]），此后该实例的
+//!   [`TimeService::now`] / [`TimeService::now_millis`] 从虚拟时间取时。结算、交易日管理、
+//!   订单到期等组件注入同一实例，交易日切换、结算日期、订单到期随之推进
+
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveTime, TimeZone, Timelike, Utc,
+    Weekday,
+};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+
+use crate::ExchangeError;
+
+/// 未设置虚拟时间（取系统时间）
+const SYSTEM_TIME: i64 = i64::MIN;
+
+/// 时区配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeConfig {
+    /// 交易所时区（时区名如 Asia/Shanghai，或偏移如 +08:00）
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+    /// 夜盘归属切换时刻（本地时间小时）：此后的行情与成交归属下一交易日
+    #[serde(default = "default_night_session_start_hour")]
+    pub night_session_start_hour: u32,
+}
+
+fn default_timezone() -> String {
+    "Asia/Shanghai".to_string()
+}
+
+fn default_night_session_start_hour() -> u32 {
+    18
+}
+
+impl Default for TimeConfig {
+    fn default() -> Self {
+        Self {
+            timezone: default_timezone(),
+            night_session_start_hour: default_night_session_start_hour(),
+        }
+    }
+}
+
+/// 解析时区（无夏令时的时区名或固定偏移）
+pub fn parse_timezone(timezone: &str) -> Result<FixedOffset, ExchangeError> {
+    let hours = match timezone {
+        "Asia/Shanghai" | "Asia/Chongqing" | "Asia/Harbin" | "PRC" | "Asia/Hong_Kong"
+        | "Asia/Macau" | "Asia/Taipei" | "Asia/Singapore" => Some(8),
+        "Asia/Tokyo" | "Asia/Seoul" => Some(9),
+        "UTC" | "Etc/UTC" | "GMT" | "Z" => Some(0),
+        _ => None,
+    };
+    if let Some(hours) = hours {
+        return Ok(FixedOffset::east_opt(hours * 3600).unwrap());
+    }
+
+    // +08:00 / -05:00 / +0800 / UTC+8
+    let invalid = || {
+        ExchangeError::InvalidParameter(format!(
+            "Unsupported timezone '{}', expected a zone name without DST (e.g. Asia/Shanghai) or an offset (e.g. +08:00)",
+            timezone
+        ))
+    };
+    let offset = timezone
+        .strip_prefix("UTC")
+        .or_else(|| timezone.strip_prefix("GMT"))
+        .unwrap_or(timezone);
+    let (sign, rest) = match offset.as_bytes().first() {
+        Some(b'+') => (1, &offset[1..]),
+        Some(b'-') => (-1, &offset[1..]),
+        _ => return Err(invalid()),
+    };
+    let (h, m) = match rest.split_once(':') {
+        Some((h, m)) => (h, m),
+        None if rest.len() == 4 => rest.split_at(2),
+        None => (rest, "0"),
+    };
+    let h: i32 = h.parse().map_err(|_| invalid())?;
+    let m: i32 = m.parse().map_err(|_| invalid())?;
+    if h > 14 || m >= 60 {
+        return Err(invalid());
+    }
+    FixedOffset::east_opt(sign * (h * 3600 + m * 60)).ok_or_else(invalid)
+}
+
+/// 交易所时区服务
+#[derive(Debug, Clone)]
+pub struct TimeService {
+    timezone: String,
+    offset: FixedOffset,
+    night_session_start_hour: u32,
+    /// 虚拟时间源（纳秒），`SYSTEM_TIME` 表示取系统时间；克隆共享同一时间源
+    virtual_now_nanos: Arc<AtomicI64>,
+}
+
+impl Default for TimeService {
+    fn default() -> Self {
+        Self {
+            timezone: default_timezone(),
+            offset: FixedOffset::east_opt(8 * 3600).unwrap(),
+            night_session_start_hour: default_night_session_start_hour(),
+            virtual_now_nanos: Arc::new(AtomicI64::new(SYSTEM_TIME)),
+        }
+    }
+}
+
+impl TimeService {
+    pub fn new(config: &TimeConfig) -> Result<Self, ExchangeError> {
+        if config.night_session_start_hour >= 24 {
+            return Err(ExchangeError::InvalidParameter(format!(
+                "night_session_start_hour must be in [0, 23], got {}",
+                config.night_session_start_hour
+            )));
+        }
+        Ok(Self {
+            timezone: config.timezone.clone(),
+            offset: parse_timezone(&config.timezone)?,
+            night_session_start_hour: config.night_session_start_hour,
+            virtual_now_nanos: Arc::new(AtomicI64::new(SYSTEM_TIME)),
+        })
+    }
+
+    /// 交易所时区名
+    pub fn timezone(&self) -> &str {
+        &self.timezone
+    }
+
+    /// 交易所时区偏移
+    pub fn offset(&self) -> FixedOffset {
+        self.offset
+    }
+
+    /// 当前交易所本地时间（按时间源，虚拟时间下为虚拟时间）
+    pub fn now(&self) -> DateTime<FixedOffset> {
+        self.to_local_nanos(self.now_nanos())
+    }
+
+    /// 当前 UTC 时间戳（纳秒，按时间源）
+    #[inline]
+    pub fn now_nanos(&self) -> i64 {
+        match self.virtual_now_nanos.load(Ordering::Relaxed) {
+            SYSTEM_TIME => Utc::now().timestamp_nanos_opt().unwrap_or(0),
+            nanos => nanos,
+        }
+    }
+
+    /// 当前 UTC 时间戳（毫秒，按时间源）
+    #[inline]
+    pub fn now_millis(&self) -> i64 {
+        match self.virtual_now_nanos.load(Ordering::Relaxed) {
+            SYSTEM_TIME => Utc::now().timestamp_millis(),
+            nanos => nanos.div_euclid(1_000_000),
+        }
+    }
+
+    /// 切换为虚拟时间并设置当前时间（毫秒，仅测试环境）
+    pub fn set_virtual_time_millis(&self, millis: i64) {
+        self.virtual_now_nanos
+            .store(millis.saturating_mul(1_000_000), Ordering::SeqCst);
+    }
+
+    /// 恢复系统时间
+    pub fn clear_virtual_time(&self) {
+        self.virtual_now_nanos.store(SYSTEM_TIME, Ordering::SeqCst);
+    }
+
+    /// 当前虚拟时间（毫秒），未启用虚拟时间时为 None
+    pub fn virtual_time_millis(&self) -> Option<i64> {
+        match self.virtual_now_nanos.load(Ordering::SeqCst) {
+            SYSTEM_TIME => None,
+            nanos => Some(nanos.div_euclid(1_000_000)),
+        }
+    }
+
+    /// UTC 毫秒时间戳转交易所本地时间
+    pub fn to_local_millis(&self, timestamp_ms: i64) -> DateTime<FixedOffset> {
+        DateTime::from_timestamp_millis(timestamp_ms)
+            .unwrap_or_default()
+            .with_timezone(&self.offset)
+    }
+
+    /// UTC 纳秒时间戳转交易所本地时间
+    pub fn to_local_nanos(&self, timestamp_ns: i64) -> DateTime<FixedOffset> {
+        self.offset.timestamp_nanos(timestamp_ns)
+    }
+
+    /// 本地时间显示（YYYY-MM-DD HH:MM:SS）
+    pub fn format_millis(&self, timestamp_ms: i64) -> String {
+        self.to_local_millis(timestamp_ms)
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string()
+    }
+
+    /// 当前本地自然日
+    pub fn today(&self) -> NaiveDate {
+        self.now().date_naive()
+    }
+
+    /// 时间戳（毫秒）所属的本地自然日
+    pub fn natural_day_of(&self, timestamp_ms: i64) -> NaiveDate {
+        self.to_local_millis(timestamp_ms).date_naive()
+    }
+
+    /// 本地自然日 0 点对应的 UTC 毫秒时间戳
+    pub fn day_start_millis(&self, day: NaiveDate) -> i64 {
+        self.offset
+            .from_local_datetime(&day.and_hms_opt(0, 0, 0).unwrap())
+            .unwrap()
+            .timestamp_millis()
+    }
+
+    /// 时间戳（毫秒）所属的交易日
+    ///
+    /// - 本地时间夜盘切换时刻之后归属下一个工作日，周五夜盘归属下周一
+    /// - 夜盘跨零点部分（如周六凌晨）同样顺延到下一个工作日
+    /// - 其余时间归属当日
+    pub fn trading_day_of(&self, timestamp_ms: i64) -> NaiveDate {
+        let local = self.to_local_millis(timestamp_ms);
+        let mut day = local.date_naive();
+        if local.hour() >= self.night_session_start_hour {
+            day += Duration::days(1);
+        }
+        while matches!(day.weekday(), Weekday::Sat | Weekday::Sun) {
+            day += Duration::days(1);
+        }
+        day
+    }
+
+    /// 当前交易日
+    pub fn current_trading_day(&self) -> NaiveDate {
+        self.trading_day_of(self.now_millis())
+    }
+
+    /// 下一交易日开始时间（毫秒）：此后第一个归属新交易日的夜盘开始时刻
+    pub fn next_trading_day_start(&self, now_ms: i64) -> i64 {
+        let current = self.trading_day_of(now_ms);
+        let local = self.to_local_millis(now_ms);
+
+        let session_start = NaiveTime::from_hms_opt(self.night_session_start_hour, 0, 0).unwrap();
+        let mut candidate = self
+            .offset
+            .from_local_datetime(&local.date_naive().and_time(session_start))
+            .unwrap();
+        if candidate <= local {
+            candidate += Duration::days(1);
+        }
+        while self.trading_day_of(candidate.timestamp_millis()) == current {
+            candidate += Duration::days(1);
+        }
+        candidate.timestamp_millis()
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref TIME_SERVICE: RwLock<Arc<TimeService>> = RwLock::new(Arc::new(TimeService::default()));
+}
+
+/// 全局交易所时区服务
+pub fn time_service() -> Arc<TimeService> {
+    TIME_SERVICE.read().clone()
+}
+
+/// 设置全局交易所时区服务（启动时按配置设置，与注入各组件的实例共享时间源）
+pub fn set_time_service(service: Arc<TimeService>) {
+    log::info!(
+        "[TimeService] Exchange timezone: {} ({}), night session from {}:00",
+        service.timezone,
+        service.offset,
+        service.night_session_start_hour
+    );
+    *TIME_SERVICE.write() = service;
+}
+
+/// 当前交易所本地时间
+pub fn now_local() -> DateTime<FixedOffset> {
+    time_service().now()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cst_ms(y: i32, m: u32, d: u32, h: u32, mi: u32, sec: u32) -> i64 {
+        FixedOffset::east_opt(8 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(y, m, d, h, mi, sec)
+            .unwrap()
+            .timestamp_millis()
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_parse_timezone() {
+        assert_eq!(
+            parse_timezone("Asia/Shanghai").unwrap().local_minus_utc(),
+            8 * 3600
+        );
+        assert_eq!(parse_timezone("UTC").unwrap().local_minus_utc(), 0);
+        assert_eq!(
+            parse_timezone("+08:00").unwrap().local_minus_utc(),
+            8 * 3600
+        );
+        assert_eq!(parse_timezone("UTC+8").unwrap().local_minus_utc(), 8 * 3600);
+        assert_eq!(
+            parse_timezone("-0530").unwrap().local_minus_utc(),
+            -(5 * 3600 + 1800)
+        );
+        assert!(parse_timezone("America/New_York").is_err());
+        assert!(parse_timezone("+25:00").is_err());
+    }
+
+    /// 自然日边界按本地 0 点切换（而非 UTC 0 点即北京时间 8 点）
+    #[test]
+    fn test_natural_day_boundary_is_local_midnight() {
+        let ts = TimeService::default();
+
+        assert_eq!(
+            ts.natural_day_of(cst_ms(2025, 1, 6, 7, 59, 59)),
+            date(2025, 1, 6)
+        );
+        assert_eq!(
+            ts.natural_day_of(cst_ms(2025, 1, 5, 23, 59, 59)),
+            date(2025, 1, 5)
+        );
+        assert_eq!(
+            ts.day_start_millis(date(2025, 1, 6)),
+            cst_ms(2025, 1, 6, 0, 0, 0)
+        );
+        assert_eq!(
+            ts.format_millis(cst_ms(2025, 1, 6, 0, 0, 1)),
+            "2025-01-06 00:00:01"
+        );
+    }
+
+    /// 跨零点的夜盘成交（23:59:59 与 00:00:01）归属同一交易日
+    #[test]
+    fn test_night_session_across_midnight_same_trading_day() {
+        let ts = TimeService::default();
+
+        // 周二夜盘 -> 周三交易日
+        let before = cst_ms(2025, 1, 7, 23, 59, 59);
+        let after = cst_ms(2025, 1, 8, 0, 0, 1);
+        assert_eq!(ts.trading_day_of(before), date(2025, 1, 8));
+        assert_eq!(ts.trading_day_of(after), date(2025, 1, 8));
+        // 同日日盘仍归属当日
+        assert_eq!(
+            ts.trading_day_of(cst_ms(2025, 1, 8, 14, 59, 0)),
+            date(2025, 1, 8)
+        );
+
+        // 周五夜盘跨零点到周六凌晨 -> 下周一交易日
+        assert_eq!(
+            ts.trading_day_of(cst_ms(2025, 1, 10, 23, 59, 59)),
+            date(2025, 1, 13)
+        );
+        assert_eq!(
+            ts.trading_day_of(cst_ms(2025, 1, 11, 0, 0, 1)),
+            date(2025, 1, 13)
+        );
+
+        // 下一交易日从当晚夜盘开始
+        assert_eq!(
+            ts.next_trading_day_start(cst_ms(2025, 1, 8, 10, 0, 0)),
+            cst_ms(2025, 1, 8, 18, 0, 0)
+        );
+    }
+
+    /// 虚拟时间只作用于本实例（及其克隆），其他实例仍取系统时间
+    #[test]
+    fn test_virtual_time_is_per_instance() {
+        let ts = TimeService::default();
+        let shared = ts.clone();
+        let other = TimeService::default();
+
+        ts.set_virtual_time_millis(cst_ms(2025, 1, 8, 20, 0, 0));
+        assert_eq!(
+            shared.virtual_time_millis(),
+            Some(cst_ms(2025, 1, 8, 20, 0, 0))
+        );
+        assert_eq!(shared.now_millis(), cst_ms(2025, 1, 8, 20, 0, 0));
+        assert_eq!(shared.current_trading_day(), date(2025, 1, 9));
+        assert_eq!(other.virtual_time_millis(), None);
+
+        ts.clear_virtual_time();
+        assert_eq!(shared.virtual_time_millis(), None);
+    }
+
+    /// 配置其他时区时按该时区计算
+    #[test]
+    fn test_configured_timezone() {
+        let utc = TimeService::new(&TimeConfig {
+            timezone: "UTC".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        // 北京时间 2025-01-06 07:00 为 UTC 2025-01-05 23:00
+        let ts = cst_ms(2025, 1, 6, 7, 0, 0);
+        assert_eq!(utc.natural_day_of(ts), date(2025, 1, 5));
+        assert_eq!(TimeService::default().natural_day_of(ts), date(2025, 1, 6));
+
+        assert!(TimeService::new(&TimeConfig {
+            timezone: "Mars/Olympus".to_string(),
+            ..Default::default()
+        })
+        .is_err());
+    }
+}
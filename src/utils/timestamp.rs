@@ -0,0 +1,100 @@
+//! 时间戳单位换算
+//!
+//! @yutiansut @quantaxis
+//!
+//! 内部时间戳统一为 UTC 纳秒（`i64`）：成交记录、WAL、SSTable、OLAP 存储均按纳秒写入。
+//! 毫秒/秒只出现在边界（对外 API 字段、K线周期对齐、配置中的超时与保留时长），
+//! 边界处一律经本模块换算，不在业务代码里手写 `/ 1_000_000`、`* 1_000_000_000`。
+//!
+//! 纳秒转粗粒度单位时向下取整（`div_euclid`），1970 年以前的负时间戳同样落在正确的毫秒/秒内；
+//! 粗粒度转纳秒时饱和相乘，异常大的输入不会溢出回绕。
+
+/// 每微秒纳秒数
+pub const NANOS_PER_MICRO: i64 = 1_000;
+/// 每毫秒纳秒数
+pub const NANOS_PER_MILLI: i64 = 1_000_000;
+/// 每秒纳秒数
+pub const NANOS_PER_SEC: i64 = 1_000_000_000;
+/// 每秒毫秒数
+pub const MILLIS_PER_SEC: i64 = 1_000;
+
+/// 当前时刻（UTC 纳秒）
+pub fn now_nanos() -> i64 {
+    chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0)
+}
+
+/// 当前时刻（UTC 毫秒）
+pub fn now_millis() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+/// 纳秒 → 毫秒（向下取整）
+pub fn nanos_to_millis(nanos: i64) -> i64 {
+    nanos.div_euclid(NANOS_PER_MILLI)
+}
+
+/// 纳秒 → 秒（向下取整）
+pub fn nanos_to_secs(nanos: i64) -> i64 {
+    nanos.div_euclid(NANOS_PER_SEC)
+}
+
+/// 纳秒 → 微秒（向下取整）
+pub fn nanos_to_micros(nanos: i64) -> i64 {
+    nanos.div_euclid(NANOS_PER_MICRO)
+}
+
+/// 毫秒 → 纳秒
+pub fn millis_to_nanos(millis: i64) -> i64 {
+    millis.saturating_mul(NANOS_PER_MILLI)
+}
+
+/// 秒 → 纳秒
+pub fn secs_to_nanos(secs: i64) -> i64 {
+    secs.saturating_mul(NANOS_PER_SEC)
+}
+
+/// 秒 → 毫秒
+pub fn secs_to_millis(secs: i64) -> i64 {
+    secs.saturating_mul(MILLIS_PER_SEC)
+}
+
+/// 毫秒 → 秒（向下取整）
+pub fn millis_to_secs(millis: i64) -> i64 {
+    millis.div_euclid(MILLIS_PER_SEC)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unit_conversions() {
+        let ns = 1_735_689_600_123_456_789;
+        assert_eq!(nanos_to_millis(ns), 1_735_689_600_123);
+        assert_eq!(nanos_to_secs(ns), 1_735_689_600);
+        assert_eq!(nanos_to_micros(ns), 1_735_689_600_123_456);
+        assert_eq!(
+            millis_to_nanos(nanos_to_millis(ns)),
+            1_735_689_600_123_000_000
+        );
+        assert_eq!(secs_to_nanos(3600), 3_600_000_000_000);
+        assert_eq!(secs_to_millis(60), 60_000);
+        assert_eq!(millis_to_secs(1_999), 1);
+
+        // 负时间戳向下取整，不向零截断
+        assert_eq!(nanos_to_millis(-1), -1);
+        assert_eq!(nanos_to_secs(-1), -1);
+        assert_eq!(millis_to_secs(-1), -1);
+
+        // 溢出时饱和
+        assert_eq!(millis_to_nanos(i64::MAX), i64::MAX);
+        assert_eq!(secs_to_nanos(i64::MIN), i64::MIN);
+    }
+
+    #[test]
+    fn test_now_units_agree() {
+        let ns = now_nanos();
+        let ms = now_millis();
+        assert!((ms - nanos_to_millis(ns)).abs() < 1_000);
+    }
+}
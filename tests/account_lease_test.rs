@@ -0,0 +1,209 @@
+// 账户操作租约集成测试 @yutiansut @quantaxis
+//
+// 两个网关实例共享租约目录、各自加载同一账户，并发对该账户下单：
+// 只有持有租约的实例能冻结资金，另一实例全部拒绝；
+// 持有实例释放或宕机（租约超时）后另一实例接管，接管前加载上一持有实例发布的账户状态，
+// 整个交接过程中两个实例累计冻结的资金不超过账户资金，不会双花
+//
+// 运行：cargo test --test account_lease_test -- --nocapture
+
+use std::path::Path;
+use std::sync::{Arc, Barrier};
+use std::thread;
+use std::time::Duration;
+
+use qaexchange::core::account_ext::{AccountType, OpenAccountRequest};
+use qaexchange::exchange::instrument_registry::{InstrumentInfo, InstrumentStatus, InstrumentType};
+use qaexchange::exchange::order_router::{SubmitOrderRequest, SubmitOrderResponse};
+use qaexchange::exchange::{
+    AccountLeaseManager, AccountManager, CapitalManager, InstrumentRegistry, OrderRouter,
+    TradeGateway,
+};
+use qaexchange::matching::engine::ExchangeMatchingEngine;
+use qaexchange::risk::RejectReason;
+
+const ACCOUNT: &str = "shared";
+const INIT_CASH: f64 = 100000.0;
+
+struct Instance {
+    account_mgr: Arc<AccountManager>,
+    router: Arc<OrderRouter>,
+    capital_mgr: CapitalManager,
+}
+
+/// 一个网关实例：独立的内存状态，共享租约目录
+fn create_instance(instance_id: &str, lease_dir: &Path, ttl_ms: i64) -> Instance {
+    let account_mgr = Arc::new(AccountManager::new());
+    account_mgr
+        .open_account(OpenAccountRequest {
+            user_id: ACCOUNT.to_string(),
+            account_id: Some(ACCOUNT.to_string()),
+            account_name: ACCOUNT.to_string(),
+            init_cash: INIT_CASH,
+            account_type: AccountType::Individual,
+        })
+        .unwrap();
+
+    let matching_engine = Arc::new(ExchangeMatchingEngine::new());
+    matching_engine
+        .register_instrument("IX2301".to_string(), 100.0)
+        .unwrap();
+    let registry = Arc::new(InstrumentRegistry::new());
+    registry
+        .register(InstrumentInfo {
+            instrument_id: "IX2301".to_string(),
+            instrument_name: "IX2301".to_string(),
+            instrument_type: InstrumentType::CommodityFuture,
+            exchange: "SHFE".to_string(),
+            contract_multiplier: 1,
+            trading_unit: "手".to_string(),
+            quote_unit: String::new(),
+            price_tick: 0.01,
+            margin_rate: 0.1,
+            commission_rate: 0.0005,
+            limit_up_rate: 0.1,
+            limit_down_rate: 0.1,
+            status: InstrumentStatus::Active,
+            list_date: Some("2023-01-01".to_string()),
+            expire_date: Some("2023-12-31".to_string()),
+            created_at: "2023-01-01T00:00:00Z".to_string(),
+            updated_at: "2023-01-01T00:00:00Z".to_string(),
+        })
+        .unwrap();
+
+    let lease = Arc::new(
+        AccountLeaseManager::new(instance_id, lease_dir, ttl_ms)
+            .unwrap()
+            .with_account_manager(account_mgr.clone()),
+    );
+    let trade_gateway = Arc::new(TradeGateway::new(account_mgr.clone()));
+    let trade_recorder = matching_engine.get_trade_recorder();
+    let mut router = OrderRouter::new(
+        account_mgr.clone(),
+        matching_engine,
+        registry,
+        trade_gateway,
+    );
+    router
+        .trade_bus()
+        .subscribe_inline("trade_recorder", trade_recorder);
+    router.set_account_lease(lease.clone());
+    let capital_mgr = CapitalManager::new(account_mgr.clone()).with_account_lease(lease);
+
+    Instance {
+        account_mgr,
+        router: Arc::new(router),
+        capital_mgr,
+    }
+}
+
+fn buy_order(volume: f64) -> SubmitOrderRequest {
+    SubmitOrderRequest {
+        account_id: ACCOUNT.to_string(),
+        instrument_id: "IX2301".to_string(),
+        direction: "BUY".to_string(),
+        offset: "OPEN".to_string(),
+        volume,
+        price: 100.0,
+        order_type: "LIMIT".to_string(),
+        ..Default::default()
+    }
+}
+
+/// (冻结保证金, 可用资金)
+fn funds(account_mgr: &AccountManager) -> (f64, f64) {
+    let qifi = account_mgr.get_qifi_slice(ACCOUNT).unwrap();
+    (qifi.accounts.frozen_margin, qifi.accounts.available)
+}
+
+#[test]
+fn test_lease_handoff_does_not_double_spend() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut instances = vec![
+        create_instance("gw-a", dir.path(), 500),
+        create_instance("gw-b", dir.path(), 500),
+    ];
+
+    // 1. 两个实例同时对同一账户下单：只有一个实例拿到租约
+    let barrier = Arc::new(Barrier::new(instances.len()));
+    let handles: Vec<_> = instances
+        .iter()
+        .map(|instance| {
+            let router = instance.router.clone();
+            let barrier = barrier.clone();
+            thread::spawn(move || {
+                barrier.wait();
+                (0..3)
+                    .map(|_| router.submit_order(buy_order(1000.0)))
+                    .collect::<Vec<SubmitOrderResponse>>()
+            })
+        })
+        .collect();
+    let results: Vec<Vec<SubmitOrderResponse>> =
+        handles.into_iter().map(|h| h.join().unwrap()).collect();
+
+    let winner = results
+        .iter()
+        .position(|responses| responses.iter().any(|r| r.success))
+        .expect("一个实例应成功下单");
+    let loser = 1 - winner;
+    assert!(results[loser].iter().all(|r| {
+        r.error_code == Some(RejectReason::AccountLeaseHeld.error_code())
+            && r.error_message.as_deref().unwrap().contains("retry")
+    }));
+    assert!(instances[loser]
+        .capital_mgr
+        .withdraw_with_record(ACCOUNT.to_string(), 1000.0, None, None)
+        .is_err());
+    let b = instances.remove(loser);
+    let a = instances.remove(0);
+
+    let (a_frozen, _) = funds(&a.account_mgr);
+    assert!(a_frozen > 0.0);
+
+    // 2. A 释放租约：B 接管前加载 A 发布的状态，A 的冻结资金对 B 可见
+    a.router.account_lease().unwrap().release(ACCOUNT).unwrap();
+    let accepted = (0..10)
+        .filter(|_| b.router.submit_order(buy_order(1000.0)).success)
+        .count();
+    assert!(accepted > 0);
+    assert!(accepted < 10, "B 不应按初始资金接单");
+    let (b_frozen, b_available) = funds(&b.account_mgr);
+    assert!(b_frozen >= a_frozen);
+    assert!(b_available >= 0.0);
+    assert!(b_frozen <= INIT_CASH);
+    assert!(!a.router.submit_order(buy_order(1.0)).success);
+
+    // 3. B 宕机：不释放租约，超时后 A 接管并加载 B 最后发布的状态
+    drop(b);
+    thread::sleep(Duration::from_millis(600));
+    for _ in 0..10 {
+        a.router.submit_order(buy_order(1000.0));
+    }
+    let (frozen, available) = funds(&a.account_mgr);
+    assert!(frozen >= b_frozen);
+    assert!(available >= 0.0);
+    assert!(frozen <= INIT_CASH);
+    assert_eq!(
+        a.router
+            .account_lease()
+            .unwrap()
+            .holder(ACCOUNT)
+            .unwrap()
+            .holder,
+        "gw-a"
+    );
+}
+
+/// 未注入账户管理器时无法加载账户状态，拒绝接管其他实例持有过的租约
+#[test]
+fn test_takeover_refused_without_state_reload() {
+    let dir = tempfile::tempdir().unwrap();
+    let a = AccountLeaseManager::new("gw-a", dir.path(), 60_000).unwrap();
+    let b = AccountLeaseManager::new("gw-b", dir.path(), 60_000).unwrap();
+
+    drop(a.acquire(ACCOUNT).unwrap());
+    a.release(ACCOUNT).unwrap();
+    assert!(b.acquire(ACCOUNT).is_err());
+    assert!(a.acquire(ACCOUNT).is_ok());
+}
@@ -0,0 +1,199 @@
+// 确定性模式回放集成测试 @yutiansut @quantaxis
+//
+// 同一输入订单流（下单、撤单、推进虚拟时钟触发 TTL 撤单）在两个独立的确定性交易所上回放，
+// 成交序列与最终账户状态序列化后逐字节相同；成交时间取自虚拟时钟
+//
+// 运行：cargo test --test deterministic_replay_test -- --nocapture
+
+use std::thread;
+
+use qaexchange::core::account_ext::{AccountType, OpenAccountRequest};
+use qaexchange::exchange::deterministic::ReplayResult;
+use qaexchange::exchange::instrument_registry::{InstrumentInfo, InstrumentStatus, InstrumentType};
+use qaexchange::exchange::order_router::SubmitOrderRequest;
+use qaexchange::exchange::{DeterministicConfig, DeterministicExchange, ReplayEvent, ReplayOutput};
+
+const START_MS: i64 = 1_735_689_600_000;
+
+fn config() -> DeterministicConfig {
+    DeterministicConfig {
+        enabled: true,
+        seed: 20250101,
+        start_time_ms: START_MS,
+        trading_day: "20250101".to_string(),
+    }
+}
+
+fn create_exchange(wal_root: &std::path::Path) -> DeterministicExchange {
+    create_exchange_with(&config(), wal_root)
+}
+
+fn create_exchange_with(
+    config: &DeterministicConfig,
+    wal_root: &std::path::Path,
+) -> DeterministicExchange {
+    let exchange = DeterministicExchange::new(config, wal_root.to_str().unwrap());
+    exchange
+        .register_instrument(
+            InstrumentInfo {
+                instrument_id: "IX2301".to_string(),
+                instrument_name: "IX2301".to_string(),
+                instrument_type: InstrumentType::CommodityFuture,
+                exchange: "SHFE".to_string(),
+                contract_multiplier: 10,
+                trading_unit: "手".to_string(),
+                quote_unit: String::new(),
+                price_tick: 0.01,
+                margin_rate: 0.1,
+                commission_rate: 0.0005,
+                limit_up_rate: 0.1,
+                limit_down_rate: 0.1,
+                status: InstrumentStatus::Active,
+                list_date: Some("2023-01-01".to_string()),
+                expire_date: Some("2026-12-31".to_string()),
+                created_at: "2023-01-01T00:00:00Z".to_string(),
+                updated_at: "2023-01-01T00:00:00Z".to_string(),
+            },
+            100.0,
+        )
+        .unwrap();
+    for user in ["alice", "bob", "carol"] {
+        exchange
+            .open_account(OpenAccountRequest {
+                user_id: user.to_string(),
+                account_id: Some(user.to_string()),
+                account_name: user.to_string(),
+                init_cash: 1_000_000.0,
+                account_type: AccountType::Individual,
+            })
+            .unwrap();
+    }
+    exchange
+}
+
+fn order(
+    account_id: &str,
+    direction: &str,
+    offset: &str,
+    volume: f64,
+    price: f64,
+    ttl_secs: Option<u64>,
+) -> ReplayEvent {
+    ReplayEvent::Submit(SubmitOrderRequest {
+        account_id: account_id.to_string(),
+        instrument_id: "IX2301".to_string(),
+        direction: direction.to_string(),
+        offset: offset.to_string(),
+        volume,
+        price,
+        order_type: "LIMIT".to_string(),
+        ttl_secs,
+        ..Default::default()
+    })
+}
+
+/// 挂单、多档吃单、部分成交、撤单、TTL 到期撤单、平仓
+fn order_flow() -> Vec<ReplayEvent> {
+    vec![
+        order("alice", "SELL", "OPEN", 2.0, 101.0, None),
+        order("alice", "SELL", "OPEN", 3.0, 102.0, None),
+        ReplayEvent::AdvanceClock { millis: 150 },
+        order("bob", "BUY", "OPEN", 4.0, 102.0, None),
+        order("carol", "BUY", "OPEN", 5.0, 99.0, Some(2)),
+        ReplayEvent::AdvanceClock { millis: 1_000 },
+        order("carol", "BUY", "OPEN", 2.0, 102.0, None),
+        order("bob", "SELL", "OPEN", 3.0, 100.0, None),
+        // 撤销 bob 剩余的卖单（事件序号 7）
+        ReplayEvent::Cancel {
+            account_id: "bob".to_string(),
+            submit_index: 7,
+        },
+        ReplayEvent::AdvanceClock { millis: 1_500 },
+        order("bob", "SELL", "CLOSE", 2.0, 98.0, None),
+        order("alice", "BUY", "OPEN", 2.0, 98.0, None),
+        ReplayEvent::AdvanceClock { millis: 60_000 },
+    ]
+}
+
+fn replay() -> (ReplayOutput, Vec<u8>) {
+    let dir = tempfile::tempdir().unwrap();
+    let exchange = create_exchange(dir.path());
+    let output = exchange.run(&order_flow());
+    let bytes = output.to_bytes().unwrap();
+    (output, bytes)
+}
+
+#[test]
+fn test_replay_twice_is_byte_identical() {
+    // 第二次回放放在另一个线程，与第一次的线程环境不同
+    let (first, first_bytes) = replay();
+    let (_, second_bytes) = thread::spawn(replay).join().unwrap();
+    assert_eq!(first_bytes, second_bytes);
+
+    // bob 吃两档、carol 吃 alice 剩余挂单、bob 卖给 carol、bob 平仓卖给 alice
+    assert_eq!(first.trades.len(), 5, "trades: {:?}", first.trades);
+    assert!(first
+        .trades
+        .windows(2)
+        .all(|w| w[0].trade_id < w[1].trade_id));
+    // 成交时间来自虚拟时钟
+    assert!(first
+        .trades
+        .iter()
+        .all(|t| t.timestamp >= START_MS * 1_000_000
+            && t.timestamp < (START_MS + 70_000) * 1_000_000));
+    assert!(first.trades.iter().all(|t| t.trading_day == "2025-01-01"));
+
+    let cancelled = first
+        .results
+        .iter()
+        .any(|r| matches!(r, ReplayResult::Cancel { error: None, .. }));
+    assert!(cancelled);
+    // carol 的 TTL 挂单在虚拟时钟推进后被撤销
+    let expired: usize = first
+        .results
+        .iter()
+        .map(|r| match r {
+            ReplayResult::AdvanceClock { expired_orders, .. } => *expired_orders,
+            _ => 0,
+        })
+        .sum();
+    assert_eq!(expired, 1);
+
+    let account_ids: Vec<&str> = first
+        .accounts
+        .iter()
+        .map(|a| a.account_id.as_str())
+        .collect();
+    assert_eq!(account_ids, vec!["alice", "bob", "carol"]);
+}
+
+/// 跨零点的夜盘成交（北京时间 23:59:59 与 00:00:01）按交易所时区归属同一交易日
+#[test]
+fn test_night_session_trades_across_midnight_share_trading_day() {
+    // 2025-01-07（周二）23:59:59 北京时间 = 2025-01-07 15:59:59 UTC
+    const NIGHT_MS: i64 = 1_736_265_599_000;
+    let dir = tempfile::tempdir().unwrap();
+    let exchange = create_exchange_with(
+        &DeterministicConfig {
+            start_time_ms: NIGHT_MS,
+            trading_day: "20250108".to_string(),
+            ..config()
+        },
+        dir.path(),
+    );
+
+    let output = exchange.run(&[
+        order("alice", "SELL", "OPEN", 1.0, 100.0, None),
+        order("bob", "BUY", "OPEN", 1.0, 100.0, None),
+        ReplayEvent::AdvanceClock { millis: 2_000 },
+        order("alice", "SELL", "OPEN", 1.0, 100.0, None),
+        order("carol", "BUY", "OPEN", 1.0, 100.0, None),
+    ]);
+
+    assert_eq!(output.trades.len(), 2, "trades: {:?}", output.trades);
+    assert!(output.trades[0].timestamp < (NIGHT_MS + 1_000) * 1_000_000);
+    assert!(output.trades[1].timestamp >= (NIGHT_MS + 2_000) * 1_000_000);
+    // UTC 日期同为 2025-01-07、北京时间日期跨到 01-08，两笔成交均归属周三交易日
+    assert!(output.trades.iter().all(|t| t.trading_day == "2025-01-08"));
+}
@@ -303,3 +303,93 @@ async fn test_session_unregister() {
 
     assert_eq!(gateway.get_stats().active_sessions, 0);
 }
+
+/// 测试管理员会话通过 system_events 频道收到结算开始/结束事件，普通用户订阅被拒绝
+#[tokio::test]
+async fn test_admin_receives_settlement_system_events() {
+    use qaexchange::core::account_ext::{AccountType, OpenAccountRequest};
+    use qaexchange::exchange::{AccountManager, SettlementEngine};
+    use qaexchange::notification::SystemEventCategory;
+    use qaexchange::service::websocket::messages::ServerMessage;
+    use qaexchange::service::websocket::system_events::{
+        subscribe_system_events, system_event_message,
+    };
+    use qaexchange::user::{UserManager, UserRegisterRequest};
+    use std::collections::HashSet;
+
+    let broker = Arc::new(NotificationBroker::new());
+    let _processor = broker.clone().start_priority_processor();
+
+    // 第一个注册的用户为管理员，后续为普通交易员
+    let user_mgr = UserManager::new();
+    let register = |username: &str| {
+        user_mgr
+            .register(UserRegisterRequest {
+                username: username.to_string(),
+                password: "password123".to_string(),
+                phone: None,
+                email: None,
+                real_name: None,
+                id_card: None,
+            })
+            .unwrap()
+    };
+    let admin = register("ops_admin");
+    let trader = register("trader_01");
+
+    // 普通用户订阅被拒绝
+    assert!(subscribe_system_events(
+        &broker,
+        &user_mgr,
+        &trader.user_id,
+        "session_trader",
+        HashSet::new(),
+    )
+    .is_err());
+
+    // 管理员只订阅结算类事件
+    let mut admin_rx = subscribe_system_events(
+        &broker,
+        &user_mgr,
+        &admin.user_id,
+        "session_admin",
+        HashSet::from([SystemEventCategory::Settlement]),
+    )
+    .unwrap();
+
+    let account_mgr = Arc::new(AccountManager::with_notification_broker(broker.clone()));
+    account_mgr
+        .open_account(OpenAccountRequest {
+            user_id: "settle_user".to_string(),
+            account_id: None,
+            account_name: "Settle User".to_string(),
+            init_cash: 1000000.0,
+            account_type: AccountType::Individual,
+        })
+        .unwrap();
+    let engine = SettlementEngine::new(account_mgr);
+    engine.set_settlement_price("IX2301".to_string(), 120.0);
+    let result = engine.daily_settlement().unwrap();
+
+    let mut titles = Vec::new();
+    while titles.len() < 2 {
+        let notification = tokio::time::timeout(Duration::from_secs(1), admin_rx.recv())
+            .await
+            .expect("Timeout waiting for system event")
+            .unwrap();
+        match system_event_message(&notification) {
+            Some(ServerMessage::SystemEvent {
+                category,
+                title,
+                details,
+                ..
+            }) => {
+                assert_eq!(category, "settlement");
+                assert_eq!(details["settlement_date"], result.settlement_date.as_str());
+                titles.push(title);
+            }
+            _ => panic!("Expected system event"),
+        }
+    }
+    assert_eq!(titles, vec!["日终结算开始", "日终结算完成"]);
+}
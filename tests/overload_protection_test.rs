@@ -0,0 +1,139 @@
+// 网关过载保护集成测试 @yutiansut @quantaxis
+//
+// 下游容量有限（共享 4 个处理槽位）时，大量行情查询与少量交易请求同时涌入：
+// 不开启保护时所有行情查询都进入下游排队，交易请求排在积压之后；
+// 开启保护后在途请求达到高水位即拒绝后续行情查询（503 + Retry-After），
+// 进入下游的行情查询数被限制在高水位以内，交易请求全部正常处理
+//
+// 运行：cargo test --test overload_protection_test
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::http::header;
+use actix_web::{test, web, App, HttpResponse};
+use futures::future::join_all;
+use tokio::sync::Semaphore;
+
+use qaexchange::service::http::load_shedding::LoadShedding;
+use qaexchange::service::overload::{OverloadConfig, OverloadGuard, OverloadStatus, ShedLevel};
+
+const BACKEND_SLOTS: usize = 4;
+const QUERY_COUNT: usize = 200;
+const ORDER_COUNT: u64 = 20;
+/// 开启保护时的在途容量与高水位：在途达到 8 后拒绝行情查询
+const MAX_IN_FLIGHT: usize = 32;
+const HIGH_WATER: f64 = 0.25;
+const HIGH_WATER_IN_FLIGHT: usize = 8;
+
+/// 模拟下游：所有请求共享有限的处理槽位
+struct Backend {
+    slots: Semaphore,
+    served_queries: AtomicUsize,
+}
+
+async fn query_tick(backend: web::Data<Backend>) -> HttpResponse {
+    backend.served_queries.fetch_add(1, Ordering::SeqCst);
+    let _slot = backend.slots.acquire().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    HttpResponse::Ok().finish()
+}
+
+async fn submit_order(backend: web::Data<Backend>) -> HttpResponse {
+    let _slot = backend.slots.acquire().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(2)).await;
+    HttpResponse::Ok().finish()
+}
+
+/// 负载结果
+struct LoadOutcome {
+    /// 被拒绝的行情查询数
+    rejected: usize,
+    /// 进入下游处理的行情查询数
+    served_queries: usize,
+    status: OverloadStatus,
+}
+
+async fn run_load(config: OverloadConfig) -> LoadOutcome {
+    let guard = Arc::new(OverloadGuard::new(config));
+    let backend = web::Data::new(Backend {
+        slots: Semaphore::new(BACKEND_SLOTS),
+        served_queries: AtomicUsize::new(0),
+    });
+    let app = test::init_service(
+        App::new()
+            .app_data(backend.clone())
+            .wrap(LoadShedding::new(guard.clone()))
+            .route(
+                "/api/market/tick/{instrument_id}",
+                web::get().to(query_tick),
+            )
+            .route("/api/order/submit", web::post().to(submit_order)),
+    )
+    .await;
+    let app = &app;
+
+    let queries = (0..QUERY_COUNT).map(|i| async move {
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/market/tick/IF{}", i))
+            .to_request();
+        let resp = test::call_service(app, req).await;
+        if resp.status().as_u16() == 503 {
+            assert_eq!(resp.headers().get(header::RETRY_AFTER).unwrap(), "1");
+        }
+        resp.status()
+    });
+    let orders = (0..ORDER_COUNT).map(|i| async move {
+        // 交易请求在行情洪峰开始后陆续到达
+        tokio::time::sleep(Duration::from_millis(5 * i)).await;
+        let req = test::TestRequest::post()
+            .uri("/api/order/submit")
+            .to_request();
+        assert!(test::call_service(app, req).await.status().is_success());
+    });
+
+    let (statuses, _) = futures::join!(join_all(queries), join_all(orders));
+    assert_eq!(guard.in_flight(), 0);
+
+    LoadOutcome {
+        rejected: statuses.iter().filter(|s| s.as_u16() == 503).count(),
+        served_queries: backend.served_queries.load(Ordering::SeqCst),
+        status: guard.status(),
+    }
+}
+
+#[actix::test]
+async fn test_load_shedding_bounds_queries_and_keeps_trading() {
+    let unprotected = run_load(OverloadConfig::default()).await;
+    assert_eq!(unprotected.rejected, 0);
+    assert_eq!(unprotected.served_queries, QUERY_COUNT);
+
+    // 交易请求最多 8 + 20 个在途，低于 critical_high_water 对应的 32，交易请求永不被拒；
+    // 恢复保持时间足够长，洪峰期间不会提前解除保护
+    let protected = run_load(OverloadConfig {
+        enabled: true,
+        max_in_flight: MAX_IN_FLIGHT,
+        high_water: HIGH_WATER,
+        critical_high_water: 1.0,
+        low_water: 0.1,
+        max_latency_ms: 0,
+        recover_hold_ms: 60_000,
+        ..Default::default()
+    })
+    .await;
+
+    // 进入下游的行情查询被限制在高水位，其余全部 503
+    assert_eq!(protected.served_queries, HIGH_WATER_IN_FLIGHT);
+    assert_eq!(protected.rejected, QUERY_COUNT - HIGH_WATER_IN_FLIGHT);
+
+    // 拒绝原因：在途请求数触发，只拒绝非关键请求
+    let status = protected.status;
+    assert_eq!(status.rejected_non_critical, protected.rejected as u64);
+    assert_eq!(status.rejected_critical, 0);
+    assert_eq!(status.level, ShedLevel::ShedNonCritical);
+    assert_eq!(status.events.len(), 1);
+    assert_eq!(status.events[0].from, ShedLevel::Normal);
+    assert_eq!(status.events[0].to, ShedLevel::ShedNonCritical);
+    assert_eq!(status.events[0].trigger, "in_flight");
+}
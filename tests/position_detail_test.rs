@@ -0,0 +1,303 @@
+// 持仓明细（逐笔开仓明细）集成测试 @yutiansut @quantaxis
+//
+// 多次开平交织（FIFO 平仓、指定批次平仓）后：
+// 逐笔明细剩余量/平仓盈亏正确，批次汇总与 qars 汇总持仓一致（对账无差异），
+// QIFI 导出按开关附加 lots，结算后今仓批次转昨仓，平仓挂单冻结量按平仓顺序扣减可平量
+//
+// 运行：cargo test --test position_detail_test -- --nocapture
+
+use std::sync::Arc;
+
+use qaexchange::core::account_ext::{AccountType, OpenAccountRequest};
+use qaexchange::exchange::instrument_registry::{InstrumentInfo, InstrumentStatus, InstrumentType};
+use qaexchange::exchange::order_router::{CancelOrderRequest, SubmitOrderRequest};
+use qaexchange::exchange::{
+    AccountManager, InstrumentRegistry, LotSide, OrderRouter, PositionDetailView,
+    PositionLotsConfig, SettlementEngine, TradeGateway,
+};
+use qaexchange::matching::engine::ExchangeMatchingEngine;
+
+fn order(
+    account_id: &str,
+    direction: &str,
+    offset: &str,
+    volume: f64,
+    price: f64,
+) -> SubmitOrderRequest {
+    SubmitOrderRequest {
+        account_id: account_id.to_string(),
+        instrument_id: "IX2301".to_string(),
+        direction: direction.to_string(),
+        offset: offset.to_string(),
+        volume,
+        price,
+        order_type: "LIMIT".to_string(),
+        ..Default::default()
+    }
+}
+
+fn approx(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-6
+}
+
+fn detail(account_mgr: &AccountManager, account_id: &str) -> PositionDetailView {
+    let mut views = account_mgr.position_detail(account_id).unwrap();
+    assert_eq!(views.len(), 1);
+    views.remove(0)
+}
+
+/// alice、bob 两个账户与 IX2301 合约（合约乘数 10）的下单链路
+fn setup() -> (Arc<AccountManager>, Arc<OrderRouter>) {
+    let account_mgr = Arc::new(AccountManager::new());
+    for user in ["alice", "bob"] {
+        account_mgr
+            .open_account(OpenAccountRequest {
+                user_id: user.to_string(),
+                account_id: Some(user.to_string()),
+                account_name: user.to_string(),
+                init_cash: 1000000.0,
+                account_type: AccountType::Individual,
+            })
+            .unwrap();
+    }
+
+    let matching_engine = Arc::new(ExchangeMatchingEngine::new());
+    matching_engine
+        .register_instrument("IX2301".to_string(), 100.0)
+        .unwrap();
+    let registry = Arc::new(InstrumentRegistry::new());
+    registry
+        .register(InstrumentInfo {
+            instrument_id: "IX2301".to_string(),
+            instrument_name: "IX2301".to_string(),
+            instrument_type: InstrumentType::CommodityFuture,
+            exchange: "SHFE".to_string(),
+            contract_multiplier: 10,
+            trading_unit: "手".to_string(),
+            quote_unit: String::new(),
+            price_tick: 0.01,
+            margin_rate: 0.1,
+            commission_rate: 0.0005,
+            limit_up_rate: 0.1,
+            limit_down_rate: 0.1,
+            status: InstrumentStatus::Active,
+            list_date: Some("2023-01-01".to_string()),
+            expire_date: None,
+            created_at: "2023-01-01T00:00:00Z".to_string(),
+            updated_at: "2023-01-01T00:00:00Z".to_string(),
+        })
+        .unwrap();
+    let trade_gateway = Arc::new(TradeGateway::new(account_mgr.clone()));
+    let router = Arc::new(OrderRouter::new(
+        account_mgr.clone(),
+        matching_engine.clone(),
+        registry,
+        trade_gateway,
+    ));
+    (account_mgr, router)
+}
+
+#[test]
+fn test_position_detail_after_interleaved_open_close() {
+    let (account_mgr, router) = setup();
+    let trade = |alice: SubmitOrderRequest, bob: SubmitOrderRequest| {
+        assert!(router.submit_order(alice).success);
+        assert!(router.submit_order(bob).success);
+    };
+    let lots = account_mgr.position_lots();
+
+    // 开 2@100、开 1@102 → FIFO 平 1@103（消耗 100 批次）→ 开 2@101
+    trade(
+        order("alice", "BUY", "OPEN", 2.0, 100.0),
+        order("bob", "SELL", "OPEN", 2.0, 100.0),
+    );
+    trade(
+        order("alice", "BUY", "OPEN", 1.0, 102.0),
+        order("bob", "SELL", "OPEN", 1.0, 102.0),
+    );
+    trade(
+        order("alice", "SELL", "CLOSE", 1.0, 103.0),
+        order("bob", "BUY", "CLOSE", 1.0, 103.0),
+    );
+    trade(
+        order("alice", "BUY", "OPEN", 2.0, 101.0),
+        order("bob", "SELL", "OPEN", 2.0, 101.0),
+    );
+
+    // 指定批次平仓：alice 平 101 批次 2 手；bob 指定 102 空头批次，不足部分按 FIFO
+    let alice_lots = detail(&account_mgr, "alice").lots;
+    assert_eq!(alice_lots.len(), 3);
+    let alice_101 = alice_lots
+        .iter()
+        .find(|d| approx(d.lot.price, 101.0))
+        .unwrap()
+        .lot
+        .lot_id;
+    let bob_102 = detail(&account_mgr, "bob")
+        .lots
+        .iter()
+        .find(|d| approx(d.lot.price, 102.0))
+        .unwrap()
+        .lot
+        .lot_id;
+    // 方向不符拒绝
+    assert!(lots
+        .designate_close("alice", "IX2301", "check", alice_101, 3)
+        .is_err());
+    trade(
+        SubmitOrderRequest {
+            close_lot_id: Some(alice_101),
+            ..order("alice", "SELL", "CLOSE", 2.0, 104.0)
+        },
+        SubmitOrderRequest {
+            close_lot_id: Some(bob_102),
+            ..order("bob", "BUY", "CLOSE", 2.0, 104.0)
+        },
+    );
+
+    // alice 剩 100×1、102×1；批次平仓盈亏 (103-100)×1 + (104-101)×2 = 9 点
+    let alice = detail(&account_mgr, "alice");
+    let prices: Vec<(f64, f64)> = alice
+        .lots
+        .iter()
+        .map(|d| (d.lot.price, d.lot.volume))
+        .collect();
+    assert_eq!(prices, vec![(100.0, 1.0), (102.0, 1.0)]);
+    assert!(alice.lots.iter().all(|d| d.lot.side == LotSide::Long));
+    assert!(approx(alice.long_volume, 2.0));
+    let multiplier = alice.close_profit / 9.0;
+    assert!(multiplier >= 1.0);
+    for d in &alice.lots {
+        assert!(approx(
+            d.float_profit,
+            (alice.last_price - d.lot.price) * d.lot.volume * multiplier
+        ));
+    }
+    assert!(approx(
+        alice.float_profit,
+        alice.lots.iter().map(|d| d.float_profit).sum()
+    ));
+
+    // bob 剩 101×2 空头；平仓盈亏 (100-103)×1 + (102-104)×1 + (100-104)×1 = -9 点
+    let bob = detail(&account_mgr, "bob");
+    assert_eq!(bob.lots.len(), 1);
+    assert_eq!(bob.lots[0].lot.side, LotSide::Short);
+    assert!(approx(bob.lots[0].lot.price, 101.0));
+    assert!(approx(bob.short_volume, 2.0));
+    assert!(approx(bob.close_profit, -9.0 * multiplier));
+
+    // 明细汇总与 qars 汇总持仓一致
+    for account_id in ["alice", "bob"] {
+        assert!(account_mgr
+            .reconcile_position_lots(account_id)
+            .unwrap()
+            .is_empty());
+    }
+
+    // QIFI 导出：开关关闭时不带 lots（兼容旧格式），打开后附加开仓明细
+    let export = account_mgr.export_account("alice").unwrap();
+    assert!(export.lots.is_empty());
+    assert!(serde_json::to_value(&export).unwrap().get("lots").is_none());
+    lots.apply_config(&PositionLotsConfig {
+        export_in_qifi: true,
+        ..Default::default()
+    });
+    let export = account_mgr.export_account("alice").unwrap();
+    assert_eq!(export.lots.len(), 1);
+    assert_eq!(export.lots[0].lots.len(), 2);
+    let json = serde_json::to_value(&export).unwrap();
+    assert_eq!(json["lots"][0]["lots"][0]["closable_volume"], 1.0);
+
+    // 结算：对账一致，今仓批次转昨仓
+    let settlement_engine = SettlementEngine::new(account_mgr.clone());
+    settlement_engine.set_settlement_price("IX2301".to_string(), 104.0);
+    settlement_engine.daily_settlement().unwrap();
+    for account_id in ["alice", "bob"] {
+        assert!(account_mgr
+            .reconcile_position_lots(account_id)
+            .unwrap()
+            .is_empty());
+    }
+    let alice = detail(&account_mgr, "alice");
+    assert!(alice.lots.iter().all(|d| !d.lot.today));
+    assert!(approx(alice.close_profit, 0.0));
+
+    // 平仓挂单冻结 1 手：FIFO 先扣 100 批次的可平量
+    assert!(
+        router
+            .submit_order(order("alice", "SELL", "CLOSE", 1.0, 108.0))
+            .success
+    );
+    let alice = detail(&account_mgr, "alice");
+    let closable: Vec<f64> = alice.lots.iter().map(|d| d.closable_volume).collect();
+    assert_eq!(closable, vec![0.0, 1.0]);
+}
+
+#[test]
+fn test_designation_applies_only_to_designating_order() {
+    let (account_mgr, router) = setup();
+    let submit = |req: SubmitOrderRequest| {
+        let response = router.submit_order(req);
+        assert!(response.success);
+        response.order_id.unwrap()
+    };
+    let lot_at = |price: f64| {
+        detail(&account_mgr, "alice")
+            .lots
+            .iter()
+            .find(|d| approx(d.lot.price, price))
+            .unwrap()
+            .clone()
+    };
+
+    // alice 多头：100×1、101×1、102×1
+    for price in [100.0, 101.0, 102.0] {
+        submit(order("alice", "BUY", "OPEN", 1.0, price));
+        submit(order("bob", "SELL", "OPEN", 1.0, price));
+    }
+    let lot_102 = lot_at(102.0).lot.lot_id;
+
+    // 两笔平仓委托同时挂单：先挂的不指定，后挂的指定 102 批次
+    submit(order("alice", "SELL", "CLOSE", 1.0, 105.0));
+    submit(SubmitOrderRequest {
+        close_lot_id: Some(lot_102),
+        ..order("alice", "SELL", "CLOSE", 1.0, 106.0)
+    });
+
+    // 不指定的委托先成交：按 FIFO 平 100 批次，不受另一委托的指定影响
+    submit(order("bob", "BUY", "CLOSE", 1.0, 105.0));
+    let prices: Vec<f64> = detail(&account_mgr, "alice")
+        .lots
+        .iter()
+        .map(|d| d.lot.price)
+        .collect();
+    assert_eq!(prices, vec![101.0, 102.0]);
+
+    // 指定委托成交：消耗 102 批次
+    submit(order("bob", "BUY", "CLOSE", 1.0, 106.0));
+    let alice = detail(&account_mgr, "alice");
+    assert_eq!(alice.lots.len(), 1);
+    assert!(approx(alice.lots[0].lot.price, 101.0));
+
+    // 再开 99×1；挂单指定 99 批次时冻结量先落在该批次
+    submit(order("alice", "BUY", "OPEN", 1.0, 99.0));
+    submit(order("bob", "SELL", "OPEN", 1.0, 99.0));
+    let lot_99 = lot_at(99.0).lot.lot_id;
+    let designated = submit(SubmitOrderRequest {
+        close_lot_id: Some(lot_99),
+        ..order("alice", "SELL", "CLOSE", 1.0, 109.0)
+    });
+    assert!(approx(lot_at(99.0).closable_volume, 0.0));
+    assert!(approx(lot_at(101.0).closable_volume, 1.0));
+
+    // 撤单撤销登记：之后不指定的平仓挂单按 FIFO 冻结 101 批次
+    router
+        .cancel_order(CancelOrderRequest {
+            account_id: "alice".to_string(),
+            order_id: designated,
+        })
+        .unwrap();
+    submit(order("alice", "SELL", "CLOSE", 1.0, 107.0));
+    assert!(approx(lot_at(99.0).closable_volume, 1.0));
+    assert!(approx(lot_at(101.0).closable_volume, 0.0));
+}
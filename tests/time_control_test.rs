@@ -0,0 +1,194 @@
+// 测试环境时间控制集成测试 @yutiansut @quantaxis
+//
+// 设置虚拟时间 → 盘中成交、挂 TTL 订单 → 快进 3 天：
+// 依次触发 3 次日终结算与 3 次交易日切换，结算日期随虚拟时间推进、账户数据守恒，
+// TTL 订单在结算前到期撤销；虚拟时间不能回退
+//
+// 虚拟时间只作用于注入各组件的 TimeService 实例，不影响全局时区服务
+//
+// 运行：cargo test --test time_control_test -- --nocapture
+
+use std::sync::Arc;
+
+use qaexchange::core::account_ext::{AccountType, OpenAccountRequest};
+use qaexchange::exchange::instrument_registry::{InstrumentInfo, InstrumentStatus, InstrumentType};
+use qaexchange::exchange::order_router::{OrderStatus, SubmitOrderRequest};
+use qaexchange::exchange::{
+    AccountManager, InstrumentRegistry, OrderRouter, SettlementEngine, TimeController,
+    TimeEventKind, TradeGateway,
+};
+use qaexchange::matching::engine::ExchangeMatchingEngine;
+use qaexchange::utils::time_service::{time_service, TimeService};
+use qaexchange::ExchangeError;
+
+fn order(account_id: &str, direction: &str, volume: f64, price: f64) -> SubmitOrderRequest {
+    SubmitOrderRequest {
+        account_id: account_id.to_string(),
+        instrument_id: "IX2301".to_string(),
+        direction: direction.to_string(),
+        offset: "OPEN".to_string(),
+        volume,
+        price,
+        order_type: "LIMIT".to_string(),
+        ..Default::default()
+    }
+}
+
+fn total_balance(account_mgr: &AccountManager) -> f64 {
+    ["buyer", "seller"]
+        .iter()
+        .map(|id| account_mgr.get_qifi_slice(id).unwrap().accounts.balance)
+        .sum()
+}
+
+#[test]
+fn test_advance_three_days_triggers_three_settlements() {
+    let clock = Arc::new(TimeService::default());
+    let account_mgr = Arc::new(AccountManager::new());
+    for user in ["buyer", "seller"] {
+        account_mgr
+            .open_account(OpenAccountRequest {
+                user_id: user.to_string(),
+                account_id: Some(user.to_string()),
+                account_name: user.to_string(),
+                init_cash: 1000000.0,
+                account_type: AccountType::Individual,
+            })
+            .unwrap();
+    }
+
+    let matching_engine = Arc::new(ExchangeMatchingEngine::new());
+    matching_engine
+        .register_instrument("IX2301".to_string(), 120.0)
+        .unwrap();
+    let registry = Arc::new(InstrumentRegistry::new());
+    registry
+        .register(InstrumentInfo {
+            instrument_id: "IX2301".to_string(),
+            instrument_name: "IX2301".to_string(),
+            instrument_type: InstrumentType::CommodityFuture,
+            exchange: "SHFE".to_string(),
+            contract_multiplier: 1,
+            trading_unit: "手".to_string(),
+            quote_unit: String::new(),
+            price_tick: 0.01,
+            margin_rate: 0.1,
+            commission_rate: 0.0005,
+            limit_up_rate: 0.1,
+            limit_down_rate: 0.1,
+            status: InstrumentStatus::Active,
+            list_date: Some("2023-01-01".to_string()),
+            expire_date: None,
+            created_at: "2023-01-01T00:00:00Z".to_string(),
+            updated_at: "2023-01-01T00:00:00Z".to_string(),
+        })
+        .unwrap();
+    let trade_gateway = Arc::new(TradeGateway::new(account_mgr.clone()));
+    let mut router = OrderRouter::new(
+        account_mgr.clone(),
+        matching_engine.clone(),
+        registry,
+        trade_gateway,
+    );
+    router.set_time_service(clock.clone());
+    let router = Arc::new(router);
+
+    let settlement_engine = Arc::new(SettlementEngine::new(account_mgr.clone()));
+    settlement_engine.set_time_service(clock.clone());
+    settlement_engine.set_settlement_price("IX2301".to_string(), 121.0);
+
+    // 生产配置不可用
+    let production = TimeController::new("production", settlement_engine.clone());
+    assert!(matches!(
+        production.advance_hours(1.0),
+        Err(ExchangeError::PermissionDenied(_))
+    ));
+
+    let controller = TimeController::new("development", settlement_engine.clone())
+        .with_order_router(router.clone())
+        .with_matching_engine(matching_engine.clone());
+
+    // 周一 09:00 开始虚拟时间
+    let start = controller.set_local_time("2026-10-19 09:00:00").unwrap();
+    assert!(start.events.is_empty());
+    assert_eq!(clock.virtual_time_millis(), Some(start.to));
+    assert!(Arc::ptr_eq(&controller.time_service(), &clock));
+    assert_eq!(time_service().virtual_time_millis(), None);
+
+    // 盘中成交 2 手，另挂一笔 1 小时后到期的买单
+    assert!(
+        router
+            .submit_order(order("buyer", "BUY", 2.0, 120.0))
+            .success
+    );
+    assert!(
+        router
+            .submit_order(order("seller", "SELL", 2.0, 120.0))
+            .success
+    );
+    let ttl_order = router.submit_order(SubmitOrderRequest {
+        ttl_secs: Some(3600),
+        ..order("buyer", "BUY", 1.0, 110.0)
+    });
+    assert!(ttl_order.success);
+    let ttl_order_id = ttl_order.order_id.unwrap();
+    let balance_before = total_balance(&account_mgr);
+
+    // 快进 3 天：周一/周二/周三 15:30 结算，每晚 18:00 切换交易日
+    let report = controller.advance_hours(72.0).unwrap();
+    assert_eq!(report.to - report.from, 72 * 3_600_000);
+    assert_eq!(report.to_local, "2026-10-22 09:00:00");
+
+    let kinds: Vec<TimeEventKind> = report.events.iter().map(|e| e.kind).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            TimeEventKind::Settlement,
+            TimeEventKind::TradingDaySwitch,
+            TimeEventKind::Settlement,
+            TimeEventKind::TradingDaySwitch,
+            TimeEventKind::Settlement,
+            TimeEventKind::TradingDaySwitch,
+        ]
+    );
+    assert!(report.events.iter().all(|e| e.success));
+    assert!(report
+        .events
+        .windows(2)
+        .all(|pair| pair[0].timestamp < pair[1].timestamp));
+    assert_eq!(report.events[0].local_time, "2026-10-19 15:30:00");
+
+    let dates: Vec<&str> = report
+        .settlements
+        .iter()
+        .map(|r| r.settlement_date.as_str())
+        .collect();
+    assert_eq!(dates, vec!["2026-10-19", "2026-10-20", "2026-10-21"]);
+    for result in &report.settlements {
+        assert_eq!(result.total_accounts, 2);
+        assert_eq!(result.settled_accounts, 2);
+        assert_eq!(result.failed_accounts, 0);
+    }
+    assert_eq!(settlement_engine.get_settlement_history().len(), 3);
+
+    // 多空盈亏相抵，结算前后总权益守恒
+    assert!((total_balance(&account_mgr) - balance_before).abs() < 1e-6);
+
+    // TTL 订单在周一结算前到期撤销；交易日推进到周四
+    assert_eq!(
+        router.get_order_status(&ttl_order_id),
+        Some(OrderStatus::Cancelled)
+    );
+    assert_eq!(matching_engine.get_trading_day(), "20261022");
+    assert_eq!(
+        clock.current_trading_day().format("%Y-%m-%d").to_string(),
+        "2026-10-22"
+    );
+
+    // 虚拟时间不能回退
+    assert!(matches!(
+        controller.set_local_time("2026-10-20 09:00:00"),
+        Err(ExchangeError::InvalidParameter(_))
+    ));
+    assert_eq!(time_service().virtual_time_millis(), None);
+}
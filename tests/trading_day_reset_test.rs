@@ -0,0 +1,277 @@
+// 交易日重置集成测试 @yutiansut @quantaxis
+//
+// 开盘标记 → 盘中下单/成交/入金 → 重置 → 账户、订单簿、成交回到开盘状态，
+// 重置后重新下单撮合流程正常；非 development 环境拒绝重置
+//
+// 运行：cargo test --test trading_day_reset_test -- --nocapture
+
+use std::sync::Arc;
+
+use qaexchange::core::account_ext::{AccountType, OpenAccountRequest};
+use qaexchange::exchange::emergency::{capture_orderbooks, maintenance_status};
+use qaexchange::exchange::instrument_registry::{InstrumentInfo, InstrumentStatus, InstrumentType};
+use qaexchange::exchange::order_router::SubmitOrderRequest;
+use qaexchange::exchange::trading_day_manager::TradingDayManager;
+use qaexchange::exchange::{
+    AccountManager, CapitalManager, InstrumentRegistry, OrderRouter, TradeGateway,
+};
+use qaexchange::market::kline::{KLineManager, KLinePeriod};
+use qaexchange::market::OrderBookSnapshot;
+use qaexchange::matching::engine::ExchangeMatchingEngine;
+use qaexchange::ExchangeError;
+
+struct TestExchange {
+    account_mgr: Arc<AccountManager>,
+    matching_engine: Arc<ExchangeMatchingEngine>,
+    router: Arc<OrderRouter>,
+}
+
+fn create_test_exchange() -> TestExchange {
+    let account_mgr = Arc::new(AccountManager::new());
+    for user in ["buyer", "seller"] {
+        account_mgr
+            .open_account(OpenAccountRequest {
+                user_id: user.to_string(),
+                account_id: Some(user.to_string()),
+                account_name: user.to_string(),
+                init_cash: 1000000.0,
+                account_type: AccountType::Individual,
+            })
+            .unwrap();
+    }
+
+    let matching_engine = Arc::new(ExchangeMatchingEngine::new());
+    matching_engine
+        .register_instrument("IX2301".to_string(), 120.0)
+        .unwrap();
+    let registry = Arc::new(InstrumentRegistry::new());
+    registry
+        .register(InstrumentInfo {
+            instrument_id: "IX2301".to_string(),
+            instrument_name: "IX2301".to_string(),
+            instrument_type: InstrumentType::CommodityFuture,
+            exchange: "SHFE".to_string(),
+            contract_multiplier: 1,
+            trading_unit: "手".to_string(),
+            quote_unit: String::new(),
+            price_tick: 0.01,
+            margin_rate: 0.1,
+            commission_rate: 0.0005,
+            limit_up_rate: 0.1,
+            limit_down_rate: 0.1,
+            status: InstrumentStatus::Active,
+            list_date: Some("2023-01-01".to_string()),
+            expire_date: Some("2023-12-31".to_string()),
+            created_at: "2023-01-01T00:00:00Z".to_string(),
+            updated_at: "2023-01-01T00:00:00Z".to_string(),
+        })
+        .unwrap();
+
+    let trade_gateway = Arc::new(TradeGateway::new(account_mgr.clone()));
+    let router = Arc::new(OrderRouter::new(
+        account_mgr.clone(),
+        matching_engine.clone(),
+        registry,
+        trade_gateway,
+    ));
+    router
+        .trade_bus()
+        .subscribe_inline("trade_recorder", matching_engine.get_trade_recorder());
+    TestExchange {
+        account_mgr,
+        matching_engine,
+        router,
+    }
+}
+
+fn order(account_id: &str, direction: &str, volume: f64, price: f64) -> SubmitOrderRequest {
+    SubmitOrderRequest {
+        account_id: account_id.to_string(),
+        instrument_id: "IX2301".to_string(),
+        direction: direction.to_string(),
+        offset: "OPEN".to_string(),
+        volume,
+        price,
+        order_type: "LIMIT".to_string(),
+        ..Default::default()
+    }
+}
+
+/// (balance, 持仓多头, 持仓空头, 冻结保证金)
+fn account_state(account_mgr: &AccountManager, account_id: &str) -> (f64, f64, f64, f64) {
+    let account = account_mgr.get_account(account_id).unwrap();
+    let mut acc = account.write();
+    let (long, short) = acc
+        .get_position("IX2301")
+        .map_or((0.0, 0.0), |pos| (pos.volume_long(), pos.volume_short()));
+    let qifi = acc.get_qifi_slice();
+    (
+        qifi.accounts.balance,
+        long,
+        short,
+        qifi.accounts.frozen_margin,
+    )
+}
+
+fn bid_levels(book: &OrderBookSnapshot) -> Vec<(f64, i64)> {
+    book.bids.iter().map(|l| (l.price, l.volume)).collect()
+}
+
+#[test]
+fn test_reset_trading_day_then_trade_again() {
+    let dir = tempfile::tempdir().unwrap();
+    let exchange = create_test_exchange();
+    let capital_mgr = Arc::new(CapitalManager::new(exchange.account_mgr.clone()));
+    let klines = KLineManager::new();
+
+    // 开盘前已有一笔挂单（隔夜订单），重置后应保留
+    assert!(
+        exchange
+            .router
+            .submit_order(order("buyer", "BUY", 1.0, 118.0))
+            .success
+    );
+
+    let manager = TradingDayManager::new(
+        "development",
+        exchange.account_mgr.clone(),
+        exchange.router.clone(),
+        exchange.matching_engine.clone(),
+        dir.path(),
+    )
+    .with_capital_manager(capital_mgr.clone())
+    .with_klines(klines.aggregators());
+
+    let mark = manager.ensure_opening_mark().unwrap();
+    assert_eq!(mark.accounts.len(), 2);
+    assert!(dir
+        .path()
+        .join("trading_day")
+        .join(format!("opening_{}.json", mark.trading_day))
+        .exists());
+    let opening_buyer = account_state(&exchange.account_mgr, "buyer");
+    let opening_seller = account_state(&exchange.account_mgr, "seller");
+    let opening_books = capture_orderbooks(&exchange.matching_engine);
+
+    // 盘中：成交 2 手、挂卖单 1 笔、入金、生成K线、写入 WAL
+    assert!(
+        exchange
+            .router
+            .submit_order(order("buyer", "BUY", 2.0, 120.0))
+            .success
+    );
+    assert!(
+        exchange
+            .router
+            .submit_order(order("seller", "SELL", 2.0, 120.0))
+            .success
+    );
+    assert!(
+        exchange
+            .router
+            .submit_order(order("seller", "SELL", 1.0, 123.0))
+            .success
+    );
+    capital_mgr
+        .deposit_with_record("buyer".to_string(), 50000.0, None, None)
+        .unwrap();
+    klines.on_tick("IX2301", 120.0, 2, mark.marked_at + 1000);
+    let wal_dir = dir.path().join("IX2301").join("wal");
+    std::fs::create_dir_all(&wal_dir).unwrap();
+    std::fs::write(wal_dir.join("wal_00000000000000000001.log"), b"wal").unwrap();
+
+    assert_eq!(
+        exchange
+            .matching_engine
+            .get_trade_recorder()
+            .get_trade_count(),
+        1
+    );
+    assert_eq!(account_state(&exchange.account_mgr, "buyer").1, 2.0);
+
+    // 非 development 环境拒绝重置，也不打开盘标记
+    let production = TradingDayManager::new(
+        "production",
+        exchange.account_mgr.clone(),
+        exchange.router.clone(),
+        exchange.matching_engine.clone(),
+        dir.path(),
+    );
+    assert!(production.ensure_opening_mark().is_none());
+    assert!(matches!(
+        production.reset_trading_day(),
+        Err(ExchangeError::PermissionDenied(_))
+    ));
+    assert_eq!(account_state(&exchange.account_mgr, "buyer").1, 2.0);
+
+    let report = manager.reset_trading_day().unwrap();
+    assert_eq!(report.trading_day, mark.trading_day);
+    assert_eq!(report.orders_cleared, 4);
+    assert_eq!(report.trades_cleared, 1);
+    assert_eq!(report.fund_transactions_removed, 1);
+    assert_eq!(report.wal_segments_archived, 1);
+    assert!(report
+        .archive_dir
+        .join("IX2301/wal/wal_00000000000000000001.log")
+        .exists());
+    assert_eq!(report.accounts_restored.len(), 2);
+    assert!(report.accounts_kept.is_empty());
+    assert!(report.accounts_failed.is_empty());
+    assert_eq!(report.instruments_restored, 1);
+
+    // 账户、订单簿、成交记录回到开盘状态，恢复接单
+    assert_eq!(account_state(&exchange.account_mgr, "buyer"), opening_buyer);
+    assert_eq!(
+        account_state(&exchange.account_mgr, "seller"),
+        opening_seller
+    );
+    let books = capture_orderbooks(&exchange.matching_engine);
+    assert_eq!(bid_levels(&books[0]), bid_levels(&opening_books[0]));
+    assert_eq!(bid_levels(&books[0]), vec![(118.0, 1)]);
+    assert!(books[0].asks.is_empty());
+    assert_eq!(
+        exchange
+            .matching_engine
+            .get_trade_recorder()
+            .get_trade_count(),
+        0
+    );
+    assert!(capital_mgr.get_transactions("buyer").is_empty());
+    assert!(klines
+        .get_current_kline("IX2301", KLinePeriod::Min1)
+        .is_none());
+    assert!(exchange.router.order_entry_halt_reason().is_none());
+    assert!(maintenance_status().is_none());
+
+    // 重置后重新下单：与开盘前的挂单撮合，再开新仓
+    assert!(
+        exchange
+            .router
+            .submit_order(order("seller", "SELL", 1.0, 118.0))
+            .success
+    );
+    assert!(
+        exchange
+            .router
+            .submit_order(order("seller", "SELL", 3.0, 121.0))
+            .success
+    );
+    assert!(
+        exchange
+            .router
+            .submit_order(order("buyer", "BUY", 3.0, 121.0))
+            .success
+    );
+    assert_eq!(
+        exchange
+            .matching_engine
+            .get_trade_recorder()
+            .get_trade_count(),
+        2
+    );
+    assert_eq!(account_state(&exchange.account_mgr, "buyer").1, 4.0);
+    assert_eq!(account_state(&exchange.account_mgr, "seller").2, 4.0);
+    assert!(capture_orderbooks(&exchange.matching_engine)[0]
+        .bids
+        .is_empty());
+}